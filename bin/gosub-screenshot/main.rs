@@ -144,6 +144,7 @@ fn main() {
                 url: None,
                 title: Some("screenshot".to_string()),
                 viewport: None,
+                opener: None,
             },
             None,
         ))
@@ -0,0 +1,186 @@
+use std::cell::{Cell, RefCell};
+use std::ptr::NonNull;
+use std::rc::{Rc, Weak};
+
+use boa_engine::context::ContextBuilder;
+use boa_engine::job::SimpleJobQueue;
+use boa_engine::{Context, JsError};
+
+pub use array::*;
+pub use compile::*;
+pub use function::*;
+use gosub_shared::types::Result;
+use gosub_webexecutor::js::{JSError, WebRuntime};
+use gosub_webexecutor::Error;
+use module::GraphLoader;
+pub use object::*;
+pub use value::*;
+
+mod array;
+mod compile;
+mod context;
+mod function;
+mod module;
+mod object;
+mod value;
+
+/// Boa keeps no global state, so this is just a dummy struct for the wrapper
+#[derive(Debug, Default)]
+pub struct BoaEngine;
+
+impl BoaEngine {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// The Boa context is stored in an `Rc`, so we can attach it to values, objects, ...
+pub struct BoaContext {
+    ctx: Rc<BoaCtx>,
+}
+
+pub(crate) struct BoaCtx {
+    context: RefCell<Context>,
+    /// The context a native function was called with, while that function runs. The `RefCell`
+    /// is borrowed by whoever started the script at that point.
+    active: Cell<Option<NonNull<Context>>>,
+    /// Where the context's module imports are looked up
+    loader: Rc<GraphLoader>,
+}
+
+impl BoaContext {
+    pub fn with_default() -> Result<Self> {
+        // Promise jobs wait in the queue until the embedder asks for them, see
+        // `perform_microtask_checkpoint`.
+        let loader = Rc::new(GraphLoader::default());
+        let context = ContextBuilder::new()
+            .job_queue(Rc::new(SimpleJobQueue::new()))
+            .module_loader(Rc::clone(&loader))
+            .build()
+            .map_err(|e| Error::JS(JSError::Initialize(e.to_string())))?;
+
+        Ok(Self {
+            ctx: Rc::new(BoaCtx {
+                context: RefCell::new(context),
+                active: Cell::new(None),
+                loader,
+            }),
+        })
+    }
+
+    /// Runs `f` with the Boa context: the one the running native function got, or else the
+    /// context itself. Fails when called from within `f` itself, since the context is in use.
+    #[allow(unsafe_code)]
+    pub fn with<R>(&self, f: impl FnOnce(&mut Context) -> R) -> Result<R> {
+        if let Some(mut active) = self.ctx.active.take() {
+            // SAFETY: `active` was set by `enter` from the `&mut Context` of a native call that
+            // is still running (it is reset before that call returns), and that call doesn't
+            // touch its context while it waits for us. Taking it out of the cell keeps a nested
+            // `with` from handing out a second reference.
+            let result = f(unsafe { active.as_mut() });
+            self.ctx.active.set(Some(active));
+            return Ok(result);
+        }
+
+        match self.ctx.context.try_borrow_mut() {
+            Ok(mut context) => Ok(f(&mut context)),
+            Err(_) => Err(Error::JS(JSError::Runtime("the context is in use".to_owned())).into()),
+        }
+    }
+
+    /// Makes `context`, which a native function was called with, the one [`Self::with`] hands
+    /// out while `f` runs.
+    pub(crate) fn enter<R>(&self, context: &mut Context, f: impl FnOnce() -> R) -> R {
+        let previous = self.ctx.active.replace(Some(NonNull::from(context)));
+        let result = f();
+        self.ctx.active.set(previous);
+        result
+    }
+
+    /// A handle that doesn't keep the context alive, for native functions the context itself
+    /// owns.
+    pub(crate) fn downgrade(&self) -> Weak<BoaCtx> {
+        Rc::downgrade(&self.ctx)
+    }
+
+    pub(crate) fn upgrade(ctx: &Weak<BoaCtx>) -> Option<Self> {
+        ctx.upgrade().map(|ctx| Self { ctx })
+    }
+
+    /// Converts an exception to an error, with the message the way `String(e)` has it
+    /// (`TypeError: x is not a function`).
+    pub(crate) fn exception(context: &mut Context, error: JsError) -> anyhow::Error {
+        let message = error
+            .to_opaque(context)
+            .to_string(context)
+            .map(|s| s.to_std_string_escaped())
+            .unwrap_or_else(|_| error.to_string());
+
+        Error::JS(JSError::Exception(message)).into()
+    }
+}
+
+impl Clone for BoaContext {
+    fn clone(&self) -> Self {
+        Self {
+            ctx: Rc::clone(&self.ctx),
+        }
+    }
+}
+
+impl WebRuntime for BoaEngine {
+    type Context = BoaContext;
+    type Value = BoaValue;
+    type Object = BoaObject;
+    type Compiled = BoaCompiled;
+    type GetterCB = GetterCallback;
+    type SetterCB = SetterCallback;
+    type Function = BoaFunction;
+    type FunctionVariadic = BoaFunctionVariadic;
+    type Array = BoaArray;
+    type FunctionCallBack = BoaFunctionCallBack;
+    type FunctionCallBackVariadic = BoaFunctionCallBackVariadic;
+    type Args = BoaArgs;
+    type VariadicArgs = BoaVariadicArgs;
+    type VariadicArgsInternal = BoaVariadicArgsInternal;
+
+    fn new_context(&mut self) -> Result<Self::Context> {
+        BoaContext::with_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gosub_webexecutor::js::{WebContext, WebRuntime, WebValue};
+
+    use crate::boa::BoaEngine;
+
+    #[test]
+    fn boa_js_execution() {
+        let mut engine = BoaEngine::new();
+        let mut context = engine.new_context().unwrap();
+
+        let value = context.run("const a = 1200; a + 34").unwrap();
+
+        assert!(value.is_number());
+        assert_eq!(value.as_number().unwrap(), 1234.0);
+    }
+
+    #[test]
+    fn boa_run_invalid_syntax() {
+        let mut engine = BoaEngine::new();
+        let mut context = engine.new_context().unwrap();
+
+        let error = context.run("console.log(Hello World!);").unwrap_err();
+        assert!(error.to_string().contains("SyntaxError"), "{error}");
+    }
+
+    #[test]
+    fn boa_exceptions_keep_their_message() {
+        let mut engine = BoaEngine::new();
+        let mut context = engine.new_context().unwrap();
+
+        let error = context.run("null.x").unwrap_err();
+        assert!(error.to_string().contains("exception: TypeError"), "{error}");
+    }
+}
@@ -0,0 +1,147 @@
+use boa_engine::object::builtins::JsArray;
+use boa_engine::JsValue;
+
+use gosub_shared::types::Result;
+use gosub_webexecutor::js::{AsArray, Ref, WebArray, WebRuntime};
+
+use crate::boa::{BoaContext, BoaEngine, BoaValue};
+
+#[derive(Clone)]
+pub struct BoaArray {
+    pub(crate) ctx: BoaContext,
+    pub(crate) array: JsArray,
+    next: usize, //TODO; this should not be in the array itself
+}
+
+impl BoaArray {
+    pub fn new_from(ctx: BoaContext, array: JsArray) -> Self {
+        Self { ctx, array, next: 0 }
+    }
+}
+
+impl Iterator for BoaArray {
+    type Item = BoaValue;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.len() {
+            return None;
+        }
+        let value = self.get(self.next).ok();
+        self.next += 1;
+        value
+    }
+}
+
+impl AsArray for BoaArray {
+    type Runtime = BoaEngine;
+
+    fn array(&self) -> Result<Ref<'_, <Self::Runtime as WebRuntime>::Array>> {
+        Ok(Ref::Ref(self))
+    }
+}
+
+impl WebArray for BoaArray {
+    type RT = BoaEngine;
+
+    fn get(&self, index: usize) -> Result<<Self::RT as WebRuntime>::Value> {
+        let value = self.ctx.with(|context| {
+            self.array
+                .get(index as u32, context)
+                .map_err(|e| BoaContext::exception(context, e))
+        })??;
+
+        Ok(BoaValue::new(self.ctx.clone(), value))
+    }
+
+    fn set(&self, index: usize, value: &BoaValue) -> Result<()> {
+        self.ctx.with(|context| {
+            self.array
+                .set(index as u32, value.value.clone(), true, context)
+                .map(|_| ())
+                .map_err(|e| BoaContext::exception(context, e))
+        })?
+    }
+
+    fn push(&self, value: BoaValue) -> Result<()> {
+        self.ctx.with(|context| {
+            self.array
+                .push(value.value, context)
+                .map(|_| ())
+                .map_err(|e| BoaContext::exception(context, e))
+        })?
+    }
+
+    fn pop(&self) -> Result<<Self::RT as WebRuntime>::Value> {
+        let value = self
+            .ctx
+            .with(|context| self.array.pop(context).map_err(|e| BoaContext::exception(context, e)))??;
+
+        Ok(BoaValue::new(self.ctx.clone(), value))
+    }
+
+    fn remove(&self, index: usize) -> Result<()> {
+        // Like V8's `delete_index`: the slot becomes a hole, the length stays.
+        self.ctx.with(|context| {
+            self.array
+                .delete_property_or_throw(index as u32, context)
+                .map(|_| ())
+                .map_err(|e| BoaContext::exception(context, e))
+        })?
+    }
+
+    fn len(&self) -> usize {
+        self.ctx
+            .with(|context| self.array.length(context).unwrap_or_default())
+            .unwrap_or_default() as usize
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn new(ctx: <Self::RT as WebRuntime>::Context, _cap: usize) -> Result<Self> {
+        let array = ctx.with(JsArray::new)?;
+
+        Ok(Self::new_from(ctx, array))
+    }
+
+    fn new_with_data(ctx: <Self::RT as WebRuntime>::Context, data: &[BoaValue]) -> Result<Self> {
+        let values = data.iter().map(|v| v.value.clone()).collect::<Vec<JsValue>>();
+        let array = ctx.with(|context| JsArray::from_iter(values, context))?;
+
+        Ok(Self::new_from(ctx, array))
+    }
+
+    fn as_value(&self) -> <Self::RT as WebRuntime>::Value {
+        BoaValue::new(self.ctx.clone(), self.array.clone().into())
+    }
+
+    fn as_vec(&self) -> Vec<<Self::RT as WebRuntime>::Value> {
+        (0..self.len()).filter_map(|i| self.get(i).ok()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gosub_webexecutor::js::{IntoRustValue, WebArray, WebContext, WebRuntime, WebValue};
+
+    use crate::boa::{BoaArray, BoaEngine, BoaValue};
+
+    #[test]
+    fn arrays_round_trip() {
+        let mut engine = BoaEngine::new();
+        let mut context = engine.new_context().unwrap();
+
+        let data = [1, 2, 3].map(|n| BoaValue::new_number(context.clone(), n).unwrap());
+        let array = BoaArray::new_with_data(context.clone(), &data).unwrap();
+        array.push(BoaValue::new_number(context.clone(), 4).unwrap()).unwrap();
+        assert_eq!(array.len(), 4);
+        assert_eq!(array.pop().unwrap().as_number().unwrap(), 4.0);
+
+        context.set_on_global_object("arr", array.as_value()).unwrap();
+        context.run("arr[0] = 10").unwrap();
+        let values: Vec<i32> = array.as_value().to_rust_value().unwrap();
+        assert_eq!(values, vec![10, 2, 3]);
+        assert_eq!(array.clone().map(|v| v.as_number().unwrap()).sum::<f64>(), 15.0);
+    }
+}
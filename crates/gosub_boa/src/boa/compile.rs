@@ -0,0 +1,25 @@
+use boa_engine::Script;
+
+use gosub_shared::types::Result;
+use gosub_webexecutor::js::{WebCompiled, WebRuntime};
+
+use crate::boa::{BoaContext, BoaEngine, BoaValue};
+
+pub struct BoaCompiled {
+    pub(crate) ctx: BoaContext,
+    pub(crate) script: Script,
+}
+
+impl WebCompiled for BoaCompiled {
+    type RT = BoaEngine;
+
+    fn run(&mut self) -> Result<<Self::RT as WebRuntime>::Value> {
+        let value = self.ctx.with(|context| {
+            self.script
+                .evaluate(context)
+                .map_err(|e| BoaContext::exception(context, e))
+        })??;
+
+        Ok(BoaValue::new(self.ctx.clone(), value))
+    }
+}
@@ -0,0 +1,118 @@
+use boa_engine::{JsString, Script, Source};
+
+use gosub_shared::types::Result;
+use gosub_webexecutor::js::{ModuleGraph, WebCompiled, WebContext, WebRuntime};
+
+use crate::boa::{BoaCompiled, BoaContext, BoaEngine};
+
+impl WebContext for BoaContext {
+    type RT = BoaEngine;
+
+    fn run(&mut self, code: &str) -> Result<<Self::RT as WebRuntime>::Value> {
+        self.compile(code)?.run()
+    }
+
+    fn compile(&mut self, code: &str) -> Result<<Self::RT as WebRuntime>::Compiled> {
+        let script = self.with(|context| {
+            Script::parse(Source::from_bytes(code), None, context).map_err(|e| BoaContext::exception(context, e))
+        })??;
+
+        Ok(BoaCompiled {
+            ctx: self.clone(),
+            script,
+        })
+    }
+
+    fn run_compiled(
+        &mut self,
+        compiled: &mut <Self::RT as WebRuntime>::Compiled,
+    ) -> Result<<Self::RT as WebRuntime>::Value> {
+        compiled.run()
+    }
+
+    fn set_on_global_object(&mut self, name: &str, value: <Self::RT as WebRuntime>::Value) -> Result<()> {
+        self.with(|context| {
+            context
+                .global_object()
+                .set(JsString::from(name), value.value, false, context)
+                .map(|_| ())
+                .map_err(|e| BoaContext::exception(context, e))
+        })?
+    }
+
+    fn run_module(&mut self, url: &str, graph: &ModuleGraph) -> Result<<Self::RT as WebRuntime>::Value> {
+        self.run_module_graph(url, graph)
+    }
+
+    fn perform_microtask_checkpoint(&mut self) -> Result<()> {
+        self.with(|context| context.run_jobs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use gosub_webexecutor::js::{ModuleGraph, ModuleRecord, WebContext, WebRuntime, WebValue};
+
+    use crate::boa::BoaEngine;
+
+    #[test]
+    fn promise_jobs_wait_for_a_checkpoint() {
+        let mut engine = BoaEngine::new();
+        let mut context = engine.new_context().unwrap();
+
+        context
+            .run("globalThis.log = []; Promise.resolve().then(() => log.push('job')); log.push('task');")
+            .unwrap();
+        assert_eq!(context.run("log.join()").unwrap().as_string().unwrap(), "task");
+
+        context.perform_microtask_checkpoint().unwrap();
+        assert_eq!(context.run("log.join()").unwrap().as_string().unwrap(), "task,job");
+    }
+
+    #[test]
+    fn modules_import_each_other_once() {
+        let mut engine = BoaEngine::new();
+        let mut context = engine.new_context().unwrap();
+        context.run("globalThis.log = [];").unwrap();
+
+        let mut graph = ModuleGraph::new();
+        graph.insert(
+            "https://a.example/main.js",
+            ModuleRecord {
+                source: "import { name } from './a.js'; log.push('main:' + name);".into(),
+                imports: HashMap::from([("./a.js".into(), "https://a.example/a.js".into())]),
+            },
+        );
+        // `a` and `b` import each other.
+        graph.insert(
+            "https://a.example/a.js",
+            ModuleRecord {
+                source: "import './b.js'; export const name = 'a'; log.push('a');".into(),
+                imports: HashMap::from([("./b.js".into(), "https://a.example/b.js".into())]),
+            },
+        );
+        graph.insert(
+            "https://a.example/b.js",
+            ModuleRecord {
+                source: "import './a.js'; log.push('b');".into(),
+                imports: HashMap::from([("./a.js".into(), "https://a.example/a.js".into())]),
+            },
+        );
+
+        context.run_module("https://a.example/main.js", &graph).unwrap();
+        context.run_module("https://a.example/a.js", &graph).unwrap();
+        assert_eq!(context.run("log.join()").unwrap().as_string().unwrap(), "b,a,main:a");
+
+        let mut broken = ModuleGraph::new();
+        broken.insert(
+            "https://a.example/broken.js",
+            ModuleRecord {
+                source: "import 'missing';".into(),
+                imports: HashMap::new(),
+            },
+        );
+        assert!(context.run_module("https://a.example/broken.js", &broken).is_err());
+    }
+}
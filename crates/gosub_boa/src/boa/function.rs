@@ -0,0 +1,391 @@
+use core::fmt::Display;
+
+use boa_engine::object::FunctionObjectBuilder;
+use boa_engine::{JsNativeError, JsObject, JsValue, NativeFunction};
+
+use gosub_shared::types::Result;
+use gosub_webexecutor::js::{
+    Args, IntoRustValue, VariadicArgs, VariadicArgsInternal, WebFunction, WebFunctionCallBack,
+    WebFunctionCallBackVariadic, WebFunctionVariadic, WebRuntime,
+};
+
+use crate::boa::{BoaContext, BoaEngine, BoaValue};
+
+/// Builds a JS function that runs `f` with the arguments it is called with. An `Err` is thrown
+/// as an `Error` with that message.
+#[allow(unsafe_code)]
+pub(crate) fn native_function(
+    ctx: &BoaContext,
+    f: impl Fn(BoaContext, &[JsValue]) -> std::result::Result<JsValue, String> + 'static,
+) -> Result<JsObject> {
+    // The context owns the function, so the function must not own the context.
+    let weak = ctx.downgrade();
+
+    // SAFETY: the closure must not capture anything the garbage collector needs to trace. It
+    // holds `f` and a weak handle to the context; JS values only live in `f` as long as a call
+    // runs, and values a caller keeps (in a `BoaValue`) are rooted by Boa itself.
+    let native = unsafe {
+        NativeFunction::from_closure(move |_this, args, context| {
+            let Some(ctx) = BoaContext::upgrade(&weak) else {
+                return Err(JsNativeError::error().with_message("the context is gone").into());
+            };
+
+            ctx.enter(context, || f(ctx.clone(), args))
+                .map_err(|message| JsNativeError::error().with_message(message).into())
+        })
+    };
+
+    ctx.with(|context| FunctionObjectBuilder::new(context.realm(), native).build().into())
+}
+
+fn call(ctx: &BoaContext, function: &JsObject, args: &[BoaValue]) -> Result<BoaValue> {
+    let args = args.iter().map(|arg| arg.value.clone()).collect::<Vec<_>>();
+
+    let value = ctx.with(|context| {
+        function
+            .call(&JsValue::undefined(), &args, context)
+            .map_err(|e| BoaContext::exception(context, e))
+    })??;
+
+    Ok(BoaValue::new(ctx.clone(), value))
+}
+
+pub struct BoaFunction {
+    pub(crate) ctx: BoaContext,
+    pub(crate) function: JsObject,
+}
+
+pub struct BoaFunctionCallBack {
+    ctx: BoaContext,
+    args: BoaArgs,
+    ret: Option<JsValue>,
+    error: Option<String>,
+}
+
+pub struct BoaArgs {
+    next: usize,
+    args: Vec<JsValue>,
+}
+
+impl Iterator for BoaArgs {
+    type Item = JsValue;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let value = self.args.get(self.next)?.clone();
+        self.next += 1;
+        Some(value)
+    }
+}
+
+impl Args for BoaArgs {
+    type RT = BoaEngine;
+
+    fn get(&self, index: usize, ctx: <Self::RT as WebRuntime>::Context) -> Option<<Self::RT as WebRuntime>::Value> {
+        self.args.get(index).map(|value| BoaValue::new(ctx, value.clone()))
+    }
+
+    fn len(&self) -> usize {
+        self.args.len()
+    }
+
+    fn as_vec(&self, ctx: <Self::RT as WebRuntime>::Context) -> Vec<<Self::RT as WebRuntime>::Value> {
+        self.args
+            .iter()
+            .map(|value| BoaValue::new(ctx.clone(), value.clone()))
+            .collect()
+    }
+}
+
+impl WebFunctionCallBack for BoaFunctionCallBack {
+    type RT = BoaEngine;
+
+    fn context(&mut self) -> <Self::RT as WebRuntime>::Context {
+        self.ctx.clone()
+    }
+
+    fn args(&mut self) -> &<Self::RT as WebRuntime>::Args {
+        &self.args
+    }
+
+    fn len(&self) -> usize {
+        self.args.len()
+    }
+
+    fn error(&mut self, error: impl Display) {
+        self.error = Some(error.to_string());
+    }
+
+    fn ret(&mut self, value: <Self::RT as WebRuntime>::Value) {
+        self.ret = Some(value.value);
+    }
+}
+
+impl WebFunction for BoaFunction {
+    type RT = BoaEngine;
+
+    fn new(
+        ctx: <Self::RT as WebRuntime>::Context,
+        f: impl Fn(&mut <Self::RT as WebRuntime>::FunctionCallBack) + 'static,
+    ) -> Result<Self> {
+        let function = native_function(&ctx, move |ctx, args| {
+            let mut cb = BoaFunctionCallBack {
+                ctx,
+                args: BoaArgs {
+                    next: 0,
+                    args: args.to_vec(),
+                },
+                ret: None,
+                error: None,
+            };
+            f(&mut cb);
+
+            match cb.error {
+                Some(error) => Err(error),
+                None => Ok(cb.ret.unwrap_or_default()),
+            }
+        })?;
+
+        Ok(Self { ctx, function })
+    }
+
+    fn call(&mut self, args: &[<Self::RT as WebRuntime>::Value]) -> Result<<Self::RT as WebRuntime>::Value> {
+        call(&self.ctx, &self.function, args)
+    }
+}
+
+pub struct BoaFunctionVariadic {
+    pub(crate) ctx: BoaContext,
+    pub(crate) function: JsObject,
+}
+
+pub struct BoaFunctionCallBackVariadic {
+    ctx: BoaContext,
+    args: BoaVariadicArgsInternal,
+    ret: Option<JsValue>,
+    error: Option<String>,
+}
+
+pub struct BoaVariadicArgsInternal {
+    next: usize,
+    args: Vec<JsValue>,
+}
+
+impl Iterator for BoaVariadicArgsInternal {
+    type Item = JsValue;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let value = self.args.get(self.next)?.clone();
+        self.next += 1;
+        Some(value)
+    }
+}
+
+impl VariadicArgsInternal for BoaVariadicArgsInternal {
+    type RT = BoaEngine;
+
+    fn get(&self, index: usize, ctx: <Self::RT as WebRuntime>::Context) -> Option<<Self::RT as WebRuntime>::Value> {
+        self.args.get(index).map(|value| BoaValue::new(ctx, value.clone()))
+    }
+
+    fn len(&self) -> usize {
+        self.args.len()
+    }
+
+    fn as_vec(&self, ctx: <Self::RT as WebRuntime>::Context) -> Vec<<Self::RT as WebRuntime>::Value> {
+        self.args
+            .iter()
+            .map(|value| BoaValue::new(ctx.clone(), value.clone()))
+            .collect()
+    }
+
+    fn variadic(&self, ctx: <Self::RT as WebRuntime>::Context) -> <Self::RT as WebRuntime>::VariadicArgs {
+        BoaVariadicArgs { args: self.as_vec(ctx) }
+    }
+
+    fn variadic_start(
+        &self,
+        start: usize,
+        ctx: <Self::RT as WebRuntime>::Context,
+    ) -> <Self::RT as WebRuntime>::VariadicArgs {
+        BoaVariadicArgs {
+            args: self
+                .args
+                .iter()
+                .skip(start)
+                .map(|value| BoaValue::new(ctx.clone(), value.clone()))
+                .collect(),
+        }
+    }
+}
+
+pub struct BoaVariadicArgs {
+    args: Vec<BoaValue>,
+}
+
+impl VariadicArgs for BoaVariadicArgs {
+    type RT = BoaEngine;
+
+    fn get(&self, index: usize) -> Option<&<Self::RT as WebRuntime>::Value> {
+        self.args.get(index)
+    }
+
+    fn len(&self) -> usize {
+        self.args.len()
+    }
+
+    fn as_vec(&self) -> &Vec<<Self::RT as WebRuntime>::Value> {
+        &self.args
+    }
+
+    fn as_vec_as<T>(&self) -> Vec<T>
+    where
+        <Self::RT as WebRuntime>::Value: IntoRustValue<T>,
+    {
+        self.args
+            .iter()
+            .filter_map(|x| match x.to_rust_value() {
+                Ok(value) => Some(value),
+                Err(e) => {
+                    log::warn!("Failed to convert JS argument to Rust value: {e}");
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn get_as<T>(&self, index: usize) -> Option<T>
+    where
+        <Self::RT as WebRuntime>::Value: IntoRustValue<T>,
+    {
+        self.args.get(index).and_then(|x| x.to_rust_value().ok())
+    }
+}
+
+impl WebFunctionCallBackVariadic for BoaFunctionCallBackVariadic {
+    type RT = BoaEngine;
+
+    fn context(&mut self) -> <Self::RT as WebRuntime>::Context {
+        self.ctx.clone()
+    }
+
+    fn args(&mut self) -> &<Self::RT as WebRuntime>::VariadicArgsInternal {
+        &self.args
+    }
+
+    fn len(&self) -> usize {
+        self.args.len()
+    }
+
+    fn error(&mut self, error: impl Display) {
+        self.error = Some(error.to_string());
+    }
+
+    fn ret(&mut self, value: <Self::RT as WebRuntime>::Value) {
+        self.ret = Some(value.value);
+    }
+}
+
+impl WebFunctionVariadic for BoaFunctionVariadic {
+    type RT = BoaEngine;
+
+    fn new(
+        ctx: <Self::RT as WebRuntime>::Context,
+        f: impl Fn(&mut <Self::RT as WebRuntime>::FunctionCallBackVariadic) + 'static,
+    ) -> Result<Self> {
+        let function = native_function(&ctx, move |ctx, args| {
+            let mut cb = BoaFunctionCallBackVariadic {
+                ctx,
+                args: BoaVariadicArgsInternal {
+                    next: 0,
+                    args: args.to_vec(),
+                },
+                ret: None,
+                error: None,
+            };
+            f(&mut cb);
+
+            match cb.error {
+                Some(error) => Err(error),
+                None => Ok(cb.ret.unwrap_or_default()),
+            }
+        })?;
+
+        Ok(Self { ctx, function })
+    }
+
+    fn call(&mut self, args: &[<Self::RT as WebRuntime>::Value]) -> Result<<Self::RT as WebRuntime>::Value> {
+        call(&self.ctx, &self.function, args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gosub_webexecutor::js::{
+        Args, VariadicArgs, VariadicArgsInternal, WebContext, WebFunction, WebFunctionCallBack,
+        WebFunctionCallBackVariadic, WebFunctionVariadic, WebObject, WebRuntime, WebValue,
+    };
+
+    use crate::boa::{BoaEngine, BoaFunction, BoaFunctionVariadic, BoaObject, BoaValue};
+
+    #[test]
+    fn native_functions_call_back_into_js() {
+        let mut engine = BoaEngine::new();
+        let mut context = engine.new_context().unwrap();
+
+        // Calls its first argument with the second, from Rust.
+        let apply = BoaFunction::new(context.clone(), |cb| {
+            let ctx = cb.context();
+            let args = cb.args().as_vec(ctx);
+            let (Some(callee), Some(arg)) = (args.first(), args.get(1)) else {
+                cb.error("apply: two arguments are required");
+                return;
+            };
+            let value = callee
+                .as_object()
+                .and_then(|o| o.call_method("call", &[&callee.clone(), &arg.clone()]));
+            match value {
+                Ok(value) => cb.ret(value),
+                Err(e) => cb.error(e),
+            }
+        })
+        .unwrap();
+
+        let sum = BoaFunctionVariadic::new(context.clone(), |cb| {
+            let ctx = cb.context();
+            let total: f64 = cb.args().variadic(ctx.clone()).as_vec_as::<f64>().iter().sum();
+            cb.ret(BoaValue::new_number(ctx, total).unwrap());
+        })
+        .unwrap();
+
+        let native = BoaObject::new(context.clone()).unwrap();
+        native.set_method("apply", &apply).unwrap();
+        native.set_method_variadic("sum", &sum).unwrap();
+        context.set_on_global_object("native", native.into()).unwrap();
+
+        let value = context.run("native.apply((n) => native.sum(n, 2, 3), 37)").unwrap();
+        assert_eq!(value.as_number().unwrap(), 42.0);
+
+        let error = context.run("native.apply()").unwrap_err();
+        assert!(error.to_string().contains("two arguments are required"), "{error}");
+        let value = context
+            .run("try { native.apply(() => { throw new TypeError('inner'); }, 0) } catch (e) { String(e) }")
+            .unwrap();
+        assert!(value.as_string().unwrap().contains("TypeError: inner"));
+    }
+
+    #[test]
+    fn js_functions_can_be_called_from_rust() {
+        let mut engine = BoaEngine::new();
+        let mut context = engine.new_context().unwrap();
+
+        let double = context.run("(n) => n * 2").unwrap().as_object().unwrap();
+        let mut function = BoaFunction {
+            ctx: context.clone(),
+            function: double.object().clone(),
+        };
+        let value = function
+            .call(&[BoaValue::new_number(context.clone(), 21).unwrap()])
+            .unwrap();
+        assert_eq!(value.as_number().unwrap(), 42.0);
+    }
+}
@@ -0,0 +1,95 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+
+use boa_engine::builtins::promise::PromiseState;
+use boa_engine::module::{ModuleLoader, Referrer};
+use boa_engine::object::builtins::JsPromise;
+use boa_engine::{Context, JsError, JsNativeError, JsResult, JsString, Module, Source};
+
+use gosub_shared::types::Result;
+use gosub_webexecutor::js::ModuleGraph;
+
+use crate::boa::{BoaContext, BoaValue};
+
+/// Hands Boa the modules of the graphs run so far. A module is parsed once per context and
+/// known by its URL, which doubles as its path so imports can find their referrer.
+#[derive(Default)]
+pub(crate) struct GraphLoader {
+    modules: RefCell<HashMap<String, Module>>,
+    /// Referrer URL to import specifier to URL
+    imports: RefCell<HashMap<String, HashMap<String, String>>>,
+}
+
+impl GraphLoader {
+    fn resolve(&self, referrer: &Referrer, specifier: &str) -> Option<Module> {
+        let Referrer::Module(referrer) = referrer else {
+            return None;
+        };
+        let referrer = referrer.path()?.to_str()?;
+        let url = self.imports.borrow().get(referrer)?.get(specifier)?.clone();
+        self.modules.borrow().get(&url).cloned()
+    }
+}
+
+impl ModuleLoader for GraphLoader {
+    fn load_imported_module(
+        &self,
+        referrer: Referrer,
+        specifier: JsString,
+        finish_load: Box<dyn FnOnce(JsResult<Module>, &mut Context)>,
+        context: &mut Context,
+    ) {
+        let specifier = specifier.to_std_string_escaped();
+        let module = self.resolve(&referrer, &specifier).ok_or_else(|| {
+            JsNativeError::typ()
+                .with_message(format!("Failed to resolve module specifier \"{specifier}\""))
+                .into()
+        });
+        finish_load(module, context);
+    }
+}
+
+impl BoaContext {
+    pub(crate) fn run_module_graph(&mut self, url: &str, graph: &ModuleGraph) -> Result<BoaValue> {
+        let loader = Rc::clone(&self.ctx.loader);
+        let promise = self
+            .with(|context| evaluate(&loader, url, graph, context).map_err(|e| BoaContext::exception(context, e)))??;
+
+        Ok(BoaValue::new(self.clone(), promise.into()))
+    }
+}
+
+/// Parses the modules of `graph` that `loader` doesn't know yet and evaluates the one at `url`.
+fn evaluate(loader: &GraphLoader, url: &str, graph: &ModuleGraph, context: &mut Context) -> JsResult<JsPromise> {
+    for (module_url, record) in graph.iter() {
+        loader
+            .imports
+            .borrow_mut()
+            .entry(module_url.to_owned())
+            .or_default()
+            .extend(record.imports.clone());
+        if loader.modules.borrow().contains_key(module_url) {
+            continue;
+        }
+        let source = Source::from_bytes(record.source.as_bytes()).with_path(Path::new(module_url));
+        let module = Module::parse(source, None, context)?;
+        loader.modules.borrow_mut().insert(module_url.to_owned(), module);
+    }
+    let entry = loader
+        .modules
+        .borrow()
+        .get(url)
+        .cloned()
+        .ok_or_else(|| JsNativeError::typ().with_message(format!("module {url} is not in the graph")))?;
+
+    // Loading and linking go through promise jobs; the loader answers right away, so one round
+    // of jobs gets the module evaluated up to its first `await`.
+    let promise = entry.load_link_evaluate(context);
+    context.run_jobs();
+    if let PromiseState::Rejected(reason) = promise.state() {
+        return Err(JsError::from_opaque(reason));
+    }
+    Ok(promise)
+}
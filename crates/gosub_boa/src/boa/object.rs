@@ -0,0 +1,245 @@
+use core::fmt::Display;
+
+use boa_engine::property::PropertyDescriptor;
+use boa_engine::{JsObject, JsString, JsValue};
+
+use gosub_shared::types::Result;
+use gosub_webexecutor::js::{JSError, WebGetterCallback, WebObject, WebRuntime, WebSetterCallback};
+use gosub_webexecutor::Error;
+
+use crate::boa::{native_function, BoaContext, BoaEngine, BoaFunction, BoaFunctionVariadic, BoaValue};
+
+#[derive(Clone)]
+pub struct BoaObject {
+    pub(crate) ctx: BoaContext,
+    pub(crate) object: JsObject,
+}
+
+impl BoaObject {
+    pub fn new(ctx: BoaContext) -> Result<BoaObject> {
+        let object = ctx.with(|context| JsObject::with_object_proto(context.intrinsics()))?;
+
+        Ok(Self { ctx, object })
+    }
+
+    pub fn new_from(ctx: BoaContext, object: JsObject) -> Self {
+        Self { ctx, object }
+    }
+
+    pub fn object(&self) -> &JsObject {
+        &self.object
+    }
+
+    fn set(&self, name: &str, value: JsValue) -> Result<()> {
+        self.ctx.with(|context| {
+            self.object
+                .set(JsString::from(name), value, true, context)
+                .map(|_| ())
+                .map_err(|e| BoaContext::exception(context, e))
+        })?
+    }
+}
+
+pub struct GetterCallback {
+    ctx: BoaContext,
+    ret: BoaValue,
+    error: Option<String>,
+}
+
+impl WebGetterCallback for GetterCallback {
+    type RT = BoaEngine;
+
+    fn context(&mut self) -> &mut <Self::RT as WebRuntime>::Context {
+        &mut self.ctx
+    }
+
+    fn error(&mut self, error: impl Display) {
+        self.error = Some(error.to_string());
+    }
+
+    fn ret(&mut self, value: <Self::RT as WebRuntime>::Value) {
+        self.ret = value;
+    }
+}
+
+pub struct SetterCallback {
+    ctx: BoaContext,
+    value: BoaValue,
+    error: Option<String>,
+}
+
+impl WebSetterCallback for SetterCallback {
+    type RT = BoaEngine;
+
+    fn context(&mut self) -> &mut <Self::RT as WebRuntime>::Context {
+        &mut self.ctx
+    }
+
+    fn error(&mut self, error: impl Display) {
+        self.error = Some(error.to_string());
+    }
+
+    fn value(&mut self) -> &<Self::RT as WebRuntime>::Value {
+        &self.value
+    }
+}
+
+impl WebObject for BoaObject {
+    type RT = BoaEngine;
+
+    fn set_property(&self, name: &str, value: &BoaValue) -> Result<()> {
+        self.set(name, value.value.clone())
+    }
+
+    fn get_property(&self, name: &str) -> Result<<Self::RT as WebRuntime>::Value> {
+        let value = self.ctx.with(|context| {
+            self.object
+                .get(JsString::from(name), context)
+                .map_err(|e| BoaContext::exception(context, e))
+        })??;
+
+        Ok(BoaValue::new(self.ctx.clone(), value))
+    }
+
+    fn call_method(
+        &self,
+        name: &str,
+        args: &[&<Self::RT as WebRuntime>::Value],
+    ) -> Result<<Self::RT as WebRuntime>::Value> {
+        let args = args.iter().map(|arg| arg.value.clone()).collect::<Vec<_>>();
+        let this = JsValue::from(self.object.clone());
+
+        let value = self.ctx.with(|context| {
+            let method = self
+                .object
+                .get(JsString::from(name), context)
+                .map_err(|e| BoaContext::exception(context, e))?;
+            let Some(method) = method.as_callable() else {
+                return Err(Error::JS(JSError::Execution(format!("{name} is not a function"))).into());
+            };
+
+            method
+                .call(&this, &args, context)
+                .map_err(|e| BoaContext::exception(context, e))
+        })??;
+
+        Ok(BoaValue::new(self.ctx.clone(), value))
+    }
+
+    fn set_method(&self, name: &str, func: &BoaFunction) -> Result<()> {
+        self.set(name, func.function.clone().into())
+    }
+
+    fn set_method_variadic(&self, name: &str, func: &BoaFunctionVariadic) -> Result<()> {
+        self.set(name, func.function.clone().into())
+    }
+
+    fn set_property_accessor(
+        &self,
+        name: &str,
+        getter: Box<dyn Fn(&mut <Self::RT as WebRuntime>::GetterCB)>,
+        setter: Box<dyn Fn(&mut <Self::RT as WebRuntime>::SetterCB)>,
+    ) -> Result<()> {
+        let get = native_function(&self.ctx, move |ctx, _args| {
+            let mut cb = GetterCallback {
+                ret: BoaValue::new(ctx.clone(), JsValue::undefined()),
+                ctx,
+                error: None,
+            };
+            getter(&mut cb);
+
+            match cb.error {
+                Some(error) => Err(error),
+                None => Ok(cb.ret.value),
+            }
+        })?;
+
+        let set = native_function(&self.ctx, move |ctx, args| {
+            let value = args.first().cloned().unwrap_or_default();
+            let mut cb = SetterCallback {
+                value: BoaValue::new(ctx.clone(), value),
+                ctx,
+                error: None,
+            };
+            setter(&mut cb);
+
+            match cb.error {
+                Some(error) => Err(error),
+                None => Ok(JsValue::undefined()),
+            }
+        })?;
+
+        let descriptor = PropertyDescriptor::builder()
+            .get(get)
+            .set(set)
+            .enumerable(true)
+            .configurable(true)
+            .build();
+
+        self.ctx.with(|context| {
+            self.object
+                .define_property_or_throw(JsString::from(name), descriptor, context)
+                .map(|_| ())
+                .map_err(|e| BoaContext::exception(context, e))
+        })?
+    }
+
+    fn new(ctx: &<Self::RT as WebRuntime>::Context) -> Result<Self> {
+        Self::new(ctx.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use gosub_webexecutor::js::{WebContext, WebGetterCallback, WebObject, WebRuntime, WebSetterCallback, WebValue};
+
+    use crate::boa::{BoaEngine, BoaObject, BoaValue};
+
+    #[test]
+    fn test_object_accessor() {
+        let mut engine = BoaEngine::new();
+        let mut context = engine.new_context().unwrap();
+
+        let stored = Rc::new(RefCell::new(String::from("initial")));
+        let object = BoaObject::new(context.clone()).unwrap();
+
+        let read = Rc::clone(&stored);
+        let write = Rc::clone(&stored);
+        object
+            .set_property_accessor(
+                "text",
+                Box::new(move |cb| {
+                    let value = BoaValue::new_string(cb.context().clone(), &read.borrow()).unwrap();
+                    cb.ret(value);
+                }),
+                Box::new(move |cb| {
+                    let value = cb.value().as_string().unwrap();
+                    *write.borrow_mut() = value;
+                }),
+            )
+            .unwrap();
+        context.set_on_global_object("obj", object.into()).unwrap();
+
+        assert_eq!(context.run("obj.text").unwrap().as_string().unwrap(), "initial");
+        context.run("obj.text = 'changed'").unwrap();
+        assert_eq!(*stored.borrow(), "changed");
+    }
+
+    #[test]
+    fn test_object_method_call() {
+        let mut engine = BoaEngine::new();
+        let mut context = engine.new_context().unwrap();
+
+        let object = context.run("({ base: 40, add(n) { return this.base + n; } })").unwrap();
+        let object = object.as_object().unwrap();
+        let two = BoaValue::new_number(context.clone(), 2).unwrap();
+
+        let value = object.call_method("add", &[&two]).unwrap();
+        assert_eq!(value.as_number().unwrap(), 42.0);
+        assert!(object.call_method("base", &[]).is_err());
+        assert_eq!(object.get_property("base").unwrap().as_number().unwrap(), 40.0);
+    }
+}
@@ -0,0 +1,231 @@
+use std::fmt::{Debug, Formatter};
+
+use boa_engine::object::builtins::JsArray;
+use boa_engine::{JsString, JsValue};
+
+use gosub_shared::types::Result;
+use gosub_webexecutor::js::{AsArray, IntoWebValue, JSError, JSType, Ref, WebArray, WebRuntime, WebValue};
+use gosub_webexecutor::Error;
+
+use crate::boa::{BoaArray, BoaContext, BoaEngine, BoaObject};
+
+#[derive(Clone)]
+pub struct BoaValue {
+    pub(crate) ctx: BoaContext,
+    pub(crate) value: JsValue,
+}
+
+impl BoaValue {
+    pub fn new(ctx: BoaContext, value: JsValue) -> Self {
+        Self { ctx, value }
+    }
+
+    pub fn value(&self) -> &JsValue {
+        &self.value
+    }
+}
+
+impl Debug for BoaValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoaValue")
+            .field("value", &self.value)
+            .finish_non_exhaustive()
+    }
+}
+
+impl From<BoaObject> for BoaValue {
+    fn from(object: BoaObject) -> Self {
+        Self {
+            ctx: object.ctx,
+            value: object.object.into(),
+        }
+    }
+}
+
+impl From<BoaArray> for BoaValue {
+    fn from(array: BoaArray) -> Self {
+        Self {
+            ctx: array.ctx,
+            value: array.array.into(),
+        }
+    }
+}
+
+impl AsArray for BoaValue {
+    type Runtime = BoaEngine;
+
+    fn array(&self) -> Result<Ref<'_, <Self::Runtime as WebRuntime>::Array>> {
+        Ok(Ref::Owned(self.as_array()?))
+    }
+}
+
+impl WebValue for BoaValue {
+    type RT = BoaEngine;
+
+    fn as_string(&self) -> Result<String> {
+        self.ctx.with(|context| {
+            self.value
+                .to_string(context)
+                .map(|s| s.to_std_string_escaped())
+                .map_err(|e| BoaContext::exception(context, e))
+        })?
+    }
+
+    fn as_number(&self) -> Result<f64> {
+        self.ctx.with(|context| {
+            self.value
+                .to_number(context)
+                .map_err(|e| BoaContext::exception(context, e))
+        })?
+    }
+
+    fn as_bool(&self) -> Result<bool> {
+        Ok(self.value.to_boolean())
+    }
+
+    fn as_object(&self) -> Result<<Self::RT as WebRuntime>::Object> {
+        let object = self.ctx.with(|context| {
+            self.value
+                .to_object(context)
+                .map_err(|e| BoaContext::exception(context, e))
+        })??;
+
+        Ok(BoaObject::new_from(self.ctx.clone(), object))
+    }
+
+    fn as_array(&self) -> Result<<Self::RT as WebRuntime>::Array> {
+        let Some(object) = self.value.as_object().filter(|o| o.is_array()) else {
+            return Err(Error::JS(JSError::Conversion("value is not an array".to_owned())).into());
+        };
+
+        let array = JsArray::from_object(object.clone())
+            .map_err(|_| Error::JS(JSError::Conversion("value is not an array".to_owned())))?;
+
+        Ok(BoaArray::new_from(self.ctx.clone(), array))
+    }
+
+    fn is_string(&self) -> bool {
+        self.value.is_string()
+    }
+
+    fn is_number(&self) -> bool {
+        self.value.is_number()
+    }
+
+    fn is_bool(&self) -> bool {
+        self.value.is_boolean()
+    }
+
+    fn is_object(&self) -> bool {
+        self.value.is_object()
+    }
+
+    fn is_array(&self) -> bool {
+        self.value.as_object().is_some_and(|o| o.is_array())
+    }
+
+    fn is_null(&self) -> bool {
+        self.value.is_null()
+    }
+
+    fn is_undefined(&self) -> bool {
+        self.value.is_undefined()
+    }
+
+    fn is_function(&self) -> bool {
+        self.value.is_callable()
+    }
+
+    fn type_of(&self) -> JSType {
+        if self.is_undefined() {
+            JSType::Undefined
+        } else if self.is_null() {
+            JSType::Null
+        } else if self.is_bool() {
+            JSType::Boolean
+        } else if self.is_number() {
+            JSType::Number
+        } else if self.is_string() {
+            JSType::String
+        } else if self.is_array() {
+            JSType::Array
+        } else if self.is_function() {
+            JSType::Function
+        } else if self.is_object() {
+            JSType::Object
+        } else if self.value.is_bigint() {
+            JSType::Other("bigint".to_owned())
+        } else {
+            JSType::Other("symbol".to_owned())
+        }
+    }
+
+    fn new_object(ctx: <Self::RT as WebRuntime>::Context) -> Result<<Self::RT as WebRuntime>::Object> {
+        BoaObject::new(ctx)
+    }
+
+    fn new_array<T: IntoWebValue<Self, Value = Self>>(
+        ctx: <Self::RT as WebRuntime>::Context,
+        value: &[T],
+    ) -> Result<<Self::RT as WebRuntime>::Array> {
+        let data = value
+            .iter()
+            .map(|v| v.to_web_value(ctx.clone()))
+            .collect::<Result<Vec<_>>>()?;
+
+        BoaArray::new_with_data(ctx, &data)
+    }
+
+    fn new_empty_array(ctx: <Self::RT as WebRuntime>::Context) -> Result<<Self::RT as WebRuntime>::Array> {
+        BoaArray::new(ctx, 0)
+    }
+
+    fn new_string(ctx: <Self::RT as WebRuntime>::Context, value: &str) -> Result<Self> {
+        Ok(Self::new(ctx, JsString::from(value).into()))
+    }
+
+    fn new_number<N: Into<f64>>(ctx: <Self::RT as WebRuntime>::Context, value: N) -> Result<Self> {
+        Ok(Self::new(ctx, JsValue::from(value.into())))
+    }
+
+    fn new_bool(ctx: <Self::RT as WebRuntime>::Context, value: bool) -> Result<Self> {
+        Ok(Self::new(ctx, JsValue::from(value)))
+    }
+
+    fn new_null(ctx: <Self::RT as WebRuntime>::Context) -> Result<Self> {
+        Ok(Self::new(ctx, JsValue::null()))
+    }
+
+    fn new_undefined(ctx: <Self::RT as WebRuntime>::Context) -> Result<Self> {
+        Ok(Self::new(ctx, JsValue::undefined()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gosub_webexecutor::js::{JSType, WebArray, WebContext, WebRuntime, WebValue};
+
+    use crate::boa::{BoaEngine, BoaValue};
+
+    #[test]
+    fn values_convert_both_ways() {
+        let mut engine = BoaEngine::new();
+        let mut context = engine.new_context().unwrap();
+
+        let value = BoaValue::new_string(context.clone(), "hello").unwrap();
+        assert!(value.is_string());
+        assert_eq!(value.as_string().unwrap(), "hello");
+        assert_eq!(value.type_of(), JSType::String);
+
+        let value = context.run("[1, 'two', null]").unwrap();
+        assert_eq!(value.type_of(), JSType::Array);
+        let array = value.as_array().unwrap();
+        assert_eq!(array.len(), 3);
+        assert_eq!(array.get(1).unwrap().as_string().unwrap(), "two");
+        assert!(array.get(2).unwrap().is_null());
+
+        let value = context.run("(() => 1)").unwrap();
+        assert_eq!(value.type_of(), JSType::Function);
+        assert!(context.run("({})").unwrap().as_array().is_err());
+    }
+}
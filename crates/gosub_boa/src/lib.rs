@@ -0,0 +1,2 @@
+mod boa;
+pub use boa::*;
@@ -1,4 +1,5 @@
 //! Error results that can be returned from the engine
+use crate::settings::Constraint;
 use thiserror::Error;
 
 /// Errors returned by the config crate
@@ -7,16 +8,38 @@ pub enum Error {
     #[error("config error: {0}")]
     Config(String),
 
+    #[error("setting {0} is not registered")]
+    NotRegistered(String),
+
+    #[error("setting {0} is already registered")]
+    AlreadyRegistered(String),
+
+    #[error("invalid value for {key}: {error}")]
+    InvalidValue { key: String, error: ValueError },
+
     #[error("io error: {0}")]
     IO(#[from] std::io::Error),
 
     #[error("json parsing error: {0}")]
     JsonSerde(#[from] serde_json::Error),
 
-    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
     #[error("sqlite error: {0}")]
     Sqlite(#[from] rusqlite::Error),
 
     #[error("there was a problem: {0}")]
     Generic(String),
 }
+
+/// Why a value doesn't fit a setting, see [`SettingInfo::validate`](crate::settings::SettingInfo::validate)
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ValueError {
+    #[error("expected a {expected} value, got a {found} one")]
+    WrongType {
+        expected: &'static str,
+        found: &'static str,
+    },
+
+    #[error("{value} is not allowed, expected {constraint}")]
+    NotAllowed { value: String, constraint: Constraint },
+}
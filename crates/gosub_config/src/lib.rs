@@ -7,11 +7,14 @@ pub(crate) type Result<T> = std::result::Result<T, Error>;
 
 use crate::settings::{Setting, SettingInfo};
 use crate::storage::MemoryStorageAdapter;
+use cow_utils::CowUtils;
 use log::warn;
-use parking_lot::RwLock;
-use std::collections::HashMap;
-use std::mem;
+use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
 use wildmatch::WildMatch;
 
 /// `StoreAdapter` is the interface for storing and retrieving settings
@@ -43,6 +46,30 @@ pub trait StorageAdapter: Send + Sync {
     fn flush(&self) -> Result<()> {
         Ok(())
     }
+
+    /// Re-reads the backing store when something other than this adapter changed it, e.g. a user
+    /// editing the settings file, and returns the keys whose stored value changed (including
+    /// removed ones). Adapters whose store nobody else writes to return nothing.
+    fn reload(&self) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Prefix of the environment variables that override settings, see [`Config::apply_env`].
+pub const ENV_PREFIX: &str = "GOSUB_";
+
+/// Where the current value of a setting comes from. Each layer wins over the ones before it:
+/// defaults < stored < environment < command line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layer {
+    /// The default from the schema
+    Default,
+    /// A value kept by the storage adapter
+    Stored,
+    /// A `GOSUB_*` environment variable
+    Env,
+    /// A command line override
+    Cli,
 }
 
 /// Identifies a registered subscription so it can later be removed via [`Config::unsubscribe`].
@@ -56,6 +83,10 @@ pub struct SubscriptionId(u64);
 /// key it also subscribes to.
 pub type SubscriptionCallback = Arc<dyn Fn(&str, &Setting) + Send + Sync>;
 
+/// Migrates exported settings from one schema version to the next, see [`Config::add_migration`].
+/// It may rename, convert or drop entries; whatever is left is imported.
+pub type Migration = Arc<dyn Fn(&mut BTreeMap<String, Setting>) + Send + Sync>;
+
 struct Subscription {
     id: SubscriptionId,
     matcher: WildMatch,
@@ -74,7 +105,8 @@ impl Config {
     ///
     /// `gosub_config` is agnostic of which settings exist: the caller (e.g. the engine) supplies
     /// the schema - the set of known keys with their defaults and constraints. Only keys present
-    /// in the schema can be read or written.
+    /// in the schema, or added later with [`Config::register_runtime_setting`], can be read or
+    /// written; others give [`Error::NotRegistered`].
     #[must_use]
     pub fn new(schema: impl IntoIterator<Item = SettingInfo>) -> Self {
         Config(Arc::new(RwLock::new(ConfigStore::new(schema))))
@@ -112,12 +144,19 @@ impl Config {
         self.0.write().absorb(entries, namespace)
     }
 
-    /// Returns the setting for the given key, falling back to the default, or `Ok(None)` when the
-    /// key is unknown. See [`ConfigStore::get`].
-    pub fn get(&self, key: &str) -> Result<Option<Setting>> {
+    /// Returns the setting for the given key, falling back to the default, or
+    /// [`Error::NotRegistered`] when the key is unknown. See [`ConfigStore::get`].
+    pub fn get(&self, key: &str) -> Result<Setting> {
         self.0.read().get(key)
     }
 
+    /// Registers a setting that isn't part of the schema the config was built from, e.g. one of
+    /// an extension loaded at runtime. A value stored for the key earlier is picked up when it
+    /// fits. See [`ConfigStore::register_runtime_setting`].
+    pub fn register_runtime_setting(&self, info: SettingInfo) -> Result<()> {
+        self.0.write().register_runtime_setting(info)
+    }
+
     /// Sets a setting, persisting it and notifying any matching subscribers when the value changes.
     pub fn set(&self, key: &str, value: Setting) -> Result<()> {
         // Mutate under the lock, collect the callbacks to fire, then release the lock *before*
@@ -160,6 +199,124 @@ impl Config {
         self.0.read().flush()
     }
 
+    /// Picks up what was changed in the storage behind this config's back, e.g. by editing the
+    /// settings file, notifying the subscribers of the settings that changed. Stored values that
+    /// don't fit the schema are ignored. Returns the number of settings that changed.
+    pub fn reload(&self) -> Result<usize> {
+        let store = self.0.read();
+        let changed = store.reload()?;
+        Ok(Self::notify(store, changed))
+    }
+
+    /// Overrides settings with the `GOSUB_*` variables of this process's environment, see
+    /// [`Config::set_env_overrides`].
+    pub fn apply_env(&self) -> Result<usize> {
+        self.set_env_overrides(std::env::vars())
+    }
+
+    /// Replaces the environment layer with the `GOSUB_*` variables among `vars`. A variable names
+    /// a setting by its key in upper case, with dots as underscores, e.g.
+    /// `GOSUB_DNS_REMOTE_RETRIES=5` for `dns.remote.retries`; its value is the bare value without
+    /// type prefix. Variables that don't name a setting are left alone. Overrides win over stored
+    /// values but are never persisted, so `set` still stores a value for when the override is
+    /// gone. Returns the number of settings whose value changed.
+    pub fn set_env_overrides(&self, vars: impl IntoIterator<Item = (String, String)>) -> Result<usize> {
+        let mut store = self.0.write();
+        let changed = store.set_env_overrides(vars)?;
+        Ok(Self::notify(RwLockWriteGuard::downgrade(store), changed))
+    }
+
+    /// Replaces the command line layer with `overrides`, each written as `key=value` with the
+    /// bare value (e.g. `dns.remote.retries=5`). These win over everything else and are never
+    /// persisted. Returns the number of settings whose value changed.
+    pub fn set_cli_overrides<S: AsRef<str>>(&self, overrides: impl IntoIterator<Item = S>) -> Result<usize> {
+        let mut store = self.0.write();
+        let changed = store.set_cli_overrides(overrides)?;
+        Ok(Self::notify(RwLockWriteGuard::downgrade(store), changed))
+    }
+
+    /// Drops the environment and command line overrides, falling back to the stored values.
+    pub fn clear_overrides(&self) -> usize {
+        let mut store = self.0.write();
+        let changed = store.clear_overrides();
+        Self::notify(RwLockWriteGuard::downgrade(store), changed)
+    }
+
+    /// Sets the version of the schema this config was built from. Exports carry it, so that
+    /// importing an older export can run the migrations added since.
+    pub fn set_schema_version(&self, version: u32) {
+        self.0.write().schema_version = version;
+    }
+
+    /// The version of the schema, see [`Config::set_schema_version`].
+    #[must_use]
+    pub fn schema_version(&self) -> u32 {
+        self.0.read().schema_version
+    }
+
+    /// Adds the migration that takes exported settings from schema version `from` to `from + 1`,
+    /// e.g. to follow a renamed key. Replaces an earlier migration from the same version.
+    pub fn add_migration<F>(&self, from: u32, migration: F)
+    where
+        F: Fn(&mut BTreeMap<String, Setting>) + Send + Sync + 'static,
+    {
+        self.0.write().migrations.insert(from, Arc::new(migration));
+    }
+
+    /// Exports the stored settings as JSON, for backups or moving them to another profile. See
+    /// [`ConfigStore::export`].
+    pub fn export_json(&self) -> Result<String> {
+        self.0.read().export()
+    }
+
+    /// Imports settings exported by [`Config::export_json`], migrating them to the current schema
+    /// version first and notifying the subscribers of the settings that changed. See
+    /// [`ConfigStore::import`]. Returns the number of settings that changed.
+    pub fn import_json(&self, json: &str) -> Result<usize> {
+        let store = self.0.write();
+        let changed = store.import(json)?;
+        Ok(Self::notify(RwLockWriteGuard::downgrade(store), changed))
+    }
+
+    /// Returns the layer the current value of `key` comes from, or `None` for an unknown key.
+    /// Settings UIs can use this to show that a setting is overridden and can't be changed there.
+    #[must_use]
+    pub fn layer(&self, key: &str) -> Option<Layer> {
+        self.0.read().layer(key)
+    }
+
+    /// Calls the subscribers of the `changed` settings, after letting go of the store so they can
+    /// use it. Returns the number of settings that changed.
+    fn notify(store: RwLockReadGuard<'_, ConfigStore>, changed: Vec<(String, Setting)>) -> usize {
+        let callbacks: Vec<_> = changed.iter().map(|(key, _)| store.matching_callbacks(key)).collect();
+        drop(store);
+        for ((key, value), callbacks) in changed.iter().zip(callbacks) {
+            for callback in callbacks {
+                callback(key, value);
+            }
+        }
+        changed.len()
+    }
+
+    /// Calls [`Config::reload`] every `interval` on a thread of its own, until the returned
+    /// watcher is dropped. With the JSON adapter this reloads the settings file whenever it
+    /// changes.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn watch(&self, interval: Duration) -> Result<ConfigWatcher> {
+        let (stop, stopped) = std::sync::mpsc::channel::<()>();
+        let config = self.clone();
+        std::thread::Builder::new()
+            .name("config-watcher".into())
+            .spawn(move || {
+                while let Err(std::sync::mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    if let Err(err) = config.reload() {
+                        warn!("config: reloading the settings failed: {err}");
+                    }
+                }
+            })?;
+        Ok(ConfigWatcher { _stop: stop })
+    }
+
     /// Returns true when the store knows about the given key.
     #[must_use]
     pub fn has(&self, key: &str) -> bool {
@@ -178,6 +335,13 @@ impl Config {
         self.0.read().get_info(key)
     }
 
+    /// Returns the information of every known setting, so a settings UI can render each one with
+    /// fitting controls and check input against [`SettingInfo::validate`] before setting it.
+    #[must_use]
+    pub fn schema(&self) -> Vec<SettingInfo> {
+        self.0.read().schema()
+    }
+
     /// Subscribes to changes on settings whose key matches `pattern` (a [`WildMatch`] pattern, so
     /// `*`/`?` wildcards work, e.g. `dns.*` or `*`). The callback fires whenever a matching
     /// setting's value actually changes via `set` or `remove`. Returns an id used to unsubscribe.
@@ -230,11 +394,10 @@ impl Config {
     }
 
     /// Shared helper for the typed getters: reads the setting, applies `convert`, or returns
-    /// `default` when the key is unknown (logging on a storage error).
+    /// `default` when the key is unknown or on a storage error (logging either).
     fn typed_get<T>(&self, key: &str, default: T, convert: impl Fn(&Setting) -> T) -> T {
         match self.get(key) {
-            Ok(Some(setting)) => convert(&setting),
-            Ok(None) => default,
+            Ok(setting) => convert(&setting),
             Err(err) => {
                 warn!("config error: {err}");
                 default
@@ -243,6 +406,12 @@ impl Config {
     }
 }
 
+/// Keeps reloading a [`Config`], see [`Config::watch`]. Dropping it stops that.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct ConfigWatcher {
+    _stop: std::sync::mpsc::Sender<()>,
+}
+
 /// Grants access to a [`Config`] handle. Subsystems that only need to read or watch settings
 /// should bound on `T: HasConfig` rather than taking a concrete context type, so they stay
 /// decoupled from how the engine is assembled.
@@ -273,6 +442,14 @@ pub struct ConfigStore {
     subscriptions: Vec<Subscription>,
     /// Monotonic counter used to hand out unique `SubscriptionId`s
     next_subscription_id: u64,
+    /// Values from `GOSUB_*` environment variables, winning over the stored ones
+    env_overrides: HashMap<String, Setting>,
+    /// Values from the command line, winning over everything else
+    cli_overrides: HashMap<String, Setting>,
+    /// Version of the schema, written into exports
+    schema_version: u32,
+    /// Migrations of exported settings, by the schema version they migrate from
+    migrations: BTreeMap<u32, Migration>,
 }
 
 impl ConfigStore {
@@ -286,6 +463,10 @@ impl ConfigStore {
             storage: Box::new(MemoryStorageAdapter::new()),
             subscriptions: Vec::new(),
             next_subscription_id: 0,
+            env_overrides: HashMap::new(),
+            cli_overrides: HashMap::new(),
+            schema_version: 1,
+            migrations: BTreeMap::new(),
         };
 
         for info in schema {
@@ -338,55 +519,85 @@ impl ConfigStore {
         self.settings_info.get(key).cloned()
     }
 
-    /// Returns the setting with the given key. If the setting is not found in the current
-    /// storage, it will load the key from the storage. If the key is still not found, it will
-    /// return the default value for the given key. Returns `Ok(None)` when the key is unknown.
-    pub fn get(&self, key: &str) -> Result<Option<Setting>> {
+    /// Returns the information of every known setting, in registration order.
+    pub fn schema(&self) -> Vec<SettingInfo> {
+        self.setting_keys
+            .iter()
+            .filter_map(|key| self.settings_info.get(key).cloned())
+            .collect()
+    }
+
+    /// Returns the setting with the given key. A command line or environment override wins;
+    /// otherwise, if the setting is not found in the current storage, it will load the key from
+    /// the storage. If the key is still not found, it will return the default value for the given
+    /// key. Returns [`Error::NotRegistered`] when the key is unknown.
+    pub fn get(&self, key: &str) -> Result<Setting> {
+        let Some(info) = self.settings_info.get(key) else {
+            return Err(Error::NotRegistered(key.to_string()));
+        };
+
+        if let Some(setting) = self.override_for(key) {
+            return Ok(setting.clone());
+        }
+
         if let Some(setting) = self.settings.lock().get(key) {
-            return Ok(Some(setting.clone()));
+            return Ok(setting.clone());
         }
 
         // Setting not found, try and load it from the storage adapter
         if let Some(setting) = self.storage.get(key)? {
             self.settings.lock().insert(key.to_string(), setting.clone());
-            return Ok(Some(setting.clone()));
+            return Ok(setting);
         }
 
         // Return the default value for the setting when nothing is found
-        if let Some(info) = self.settings_info.get(key) {
-            return Ok(Some(info.default.clone()));
+        Ok(info.default.clone())
+    }
+
+    /// Registers a setting at runtime, next to the ones of the schema. Its default has to satisfy
+    /// its own constraint, and the key must not be known yet ([`Error::AlreadyRegistered`]). A
+    /// value the storage holds for the key is used when it fits; otherwise the default is.
+    pub fn register_runtime_setting(&mut self, info: SettingInfo) -> Result<()> {
+        let key = info.key.clone();
+        if self.settings_info.contains_key(&key) {
+            return Err(Error::AlreadyRegistered(key));
         }
+        Self::validate(&key, &info, &info.default)?;
 
-        Ok(None)
+        let stored = self.settings.lock().get(&key).cloned();
+        let stored = match stored {
+            Some(value) => Some(value),
+            None => self.storage.get(&key)?,
+        };
+        let value = match stored {
+            Some(value) => match Self::validate(&key, &info, &value) {
+                Ok(()) => value,
+                Err(err) => {
+                    warn!("config: ignoring stored value: {err}");
+                    info.default.clone()
+                }
+            },
+            None => info.default.clone(),
+        };
+
+        self.settings.lock().insert(key.clone(), value);
+        self.setting_keys.push(key.clone());
+        self.settings_info.insert(key, info);
+        Ok(())
     }
 
     /// Sets the given setting to the given value and persists it. The setting MUST have a
-    /// settings-info entry and satisfy its type and constraint, otherwise an error is returned.
+    /// settings-info entry and satisfy its type and constraint, otherwise an error is returned
+    /// ([`Error::InvalidValue`] for a value that doesn't fit).
     /// Returns `Ok(Some(value))` when the value actually changed (so the caller should notify
-    /// subscribers), or `Ok(None)` when the value was already set to `value`.
+    /// subscribers), or `Ok(None)` when the value was already set to `value` or an override hides
+    /// it.
     pub fn set(&self, key: &str, value: Setting) -> Result<Option<Setting>> {
-        let info = if let Some(info) = self.settings_info.get(key) {
-            info
-        } else {
-            warn!("config: Setting {key} is not known");
-            return Err(Error::Config(format!("Setting {key} is not known")));
+        let Some(info) = self.settings_info.get(key) else {
+            return Err(Error::NotRegistered(key.to_string()));
         };
 
-        if mem::discriminant(&info.default) != mem::discriminant(&value) {
-            warn!("config: Setting {key} is of different type than setting expects");
-            return Err(Error::Config(format!(
-                "Setting {key} is of different type than expected"
-            )));
-        }
-
-        if let Some(constraint) = &info.constraint {
-            if !constraint.allows(&value) {
-                warn!("config: Setting {key} value {value} violates its constraint");
-                return Err(Error::Config(format!(
-                    "Setting {key} value is not allowed by its constraint"
-                )));
-            }
-        }
+        Self::validate(key, info, &value)?;
 
         let changed = {
             let mut settings = self.settings.lock();
@@ -396,18 +607,233 @@ impl ConfigStore {
         };
         self.storage.set(key, value.clone())?;
 
-        Ok(changed.then_some(value))
+        Ok((changed && self.override_for(key).is_none()).then_some(value))
+    }
+
+    /// Checks that `value` has the type of `info`'s default and satisfies its constraint.
+    fn validate(key: &str, info: &SettingInfo, value: &Setting) -> Result<()> {
+        info.validate(value).map_err(|error| Error::InvalidValue {
+            key: key.to_string(),
+            error,
+        })
+    }
+
+    /// Reloads the storage and takes over its changes to known settings; a removed value
+    /// reverts to the default. Returns the settings whose value changed.
+    fn reload(&self) -> Result<Vec<(String, Setting)>> {
+        let mut changed = Vec::new();
+        for key in self.storage.reload()? {
+            let Some(info) = self.settings_info.get(&key) else {
+                continue;
+            };
+            let value = match self.storage.get(&key)? {
+                Some(value) => match Self::validate(&key, info, &value) {
+                    Ok(()) => value,
+                    Err(err) => {
+                        warn!("config: ignoring stored value: {err}");
+                        continue;
+                    }
+                },
+                None => info.default.clone(),
+            };
+
+            let mut settings = self.settings.lock();
+            if settings.get(&key) != Some(&value) {
+                settings.insert(key.clone(), value.clone());
+                if self.override_for(&key).is_none() {
+                    changed.push((key, value));
+                }
+            }
+        }
+        Ok(changed)
+    }
+
+    /// The override of the given key, if any
+    fn override_for(&self, key: &str) -> Option<&Setting> {
+        self.cli_overrides.get(key).or_else(|| self.env_overrides.get(key))
+    }
+
+    /// Returns the layer the current value of `key` comes from, or `None` for an unknown key.
+    pub fn layer(&self, key: &str) -> Option<Layer> {
+        if !self.settings_info.contains_key(key) {
+            return None;
+        }
+
+        Some(if self.cli_overrides.contains_key(key) {
+            Layer::Cli
+        } else if self.env_overrides.contains_key(key) {
+            Layer::Env
+        } else if matches!(self.storage.get(key), Ok(Some(_))) {
+            Layer::Stored
+        } else {
+            Layer::Default
+        })
+    }
+
+    /// Replaces the environment layer with the `GOSUB_*` variables among `vars` that name a
+    /// known setting, see [`Config::set_env_overrides`]. Nothing changes when one doesn't fit its
+    /// setting. Returns the settings whose value changed.
+    pub fn set_env_overrides(
+        &mut self,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Vec<(String, Setting)>> {
+        let names: HashMap<String, &str> = self
+            .setting_keys
+            .iter()
+            .map(|key| {
+                let name = key.cow_replace('.', "_").cow_to_uppercase().into_owned();
+                (format!("{ENV_PREFIX}{name}"), key.as_str())
+            })
+            .collect();
+
+        let mut overrides = HashMap::new();
+        for (name, raw) in vars {
+            let Some(key) = names.get(&name) else {
+                continue;
+            };
+            let value = self.parse_override(key, &raw)?;
+            overrides.insert((*key).to_string(), value);
+        }
+
+        let keys = self.env_overrides.keys().chain(overrides.keys()).cloned().collect();
+        Ok(self.changes_by(keys, |store| store.env_overrides = overrides))
+    }
+
+    /// Replaces the command line layer with `overrides`, written as `key=value`, see
+    /// [`Config::set_cli_overrides`]. Nothing changes when one of them is unknown or doesn't fit
+    /// its setting. Returns the settings whose value changed.
+    pub fn set_cli_overrides<S: AsRef<str>>(
+        &mut self,
+        overrides: impl IntoIterator<Item = S>,
+    ) -> Result<Vec<(String, Setting)>> {
+        let mut parsed = HashMap::new();
+        for arg in overrides {
+            let arg = arg.as_ref();
+            let (key, raw) = arg
+                .split_once('=')
+                .ok_or_else(|| Error::Config(format!("override {arg:?} is not written as key=value")))?;
+            let key = key.trim();
+            let value = self.parse_override(key, raw)?;
+            parsed.insert(key.to_string(), value);
+        }
+
+        let keys = self.cli_overrides.keys().chain(parsed.keys()).cloned().collect();
+        Ok(self.changes_by(keys, |store| store.cli_overrides = parsed))
+    }
+
+    /// Drops the environment and command line overrides. Returns the settings whose value
+    /// changed.
+    pub fn clear_overrides(&mut self) -> Vec<(String, Setting)> {
+        let keys = self
+            .env_overrides
+            .keys()
+            .chain(self.cli_overrides.keys())
+            .cloned()
+            .collect();
+        self.changes_by(keys, |store| {
+            store.env_overrides.clear();
+            store.cli_overrides.clear();
+        })
+    }
+
+    /// Reads the bare value `raw` as an override of `key`, checking it like [`ConfigStore::set`].
+    fn parse_override(&self, key: &str, raw: &str) -> Result<Setting> {
+        let Some(info) = self.settings_info.get(key) else {
+            return Err(Error::NotRegistered(key.to_string()));
+        };
+        let value = info
+            .default
+            .parse_like(raw)
+            .map_err(|err| Error::Config(format!("override of {key}: {err}")))?;
+        Self::validate(key, info, &value)?;
+        Ok(value)
+    }
+
+    /// Exports the settings kept by the storage adapter (so not the defaults, nor the overrides)
+    /// as a JSON document along with the schema version:
+    ///
+    /// ```json
+    /// { "schema_version": 1, "settings": { "dns.remote.retries": "u:5" } }
+    /// ```
+    pub fn export(&self) -> Result<String> {
+        let settings: BTreeMap<String, Setting> = self
+            .storage
+            .all()?
+            .into_iter()
+            .filter(|(key, _)| self.settings_info.contains_key(key))
+            .collect();
+        let mut export = serde_json::Map::new();
+        export.insert("schema_version".into(), Value::from(self.schema_version));
+        export.insert("settings".into(), serde_json::to_value(settings)?);
+        Ok(serde_json::to_string_pretty(&export)?)
+    }
+
+    /// Imports settings exported by [`ConfigStore::export`], storing them like [`ConfigStore::set`].
+    /// An export from an older schema version runs through the migrations from its version on;
+    /// one from a newer version is refused. Settings that are unknown or don't fit the schema
+    /// (after migrating) are skipped. Returns the settings whose value changed.
+    pub fn import(&self, json: &str) -> Result<Vec<(String, Setting)>> {
+        let export: Value = serde_json::from_str(json)?;
+        let version = export
+            .get("schema_version")
+            .and_then(Value::as_u64)
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| Error::Config("settings export has no schema version".into()))?;
+        if version > self.schema_version {
+            return Err(Error::Config(format!(
+                "settings export is of schema version {version}, newer than {}",
+                self.schema_version
+            )));
+        }
+
+        let mut settings: BTreeMap<String, Setting> = match export.get("settings") {
+            Some(settings) => serde_json::from_value(settings.clone())?,
+            None => BTreeMap::new(),
+        };
+        for (_, migration) in self.migrations.range(version..self.schema_version) {
+            migration(&mut settings);
+        }
+
+        let mut changed = Vec::new();
+        for (key, value) in settings {
+            let Some(info) = self.settings_info.get(&key) else {
+                warn!("config: import skipped unknown key {key}");
+                continue;
+            };
+            if let Err(err) = Self::validate(&key, info, &value) {
+                warn!("config: import skipped a value: {err}");
+                continue;
+            }
+            if let Some(value) = self.set(&key, value)? {
+                changed.push((key, value));
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Applies `change`, returning which of `keys` ended up with a different value.
+    fn changes_by(&mut self, mut keys: Vec<String>, change: impl FnOnce(&mut Self)) -> Vec<(String, Setting)> {
+        keys.sort();
+        keys.dedup();
+        let before: Vec<Option<Setting>> = keys.iter().map(|key| self.get(key).ok()).collect();
+
+        change(self);
+
+        keys.into_iter()
+            .zip(before)
+            .filter_map(|(key, before)| {
+                let after = self.get(&key).ok()?;
+                (before.as_ref() != Some(&after)).then_some((key, after))
+            })
+            .collect()
     }
 
     /// Removes the stored override for the given key, reverting it back to its default value. The key
     /// MUST have a settings-info entry, otherwise this function returns an error and does nothing.
     /// Returns `Ok(Some(default))` when the value actually changed, or `Ok(None)` otherwise.
     pub fn remove(&self, key: &str) -> Result<Option<Setting>> {
-        let info = if let Some(info) = self.settings_info.get(key) {
-            info
-        } else {
-            warn!("config: Setting {key} is not known");
-            return Err(Error::Config(format!("Setting {key} is not known")));
+        let Some(info) = self.settings_info.get(key) else {
+            return Err(Error::NotRegistered(key.to_string()));
         };
 
         self.storage.remove(key)?;
@@ -421,7 +847,7 @@ impl ConfigStore {
             changed
         };
 
-        Ok(changed.then_some(default))
+        Ok((changed && self.override_for(key).is_none()).then_some(default))
     }
 
     /// Flushes any buffered writes in the underlying storage adapter to its backing store.
@@ -508,6 +934,7 @@ impl ConfigStore {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::errors::ValueError;
     use crate::settings::Constraint;
     use parking_lot::Mutex;
     use std::str::FromStr;
@@ -526,8 +953,8 @@ mod test {
 
     /// A small, self-contained schema for the tests. `gosub_config` no longer ships any settings of
     /// its own, so the tests define exactly the keys they exercise.
-    fn test_config() -> Config {
-        Config::new([
+    fn test_schema() -> Vec<SettingInfo> {
+        vec![
             info("dns.local.enabled", "b:true", None),
             info("dns.cache.max_entries", "u:1000", None),
             info("dns.cache.ttl.override.seconds", "u:0", None),
@@ -539,18 +966,22 @@ mod test {
             info("useragent.tab.close_button", "m:left", Some("left,right")),
             info("useragent.tab.max_opened", "i:-1", Some("-1,0-9999")),
             info("renderer.opengl.enabled", "b:true", None),
-        ])
+        ]
+    }
+
+    fn test_config() -> Config {
+        Config::new(test_schema())
     }
 
     #[test]
     fn get_and_set() {
         let cfg = test_config();
 
-        let setting = cfg.get("dns.local.enabled").unwrap().unwrap();
+        let setting = cfg.get("dns.local.enabled").unwrap();
         assert_eq!(setting, Setting::Bool(true));
 
         cfg.set("dns.local.enabled", Setting::Bool(false)).unwrap();
-        assert_eq!(cfg.get("dns.local.enabled").unwrap().unwrap(), Setting::Bool(false));
+        assert_eq!(cfg.get("dns.local.enabled").unwrap(), Setting::Bool(false));
     }
 
     #[test]
@@ -563,14 +994,44 @@ mod test {
 
         let cfg = test_config();
         let result = cfg.set("dns.local.enabled", Setting::String("wont accept strings".into()));
-        assert!(result.is_err());
+        assert!(matches!(
+            result,
+            Err(Error::InvalidValue {
+                error: ValueError::WrongType {
+                    expected: "boolean",
+                    found: "string"
+                },
+                ..
+            })
+        ));
+
+        let result = cfg.set("useragent.tab.max_opened", Setting::SInt(10_000));
+        assert!(matches!(
+            result,
+            Err(Error::InvalidValue { ref key, error: ValueError::NotAllowed { .. } }) if key == "useragent.tab.max_opened"
+        ));
+        assert_eq!(cfg.get_sint("useragent.tab.max_opened"), -1);
 
+        // The caller gets the reason; nothing is logged behind its back.
         testing_logger::validate(|captured_logs| {
-            assert_eq!(captured_logs.len(), 1);
-            assert_eq!(captured_logs[0].level, log::Level::Warn);
+            assert_eq!(captured_logs.len(), 0);
         });
     }
 
+    #[test]
+    fn schema_lists_constraints() {
+        let cfg = test_config();
+        let schema = cfg.schema();
+        assert_eq!(schema.len(), test_schema().len());
+        assert_eq!(schema[0].key, "dns.local.enabled");
+
+        let tab = schema
+            .iter()
+            .find(|info| info.key == "useragent.tab.close_button")
+            .unwrap();
+        assert_eq!(tab.constraint, Constraint::parse("left,right"));
+    }
+
     #[test]
     fn typed_accessors() {
         let cfg = test_config();
@@ -595,10 +1056,10 @@ mod test {
         let cfg = test_config();
 
         cfg.set("dns.remote.retries", Setting::UInt(42)).unwrap();
-        assert_eq!(cfg.get("dns.remote.retries").unwrap().unwrap(), Setting::UInt(42));
+        assert_eq!(cfg.get("dns.remote.retries").unwrap(), Setting::UInt(42));
 
         cfg.remove("dns.remote.retries").unwrap();
-        assert_eq!(cfg.get("dns.remote.retries").unwrap().unwrap(), Setting::UInt(3));
+        assert_eq!(cfg.get("dns.remote.retries").unwrap(), Setting::UInt(3));
     }
 
     #[test]
@@ -614,9 +1075,182 @@ mod test {
     }
 
     #[test]
-    fn unknown_key_returns_none() {
+    fn reload_takes_over_edits_to_the_settings_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        let storage = crate::storage::JsonStorageAdapter::open(&path).unwrap();
+        let cfg = Config::with_storage(test_schema(), Box::new(storage));
+        cfg.set("dns.remote.retries", Setting::UInt(9)).unwrap();
+
+        let (captured, cb) = capturing_callback();
+        cfg.subscribe("*", cb);
+
+        std::fs::write(
+            &path,
+            r#"{ "dns.remote.timeout": "u:10", "dns.local.enabled": "s:not a bool", "unknown.key": "b:true" }"#,
+        )
+        .unwrap();
+        assert_eq!(cfg.reload().unwrap(), 2);
+
+        // The removed override reverts to its default; the value of the wrong type is ignored.
+        captured.lock().sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            *captured.lock(),
+            vec![
+                ("dns.remote.retries".to_string(), Setting::UInt(3)),
+                ("dns.remote.timeout".to_string(), Setting::UInt(10)),
+            ]
+        );
+        assert!(cfg.get_bool("dns.local.enabled"));
+    }
+
+    #[test]
+    fn overrides_are_layered() {
+        let cfg = test_config();
+        cfg.set("dns.remote.retries", Setting::UInt(4)).unwrap();
+        assert_eq!(cfg.layer("dns.remote.retries"), Some(Layer::Stored));
+        assert_eq!(cfg.layer("dns.remote.timeout"), Some(Layer::Default));
+        assert_eq!(cfg.layer("this.key.doesnt.exist"), None);
+
+        let (captured, cb) = capturing_callback();
+        cfg.subscribe("*", cb);
+
+        let env = [
+            ("GOSUB_DNS_REMOTE_RETRIES", "5"),
+            ("GOSUB_DNS_CACHE_MAX_ENTRIES", "10"),
+            ("GOSUB_SOMETHING_ELSE", "1"),
+            ("HOME", "/root"),
+        ];
+        let env = env.map(|(name, value)| (name.to_string(), value.to_string()));
+        assert_eq!(cfg.set_env_overrides(env).unwrap(), 2);
+        assert_eq!(cfg.get_uint("dns.remote.retries"), 5);
+        assert_eq!(cfg.layer("dns.remote.retries"), Some(Layer::Env));
+
+        assert_eq!(cfg.set_cli_overrides(["dns.remote.retries=6"]).unwrap(), 1);
+        assert_eq!(cfg.get_uint("dns.remote.retries"), 6);
+        assert_eq!(cfg.layer("dns.remote.retries"), Some(Layer::Cli));
+
+        // Setting an overridden value stores it for later, but doesn't change what's in effect.
+        cfg.set("dns.remote.retries", Setting::UInt(7)).unwrap();
+        assert_eq!(cfg.get_uint("dns.remote.retries"), 6);
+
+        assert_eq!(cfg.clear_overrides(), 2);
+        assert_eq!(cfg.get_uint("dns.remote.retries"), 7);
+        assert_eq!(cfg.get_uint("dns.cache.max_entries"), 1000);
+        assert_eq!(captured.lock().len(), 5);
+    }
+
+    #[test]
+    fn bad_overrides_change_nothing() {
         let cfg = test_config();
-        assert!(cfg.get("this.key.doesnt.exist").unwrap().is_none());
+        assert!(cfg
+            .set_cli_overrides(["dns.remote.retries=5", "useragent.tab.max_opened=10000"])
+            .is_err());
+        assert!(cfg.set_cli_overrides(["this.key.doesnt.exist=1"]).is_err());
+        assert!(cfg.set_cli_overrides(["dns.remote.retries"]).is_err());
+        assert!(cfg
+            .set_env_overrides([("GOSUB_DNS_LOCAL_ENABLED".to_string(), "maybe".to_string())])
+            .is_err());
+
+        assert_eq!(cfg.get_uint("dns.remote.retries"), 3);
+        assert_eq!(cfg.layer("dns.remote.retries"), Some(Layer::Default));
+    }
+
+    #[test]
+    fn export_and_import() {
+        let cfg = test_config();
+        cfg.set("dns.remote.retries", Setting::UInt(8)).unwrap();
+        cfg.set("useragent.tab.close_button", Setting::Map(vec!["right".into()]))
+            .unwrap();
+        cfg.set_cli_overrides(["dns.remote.timeout=9"]).unwrap();
+        let json = cfg.export_json().unwrap();
+
+        let export: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(export["schema_version"], 1);
+        assert_eq!(export["settings"]["dns.remote.retries"], "u:8");
+        assert!(
+            export["settings"].get("dns.remote.timeout").is_none(),
+            "overrides aren't exported"
+        );
+
+        let other = test_config();
+        assert_eq!(other.import_json(&json).unwrap(), 2);
+        assert_eq!(other.get_uint("dns.remote.retries"), 8);
+        assert_eq!(other.get_map("useragent.tab.close_button"), ["right"]);
+    }
+
+    #[test]
+    fn import_migrates_older_exports() {
+        let cfg = test_config();
+        cfg.set_schema_version(3);
+        cfg.add_migration(1, |settings| {
+            if let Some(value) = settings.remove("dns.retries") {
+                settings.insert("dns.remote.retries".into(), value);
+            }
+        });
+        cfg.add_migration(2, |settings| {
+            if let Some(Setting::UInt(seconds)) = settings.get("dns.remote.timeout").cloned() {
+                settings.insert("dns.remote.timeout".into(), Setting::UInt(seconds.min(60)));
+            }
+        });
+
+        let json = r#"{ "schema_version": 2, "settings": { "dns.retries": "u:4", "dns.remote.timeout": "u:600" } }"#;
+        assert_eq!(cfg.import_json(json).unwrap(), 1);
+        assert_eq!(
+            cfg.get_uint("dns.remote.retries"),
+            3,
+            "the first migration doesn't apply"
+        );
+        assert_eq!(cfg.get_uint("dns.remote.timeout"), 60);
+
+        let json = r#"{ "schema_version": 1, "settings": { "dns.retries": "u:4", "dns.local.enabled": "u:1" } }"#;
+        assert_eq!(cfg.import_json(json).unwrap(), 1);
+        assert_eq!(cfg.get_uint("dns.remote.retries"), 4);
+        assert!(cfg.get_bool("dns.local.enabled"), "a value that doesn't fit is skipped");
+
+        assert!(cfg.import_json(r#"{ "schema_version": 4, "settings": {} }"#).is_err());
+    }
+
+    #[test]
+    fn unknown_key_is_not_registered() {
+        let cfg = test_config();
+        assert!(
+            matches!(cfg.get("this.key.doesnt.exist"), Err(Error::NotRegistered(key)) if key == "this.key.doesnt.exist")
+        );
+        assert!(matches!(
+            cfg.set("this.key.doesnt.exist", Setting::Bool(true)),
+            Err(Error::NotRegistered(_))
+        ));
+    }
+
+    #[test]
+    fn runtime_settings() {
+        let storage = MemoryStorageAdapter::new();
+        storage
+            .set("extension.adblock.lists", Setting::Map(vec!["easylist".into()]))
+            .unwrap();
+        storage.set("extension.adblock.level", Setting::UInt(9)).unwrap();
+        let cfg = Config::with_storage(test_schema(), Box::new(storage));
+
+        cfg.register_runtime_setting(info("extension.adblock.lists", "m:", None))
+            .unwrap();
+        assert_eq!(cfg.get_map("extension.adblock.lists"), ["easylist"]);
+
+        // A stored value that doesn't fit falls back to the default.
+        cfg.register_runtime_setting(info("extension.adblock.level", "u:1", Some("0-3")))
+            .unwrap();
+        assert_eq!(cfg.get_uint("extension.adblock.level"), 1);
+        cfg.set("extension.adblock.level", Setting::UInt(2)).unwrap();
+        assert_eq!(cfg.get_uint("extension.adblock.level"), 2);
+
+        assert!(matches!(
+            cfg.register_runtime_setting(info("dns.remote.retries", "u:1", None)),
+            Err(Error::AlreadyRegistered(_))
+        ));
+        assert!(matches!(
+            cfg.register_runtime_setting(info("extension.other", "u:5", Some("0-3"))),
+            Err(Error::InvalidValue { .. })
+        ));
     }
 
     #[test]
@@ -633,7 +1267,7 @@ mod test {
 
         // Registered under the namespace, carrying the current (overridden) value and constraint.
         assert_eq!(
-            engine.get("user_agent.tabs.close_position").unwrap().unwrap(),
+            engine.get("user_agent.tabs.close_position").unwrap(),
             Setting::Map(vec!["right".into()])
         );
         let info = engine.get_info("user_agent.tabs.close_position").unwrap();
@@ -1,7 +1,8 @@
-use crate::errors::Error;
+use crate::errors::{Error, ValueError};
 use core::fmt::Display;
 use cow_utils::CowUtils;
 use log::warn;
+use regex::Regex;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::str::FromStr;
 
@@ -132,6 +133,21 @@ impl Setting {
         }
     }
 
+    /// Parses `raw`, a bare value without type prefix (e.g. `42` or `left,right`), as a setting of
+    /// the same type as this one. This is how values from outside the wire format, like
+    /// environment variables, are read.
+    pub fn parse_like(&self, raw: &str) -> Result<Setting, Error> {
+        let prefix = match self {
+            Setting::SInt(_) => "i",
+            Setting::UInt(_) => "u",
+            Setting::Float(_) => "f",
+            Setting::String(_) => "s",
+            Setting::Bool(_) => "b",
+            Setting::Map(_) => "m",
+        };
+        Setting::from_str(&format!("{prefix}:{raw}"))
+    }
+
     /// Returns the bare value of this setting as a string, without the type prefix used by
     /// [`Display`] and without emitting a type-mismatch warning (unlike [`Setting::to_string`]).
     #[must_use]
@@ -250,17 +266,70 @@ impl FromStr for Setting {
 pub enum Constraint {
     /// The setting's string form must equal one of these literals (e.g. `left,right`).
     Enum(Vec<String>),
-    /// The setting's numeric (signed integer) value must fall within one of these inclusive
-    /// ranges (e.g. `-1,0-9999` -> `[(-1, -1), (0, 9999)]`).
+    /// The setting's numeric value must fall within one of these inclusive ranges (e.g.
+    /// `-1,0-9999` -> `[(-1, -1), (0, 9999)]`).
     Range(Vec<(isize, isize)>),
+    /// The setting's string form must match this regular expression as a whole (e.g.
+    /// `/[a-z]+/`).
+    Pattern(Pattern),
+}
+
+/// A regular expression a setting has to match, see [`Constraint::Pattern`].
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    source: String,
+    regex: Regex,
+}
+
+impl Pattern {
+    /// Compiles `source`, which then has to match a value as a whole.
+    pub fn new(source: &str) -> Result<Self, Error> {
+        let regex = Regex::new(&format!("^(?:{source})$"))
+            .map_err(|err| Error::Config(format!("invalid pattern /{source}/: {err}")))?;
+        Ok(Self {
+            source: source.to_string(),
+            regex,
+        })
+    }
+
+    /// The expression as written.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns true when `value` matches as a whole.
+    #[must_use]
+    pub fn is_match(&self, value: &str) -> bool {
+        self.regex.is_match(value)
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
 }
 
 impl Constraint {
     /// Parses the `values` field from `settings.json` into a `Constraint`. Returns `None` when the
-    /// field is empty. When every comma-separated token parses as an integer or `lo-hi` range, the
-    /// result is a [`Constraint::Range`]; otherwise it is a [`Constraint::Enum`] of the raw tokens.
+    /// field is empty. A field written as `/regex/` is a [`Constraint::Pattern`] (or `None`, with
+    /// a warning, when it doesn't compile). When every comma-separated token parses as an integer
+    /// or `lo-hi` range, the result is a [`Constraint::Range`]; otherwise it is a
+    /// [`Constraint::Enum`] of the raw tokens.
     #[must_use]
     pub fn parse(values: &str) -> Option<Constraint> {
+        let trimmed = values.trim();
+        if let Some(source) = trimmed.strip_prefix('/').and_then(|rest| rest.strip_suffix('/')) {
+            return match Pattern::new(source) {
+                Ok(pattern) => Some(Constraint::Pattern(pattern)),
+                Err(err) => {
+                    warn!("{err}");
+                    None
+                }
+            };
+        }
+
         let tokens: Vec<&str> = values.split(',').map(str::trim).filter(|s| !s.is_empty()).collect();
         if tokens.is_empty() {
             return None;
@@ -283,6 +352,7 @@ impl Constraint {
                 .iter()
                 .map(|(lo, hi)| if lo == hi { lo.to_string() } else { format!("{lo}-{hi}") })
                 .collect(),
+            Constraint::Pattern(pattern) => vec![format!("/{}/", pattern.as_str())],
         }
     }
 
@@ -301,16 +371,23 @@ impl Constraint {
                 allowed.iter().any(|a| a == &v)
             }
             Constraint::Range(ranges) => {
+                if let Setting::Float(n) = value {
+                    return ranges.iter().any(|(lo, hi)| *n >= *lo as f64 && *n <= *hi as f64);
+                }
                 let n = value.to_sint();
                 ranges.iter().any(|(lo, hi)| n >= *lo && n <= *hi)
             }
+            Constraint::Pattern(pattern) => pattern.is_match(&value.value_string()),
         }
     }
 }
 
 impl Display for Constraint {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "one of: {}", self.tokens().join(", "))
+        match self {
+            Constraint::Pattern(pattern) => write!(f, "a match for /{}/", pattern.as_str()),
+            _ => write!(f, "one of: {}", self.tokens().join(", ")),
+        }
     }
 }
 
@@ -342,6 +419,31 @@ pub struct SettingInfo {
     pub constraint: Option<Constraint>,
 }
 
+impl SettingInfo {
+    /// Checks that `value` has the type of the default and satisfies the constraint, the way
+    /// [`ConfigStore::set`](crate::ConfigStore::set) does. Settings UIs can use this to validate
+    /// input before setting it.
+    pub fn validate(&self, value: &Setting) -> Result<(), ValueError> {
+        if std::mem::discriminant(&self.default) != std::mem::discriminant(value) {
+            return Err(ValueError::WrongType {
+                expected: self.default.type_name(),
+                found: value.type_name(),
+            });
+        }
+
+        if let Some(constraint) = &self.constraint {
+            if !constraint.allows(value) {
+                return Err(ValueError::NotAllowed {
+                    value: value.value_string(),
+                    constraint: constraint.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -436,6 +538,22 @@ mod test {
         assert_eq!(0, s.to_uint());
     }
 
+    #[test]
+    fn parse_like_takes_the_type_of_the_setting() {
+        assert_eq!(Setting::UInt(3).parse_like("42").unwrap(), Setting::UInt(42));
+        assert_eq!(Setting::SInt(0).parse_like("-1").unwrap(), Setting::SInt(-1));
+        assert_eq!(Setting::Bool(true).parse_like("false").unwrap(), Setting::Bool(false));
+        assert_eq!(
+            Setting::Map(vec![]).parse_like("left,right").unwrap(),
+            Setting::Map(vec!["left".into(), "right".into()])
+        );
+        assert_eq!(
+            Setting::String(String::new()).parse_like("a:b").unwrap(),
+            Setting::String("a:b".into())
+        );
+        assert!(Setting::UInt(3).parse_like("-1").is_err());
+    }
+
     #[test]
     fn float_setting() {
         let s = Setting::from_str("f:1.5").unwrap();
@@ -483,6 +601,52 @@ mod test {
         assert!(!c.allows(&Setting::SInt(10000)));
     }
 
+    #[test]
+    fn constraint_pattern() {
+        let c = Constraint::parse("/[a-z]+(-[a-z]+)*/").unwrap();
+        assert!(matches!(&c, Constraint::Pattern(p) if p.as_str() == "[a-z]+(-[a-z]+)*"));
+        assert!(c.allows(&Setting::String("dark-mode".into())));
+        assert!(!c.allows(&Setting::String("dark mode".into())));
+        assert!(
+            !c.allows(&Setting::String("x-dark-mode!".into())),
+            "it matches as a whole"
+        );
+        assert_eq!(c.to_string(), "a match for /[a-z]+(-[a-z]+)*/");
+
+        assert_eq!(Constraint::parse("/(/"), None);
+    }
+
+    #[test]
+    fn constraint_range_with_floats() {
+        let c = Constraint::parse("0-1").unwrap();
+        assert!(c.allows(&Setting::Float(0.5)));
+        assert!(!c.allows(&Setting::Float(1.5)));
+    }
+
+    #[test]
+    fn validate_reports_why() {
+        let info = SettingInfo {
+            key: "tab.close_button".into(),
+            description: String::new(),
+            default: Setting::Map(vec!["left".into()]),
+            constraint: Constraint::parse("left,right"),
+        };
+        assert_eq!(info.validate(&Setting::Map(vec!["right".into()])), Ok(()));
+        assert_eq!(
+            info.validate(&Setting::Bool(true)),
+            Err(ValueError::WrongType {
+                expected: "map",
+                found: "boolean"
+            })
+        );
+        assert_eq!(
+            info.validate(&Setting::Map(vec!["middle".into()]))
+                .unwrap_err()
+                .to_string(),
+            "middle is not allowed, expected one of: left, right"
+        );
+    }
+
     #[test]
     fn constraint_parse_edge_cases() {
         assert_eq!(Constraint::parse(""), None);
@@ -1,9 +1,32 @@
 pub use json::*;
 pub use memory::*;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
 pub use sqlite::*;
 
+use std::env;
+use std::path::PathBuf;
+
 mod json;
 mod memory;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
 mod sqlite;
+
+/// Gosub's directory in the platform's config directory: `%APPDATA%\Gosub` on Windows,
+/// `~/Library/Application Support/Gosub` on macOS and `$XDG_CONFIG_HOME/gosub` (by default
+/// `~/.config/gosub`) elsewhere. `None` when the environment doesn't say where that is.
+#[must_use]
+pub fn default_config_dir() -> Option<PathBuf> {
+    let var = |name: &str| env::var_os(name).map(PathBuf::from).filter(|path| path.is_absolute());
+
+    if cfg!(target_arch = "wasm32") {
+        None
+    } else if cfg!(windows) {
+        var("APPDATA").map(|dir| dir.join("Gosub"))
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| home.join("Library").join("Application Support").join("Gosub"))
+    } else {
+        var("XDG_CONFIG_HOME")
+            .or_else(|| var("HOME").map(|home| home.join(".config")))
+            .map(|dir| dir.join("gosub"))
+    }
+}
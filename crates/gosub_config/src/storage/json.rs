@@ -1,4 +1,5 @@
 use crate::settings::Setting;
+use crate::storage::default_config_dir;
 use crate::{Result, StorageAdapter};
 use log::warn;
 use parking_lot::Mutex;
@@ -7,36 +8,28 @@ use std::collections::HashMap;
 use std::fs;
 use std::fs::File;
 use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The name of the settings file in the config directory
+const SETTINGS_FILE: &str = "settings.json";
 
 /// JSON file-backed storage adapter. All settings are held in memory and written through to the
-/// backing file on every `set` call.
+/// backing file on every `set` call. Writes go to a temporary file that then replaces the
+/// settings file, so a crash never leaves a half-written one behind.
 pub struct JsonStorageAdapter {
-    path: String,
+    path: PathBuf,
     elements: Mutex<HashMap<String, Setting>>,
+    /// Modification time and length of the file as last read or written here, to notice when
+    /// something else changed it
+    stamp: Mutex<Option<(SystemTime, u64)>>,
 }
 
 impl TryFrom<&String> for JsonStorageAdapter {
     type Error = crate::errors::Error;
 
     fn try_from(path: &String) -> Result<Self> {
-        if let Ok(metadata) = fs::metadata(path) {
-            if !metadata.is_file() {
-                return Err(crate::errors::Error::Config(format!("{path} is not a regular file")));
-            }
-            File::options().read(true).write(true).open(path)?;
-        } else {
-            let mut file = File::create(path)?;
-            file.write_all(b"{}")?;
-        }
-
-        let mut adapter = JsonStorageAdapter {
-            path: path.to_string(),
-            elements: Mutex::new(HashMap::new()),
-        };
-
-        adapter.read_file()?;
-
-        Ok(adapter)
+        Self::open(path)
     }
 }
 
@@ -62,11 +55,73 @@ impl StorageAdapter for JsonStorageAdapter {
         let lock = self.elements.lock();
         Ok(lock.clone())
     }
+
+    fn reload(&self) -> Result<Vec<String>> {
+        if file_stamp(&self.path) == *self.stamp.lock() {
+            return Ok(Vec::new());
+        }
+
+        let old = self.elements.lock().clone();
+        self.read_file()?;
+
+        let new = self.elements.lock();
+        let mut changed: Vec<String> = new
+            .iter()
+            .filter(|(key, value)| old.get(*key) != Some(*value))
+            .map(|(key, _)| key.clone())
+            .collect();
+        changed.extend(old.keys().filter(|key| !new.contains_key(*key)).cloned());
+        Ok(changed)
+    }
 }
 
 impl JsonStorageAdapter {
-    fn read_file(&mut self) -> Result<()> {
+    /// Opens the settings file at `path`, creating it when it doesn't exist yet.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Ok(metadata) = fs::metadata(path) {
+            if !metadata.is_file() {
+                return Err(crate::errors::Error::Config(format!(
+                    "{} is not a regular file",
+                    path.display()
+                )));
+            }
+            File::options().read(true).write(true).open(path)?;
+        } else {
+            let mut file = File::create(path)?;
+            file.write_all(b"{}")?;
+        }
+
+        let adapter = JsonStorageAdapter {
+            path: path.to_path_buf(),
+            elements: Mutex::new(HashMap::new()),
+            stamp: Mutex::new(None),
+        };
+
+        adapter.read_file()?;
+
+        Ok(adapter)
+    }
+
+    /// Opens `settings.json` in the platform's config directory (see [`default_config_dir`]),
+    /// creating the directory and the file when needed. This is where user settings live unless
+    /// the embedder says otherwise.
+    pub fn open_default() -> Result<Self> {
+        let dir = default_config_dir()
+            .ok_or_else(|| crate::errors::Error::Config("there is no config directory on this platform".into()))?;
+        fs::create_dir_all(&dir)?;
+        Self::open(dir.join(SETTINGS_FILE))
+    }
+
+    /// The settings file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read_file(&self) -> Result<()> {
         let mut file = File::open(&self.path)?;
+        let stamp = file_stamp(&self.path);
 
         let mut buf = String::new();
         file.read_to_string(&mut buf)?;
@@ -88,13 +143,68 @@ impl JsonStorageAdapter {
             }
         }
 
+        *self.stamp.lock() = stamp;
         Ok(())
     }
 
     fn write_file(&self) -> Result<()> {
-        let mut file = File::options().write(true).truncate(true).open(&self.path)?;
         let json = serde_json::to_string_pretty(&*self.elements.lock())?;
+
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        let temp = PathBuf::from(temp);
+
+        let mut file = File::create(&temp)?;
         file.write_all(json.as_bytes())?;
+        file.sync_all()?;
+        drop(file);
+        fs::rename(&temp, &self.path)?;
+
+        *self.stamp.lock() = file_stamp(&self.path);
         Ok(())
     }
 }
+
+/// What tells one version of the file at `path` from another
+fn file_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_survive_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+
+        let adapter = JsonStorageAdapter::open(&path).unwrap();
+        adapter.set("dns.remote.retries", Setting::UInt(7)).unwrap();
+        assert!(!dir.path().join("settings.json.tmp").exists());
+        drop(adapter);
+
+        let adapter = JsonStorageAdapter::open(&path).unwrap();
+        assert_eq!(adapter.get("dns.remote.retries").unwrap(), Some(Setting::UInt(7)));
+    }
+
+    #[test]
+    fn reload_picks_up_changes_to_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+
+        let adapter = JsonStorageAdapter::open(&path).unwrap();
+        adapter.set("a", Setting::UInt(1)).unwrap();
+        adapter.set("b", Setting::Bool(true)).unwrap();
+        assert!(adapter.reload().unwrap().is_empty(), "its own writes aren't changes");
+
+        fs::write(&path, r#"{ "a": "u:2", "c": "s:new value" }"#).unwrap();
+
+        let mut changed = adapter.reload().unwrap();
+        changed.sort();
+        assert_eq!(changed, ["a", "b", "c"]);
+        assert_eq!(adapter.get("a").unwrap(), Some(Setting::UInt(2)));
+        assert_eq!(adapter.get("b").unwrap(), None);
+    }
+}
@@ -6,36 +6,85 @@ use rusqlite::{named_params, Connection};
 use std::collections::HashMap;
 use std::str::FromStr;
 
+/// The table settings go in, unless the embedder picks another
+const DEFAULT_TABLE: &str = "settings";
+
 /// SQLite-backed storage adapter. Each `get` and `set` call hits the database directly,
 /// so settings are persisted immediately without a separate flush step.
+///
+/// Embedders that keep a profile database of their own can hand over its connection with
+/// [`SqliteStorageAdapter::from_connection`], so settings live next to the rest of the profile.
 pub struct SqliteStorageAdapter {
     connection: Mutex<Connection>,
+    table: String,
 }
 
 impl TryFrom<&String> for SqliteStorageAdapter {
     type Error = Error;
 
     fn try_from(path: &String) -> Result<Self> {
-        let conn = Connection::open(path)?;
+        Self::from_connection(Connection::open(path)?, DEFAULT_TABLE)
+    }
+}
 
-        let query = "CREATE TABLE IF NOT EXISTS settings (
+impl SqliteStorageAdapter {
+    /// Keeps the settings in `table` of the database behind `connection`, creating the table
+    /// when it doesn't exist yet. `table` must be a plain identifier.
+    pub fn from_connection(connection: Connection, table: &str) -> Result<Self> {
+        let mut chars = table.chars();
+        let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(Error::Config(format!("{table:?} is not a valid table name")));
+        }
+
+        let query = format!(
+            "CREATE TABLE IF NOT EXISTS {table} (
             id INTEGER PRIMARY KEY,
             key TEXT NOT NULL UNIQUE,
             value TEXT NOT NULL
-        )";
-        conn.execute(query, [])?;
+        )"
+        );
+        connection.execute(&query, [])?;
 
         Ok(SqliteStorageAdapter {
-            connection: Mutex::new(conn),
+            connection: Mutex::new(connection),
+            table: table.to_string(),
         })
     }
+
+    /// Copies every setting stored in `from` into this database in one transaction, replacing
+    /// the values stored here for the same keys. This is how settings kept with the memory or
+    /// JSON adapters move into a profile database. Returns the number of settings copied.
+    pub fn import(&self, from: &dyn StorageAdapter) -> Result<usize> {
+        let settings = from.all()?;
+
+        let mut db_lock = self.connection.lock();
+        let transaction = db_lock.transaction()?;
+        {
+            let query = format!(
+                "INSERT OR REPLACE INTO {} (key, value) VALUES (:key, :value)",
+                self.table
+            );
+            let mut statement = transaction.prepare(&query)?;
+            for (key, value) in &settings {
+                statement.execute(named_params! {
+                    ":key": key,
+                    ":value": format!("{value}"),
+                })?;
+            }
+        }
+        transaction.commit()?;
+
+        Ok(settings.len())
+    }
 }
 
 impl StorageAdapter for SqliteStorageAdapter {
     fn get(&self, key: &str) -> Result<Option<Setting>> {
         let db_lock = self.connection.lock();
-        let query = "SELECT value FROM settings WHERE key = :key";
-        let mut statement = db_lock.prepare(query)?;
+        let query = format!("SELECT value FROM {} WHERE key = :key", self.table);
+        let mut statement = db_lock.prepare(&query)?;
 
         match statement.query_row(named_params! { ":key": key }, |row| row.get::<_, String>(0)) {
             Ok(val) => Setting::from_str(&val).map(Some),
@@ -46,8 +95,11 @@ impl StorageAdapter for SqliteStorageAdapter {
 
     fn set(&self, key: &str, value: Setting) -> Result<()> {
         let db_lock = self.connection.lock();
-        let query = "INSERT OR REPLACE INTO settings (key, value) VALUES (:key, :value)";
-        let mut statement = db_lock.prepare(query)?;
+        let query = format!(
+            "INSERT OR REPLACE INTO {} (key, value) VALUES (:key, :value)",
+            self.table
+        );
+        let mut statement = db_lock.prepare(&query)?;
         statement.execute(named_params! {
             ":key": key,
             ":value": format!("{value}"),
@@ -57,8 +109,8 @@ impl StorageAdapter for SqliteStorageAdapter {
 
     fn remove(&self, key: &str) -> Result<()> {
         let db_lock = self.connection.lock();
-        let query = "DELETE FROM settings WHERE key = :key";
-        let mut statement = db_lock.prepare(query)?;
+        let query = format!("DELETE FROM {} WHERE key = :key", self.table);
+        let mut statement = db_lock.prepare(&query)?;
         statement.execute(named_params! { ":key": key })?;
         Ok(())
     }
@@ -67,8 +119,8 @@ impl StorageAdapter for SqliteStorageAdapter {
         let mut settings = HashMap::new();
 
         let db_lock = self.connection.lock();
-        let query = "SELECT id,key,value FROM settings";
-        let mut statement = db_lock.prepare(query)?;
+        let query = format!("SELECT id,key,value FROM {}", self.table);
+        let mut statement = db_lock.prepare(&query)?;
 
         let mut rows = statement.query([])?;
         while let Some(row) = rows.next()? {
@@ -80,3 +132,43 @@ impl StorageAdapter for SqliteStorageAdapter {
         Ok(settings)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorageAdapter;
+
+    #[test]
+    fn settings_share_a_profile_database() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute("CREATE TABLE bookmarks (id INTEGER PRIMARY KEY, url TEXT)", [])
+            .unwrap();
+
+        let adapter = SqliteStorageAdapter::from_connection(connection, "gosub_settings").unwrap();
+        adapter.set("dns.remote.retries", Setting::UInt(7)).unwrap();
+        assert_eq!(adapter.get("dns.remote.retries").unwrap(), Some(Setting::UInt(7)));
+
+        assert!(SqliteStorageAdapter::from_connection(Connection::open_in_memory().unwrap(), "x; DROP").is_err());
+    }
+
+    #[test]
+    fn import_copies_another_adapter() {
+        let memory = MemoryStorageAdapter::new();
+        memory.set("a", Setting::Bool(true)).unwrap();
+        memory
+            .set("b", Setting::Map(vec!["left".into(), "right".into()]))
+            .unwrap();
+
+        let adapter = SqliteStorageAdapter::from_connection(Connection::open_in_memory().unwrap(), "settings").unwrap();
+        adapter.set("a", Setting::Bool(false)).unwrap();
+        adapter.set("c", Setting::UInt(1)).unwrap();
+
+        assert_eq!(adapter.import(&memory).unwrap(), 2);
+
+        let all = adapter.all().unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all["a"], Setting::Bool(true));
+        assert_eq!(all["b"], Setting::Map(vec!["left".into(), "right".into()]));
+    }
+}
@@ -65,6 +65,7 @@ fn collect_rule(node: &CssNode) -> CssResult<Option<CssRule>> {
     let mut rule = CssRule {
         selectors: vec![],
         declarations: vec![],
+        media: vec![],
     };
 
     let Some((prelude, declarations)) = node.as_rule() else {
@@ -201,11 +202,20 @@ fn collect_rule(node: &CssNode) -> CssResult<Option<CssRule>> {
     Ok(Some(rule))
 }
 
-fn collect_rules(nodes: &[CssNode], rules: &mut Vec<CssRule>, font_faces: &mut Vec<FontFace>) -> CssResult<()> {
+/// Collects the style rules and font faces of `nodes`. Rules nested in `@media` blocks keep the
+/// media query lists of those blocks (`media` holds the enclosing ones), so whether they apply
+/// can be decided at style time, against the viewport of that moment.
+fn collect_rules(
+    nodes: &[CssNode],
+    media: &[CssNode],
+    rules: &mut Vec<CssRule>,
+    font_faces: &mut Vec<FontFace>,
+) -> CssResult<()> {
     for node in nodes {
         match &*node.node_type {
             NodeType::Rule { .. } => {
-                if let Some(rule) = collect_rule(node)? {
+                if let Some(mut rule) = collect_rule(node)? {
+                    rule.media = media.to_vec();
                     rules.push(rule);
                 }
             }
@@ -215,7 +225,19 @@ fn collect_rules(nodes: &[CssNode], rules: &mut Vec<CssRule>, font_faces: &mut V
                 ..
             } if name.eq_ignore_ascii_case("layer") => {
                 if let Some(children) = block.as_block() {
-                    collect_rules(children, rules, font_faces)?;
+                    collect_rules(children, media, rules, font_faces)?;
+                }
+            }
+            NodeType::AtRule {
+                name,
+                prelude,
+                block: Some(block),
+            } if name.eq_ignore_ascii_case("media") => {
+                if let Some(children) = block.as_block() {
+                    let mut media = media.to_vec();
+                    // `@media {}` without a query list applies everywhere.
+                    media.extend(prelude.iter().cloned());
+                    collect_rules(children, &media, rules, font_faces)?;
                 }
             }
             NodeType::AtRule {
@@ -241,6 +263,7 @@ fn collect_font_face(nodes: &[CssNode]) -> Option<FontFace> {
     let mut family: Option<String> = None;
     let mut sources: Vec<String> = Vec::new();
     let mut unicode_range: Option<String> = None;
+    let mut display: Option<String> = None;
 
     for decl in nodes {
         let Some((property, value_nodes, _important)) = decl.as_declaration() else {
@@ -286,6 +309,15 @@ fn collect_font_face(nodes: &[CssNode]) -> Option<FontFace> {
                     unicode_range = Some(raw);
                 }
             }
+            "font-display" => {
+                display = value_nodes
+                    .iter()
+                    .filter_map(|n| CssValue::parse_ast_node(n).ok())
+                    .find_map(|v| match v {
+                        CssValue::String(s) => Some(s.cow_to_ascii_lowercase().into_owned()),
+                        _ => None,
+                    });
+            }
             _ => {}
         }
     }
@@ -298,6 +330,7 @@ fn collect_font_face(nodes: &[CssNode]) -> Option<FontFace> {
         family,
         sources,
         unicode_range,
+        display,
     })
 }
 
@@ -337,7 +370,7 @@ pub fn convert_ast_to_stylesheet(css_ast: &CssNode, origin: CssOrigin, url: &str
         parse_log: vec![],
     };
 
-    collect_rules(children, &mut sheet.rules, &mut sheet.font_faces)?;
+    collect_rules(children, &[], &mut sheet.rules, &mut sheet.font_faces)?;
     Ok(sheet)
 }
 
@@ -357,6 +390,7 @@ mod tests {
               font-weight: 600;
               src: url(https://example.com/ss.ttf) format('truetype');
               unicode-range: U+0000-00FF, U+0131, U+0152-0153;
+              font-display: Swap;
             }
             h1 { color: red; }
             "#,
@@ -372,6 +406,51 @@ mod tests {
         assert_eq!(face.family, "Source Serif 4");
         assert_eq!(face.sources, vec!["https://example.com/ss.ttf".to_string()]);
         assert!(face.unicode_range.as_deref().unwrap_or("").contains("U+0000"));
+        assert_eq!(face.display.as_deref(), Some("swap"));
+    }
+
+    #[test]
+    fn media_rules_keep_their_queries() {
+        use crate::media::{ColorScheme, MediaEnvironment};
+
+        let stylesheet = Css3::parse_str(
+            r#"
+            h1 { color: red; }
+            @media (min-width: 600px) {
+                h1 { color: blue; }
+                @media (prefers-color-scheme: dark) {
+                    h1 { color: white; }
+                }
+            }
+            @media print { h1 { color: black; } }
+            "#,
+            ParserConfig::default(),
+            CssOrigin::Author,
+            "test.css",
+        )
+        .unwrap();
+        assert_eq!(stylesheet.rules.len(), 4);
+
+        let desktop = MediaEnvironment {
+            width: 1024.0,
+            height: 768.0,
+            ..MediaEnvironment::default()
+        };
+        let applying = |env: &MediaEnvironment| {
+            stylesheet
+                .rules
+                .iter()
+                .map(|rule| rule.applies_in(env))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(applying(&desktop), [true, true, false, false]);
+        let dark = MediaEnvironment {
+            color_scheme: ColorScheme::Dark,
+            ..desktop.clone()
+        };
+        assert_eq!(applying(&dark), [true, true, true, false]);
+        let narrow = MediaEnvironment { width: 400.0, ..dark };
+        assert_eq!(applying(&narrow), [true, false, false, false]);
     }
 
     #[test]
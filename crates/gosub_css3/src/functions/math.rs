@@ -1,52 +1,479 @@
+//! `calc()` and the math comparison functions `min()`, `max()` and `clamp()`.
+//!
+//! Expressions are evaluated during style computation, so what the render pipeline usually sees
+//! is a plain length, percentage or number. A `calc()` body reaches us as its raw text and the
+//! arguments of `min()`/`max()`/`clamp()` as parsed values; both are serialized to expression
+//! text and evaluated the same way, with `var()` references looked up in the custom properties.
+//!
+//! Lengths are kept as `px + em + %`. Absolute, `rem` and viewport units resolve to px via
+//! [`CssValue::unit_to_px`]; font-relative units stay in em so the pipeline can resolve them
+//! against the element's font size. A result that still mixes a percentage with a length, or
+//! that compares lengths only layout can tell apart (`min(10px, 2em)`), is kept as a
+//! [`CalcLength`] and serialized back to a `calc()` without its `var()`s; the pipeline picks it
+//! up through [`calc_length`]. Type errors (`1px * 2px`, `1px + 2`) leave the function
+//! unresolved.
+
 use crate::stylesheet::CssValue;
+use cow_utils::CowUtils;
+use gosub_interface::css3::CalcLength;
+use std::collections::HashMap;
 
-/// Resolve the math comparison functions `clamp()`, `min()` and `max()` over their
-/// length/number arguments.
-///
-/// Each operand is reduced to pixels via [`CssValue::unit_to_px`] (which handles px, em,
-/// rem, the absolute physical units and the viewport units), so mixed-unit expressions
-/// like `clamp(2.3rem, 5.5vw, 3.6rem)` collapse to a single pixel value. The result is
-/// returned as a `Unit(_, "px")`.
+/// How deep `var()` references and parentheses may nest before we give up.
+const MAX_DEPTH: usize = 32;
+
+/// Resolve `calc()`, `min()`, `max()` or `clamp()` (also `-webkit-calc()`) with arguments
+/// `values` to a single value: `Unit(_, "px")`, `Unit(_, "em")`, `Percentage`, `Number`, or a
+/// `calc()` holding a [`CalcLength`] when the value needs the element's font size or a
+/// percentage basis, e.g. `calc(100% - 20px)`.
 ///
-/// Returns `None` (leaving the function unresolved) when any operand is not a plain
-/// length we can compare - e.g. a percentage (no containing block here) or a nested
-/// function - so callers can fall back to the original token.
-pub fn resolve_math(func: &str, values: &[CssValue]) -> Option<CssValue> {
-    // Drop the comma separators that the parser keeps between arguments.
-    let operands: Option<Vec<f32>> = values
-        .iter()
-        .filter(|v| !matches!(v, CssValue::Comma))
-        .map(operand_px)
-        .collect();
-    let operands = operands?;
-    if operands.is_empty() {
+/// Returns `None` (leaving the function unresolved) when the expression is invalid, so callers
+/// can fall back to the original token.
+pub fn resolve_math(func: &str, values: &[CssValue], custom_props: &HashMap<String, CssValue>) -> Option<CssValue> {
+    evaluate(func, values, custom_props)?.into_css()
+}
+
+/// The [`CalcLength`] of a math function [`resolve_math`] couldn't reduce to a single value.
+pub fn calc_length(func: &str, values: &[CssValue]) -> Option<CalcLength> {
+    match evaluate(func, values, &HashMap::new())? {
+        Calc::Length(length) if finite(&length) => Some(length),
+        _ => None,
+    }
+}
+
+fn evaluate(func: &str, values: &[CssValue], custom_props: &HashMap<String, CssValue>) -> Option<Calc> {
+    let func = func.strip_prefix("-webkit-").unwrap_or(func);
+    let args = values.iter().map(calc_text).collect::<Vec<_>>().join(" ");
+    let text = if func == "calc" {
+        args
+    } else {
+        format!("{func}({args})")
+    };
+
+    let mut parser = Parser {
+        tokens: tokenize(&text)?,
+        pos: 0,
+        custom_props,
+        depth: 0,
+    };
+    let value = parser.sum()?;
+    if parser.pos != parser.tokens.len() {
         return None;
     }
+    Some(value)
+}
+
+/// A math expression's value: a number or a length.
+#[derive(Clone, Debug, PartialEq)]
+enum Calc {
+    Number(f32),
+    Length(CalcLength),
+}
+
+impl Calc {
+    fn length(px: f32, em: f32, percent: f32) -> Self {
+        Calc::Length(CalcLength::Linear { px, em, percent })
+    }
 
-    let px = match func {
-        "min" => operands.iter().copied().fold(f32::INFINITY, f32::min),
-        "max" => operands.iter().copied().fold(f32::NEG_INFINITY, f32::max),
-        // clamp(MIN, VAL, MAX) == max(MIN, min(VAL, MAX)).
-        "clamp" => {
-            let [min, val, max] = operands[..] else {
-                return None;
-            };
-            val.min(max).max(min)
+    fn add(self, other: Calc, sign: f32) -> Option<Calc> {
+        match (self, other) {
+            (Calc::Number(a), Calc::Number(b)) => Some(Calc::Number(a + sign * b)),
+            (Calc::Length(a), Calc::Length(b)) => {
+                let b = if sign < 0.0 { scale(b, -1.0) } else { b };
+                Some(Calc::Length(match (a, b) {
+                    (
+                        CalcLength::Linear { px, em, percent },
+                        CalcLength::Linear {
+                            px: px2,
+                            em: em2,
+                            percent: percent2,
+                        },
+                    ) => CalcLength::Linear {
+                        px: px + px2,
+                        em: em + em2,
+                        percent: percent + percent2,
+                    },
+                    (CalcLength::Sum(mut terms), other) => {
+                        terms.push(other);
+                        CalcLength::Sum(terms)
+                    }
+                    (a, b) => CalcLength::Sum(vec![a, b]),
+                }))
+            }
+            _ => None,
         }
-        _ => return None,
+    }
+
+    fn scale(self, factor: f32) -> Calc {
+        match self {
+            Calc::Number(n) => Calc::Number(n * factor),
+            Calc::Length(length) => Calc::Length(scale(length, factor)),
+        }
+    }
+
+    fn into_css(self) -> Option<CssValue> {
+        match self {
+            Calc::Number(n) => n.is_finite().then_some(CssValue::Number(n)),
+            Calc::Length(length) if !finite(&length) => None,
+            Calc::Length(CalcLength::Linear { px, em, percent }) if px == 0.0 && em == 0.0 && percent != 0.0 => {
+                Some(CssValue::Percentage(percent))
+            }
+            Calc::Length(CalcLength::Linear { px, em, percent }) if px == 0.0 && percent == 0.0 && em != 0.0 => {
+                Some(CssValue::Unit(em, "em".to_string()))
+            }
+            Calc::Length(CalcLength::Linear { px, em, percent }) if em == 0.0 && percent == 0.0 => {
+                Some(CssValue::Unit(px, "px".to_string()))
+            }
+            Calc::Length(length) => Some(CssValue::Function(
+                "calc".to_string(),
+                vec![CssValue::String(length.to_string())],
+            )),
+        }
+    }
+}
+
+fn scale(length: CalcLength, factor: f32) -> CalcLength {
+    match length {
+        CalcLength::Linear { px, em, percent } => CalcLength::Linear {
+            px: px * factor,
+            em: em * factor,
+            percent: percent * factor,
+        },
+        CalcLength::Sum(terms) => CalcLength::Sum(terms.into_iter().map(|t| scale(t, factor)).collect()),
+        CalcLength::Scaled(term, f) => CalcLength::Scaled(term, f * factor),
+        other => CalcLength::Scaled(Box::new(other), factor),
+    }
+}
+
+fn finite(length: &CalcLength) -> bool {
+    match length {
+        CalcLength::Linear { px, em, percent } => px.is_finite() && em.is_finite() && percent.is_finite(),
+        CalcLength::Scaled(term, factor) => factor.is_finite() && finite(term),
+        CalcLength::Sum(args) | CalcLength::Min(args) | CalcLength::Max(args) => args.iter().all(finite),
+    }
+}
+
+/// The value `min()`/`max()` can compare `length` by without knowing the font size or the
+/// percentage basis: its px, em or percentage part, when it is the only part `kind` allows.
+fn comparable(length: &CalcLength, kind: usize) -> Option<f32> {
+    let CalcLength::Linear { px, em, percent } = *length else {
+        return None;
     };
+    match kind {
+        0 => (em == 0.0 && percent == 0.0).then_some(px),
+        1 => (px == 0.0 && percent == 0.0).then_some(em),
+        _ => (px == 0.0 && em == 0.0).then_some(percent),
+    }
+}
 
-    Some(CssValue::Unit(px, "px".to_string()))
+/// `min(args)` when `largest` is false, else `max(args)`. Picks the argument right away when
+/// they all compare by the same part, else leaves the comparison to layout.
+fn pick(args: Vec<Calc>, largest: bool) -> Option<Calc> {
+    let better = |a: f32, b: f32| if largest { b > a } else { b < a };
+    if args.iter().all(|a| matches!(a, Calc::Number(_))) {
+        let key = |a: &Calc| if let Calc::Number(n) = a { *n } else { 0.0 };
+        return args
+            .into_iter()
+            .reduce(|a, b| if better(key(&a), key(&b)) { b } else { a });
+    }
+    let lengths = args
+        .into_iter()
+        .map(|a| match a {
+            Calc::Length(length) => Some(length),
+            Calc::Number(_) => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    for kind in 0..3 {
+        let Some(keys) = lengths.iter().map(|l| comparable(l, kind)).collect::<Option<Vec<_>>>() else {
+            continue;
+        };
+        let index = (0..keys.len()).reduce(|a, b| if better(keys[a], keys[b]) { b } else { a })?;
+        return lengths.into_iter().nth(index).map(Calc::Length);
+    }
+    Some(Calc::Length(if largest {
+        CalcLength::Max(lengths)
+    } else {
+        CalcLength::Min(lengths)
+    }))
 }
 
-/// Reduce a single math operand to pixels, or `None` if it is not a comparable length.
-fn operand_px(value: &CssValue) -> Option<f32> {
+/// Serializes a value back to expression text. Lists are space-joined (their `Display` is a
+/// debug form), everything else uses `Display`.
+fn calc_text(value: &CssValue) -> String {
     match value {
-        CssValue::Unit(..) => Some(value.unit_to_px()),
-        CssValue::Number(n) => Some(*n),
-        CssValue::Zero => Some(0.0),
+        CssValue::List(list) => list.iter().map(calc_text).collect::<Vec<_>>().join(" "),
+        CssValue::Function(name, args) => {
+            let args = args.iter().map(calc_text).collect::<Vec<_>>().join(" ");
+            format!("{name}({args})")
+        }
+        _ => value.to_string(),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f32),
+    Dimension(f32, String),
+    Percentage(f32),
+    Ident(String),
+    /// An identifier directly followed by `(`
+    Function(String),
+    Op(char),
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokenize(text: &str) -> Option<Vec<Token>> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        // A sign starts a number only where an operand is expected: `1px -2px` subtracts.
+        let after_operand = matches!(
+            tokens.last(),
+            Some(Token::Number(_) | Token::Dimension(..) | Token::Percentage(_) | Token::Ident(_) | Token::Close)
+        );
+        let starts_number = c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit))
+            || (matches!(c, '+' | '-')
+                && !after_operand
+                && chars.get(i + 1).is_some_and(|n| {
+                    n.is_ascii_digit() || (*n == '.' && chars.get(i + 2).is_some_and(char::is_ascii_digit))
+                }));
+
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '-' && after_operand {
+            tokens.push(Token::Op('-'));
+            i += 1;
+        } else if starts_number {
+            let start = i;
+            i += 1;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            // An exponent, but not the `e` of a unit like `em`.
+            if i < chars.len()
+                && matches!(chars[i], 'e' | 'E')
+                && chars.get(i + 1).is_some_and(|n| {
+                    n.is_ascii_digit() || (matches!(n, '+' | '-') && chars.get(i + 2).is_some_and(char::is_ascii_digit))
+                })
+            {
+                i += 2;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            let number = chars[start..i].iter().collect::<String>().parse::<f32>().ok()?;
+            if chars.get(i) == Some(&'%') {
+                i += 1;
+                tokens.push(Token::Percentage(number));
+            } else if chars.get(i).is_some_and(|c| c.is_ascii_alphabetic()) {
+                let start = i;
+                while i < chars.len() && chars[i].is_ascii_alphabetic() {
+                    i += 1;
+                }
+                let unit = chars[start..i].iter().collect::<String>();
+                tokens.push(Token::Dimension(number, unit.cow_to_ascii_lowercase().into_owned()));
+            } else {
+                tokens.push(Token::Number(number));
+            }
+        } else if c.is_alphabetic() || c == '-' || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '-' || chars[i] == '_') {
+                i += 1;
+            }
+            let name = chars[start..i].iter().collect::<String>();
+            if name == "-" {
+                tokens.push(Token::Op('-'));
+            } else if chars.get(i) == Some(&'(') {
+                i += 1;
+                tokens.push(Token::Function(name.cow_to_ascii_lowercase().into_owned()));
+            } else {
+                tokens.push(Token::Ident(name));
+            }
+        } else {
+            tokens.push(match c {
+                '+' | '*' | '/' => Token::Op(c),
+                '(' => Token::Open,
+                ')' => Token::Close,
+                ',' => Token::Comma,
+                _ => return None,
+            });
+            i += 1;
+        }
+    }
+    Some(tokens)
+}
+
+/// Recursive-descent evaluator over [`Token`]s.
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    custom_props: &'a HashMap<String, CssValue>,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, token: Token) -> Option<()> {
+        (self.next()? == token).then_some(())
+    }
+
+    /// `product (('+' | '-') product)*`
+    fn sum(&mut self) -> Option<Calc> {
+        let mut value = self.product()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.pos += 1;
+            let rhs = self.product()?;
+            value = value.add(rhs, if op == '+' { 1.0 } else { -1.0 })?;
+        }
+        Some(value)
+    }
+
+    /// `operand (('*' | '/') operand)*`
+    fn product(&mut self) -> Option<Calc> {
+        let mut value = self.operand()?;
+        while let Some(Token::Op(op @ ('*' | '/'))) = self.peek().cloned() {
+            self.pos += 1;
+            let rhs = self.operand()?;
+            value = match (op, value, rhs) {
+                ('*', Calc::Number(n), other) | ('*', other, Calc::Number(n)) => other.scale(n),
+                ('/', other, Calc::Number(n)) if n != 0.0 => other.scale(1.0 / n),
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    fn operand(&mut self) -> Option<Calc> {
+        match self.next()? {
+            Token::Number(n) => Some(Calc::Number(n)),
+            Token::Percentage(p) => Some(Calc::length(0.0, 0.0, p)),
+            Token::Dimension(n, unit) => length(n, &unit),
+            Token::Ident(name) => match name.cow_to_ascii_lowercase().as_ref() {
+                "pi" => Some(Calc::Number(std::f32::consts::PI)),
+                "e" => Some(Calc::Number(std::f32::consts::E)),
+                _ => None,
+            },
+            Token::Open => self.nested(|p| p.sum()),
+            Token::Function(name) => match name.strip_prefix("-webkit-").unwrap_or(&name) {
+                "calc" => self.nested(|p| p.sum()),
+                "min" | "max" | "clamp" => self.nested(|p| p.comparison(&name)),
+                "var" => self.var(),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Evaluates `inner` one level deeper, then consumes the closing `)`.
+    fn nested(&mut self, inner: impl FnOnce(&mut Self) -> Option<Calc>) -> Option<Calc> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return None;
+        }
+        let value = inner(self)?;
+        self.expect(Token::Close)?;
+        self.depth -= 1;
+        Some(value)
+    }
+
+    fn comparison(&mut self, func: &str) -> Option<Calc> {
+        let mut args = vec![self.sum()?];
+        while self.peek() == Some(&Token::Comma) {
+            self.pos += 1;
+            args.push(self.sum()?);
+        }
+
+        match func.strip_prefix("-webkit-").unwrap_or(func) {
+            "min" => pick(args, false),
+            "max" => pick(args, true),
+            // clamp(MIN, VAL, MAX) == max(MIN, min(VAL, MAX)).
+            "clamp" if args.len() == 3 => {
+                let mut args = args.into_iter();
+                let (min, val, max) = (args.next()?, args.next()?, args.next()?);
+                pick(vec![min, pick(vec![val, max], false)?], true)
+            }
+            _ => None,
+        }
+    }
+
+    /// `var(--name)` or `var(--name, fallback)`: the custom property's value, else the
+    /// fallback, evaluated as an operand.
+    fn var(&mut self) -> Option<Calc> {
+        let Some(Token::Ident(name)) = self.next() else {
+            return None;
+        };
+        let fallback_start = match self.next()? {
+            Token::Close => None,
+            Token::Comma => Some(self.pos),
+            _ => return None,
+        };
+        // Skip the fallback, which is only evaluated when the property is missing.
+        let mut open = usize::from(fallback_start.is_some());
+        while open > 0 {
+            match self.next()? {
+                Token::Open | Token::Function(_) => open += 1,
+                Token::Close => open -= 1,
+                _ => {}
+            }
+        }
+        let tokens = match (self.custom_props.get(&name), fallback_start) {
+            (Some(value), _) => tokenize(&calc_text(value))?,
+            (None, Some(start)) => self.tokens[start..self.pos - 1].to_vec(),
+            (None, None) => return None,
+        };
+
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return None;
+        }
+        let mut inner = Parser {
+            tokens,
+            pos: 0,
+            custom_props: self.custom_props,
+            depth: self.depth,
+        };
+        let value = inner.sum()?;
+        self.depth -= 1;
+        (inner.pos == inner.tokens.len()).then_some(value)
+    }
+}
+
+/// A dimension as a [`Calc`] length, `None` for units that aren't lengths.
+fn length(n: f32, unit: &str) -> Option<Calc> {
+    // Font-relative units as em, with the same stand-in factors as the render pipeline.
+    let em = match unit {
+        "em" => Some(1.0),
+        "ch" => Some(0.55),
+        "ex" => Some(0.5),
+        "ic" => Some(1.0),
+        "lh" => Some(1.4),
         _ => None,
+    };
+    if let Some(factor) = em {
+        return Some(Calc::length(0.0, n * factor, 0.0));
+    }
+    const ABSOLUTE: [&str; 18] = [
+        "px", "rem", "pt", "pc", "in", "cm", "mm", "q", "vw", "vh", "vmin", "vmax", "svw", "lvw", "dvw", "svh", "lvh",
+        "dvh",
+    ];
+    if !ABSOLUTE.contains(&unit) {
+        return None;
     }
+    Some(Calc::length(CssValue::Unit(n, unit.to_string()).unit_to_px(), 0.0, 0.0))
 }
 
 #[cfg(test)]
@@ -57,6 +484,10 @@ mod tests {
         CssValue::Unit(v, u.to_string())
     }
 
+    fn calc(body: &str) -> Option<CssValue> {
+        resolve_math("calc", &[CssValue::String(body.to_string())], &HashMap::new())
+    }
+
     #[test]
     fn clamp_picks_the_middle_when_in_range() {
         // clamp(2.3rem=36.8, 5.5vw=70.4, 3.6rem=57.6) -> max(36.8, min(70.4, 57.6)) = 57.6
@@ -67,7 +498,7 @@ mod tests {
             CssValue::Comma,
             unit(3.6, "rem"),
         ];
-        assert_eq!(resolve_math("clamp", &args), Some(unit(57.6, "px")));
+        assert_eq!(resolve_math("clamp", &args, &HashMap::new()), Some(unit(57.6, "px")));
     }
 
     #[test]
@@ -80,19 +511,88 @@ mod tests {
             CssValue::Comma,
             unit(200.0, "px"),
         ];
-        assert_eq!(resolve_math("clamp", &args), Some(unit(100.0, "px")));
+        assert_eq!(resolve_math("clamp", &args, &HashMap::new()), Some(unit(100.0, "px")));
     }
 
     #[test]
     fn min_and_max() {
         let args = vec![unit(10.0, "px"), CssValue::Comma, unit(20.0, "px")];
-        assert_eq!(resolve_math("min", &args), Some(unit(10.0, "px")));
-        assert_eq!(resolve_math("max", &args), Some(unit(20.0, "px")));
+        assert_eq!(resolve_math("min", &args, &HashMap::new()), Some(unit(10.0, "px")));
+        assert_eq!(resolve_math("max", &args, &HashMap::new()), Some(unit(20.0, "px")));
+    }
+
+    #[test]
+    fn mismatched_operands_bail() {
+        let args = vec![unit(10.0, "px"), CssValue::Comma, CssValue::Number(2.0)];
+        assert_eq!(resolve_math("min", &args, &HashMap::new()), None);
     }
 
     #[test]
-    fn unresolvable_operand_bails() {
+    fn comparisons_layout_must_decide_are_kept() {
+        let args = vec![unit(10.0, "px"), CssValue::Comma, unit(2.0, "em")];
+        let min = calc_length("min", &args).unwrap();
+        assert_eq!(min.resolve(16.0, 0.0), 10.0);
+        assert_eq!(min.resolve(4.0, 0.0), 8.0);
+        assert!(!min.has_percentage());
+
         let args = vec![unit(10.0, "px"), CssValue::Comma, CssValue::Percentage(50.0)];
-        assert_eq!(resolve_math("min", &args), None);
+        let max = calc_length("max", &args).unwrap();
+        assert_eq!(max.resolve(16.0, 100.0), 50.0);
+        assert_eq!(max.resolve(16.0, 10.0), 10.0);
+    }
+
+    #[test]
+    fn calc_arithmetic() {
+        assert_eq!(calc("10px + 2 * 5px"), Some(unit(20.0, "px")));
+        assert_eq!(calc("(10px + 2px) * 2"), Some(unit(24.0, "px")));
+        assert_eq!(calc("1in - 6px / 2"), Some(unit(93.0, "px")));
+        assert_eq!(calc("100% / 4 * 2"), Some(CssValue::Percentage(50.0)));
+        assert_eq!(calc("1.5em + 0.5em"), Some(unit(2.0, "em")));
+        assert_eq!(calc("3 / 2"), Some(CssValue::Number(1.5)));
+        assert_eq!(calc("1px -2px"), Some(unit(-1.0, "px")));
+        assert_eq!(calc("calc(2px + 3px) * 2"), Some(unit(10.0, "px")));
+        assert_eq!(calc("min(10px, 20px) + max(1px, 3px)"), Some(unit(13.0, "px")));
+        assert_eq!(calc("max(0px, 2em)"), Some(unit(2.0, "em")));
+    }
+
+    #[test]
+    fn calc_type_errors_stay_unresolved() {
+        assert_eq!(calc("1px * 2px"), None);
+        assert_eq!(calc("1px + 2"), None);
+        assert_eq!(calc("10px / 0"), None);
+        assert_eq!(calc("10deg"), None);
+    }
+
+    #[test]
+    fn percentages_mixed_with_lengths_are_left_to_layout() {
+        let value = calc("100% - 20px").unwrap();
+        let CssValue::Function(name, args) = &value else {
+            panic!("expected a calc(), got {value:?}");
+        };
+        let length = calc_length(name, args).unwrap();
+        assert!(length.has_percentage());
+        assert_eq!(length.resolve(16.0, 300.0), 280.0);
+
+        // Font-relative parts stay in em until the font size is known.
+        let length = calc_length("calc", &[CssValue::String("50% + 1em - min(4px, 1em)".to_string())]).unwrap();
+        assert_eq!(length.resolve(10.0, 200.0), 106.0);
+        assert_eq!(length.at_font_size(10.0).resolve(99.0, 200.0), 106.0);
+    }
+
+    #[test]
+    fn calc_reads_custom_properties() {
+        let mut props = HashMap::new();
+        props.insert("--gap".to_string(), unit(8.0, "px"));
+        props.insert("--cols".to_string(), CssValue::Number(3.0));
+        let body = |s: &str| vec![CssValue::String(s.to_string())];
+        assert_eq!(
+            resolve_math("calc", &body("var(--gap) * var(--cols)"), &props),
+            Some(unit(24.0, "px"))
+        );
+        assert_eq!(
+            resolve_math("calc", &body("var(--missing, 2px) + 1px"), &props),
+            Some(unit(3.0, "px"))
+        );
+        assert_eq!(resolve_math("calc", &body("var(--missing)"), &props), None);
     }
 }
@@ -6,11 +6,12 @@ use std::fmt::Display;
 
 use gosub_interface::config::HasDocument;
 use gosub_interface::css3;
-use gosub_interface::css3::{CssOrigin, CssPropertyMap};
+use gosub_interface::css3::{CalcLength, CssOrigin, CssPropertyMap};
 use gosub_interface::document::Document;
 use gosub_interface::node::NodeType;
 use gosub_shared::node::NodeId;
 
+use crate::functions::math::calc_length;
 use crate::matcher::property_definitions::get_css_definitions;
 use crate::stylesheet::{Combinator, CssSelector, CssSelectorPart, CssValue, MatcherType, Specificity};
 use crate::system::Css3System;
@@ -48,6 +49,21 @@ pub(crate) fn match_selector<C: HasDocument>(
     (false, Specificity::new(0, 0, 0))
 }
 
+/// The selector out of the selector list `selector` that matches element `node_id` itself, if
+/// any. With several matching, the most specific one.
+pub(crate) fn matching_selector<'a, C: HasDocument>(
+    document: &C::Document,
+    node_id: NodeId,
+    selector: &'a CssSelector,
+) -> Option<&'a [CssSelectorPart]> {
+    selector
+        .parts
+        .iter()
+        .map(Vec::as_slice)
+        .filter(|part| match_selector_parts::<C>(document, node_id, part, None))
+        .max_by_key(|part| Specificity::from(*part))
+}
+
 /// Case-insensitive compare of a pseudo-element name against a target (`before`/`after`).
 fn pseudo_eq(name: &str, target: &str) -> bool {
     name.eq_ignore_ascii_case(target)
@@ -239,8 +255,20 @@ fn match_selector_part<C: HasDocument>(
                     && doc.attribute(current_id, "disabled").is_none()
                     && doc.node_type(current_id) == NodeType::ElementNode
             }
-            "focus" | "focus-visible" | "focus-within" => false,
-            "active" => false,
+            // All focus moves come from the keyboard or a click on a focusable element, so
+            // `:focus-visible` matches whenever `:focus` does.
+            "focus" | "focus-visible" => doc.focused() == Some(current_id),
+            "focus-within" => {
+                let mut focused = doc.focused();
+                while let Some(id) = focused {
+                    if id == current_id {
+                        return true;
+                    }
+                    focused = doc.parent(id);
+                }
+                false
+            }
+            "active" => doc.is_active(current_id),
             // Unknown / unimplemented pseudo-classes never match.
             _ => false,
         },
@@ -669,6 +697,13 @@ impl css3::CssProperty<Css3System> for CssProperty {
         }
     }
 
+    fn as_calc(&self) -> Option<CalcLength> {
+        match &self.actual {
+            CssValue::Function(name, args) if name == "calc" => calc_length(name, args),
+            _ => None,
+        }
+    }
+
     fn is_none(&self) -> bool {
         matches!(self.actual, CssValue::None)
     }
@@ -0,0 +1,385 @@
+//! Media query evaluation.
+//!
+//! A [`MediaEnvironment`] describes the output device a document is shown on: the viewport, the
+//! device pixel ratio and the user's preferences. It answers whether a parsed media query list
+//! (the prelude of `@media`, or the argument of `matchMedia()`) applies. The engine renders to a
+//! screen, so `screen` and `all` match and `print` doesn't. Features the evaluator doesn't know
+//! never match, the way an unknown feature makes a query false in browsers.
+//!
+//! Style rules inside `@media` blocks are matched against the environment of the layout pass
+//! that computes styles: its viewport comes from
+//! [`set_layout_viewport`](crate::stylesheet::set_layout_viewport), the rest from
+//! [`set_media_preferences`]. A resize or a changed preference therefore takes effect with the
+//! next style computation.
+
+use crate::node::{Node, NodeType, Number};
+use crate::tokenizer::TokenType;
+use crate::Css3;
+use cow_utils::CowUtils;
+use gosub_interface::css3::CssOrigin;
+use gosub_shared::byte_stream::{ByteStream, Encoding};
+use gosub_shared::config::ParserConfig;
+use gosub_shared::errors::{CssError, CssResult};
+use std::cell::Cell;
+
+/// `prefers-color-scheme`
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum ColorScheme {
+    #[default]
+    Light,
+    Dark,
+}
+
+/// What media queries are evaluated against.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaEnvironment {
+    /// Viewport width in CSS pixels
+    pub width: f32,
+    /// Viewport height in CSS pixels
+    pub height: f32,
+    /// Device pixels per CSS pixel
+    pub device_pixel_ratio: f32,
+    pub color_scheme: ColorScheme,
+}
+
+impl Default for MediaEnvironment {
+    fn default() -> Self {
+        Self {
+            width: 0.0,
+            height: 0.0,
+            device_pixel_ratio: 1.0,
+            color_scheme: ColorScheme::default(),
+        }
+    }
+}
+
+thread_local! {
+    /// Device pixel ratio and color scheme `@media` rules are matched against on this thread.
+    static MEDIA_PREFERENCES: Cell<(f32, ColorScheme)> = const { Cell::new((1.0, ColorScheme::Light)) };
+}
+
+/// Sets the device pixel ratio and color scheme `@media` rules are matched against in
+/// subsequent style computations on this thread.
+pub fn set_media_preferences(device_pixel_ratio: f32, color_scheme: ColorScheme) {
+    MEDIA_PREFERENCES.with(|prefs| prefs.set((device_pixel_ratio, color_scheme)));
+}
+
+/// The environment `@media` rules are matched against on this thread.
+pub(crate) fn style_media() -> MediaEnvironment {
+    let (width, height) = crate::stylesheet::layout_viewport();
+    let (device_pixel_ratio, color_scheme) = MEDIA_PREFERENCES.with(Cell::get);
+    MediaEnvironment {
+        width,
+        height,
+        device_pixel_ratio,
+        color_scheme,
+    }
+}
+
+/// Parses a media query list such as `screen and (min-width: 600px), print`.
+pub fn parse_media_query_list(text: &str) -> CssResult<Node> {
+    let mut stream = ByteStream::from_str(text, Encoding::UTF8);
+    let mut parser = Css3::new(&mut stream, ParserConfig::default(), CssOrigin::Author, "");
+    let list = parser.parse_media_query_list()?;
+    parser.consume_whitespace_comments();
+    let rest = parser.tokenizer.lookahead(0);
+    if rest.token_type != TokenType::Eof {
+        return Err(CssError::with_location(
+            "Unexpected token after media query",
+            rest.location,
+        ));
+    }
+    Ok(list)
+}
+
+/// A feature value, with lengths in CSS pixels and resolutions in dppx.
+#[derive(Debug, Clone, PartialEq)]
+enum FeatureValue {
+    Number(f32),
+    Ident(String),
+}
+
+impl MediaEnvironment {
+    /// Whether `text` matches. A list that doesn't parse matches nothing, like `not all`.
+    pub fn matches_str(&self, text: &str) -> bool {
+        if text.trim().is_empty() {
+            return true;
+        }
+        parse_media_query_list(text).is_ok_and(|list| self.matches(&list))
+    }
+
+    /// Whether a parsed media query list matches: any of its queries does, or it is empty.
+    pub fn matches(&self, node: &Node) -> bool {
+        match node.node_type.as_ref() {
+            NodeType::MediaQueryList { media_queries } => {
+                media_queries.is_empty() || media_queries.iter().any(|query| self.matches(query))
+            }
+            NodeType::MediaQuery {
+                modifier,
+                media_type,
+                condition,
+            } => {
+                let media_type = media_type.cow_to_ascii_lowercase();
+                let type_matches = matches!(media_type.as_ref(), "" | "all" | "screen");
+                let matches = type_matches && condition.as_ref().is_none_or(|c| self.matches_condition(c));
+                if modifier.eq_ignore_ascii_case("not") {
+                    !matches
+                } else {
+                    matches
+                }
+            }
+            _ => self.matches_condition(node),
+        }
+    }
+
+    /// Evaluates a condition: its terms joined by `and` or `or`, each optionally negated by `not`.
+    fn matches_condition(&self, node: &Node) -> bool {
+        let list = match node.node_type.as_ref() {
+            NodeType::Condition { list } => list,
+            NodeType::Feature { name, value, .. } => return self.matches_feature(name, value.as_ref()),
+            NodeType::Range {
+                left,
+                left_comparison,
+                middle,
+                right_comparison,
+                right,
+            } => return self.matches_range(left, left_comparison, middle, right_comparison.as_ref(), right.as_ref()),
+            _ => return false,
+        };
+
+        let mut any = false;
+        let mut all = true;
+        let mut negate = false;
+        let mut or = false;
+        for term in list {
+            if let NodeType::Ident { value } = term.node_type.as_ref() {
+                match value.cow_to_ascii_lowercase().as_ref() {
+                    "not" => negate = !negate,
+                    "or" => or = true,
+                    "and" => {}
+                    _ => return false,
+                }
+                continue;
+            }
+            let matches = self.matches_condition(term) != negate;
+            negate = false;
+            any |= matches;
+            all &= matches;
+        }
+        if or {
+            any
+        } else {
+            all
+        }
+    }
+
+    /// `(name)` or `(name: value)`, including the `min-` and `max-` forms.
+    fn matches_feature(&self, name: &str, value: Option<&Node>) -> bool {
+        let name = name.cow_to_ascii_lowercase();
+        let (name, comparison) = if let Some(name) = name.strip_prefix("min-") {
+            (name, ">=")
+        } else if let Some(name) = name.strip_prefix("max-") {
+            (name, "<=")
+        } else {
+            (name.as_ref(), "=")
+        };
+        let Some(actual) = self.feature(name) else {
+            return false;
+        };
+
+        let Some(value) = value else {
+            // Boolean context: true unless the feature is zero or `none`.
+            return comparison == "="
+                && match actual {
+                    FeatureValue::Number(n) => n != 0.0,
+                    FeatureValue::Ident(ident) => ident != "none" && ident != "no-preference",
+                };
+        };
+        let Some(expected) = feature_value(value) else {
+            return false;
+        };
+        compare(&actual, comparison, &expected)
+    }
+
+    /// `(width >= 600px)` or `(400px <= width < 700px)`.
+    fn matches_range(
+        &self,
+        left: &Node,
+        left_comparison: &Node,
+        middle: &Node,
+        right_comparison: Option<&Node>,
+        right: Option<&Node>,
+    ) -> bool {
+        let operator = |node: &Node| match node.node_type.as_ref() {
+            NodeType::Operator(op) => Some(op.clone()),
+            _ => None,
+        };
+        let Some(left_op) = operator(left_comparison) else {
+            return false;
+        };
+
+        // `name op value`
+        if let (NodeType::Ident { value: name }, None) = (left.node_type.as_ref(), right) {
+            let actual = self.feature(&name.cow_to_ascii_lowercase());
+            let expected = feature_value(middle);
+            return match (actual, expected) {
+                (Some(actual), Some(expected)) => compare(&actual, &left_op, &expected),
+                _ => false,
+            };
+        }
+
+        // `value op name [op value]`
+        let NodeType::Ident { value: name } = middle.node_type.as_ref() else {
+            return false;
+        };
+        let Some(actual) = self.feature(&name.cow_to_ascii_lowercase()) else {
+            return false;
+        };
+        let Some(low) = feature_value(left) else {
+            return false;
+        };
+        if !compare(&low, &left_op, &actual) {
+            return false;
+        }
+        match (right_comparison.and_then(operator), right.and_then(feature_value)) {
+            (Some(right_op), Some(high)) => compare(&actual, &right_op, &high),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    /// The value of a media feature, or `None` for features the evaluator doesn't know.
+    fn feature(&self, name: &str) -> Option<FeatureValue> {
+        let ident = |s: &str| Some(FeatureValue::Ident(s.to_string()));
+        match name {
+            "width" | "device-width" => Some(FeatureValue::Number(self.width)),
+            "height" | "device-height" => Some(FeatureValue::Number(self.height)),
+            "aspect-ratio" | "device-aspect-ratio" if self.height > 0.0 => {
+                Some(FeatureValue::Number(self.width / self.height))
+            }
+            "orientation" if self.height >= self.width => ident("portrait"),
+            "orientation" => ident("landscape"),
+            "resolution" | "-webkit-device-pixel-ratio" => Some(FeatureValue::Number(self.device_pixel_ratio)),
+            "prefers-color-scheme" => match self.color_scheme {
+                ColorScheme::Light => ident("light"),
+                ColorScheme::Dark => ident("dark"),
+            },
+            "prefers-reduced-motion" | "prefers-reduced-transparency" => ident("no-preference"),
+            "prefers-contrast" => ident("no-preference"),
+            "hover" | "any-hover" => ident("hover"),
+            "pointer" | "any-pointer" => ident("fine"),
+            "color" => Some(FeatureValue::Number(8.0)),
+            "monochrome" | "grid" => Some(FeatureValue::Number(0.0)),
+            "scan" => ident("progressive"),
+            "update" => ident("fast"),
+            "display-mode" => ident("browser"),
+            _ => None,
+        }
+    }
+}
+
+/// A value from a query, with lengths converted to CSS pixels and resolutions to dppx. `16/9`
+/// becomes its quotient.
+fn feature_value(node: &Node) -> Option<FeatureValue> {
+    match node.node_type.as_ref() {
+        NodeType::Number { value } => Some(FeatureValue::Number(*value)),
+        NodeType::Ident { value } => Some(FeatureValue::Ident(value.cow_to_ascii_lowercase().into_owned())),
+        NodeType::Dimension { value, unit } => dimension(*value, unit).map(FeatureValue::Number),
+        NodeType::Value { children } => match children.as_slice() {
+            [numerator, _, denominator] => match (feature_value(numerator), feature_value(denominator)) {
+                (Some(FeatureValue::Number(n)), Some(FeatureValue::Number(d))) if d != 0.0 => {
+                    Some(FeatureValue::Number(n / d))
+                }
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Lengths in CSS pixels (`em` and `rem` are the initial 16px) and resolutions in dppx.
+fn dimension(value: Number, unit: &str) -> Option<f32> {
+    let factor = match unit.cow_to_ascii_lowercase().as_ref() {
+        "px" | "dppx" | "x" => 1.0,
+        "em" | "rem" => 16.0,
+        "in" => 96.0,
+        "cm" => 96.0 / 2.54,
+        "mm" => 96.0 / 25.4,
+        "q" => 96.0 / 101.6,
+        "pt" => 96.0 / 72.0,
+        "pc" => 16.0,
+        "dpi" => 1.0 / 96.0,
+        "dpcm" => 2.54 / 96.0,
+        _ => return None,
+    };
+    Some(value * factor)
+}
+
+fn compare(left: &FeatureValue, operator: &str, right: &FeatureValue) -> bool {
+    // Values converted from other units are rarely exact.
+    const EPSILON: f32 = 1e-3;
+    match (left, right) {
+        (FeatureValue::Number(l), FeatureValue::Number(r)) => match operator {
+            "=" => (l - r).abs() < EPSILON,
+            "<" => l < r,
+            "<=" => *l <= r + EPSILON,
+            ">" => l > r,
+            ">=" => *l >= r - EPSILON,
+            _ => false,
+        },
+        (FeatureValue::Ident(l), FeatureValue::Ident(r)) => operator == "=" && l == r,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phone() -> MediaEnvironment {
+        MediaEnvironment {
+            width: 390.0,
+            height: 844.0,
+            device_pixel_ratio: 3.0,
+            color_scheme: ColorScheme::Dark,
+        }
+    }
+
+    #[test]
+    fn features_and_ranges() {
+        let env = phone();
+        assert!(env.matches_str("(max-width: 600px)"));
+        assert!(!env.matches_str("(min-width: 600px)"));
+        assert!(env.matches_str("(width < 37.5em)"));
+        assert!(env.matches_str("(300px <= width <= 400px)"));
+        assert!(!env.matches_str("(400px < width)"));
+        assert!(env.matches_str("(orientation: portrait)"));
+        assert!(env.matches_str("(min-resolution: 2dppx)"));
+        assert!(env.matches_str("(prefers-color-scheme: dark)"));
+        assert!(env.matches_str("(hover)"));
+        assert!(!env.matches_str("(unknown-feature: 1)"));
+    }
+
+    #[test]
+    fn media_types_and_logic() {
+        let env = phone();
+        assert!(env.matches_str("screen and (max-width: 600px)"));
+        assert!(!env.matches_str("print"));
+        assert!(env.matches_str("print, (prefers-color-scheme: dark)"));
+        assert!(env.matches_str("not print"));
+        assert!(!env.matches_str("(min-width: 600px) and (prefers-color-scheme: dark)"));
+        assert!(env.matches_str("(min-width: 600px) or (prefers-color-scheme: dark)"));
+        assert!(env.matches_str(""));
+        assert!(!env.matches_str("screen and"));
+    }
+
+    #[test]
+    fn scoped_viewport_is_restored() {
+        crate::stylesheet::set_layout_viewport(1024.0, 768.0);
+        let inside = crate::stylesheet::with_layout_viewport(700.0, 1000.0, style_media);
+        assert_eq!((inside.width, inside.height), (700.0, 1000.0));
+        let after = style_media();
+        assert_eq!((after.width, after.height), (1024.0, 768.0));
+    }
+}
@@ -1,5 +1,7 @@
 use crate::tokenizer::{Number, Token, TokenType};
 use crate::Css3;
+use gosub_shared::byte_stream::Location;
+use gosub_shared::error_report::{self, ErrorReport, ErrorSource};
 use gosub_shared::errors::{CssError, CssResult};
 
 mod anplusb;
@@ -21,6 +23,18 @@ mod url;
 mod value;
 
 impl Css3<'_> {
+    /// Reports an error that was recovered from because `config.ignore_errors` is set
+    fn report_ignored(&self, message: &str, location: Option<Location>) {
+        let mut report = ErrorReport::warning(ErrorSource::Css, message);
+        if !self.source.is_empty() {
+            report = report.with_url(self.source.as_str());
+        }
+        if let Some(location) = location {
+            report = report.with_location(location);
+        }
+        error_report::report(report);
+    }
+
     /// Consumes a specific token
     pub fn consume(&mut self, token_type: TokenType) -> CssResult<Token> {
         let t = self.tokenizer.consume();
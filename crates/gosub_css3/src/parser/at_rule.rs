@@ -173,7 +173,7 @@ impl Css3<'_> {
             Ok(at_rule_node) => Ok(Some(at_rule_node)),
             Err(err) if self.config.ignore_errors => {
                 self.parse_until_rule_end();
-                log::warn!("Ignoring error in parse_at_rule: {err:?}");
+                self.report_ignored(&format!("Ignoring invalid at-rule: {}", err.message), err.location);
                 Ok(None)
             }
             Err(err) => Err(err),
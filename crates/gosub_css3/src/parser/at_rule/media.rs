@@ -234,7 +234,8 @@ impl Css3<'_> {
                     self.consume_ident("and")?;
                     condition = Some(self.parse_condition(FeatureKind::Media)?);
                 }
-                TokenType::LCurly | TokenType::Semicolon | TokenType::Comma => {
+                // A query can end the input when it isn't part of a stylesheet, as in `matchMedia()`.
+                TokenType::LCurly | TokenType::Semicolon | TokenType::Comma | TokenType::Eof => {
                     // skip;
                 }
                 _ => {
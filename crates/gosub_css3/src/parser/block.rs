@@ -168,7 +168,10 @@ impl Css3<'_> {
                                 // Per the CSS spec, discard the bad declaration and keep parsing
                                 // the remaining declarations in this block rather than aborting
                                 // the whole rule, which would desync block boundaries.
-                                log::warn!("Ignoring error in parse_block: Expected a ; got {t:?}");
+                                self.report_ignored(
+                                    &format!("Ignoring invalid declaration: expected a ; got {t:?}"),
+                                    Some(self.tokenizer.current_location()),
+                                );
                                 self.tokenizer.reconsume();
                                 self.skip_to_declaration_end();
                                 semicolon_seperated = true;
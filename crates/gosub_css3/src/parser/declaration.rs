@@ -36,10 +36,12 @@ impl Css3<'_> {
         log::trace!("parse_declaration");
 
         let result = self.parse_declaration_internal();
-        if result.is_err() && self.config.ignore_errors {
-            log::warn!("Ignoring error in parse_declaration: {result:?}");
-            self.parse_until_declaration_end();
-            return Ok(None);
+        if let Err(err) = &result {
+            if self.config.ignore_errors {
+                self.report_ignored(&format!("Ignoring invalid declaration: {}", err.message), err.location);
+                self.parse_until_declaration_end();
+                return Ok(None);
+            }
         }
 
         if let Ok(declaration) = result {
@@ -15,7 +15,7 @@ impl Css3<'_> {
             Ok(rule_node) => Ok(Some(rule_node)),
             Err(err) if self.config.ignore_errors => {
                 self.parse_until_rule_end();
-                log::warn!("Ignoring error in parse_rule: {err:?}");
+                self.report_ignored(&format!("Ignoring invalid rule: {}", err.message), err.location);
                 Ok(None)
             }
             Err(err) => Err(err),
@@ -1,7 +1,7 @@
 use core::fmt::Debug;
 use core::slice;
 use cow_utils::CowUtils;
-use gosub_interface::css3::CssOrigin;
+use gosub_interface::css3::{CssOrigin, FontFaceRule};
 use gosub_shared::byte_stream::Location;
 use gosub_shared::errors::CssError;
 use gosub_shared::errors::CssResult;
@@ -28,8 +28,25 @@ pub fn set_layout_viewport(width: f32, height: f32) {
     }
 }
 
+/// Runs `f` with viewport-relative units resolving against `width` × `height` (CSS px), then
+/// puts back the viewport that was set before, even if `f` panics. For layouts that have their
+/// own viewport next to the window's, like print layout against the page area.
+pub fn with_layout_viewport<R>(width: f32, height: f32, f: impl FnOnce() -> R) -> R {
+    struct Restore((f32, f32));
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            LAYOUT_VIEWPORT.with(|vp| vp.set(self.0));
+        }
+    }
+
+    let _restore = Restore(layout_viewport());
+    set_layout_viewport(width, height);
+    f()
+}
+
 /// The current viewport (CSS px) for resolving viewport-relative units on this thread.
-fn layout_viewport() -> (f32, f32) {
+pub(crate) fn layout_viewport() -> (f32, f32) {
     LAYOUT_VIEWPORT.with(Cell::get)
 }
 
@@ -134,6 +151,8 @@ pub struct FontFace {
     /// The raw `unicode-range` descriptor, if any (e.g. `"U+0000-00FF, U+0131"`). Used to
     /// pick the subset that covers the content; `None` means the face covers all code points.
     pub unicode_range: Option<String>,
+    /// The `font-display` keyword (lowercased), if any.
+    pub display: Option<String>,
 }
 
 /// Defines a complete stylesheet with all its rules and the location where it was found
@@ -160,10 +179,15 @@ impl gosub_interface::css3::CssStylesheet for CssStylesheet {
         &self.url
     }
 
-    fn font_faces(&self) -> Vec<(String, Vec<String>, Option<String>)> {
+    fn font_faces(&self) -> Vec<FontFaceRule> {
         self.font_faces
             .iter()
-            .map(|f| (f.family.clone(), f.sources.clone(), f.unicode_range.clone()))
+            .map(|f| FontFaceRule {
+                family: f.family.clone(),
+                sources: f.sources.clone(),
+                unicode_range: f.unicode_range.clone(),
+                display: f.display.clone(),
+            })
             .collect()
     }
 }
@@ -175,6 +199,8 @@ pub struct CssRule {
     pub selectors: Vec<CssSelector>,
     /// Actual declarations that will be applied if the selectors match
     pub declarations: Vec<CssDeclaration>,
+    /// Media query lists of the `@media` rules the rule is nested in; all of them must match
+    pub media: Vec<crate::node::Node>,
 }
 
 impl CssRule {
@@ -187,6 +213,12 @@ impl CssRule {
     pub fn declarations(&self) -> &Vec<CssDeclaration> {
         &self.declarations
     }
+
+    /// Whether the rule's `@media` conditions hold in `env`.
+    #[must_use]
+    pub fn applies_in(&self, env: &crate::media::MediaEnvironment) -> bool {
+        self.media.iter().all(|list| env.matches(list))
+    }
 }
 
 /// A CSS declaration, which contains a property, value and a flag for !important
@@ -218,6 +250,37 @@ impl CssSelector {
     }
 }
 
+/// Writes one selector of a selector list back as CSS text, e.g. `ul > li.active`.
+#[must_use]
+pub fn selector_to_css(parts: &[CssSelectorPart]) -> String {
+    let mut css = String::new();
+    for part in parts {
+        match part {
+            CssSelectorPart::Universal => css.push('*'),
+            CssSelectorPart::Attribute(attr) => {
+                css.push('[');
+                css.push_str(&attr.name);
+                if attr.matcher != MatcherType::None {
+                    css.push_str(&format!("{}\"{}\"", attr.matcher, attr.value));
+                    if attr.case_insensitive {
+                        css.push_str(" i");
+                    }
+                }
+                css.push(']');
+            }
+            CssSelectorPart::Class(name) => css.push_str(&format!(".{name}")),
+            CssSelectorPart::Id(name) => css.push_str(&format!("#{name}")),
+            CssSelectorPart::PseudoClass(name) => css.push_str(&format!(":{name}")),
+            CssSelectorPart::PseudoElement(name) => css.push_str(&format!("::{name}")),
+            CssSelectorPart::Combinator(Combinator::Descendant) => css.push(' '),
+            CssSelectorPart::Combinator(Combinator::Namespace) => css.push('|'),
+            CssSelectorPart::Combinator(combinator) => css.push_str(&format!(" {combinator} ")),
+            CssSelectorPart::Type(name) => css.push_str(name),
+        }
+    }
+    css
+}
+
 /// Represents a CSS selector part, which has a type and value (e.g. type=Class, class="my-class")
 #[derive(PartialEq, Clone, Default)]
 pub enum CssSelectorPart {
@@ -348,6 +411,12 @@ impl Specificity {
     pub fn new(a: u32, b: u32, c: u32) -> Self {
         Self(a, b, c)
     }
+
+    /// The id, class and type counts
+    #[must_use]
+    pub fn counts(&self) -> (u32, u32, u32) {
+        (self.0, self.1, self.2)
+    }
 }
 
 impl From<&[CssSelectorPart]> for Specificity {
@@ -459,6 +528,32 @@ impl Display for CssValue {
 }
 
 impl CssValue {
+    /// The value as CSS text, the way it would be written in a declaration. Unlike the
+    /// `Display` output, lists are written as space separated values.
+    #[must_use]
+    pub fn to_css_text(&self) -> String {
+        fn join(values: &[CssValue]) -> String {
+            let mut css = String::new();
+            for value in values {
+                if matches!(value, CssValue::Comma) {
+                    css.push(',');
+                    continue;
+                }
+                if !css.is_empty() {
+                    css.push(' ');
+                }
+                css.push_str(&value.to_css_text());
+            }
+            css
+        }
+
+        match self {
+            CssValue::Function(name, args) => format!("{name}({})", join(args)),
+            CssValue::List(values) => join(values),
+            value => value.to_string(),
+        }
+    }
+
     #[must_use]
     pub fn to_color(&self) -> Option<RgbColor> {
         match self {
@@ -936,6 +1031,7 @@ mod test {
                 value: CssValue::String("red".to_string()),
                 important: false,
             }],
+            media: vec![],
         };
 
         assert_eq!(rule.selectors().len(), 1);
@@ -3,12 +3,13 @@ use crate::functions::math::resolve_math;
 use crate::functions::var::resolve_var;
 use crate::matcher::property_definitions::get_css_definitions;
 use crate::matcher::shorthands::{FixList, FixListInfo};
-use crate::matcher::styling::{match_selector, CssProperties, CssProperty, DeclarationProperty};
-use crate::stylesheet::{CssDeclaration, CssStylesheet, CssValue, Specificity};
+use crate::matcher::styling::{match_selector, matching_selector, CssProperties, CssProperty, DeclarationProperty};
+use crate::media::style_media;
+use crate::stylesheet::{selector_to_css, CssDeclaration, CssStylesheet, CssValue, Specificity};
 use crate::{load_default_useragent_stylesheet, Css3};
 use cow_utils::CowUtils;
 use gosub_interface::config::HasDocument;
-use gosub_interface::css3::{CssOrigin, CssPropertyMap, CssSystem, HoverFingerprints};
+use gosub_interface::css3::{CssOrigin, CssPropertyMap, CssSystem, HoverFingerprints, MatchedDeclaration, MatchedRule};
 use gosub_interface::document::Document;
 use gosub_interface::node::NodeType;
 use gosub_shared::config::ParserConfig;
@@ -77,6 +78,14 @@ impl CssSystem for Css3System {
         Some(map)
     }
 
+    fn matched_rules<C: HasDocument<CssSystem = Self>>(
+        doc: &C::Document,
+        id: NodeId,
+        sheets: &[Self::Stylesheet],
+    ) -> Vec<MatchedRule> {
+        matched_rules_impl::<C>(doc, id, sheets)
+    }
+
     fn load_default_useragent_stylesheet() -> Self::Stylesheet {
         load_default_useragent_stylesheet()
     }
@@ -109,9 +118,13 @@ fn compute_properties<C: HasDocument<CssSystem = Css3System>>(
     let custom_props = collect_custom_props::<C>(doc, id, sheets);
 
     let mut fix_list = FixList::new();
+    let media = style_media();
 
     for sheet in sheets {
         for rule in &sheet.rules {
+            if !rule.applies_in(&media) {
+                continue;
+            }
             for selector in rule.selectors() {
                 let (matched, specificity) = match_selector::<C>(doc, id, selector, pseudo);
 
@@ -290,8 +303,61 @@ fn compute_properties<C: HasDocument<CssSystem = Css3System>>(
     Some(css_map_entry)
 }
 
+/// The rules matching element `id`, sorted the way the cascade ranks normal declarations: by
+/// origin, then by specificity, keeping source order between equals.
+fn matched_rules_impl<C: HasDocument<CssSystem = Css3System>>(
+    doc: &C::Document,
+    id: NodeId,
+    sheets: &[CssStylesheet],
+) -> Vec<MatchedRule> {
+    if doc.node_type(id) != NodeType::ElementNode {
+        return Vec::new();
+    }
+
+    let mut matched = Vec::new();
+    let media = style_media();
+    for sheet in sheets {
+        for rule in &sheet.rules {
+            if !rule.applies_in(&media) {
+                continue;
+            }
+            let Some(parts) = rule
+                .selectors()
+                .iter()
+                .filter_map(|selector| matching_selector::<C>(doc, id, selector))
+                .max_by_key(|parts| Specificity::from(*parts))
+            else {
+                continue;
+            };
+            matched.push(MatchedRule {
+                selector: selector_to_css(parts),
+                specificity: Specificity::from(parts).counts(),
+                origin: sheet.origin,
+                url: sheet.url.clone(),
+                declarations: rule
+                    .declarations()
+                    .iter()
+                    .map(|declaration| MatchedDeclaration {
+                        property: declaration.property.clone(),
+                        value: declaration.value.to_css_text(),
+                        important: declaration.important,
+                    })
+                    .collect(),
+            });
+        }
+    }
+
+    let origin_rank = |origin: CssOrigin| match origin {
+        CssOrigin::UserAgent => 0,
+        CssOrigin::User => 1,
+        CssOrigin::Author => 2,
+    };
+    matched.sort_by_key(|rule| (origin_rank(rule.origin), rule.specificity));
+    matched
+}
+
 fn hover_fingerprints_impl(sheets: &[CssStylesheet]) -> HoverFingerprints {
-    use crate::stylesheet::CssSelectorPart;
+    use crate::stylesheet::{Combinator, CssSelectorPart};
 
     let mut fp = HoverFingerprints::default();
 
@@ -300,19 +366,28 @@ fn hover_fingerprints_impl(sheets: &[CssStylesheet]) -> HoverFingerprints {
             for selector in &rule.selectors {
                 for part_list in &selector.parts {
                     // Split the part list into compounds (groups between Combinators).
-                    // :hover belongs to the compound it appears in; that compound's
+                    // :hover / :active belong to the compound they appear in; that compound's
                     // Type/Class/Id parts are the hover-subject fingerprint.
                     let mut compound: Vec<&CssSelectorPart> = Vec::new();
-                    for part in part_list {
+                    for (i, part) in part_list.iter().enumerate() {
                         if matches!(part, CssSelectorPart::Combinator(_)) {
                             compound.clear();
                             continue;
                         }
                         compound.push(part);
-                        if !matches!(part, CssSelectorPart::PseudoClass(n) if n == "hover") {
+                        if !matches!(part, CssSelectorPart::PseudoClass(n) if n == "hover" || n == "active") {
                             continue;
                         }
-                        // Found :hover - classify this compound.
+                        // The combinator after this compound decides whether the rule reaches
+                        // past the subject's subtree to its later siblings.
+                        let next = part_list[i + 1..].iter().find_map(|p| match p {
+                            CssSelectorPart::Combinator(c) => Some(c),
+                            _ => None,
+                        });
+                        if matches!(next, Some(Combinator::NextSibling | Combinator::SubsequentSibling)) {
+                            fp.has_sibling_combinator = true;
+                        }
+                        // Found :hover / :active - classify this compound.
                         let mut specific = false;
                         for p in &compound {
                             match p {
@@ -334,7 +409,6 @@ fn hover_fingerprints_impl(sheets: &[CssStylesheet]) -> HoverFingerprints {
                         if !specific {
                             // Bare :hover or *:hover - everything is sensitive.
                             fp.has_universal = true;
-                            return fp;
                         }
                     }
                 }
@@ -378,7 +452,8 @@ pub fn add_property_to_map(
 }
 
 pub fn node_is_unrenderable<C: HasDocument>(doc: &C::Document, id: NodeId) -> bool {
-    const REMOVABLE_ELEMENTS: [&str; 6] = ["head", "script", "style", "svg", "noscript", "title"];
+    // Inline `<svg>` is styled like any element: its cascaded paint is handed to usvg.
+    const REMOVABLE_ELEMENTS: [&str; 5] = ["head", "script", "style", "noscript", "title"];
 
     match doc.node_type(id) {
         NodeType::ElementNode => doc.tag_name(id).is_some_and(|name| REMOVABLE_ELEMENTS.contains(&name)),
@@ -403,9 +478,10 @@ fn collect_custom_props<C: HasDocument<CssSystem = Css3System>>(
     chain.reverse(); // root first - descendants override ancestors
 
     let mut custom_props: HashMap<String, CssValue> = HashMap::new();
+    let media = style_media();
     for node_id in chain {
         for sheet in sheets {
-            for rule in &sheet.rules {
+            for rule in sheet.rules.iter().filter(|rule| rule.applies_in(&media)) {
                 for selector in rule.selectors() {
                     let (matched, _) = match_selector::<C>(doc, node_id, selector, None);
                     if !matched {
@@ -474,8 +550,8 @@ pub fn resolve_functions<C: HasDocument>(
                 let resolved = match func.as_str() {
                     "attr" => resolve_attr::<C>(values, doc, id),
                     "var" => resolve_var(values, custom_props),
-                    "clamp" | "min" | "max" => {
-                        resolve_math(func, values).map_or_else(|| vec![val.clone()], |v| vec![v])
+                    "calc" | "-webkit-calc" | "clamp" | "min" | "max" => {
+                        resolve_math(func, values, custom_props).map_or_else(|| vec![val.clone()], |v| vec![v])
                     }
                     _ => vec![val.clone()],
                 };
@@ -506,3 +582,29 @@ pub fn resolve_functions<C: HasDocument>(
         resolve::<C>(value, doc, id, custom_props)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fingerprints(css: &str) -> HoverFingerprints {
+        let sheet = Css3::parse_str(css, ParserConfig::default(), CssOrigin::Author, "test.css").unwrap();
+        hover_fingerprints_impl(&[sheet])
+    }
+
+    #[test]
+    fn hover_fingerprints_note_sibling_combinators() {
+        let fp = fingerprints("nav a:hover { color: red } .menu:hover > li + li { color: blue }");
+        assert!(fp.types.contains("a"));
+        assert!(fp.classes.contains("menu"));
+        assert!(!fp.has_sibling_combinator);
+
+        assert!(fingerprints("a:hover + p { color: red }").has_sibling_combinator);
+        assert!(fingerprints("#toggle:active ~ .panel { display: block }").has_sibling_combinator);
+
+        // Still noted after a bare :hover made everything sensitive.
+        let fp = fingerprints(":hover { color: red } a:hover ~ p { color: blue }");
+        assert!(fp.has_universal);
+        assert!(fp.has_sibling_combinator);
+    }
+}
@@ -6,12 +6,24 @@ mod context;
 #[allow(clippy::module_inception)]
 mod engine;
 mod errors;
+mod font_settings;
+mod lazy_load;
 
 pub mod events;
 
+pub mod accessibility;
 pub mod cookies;
+pub mod favicon;
+pub mod focus;
+pub mod forms;
+pub mod keyboard;
+pub mod node_desc;
+pub mod permissions;
+pub mod script;
+pub mod selection;
 pub mod storage;
 pub mod tab;
+pub mod user_content;
 pub mod zone;
 
 pub mod config;
@@ -19,7 +31,7 @@ mod policy;
 pub mod settings_store;
 pub mod types;
 
-pub use context::BrowsingContext;
+pub use context::{BrowsingContext, FullPageCapture, FullPagePixmap, HitTestResult, LinkTarget, SelectPopup};
 pub use engine::EngineContext;
 pub use engine::GosubEngine;
 pub use errors::EngineError;
@@ -0,0 +1,655 @@
+//! Accessibility tree.
+//!
+//! Builds a platform-neutral accessibility tree (roles, accessible names, states and bounds)
+//! from a laid-out document. The browsing context produces a fresh tree after each layout and
+//! the tab worker pushes it to the UA as [`EngineEvent::AccessibilityTreeChanged`]. With the
+//! `accesskit` feature enabled, [`AccessibilityTree::to_tree_update`] turns it into an AccessKit
+//! `TreeUpdate` that can be fed straight into a platform adapter (e.g. `accesskit_winit`).
+//!
+//! Generic containers (`div`, `span`, ...) without a role or name are flattened away so the
+//! tree only holds nodes a screen reader would announce.
+//!
+//! [`EngineEvent::AccessibilityTreeChanged`]: crate::events::EngineEvent::AccessibilityTreeChanged
+
+use crate::html::{EngineDocument, RenderConfiguration};
+use cow_utils::CowUtils;
+use gosub_interface::document::Document as _;
+use gosub_interface::node::NodeType;
+use gosub_shared::node::NodeId;
+use std::collections::HashMap;
+
+/// Bounds of a node in page coordinates: `(x, y, width, height)`.
+pub type AccessBounds = (f64, f64, f64, f64);
+
+/// Role of an accessible node. Mirrors the subset of ARIA roles the engine can derive today.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AccessRole {
+    Document,
+    Article,
+    Banner,
+    Button,
+    Cell,
+    CheckBox,
+    ColumnHeader,
+    ComboBox,
+    Complementary,
+    ContentInfo,
+    Form,
+    Generic,
+    Heading,
+    Image,
+    Label,
+    Link,
+    List,
+    ListItem,
+    Main,
+    MultilineTextInput,
+    Navigation,
+    Paragraph,
+    RadioButton,
+    Region,
+    Row,
+    StaticText,
+    Table,
+    TextInput,
+}
+
+impl AccessRole {
+    /// Roles whose accessible name is computed from their content when not given explicitly.
+    fn name_from_content(self) -> bool {
+        matches!(
+            self,
+            AccessRole::Button
+                | AccessRole::Cell
+                | AccessRole::ColumnHeader
+                | AccessRole::Heading
+                | AccessRole::Label
+                | AccessRole::Link
+                | AccessRole::ListItem
+        )
+    }
+
+    /// Maps an explicit ARIA `role` attribute value.
+    fn from_aria(role: &str) -> Option<AccessRole> {
+        let role = match role.trim().cow_to_ascii_lowercase().as_ref() {
+            "article" => AccessRole::Article,
+            "banner" => AccessRole::Banner,
+            "button" => AccessRole::Button,
+            "cell" | "gridcell" => AccessRole::Cell,
+            "checkbox" | "switch" => AccessRole::CheckBox,
+            "columnheader" => AccessRole::ColumnHeader,
+            "combobox" | "listbox" => AccessRole::ComboBox,
+            "complementary" => AccessRole::Complementary,
+            "contentinfo" => AccessRole::ContentInfo,
+            "form" => AccessRole::Form,
+            "heading" => AccessRole::Heading,
+            "img" | "image" => AccessRole::Image,
+            "link" => AccessRole::Link,
+            "list" => AccessRole::List,
+            "listitem" => AccessRole::ListItem,
+            "main" => AccessRole::Main,
+            "navigation" => AccessRole::Navigation,
+            "paragraph" => AccessRole::Paragraph,
+            "radio" => AccessRole::RadioButton,
+            "region" => AccessRole::Region,
+            "row" => AccessRole::Row,
+            "table" | "grid" => AccessRole::Table,
+            "textbox" | "searchbox" => AccessRole::TextInput,
+            "generic" | "none" | "presentation" => AccessRole::Generic,
+            _ => return None,
+        };
+        Some(role)
+    }
+}
+
+/// States of an accessible node.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AccessStates {
+    pub disabled: bool,
+    /// `Some` for checkable controls.
+    pub checked: Option<bool>,
+    pub required: bool,
+    pub readonly: bool,
+    /// `Some` when the node declares `aria-expanded`.
+    pub expanded: Option<bool>,
+    /// The node takes keyboard focus (links, form controls, `tabindex`).
+    pub focusable: bool,
+}
+
+/// A single node of the accessibility tree.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccessNode {
+    /// DOM node this accessible node represents.
+    pub id: NodeId,
+    pub role: AccessRole,
+    /// Computed accessible name.
+    pub name: Option<String>,
+    pub states: AccessStates,
+    /// Bounds from layout; `None` when the node produced no boxes.
+    pub bounds: Option<AccessBounds>,
+    /// Heading level (1-6) for headings.
+    pub level: Option<usize>,
+    /// Link target, resolved against the document URL.
+    pub url: Option<String>,
+    /// Accessible children in document order.
+    pub children: Vec<NodeId>,
+}
+
+/// A complete accessibility tree for one document, rooted at the document node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccessibilityTree {
+    pub root: NodeId,
+    /// Nodes in pre-order; the first entry is the root.
+    nodes: Vec<AccessNode>,
+    index: HashMap<NodeId, usize>,
+}
+
+/// Elements that never produce accessible output.
+const SKIPPED_TAGS: &[&str] = &[
+    "head", "script", "style", "template", "noscript", "meta", "link", "title", "base",
+];
+
+impl AccessibilityTree {
+    /// Builds the tree for `doc`. `bounds` maps DOM nodes to their laid-out boxes (see
+    /// `BrowsingContext::accessibility_tree`); nodes missing from it simply have no bounds.
+    pub fn build<C: RenderConfiguration>(doc: &EngineDocument<C>, bounds: &HashMap<NodeId, AccessBounds>) -> Self {
+        let root_id = doc.root();
+        let mut builder = Builder {
+            doc,
+            bounds,
+            nodes: Vec::new(),
+        };
+
+        let root_index = builder.push(AccessNode {
+            id: root_id,
+            role: AccessRole::Document,
+            name: crate::html::document_title(doc),
+            states: AccessStates::default(),
+            bounds: bounds.get(&root_id).copied(),
+            level: None,
+            url: None,
+            children: Vec::new(),
+        });
+        let children = builder.children_of(root_id);
+        builder.nodes[root_index].children = children;
+
+        let index = builder.nodes.iter().enumerate().map(|(i, n)| (n.id, i)).collect();
+        AccessibilityTree {
+            root: root_id,
+            nodes: builder.nodes,
+            index,
+        }
+    }
+
+    /// Looks up the accessible node for a DOM node.
+    pub fn get(&self, id: NodeId) -> Option<&AccessNode> {
+        self.index.get(&id).map(|&i| &self.nodes[i])
+    }
+
+    /// All nodes in pre-order, root first.
+    pub fn nodes(&self) -> &[AccessNode] {
+        &self.nodes
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+struct Builder<'a, C: RenderConfiguration> {
+    doc: &'a EngineDocument<C>,
+    bounds: &'a HashMap<NodeId, AccessBounds>,
+    nodes: Vec<AccessNode>,
+}
+
+impl<C: RenderConfiguration> Builder<'_, C> {
+    fn push(&mut self, node: AccessNode) -> usize {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    /// Accessible children of `parent`, descending through flattened containers.
+    fn children_of(&mut self, parent: NodeId) -> Vec<NodeId> {
+        let mut out = Vec::new();
+        for &child in self.doc.children(parent) {
+            self.visit(child, &mut out);
+        }
+        out
+    }
+
+    /// Emits the accessible node(s) for `id` into `out`.
+    fn visit(&mut self, id: NodeId, out: &mut Vec<NodeId>) {
+        let doc = self.doc;
+        match doc.node_type(id) {
+            NodeType::TextNode => {
+                let Some(text) = doc.text_value(id).map(collapse_whitespace) else {
+                    return;
+                };
+                if text.is_empty() {
+                    return;
+                }
+                self.push(AccessNode {
+                    id,
+                    role: AccessRole::StaticText,
+                    name: Some(text),
+                    states: AccessStates::default(),
+                    bounds: self.bounds.get(&id).copied(),
+                    level: None,
+                    url: None,
+                    children: Vec::new(),
+                });
+                out.push(id);
+            }
+            NodeType::ElementNode => {
+                if is_hidden(doc, id) {
+                    return;
+                }
+                let Some(role) = element_role(doc, id) else {
+                    return;
+                };
+
+                let name = accessible_name(doc, id, role);
+                // Unnamed generic containers are transparent: hoist their children.
+                if role == AccessRole::Generic && name.is_none() {
+                    for &child in doc.children(id) {
+                        self.visit(child, out);
+                    }
+                    return;
+                }
+
+                let index = self.push(AccessNode {
+                    id,
+                    role,
+                    name,
+                    states: element_states(doc, id, role),
+                    bounds: self.bounds.get(&id).copied(),
+                    level: heading_level(doc, id, role),
+                    url: link_url(doc, id, role),
+                    children: Vec::new(),
+                });
+                out.push(id);
+
+                // Images and text fields are leaves; their content is exposed through the name.
+                if !matches!(
+                    role,
+                    AccessRole::Image | AccessRole::TextInput | AccessRole::MultilineTextInput
+                ) {
+                    let children = self.children_of(id);
+                    self.nodes[index].children = children;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn attr_is_true<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId, name: &str) -> bool {
+    doc.attribute(id, name).is_some_and(|v| v.eq_ignore_ascii_case("true"))
+}
+
+fn is_hidden<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId) -> bool {
+    let tag = doc.tag_name(id).unwrap_or_default();
+    SKIPPED_TAGS.iter().any(|t| tag.eq_ignore_ascii_case(t))
+        || doc.attribute(id, "hidden").is_some()
+        || attr_is_true(doc, id, "aria-hidden")
+        || (tag.eq_ignore_ascii_case("input")
+            && doc
+                .attribute(id, "type")
+                .is_some_and(|t| t.eq_ignore_ascii_case("hidden")))
+}
+
+/// Role of an element: an explicit ARIA `role` wins, otherwise the implicit role of the tag.
+/// Returns `None` for elements that should be dropped entirely (decorative images).
+fn element_role<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId) -> Option<AccessRole> {
+    if let Some(role) = doc.attribute(id, "role").and_then(AccessRole::from_aria) {
+        return Some(role);
+    }
+
+    let tag = doc.tag_name(id).unwrap_or_default().cow_to_ascii_lowercase();
+    let role = match tag.as_ref() {
+        "a" if doc.attribute(id, "href").is_some() => AccessRole::Link,
+        "article" => AccessRole::Article,
+        "aside" => AccessRole::Complementary,
+        "button" => AccessRole::Button,
+        "footer" => AccessRole::ContentInfo,
+        "form" => AccessRole::Form,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => AccessRole::Heading,
+        "header" => AccessRole::Banner,
+        "img" => {
+            // `alt=""` marks an image as decorative.
+            if doc.attribute(id, "alt").is_some_and(str::is_empty) {
+                return None;
+            }
+            AccessRole::Image
+        }
+        "input" => match doc
+            .attribute(id, "type")
+            .unwrap_or("text")
+            .cow_to_ascii_lowercase()
+            .as_ref()
+        {
+            "checkbox" => AccessRole::CheckBox,
+            "radio" => AccessRole::RadioButton,
+            "button" | "submit" | "reset" | "image" => AccessRole::Button,
+            _ => AccessRole::TextInput,
+        },
+        "label" => AccessRole::Label,
+        "li" => AccessRole::ListItem,
+        "main" => AccessRole::Main,
+        "nav" => AccessRole::Navigation,
+        "ol" | "ul" | "menu" => AccessRole::List,
+        "p" => AccessRole::Paragraph,
+        "section" if doc.attribute(id, "aria-label").is_some() => AccessRole::Region,
+        "select" => AccessRole::ComboBox,
+        "table" => AccessRole::Table,
+        "td" => AccessRole::Cell,
+        "textarea" => AccessRole::MultilineTextInput,
+        "th" => AccessRole::ColumnHeader,
+        "tr" => AccessRole::Row,
+        _ => AccessRole::Generic,
+    };
+    Some(role)
+}
+
+/// Concatenated, whitespace-collapsed text of all descendant text nodes.
+fn text_content<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId) -> String {
+    fn collect<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId, out: &mut String) {
+        for &child in doc.children(id) {
+            match doc.node_type(child) {
+                NodeType::TextNode => {
+                    if let Some(text) = doc.text_value(child) {
+                        out.push_str(text);
+                        out.push(' ');
+                    }
+                }
+                NodeType::ElementNode if !is_hidden(doc, child) => {
+                    // An image inside a link/button contributes its alt text.
+                    if doc.tag_name(child).is_some_and(|t| t.eq_ignore_ascii_case("img")) {
+                        if let Some(alt) = doc.attribute(child, "alt") {
+                            out.push_str(alt);
+                            out.push(' ');
+                        }
+                    }
+                    collect(doc, child, out);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut raw = String::new();
+    collect(doc, id, &mut raw);
+    collapse_whitespace(&raw)
+}
+
+/// A simplified version of the accessible name computation: `aria-label`, then host-language
+/// labels (`alt`, button `value`, `placeholder`), then content for roles that allow it, then
+/// `title`.
+fn accessible_name<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId, role: AccessRole) -> Option<String> {
+    let non_empty = |s: &str| {
+        let s = collapse_whitespace(s);
+        (!s.is_empty()).then_some(s)
+    };
+
+    if let Some(name) = doc.attribute(id, "aria-label").and_then(non_empty) {
+        return Some(name);
+    }
+
+    let host = match role {
+        AccessRole::Image => doc.attribute(id, "alt"),
+        AccessRole::Button => doc.attribute(id, "value"),
+        AccessRole::TextInput | AccessRole::MultilineTextInput => doc.attribute(id, "placeholder"),
+        _ => None,
+    };
+    if let Some(name) = host.and_then(non_empty) {
+        return Some(name);
+    }
+
+    if role.name_from_content() {
+        if let Some(name) = non_empty(&text_content(doc, id)) {
+            return Some(name);
+        }
+    }
+
+    doc.attribute(id, "title").and_then(non_empty)
+}
+
+fn element_states<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId, role: AccessRole) -> AccessStates {
+    let is_control = matches!(
+        role,
+        AccessRole::Button
+            | AccessRole::CheckBox
+            | AccessRole::ComboBox
+            | AccessRole::MultilineTextInput
+            | AccessRole::RadioButton
+            | AccessRole::TextInput
+    );
+
+    let checked = matches!(role, AccessRole::CheckBox | AccessRole::RadioButton)
+        .then(|| doc.attribute(id, "checked").is_some() || attr_is_true(doc, id, "aria-checked"));
+
+    let disabled = doc.attribute(id, "disabled").is_some() || attr_is_true(doc, id, "aria-disabled");
+
+    AccessStates {
+        disabled,
+        checked,
+        required: doc.attribute(id, "required").is_some() || attr_is_true(doc, id, "aria-required"),
+        readonly: doc.attribute(id, "readonly").is_some() || attr_is_true(doc, id, "aria-readonly"),
+        expanded: doc
+            .attribute(id, "aria-expanded")
+            .map(|v| v.eq_ignore_ascii_case("true")),
+        focusable: !disabled && (is_control || role == AccessRole::Link || doc.attribute(id, "tabindex").is_some()),
+    }
+}
+
+fn heading_level<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId, role: AccessRole) -> Option<usize> {
+    if role != AccessRole::Heading {
+        return None;
+    }
+    if let Some(level) = doc.attribute(id, "aria-level").and_then(|l| l.trim().parse().ok()) {
+        return Some(level);
+    }
+    let tag = doc.tag_name(id)?;
+    match tag.as_bytes() {
+        [b'h' | b'H', d @ b'1'..=b'6'] => Some((d - b'0') as usize),
+        _ => None,
+    }
+}
+
+fn link_url<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId, role: AccessRole) -> Option<String> {
+    if role != AccessRole::Link {
+        return None;
+    }
+    let href = doc.attribute(id, "href")?;
+    Some(
+        doc.url()
+            .and_then(|base| base.join(href).ok())
+            .map(|u| u.to_string())
+            .unwrap_or_else(|| href.to_string()),
+    )
+}
+
+#[cfg(feature = "accesskit")]
+mod accesskit_adapter {
+    use super::{AccessNode, AccessRole, AccessibilityTree};
+    use accesskit::{Action, Node, NodeId, Rect, Role, Toggled, Tree, TreeUpdate};
+
+    fn to_id(id: gosub_shared::node::NodeId) -> NodeId {
+        NodeId(usize::from(id) as u64)
+    }
+
+    fn to_role(role: AccessRole) -> Role {
+        match role {
+            AccessRole::Document => Role::Document,
+            AccessRole::Article => Role::Article,
+            AccessRole::Banner => Role::Banner,
+            AccessRole::Button => Role::Button,
+            AccessRole::Cell => Role::Cell,
+            AccessRole::CheckBox => Role::CheckBox,
+            AccessRole::ColumnHeader => Role::ColumnHeader,
+            AccessRole::ComboBox => Role::ComboBox,
+            AccessRole::Complementary => Role::Complementary,
+            AccessRole::ContentInfo => Role::ContentInfo,
+            AccessRole::Form => Role::Form,
+            AccessRole::Generic => Role::GenericContainer,
+            AccessRole::Heading => Role::Heading,
+            AccessRole::Image => Role::Image,
+            AccessRole::Label | AccessRole::StaticText => Role::Label,
+            AccessRole::Link => Role::Link,
+            AccessRole::List => Role::List,
+            AccessRole::ListItem => Role::ListItem,
+            AccessRole::Main => Role::Main,
+            AccessRole::MultilineTextInput => Role::MultilineTextInput,
+            AccessRole::Navigation => Role::Navigation,
+            AccessRole::Paragraph => Role::Paragraph,
+            AccessRole::RadioButton => Role::RadioButton,
+            AccessRole::Region => Role::Region,
+            AccessRole::Row => Role::Row,
+            AccessRole::Table => Role::Table,
+            AccessRole::TextInput => Role::TextInput,
+        }
+    }
+
+    fn to_node(src: &AccessNode) -> Node {
+        let mut node = Node::new(to_role(src.role));
+        if let Some(name) = &src.name {
+            node.set_label(name.clone());
+        }
+        if let Some((x, y, w, h)) = src.bounds {
+            node.set_bounds(Rect::new(x, y, x + w, y + h));
+        }
+        if let Some(level) = src.level {
+            node.set_level(level);
+        }
+        if let Some(url) = &src.url {
+            node.set_url(url.clone());
+        }
+        if src.states.disabled {
+            node.set_disabled();
+        }
+        if let Some(checked) = src.states.checked {
+            node.set_toggled(if checked { Toggled::True } else { Toggled::False });
+        }
+        if src.states.required {
+            node.set_required();
+        }
+        if src.states.readonly {
+            node.set_read_only();
+        }
+        if let Some(expanded) = src.states.expanded {
+            node.set_expanded(expanded);
+        }
+        if src.states.focusable {
+            node.add_action(Action::Focus);
+        }
+        if matches!(src.role, AccessRole::Link | AccessRole::Button) && !src.states.disabled {
+            node.add_action(Action::Click);
+        }
+        node.set_children(src.children.iter().copied().map(to_id).collect::<Vec<_>>());
+        node
+    }
+
+    impl AccessibilityTree {
+        /// Converts the tree into a full AccessKit update. `focus` is the focused DOM node, if
+        /// any; AccessKit requires a focus, so the document root is used otherwise.
+        pub fn to_tree_update(&self, focus: Option<gosub_shared::node::NodeId>) -> TreeUpdate {
+            TreeUpdate {
+                nodes: self.nodes.iter().map(|n| (to_id(n.id), to_node(n))).collect(),
+                tree: Some(Tree::new(to_id(self.root))),
+                focus: to_id(focus.filter(|f| self.index.contains_key(f)).unwrap_or(self.root)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::testing::parse_at;
+
+    const PAGE_URL: &str = "https://example.com/dir/";
+
+    fn find(tree: &AccessibilityTree, role: AccessRole) -> Vec<&AccessNode> {
+        tree.nodes().iter().filter(|n| n.role == role).collect()
+    }
+
+    #[test]
+    fn roles_names_and_flattening() {
+        let doc = parse_at(
+            PAGE_URL,
+            r#"<html><head><title>Page</title></head><body>
+                <div><div><h2>Heading <span>text</span></h2></div></div>
+                <a href="next.html">Next <img src="x.png" alt="page"></a>
+                <img src="deco.png" alt="">
+                <p aria-hidden="true">invisible</p>
+            </body></html>"#,
+        );
+        let tree = AccessibilityTree::build(&doc, &HashMap::new());
+
+        let root = tree.get(tree.root).unwrap();
+        assert_eq!(root.role, AccessRole::Document);
+        assert_eq!(root.name.as_deref(), Some("Page"));
+
+        // The wrapping divs are flattened: the heading hangs directly off the root.
+        let heading = find(&tree, AccessRole::Heading)[0];
+        assert!(root.children.contains(&heading.id));
+        assert_eq!(heading.name.as_deref(), Some("Heading text"));
+        assert_eq!(heading.level, Some(2));
+
+        let link = find(&tree, AccessRole::Link)[0];
+        assert_eq!(link.name.as_deref(), Some("Next page"));
+        assert_eq!(link.url.as_deref(), Some("https://example.com/dir/next.html"));
+        assert!(link.states.focusable);
+
+        // Decorative image and aria-hidden content are dropped.
+        assert_eq!(find(&tree, AccessRole::Image).len(), 1);
+        assert!(find(&tree, AccessRole::Paragraph).is_empty());
+    }
+
+    #[test]
+    fn form_control_states() {
+        let doc = parse_at(
+            PAGE_URL,
+            r#"<body>
+                <input type="checkbox" checked aria-label="Remember me">
+                <input type="text" placeholder="Name" required>
+                <button disabled>Send</button>
+                <input type="hidden" value="secret">
+            </body>"#,
+        );
+        let tree = AccessibilityTree::build(&doc, &HashMap::new());
+
+        let checkbox = find(&tree, AccessRole::CheckBox)[0];
+        assert_eq!(checkbox.name.as_deref(), Some("Remember me"));
+        assert_eq!(checkbox.states.checked, Some(true));
+
+        let text = find(&tree, AccessRole::TextInput);
+        assert_eq!(text.len(), 1, "hidden inputs are not exposed");
+        assert_eq!(text[0].name.as_deref(), Some("Name"));
+        assert!(text[0].states.required);
+
+        let button = find(&tree, AccessRole::Button)[0];
+        assert_eq!(button.name.as_deref(), Some("Send"));
+        assert!(button.states.disabled);
+        assert!(!button.states.focusable);
+    }
+
+    #[test]
+    fn bounds_are_attached() {
+        let doc = parse_at(PAGE_URL, "<body><p>Hello</p></body>");
+        let probe = AccessibilityTree::build(&doc, &HashMap::new());
+        let p = find(&probe, AccessRole::Paragraph)[0].id;
+
+        let bounds = HashMap::from([(p, (8.0, 16.0, 100.0, 20.0))]);
+        let tree = AccessibilityTree::build(&doc, &bounds);
+        assert_eq!(tree.get(p).unwrap().bounds, Some((8.0, 16.0, 100.0, 20.0)));
+    }
+}
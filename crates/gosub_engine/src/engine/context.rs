@@ -8,26 +8,47 @@
 //! context via `set_document`, after which the context rebuilds whichever render
 //! representation the active backend consumes.
 
+use crate::engine::accessibility::{AccessBounds, AccessibilityTree};
+use crate::engine::errors::EngineError;
+use crate::engine::events::CursorIcon;
+use crate::engine::focus;
+use crate::engine::forms::{self, FormSubmission, SelectOption, SelectedFiles};
+use crate::engine::lazy_load::{self, LazyElement, LazyKind};
+use crate::engine::node_desc::NodeDesc;
+use crate::engine::selection::{boundary_at, TextFragment, TextSelection};
 use crate::engine::storage::{StorageArea, StorageHandles};
 use crate::html::EngineDocument;
 use gosub_config::{Config, HasConfig};
+use gosub_css3::media::ColorScheme;
 use gosub_render_pipeline::rasterizer::{
     collect_placed_gpu_tiles, cpu_cached_tiles, rasterize_parallel, rasterize_sequential, BakedTile, RasterStrategy,
     Rasterable, TilePixelCache,
 };
-use gosub_render_pipeline::render::{Color, DisplayItem, RenderContext, RenderList, Viewport};
+use gosub_render_pipeline::render::{
+    argb_u32_to_rgba8, composite_tiles, Color, DisplayItem, RenderContext, RenderList, TileTarget, Viewport,
+};
 use std::sync::Arc;
 
 use crate::html::RenderConfiguration;
 use gosub_interface::css3::{CssSystem, HoverFingerprints};
 use gosub_interface::document::Document as _;
+use gosub_render_pipeline::common::browser_state::PaintDebug;
+use gosub_render_pipeline::common::document::inline_svg::{inline_svg_element, inline_svg_pointer_events};
+use gosub_render_pipeline::common::document::style::{lookup, StyleProperty, Value};
+use gosub_render_pipeline::common::geo::{Coordinate, Rect};
+use gosub_render_pipeline::common::selection::{SelectionRanges, TextLines};
 use gosub_render_pipeline::common::texture::TilePixels;
 use gosub_render_pipeline::layering::layer::LayerList;
-use gosub_render_pipeline::layouter::LayoutElementId;
-use gosub_render_pipeline::painter::{PaintScene, Painter};
+use gosub_render_pipeline::layouter::scroll::{apply_scroll_offsets, route_delta, scroll_chain, ScrollOffsets};
+use gosub_render_pipeline::layouter::{ElementContext, LayoutElementId, LayoutElementNode, LayoutTree};
+use gosub_render_pipeline::paginator::{PageSetup, PrintPage};
+use gosub_render_pipeline::painter::commands::PaintCommand;
+use gosub_render_pipeline::painter::{FrameContents, PaintScene, Painter};
 use gosub_render_pipeline::render::backend::{CachedTile, ExternalHandle};
 use gosub_shared::node::NodeId;
+use gosub_svg::PointerEvents;
 use std::any::Any;
+use url::Url;
 
 /// GPU-scene cache: the layer list (for hit-testing) plus the whole-page paint command list
 /// (for the backend to render). The GPU equivalent of [`PipelineCache`] - it skips tiling,
@@ -62,6 +83,119 @@ fn hover_matches<C: RenderConfiguration>(fp: &HoverFingerprints, doc: &EngineDoc
     }
     false
 }
+
+/// Result of a [`BrowsingContext::hit_test`]: the topmost node under a viewport point plus the
+/// link and image metadata that input handling, context menus and devtools picking need.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HitTestResult {
+    /// The DOM node of the topmost layout element under the point.
+    pub node_id: NodeId,
+    /// The layout element that was hit (useful for devtools highlighting).
+    pub layout_element: LayoutElementId,
+    /// Margin box of the hit element in page coordinates `(x, y, width, height)`.
+    pub bounds: (f64, f64, f64, f64),
+    /// The nearest `<a href>` ancestor (or the node itself), resolved against the document URL.
+    pub link_url: Option<String>,
+    /// The `src` of the hit node when it (or an ancestor) is an `<img>`, resolved like `link_url`.
+    pub image_src: Option<String>,
+    /// The selected text, when the point is inside the selection.
+    pub selected_text: Option<String>,
+    /// Whether the hit node takes typed text (see [`focus::is_editable`]).
+    pub editable: bool,
+}
+
+/// A `<select>` drop-down for the UA to show, from [`BrowsingContext::select_at`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelectPopup {
+    pub node_id: NodeId,
+    pub options: Vec<SelectOption>,
+    /// The `multiple` attribute is set, so several options may be chosen
+    pub multiple: bool,
+    /// Border box of the `<select>` in viewport coordinates `(x, y, width, height)`
+    pub anchor: (f64, f64, f64, f64),
+}
+
+/// Where a link opens, from [`BrowsingContext::link_target_at`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkTarget {
+    /// The link's `target`, else the document's `<base target>`; empty for the current context
+    pub target: String,
+    /// Whether a page opened in a new context may reach back to this one. `target="_blank"`
+    /// implies `noopener`, so only `rel="opener"` sets it.
+    pub opener: bool,
+}
+
+impl LinkTarget {
+    /// Whether the link opens in a new browsing context; see [`is_new_context_target`].
+    ///
+    /// [`is_new_context_target`]: crate::html::is_new_context_target
+    pub fn is_new_context(&self) -> bool {
+        crate::html::is_new_context_target(&self.target)
+    }
+}
+
+/// The whole document rendered in one piece rather than through the viewport, from
+/// [`BrowsingContext::capture_full_page`]. For full-page screenshots and exports.
+pub struct FullPageCapture {
+    /// Page size in CSS pixels: the viewport width by the full document height.
+    pub width: f64,
+    pub height: f64,
+    /// Paint commands for the whole page, in page coordinates.
+    pub scene: PaintScene,
+    /// The page rasterized at the backend's device pixel ratio. Only CPU tile backends (Cairo,
+    /// Skia) have pixels to hand out; `None` elsewhere, in which case render `scene` instead.
+    pub pixels: Option<FullPagePixmap>,
+}
+
+impl std::fmt::Debug for FullPageCapture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FullPageCapture")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("commands", &self.scene.commands.len())
+            .field("pixels", &self.pixels)
+            .finish()
+    }
+}
+
+/// Opaque RGBA8 pixels, row-major, `width * 4` bytes per row.
+#[derive(Clone, PartialEq)]
+pub struct FullPagePixmap {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl std::fmt::Debug for FullPagePixmap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FullPagePixmap({}x{})", self.width, self.height)
+    }
+}
+
+/// Resolves a raw `href`/`src` attribute against the document URL. Falls back to the raw value
+/// when there is no base URL or the join fails, so callers still see what the page declared.
+fn resolve_against_document<C: RenderConfiguration>(doc: &EngineDocument<C>, raw: &str) -> String {
+    doc.url()
+        .and_then(|base| base.join(raw).ok())
+        .map(|u| u.to_string())
+        .unwrap_or_else(|| raw.to_string())
+}
+
+/// The target of link element `id`, falling back to the first `<base target>`.
+fn link_target<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId) -> LinkTarget {
+    let target = doc
+        .attribute(id, "target")
+        .map(str::to_string)
+        .or_else(|| crate::html::base_target(doc))
+        .unwrap_or_default();
+    let rel = doc.attribute(id, "rel").unwrap_or_default();
+    let has = |token: &str| rel.split_ascii_whitespace().any(|t| t.eq_ignore_ascii_case(token));
+    LinkTarget {
+        target,
+        opener: has("opener") && !has("noopener") && !has("noreferrer"),
+    }
+}
+
 /// Cached output of stages 1–6 for the whole page. Re-used on every scroll tick.
 struct PipelineCache {
     tiles: Vec<BakedTile>,
@@ -93,8 +227,13 @@ pub struct BrowsingContext<C: RenderConfiguration = crate::html::DefaultRenderCo
     render_dirty: bool,
     /// Viewport size (width/height only - scroll offset lives in scroll_x/y)
     viewport: Viewport,
+    /// Device pixel ratio and color scheme `@media` rules are matched against
+    media_preferences: (f32, ColorScheme),
     /// Epoch of the scene, used to determine if the scene has changed
     scene_epoch: u64,
+    /// Bumped whenever layout is recomputed (not on scroll or hover repaints). Consumers that
+    /// derive data from the layout tree, like the accessibility tree, key off this.
+    layout_epoch: u64,
 
     /// DOM dirty flag, used to determine if the DOM has changed
     dom_dirty: bool,
@@ -108,6 +247,16 @@ pub struct BrowsingContext<C: RenderConfiguration = crate::html::DefaultRenderCo
     scroll_y: f64,
     /// True when only the scroll offset changed (no full re-layout needed).
     scroll_dirty: bool,
+    /// Scroll offsets of element scroll containers (`overflow: auto | scroll`). Applied after
+    /// layout, so changing one needs a rebuild but not a new document.
+    element_scroll: ScrollOffsets,
+    /// Paint commands of loaded child frames, keyed by their `<iframe>` element. Supplied by the
+    /// tab worker, which owns the child contexts.
+    frames: FrameContents,
+    /// `loading=lazy` images and iframes that were laid out but haven't started loading
+    lazy: Vec<LazyElement>,
+    /// Layout epoch and scroll offset `lazy` was last checked at
+    lazy_checked: Option<(u64, f64, f64)>,
 
     /// Cached rasterized tiles for the full page. Valid until render_dirty is set.
     pipeline_cache: Option<PipelineCache>,
@@ -119,19 +268,41 @@ pub struct BrowsingContext<C: RenderConfiguration = crate::html::DefaultRenderCo
     hover_dirty: bool,
     /// The DOM node currently under the pointer (for :hover matching).
     hover_leaf: Option<NodeId>,
-    /// Layout element ID from the PREVIOUS hover update (needed to find which tile to repaint).
-    hover_old_lei: Option<LayoutElementId>,
-    /// DOM nodes whose hover state changed in the last update (old chain ∪ new chain).
-    /// Only these nodes need their cached CSS invalidated; everything else in the tile stays cached.
+    /// The DOM node the primary button was pressed on (for :active matching), until release.
+    active_leaf: Option<NodeId>,
+    /// Layout elements covering everything pending :hover/:active changes restyle (needed to
+    /// find which tiles to repaint).
+    hover_repaint: Vec<LayoutElementId>,
+    /// DOM nodes restyled by pending :hover/:active changes: each targeted node whose state
+    /// flipped, with its subtree. Only these nodes need their cached CSS invalidated; everything
+    /// else in the tile stays cached.
     hover_dirty_nodes: Vec<NodeId>,
     /// The layout element currently under the pointer, used for bounding-box pre-check.
     hover_layout_element: Option<LayoutElementId>,
-    /// Cached :hover fingerprints for the current document; rebuilt on document change.
+    /// Cached :hover/:active fingerprints for the current document; rebuilt on document change.
     hover_fingerprints: Option<HoverFingerprints>,
-    /// True when the last hover chain contained a fingerprint-sensitive node.
-    hover_chain_sensitive: bool,
-    /// The href of the link currently under the pointer, if any.
+    /// The absolute URL of the link currently under the pointer, if any.
     pub hover_link_url: Option<String>,
+    /// Cursor last reported for the pointer position, and the (layout element, layout epoch) it
+    /// was computed for.
+    hover_cursor: CursorIcon,
+    hover_cursor_key: Option<(Option<LayoutElementId>, u64)>,
+    /// Last pointer position in viewport coordinates; wheel events scroll what is under it.
+    pointer: Option<(f64, f64)>,
+
+    /// Drag-selection state over the laid-out text.
+    selection: TextSelection,
+    /// Highlighted char ranges derived from `selection`, handed to the painter.
+    selection_ranges: SelectionRanges,
+
+    /// DOM node the inspector overlay is drawn on, if any.
+    inspected_node: Option<NodeId>,
+    /// Active paint/layer debug visualizations.
+    paint_debug: PaintDebug,
+    /// When the current repaint flash should be painted away again.
+    flash_clear_at: Option<std::time::Instant>,
+    /// The next pass only clears a flash and must not flash itself.
+    flash_suppressed: bool,
 
     /// The active backend's per-tile rasterizer and how to drive it. Built once by the tab
     /// worker from the engine's `RenderBackend` (replacing the former per-backend cfg cascade).
@@ -157,23 +328,38 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
             render_list: RenderList::new(),
             render_dirty: false,
             viewport: Viewport::default(),
+            media_preferences: (1.0, ColorScheme::default()),
             scene_epoch: 0,
+            layout_epoch: 0,
             dom_dirty: false,
             style_dirty: false,
             layout_dirty: false,
             scroll_x: 0.0,
             scroll_y: 0.0,
             scroll_dirty: false,
+            element_scroll: ScrollOffsets::new(),
+            frames: FrameContents::new(),
+            lazy: Vec::new(),
+            lazy_checked: None,
             pipeline_cache: None,
             scene_cache: None,
             hover_dirty: false,
             hover_leaf: None,
-            hover_old_lei: None,
+            active_leaf: None,
+            hover_repaint: Vec::new(),
             hover_dirty_nodes: Vec::new(),
             hover_layout_element: None,
             hover_fingerprints: None,
-            hover_chain_sensitive: false,
             hover_link_url: None,
+            hover_cursor: CursorIcon::Default,
+            hover_cursor_key: None,
+            pointer: None,
+            selection: TextSelection::default(),
+            selection_ranges: SelectionRanges::new(),
+            inspected_node: None,
+            paint_debug: PaintDebug::default(),
+            flash_clear_at: None,
+            flash_suppressed: false,
             rasterizer: None,
             raster_strategy: RasterStrategy::None,
             media_store: std::sync::Arc::new(gosub_render_pipeline::common::media::MediaStore::new()),
@@ -215,9 +401,41 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
         self.scene_cache = None;
         self.hover_dirty = false;
         self.hover_leaf = None;
+        self.active_leaf = None;
+        self.hover_repaint.clear();
+        self.hover_dirty_nodes.clear();
         self.hover_layout_element = None;
         self.hover_fingerprints = None;
-        self.hover_chain_sensitive = false;
+        self.hover_link_url = None;
+        self.element_scroll.clear();
+        self.frames.clear();
+        self.lazy.clear();
+        self.lazy_checked = None;
+        self.selection.clear();
+        self.selection_ranges.clear();
+        self.inspected_node = None;
+    }
+
+    /// The parsed document, if one committed.
+    pub(crate) fn document(&self) -> Option<&EngineDocument<C>> {
+        self.document.as_deref()
+    }
+
+    /// Changes the document in place, the way scripts do, and has it styled and laid out again.
+    /// Returns `false`, leaving the document alone, when it is shared and can't be changed.
+    pub(crate) fn mutate_document(&mut self, f: impl FnOnce(&mut EngineDocument<C>)) -> bool {
+        // The render caches hold on to the document, and are stale after this anyway.
+        self.pipeline_cache = None;
+        self.scene_cache = None;
+        self.invalidate_render();
+        let Some(doc) = self.document.as_mut().and_then(Arc::get_mut) else {
+            return false;
+        };
+        f(doc);
+        self.dom_dirty = true;
+        self.style_dirty = true;
+        self.layout_dirty = true;
+        true
     }
 
     /// Update the viewport SIZE. Only triggers a full re-layout when width or height changes.
@@ -234,6 +452,27 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
         self.scene_cache = None;
     }
 
+    /// Update the device pixel ratio and color scheme the page's `@media` rules see. Like a
+    /// resize, a change restyles and re-lays out the page.
+    pub fn set_media_preferences(&mut self, device_pixel_ratio: f32, color_scheme: ColorScheme) {
+        if self.media_preferences == (device_pixel_ratio, color_scheme) {
+            return;
+        }
+        self.media_preferences = (device_pixel_ratio, color_scheme);
+        self.style_dirty = true;
+        self.layout_dirty = true;
+        self.invalidate_render();
+        self.pipeline_cache = None;
+        self.scene_cache = None;
+    }
+
+    /// `@media` evaluation is per-thread style state, like viewport units; point it at this
+    /// context's preferences before styles are computed.
+    fn apply_media_preferences(&self) {
+        let (device_pixel_ratio, color_scheme) = self.media_preferences;
+        gosub_css3::media::set_media_preferences(device_pixel_ratio, color_scheme);
+    }
+
     /// Update the scroll offset without triggering a full re-layout.
     /// The next composite will shift tiles by (x, y).
     pub fn set_scroll(&mut self, x: f64, y: f64) {
@@ -257,6 +496,27 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
         self.scroll_y = 0.0;
     }
 
+    /// Offsets of the element scroll containers that were scrolled, by element.
+    pub fn element_scroll_offsets(&self) -> Vec<(NodeId, f64, f64)> {
+        self.element_scroll
+            .iter()
+            .map(|(node, offset)| (*node, offset.x, offset.y))
+            .collect()
+    }
+
+    /// Scrolls element scroll containers to `offsets`, e.g. when returning to a history entry.
+    /// The next layout clamps them to what the containers can scroll; elements that are gone or
+    /// don't scroll are ignored.
+    pub fn restore_element_scroll(&mut self, offsets: &[(NodeId, f64, f64)]) {
+        if offsets.is_empty() {
+            return;
+        }
+        self.element_scroll
+            .extend(offsets.iter().map(|&(node, x, y)| (node, Coordinate::new(x, y))));
+        self.layout_dirty = true;
+        self.invalidate_render();
+    }
+
     #[inline]
     pub fn viewport(&self) -> &Viewport {
         &self.viewport
@@ -267,6 +527,11 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
         self.scene_epoch
     }
 
+    #[inline]
+    pub fn layout_epoch(&self) -> u64 {
+        self.layout_epoch
+    }
+
     pub fn invalidate_render(&mut self) {
         self.render_dirty = true;
     }
@@ -284,11 +549,42 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
         }
     }
 
+    /// Advance the animated SVGs in the media store to `now`. Returns `true`, with the render
+    /// marked dirty, when one of them moved on to a new frame.
+    pub fn tick_media_animations(&mut self, now: std::time::Instant) -> bool {
+        if self.media_store.tick_animations(now) {
+            self.render_dirty = true;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// A web font was registered: text measures differently now, so lay out and paint again.
+    pub(crate) fn fonts_changed(&mut self) {
+        self.layout_dirty = true;
+        self.invalidate_render();
+        self.pipeline_cache = None;
+        self.scene_cache = None;
+    }
+
+    /// Fetches the context's media through `fetcher` from now on.
+    pub(crate) fn set_media_fetcher(&self, fetcher: Arc<dyn gosub_render_pipeline::common::media::ResourceFetcher>) {
+        self.media_store.set_fetcher(fetcher);
+    }
+
+    /// Whether images or other media requested by layout are still downloading.
+    pub fn has_pending_media(&self) -> bool {
+        self.media_store.has_pending()
+    }
+
     /// Full pipeline rebuild (stages 1–6): re-tiles and re-rasterizes the whole page,
     /// carrying over the previous tile-pixel cache, then clears the content dirty flags.
     /// Shared by [`Self::rebuild_pipeline_cache_if_needed`] and
     /// [`Self::rebuild_render_list_if_needed`].
     fn rebuild_full_pipeline(&mut self) {
+        self.apply_media_preferences();
+        let debug = self.pass_paint_debug();
         if let Some(doc) = &self.document {
             let prev_tile_cache = self
                 .pipeline_cache
@@ -303,10 +599,18 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
                 prev_tile_cache,
                 self.media_store.clone(),
                 self.config_store.get_uint("renderer.tile.size") as f64,
+                &self.selection_ranges,
+                &self.element_scroll,
+                &self.frames,
+                self.inspected_node,
+                debug,
             ));
+            self.layout_epoch = self.layout_epoch.wrapping_add(1);
         }
         self.render_dirty = false;
         self.hover_dirty = false;
+        self.hover_repaint.clear();
+        self.hover_dirty_nodes.clear();
         self.dom_dirty = false;
         self.style_dirty = false;
         self.layout_dirty = false;
@@ -328,6 +632,8 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
         if self.render_dirty {
             self.rebuild_full_pipeline();
         } else if self.hover_dirty {
+            self.apply_media_preferences();
+            let debug = self.pass_paint_debug();
             // Paint-only repaint: reuse the cached layout tree, skip stages 1–2.
             if let Some(old_cache) = self.pipeline_cache.take() {
                 let PipelineCache {
@@ -341,8 +647,7 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
                     layer_list,
                     page_height,
                     prev_baked_tiles,
-                    self.hover_old_lei,
-                    self.hover_layout_element,
+                    &self.hover_repaint,
                     &self.hover_dirty_nodes,
                     &self.viewport,
                     self.rasterizer.as_deref(),
@@ -350,6 +655,10 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
                     prev_tile_cache,
                     self.media_store.clone(),
                     self.config_store.get_uint("renderer.tile.size") as f64,
+                    &self.selection_ranges,
+                    &self.frames,
+                    self.inspected_node,
+                    debug,
                 ));
             } else {
                 // No cached layout yet - fall back to a full rebuild.
@@ -362,10 +671,18 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
                         std::collections::HashMap::new(),
                         self.media_store.clone(),
                         self.config_store.get_uint("renderer.tile.size") as f64,
+                        &self.selection_ranges,
+                        &self.element_scroll,
+                        &self.frames,
+                        self.inspected_node,
+                        debug,
                     ));
+                    self.layout_epoch = self.layout_epoch.wrapping_add(1);
                 }
             }
             self.hover_dirty = false;
+            self.hover_repaint.clear();
+            self.hover_dirty_nodes.clear();
         }
         self.scroll_dirty = false;
         self.scene_epoch = self.scene_epoch.wrapping_add(1);
@@ -421,16 +738,26 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
         // the cached layout (it only changes paint), but a GPU re-paint is cheap and avoids the
         // tile path's hover-repaint bookkeeping; revisit if hover proves hot.
         if self.render_dirty || self.hover_dirty {
+            self.apply_media_preferences();
+            let debug = self.pass_paint_debug();
             if let Some(doc) = &self.document {
                 self.scene_cache = Some(pipeline_build_scene(
                     doc.clone(),
                     &self.viewport,
                     self.rasterizer.as_deref(),
                     self.media_store.clone(),
+                    &self.selection_ranges,
+                    &self.element_scroll,
+                    &self.frames,
+                    self.inspected_node,
+                    debug,
                 ));
+                self.layout_epoch = self.layout_epoch.wrapping_add(1);
             }
             self.render_dirty = false;
             self.hover_dirty = false;
+            self.hover_repaint.clear();
+            self.hover_dirty_nodes.clear();
             self.dom_dirty = false;
             self.style_dirty = false;
             self.layout_dirty = false;
@@ -515,112 +842,965 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
         (self.scroll_x, self.scroll_y)
     }
 
+    /// Scrolls the element scroll containers under the pointer by `(dx, dy)`, innermost first;
+    /// whatever a container can't take (it's at its limit) chains to the one around it. Returns
+    /// the part of the delta left over for the viewport.
+    pub fn scroll_containers(&mut self, dx: f64, dy: f64) -> (f64, f64) {
+        let Some((vp_x, vp_y)) = self.pointer else {
+            return (dx, dy);
+        };
+        let Some((_, lei)) = self.element_at(vp_x, vp_y) else {
+            return (dx, dy);
+        };
+        self.scroll_chain_from(lei, dx, dy)
+    }
+
+    /// Like [`scroll_containers`](Self::scroll_containers), but for keyboard scrolling: the chain
+    /// starts at the focused element instead of the one under the pointer.
+    pub fn scroll_focused_containers(&mut self, dx: f64, dy: f64) -> (f64, f64) {
+        let lei = self
+            .active_layer_list()
+            .and_then(|layer_list| layout_element_of(layer_list, self.focused_node()));
+        match lei {
+            Some(lei) => self.scroll_chain_from(lei, dx, dy),
+            None => (dx, dy),
+        }
+    }
+
+    fn scroll_chain_from(&mut self, lei: LayoutElementId, dx: f64, dy: f64) -> (f64, f64) {
+        let Some(layer_list) = self.active_layer_list() else {
+            return (dx, dy);
+        };
+        let chain = scroll_chain(&layer_list.layout_tree, lei);
+        let (moved, rest) = route_delta(&chain, Coordinate::new(dx, dy));
+        if !moved.is_empty() {
+            self.element_scroll.extend(moved);
+            self.layout_dirty = true;
+            self.invalidate_render();
+        }
+        (rest.x, rest.y)
+    }
+
+    /// A fresh context for a child frame of this one. It shares this context's media store, so
+    /// media ids in the frame's paint commands resolve when this context rasterizes them.
+    pub(crate) fn new_child(&self) -> BrowsingContext<C> {
+        let mut child = BrowsingContext::new(self.config_store.clone());
+        child.media_store = Arc::clone(&self.media_store);
+        child.media_preferences = self.media_preferences;
+        child
+    }
+
+    /// The `<iframe>` elements of the document that have a loadable `src`, with its absolute URL
+    /// and whether the frame loads lazily.
+    pub(crate) fn frame_elements(&self) -> Vec<(NodeId, Url, bool)> {
+        let Some(doc) = &self.document else {
+            return Vec::new();
+        };
+        let mut frames = Vec::new();
+        let mut stack = vec![doc.root()];
+        while let Some(id) = stack.pop() {
+            if doc.tag_name(id) == Some("iframe") {
+                let url = doc
+                    .attribute(id, "src")
+                    .and_then(|src| Url::parse(&resolve_against_document(doc, src)).ok())
+                    .filter(|url| url.scheme() != "about");
+                if let Some(url) = url {
+                    frames.push((id, url, lazy_load::is_lazy(doc, id)));
+                }
+            }
+            stack.extend(doc.children(id).iter().rev().copied());
+        }
+        frames
+    }
+
+    /// Starts fetching the `loading=lazy` images that came near the viewport, and returns the
+    /// lazy iframes that did so the tab can load them. Checks again only after a layout or a
+    /// scroll.
+    pub(crate) fn load_lazy_media(&mut self) -> Vec<NodeId> {
+        let (Some(doc), Some(layer_list)) = (self.document.clone(), self.active_layer_list().cloned()) else {
+            return Vec::new();
+        };
+        let key = (self.layout_epoch, self.scroll_x, self.scroll_y);
+        match self.lazy_checked {
+            Some(checked) if checked == key => return Vec::new(),
+            Some((epoch, _, _)) if epoch == self.layout_epoch => {}
+            _ => {
+                self.lazy = lazy_load::lazy_elements(&doc, &layer_list, |src| resolve_against_document(&doc, src));
+                self.lazy.retain(
+                    |el| !matches!(&el.kind, LazyKind::Image(src) if self.media_store.cached_media(src).is_some()),
+                );
+            }
+        }
+        self.lazy_checked = Some(key);
+        if self.lazy.is_empty() {
+            return Vec::new();
+        }
+
+        let view = Rect::new(
+            self.scroll_x,
+            self.scroll_y,
+            self.viewport.width as f64,
+            self.viewport.height as f64,
+        );
+        let image_margin = self.config_store.get_uint("renderer.lazy_load.image_margin") as f64;
+        let iframe_margin = self.config_store.get_uint("renderer.lazy_load.iframe_margin") as f64;
+        let mut frames = Vec::new();
+        self.lazy.retain(|el| match &el.kind {
+            LazyKind::Image(src) if lazy_load::near_viewport(&el.rect, &view, image_margin) => {
+                // Lands like any other background fetch: `poll_media_completed` reflows.
+                let _ = self.media_store.request_media(src);
+                false
+            }
+            LazyKind::Frame if lazy_load::near_viewport(&el.rect, &view, iframe_margin) => {
+                frames.push(el.node);
+                false
+            }
+            _ => true,
+        });
+        frames
+    }
+
+    /// Content box of frame element `node` in page coordinates, once it has been laid out.
+    pub(crate) fn frame_rect(&self, node: NodeId) -> Option<Rect> {
+        let layer_list = self.active_layer_list()?;
+        let lei = layout_element_of(layer_list, Some(node))?;
+        Some(layer_list.layout_tree.get_node_by_id(lei)?.box_model.content_box)
+    }
+
+    /// The loaded child frame under viewport point `(vp_x, vp_y)`, and the point in that frame's
+    /// viewport.
+    pub(crate) fn frame_at(&self, vp_x: f64, vp_y: f64) -> Option<(NodeId, f64, f64)> {
+        if self.frames.is_empty() {
+            return None;
+        }
+        let (node, _) = self.element_at(vp_x, vp_y)?;
+        if !self.frames.contains_key(&node) {
+            return None;
+        }
+        let rect = self.frame_rect(node)?;
+        let (x, y) = (vp_x + self.scroll_x, vp_y + self.scroll_y);
+        rect.contains(x, y).then_some((node, x - rect.x, y - rect.y))
+    }
+
+    /// Shows `commands` inside frame element `node`. They are in the frame's viewport
+    /// coordinates (see [`Self::frame_paint`]).
+    pub(crate) fn set_frame_content(&mut self, node: NodeId, commands: Arc<Vec<PaintCommand>>) {
+        self.frames.insert(node, commands);
+        self.layout_dirty = true;
+        self.invalidate_render();
+    }
+
+    /// The loaded child frame under the pointer, with the pointer in that frame's viewport.
+    pub(crate) fn frame_under_pointer(&self) -> Option<(NodeId, f64, f64)> {
+        let (x, y) = self.pointer?;
+        self.frame_at(x, y)
+    }
+
+    /// This context's page as paint commands for a parent to show in a frame: the whole page,
+    /// shifted by the scroll offset. `None` when nothing changed since the last call.
+    pub(crate) fn frame_paint(&mut self) -> Option<Arc<Vec<PaintCommand>>> {
+        let epoch = self.scene_epoch;
+        self.rebuild_scene_cache_if_needed();
+        if self.scene_epoch == epoch {
+            return None;
+        }
+        let scene = &self.scene_cache.as_ref()?.scene;
+        let (dx, dy) = (-self.scroll_x, -self.scroll_y);
+        let commands = scene
+            .commands
+            .iter()
+            .cloned()
+            .map(|mut cmd| {
+                cmd.translate(dx, dy);
+                cmd
+            })
+            .collect();
+        Some(Arc::new(commands))
+    }
+
+    /// Topmost layout element at viewport coordinates `(vp_x, vp_y)` and its DOM node.
+    ///
+    /// Layers are walked front-to-back in stacking order and the point is mapped into each
+    /// layer's space, so scroll offsets (and fixed/sticky anchoring) are honoured. CSS transforms
+    /// are not laid out by the pipeline yet, so boxes are tested untransformed.
+    fn element_at(&self, vp_x: f64, vp_y: f64) -> Option<(NodeId, LayoutElementId)> {
+        let layer_list = self.active_layer_list()?;
+        let lei = layer_list.find_element_at(vp_x, vp_y, self.scroll_x, self.scroll_y)?;
+        let el = layer_list.layout_tree.get_node_by_id(lei)?;
+        Some((el.dom_node_id, lei))
+    }
+
+    /// The element of the inline `<svg>` laid out as `el` that is under page point `(x, y)`, as
+    /// its painted tree says, honouring `pointer-events`. `None` for SVG images and for points
+    /// on the box that no element is hit at.
+    fn svg_element_at(&self, layout_tree: &LayoutTree, el: &LayoutElementNode, x: f64, y: f64) -> Option<NodeId> {
+        let ElementContext::Svg(context) = &el.context else {
+            return None;
+        };
+        let doc = layout_tree.render_tree.doc.as_ref();
+        if doc.tag_name(el.dom_node_id).as_deref() != Some("svg") {
+            return None;
+        }
+
+        // The tree is painted stretched over the border box.
+        let media = self.media_store.get_svg(context.media_id);
+        let tree = &media.svg.tree;
+        let b = el.box_model.border_box;
+        if b.width <= 0.0 || b.height <= 0.0 {
+            return None;
+        }
+        let tx = (x - b.x) * f64::from(tree.size().width()) / b.width;
+        let ty = (y - b.y) * f64::from(tree.size().height()) / b.height;
+
+        let root = el.dom_node_id;
+        let hit = gosub_svg::hit_test_tree(tree, tx as f32, ty as f32, &|id| {
+            let node = inline_svg_element(doc, root, id)?;
+            PointerEvents::parse(&inline_svg_pointer_events(doc, root, node))
+        })?;
+        hit.ids.iter().find_map(|id| inline_svg_element(doc, root, id))
+    }
+
+    /// Maps a viewport point to the topmost node under it, without touching hover state.
+    ///
+    /// Returns `None` when nothing has been laid out yet or the point misses every element.
+    /// Inside an inline `<svg>` the element under the point is found in the painted SVG; where
+    /// none is, the `<svg>` itself is hit. Link and image metadata is collected by walking up
+    /// from the hit node, so clicking the text inside `<a><span>..</span></a>` (or a shape
+    /// inside an SVG `<a>`) still reports the link.
+    pub fn hit_test(&self, vp_x: f64, vp_y: f64) -> Option<HitTestResult> {
+        let _t = gosub_shared::timing_guard!("hit_test");
+
+        let (mut node_id, lei) = self.element_at(vp_x, vp_y)?;
+        let layer_list = self.active_layer_list()?;
+        let el = layer_list.layout_tree.get_node_by_id(lei)?;
+        let m = el.box_model.margin_box;
+        if let Some(inner) =
+            self.svg_element_at(&layer_list.layout_tree, el, vp_x + self.scroll_x, vp_y + self.scroll_y)
+        {
+            node_id = inner;
+        }
+
+        let mut link_url = None;
+        let mut image_src = None;
+        if let Some(doc) = &self.document {
+            let mut id = node_id;
+            loop {
+                match doc.tag_name(id) {
+                    Some("a") if link_url.is_none() => {
+                        link_url = doc
+                            .attribute(id, "href")
+                            .or_else(|| doc.attribute(id, "xlink:href"))
+                            .map(|href| resolve_against_document(doc, href));
+                    }
+                    Some("img") if image_src.is_none() => {
+                        image_src = doc.attribute(id, "src").map(|src| resolve_against_document(doc, src));
+                    }
+                    _ => {}
+                }
+                if link_url.is_some() && image_src.is_some() {
+                    break;
+                }
+                match doc.parent(id) {
+                    Some(parent) => id = parent,
+                    None => break,
+                }
+            }
+        }
+
+        let selected_text = self.selection.range().and_then(|range| {
+            let fragments = self.text_fragments();
+            let point = boundary_at(&fragments, vp_x + self.scroll_x, vp_y + self.scroll_y)?;
+            range.contains(point, &fragments).then(|| range.to_text(&fragments))
+        });
+        let editable = self
+            .document
+            .as_ref()
+            .is_some_and(|doc| focus::is_editable(doc, node_id));
+
+        Some(HitTestResult {
+            node_id,
+            layout_element: lei,
+            bounds: (m.x, m.y, m.width, m.height),
+            link_url,
+            image_src,
+            selected_text,
+            editable,
+        })
+    }
+
+    /// Draws the devtools box-model overlay (margin/border/padding/content tints, rulers and a
+    /// size label) on `node`, or removes it with `None`. Pick the node with [`Self::hit_test`].
+    pub fn inspect_node(&mut self, node: Option<NodeId>) {
+        if self.inspected_node != node {
+            self.inspected_node = node;
+            self.render_dirty = true;
+        }
+    }
+
+    pub fn inspected_node(&self) -> Option<NodeId> {
+        self.inspected_node
+    }
+
+    /// Text of the document's `<title>`, if it has a non-empty one.
+    pub fn document_title(&self) -> Option<String> {
+        self.document.as_deref().and_then(crate::html::document_title)
+    }
+
+    /// The element with keyboard focus, if any. The document holds it, so `:focus` selectors
+    /// see the same node.
+    pub fn focused_node(&self) -> Option<NodeId> {
+        self.document.as_ref()?.focused()
+    }
+
+    /// Moves keyboard focus to `node`, or blurs with `None`. Returns `(blurred, focused)` when
+    /// focus actually changed, so the caller can dispatch blur/focus.
+    pub fn set_focus(&mut self, node: Option<NodeId>) -> Option<(Option<NodeId>, Option<NodeId>)> {
+        let doc = self.document.as_ref()?;
+        let old = doc.focused();
+        if old == node {
+            return None;
+        }
+        doc.set_focused_node(node);
+        // `:focus` styles and the focus ring both change paint; restyle from scratch.
+        self.render_dirty = true;
+        Some((old, node))
+    }
+
+    /// Moves focus to the next element in Tab order, or the previous one when `backwards`
+    /// (Shift-Tab). See [`focus::tab_order`].
+    pub fn focus_next(&mut self, backwards: bool) -> Option<(Option<NodeId>, Option<NodeId>)> {
+        let doc = self.document.as_ref()?;
+        let rendered = self.rendered_nodes();
+        let order = focus::tab_order(doc, |id| rendered.as_ref().is_none_or(|r| r.contains(&id)));
+        let next = focus::next_in_order(&order, doc.focused(), backwards)?;
+        self.set_focus(Some(next))
+    }
+
+    /// Whether the focused element takes typed text, in which case keys belong to it rather than
+    /// to page scrolling.
+    pub fn focused_is_editable(&self) -> bool {
+        let Some(doc) = self.document.as_ref() else {
+            return false;
+        };
+        doc.focused().is_some_and(|node| focus::is_editable(doc, node))
+    }
+
+    /// The absolute URL of the focused element when it is a link.
+    pub fn focused_link(&self) -> Option<String> {
+        let doc = self.document.as_ref()?;
+        let node = doc.focused()?;
+        if doc.tag_name(node) != Some("a") {
+            return None;
+        }
+        doc.attribute(node, "href")
+            .map(|href| resolve_against_document(doc, href))
+    }
+
+    /// The form a click at viewport coordinates submits, when it lands on a submit button.
+    pub fn submission_at(&self, vp_x: f64, vp_y: f64, files: &SelectedFiles) -> Option<FormSubmission> {
+        let (node, _) = self.element_at(vp_x, vp_y)?;
+        let doc = self.document.as_ref()?;
+        let submitter = forms::submitter_at(doc, node)?;
+        forms::submission(doc, forms::form_owner(doc, submitter)?, Some(submitter), files)
+    }
+
+    /// The submission of `form` by `submitter`, for a form scripts submit.
+    pub fn form_submission(
+        &self,
+        form: NodeId,
+        submitter: Option<NodeId>,
+        files: &SelectedFiles,
+    ) -> Option<FormSubmission> {
+        let doc = self.document.as_ref()?;
+        if doc.tag_name(form) != Some("form") {
+            return None;
+        }
+        let submitter = submitter.filter(|&node| forms::is_submit_button(doc, node));
+        forms::submission(doc, form, submitter, files)
+    }
+
+    /// The form Enter submits: the focused submit button's, or for a focused text field its
+    /// form's, submitted by its default button. A form whose default button is disabled doesn't
+    /// submit implicitly.
+    pub fn enter_submission(&self, files: &SelectedFiles) -> Option<FormSubmission> {
+        let doc = self.document.as_ref()?;
+        let node = doc.focused()?;
+        if forms::is_submit_button(doc, node) {
+            let submitter = forms::submitter_at(doc, node)?;
+            return forms::submission(doc, forms::form_owner(doc, submitter)?, Some(submitter), files);
+        }
+        if !forms::submits_on_enter(doc, node) {
+            return None;
+        }
+        let form = forms::form_owner(doc, node)?;
+        let submitter = match forms::default_button(doc, form) {
+            Some(button) => Some(forms::submitter_at(doc, button)?),
+            None => None,
+        };
+        forms::submission(doc, form, submitter, files)
+    }
+
+    /// The enabled `<input type=file>` at viewport coordinates, with its `multiple` flag and
+    /// `accept` attribute, for asking the UA to show a file chooser.
+    pub fn file_input_at(&self, vp_x: f64, vp_y: f64) -> Option<(NodeId, bool, Option<String>)> {
+        let (node, _) = self.element_at(vp_x, vp_y)?;
+        let doc = self.document.as_ref()?;
+        let is_file = doc.tag_name(node) == Some("input")
+            && doc
+                .attribute(node, "type")
+                .is_some_and(|t| t.eq_ignore_ascii_case("file"));
+        if !is_file || doc.attribute(node, "disabled").is_some() {
+            return None;
+        }
+        let multiple = doc.attribute(node, "multiple").is_some();
+        Some((node, multiple, doc.attribute(node, "accept").map(str::to_string)))
+    }
+
+    /// The enabled `<select>` at viewport coordinates, for asking the UA to show its drop-down:
+    /// its options, whether it takes several, and its border box in viewport coordinates
+    /// `(x, y, width, height)` to place the drop-down against.
+    pub fn select_at(&self, vp_x: f64, vp_y: f64) -> Option<SelectPopup> {
+        let (node, lei) = self.element_at(vp_x, vp_y)?;
+        let doc = self.document.as_ref()?;
+        if doc.tag_name(node) != Some("select") || doc.attribute(node, "disabled").is_some() {
+            return None;
+        }
+        let b = self
+            .active_layer_list()?
+            .layout_tree
+            .get_node_by_id(lei)?
+            .box_model
+            .border_box;
+        Some(SelectPopup {
+            node_id: node,
+            options: forms::select_options(doc, node),
+            multiple: doc.attribute(node, "multiple").is_some(),
+            anchor: (b.x - self.scroll_x, b.y - self.scroll_y, b.width, b.height),
+        })
+    }
+
+    /// Selects the options of `<select>` `node` at `indices`, as the user chose them in its
+    /// drop-down (see [`forms::set_selected_options`]). Returns whether the page changed.
+    pub fn choose_select_options(&mut self, node: NodeId, indices: &[usize]) -> bool {
+        let is_select = self
+            .document
+            .as_ref()
+            .is_some_and(|doc| doc.tag_name(node) == Some("select") && doc.attribute(node, "disabled").is_none());
+        if !is_select {
+            return false;
+        }
+        let mut changed = false;
+        if !self.mutate_document(|doc| changed = forms::set_selected_options(doc, node, indices)) {
+            log::warn!("document is shared, the chosen option is lost");
+        }
+        changed
+    }
+
+    /// Where the link at viewport coordinates opens; `None` when the point is not on a link.
+    pub fn link_target_at(&self, vp_x: f64, vp_y: f64) -> Option<LinkTarget> {
+        let (node, _) = self.element_at(vp_x, vp_y)?;
+        let doc = self.document.as_ref()?;
+        let mut current = Some(node);
+        while let Some(id) = current {
+            if matches!(doc.tag_name(id), Some("a") | Some("area")) && doc.attribute(id, "href").is_some() {
+                return Some(link_target(doc, id));
+            }
+            current = doc.parent(id);
+        }
+        None
+    }
+
+    /// Where the focused link opens, like [`link_target_at`](Self::link_target_at).
+    pub fn focused_link_target(&self) -> Option<LinkTarget> {
+        let doc = self.document.as_ref()?;
+        let node = doc.focused()?;
+        (doc.tag_name(node) == Some("a")).then(|| link_target(doc, node))
+    }
+
+    /// Focus for a click at viewport coordinates: the nearest focusable ancestor of the node
+    /// under the pointer. Clicking anything else blurs.
+    pub fn focus_at(&mut self, vp_x: f64, vp_y: f64) -> Option<(Option<NodeId>, Option<NodeId>)> {
+        let target = match (self.element_at(vp_x, vp_y), &self.document) {
+            (Some((node, _)), Some(doc)) => focus::focusable_ancestor(doc, node),
+            _ => None,
+        };
+        self.set_focus(target)
+    }
+
+    /// DOM nodes that generated a box, plus their ancestors. `None` before the first layout,
+    /// when nothing can be ruled out yet.
+    fn rendered_nodes(&self) -> Option<std::collections::HashSet<NodeId>> {
+        let layer_list = self.active_layer_list()?;
+        let doc = self.document.as_ref()?;
+        let mut rendered = std::collections::HashSet::new();
+        for el in layer_list.layout_tree.arena.values() {
+            let mut id = Some(el.dom_node_id);
+            while let Some(node) = id {
+                if !rendered.insert(node) {
+                    break;
+                }
+                id = doc.parent(node);
+            }
+        }
+        Some(rendered)
+    }
+
+    /// Switches the paint/layer debug visualizations; see [`PaintDebug`].
+    pub fn set_paint_debug(&mut self, debug: PaintDebug) {
+        if self.paint_debug != debug {
+            self.paint_debug = debug;
+            self.flash_clear_at = None;
+            self.render_dirty = true;
+        }
+    }
+
+    pub fn paint_debug(&self) -> PaintDebug {
+        self.paint_debug
+    }
+
+    /// Debug settings for the paint pass about to run. A flashing pass arms the timer that paints
+    /// the flash away again; the pass that does so is itself not flashed.
+    fn pass_paint_debug(&mut self) -> PaintDebug {
+        let mut debug = self.paint_debug;
+        if std::mem::take(&mut self.flash_suppressed) {
+            debug.repaint_flashing = false;
+        } else if debug.repaint_flashing {
+            self.flash_clear_at = Some(std::time::Instant::now() + PAINT_FLASH_DURATION);
+        }
+        debug
+    }
+
+    /// Poll whether a repaint flash has been shown long enough. When it has, schedules a full
+    /// repaint without the flash and returns `true` so the caller wakes its draw loop.
+    pub fn poll_paint_flash(&mut self) -> bool {
+        match self.flash_clear_at {
+            Some(at) if std::time::Instant::now() >= at => {
+                self.flash_clear_at = None;
+                self.flash_suppressed = true;
+                self.render_dirty = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Builds the accessibility tree for the current document, with bounds taken from the active
+    /// layout. A DOM node that produced several layout boxes gets the union of their border
+    /// boxes. Returns `None` when no document is loaded.
+    pub fn accessibility_tree(&self) -> Option<AccessibilityTree> {
+        let doc = self.document.as_ref()?;
+
+        let mut bounds: std::collections::HashMap<NodeId, AccessBounds> = std::collections::HashMap::new();
+        if let Some(layer_list) = self.active_layer_list() {
+            for el in layer_list.layout_tree.arena.values() {
+                let b = el.box_model.border_box;
+                if b.width <= 0.0 && b.height <= 0.0 {
+                    continue;
+                }
+                bounds
+                    .entry(el.dom_node_id)
+                    .and_modify(|(x, y, w, h)| {
+                        let x1 = (*x + *w).max(b.x + b.width);
+                        let y1 = (*y + *h).max(b.y + b.height);
+                        *x = x.min(b.x);
+                        *y = y.min(b.y);
+                        *w = x1 - *x;
+                        *h = y1 - *y;
+                    })
+                    .or_insert((b.x, b.y, b.width, b.height));
+            }
+        }
+
+        Some(AccessibilityTree::build(doc, &bounds))
+    }
+
+    /// Describes `node` and its subtree for inspectors, or the whole document with `None`. Boxes
+    /// and computed values come from the active layout; a DOM node that produced several layout
+    /// boxes is described with its first. Returns `None` when no document is loaded.
+    pub fn describe_node(&self, node: Option<NodeId>) -> Option<NodeDesc> {
+        let doc = self.document.as_ref()?;
+        let layer_list = self.active_layer_list();
+
+        let mut boxes = std::collections::HashMap::new();
+        if let Some(layer_list) = layer_list {
+            let mut elements: Vec<&LayoutElementNode> = layer_list.layout_tree.arena.values().collect();
+            elements.sort_by_key(|el| el.id.as_u64());
+            for el in elements {
+                boxes.entry(el.dom_node_id).or_insert(el.box_model);
+            }
+        }
+        let styles = layer_list.map(|layer_list| layer_list.layout_tree.render_tree.doc.as_ref());
+
+        Some(NodeDesc::build(doc, styles, &boxes, node.unwrap_or_else(|| doc.root())))
+    }
+
+    /// Renders the whole document at the current viewport width and its full height, instead of
+    /// just the part in the viewport. Selection, inspector and debug overlays are left out.
+    /// Returns `None` when no document is loaded.
+    ///
+    /// `dpr` must be the device pixel ratio the tab's tiles are rasterized at; the pixmap is
+    /// assembled from the tile cache, which already covers the full page. Fails with
+    /// [`EngineError::CaptureTooLarge`] when the page wouldn't fit in one pixmap.
+    pub fn capture_full_page(&mut self, dpr: u32) -> Result<Option<FullPageCapture>, EngineError> {
+        let Some(doc) = self.document.clone() else {
+            return Ok(None);
+        };
+        self.apply_media_preferences();
+        let cache = pipeline_build_scene(
+            doc,
+            &self.viewport,
+            self.rasterizer.as_deref(),
+            self.media_store.clone(),
+            &SelectionRanges::default(),
+            &self.element_scroll,
+            &self.frames,
+            None,
+            PaintDebug::default(),
+        );
+        let pixels = self.full_page_pixels(dpr)?;
+        Ok(Some(FullPageCapture {
+            width: self.viewport.width as f64,
+            height: cache.scene.page_height,
+            scene: cache.scene,
+            pixels,
+        }))
+    }
+
+    /// Composites every cached CPU tile onto the clear color in one page-sized buffer.
+    fn full_page_pixels(&mut self, dpr: u32) -> Result<Option<FullPagePixmap>, EngineError> {
+        if self.raster_strategy == RasterStrategy::None {
+            return Ok(None);
+        }
+        self.rebuild_pipeline_cache_if_needed();
+        let Some(cache) = self.pipeline_cache.as_ref() else {
+            return Ok(None);
+        };
+        if cache.cached_tiles.is_empty() {
+            return Ok(None);
+        }
+
+        let (width, height) = full_page_size(self.viewport.width, cache.page_height, dpr)?;
+        let clear = parse_clear_color(&self.config_store.get_string("renderer.clear_color"));
+        let channel = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u32;
+        let background = 0xFF00_0000 | (channel(clear.r) << 16) | (channel(clear.g) << 8) | channel(clear.b);
+
+        let mut buf = vec![background; width * height];
+        let mut target = TileTarget {
+            buf: &mut buf,
+            stride: width,
+            origin_x: 0,
+            origin_y: 0,
+            width,
+            height,
+        };
+        composite_tiles(&cache.cached_tiles, dpr, (0.0, 0.0), &mut target);
+
+        Ok(Some(FullPagePixmap {
+            width: width as u32,
+            height: height as u32,
+            rgba: argb_u32_to_rgba8(&buf),
+        }))
+    }
+
+    /// Lays the current document out for printing on `setup`-sized paper and returns one paint
+    /// scene per page. The document is laid out at the width of the printable area and cut at
+    /// page boundaries chosen by `break-before` / `break-after` / `break-inside`. The on-screen
+    /// layout is left untouched. Returns an empty list when no document is loaded.
+    pub fn print_pages(&self, setup: PageSetup) -> Vec<PrintPage> {
+        let Some(doc) = &self.document else {
+            return Vec::new();
+        };
+        self.apply_media_preferences();
+        // The page area is the print layout's own viewport; the on-screen one is left as it is.
+        let (page_w, page_h) = (setup.content_width() as f32, setup.content_height() as f32);
+        gosub_css3::stylesheet::with_layout_viewport(page_w, page_h, || {
+            pipeline_build_print(doc.clone(), setup, self.rasterizer.as_deref(), self.media_store.clone())
+        })
+    }
+
     /// Hit-test at viewport coordinates `(vp_x, vp_y)` and update hover state.
     ///
     /// Returns `(visual_dirty, url_changed, link_url)`:
     /// - `visual_dirty`: a node with a `:hover` CSS rule entered or left the hover chain → needs repaint.
     /// - `url_changed`: the link URL under the cursor changed → caller should emit a `HoverUrl` event.
-    /// - `link_url`: the href of the nearest `<a>` ancestor, if any.
+    /// - `link_url`: the href of the nearest `<a>` ancestor, resolved to an absolute URL, if any.
     pub fn update_hover(&mut self, vp_x: f64, vp_y: f64) -> (bool, bool, Option<String>) {
         let _t_total = gosub_shared::timing_guard!("hover.total");
+        self.pointer = Some((vp_x, vp_y));
 
-        let (scroll_x, scroll_y) = (self.scroll_x, self.scroll_y);
-
-        let (new_leaf, new_lei) = self.active_layer_list().map_or((None, None), |layer_list| {
+        let (new_leaf, new_lei) = {
             let _t = gosub_shared::timing_guard!("hover.hit_test");
-            // find_element_at handles scroll per-layer (fixed layers ignore it).
-            let Some(lei) = layer_list.find_element_at(vp_x, vp_y, scroll_x, scroll_y) else {
-                return (None, None);
-            };
-            let dom_node_id = layer_list.layout_tree.get_node_by_id(lei).map(|el| el.dom_node_id);
-            (dom_node_id, Some(lei))
-        });
+            match self.element_at(vp_x, vp_y) {
+                Some((node_id, lei)) => (Some(node_id), Some(lei)),
+                None => (None, None),
+            }
+        };
 
         // Common case: same element - skip the ancestor walk entirely.
         if new_leaf == self.hover_leaf {
             return (false, false, self.hover_link_url.clone());
         }
 
-        self.hover_old_lei = self.hover_layout_element;
-
-        // Collect old and new ancestor chains - only these nodes need CSS cache invalidation.
-        self.hover_dirty_nodes.clear();
-        if let Some(doc) = &self.document {
-            let mut seen = std::collections::HashSet::new();
-            for start in [self.hover_leaf, new_leaf].into_iter().flatten() {
-                let mut id = start;
-                loop {
-                    if seen.insert(id) {
-                        self.hover_dirty_nodes.push(id);
-                    }
-                    match doc.parent(id) {
-                        Some(p) => id = p,
-                        None => break,
-                    }
-                }
-            }
-        }
-
+        let old_leaf = self.hover_leaf;
         self.hover_leaf = new_leaf;
         self.hover_layout_element = new_lei;
 
-        // Build hover fingerprints lazily on first use after a document load.
-        let fps = self.hover_fingerprints.get_or_insert_with(|| {
-            self.document
-                .as_ref()
-                .map(|doc| <C::CssSystem as CssSystem>::hover_fingerprints(doc.stylesheets()))
-                .unwrap_or_default()
-        });
-
-        // Walk the ancestor chain once for both link detection and fingerprint matching.
-        // Terminate early once both are found.
-        let (link_url, new_sensitive) = {
+        // Walk the ancestor chain for the nearest link.
+        let link_url = {
             let mut link: Option<String> = None;
-            let mut sensitive = false;
-
             if let (Some(leaf), Some(doc)) = (new_leaf, self.document.as_ref()) {
                 let _t = gosub_shared::timing_guard!("hover.ancestor_walk");
-                let mut id = leaf;
-                loop {
-                    if !sensitive && hover_matches(fps, doc, id) {
-                        sensitive = true;
-                    }
-                    if link.is_none() && doc.tag_name(id) == Some("a") {
-                        if let Some(href) = doc.attribute(id, "href") {
-                            link = Some(href.to_string());
+                let mut id = Some(leaf);
+                while let Some(node) = id {
+                    if matches!(doc.tag_name(node), Some("a") | Some("area")) {
+                        if let Some(href) = doc.attribute(node, "href") {
+                            link = Some(resolve_against_document(doc, href));
+                            break;
                         }
                     }
-                    if sensitive && link.is_some() {
-                        break;
-                    }
-                    match doc.parent(id) {
-                        Some(parent) => id = parent,
-                        None => break,
-                    }
+                    id = doc.parent(node);
                 }
             }
-            (link, sensitive)
+            link
         };
 
         let url_changed = link_url != self.hover_link_url;
         self.hover_link_url = link_url.clone();
 
-        // Only trigger a style recalc + repaint when a hover-sensitive node entered or left
-        // the hover chain. If neither the old nor new chain touches a :hover rule, skip it.
-        let visual_dirty = self.hover_chain_sensitive || new_sensitive;
-        self.hover_chain_sensitive = new_sensitive;
+        if let Some(doc) = &self.document {
+            let _t = gosub_shared::timing_guard!("hover.set_hovered");
+            doc.set_hovered_nodes(new_leaf);
+        }
+        let visual_dirty = self.queue_pointer_restyle(old_leaf, new_leaf);
 
-        if visual_dirty {
-            if let Some(doc) = &self.document {
-                let _t = gosub_shared::timing_guard!("hover.set_hovered");
-                doc.set_hovered_nodes(new_leaf);
+        (visual_dirty, url_changed, link_url)
+    }
+
+    /// Makes the element at viewport `(vp_x, vp_y)` and its ancestors `:active`, as on a press of
+    /// the primary button. Returns true when that needs a repaint.
+    pub fn press_at(&mut self, vp_x: f64, vp_y: f64) -> bool {
+        let leaf = self.element_at(vp_x, vp_y).map(|(node_id, _)| node_id);
+        self.set_active(leaf)
+    }
+
+    /// Ends `:active` when the primary button is released. Returns true when that needs a repaint.
+    pub fn release_active(&mut self) -> bool {
+        self.set_active(None)
+    }
+
+    fn set_active(&mut self, leaf: Option<NodeId>) -> bool {
+        if leaf == self.active_leaf {
+            return false;
+        }
+        let old_leaf = std::mem::replace(&mut self.active_leaf, leaf);
+        if let Some(doc) = &self.document {
+            doc.set_active_nodes(leaf);
+        }
+        self.queue_pointer_restyle(old_leaf, leaf)
+    }
+
+    /// Queues the restyle for a `:hover` or `:active` change from the ancestor chain of `old` to
+    /// that of `new`. Only nodes that left or entered the chain change state, and of those only
+    /// the ones a rule targets (per the fingerprints) restyle - along with their subtrees, which
+    /// inherit from them and may be reached through descendant selectors. When a sibling
+    /// combinator follows a `:hover` compound, the parent's subtree restyles instead. Returns
+    /// true when something was queued.
+    fn queue_pointer_restyle(&mut self, old: Option<NodeId>, new: Option<NodeId>) -> bool {
+        let Some(doc) = self.document.clone() else {
+            return false;
+        };
+        let chain = |leaf: Option<NodeId>| {
+            let mut chain = Vec::new();
+            let mut id = leaf;
+            while let Some(node) = id {
+                chain.push(node);
+                id = doc.parent(node);
             }
-            // Hover-only changes are paint-only (color, background, box-shadow).
-            // Use the cheap hover-dirty path which skips render-tree + layout.
-            self.hover_dirty = true;
+            chain
+        };
+        let (old_chain, new_chain) = (chain(old), chain(new));
+
+        // Build hover fingerprints lazily on first use after a document load.
+        let fps = &*self
+            .hover_fingerprints
+            .get_or_insert_with(|| <C::CssSystem as CssSystem>::hover_fingerprints(doc.stylesheets()));
+        let mut roots = restyle_roots(&old_chain, &new_chain, |id| hover_matches(fps, &doc, id));
+        if fps.has_sibling_combinator {
+            // `a:hover + p` restyles siblings of the node that flipped; its parent's subtree (and
+            // box, for the repaint) holds them.
+            for root in &mut roots {
+                *root = doc.parent(*root).unwrap_or(*root);
+            }
+            roots.dedup();
         }
 
-        (visual_dirty, url_changed, link_url)
+        for root in &roots {
+            let mut stack = vec![*root];
+            while let Some(id) = stack.pop() {
+                self.hover_dirty_nodes.push(id);
+                stack.extend_from_slice(doc.children(id));
+            }
+            let lei = self
+                .active_layer_list()
+                .and_then(|layer_list| layout_element_of(layer_list, Some(*root)));
+            match lei {
+                Some(lei) => self.hover_repaint.push(lei),
+                // Without a box to bound the repaint, repaint everything.
+                None => self.render_dirty = true,
+            }
+        }
+        if roots.is_empty() {
+            return false;
+        }
+        // Hover-only changes are paint-only (color, background, box-shadow).
+        // Use the cheap hover-dirty path which skips render-tree + layout.
+        self.hover_dirty = true;
+        true
+    }
+
+    /// True when the root element asks for `scroll-behavior: smooth`, which makes programmatic
+    /// scrolls without an explicit behavior animate.
+    pub fn prefers_smooth_scroll(&self) -> bool {
+        let Some(layer_list) = self.active_layer_list() else {
+            return false;
+        };
+        let doc = &layer_list.layout_tree.render_tree.doc;
+        doc.root().is_some_and(|root| {
+            matches!(doc.get_style(root, &StyleProperty::ScrollBehavior), Value::Keyword(kw) if lookup(kw) == "smooth")
+        })
+    }
+
+    /// Resolves the CSS `cursor` for the element under the pointer (as last set by
+    /// [`Self::update_hover`]). Returns the new cursor when it differs from the one last
+    /// returned, so the caller only notifies the UA on changes.
+    pub fn update_cursor(&mut self) -> Option<CursorIcon> {
+        let key = (self.hover_layout_element, self.layout_epoch);
+        if self.hover_cursor_key == Some(key) {
+            return None;
+        }
+        self.hover_cursor_key = Some(key);
+
+        let el = self
+            .hover_layout_element
+            .zip(self.active_layer_list())
+            .and_then(|(lei, layer_list)| Some((layer_list.layout_tree.get_node_by_id(lei)?, layer_list)));
+        let cursor = match el {
+            Some((el, layer_list)) => {
+                let css = match layer_list
+                    .layout_tree
+                    .render_tree
+                    .doc
+                    .get_style(el.dom_node_id, &StyleProperty::Cursor)
+                {
+                    Value::Keyword(kw) => CursorIcon::from_css(&lookup(kw)),
+                    _ => None,
+                };
+                // `auto`: a hand over links, an I-beam over text, the arrow elsewhere.
+                css.unwrap_or(if self.hover_link_url.is_some() {
+                    CursorIcon::Pointer
+                } else if matches!(el.context, ElementContext::Text(_)) {
+                    CursorIcon::Text
+                } else {
+                    CursorIcon::Default
+                })
+            }
+            None => CursorIcon::Default,
+        };
+
+        if cursor == self.hover_cursor {
+            return None;
+        }
+        self.hover_cursor = cursor;
+        Some(cursor)
+    }
+
+    /// Laid-out text elements of the current page in document order (layout tree pre-order),
+    /// for mapping the pointer to text boundary points.
+    fn text_fragments(&self) -> Vec<TextFragment> {
+        let Some(layer_list) = self.active_layer_list() else {
+            return Vec::new();
+        };
+        let tree = &layer_list.layout_tree;
+
+        let mut fragments = Vec::new();
+        let mut stack = vec![tree.root_id];
+        while let Some(id) = stack.pop() {
+            let Some(el) = tree.get_node_by_id(id) else {
+                continue;
+            };
+            if let ElementContext::Text(text) = &el.context {
+                fragments.push(TextFragment {
+                    node_id: text.node_id,
+                    text: text.text.clone(),
+                    lines: TextLines::new(
+                        el.box_model.content_box,
+                        text.font_info.line_height,
+                        text.text.chars().count(),
+                    ),
+                });
+            }
+            stack.extend(el.children.iter().rev());
+        }
+        fragments
+    }
+
+    /// Stores the painter-facing ranges for the current selection, invalidating the render only
+    /// when they actually changed. Returns true when a repaint is needed.
+    fn sync_selection_ranges(&mut self, fragments: &[TextFragment]) -> bool {
+        let ranges = self
+            .selection
+            .range()
+            .map(|range| range.ranges(fragments))
+            .unwrap_or_default();
+        if ranges == self.selection_ranges {
+            return false;
+        }
+        self.selection_ranges = ranges;
+        self.invalidate_render();
+        true
+    }
+
+    /// Starts a drag selection at viewport coordinates `(vp_x, vp_y)`, dropping any existing
+    /// selection. Returns true when the highlight changed and a repaint is needed.
+    pub fn begin_selection(&mut self, vp_x: f64, vp_y: f64) -> bool {
+        let fragments = self.text_fragments();
+        let anchor = boundary_at(&fragments, vp_x + self.scroll_x, vp_y + self.scroll_y);
+        self.selection.begin(anchor);
+        self.sync_selection_ranges(&fragments)
+    }
+
+    /// Extends an in-progress drag selection to `(vp_x, vp_y)`. Returns true when the highlight
+    /// changed and a repaint is needed; a no-op when no drag is active.
+    pub fn extend_selection(&mut self, vp_x: f64, vp_y: f64) -> bool {
+        if !self.selection.is_dragging() {
+            return false;
+        }
+        let fragments = self.text_fragments();
+        let Some(focus) = boundary_at(&fragments, vp_x + self.scroll_x, vp_y + self.scroll_y) else {
+            return false;
+        };
+        if !self.selection.extend(focus, &fragments) {
+            return false;
+        }
+        self.sync_selection_ranges(&fragments)
+    }
+
+    /// Finishes the drag; the selection itself stays until cleared or a new drag starts.
+    pub fn end_selection(&mut self) {
+        self.selection.end();
+    }
+
+    /// Drops the current selection. Returns true when a repaint is needed.
+    pub fn clear_selection(&mut self) -> bool {
+        self.selection.clear();
+        self.sync_selection_ranges(&[])
+    }
+
+    /// The currently selected text, or `None` when nothing is selected.
+    pub fn selected_text(&self) -> Option<String> {
+        let range = self.selection.range()?;
+        let text = range.to_text(&self.text_fragments());
+        (!text.is_empty()).then_some(text)
     }
 
     /// Returns the render list
@@ -669,11 +1849,17 @@ impl<C: RenderConfiguration> RenderContext for BrowsingContext<C> {
 /// GPU-scene build: stages 1–3 (render tree → layout → layering) plus a paint pass over every
 /// element, producing one ordered paint-command list for the whole page. Skips tiling,
 /// rasterization, and compositing - the backend renders the commands into a GPU texture.
+#[allow(clippy::too_many_arguments)]
 fn pipeline_build_scene<C: RenderConfiguration>(
     doc: Arc<EngineDocument<C>>,
     viewport: &Viewport,
     rasterizer: Option<&(dyn Rasterable + Send + Sync)>,
     media_store: Arc<gosub_render_pipeline::common::media::MediaStore>,
+    selection: &SelectionRanges,
+    element_scroll: &ScrollOffsets,
+    frames: &FrameContents,
+    inspected: Option<NodeId>,
+    paint_debug: PaintDebug,
 ) -> SceneCache {
     use gosub_render_pipeline::common::browser_state::{BrowserState, WireframeState};
     use gosub_render_pipeline::common::document::pipeline_doc::GosubDocumentAdapter;
@@ -705,7 +1891,8 @@ fn pipeline_build_scene<C: RenderConfiguration>(
         None => TaffyLayouter::new(),
     };
     layouter.set_media_store(Arc::clone(&media_store));
-    let layout_tree = layouter.layout(render_tree, vp_dim, 1.0);
+    let mut layout_tree = layouter.layout(render_tree, vp_dim, 1.0);
+    apply_scroll_offsets(&mut layout_tree, element_scroll);
     let page_height = layout_tree.root_dimension.height;
 
     // Stage 3: layering
@@ -720,13 +1907,19 @@ fn pipeline_build_scene<C: RenderConfiguration>(
         wireframed: WireframeState::None,
         debug_hover: false,
         current_hovered_element: None,
+        inspected_element: layout_element_of(&layer_list, inspected),
+        paint_debug,
+        damage: vec![full_page_rect],
+        selection: selection.clone(),
         show_tilegrid: false,
         debug_table_cells: std::env::var("GOSUB_DEBUG_TABLE_CELLS").is_ok(),
         viewport: full_page_rect,
         tile_list: None,
         dpi_scale_factor: 1.0,
     };
-    let painter = Painter::new(Arc::clone(&layer_list), rasterizer.and_then(|r| r.font_system()));
+    let painter = Painter::new(Arc::clone(&layer_list), rasterizer.and_then(|r| r.font_system()))
+        .with_frames(frames.clone())
+        .with_media_store(Arc::clone(&media_store));
     let commands = painter.paint_all(&state);
 
     SceneCache {
@@ -739,11 +1932,142 @@ fn pipeline_build_scene<C: RenderConfiguration>(
     }
 }
 
+/// Print build: stages 1–3 at the printable width of `setup`, then one paint pass per page over
+/// just the elements on that page. Viewport units resolve against whatever viewport the caller
+/// set up, which for print is the page area, as in CSS paged media.
+fn pipeline_build_print<C: RenderConfiguration>(
+    doc: Arc<EngineDocument<C>>,
+    setup: PageSetup,
+    rasterizer: Option<&(dyn Rasterable + Send + Sync)>,
+    media_store: Arc<gosub_render_pipeline::common::media::MediaStore>,
+) -> Vec<PrintPage> {
+    use gosub_render_pipeline::common::browser_state::{BrowserState, WireframeState};
+    use gosub_render_pipeline::common::document::pipeline_doc::GosubDocumentAdapter;
+    use gosub_render_pipeline::common::geo::{Dimension as PipelineDimension, Rect as PipelineRect};
+    use gosub_render_pipeline::layouter::taffy::TaffyLayouter;
+    use gosub_render_pipeline::layouter::CanLayout;
+    use gosub_render_pipeline::paginator::{break_boxes, paginate};
+    use gosub_render_pipeline::rendertree_builder::RenderTree;
+
+    let (page_w, page_h) = (setup.content_width(), setup.content_height());
+
+    let adapter = GosubDocumentAdapter::<C>::new(doc);
+    let mut render_tree = RenderTree::new(Arc::new(adapter));
+    if let Err(e) = render_tree.parse() {
+        log::error!("Failed to build render tree: {e}");
+    }
+
+    let mut layouter = match rasterizer.and_then(|r| r.font_system()) {
+        Some(font_system) => TaffyLayouter::with_font_system(font_system),
+        None => TaffyLayouter::new(),
+    };
+    layouter.set_media_store(Arc::clone(&media_store));
+    let layout_tree = layouter.layout(render_tree, Some(PipelineDimension::new(page_w, page_h)), 1.0);
+    let doc_height = layout_tree.root_dimension.height;
+    let bands = paginate(&break_boxes(&layout_tree), doc_height, page_h);
+
+    let layer_list = Arc::new(LayerList::new(layout_tree));
+    let layer_count = layer_list.layer_ids.read().len();
+    let painter = Painter::new(Arc::clone(&layer_list), rasterizer.and_then(|r| r.font_system()))
+        .with_media_store(Arc::clone(&media_store));
+
+    bands
+        .into_iter()
+        .enumerate()
+        .map(|(index, (top, bottom))| {
+            let band = PipelineRect::new(0.0, top, page_w, bottom - top);
+            let state = BrowserState {
+                visible_layer_list: vec![true; layer_count],
+                wireframed: WireframeState::None,
+                debug_hover: false,
+                current_hovered_element: None,
+                inspected_element: None,
+                paint_debug: PaintDebug::default(),
+                damage: Vec::new(),
+                selection: SelectionRanges::default(),
+                show_tilegrid: false,
+                debug_table_cells: false,
+                viewport: band,
+                tile_list: None,
+                dpi_scale_factor: 1.0,
+            };
+            PrintPage {
+                index,
+                band,
+                setup,
+                scene: PaintScene {
+                    commands: painter.paint_region(band, &state),
+                    media_store: Arc::clone(&media_store),
+                    page_height: doc_height,
+                },
+            }
+        })
+        .collect()
+}
+
+/// Largest side of a full-page capture in device pixels, the image size limit of the CPU
+/// backends (Cairo, Skia).
+const MAX_CAPTURE_DIMENSION: u64 = 32_767;
+
+/// Most device pixels in a full-page capture. The page is composited into an ARGB buffer and
+/// then copied to RGBA, so this caps the capture at about 1 GiB.
+const MAX_CAPTURE_PIXELS: u64 = 128 * 1024 * 1024;
+
+/// Device-pixel size of a full-page capture of a `viewport_width` wide page that is
+/// `page_height` CSS pixels tall, or [`EngineError::CaptureTooLarge`] when it is over
+/// [`MAX_CAPTURE_DIMENSION`] on either side or over [`MAX_CAPTURE_PIXELS`] in total.
+fn full_page_size(viewport_width: u32, page_height: f64, dpr: u32) -> Result<(usize, usize), EngineError> {
+    let width = viewport_width as u64 * dpr as u64;
+    // Saturates for absurd (or infinite) heights, which the checks below then reject.
+    let height = (page_height * dpr as f64).ceil().max(0.0) as u64;
+    if width > MAX_CAPTURE_DIMENSION
+        || height > MAX_CAPTURE_DIMENSION
+        || width.saturating_mul(height) > MAX_CAPTURE_PIXELS
+    {
+        return Err(EngineError::CaptureTooLarge { width, height });
+    }
+    Ok((width as usize, height as usize))
+}
+
+/// How long repaint flashing keeps a repainted region highlighted.
+const PAINT_FLASH_DURATION: std::time::Duration = std::time::Duration::from_millis(300);
+
+/// First layout element generated for DOM node `node`, for the inspector overlay.
+fn layout_element_of(layer_list: &LayerList, node: Option<NodeId>) -> Option<LayoutElementId> {
+    let node = node?;
+    layer_list
+        .layout_tree
+        .arena
+        .values()
+        .filter(|el| el.dom_node_id == node)
+        .map(|el| el.id)
+        .min_by_key(|id| id.as_u64())
+}
+
+/// The subtrees to restyle when the pointer state (`:hover` or `:active`) moves from one
+/// ancestor chain to another, both listed leaf first. Nodes the chains share keep their state;
+/// of the rest, each chain contributes the `targeted` node nearest the root, whose subtree holds
+/// every node it restyles.
+fn restyle_roots(old_chain: &[NodeId], new_chain: &[NodeId], targeted: impl Fn(NodeId) -> bool) -> Vec<NodeId> {
+    [(old_chain, new_chain), (new_chain, old_chain)]
+        .into_iter()
+        .filter_map(|(chain, other)| {
+            chain
+                .iter()
+                .copied()
+                .take_while(|id| !other.contains(id))
+                .filter(|&id| targeted(id))
+                .last()
+        })
+        .collect()
+}
+
 /// Runs pipeline stages 1–6 for the **entire page** (all tiles, not just the viewport slice)
 /// and returns a `PipelineCache` of rasterized tiles ready for repeated compositing.
 ///
 /// Splitting the full pipeline from compositing lets scroll re-use the cached tiles without
 /// re-running layout or rasterization.
+#[allow(clippy::too_many_arguments)]
 fn pipeline_build_cache<C: RenderConfiguration>(
     doc: Arc<EngineDocument<C>>,
     viewport: &Viewport,
@@ -752,6 +2076,11 @@ fn pipeline_build_cache<C: RenderConfiguration>(
     prev_tile_cache: TilePixelCache,
     media_store: Arc<gosub_render_pipeline::common::media::MediaStore>,
     tile_size: f64,
+    selection: &SelectionRanges,
+    element_scroll: &ScrollOffsets,
+    frames: &FrameContents,
+    inspected: Option<NodeId>,
+    paint_debug: PaintDebug,
 ) -> PipelineCache {
     use gosub_render_pipeline::common::browser_state::{BrowserState, WireframeState};
     use gosub_render_pipeline::common::document::pipeline_doc::GosubDocumentAdapter;
@@ -798,7 +2127,10 @@ fn pipeline_build_cache<C: RenderConfiguration>(
     // Share the persistent media store so resources loaded during layout are visible to the
     // rasterizer (which resolves them by id). Otherwise every image renders as a placeholder.
     layouter.set_media_store(Arc::clone(&media_store));
-    let layout_tree = layouter.layout(render_tree, vp_dim, 1.0);
+    let mut layout_tree = layouter.layout(render_tree, vp_dim, 1.0);
+    // Element scroll containers scroll by moving their content, before anything downstream
+    // looks at positions.
+    apply_scroll_offsets(&mut layout_tree, element_scroll);
     timing_stop!(ts2);
     let page_height = layout_tree.root_dimension.height;
 
@@ -828,13 +2160,19 @@ fn pipeline_build_cache<C: RenderConfiguration>(
         wireframed: WireframeState::None,
         debug_hover: false,
         current_hovered_element: None,
+        inspected_element: layout_element_of(&tile_list.layer_list, inspected),
+        paint_debug,
+        damage: vec![full_page_rect],
+        selection: selection.clone(),
         show_tilegrid: false,
         debug_table_cells: std::env::var("GOSUB_DEBUG_TABLE_CELLS").is_ok(),
         viewport: full_page_rect,
         tile_list: None,
         dpi_scale_factor: 1.0,
     };
-    let painter = Painter::new(tile_list.layer_list.clone(), rasterizer.and_then(|r| r.font_system()));
+    let painter = Painter::new(tile_list.layer_list.clone(), rasterizer.and_then(|r| r.font_system()))
+        .with_frames(frames.clone())
+        .with_media_store(Arc::clone(&media_store));
     for &layer_id in &layer_ids {
         let tile_ids = tile_list.get_intersecting_tiles(layer_id, full_page_rect);
         for tile_id in tile_ids {
@@ -847,6 +2185,12 @@ fn pipeline_build_cache<C: RenderConfiguration>(
             for tiled_element in &mut tile.elements {
                 tiled_element.paint_commands = painter.paint(tiled_element, &paint_state);
             }
+            if paint_state.paint_debug.any() {
+                let overlay = painter.tile_debug_commands(tile.layer_id, &paint_state);
+                if let Some(last) = tile.elements.last_mut() {
+                    last.paint_commands.extend(overlay);
+                }
+            }
         }
     }
     timing_stop!(ts5);
@@ -886,7 +2230,7 @@ fn pipeline_build_cache<C: RenderConfiguration>(
 }
 
 /// Hover-only repaint: skip stages 1–2 (render-tree + layout), reuse the cached
-/// `LayerList`, and only repaint tiles that intersect the old or new hovered element.
+/// `LayerList`, and only repaint tiles that intersect the elements in `repaint`.
 /// All other tiles are carried over from `prev_baked_tiles` unchanged - no CSS
 /// re-evaluation, no re-rasterization.
 #[allow(clippy::too_many_arguments)]
@@ -894,8 +2238,7 @@ fn pipeline_hover_repaint(
     layer_list: Arc<gosub_render_pipeline::layering::layer::LayerList>,
     page_height: f64,
     prev_baked_tiles: Vec<BakedTile>,
-    old_hover_lei: Option<LayoutElementId>,
-    new_hover_lei: Option<LayoutElementId>,
+    repaint: &[LayoutElementId],
     hover_dirty_nodes: &[NodeId],
     viewport: &gosub_render_pipeline::render::Viewport,
     rasterizer: Option<&(dyn Rasterable + Send + Sync)>,
@@ -903,6 +2246,10 @@ fn pipeline_hover_repaint(
     prev_tile_cache: TilePixelCache,
     media_store: Arc<gosub_render_pipeline::common::media::MediaStore>,
     tile_size: f64,
+    selection: &SelectionRanges,
+    frames: &FrameContents,
+    inspected: Option<NodeId>,
+    paint_debug: PaintDebug,
 ) -> PipelineCache {
     use gosub_render_pipeline::common::browser_state::{BrowserState, WireframeState};
     use gosub_render_pipeline::common::geo::{Dimension as PipelineDimension, Rect as PipelineRect};
@@ -928,11 +2275,11 @@ fn pipeline_hover_repaint(
         .map(|t| ((t.page_x.to_bits(), t.page_y.to_bits(), t.layer_id), t))
         .collect();
 
-    // Compute the union bounding box of old and new hovered elements.  Tiles that
+    // Compute the union bounding box of the restyled elements.  Tiles that
     // don't intersect this region cannot have changed visually, so we skip them.
     let hover_rect: Option<PipelineRect> = {
         let mut union: Option<PipelineRect> = None;
-        for lei in [old_hover_lei, new_hover_lei].into_iter().flatten() {
+        for &lei in repaint {
             if let Some(el) = layer_list.layout_tree.get_node_by_id(lei) {
                 let m = el.box_model.margin_box;
                 let r = PipelineRect::new(m.x, m.y, m.width, m.height);
@@ -969,9 +2316,9 @@ fn pipeline_hover_repaint(
                 && tile_rect.y < hover_rect.y + hover_rect.height
                 && tile_rect.y + tile_rect.height > hover_rect.y;
             if overlaps {
-                // Invalidate cached styles only for the hover-chain nodes (old + new ancestors).
-                // Non-hover elements in this tile keep their cached CSS - only the nodes that
-                // actually gained or lost :hover need re-evaluation.
+                // Invalidate cached styles only for the restyled nodes. Other elements in this
+                // tile keep their cached CSS - only the subtrees of nodes that actually gained or
+                // lost a targeted :hover/:active need re-evaluation.
                 doc.invalidate_style_for_nodes(hover_dirty_nodes);
                 continue;
             }
@@ -1005,13 +2352,19 @@ fn pipeline_hover_repaint(
         wireframed: WireframeState::None,
         debug_hover: false,
         current_hovered_element: None,
+        inspected_element: layout_element_of(&tile_list.layer_list, inspected),
+        paint_debug,
+        damage: hover_rect.into_iter().collect(),
+        selection: selection.clone(),
         show_tilegrid: false,
         debug_table_cells: std::env::var("GOSUB_DEBUG_TABLE_CELLS").is_ok(),
         viewport: full_page_rect,
         tile_list: None,
         dpi_scale_factor: 1.0,
     };
-    let painter = Painter::new(tile_list.layer_list.clone(), rasterizer.and_then(|r| r.font_system()));
+    let painter = Painter::new(tile_list.layer_list.clone(), rasterizer.and_then(|r| r.font_system()))
+        .with_frames(frames.clone())
+        .with_media_store(Arc::clone(&media_store));
     for &layer_id in &layer_ids {
         let tile_ids = tile_list.get_intersecting_tiles(layer_id, full_page_rect);
         for tile_id in tile_ids {
@@ -1024,6 +2377,12 @@ fn pipeline_hover_repaint(
             for tiled_element in &mut tile.elements {
                 tiled_element.paint_commands = painter.paint(tiled_element, &paint_state);
             }
+            if paint_state.paint_debug.any() {
+                let overlay = painter.tile_debug_commands(tile.layer_id, &paint_state);
+                if let Some(last) = tile.elements.last_mut() {
+                    last.paint_commands.extend(overlay);
+                }
+            }
         }
     }
     timing_stop!(ts5);
@@ -1133,7 +2492,48 @@ fn pipeline_composite(cache: &PipelineCache, scroll_x: f64, scroll_y: f64, vp_w:
 
 #[cfg(test)]
 mod tests {
-    use super::parse_clear_color;
+    use super::{
+        full_page_size, layout_element_of, parse_clear_color, restyle_roots, BrowsingContext, EngineError, Viewport,
+        MAX_CAPTURE_DIMENSION,
+    };
+    use crate::engine::default_settings;
+    use crate::html::testing::parse;
+    use crate::html::DefaultRenderConfig;
+    use gosub_css3::system::Css3System;
+    use gosub_interface::css3::CssSystem as _;
+    use gosub_interface::document::Document as _;
+    use gosub_shared::node::NodeId;
+    use std::sync::Arc;
+
+    /// A context with `html` laid out in a 400×300 viewport.
+    fn laid_out(html: &str) -> BrowsingContext<DefaultRenderConfig> {
+        let mut doc = parse(html);
+        doc.add_stylesheet(Css3System::load_default_useragent_stylesheet());
+        let mut context = BrowsingContext::new(default_settings());
+        context.set_viewport(Viewport::new(0, 0, 400, 300));
+        context.set_document(Arc::new(doc));
+        context.rebuild_scene_cache_if_needed();
+        context
+    }
+
+    /// The `id` (else the tag name) of the node hit at viewport point `(x, y)`.
+    fn hit(context: &BrowsingContext<DefaultRenderConfig>, x: f64, y: f64) -> Option<String> {
+        let node = context.hit_test(x, y)?.node_id;
+        let doc = context.document()?;
+        doc.attribute(node, "id").or(doc.tag_name(node)).map(str::to_string)
+    }
+
+    fn by_id(context: &BrowsingContext<DefaultRenderConfig>, id: &str) -> NodeId {
+        let doc = context.document().unwrap();
+        let mut stack = vec![doc.root()];
+        while let Some(node) = stack.pop() {
+            if doc.attribute(node, "id") == Some(id) {
+                return node;
+            }
+            stack.extend_from_slice(doc.children(node));
+        }
+        panic!("no element #{id}");
+    }
 
     #[test]
     fn parse_clear_color_handles_rgb_rgba_and_garbage() {
@@ -1153,4 +2553,136 @@ mod tests {
         let c = parse_clear_color("not-a-color");
         assert_eq!((c.r, c.g, c.b, c.a), (1.0, 1.0, 1.0, 1.0));
     }
+
+    #[test]
+    fn full_page_size_is_capped() {
+        assert_eq!(full_page_size(800, 1200.5, 2).unwrap(), (1600, 2401));
+
+        // One side over the backend limit, though the area would fit.
+        let tall = MAX_CAPTURE_DIMENSION as f64 + 1.0;
+        assert!(matches!(
+            full_page_size(10, tall, 1),
+            Err(EngineError::CaptureTooLarge { width: 10, .. })
+        ));
+
+        // Both sides within the limit, but too many pixels in total.
+        assert!(matches!(
+            full_page_size(20_000, 20_000.0, 1),
+            Err(EngineError::CaptureTooLarge { .. })
+        ));
+
+        assert!(full_page_size(800, f64::INFINITY, 1).is_err());
+    }
+
+    #[test]
+    fn restyle_roots_cover_only_nodes_whose_state_flipped() {
+        let id = |n: usize| NodeId::from(n);
+        // html(1) > body(2) > nav(3) > a(4), with body(2) > main(5) > p(6)
+        let old_chain = [id(4), id(3), id(2), id(1)];
+        let new_chain = [id(6), id(5), id(2), id(1)];
+
+        // `a:hover` and `main:hover`: each chain restyles its own targeted node.
+        let roots = restyle_roots(&old_chain, &new_chain, |n| n == id(4) || n == id(5));
+        assert_eq!(roots, vec![id(4), id(5)]);
+
+        // `nav:hover` covers `a` too; `body:hover` doesn't flip, as body stays hovered.
+        let roots = restyle_roots(&old_chain, &new_chain, |n| n == id(2) || n == id(3) || n == id(4));
+        assert_eq!(roots, vec![id(3)]);
+
+        // Leaving the page: everything on the old chain flips.
+        let roots = restyle_roots(&old_chain, &[], |n| n == id(2) || n == id(4));
+        assert_eq!(roots, vec![id(2)]);
+
+        assert!(restyle_roots(&old_chain, &old_chain, |_| true).is_empty());
+    }
+
+    #[test]
+    fn hover_restyles_reach_siblings_through_sibling_combinators() {
+        let page = |css: &str| {
+            laid_out(&format!(
+                r#"<html><head><style>{css}</style></head>
+                <body><div id="menu"><a id="link">x</a><p id="next">y</p></div></body></html>"#
+            ))
+        };
+
+        let mut context = page("a:hover span { color: red }");
+        let (link, next) = (by_id(&context, "link"), by_id(&context, "next"));
+        assert!(context.queue_pointer_restyle(None, Some(link)));
+        assert!(context.hover_dirty_nodes.contains(&link));
+        assert!(!context.hover_dirty_nodes.contains(&next));
+
+        for css in ["a:hover + p { color: red }", "a:hover ~ p { color: red }"] {
+            let mut context = page(css);
+            let (menu, link, next) = (
+                by_id(&context, "menu"),
+                by_id(&context, "link"),
+                by_id(&context, "next"),
+            );
+            assert!(context.queue_pointer_restyle(None, Some(link)));
+            assert!(context.hover_dirty_nodes.contains(&next), "{css}");
+            // The repaint covers the parent's box, which holds the sibling.
+            let menu_box = layout_element_of(context.active_layer_list().unwrap(), Some(menu));
+            assert_eq!(context.hover_repaint, menu_box.into_iter().collect::<Vec<_>>(), "{css}");
+        }
+    }
+
+    #[test]
+    fn hit_test_picks_the_topmost_of_overlapping_boxes() {
+        let box_at = |id: &str, at: u32, z: &str| {
+            format!(
+                r#"<div id="{id}" style="position:absolute; left:{at}px; top:{at}px; width:150px; height:150px{z}"></div>"#
+            )
+        };
+
+        // Later in the document paints on top.
+        let context = laid_out(&format!(
+            r#"<body style="margin:0">{}{}</body>"#,
+            box_at("under", 0, ""),
+            box_at("over", 100, "")
+        ));
+        assert_eq!(hit(&context, 50.0, 50.0).as_deref(), Some("under"));
+        assert_eq!(hit(&context, 125.0, 125.0).as_deref(), Some("over"));
+        assert_eq!(hit(&context, 200.0, 200.0).as_deref(), Some("over"));
+
+        // Unless z-index says otherwise.
+        let context = laid_out(&format!(
+            r#"<body style="margin:0">{}{}</body>"#,
+            box_at("under", 0, "; z-index:2"),
+            box_at("over", 100, "; z-index:1")
+        ));
+        assert_eq!(hit(&context, 125.0, 125.0).as_deref(), Some("under"));
+        assert_eq!(hit(&context, 200.0, 200.0).as_deref(), Some("over"));
+    }
+
+    #[test]
+    fn hit_test_follows_scrolled_content() {
+        let mut context = laid_out(
+            r#"<body style="margin:0">
+                <div id="top" style="height:1000px"></div>
+                <div id="target" style="height:100px"></div>
+                <div style="height:1000px"></div>
+            </body>"#,
+        );
+        assert_eq!(hit(&context, 10.0, 50.0).as_deref(), Some("top"));
+
+        context.set_scroll(0.0, 950.0);
+        assert_eq!(hit(&context, 10.0, 50.0).as_deref(), Some("target"));
+        assert_eq!(hit(&context, 10.0, 40.0).as_deref(), Some("top"));
+        let result = context.hit_test(10.0, 50.0).unwrap();
+        // Bounds stay in page coordinates.
+        assert_eq!(result.bounds.1, 1000.0);
+    }
+
+    #[test]
+    fn hit_test_misses() {
+        // Nothing laid out yet.
+        let context = BrowsingContext::<DefaultRenderConfig>::new(default_settings());
+        assert!(context.hit_test(10.0, 10.0).is_none());
+
+        let context = laid_out(r#"<body style="margin:0"><div style="height:100px"></div></body>"#);
+        assert!(context.hit_test(10.0, 10.0).is_some());
+        assert!(context.hit_test(-5.0, 10.0).is_none());
+        assert!(context.hit_test(10.0, -5.0).is_none());
+        assert!(context.hit_test(500.0, 10.0).is_none());
+    }
 }
@@ -106,7 +106,7 @@ fn parse_http_date(s: &str) -> Option<i64> {
 /// Uses the compile-time embedded Mozilla Public Suffix List (`psl` crate) for
 /// accurate comparison. Falls back to exact hostname equality for IP addresses,
 /// `localhost`, and other labels not present in the PSL.
-fn same_site(host_a: &str, host_b: &str) -> bool {
+pub(crate) fn same_site(host_a: &str, host_b: &str) -> bool {
     let registrable = |host: &str| -> Option<String> {
         let d = psl::List.domain(host.as_bytes())?;
         std::str::from_utf8(d.as_bytes()).ok().map(str::to_owned)
@@ -162,6 +162,16 @@ pub enum SameSiteContext {
     CrossSite,
 }
 
+impl SameSiteContext {
+    /// The context of a subrequest for `url` made by the page at `top_level`.
+    pub fn for_subrequest(url: &Url, top_level: &Url) -> Self {
+        match (url.host_str(), top_level.host_str()) {
+            (Some(a), Some(b)) if same_site(a, b) => SameSiteContext::SameSite,
+            _ => SameSiteContext::CrossSite,
+        }
+    }
+}
+
 /// A cookie jar keeps the cookies for one single zone.
 ///
 /// Types implementing this trait should encapsulate storage, retrieval, and
@@ -50,13 +50,15 @@
 
 use crate::cookies::DefaultCookieJar;
 use crate::engine::cookies::store::CookieStore;
-use crate::engine::cookies::CookieJar;
+use crate::engine::cookies::{CookieJar, SameSiteContext};
 use crate::zone::ZoneId;
+use http::HeaderMap;
 use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 use std::ops::Deref;
 use std::sync::Arc;
+use url::Url;
 
 /// A handle to a cookie jar trait.
 ///
@@ -112,6 +114,23 @@ impl CookieJarHandle {
     pub fn write(&self) -> RwLockWriteGuard<'_, Box<dyn CookieJar + Send + Sync>> {
         self.0.write()
     }
+
+    /// Sets the `Cookie` header of a request for `url` in `headers` to the cookies the jar
+    /// sends with it. Leaves `headers` alone when there are none.
+    pub fn add_request_cookies(
+        &self,
+        headers: &mut HeaderMap,
+        url: &Url,
+        top_level: Option<&Url>,
+        samesite: SameSiteContext,
+    ) {
+        let Some(cookies) = self.read().get_request_cookies(url, top_level, samesite) else {
+            return;
+        };
+        if let Ok(value) = cookies.parse() {
+            headers.insert(http::header::COOKIE, value);
+        }
+    }
 }
 
 impl Deref for CookieJarHandle {
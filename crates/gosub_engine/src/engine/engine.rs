@@ -22,6 +22,7 @@
 
 use crate::cookies::CookieStoreHandle;
 use crate::engine::events::{EngineCommand, EngineEvent};
+use crate::engine::font_settings::{apply_cache_budget, apply_generic_families};
 use crate::engine::types::{EventChannel, IoChannel};
 use crate::engine::DEFAULT_CHANNEL_CAPACITY;
 use crate::html::RenderConfiguration;
@@ -127,17 +128,22 @@ impl<C: RenderConfiguration> GosubEngine<C> {
         // Broadcast event bus. Subscribe to receive engine events (including zone and tab events)
         let (event_tx, _first_rx) = broadcast::channel::<EngineEvent>(DEFAULT_CHANNEL_CAPACITY);
 
+        let config_store = crate::engine::settings_store::default_config();
+        let font_system = Arc::new(Mutex::new(C::FontSystem::default()));
+        apply_generic_families(&config_store, font_system.clone());
+        apply_cache_budget(&config_store, font_system.clone());
+
         Self {
             context: Arc::new(EngineContext {
                 event_tx: event_tx.clone(),
                 config: Arc::new(resolved_config),
-                config_store: crate::engine::settings_store::default_config(),
+                config_store,
                 io_tx: OnceLock::new(),
                 request_reference_map: Arc::new(RwLock::new(RequestReferenceMap::new())),
             }),
             render_backend: backend,
             compositor,
-            font_system: Arc::new(Mutex::new(C::FontSystem::default())),
+            font_system,
             zones: HashMap::new(),
             cookie_stores: HashMap::new(),
             cmd_tx,
@@ -50,6 +50,18 @@ pub enum EngineError {
     #[error("Zone already exists")]
     ZoneAlreadyExists,
 
+    /// A saved tab session could not be decoded
+    #[error("Invalid session state: {0}")]
+    InvalidSession(String),
+
+    /// A user script or style was registered with a malformed URL match pattern
+    #[error("Invalid URL pattern: {0}")]
+    InvalidUrlPattern(String),
+
+    /// A URL could not be parsed
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+
     /// An invalid configuration was provided for the engine or zone
     #[error("Invalid configuration: {0}")]
     InvalidConfiguration(String),
@@ -79,6 +91,10 @@ pub enum EngineError {
     /// A cookie/storage backing store failed to initialize.
     #[error("Cookie store error: {0}")]
     CookieStore(#[source] anyhow::Error),
+
+    /// A full-page capture would be bigger than one pixmap can hold (size in device pixels)
+    #[error("Full-page capture of {width}x{height} pixels is too large")]
+    CaptureTooLarge { width: u64, height: u64 },
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -92,6 +108,14 @@ pub enum NavigationError {
     #[error("io cancelled: {0}")]
     Cancelled(String),
 
+    /// A content blocking rule matched the request
+    #[error("blocked by filter rule: {0}")]
+    Blocked(String),
+
+    /// A secure document tried to load an insecure one into a frame
+    #[error("blocked mixed content: {0}")]
+    MixedContent(url::Url),
+
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
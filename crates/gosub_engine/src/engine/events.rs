@@ -8,23 +8,40 @@
 //!
 //! - [`MouseButton`]: Represents mouse buttons (left, middle, right).
 //! - [`Modifiers`]: Keyboard modifiers (Shift, Control, Alt, Meta).
+//! - [`CursorIcon`]: Mouse cursor shape requested by the page.
 //! - [`TabCommand`]: Commands for tab navigation and control.
 //! - [`EngineCommand`]: Commands for engine control.
 //! - [`EngineEvent`]: Events emitted by the engine, such as lifecycle events, rendering events, and errors.
 
 use crate::cookies::Cookie;
+use crate::engine::accessibility::AccessibilityTree;
+use crate::engine::context::{FullPageCapture, HitTestResult, SelectPopup};
+use crate::engine::favicon::Favicon;
+use crate::engine::node_desc::NodeDesc;
+use crate::engine::script::{
+    ClipboardAccess, ClipboardAnswer, ClipboardRequestId, ConsoleLevel, EvaluationId, LimitViolation, NotificationId,
+    NotificationRequest, PermissionRequestId, ScriptResult,
+};
 use crate::engine::types::{Action, NavigationId, RequestId};
+use crate::engine::user_content::{UserContentId, UserScript, UserStyle};
 use crate::net::req_ref_tracker::RequestReference;
 use crate::net::types::{FetchHandle, FetchRequest, FetchResult, FetchResultMeta, Initiator, Priority, ResourceKind};
 use crate::net::DecisionToken;
 use crate::storage::event::StorageScope;
-use crate::tab::TabId;
+use crate::storage::PartitionKey;
+use crate::tab::{SessionState, TabId};
 use crate::zone::ZoneId;
 use crate::EngineError;
 use bitflags::bitflags;
+use gosub_css3::media::ColorScheme;
+use gosub_render_pipeline::common::browser_state::PaintDebug;
+use gosub_render_pipeline::paginator::{PageSetup, PrintPage};
 use gosub_render_pipeline::render::backend::ExternalHandle;
 use gosub_render_pipeline::render::Viewport;
+use gosub_shared::node::NodeId;
+use gosub_web_platform::permissions::{PermissionName, PermissionState};
 use std::fmt::{Debug, Display, Formatter};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::oneshot;
@@ -86,6 +103,108 @@ impl Display for Modifiers {
     }
 }
 
+/// Mouse cursor shape requested by the page, from the CSS `cursor` property. `auto` never
+/// appears here: the engine resolves it to [`CursorIcon::Pointer`] over links,
+/// [`CursorIcon::Text`] over text and [`CursorIcon::Default`] elsewhere.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CursorIcon {
+    #[default]
+    Default,
+    /// `cursor: none` - hide the cursor
+    None,
+    ContextMenu,
+    Help,
+    Pointer,
+    Progress,
+    Wait,
+    Cell,
+    Crosshair,
+    Text,
+    VerticalText,
+    Alias,
+    Copy,
+    Move,
+    NoDrop,
+    NotAllowed,
+    Grab,
+    Grabbing,
+    AllScroll,
+    ColResize,
+    RowResize,
+    NResize,
+    EResize,
+    SResize,
+    WResize,
+    NeResize,
+    NwResize,
+    SeResize,
+    SwResize,
+    EwResize,
+    NsResize,
+    NeswResize,
+    NwseResize,
+    ZoomIn,
+    ZoomOut,
+}
+
+impl CursorIcon {
+    /// Parses a computed `cursor` value. Custom `url(...)` cursors are not supported, so only the
+    /// keyword fallback after the last comma counts. Returns `None` for `auto` and unknown values.
+    pub fn from_css(value: &str) -> Option<CursorIcon> {
+        let keyword = value.rsplit(',').next().unwrap_or(value).trim();
+        Some(match keyword {
+            "default" => CursorIcon::Default,
+            "none" => CursorIcon::None,
+            "context-menu" => CursorIcon::ContextMenu,
+            "help" => CursorIcon::Help,
+            "pointer" => CursorIcon::Pointer,
+            "progress" => CursorIcon::Progress,
+            "wait" => CursorIcon::Wait,
+            "cell" => CursorIcon::Cell,
+            "crosshair" => CursorIcon::Crosshair,
+            "text" => CursorIcon::Text,
+            "vertical-text" => CursorIcon::VerticalText,
+            "alias" => CursorIcon::Alias,
+            "copy" => CursorIcon::Copy,
+            "move" => CursorIcon::Move,
+            "no-drop" => CursorIcon::NoDrop,
+            "not-allowed" => CursorIcon::NotAllowed,
+            "grab" => CursorIcon::Grab,
+            "grabbing" => CursorIcon::Grabbing,
+            "all-scroll" => CursorIcon::AllScroll,
+            "col-resize" => CursorIcon::ColResize,
+            "row-resize" => CursorIcon::RowResize,
+            "n-resize" => CursorIcon::NResize,
+            "e-resize" => CursorIcon::EResize,
+            "s-resize" => CursorIcon::SResize,
+            "w-resize" => CursorIcon::WResize,
+            "ne-resize" => CursorIcon::NeResize,
+            "nw-resize" => CursorIcon::NwResize,
+            "se-resize" => CursorIcon::SeResize,
+            "sw-resize" => CursorIcon::SwResize,
+            "ew-resize" => CursorIcon::EwResize,
+            "ns-resize" => CursorIcon::NsResize,
+            "nesw-resize" => CursorIcon::NeswResize,
+            "nwse-resize" => CursorIcon::NwseResize,
+            "zoom-in" => CursorIcon::ZoomIn,
+            "zoom-out" => CursorIcon::ZoomOut,
+            _ => return None,
+        })
+    }
+}
+
+/// The `behavior` of a programmatic scroll, as in the DOM `ScrollToOptions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScrollToBehavior {
+    /// Follow the root element's CSS `scroll-behavior`
+    #[default]
+    Auto,
+    /// Jump straight to the target
+    Instant,
+    /// Animate to the target
+    Smooth,
+}
+
 // Commands sent to the IO / network layer
 #[derive(Debug)]
 #[allow(clippy::large_enum_variant)]
@@ -93,6 +212,8 @@ pub enum IoCommand {
     /// Perform a fetch of the given request
     Fetch {
         zone_id: ZoneId,
+        /// Storage partition of the requesting tab, which the HTTP cache keeps entries apart by
+        partition: PartitionKey,
         req: FetchRequest,
         handle: FetchHandle,
         reply_tx: oneshot::Sender<FetchResult>,
@@ -116,8 +237,19 @@ pub enum TabCommand {
     Navigate { url: String },
     /// Reload current URL (with or without cache)
     Reload { ignore_cache: bool },
+    /// Go back one entry in the tab's session history
+    GoBack,
+    /// Go forward one entry in the tab's session history
+    GoForward,
+    /// Snapshot the tab's session; it arrives as [`EngineEvent::SessionSaved`]
+    SaveSession,
+    /// Replace the tab's history with a saved session and load its current entry
+    RestoreSession { state: SessionState },
     /// Cancel the current navigation
     CancelNavigation,
+    /// Stop loading: abort the in-flight navigation (its fetch, parsing and the subresource
+    /// fetches the parser started) and any pending frame loads. The page on screen stays.
+    Stop,
     /// Make a decision what to do with the navigated resource
     SubmitDecision {
         nav_id: NavigationId,
@@ -135,11 +267,17 @@ pub enum TabCommand {
     SuspendDrawing,
     /// Set viewport
     SetViewport { x: i32, y: i32, width: u32, height: u32 },
+    /// Scroll the page to an absolute offset in CSS pixels (`window.scrollTo`)
+    ScrollTo { x: f32, y: f32, behavior: ScrollToBehavior },
 
     // ****************************************
     // ** Tab properties
     /// Set the title
     SetTitle { title: String },
+    /// Set the tab whose page opened this one, see [`EngineEvent::NewWindowRequested`]
+    SetOpener { opener: Option<TabId> },
+    /// Set the color scheme the UA uses, which pages see as `prefers-color-scheme`
+    SetColorScheme { scheme: ColorScheme },
 
     // ****************************************
     // ** User input
@@ -168,6 +306,47 @@ pub enum TabCommand {
     /// Char input (@TODO: Needed since we have TextInput)?
     CharInput { ch: char },
 
+    // ****************************************
+    // ** Editing
+    /// Copy the current text selection; the text is delivered as [`EngineEvent::ClipboardWrite`]
+    CopySelection,
+    /// Drop the current text selection
+    ClearSelection,
+    /// Describe what is at viewport point `(x, y)` (link, image, selection, editable field), for
+    /// building a context menu; the answer arrives as [`EngineEvent::HitTested`]
+    HitTest { x: f32, y: f32 },
+
+    // ****************************************
+    // ** Forms
+    /// Files picked for the file input `node_id`, answering [`EngineEvent::FileChooserRequested`].
+    /// They are uploaded when the form submits; an empty list clears the selection.
+    SetInputFiles { node_id: NodeId, files: Vec<PathBuf> },
+    /// The options the user chose in the drop-down of `<select>` `node_id`, answering
+    /// [`EngineEvent::SelectPopupRequested`]. Indices are into its `options`; a single-choice
+    /// select takes the last. Nothing is sent when the drop-down is dismissed.
+    ChooseSelectOptions { node_id: NodeId, indices: Vec<usize> },
+    /// The UA's answer to [`EngineEvent::ClipboardRequested`] `id`
+    AnswerClipboard {
+        id: ClipboardRequestId,
+        answer: ClipboardAnswer,
+    },
+    /// The UA's decision on [`EngineEvent::PermissionRequested`] `id`. `Granted` and `Denied` are
+    /// remembered for the origin; `Prompt` (the user dismissed the question) only settles the
+    /// page's request.
+    AnswerPermission {
+        id: PermissionRequestId,
+        state: PermissionState,
+    },
+
+    // ****************************************
+    // ** User content
+    /// Register a script to inject into matching pages; one with the same id is replaced
+    AddUserScript { script: UserScript },
+    /// Register a stylesheet to inject into matching pages; one with the same id is replaced
+    AddUserStyle { style: UserStyle },
+    /// Unregister a user script or style. Pages already showing it keep it until they reload.
+    RemoveUserContent { id: UserContentId },
+
     // ****************************************
     // ** Session / zone state
     /// Set a specific cookie
@@ -185,15 +364,32 @@ pub enum TabCommand {
     // ** Media / scripting
     /// Execute given javascript (how about lua?)
     ExecuteScript { source: String },
+    /// Run `source` in the page's JS context; its value arrives as [`EngineEvent::ScriptEvaluated`]
+    EvaluateScript { id: EvaluationId, source: String },
     /// Play media in element_id
     PlayMedia { element_id: u64 },
     /// Pause media in element_id
     PauseMedia { element_id: u64 },
 
+    // ****************************************
+    // ** Printing and capture
+    /// Paginate the document for `setup`-sized paper; the pages arrive as [`EngineEvent::PrintReady`]
+    Print { setup: PageSetup },
+    /// Render the whole document at its full height; the result arrives as
+    /// [`EngineEvent::FullPageCaptured`]
+    CaptureFullPage,
+
     // ****************************************
     // ** Debug / devtools
     /// Dump dom tree
     DumpDomTree,
+    /// Draw the box-model inspector overlay on a DOM node, or remove it with `None`
+    InspectNode { node_id: Option<NodeId> },
+    /// Describe a DOM node and its subtree, or the whole document with `None`; the answer
+    /// arrives as [`EngineEvent::NodeDescribed`]
+    DescribeNode { node_id: Option<NodeId> },
+    /// Toggle the paint-order, layer-border and repaint-flashing visualizations
+    SetPaintDebug { debug: PaintDebug },
 }
 
 #[derive(Debug)]
@@ -212,9 +408,20 @@ pub enum EngineCommand {
 pub enum NavigationEvent {
     /// Navigation has been started
     Started { nav_id: NavigationId, url: Url },
+    /// The server redirected the navigation to `to`
+    Redirected { nav_id: NavigationId, from: Url, to: Url },
+    /// Response headers of the main document arrived
+    ResponseReceived {
+        nav_id: NavigationId,
+        url: Url,
+        status: u16,
+        content_type: Option<String>,
+    },
     /// A new document will replace current one
     Committed { nav_id: NavigationId, url: Url },
-    /// Finished loading the main document for this navigation
+    /// The document is parsed and shown; images and frames may still be loading
+    DomContentLoaded { nav_id: NavigationId, url: Url },
+    /// The document and its subresources (images, frames) finished loading
     Finished { nav_id: NavigationId, url: Url },
     /// Navigation has failed
     Failed {
@@ -432,20 +639,43 @@ pub enum EngineEvent {
 
     // ****************************************
     // ** Tab state
-    /// Hovered link URL changed (None = no link under cursor)
+    /// The link under the pointer changed, for a status-bar preview. `url` is absolute
+    /// (resolved against the document URL); `None` when the pointer left all links.
     HoverUrl {
         tab_id: TabId,
         url: Option<String>,
     },
+    /// Keyboard focus moved. Either side is `None` when focus came from or went to nothing.
+    FocusChanged {
+        tab_id: TabId,
+        blurred: Option<NodeId>,
+        focused: Option<NodeId>,
+    },
+    /// Cursor shape for the element under the mouse changed
+    CursorChanged {
+        tab_id: TabId,
+        cursor: CursorIcon,
+    },
+    /// The tab's session history changed; UAs use this to enable the Back/Forward buttons
+    HistoryChanged {
+        tab_id: TabId,
+        can_go_back: bool,
+        can_go_forward: bool,
+    },
+    /// Snapshot of the tab's session, in response to [`TabCommand::SaveSession`]
+    SessionSaved {
+        tab_id: TabId,
+        state: SessionState,
+    },
     /// Title of the tab has changed
     TitleChanged {
         tab_id: TabId,
         title: String,
     },
-    /// Favicon of tab has changed
+    /// Favicon of tab has changed; `None` when the new page has no (loadable) icon yet
     FavIconChanged {
         tab_id: TabId,
-        favicon: Vec<u8>,
+        favicon: Option<Favicon>,
     },
     /// Location of the tab has changed
     LocationChanged {
@@ -457,6 +687,92 @@ pub enum EngineEvent {
         tab_id: TabId,
         viewport: Viewport,
     },
+    /// The tab wants `text` placed on the system clipboard (e.g. after `CopySelection`)
+    ClipboardWrite {
+        tab_id: TabId,
+        text: String,
+    },
+    /// The page wants to read or write the system clipboard (`navigator.clipboard`). The UA decides
+    /// whether `origin` may, does the access, and answers with [`TabCommand::AnswerClipboard`].
+    /// Until then the page's promise stays pending.
+    ClipboardRequested {
+        tab_id: TabId,
+        id: ClipboardRequestId,
+        origin: String,
+        access: ClipboardAccess,
+    },
+    /// The page asks for permission `name` (e.g. `Notification.requestPermission()`) and `origin`
+    /// has no decision yet. The UA answers with [`TabCommand::AnswerPermission`]; until then the
+    /// page's promise stays pending.
+    PermissionRequested {
+        tab_id: TabId,
+        id: PermissionRequestId,
+        origin: String,
+        name: PermissionName,
+    },
+    /// The page shows a notification (`new Notification()`); `origin` has the permission for it
+    NotificationRequested {
+        tab_id: TabId,
+        id: NotificationId,
+        origin: String,
+        notification: NotificationRequest,
+    },
+    /// The page closed notification `id`
+    NotificationClosed {
+        tab_id: TabId,
+        id: NotificationId,
+    },
+    /// What is at a viewport point, in response to [`TabCommand::HitTest`]. `hit` is `None` when
+    /// the point is outside every element. Inside an iframe it describes the frame's document.
+    HitTested {
+        tab_id: TabId,
+        x: f32,
+        y: f32,
+        hit: Option<HitTestResult>,
+    },
+    /// The answer to [`TabCommand::DescribeNode`]. `desc` is `None` when no document is loaded.
+    NodeDescribed {
+        tab_id: TabId,
+        node_id: Option<NodeId>,
+        desc: Option<Arc<NodeDesc>>,
+    },
+    /// The result of [`TabCommand::EvaluateScript`]: the script's completion value as JSON, or the
+    /// exception it threw.
+    ScriptEvaluated {
+        tab_id: TabId,
+        id: EvaluationId,
+        result: ScriptResult,
+    },
+    /// A file input was activated. The UA shows a file chooser and answers with
+    /// [`TabCommand::SetInputFiles`]. `accept` is the input's `accept` attribute.
+    FileChooserRequested {
+        tab_id: TabId,
+        node_id: NodeId,
+        multiple: bool,
+        accept: Option<String>,
+    },
+    /// A `<select>` was activated. Its drop-down can't be drawn on the page surface, so the UA
+    /// shows it against `popup.anchor` and answers with [`TabCommand::ChooseSelectOptions`].
+    SelectPopupRequested {
+        tab_id: TabId,
+        popup: SelectPopup,
+    },
+    /// A new accessibility tree is available after layout. Only sent when
+    /// `engine.accessibility.enabled` is set.
+    AccessibilityTreeChanged {
+        tab_id: TabId,
+        tree: Arc<AccessibilityTree>,
+    },
+    /// The document has been paginated in response to [`TabCommand::Print`], one scene per page
+    PrintReady {
+        tab_id: TabId,
+        pages: Arc<Vec<PrintPage>>,
+    },
+    /// The whole document has been rendered in response to [`TabCommand::CaptureFullPage`]
+    FullPageCaptured {
+        tab_id: TabId,
+        capture: Arc<FullPageCapture>,
+    },
 
     // ****************************************
     // ** Navigation
@@ -495,6 +811,32 @@ pub enum EngineEvent {
         tab_id: TabId,
         zone_id: ZoneId,
     },
+    /// The tab's worker has had commands waiting for `stalled` without handling any, longer
+    /// than `engine.tab.unresponsive_ms`. The UA can wait or replace the tab with
+    /// [`Zone::respawn_tab`](crate::zone::Zone::respawn_tab).
+    TabUnresponsive {
+        tab_id: TabId,
+        zone_id: ZoneId,
+        stalled: Duration,
+    },
+    /// A tab reported as unresponsive is handling commands again
+    TabResponsive {
+        tab_id: TabId,
+        zone_id: ZoneId,
+    },
+    /// The page in `tab_id` wants `url` opened in a new browsing context (a link or form with
+    /// `target="_blank"` or a named target). The UA decides whether and where to open it; it
+    /// passes `opener` on as [`TabDefaults::opener`](crate::tab::TabDefaults::opener) so the new
+    /// tab knows who opened it. `opener` is `None` when the page asked for `noopener`.
+    NewWindowRequested {
+        tab_id: TabId,
+        url: Url,
+        /// The requested target name (`_blank` or a window name)
+        target: String,
+        /// The `window.open` features string; empty for links and forms
+        features: String,
+        opener: Option<TabId>,
+    },
 
     // ** Tab
 
@@ -526,6 +868,19 @@ pub enum EngineEvent {
         tab_id: TabId,
         element_id: u64,
     },
+    /// The page wrote `message` to its `console`. It is also logged through the `log` crate.
+    ConsoleMessage {
+        tab_id: TabId,
+        level: ConsoleLevel,
+        message: String,
+    },
+    /// A script of the page, or of one of its workers, ran into `violation` of the script host's
+    /// limits and was stopped. The UA may tell the user the page is unresponsive and offer to
+    /// reload it.
+    PageUnresponsive {
+        tab_id: TabId,
+        violation: LimitViolation,
+    },
     /// Result of a script is returned (console stuff?)
     ScriptResult {
         tab_id: TabId,
@@ -540,6 +895,13 @@ pub enum EngineEvent {
         url: Url,
         message: String,
     },
+    /// A secure document tried to load `url` over an insecure connection, and the load was
+    /// blocked (see [`crate::net::mixed_content`])
+    MixedContentBlocked {
+        tab_id: TabId,
+        document: Url,
+        url: Url,
+    },
     /// Javascript (parse) error
     JavaScriptError {
         tab_id: TabId,
@@ -566,6 +928,18 @@ mod tests {
         assert_eq!(MouseButton::Right.to_string(), "Right");
     }
 
+    #[test]
+    fn cursor_icon_from_css() {
+        assert_eq!(CursorIcon::from_css("pointer"), Some(CursorIcon::Pointer));
+        assert_eq!(CursorIcon::from_css("nwse-resize"), Some(CursorIcon::NwseResize));
+        assert_eq!(
+            CursorIcon::from_css("url(hand.cur), pointer"),
+            Some(CursorIcon::Pointer)
+        );
+        assert_eq!(CursorIcon::from_css("auto"), None);
+        assert_eq!(CursorIcon::from_css("bogus"), None);
+    }
+
     #[test]
     fn modifiers_display_empty_is_none() {
         let m = Modifiers::empty();
@@ -0,0 +1,177 @@
+//! Favicons: finding the icon a page offers and decoding it for the UA's tab strip.
+//!
+//! Candidates come from `<link>` elements whose `rel` contains the `icon` token (so
+//! `rel="shortcut icon"` counts too), ordered by how well their `sizes` fit the size we want:
+//! the smallest icon at least that large first, then icons without a size, then smaller ones.
+//! `/favicon.ico` at the page's origin is always the last resort. The tab fetches them in order
+//! through its own fetcher, and the first candidate that decodes wins. ICO files carry several
+//! images; the decoder takes the largest, which is then scaled down.
+
+use crate::html::{EngineDocument, RenderConfiguration};
+use cow_utils::CowUtils;
+use gosub_interface::document::Document as _;
+use image::imageops::FilterType;
+use std::fmt;
+use url::Url;
+
+/// Edge length in pixels that icons are scaled down to. Tab strips draw them at 16px; 32 keeps
+/// them sharp on 2x displays.
+pub const FAVICON_SIZE: u32 = 32;
+
+/// A decoded favicon in straight (non-premultiplied) RGBA8.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Favicon {
+    /// Where the icon was loaded from
+    pub url: Url,
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl fmt::Debug for Favicon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Favicon")
+            .field("url", &self.url.as_str())
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish_non_exhaustive()
+    }
+}
+
+/// One icon the page offers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IconCandidate {
+    pub url: Url,
+    /// The declared size closest to the wanted one; `None` without `sizes` or for `sizes="any"`
+    pub size: Option<u32>,
+}
+
+/// The icons to try for `doc`, best first, ending with `/favicon.ico`. `base_url` is the
+/// document URL that relative `href`s resolve against.
+pub fn icon_candidates<C: RenderConfiguration>(
+    doc: &EngineDocument<C>,
+    base_url: &Url,
+    wanted: u32,
+) -> Vec<IconCandidate> {
+    let mut candidates = Vec::new();
+
+    let mut stack = vec![doc.root()];
+    while let Some(id) = stack.pop() {
+        if doc.tag_name(id) == Some("link") && doc.attribute(id, "rel").is_some_and(is_icon_rel) {
+            let url = doc
+                .attribute(id, "href")
+                .and_then(|href| base_url.join(href.trim()).ok());
+            if let Some(url) = url {
+                let size = doc.attribute(id, "sizes").and_then(|s| closest_size(s, wanted));
+                candidates.push(IconCandidate { url, size });
+            }
+        }
+        stack.extend(doc.children(id).iter().rev().copied());
+    }
+
+    // Stable, so equally good icons keep document order.
+    candidates.sort_by_key(|c| rank(c.size, wanted));
+
+    if matches!(base_url.scheme(), "http" | "https") {
+        if let Ok(fallback) = base_url.join("/favicon.ico") {
+            if !candidates.iter().any(|c| c.url == fallback) {
+                candidates.push(IconCandidate {
+                    url: fallback,
+                    size: None,
+                });
+            }
+        }
+    }
+    candidates
+}
+
+/// Decodes icon bytes (ICO, PNG, GIF, ...), scaling them down to fit `wanted` x `wanted`.
+pub fn decode_favicon(url: &Url, bytes: &[u8], wanted: u32) -> Option<Favicon> {
+    let mut img = image::load_from_memory(bytes).ok()?;
+    if img.width() > wanted || img.height() > wanted {
+        img = img.resize(wanted, wanted, FilterType::Triangle);
+    }
+    let rgba = img.to_rgba8();
+    Some(Favicon {
+        url: url.clone(),
+        width: rgba.width(),
+        height: rgba.height(),
+        rgba: rgba.into_raw(),
+    })
+}
+
+fn is_icon_rel(rel: &str) -> bool {
+    rel.split_ascii_whitespace().any(|t| t.eq_ignore_ascii_case("icon"))
+}
+
+/// Picks from a `sizes` attribute (`"16x16 32x32"`) the size that ranks best for `wanted`.
+fn closest_size(sizes: &str, wanted: u32) -> Option<u32> {
+    sizes
+        .split_ascii_whitespace()
+        .filter_map(|s| {
+            let (w, h) = s
+                .cow_to_ascii_lowercase()
+                .split_once('x')
+                .map(|(w, h)| (w.parse::<u32>(), h.parse::<u32>()))?;
+            Some(w.ok()?.max(h.ok()?))
+        })
+        .min_by_key(|&s| rank(Some(s), wanted))
+}
+
+/// Sort key: large-enough icons by how little they overshoot, then unsized, then too-small ones
+/// by how much they fall short.
+fn rank(size: Option<u32>, wanted: u32) -> (u8, u32) {
+    match size {
+        Some(s) if s >= wanted => (0, s - wanted),
+        None => (1, 0),
+        Some(s) => (2, wanted - s),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::testing::parse;
+    use image::{DynamicImage, ImageFormat, RgbaImage};
+    use std::io::Cursor;
+
+    #[test]
+    fn candidates_are_ranked_by_size() {
+        let doc = parse(
+            r#"<head>
+                <link rel="stylesheet" href="/style.css">
+                <link rel="icon" href="small.png" sizes="16x16">
+                <link rel="shortcut icon" href="/any.ico">
+                <link rel="icon" href="big.png" sizes="16x16 48x48 256x256">
+            </head>"#,
+        );
+        let base = Url::parse("https://example.com/dir/page.html").unwrap();
+        let urls: Vec<String> = icon_candidates(&doc, &base, 32)
+            .into_iter()
+            .map(|c| c.url.to_string())
+            .collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/dir/big.png",
+                "https://example.com/any.ico",
+                "https://example.com/dir/small.png",
+                "https://example.com/favicon.ico",
+            ]
+        );
+    }
+
+    #[test]
+    fn large_icons_are_scaled_down() {
+        let mut png = Vec::new();
+        DynamicImage::ImageRgba8(RgbaImage::new(64, 64))
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        let url = Url::parse("https://example.com/icon.png").unwrap();
+
+        let icon = decode_favicon(&url, &png, 32).unwrap();
+        assert_eq!((icon.width, icon.height), (32, 32));
+        assert_eq!(icon.rgba.len(), 32 * 32 * 4);
+        assert!(decode_favicon(&url, b"not an image", 32).is_none());
+    }
+}
@@ -0,0 +1,208 @@
+//! Keyboard focus: which elements can take focus, and the order Tab moves through them.
+//!
+//! Follows the HTML "sequential focus navigation order": elements with a positive `tabindex`
+//! come first, in increasing `tabindex` and then document order, followed by every other
+//! focusable element with `tabindex="0"` or no `tabindex` in document order. A negative
+//! `tabindex` makes an element focusable by click or script but takes it out of the Tab order.
+//!
+//! The browsing context owns the focused node and mirrors it into the document so `:focus`
+//! selectors match; the painter draws the focus ring. When focus moves, the tab worker fires
+//! `blur`, `focusout`, `focus` and `focusin` at the page's elements.
+
+use crate::html::{EngineDocument, RenderConfiguration};
+use gosub_interface::document::Document as _;
+use gosub_interface::node::NodeType;
+use gosub_shared::node::NodeId;
+
+/// The element's parsed `tabindex`, if it has a valid one.
+pub fn tabindex<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId) -> Option<i32> {
+    doc.attribute(id, "tabindex")?.trim().parse().ok()
+}
+
+/// True when `id` can receive focus at all (by Tab, click or script).
+pub fn is_focusable<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId) -> bool {
+    if doc.node_type(id) != NodeType::ElementNode {
+        return false;
+    }
+    let disabled = doc.attribute(id, "disabled").is_some();
+    let implicit = match doc.tag_name(id) {
+        Some("a") | Some("area") => doc.attribute(id, "href").is_some(),
+        Some("input") => {
+            !disabled
+                && !doc
+                    .attribute(id, "type")
+                    .is_some_and(|t| t.eq_ignore_ascii_case("hidden"))
+        }
+        Some("button") | Some("select") | Some("textarea") => !disabled,
+        Some("iframe") | Some("summary") => true,
+        _ => false,
+    };
+    let editable = doc
+        .attribute(id, "contenteditable")
+        .is_some_and(|v| !v.eq_ignore_ascii_case("false"));
+
+    implicit || editable || (tabindex(doc, id).is_some() && !disabled)
+}
+
+/// True when `id` takes typed text: a text-like `<input>` or a `<textarea>` that is neither
+/// disabled nor read-only, or an element inside a `contenteditable` region.
+pub fn is_editable<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId) -> bool {
+    const NON_TEXT_INPUTS: [&str; 10] = [
+        "hidden", "checkbox", "radio", "button", "submit", "reset", "image", "file", "color", "range",
+    ];
+    let locked = doc.attribute(id, "disabled").is_some() || doc.attribute(id, "readonly").is_some();
+    match doc.tag_name(id) {
+        Some("input") => {
+            let kind = doc.attribute(id, "type").unwrap_or("text");
+            return !locked && !NON_TEXT_INPUTS.iter().any(|t| kind.eq_ignore_ascii_case(t));
+        }
+        Some("textarea") => return !locked,
+        _ => {}
+    }
+
+    // The nearest `contenteditable` decides; `"false"` switches editing off for a subtree.
+    let mut current = Some(id);
+    while let Some(id) = current {
+        if let Some(value) = doc.attribute(id, "contenteditable") {
+            return !value.eq_ignore_ascii_case("false");
+        }
+        current = doc.parent(id);
+    }
+    false
+}
+
+/// Every element Tab visits, in sequential focus navigation order. `is_rendered` filters out
+/// elements that have no box (e.g. `display: none`), which can't be focused.
+pub fn tab_order<C: RenderConfiguration>(doc: &EngineDocument<C>, is_rendered: impl Fn(NodeId) -> bool) -> Vec<NodeId> {
+    let mut positive = Vec::new();
+    let mut normal = Vec::new();
+
+    let mut stack = vec![doc.root()];
+    while let Some(id) = stack.pop() {
+        if is_focusable(doc, id) && is_rendered(id) {
+            match tabindex(doc, id) {
+                Some(n) if n > 0 => positive.push((n, id)),
+                Some(n) if n < 0 => {}
+                _ => normal.push(id),
+            }
+        }
+        stack.extend(doc.children(id).iter().rev().copied());
+    }
+
+    // Stable sort keeps document order among equal tabindex values.
+    positive.sort_by_key(|(n, _)| *n);
+    positive.into_iter().map(|(_, id)| id).chain(normal).collect()
+}
+
+/// The element Tab (or Shift-Tab when `backwards`) moves to from `current`, wrapping around at
+/// either end. When `current` is not in `order` (nothing focused, or focused by click on an
+/// element outside the Tab order) navigation starts from the first or last element.
+pub fn next_in_order(order: &[NodeId], current: Option<NodeId>, backwards: bool) -> Option<NodeId> {
+    if order.is_empty() {
+        return None;
+    }
+    let len = order.len();
+    let index = match current.and_then(|c| order.iter().position(|&id| id == c)) {
+        Some(i) if backwards => (i + len - 1) % len,
+        Some(i) => (i + 1) % len,
+        None if backwards => len - 1,
+        None => 0,
+    };
+    Some(order[index])
+}
+
+/// The nearest focusable inclusive ancestor of `id`: what a click on `id` focuses.
+pub fn focusable_ancestor<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId) -> Option<NodeId> {
+    let mut current = Some(id);
+    while let Some(id) = current {
+        if is_focusable(doc, id) {
+            return Some(id);
+        }
+        current = doc.parent(id);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::testing::parse;
+    use crate::html::DefaultRenderConfig;
+
+    fn ids(doc: &EngineDocument<DefaultRenderConfig>, order: &[NodeId]) -> Vec<String> {
+        order
+            .iter()
+            .map(|&id| doc.attribute(id, "id").unwrap_or_default().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn order_follows_tabindex_then_document() {
+        let doc = parse(
+            r#"<body>
+                <a id="link" href="/">link</a>
+                <a id="anchor">no href</a>
+                <input id="hidden" type="HIDDEN">
+                <button id="disabled" disabled>off</button>
+                <div id="second" tabindex="2">two</div>
+                <input id="text">
+                <div id="first" tabindex="1">one</div>
+                <div id="skipped" tabindex="-1">skip</div>
+                <div id="also-first" tabindex="1">one too</div>
+                <p id="edit" contenteditable>edit</p>
+            </body>"#,
+        );
+        let order = tab_order(&doc, |_| true);
+        assert_eq!(
+            ids(&doc, &order),
+            vec!["first", "also-first", "second", "link", "text", "edit"]
+        );
+
+        // tabindex="-1" is still focusable by click, just not by Tab.
+        let skipped = doc.node_by_named_id("skipped").unwrap();
+        assert!(is_focusable(&doc, skipped));
+    }
+
+    #[test]
+    fn unrendered_elements_are_skipped() {
+        let doc = parse(r#"<body><input id="a"><input id="b"></body>"#);
+        let b = doc.node_by_named_id("b").unwrap();
+        let order = tab_order(&doc, |id| id != b);
+        assert_eq!(ids(&doc, &order), vec!["a"]);
+    }
+
+    #[test]
+    fn next_wraps_in_both_directions() {
+        let order = [NodeId::from(1usize), NodeId::from(2usize), NodeId::from(3usize)];
+        assert_eq!(next_in_order(&order, None, false), Some(order[0]));
+        assert_eq!(next_in_order(&order, None, true), Some(order[2]));
+        assert_eq!(next_in_order(&order, Some(order[2]), false), Some(order[0]));
+        assert_eq!(next_in_order(&order, Some(order[0]), true), Some(order[2]));
+        assert_eq!(next_in_order(&order, Some(NodeId::from(9usize)), false), Some(order[0]));
+        assert_eq!(next_in_order(&[], None, false), None);
+    }
+
+    #[test]
+    fn click_focuses_nearest_focusable_ancestor() {
+        let doc = parse(r#"<body><a id="link" href="/"><span id="inner">x</span></a><p id="plain">y</p></body>"#);
+        let inner = doc.node_by_named_id("inner").unwrap();
+        let plain = doc.node_by_named_id("plain").unwrap();
+        assert_eq!(focusable_ancestor(&doc, inner), doc.node_by_named_id("link"));
+        assert_eq!(focusable_ancestor(&doc, plain), None);
+    }
+
+    #[test]
+    fn editable_fields_and_regions() {
+        let doc = parse(
+            r#"<body>
+                <input id="text"><input id="check" type="checkbox"><input id="ro" readonly>
+                <textarea id="area"></textarea>
+                <div contenteditable><p id="inside">x</p><p id="off" contenteditable="false">y</p></div>
+                <p id="plain">z</p>
+            </body>"#,
+        );
+        let editable = |id: &str| is_editable(&doc, doc.node_by_named_id(id).unwrap());
+        assert!(editable("text") && editable("area") && editable("inside"));
+        assert!(!editable("check") && !editable("ro") && !editable("off") && !editable("plain"));
+    }
+}
@@ -0,0 +1,161 @@
+//! Font settings handed to the font system and kept up to date there: the families the CSS
+//! generic font families resolve to (`useragent.fonts.family.*`) and the memory budget of its
+//! font caches (`engine.storage.font_cache_bytes`).
+
+use gosub_config::settings::Setting;
+use gosub_config::Config;
+use gosub_interface::font_system::{FontSystem, GenericFamilies, CONFIGURABLE_GENERICS};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Settings holding the families for each generic, in [`CONFIGURABLE_GENERICS`] order. Each is a
+/// CSS family list; the generic's own name leaves it to the platform.
+const GENERIC_KEYS: [&str; 5] = [
+    "useragent.fonts.family.serif",
+    "useragent.fonts.family.sans_serif",
+    "useragent.fonts.family.monospace",
+    "useragent.fonts.family.cursive",
+    "useragent.fonts.family.fantasy",
+];
+
+/// Setting holding the per-language families, as `lang:generic=family` entries
+const BY_LANGUAGE_KEY: &str = "useragent.fonts.family.by_language";
+
+/// Setting holding the memory budget of the font caches, in bytes
+const CACHE_BUDGET_KEY: &str = "engine.storage.font_cache_bytes";
+
+/// Hands the font cache budget to `font_system`, and again whenever the setting changes.
+pub(crate) fn apply_cache_budget<F: FontSystem>(config: &Config, font_system: Arc<Mutex<F>>) {
+    font_system.lock().set_cache_budget(config.get_uint(CACHE_BUDGET_KEY));
+    config.subscribe(CACHE_BUDGET_KEY, move |_, value: &Setting| {
+        font_system.lock().set_cache_budget(value.to_uint());
+    });
+}
+
+/// Hands the configured generic families to `font_system`, and again whenever one of the
+/// settings changes.
+pub(crate) fn apply_generic_families<F: FontSystem>(config: &Config, font_system: Arc<Mutex<F>>) {
+    // The subscription keeps its own copy of the settings: reading the config back from inside
+    // its callback would keep the config alive through its own subscription.
+    let settings: HashMap<String, Setting> = GENERIC_KEYS
+        .iter()
+        .chain([&BY_LANGUAGE_KEY])
+        .filter_map(|&key| Some((key.to_string(), config.get(key).ok()?)))
+        .collect();
+    font_system.lock().set_generic_families(generic_families(&settings));
+
+    let settings = Mutex::new(settings);
+    config.subscribe("useragent.fonts.family.*", move |key, value: &Setting| {
+        let families = {
+            let mut settings = settings.lock();
+            settings.insert(key.to_string(), value.clone());
+            generic_families(&settings)
+        };
+        font_system.lock().set_generic_families(families);
+    });
+}
+
+/// Builds the mapping from the values of the family settings, keyed by setting.
+fn generic_families(settings: &HashMap<String, Setting>) -> GenericFamilies {
+    let mut families = GenericFamilies::new();
+    for (generic, key) in CONFIGURABLE_GENERICS.iter().zip(GENERIC_KEYS) {
+        let names = settings
+            .get(key)
+            .map(|value| family_list(&value.value_string(), generic))
+            .unwrap_or_default();
+        families.set(generic, None, names);
+    }
+
+    // Entries for the same language and generic add up, in order.
+    let entries = settings.get(BY_LANGUAGE_KEY).map(Setting::to_map).unwrap_or_default();
+    let mut by_language: Vec<(&str, &str, Vec<String>)> = Vec::new();
+    for entry in &entries {
+        let Some((lang, mapping)) = entry.split_once(':') else {
+            continue;
+        };
+        let Some((generic, family)) = mapping.split_once('=') else {
+            continue;
+        };
+        let (lang, generic, family) = (lang.trim(), generic.trim(), family.trim());
+        if lang.is_empty() || family.is_empty() || !CONFIGURABLE_GENERICS.contains(&generic) {
+            continue;
+        }
+        match by_language.iter_mut().find(|(l, g, _)| *l == lang && *g == generic) {
+            Some((_, _, names)) => names.push(family.to_string()),
+            None => by_language.push((lang, generic, vec![family.to_string()])),
+        }
+    }
+    for (lang, generic, names) in by_language {
+        families.set(generic, Some(lang), names);
+    }
+    families
+}
+
+/// The family names of the CSS family list `value`, without the generic it is configured for
+fn family_list(value: &str, generic: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|name| name.trim().trim_matches(['"', '\'']).trim())
+        .filter(|name| !name.is_empty() && !name.eq_ignore_ascii_case(generic))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gosub_interface::font_system::FontQuery;
+
+    #[test]
+    fn builds_the_mapping_per_generic_and_language() {
+        let settings: HashMap<String, Setting> = [
+            (
+                "useragent.fonts.family.serif",
+                Setting::String("Georgia, \"DejaVu Serif\", serif".into()),
+            ),
+            ("useragent.fonts.family.monospace", Setting::String("monospace".into())),
+            (
+                "useragent.fonts.family.by_language",
+                Setting::Map(vec![
+                    "ja:serif=Noto Serif CJK JP".into(),
+                    "ja:serif=IPAMincho".into(),
+                    "zh-Hant:sans-serif=Noto Sans CJK TC".into(),
+                    "ko:headline=Nope".into(),
+                    "garbage".into(),
+                ]),
+            ),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect();
+        let families = generic_families(&settings);
+
+        assert_eq!(families.lookup("serif", None), ["Georgia", "DejaVu Serif"]);
+        assert_eq!(
+            families.lookup("serif", Some("ja-JP")),
+            ["Noto Serif CJK JP", "IPAMincho"]
+        );
+        assert_eq!(families.lookup("sans-serif", Some("zh-Hant-TW")), ["Noto Sans CJK TC"]);
+        assert!(families.lookup("sans-serif", Some("zh")).is_empty());
+        assert!(
+            families.lookup("monospace", None).is_empty(),
+            "the generic itself is the platform default"
+        );
+    }
+
+    #[test]
+    fn applies_changed_settings_to_the_font_system() {
+        let config = crate::engine::settings_store::default_config();
+        let font_system = Arc::new(Mutex::new(gosub_fontmanager::ParleyFontSystem::new()));
+        apply_generic_families(&config, font_system.clone());
+
+        config
+            .set("useragent.fonts.family.fantasy", Setting::String("Roboto".into()))
+            .unwrap();
+
+        // With fantasy bound to the bundled Roboto, the generic resolves to it on any system.
+        let resolved = font_system.lock().resolve(&FontQuery::new(&["fantasy"])).unwrap();
+        assert_eq!(resolved.family, "Roboto");
+    }
+}
@@ -0,0 +1,622 @@
+//! Form submission: turning a `<form>` and the button that submitted it into a navigation.
+//!
+//! Follows the HTML "form submission algorithm" closely enough for ordinary forms: the entry list
+//! is built from the form's controls in tree order, the submitter's `formaction`, `formmethod`
+//! and `formenctype` override the form's own, and the entries are encoded as a query string
+//! (GET) or as an `application/x-www-form-urlencoded`, `multipart/form-data` or `text/plain`
+//! body (POST).
+//!
+//! Controls have no live state yet, so their values are the ones in the markup: `value`,
+//! `checked` and `selected` attributes and the text of a `<textarea>`. Files for
+//! `<input type=file>` are picked by the UA and handed in by the caller; they are read when the
+//! body is encoded. Constraint validation is not performed.
+//!
+//! The page can't draw a `<select>` drop-down, so the UA shows it: [`select_options`] lists what
+//! to offer and [`set_selected_options`] writes the user's choice back as `selected` attributes.
+
+use crate::html::{EngineDocument, RenderConfiguration};
+use crate::net::form_data::{EncodedBody, MultipartBody, UrlEncodedBody};
+use cow_utils::CowUtils;
+use gosub_interface::document::Document as _;
+use gosub_interface::node::NodeType;
+use gosub_shared::node::NodeId;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use url::Url;
+
+/// Files the user picked for `<input type=file>` elements, by element.
+pub type SelectedFiles = HashMap<NodeId, Vec<PathBuf>>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FormMethod {
+    Get,
+    Post,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FormEnctype {
+    UrlEncoded,
+    Multipart,
+    TextPlain,
+}
+
+/// The value of one form entry.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FormValue {
+    Text(String),
+    /// A file to upload; `None` for a file input with nothing picked, which still submits an
+    /// empty part.
+    File(Option<PathBuf>),
+}
+
+/// One entry of a `<select>` drop-down.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelectOption {
+    /// What the drop-down shows: the `label` attribute, else the option's text
+    pub label: String,
+    /// What the form submits
+    pub value: String,
+    /// The `label` of the `<optgroup>` the option is in
+    pub group: Option<String>,
+    pub selected: bool,
+    /// Disabled options are shown but can't be chosen
+    pub disabled: bool,
+}
+
+/// A form ready to submit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FormSubmission {
+    /// Absolute URL the form submits to, before any query is added
+    pub action: Url,
+    pub method: FormMethod,
+    pub enctype: FormEnctype,
+    /// Browsing context to submit into: the submitter's `formtarget`, the form's `target` or the
+    /// document's `<base target>`; empty for the current one
+    pub target: String,
+    /// Name/value pairs in tree order
+    pub entries: Vec<(String, FormValue)>,
+}
+
+impl FormSubmission {
+    /// The URL to navigate to. For GET the entries replace the action's query.
+    pub fn url(&self) -> Url {
+        let mut url = self.action.clone();
+        if self.method == FormMethod::Get {
+            url.set_query(Some(url_encode(&self.entries).as_query()));
+        }
+        url
+    }
+
+    /// The request body, `None` for GET. Reads picked files from disk, so this blocks.
+    pub fn encode_body(&self) -> Option<EncodedBody> {
+        if self.method == FormMethod::Get {
+            return None;
+        }
+        let body = match self.enctype {
+            FormEnctype::UrlEncoded => url_encode(&self.entries).finish(),
+            FormEnctype::TextPlain => {
+                let mut text = String::new();
+                for (name, value) in &self.entries {
+                    text.push_str(name);
+                    text.push('=');
+                    text.push_str(&value_as_text(value));
+                    text.push_str("\r\n");
+                }
+                EncodedBody {
+                    content_type: "text/plain;charset=UTF-8".to_string(),
+                    bytes: text.into_bytes(),
+                }
+            }
+            FormEnctype::Multipart => multipart_encode(&self.entries),
+        };
+        Some(body)
+    }
+}
+
+/// The form `id` belongs to: the one named by its `form` attribute, else its nearest `<form>`
+/// ancestor.
+pub fn form_owner<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId) -> Option<NodeId> {
+    if let Some(form_id) = doc.attribute(id, "form") {
+        return find_element(doc, |n| {
+            doc.tag_name(n) == Some("form") && doc.attribute(n, "id") == Some(form_id)
+        });
+    }
+    let mut current = doc.parent(id);
+    while let Some(node) = current {
+        if doc.tag_name(node) == Some("form") {
+            return Some(node);
+        }
+        current = doc.parent(node);
+    }
+    None
+}
+
+/// Whether `id` is a submit button: `<button>` without a `type` or with `type=submit`, or
+/// `<input type=submit|image>`.
+pub fn is_submit_button<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId) -> bool {
+    let kind = doc.attribute(id, "type");
+    match doc.tag_name(id) {
+        Some("button") => kind.is_none_or(|t| t.eq_ignore_ascii_case("submit")),
+        Some("input") => kind.is_some_and(|t| t.eq_ignore_ascii_case("submit") || t.eq_ignore_ascii_case("image")),
+        _ => false,
+    }
+}
+
+/// The enabled submit button at or around `id` (a click can land on a button's content).
+pub fn submitter_at<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId) -> Option<NodeId> {
+    let mut current = Some(id);
+    while let Some(node) = current {
+        if is_submit_button(doc, node) {
+            return (!is_disabled(doc, node)).then_some(node);
+        }
+        current = doc.parent(node);
+    }
+    None
+}
+
+/// The form's default button: its first submit button in tree order.
+pub fn default_button<C: RenderConfiguration>(doc: &EngineDocument<C>, form: NodeId) -> Option<NodeId> {
+    find_element(doc, |n| is_submit_button(doc, n) && form_owner(doc, n) == Some(form))
+}
+
+/// Whether Enter in `id` submits its form ("implicit submission"): true for text-like inputs.
+pub fn submits_on_enter<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId) -> bool {
+    const NO_IMPLICIT_SUBMIT: [&str; 9] = [
+        "hidden", "checkbox", "radio", "button", "submit", "reset", "image", "file", "range",
+    ];
+    doc.tag_name(id) == Some("input")
+        && !doc
+            .attribute(id, "type")
+            .is_some_and(|t| NO_IMPLICIT_SUBMIT.iter().any(|k| t.eq_ignore_ascii_case(k)))
+}
+
+/// The options of `select` in tree order, for the UA to show in a drop-down. An option is
+/// `selected` when it would submit.
+pub fn select_options<C: RenderConfiguration>(doc: &EngineDocument<C>, select: NodeId) -> Vec<SelectOption> {
+    let selected = selected_options(doc, select);
+    option_elements(doc, select)
+        .into_iter()
+        .map(|option| {
+            let group = doc
+                .parent(option)
+                .filter(|&p| doc.tag_name(p) == Some("optgroup"))
+                .map(|p| doc.attribute(p, "label").unwrap_or_default().to_string());
+            let label = match doc.attribute(option, "label") {
+                Some(label) => label.to_string(),
+                None => collapse_whitespace(&text_content(doc, option)),
+            };
+            SelectOption {
+                label,
+                value: option_value(doc, option),
+                group,
+                selected: selected.contains(&option),
+                disabled: is_disabled(doc, option),
+            }
+        })
+        .collect()
+}
+
+/// Selects the options of `select` at `indices` (into [`select_options`]) and deselects the
+/// others. A single-choice select keeps only the last of them; disabled options and indices out
+/// of range are skipped, and a choice that skips all of them leaves the select untouched.
+/// Returns whether any `selected` attribute changed.
+pub fn set_selected_options<C: RenderConfiguration>(
+    doc: &mut EngineDocument<C>,
+    select: NodeId,
+    indices: &[usize],
+) -> bool {
+    let options = option_elements(doc, select);
+    let mut chosen = indices
+        .iter()
+        .filter_map(|&i| options.get(i).copied())
+        .filter(|&o| !is_disabled(doc, o))
+        .collect::<Vec<_>>();
+    if chosen.is_empty() && !indices.is_empty() {
+        return false;
+    }
+    if doc.attribute(select, "multiple").is_none() {
+        chosen = chosen.last().copied().into_iter().collect();
+    }
+
+    let mut changed = false;
+    for option in options {
+        let selected = chosen.contains(&option);
+        if selected == doc.attribute(option, "selected").is_some() {
+            continue;
+        }
+        if selected {
+            doc.set_attribute(option, "selected", "");
+        } else {
+            doc.remove_attribute(option, "selected");
+        }
+        changed = true;
+    }
+    changed
+}
+
+/// Builds the submission for `form` as submitted by `submitter` (`None` when submitted
+/// implicitly without a default button). Returns `None` when the form submits nowhere this
+/// engine can navigate to, or uses `method=dialog`.
+pub fn submission<C: RenderConfiguration>(
+    doc: &EngineDocument<C>,
+    form: NodeId,
+    submitter: Option<NodeId>,
+    files: &SelectedFiles,
+) -> Option<FormSubmission> {
+    let attr = |form_attr: &str, submitter_attr: &str| {
+        submitter
+            .and_then(|s| doc.attribute(s, submitter_attr))
+            .or_else(|| doc.attribute(form, form_attr))
+    };
+
+    let base = doc.url()?;
+    let action = match attr("action", "formaction").map(str::trim) {
+        Some(action) if !action.is_empty() => base.join(action).ok()?,
+        _ => base,
+    };
+    if !matches!(action.scheme(), "http" | "https" | "file") {
+        log::debug!("Not submitting form to {action}");
+        return None;
+    }
+
+    let method = match attr("method", "formmethod") {
+        Some(m) if m.eq_ignore_ascii_case("post") => FormMethod::Post,
+        Some(m) if m.eq_ignore_ascii_case("dialog") => return None,
+        _ => FormMethod::Get,
+    };
+    let enctype = match attr("enctype", "formenctype") {
+        Some(e) if e.eq_ignore_ascii_case("multipart/form-data") => FormEnctype::Multipart,
+        Some(e) if e.eq_ignore_ascii_case("text/plain") => FormEnctype::TextPlain,
+        _ => FormEnctype::UrlEncoded,
+    };
+
+    let target = attr("target", "formtarget")
+        .map(str::to_string)
+        .or_else(|| crate::html::base_target(doc))
+        .unwrap_or_default();
+
+    Some(FormSubmission {
+        action,
+        method,
+        enctype,
+        target,
+        entries: entry_list(doc, form, submitter, files),
+    })
+}
+
+/// The form's entries in tree order ("constructing the entry list").
+fn entry_list<C: RenderConfiguration>(
+    doc: &EngineDocument<C>,
+    form: NodeId,
+    submitter: Option<NodeId>,
+    files: &SelectedFiles,
+) -> Vec<(String, FormValue)> {
+    let mut entries = Vec::new();
+
+    let mut stack = vec![doc.root()];
+    while let Some(id) = stack.pop() {
+        stack.extend(doc.children(id).iter().rev().copied());
+
+        let Some(tag) = doc.tag_name(id) else {
+            continue;
+        };
+        if !matches!(tag, "input" | "button" | "select" | "textarea")
+            || form_owner(doc, id) != Some(form)
+            || is_disabled(doc, id)
+        {
+            continue;
+        }
+        let Some(name) = doc.attribute(id, "name").filter(|n| !n.is_empty()) else {
+            continue;
+        };
+        let name = name.to_string();
+        let value = || doc.attribute(id, "value").unwrap_or_default().to_string();
+
+        match tag {
+            "button" => {
+                if submitter == Some(id) {
+                    entries.push((name, FormValue::Text(value())));
+                }
+            }
+            "textarea" => entries.push((name, FormValue::Text(text_content(doc, id)))),
+            "select" => {
+                for option in selected_options(doc, id) {
+                    entries.push((name.clone(), FormValue::Text(option_value(doc, option))));
+                }
+            }
+            _ => {
+                let kind = doc.attribute(id, "type").unwrap_or("text").cow_to_ascii_lowercase();
+                match &*kind {
+                    "submit" if submitter == Some(id) => entries.push((name, FormValue::Text(value()))),
+                    // The click position isn't tracked; an image button submits its origin.
+                    "image" if submitter == Some(id) => {
+                        entries.push((format!("{name}.x"), FormValue::Text("0".into())));
+                        entries.push((format!("{name}.y"), FormValue::Text("0".into())));
+                    }
+                    "submit" | "image" | "reset" | "button" => {}
+                    "checkbox" | "radio" => {
+                        if doc.attribute(id, "checked").is_some() {
+                            let value = doc.attribute(id, "value").unwrap_or("on").to_string();
+                            entries.push((name, FormValue::Text(value)));
+                        }
+                    }
+                    "file" => match files.get(&id).filter(|f| !f.is_empty()) {
+                        Some(picked) => {
+                            for path in picked {
+                                entries.push((name.clone(), FormValue::File(Some(path.clone()))));
+                            }
+                        }
+                        None => entries.push((name, FormValue::File(None))),
+                    },
+                    _ => entries.push((name, FormValue::Text(value()))),
+                }
+            }
+        }
+    }
+    entries
+}
+
+/// The options of `select` that submit: those marked `selected`, or for a single-choice select
+/// without one, its first enabled option.
+fn selected_options<C: RenderConfiguration>(doc: &EngineDocument<C>, select: NodeId) -> Vec<NodeId> {
+    let options = option_elements(doc, select);
+    let selected = options
+        .iter()
+        .copied()
+        .filter(|&o| doc.attribute(o, "selected").is_some() && !is_disabled(doc, o))
+        .collect::<Vec<_>>();
+    if doc.attribute(select, "multiple").is_some() {
+        return selected;
+    }
+    match selected.last() {
+        Some(&last) => vec![last],
+        None => options
+            .into_iter()
+            .find(|&o| !is_disabled(doc, o))
+            .into_iter()
+            .collect(),
+    }
+}
+
+/// The `<option>`s of `select` in tree order, including those in `<optgroup>`s.
+fn option_elements<C: RenderConfiguration>(doc: &EngineDocument<C>, select: NodeId) -> Vec<NodeId> {
+    let mut options = Vec::new();
+    let mut stack = doc.children(select).iter().rev().copied().collect::<Vec<_>>();
+    while let Some(id) = stack.pop() {
+        if doc.tag_name(id) == Some("option") {
+            options.push(id);
+        } else {
+            stack.extend(doc.children(id).iter().rev().copied());
+        }
+    }
+    options
+}
+
+/// The value an option submits: its `value` attribute, else its text.
+fn option_value<C: RenderConfiguration>(doc: &EngineDocument<C>, option: NodeId) -> String {
+    doc.attribute(option, "value")
+        .map(str::to_string)
+        .unwrap_or_else(|| collapse_whitespace(&text_content(doc, option)))
+}
+
+/// Disabled itself, or inside a disabled `<fieldset>` (but not in its first `<legend>`, which
+/// stays enabled).
+fn is_disabled<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId) -> bool {
+    if doc.attribute(id, "disabled").is_some() {
+        return true;
+    }
+    let mut child = id;
+    let mut current = doc.parent(id);
+    while let Some(node) = current {
+        if doc.tag_name(node) == Some("fieldset") && doc.attribute(node, "disabled").is_some() {
+            let first_legend = doc
+                .children(node)
+                .iter()
+                .copied()
+                .find(|&c| doc.tag_name(c) == Some("legend"));
+            if first_legend != Some(child) {
+                return true;
+            }
+        }
+        child = node;
+        current = doc.parent(node);
+    }
+    false
+}
+
+fn find_element<C: RenderConfiguration>(doc: &EngineDocument<C>, pred: impl Fn(NodeId) -> bool) -> Option<NodeId> {
+    let mut stack = vec![doc.root()];
+    while let Some(id) = stack.pop() {
+        if doc.node_type(id) == NodeType::ElementNode && pred(id) {
+            return Some(id);
+        }
+        stack.extend(doc.children(id).iter().rev().copied());
+    }
+    None
+}
+
+fn text_content<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId) -> String {
+    let mut text = String::new();
+    let mut stack = vec![id];
+    while let Some(node) = stack.pop() {
+        if doc.node_type(node) == NodeType::TextNode {
+            text.push_str(doc.text_value(node).unwrap_or_default());
+        }
+        stack.extend(doc.children(node).iter().rev().copied());
+    }
+    text
+}
+
+fn collapse_whitespace(s: &str) -> String {
+    s.split_ascii_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn value_as_text(value: &FormValue) -> String {
+    match value {
+        FormValue::Text(text) => text.clone(),
+        FormValue::File(path) => path
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    }
+}
+
+fn url_encode(entries: &[(String, FormValue)]) -> UrlEncodedBody {
+    let mut body = UrlEncodedBody::new();
+    for (name, value) in entries {
+        body.append(name, &value_as_text(value));
+    }
+    body
+}
+
+fn multipart_encode(entries: &[(String, FormValue)]) -> EncodedBody {
+    let mut body = MultipartBody::new();
+    for (name, value) in entries {
+        match value {
+            FormValue::Text(text) => {
+                body.text(name, text);
+            }
+            FormValue::File(path) => {
+                let data = match path {
+                    Some(path) => std::fs::read(path).unwrap_or_else(|e| {
+                        log::warn!("Could not read {} for upload: {e}", path.display());
+                        Vec::new()
+                    }),
+                    None => Vec::new(),
+                };
+                body.file(name, &value_as_text(value), "application/octet-stream", &data);
+            }
+        }
+    }
+    body.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::testing::parse_at;
+    use crate::html::DefaultRenderConfig;
+
+    const PAGE_URL: &str = "https://example.com/dir/page.html";
+
+    fn by_id(doc: &EngineDocument<DefaultRenderConfig>, id: &str) -> NodeId {
+        find_element(doc, |n| doc.attribute(n, "id") == Some(id)).unwrap()
+    }
+
+    #[test]
+    fn get_submission_builds_query() {
+        let doc = parse_at(
+            PAGE_URL,
+            r#"<form id="f" action="search?old=1">
+                <input name="q" value="gosub engine">
+                <input type="checkbox" name="safe" checked>
+                <input type="checkbox" name="unchecked">
+                <input name="off" value="x" disabled>
+                <select name="lang"><option>en</option><option value="nl" selected>Dutch</option></select>
+                <textarea name="note">a&amp;b</textarea>
+                <button id="go" name="go" value="1">Go</button>
+                <button name="other" value="2">Other</button>
+            </form>"#,
+        );
+        let form = by_id(&doc, "f");
+        let go = by_id(&doc, "go");
+        assert_eq!(default_button(&doc, form), Some(go));
+
+        let sub = submission(&doc, form, Some(go), &SelectedFiles::new()).unwrap();
+        assert_eq!(sub.method, FormMethod::Get);
+        assert_eq!(
+            sub.url().as_str(),
+            "https://example.com/dir/search?q=gosub+engine&safe=on&lang=nl&note=a%26b&go=1"
+        );
+        assert!(sub.encode_body().is_none());
+    }
+
+    #[test]
+    fn submitter_overrides_and_multipart_body() {
+        let doc = parse_at(
+            PAGE_URL,
+            r#"<form id="f" action="/get">
+                <input name="title" value="hi">
+                <input type="file" name="upload">
+                <input id="send" type="submit" formaction="/upload" formmethod="post"
+                       formenctype="multipart/form-data">
+            </form>
+            <input name="outside" value="1" form="f">"#,
+        );
+        let form = by_id(&doc, "f");
+        let send = by_id(&doc, "send");
+        let sub = submission(&doc, form, Some(send), &SelectedFiles::new()).unwrap();
+        assert_eq!(sub.action.as_str(), "https://example.com/upload");
+        assert_eq!(sub.method, FormMethod::Post);
+        assert_eq!(
+            sub.entries,
+            vec![
+                ("title".to_string(), FormValue::Text("hi".into())),
+                ("upload".to_string(), FormValue::File(None)),
+                ("outside".to_string(), FormValue::Text("1".into())),
+            ]
+        );
+
+        let body = sub.encode_body().unwrap();
+        let boundary = body
+            .content_type
+            .strip_prefix("multipart/form-data; boundary=")
+            .unwrap();
+        let text = String::from_utf8(body.bytes).unwrap();
+        assert!(text.starts_with(&format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nhi\r\n"
+        )));
+        assert!(text.contains("name=\"upload\"; filename=\"\"\r\nContent-Type: application/octet-stream\r\n\r\n\r\n"));
+        assert!(text.ends_with(&format!("--{boundary}--\r\n")));
+    }
+
+    #[test]
+    fn choosing_an_option_moves_selected() {
+        let mut doc = parse_at(
+            PAGE_URL,
+            r#"<form id="f"><select id="s" name="size">
+                <option>Small</option>
+                <optgroup label="Big"><option value="l" selected>Large</option><option disabled>Huge</option></optgroup>
+            </select></form>"#,
+        );
+        let select = by_id(&doc, "s");
+        let options = select_options(&doc, select);
+        let shown = options
+            .iter()
+            .map(|o| {
+                (
+                    o.label.as_str(),
+                    o.value.as_str(),
+                    o.group.as_deref(),
+                    o.selected,
+                    o.disabled,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            shown,
+            vec![
+                ("Small", "Small", None, false, false),
+                ("Large", "l", Some("Big"), true, false),
+                ("Huge", "Huge", Some("Big"), false, true),
+            ]
+        );
+
+        assert!(
+            !set_selected_options(&mut doc, select, &[2]),
+            "disabled options can't be chosen"
+        );
+        assert!(set_selected_options(&mut doc, select, &[0]));
+        assert!(!set_selected_options(&mut doc, select, &[0]));
+        let selected = select_options(&doc, select)
+            .iter()
+            .map(|o| o.selected)
+            .collect::<Vec<_>>();
+        assert_eq!(selected, vec![true, false, false]);
+
+        let form = by_id(&doc, "f");
+        let sub = submission(&doc, form, None, &SelectedFiles::new()).unwrap();
+        assert_eq!(sub.entries, vec![("size".to_string(), FormValue::Text("Small".into()))]);
+    }
+}
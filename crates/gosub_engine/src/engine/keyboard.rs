@@ -0,0 +1,101 @@
+//! Default actions for keys the page doesn't handle itself.
+//!
+//! Key events go to the focused element, or to `<body>` when nothing has focus (see
+//! [`key_event_target`]). The page's listeners see `keydown`, `keypress` for the characters typed,
+//! and `keyup` first; a `keydown` they cancel has no default action and types no `keypress`.
+//! Otherwise the default action runs: a focused link follows on Enter, and the navigation keys
+//! scroll the focused element's scroll container (or the page). While an editable field has focus
+//! the keys are its own and nothing scrolls. Shortcuts with Control, Alt or Meta held are left to
+//! the UA.
+
+use crate::events::Modifiers;
+use crate::html::{EngineDocument, RenderConfiguration};
+use gosub_interface::document::Document as _;
+use gosub_interface::node::NodeType;
+use gosub_shared::node::NodeId;
+
+/// Distance one arrow key press scrolls, in CSS pixels.
+pub const LINE_SCROLL: f64 = 40.0;
+
+/// Fraction of the viewport Page Up/Down and Space scroll, so a little of the previous screen
+/// stays visible.
+pub const PAGE_SCROLL_FRACTION: f64 = 0.875;
+
+/// The scroll delta `key` (a DOM `KeyboardEvent.key` value) asks for, or `None` when it doesn't
+/// scroll. `page_height` is the scrollable height, used by Home and End.
+pub fn scroll_delta(key: &str, modifiers: Modifiers, viewport_height: f64, page_height: f64) -> Option<(f64, f64)> {
+    if modifiers.intersects(Modifiers::CONTROL | Modifiers::ALT | Modifiers::META) {
+        return None;
+    }
+    let page = viewport_height * PAGE_SCROLL_FRACTION;
+    let delta = match key {
+        "ArrowUp" => (0.0, -LINE_SCROLL),
+        "ArrowDown" => (0.0, LINE_SCROLL),
+        "ArrowLeft" => (-LINE_SCROLL, 0.0),
+        "ArrowRight" => (LINE_SCROLL, 0.0),
+        "PageUp" => (0.0, -page),
+        "PageDown" => (0.0, page),
+        " " | "Spacebar" if modifiers.contains(Modifiers::SHIFT) => (0.0, -page),
+        " " | "Spacebar" => (0.0, page),
+        "Home" => (0.0, -page_height.max(viewport_height)),
+        "End" => (0.0, page_height.max(viewport_height)),
+        _ => return None,
+    };
+    Some(delta)
+}
+
+/// The element key events go to: `focused`, or else the document's `<body>`, or its root element
+/// when there is no body.
+pub fn key_event_target<C: RenderConfiguration>(doc: &EngineDocument<C>, focused: Option<NodeId>) -> Option<NodeId> {
+    if focused.is_some() {
+        return focused;
+    }
+    let root = doc
+        .children(doc.root())
+        .iter()
+        .copied()
+        .find(|&id| doc.node_type(id) == NodeType::ElementNode)?;
+    let body = doc
+        .children(root)
+        .iter()
+        .copied()
+        .find(|&id| doc.tag_name(id) == Some("body"));
+    Some(body.unwrap_or(root))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::testing::parse;
+
+    #[test]
+    fn keys_go_to_the_focused_element_or_the_body() {
+        let doc = parse(r#"<body><input id="name"></body>"#);
+        let input = doc.node_by_named_id("name");
+        assert_eq!(key_event_target(&doc, input), input);
+
+        let body = key_event_target(&doc, None).unwrap();
+        assert_eq!(doc.tag_name(body), Some("body"));
+    }
+
+    #[test]
+    fn navigation_keys_scroll() {
+        let none = Modifiers::empty();
+        assert_eq!(scroll_delta("ArrowDown", none, 800.0, 3000.0), Some((0.0, LINE_SCROLL)));
+        assert_eq!(
+            scroll_delta("ArrowLeft", none, 800.0, 3000.0),
+            Some((-LINE_SCROLL, 0.0))
+        );
+        assert_eq!(scroll_delta("PageDown", none, 800.0, 3000.0), Some((0.0, 700.0)));
+        assert_eq!(scroll_delta(" ", none, 800.0, 3000.0), Some((0.0, 700.0)));
+        assert_eq!(scroll_delta(" ", Modifiers::SHIFT, 800.0, 3000.0), Some((0.0, -700.0)));
+        assert_eq!(scroll_delta("End", none, 800.0, 3000.0), Some((0.0, 3000.0)));
+        assert_eq!(scroll_delta("a", none, 800.0, 3000.0), None);
+    }
+
+    #[test]
+    fn shortcuts_are_left_to_the_ua() {
+        assert_eq!(scroll_delta("ArrowDown", Modifiers::CONTROL, 800.0, 3000.0), None);
+        assert_eq!(scroll_delta("Home", Modifiers::ALT, 800.0, 3000.0), None);
+    }
+}
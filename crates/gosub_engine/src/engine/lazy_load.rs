@@ -0,0 +1,124 @@
+//! Lazy loading: `<img loading=lazy>` and `<iframe loading=lazy>`.
+//!
+//! Layout doesn't fetch a lazy image it hasn't loaded yet; the image keeps its placeholder size
+//! from its `width`/`height` attributes. The tab doesn't load lazy iframes with the document's
+//! other frames either. After every layout and scroll, the browsing context checks which lazy
+//! elements came within `renderer.lazy_load.image_margin` or `renderer.lazy_load.iframe_margin`
+//! CSS pixels of the viewport and starts their loads. Elements without a box (`display: none`)
+//! are never loaded.
+
+use crate::html::{EngineDocument, RenderConfiguration};
+use gosub_interface::document::Document as _;
+use gosub_render_pipeline::common::geo::Rect;
+use gosub_render_pipeline::layering::layer::LayerList;
+use gosub_shared::node::NodeId;
+use std::collections::HashMap;
+
+/// What loads once a lazy element comes near the viewport
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum LazyKind {
+    /// An image, by its absolute `src`
+    Image(String),
+    /// A child frame, loaded by the tab worker
+    Frame,
+}
+
+/// A lazy element that has been laid out but not loaded
+#[derive(Debug, Clone)]
+pub(crate) struct LazyElement {
+    pub node: NodeId,
+    pub kind: LazyKind,
+    /// Border box in page coordinates
+    pub rect: Rect,
+}
+
+/// Whether element `id` has `loading=lazy`.
+pub(crate) fn is_lazy<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId) -> bool {
+    doc.attribute(id, "loading")
+        .is_some_and(|loading| loading.trim().eq_ignore_ascii_case("lazy"))
+}
+
+/// The lazy images and iframes of `doc` that got a box in `layer_list`, in tree order. `resolve`
+/// makes an image's `src` absolute.
+pub(crate) fn lazy_elements<C: RenderConfiguration>(
+    doc: &EngineDocument<C>,
+    layer_list: &LayerList,
+    resolve: impl Fn(&str) -> String,
+) -> Vec<LazyElement> {
+    let mut found = Vec::new();
+    let mut stack = vec![doc.root()];
+    while let Some(id) = stack.pop() {
+        let kind = match doc.tag_name(id) {
+            Some("img") if is_lazy(doc, id) => doc.attribute(id, "src").map(|src| LazyKind::Image(resolve(src))),
+            Some("iframe") if is_lazy(doc, id) => Some(LazyKind::Frame),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            found.push((id, kind));
+        }
+        stack.extend(doc.children(id).iter().rev().copied());
+    }
+    if found.is_empty() {
+        return Vec::new();
+    }
+
+    // The first layout element generated for each node carries its box.
+    let mut boxes = HashMap::new();
+    for el in layer_list.layout_tree.arena.values() {
+        if !found.iter().any(|(id, _)| *id == el.dom_node_id) {
+            continue;
+        }
+        boxes
+            .entry(el.dom_node_id)
+            .and_modify(|(lei, rect): &mut (u64, Rect)| {
+                if el.id.as_u64() < *lei {
+                    *lei = el.id.as_u64();
+                    *rect = el.box_model.border_box;
+                }
+            })
+            .or_insert((el.id.as_u64(), el.box_model.border_box));
+    }
+    found
+        .into_iter()
+        .filter_map(|(node, kind)| {
+            let (_, rect) = boxes.get(&node)?;
+            Some(LazyElement {
+                node,
+                kind,
+                rect: *rect,
+            })
+        })
+        .collect()
+}
+
+/// Whether `rect` is within `margin` pixels of `view`, edges included.
+pub(crate) fn near_viewport(rect: &Rect, view: &Rect, margin: f64) -> bool {
+    rect.x <= view.x + view.width + margin
+        && rect.x + rect.width >= view.x - margin
+        && rect.y <= view.y + view.height + margin
+        && rect.y + rect.height >= view.y - margin
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn margin_around_the_viewport() {
+        // Scrolled 1000px down a 800×600 viewport
+        let view = Rect::new(0.0, 1000.0, 800.0, 600.0);
+        let at = |y: f64| Rect::new(10.0, y, 100.0, 50.0);
+
+        assert!(near_viewport(&at(1200.0), &view, 0.0));
+        assert!(!near_viewport(&at(1700.0), &view, 0.0));
+        assert!(near_viewport(&at(1700.0), &view, 100.0));
+        assert!(!near_viewport(&at(1701.0), &view, 100.0));
+        // Above the viewport counts too, for pages restored scrolled down.
+        assert!(near_viewport(&at(900.0), &view, 50.0));
+        assert!(!near_viewport(&at(800.0), &view, 100.0));
+        // A bare <img> without a size still has a position.
+        assert!(near_viewport(&Rect::new(0.0, 1600.0, 0.0, 0.0), &view, 0.0));
+        // Off to the side of a horizontally scrolling page
+        assert!(!near_viewport(&Rect::new(2000.0, 1200.0, 10.0, 10.0), &view, 1000.0));
+    }
+}
@@ -0,0 +1,313 @@
+//! Node descriptions for inspectors.
+//!
+//! A [`NodeDesc`] describes a DOM node and its subtree: name, attributes, the computed values of
+//! the properties an inspector shows first, the laid-out box with its margin, border and padding,
+//! and the style rules matching it. It prints as an indented tree for logs, and serializes (to
+//! JSON, say) for external inspectors to consume.
+//!
+//! Build one for the current document with [`BrowsingContext::describe_node`].
+//!
+//! [`BrowsingContext::describe_node`]: crate::BrowsingContext::describe_node
+
+use crate::html::{EngineDocument, RenderConfiguration};
+use gosub_interface::css3::{CssOrigin, CssSystem, MatchedRule};
+use gosub_interface::document::Document as _;
+use gosub_interface::node::NodeType;
+use gosub_render_pipeline::common::document::pipeline_doc::PipelineDocument;
+use gosub_render_pipeline::common::document::style::StyleProperty;
+use gosub_render_pipeline::layouter::box_model::{BoxModel, Edges};
+use gosub_shared::node::NodeId;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+
+/// Properties whose computed values go into [`NodeDesc::computed`]
+const SUMMARY_PROPERTIES: [StyleProperty; 14] = [
+    StyleProperty::Display,
+    StyleProperty::Position,
+    StyleProperty::BoxSizing,
+    StyleProperty::Width,
+    StyleProperty::Height,
+    StyleProperty::Color,
+    StyleProperty::BackgroundColor,
+    StyleProperty::FontFamily,
+    StyleProperty::FontSize,
+    StyleProperty::FontWeight,
+    StyleProperty::LineHeight,
+    StyleProperty::OverflowX,
+    StyleProperty::OverflowY,
+    StyleProperty::Opacity,
+];
+
+/// A DOM node and its subtree, as shown to inspectors.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeDesc {
+    pub node_id: usize,
+    /// Tag name for elements; `#document`, `#doctype`, `#text` or `#comment` otherwise
+    pub name: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, String>,
+    /// Contents of text and comment nodes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Computed values of the most looked at properties, for laid out elements
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub computed: BTreeMap<String, String>,
+    /// The element's box, when it was laid out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub box_metrics: Option<BoxMetrics>,
+    /// Rules matching the element, in the order the cascade applies them
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub matched_rules: Vec<RuleDesc>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<NodeDesc>,
+}
+
+/// The laid out box of an element, in CSS pixels
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BoxMetrics {
+    /// Left edge of the border box, in page coordinates
+    pub x: f64,
+    /// Top edge of the border box, in page coordinates
+    pub y: f64,
+    /// Width of the border box
+    pub width: f64,
+    /// Height of the border box
+    pub height: f64,
+    pub margin: EdgeSizes,
+    pub border: EdgeSizes,
+    pub padding: EdgeSizes,
+}
+
+impl From<&BoxModel> for BoxMetrics {
+    fn from(box_model: &BoxModel) -> Self {
+        let b = box_model.border_box;
+        BoxMetrics {
+            x: b.x,
+            y: b.y,
+            width: b.width,
+            height: b.height,
+            margin: EdgeSizes::from(&box_model.margin),
+            border: EdgeSizes::from(&box_model.border),
+            padding: EdgeSizes::from(&box_model.padding),
+        }
+    }
+}
+
+/// Widths of the four sides of a margin, border or padding
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct EdgeSizes {
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub left: f64,
+}
+
+impl From<&Edges> for EdgeSizes {
+    fn from(edges: &Edges) -> Self {
+        EdgeSizes {
+            top: edges.top,
+            right: edges.right,
+            bottom: edges.bottom,
+            left: edges.left,
+        }
+    }
+}
+
+/// A style rule matching an element
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RuleDesc {
+    /// The selector that matched
+    pub selector: String,
+    /// Id, class and type counts of `selector`
+    pub specificity: [u32; 3],
+    /// `user-agent`, `user` or `author`
+    pub origin: &'static str,
+    /// The stylesheet the rule is in
+    pub url: String,
+    pub declarations: Vec<DeclarationDesc>,
+}
+
+impl From<MatchedRule> for RuleDesc {
+    fn from(rule: MatchedRule) -> Self {
+        let (ids, classes, types) = rule.specificity;
+        RuleDesc {
+            selector: rule.selector,
+            specificity: [ids, classes, types],
+            origin: match rule.origin {
+                CssOrigin::UserAgent => "user-agent",
+                CssOrigin::User => "user",
+                CssOrigin::Author => "author",
+            },
+            url: rule.url,
+            declarations: rule
+                .declarations
+                .into_iter()
+                .map(|declaration| DeclarationDesc {
+                    property: declaration.property,
+                    value: declaration.value,
+                    important: declaration.important,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A declaration of a [`RuleDesc`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeclarationDesc {
+    pub property: String,
+    pub value: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub important: bool,
+}
+
+impl NodeDesc {
+    /// Describes `id` and its subtree in `doc`. `styles` is the styled document of the active
+    /// layout and `boxes` maps elements to their laid out box (see
+    /// `BrowsingContext::describe_node`); without them nodes have no computed values or boxes.
+    pub fn build<C: RenderConfiguration>(
+        doc: &EngineDocument<C>,
+        styles: Option<&dyn PipelineDocument>,
+        boxes: &HashMap<NodeId, BoxModel>,
+        id: NodeId,
+    ) -> Self {
+        let mut desc = NodeDesc {
+            node_id: usize::from(id),
+            name: String::new(),
+            attributes: BTreeMap::new(),
+            text: None,
+            computed: BTreeMap::new(),
+            box_metrics: None,
+            matched_rules: Vec::new(),
+            children: Vec::new(),
+        };
+
+        match doc.node_type(id) {
+            NodeType::DocumentNode => desc.name = "#document".into(),
+            NodeType::DocTypeNode => desc.name = "#doctype".into(),
+            NodeType::TextNode => {
+                desc.name = "#text".into();
+                desc.text = doc.text_value(id).map(str::to_string);
+            }
+            NodeType::CommentNode => {
+                desc.name = "#comment".into();
+                desc.text = doc.comment_value(id).map(str::to_string);
+            }
+            NodeType::ElementNode => {
+                desc.name = doc.tag_name(id).unwrap_or_default().to_string();
+                if let Some(attributes) = doc.attributes(id) {
+                    desc.attributes = attributes.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+                }
+                desc.box_metrics = boxes.get(&id).map(BoxMetrics::from);
+                // Only elements that were laid out have meaningful computed values.
+                if let Some(styles) = styles.filter(|_| desc.box_metrics.is_some()) {
+                    for prop in &SUMMARY_PROPERTIES {
+                        let value = styles.get_style(id, prop).to_css_string();
+                        desc.computed.insert(prop.css_name().to_string(), value);
+                    }
+                }
+                desc.matched_rules = C::CssSystem::matched_rules::<C>(doc, id, doc.stylesheets())
+                    .into_iter()
+                    .map(RuleDesc::from)
+                    .collect();
+            }
+        }
+
+        desc.children = doc
+            .children(id)
+            .iter()
+            .map(|&child| Self::build(doc, styles, boxes, child))
+            .collect();
+        desc
+    }
+
+    fn fmt_tree(&self, f: &mut Formatter<'_>, depth: usize) -> std::fmt::Result {
+        write!(f, "{:indent$}", "", indent = depth * 2)?;
+        match &self.text {
+            Some(text) if self.name == "#comment" => write!(f, "<!-- {} -->", text.trim())?,
+            Some(text) => write!(f, "{:?}", text.trim())?,
+            None if self.name.starts_with('#') => f.write_str(&self.name)?,
+            None => {
+                write!(f, "<{}", self.name)?;
+                for (name, value) in &self.attributes {
+                    write!(f, " {name}=\"{value}\"")?;
+                }
+                f.write_str(">")?;
+            }
+        }
+        if let Some(b) = &self.box_metrics {
+            write!(f, " {}×{} at ({}, {})", b.width, b.height, b.x, b.y)?;
+        }
+        writeln!(f)?;
+
+        for child in &self.children {
+            child.fmt_tree(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for NodeDesc {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.fmt_tree(f, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::testing::parse_at;
+
+    const PAGE_URL: &str = "https://example.com/";
+
+    fn find<'a>(desc: &'a NodeDesc, name: &str) -> Option<&'a NodeDesc> {
+        if desc.name == name {
+            return Some(desc);
+        }
+        desc.children.iter().find_map(|child| find(child, name))
+    }
+
+    #[test]
+    fn describes_rules_and_boxes() {
+        let doc = parse_at(
+            PAGE_URL,
+            r#"<html><head><style>
+                p { color: red }
+                div > p.note, #main { margin: 0 auto; color: blue !important }
+            </style></head><body><div><p class="note">Hi</p></div></body></html>"#,
+        );
+        let p_id = find(&NodeDesc::build(&doc, None, &HashMap::new(), doc.root()), "p")
+            .map(|p| NodeId::from(p.node_id as u64))
+            .unwrap();
+        let mut boxes = HashMap::new();
+        let mut box_model = BoxModel::ZERO;
+        box_model.border_box.width = 320.0;
+        box_model.border_box.height = 48.0;
+        box_model.margin.left = 8.0;
+        boxes.insert(p_id, box_model);
+
+        let desc = NodeDesc::build(&doc, None, &boxes, doc.root());
+        let p = find(&desc, "p").unwrap();
+        assert_eq!(p.attributes.get("class").map(String::as_str), Some("note"));
+        assert_eq!(p.box_metrics.unwrap().width, 320.0);
+        assert_eq!(p.box_metrics.unwrap().margin.left, 8.0);
+
+        // Least specific first, and only the selector of the list that matched.
+        let selectors: Vec<_> = p.matched_rules.iter().map(|r| r.selector.as_str()).collect();
+        assert_eq!(selectors, ["p", "div > p.note"]);
+        let rule = &p.matched_rules[1];
+        assert_eq!(rule.specificity, [0, 1, 2]);
+        assert_eq!(rule.origin, "author");
+        assert_eq!(rule.declarations[0].value, "0 auto");
+        assert!(rule.declarations[1].important);
+
+        let json = serde_json::to_value(p).unwrap();
+        assert_eq!(json["matched_rules"][1]["declarations"][1]["property"], "color");
+        assert_eq!(json["box_metrics"]["height"], 48.0);
+        assert_eq!(json["children"][0]["text"], "Hi");
+
+        let tree = desc.to_string();
+        assert!(tree.contains("  <p class=\"note\"> 320×48 at (0, 0)\n"), "{tree}");
+    }
+}
@@ -0,0 +1,218 @@
+//! Permission decisions kept in the engine's settings.
+//!
+//! Each [`PermissionName`] has two settings, `permissions.<name>.granted` and
+//! `permissions.<name>.denied`, listing the origins the UA decided for. They are ordinary
+//! settings, so they persist with the config's storage adapter and the UA can edit them (a
+//! "site settings" page) like any other.
+//!
+//! The embedder can override the stored decisions with a [`PermissionHandler`] on the zone's
+//! [`ZoneConfig`](crate::zone::ZoneConfig), which is asked first. On top of that, the
+//! [`PermissionsPolicy`] of the tab's current document can take policy-controlled features away;
+//! [`DocumentPermissions`] combines the two for the tab and its script thread.
+
+use gosub_config::settings::Setting;
+use gosub_config::Config;
+use gosub_web_platform::permissions::{PermissionName, PermissionState, PermissionStore};
+use gosub_web_platform::permissions_policy::PermissionsPolicy;
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+type PermissionCallback = dyn Fn(&str, PermissionName) -> Option<PermissionState> + Send + Sync;
+
+/// An embedder callback deciding permissions before the stored decisions are looked at. It gets
+/// the serialized origin and the permission, and returns `None` to leave it to the store.
+#[derive(Clone)]
+pub struct PermissionHandler(Arc<PermissionCallback>);
+
+impl PermissionHandler {
+    pub fn new(f: impl Fn(&str, PermissionName) -> Option<PermissionState> + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    pub fn decide(&self, origin: &str, name: PermissionName) -> Option<PermissionState> {
+        (self.0)(origin, name)
+    }
+}
+
+impl std::fmt::Debug for PermissionHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PermissionHandler")
+    }
+}
+
+/// A [`PermissionStore`] on top of a [`Config`].
+#[derive(Clone)]
+pub struct ConfigPermissionStore {
+    config: Config,
+    handler: Option<PermissionHandler>,
+}
+
+impl ConfigPermissionStore {
+    pub fn new(config: Config) -> Self {
+        Self { config, handler: None }
+    }
+
+    /// The store with `handler` deciding before the settings do.
+    pub fn with_handler(mut self, handler: Option<PermissionHandler>) -> Self {
+        self.handler = handler;
+        self
+    }
+
+    fn key(name: PermissionName, state: PermissionState) -> String {
+        format!("permissions.{}.{}", name.as_str(), state.as_str())
+    }
+
+    /// Adds `origin` to, or removes it from, the origins in the `state` setting of `name`.
+    fn update(&self, name: PermissionName, state: PermissionState, origin: &str, listed: bool) {
+        let key = Self::key(name, state);
+        let mut origins = self.config.get_map(&key);
+        if origins.iter().any(|o| o == origin) == listed {
+            return;
+        }
+        origins.retain(|o| o != origin);
+        if listed {
+            origins.push(origin.to_string());
+        }
+        if let Err(e) = self.config.set(&key, Setting::Map(origins)) {
+            log::warn!("Could not store the {name} permission of {origin}: {e}");
+        }
+    }
+}
+
+impl std::fmt::Debug for ConfigPermissionStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConfigPermissionStore").finish_non_exhaustive()
+    }
+}
+
+impl PermissionStore for ConfigPermissionStore {
+    fn state(&self, origin: &str, name: PermissionName) -> PermissionState {
+        if origin == "null" {
+            return PermissionState::Denied;
+        }
+        if let Some(state) = self.handler.as_ref().and_then(|h| h.decide(origin, name)) {
+            return state;
+        }
+        let listed = |state| self.config.get_map(&Self::key(name, state)).iter().any(|o| o == origin);
+        if listed(PermissionState::Denied) {
+            PermissionState::Denied
+        } else if listed(PermissionState::Granted) {
+            PermissionState::Granted
+        } else {
+            PermissionState::Prompt
+        }
+    }
+
+    fn set_state(&self, origin: &str, name: PermissionName, state: PermissionState) {
+        if origin == "null" {
+            return;
+        }
+        self.update(
+            name,
+            PermissionState::Granted,
+            origin,
+            state == PermissionState::Granted,
+        );
+        self.update(name, PermissionState::Denied, origin, state == PermissionState::Denied);
+    }
+}
+
+/// The permissions of a tab's current document: the zone's decisions, except for the features
+/// the document's `Permissions-Policy` doesn't allow its origin.
+#[derive(Debug)]
+pub(crate) struct DocumentPermissions {
+    store: Arc<dyn PermissionStore>,
+    /// The document's origin and policy
+    policy: RwLock<(String, PermissionsPolicy)>,
+}
+
+impl DocumentPermissions {
+    pub fn new(store: Arc<dyn PermissionStore>) -> Self {
+        Self {
+            store,
+            policy: RwLock::new(("null".to_string(), PermissionsPolicy::new())),
+        }
+    }
+
+    /// Switches to the policy of a new document of `origin`.
+    pub fn set_policy(&self, origin: String, policy: PermissionsPolicy) {
+        *self.policy.write() = (origin, policy);
+    }
+}
+
+impl PermissionStore for DocumentPermissions {
+    fn state(&self, origin: &str, name: PermissionName) -> PermissionState {
+        let allowed = {
+            let (document_origin, policy) = &*self.policy.read();
+            policy.allows(name, origin, document_origin)
+        };
+        if !allowed {
+            return PermissionState::Denied;
+        }
+        self.store.state(origin, name)
+    }
+
+    fn set_state(&self, origin: &str, name: PermissionName, state: PermissionState) {
+        self.store.set_state(origin, name, state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::settings_store::default_config;
+
+    #[test]
+    fn decisions_land_in_the_settings() {
+        let config = default_config();
+        let store = ConfigPermissionStore::new(config.clone());
+        let name = PermissionName::Notifications;
+
+        store.set_state("https://a.example", name, PermissionState::Granted);
+        store.set_state("https://b.example", name, PermissionState::Denied);
+        assert_eq!(store.state("https://a.example", name), PermissionState::Granted);
+        assert_eq!(store.state("https://b.example", name), PermissionState::Denied);
+        assert_eq!(
+            config.get_map("permissions.notifications.granted"),
+            vec!["https://a.example".to_string()]
+        );
+
+        store.set_state("https://a.example", name, PermissionState::Denied);
+        assert!(config.get_map("permissions.notifications.granted").is_empty());
+        store.set_state("https://b.example", name, PermissionState::Prompt);
+        assert_eq!(store.state("https://b.example", name), PermissionState::Prompt);
+    }
+
+    #[test]
+    fn handler_and_policy_come_first() {
+        let config = default_config();
+        let handler = PermissionHandler::new(|origin, name| {
+            (origin == "https://kiosk.example" && name == PermissionName::Geolocation)
+                .then_some(PermissionState::Granted)
+        });
+        let store = ConfigPermissionStore::new(config).with_handler(Some(handler));
+        store.set_state(
+            "https://kiosk.example",
+            PermissionName::Geolocation,
+            PermissionState::Denied,
+        );
+        assert_eq!(
+            store.state("https://kiosk.example", PermissionName::Geolocation),
+            PermissionState::Granted
+        );
+
+        let page = "https://a.example";
+        store.set_state(page, PermissionName::ClipboardRead, PermissionState::Granted);
+        store.set_state(page, PermissionName::ClipboardWrite, PermissionState::Granted);
+        let permissions = DocumentPermissions::new(Arc::new(store));
+        permissions.set_policy(page.to_string(), PermissionsPolicy::parse("clipboard-read=()"));
+        assert_eq!(
+            permissions.state(page, PermissionName::ClipboardRead),
+            PermissionState::Denied
+        );
+        assert_eq!(
+            permissions.state(page, PermissionName::ClipboardWrite),
+            PermissionState::Granted
+        );
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! Each module defines a trait for parsing streams and byte slices of the respective asset type.
 
+use crate::cookies::CookieJarHandle;
 use crate::engine::resource_pipeline::css::{CssPipeline, CssPipelineImpl};
 use crate::engine::resource_pipeline::font::{FontPipeline, FontPipelineImpl};
 use crate::engine::resource_pipeline::html::{HtmlPipeline, HtmlPipelineImpl};
@@ -9,7 +10,13 @@ use crate::engine::resource_pipeline::image::{ImagePipeline, ImagePipelineImpl};
 use crate::engine::resource_pipeline::js::{JsPipeline, JsPipelineImpl};
 use crate::engine::types::IoChannel;
 use crate::html::RenderConfiguration;
+use crate::net::blocking::ContentBlocker;
+use crate::net::mixed_content::MixedContentGuard;
+use crate::net::types::FetchResultMeta;
+use crate::storage::PartitionKey;
 use crate::zone::ZoneId;
+use gosub_css3::media::MediaEnvironment;
+use std::sync::Arc;
 
 pub mod css;
 pub mod font;
@@ -30,18 +37,41 @@ pub struct ResourcePipelines<C: RenderConfiguration> {
 }
 
 impl<C: RenderConfiguration> ResourcePipelines<C> {
-    pub fn new(zone_id: ZoneId, io_tx: IoChannel, accept_language: Option<String>, max_document_bytes: usize) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        zone_id: ZoneId,
+        partition: PartitionKey,
+        io_tx: IoChannel,
+        accept_language: Option<String>,
+        max_document_bytes: usize,
+        content_blocker: Option<Arc<ContentBlocker>>,
+        cookie_jar: Option<CookieJarHandle>,
+        media: MediaEnvironment,
+        mixed_content: MixedContentGuard,
+    ) -> Self {
         Self {
             html: Box::new(HtmlPipelineImpl::new(
                 zone_id,
+                partition,
                 io_tx,
                 accept_language,
                 max_document_bytes,
+                content_blocker,
+                cookie_jar,
+                media,
+                mixed_content,
             )),
-            css: Box::new(CssPipelineImpl {}),
-            js: Box::new(JsPipelineImpl {}),
+            css: Box::new(CssPipelineImpl),
+            js: Box::new(JsPipelineImpl),
             images: Box::new(ImagePipelineImpl {}),
             fonts: Box::new(FontPipelineImpl {}),
         }
     }
 }
+
+/// The `Content-Type` a response was sent with.
+fn content_type(meta: &FetchResultMeta) -> Option<&str> {
+    meta.headers
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+}
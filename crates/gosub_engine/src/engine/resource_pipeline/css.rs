@@ -1,4 +1,6 @@
+use crate::engine::resource_pipeline::content_type;
 use crate::engine::types::PeekBuf;
+use crate::net::charset::decode_stylesheet;
 use crate::net::types::FetchResultMeta;
 use crate::net::{stream_to_bytes, SharedBody};
 use async_trait::async_trait;
@@ -24,18 +26,18 @@ pub struct CssPipelineImpl;
 impl CssPipeline for CssPipelineImpl {
     async fn parse_stream(
         &mut self,
-        _meta: FetchResultMeta,
+        meta: FetchResultMeta,
         peek_buf: PeekBuf,
         shared: Arc<SharedBody>,
     ) -> anyhow::Result<DummyStylesheet> {
         // Normally, we send chunks to the CSS parser. Right now, we just collect everything
         match stream_to_bytes(peek_buf, shared).await {
-            Ok(buf) => Ok(String::from_utf8_lossy(buf.as_ref()).to_string()),
+            Ok(buf) => Ok(decode_stylesheet(buf.as_ref(), content_type(&meta), None)),
             Err(e) => Err(anyhow::anyhow!("Failed to read CSS stream: {}", e)),
         }
     }
 
-    async fn parse_bytes(&mut self, _meta: FetchResultMeta, body: &[u8]) -> anyhow::Result<DummyStylesheet> {
-        Ok(String::from_utf8_lossy(body).to_string())
+    async fn parse_bytes(&mut self, meta: FetchResultMeta, body: &[u8]) -> anyhow::Result<DummyStylesheet> {
+        Ok(decode_stylesheet(body, content_type(&meta), None))
     }
 }
@@ -1,21 +1,33 @@
+use crate::cookies::{CookieJarHandle, SameSiteContext};
+use crate::engine::resource_pipeline::content_type;
 use crate::engine::types::{IoChannel, PeekBuf, RequestId};
+use crate::html::stylesheets::{parse_author_stylesheet, style_sources, StyleSource};
 use crate::html::{parse_main_document_stream, EngineDocument, RenderConfiguration, ResourceHint};
+use crate::net::blocking::ContentBlocker;
+use crate::net::charset::decode_stylesheet;
+use crate::net::mixed_content::MixedContentGuard;
 use crate::net::req_ref_tracker::REF_REGISTRY;
-use crate::net::types::{FetchHandle, FetchRequest, FetchResultMeta, Initiator};
+use crate::net::types::{FetchHandle, FetchRequest, FetchResultMeta, Initiator, ResourceKind};
 use crate::net::{submit_to_io, SharedBody};
+use crate::storage::PartitionKey;
 use crate::util::spawn_named;
 use crate::zone::ZoneId;
 use anyhow::anyhow;
 use async_trait::async_trait;
 use bytes::Bytes;
 use futures_util::stream;
+use gosub_css3::media::MediaEnvironment;
+use gosub_interface::document::Document as _;
 use gosub_shared::timing_guard;
 use http::Method;
 use parking_lot::Mutex;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::io::AsyncRead;
+use tokio::sync::oneshot;
 use tokio::task::JoinHandle;
 use tokio_util::io::StreamReader;
+use url::Url;
 
 #[async_trait]
 pub trait HtmlPipeline<C: RenderConfiguration> {
@@ -40,19 +52,49 @@ pub trait HtmlPipeline<C: RenderConfiguration> {
 pub struct HtmlPipelineImpl {
     io_tx: IoChannel,
     zone_id: ZoneId,
+    /// Storage partition of the tab, which subresource requests are cached under
+    partition: PartitionKey,
     /// `Accept-Language` header value sent with discovered subresource requests.
     accept_language: Option<String>,
     /// Max document size in bytes (`net.document.max_bytes`); larger documents are truncated.
     max_document_bytes: usize,
+    /// Filter rules checked before discovered subresources are requested.
+    content_blocker: Option<Arc<ContentBlocker>>,
+    /// The zone's cookies, sent with discovered subresource requests and updated from their
+    /// responses.
+    cookie_jar: Option<CookieJarHandle>,
+    /// What the `media` of stylesheets is matched against
+    media: MediaEnvironment,
+    /// Checks discovered subresources of secure documents that would load insecurely
+    mixed_content: MixedContentGuard,
 }
 
+/// A stylesheet read to the end, or `None` if it couldn't be fetched
+type StylesheetResponse = Option<(FetchResultMeta, Bytes)>;
+
 impl HtmlPipelineImpl {
-    pub fn new(zone_id: ZoneId, io_tx: IoChannel, accept_language: Option<String>, max_document_bytes: usize) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        zone_id: ZoneId,
+        partition: PartitionKey,
+        io_tx: IoChannel,
+        accept_language: Option<String>,
+        max_document_bytes: usize,
+        content_blocker: Option<Arc<ContentBlocker>>,
+        cookie_jar: Option<CookieJarHandle>,
+        media: MediaEnvironment,
+        mixed_content: MixedContentGuard,
+    ) -> Self {
         Self {
             io_tx,
             zone_id,
+            partition,
             accept_language,
             max_document_bytes,
+            content_blocker,
+            cookie_jar,
+            media,
+            mixed_content,
         }
     }
 
@@ -73,6 +115,7 @@ impl HtmlPipelineImpl {
 
         let io_tx = self.io_tx.clone();
         let zone_id = self.zone_id;
+        let partition = self.partition.clone();
         let parent_ref = request.reference;
         let parent_cancel = handle.cancel.clone();
 
@@ -82,6 +125,10 @@ impl HtmlPipelineImpl {
         let child_handles_for_closure = child_handles.clone();
         let child_tasks_for_closure = child_tasks.clone();
 
+        // Stylesheet fetches, which hand their response back instead of dropping it
+        let stylesheets = Arc::new(Mutex::new(HashMap::<Url, oneshot::Receiver<StylesheetResponse>>::new()));
+        let stylesheets_for_closure = stylesheets.clone();
+
         let mut sub_headers = http::HeaderMap::new();
         if let Some(langs) = &self.accept_language {
             if let Ok(val) = langs.parse() {
@@ -89,7 +136,30 @@ impl HtmlPipelineImpl {
             }
         }
 
-        let mut on_discover = |hint: ResourceHint| {
+        let document_url = meta.final_url.clone();
+        let content_blocker = self.content_blocker.clone();
+        let cookie_jar = self.cookie_jar.clone();
+        let mixed_content = self.mixed_content.clone();
+
+        let mut on_discover = |mut hint: ResourceHint| {
+            if let Some(rule) = content_blocker
+                .as_ref()
+                .and_then(|b| b.check(&hint.url, hint.kind.into(), Some(&document_url)))
+            {
+                log::debug!("Not requesting {}: blocked by filter rule {rule}", hint.url);
+                return;
+            }
+            match mixed_content.check(&document_url, &hint.url, hint.kind) {
+                Some(url) => hint.url = url,
+                None => return,
+            }
+            let mut headers = sub_headers.clone();
+            if let Some(jar) = &cookie_jar {
+                let site = SameSiteContext::for_subrequest(&hint.url, &document_url);
+                jar.add_request_cookies(&mut headers, &hint.url, Some(&document_url), site);
+            }
+            let url = hint.url.clone();
+            let is_stylesheet = hint.kind == ResourceKind::Stylesheet;
             let sub_req_id = RequestId::new();
             REF_REGISTRY.register_request(sub_req_id, hint.kind, Initiator::Parser);
             let sub_req = FetchRequest::builder(Method::GET, hint.url)
@@ -98,27 +168,49 @@ impl HtmlPipelineImpl {
                 .with_priority(hint.priority)
                 .with_initiator(Initiator::Parser.to_net())
                 .with_kind(hint.kind.to_net())
-                .with_headers(sub_headers.clone())
+                .with_headers(headers)
                 .with_streaming(true)
                 .with_auto_decode(true)
                 .build();
 
             let io_tx_cloned = io_tx.clone();
+            let partition = partition.clone();
             let parent_cancel_cloned = parent_cancel.clone();
             let child_handles = child_handles_for_closure.clone();
             let child_tasks = child_tasks_for_closure.clone();
+            let cookie_jar = cookie_jar.clone();
+            let document_url = document_url.clone();
 
             // Parent cancelled, so we don't have to do anything
             if parent_cancel_cloned.is_cancelled() {
                 return;
             }
 
-            let join_handle = spawn_named("html-sub-resource", async move {
-                match submit_to_io(zone_id, sub_req, io_tx_cloned, Some(parent_cancel_cloned)).await {
-                    Ok((child_handle, rx)) => {
-                        child_handles.lock().push(child_handle);
+            let sheet_tx = is_stylesheet.then(|| {
+                let (tx, rx) = oneshot::channel();
+                stylesheets_for_closure.lock().insert(url, rx);
+                tx
+            });
 
-                        let _ = rx.await;
+            let join_handle = spawn_named("html-sub-resource", async move {
+                match submit_to_io(zone_id, partition, sub_req, io_tx_cloned, Some(parent_cancel_cloned)).await {
+                    Ok(pending) => {
+                        child_handles.lock().push(pending.handle().clone());
+
+                        // A stylesheet is read to the end: the document waits for it.
+                        if let Some(tx) = sheet_tx {
+                            let response = pending.bytes().await.ok();
+                            if let (Some((meta, _)), Some(jar)) = (&response, &cookie_jar) {
+                                jar.write()
+                                    .store_response_cookies(&meta.final_url, &meta.headers, Some(&document_url));
+                            }
+                            let _ = tx.send(response);
+                        } else if let (Ok(result), Some(jar)) = (pending.result().await, &cookie_jar) {
+                            if let Some(meta) = result.meta() {
+                                jar.write()
+                                    .store_response_cookies(&meta.final_url, &meta.headers, Some(&document_url));
+                            }
+                        }
                     }
                     Err(e) => {
                         log::warn!("Failed to submit discovered resource request: {:?}", e);
@@ -133,7 +225,7 @@ impl HtmlPipelineImpl {
 
         let _doc_timer = timing_guard!("html.document", meta.final_url.as_str());
         let res = parse_main_document_stream(
-            meta.final_url, // This is the base URL
+            meta.final_url.clone(), // This is the base URL
             reader,
             handle.cancel.clone(),
             cfg,
@@ -141,6 +233,16 @@ impl HtmlPipelineImpl {
         )
         .await;
 
+        // Stylesheets block rendering, so the document isn't handed on without them.
+        let res = match res {
+            Ok(mut doc) if !parent_cancel.is_cancelled() => {
+                let _css_timer = timing_guard!("html.stylesheets", meta.final_url.as_str());
+                load_stylesheets(&mut doc, &meta.final_url, &self.media, &stylesheets, &mut on_discover).await;
+                Ok(doc)
+            }
+            res => res,
+        };
+
         // Cancel the parent token so that all child fetch tokens (which are children of
         // parent_cancel via child_token()) are also cancelled. This works regardless of
         // whether the spawned submission tasks have run yet, since the cancellation
@@ -163,6 +265,64 @@ impl HtmlPipelineImpl {
     }
 }
 
+/// Adds the stylesheets of `doc` to it in cascade order, waiting for the linked ones to arrive.
+/// `fetches` holds the stylesheet fetches started while parsing; linked sheets the discovery
+/// scan missed are requested through `request`.
+async fn load_stylesheets<C: RenderConfiguration>(
+    doc: &mut EngineDocument<C>,
+    document_url: &Url,
+    media: &MediaEnvironment,
+    fetches: &Mutex<HashMap<Url, oneshot::Receiver<StylesheetResponse>>>,
+    mut request: impl FnMut(ResourceHint),
+) {
+    let sources = style_sources(doc, document_url, media);
+    for source in &sources {
+        if let StyleSource::Linked(url) = source {
+            if !fetches.lock().contains_key(url) {
+                request(ResourceHint::stylesheet(url.clone()));
+            }
+        }
+    }
+
+    let inline_url = format!("{document_url}#inline");
+    // A sheet linked twice is fetched once, but cascades at both places.
+    let mut loaded = HashMap::<Url, Option<String>>::new();
+    for source in sources {
+        let sheet = match source {
+            StyleSource::Inline(css) => parse_author_stylesheet::<C>(&css, &inline_url),
+            StyleSource::Linked(url) => {
+                let css = match loaded.get(&url) {
+                    Some(css) => css.clone(),
+                    None => {
+                        // No fetch at all when a filter rule blocked it.
+                        let rx = fetches.lock().remove(&url);
+                        let response = match rx {
+                            Some(rx) => rx.await.ok().flatten(),
+                            None => None,
+                        };
+                        let css = match response {
+                            Some((meta, body)) if (200..300).contains(&meta.status) => {
+                                Some(decode_stylesheet(&body, content_type(&meta), None))
+                            }
+                            Some((meta, _)) => {
+                                log::debug!("Stylesheet {url} returned status {}, not applied", meta.status);
+                                None
+                            }
+                            None => None,
+                        };
+                        loaded.insert(url.clone(), css.clone());
+                        css
+                    }
+                };
+                css.and_then(|css| parse_author_stylesheet::<C>(&css, url.as_str()))
+            }
+        };
+        if let Some(sheet) = sheet {
+            doc.add_stylesheet(sheet);
+        }
+    }
+}
+
 #[async_trait]
 impl<C: RenderConfiguration> HtmlPipeline<C> for HtmlPipelineImpl {
     async fn parse_stream(
@@ -197,12 +357,11 @@ mod tests {
     use crate::events::IoCommand;
     use crate::html::DefaultRenderConfig;
     use crate::net::req_ref_tracker::RequestReference;
-    use crate::net::types::{Priority, ResourceKind};
+    use crate::net::types::{FetchResult, Priority};
     use crate::NavigationId;
     use std::time::Duration;
     use tokio::sync::mpsc;
     use tokio::time::sleep;
-    use url::Url;
 
     // Minimal HTML that triggers 3 resource discoveries: link/script/img + a title.
     const HTML_WITH_RESOURCES: &str = r#"
@@ -261,6 +420,7 @@ mod tests {
                 match cmd {
                     IoCommand::Fetch {
                         zone_id: _,
+                        partition: _,
                         req: _,
                         handle,
                         reply_tx,
@@ -286,7 +446,17 @@ mod tests {
         // Arrange
         let (io_tx, seen_children) = start_dummy_io();
         let zone_id = ZoneId::new();
-        let mut pipeline = HtmlPipelineImpl::new(zone_id, io_tx, None, 10 * 1024 * 1024);
+        let mut pipeline = HtmlPipelineImpl::new(
+            zone_id,
+            PartitionKey::None,
+            io_tx,
+            None,
+            10 * 1024 * 1024,
+            None,
+            None,
+            MediaEnvironment::default(),
+            MixedContentGuard::default(),
+        );
 
         let (req, handle) = test_request("https://example.com/path/index.html");
         let meta = test_meta("https://example.com/path/index.html");
@@ -308,12 +478,132 @@ mod tests {
         assert_eq!(count, 3, "expected 3 subresource fetches, saw {}", count);
     }
 
+    #[tokio::test(flavor = "current_thread")]
+    async fn subresource_requests_carry_the_zones_cookies() {
+        let jar: CookieJarHandle = crate::cookies::DefaultCookieJar::new().into();
+        let mut set_cookie = http::HeaderMap::new();
+        set_cookie.insert(http::header::SET_COOKIE, "session=abc; Path=/".parse().unwrap());
+        let document = Url::parse("https://example.com/").unwrap();
+        jar.write().store_response_cookies(&document, &set_cookie, None);
+
+        let (io_tx, mut rx) = mpsc::unbounded_channel::<IoCommand>();
+        let cookies = Arc::new(Mutex::new(Vec::new()));
+        let seen = cookies.clone();
+        tokio::spawn(async move {
+            while let Some(cmd) = rx.recv().await {
+                if let IoCommand::Fetch { req, .. } = cmd {
+                    let cookie = req.key_data.headers.get(http::header::COOKIE).cloned();
+                    seen.lock().push(cookie);
+                }
+            }
+        });
+
+        let mut pipeline = HtmlPipelineImpl::new(
+            ZoneId::new(),
+            PartitionKey::None,
+            io_tx,
+            None,
+            10 * 1024 * 1024,
+            None,
+            Some(jar),
+            MediaEnvironment::default(),
+            MixedContentGuard::default(),
+        );
+        let (req, handle) = test_request("https://example.com/");
+        let meta = test_meta("https://example.com/");
+        let _ = HtmlPipeline::<DefaultRenderConfig>::parse_bytes(
+            &mut pipeline,
+            req,
+            handle,
+            meta,
+            HTML_WITH_RESOURCES.as_bytes(),
+        )
+        .await
+        .expect("parse ok");
+        sleep(Duration::from_millis(10)).await;
+
+        let cookies = cookies.lock();
+        assert_eq!(cookies.len(), 3);
+        for cookie in cookies.iter() {
+            assert_eq!(cookie.as_ref().map(|c| c.to_str().unwrap()), Some("session=abc"));
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn waits_for_stylesheets_and_adds_them_in_tree_order() {
+        use gosub_interface::css3::{CssOrigin, CssStylesheet as _};
+
+        let (io_tx, mut rx) = mpsc::unbounded_channel::<IoCommand>();
+        tokio::spawn(async move {
+            while let Some(cmd) = rx.recv().await {
+                if let IoCommand::Fetch { req, reply_tx, .. } = cmd {
+                    let mut meta = test_meta(req.key_data.url.as_str());
+                    if req.key_data.url.path() == "/missing.css" {
+                        meta.status = 404;
+                    }
+                    let body = Bytes::from_static(b"p { color: red }");
+                    let _ = reply_tx.send(FetchResult::Buffered { meta, body });
+                }
+            }
+        });
+
+        // `late.css` is only found by the parser, not by the discovery scan.
+        let html = r#"<html><head>
+            <link rel="stylesheet" href="/first.css">
+            <style>h1 { color: blue }</style>
+            <link rel="stylesheet" href="/print.css" media="print">
+            <link rel="stylesheet" href="/missing.css">
+            <link rel=stylesheet href=/late.css>
+        </head><body></body></html>"#;
+        let mut pipeline = HtmlPipelineImpl::new(
+            ZoneId::new(),
+            PartitionKey::None,
+            io_tx,
+            None,
+            1024 * 1024,
+            None,
+            None,
+            MediaEnvironment::default(),
+            MixedContentGuard::default(),
+        );
+        let (req, handle) = test_request("https://example.com/");
+        let meta = test_meta("https://example.com/");
+        let doc = HtmlPipeline::<DefaultRenderConfig>::parse_bytes(&mut pipeline, req, handle, meta, html.as_bytes())
+            .await
+            .expect("parse ok");
+
+        let author: Vec<_> = doc
+            .stylesheets()
+            .iter()
+            .filter(|sheet| sheet.origin() == CssOrigin::Author)
+            .map(|sheet| sheet.url())
+            .collect();
+        assert_eq!(
+            author,
+            [
+                "https://example.com/first.css",
+                "https://example.com/#inline",
+                "https://example.com/late.css"
+            ]
+        );
+    }
+
     #[tokio::test(flavor = "current_thread")]
     async fn parse_bytes_cancels_children_on_finish() {
         // Arrange
         let (io_tx, seen_children) = start_dummy_io();
         let zone_id = ZoneId::new();
-        let mut pipeline = HtmlPipelineImpl::new(zone_id, io_tx, None, 10 * 1024 * 1024);
+        let mut pipeline = HtmlPipelineImpl::new(
+            zone_id,
+            PartitionKey::None,
+            io_tx,
+            None,
+            10 * 1024 * 1024,
+            None,
+            None,
+            MediaEnvironment::default(),
+            MixedContentGuard::default(),
+        );
 
         let (req, handle) = test_request("https://example.com/");
         let meta = test_meta("https://example.com/");
@@ -0,0 +1,1472 @@
+//! JavaScript for tabs.
+//!
+//! The engine doesn't ship a JS engine of its own; the embedder enables scripting for a tab by
+//! handing it a [`ScriptHost`] (see [`TabOverrides::script_host`](crate::tab::TabOverrides)),
+//! which builds a `gosub_webexecutor` runtime such as V8. JS runtimes are generally tied to the
+//! thread that created them, so each tab runs its scripts on a thread of its own: the tab worker
+//! queues jobs there and gets results back over a oneshot channel.
+//!
+//! Every committed document gets a fresh JS context. Results cross back as JSON, produced by
+//! `JSON.stringify` in the page's own context, so they follow the usual rules: `undefined` and
+//! functions become `null`, and cyclic values raise an exception.
+//!
+//! Contexts come with `setTimeout()`/`setInterval()`, scheduled by a
+//! [`WebTimers`](gosub_web_platform::timers::WebTimers), and `requestAnimationFrame()`. The
+//! script thread runs due timers between jobs; animation frame callbacks run when the tab worker
+//! is about to draw a frame, with timestamps relative to when the document committed.
+//! Promise jobs run at a microtask checkpoint after every task and callback, so a frame is only
+//! drawn once the `.then()`s of its callbacks have run.
+//!
+//! `fetch()` is handed to the tab worker through a [`PageChannel`], so page requests go through
+//! the tab's fetcher, cookie jar and content blocker, with CORS applied. The response is posted
+//! back to the script thread, which settles the promise; responses for a document that has since
+//! been replaced are dropped. An `AbortSignal` cancels it there too.
+//!
+//! `indexedDB` works on the document's [`IndexedDb`], the databases of its origin, directly from
+//! the script thread. Documents with an opaque origin get none, and their requests fail.
+//!
+//! `new Worker(url)` starts a dedicated worker: another script thread with a context of its own,
+//! see [`workers`]. Workers fetch through the same [`PageChannel`] as their document and go away
+//! with it.
+//!
+//! `location` reads the document's URL; changing it makes the tab worker navigate, see
+//! [`location`].
+//!
+//! `history` traverses the tab's session history through the tab worker and switches its
+//! scroll restoration between automatic and manual, see [`history`].
+//!
+//! `matchMedia()` evaluates media queries against the tab's viewport, device pixel ratio and
+//! color scheme, and fires `change` when the tab worker reports a new one, see [`media_queries`].
+//!
+//! `navigator.clipboard` asks the UA for access through the tab worker, see [`clipboard`].
+//!
+//! `navigator.permissions` reads the tab's permission decisions, and `Notification` asks the UA
+//! for one and hands it what to show, see [`permissions`].
+//!
+//! Event targets, the global object included, share one `EventTarget` implementation, see
+//! [`events`].
+//!
+//! `MessageChannel` ports work between any of a tab's contexts, see [`messaging`]; a port sent
+//! along with `postMessage()` moves to the receiving context. Messages, like IndexedDB values,
+//! are structured clones, see [`structured_clone`].
+//!
+//! `<script type="module">`s are loaded by the tab worker once the document has committed and
+//! run here in document order, see [`modules`].
+//!
+//! `console` output is logged by the tab worker and handed to the UA, see [`console`].
+//!
+//! With an [`InspectorServer`], external debuggers can attach to a tab's scripts, see
+//! [`inspector`].
+//!
+//! A host can hold scripts to [`ExecutionLimits`]: a watchdog interrupts tasks that run past the
+//! time slice, and the runtime stops scripts that run out of heap. Either way the tab worker is
+//! told, and hands the UA an [`EngineEvent::PageUnresponsive`](crate::events::EngineEvent::PageUnresponsive).
+//!
+//! `document` and its nodes work on a copy of the document, whose changes are handed to the tab
+//! worker after every task, see [`dom`]. The tab worker fires the events the user causes at those
+//! nodes, and learns whether a listener canceled them.
+//!
+//! The futures of `async` native methods run on the script thread as local tasks, which are
+//! polled whenever one of them is woken and dropped with the document.
+
+mod abort;
+mod clipboard;
+mod console;
+mod dom;
+mod events;
+mod fetch;
+mod history;
+mod indexed_db;
+mod inspector;
+mod location;
+mod media_queries;
+mod messaging;
+mod modules;
+mod permissions;
+mod structured_clone;
+mod workers;
+
+pub(crate) use clipboard::ClipboardReply;
+pub use clipboard::{ClipboardAccess, ClipboardAnswer, ClipboardOutcome, ClipboardRequestId};
+pub use console::ConsoleLevel;
+pub(crate) use dom::{apply_mutations, DomEvent, DomEventInit, DomMutation, DomTree};
+pub use fetch::{FetchOutcome, RedirectMode, ResponseType, ScriptFetchRequest, ScriptFetchResponse};
+pub use gosub_webexecutor::js::{ExecutionLimits, LimitViolation};
+pub use inspector::{InspectorConnection, InspectorServer};
+pub use messaging::MessageSource;
+pub(crate) use modules::{load_module_graph, module_response, module_scripts, ModuleCache, ModuleQueue};
+pub(crate) use permissions::PermissionReply;
+pub use permissions::{NotificationId, NotificationRequest, PermissionRequestId};
+
+use crate::engine::storage::IndexedDb;
+use crate::tab::ScrollRestoration;
+
+use gosub_css3::media::MediaEnvironment;
+use gosub_shared::error_report::{self, ErrorReport, ErrorSource, TabScope};
+use gosub_web_platform::animation_frames::{high_res_timestamp, AnimationFrameId, AnimationFrames};
+use gosub_web_platform::permissions::{PermissionName, PermissionState, PermissionStore};
+use gosub_web_platform::timers::{TimerId, WebTimers};
+use gosub_webexecutor::js::{
+    clear_local_tasks, run_local_tasks, set_local_task_notify, Args, JSError, ModuleGraph, Watchdog, WebContext,
+    WebFunction, WebFunctionCallBack, WebInspectorSession, WebObject, WebRuntime, WebValue,
+};
+use inspector::InspectorTarget;
+use messaging::{MessagePorts, OwnedPorts};
+use std::cell::RefCell;
+use std::fmt::{Debug, Display, Formatter};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc as tokio_mpsc, oneshot};
+use url::Url;
+use uuid::Uuid;
+use workers::{WorkerScope, Workers};
+
+/// Identifies one [`TabCommand::EvaluateScript`](crate::events::TabCommand::EvaluateScript)
+/// call, so its result can be matched up.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EvaluationId(Uuid);
+
+impl EvaluationId {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for EvaluationId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for EvaluationId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Why a script didn't produce a value.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ScriptError {
+    /// The tab has no script host
+    #[error("scripting is not enabled for this tab")]
+    Disabled,
+    /// The script didn't compile
+    #[error("compile error: {0}")]
+    Compile(String),
+    /// The script threw, or its value could not be serialized
+    #[error("uncaught exception: {0}")]
+    Exception(String),
+    /// The runtime itself failed (couldn't start, went away, ...)
+    #[error("script runtime error: {0}")]
+    Runtime(String),
+}
+
+impl From<anyhow::Error> for ScriptError {
+    fn from(e: anyhow::Error) -> Self {
+        let js = e
+            .downcast_ref::<gosub_webexecutor::Error>()
+            .map(|gosub_webexecutor::Error::JS(js)| js)
+            .or_else(|| e.downcast_ref::<JSError>());
+        match js {
+            Some(JSError::Compile(msg)) => ScriptError::Compile(msg.clone()),
+            Some(JSError::Exception(msg)) => ScriptError::Exception(msg.clone()),
+            _ => ScriptError::Runtime(e.to_string()),
+        }
+    }
+}
+
+/// The completion value of a script as JSON, or why there is none.
+pub type ScriptResult = Result<serde_json::Value, ScriptError>;
+
+/// One document's JS context.
+pub trait ScriptContext {
+    /// Runs `source` as a classic script and returns its completion value.
+    fn evaluate(&mut self, source: &str) -> ScriptResult;
+
+    /// Links and evaluates module `url` of `graph`, see [`modules`].
+    fn run_module(&mut self, _url: &str, _graph: &ModuleGraph) {}
+
+    /// When the earliest pending timer is due.
+    fn next_timer(&self) -> Option<Instant> {
+        None
+    }
+
+    /// Runs the timers that are due.
+    fn run_timers(&mut self) {}
+
+    /// Whether the page is waiting for an animation frame.
+    fn wants_animation_frame(&self) -> bool {
+        false
+    }
+
+    /// Runs the animation frame callbacks for a frame at `timestamp` (a `DOMHighResTimeStamp`).
+    fn run_animation_frame(&mut self, _timestamp: f64) {}
+
+    /// Settles the promise of the `fetch()` call that was handed over as `id`.
+    fn fetch_done(&mut self, _id: u32, _outcome: FetchOutcome) {}
+
+    /// Settles the promise of the clipboard request that was handed over as `id`.
+    fn clipboard_done(&mut self, _id: u32, _outcome: ClipboardOutcome) {}
+
+    /// Settles the promise of the permission request that was handed over as `id`.
+    fn permission_done(&mut self, _id: u32, _state: PermissionState) {}
+
+    /// Delivers a message `source` posted to this context. `data` is the message as JSON.
+    fn message(&mut self, _source: MessageSource, _data: String) {}
+
+    /// Fires `error` at this context's worker `id`, whose script failed with `message`.
+    fn worker_error(&mut self, _id: u32, _message: String) {}
+
+    /// Fires `change` at the `MediaQueryList`s whose result changed with the media environment.
+    fn media_changed(&mut self) {}
+
+    /// Fires `event` at its node. Returns `false` when a listener canceled it.
+    fn dispatch_event(&mut self, _event: &DomEvent) -> bool {
+        true
+    }
+
+    /// Hands what scripts changed in the document since the last call to the tab worker.
+    fn flush_mutations(&mut self) {}
+
+    /// The limit the last task ran into, if any, see [`WebContext::take_limit_violation`].
+    fn take_limit_violation(&mut self) -> Option<LimitViolation> {
+        None
+    }
+
+    /// Attaches the debugger on the other end of `connection`, in place of any attached one.
+    fn attach_inspector(&mut self, _connection: &InspectorConnection) {}
+
+    /// Hands the attached debugger's pending messages to the runtime.
+    fn dispatch_inspector(&mut self) {}
+
+    /// Detaches the debugger.
+    fn detach_inspector(&mut self) {}
+
+    /// Polls the thread's local tasks that were woken.
+    fn run_local_tasks(&mut self) {}
+}
+
+/// How a document's JS context, or one of its workers', reaches the tab worker. Cheap to clone.
+#[derive(Debug, Clone)]
+pub struct PageChannel {
+    requests: tokio_mpsc::UnboundedSender<ScriptRequest>,
+    jobs: mpsc::Sender<ScriptJob>,
+    document: u64,
+    /// What relative URLs resolve against: the document's URL, or a worker's script URL
+    url: Option<Url>,
+    /// The document's databases, if its origin has any
+    indexed_db: Option<IndexedDb>,
+    /// The document as it committed, for its context to work on
+    dom: Option<Arc<DomTree>>,
+    /// Shared by a document and its workers, so their `fetch()` ids don't clash at the worker
+    fetch_ids: Arc<AtomicU32>,
+    /// The message ports of the tab's contexts
+    ports: Arc<parking_lot::Mutex<MessagePorts>>,
+    /// What `matchMedia()` evaluates against, kept current by the tab worker
+    media: Arc<parking_lot::Mutex<MediaEnvironment>>,
+    /// Whether `history.scrollRestoration` is `"manual"`, set by the tab worker on commit
+    scroll_restoration: Arc<AtomicBool>,
+    /// The tab's permission decisions
+    permissions: Arc<dyn PermissionStore>,
+    /// Runs the threads of workers
+    host: ScriptHost,
+    /// Set in a worker's context
+    worker: Option<WorkerScope>,
+    /// Interrupts the tasks of this thread that run longer than the host's time slice
+    watchdog: Option<Arc<Watchdog>>,
+}
+
+impl PageChannel {
+    /// Hands `request` to the worker and returns the id of the call. The outcome comes back
+    /// through [`ScriptContext::fetch_done`].
+    pub fn fetch(&self, request: ScriptFetchRequest) -> u32 {
+        let id = self.fetch_ids.fetch_add(1, Ordering::Relaxed);
+        self.send_fetch(id, request, self.jobs.clone());
+        id
+    }
+
+    /// Runs `request` and waits for its outcome. Only for worker threads, which may block.
+    pub fn fetch_blocking(&self, request: ScriptFetchRequest) -> FetchOutcome {
+        let (jobs, rx) = mpsc::channel();
+        let id = self.fetch_ids.fetch_add(1, Ordering::Relaxed);
+        self.send_fetch(id, request, jobs);
+        match rx.recv() {
+            Ok(ScriptJob::FetchDone { outcome, .. }) => outcome,
+            // The worker dropped the request: the document is gone.
+            _ => Err("the document was unloaded".into()),
+        }
+    }
+
+    fn send_fetch(&self, id: u32, mut request: ScriptFetchRequest, jobs: mpsc::Sender<ScriptJob>) {
+        if let Some(url) = self.url.as_ref().and_then(|base| base.join(&request.url).ok()) {
+            request.url = url.into();
+        }
+        let reply = FetchReply {
+            jobs,
+            document: self.document,
+            id,
+        };
+        let _ = self.requests.send(ScriptRequest::Fetch { request, reply });
+    }
+
+    /// Cancels `fetch()` call `id`, e.g. because its signal was aborted.
+    pub fn abort_fetch(&self, id: u32) {
+        let _ = self.requests.send(ScriptRequest::AbortFetch {
+            document: self.document,
+            id,
+        });
+    }
+}
+
+/// Something a page asks of the tab worker.
+#[derive(Debug)]
+pub(crate) enum ScriptRequest {
+    Fetch {
+        request: ScriptFetchRequest,
+        reply: FetchReply,
+    },
+    AbortFetch {
+        document: u64,
+        id: u32,
+    },
+    /// `location` wants `document` replaced by `url`, in place of the current history entry
+    /// when `replace` is set
+    Navigate {
+        document: u64,
+        url: Url,
+        replace: bool,
+    },
+    /// `location.reload()` of `document`
+    Reload {
+        document: u64,
+    },
+    /// `history.go(delta)` of `document`; `0` reloads
+    TraverseHistory {
+        document: u64,
+        delta: isize,
+    },
+    /// `history.scrollRestoration` of `document` was set to `mode`
+    ScrollRestoration {
+        document: u64,
+        mode: ScrollRestoration,
+    },
+    /// `navigator.clipboard` wants to read or write the system clipboard
+    Clipboard {
+        access: ClipboardAccess,
+        reply: ClipboardReply,
+    },
+    /// `Notification.requestPermission()` wants a decision on `name`
+    Permission {
+        name: PermissionName,
+        reply: PermissionReply,
+    },
+    /// `new Notification()` of `document`, with the permission granted
+    ShowNotification {
+        document: u64,
+        id: NotificationId,
+        notification: NotificationRequest,
+    },
+    /// `notification.close()` of `document`
+    CloseNotification {
+        document: u64,
+        id: NotificationId,
+    },
+    /// A line of `console` output of `document` or one of its workers
+    Console {
+        document: u64,
+        level: ConsoleLevel,
+        message: String,
+    },
+    /// Scripts changed `document`
+    MutateDom {
+        document: u64,
+        mutations: Vec<DomMutation>,
+    },
+    /// `form.submit()` or `form.requestSubmit()` of `document`, by its script ids. Comes after
+    /// the [`MutateDom`](Self::MutateDom) of the same task, so the form is sent as scripts left it.
+    SubmitForm {
+        document: u64,
+        form: usize,
+        submitter: Option<usize>,
+    },
+    /// A script of `document`, or of one of its workers, ran into `violation` and was stopped
+    LimitExceeded {
+        document: u64,
+        violation: LimitViolation,
+    },
+}
+
+/// Where the outcome of a `fetch()` call goes.
+#[derive(Debug)]
+pub(crate) struct FetchReply {
+    jobs: mpsc::Sender<ScriptJob>,
+    document: u64,
+    id: u32,
+}
+
+impl FetchReply {
+    /// Identifies the call for [`ScriptRequest::AbortFetch`].
+    pub fn key(&self) -> (u64, u32) {
+        (self.document, self.id)
+    }
+
+    pub fn send(self, outcome: FetchOutcome) {
+        let _ = self.jobs.send(ScriptJob::FetchDone {
+            document: self.document,
+            id: self.id,
+            outcome,
+        });
+    }
+}
+
+/// Global the completion value is parked on while it is serialized.
+const RESULT_GLOBAL: &str = "__gosubEvaluateResult";
+/// Global the native timer functions are handed to the timers shim on.
+const TIMERS_GLOBAL: &str = "__gosubTimers";
+/// Function the shim leaves behind for running a timer's handler.
+const FIRE_TIMER: &str = "__gosubFireTimer";
+/// Global the native animation frame functions are handed to the shim on.
+const ANIMATION_FRAMES_GLOBAL: &str = "__gosubAnimationFrames";
+/// Function the shim leaves behind for running an animation frame callback.
+const RUN_ANIMATION_FRAME: &str = "__gosubRunAnimationFrame";
+
+/// The web-facing timer API. Handlers and their arguments stay on the JS side; the native half
+/// only schedules ids. `timeout | 0` is the WebIDL `long` conversion.
+const TIMERS_SHIM: &str = r#"(() => {
+    const native = globalThis.__gosubTimers;
+    delete globalThis.__gosubTimers;
+    const handlers = new Map();
+    const schedule = (repeat, handler, timeout, args) => {
+        const id = native.schedule(Math.max(0, timeout | 0), repeat);
+        handlers.set(id, { handler, args });
+        return id;
+    };
+    const clear = (id = 0) => {
+        id = id | 0;
+        handlers.delete(id);
+        native.clear(id);
+    };
+    globalThis.setTimeout = (handler, timeout = 0, ...args) => schedule(false, handler, timeout, args);
+    globalThis.setInterval = (handler, timeout = 0, ...args) => schedule(true, handler, timeout, args);
+    globalThis.clearTimeout = clear;
+    globalThis.clearInterval = clear;
+    Object.defineProperty(globalThis, "__gosubFireTimer", {
+        value: (id, last) => {
+            const entry = handlers.get(id);
+            if (last) handlers.delete(id);
+            if (!entry) return;
+            if (typeof entry.handler === "function") entry.handler.apply(globalThis, entry.args);
+            else (0, eval)(String(entry.handler));
+        },
+    });
+})()"#;
+
+/// `requestAnimationFrame()`/`cancelAnimationFrame()`, built like [`TIMERS_SHIM`].
+const ANIMATION_FRAMES_SHIM: &str = r#"(() => {
+    const native = globalThis.__gosubAnimationFrames;
+    delete globalThis.__gosubAnimationFrames;
+    const callbacks = new Map();
+    globalThis.requestAnimationFrame = (callback) => {
+        if (typeof callback !== "function") {
+            throw new TypeError("requestAnimationFrame: callback is not a function");
+        }
+        const id = native.request();
+        callbacks.set(id, callback);
+        return id;
+    };
+    globalThis.cancelAnimationFrame = (id = 0) => {
+        id = id | 0;
+        callbacks.delete(id);
+        native.cancel(id);
+    };
+    Object.defineProperty(globalThis, "__gosubRunAnimationFrame", {
+        value: (id, timestamp) => {
+            const callback = callbacks.get(id);
+            callbacks.delete(id);
+            if (callback) callback.call(globalThis, timestamp);
+        },
+    });
+})()"#;
+
+/// A `gosub_webexecutor` context plus the platform state the engine keeps for it.
+struct JsContext<RT: WebRuntime> {
+    ctx: RT::Context,
+    timers: WebTimers,
+    animation_frames: AnimationFrames,
+    /// The workers this context started
+    workers: Workers,
+    /// The tab's message ports, and the ones this context owns
+    ports: Arc<parking_lot::Mutex<MessagePorts>>,
+    owned_ports: OwnedPorts,
+    /// Set when this is a worker's context
+    worker: Option<WorkerScope>,
+    /// The document's tree, which a worker's context has none of
+    dom: Option<Rc<RefCell<dom::DomBinding>>>,
+    /// Where changes to the tree go
+    requests: tokio_mpsc::UnboundedSender<ScriptRequest>,
+    document: u64,
+    /// What a debugger knows this context as
+    title: String,
+    /// Held off while a debugger keeps the page paused
+    watchdog: Option<Arc<Watchdog>>,
+    inspector: Option<(Box<dyn WebInspectorSession>, InspectorConnection)>,
+}
+
+impl<RT: WebRuntime> JsContext<RT> {
+    fn new(mut ctx: RT::Context, page: PageChannel) -> anyhow::Result<Self> {
+        let timers = WebTimers::new();
+        install_timers::<RT>(&mut ctx, &timers)?;
+        events::install_events::<RT>(&mut ctx)?;
+        structured_clone::install_structured_clone::<RT>(&mut ctx)?;
+        console::install_console::<RT>(&mut ctx, &page)?;
+        // Workers don't draw, so they get no document, animation frames or media queries, and
+        // have no clipboard or notifications.
+        let animation_frames = AnimationFrames::new();
+        let mut dom = None;
+        if page.worker.is_none() {
+            if let Some(tree) = &page.dom {
+                dom = Some(dom::install_dom::<RT>(&mut ctx, DomTree::clone(tree))?);
+            }
+            install_animation_frames::<RT>(&mut ctx, &animation_frames)?;
+            location::install_location::<RT>(&mut ctx, &page)?;
+            history::install_history::<RT>(&mut ctx, &page)?;
+            media_queries::install_media_queries::<RT>(&mut ctx, &page)?;
+            clipboard::install_clipboard::<RT>(&mut ctx, &page)?;
+            permissions::install_permissions::<RT>(&mut ctx, &page)?;
+        }
+        fetch::install_fetch::<RT>(&mut ctx, &page)?;
+        indexed_db::install_indexed_db::<RT>(&mut ctx, page.indexed_db.clone())?;
+        let owned_ports = OwnedPorts::default();
+        messaging::install_messaging::<RT>(&mut ctx, &page, &owned_ports)?;
+        abort::install_abort::<RT>(&mut ctx)?;
+        let workers = Workers::default();
+        workers::install_workers::<RT>(&mut ctx, &page, &workers)?;
+        Ok(Self {
+            ctx,
+            timers,
+            animation_frames,
+            workers,
+            ports: page.ports,
+            owned_ports,
+            worker: page.worker,
+            dom,
+            requests: page.requests,
+            document: page.document,
+            title: page
+                .url
+                .as_ref()
+                .map_or_else(|| "about:blank".to_owned(), Url::to_string),
+            watchdog: page.watchdog,
+            inspector: None,
+        })
+    }
+}
+
+impl<RT: WebRuntime> Drop for JsContext<RT> {
+    fn drop(&mut self) {
+        // A document's workers and ports go with it.
+        self.workers.borrow_mut().clear();
+        messaging::close_ports(&self.ports, &self.owned_ports);
+    }
+}
+
+impl<RT: WebRuntime> ScriptContext for JsContext<RT> {
+    fn evaluate(&mut self, source: &str) -> ScriptResult {
+        let value = self.ctx.run(source);
+        // Promise jobs run whether or not the script threw.
+        microtask_checkpoint::<RT>(&mut self.ctx);
+        let value = value?;
+        if value.is_undefined() {
+            return Ok(serde_json::Value::Null);
+        }
+        self.ctx.set_on_global_object(RESULT_GLOBAL, value)?;
+        let json = self.ctx.run(&format!(
+            "(() => {{ try {{ return JSON.stringify(globalThis.{RESULT_GLOBAL}) ?? 'null'; }} \
+             finally {{ delete globalThis.{RESULT_GLOBAL}; }} }})()"
+        ))?;
+        let json = json.as_string()?;
+        serde_json::from_str(&json).map_err(|e| ScriptError::Runtime(format!("invalid JSON from runtime: {e}")))
+    }
+
+    fn run_module(&mut self, url: &str, graph: &ModuleGraph) {
+        let result = self.ctx.run_module(url, graph);
+        microtask_checkpoint::<RT>(&mut self.ctx);
+        if let Err(e) = result {
+            report_script_error(format!("Module script failed: {e}"), Some(url));
+        }
+    }
+
+    fn next_timer(&self) -> Option<Instant> {
+        self.timers.next_deadline()
+    }
+
+    fn run_timers(&mut self) {
+        let ctx = &mut self.ctx;
+        self.timers.run_due(Instant::now(), |due, _| {
+            if let Err(e) = ctx.run(&format!("{FIRE_TIMER}({}, {})", due.id, !due.repeat)) {
+                report_script_error(format!("Timer {} failed: {e}", due.id), None);
+            }
+            microtask_checkpoint::<RT>(ctx);
+        });
+    }
+
+    fn wants_animation_frame(&self) -> bool {
+        !self.animation_frames.is_empty()
+    }
+
+    fn run_animation_frame(&mut self, timestamp: f64) {
+        let ctx = &mut self.ctx;
+        self.animation_frames.run(|id, _| {
+            if let Err(e) = ctx.run(&format!("{RUN_ANIMATION_FRAME}({id}, {timestamp})")) {
+                report_script_error(format!("Animation frame callback {id} failed: {e}"), None);
+            }
+            microtask_checkpoint::<RT>(ctx);
+        });
+    }
+
+    fn fetch_done(&mut self, id: u32, outcome: FetchOutcome) {
+        if let Err(e) = fetch::deliver::<RT>(&mut self.ctx, id, outcome) {
+            log::warn!("Settling fetch {id} failed: {e}");
+        }
+        microtask_checkpoint::<RT>(&mut self.ctx);
+    }
+
+    fn clipboard_done(&mut self, id: u32, outcome: ClipboardOutcome) {
+        if let Err(e) = clipboard::deliver::<RT>(&mut self.ctx, id, outcome) {
+            log::warn!("Settling clipboard request {id} failed: {e}");
+        }
+        microtask_checkpoint::<RT>(&mut self.ctx);
+    }
+
+    fn permission_done(&mut self, id: u32, state: PermissionState) {
+        if let Err(e) = permissions::deliver::<RT>(&mut self.ctx, id, state) {
+            log::warn!("Settling permission request {id} failed: {e}");
+        }
+        microtask_checkpoint::<RT>(&mut self.ctx);
+    }
+
+    fn message(&mut self, source: MessageSource, data: String) {
+        let delivered = messaging::deliver_message::<RT>(&mut self.ctx, source, data);
+        microtask_checkpoint::<RT>(&mut self.ctx);
+        if let Err(e) = delivered {
+            match &self.worker {
+                Some(scope) => scope.report_error(e.to_string()),
+                None => report_script_error(format!("Message handler failed: {e}"), None),
+            }
+        }
+    }
+
+    fn worker_error(&mut self, id: u32, message: String) {
+        if let Err(e) = workers::deliver_error::<RT>(&mut self.ctx, id, &message) {
+            log::warn!("Error handler of worker {id} failed: {e}");
+        }
+        microtask_checkpoint::<RT>(&mut self.ctx);
+    }
+
+    fn media_changed(&mut self) {
+        if let Err(e) = media_queries::media_changed::<RT>(&mut self.ctx) {
+            report_script_error(format!("Media query change listener failed: {e}"), None);
+        }
+        microtask_checkpoint::<RT>(&mut self.ctx);
+    }
+
+    fn dispatch_event(&mut self, event: &DomEvent) -> bool {
+        // Nodes the tab worker made since the snapshot have no listeners.
+        if !self.dom.as_ref().is_some_and(|dom| dom.borrow().has_node(event.target)) {
+            return true;
+        }
+        let dispatched = dom::dispatch_event::<RT>(&mut self.ctx, event);
+        microtask_checkpoint::<RT>(&mut self.ctx);
+        match dispatched {
+            Ok(dispatched) => {
+                if let Some(error) = dispatched.error {
+                    report_script_error(format!("{} listener failed: {error}", event.event_type), None);
+                }
+                !dispatched.canceled
+            }
+            Err(e) => {
+                log::warn!("Firing {} failed: {e}", event.event_type);
+                true
+            }
+        }
+    }
+
+    fn take_limit_violation(&mut self) -> Option<LimitViolation> {
+        self.ctx.take_limit_violation()
+    }
+
+    fn attach_inspector(&mut self, connection: &InspectorConnection) {
+        self.inspector = None;
+        match connection.attach::<RT>(&mut self.ctx, &self.title, self.watchdog.clone()) {
+            Some(session) => self.inspector = Some((session, connection.clone())),
+            None => log::warn!("The script runtime has no inspector"),
+        }
+    }
+
+    fn dispatch_inspector(&mut self) {
+        if let Some((session, connection)) = &mut self.inspector {
+            connection.dispatch(session.as_mut());
+            microtask_checkpoint::<RT>(&mut self.ctx);
+        }
+    }
+
+    fn detach_inspector(&mut self) {
+        self.inspector = None;
+    }
+
+    fn run_local_tasks(&mut self) {
+        run_local_tasks();
+        microtask_checkpoint::<RT>(&mut self.ctx);
+    }
+
+    fn flush_mutations(&mut self) {
+        let Some(dom) = &self.dom else {
+            return;
+        };
+        let (mutations, submission) = {
+            let mut dom = dom.borrow_mut();
+            (dom.take_mutations(), dom.take_submission())
+        };
+        if !mutations.is_empty() {
+            let _ = self.requests.send(ScriptRequest::MutateDom {
+                document: self.document,
+                mutations,
+            });
+        }
+        if let Some((form, submitter)) = submission {
+            let _ = self.requests.send(ScriptRequest::SubmitForm {
+                document: self.document,
+                form,
+                submitter,
+            });
+        }
+    }
+}
+
+/// Reports an exception a script threw and nothing caught
+fn report_script_error(message: String, url: Option<&str>) {
+    let report = ErrorReport::error(ErrorSource::Js, message);
+    error_report::report(match url {
+        Some(url) => report.with_url(url),
+        None => report,
+    });
+}
+
+/// Runs the promise jobs the last task queued. Every task (a script, a timer, an animation frame
+/// callback, a settled `fetch()`, a message) ends with one, so `.then()` callbacks run before
+/// the next task and before the frame is drawn.
+fn microtask_checkpoint<RT: WebRuntime>(ctx: &mut RT::Context) {
+    if let Err(e) = ctx.perform_microtask_checkpoint() {
+        log::warn!("Running microtasks failed: {e}");
+    }
+}
+
+/// Wraps `f` as a JS function for the shims. `f` gets the call's arguments and returns a number
+/// to hand back, or `None` for `undefined`.
+fn native_function<RT: WebRuntime>(
+    ctx: &RT::Context,
+    f: impl Fn(&[RT::Value]) -> Option<u32> + 'static,
+) -> anyhow::Result<RT::Function> {
+    RT::Function::new(ctx.clone(), move |cb| {
+        let ctx = cb.context();
+        let args = cb.args().as_vec(ctx.clone());
+        let ret = match f(&args) {
+            Some(n) => RT::Value::new_number(ctx, n),
+            None => RT::Value::new_undefined(ctx),
+        };
+        match ret {
+            Ok(value) => cb.ret(value),
+            Err(e) => cb.error(e),
+        }
+    })
+}
+
+/// Like [`native_function`], for natives that hand back a string.
+fn native_string_function<RT: WebRuntime>(
+    ctx: &RT::Context,
+    f: impl Fn(&[RT::Value]) -> String + 'static,
+) -> anyhow::Result<RT::Function> {
+    RT::Function::new(ctx.clone(), move |cb| {
+        let ctx = cb.context();
+        let args = cb.args().as_vec(ctx.clone());
+        match RT::Value::new_string(ctx, &f(&args)) {
+            Ok(value) => cb.ret(value),
+            Err(e) => cb.error(e),
+        }
+    })
+}
+
+fn number_arg<V: WebValue>(args: &[V], index: usize) -> Option<f64> {
+    args.get(index).and_then(|v| v.as_number().ok())
+}
+
+/// Puts the native `schedule(timeout, repeat)` and `clear(id)` on the global object and runs
+/// [`TIMERS_SHIM`] to build the web API on top of them.
+fn install_timers<RT: WebRuntime>(ctx: &mut RT::Context, timers: &WebTimers) -> anyhow::Result<()> {
+    let native = RT::Object::new(ctx)?;
+
+    let handle = timers.clone();
+    let schedule = native_function::<RT>(ctx, move |args| {
+        // `as` saturates, and NaN becomes 0.
+        let timeout = Duration::from_millis(number_arg(args, 0).unwrap_or(0.0) as u64);
+        let repeat = args.get(1).and_then(|v| v.as_bool().ok()).unwrap_or(false);
+        let id = if repeat {
+            handle.set_interval(timeout, ())
+        } else {
+            handle.set_timeout(timeout, ())
+        };
+        Some(id.as_u32())
+    })?;
+    native.set_method("schedule", &schedule)?;
+
+    let handle = timers.clone();
+    let clear = native_function::<RT>(ctx, move |args| {
+        if let Some(id) = number_arg(args, 0) {
+            handle.clear(TimerId::from(id as u32));
+        }
+        None
+    })?;
+    native.set_method("clear", &clear)?;
+
+    ctx.set_on_global_object(TIMERS_GLOBAL, native.into())?;
+    ctx.run(TIMERS_SHIM)?;
+    Ok(())
+}
+
+/// Same as [`install_timers`], for [`ANIMATION_FRAMES_SHIM`]: native `request()` and `cancel(id)`.
+fn install_animation_frames<RT: WebRuntime>(
+    ctx: &mut RT::Context,
+    animation_frames: &AnimationFrames,
+) -> anyhow::Result<()> {
+    let native = RT::Object::new(ctx)?;
+
+    let handle = animation_frames.clone();
+    let request = native_function::<RT>(ctx, move |_| Some(handle.request(()).as_u32()))?;
+    native.set_method("request", &request)?;
+
+    let handle = animation_frames.clone();
+    let cancel = native_function::<RT>(ctx, move |args| {
+        if let Some(id) = number_arg(args, 0) {
+            handle.cancel(AnimationFrameId::from(id as u32));
+        }
+        None
+    })?;
+    native.set_method("cancel", &cancel)?;
+
+    ctx.set_on_global_object(ANIMATION_FRAMES_GLOBAL, native.into())?;
+    ctx.run(ANIMATION_FRAMES_SHIM)?;
+    Ok(())
+}
+
+/// A JS engine able to create contexts.
+pub trait ScriptRuntime {
+    /// Creates the context of a new document, which reaches the tab worker through `page`.
+    fn new_context(&mut self, page: PageChannel) -> anyhow::Result<Box<dyn ScriptContext>>;
+}
+
+impl<RT> ScriptRuntime for RT
+where
+    RT: WebRuntime + 'static,
+{
+    fn new_context(&mut self, page: PageChannel) -> anyhow::Result<Box<dyn ScriptContext>> {
+        let ctx = <RT as WebRuntime>::new_context_with_limits(self, &page.host.limits)?;
+        if let Some(watchdog) = &page.watchdog {
+            watchdog.watch(ctx.interrupt_handle());
+        }
+        Ok(Box::new(JsContext::<RT>::new(ctx, page)?))
+    }
+}
+
+type RuntimeFactory = dyn Fn() -> anyhow::Result<Box<dyn ScriptRuntime>> + Send + Sync;
+
+/// Builds the JS runtime for each tab that has scripting enabled. Cheap to clone.
+#[derive(Clone)]
+pub struct ScriptHost {
+    factory: Arc<RuntimeFactory>,
+    inspector: Option<InspectorServer>,
+    limits: ExecutionLimits,
+}
+
+impl ScriptHost {
+    /// `factory` runs on the tab's script thread, so the runtime it returns doesn't need to be
+    /// `Send`.
+    pub fn new<RT, F>(factory: F) -> Self
+    where
+        RT: ScriptRuntime + 'static,
+        F: Fn() -> anyhow::Result<RT> + Send + Sync + 'static,
+    {
+        Self {
+            factory: Arc::new(move || Ok(Box::new(factory()?) as Box<dyn ScriptRuntime>)),
+            inspector: None,
+            limits: ExecutionLimits::default(),
+        }
+    }
+
+    /// Holds the scripts of the tabs using this host, their workers' included, to `limits`. A
+    /// script that runs into them is stopped, and the tab hands the UA an
+    /// [`EngineEvent::PageUnresponsive`](crate::events::EngineEvent::PageUnresponsive).
+    pub fn with_limits(mut self, limits: ExecutionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Lists the scripts of the tabs using this host on `server`, for debuggers to attach to.
+    pub fn with_inspector(mut self, server: InspectorServer) -> Self {
+        self.inspector = Some(server);
+        self
+    }
+}
+
+impl Debug for ScriptHost {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScriptHost").finish_non_exhaustive()
+    }
+}
+
+enum ScriptJob {
+    /// A new document committed; its scripts get a fresh context
+    NewDocument {
+        url: Option<Url>,
+        indexed_db: Option<IndexedDb>,
+        dom: Option<Arc<DomTree>>,
+    },
+    Evaluate {
+        source: String,
+        reply: Option<oneshot::Sender<ScriptResult>>,
+    },
+    /// Run module `url` of `graph` in `document`'s context
+    RunModule {
+        document: u64,
+        url: String,
+        graph: ModuleGraph,
+    },
+    /// A frame is about to be drawn
+    AnimationFrame { at: Instant, done: oneshot::Sender<()> },
+    /// A `fetch()` call of `document` finished
+    FetchDone {
+        document: u64,
+        id: u32,
+        outcome: FetchOutcome,
+    },
+    /// A clipboard request of `document` was answered
+    ClipboardDone {
+        document: u64,
+        id: u32,
+        outcome: ClipboardOutcome,
+    },
+    /// A permission request of `document` was answered
+    PermissionDone {
+        document: u64,
+        id: u32,
+        state: PermissionState,
+    },
+    /// Load and run a worker's script; the first job of a worker thread
+    StartWorker { url: Url },
+    /// `source` posted a message to `document`'s context
+    Message {
+        document: u64,
+        source: MessageSource,
+        data: String,
+    },
+    /// The script of worker `id` of `document`'s context failed
+    WorkerError { document: u64, id: u32, message: String },
+    /// The tab's media environment changed
+    MediaChanged,
+    /// Fire `event` at `document`'s tree; `reply` learns whether it was not canceled
+    DomEvent {
+        document: u64,
+        event: DomEvent,
+        reply: oneshot::Sender<bool>,
+    },
+    /// A debugger attached; it sends on `incoming` and gets the page's answers on `outgoing`
+    InspectorConnect {
+        incoming: mpsc::Receiver<String>,
+        outgoing: mpsc::Sender<String>,
+    },
+    /// The attached debugger sent a message
+    InspectorMessage,
+    /// The debugger went away
+    InspectorDisconnect,
+    /// One of the thread's local tasks was woken
+    RunLocalTasks,
+    /// Stop the thread
+    Terminate,
+}
+
+/// The thread a tab's scripts run on. It stops when this handle is dropped.
+pub(crate) struct ScriptThread {
+    tx: mpsc::Sender<ScriptJob>,
+    /// Set by the thread while the current document has animation frame callbacks queued
+    frame_requested: Arc<AtomicBool>,
+    /// Bumped with every new document, in step with the thread
+    document: u64,
+    /// Shared with the thread's contexts
+    media: Arc<parking_lot::Mutex<MediaEnvironment>>,
+    scroll_restoration: Arc<AtomicBool>,
+    /// The thread's listing on the host's inspector server
+    inspector: Option<InspectorTarget>,
+}
+
+impl ScriptThread {
+    /// `requests` receives what the page asks of the tab worker, such as `fetch()` calls. `url`
+    /// is the current document's URL, `indexed_db` what its `indexedDB` works on and `dom` its
+    /// tree. `permissions` is where the tab's permission decisions are read from.
+    pub fn spawn(
+        host: &ScriptHost,
+        name: String,
+        requests: tokio_mpsc::UnboundedSender<ScriptRequest>,
+        url: Option<Url>,
+        indexed_db: Option<IndexedDb>,
+        dom: Option<DomTree>,
+        permissions: Arc<dyn PermissionStore>,
+    ) -> std::io::Result<Self> {
+        let (tx, rx) = mpsc::channel();
+        let factory = Arc::clone(&host.factory);
+        let frame_requested = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&frame_requested);
+        let media: Arc<parking_lot::Mutex<MediaEnvironment>> = Default::default();
+        let scroll_restoration = Arc::new(AtomicBool::new(false));
+        let page = PageChannel {
+            requests,
+            jobs: tx.clone(),
+            document: 0,
+            url,
+            indexed_db,
+            dom: dom.map(Arc::new),
+            fetch_ids: Arc::new(AtomicU32::new(1)),
+            ports: Default::default(),
+            media: Arc::clone(&media),
+            scroll_restoration: Arc::clone(&scroll_restoration),
+            permissions,
+            host: host.clone(),
+            worker: None,
+            watchdog: None,
+        };
+        let inspector = host
+            .inspector
+            .as_ref()
+            .map(|server| server.register(page.url.clone(), tx.clone()));
+        // Spawned from the tab worker; reports from the script thread are for the same tab.
+        let tab = error_report::current_tab();
+        std::thread::Builder::new().name(name).spawn(move || {
+            let _scope = tab.map(TabScope::enter);
+            run_script_thread(&*factory, rx, page, &flag)
+        })?;
+        Ok(Self {
+            tx,
+            frame_requested,
+            document: 0,
+            media,
+            scroll_restoration,
+            inspector,
+        })
+    }
+
+    /// Drops the current document's context. The next one is for a document at `url` with the
+    /// tree `dom`, working on `indexed_db`.
+    pub fn new_document(&mut self, url: Option<Url>, indexed_db: Option<IndexedDb>, dom: Option<DomTree>) {
+        self.document += 1;
+        if let Some(inspector) = &self.inspector {
+            inspector.set_url(url.clone());
+        }
+        let _ = self.tx.send(ScriptJob::NewDocument {
+            url,
+            indexed_db,
+            dom: dom.map(Arc::new),
+        });
+    }
+
+    /// The current document, as in [`FetchReply::key`].
+    pub fn document(&self) -> u64 {
+        self.document
+    }
+
+    /// Queues `source` for the current document. The result arrives on the returned receiver.
+    pub fn evaluate(&self, source: String) -> oneshot::Receiver<ScriptResult> {
+        let (reply, rx) = oneshot::channel();
+        // If the thread is gone the reply sender is dropped with the job, which the receiver
+        // sees as a closed channel.
+        let _ = self.tx.send(ScriptJob::Evaluate {
+            source,
+            reply: Some(reply),
+        });
+        rx
+    }
+
+    /// Where the current document's module scripts go once their graphs are loaded.
+    pub fn module_queue(&self) -> ModuleQueue {
+        ModuleQueue {
+            jobs: self.tx.clone(),
+            document: self.document,
+        }
+    }
+
+    /// Queues `source` without waiting for its value.
+    pub fn run(&self, source: String) {
+        let _ = self.tx.send(ScriptJob::Evaluate { source, reply: None });
+    }
+
+    /// Updates what `matchMedia()` queries are evaluated against, and has the page's lists whose
+    /// result changed fire `change`. Nothing happens when `environment` is the current one.
+    pub fn set_media(&self, environment: MediaEnvironment) {
+        {
+            let mut media = self.media.lock();
+            if *media == environment {
+                return;
+            }
+            *media = environment;
+        }
+        let _ = self.tx.send(ScriptJob::MediaChanged);
+    }
+
+    /// Fires `event` at the current document's tree, once the jobs before it ran. The returned
+    /// receiver resolves to `false` when a listener canceled it.
+    pub fn dispatch_event(&self, event: DomEvent) -> oneshot::Receiver<bool> {
+        let (reply, rx) = oneshot::channel();
+        let _ = self.tx.send(ScriptJob::DomEvent {
+            document: self.document,
+            event,
+            reply,
+        });
+        rx
+    }
+
+    /// Sets what `history.scrollRestoration` reads, for the entry the current document committed
+    /// into.
+    pub fn set_scroll_restoration(&self, mode: ScrollRestoration) {
+        self.scroll_restoration
+            .store(mode == ScrollRestoration::Manual, Ordering::Release);
+    }
+
+    /// Whether the page called `requestAnimationFrame()` since the last frame.
+    pub fn wants_animation_frame(&self) -> bool {
+        self.frame_requested.load(Ordering::Acquire)
+    }
+
+    /// Runs the animation frame callbacks for a frame drawn at `at`. The returned receiver
+    /// resolves once they are done.
+    pub fn animation_frame(&self, at: Instant) -> oneshot::Receiver<()> {
+        let (done, rx) = oneshot::channel();
+        let _ = self.tx.send(ScriptJob::AnimationFrame { at, done });
+        rx
+    }
+}
+
+impl Drop for ScriptThread {
+    fn drop(&mut self) {
+        // The thread's own page channel keeps its queue open, so it has to be told.
+        let _ = self.tx.send(ScriptJob::Terminate);
+    }
+}
+
+fn run_script_thread(
+    factory: &RuntimeFactory,
+    rx: mpsc::Receiver<ScriptJob>,
+    mut page: PageChannel,
+    frame_requested: &AtomicBool,
+) {
+    let mut runtime = match factory() {
+        Ok(runtime) => Some(runtime),
+        Err(e) => {
+            log::error!("Failed to start the script runtime: {e}");
+            None
+        }
+    };
+    let mut context: Option<Box<dyn ScriptContext>> = None;
+    let mut time_origin = Instant::now();
+    page.watchdog = page
+        .host
+        .limits
+        .time_slice
+        .and_then(|time_slice| match Watchdog::new(time_slice) {
+            Ok(watchdog) => Some(Arc::new(watchdog)),
+            Err(e) => {
+                log::error!("Failed to start the script watchdog, scripts run without a time limit: {e}");
+                None
+            }
+        });
+    // The attached debugger, which gets each new document's context
+    let mut inspector: Option<InspectorConnection> = None;
+    let jobs = page.jobs.clone();
+    set_local_task_notify(Arc::new(move || {
+        let _ = jobs.send(ScriptJob::RunLocalTasks);
+    }));
+
+    loop {
+        let wants_frame = context.as_ref().is_some_and(|ctx| ctx.wants_animation_frame());
+        frame_requested.store(wants_frame, Ordering::Release);
+
+        // Between jobs, wake up for the context's timers.
+        let job = match context.as_ref().and_then(|ctx| ctx.next_timer()) {
+            Some(deadline) => match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(job) => job,
+                Err(RecvTimeoutError::Timeout) => {
+                    start_task(&page);
+                    if let Some(ctx) = &mut context {
+                        ctx.run_timers();
+                        ctx.flush_mutations();
+                    }
+                    end_task(&mut context, &page);
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            },
+            None => match rx.recv() {
+                Ok(job) => job,
+                Err(_) => break,
+            },
+        };
+
+        start_task(&page);
+        match job {
+            ScriptJob::NewDocument { url, indexed_db, dom } => {
+                context = None;
+                clear_local_tasks();
+                time_origin = Instant::now();
+                page.document += 1;
+                page.url = url;
+                page.indexed_db = indexed_db;
+                page.dom = dom;
+            }
+            ScriptJob::FetchDone { document, id, outcome } => match &mut context {
+                Some(ctx) if document == page.document => ctx.fetch_done(id, outcome),
+                _ => log::debug!("Dropping fetch {id} of a replaced document"),
+            },
+            ScriptJob::ClipboardDone { document, id, outcome } => match &mut context {
+                Some(ctx) if document == page.document => ctx.clipboard_done(id, outcome),
+                _ => log::debug!("Dropping clipboard request {id} of a replaced document"),
+            },
+            ScriptJob::PermissionDone { document, id, state } => match &mut context {
+                Some(ctx) if document == page.document => ctx.permission_done(id, state),
+                _ => log::debug!("Dropping permission request {id} of a replaced document"),
+            },
+            ScriptJob::StartWorker { url } => {
+                context = workers::start_worker(runtime.as_deref_mut(), &mut page, url);
+            }
+            ScriptJob::Message { document, source, data } => match &mut context {
+                Some(ctx) if document == page.document => ctx.message(source, data),
+                _ => log::debug!("Dropping a message to a replaced document"),
+            },
+            ScriptJob::WorkerError { document, id, message } => match &mut context {
+                Some(ctx) if document == page.document => ctx.worker_error(id, message),
+                _ => log::debug!("Dropping an error of worker {id} of a replaced document"),
+            },
+            ScriptJob::MediaChanged => {
+                if let Some(ctx) = &mut context {
+                    ctx.media_changed();
+                }
+            }
+            ScriptJob::DomEvent { document, event, reply } => {
+                // Without a context no script could have listened.
+                let not_canceled = match &mut context {
+                    Some(ctx) if document == page.document => ctx.dispatch_event(&event),
+                    _ => true,
+                };
+                let _ = reply.send(not_canceled);
+            }
+            ScriptJob::InspectorConnect { incoming, outgoing } => {
+                let connection = InspectorConnection::new(incoming, outgoing);
+                if let Some(ctx) = document_context(&mut context, runtime.as_deref_mut(), &page, None) {
+                    ctx.attach_inspector(&connection);
+                }
+                inspector = Some(connection);
+            }
+            ScriptJob::InspectorMessage => {
+                if let Some(ctx) = &mut context {
+                    ctx.dispatch_inspector();
+                }
+            }
+            ScriptJob::InspectorDisconnect => {
+                inspector = None;
+                if let Some(ctx) = &mut context {
+                    ctx.detach_inspector();
+                }
+            }
+            ScriptJob::RunLocalTasks => {
+                if let Some(ctx) = &mut context {
+                    ctx.run_local_tasks();
+                }
+            }
+            ScriptJob::Terminate => break,
+            ScriptJob::AnimationFrame { at, done } => {
+                if let Some(ctx) = &mut context {
+                    ctx.run_animation_frame(high_res_timestamp(time_origin, at));
+                    // The frame is drawn with what the callbacks changed.
+                    ctx.flush_mutations();
+                }
+                let _ = done.send(());
+            }
+            ScriptJob::RunModule { document, url, graph } => {
+                if document != page.document {
+                    log::debug!("Dropping module script {url} of a replaced document");
+                } else if let Some(ctx) =
+                    document_context(&mut context, runtime.as_deref_mut(), &page, inspector.as_ref())
+                {
+                    ctx.run_module(&url, &graph);
+                }
+            }
+            ScriptJob::Evaluate { source, reply } => {
+                let result = match document_context(&mut context, runtime.as_deref_mut(), &page, inspector.as_ref()) {
+                    Some(ctx) => ctx.evaluate(&source),
+                    None => Err(ScriptError::Runtime("no script context available".into())),
+                };
+                match reply {
+                    Some(reply) => {
+                        let _ = reply.send(result);
+                    }
+                    None => {
+                        if let Err(e) = result {
+                            report_script_error(format!("Script failed: {e}"), None);
+                        }
+                    }
+                }
+            }
+        }
+
+        // What the task changed in the document goes to the tab worker before the next one runs.
+        if let Some(ctx) = &mut context {
+            ctx.flush_mutations();
+        }
+        end_task(&mut context, &page);
+    }
+    // The tasks hold on to the context, which has to go before the runtime does.
+    clear_local_tasks();
+}
+
+/// Starts the clock on a task of `page`'s thread.
+fn start_task(page: &PageChannel) {
+    if let Some(watchdog) = &page.watchdog {
+        watchdog.start();
+    }
+}
+
+/// Stops the clock, and tells the tab worker when the task ran into a limit.
+fn end_task(context: &mut Option<Box<dyn ScriptContext>>, page: &PageChannel) {
+    if let Some(watchdog) = &page.watchdog {
+        watchdog.stop();
+    }
+    let Some(violation) = context.as_mut().and_then(|ctx| ctx.take_limit_violation()) else {
+        return;
+    };
+    let _ = page.requests.send(ScriptRequest::LimitExceeded {
+        document: page.document,
+        violation,
+    });
+}
+
+/// The current document's context, created when it is first needed and attached to the
+/// debugger on `inspector`, if there is one.
+fn document_context<'a>(
+    context: &'a mut Option<Box<dyn ScriptContext>>,
+    runtime: Option<&mut (dyn ScriptRuntime + 'static)>,
+    page: &PageChannel,
+    inspector: Option<&InspectorConnection>,
+) -> Option<&'a mut Box<dyn ScriptContext>> {
+    if context.is_none() {
+        if let Some(runtime) = runtime {
+            match runtime.new_context(page.clone()) {
+                Ok(mut ctx) => {
+                    if let Some(connection) = inspector {
+                        ctx.attach_inspector(connection);
+                    }
+                    *context = Some(ctx);
+                }
+                Err(e) => log::error!("Failed to create a script context: {e}"),
+            }
+        }
+    }
+    context.as_mut()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gosub_web_platform::permissions::MemoryPermissionStore;
+    use serde_json::json;
+
+    /// Evaluates every script to the number of scripts its context has run.
+    struct CountingContext(u64);
+
+    impl ScriptContext for CountingContext {
+        fn evaluate(&mut self, source: &str) -> ScriptResult {
+            if source == "throw" {
+                return Err(ScriptError::Exception("boom".into()));
+            }
+            self.0 += 1;
+            Ok(json!(self.0))
+        }
+
+        /// Cancels events at nodes whose id is below the number of scripts run.
+        fn dispatch_event(&mut self, event: &DomEvent) -> bool {
+            event.target as u64 >= self.0
+        }
+    }
+
+    struct CountingRuntime;
+
+    impl ScriptRuntime for CountingRuntime {
+        fn new_context(&mut self, _page: PageChannel) -> anyhow::Result<Box<dyn ScriptContext>> {
+            Ok(Box::new(CountingContext(0)))
+        }
+    }
+
+    #[test]
+    fn each_document_gets_a_fresh_context() {
+        let host = ScriptHost::new(|| Ok(CountingRuntime));
+        let (requests, _) = tokio_mpsc::unbounded_channel();
+        let mut thread = ScriptThread::spawn(
+            &host,
+            "test-script".into(),
+            requests,
+            None,
+            None,
+            None,
+            Arc::new(MemoryPermissionStore::new()),
+        )
+        .unwrap();
+
+        assert_eq!(thread.evaluate("a".into()).blocking_recv().unwrap(), Ok(json!(1)));
+        thread.run("b".into());
+        assert_eq!(thread.evaluate("c".into()).blocking_recv().unwrap(), Ok(json!(3)));
+        assert_eq!(
+            thread.evaluate("throw".into()).blocking_recv().unwrap(),
+            Err(ScriptError::Exception("boom".into()))
+        );
+
+        thread.new_document(None, None, None);
+        assert_eq!(thread.evaluate("d".into()).blocking_recv().unwrap(), Ok(json!(1)));
+    }
+
+    #[test]
+    fn events_report_whether_they_were_canceled() {
+        let host = ScriptHost::new(|| Ok(CountingRuntime));
+        let (requests, _) = tokio_mpsc::unbounded_channel();
+        let thread = ScriptThread::spawn(
+            &host,
+            "test-script".into(),
+            requests,
+            None,
+            None,
+            None,
+            Arc::new(MemoryPermissionStore::new()),
+        )
+        .unwrap();
+        let focus = |target| DomEvent {
+            target,
+            event_type: "focus",
+            init: DomEventInit::Focus { related: None },
+        };
+
+        // No context yet, so no listeners.
+        assert!(thread.dispatch_event(focus(0)).blocking_recv().unwrap());
+
+        thread.run("a".into());
+        thread.run("b".into());
+        // Events come after the jobs queued before them.
+        assert!(!thread.dispatch_event(focus(1)).blocking_recv().unwrap());
+        assert!(thread.dispatch_event(focus(2)).blocking_recv().unwrap());
+    }
+}
@@ -0,0 +1,94 @@
+//! `AbortController` and `AbortSignal`.
+//!
+//! Pure JS on top of the events shim. What takes a signal: `fetch()` (which cancels
+//! the tab worker's fetch), `addEventListener()`'s `signal` option, and `AbortSignal.timeout()`,
+//! which is an ordinary timer.
+
+use gosub_webexecutor::js::{WebContext, WebRuntime};
+
+const ABORT_SHIM: &str = r#"(() => {
+    const { domError } = globalThis.__gosubMessageInternals;
+    const INTERNAL = Symbol("internal");
+    let abortSignal;
+
+    class AbortSignal extends EventTarget {
+        #aborted = false;
+        #reason = undefined;
+        constructor(token) {
+            if (token !== INTERNAL) throw new TypeError("Illegal constructor");
+            super();
+            this.onabort = null;
+        }
+        get aborted() {
+            return this.#aborted;
+        }
+        get reason() {
+            return this.#reason;
+        }
+        throwIfAborted() {
+            if (this.#aborted) throw this.#reason;
+        }
+        // Listeners that throw don't stop the abort; their errors are reported after the fact.
+        #abort(reason) {
+            if (this.#aborted) return;
+            this.#aborted = true;
+            this.#reason = reason === undefined ? domError("AbortError", "signal is aborted without reason") : reason;
+            try {
+                this.dispatchEvent(new Event("abort"));
+            } catch (e) {
+                setTimeout(() => {
+                    throw e;
+                }, 0);
+            }
+        }
+
+        static {
+            abortSignal = (signal, reason) => signal.#abort(reason);
+        }
+
+        static abort(reason) {
+            const signal = new AbortSignal(INTERNAL);
+            abortSignal(signal, reason);
+            return signal;
+        }
+        static timeout(ms) {
+            const signal = new AbortSignal(INTERNAL);
+            setTimeout(() => abortSignal(signal, domError("TimeoutError", "signal timed out")), ms);
+            return signal;
+        }
+        static any(signals) {
+            const signal = new AbortSignal(INTERNAL);
+            signals = [...signals];
+            const done = signals.find((source) => source.aborted);
+            if (done) {
+                abortSignal(signal, done.reason);
+                return signal;
+            }
+            const onAbort = (event) => {
+                for (const source of signals) source.removeEventListener("abort", onAbort);
+                abortSignal(signal, event.target.reason);
+            };
+            for (const source of signals) source.addEventListener("abort", onAbort);
+            return signal;
+        }
+    }
+
+    class AbortController {
+        #signal = new AbortSignal(INTERNAL);
+        get signal() {
+            return this.#signal;
+        }
+        abort(reason) {
+            abortSignal(this.#signal, reason);
+        }
+    }
+
+    globalThis.AbortSignal = AbortSignal;
+    globalThis.AbortController = AbortController;
+})()"#;
+
+/// Runs [`ABORT_SHIM`]. Goes after the messaging shim, whose helpers it borrows.
+pub(super) fn install_abort<RT: WebRuntime>(ctx: &mut RT::Context) -> anyhow::Result<()> {
+    ctx.run(ABORT_SHIM)?;
+    Ok(())
+}
@@ -0,0 +1,227 @@
+//! `navigator.clipboard`.
+//!
+//! The engine never touches the system clipboard itself. `readText()` and `writeText()` go to
+//! the tab worker, which asks the UA with [`EngineEvent::ClipboardRequested`]; the UA decides
+//! whether the page may have access (prompting, or checking for user activation) and answers with
+//! [`TabCommand::AnswerClipboard`]. The answer settles the promise. An origin whose
+//! `clipboard-read` or `clipboard-write` permission is denied is refused without asking. Workers
+//! have no clipboard.
+//!
+//! [`EngineEvent::ClipboardRequested`]: crate::events::EngineEvent::ClipboardRequested
+//! [`TabCommand::AnswerClipboard`]: crate::events::TabCommand::AnswerClipboard
+
+use super::{native_function, PageChannel, ScriptJob, ScriptRequest};
+use gosub_webexecutor::js::{WebContext, WebObject, WebRuntime, WebValue};
+use std::cell::Cell;
+use std::fmt::{Display, Formatter};
+use std::sync::mpsc;
+use uuid::Uuid;
+
+/// Global the native clipboard functions are handed to the shim on.
+const CLIPBOARD_GLOBAL: &str = "__gosubClipboard";
+/// Function the shim leaves behind for settling a clipboard promise.
+const CLIPBOARD_DONE: &str = "__gosubClipboardDone";
+/// Global the text read from the clipboard is parked on while it is handed over.
+const TEXT_GLOBAL: &str = "__gosubClipboardText";
+
+const CLIPBOARD_SHIM: &str = r#"(() => {
+    const native = globalThis.__gosubClipboard;
+    delete globalThis.__gosubClipboard;
+    const pending = new Map();
+
+    const request = (write, text) =>
+        new Promise((resolve, reject) => {
+            const id = native.request(write, text);
+            if (id === undefined) {
+                reject(new TypeError("The clipboard is not available."));
+                return;
+            }
+            pending.set(id, { resolve, reject });
+        });
+
+    class Clipboard {
+        readText() {
+            return request(false, "");
+        }
+        writeText(text) {
+            return request(true, String(text)).then(() => undefined);
+        }
+    }
+    globalThis.Clipboard = Clipboard;
+    if (typeof globalThis.navigator !== "object" || globalThis.navigator === null) globalThis.navigator = {};
+    Object.defineProperty(globalThis.navigator, "clipboard", {
+        value: new Clipboard(),
+        enumerable: true,
+        configurable: true,
+    });
+
+    Object.defineProperty(globalThis, "__gosubClipboardDone", {
+        value: (id, error) => {
+            const call = pending.get(id);
+            pending.delete(id);
+            const text = globalThis.__gosubClipboardText;
+            delete globalThis.__gosubClipboardText;
+            if (!call) return;
+            if (error === null) {
+                call.resolve(text);
+            } else {
+                const e = new Error(error);
+                e.name = "NotAllowedError";
+                call.reject(e);
+            }
+        },
+    });
+})()"#;
+
+/// Identifies one clipboard request of a page, so the UA's answer can be matched up.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ClipboardRequestId(Uuid);
+
+impl ClipboardRequestId {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for ClipboardRequestId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for ClipboardRequestId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// What a page wants from the system clipboard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClipboardAccess {
+    /// `navigator.clipboard.readText()`
+    ReadText,
+    /// `navigator.clipboard.writeText(text)`
+    WriteText { text: String },
+}
+
+/// The UA's answer to a [`ClipboardAccess`] request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClipboardAnswer {
+    /// The page may not access the clipboard, or the clipboard couldn't be used
+    Denied,
+    /// The text of a [`ClipboardAccess::WriteText`] was placed on the clipboard
+    Written,
+    /// The clipboard's text, for a [`ClipboardAccess::ReadText`]
+    Text(String),
+}
+
+/// The text read (empty for writes), or why access failed.
+pub type ClipboardOutcome = Result<String, String>;
+
+impl From<ClipboardAnswer> for ClipboardOutcome {
+    fn from(answer: ClipboardAnswer) -> Self {
+        match answer {
+            ClipboardAnswer::Denied => Err("Clipboard access was denied.".into()),
+            ClipboardAnswer::Written => Ok(String::new()),
+            ClipboardAnswer::Text(text) => Ok(text),
+        }
+    }
+}
+
+/// Where the outcome of a clipboard request goes.
+#[derive(Debug)]
+pub(crate) struct ClipboardReply {
+    jobs: mpsc::Sender<ScriptJob>,
+    document: u64,
+    id: u32,
+}
+
+impl ClipboardReply {
+    /// The document the request came from.
+    pub fn document(&self) -> u64 {
+        self.document
+    }
+
+    pub fn send(self, outcome: ClipboardOutcome) {
+        let _ = self.jobs.send(ScriptJob::ClipboardDone {
+            document: self.document,
+            id: self.id,
+            outcome,
+        });
+    }
+}
+
+/// Puts the native `request(write, text)` on the global object and runs [`CLIPBOARD_SHIM`].
+pub(super) fn install_clipboard<RT: WebRuntime>(ctx: &mut RT::Context, page: &PageChannel) -> anyhow::Result<()> {
+    let native = RT::Object::new(ctx)?;
+
+    let channel = page.clone();
+    let next_id = Cell::new(1u32);
+    let request = native_function::<RT>(ctx, move |args| {
+        let write = args.first().and_then(|v| v.as_bool().ok())?;
+        let access = if write {
+            let text = args.get(1).and_then(|v| v.as_string().ok())?;
+            ClipboardAccess::WriteText { text }
+        } else {
+            ClipboardAccess::ReadText
+        };
+        let id = next_id.get();
+        next_id.set(id.wrapping_add(1));
+        let reply = ClipboardReply {
+            jobs: channel.jobs.clone(),
+            document: channel.document,
+            id,
+        };
+        channel.requests.send(ScriptRequest::Clipboard { access, reply }).ok()?;
+        Some(id)
+    })?;
+    native.set_method("request", &request)?;
+
+    ctx.set_on_global_object(CLIPBOARD_GLOBAL, native.into())?;
+    ctx.run(CLIPBOARD_SHIM)?;
+    Ok(())
+}
+
+/// Settles the promise of clipboard request `id` with `outcome`.
+pub(super) fn deliver<RT: WebRuntime>(ctx: &mut RT::Context, id: u32, outcome: ClipboardOutcome) -> anyhow::Result<()> {
+    let error = match outcome {
+        Ok(text) => {
+            let text = RT::Value::new_string(ctx.clone(), &text)?;
+            ctx.set_on_global_object(TEXT_GLOBAL, text)?;
+            "null".to_string()
+        }
+        // A JSON string is a valid JS string literal.
+        Err(message) => serde_json::to_string(&message)?,
+    };
+    ctx.run(&format!("{CLIPBOARD_DONE}({id}, {error})"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn answers_settle_the_request_they_belong_to() {
+        let (jobs, rx) = mpsc::channel();
+        let reply = ClipboardReply {
+            jobs,
+            document: 4,
+            id: 9,
+        };
+        reply.send(ClipboardAnswer::Denied.into());
+        match rx.try_recv() {
+            Ok(ScriptJob::ClipboardDone { document, id, outcome }) => {
+                assert_eq!((document, id), (4, 9));
+                assert!(outcome.is_err());
+            }
+            _ => panic!("expected the clipboard outcome"),
+        }
+
+        assert_eq!(ClipboardOutcome::from(ClipboardAnswer::Written), Ok(String::new()));
+        assert_eq!(
+            ClipboardOutcome::from(ClipboardAnswer::Text("hi".into())),
+            Ok("hi".into())
+        );
+    }
+}
@@ -0,0 +1,276 @@
+//! `console`.
+//!
+//! The shim does the formatting: `%s`/`%d`/`%i`/`%f`/`%o`/`%O`/`%c` substitutions, a readable
+//! rendering of objects, `console.table()` as a text table, groups as indentation, and the
+//! counters and timers. Each finished line goes to the tab worker, which logs it through the
+//! `log` crate, tagged with the tab, and hands it to the UA as [`EngineEvent::ConsoleMessage`].
+//! Workers get a console too; their lines are prefixed with the worker's name.
+//!
+//! [`EngineEvent::ConsoleMessage`]: crate::events::EngineEvent::ConsoleMessage
+
+use super::{native_function, number_arg, PageChannel, ScriptRequest};
+use gosub_webexecutor::js::{WebContext, WebObject, WebRuntime, WebValue};
+use std::fmt::{Display, Formatter};
+
+/// Global the native console function is handed to the shim on.
+const CONSOLE_GLOBAL: &str = "__gosubConsole";
+
+/// The web-facing `console`. The level numbers are those of [`ConsoleLevel::from_js`].
+const CONSOLE_SHIM: &str = r#"(() => {
+    const native = globalThis.__gosubConsole;
+    delete globalThis.__gosubConsole;
+    const LOG = 0, INFO = 1, WARN = 2, ERROR = 3, DEBUG = 4;
+
+    const inspect = (value, depth = 2, seen = new Set()) => {
+        switch (typeof value) {
+            case "string": return JSON.stringify(value);
+            case "bigint": return `${value}n`;
+            case "symbol": return value.toString();
+            case "function": return `[Function: ${value.name || "(anonymous)"}]`;
+            case "undefined": return "undefined";
+            case "object": break;
+            default: return String(value);
+        }
+        if (value === null) return "null";
+        if (value instanceof Error) return value.stack || `${value.name}: ${value.message}`;
+        if (value instanceof Date) return value.toISOString();
+        if (value instanceof RegExp) return String(value);
+        if (seen.has(value)) return "[Circular]";
+        if (depth < 0) return Array.isArray(value) ? "[Array]" : "[Object]";
+        seen.add(value);
+        try {
+            if (Array.isArray(value)) {
+                return `[ ${value.map((v) => inspect(v, depth - 1, seen)).join(", ")} ]`;
+            }
+            if (value instanceof Map) {
+                const entries = [...value].map(([k, v]) => `${inspect(k, depth - 1, seen)} => ${inspect(v, depth - 1, seen)}`);
+                return `Map(${value.size}) { ${entries.join(", ")} }`;
+            }
+            if (value instanceof Set) {
+                return `Set(${value.size}) { ${[...value].map((v) => inspect(v, depth - 1, seen)).join(", ")} }`;
+            }
+            const name = value.constructor && value.constructor !== Object ? `${value.constructor.name} ` : "";
+            const entries = Object.keys(value).map((k) => `${k}: ${inspect(value[k], depth - 1, seen)}`);
+            return entries.length ? `${name}{ ${entries.join(", ")} }` : `${name}{}`;
+        } finally {
+            seen.delete(value);
+        }
+    };
+    const show = (value) => (typeof value === "string" ? value : inspect(value));
+
+    // https://console.spec.whatwg.org/#formatter
+    const format = (args) => {
+        if (args.length === 0) return "";
+        let [first, ...rest] = args;
+        if (typeof first !== "string") return args.map(show).join(" ");
+        first = first.replace(/%([sdifoOc%])/g, (match, spec) => {
+            if (spec === "%") return "%";
+            if (rest.length === 0) return match;
+            const arg = rest.shift();
+            switch (spec) {
+                case "s": return typeof arg === "string" ? arg : inspect(arg, 1);
+                case "d":
+                case "i": return typeof arg === "symbol" ? "NaN" : String(parseInt(arg, 10));
+                case "f": return typeof arg === "symbol" ? "NaN" : String(parseFloat(arg));
+                case "c": return "";
+                default: return inspect(arg);
+            }
+        });
+        return [first, ...rest.map(show)].join(" ");
+    };
+
+    let indent = "";
+    const print = (level, text) => native.send(level, text.split("\n").map((line) => indent + line).join("\n"));
+    const logger = (level) => (...args) => print(level, format(args));
+
+    const table = (data, columns) => {
+        if (typeof data !== "object" || data === null) return print(LOG, format([data]));
+        const rows = data instanceof Map ? [...data] : Object.entries(data);
+        const index = "(index)", values = "Values";
+        const keys = [];
+        let primitives = false;
+        for (const [, row] of rows) {
+            if (typeof row === "object" && row !== null) {
+                for (const key of Object.keys(row)) if (!keys.includes(key)) keys.push(key);
+            } else {
+                primitives = true;
+            }
+        }
+        const header = [index, ...(Array.isArray(columns) ? columns.map(String) : keys)];
+        if (primitives) header.push(values);
+        const cells = rows.map(([key, row]) => header.map((column) => {
+            if (column === index) return String(key);
+            const isObject = typeof row === "object" && row !== null;
+            if (column === values) return isObject ? "" : inspect(row, 0);
+            return isObject && column in row ? inspect(row[column], 0) : "";
+        }));
+        const widths = header.map((h, i) => Math.max(h.length, ...cells.map((row) => row[i].length)) + 2);
+        const line = (l, m, r) => l + widths.map((w) => "─".repeat(w)).join(m) + r;
+        const row = (values) => "│" + values.map((v, i) => {
+            const pad = widths[i] - v.length;
+            return " ".repeat(pad >> 1) + v + " ".repeat(pad - (pad >> 1));
+        }).join("│") + "│";
+        print(LOG, [line("┌", "┬", "┐"), row(header), line("├", "┼", "┤"), ...cells.map(row), line("└", "┴", "┘")].join("\n"));
+    };
+
+    const counts = new Map();
+    const timers = new Map();
+    const now = () => (globalThis.performance?.now ? performance.now() : Date.now());
+    const elapsed = (label) => `${label}: ${Math.round((now() - timers.get(label)) * 1000) / 1000} ms`;
+
+    const console = {
+        log: logger(LOG),
+        info: logger(INFO),
+        warn: logger(WARN),
+        error: logger(ERROR),
+        debug: logger(DEBUG),
+        dir: (item) => print(LOG, inspect(item)),
+        dirxml: logger(LOG),
+        table,
+        trace: (...args) => {
+            const stack = (new Error().stack || "").split("\n").slice(2).join("\n");
+            print(DEBUG, ["Trace" + (args.length ? `: ${format(args)}` : ""), stack].filter(Boolean).join("\n"));
+        },
+        assert: (condition, ...args) => {
+            if (condition) return;
+            if (typeof args[0] === "string") args[0] = `Assertion failed: ${args[0]}`;
+            else args.unshift("Assertion failed");
+            print(ERROR, format(args));
+        },
+        count: (label = "default") => {
+            label = String(label);
+            const count = (counts.get(label) || 0) + 1;
+            counts.set(label, count);
+            print(INFO, `${label}: ${count}`);
+        },
+        countReset: (label = "default") => {
+            label = String(label);
+            if (counts.has(label)) counts.set(label, 0);
+            else print(WARN, `Count for '${label}' does not exist`);
+        },
+        group: (...args) => {
+            if (args.length) print(LOG, format(args));
+            indent += "  ";
+        },
+        groupCollapsed: (...args) => console.group(...args),
+        groupEnd: () => {
+            indent = indent.slice(2);
+        },
+        time: (label = "default") => {
+            label = String(label);
+            if (timers.has(label)) print(WARN, `Timer '${label}' already exists`);
+            else timers.set(label, now());
+        },
+        timeLog: (label = "default", ...args) => {
+            label = String(label);
+            if (!timers.has(label)) return print(WARN, `Timer '${label}' does not exist`);
+            print(INFO, [elapsed(label), ...args.map(show)].join(" "));
+        },
+        timeEnd: (label = "default") => {
+            label = String(label);
+            if (!timers.has(label)) return print(WARN, `Timer '${label}' does not exist`);
+            print(INFO, elapsed(label));
+            timers.delete(label);
+        },
+        clear: () => {},
+    };
+    Object.defineProperty(globalThis, "console", {
+        value: console,
+        writable: true,
+        configurable: true,
+    });
+})()"#;
+
+/// How serious a console message is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ConsoleLevel {
+    /// `console.log()`, and the other methods that have no level of their own
+    Log,
+    /// `console.info()`, the counters and timers
+    Info,
+    /// `console.warn()`
+    Warn,
+    /// `console.error()` and failed `console.assert()`s
+    Error,
+    /// `console.debug()` and `console.trace()`
+    Debug,
+}
+
+impl ConsoleLevel {
+    /// The level for the shim's level number.
+    fn from_js(level: f64) -> Option<Self> {
+        Some(match level as u32 {
+            0 => Self::Log,
+            1 => Self::Info,
+            2 => Self::Warn,
+            3 => Self::Error,
+            4 => Self::Debug,
+            _ => return None,
+        })
+    }
+}
+
+impl Display for ConsoleLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Log => "log",
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Error => "error",
+            Self::Debug => "debug",
+        })
+    }
+}
+
+impl From<ConsoleLevel> for log::Level {
+    fn from(level: ConsoleLevel) -> Self {
+        match level {
+            ConsoleLevel::Log | ConsoleLevel::Info => log::Level::Info,
+            ConsoleLevel::Warn => log::Level::Warn,
+            ConsoleLevel::Error => log::Level::Error,
+            ConsoleLevel::Debug => log::Level::Debug,
+        }
+    }
+}
+
+/// Puts the native `send(level, message)` on the global object and runs [`CONSOLE_SHIM`].
+pub(super) fn install_console<RT: WebRuntime>(ctx: &mut RT::Context, page: &PageChannel) -> anyhow::Result<()> {
+    let native = RT::Object::new(ctx)?;
+
+    let requests = page.requests.clone();
+    let document = page.document;
+    let prefix = page
+        .worker
+        .as_ref()
+        .map(|scope| format!("[worker {}] ", scope.label()))
+        .unwrap_or_default();
+    let send = native_function::<RT>(ctx, move |args| {
+        let level = number_arg(args, 0).and_then(ConsoleLevel::from_js)?;
+        let message = args.get(1).and_then(|v| v.as_string().ok())?;
+        let _ = requests.send(ScriptRequest::Console {
+            document,
+            level,
+            message: format!("{prefix}{message}"),
+        });
+        None
+    })?;
+    native.set_method("send", &send)?;
+
+    ctx.set_on_global_object(CONSOLE_GLOBAL, native.into())?;
+    ctx.run(CONSOLE_SHIM)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_map_onto_the_log_crate() {
+        assert_eq!(ConsoleLevel::from_js(2.0), Some(ConsoleLevel::Warn));
+        assert_eq!(ConsoleLevel::from_js(9.0), None);
+        assert_eq!(log::Level::from(ConsoleLevel::Log), log::Level::Info);
+        assert_eq!(log::Level::from(ConsoleLevel::Error), log::Level::Error);
+        assert_eq!(ConsoleLevel::Debug.to_string(), "debug");
+    }
+}
@@ -0,0 +1,1143 @@
+//! `document`, and the `Node`, `Element`, `Text` and `Document` objects it hands out.
+//!
+//! The document lives on the tab worker, which renders it; scripts run on the script thread and
+//! need answers right away. So every context gets a [`DomTree`]: a copy of the document taken when
+//! it committed, which scripts read and change directly. What they change is also recorded as
+//! [`DomMutation`]s, which the script thread hands to the tab worker after every task (see
+//! [`ScriptContext::flush_mutations`](super::ScriptContext::flush_mutations)); the worker applies
+//! them to its document with [`apply_mutations`] and lays it out again. Nodes scripts create get
+//! the ids the document would give them, and the worker keeps track of the ones it gave instead.
+//!
+//! The tree is exposed through the `#[web_interop]` glue of [`DomBinding`], which deals in node
+//! ids; [`DOM_SHIM`] builds the web-facing objects on top of it. There is one wrapper per node, so
+//! `getElementById()` hands out the same object every time, and nodes are event targets whose
+//! events bubble up the tree to the document and the global object.
+//!
+//! Events the user causes, like focus moving, are fired at the script's nodes by the tab worker
+//! as [`DomEvent`]s, through a function the shim leaves behind ([`DISPATCH_EVENT`]).
+//!
+//! `<form>` elements are `HTMLFormElement`s. Their `submit()` and `requestSubmit()` are handed to
+//! the worker after the task's mutations, which builds the submission from its own document, see
+//! [`forms`](crate::engine::forms).
+
+use crate::events::Modifiers;
+use crate::html::{EngineDocument, RenderConfiguration};
+use cow_utils::CowUtils;
+use gosub_html5::node::HTML_NAMESPACE;
+use gosub_interface::document::Document as _;
+use gosub_interface::node::NodeType;
+use gosub_shared::byte_stream::Location;
+use gosub_shared::node::NodeId;
+use gosub_shared::types::Result;
+use gosub_webexecutor::js::{
+    Args, IntoRustValue, IntoWebValue, JSInterop, WebContext, WebFunction, WebFunctionCallBack, WebObject, WebRuntime,
+    WebValue,
+};
+use gosub_webinterop::{web_fns, web_interop};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Stands in for a missing node where [`DomBinding`] hands back an id.
+const NO_NODE: i64 = -1;
+
+/// Function the shim leaves behind for firing a [`DomEvent`].
+const DISPATCH_EVENT: &str = "__gosubDispatchEvent";
+
+const DOM_SHIM: &str = r#"(() => {
+    const native = globalThis.__gosubDom;
+    delete globalThis.__gosubDom;
+    const PARENT = Symbol.for("gosub.eventParent");
+    const INTERNAL = Symbol("internal");
+    const [ELEMENT_NODE, TEXT_NODE, COMMENT_NODE, DOCUMENT_NODE, DOCUMENT_TYPE_NODE] = [1, 3, 8, 9, 10];
+    const domError = (failure) => {
+        const split = failure.indexOf(": ");
+        const error = new Error(failure.slice(split + 2));
+        error.name = failure.slice(0, split);
+        return error;
+    };
+
+    // One wrapper per node, created on first use.
+    const ids = new WeakMap();
+    const wrappers = new Map();
+    const idOf = (node, what) => {
+        const id = ids.get(node);
+        if (id === undefined) throw new TypeError(`${what}: the argument is not a Node`);
+        return id;
+    };
+    const classOf = (type, id) =>
+        ({
+            [ELEMENT_NODE]: native.node_name(id) === "FORM" ? HTMLFormElement : HTMLElement,
+            [TEXT_NODE]: Text,
+            [COMMENT_NODE]: Comment,
+            [DOCUMENT_NODE]: Document,
+            [DOCUMENT_TYPE_NODE]: DocumentType,
+        })[type] ?? Node;
+    const wrap = (id) => {
+        if (id < 0) return null;
+        let node = wrappers.get(id);
+        if (!node) {
+            node = new (classOf(native.node_type(id), id))(INTERNAL, id);
+            wrappers.set(id, node);
+        }
+        return node;
+    };
+    const nodes = (list) => Object.freeze(list.map(wrap));
+    const insert = (node, parent, child) => {
+        const before = child == null ? -1 : idOf(child, "insertBefore");
+        const failure = native.insert(idOf(node, "insertBefore"), ids.get(parent), before);
+        if (failure) throw domError(failure);
+        return node;
+    };
+    const asNode = (value) => (ids.has(value) ? value : document.createTextNode(String(value)));
+
+    class Node extends EventTarget {
+        constructor(token, id) {
+            if (token !== INTERNAL) throw new TypeError("Illegal constructor");
+            super();
+            ids.set(this, id);
+        }
+        get nodeType() {
+            return native.node_type(ids.get(this));
+        }
+        get nodeName() {
+            return native.node_name(ids.get(this));
+        }
+        get ownerDocument() {
+            return this.nodeType === DOCUMENT_NODE ? null : document;
+        }
+        get parentNode() {
+            return wrap(native.parent(ids.get(this)));
+        }
+        get parentElement() {
+            const parent = this.parentNode;
+            return parent?.nodeType === ELEMENT_NODE ? parent : null;
+        }
+        get childNodes() {
+            return nodes(native.children(ids.get(this)));
+        }
+        get firstChild() {
+            return wrap(native.children(ids.get(this)).at(0) ?? -1);
+        }
+        get lastChild() {
+            return wrap(native.children(ids.get(this)).at(-1) ?? -1);
+        }
+        get previousSibling() {
+            const siblings = this.parentNode?.childNodes ?? [];
+            return siblings[siblings.indexOf(this) - 1] ?? null;
+        }
+        get nextSibling() {
+            const siblings = this.parentNode?.childNodes ?? [];
+            const index = siblings.indexOf(this);
+            return index < 0 ? null : siblings[index + 1] ?? null;
+        }
+        get isConnected() {
+            let node = this;
+            while (node.parentNode) node = node.parentNode;
+            return node === document;
+        }
+        get textContent() {
+            const type = this.nodeType;
+            return type === DOCUMENT_NODE || type === DOCUMENT_TYPE_NODE ? null : native.text(ids.get(this));
+        }
+        set textContent(value) {
+            if (this.nodeType === DOCUMENT_NODE || this.nodeType === DOCUMENT_TYPE_NODE) return;
+            native.set_text(ids.get(this), value == null ? "" : String(value));
+        }
+        get nodeValue() {
+            return this.nodeType === TEXT_NODE || this.nodeType === COMMENT_NODE ? this.textContent : null;
+        }
+        set nodeValue(value) {
+            if (this.nodeType === TEXT_NODE || this.nodeType === COMMENT_NODE) this.textContent = value;
+        }
+        hasChildNodes() {
+            return native.children(ids.get(this)).length > 0;
+        }
+        contains(other) {
+            for (let node = other; node; node = node.parentNode) if (node === this) return true;
+            return false;
+        }
+        appendChild(node) {
+            return insert(node, this, null);
+        }
+        insertBefore(node, child) {
+            return insert(node, this, child);
+        }
+        removeChild(child) {
+            if (child?.parentNode !== this) {
+                throw domError("NotFoundError: The node to be removed is not a child of this node.");
+            }
+            native.remove(idOf(child, "removeChild"));
+            return child;
+        }
+        replaceChild(node, child) {
+            if (child?.parentNode !== this) {
+                throw domError("NotFoundError: The node to be replaced is not a child of this node.");
+            }
+            if (node !== child) {
+                insert(node, this, child);
+                native.remove(idOf(child, "replaceChild"));
+            }
+            return child;
+        }
+        remove() {
+            native.remove(ids.get(this));
+        }
+        get [PARENT]() {
+            return this.nodeType === DOCUMENT_NODE ? globalThis : this.parentNode;
+        }
+    }
+    Object.assign(Node, { ELEMENT_NODE, TEXT_NODE, COMMENT_NODE, DOCUMENT_NODE, DOCUMENT_TYPE_NODE });
+
+    // What elements and the document share: element children and lookups below them.
+    const parentNodeMembers = {
+        get children() {
+            return this.childNodes.filter((node) => node.nodeType === ELEMENT_NODE);
+        },
+        get firstElementChild() {
+            return this.children.at(0) ?? null;
+        },
+        get lastElementChild() {
+            return this.children.at(-1) ?? null;
+        },
+        get childElementCount() {
+            return this.children.length;
+        },
+        getElementsByTagName(name) {
+            return nodes(native.elements_by_tag_name(ids.get(this), String(name)));
+        },
+        append(...values) {
+            for (const value of values) this.appendChild(asNode(value));
+        },
+        prepend(...values) {
+            const first = this.firstChild;
+            for (const value of values) this.insertBefore(asNode(value), first);
+        },
+    };
+
+    class Element extends Node {
+        get tagName() {
+            return this.nodeName;
+        }
+        get localName() {
+            return native.node_name(ids.get(this)).toLowerCase();
+        }
+        get id() {
+            return this.getAttribute("id") ?? "";
+        }
+        set id(value) {
+            this.setAttribute("id", value);
+        }
+        get className() {
+            return this.getAttribute("class") ?? "";
+        }
+        set className(value) {
+            this.setAttribute("class", value);
+        }
+        getAttribute(name) {
+            return native.attribute(ids.get(this), String(name).toLowerCase()).at(0) ?? null;
+        }
+        hasAttribute(name) {
+            return this.getAttribute(name) !== null;
+        }
+        getAttributeNames() {
+            return native.attribute_names(ids.get(this));
+        }
+        setAttribute(name, value) {
+            native.set_attribute(ids.get(this), String(name).toLowerCase(), String(value));
+        }
+        removeAttribute(name) {
+            native.remove_attribute(ids.get(this), String(name).toLowerCase());
+        }
+    }
+    Object.defineProperties(Element.prototype, Object.getOwnPropertyDescriptors(parentNodeMembers));
+
+    class HTMLElement extends Element {}
+
+    // Submitting is left to the tab, which sends the form once the current task is done.
+    class HTMLFormElement extends HTMLElement {
+        submit() {
+            native.submit(ids.get(this), -1);
+        }
+        requestSubmit(submitter = null) {
+            const by = submitter === null ? -1 : idOf(submitter, "requestSubmit");
+            if (this.dispatchEvent(new Event("submit", { bubbles: true, cancelable: true }))) {
+                native.submit(ids.get(this), by);
+            }
+        }
+    }
+
+    class UIEvent extends Event {
+        constructor(type, init = {}) {
+            super(type, init);
+            this.view = init?.view ?? null;
+            this.detail = init?.detail ?? 0;
+        }
+    }
+    class FocusEvent extends UIEvent {
+        constructor(type, init = {}) {
+            super(type, init);
+            this.relatedTarget = init?.relatedTarget ?? null;
+        }
+    }
+    class KeyboardEvent extends UIEvent {
+        constructor(type, init = {}) {
+            super(type, init);
+            this.key = String(init?.key ?? "");
+            this.code = String(init?.code ?? "");
+            this.location = init?.location ?? 0;
+            this.repeat = !!init?.repeat;
+            this.isComposing = !!init?.isComposing;
+            for (const modifier of ["ctrlKey", "shiftKey", "altKey", "metaKey"]) this[modifier] = !!init?.[modifier];
+        }
+        getModifierState(key) {
+            const state = { Control: this.ctrlKey, Shift: this.shiftKey, Alt: this.altKey, Meta: this.metaKey };
+            return state[key] ?? false;
+        }
+    }
+
+    class CharacterData extends Node {
+        get data() {
+            return this.textContent;
+        }
+        set data(value) {
+            this.textContent = value;
+        }
+        get length() {
+            return this.data.length;
+        }
+    }
+    class Text extends CharacterData {}
+    class Comment extends CharacterData {}
+
+    class DocumentType extends Node {
+        get name() {
+            return this.nodeName;
+        }
+    }
+
+    class Document extends Node {
+        get documentElement() {
+            return this.firstElementChild;
+        }
+        get head() {
+            return this.documentElement?.children.find((node) => node.localName === "head") ?? null;
+        }
+        get body() {
+            return this.documentElement?.children.find((node) => node.localName === "body") ?? null;
+        }
+        get title() {
+            const title = this.getElementsByTagName("title").at(0);
+            return (title?.textContent ?? "").replace(/[\t\n\f\r ]+/g, " ").trim();
+        }
+        getElementById(id) {
+            return wrap(native.element_by_id(String(id)));
+        }
+        createElement(name) {
+            return wrap(native.create_element(String(name).toLowerCase()));
+        }
+        createTextNode(data) {
+            return wrap(native.create_text(String(data)));
+        }
+    }
+    Object.defineProperties(Document.prototype, Object.getOwnPropertyDescriptors(parentNodeMembers));
+
+    Object.assign(globalThis, {
+        UIEvent,
+        FocusEvent,
+        KeyboardEvent,
+        Node,
+        Element,
+        HTMLElement,
+        HTMLFormElement,
+        CharacterData,
+        Text,
+        Comment,
+        DocumentType,
+        Document,
+    });
+    const document = wrap(native.root());
+    Object.defineProperty(globalThis, "document", { get: () => document, enumerable: true });
+
+    // Fires an event the user caused, see `DomEvent`. Reports whether a listener canceled it,
+    // and what the first listener that failed threw.
+    const interfaces = { FocusEvent, KeyboardEvent };
+    Object.defineProperty(globalThis, "__gosubDispatchEvent", {
+        value: ({ target, kind, type, init }) => {
+            const related = wrap(init.relatedTarget ?? -1);
+            const event = new interfaces[kind](type, { ...init, view: globalThis, relatedTarget: related });
+            event.isTrusted = true;
+            let error = null;
+            try {
+                wrap(target)?.dispatchEvent(event);
+            } catch (e) {
+                error = String(e);
+            }
+            return JSON.stringify({ canceled: event.defaultPrevented, error });
+        },
+    });
+})()"#;
+
+/// A change scripts made to their document, to be made to the tab worker's copy too. Node ids are
+/// the ones in the script's [`DomTree`].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum DomMutation {
+    CreateElement {
+        id: usize,
+        name: String,
+    },
+    CreateText {
+        id: usize,
+        text: String,
+    },
+    /// `node` moves under `parent`, before its child `before`, or last without one
+    Insert {
+        node: usize,
+        parent: usize,
+        before: Option<usize>,
+    },
+    /// `node` is taken out of the tree
+    Remove {
+        node: usize,
+    },
+    SetAttribute {
+        node: usize,
+        name: String,
+        value: String,
+    },
+    RemoveAttribute {
+        node: usize,
+        name: String,
+    },
+    /// The data of a text or comment node changed
+    SetText {
+        node: usize,
+        text: String,
+    },
+}
+
+/// An event the user caused, for the tab worker to fire at node `target` of the script's
+/// [`DomTree`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DomEvent {
+    pub target: usize,
+    /// The event's `type`, e.g. `"focus"`
+    pub event_type: &'static str,
+    pub init: DomEventInit,
+}
+
+/// What the event carries besides its type, which also picks its interface.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DomEventInit {
+    /// A `FocusEvent`; `related` is the element focus moves from or to
+    Focus { related: Option<usize> },
+    /// A `KeyboardEvent`, with the DOM `key` and `code` values
+    Key {
+        key: String,
+        code: String,
+        modifiers: Modifiers,
+    },
+}
+
+impl DomEvent {
+    /// The events of focus moving from `blurred` to `focused`, in the order they fire: `blur`
+    /// and `focusout` at the element losing focus, then `focus` and `focusin` at the one gaining
+    /// it. `blur` and `focus` don't bubble.
+    pub(crate) fn focus_change(blurred: Option<usize>, focused: Option<usize>) -> Vec<Self> {
+        let mut events = Vec::new();
+        if let Some(target) = blurred {
+            for event_type in ["blur", "focusout"] {
+                events.push(Self {
+                    target,
+                    event_type,
+                    init: DomEventInit::Focus { related: focused },
+                });
+            }
+        }
+        if let Some(target) = focused {
+            for event_type in ["focus", "focusin"] {
+                events.push(Self {
+                    target,
+                    event_type,
+                    init: DomEventInit::Focus { related: blurred },
+                });
+            }
+        }
+        events
+    }
+
+    /// The call of [`DISPATCH_EVENT`] that fires the event.
+    fn dispatch_call(&self) -> String {
+        let (kind, init) = match &self.init {
+            DomEventInit::Focus { related } => (
+                "FocusEvent",
+                serde_json::json!({
+                    "bubbles": matches!(self.event_type, "focusin" | "focusout"),
+                    "relatedTarget": js_id(*related),
+                }),
+            ),
+            DomEventInit::Key { key, code, modifiers } => (
+                "KeyboardEvent",
+                serde_json::json!({
+                    "bubbles": true,
+                    "cancelable": true,
+                    "key": key,
+                    "code": code,
+                    "ctrlKey": modifiers.contains(Modifiers::CONTROL),
+                    "shiftKey": modifiers.contains(Modifiers::SHIFT),
+                    "altKey": modifiers.contains(Modifiers::ALT),
+                    "metaKey": modifiers.contains(Modifiers::META),
+                }),
+            ),
+        };
+        let args = serde_json::json!({
+            "target": self.target,
+            "kind": kind,
+            "type": self.event_type,
+            "init": init,
+        });
+        format!("{DISPATCH_EVENT}({args})")
+    }
+}
+
+/// How dispatching a [`DomEvent`] went.
+#[derive(Debug, serde::Deserialize)]
+pub(super) struct Dispatched {
+    /// A listener called `preventDefault()`
+    pub canceled: bool,
+    /// What the first listener that failed threw
+    pub error: Option<String>,
+}
+
+/// Fires `event` in `ctx`, whose document has the node.
+pub(super) fn dispatch_event<RT: WebRuntime>(ctx: &mut RT::Context, event: &DomEvent) -> anyhow::Result<Dispatched> {
+    let outcome = ctx.run(&event.dispatch_call())?.as_string()?;
+    Ok(serde_json::from_str(&outcome)?)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum DomData {
+    Document,
+    Doctype(String),
+    Element {
+        name: String,
+        attributes: HashMap<String, String>,
+    },
+    Text(String),
+    Comment(String),
+}
+
+#[derive(Debug, Clone)]
+struct DomNode {
+    data: DomData,
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+/// A document as its scripts see it. See the [module docs](self).
+#[derive(Debug, Clone)]
+pub(crate) struct DomTree {
+    nodes: HashMap<usize, DomNode>,
+    root: usize,
+    /// The id the next node gets, in step with the document's
+    next_id: usize,
+    /// What changed since the last [`take_mutations`](Self::take_mutations)
+    mutations: Vec<DomMutation>,
+}
+
+impl DomTree {
+    /// Copies the tree of `doc`.
+    pub(crate) fn snapshot<C: RenderConfiguration>(doc: &EngineDocument<C>) -> Self {
+        let root = doc.root();
+        let mut nodes = HashMap::new();
+        let mut stack = vec![root];
+        while let Some(id) = stack.pop() {
+            let data = match doc.node_type(id) {
+                NodeType::DocumentNode => DomData::Document,
+                NodeType::DocTypeNode => DomData::Doctype(doc.doctype_name(id).unwrap_or_default().to_string()),
+                NodeType::TextNode => DomData::Text(doc.text_value(id).unwrap_or_default().to_string()),
+                NodeType::CommentNode => DomData::Comment(doc.comment_value(id).unwrap_or_default().to_string()),
+                NodeType::ElementNode => DomData::Element {
+                    name: doc.tag_name(id).unwrap_or_default().to_string(),
+                    attributes: doc.attributes(id).cloned().unwrap_or_default(),
+                },
+            };
+            let children = doc.children(id);
+            nodes.insert(
+                usize::from(id),
+                DomNode {
+                    data,
+                    parent: doc.parent(id).map(usize::from),
+                    children: children.iter().map(|&child| usize::from(child)).collect(),
+                },
+            );
+            stack.extend(children.iter().copied());
+        }
+        Self {
+            nodes,
+            root: usize::from(root),
+            next_id: usize::from(doc.peek_next_id()),
+            mutations: Vec::new(),
+        }
+    }
+
+    /// Hands over what changed since the last call.
+    pub(crate) fn take_mutations(&mut self) -> Vec<DomMutation> {
+        std::mem::take(&mut self.mutations)
+    }
+
+    fn node(&self, id: usize) -> Option<&DomNode> {
+        self.nodes.get(&id)
+    }
+
+    fn add(&mut self, data: DomData) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.nodes.insert(
+            id,
+            DomNode {
+                data,
+                parent: None,
+                children: Vec::new(),
+            },
+        );
+        id
+    }
+
+    fn create_element(&mut self, name: &str) -> usize {
+        let id = self.add(DomData::Element {
+            name: name.to_string(),
+            attributes: HashMap::new(),
+        });
+        self.mutations.push(DomMutation::CreateElement {
+            id,
+            name: name.to_string(),
+        });
+        id
+    }
+
+    fn create_text(&mut self, text: &str) -> usize {
+        let id = self.add(DomData::Text(text.to_string()));
+        self.mutations.push(DomMutation::CreateText {
+            id,
+            text: text.to_string(),
+        });
+        id
+    }
+
+    /// `ancestor` or one of its descendants is `node`.
+    fn is_inclusive_ancestor(&self, ancestor: usize, node: usize) -> bool {
+        let mut current = Some(node);
+        while let Some(id) = current {
+            if id == ancestor {
+                return true;
+            }
+            current = self.node(id).and_then(|n| n.parent);
+        }
+        false
+    }
+
+    /// Moves `node` under `parent`, before `before` or last. Fails with the `DOMException` to
+    /// throw, as `"<name>: <message>"`.
+    fn insert(&mut self, node: usize, parent: usize, before: Option<usize>) -> std::result::Result<(), String> {
+        let (Some(child), Some(target)) = (self.node(node), self.node(parent)) else {
+            return Err("NotFoundError: The node is not part of this document.".into());
+        };
+        let allowed = matches!(
+            (&target.data, &child.data),
+            (
+                DomData::Document | DomData::Element { .. },
+                DomData::Element { .. } | DomData::Comment(_)
+            ) | (DomData::Element { .. }, DomData::Text(_))
+                | (DomData::Document, DomData::Doctype(_))
+        );
+        if !allowed || self.is_inclusive_ancestor(node, parent) {
+            return Err("HierarchyRequestError: The operation would yield an incorrect node tree.".into());
+        }
+        if before.is_some_and(|before| !target.children.contains(&before)) {
+            return Err(
+                "NotFoundError: The node before which the new node is to be inserted is not a child of this node."
+                    .into(),
+            );
+        }
+        // Inserting a node before itself puts it where it already is.
+        let before = match before {
+            Some(before) if before == node => self.next_sibling(node),
+            before => before,
+        };
+
+        self.detach(node);
+        let Some(target) = self.nodes.get_mut(&parent) else {
+            return Ok(());
+        };
+        let position = before.and_then(|before| target.children.iter().position(|&id| id == before));
+        match position {
+            Some(position) => target.children.insert(position, node),
+            None => target.children.push(node),
+        }
+        if let Some(child) = self.nodes.get_mut(&node) {
+            child.parent = Some(parent);
+        }
+        self.mutations.push(DomMutation::Insert { node, parent, before });
+        Ok(())
+    }
+
+    fn next_sibling(&self, node: usize) -> Option<usize> {
+        let parent = self.node(self.node(node)?.parent?)?;
+        let index = parent.children.iter().position(|&id| id == node)?;
+        parent.children.get(index + 1).copied()
+    }
+
+    /// Takes `node` out of its parent, without recording it.
+    fn detach(&mut self, node: usize) -> bool {
+        let Some(parent) = self.nodes.get_mut(&node).and_then(|n| n.parent.take()) else {
+            return false;
+        };
+        if let Some(parent) = self.nodes.get_mut(&parent) {
+            parent.children.retain(|&id| id != node);
+        }
+        true
+    }
+
+    fn remove(&mut self, node: usize) {
+        if self.detach(node) {
+            self.mutations.push(DomMutation::Remove { node });
+        }
+    }
+
+    /// The `textContent` of `id`: its data, or the data of the text nodes below it.
+    fn text(&self, id: usize) -> String {
+        let Some(node) = self.node(id) else {
+            return String::new();
+        };
+        if let DomData::Text(data) | DomData::Comment(data) = &node.data {
+            return data.clone();
+        }
+        let mut text = String::new();
+        let mut stack: Vec<usize> = node.children.iter().rev().copied().collect();
+        while let Some(id) = stack.pop() {
+            let Some(node) = self.node(id) else {
+                continue;
+            };
+            match &node.data {
+                DomData::Text(data) => text.push_str(data),
+                DomData::Element { .. } => stack.extend(node.children.iter().rev()),
+                _ => {}
+            }
+        }
+        text
+    }
+
+    /// Sets the `textContent` of `id`: the data of a text or comment node, or a single text node
+    /// in place of an element's children.
+    fn set_text(&mut self, id: usize, text: &str) {
+        let Some(node) = self.nodes.get_mut(&id) else {
+            return;
+        };
+        match &mut node.data {
+            DomData::Text(data) | DomData::Comment(data) => {
+                *data = text.to_string();
+                self.mutations.push(DomMutation::SetText {
+                    node: id,
+                    text: text.to_string(),
+                });
+            }
+            DomData::Element { .. } => {
+                let children = node.children.clone();
+                for child in children {
+                    self.remove(child);
+                }
+                if !text.is_empty() {
+                    let child = self.create_text(text);
+                    let _ = self.insert(child, id, None);
+                }
+            }
+            DomData::Document | DomData::Doctype(_) => {}
+        }
+    }
+
+    fn attributes_mut(&mut self, id: usize) -> Option<&mut HashMap<String, String>> {
+        match &mut self.nodes.get_mut(&id)?.data {
+            DomData::Element { attributes, .. } => Some(attributes),
+            _ => None,
+        }
+    }
+
+    /// The elements below `root` in tree order, `root` excluded.
+    fn descendant_elements(&self, root: usize) -> Vec<usize> {
+        let mut elements = Vec::new();
+        let mut stack: Vec<usize> = self.node(root).map(|n| n.children.clone()).unwrap_or_default();
+        stack.reverse();
+        while let Some(id) = stack.pop() {
+            let Some(node) = self.node(id) else {
+                continue;
+            };
+            if matches!(node.data, DomData::Element { .. }) {
+                elements.push(id);
+            }
+            stack.extend(node.children.iter().rev());
+        }
+        elements
+    }
+}
+
+/// Makes the changes scripts made to their [`DomTree`] to `doc`. `created` maps the ids of the
+/// nodes scripts created to the ones `doc` gave them; it lives as long as the document.
+pub(crate) fn apply_mutations<C: RenderConfiguration>(
+    doc: &mut EngineDocument<C>,
+    mutations: Vec<DomMutation>,
+    created: &mut HashMap<usize, NodeId>,
+) {
+    let resolve =
+        |created: &HashMap<usize, NodeId>, id: usize| created.get(&id).copied().unwrap_or_else(|| NodeId::from(id));
+    for mutation in mutations {
+        match mutation {
+            DomMutation::CreateElement { id, name } => {
+                let node = doc.create_element(&name, Some(HTML_NAMESPACE), HashMap::new(), Location::default());
+                created.insert(id, node);
+            }
+            DomMutation::CreateText { id, text } => {
+                let node = doc.create_text(&text, Location::default());
+                created.insert(id, node);
+            }
+            DomMutation::Insert { node, parent, before } => {
+                let (node, parent) = (resolve(created, node), resolve(created, parent));
+                doc.detach(node);
+                let position = before.and_then(|before| {
+                    let before = resolve(created, before);
+                    doc.children(parent).iter().position(|&id| id == before)
+                });
+                doc.attach(node, parent, position);
+            }
+            DomMutation::Remove { node } => doc.detach(resolve(created, node)),
+            DomMutation::SetAttribute { node, name, value } => doc.set_attribute(resolve(created, node), &name, &value),
+            DomMutation::RemoveAttribute { node, name } => doc.remove_attribute(resolve(created, node), &name),
+            DomMutation::SetText { node, text } => doc.set_text_value(resolve(created, node), &text),
+        }
+    }
+}
+
+/// The native half of [`DOM_SHIM`]. Nodes are passed as their ids, with [`NO_NODE`] for none.
+#[web_interop(js_name = __gosubDom)]
+pub(super) struct DomBinding {
+    tree: DomTree,
+    /// The form a script submitted, with its submitter, until the tab is told
+    submission: Option<(usize, Option<usize>)>,
+}
+
+impl DomBinding {
+    pub(super) fn new(tree: DomTree) -> Self {
+        Self { tree, submission: None }
+    }
+
+    pub(super) fn take_mutations(&mut self) -> Vec<DomMutation> {
+        self.tree.take_mutations()
+    }
+
+    /// Whether node `id` is in the tree.
+    pub(super) fn has_node(&self, id: usize) -> bool {
+        self.tree.node(id).is_some()
+    }
+
+    /// The form submitted since the last call, with its submitter. When a task submits more than
+    /// once, the last one wins.
+    pub(super) fn take_submission(&mut self) -> Option<(usize, Option<usize>)> {
+        self.submission.take()
+    }
+}
+
+fn node_id(id: i64) -> usize {
+    usize::try_from(id).unwrap_or(usize::MAX)
+}
+
+fn js_id(id: Option<usize>) -> i64 {
+    id.and_then(|id| i64::try_from(id).ok()).unwrap_or(NO_NODE)
+}
+
+#[web_fns(1)]
+impl DomBinding {
+    fn root(&self) -> i64 {
+        js_id(Some(self.tree.root))
+    }
+
+    /// The `nodeType` of `id`, 0 when there is no such node.
+    fn node_type(&self, id: i64) -> i64 {
+        match self.tree.node(node_id(id)).map(|n| &n.data) {
+            Some(DomData::Element { .. }) => 1,
+            Some(DomData::Text(_)) => 3,
+            Some(DomData::Comment(_)) => 8,
+            Some(DomData::Document) => 9,
+            Some(DomData::Doctype(_)) => 10,
+            None => 0,
+        }
+    }
+
+    fn node_name(&self, id: i64) -> String {
+        match self.tree.node(node_id(id)).map(|n| &n.data) {
+            // HTML elements are named in upper case.
+            Some(DomData::Element { name, .. }) => name.cow_to_ascii_uppercase().into_owned(),
+            Some(DomData::Text(_)) => "#text".into(),
+            Some(DomData::Comment(_)) => "#comment".into(),
+            Some(DomData::Document) => "#document".into(),
+            Some(DomData::Doctype(name)) => name.clone(),
+            None => String::new(),
+        }
+    }
+
+    fn parent(&self, id: i64) -> i64 {
+        js_id(self.tree.node(node_id(id)).and_then(|n| n.parent))
+    }
+
+    fn children(&self, id: i64) -> Vec<i64> {
+        let children = self
+            .tree
+            .node(node_id(id))
+            .map(|n| n.children.as_slice())
+            .unwrap_or_default();
+        children.iter().map(|&child| js_id(Some(child))).collect()
+    }
+
+    fn text(&self, id: i64) -> String {
+        self.tree.text(node_id(id))
+    }
+
+    fn set_text(&mut self, id: i64, text: String) {
+        self.tree.set_text(node_id(id), &text);
+    }
+
+    /// The value of attribute `name` of element `id`, as a list of none or one.
+    fn attribute(&self, id: i64, name: String) -> Vec<String> {
+        match self.tree.node(node_id(id)).map(|n| &n.data) {
+            Some(DomData::Element { attributes, .. }) => attributes.get(&name).cloned().into_iter().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn attribute_names(&self, id: i64) -> Vec<String> {
+        match self.tree.node(node_id(id)).map(|n| &n.data) {
+            Some(DomData::Element { attributes, .. }) => {
+                let mut names: Vec<String> = attributes.keys().cloned().collect();
+                names.sort();
+                names
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn set_attribute(&mut self, id: i64, name: String, value: String) {
+        let node = node_id(id);
+        let Some(attributes) = self.tree.attributes_mut(node) else {
+            return;
+        };
+        attributes.insert(name.clone(), value.clone());
+        self.tree
+            .mutations
+            .push(DomMutation::SetAttribute { node, name, value });
+    }
+
+    fn remove_attribute(&mut self, id: i64, name: String) {
+        let node = node_id(id);
+        if self
+            .tree
+            .attributes_mut(node)
+            .and_then(|attributes| attributes.remove(&name))
+            .is_some()
+        {
+            self.tree.mutations.push(DomMutation::RemoveAttribute { node, name });
+        }
+    }
+
+    /// The first element in the document with `id` as its id.
+    fn element_by_id(&self, id: String) -> i64 {
+        let found = self
+            .tree
+            .descendant_elements(self.tree.root)
+            .into_iter()
+            .find(|&element| {
+                self.tree.node(element).is_some_and(|node| {
+                matches!(&node.data, DomData::Element { attributes, .. } if attributes.get("id") == Some(&id))
+            })
+            });
+        js_id(found)
+    }
+
+    /// The elements below `root` named `name`, or all of them for `*`.
+    fn elements_by_tag_name(&self, root: i64, name: String) -> Vec<i64> {
+        self.tree
+            .descendant_elements(node_id(root))
+            .into_iter()
+            .filter(|&element| {
+                self.tree.node(element).is_some_and(|node| {
+                    matches!(&node.data, DomData::Element { name: tag, .. }
+                        if name == "*" || tag.eq_ignore_ascii_case(&name))
+                })
+            })
+            .map(|element| js_id(Some(element)))
+            .collect()
+    }
+
+    fn create_element(&mut self, name: String) -> i64 {
+        js_id(Some(self.tree.create_element(&name)))
+    }
+
+    fn create_text(&mut self, text: String) -> i64 {
+        js_id(Some(self.tree.create_text(&text)))
+    }
+
+    /// Moves `node` under `parent`, before `before` or last. Hands back the `DOMException` to
+    /// throw as `"<name>: <message>"`, or an empty string.
+    fn insert(&mut self, node: i64, parent: i64, before: i64) -> String {
+        let before = (before != NO_NODE).then(|| node_id(before));
+        match self.tree.insert(node_id(node), node_id(parent), before) {
+            Ok(()) => String::new(),
+            Err(failure) => failure,
+        }
+    }
+
+    fn remove(&mut self, id: i64) {
+        self.tree.remove(node_id(id));
+    }
+
+    /// `form.submit()`, or a `requestSubmit()` nobody cancelled; `submitter` is [`NO_NODE`] for
+    /// none.
+    fn submit(&mut self, form: i64, submitter: i64) {
+        if self.tree.node(node_id(form)).is_none() {
+            return;
+        }
+        let submitter = (submitter != NO_NODE).then(|| node_id(submitter));
+        self.submission = Some((node_id(form), submitter));
+    }
+}
+
+/// Puts the [`DomBinding`] for `tree` on the global object and runs [`DOM_SHIM`]. The returned
+/// binding is where the mutations come from.
+pub(super) fn install_dom<RT: WebRuntime>(ctx: &mut RT::Context, tree: DomTree) -> Result<Rc<RefCell<DomBinding>>> {
+    let binding = Rc::new(RefCell::new(DomBinding::new(tree)));
+    DomBinding::implement::<RT>(Rc::clone(&binding), ctx.clone())?;
+    ctx.run(DOM_SHIM)?;
+    Ok(binding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::testing::parse;
+
+    #[test]
+    fn script_changes_reach_the_document() {
+        let mut doc = parse(r#"<body><p id="intro">Hello <b>there</b></p><ul id="list"></ul></body>"#);
+        let mut dom = DomBinding::new(DomTree::snapshot(&doc));
+
+        let intro = dom.element_by_id("intro".into());
+        assert_eq!(dom.text(intro), "Hello there");
+        assert_eq!(dom.node_name(intro), "P");
+        let list = dom.element_by_id("list".into());
+        let body = dom.parent(list);
+        assert_eq!(dom.elements_by_tag_name(body, "li".into()), Vec::<i64>::new());
+
+        let item = dom.create_element("li".into());
+        dom.set_text(item, "first".into());
+        dom.set_attribute(item, "class".into(), "done".into());
+        assert_eq!(dom.insert(item, list, NO_NODE), "");
+        let second = dom.create_element("li".into());
+        assert_eq!(dom.insert(second, list, item), "");
+        dom.set_text(intro, "Bye".into());
+
+        // A node can't go below itself, and `before` has to be a child.
+        assert!(dom.insert(list, item, NO_NODE).starts_with("HierarchyRequestError"));
+        assert!(dom.insert(second, list, intro).starts_with("NotFoundError"));
+        assert_eq!(dom.children(list), vec![second, item]);
+
+        let mut created = HashMap::new();
+        apply_mutations(&mut doc, dom.take_mutations(), &mut created);
+        assert!(dom.take_mutations().is_empty());
+
+        let list = doc.node_by_named_id("list").unwrap();
+        let items = doc.children(list).to_vec();
+        assert_eq!(items.len(), 2);
+        assert_eq!(doc.tag_name(items[1]), Some("li"));
+        assert_eq!(doc.attribute(items[1], "class"), Some("done"));
+        let text = doc.children(items[1])[0];
+        assert_eq!(doc.text_value(text), Some("first"));
+
+        // The document and the script's copy agree again.
+        let intro = doc.node_by_named_id("intro").unwrap();
+        let fresh = DomBinding::new(DomTree::snapshot(&doc));
+        assert_eq!(fresh.text(js_id(Some(usize::from(intro)))), "Bye");
+        assert_eq!(fresh.children(js_id(Some(usize::from(list)))).len(), 2);
+    }
+
+    #[test]
+    fn focus_moves_fire_blur_before_focus() {
+        let events = DomEvent::focus_change(Some(4), Some(7));
+        let fired: Vec<_> = events.iter().map(|e| (e.event_type, e.target)).collect();
+        assert_eq!(fired, vec![("blur", 4), ("focusout", 4), ("focus", 7), ("focusin", 7)]);
+        assert_eq!(events[1].init, DomEventInit::Focus { related: Some(7) });
+        assert_eq!(events[2].init, DomEventInit::Focus { related: Some(4) });
+
+        let call = events[0].dispatch_call();
+        assert!(call.starts_with(DISPATCH_EVENT), "{call}");
+        assert!(call.contains(r#""bubbles":false"#), "{call}");
+        assert!(events[1].dispatch_call().contains(r#""bubbles":true"#));
+
+        // Focus leaving the page only blurs.
+        let fired: Vec<_> = DomEvent::focus_change(Some(4), None)
+            .iter()
+            .map(|e| (e.event_type, e.init.clone()))
+            .collect();
+        assert_eq!(
+            fired,
+            vec![
+                ("blur", DomEventInit::Focus { related: None }),
+                ("focusout", DomEventInit::Focus { related: None }),
+            ]
+        );
+    }
+
+    #[test]
+    fn key_events_are_cancelable() {
+        let event = DomEvent {
+            target: 3,
+            event_type: "keydown",
+            init: DomEventInit::Key {
+                key: "\"".into(),
+                code: "Quote".into(),
+                modifiers: Modifiers::SHIFT,
+            },
+        };
+        let call = event.dispatch_call();
+        let args: serde_json::Value = serde_json::from_str(
+            call.strip_prefix(DISPATCH_EVENT)
+                .and_then(|c| c.strip_prefix('('))
+                .and_then(|c| c.strip_suffix(')'))
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(args["kind"], "KeyboardEvent");
+        assert_eq!(args["type"], "keydown");
+        assert_eq!(args["init"]["key"], "\"");
+        assert_eq!(args["init"]["cancelable"], true);
+        assert_eq!(args["init"]["shiftKey"], true);
+        assert_eq!(args["init"]["ctrlKey"], false);
+    }
+
+    #[test]
+    fn last_submission_of_a_task_wins() {
+        let doc = parse(r#"<form id="a"></form><form id="b"><button id="go">Go</button></form>"#);
+        let mut dom = DomBinding::new(DomTree::snapshot(&doc));
+        let (a, b, go) = (
+            dom.element_by_id("a".into()),
+            dom.element_by_id("b".into()),
+            dom.element_by_id("go".into()),
+        );
+        assert_eq!(dom.node_name(b), "FORM");
+
+        dom.submit(a, NO_NODE);
+        dom.submit(b, go);
+        assert_eq!(dom.take_submission(), Some((node_id(b), Some(node_id(go)))));
+        assert_eq!(dom.take_submission(), None);
+        // Gone from the tree
+        dom.submit(9999, NO_NODE);
+        assert_eq!(dom.take_submission(), None);
+    }
+}
@@ -0,0 +1,185 @@
+//! `Event`, `CustomEvent` and `EventTarget`, which the other shims build their objects on.
+//!
+//! Listener lists follow the DOM rules: one entry per listener and `capture` flag, `once`
+//! listeners go before they run, `passive` ones can't cancel, a `signal` removes its listener,
+//! and a listener removed during dispatch doesn't run. An object with a parent (looked up under
+//! `Symbol.for("gosub.eventParent")`) gets the capture and bubble phases along that chain, the way
+//! IndexedDB requests bubble to their transaction and database.
+//!
+//! Unlike in browsers, an exception in a listener doesn't stop at the console: the other listeners
+//! run, then `dispatchEvent()` rethrows it, so whoever delivered the event can report it.
+
+use gosub_webexecutor::js::{WebContext, WebRuntime};
+
+const EVENTS_SHIM: &str = r#"(() => {
+    const PARENT = Symbol.for("gosub.eventParent");
+    const [NONE, CAPTURING_PHASE, AT_TARGET, BUBBLING_PHASE] = [0, 1, 2, 3];
+    const domError = (name, message) => {
+        const error = new Error(message);
+        error.name = name;
+        return error;
+    };
+
+    // Dispatch state of each event.
+    const flags = new WeakMap();
+
+    class Event {
+        constructor(type, init = {}) {
+            if (arguments.length === 0) throw new TypeError("Event: a type is required");
+            this.type = String(type);
+            this.bubbles = !!init?.bubbles;
+            this.cancelable = !!init?.cancelable;
+            this.composed = !!init?.composed;
+            this.target = null;
+            this.currentTarget = null;
+            this.eventPhase = NONE;
+            this.defaultPrevented = false;
+            this.isTrusted = false;
+            this.timeStamp = Date.now();
+            flags.set(this, { dispatching: false, stop: false, stopImmediate: false, passive: false, path: [] });
+        }
+        preventDefault() {
+            if (this.cancelable && !flags.get(this)?.passive) this.defaultPrevented = true;
+        }
+        get returnValue() {
+            return !this.defaultPrevented;
+        }
+        set returnValue(value) {
+            if (!value) this.preventDefault();
+        }
+        stopPropagation() {
+            flags.get(this).stop = true;
+        }
+        get cancelBubble() {
+            return flags.get(this)?.stop ?? false;
+        }
+        set cancelBubble(value) {
+            if (value) this.stopPropagation();
+        }
+        stopImmediatePropagation() {
+            Object.assign(flags.get(this), { stop: true, stopImmediate: true });
+        }
+        composedPath() {
+            return [...(flags.get(this)?.path ?? [])];
+        }
+    }
+    Object.assign(Event, { NONE, CAPTURING_PHASE, AT_TARGET, BUBBLING_PHASE });
+
+    class CustomEvent extends Event {
+        constructor(type, init = {}) {
+            super(type, init);
+            this.detail = init?.detail ?? null;
+        }
+    }
+
+    // Listener lists by target, and in them by type. Kept outside the targets so any object,
+    // the global one included, can be a target.
+    const listenersOf = new WeakMap();
+    const flatten = (options) =>
+        typeof options === "object" && options !== null
+            ? { capture: !!options.capture, once: !!options.once, passive: !!options.passive, signal: options.signal }
+            : { capture: !!options, once: false, passive: false, signal: undefined };
+    const removeEntry = (target, type, entry) => {
+        entry.removed = true;
+        const map = listenersOf.get(target);
+        const list = map?.get(type);
+        if (list) map.set(type, list.filter((other) => other !== entry));
+    };
+
+    // Runs the `capture` (or the other) listeners of `target`, plus its `on<type>` handler with
+    // the non-capture ones. The first error is kept for `dispatch` to rethrow.
+    const invoke = (target, event, capture, state) => {
+        event.currentTarget = target;
+        const call = (listener, passive) => {
+            state.passive = passive;
+            try {
+                if (typeof listener === "function") listener.call(target, event);
+                else listener?.handleEvent?.(event);
+            } catch (e) {
+                if (!state.failed) Object.assign(state, { failed: true, error: e });
+            }
+            state.passive = false;
+        };
+        const handler = capture ? null : target["on" + event.type];
+        if (typeof handler === "function") call(handler, false);
+        for (const entry of [...(listenersOf.get(target)?.get(event.type) ?? [])]) {
+            if (state.stopImmediate) break;
+            if (entry.removed || entry.capture !== capture) continue;
+            if (entry.once) removeEntry(target, event.type, entry);
+            call(entry.listener, entry.passive);
+        }
+    };
+
+    const dispatch = (target, event) => {
+        const state = flags.get(event);
+        if (state.dispatching) throw domError("InvalidStateError", "The event is already being dispatched.");
+        const path = [target];
+        for (let node = target[PARENT]; node && !path.includes(node); node = node[PARENT]) path.push(node);
+        Object.assign(state, { dispatching: true, stop: false, stopImmediate: false, failed: false, path });
+        event.target = target;
+        try {
+            event.eventPhase = CAPTURING_PHASE;
+            for (let i = path.length - 1; i > 0 && !state.stop; i--) invoke(path[i], event, true, state);
+            if (!state.stop) {
+                event.eventPhase = AT_TARGET;
+                invoke(target, event, true, state);
+                if (!state.stopImmediate) invoke(target, event, false, state);
+            }
+            event.eventPhase = BUBBLING_PHASE;
+            for (let i = 1; i < path.length && event.bubbles && !state.stop; i++) invoke(path[i], event, false, state);
+        } finally {
+            event.eventPhase = NONE;
+            event.currentTarget = null;
+            Object.assign(state, { dispatching: false, path: [] });
+        }
+        if (state.failed) throw state.error;
+        return !event.defaultPrevented;
+    };
+
+    class EventTarget {
+        addEventListener(type, listener, options) {
+            if (listener == null) return;
+            const { capture, once, passive, signal } = flatten(options);
+            if (signal?.aborted) return;
+            type = String(type);
+            let map = listenersOf.get(this);
+            if (!map) listenersOf.set(this, (map = new Map()));
+            const list = map.get(type) ?? [];
+            if (list.some((entry) => entry.listener === listener && entry.capture === capture)) return;
+            const entry = { listener, capture, once, passive, removed: false };
+            map.set(type, [...list, entry]);
+            signal?.addEventListener("abort", () => removeEntry(this, type, entry), { once: true });
+        }
+        removeEventListener(type, listener, options) {
+            const { capture } = flatten(options);
+            type = String(type);
+            const entry = listenersOf
+                .get(this)
+                ?.get(type)
+                ?.find((entry) => entry.listener === listener && entry.capture === capture);
+            if (entry) removeEntry(this, type, entry);
+        }
+        dispatchEvent(event) {
+            if (!(event instanceof Event)) throw new TypeError("dispatchEvent: the argument is not an Event");
+            return dispatch(this, event);
+        }
+    }
+
+    globalThis.Event = Event;
+    globalThis.CustomEvent = CustomEvent;
+    globalThis.EventTarget = EventTarget;
+    for (const name of ["addEventListener", "removeEventListener", "dispatchEvent"]) {
+        const method = EventTarget.prototype[name];
+        Object.defineProperty(globalThis, name, {
+            value: (...args) => method.apply(globalThis, args),
+            writable: true,
+            configurable: true,
+        });
+    }
+})()"#;
+
+/// Runs [`EVENTS_SHIM`]. Goes before every shim that defines event targets.
+pub(super) fn install_events<RT: WebRuntime>(ctx: &mut RT::Context) -> anyhow::Result<()> {
+    ctx.run(EVENTS_SHIM)?;
+    Ok(())
+}
@@ -0,0 +1,421 @@
+//! The script side of `fetch()`.
+//!
+//! `Headers`, `Request` and `Response` are plain JS classes in [`FETCH_SHIM`]; a call to `fetch()`
+//! hands the request to the tab worker as JSON through [`PageChannel`], which fetches it (see
+//! `tab::script_fetch`) and posts the outcome back to the script thread. Bodies cross as "binary
+//! strings", one char per byte, since that is all the executor's value conversions can carry.
+
+use super::{native_function, PageChannel};
+use crate::net::cors::{CredentialsMode, RequestMode};
+use gosub_webexecutor::js::{WebContext, WebObject, WebRuntime, WebValue};
+use serde::{Deserialize, Serialize};
+
+/// Global the native fetch functions are handed to the shim on.
+const FETCH_GLOBAL: &str = "__gosubFetch";
+/// Function the shim leaves behind for settling a `fetch()` promise.
+const FETCH_DONE: &str = "__gosubFetchDone";
+/// Globals a response's metadata (as JSON) and body are parked on while it is handed over.
+const RESPONSE_GLOBAL: &str = "__gosubFetchResponse";
+const BODY_GLOBAL: &str = "__gosubFetchBody";
+
+const FETCH_SHIM: &str = r#"(() => {
+    const native = globalThis.__gosubFetch;
+    delete globalThis.__gosubFetch;
+    const pending = new Map();
+    const bodies = new WeakMap();
+
+    const utf8Encode = (s) => unescape(encodeURIComponent(s));
+    const utf8Decode = (b) => {
+        try {
+            return decodeURIComponent(escape(b));
+        } catch (e) {
+            return b;
+        }
+    };
+    const toBinary = (bytes) => {
+        let s = "";
+        for (let i = 0; i < bytes.length; i += 0x8000) {
+            s += String.fromCharCode.apply(null, bytes.subarray(i, i + 0x8000));
+        }
+        return s;
+    };
+    const fromBinary = (s) => {
+        const bytes = new Uint8Array(s.length);
+        for (let i = 0; i < s.length; i++) bytes[i] = s.charCodeAt(i) & 0xff;
+        return bytes;
+    };
+    const bodyToBinary = (body) => {
+        if (body == null) return null;
+        if (body instanceof ArrayBuffer) return toBinary(new Uint8Array(body));
+        if (ArrayBuffer.isView(body)) return toBinary(new Uint8Array(body.buffer, body.byteOffset, body.byteLength));
+        return utf8Encode(String(body));
+    };
+    const abortError = (signal) => {
+        if (signal && signal.reason !== undefined) return signal.reason;
+        const error = new Error("The operation was aborted.");
+        error.name = "AbortError";
+        return error;
+    };
+
+    const normalizeName = (name) => {
+        name = String(name);
+        if (!/^[!#$%&'*+\-.^_`|~0-9A-Za-z]+$/.test(name)) throw new TypeError(`Invalid header name: ${name}`);
+        return name.toLowerCase();
+    };
+
+    class Headers {
+        #list = [];
+        constructor(init) {
+            if (init == null) return;
+            if (typeof init[Symbol.iterator] === "function") {
+                for (const [name, value] of init) this.append(name, value);
+            } else {
+                for (const name of Object.keys(init)) this.append(name, init[name]);
+            }
+        }
+        append(name, value) {
+            this.#list.push([normalizeName(name), String(value).trim()]);
+        }
+        delete(name) {
+            name = normalizeName(name);
+            this.#list = this.#list.filter(([n]) => n !== name);
+        }
+        get(name) {
+            name = normalizeName(name);
+            const values = this.#list.filter(([n]) => n === name).map(([, v]) => v);
+            return values.length ? values.join(", ") : null;
+        }
+        getSetCookie() {
+            return this.#list.filter(([n]) => n === "set-cookie").map(([, v]) => v);
+        }
+        has(name) {
+            name = normalizeName(name);
+            return this.#list.some(([n]) => n === name);
+        }
+        set(name, value) {
+            this.delete(name);
+            this.append(name, value);
+        }
+        *entries() {
+            for (const name of [...new Set(this.#list.map(([n]) => n))].sort()) yield [name, this.get(name)];
+        }
+        *keys() {
+            for (const [name] of this.entries()) yield name;
+        }
+        *values() {
+            for (const [, value] of this.entries()) yield value;
+        }
+        forEach(callback, thisArg) {
+            for (const [name, value] of this.entries()) callback.call(thisArg, value, name, this);
+        }
+        [Symbol.iterator]() {
+            return this.entries();
+        }
+    }
+
+    const consume = (holder) => {
+        const body = bodies.get(holder);
+        if (body.used) return Promise.reject(new TypeError("Body has already been consumed"));
+        body.used = true;
+        return Promise.resolve(body.binary ?? "");
+    };
+    const cloneBody = (from, to) => {
+        const body = bodies.get(from);
+        if (body.used) throw new TypeError("Body has already been consumed");
+        bodies.set(to, { binary: body.binary, used: false });
+    };
+
+    class Body {
+        get bodyUsed() {
+            return bodies.get(this).used;
+        }
+        text() {
+            return consume(this).then(utf8Decode);
+        }
+        json() {
+            return this.text().then(JSON.parse);
+        }
+        arrayBuffer() {
+            return consume(this).then((b) => fromBinary(b).buffer);
+        }
+        bytes() {
+            return consume(this).then(fromBinary);
+        }
+    }
+
+    const METHODS = ["DELETE", "GET", "HEAD", "OPTIONS", "POST", "PUT"];
+
+    class Request extends Body {
+        constructor(input, init = {}) {
+            super();
+            const from = input instanceof Request ? input : null;
+            let method = String(init.method ?? from?.method ?? "GET");
+            if (METHODS.includes(method.toUpperCase())) method = method.toUpperCase();
+            this.url = from ? from.url : String(input);
+            this.method = method;
+            this.headers = new Headers(init.headers ?? from?.headers);
+            this.mode = init.mode ?? from?.mode ?? "cors";
+            this.credentials = init.credentials ?? from?.credentials ?? "same-origin";
+            this.redirect = init.redirect ?? from?.redirect ?? "follow";
+            this.signal = init.signal ?? from?.signal ?? null;
+            if (init.body != null) {
+                if (method === "GET" || method === "HEAD") throw new TypeError(`${method} requests cannot have a body`);
+                if (typeof init.body === "string" && !this.headers.has("content-type")) {
+                    this.headers.set("content-type", "text/plain;charset=UTF-8");
+                }
+                bodies.set(this, { binary: bodyToBinary(init.body), used: false });
+            } else if (from) {
+                cloneBody(from, this);
+            } else {
+                bodies.set(this, { binary: null, used: false });
+            }
+        }
+        clone() {
+            return new Request(this);
+        }
+    }
+
+    class Response extends Body {
+        constructor(body = null, init = {}) {
+            super();
+            this.status = init.status ?? 200;
+            this.statusText = init.statusText ?? "";
+            this.headers = new Headers(init.headers);
+            this.type = "default";
+            this.url = "";
+            this.redirected = false;
+            if (typeof body === "string" && !this.headers.has("content-type")) {
+                this.headers.set("content-type", "text/plain;charset=UTF-8");
+            }
+            bodies.set(this, { binary: bodyToBinary(body), used: false });
+        }
+        get ok() {
+            return this.status >= 200 && this.status < 300;
+        }
+        clone() {
+            const copy = Object.assign(Object.create(Response.prototype), this, { headers: new Headers(this.headers) });
+            cloneBody(this, copy);
+            return copy;
+        }
+        static error() {
+            return Object.assign(new Response(), { status: 0, type: "error" });
+        }
+        static json(data, init = {}) {
+            const response = new Response(JSON.stringify(data), init);
+            response.headers.set("content-type", "application/json");
+            return response;
+        }
+    }
+
+    globalThis.Headers = Headers;
+    globalThis.Request = Request;
+    globalThis.Response = Response;
+
+    globalThis.fetch = (input, init) =>
+        new Promise((resolve, reject) => {
+            const request = new Request(input, init);
+            const signal = request.signal;
+            if (signal?.aborted) return reject(abortError(signal));
+            const id = native.start(
+                JSON.stringify({
+                    url: request.url,
+                    method: request.method,
+                    headers: [...request.headers],
+                    body: bodies.get(request).binary,
+                    mode: request.mode,
+                    credentials: request.credentials,
+                    redirect: request.redirect,
+                }),
+            );
+            if (id === undefined) return reject(new TypeError("Failed to fetch: invalid request"));
+
+            const onAbort = () => {
+                pending.delete(id);
+                native.abort(id);
+                reject(abortError(signal));
+            };
+            signal?.addEventListener?.("abort", onAbort, { once: true });
+            const cleanup = () => signal?.removeEventListener?.("abort", onAbort);
+            pending.set(id, { resolve, reject, cleanup });
+        });
+
+    Object.defineProperty(globalThis, "__gosubFetchDone", {
+        value: (id, error) => {
+            const meta = globalThis.__gosubFetchResponse;
+            const binary = globalThis.__gosubFetchBody;
+            delete globalThis.__gosubFetchResponse;
+            delete globalThis.__gosubFetchBody;
+
+            const entry = pending.get(id);
+            if (!entry) return;
+            pending.delete(id);
+            entry.cleanup();
+            if (error !== null) return entry.reject(new TypeError(`Failed to fetch: ${error}`));
+
+            const { headers, ...fields } = JSON.parse(meta);
+            const response = Object.assign(new Response(), fields, { headers: new Headers(headers) });
+            bodies.set(response, { binary, used: false });
+            entry.resolve(response);
+        },
+    });
+})()"#;
+
+/// What `fetch()` does with redirects.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RedirectMode {
+    #[default]
+    Follow,
+    /// A redirected response is a network error
+    Error,
+    /// Not supported: the fetcher always follows, so this behaves like `Follow`
+    Manual,
+}
+
+/// A `fetch()` call, as the shim hands it over.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ScriptFetchRequest {
+    /// As passed to `fetch()`; resolved against the document URL by the worker
+    pub url: String,
+    pub method: String,
+    pub headers: Vec<(String, String)>,
+    /// Binary string, see the module docs
+    pub body: Option<String>,
+    #[serde(default)]
+    pub mode: RequestMode,
+    #[serde(default)]
+    pub credentials: CredentialsMode,
+    #[serde(default)]
+    pub redirect: RedirectMode,
+}
+
+impl ScriptFetchRequest {
+    /// The request body as bytes.
+    pub fn body_bytes(&self) -> Option<Vec<u8>> {
+        // Every char of a binary string is a byte; anything wider is cut to its low byte like
+        // the shim's own conversions do.
+        self.body
+            .as_ref()
+            .map(|body| body.chars().map(|c| c as u32 as u8).collect())
+    }
+}
+
+/// `Response.type`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseType {
+    /// Same-origin
+    Basic,
+    /// Cross-origin, cleared by CORS
+    Cors,
+    /// Cross-origin in `no-cors` mode: no status, headers or body for the page
+    Opaque,
+}
+
+/// A response as the page gets to see it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptFetchResponse {
+    pub url: String,
+    pub status: u16,
+    pub status_text: String,
+    pub headers: Vec<(String, String)>,
+    pub redirected: bool,
+    #[serde(rename = "type")]
+    pub kind: ResponseType,
+    #[serde(skip)]
+    pub body: Vec<u8>,
+}
+
+impl ScriptFetchResponse {
+    /// The response of a cross-origin `no-cors` request.
+    pub fn opaque() -> Self {
+        Self {
+            url: String::new(),
+            status: 0,
+            status_text: String::new(),
+            headers: Vec::new(),
+            redirected: false,
+            kind: ResponseType::Opaque,
+            body: Vec::new(),
+        }
+    }
+}
+
+/// A response, or the message of the `TypeError` the promise rejects with.
+pub type FetchOutcome = Result<ScriptFetchResponse, String>;
+
+/// Puts the native `start(json)` and `abort(id)` on the global object and runs [`FETCH_SHIM`].
+pub(super) fn install_fetch<RT: WebRuntime>(ctx: &mut RT::Context, page: &PageChannel) -> anyhow::Result<()> {
+    let native = RT::Object::new(ctx)?;
+
+    let channel = page.clone();
+    let start = native_function::<RT>(ctx, move |args| {
+        let json = args.first().and_then(|v| v.as_string().ok())?;
+        let request = match serde_json::from_str::<ScriptFetchRequest>(&json) {
+            Ok(request) => request,
+            Err(e) => {
+                log::warn!("Rejecting malformed fetch() request: {e}");
+                return None;
+            }
+        };
+        Some(channel.fetch(request))
+    })?;
+    native.set_method("start", &start)?;
+
+    let channel = page.clone();
+    let abort = native_function::<RT>(ctx, move |args| {
+        if let Some(id) = args.first().and_then(|v| v.as_number().ok()) {
+            channel.abort_fetch(id as u32);
+        }
+        None
+    })?;
+    native.set_method("abort", &abort)?;
+
+    ctx.set_on_global_object(FETCH_GLOBAL, native.into())?;
+    ctx.run(FETCH_SHIM)?;
+    Ok(())
+}
+
+/// Settles the promise of fetch `id`.
+pub(super) fn deliver<RT: WebRuntime>(ctx: &mut RT::Context, id: u32, outcome: FetchOutcome) -> anyhow::Result<()> {
+    let error = match outcome {
+        Ok(response) => {
+            let meta = RT::Value::new_string(ctx.clone(), &serde_json::to_string(&response)?)?;
+            ctx.set_on_global_object(RESPONSE_GLOBAL, meta)?;
+            let body: String = response.body.iter().map(|&b| char::from(b)).collect();
+            let body = RT::Value::new_string(ctx.clone(), &body)?;
+            ctx.set_on_global_object(BODY_GLOBAL, body)?;
+            "null".to_string()
+        }
+        // A JSON string is a valid JS string literal.
+        Err(message) => serde_json::to_string(&message)?,
+    };
+    ctx.run(&format!("{FETCH_DONE}({id}, {error})"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_deserialize_from_the_shim() {
+        let json = r#"{"url":"/api","method":"POST","headers":[["content-type","text/plain"]],
+            "body":"hé","mode":"no-cors","credentials":"include","redirect":"error"}"#;
+        let request: ScriptFetchRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(request.mode, RequestMode::NoCors);
+        assert_eq!(request.credentials, CredentialsMode::Include);
+        assert_eq!(request.redirect, RedirectMode::Error);
+        assert_eq!(
+            request.headers,
+            vec![("content-type".to_string(), "text/plain".to_string())]
+        );
+        assert_eq!(request.body_bytes(), Some(vec![b'h', 0xe9]));
+
+        let json = serde_json::to_value(ScriptFetchResponse::opaque()).unwrap();
+        assert_eq!(json["type"], "opaque");
+        assert_eq!(json["statusText"], "");
+        assert!(json.get("body").is_none());
+    }
+}
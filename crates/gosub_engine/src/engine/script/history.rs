@@ -0,0 +1,93 @@
+//! `window.history`.
+//!
+//! `back()`, `forward()` and `go()` ask the tab worker to traverse the tab's session history,
+//! which replaces the document and with it this context. `scrollRestoration` is kept on the
+//! current history entry by the tab worker: when it is `"manual"`, returning to the entry leaves
+//! the scroll positions to the page. A new document reads the mode of the entry it committed
+//! into. There are no same-document entries yet, so `pushState()` and `replaceState()` are
+//! missing.
+
+use super::{native_function, PageChannel, ScriptRequest};
+use crate::tab::ScrollRestoration;
+use gosub_webexecutor::js::{WebContext, WebObject, WebRuntime, WebValue};
+use std::sync::atomic::Ordering;
+
+/// Global the native history functions are handed to the shim on.
+const HISTORY_GLOBAL: &str = "__gosubHistory";
+
+const HISTORY_SHIM: &str = r#"(() => {
+    const native = globalThis.__gosubHistory;
+    delete globalThis.__gosubHistory;
+    const INTERNAL = Symbol("internal");
+
+    class History {
+        constructor(token) {
+            if (token !== INTERNAL) throw new TypeError("Illegal constructor");
+        }
+        get scrollRestoration() {
+            return native.manual() === 1 ? "manual" : "auto";
+        }
+        set scrollRestoration(value) {
+            // Values other than the two modes are ignored, as for any enumerated attribute.
+            if (value === "auto" || value === "manual") native.setManual(value === "manual");
+        }
+        back() {
+            native.go(-1);
+        }
+        forward() {
+            native.go(1);
+        }
+        go(delta = 0) {
+            native.go(Math.trunc(Number(delta)) || 0);
+        }
+    }
+    globalThis.History = History;
+
+    const history = new History(INTERNAL);
+    Object.defineProperty(globalThis, "history", {
+        get: () => history,
+        enumerable: true,
+    });
+})()"#;
+
+/// Puts the native `manual()`, `setManual(manual)` and `go(delta)` on the global object and runs
+/// [`HISTORY_SHIM`].
+pub(super) fn install_history<RT: WebRuntime>(ctx: &mut RT::Context, page: &PageChannel) -> anyhow::Result<()> {
+    let native = RT::Object::new(ctx)?;
+
+    let mode = page.scroll_restoration.clone();
+    let manual = native_function::<RT>(ctx, move |_| Some(u32::from(mode.load(Ordering::Acquire))))?;
+    native.set_method("manual", &manual)?;
+
+    let channel = page.clone();
+    let set_manual = native_function::<RT>(ctx, move |args| {
+        let manual = args.first().and_then(|v| v.as_bool().ok()).unwrap_or(false);
+        channel.scroll_restoration.store(manual, Ordering::Release);
+        let mode = if manual {
+            ScrollRestoration::Manual
+        } else {
+            ScrollRestoration::Auto
+        };
+        let _ = channel.requests.send(ScriptRequest::ScrollRestoration {
+            document: channel.document,
+            mode,
+        });
+        None
+    })?;
+    native.set_method("setManual", &set_manual)?;
+
+    let channel = page.clone();
+    let go = native_function::<RT>(ctx, move |args| {
+        let delta = args.first().and_then(|v| v.as_number().ok()).unwrap_or(0.0);
+        let _ = channel.requests.send(ScriptRequest::TraverseHistory {
+            document: channel.document,
+            delta: delta as isize,
+        });
+        None
+    })?;
+    native.set_method("go", &go)?;
+
+    ctx.set_on_global_object(HISTORY_GLOBAL, native.into())?;
+    ctx.run(HISTORY_SHIM)?;
+    Ok(())
+}
@@ -0,0 +1,926 @@
+//! The script side of IndexedDB.
+//!
+//! The IDB interfaces are JS classes in [`INDEXED_DB_SHIM`]. They drive the document's
+//! [`IndexedDb`] through one native function that takes a command as JSON and returns
+//! `{"ok": result}` or `{"error": {name, message}}`. Commands run synchronously on the script
+//! thread; the shim delivers their results as events from a timer task, and runs transactions one
+//! at a time, committing each once it has no requests left.
+//!
+//! Values are stored as structured clones, in the format of
+//! [`structured_clone`](super::structured_clone); values stored as plain JSON by earlier versions
+//! still read back.
+
+use super::native_string_function;
+use crate::engine::storage::indexed_db::{IdbError, IdbKey, IdbTransaction, IndexedDb, KeyPath, KeyRange};
+use gosub_webexecutor::js::{WebContext, WebObject, WebRuntime, WebValue};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Global the native command function is handed to the shim on.
+const INDEXED_DB_GLOBAL: &str = "__gosubIndexedDb";
+
+const INDEXED_DB_SHIM: &str = r#"(() => {
+    const native = globalThis.__gosubIndexedDb;
+    delete globalThis.__gosubIndexedDb;
+    const later = (fn) => setTimeout(fn, 0);
+    const domError = (name, message) => {
+        const error = new Error(message);
+        error.name = name;
+        return error;
+    };
+    const call = (command) => {
+        const reply = JSON.parse(native.call(JSON.stringify(command)));
+        if (reply.error) throw domError(reply.error.name, reply.error.message);
+        return reply.ok;
+    };
+
+    const toKey = (value, seen = new Set()) => {
+        if (typeof value === "number" && !Number.isNaN(value)) return value;
+        if (typeof value === "string") return value;
+        if (value instanceof Date && !Number.isNaN(value.getTime())) return { date: value.getTime() };
+        if (value instanceof ArrayBuffer) return { binary: Array.from(new Uint8Array(value)) };
+        if (ArrayBuffer.isView(value)) {
+            return { binary: Array.from(new Uint8Array(value.buffer, value.byteOffset, value.byteLength)) };
+        }
+        if (Array.isArray(value) && !seen.has(value)) {
+            seen.add(value);
+            return Array.from(value, (item) => toKey(item, seen));
+        }
+        throw domError("DataError", "The parameter is not a valid key.");
+    };
+    const fromKey = (key) => {
+        if (Array.isArray(key)) return key.map(fromKey);
+        if (key !== null && typeof key === "object") {
+            return "date" in key ? new Date(key.date) : new Uint8Array(key.binary).buffer;
+        }
+        return key;
+    };
+    const cmp = (a, b) => call({ op: "cmp", a: toKey(a), b: toKey(b) });
+    const { write, read } = globalThis.__gosubStructuredClone;
+    const encodeValue = (value) => JSON.stringify({ c: write(value) });
+    const decodeValue = (stored) => {
+        const { c, v } = JSON.parse(stored);
+        return c === undefined ? v : read(c);
+    };
+
+    const evaluatePath = (value, path) => {
+        if (Array.isArray(path)) return path.map((p) => evaluatePath(value, p));
+        if (path === "") return value;
+        let current = value;
+        for (const part of path.split(".")) {
+            if (current === null || current === undefined || !(part in Object(current))) return undefined;
+            current = Object(current)[part];
+        }
+        return current;
+    };
+    const injectKey = (value, path, key) => {
+        const parts = path.split(".");
+        const last = parts.pop();
+        let current = value;
+        for (const part of parts) {
+            if (current[part] === undefined) current[part] = {};
+            current = current[part];
+        }
+        current[last] = key;
+    };
+    const names = (list) =>
+        Object.assign([...list].sort(), {
+            contains(name) {
+                return this.includes(name);
+            },
+            item(index) {
+                return this[index] ?? null;
+            },
+        });
+
+    // Errors bubble from a request to its transaction and on to the database, as does `abort` from
+    // a transaction.
+    const PARENT = Symbol.for("gosub.eventParent");
+    const makeEvent = (type, init = {}) =>
+        Object.assign(new Event(type, { bubbles: type === "error" || type === "abort", cancelable: type === "error" }), init);
+
+    class Target extends EventTarget {
+        // Returns whether a listener threw.
+        _dispatch(event) {
+            try {
+                this.dispatchEvent(event);
+                return false;
+            } catch (e) {
+                globalThis.console?.error?.(e);
+                return true;
+            }
+        }
+    }
+
+    class IDBRequest extends Target {
+        constructor(source, transaction) {
+            super();
+            this.source = source;
+            this.transaction = transaction;
+            this.readyState = "pending";
+            this.onsuccess = null;
+            this.onerror = null;
+            this._result = undefined;
+            this._error = null;
+        }
+        get [PARENT]() {
+            return this.transaction;
+        }
+        get result() {
+            if (this.readyState !== "done") throw domError("InvalidStateError", "The request has not finished.");
+            return this._result;
+        }
+        get error() {
+            if (this.readyState !== "done") throw domError("InvalidStateError", "The request has not finished.");
+            return this._error;
+        }
+        _succeed(result, init) {
+            this.readyState = "done";
+            this._result = result;
+            this._error = null;
+            return this._dispatch(makeEvent("success", init));
+        }
+        _fail(error) {
+            this.readyState = "done";
+            this._result = undefined;
+            this._error = error;
+            this._dispatch(makeEvent("error"));
+        }
+    }
+
+    class IDBOpenDBRequest extends IDBRequest {
+        constructor() {
+            super(null, null);
+            this.onupgradeneeded = null;
+            this.onblocked = null;
+        }
+    }
+
+    // Transactions (and opening or deleting a database) run one at a time, in order.
+    const scheduler = { queue: [], running: null };
+    const pump = () => {
+        if (scheduler.running || scheduler.queue.length === 0) return;
+        const job = scheduler.queue.shift();
+        scheduler.running = job;
+        later(() => job._start());
+    };
+    const enqueue = (job) => {
+        scheduler.queue.push(job);
+        pump();
+    };
+    const release = (job) => {
+        if (scheduler.running !== job) return;
+        scheduler.running = null;
+        pump();
+    };
+
+    class IDBTransaction extends Target {
+        constructor(db, storeNames, mode, begin = null) {
+            super();
+            this.db = db;
+            this.mode = mode;
+            this.error = null;
+            this.oncomplete = null;
+            this.onerror = null;
+            this.onabort = null;
+            this._names = storeNames;
+            this._requests = [];
+            this._state = "active";
+            this._id = null;
+            this._begin = begin;
+            this._done = null;
+            enqueue(this);
+        }
+        get [PARENT]() {
+            return this.db;
+        }
+        get objectStoreNames() {
+            return names(this._names);
+        }
+        get durability() {
+            return "default";
+        }
+        objectStore(name) {
+            if (this._state === "finished") throw domError("InvalidStateError", "The transaction has finished.");
+            if (!this._names.includes(name)) throw domError("NotFoundError", `No object store ${name} in this transaction.`);
+            return new IDBObjectStore(this, name);
+        }
+        abort() {
+            if (this._state === "finished") throw domError("InvalidStateError", "The transaction has finished.");
+            this._abort(domError("AbortError", "The transaction was aborted."), true);
+        }
+        commit() {
+            if (this._state !== "active") throw domError("InvalidStateError", "The transaction is not active.");
+            this._state = "committing";
+        }
+        _request(source, op, request = new IDBRequest(source, this)) {
+            if (this._state !== "active") throw domError("TransactionInactiveError", "The transaction is not active.");
+            request.readyState = "pending";
+            this._requests.push({ request, op });
+            return request;
+        }
+        _start() {
+            if (this._state === "finished") return release(this);
+            try {
+                if (this._begin) this._begin(this);
+                else this._id = call({ op: "begin", db: this.db.name }).tx;
+            } catch (e) {
+                return this._abort(e);
+            }
+            this._step();
+        }
+        _step() {
+            if (this._state === "finished") return;
+            const next = this._requests.shift();
+            if (!next) return this._commit();
+            const { request, op } = next;
+            let result;
+            try {
+                result = op(this._id);
+            } catch (error) {
+                request.readyState = "done";
+                request._result = undefined;
+                request._error = error;
+                const event = makeEvent("error");
+                const threw = request._dispatch(event);
+                if (threw || !event.defaultPrevented) return this._abort(error);
+                return later(() => this._step());
+            }
+            if (request._succeed(result)) {
+                return this._abort(domError("AbortError", "A success handler threw."));
+            }
+            later(() => this._step());
+        }
+        _commit() {
+            try {
+                call({ op: "commit", tx: this._id });
+            } catch (e) {
+                return this._abort(e);
+            }
+            this._state = "finished";
+            release(this);
+            this._dispatch(makeEvent("complete"));
+            this._done?.("complete");
+        }
+        _abort(error, explicit = false) {
+            if (this._state === "finished") return;
+            this._state = "finished";
+            if (this._id !== null) {
+                try {
+                    call({ op: "abort", tx: this._id });
+                } catch (e) {
+                    globalThis.console?.error?.(e);
+                }
+            }
+            this.error = explicit ? null : error;
+            for (const { request } of this._requests.splice(0)) {
+                request.readyState = "done";
+                request._result = undefined;
+                request._error = domError("AbortError", "The transaction was aborted.");
+                request._dispatch(makeEvent("error"));
+            }
+            release(this);
+            this._dispatch(makeEvent("abort"));
+            this._done?.("abort");
+        }
+    }
+
+    class IDBDatabase extends Target {
+        constructor(name, info) {
+            super();
+            this.name = name;
+            this.onabort = null;
+            this.onclose = null;
+            this.onerror = null;
+            this.onversionchange = null;
+            this._setInfo(info);
+            this._upgrade = null;
+            this._closed = false;
+        }
+        get objectStoreNames() {
+            return names(this._stores.keys());
+        }
+        _setInfo(info) {
+            this.version = info?.version ?? 0;
+            this._stores = new Map((info?.stores ?? []).map((s) => [s.name, s]));
+        }
+        _upgradeTransaction(what) {
+            const tx = this._upgrade;
+            if (!tx || tx._state !== "active") throw domError("InvalidStateError", `${what} needs an upgrade transaction.`);
+            return tx;
+        }
+        createObjectStore(name, options = {}) {
+            const tx = this._upgradeTransaction("createObjectStore");
+            name = String(name);
+            let keyPath = options?.keyPath ?? null;
+            if (keyPath !== null) keyPath = Array.isArray(keyPath) ? keyPath.map(String) : String(keyPath);
+            const autoIncrement = !!options?.autoIncrement;
+            call({ op: "createObjectStore", tx: tx._id, store: name, keyPath, autoIncrement });
+            this._stores.set(name, { name, keyPath, autoIncrement });
+            tx._names.push(name);
+            return new IDBObjectStore(tx, name);
+        }
+        deleteObjectStore(name) {
+            const tx = this._upgradeTransaction("deleteObjectStore");
+            name = String(name);
+            call({ op: "deleteObjectStore", tx: tx._id, store: name });
+            this._stores.delete(name);
+            tx._names = tx._names.filter((n) => n !== name);
+        }
+        transaction(storeNames, mode = "readonly") {
+            if (this._closed) throw domError("InvalidStateError", "The database connection is closed.");
+            if (this._upgrade) throw domError("InvalidStateError", "An upgrade is running.");
+            const list = [...new Set(typeof storeNames === "string" ? [storeNames] : Array.from(storeNames, String))];
+            if (list.length === 0) throw domError("InvalidAccessError", "No object stores given.");
+            for (const name of list) {
+                if (!this._stores.has(name)) throw domError("NotFoundError", `No object store ${name}.`);
+            }
+            if (mode !== "readonly" && mode !== "readwrite") throw new TypeError(`Invalid transaction mode: ${mode}`);
+            return new IDBTransaction(this, list, mode);
+        }
+        close() {
+            this._closed = true;
+        }
+    }
+
+    const toRange = (query, required) => {
+        if (query instanceof IDBKeyRange) return query._wire();
+        if (query === undefined || query === null) {
+            if (required) throw domError("DataError", "A key or key range is required.");
+            return {};
+        }
+        const key = toKey(query);
+        return { lower: key, upper: key };
+    };
+
+    class IDBObjectStore {
+        constructor(transaction, name) {
+            this.transaction = transaction;
+            this._name = name;
+        }
+        get name() {
+            return this._name;
+        }
+        get keyPath() {
+            return this.transaction.db._stores.get(this._name)?.keyPath ?? null;
+        }
+        get autoIncrement() {
+            return !!this.transaction.db._stores.get(this._name)?.autoIncrement;
+        }
+        get indexNames() {
+            return names([]);
+        }
+        _read(op) {
+            return this.transaction._request(this, op);
+        }
+        _writable() {
+            if (this.transaction.mode === "readonly") throw domError("ReadOnlyError", "The transaction is read-only.");
+        }
+        _write(value, key, overwrite) {
+            this._writable();
+            const { keyPath, autoIncrement } = this;
+            if (keyPath !== null && key !== undefined) {
+                throw domError("DataError", "The object store uses in-line keys; no key may be given.");
+            }
+            let wireKey = null;
+            let inject = false;
+            if (keyPath !== null) {
+                const inline = evaluatePath(value, keyPath);
+                if (inline !== undefined) wireKey = toKey(inline);
+                else if (!autoIncrement) throw domError("DataError", "The value has no key at the key path.");
+                else if (value === null || typeof value !== "object") {
+                    throw domError("DataError", "A generated key cannot be stored in this value.");
+                } else inject = true;
+            } else if (key !== undefined) {
+                wireKey = toKey(key);
+            } else if (!autoIncrement) {
+                throw domError("DataError", "The object store needs a key.");
+            }
+            const stored = encodeValue(value);
+            const store = this._name;
+            return this._read((tx) => {
+                const generated = call({ op: "put", tx, store, key: wireKey, value: stored, noOverwrite: !overwrite });
+                if (inject) {
+                    const copy = decodeValue(stored);
+                    injectKey(copy, keyPath, fromKey(generated));
+                    call({ op: "put", tx, store, key: generated, value: encodeValue(copy), noOverwrite: false });
+                }
+                return fromKey(generated);
+            });
+        }
+        _records(tx, range, count) {
+            return call({ op: "get", tx, store: this._name, range, count: count || null });
+        }
+        put(value, key) {
+            return this._write(value, key, true);
+        }
+        add(value, key) {
+            return this._write(value, key, false);
+        }
+        get(query) {
+            const range = toRange(query, true);
+            return this._read((tx) => {
+                const [record] = this._records(tx, range, 1);
+                return record ? decodeValue(record.value) : undefined;
+            });
+        }
+        getKey(query) {
+            const range = toRange(query, true);
+            return this._read((tx) => {
+                const [record] = this._records(tx, range, 1);
+                return record ? fromKey(record.key) : undefined;
+            });
+        }
+        getAll(query, count) {
+            const range = toRange(query, false);
+            return this._read((tx) => this._records(tx, range, count).map((r) => decodeValue(r.value)));
+        }
+        getAllKeys(query, count) {
+            const range = toRange(query, false);
+            return this._read((tx) => this._records(tx, range, count).map((r) => fromKey(r.key)));
+        }
+        count(query) {
+            const range = toRange(query, false);
+            return this._read((tx) => call({ op: "count", tx, store: this._name, range }));
+        }
+        delete(query) {
+            this._writable();
+            const range = toRange(query, true);
+            return this._read((tx) => {
+                call({ op: "delete", tx, store: this._name, range });
+            });
+        }
+        clear() {
+            this._writable();
+            return this._read((tx) => {
+                call({ op: "clear", tx, store: this._name });
+            });
+        }
+        openCursor(query, direction = "next") {
+            return this._cursor(query, direction, true);
+        }
+        openKeyCursor(query, direction = "next") {
+            return this._cursor(query, direction, false);
+        }
+        _cursor(query, direction, withValue) {
+            if (!["next", "nextunique", "prev", "prevunique"].includes(direction)) {
+                throw new TypeError(`Invalid cursor direction: ${direction}`);
+            }
+            const range = toRange(query, false);
+            const request = this._read((tx) => {
+                // The cursor walks the records as they were when it was opened.
+                const records = this._records(tx, range);
+                if (direction.startsWith("prev")) records.reverse();
+                return records.length ? new IDBCursor(this, request, records, direction, withValue) : null;
+            });
+            return request;
+        }
+        createIndex() {
+            throw domError("NotSupportedError", "Indexes are not supported.");
+        }
+        index(name) {
+            throw domError("NotFoundError", `No index ${name}.`);
+        }
+    }
+
+    class IDBCursor {
+        constructor(source, request, records, direction, withValue) {
+            this.source = source;
+            this.request = request;
+            this.direction = direction;
+            this._records = records;
+            this._position = 0;
+            this._withValue = withValue;
+        }
+        get key() {
+            return fromKey(this._records[this._position].key);
+        }
+        get primaryKey() {
+            return this.key;
+        }
+        get value() {
+            return this._withValue ? decodeValue(this._records[this._position].value) : undefined;
+        }
+        _move(accept) {
+            const records = this._records;
+            this.source.transaction._request(
+                this.source,
+                () => {
+                    do this._position++;
+                    while (this._position < records.length && !accept(records[this._position].key));
+                    return this._position < records.length ? this : null;
+                },
+                this.request,
+            );
+        }
+        advance(count) {
+            if (!(count > 0)) throw new TypeError("advance() needs a positive count.");
+            let left = count;
+            this._move(() => --left === 0);
+        }
+        continue(key) {
+            if (key === undefined) return this._move(() => true);
+            const target = toKey(key);
+            const forward = this.direction.startsWith("next");
+            this._move((k) => (forward ? cmp(k, target) >= 0 : cmp(k, target) <= 0));
+        }
+        update(value) {
+            return this.source.put(value, this.source.keyPath === null ? this.key : undefined);
+        }
+        delete() {
+            return this.source.delete(this.key);
+        }
+    }
+
+    class IDBKeyRange {
+        constructor(lower, upper, lowerOpen, upperOpen) {
+            this.lower = lower;
+            this.upper = upper;
+            this.lowerOpen = lowerOpen;
+            this.upperOpen = upperOpen;
+        }
+        static only(value) {
+            toKey(value);
+            return new IDBKeyRange(value, value, false, false);
+        }
+        static lowerBound(value, open = false) {
+            toKey(value);
+            return new IDBKeyRange(value, undefined, !!open, true);
+        }
+        static upperBound(value, open = false) {
+            toKey(value);
+            return new IDBKeyRange(undefined, value, true, !!open);
+        }
+        static bound(lower, upper, lowerOpen = false, upperOpen = false) {
+            const order = cmp(lower, upper);
+            if (order > 0 || (order === 0 && (lowerOpen || upperOpen))) {
+                throw domError("DataError", "The lower bound is above the upper bound.");
+            }
+            return new IDBKeyRange(lower, upper, !!lowerOpen, !!upperOpen);
+        }
+        includes(key) {
+            if (this.lower !== undefined) {
+                const order = cmp(this.lower, key);
+                if (order > 0 || (order === 0 && this.lowerOpen)) return false;
+            }
+            if (this.upper !== undefined) {
+                const order = cmp(this.upper, key);
+                if (order < 0 || (order === 0 && this.upperOpen)) return false;
+            }
+            return true;
+        }
+        _wire() {
+            return {
+                lower: this.lower === undefined ? null : toKey(this.lower),
+                upper: this.upper === undefined ? null : toKey(this.upper),
+                lowerOpen: this.lowerOpen,
+                upperOpen: this.upperOpen,
+            };
+        }
+    }
+
+    class IDBFactory {
+        open(name, version) {
+            name = String(name);
+            if (version !== undefined) {
+                version = Number(version);
+                if (!Number.isInteger(version) || version < 1) throw new TypeError("The version must be a positive integer.");
+            }
+            const request = new IDBOpenDBRequest();
+            const job = {
+                _start() {
+                    release(job);
+                    let info;
+                    try {
+                        info = call({ op: "open", name });
+                    } catch (e) {
+                        return request._fail(e);
+                    }
+                    const oldVersion = info?.version ?? 0;
+                    const newVersion = version ?? Math.max(oldVersion, 1);
+                    if (newVersion < oldVersion) {
+                        return request._fail(domError("VersionError", `The database is at version ${oldVersion}.`));
+                    }
+                    const db = new IDBDatabase(name, info);
+                    if (newVersion === oldVersion) return request._succeed(db);
+
+                    const upgrade = new IDBTransaction(db, [...db._stores.keys()], "versionchange", (tx) => {
+                        tx._id = call({ op: "begin", db: name }).tx;
+                        call({ op: "setVersion", tx: tx._id, version: newVersion });
+                        db.version = newVersion;
+                        db._upgrade = tx;
+                        request.transaction = tx;
+                        request.readyState = "done";
+                        request._result = db;
+                        const event = makeEvent("upgradeneeded", { oldVersion, newVersion });
+                        if (request._dispatch(event)) throw domError("AbortError", "The upgradeneeded handler threw.");
+                    });
+                    upgrade._done = (outcome) => {
+                        db._upgrade = null;
+                        request.transaction = null;
+                        if (outcome === "complete") return request._succeed(db);
+                        db._setInfo(info);
+                        db.close();
+                        request._fail(domError("AbortError", "The upgrade transaction was aborted."));
+                    };
+                },
+            };
+            enqueue(job);
+            return request;
+        }
+        deleteDatabase(name) {
+            const request = new IDBOpenDBRequest();
+            const job = {
+                _start() {
+                    release(job);
+                    try {
+                        const oldVersion = call({ op: "deleteDatabase", name: String(name) });
+                        request._succeed(undefined, { oldVersion, newVersion: null });
+                    } catch (e) {
+                        request._fail(e);
+                    }
+                },
+            };
+            enqueue(job);
+            return request;
+        }
+        databases() {
+            return new Promise((resolve, reject) =>
+                later(() => {
+                    try {
+                        resolve(call({ op: "databases" }));
+                    } catch (e) {
+                        reject(e);
+                    }
+                }),
+            );
+        }
+        cmp(a, b) {
+            return cmp(a, b);
+        }
+    }
+
+    globalThis.indexedDB = new IDBFactory();
+    Object.assign(globalThis, {
+        IDBFactory,
+        IDBDatabase,
+        IDBTransaction,
+        IDBObjectStore,
+        IDBRequest,
+        IDBOpenDBRequest,
+        IDBCursor,
+        IDBKeyRange,
+    });
+})()"#;
+
+/// One command of the shim.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase", rename_all_fields = "camelCase")]
+enum Command {
+    Databases,
+    Open {
+        name: String,
+    },
+    DeleteDatabase {
+        name: String,
+    },
+    Cmp {
+        a: IdbKey,
+        b: IdbKey,
+    },
+    Begin {
+        db: String,
+    },
+    SetVersion {
+        tx: u32,
+        version: u64,
+    },
+    CreateObjectStore {
+        tx: u32,
+        store: String,
+        key_path: Option<KeyPath>,
+        auto_increment: bool,
+    },
+    DeleteObjectStore {
+        tx: u32,
+        store: String,
+    },
+    Put {
+        tx: u32,
+        store: String,
+        key: Option<IdbKey>,
+        value: String,
+        no_overwrite: bool,
+    },
+    Get {
+        tx: u32,
+        store: String,
+        range: KeyRange,
+        count: Option<usize>,
+    },
+    Count {
+        tx: u32,
+        store: String,
+        range: KeyRange,
+    },
+    Delete {
+        tx: u32,
+        store: String,
+        range: KeyRange,
+    },
+    Clear {
+        tx: u32,
+        store: String,
+    },
+    Commit {
+        tx: u32,
+    },
+    Abort {
+        tx: u32,
+    },
+}
+
+/// The document's IndexedDB and its open transactions.
+struct Binding {
+    indexed_db: Option<IndexedDb>,
+    transactions: HashMap<u32, IdbTransaction>,
+    next_tx: u32,
+}
+
+fn to_json(value: impl Serialize) -> Result<Value, IdbError> {
+    serde_json::to_value(value).map_err(|e| IdbError::Backend(e.to_string()))
+}
+
+impl Binding {
+    fn call(&mut self, command: &str) -> String {
+        let result = serde_json::from_str::<Command>(command)
+            .map_err(|e| IdbError::Data(format!("malformed IndexedDB command: {e}")))
+            .and_then(|command| self.run(command));
+        match result {
+            Ok(value) => json!({ "ok": value }),
+            Err(e) => json!({ "error": { "name": e.name(), "message": e.to_string() } }),
+        }
+        .to_string()
+    }
+
+    fn run(&mut self, command: Command) -> Result<Value, IdbError> {
+        match command {
+            Command::Cmp { a, b } => Ok(json!(a.cmp(&b) as i8)),
+            Command::Databases => to_json(
+                self.indexed_db()?
+                    .databases()?
+                    .iter()
+                    .map(|db| json!({ "name": db.name, "version": db.version }))
+                    .collect::<Vec<_>>(),
+            ),
+            Command::Open { name } => to_json(self.indexed_db()?.database(&name)?),
+            Command::DeleteDatabase { name } => to_json(self.indexed_db()?.delete_database(&name)?),
+            Command::Begin { db } => {
+                let tx = self.indexed_db()?.transaction(&db)?;
+                let id = self.next_tx;
+                self.next_tx = self.next_tx.wrapping_add(1);
+                let reply = json!({ "tx": id, "database": tx.database() });
+                self.transactions.insert(id, tx);
+                Ok(reply)
+            }
+            Command::SetVersion { tx, version } => {
+                self.transaction(tx)?.set_version(version)?;
+                Ok(Value::Null)
+            }
+            Command::CreateObjectStore {
+                tx,
+                store,
+                key_path,
+                auto_increment,
+            } => {
+                self.transaction(tx)?
+                    .create_object_store(&store, key_path, auto_increment)?;
+                Ok(Value::Null)
+            }
+            Command::DeleteObjectStore { tx, store } => {
+                self.transaction(tx)?.delete_object_store(&store)?;
+                Ok(Value::Null)
+            }
+            Command::Put {
+                tx,
+                store,
+                key,
+                value,
+                no_overwrite,
+            } => to_json(self.transaction(tx)?.put(&store, key, value, !no_overwrite)?),
+            Command::Get {
+                tx,
+                store,
+                range,
+                count,
+            } => to_json(self.transaction(tx)?.get(&store, &range, count)?),
+            Command::Count { tx, store, range } => to_json(self.transaction(tx)?.count(&store, &range)?),
+            Command::Delete { tx, store, range } => {
+                self.transaction(tx)?.delete(&store, &range)?;
+                Ok(Value::Null)
+            }
+            Command::Clear { tx, store } => {
+                self.transaction(tx)?.clear(&store)?;
+                Ok(Value::Null)
+            }
+            Command::Commit { tx } => {
+                self.take_transaction(tx)?.commit();
+                Ok(Value::Null)
+            }
+            Command::Abort { tx } => {
+                self.take_transaction(tx)?.abort()?;
+                Ok(Value::Null)
+            }
+        }
+    }
+
+    fn indexed_db(&self) -> Result<&IndexedDb, IdbError> {
+        self.indexed_db
+            .as_ref()
+            .ok_or_else(|| IdbError::Security("IndexedDB is not available to this document".into()))
+    }
+
+    fn transaction(&mut self, id: u32) -> Result<&mut IdbTransaction, IdbError> {
+        self.transactions
+            .get_mut(&id)
+            .ok_or_else(|| IdbError::InvalidState(format!("transaction {id} has finished")))
+    }
+
+    fn take_transaction(&mut self, id: u32) -> Result<IdbTransaction, IdbError> {
+        self.transactions
+            .remove(&id)
+            .ok_or_else(|| IdbError::InvalidState(format!("transaction {id} has finished")))
+    }
+}
+
+/// Puts the native `call(command)` on the global object and runs [`INDEXED_DB_SHIM`]. Without
+/// `indexed_db` (opaque origins) every request fails with a `SecurityError`.
+pub(super) fn install_indexed_db<RT: WebRuntime>(
+    ctx: &mut RT::Context,
+    indexed_db: Option<IndexedDb>,
+) -> anyhow::Result<()> {
+    let native = RT::Object::new(ctx)?;
+    let binding = RefCell::new(Binding {
+        indexed_db,
+        transactions: HashMap::new(),
+        next_tx: 1,
+    });
+    let call = native_string_function::<RT>(ctx, move |args| {
+        let command = args.first().and_then(|v| v.as_string().ok()).unwrap_or_default();
+        binding.borrow_mut().call(&command)
+    })?;
+    native.set_method("call", &call)?;
+
+    ctx.set_on_global_object(INDEXED_DB_GLOBAL, native.into())?;
+    ctx.run(INDEXED_DB_SHIM)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::storage::indexed_db::{IdbScope, InMemoryIdbStore};
+    use crate::engine::storage::PartitionKey;
+    use crate::zone::ZoneId;
+    use std::sync::Arc;
+
+    #[test]
+    fn commands_round_trip_as_json() {
+        let scope = IdbScope {
+            zone: ZoneId::new(),
+            partition: PartitionKey::None,
+            origin: url::Url::parse("https://app.example").unwrap().origin(),
+        };
+        let mut binding = Binding {
+            indexed_db: Some(IndexedDb::new(Arc::new(InMemoryIdbStore::new()), scope)),
+            transactions: HashMap::new(),
+            next_tx: 1,
+        };
+        let mut call = |command: Value| -> Value { serde_json::from_str(&binding.call(&command.to_string())).unwrap() };
+
+        assert_eq!(
+            call(json!({"op": "begin", "db": "app"})),
+            json!({"ok": {"tx": 1, "database": null}})
+        );
+        call(json!({"op": "setVersion", "tx": 1, "version": 1}));
+        call(json!({"op": "createObjectStore", "tx": 1, "store": "s", "keyPath": null, "autoIncrement": true}));
+        assert_eq!(
+            call(json!({"op": "put", "tx": 1, "store": "s", "key": null, "value": "{}", "noOverwrite": true})),
+            json!({"ok": 1.0})
+        );
+        call(json!({"op": "commit", "tx": 1}));
+
+        assert_eq!(
+            call(json!({"op": "put", "tx": 1, "store": "s", "key": 2, "value": "{}", "noOverwrite": true}))["error"]
+                ["name"],
+            "InvalidStateError"
+        );
+        assert_eq!(call(json!({"op": "cmp", "a": "a", "b": 1})), json!({"ok": 1}));
+        assert_eq!(
+            call(json!({"op": "databases"})),
+            json!({"ok": [{"name": "app", "version": 1}]})
+        );
+    }
+}
@@ -0,0 +1,535 @@
+//! Debugging pages over the Chrome DevTools protocol.
+//!
+//! An [`InspectorServer`] listens on a local socket, given to tabs through
+//! [`ScriptHost::with_inspector`](super::ScriptHost::with_inspector). Every tab script thread is
+//! a target: `/json/list` lists them the way Chrome does, so DevTools (`chrome://inspect`), VS
+//! Code and the like find them, and a WebSocket on a target's `webSocketDebuggerUrl` attaches a
+//! debugger to it. One debugger per target at a time.
+//!
+//! Like Chrome's, the server keeps web pages out: requests must name it by `localhost` or an IP
+//! address in their `Host` header, which defeats DNS rebinding, and WebSockets opened with an
+//! `Origin` are only accepted from the origins given to
+//! [`InspectorServer::with_allowed_origins`] (Chrome's `--remote-allow-origins`). Target ids are
+//! random, so they can't be guessed either.
+//!
+//! The server only moves messages. The runtime does the debugging, through
+//! [`WebContext::connect_inspector`](gosub_webexecutor::js::WebContext::connect_inspector); the
+//! debugger's messages are handed to it between jobs, and while a breakpoint holds the page, the
+//! runtime waits for them itself. The debugger stays attached across navigations, getting a new
+//! session with each document's context.
+
+use super::ScriptJob;
+use gosub_webexecutor::js::{Watchdog, WebContext, WebInspectorSession, WebRuntime};
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::rc::Rc;
+use std::sync::{mpsc, Arc, Weak};
+use std::time::Duration;
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::{Message, WebSocket};
+use url::Url;
+
+/// Where the debugger's WebSockets live; followed by the target id.
+const PAGE_PATH: &str = "/devtools/page/";
+
+/// How long a connection gets to send its request head.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often an attached debugger's connection looks for messages from the page.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Longest request head we read.
+const MAX_HEAD: usize = 8 * 1024;
+
+/// The local socket debuggers attach to pages through. Cheap to clone; the server stops taking
+/// connections once the last clone is gone.
+#[derive(Clone)]
+pub struct InspectorServer {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    addr: SocketAddr,
+    targets: parking_lot::Mutex<HashMap<String, Target>>,
+    /// Origins whose pages may open a debugger WebSocket; `*` allows any
+    allowed_origins: parking_lot::RwLock<Vec<String>>,
+}
+
+struct Target {
+    url: Option<Url>,
+    jobs: mpsc::Sender<ScriptJob>,
+    attached: bool,
+}
+
+impl InspectorServer {
+    /// Listens on `addr`, which should be a loopback address: whoever connects gets to run code
+    /// in the pages. Port 0 picks a free one, see [`Self::local_addr`].
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let inner = Arc::new(Inner {
+            addr: listener.local_addr()?,
+            targets: Default::default(),
+            allowed_origins: Default::default(),
+        });
+        let server = Arc::downgrade(&inner);
+        std::thread::Builder::new()
+            .name("inspector".into())
+            .spawn(move || accept_loop(&listener, &server))?;
+        log::info!("Inspector listening on http://{}/json", inner.addr);
+        Ok(Self { inner })
+    }
+
+    /// Lets pages at `origins` (e.g. `"http://localhost:9000"`, or `"*"` for any) attach
+    /// debuggers. WebSockets without an `Origin` header, which only non-browser clients can open,
+    /// are always accepted.
+    pub fn with_allowed_origins<S: Into<String>>(self, origins: impl IntoIterator<Item = S>) -> Self {
+        self.inner
+            .allowed_origins
+            .write()
+            .extend(origins.into_iter().map(Into::into));
+        self
+    }
+
+    /// The address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.inner.addr
+    }
+
+    /// Lists the script thread with the job queue `jobs` as a target under a fresh random id,
+    /// until the returned handle is dropped.
+    pub(super) fn register(&self, url: Option<Url>, jobs: mpsc::Sender<ScriptJob>) -> InspectorTarget {
+        let id = format!("{:X}", uuid::Uuid::new_v4().simple());
+        self.inner.targets.lock().insert(
+            id.clone(),
+            Target {
+                url,
+                jobs,
+                attached: false,
+            },
+        );
+        InspectorTarget {
+            server: Arc::clone(&self.inner),
+            id,
+        }
+    }
+}
+
+/// A script thread's listing on an [`InspectorServer`].
+pub(super) struct InspectorTarget {
+    server: Arc<Inner>,
+    id: String,
+}
+
+impl InspectorTarget {
+    /// The target now shows the document at `url`.
+    pub fn set_url(&self, url: Option<Url>) {
+        if let Some(target) = self.server.targets.lock().get_mut(&self.id) {
+            target.url = url;
+        }
+    }
+}
+
+impl Drop for InspectorTarget {
+    fn drop(&mut self) {
+        self.server.targets.lock().remove(&self.id);
+    }
+}
+
+/// A debugger attached to a script thread: what it sends, and where what the page has to say
+/// goes. Lives on the script thread, outlasting the contexts it is attached to.
+#[derive(Clone)]
+pub struct InspectorConnection {
+    incoming: Rc<mpsc::Receiver<String>>,
+    outgoing: mpsc::Sender<String>,
+}
+
+impl InspectorConnection {
+    pub(super) fn new(incoming: mpsc::Receiver<String>, outgoing: mpsc::Sender<String>) -> Self {
+        Self {
+            incoming: Rc::new(incoming),
+            outgoing,
+        }
+    }
+
+    /// Attaches the debugger to `ctx`, which it knows as `name`. Time spent paused doesn't count
+    /// against `watchdog`'s time slice.
+    pub(super) fn attach<RT: WebRuntime>(
+        &self,
+        ctx: &mut RT::Context,
+        name: &str,
+        watchdog: Option<Arc<Watchdog>>,
+    ) -> Option<Box<dyn WebInspectorSession>> {
+        let outgoing = self.outgoing.clone();
+        let incoming = Rc::clone(&self.incoming);
+        ctx.connect_inspector(
+            name,
+            Box::new(move |message| {
+                let _ = outgoing.send(message);
+            }),
+            Box::new(move || {
+                if let Some(watchdog) = &watchdog {
+                    watchdog.stop();
+                }
+                let message = incoming.recv().ok();
+                if let Some(watchdog) = &watchdog {
+                    watchdog.start();
+                }
+                message
+            }),
+        )
+    }
+
+    /// Hands `session` the messages that arrived since the last call.
+    pub(super) fn dispatch(&self, session: &mut dyn WebInspectorSession) {
+        while let Ok(message) = self.incoming.try_recv() {
+            session.dispatch(&message);
+        }
+    }
+}
+
+/// Whether the request names the server by `localhost` or an IP address. Any other host means a
+/// page got a name of its own resolved to us, and is talking to the server as same-origin.
+fn host_allowed(head: &str) -> bool {
+    let Some(host) = header(head, "host") else {
+        return false;
+    };
+    let host = match host.strip_prefix('[') {
+        Some(rest) => rest.split_once(']').map_or(rest, |(ip, _)| ip),
+        None => host.rsplit_once(':').map_or(host, |(name, _)| name),
+    };
+    host.eq_ignore_ascii_case("localhost") || host.parse::<std::net::IpAddr>().is_ok()
+}
+
+/// Whether a page at `origin` may open a debugger WebSocket.
+fn origin_allowed(allowed: &[String], origin: Option<&str>) -> bool {
+    origin.is_none_or(|origin| {
+        allowed.iter().any(|a| {
+            a == "*"
+                || a.trim_end_matches('/')
+                    .eq_ignore_ascii_case(origin.trim_end_matches('/'))
+        })
+    })
+}
+
+/// The value of the header `name` in a request head.
+fn header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.lines().skip(1).find_map(|line| {
+        let (n, value) = line.split_once(':')?;
+        n.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+    })
+}
+
+/// What a connection asked for.
+#[derive(Debug, PartialEq)]
+enum Route {
+    /// `/json` and `/json/list`
+    List,
+    /// `/json/version`
+    Version,
+    /// A WebSocket for the target with this id
+    Attach(String),
+    NotFound,
+}
+
+/// Routes a request from its head.
+fn route(head: &str) -> Route {
+    let mut lines = head.lines();
+    let mut request = lines.next().unwrap_or_default().split_whitespace();
+    if request.next() != Some("GET") {
+        return Route::NotFound;
+    }
+    let path = request.next().unwrap_or_default();
+    let path = path.split('?').next().unwrap_or_default().trim_end_matches('/');
+    let upgrade = lines.any(|line| {
+        line.split_once(':').is_some_and(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("upgrade") && value.trim().eq_ignore_ascii_case("websocket")
+        })
+    });
+    match path {
+        "/json" | "/json/list" => Route::List,
+        "/json/version" => Route::Version,
+        _ => match path.strip_prefix(PAGE_PATH) {
+            Some(id) if upgrade && !id.is_empty() => Route::Attach(id.to_owned()),
+            _ => Route::NotFound,
+        },
+    }
+}
+
+fn target_list(addr: SocketAddr, targets: &HashMap<String, Target>) -> serde_json::Value {
+    let mut list: Vec<_> = targets
+        .iter()
+        .map(|(id, target)| {
+            let url = target.url.as_ref().map_or("about:blank", Url::as_str);
+            let ws = format!("{addr}{PAGE_PATH}{id}");
+            serde_json::json!({
+                "id": id,
+                "type": "page",
+                "title": url,
+                "url": url,
+                "description": "",
+                "webSocketDebuggerUrl": format!("ws://{ws}"),
+                "devtoolsFrontendUrl": format!("devtools://devtools/bundled/inspector.html?ws={ws}"),
+            })
+        })
+        .collect();
+    list.sort_by(|a, b| a["id"].as_str().cmp(&b["id"].as_str()));
+    serde_json::Value::Array(list)
+}
+
+fn accept_loop(listener: &TcpListener, server: &Weak<Inner>) {
+    for stream in listener.incoming() {
+        let Some(server) = server.upgrade() else {
+            break;
+        };
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::debug!("Inspector: failed to accept a connection: {e}");
+                continue;
+            }
+        };
+        let spawned = std::thread::Builder::new()
+            .name("inspector-connection".into())
+            .spawn(move || {
+                if let Err(e) = serve(&server, stream) {
+                    log::debug!("Inspector connection failed: {e}");
+                }
+            });
+        if let Err(e) = spawned {
+            log::warn!("Inspector: failed to start a connection thread: {e}");
+        }
+    }
+}
+
+fn serve(server: &Inner, mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    // The head is only peeked at: a WebSocket handshake reads it again.
+    let head = peek_head(&stream)?;
+    if !host_allowed(&head) {
+        log::warn!("Inspector: rejected a request for another host");
+        return respond(&mut stream, &head, "403 Forbidden", "[]");
+    }
+    match route(&head) {
+        Route::List => {
+            let list = target_list(server.addr, &server.targets.lock());
+            respond(&mut stream, &head, "200 OK", &list.to_string())
+        }
+        Route::Version => {
+            let version = serde_json::json!({
+                "Browser": concat!("Gosub/", env!("CARGO_PKG_VERSION")),
+                "Protocol-Version": "1.3",
+            });
+            respond(&mut stream, &head, "200 OK", &version.to_string())
+        }
+        Route::NotFound => respond(&mut stream, &head, "404 Not Found", "[]"),
+        Route::Attach(id) => attach(server, &id, &head, stream),
+    }
+}
+
+fn peek_head(stream: &TcpStream) -> io::Result<String> {
+    let mut buf = vec![0; MAX_HEAD];
+    let mut seen = 0;
+    loop {
+        let n = stream.peek(&mut buf)?;
+        let head = &buf[..n];
+        if let Some(end) = head.windows(4).position(|w| w == b"\r\n\r\n") {
+            return Ok(String::from_utf8_lossy(&head[..end]).into_owned());
+        }
+        if n == 0 || n == MAX_HEAD {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "no request head"));
+        }
+        if n == seen {
+            // Peeking doesn't wait for more than is already there.
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        seen = n;
+    }
+}
+
+fn respond(stream: &mut TcpStream, head: &str, status: &str, body: &str) -> io::Result<()> {
+    // Consume the request we only peeked at.
+    let mut request = vec![0; head.len() + 4];
+    stream.read_exact(&mut request)?;
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: application/json; charset=UTF-8\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+/// Attaches the debugger on `stream` to target `id`, and moves messages until either side goes.
+fn attach(server: &Inner, id: &str, head: &str, mut stream: TcpStream) -> io::Result<()> {
+    let (to_page, incoming) = mpsc::channel();
+    let (outgoing, from_page) = mpsc::channel();
+    let jobs = {
+        let mut targets = server.targets.lock();
+        let target = match targets.get_mut(id) {
+            Some(target) if !target.attached => target,
+            Some(_) => return respond(&mut stream, head, "409 Conflict", "[]"),
+            None => return respond(&mut stream, head, "404 Not Found", "[]"),
+        };
+        target.attached = true;
+        target.jobs.clone()
+    };
+    let allowed_origins = server.allowed_origins.read().clone();
+    // The rejection type is tungstenite's, so its size isn't ours to pick.
+    #[allow(clippy::result_large_err)]
+    let check_origin = |request: &Request, response: Response| {
+        let origin = request.headers().get("origin").map(|v| v.to_str().unwrap_or("null"));
+        if origin_allowed(&allowed_origins, origin) {
+            return Ok(response);
+        }
+        log::warn!(
+            "Inspector: rejected a debugger from origin {}",
+            origin.unwrap_or_default()
+        );
+        let mut rejection = ErrorResponse::new(Some("origin not allowed".into()));
+        *rejection.status_mut() = tungstenite::http::StatusCode::FORBIDDEN;
+        Err(rejection)
+    };
+    let result = match tungstenite::accept_hdr(stream, check_origin) {
+        Ok(ws) => {
+            log::info!("Inspector: debugger attached to {id}");
+            let connection = ScriptJob::InspectorConnect { incoming, outgoing };
+            match jobs.send(connection) {
+                Ok(()) => pump(ws, &to_page, &from_page, &jobs),
+                Err(_) => Ok(()),
+            }
+        }
+        Err(e) => Err(io::Error::other(e.to_string())),
+    };
+    let _ = jobs.send(ScriptJob::InspectorDisconnect);
+    if let Some(target) = server.targets.lock().get_mut(id) {
+        target.attached = false;
+    }
+    log::info!("Inspector: debugger detached from {id}");
+    result
+}
+
+fn pump(
+    mut ws: WebSocket<TcpStream>,
+    to_page: &mpsc::Sender<String>,
+    from_page: &mpsc::Receiver<String>,
+    jobs: &mpsc::Sender<ScriptJob>,
+) -> io::Result<()> {
+    ws.get_mut().set_read_timeout(Some(POLL_INTERVAL))?;
+    loop {
+        while let Ok(message) = from_page.try_recv() {
+            ws.send(Message::text(message))
+                .map_err(|e| io::Error::other(e.to_string()))?;
+        }
+        match ws.read() {
+            Ok(Message::Text(message)) => {
+                // A paused page takes the message itself; otherwise the job wakes the thread.
+                if to_page.send(message.as_str().to_owned()).is_err() || jobs.send(ScriptJob::InspectorMessage).is_err()
+                {
+                    return Ok(());
+                }
+            }
+            Ok(Message::Close(_)) => return Ok(()),
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {}
+            Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => return Ok(()),
+            Err(e) => return Err(io::Error::other(e.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_are_routed() {
+        assert_eq!(route("GET /json HTTP/1.1\r\nHost: x"), Route::List);
+        assert_eq!(route("GET /json/list/ HTTP/1.1"), Route::List);
+        assert_eq!(route("GET /json/version HTTP/1.1"), Route::Version);
+        assert_eq!(
+            route("GET /devtools/page/tab-1 HTTP/1.1\r\nUpgrade: WebSocket\r\nConnection: Upgrade"),
+            Route::Attach("tab-1".into())
+        );
+        assert_eq!(route("GET /devtools/page/tab-1 HTTP/1.1"), Route::NotFound);
+        assert_eq!(route("POST /json HTTP/1.1"), Route::NotFound);
+    }
+
+    #[test]
+    fn only_local_hosts_are_served() {
+        assert!(host_allowed("GET /json HTTP/1.1\r\nHost: localhost:9222"));
+        assert!(host_allowed("GET /json HTTP/1.1\r\nhost: 127.0.0.1:9222"));
+        assert!(host_allowed("GET /json HTTP/1.1\r\nHost: [::1]:9222"));
+        assert!(host_allowed("GET /json HTTP/1.1\r\nHost: LOCALHOST"));
+        assert!(!host_allowed("GET /json HTTP/1.1\r\nHost: attacker.example:9222"));
+        assert!(!host_allowed("GET /json HTTP/1.1\r\nHost: localhost.attacker.example"));
+        assert!(!host_allowed("GET /json HTTP/1.1"));
+    }
+
+    #[test]
+    fn only_allowed_origins_attach() {
+        let allowed = vec!["http://localhost:9000".to_string()];
+        assert!(origin_allowed(&allowed, None));
+        assert!(origin_allowed(&allowed, Some("http://localhost:9000")));
+        assert!(!origin_allowed(&allowed, Some("https://attacker.example")));
+        assert!(!origin_allowed(&[], Some("http://localhost:9000")));
+        assert!(origin_allowed(&["*".to_string()], Some("https://anything.example")));
+    }
+
+    fn request(server: &InspectorServer, head: &str) -> String {
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream.write_all(head.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn targets_are_listed_while_registered() {
+        let server = InspectorServer::bind("127.0.0.1:0").unwrap();
+        let (jobs, _rx) = mpsc::channel();
+        let target = server.register(None, jobs);
+        target.set_url(Url::parse("https://example.com/").ok());
+
+        let list = |server: &InspectorServer| {
+            let response = request(server, "GET /json/list HTTP/1.1\r\nHost: localhost\r\n\r\n");
+            let body = response.split_once("\r\n\r\n").unwrap().1.to_owned();
+            serde_json::from_str::<serde_json::Value>(&body).unwrap()
+        };
+
+        let targets = list(&server);
+        let id = targets[0]["id"].as_str().unwrap().to_owned();
+        assert_eq!(id, target.id);
+        assert_eq!(id.len(), 32);
+        assert_eq!(targets[0]["url"], "https://example.com/");
+        assert_eq!(
+            targets[0]["webSocketDebuggerUrl"],
+            format!("ws://{}/devtools/page/{id}", server.local_addr())
+        );
+
+        drop(target);
+        assert_eq!(list(&server), serde_json::json!([]));
+
+        let rebound = request(&server, "GET /json/list HTTP/1.1\r\nHost: attacker.example\r\n\r\n");
+        assert!(rebound.starts_with("HTTP/1.1 403"), "{rebound}");
+    }
+
+    #[test]
+    fn debuggers_from_other_origins_are_refused() {
+        let server = InspectorServer::bind("127.0.0.1:0").unwrap();
+        let (jobs, _rx) = mpsc::channel();
+        let target = server.register(None, jobs);
+
+        let response = request(
+            &server,
+            &format!(
+                "GET /devtools/page/{} HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\n\
+                 Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                 Sec-WebSocket-Version: 13\r\nOrigin: https://attacker.example\r\n\r\n",
+                target.id
+            ),
+        );
+        assert!(response.starts_with("HTTP/1.1 403"), "{response}");
+    }
+}
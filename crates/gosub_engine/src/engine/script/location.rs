@@ -0,0 +1,198 @@
+//! `window.location`.
+//!
+//! The getters read the URL the document committed with; a context lives as long as its
+//! document, so they never go stale. Everything that changes the location (`assign()`,
+//! `replace()`, `reload()` and the setters) asks the tab worker for a navigation, which replaces
+//! the document and with it this context. The tab has no same-document navigation yet, so
+//! setting only the `hash` loads the page again too.
+
+use super::{native_function, native_string_function, PageChannel, ScriptRequest};
+use gosub_webexecutor::js::{WebContext, WebObject, WebRuntime, WebValue};
+use serde_json::json;
+use url::Url;
+
+/// Global the native location functions are handed to the shim on.
+const LOCATION_GLOBAL: &str = "__gosubLocation";
+
+const LOCATION_SHIM: &str = r#"(() => {
+    const native = globalThis.__gosubLocation;
+    delete globalThis.__gosubLocation;
+    const parts = JSON.parse(native.parts);
+
+    const navigate = (href, replace, what) => {
+        if (href === "") {
+            const error = new Error(`Invalid URL: ${what}`);
+            error.name = "SyntaxError";
+            throw error;
+        }
+        native.navigate(href, replace);
+    };
+
+    class Location {
+        get href() {
+            return parts.href;
+        }
+        set href(value) {
+            navigate(native.resolve(String(value)), false, value);
+        }
+        get origin() {
+            return parts.origin;
+        }
+        assign(url) {
+            navigate(native.resolve(String(url)), false, url);
+        }
+        replace(url) {
+            navigate(native.resolve(String(url)), true, url);
+        }
+        reload() {
+            native.reload();
+        }
+        toString() {
+            return parts.href;
+        }
+    }
+    for (const name of ["protocol", "host", "hostname", "port", "pathname", "search", "hash"]) {
+        Object.defineProperty(Location.prototype, name, {
+            get() {
+                return parts[name];
+            },
+            set(value) {
+                navigate(native.with(name, String(value)), false, value);
+            },
+            enumerable: true,
+            configurable: true,
+        });
+    }
+    globalThis.Location = Location;
+
+    const location = new Location();
+    Object.defineProperty(globalThis, "location", {
+        get: () => location,
+        set: (value) => {
+            location.href = value;
+        },
+        enumerable: true,
+    });
+})()"#;
+
+/// The components of `url` as `Location` and `WorkerLocation` expose them.
+pub(super) fn url_parts(url: &Url) -> serde_json::Value {
+    json!({
+        "href": url.as_str(),
+        "origin": url::quirks::origin(url),
+        "protocol": url::quirks::protocol(url),
+        "host": url::quirks::host(url),
+        "hostname": url::quirks::hostname(url),
+        "port": url::quirks::port(url),
+        "pathname": url::quirks::pathname(url),
+        "search": url::quirks::search(url),
+        "hash": url::quirks::hash(url),
+    })
+}
+
+/// `url` with the component `name` set to `value` the way the `Location` setters do, or `None`
+/// when `value` doesn't fit.
+fn with_component(url: &Url, name: &str, value: &str) -> Option<Url> {
+    let mut url = url.clone();
+    match name {
+        "protocol" => url::quirks::set_protocol(&mut url, value).ok()?,
+        "host" => url::quirks::set_host(&mut url, value).ok()?,
+        "hostname" => url::quirks::set_hostname(&mut url, value).ok()?,
+        "port" => url::quirks::set_port(&mut url, value).ok()?,
+        "pathname" => url::quirks::set_pathname(&mut url, value),
+        "search" => url::quirks::set_search(&mut url, value),
+        "hash" => url::quirks::set_hash(&mut url, value),
+        _ => return None,
+    }
+    Some(url)
+}
+
+/// Puts the document's URL components, and the native `resolve(url)`, `with(name, value)`,
+/// `navigate(href, replace)` and `reload()`, on the global object and runs [`LOCATION_SHIM`].
+pub(super) fn install_location<RT: WebRuntime>(ctx: &mut RT::Context, page: &PageChannel) -> anyhow::Result<()> {
+    let native = RT::Object::new(ctx)?;
+    let url = match &page.url {
+        Some(url) => url.clone(),
+        None => Url::parse("about:blank")?,
+    };
+
+    let parts = url_parts(&url).to_string();
+    native.set_property("parts", &RT::Value::new_string(ctx.clone(), &parts)?)?;
+
+    let base = url.clone();
+    let resolve = native_string_function::<RT>(ctx, move |args| {
+        let spec = args.first().and_then(|v| v.as_string().ok()).unwrap_or_default();
+        base.join(&spec).map(String::from).unwrap_or_default()
+    })?;
+    native.set_method("resolve", &resolve)?;
+
+    let with = native_string_function::<RT>(ctx, move |args| {
+        let name = args.first().and_then(|v| v.as_string().ok()).unwrap_or_default();
+        let value = args.get(1).and_then(|v| v.as_string().ok()).unwrap_or_default();
+        with_component(&url, &name, &value)
+            .map(String::from)
+            .unwrap_or_default()
+    })?;
+    native.set_method("with", &with)?;
+
+    let channel = page.clone();
+    let navigate = native_function::<RT>(ctx, move |args| {
+        let href = args.first().and_then(|v| v.as_string().ok())?;
+        let url = Url::parse(&href).ok()?;
+        let replace = args.get(1).and_then(|v| v.as_bool().ok()).unwrap_or(false);
+        let _ = channel.requests.send(ScriptRequest::Navigate {
+            document: channel.document,
+            url,
+            replace,
+        });
+        None
+    })?;
+    native.set_method("navigate", &navigate)?;
+
+    let channel = page.clone();
+    let reload = native_function::<RT>(ctx, move |_| {
+        let _ = channel.requests.send(ScriptRequest::Reload {
+            document: channel.document,
+        });
+        None
+    })?;
+    native.set_method("reload", &reload)?;
+
+    ctx.set_on_global_object(LOCATION_GLOBAL, native.into())?;
+    ctx.run(LOCATION_SHIM)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn setters_change_one_component() {
+        let url = Url::parse("https://app.example:8443/a/b?q=1#top").unwrap();
+        let set = |name, value| with_component(&url, name, value).map(String::from);
+
+        assert_eq!(
+            set("hash", "end").as_deref(),
+            Some("https://app.example:8443/a/b?q=1#end")
+        );
+        assert_eq!(
+            set("search", "?x=2").as_deref(),
+            Some("https://app.example:8443/a/b?x=2#top")
+        );
+        assert_eq!(
+            set("pathname", "/c").as_deref(),
+            Some("https://app.example:8443/c?q=1#top")
+        );
+        assert_eq!(
+            set("hostname", "other.example").as_deref(),
+            Some("https://other.example:8443/a/b?q=1#top")
+        );
+        assert_eq!(set("href", "https://x.example/"), None);
+
+        let parts = url_parts(&url);
+        assert_eq!(parts["host"], "app.example:8443");
+        assert_eq!(parts["search"], "?q=1");
+        assert_eq!(parts["origin"], "https://app.example:8443");
+    }
+}
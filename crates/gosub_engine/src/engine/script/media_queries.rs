@@ -0,0 +1,118 @@
+//! `matchMedia()` and `MediaQueryList`.
+//!
+//! Queries are evaluated by the CSS media query evaluator against the tab's
+//! [`MediaEnvironment`](gosub_css3::media::MediaEnvironment): the viewport, the device pixel
+//! ratio and the UA's color scheme. The tab worker hands a new environment to the script thread
+//! whenever one of those changes (see [`ScriptThread::set_media`](super::ScriptThread::set_media));
+//! every `MediaQueryList` whose result flipped then gets a `change` event. Lists nobody holds on
+//! to are not kept alive for this.
+
+use super::{native_function, PageChannel};
+use gosub_webexecutor::js::{WebContext, WebObject, WebRuntime, WebValue};
+
+/// Global the native `matches()` is handed to the shim on.
+const MEDIA_GLOBAL: &str = "__gosubMedia";
+/// Function the shim leaves behind for rechecking the lists after the environment changed.
+const MEDIA_CHANGED: &str = "__gosubMediaChanged";
+
+const MEDIA_SHIM: &str = r#"(() => {
+    const native = globalThis.__gosubMedia;
+    delete globalThis.__gosubMedia;
+    const INTERNAL = Symbol("internal");
+    const lists = new Set();
+    let recheck;
+
+    class MediaQueryListEvent extends Event {
+        constructor(type, init = {}) {
+            super(type, init);
+            this.media = String(init?.media ?? "");
+            this.matches = !!init?.matches;
+        }
+    }
+
+    class MediaQueryList extends EventTarget {
+        #media;
+        #matched;
+        constructor(token, media) {
+            if (token !== INTERNAL) throw new TypeError("Illegal constructor");
+            super();
+            this.#media = media;
+            this.#matched = this.matches;
+            this.onchange = null;
+        }
+        get media() {
+            return this.#media;
+        }
+        get matches() {
+            return native.matches(this.#media) === 1;
+        }
+        // The pre-EventTarget way of listening, still widely used.
+        addListener(listener) {
+            this.addEventListener("change", listener);
+        }
+        removeListener(listener) {
+            this.removeEventListener("change", listener);
+        }
+
+        static {
+            recheck = (list) => {
+                const matches = list.matches;
+                if (matches === list.#matched) return;
+                list.#matched = matches;
+                list.dispatchEvent(new MediaQueryListEvent("change", { media: list.#media, matches }));
+            };
+        }
+    }
+
+    globalThis.MediaQueryList = MediaQueryList;
+    globalThis.MediaQueryListEvent = MediaQueryListEvent;
+    globalThis.matchMedia = function matchMedia(query) {
+        if (arguments.length === 0) throw new TypeError("matchMedia: a query is required");
+        const list = new MediaQueryList(INTERNAL, String(query).trim());
+        lists.add(new WeakRef(list));
+        return list;
+    };
+
+    // Every list gets its event even if a listener throws; the first error is rethrown after.
+    Object.defineProperty(globalThis, "__gosubMediaChanged", {
+        value: () => {
+            let failed = false;
+            let error;
+            for (const ref of lists) {
+                const list = ref.deref();
+                if (!list) {
+                    lists.delete(ref);
+                    continue;
+                }
+                try {
+                    recheck(list);
+                } catch (e) {
+                    if (!failed) [failed, error] = [true, e];
+                }
+            }
+            if (failed) throw error;
+        },
+    });
+})()"#;
+
+/// Puts the native `matches(query)` on the global object and runs [`MEDIA_SHIM`].
+pub(super) fn install_media_queries<RT: WebRuntime>(ctx: &mut RT::Context, page: &PageChannel) -> anyhow::Result<()> {
+    let native = RT::Object::new(ctx)?;
+
+    let media = page.media.clone();
+    let matches = native_function::<RT>(ctx, move |args| {
+        let query = args.first().and_then(|v| v.as_string().ok()).unwrap_or_default();
+        Some(u32::from(media.lock().matches_str(&query)))
+    })?;
+    native.set_method("matches", &matches)?;
+
+    ctx.set_on_global_object(MEDIA_GLOBAL, native.into())?;
+    ctx.run(MEDIA_SHIM)?;
+    Ok(())
+}
+
+/// Fires `change` at the lists whose result differs since they were last checked.
+pub(super) fn media_changed<RT: WebRuntime>(ctx: &mut RT::Context) -> anyhow::Result<()> {
+    ctx.run(&format!("{MEDIA_CHANGED}()"))?;
+    Ok(())
+}
@@ -0,0 +1,482 @@
+//! Messages between contexts: `MessageChannel`/`MessagePort`, `window.postMessage()`, and the
+//! serialization the worker APIs share.
+//!
+//! A message crosses as JSON: the value in the format of [`structured_clone`](super::structured_clone),
+//! with the ids of the ports it transfers. Ports live in the tab's
+//! [`MessagePorts`], shared by the document and its workers, which knows which context owns each
+//! one and posts to it as a [`ScriptJob::Message`]. A transferred port has no owner until the
+//! receiving context claims it; messages sent to it meanwhile wait there.
+//!
+//! Frames don't run scripts, so `window.postMessage()` only reaches the window itself. The target
+//! origin is still checked against the document's.
+
+use super::{native_function, native_string_function, number_arg, PageChannel, ScriptJob};
+use gosub_webexecutor::js::{WebContext, WebObject, WebRuntime, WebValue};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use std::sync::mpsc;
+use url::Url;
+
+/// Global the native port functions are handed to the shim on.
+const MESSAGING_GLOBAL: &str = "__gosubMessaging";
+/// Function the shim leaves behind for delivering a message to a port.
+const PORT_MESSAGE: &str = "__gosubPortMessage";
+/// Global a message is parked on while it is handed over.
+const MESSAGE_GLOBAL: &str = "__gosubMessageData";
+
+/// `MessageEvent`, `ErrorEvent`, `MessageChannel`, `MessagePort` and `postMessage()`, on top of
+/// the events shim. What the worker shim needs is left on `__gosubMessageInternals`.
+const MESSAGING_SHIM: &str = r#"(() => {
+    const native = globalThis.__gosubMessaging;
+    delete globalThis.__gosubMessaging;
+    const later = (fn) => setTimeout(fn, 0);
+    const domError = (name, message) => {
+        const error = new Error(message);
+        error.name = name;
+        return error;
+    };
+
+    class MessageEvent extends Event {
+        constructor(type, init = {}) {
+            super(type, init);
+            this.data = init.data ?? null;
+            this.origin = init.origin ?? "";
+            this.lastEventId = init.lastEventId ?? "";
+            this.source = init.source ?? null;
+            this.ports = Object.freeze([...(init.ports ?? [])]);
+        }
+    }
+    class ErrorEvent extends Event {
+        constructor(type, init = {}) {
+            super(type, init);
+            this.message = init.message ?? "";
+            this.filename = init.filename ?? "";
+            this.lineno = init.lineno ?? 0;
+            this.colno = init.colno ?? 0;
+            this.error = init.error ?? null;
+        }
+    }
+
+    const { write, read, detach, tag } = globalThis.__gosubStructuredClone;
+    const INTERNAL = Symbol("internal");
+    const portIds = new WeakMap();
+    const localPorts = new Map();
+
+    class MessagePort extends EventTarget {
+        #started = false;
+        #queue = [];
+        #onmessage = null;
+        constructor(token, id) {
+            if (token !== INTERNAL) throw new TypeError("Illegal constructor");
+            super();
+            portIds.set(this, id);
+            localPorts.set(id, this);
+            this.onmessageerror = null;
+        }
+        get onmessage() {
+            return this.#onmessage;
+        }
+        set onmessage(handler) {
+            this.#onmessage = handler;
+            this.start();
+        }
+        postMessage(message, transfer = []) {
+            const data = serialize(message, transfer, this);
+            const id = portIds.get(this);
+            if (id !== null) native.post(id, data);
+        }
+        start() {
+            if (this.#started) return;
+            this.#started = true;
+            for (const data of this.#queue.splice(0)) later(() => this._deliver(data));
+        }
+        close() {
+            const id = portIds.get(this);
+            if (id === null) return;
+            portIds.set(this, null);
+            localPorts.delete(id);
+            native.close(id);
+        }
+        _receive(data) {
+            if (this.#started) this._deliver(data);
+            else this.#queue.push(data);
+        }
+        _deliver(data) {
+            const { data: value, ports } = deserialize(data);
+            this.dispatchEvent(new MessageEvent("message", { data: value, ports }));
+        }
+    }
+
+    class MessageChannel {
+        constructor() {
+            // The second port's id follows the first's.
+            const id = native.entangle();
+            this.port1 = new MessagePort(INTERNAL, id);
+            this.port2 = new MessagePort(INTERNAL, id + 1);
+        }
+    }
+
+    // Ports and ArrayBuffers can be transferred (`transfer` is a list or `{ transfer }`), and the
+    // ports in `message` must be. They are detached from this context once the message is built.
+    const serialize = (message, transfer = [], sender = null) => {
+        if (transfer !== null && typeof transfer === "object" && !(Symbol.iterator in transfer)) {
+            transfer = transfer.transfer ?? [];
+        }
+        const ports = [];
+        const buffers = [];
+        for (const item of Array.from(transfer ?? [])) {
+            if (ports.includes(item) || buffers.includes(item)) throw domError("DataCloneError", "An object is transferred twice.");
+            if (tag(item) === "ArrayBuffer") {
+                if (item.detached) throw domError("DataCloneError", "An ArrayBuffer is detached.");
+                buffers.push(item);
+                continue;
+            }
+            if (!(item instanceof MessagePort)) throw domError("DataCloneError", "Only MessagePorts and ArrayBuffers can be transferred.");
+            if (item === sender) throw domError("DataCloneError", "A port cannot transfer itself.");
+            if (portIds.get(item) === null) throw domError("DataCloneError", "The port is closed or transferred.");
+            ports.push(item);
+        }
+        const host = (value) => {
+            if (!(value instanceof MessagePort)) return undefined;
+            const index = ports.indexOf(value);
+            if (index < 0) throw domError("DataCloneError", "A MessagePort in a message must be transferred.");
+            return index;
+        };
+        const data = JSON.stringify({ v: write(message, { host }), p: ports.map((port) => portIds.get(port)) });
+        detach(buffers);
+        for (const port of ports) {
+            const id = portIds.get(port);
+            portIds.set(port, null);
+            localPorts.delete(id);
+            native.detach(id);
+        }
+        return data;
+    };
+    // The transferred ports become this context's.
+    const deserialize = (data) => {
+        const { v, p } = JSON.parse(data);
+        const ports = (p ?? []).map((id) => {
+            native.attach(id);
+            return new MessagePort(INTERNAL, id);
+        });
+        return { data: read(v, { host: (index) => ports[index] }), ports };
+    };
+    const takeMessage = () => {
+        const data = globalThis.__gosubMessageData;
+        delete globalThis.__gosubMessageData;
+        return deserialize(data);
+    };
+
+    Object.assign(globalThis, {
+        MessageEvent,
+        ErrorEvent,
+        MessageChannel,
+        MessagePort,
+        onmessage: null,
+        onmessageerror: null,
+        postMessage(message, targetOrigin, transfer = []) {
+            if (targetOrigin !== null && typeof targetOrigin === "object") {
+                ({ targetOrigin = "/", transfer = [] } = targetOrigin);
+            }
+            targetOrigin = String(targetOrigin);
+            let target = native.origin;
+            if (targetOrigin === "*") target = null;
+            else if (targetOrigin !== "/") {
+                target = native.originOf(targetOrigin);
+                if (target === "") throw domError("SyntaxError", `Invalid target origin: ${targetOrigin}`);
+            }
+            const data = serialize(message, transfer);
+            if (target !== null && target !== native.origin) return;
+            later(() => {
+                const { data: value, ports } = deserialize(data);
+                globalThis.dispatchEvent(
+                    new MessageEvent("message", { data: value, origin: native.origin, source: globalThis, ports }),
+                );
+            });
+        },
+    });
+
+    Object.defineProperty(globalThis, "__gosubPortMessage", {
+        value: (id) => {
+            const data = globalThis.__gosubMessageData;
+            delete globalThis.__gosubMessageData;
+            localPorts.get(id)?._receive(data);
+        },
+    });
+    Object.defineProperty(globalThis, "__gosubMessageInternals", {
+        configurable: true,
+        value: { domError, serialize, takeMessage },
+    });
+})()"#;
+
+/// Who posted a message.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MessageSource {
+    /// The context that started this worker
+    Parent,
+    /// Worker `id` of this context
+    Worker(u32),
+    /// The port entangled with this context's port `id`
+    Port(u32),
+}
+
+/// The context that owns a port.
+#[derive(Debug, Clone)]
+pub(super) struct PortOwner {
+    jobs: mpsc::Sender<ScriptJob>,
+    document: u64,
+}
+
+impl PortOwner {
+    fn send(&self, port: u32, data: String) {
+        let _ = self.jobs.send(ScriptJob::Message {
+            document: self.document,
+            source: MessageSource::Port(port),
+            data,
+        });
+    }
+}
+
+#[derive(Debug)]
+struct Port {
+    peer: u32,
+    /// `None` while the port is being transferred
+    owner: Option<PortOwner>,
+    /// Messages that arrived while there was no owner
+    pending: Vec<String>,
+}
+
+/// The message ports of a tab's contexts, by id.
+#[derive(Debug)]
+pub(super) struct MessagePorts {
+    next_id: u32,
+    ports: HashMap<u32, Port>,
+}
+
+impl Default for MessagePorts {
+    fn default() -> Self {
+        Self {
+            next_id: 1,
+            ports: HashMap::new(),
+        }
+    }
+}
+
+impl MessagePorts {
+    /// Creates two entangled ports owned by `owner`. Returns the first's id; the second's is the
+    /// one after it.
+    fn entangle(&mut self, owner: PortOwner) -> u32 {
+        let mut id = self.next_id;
+        while id == u32::MAX || self.ports.contains_key(&id) || self.ports.contains_key(&(id + 1)) {
+            id = id.wrapping_add(2).max(1);
+        }
+        self.next_id = id.wrapping_add(2).max(1);
+        for (port, peer) in [(id, id + 1), (id + 1, id)] {
+            self.ports.insert(
+                port,
+                Port {
+                    peer,
+                    owner: Some(owner.clone()),
+                    pending: Vec::new(),
+                },
+            );
+        }
+        id
+    }
+
+    /// Posts `data` to the port entangled with `from`.
+    fn post(&mut self, from: u32, data: String) {
+        let Some(peer) = self.ports.get(&from).map(|port| port.peer) else {
+            return;
+        };
+        let Some(port) = self.ports.get_mut(&peer) else {
+            return;
+        };
+        match &port.owner {
+            Some(owner) => owner.send(peer, data),
+            None => port.pending.push(data),
+        }
+    }
+
+    /// Marks `id` as being transferred.
+    fn detach(&mut self, id: u32) {
+        if let Some(port) = self.ports.get_mut(&id) {
+            port.owner = None;
+        }
+    }
+
+    /// Hands `id` to `owner`, along with the messages that waited for it.
+    fn attach(&mut self, id: u32, owner: PortOwner) {
+        if let Some(port) = self.ports.get_mut(&id) {
+            for data in port.pending.drain(..) {
+                owner.send(id, data);
+            }
+            port.owner = Some(owner);
+        }
+    }
+
+    /// Closes `id`, which disentangles its peer.
+    fn close(&mut self, id: u32) {
+        if let Some(port) = self.ports.remove(&id) {
+            self.ports.remove(&port.peer);
+        }
+    }
+}
+
+/// The ports a context owns, closed along with it.
+pub(super) type OwnedPorts = Rc<RefCell<HashSet<u32>>>;
+
+/// Closes the ports of a context that goes away.
+pub(super) fn close_ports(ports: &parking_lot::Mutex<MessagePorts>, owned: &OwnedPorts) {
+    let mut ports = ports.lock();
+    for id in owned.borrow_mut().drain() {
+        ports.close(id);
+    }
+}
+
+/// Puts the native `entangle()`, `post(id, data)`, `detach(id)`, `attach(id)`, `close(id)` and
+/// `originOf(url)`, and the document's `origin`, on the global object and runs [`MESSAGING_SHIM`].
+pub(super) fn install_messaging<RT: WebRuntime>(
+    ctx: &mut RT::Context,
+    page: &PageChannel,
+    owned: &OwnedPorts,
+) -> anyhow::Result<()> {
+    let native = RT::Object::new(ctx)?;
+    let owner = PortOwner {
+        jobs: page.jobs.clone(),
+        document: page.document,
+    };
+
+    let (ports, mine, me) = (page.ports.clone(), Rc::clone(owned), owner.clone());
+    let entangle = native_function::<RT>(ctx, move |_| {
+        let id = ports.lock().entangle(me.clone());
+        mine.borrow_mut().extend([id, id + 1]);
+        Some(id)
+    })?;
+    native.set_method("entangle", &entangle)?;
+
+    let ports = page.ports.clone();
+    let post = native_function::<RT>(ctx, move |args| {
+        let id = number_arg(args, 0)? as u32;
+        let data = args.get(1).and_then(|v| v.as_string().ok())?;
+        ports.lock().post(id, data);
+        None
+    })?;
+    native.set_method("post", &post)?;
+
+    let (ports, mine) = (page.ports.clone(), Rc::clone(owned));
+    let detach = native_function::<RT>(ctx, move |args| {
+        let id = number_arg(args, 0)? as u32;
+        mine.borrow_mut().remove(&id);
+        ports.lock().detach(id);
+        None
+    })?;
+    native.set_method("detach", &detach)?;
+
+    let (ports, mine, me) = (page.ports.clone(), Rc::clone(owned), owner);
+    let attach = native_function::<RT>(ctx, move |args| {
+        let id = number_arg(args, 0)? as u32;
+        mine.borrow_mut().insert(id);
+        ports.lock().attach(id, me.clone());
+        None
+    })?;
+    native.set_method("attach", &attach)?;
+
+    let (ports, mine) = (page.ports.clone(), Rc::clone(owned));
+    let close = native_function::<RT>(ctx, move |args| {
+        let id = number_arg(args, 0)? as u32;
+        mine.borrow_mut().remove(&id);
+        ports.lock().close(id);
+        None
+    })?;
+    native.set_method("close", &close)?;
+
+    let base = page.url.clone();
+    let origin_of = native_string_function::<RT>(ctx, move |args| {
+        let spec = args.first().and_then(|v| v.as_string().ok()).unwrap_or_default();
+        let url = match &base {
+            Some(base) => base.join(&spec),
+            None => Url::parse(&spec),
+        };
+        url.map(|url| url.origin().ascii_serialization()).unwrap_or_default()
+    })?;
+    native.set_method("originOf", &origin_of)?;
+
+    let origin = page
+        .url
+        .as_ref()
+        .map_or_else(|| "null".to_string(), |url| url.origin().ascii_serialization());
+    native.set_property("origin", &RT::Value::new_string(ctx.clone(), &origin)?)?;
+
+    ctx.set_on_global_object(MESSAGING_GLOBAL, native.into())?;
+    ctx.run(MESSAGING_SHIM)?;
+    Ok(())
+}
+
+/// Dispatches a `message` event for `data` at whatever `source` posted to: the global scope for
+/// messages from the parent, the `Worker` object for messages from a worker, and the port for
+/// messages through a port. A handler that throws makes this fail.
+pub(super) fn deliver_message<RT: WebRuntime>(
+    ctx: &mut RT::Context,
+    source: MessageSource,
+    data: String,
+) -> anyhow::Result<()> {
+    let value = RT::Value::new_string(ctx.clone(), &data)?;
+    ctx.set_on_global_object(MESSAGE_GLOBAL, value)?;
+    let call = match source {
+        MessageSource::Parent => format!("{}()", super::workers::PARENT_MESSAGE),
+        MessageSource::Worker(id) => format!("{}({id}, null)", super::workers::WORKER_MESSAGE),
+        MessageSource::Port(id) => format!("{PORT_MESSAGE}({id})"),
+    };
+    ctx.run(&call)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owner() -> (PortOwner, mpsc::Receiver<ScriptJob>) {
+        let (jobs, rx) = mpsc::channel();
+        (PortOwner { jobs, document: 1 }, rx)
+    }
+
+    fn delivered(rx: &mpsc::Receiver<ScriptJob>) -> Vec<(MessageSource, String)> {
+        rx.try_iter()
+            .filter_map(|job| match job {
+                ScriptJob::Message { source, data, .. } => Some((source, data)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn transferred_ports_keep_their_messages() {
+        let mut ports = MessagePorts::default();
+        let (page, page_rx) = owner();
+        let (worker, worker_rx) = owner();
+
+        let a = ports.entangle(page);
+        let b = a + 1;
+        ports.post(a, "to b".into());
+        assert_eq!(delivered(&page_rx), vec![(MessageSource::Port(b), "to b".into())]);
+
+        // b is on its way to the worker; the message waits for it.
+        ports.detach(b);
+        ports.post(a, "in transit".into());
+        assert!(delivered(&page_rx).is_empty());
+        ports.attach(b, worker);
+        assert_eq!(
+            delivered(&worker_rx),
+            vec![(MessageSource::Port(b), "in transit".into())]
+        );
+
+        ports.post(b, "to a".into());
+        assert_eq!(delivered(&page_rx), vec![(MessageSource::Port(a), "to a".into())]);
+
+        ports.close(a);
+        ports.post(b, "dropped".into());
+        assert!(delivered(&page_rx).is_empty());
+    }
+}
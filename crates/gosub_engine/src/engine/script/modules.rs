@@ -0,0 +1,433 @@
+//! `<script type="module">`.
+//!
+//! Module scripts are deferred: once a document has committed, the tab worker collects them in
+//! document order and loads each one's module graph through the tab's fetcher, in CORS mode and
+//! without blocking the script thread. Import specifiers resolve against the importing module's
+//! URL (the document's, for inline modules); bare specifiers need an import map, which isn't
+//! supported, so they fail the graph. A module is fetched once per document however often it is
+//! imported, and cycles end where a module is already in the graph.
+//!
+//! The loaded graphs go to the script thread in document order, where the JS engine links and
+//! evaluates them, see [`WebContext::run_module`](gosub_webexecutor::js::WebContext::run_module).
+//! Dynamic `import()` isn't supported.
+
+use super::{ScriptFetchResponse, ScriptJob};
+use crate::html::{EngineDocument, RenderConfiguration};
+use crate::net::charset::decode_utf8;
+use cow_utils::CowUtils;
+use gosub_interface::document::Document as _;
+use gosub_webexecutor::js::{ModuleGraph, ModuleRecord};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::mpsc;
+use url::Url;
+
+/// One `<script type="module">` of a document.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ModuleScript {
+    /// `src`, resolved against the document URL
+    External(Url),
+    /// The element's text; `index` counts the document's inline modules
+    Inline { base: Url, index: usize, source: String },
+}
+
+impl ModuleScript {
+    /// What the graph knows the script's own module as.
+    pub fn name(&self) -> String {
+        match self {
+            Self::External(url) => url.to_string(),
+            Self::Inline { base, index, .. } => format!("{base}#inline-module-{index}"),
+        }
+    }
+}
+
+/// The module scripts of `doc`, in document order. `base` is the document URL.
+pub(crate) fn module_scripts<C: RenderConfiguration>(doc: &EngineDocument<C>, base: &Url) -> Vec<ModuleScript> {
+    let mut scripts = Vec::new();
+    let mut stack = vec![doc.root()];
+    while let Some(id) = stack.pop() {
+        stack.extend(doc.children(id).iter().rev().copied());
+        let is_module = doc.tag_name(id) == Some("script")
+            && doc
+                .attribute(id, "type")
+                .is_some_and(|kind| kind.trim().eq_ignore_ascii_case("module"));
+        if !is_module {
+            continue;
+        }
+        match doc.attribute(id, "src") {
+            // A `src` that doesn't parse makes the script fail, not fall back to its text.
+            Some(src) => {
+                if let Ok(url) = base.join(src.trim()) {
+                    scripts.push(ModuleScript::External(url));
+                }
+            }
+            None => {
+                let source: String = doc
+                    .children(id)
+                    .iter()
+                    .filter_map(|child| doc.text_value(*child))
+                    .collect();
+                let index = scripts
+                    .iter()
+                    .filter(|script| matches!(script, ModuleScript::Inline { .. }))
+                    .count();
+                scripts.push(ModuleScript::Inline {
+                    base: base.clone(),
+                    index,
+                    source,
+                });
+            }
+        }
+    }
+    scripts
+}
+
+/// The modules a document fetched so far: the source and final URL of each, by request URL.
+pub(crate) type ModuleCache = HashMap<Url, (Url, String)>;
+
+/// Loads the module graph of `script`, fetching the modules `cache` doesn't have with `fetch`.
+/// Returns the name of the entry module along with the graph.
+pub(crate) async fn load_module_graph<F, Fut>(
+    script: &ModuleScript,
+    cache: &mut ModuleCache,
+    fetch: F,
+) -> Result<(String, ModuleGraph), String>
+where
+    F: Fn(Url) -> Fut,
+    Fut: Future<Output = Result<(Url, String), String>>,
+{
+    let mut graph = ModuleGraph::new();
+    let mut pending = Vec::new();
+    match script {
+        ModuleScript::External(url) => pending.push(url.clone()),
+        ModuleScript::Inline { base, source, .. } => {
+            let record = module_record(source.clone(), base, &mut pending)?;
+            graph.insert(script.name(), record);
+        }
+    }
+
+    while let Some(url) = pending.pop() {
+        if graph.contains(url.as_str()) {
+            continue;
+        }
+        let (final_url, source) = match cache.get(&url) {
+            Some(module) => module.clone(),
+            None => {
+                let module = fetch(url.clone()).await?;
+                cache.insert(url.clone(), module.clone());
+                module
+            }
+        };
+        let record = module_record(source, &final_url, &mut pending)?;
+        graph.insert(url.to_string(), record);
+    }
+    Ok((script.name(), graph))
+}
+
+/// The record of a module at `base`, queueing what it imports on `pending`.
+fn module_record(source: String, base: &Url, pending: &mut Vec<Url>) -> Result<ModuleRecord, String> {
+    let mut imports = HashMap::new();
+    for specifier in import_specifiers(&source) {
+        let url = resolve_specifier(&specifier, base)
+            .ok_or_else(|| format!("failed to resolve module specifier {specifier:?} in {base}"))?;
+        imports.insert(specifier, url.to_string());
+        pending.push(url);
+    }
+    Ok(ModuleRecord { source, imports })
+}
+
+/// Resolves an import specifier against the URL of the importing module. Bare specifiers
+/// (`"lodash"`) resolve to nothing, as there are no import maps.
+pub(crate) fn resolve_specifier(specifier: &str, base: &Url) -> Option<Url> {
+    if specifier.starts_with('/') || specifier.starts_with("./") || specifier.starts_with("../") {
+        return base.join(specifier).ok();
+    }
+    Url::parse(specifier).ok()
+}
+
+/// The source of a fetched module, checked for a successful status and a JavaScript MIME type.
+pub(crate) fn module_response(url: &Url, response: ScriptFetchResponse) -> Result<(Url, String), String> {
+    if !(200..300).contains(&response.status) {
+        return Err(format!("loading module {url} failed with status {}", response.status));
+    }
+    let content_type = response
+        .headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+        .map(|(_, value)| value.as_str())
+        .unwrap_or_default();
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .cow_to_ascii_lowercase();
+    if !matches!(
+        &*essence,
+        "text/javascript" | "application/javascript" | "application/x-javascript" | "text/ecmascript"
+    ) {
+        return Err(format!("module {url} has MIME type {content_type:?}, not JavaScript"));
+    }
+    let final_url = Url::parse(&response.url).unwrap_or_else(|_| url.clone());
+    Ok((final_url, decode_utf8(&response.body)))
+}
+
+/// What the module scanner sees of a source.
+#[derive(Debug, PartialEq)]
+enum Token {
+    Word(String),
+    /// A string literal's value; template literals count as strings with no value
+    Str(Option<String>),
+    Punct(char),
+}
+
+/// The specifiers of the static `import`s and `export ... from`s in `source`. This is a scan
+/// rather than a parse: it skips comments, strings, template and regular expression literals,
+/// and takes a `/` for the start of a regular expression wherever an expression may begin.
+pub(crate) fn import_specifiers(source: &str) -> Vec<String> {
+    let tokens = tokenize(source);
+    let mut specifiers = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let is_declaration = matches!(&tokens[i], Token::Word(word) if word == "import" || word == "export")
+            && !matches!(i.checked_sub(1).map(|prev| &tokens[prev]), Some(Token::Punct('.')));
+        i += 1;
+        if !is_declaration {
+            continue;
+        }
+        // `import "x"`; `import(...)` and `import.meta` aren't declarations.
+        if let Some(Token::Str(Some(specifier))) = tokens.get(i) {
+            specifiers.push(specifier.clone());
+            i += 1;
+            continue;
+        }
+        // Skip the bindings up to `from`.
+        while let Some(token) = tokens.get(i) {
+            match token {
+                Token::Punct('{') => {
+                    while !matches!(tokens.get(i), Some(Token::Punct('}')) | None) {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                Token::Word(word) if word == "from" => {
+                    if let Some(Token::Str(Some(specifier))) = tokens.get(i + 1) {
+                        specifiers.push(specifier.clone());
+                        i += 1;
+                    }
+                    i += 1;
+                    break;
+                }
+                Token::Word(word) if !matches!(word.as_str(), "const" | "let" | "var" | "function" | "class") => i += 1,
+                Token::Punct('*' | ',') => i += 1,
+                _ => break,
+            }
+        }
+    }
+    specifiers
+}
+
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut star = false;
+                for c in chars.by_ref() {
+                    if star && c == '/' {
+                        break;
+                    }
+                    star = c == '*';
+                }
+            }
+            '/' if regex_allowed(tokens.last()) => {
+                let mut class = false;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '[' => class = true,
+                        ']' => class = false,
+                        '/' if !class => break,
+                        '\n' => break,
+                        _ => {}
+                    }
+                }
+                tokens.push(Token::Str(None));
+            }
+            '"' | '\'' => {
+                let mut value = String::new();
+                while let Some(ch) = chars.next() {
+                    match ch {
+                        '\\' => {
+                            if let Some(escaped) = chars.next() {
+                                value.push(escaped);
+                            }
+                        }
+                        ch if ch == c || ch == '\n' => break,
+                        ch => value.push(ch),
+                    }
+                }
+                tokens.push(Token::Str(Some(value)));
+            }
+            '`' => {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '`' => break,
+                        _ => {}
+                    }
+                }
+                tokens.push(Token::Str(None));
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '$' => {
+                let mut word = String::from(c);
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_' || c == '$') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+            c => tokens.push(Token::Punct(c)),
+        }
+    }
+    tokens
+}
+
+/// Whether a `/` after `prev` starts a regular expression rather than dividing.
+fn regex_allowed(prev: Option<&Token>) -> bool {
+    match prev {
+        None => true,
+        Some(Token::Punct(c)) => !matches!(c, ')' | ']' | '}'),
+        Some(Token::Word(word)) => matches!(
+            word.as_str(),
+            "return"
+                | "typeof"
+                | "instanceof"
+                | "in"
+                | "of"
+                | "new"
+                | "delete"
+                | "void"
+                | "throw"
+                | "case"
+                | "do"
+                | "else"
+                | "yield"
+                | "await"
+        ),
+        Some(Token::Str(_)) => false,
+    }
+}
+
+/// Hands loaded module graphs to a script thread, for the document that was current when it was
+/// made. Graphs that arrive after the document was replaced are dropped.
+#[derive(Debug, Clone)]
+pub(crate) struct ModuleQueue {
+    pub(super) jobs: mpsc::Sender<ScriptJob>,
+    pub(super) document: u64,
+}
+
+impl ModuleQueue {
+    /// Links and evaluates module `url` of `graph`.
+    pub fn run(&self, url: String, graph: ModuleGraph) {
+        let _ = self.jobs.send(ScriptJob::RunModule {
+            document: self.document,
+            url,
+            graph,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scanning_finds_static_imports_only() {
+        let source = r#"
+            import def, { a as b, c } from "./a.js";
+            import * as ns from '../b.js';
+            import "/side-effect.js";
+            export { x } from "./x.js";
+            export * from "https://cdn.example/y.js";
+            // import "./commented.js";
+            const s = "import './string.js'";
+            const re = /import "regex"/;
+            obj.import("./method.js");
+            import("./dynamic.js");
+            export const from = 1 / 2;
+        "#;
+        assert_eq!(
+            import_specifiers(source),
+            [
+                "./a.js",
+                "../b.js",
+                "/side-effect.js",
+                "./x.js",
+                "https://cdn.example/y.js"
+            ]
+        );
+    }
+
+    #[test]
+    fn graphs_are_fetched_once_per_module() {
+        let base = Url::parse("https://app.example/index.html").unwrap();
+        let sources = HashMap::from([
+            ("https://app.example/a.js", "import './b.js'; import '/shared.js';"),
+            ("https://app.example/b.js", "import './a.js'; import './shared.js';"),
+            ("https://app.example/shared.js", "export const x = 1;"),
+        ]);
+        let fetched = std::cell::RefCell::new(Vec::new());
+        let fetch = |url: Url| {
+            fetched.borrow_mut().push(url.to_string());
+            let source = sources.get(url.as_str()).map(|s| s.to_string());
+            async move { source.map(|s| (url, s)).ok_or_else(|| "not found".to_string()) }
+        };
+
+        let mut cache = ModuleCache::new();
+        let inline = ModuleScript::Inline {
+            base: base.clone(),
+            index: 0,
+            source: "import { x } from './a.js';".into(),
+        };
+        let (entry, graph) = futures::executor::block_on(load_module_graph(&inline, &mut cache, fetch)).unwrap();
+        assert_eq!(entry, "https://app.example/index.html#inline-module-0");
+        assert_eq!(graph.len(), 4);
+        assert_eq!(
+            graph.resolve("https://app.example/b.js", "./a.js"),
+            Some("https://app.example/a.js")
+        );
+        assert_eq!(fetched.borrow().len(), 3);
+
+        // The next script of the document takes what it can from the cache.
+        let external = ModuleScript::External(Url::parse("https://app.example/b.js").unwrap());
+        let (entry, graph) = futures::executor::block_on(load_module_graph(&external, &mut cache, fetch)).unwrap();
+        assert_eq!(entry, "https://app.example/b.js");
+        assert_eq!(graph.len(), 3);
+        assert_eq!(fetched.borrow().len(), 3);
+
+        let bare = ModuleScript::Inline {
+            base,
+            index: 1,
+            source: "import 'lodash';".into(),
+        };
+        assert!(futures::executor::block_on(load_module_graph(&bare, &mut cache, fetch)).is_err());
+    }
+}
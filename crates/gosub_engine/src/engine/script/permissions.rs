@@ -0,0 +1,359 @@
+//! `navigator.permissions` and `Notification`.
+//!
+//! Permission states come from the tab's [`PermissionStore`], read directly from the script
+//! thread. Asking for one (`Notification.requestPermission()`) goes to the tab worker, which
+//! answers from the store when the origin already has a decision, and otherwise asks the UA with
+//! [`EngineEvent::PermissionRequested`]. The UA's [`TabCommand::AnswerPermission`] is remembered
+//! in the store and settles the promise; `PermissionStatus` objects of the context see the change.
+//!
+//! Notifications are only forwarded: the UA shows them on [`EngineEvent::NotificationRequested`]
+//! and takes them down on [`EngineEvent::NotificationClosed`]. Clicks aren't reported back yet.
+//! Workers have neither.
+//!
+//! [`EngineEvent::PermissionRequested`]: crate::events::EngineEvent::PermissionRequested
+//! [`EngineEvent::NotificationRequested`]: crate::events::EngineEvent::NotificationRequested
+//! [`EngineEvent::NotificationClosed`]: crate::events::EngineEvent::NotificationClosed
+//! [`TabCommand::AnswerPermission`]: crate::events::TabCommand::AnswerPermission
+//! [`PermissionStore`]: gosub_web_platform::permissions::PermissionStore
+
+use super::{native_function, native_string_function, PageChannel, ScriptJob, ScriptRequest};
+use gosub_web_platform::permissions::{PermissionName, PermissionState};
+use gosub_webexecutor::js::{WebContext, WebObject, WebRuntime, WebValue};
+use serde::Deserialize;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+use std::sync::{mpsc, Arc};
+use uuid::Uuid;
+
+/// Global the native permission functions are handed to the shim on.
+const PERMISSIONS_GLOBAL: &str = "__gosubPermissions";
+/// Function the shim leaves behind for settling a permission request.
+const PERMISSION_DONE: &str = "__gosubPermissionDone";
+
+const PERMISSIONS_SHIM: &str = r#"(() => {
+    const native = globalThis.__gosubPermissions;
+    delete globalThis.__gosubPermissions;
+    const INTERNAL = Symbol("internal");
+    const pending = new Map();
+    const statuses = new Set();
+    let recheck;
+
+    class PermissionStatus extends EventTarget {
+        #name;
+        #state;
+        constructor(token, name) {
+            if (token !== INTERNAL) throw new TypeError("Illegal constructor");
+            super();
+            this.#name = name;
+            this.#state = this.state;
+            this.onchange = null;
+        }
+        get name() {
+            return this.#name;
+        }
+        get state() {
+            return native.state(this.#name);
+        }
+
+        static {
+            recheck = (status) => {
+                const state = status.state;
+                if (state === status.#state) return;
+                status.#state = state;
+                status.dispatchEvent(new Event("change"));
+            };
+        }
+    }
+
+    class Permissions {
+        constructor(token) {
+            if (token !== INTERNAL) throw new TypeError("Illegal constructor");
+        }
+        query(descriptor) {
+            const name = String(descriptor?.name);
+            if (native.state(name) === "") {
+                return Promise.reject(new TypeError(`The permission "${name}" is not supported.`));
+            }
+            const status = new PermissionStatus(INTERNAL, name);
+            statuses.add(new WeakRef(status));
+            return Promise.resolve(status);
+        }
+    }
+
+    // The Notification API calls "prompt" "default".
+    const notificationPermission = (state) => (state === "prompt" ? "default" : state);
+
+    class Notification extends EventTarget {
+        #id;
+        constructor(title, options = {}) {
+            if (arguments.length === 0) throw new TypeError("Notification: a title is required");
+            super();
+            this.title = String(title);
+            this.body = String(options?.body ?? "");
+            this.tag = String(options?.tag ?? "");
+            this.icon = String(options?.icon ?? "");
+            this.lang = String(options?.lang ?? "");
+            this.dir = String(options?.dir ?? "auto");
+            this.data = options?.data ?? null;
+            this.silent = options?.silent ?? null;
+            this.requireInteraction = !!options?.requireInteraction;
+            this.onshow = this.onclick = this.onclose = this.onerror = null;
+            const { body, tag, icon, silent, requireInteraction } = this;
+            this.#id = native.show(JSON.stringify({ title: this.title, body, tag, icon, silent, requireInteraction }));
+            const type = this.#id === undefined ? "error" : "show";
+            setTimeout(() => this.dispatchEvent(new Event(type)), 0);
+        }
+        static get permission() {
+            return notificationPermission(native.state("notifications"));
+        }
+        static get maxActions() {
+            return 0;
+        }
+        static requestPermission(callback) {
+            return new Promise((resolve) => {
+                const id = native.request("notifications");
+                pending.set(id, (state) => {
+                    const permission = notificationPermission(state);
+                    if (typeof callback === "function") callback(permission);
+                    resolve(permission);
+                });
+            });
+        }
+        close() {
+            if (this.#id === undefined) return;
+            native.close(this.#id);
+            this.#id = undefined;
+            setTimeout(() => this.dispatchEvent(new Event("close")), 0);
+        }
+    }
+
+    globalThis.Permissions = Permissions;
+    globalThis.PermissionStatus = PermissionStatus;
+    globalThis.Notification = Notification;
+    if (typeof globalThis.navigator !== "object" || globalThis.navigator === null) globalThis.navigator = {};
+    Object.defineProperty(globalThis.navigator, "permissions", {
+        value: new Permissions(INTERNAL),
+        enumerable: true,
+        configurable: true,
+    });
+
+    Object.defineProperty(globalThis, "__gosubPermissionDone", {
+        value: (id, state) => {
+            const settle = pending.get(id);
+            pending.delete(id);
+            for (const ref of statuses) {
+                const status = ref.deref();
+                if (status) recheck(status);
+                else statuses.delete(ref);
+            }
+            settle?.(state);
+        },
+    });
+})()"#;
+
+/// Identifies one permission request of a page, so the UA's answer can be matched up.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PermissionRequestId(Uuid);
+
+impl PermissionRequestId {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for PermissionRequestId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for PermissionRequestId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Identifies a notification a page showed, for taking it down again.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct NotificationId(Uuid);
+
+impl NotificationId {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for NotificationId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for NotificationId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// What a page wants shown, from `new Notification(title, options)`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationRequest {
+    pub title: String,
+    pub body: String,
+    /// Notifications with the same non-empty tag replace each other
+    pub tag: String,
+    /// URL of an icon, as the page gave it
+    pub icon: String,
+    /// `Some(true)` to show it without sound or vibration; `None` leaves it to the UA
+    pub silent: Option<bool>,
+    /// Keep it up until the user acts on it
+    pub require_interaction: bool,
+}
+
+/// Where the outcome of a permission request goes.
+#[derive(Debug)]
+pub(crate) struct PermissionReply {
+    jobs: mpsc::Sender<ScriptJob>,
+    document: u64,
+    id: u32,
+}
+
+impl PermissionReply {
+    /// The document the request came from.
+    pub fn document(&self) -> u64 {
+        self.document
+    }
+
+    pub fn send(self, state: PermissionState) {
+        let _ = self.jobs.send(ScriptJob::PermissionDone {
+            document: self.document,
+            id: self.id,
+            state,
+        });
+    }
+}
+
+/// The serialized origin of the page, `"null"` when it is opaque or there is no URL.
+fn page_origin(page: &PageChannel) -> String {
+    page.url
+        .as_ref()
+        .map(|url| url.origin().ascii_serialization())
+        .unwrap_or_else(|| "null".into())
+}
+
+/// Puts the native `state(name)`, `request(name)`, `show(json)` and `close(id)` on the global
+/// object and runs [`PERMISSIONS_SHIM`].
+pub(super) fn install_permissions<RT: WebRuntime>(ctx: &mut RT::Context, page: &PageChannel) -> anyhow::Result<()> {
+    let native = RT::Object::new(ctx)?;
+    let origin = page_origin(page);
+
+    let store = Arc::clone(&page.permissions);
+    let state_origin = origin.clone();
+    let state = native_string_function::<RT>(ctx, move |args| {
+        let name = args.first().and_then(|v| v.as_string().ok()).unwrap_or_default();
+        match name.parse::<PermissionName>() {
+            Ok(name) => store.state(&state_origin, name).as_str().to_string(),
+            Err(()) => String::new(),
+        }
+    })?;
+    native.set_method("state", &state)?;
+
+    let channel = page.clone();
+    let next_id = Cell::new(1u32);
+    let request = native_function::<RT>(ctx, move |args| {
+        let name = args.first().and_then(|v| v.as_string().ok())?;
+        let name = name.parse::<PermissionName>().ok()?;
+        let id = next_id.get();
+        next_id.set(id.wrapping_add(1));
+        let reply = PermissionReply {
+            jobs: channel.jobs.clone(),
+            document: channel.document,
+            id,
+        };
+        channel.requests.send(ScriptRequest::Permission { name, reply }).ok()?;
+        Some(id)
+    })?;
+    native.set_method("request", &request)?;
+
+    // Notifications shown by this context, by the id the shim knows them by.
+    let shown: Rc<RefCell<HashMap<u32, NotificationId>>> = Rc::default();
+    let next_id = Cell::new(1u32);
+
+    let store = Arc::clone(&page.permissions);
+    let channel = page.clone();
+    let notifications = Rc::clone(&shown);
+    let show = native_function::<RT>(ctx, move |args| {
+        if store.state(&origin, PermissionName::Notifications) != PermissionState::Granted {
+            return None;
+        }
+        let json = args.first().and_then(|v| v.as_string().ok())?;
+        let notification = serde_json::from_str::<NotificationRequest>(&json).ok()?;
+        let id = NotificationId::new();
+        channel
+            .requests
+            .send(ScriptRequest::ShowNotification {
+                document: channel.document,
+                id,
+                notification,
+            })
+            .ok()?;
+        let local = next_id.get();
+        next_id.set(local.wrapping_add(1));
+        notifications.borrow_mut().insert(local, id);
+        Some(local)
+    })?;
+    native.set_method("show", &show)?;
+
+    let channel = page.clone();
+    let close = native_function::<RT>(ctx, move |args| {
+        let local = args.first().and_then(|v| v.as_number().ok())? as u32;
+        let id = shown.borrow_mut().remove(&local)?;
+        let _ = channel.requests.send(ScriptRequest::CloseNotification {
+            document: channel.document,
+            id,
+        });
+        None
+    })?;
+    native.set_method("close", &close)?;
+
+    ctx.set_on_global_object(PERMISSIONS_GLOBAL, native.into())?;
+    ctx.run(PERMISSIONS_SHIM)?;
+    Ok(())
+}
+
+/// Settles the promise of permission request `id` with `state`.
+pub(super) fn deliver<RT: WebRuntime>(ctx: &mut RT::Context, id: u32, state: PermissionState) -> anyhow::Result<()> {
+    ctx.run(&format!("{PERMISSION_DONE}({id}, \"{}\")", state.as_str()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notification_options_cross_as_json() {
+        let json = r#"{"title":"Hi","body":"there","tag":"","icon":"/i.png","silent":null,"requireInteraction":true}"#;
+        let notification: NotificationRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(notification.title, "Hi");
+        assert_eq!(notification.silent, None);
+        assert!(notification.require_interaction);
+
+        let (jobs, rx) = mpsc::channel();
+        let reply = PermissionReply {
+            jobs,
+            document: 2,
+            id: 5,
+        };
+        reply.send(PermissionState::Granted);
+        match rx.try_recv() {
+            Ok(ScriptJob::PermissionDone { document, id, state }) => {
+                assert_eq!((document, id, state), (2, 5, PermissionState::Granted));
+            }
+            _ => panic!("expected the permission outcome"),
+        }
+    }
+}
@@ -0,0 +1,256 @@
+//! `structuredClone()`, and the serialization `postMessage()`, workers and IndexedDB share.
+//!
+//! A value is written as a JSON-compatible record: `{ root, objects }`, where `objects` lists
+//! every object in the graph once and values refer to them by index as `{ r: index }`. That keeps
+//! shared references and cycles intact. A value is either a JSON primitive (`null`, a boolean, a
+//! string, a finite number other than `-0`) or an object with a tag `t`:
+//!
+//! | `t`                | fields                                                            |
+//! |--------------------|-------------------------------------------------------------------|
+//! | `undefined`        |                                                                   |
+//! | `number`           | `v`: `"NaN"`, `"Infinity"`, `"-Infinity"` or `"-0"`               |
+//! | `bigint`           | `v`: the decimal digits                                           |
+//!
+//! and the records in `objects` are:
+//!
+//! | `t`                              | fields                                                 |
+//! |----------------------------------|--------------------------------------------------------|
+//! | `Object`                         | `e`: `[key, value]` pairs of the own enumerable props  |
+//! | `Array`                          | `n`: the length, `e`: like `Object`, holes left out    |
+//! | `Boolean`/`Number`/`String`/`BigInt` | `v`: the wrapped primitive                         |
+//! | `Date`                           | `v`: the time value, `null` when invalid               |
+//! | `RegExp`                         | `s`: the source, `f`: the flags                        |
+//! | `Map`                            | `e`: `[key, value]` pairs                              |
+//! | `Set`                            | `e`: the values                                        |
+//! | `ArrayBuffer`                    | `b`: the bytes, in base64                              |
+//! | a typed array name, `DataView`   | `buf`: the buffer's ref, `o`: byte offset, `l`: length |
+//! | `Error`                          | `n`: the name, `m`: the message, `s`: the stack        |
+//! | `Host`                           | `v`: what the serializing shim made of a platform object |
+//!
+//! Anything else, such as functions, symbols, promises or DOM nodes, fails with a
+//! `DataCloneError`. Platform objects that can be cloned or transferred, `MessagePort`s, are left
+//! to the shim that owns them through a hook, which is how messaging turns a transferred port
+//! into its id and back.
+//!
+//! Buffers in the transfer list are detached once the value is written, when the runtime
+//! supports `ArrayBuffer.prototype.transfer()`.
+
+use gosub_webexecutor::js::{WebContext, WebRuntime};
+
+/// `structuredClone()`. The reader and writer are left on `__gosubStructuredClone` for the other
+/// shims.
+const STRUCTURED_CLONE_SHIM: &str = r#"(() => {
+    const domError = (name, message) => {
+        const error = new Error(message);
+        error.name = name;
+        return error;
+    };
+    const cloneError = (message) => domError("DataCloneError", message);
+
+    const VIEWS = [
+        "Int8Array", "Uint8Array", "Uint8ClampedArray", "Int16Array", "Uint16Array", "Int32Array", "Uint32Array",
+        "Float32Array", "Float64Array", "BigInt64Array", "BigUint64Array", "DataView",
+    ].filter((name) => typeof globalThis[name] === "function");
+    const ERRORS = ["Error", "EvalError", "RangeError", "ReferenceError", "SyntaxError", "TypeError", "URIError"];
+    const tag = (value) => Object.prototype.toString.call(value).slice(8, -1);
+    // Instances of the page's classes are cloned as plain objects, like browsers do, but the
+    // shims' own platform objects can't be.
+    const isPlatformObject = (value) =>
+        (typeof EventTarget === "function" && value instanceof EventTarget) ||
+        (typeof Event === "function" && value instanceof Event);
+    // Defines rather than assigns, so a `__proto__` key stays a key.
+    const define = (object, key, value) =>
+        Object.defineProperty(object, key, { value, writable: true, enumerable: true, configurable: true });
+
+    const BASE64 = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    const toBase64 = (bytes) => {
+        let out = "";
+        for (let i = 0; i < bytes.length; i += 3) {
+            const [a, b = 0, c = 0] = [bytes[i], bytes[i + 1], bytes[i + 2]];
+            out += BASE64[a >> 2] + BASE64[((a & 3) << 4) | (b >> 4)];
+            out += i + 1 < bytes.length ? BASE64[((b & 15) << 2) | (c >> 6)] : "=";
+            out += i + 2 < bytes.length ? BASE64[c & 63] : "=";
+        }
+        return out;
+    };
+    const fromBase64 = (text) => {
+        const clean = text.replace(/=+$/, "");
+        const bytes = new Uint8Array((clean.length * 3) >> 2);
+        let bits = 0, count = 0, at = 0;
+        for (const char of clean) {
+            bits = (bits << 6) | BASE64.indexOf(char);
+            count += 6;
+            if (count >= 8) {
+                count -= 8;
+                bytes[at++] = (bits >> count) & 255;
+            }
+        }
+        return bytes.buffer;
+    };
+
+    // https://html.spec.whatwg.org/multipage/structured-data.html#structuredserializeinternal
+    // `host(value)` gets the objects this shim doesn't know; it returns what to store for them,
+    // or `undefined` when they can't be cloned.
+    const write = (value, { host = () => undefined } = {}) => {
+        const objects = [];
+        const refs = new Map();
+        const entries = (object) => Object.keys(object).map((key) => [key, encode(object[key])]);
+
+        const encode = (value) => {
+            switch (typeof value) {
+                case "undefined": return { t: "undefined" };
+                case "boolean":
+                case "string": return value;
+                case "number":
+                    if (Number.isNaN(value)) return { t: "number", v: "NaN" };
+                    if (!Number.isFinite(value)) return { t: "number", v: value > 0 ? "Infinity" : "-Infinity" };
+                    return Object.is(value, -0) ? { t: "number", v: "-0" } : value;
+                case "bigint": return { t: "bigint", v: value.toString() };
+                case "symbol": throw cloneError("A symbol could not be cloned.");
+                case "function": throw cloneError(`${value.name || "A function"} could not be cloned.`);
+            }
+            if (value === null) return null;
+            if (refs.has(value)) return { r: refs.get(value) };
+            const index = objects.length;
+            refs.set(value, index);
+            objects.push(null);
+            objects[index] = record(value);
+            return { r: index };
+        };
+
+        const record = (value) => {
+            const hosted = host(value);
+            if (hosted !== undefined) return { t: "Host", v: hosted };
+            const type = tag(value);
+            switch (type) {
+                case "Boolean": return { t: type, v: Boolean.prototype.valueOf.call(value) };
+                case "Number": return { t: type, v: encode(Number.prototype.valueOf.call(value)) };
+                case "String": return { t: type, v: String.prototype.valueOf.call(value) };
+                case "BigInt": return { t: type, v: BigInt.prototype.valueOf.call(value).toString() };
+                case "Date": {
+                    const time = Date.prototype.getTime.call(value);
+                    return { t: type, v: Number.isNaN(time) ? null : time };
+                }
+                case "RegExp": return { t: type, s: value.source, f: value.flags };
+                case "ArrayBuffer":
+                    if (value.detached) throw cloneError("An ArrayBuffer is detached and could not be cloned.");
+                    return { t: type, b: toBase64(new Uint8Array(value)) };
+                case "Map": return { t: type, e: [...Map.prototype.entries.call(value)].map(([k, v]) => [encode(k), encode(v)]) };
+                case "Set": return { t: type, e: [...Set.prototype.values.call(value)].map(encode) };
+                case "Array": return { t: type, n: value.length, e: entries(value) };
+                case "Error": {
+                    const name = ERRORS.includes(value.name) ? value.name : "Error";
+                    const own = (key) => (Object.prototype.hasOwnProperty.call(value, key) ? String(value[key]) : undefined);
+                    return { t: type, n: name, m: own("message") ?? "", s: typeof value.stack === "string" ? value.stack : undefined };
+                }
+                case "Object":
+                    if (!isPlatformObject(value)) return { t: type, e: entries(value) };
+                    break;
+                default:
+                    if (VIEWS.includes(type)) {
+                        const length = type === "DataView" ? value.byteLength : value.length;
+                        return { t: type, buf: encode(value.buffer).r, o: value.byteOffset, l: length };
+                    }
+            }
+            throw cloneError(`${type === "Object" ? value.constructor?.name ?? type : type} object could not be cloned.`);
+        };
+        const root = encode(value);
+        return { root, objects };
+    };
+
+    // https://html.spec.whatwg.org/multipage/structured-data.html#structureddeserialize
+    // `host(stored)` makes the platform object back out of what the writer's hook stored.
+    const read = ({ root, objects }, { host = () => undefined } = {}) => {
+        const made = new Array(objects.length);
+        const fill = [];
+
+        const decode = (value) => {
+            if (value === null || typeof value !== "object") return value;
+            if ("r" in value) return object(value.r);
+            switch (value.t) {
+                case "undefined": return undefined;
+                case "number": return value.v === "-0" ? -0 : Number(value.v);
+                case "bigint": return BigInt(value.v);
+            }
+            throw cloneError("Malformed serialized value.");
+        };
+
+        const object = (index) => {
+            if (made[index] !== undefined) return made[index];
+            const record = objects[index];
+            let result;
+            switch (record.t) {
+                case "Object":
+                case "Array":
+                    result = record.t === "Array" ? new Array(record.n) : {};
+                    fill.push(() => record.e.forEach(([k, v]) => define(result, k, decode(v))));
+                    break;
+                case "Boolean": result = Object(record.v); break;
+                case "Number": result = Object(decode(record.v)); break;
+                case "String": result = Object(record.v); break;
+                case "BigInt": result = Object(BigInt(record.v)); break;
+                case "Date": result = new Date(record.v ?? NaN); break;
+                case "RegExp": result = new RegExp(record.s, record.f); break;
+                case "ArrayBuffer": result = fromBase64(record.b); break;
+                case "Map": result = new Map(); fill.push(() => record.e.forEach(([k, v]) => result.set(decode(k), decode(v)))); break;
+                case "Set": result = new Set(); fill.push(() => record.e.forEach((v) => result.add(decode(v)))); break;
+                case "Error": {
+                    const Type = ERRORS.includes(record.n) ? globalThis[record.n] : Error;
+                    result = new Type(record.m);
+                    if (record.s !== undefined) Object.defineProperty(result, "stack", { value: record.s, writable: true, configurable: true });
+                    break;
+                }
+                case "Host": result = host(record.v); break;
+                default:
+                    if (!VIEWS.includes(record.t)) throw cloneError(`Unknown serialized type ${record.t}.`);
+                    // The buffer comes first, so views share it.
+                    result = new globalThis[record.t](object(record.buf), record.o, record.l);
+            }
+            made[index] = result;
+            return result;
+        };
+
+        const value = decode(root);
+        // Contents are filled in once every object exists, so cycles find their target.
+        for (let i = 0; i < fill.length; i++) fill[i]();
+        return value;
+    };
+
+    // Detaches transferred buffers, which have been copied by then.
+    const detach = (buffers) => {
+        for (const buffer of buffers) {
+            if (typeof buffer.transfer === "function") buffer.transfer();
+        }
+    };
+    const transferList = (options) => {
+        const list = Array.from(options?.transfer ?? []);
+        for (const [i, item] of list.entries()) {
+            if (tag(item) !== "ArrayBuffer") throw cloneError("Only ArrayBuffers can be transferred here.");
+            if (list.indexOf(item) !== i) throw cloneError("An ArrayBuffer is transferred twice.");
+            if (item.detached) throw cloneError("An ArrayBuffer is detached and could not be cloned.");
+        }
+        return list;
+    };
+
+    Object.defineProperty(globalThis, "structuredClone", {
+        value: function structuredClone(value, options = {}) {
+            if (arguments.length === 0) throw new TypeError("structuredClone: a value is required");
+            const transfer = transferList(options);
+            const record = write(value);
+            detach(transfer);
+            return read(record);
+        },
+        writable: true,
+        configurable: true,
+    });
+    Object.defineProperty(globalThis, "__gosubStructuredClone", {
+        configurable: true,
+        value: { write, read, detach, tag },
+    });
+})()"#;
+
+/// Runs [`STRUCTURED_CLONE_SHIM`].
+pub(super) fn install_structured_clone<RT: WebRuntime>(ctx: &mut RT::Context) -> anyhow::Result<()> {
+    ctx.run(STRUCTURED_CLONE_SHIM)?;
+    Ok(())
+}
@@ -0,0 +1,475 @@
+//! Dedicated workers.
+//!
+//! A worker runs on a script thread of its own, with a runtime and context of its own. It starts
+//! with a [`ScriptJob::StartWorker`], which fetches the script (same-origin only) through the
+//! document's [`PageChannel`] and runs it; after that the thread works like the document's,
+//! running timers and delivering `fetch()` outcomes and messages. Messages are structured clones,
+//! see [`structured_clone`](super::structured_clone).
+//!
+//! A worker lives as long as the context that started it: terminating it, or dropping that
+//! context (the document being replaced, or the parent worker ending), stops its thread. A worker
+//! stuck in a loop only notices once it returns to its job queue.
+
+use super::{
+    native_function, native_string_function, number_arg, MessageSource, PageChannel, RedirectMode, ScriptContext,
+    ScriptFetchRequest, ScriptJob, ScriptRuntime,
+};
+use crate::net::charset::decode_utf8;
+use crate::net::cors::{CredentialsMode, RequestMode};
+use gosub_webexecutor::js::{WebContext, WebObject, WebRuntime, WebValue};
+use serde_json::json;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::AtomicBool;
+use std::sync::{mpsc, Arc};
+use url::Url;
+
+/// Global the native worker functions are handed to the shim on.
+const WORKERS_GLOBAL: &str = "__gosubWorkers";
+/// Function the shim leaves behind for delivering a message or error from a worker.
+pub(super) const WORKER_MESSAGE: &str = "__gosubWorkerMessage";
+/// Function the shim leaves behind, in workers, for delivering a message from the parent.
+pub(super) const PARENT_MESSAGE: &str = "__gosubParentMessage";
+
+/// `Worker`, plus in a worker's context the global scope: `self`, `postMessage()`, `close()`,
+/// `importScripts()`, `location` and `name`. Builds on the messaging shim.
+const WORKERS_SHIM: &str = r#"(() => {
+    const native = globalThis.__gosubWorkers;
+    delete globalThis.__gosubWorkers;
+    const { domError, serialize, takeMessage } = globalThis.__gosubMessageInternals;
+    delete globalThis.__gosubMessageInternals;
+    const workers = new Map();
+
+    class Worker extends EventTarget {
+        #id;
+        constructor(url, options = {}) {
+            super();
+            if (options?.type === "module") throw domError("NotSupportedError", "Module workers are not supported.");
+            const id = native.start(String(url), String(options?.name ?? ""));
+            if (id === undefined) throw domError("SyntaxError", `Invalid worker URL: ${url}`);
+            this.#id = id;
+            this.onmessage = null;
+            this.onmessageerror = null;
+            this.onerror = null;
+            workers.set(id, this);
+        }
+        postMessage(message, transfer = []) {
+            const data = serialize(message, transfer);
+            if (this.#id !== null) native.post(this.#id, data);
+        }
+        terminate() {
+            if (this.#id === null) return;
+            native.terminate(this.#id);
+            workers.delete(this.#id);
+            this.#id = null;
+        }
+    }
+    globalThis.Worker = Worker;
+
+    Object.defineProperty(globalThis, "__gosubWorkerMessage", {
+        value: (id, error) => {
+            const worker = workers.get(id);
+            if (error !== null) {
+                worker?.dispatchEvent(new ErrorEvent("error", { message: error, cancelable: true }));
+                return;
+            }
+            const message = takeMessage();
+            worker?.dispatchEvent(new MessageEvent("message", message));
+        },
+    });
+
+    if (native.scope === undefined) return;
+
+    const scope = JSON.parse(native.scope);
+    const location = Object.freeze({
+        ...scope.location,
+        toString() {
+            return this.href;
+        },
+    });
+    Object.assign(globalThis, {
+        self: globalThis,
+        name: scope.name,
+        location,
+        onerror: null,
+        postMessage(message, transfer = []) {
+            native.postToParent(serialize(message, transfer));
+        },
+        close() {
+            native.close();
+        },
+        importScripts(...urls) {
+            for (const url of urls) {
+                const reply = JSON.parse(native.importScript(String(url)));
+                if (reply.error !== undefined) throw domError("NetworkError", reply.error);
+                (0, eval)(reply.source);
+            }
+        },
+    });
+
+    Object.defineProperty(globalThis, "__gosubParentMessage", {
+        value: () => {
+            globalThis.dispatchEvent(new MessageEvent("message", takeMessage()));
+        },
+    });
+})()"#;
+
+/// How a worker's context reaches the context that started it.
+#[derive(Debug, Clone)]
+pub(super) struct WorkerScope {
+    parent: mpsc::Sender<ScriptJob>,
+    document: u64,
+    id: u32,
+    name: String,
+}
+
+impl WorkerScope {
+    fn post(&self, data: String) {
+        let _ = self.parent.send(ScriptJob::Message {
+            document: self.document,
+            source: MessageSource::Worker(self.id),
+            data,
+        });
+    }
+
+    /// The worker's name, or its id when it has none, for telling its console output apart.
+    pub(super) fn label(&self) -> String {
+        if self.name.is_empty() {
+            self.id.to_string()
+        } else {
+            self.name.clone()
+        }
+    }
+
+    /// Fires `error` at the parent's `Worker` object.
+    pub(super) fn report_error(&self, message: String) {
+        let _ = self.parent.send(ScriptJob::WorkerError {
+            document: self.document,
+            id: self.id,
+            message,
+        });
+    }
+}
+
+/// A running worker, seen from its parent. Dropping it stops the worker.
+pub(super) struct WorkerHandle {
+    jobs: mpsc::Sender<ScriptJob>,
+    document: u64,
+}
+
+impl WorkerHandle {
+    fn post(&self, data: String) {
+        let _ = self.jobs.send(ScriptJob::Message {
+            document: self.document,
+            source: MessageSource::Parent,
+            data,
+        });
+    }
+}
+
+impl Drop for WorkerHandle {
+    fn drop(&mut self) {
+        let _ = self.jobs.send(ScriptJob::Terminate);
+    }
+}
+
+/// The workers a context started, by id.
+pub(super) type Workers = Rc<RefCell<HashMap<u32, WorkerHandle>>>;
+
+/// Starts the thread of worker `id` of `page`'s context, which will run the script at `url`.
+fn spawn_worker(page: &PageChannel, url: Url, id: u32, name: String) -> std::io::Result<WorkerHandle> {
+    let (tx, rx) = mpsc::channel();
+    let worker_page = PageChannel {
+        requests: page.requests.clone(),
+        jobs: tx.clone(),
+        document: page.document,
+        url: None,
+        indexed_db: page.indexed_db.clone(),
+        dom: None,
+        fetch_ids: Arc::clone(&page.fetch_ids),
+        ports: Arc::clone(&page.ports),
+        media: Arc::clone(&page.media),
+        scroll_restoration: Arc::clone(&page.scroll_restoration),
+        permissions: Arc::clone(&page.permissions),
+        host: page.host.clone(),
+        worker: Some(WorkerScope {
+            parent: page.jobs.clone(),
+            document: page.document,
+            id,
+            name,
+        }),
+        // The worker's thread starts its own.
+        watchdog: None,
+    };
+    let _ = tx.send(ScriptJob::StartWorker { url });
+
+    let factory = Arc::clone(&page.host.factory);
+    std::thread::Builder::new()
+        .name(format!("script-worker-{id}"))
+        .spawn(move || {
+            let frame_requested = AtomicBool::new(false);
+            super::run_script_thread(&*factory, rx, worker_page, &frame_requested);
+        })?;
+    Ok(WorkerHandle {
+        jobs: tx,
+        document: page.document,
+    })
+}
+
+/// Loads and runs the worker script at `url` in a new context, which is returned. `page` is the
+/// worker's; its URL becomes the script's. Failures go to the parent as an `error` event.
+pub(super) fn start_worker(
+    runtime: Option<&mut (dyn ScriptRuntime + 'static)>,
+    page: &mut PageChannel,
+    url: Url,
+) -> Option<Box<dyn ScriptContext>> {
+    let scope = page.worker.clone()?;
+    let Some(runtime) = runtime else {
+        scope.report_error("the script runtime failed to start".into());
+        return None;
+    };
+
+    let source = match fetch_script(page, &url, RequestMode::SameOrigin) {
+        Ok((final_url, source)) => {
+            page.url = Some(final_url);
+            source
+        }
+        Err(message) => {
+            scope.report_error(message);
+            return None;
+        }
+    };
+    let mut context = match runtime.new_context(page.clone()) {
+        Ok(context) => context,
+        Err(e) => {
+            scope.report_error(format!("failed to create the worker's context: {e}"));
+            return None;
+        }
+    };
+    // A worker whose script throws keeps running, like a page would. The trailing `undefined`
+    // spares serializing the script's completion value.
+    if let Err(e) = context.evaluate(&format!("{source}\n;undefined")) {
+        scope.report_error(e.to_string());
+    }
+    Some(context)
+}
+
+/// Fetches the classic script at `url`, returning its final URL and source.
+fn fetch_script(page: &PageChannel, url: &Url, mode: RequestMode) -> Result<(Url, String), String> {
+    let response = page.fetch_blocking(ScriptFetchRequest {
+        url: url.to_string(),
+        method: "GET".into(),
+        headers: Vec::new(),
+        body: None,
+        mode,
+        credentials: CredentialsMode::SameOrigin,
+        redirect: RedirectMode::Follow,
+    })?;
+    if !(200..300).contains(&response.status) {
+        return Err(format!("loading {url} failed with status {}", response.status));
+    }
+    let final_url = Url::parse(&response.url).unwrap_or_else(|_| url.clone());
+    Ok((final_url, decode_utf8(&response.body)))
+}
+
+/// Puts the native `start(url, name)`, `post(id, data)` and `terminate(id)` on the global object,
+/// plus `postToParent(data)`, `close()`, `importScript(url)` and the scope's details in a
+/// worker's context, and runs [`WORKERS_SHIM`].
+pub(super) fn install_workers<RT: WebRuntime>(
+    ctx: &mut RT::Context,
+    page: &PageChannel,
+    workers: &Workers,
+) -> anyhow::Result<()> {
+    let native = RT::Object::new(ctx)?;
+
+    let channel = page.clone();
+    let handles = Rc::clone(workers);
+    let next_id = Cell::new(1u32);
+    let start = native_function::<RT>(ctx, move |args| {
+        let spec = args.first().and_then(|v| v.as_string().ok())?;
+        let url = match &channel.url {
+            Some(base) => base.join(&spec),
+            None => Url::parse(&spec),
+        }
+        .ok()?;
+        let name = args.get(1).and_then(|v| v.as_string().ok()).unwrap_or_default();
+        let id = next_id.get();
+        next_id.set(id.wrapping_add(1));
+        match spawn_worker(&channel, url, id, name) {
+            Ok(handle) => {
+                handles.borrow_mut().insert(id, handle);
+            }
+            Err(e) => {
+                let _ = channel.jobs.send(ScriptJob::WorkerError {
+                    document: channel.document,
+                    id,
+                    message: format!("failed to start the worker: {e}"),
+                });
+            }
+        }
+        Some(id)
+    })?;
+    native.set_method("start", &start)?;
+
+    let handles = Rc::clone(workers);
+    let post = native_function::<RT>(ctx, move |args| {
+        let id = number_arg(args, 0)? as u32;
+        let data = args.get(1).and_then(|v| v.as_string().ok())?;
+        if let Some(handle) = handles.borrow().get(&id) {
+            handle.post(data);
+        }
+        None
+    })?;
+    native.set_method("post", &post)?;
+
+    let handles = Rc::clone(workers);
+    let terminate = native_function::<RT>(ctx, move |args| {
+        if let Some(id) = number_arg(args, 0) {
+            handles.borrow_mut().remove(&(id as u32));
+        }
+        None
+    })?;
+    native.set_method("terminate", &terminate)?;
+
+    if let Some(scope) = &page.worker {
+        let location = page.url.as_ref().map(super::location::url_parts);
+        let details = json!({ "name": scope.name, "location": location }).to_string();
+        native.set_property("scope", &RT::Value::new_string(ctx.clone(), &details)?)?;
+
+        let parent = scope.clone();
+        let post_to_parent = native_function::<RT>(ctx, move |args| {
+            if let Some(data) = args.first().and_then(|v| v.as_string().ok()) {
+                parent.post(data);
+            }
+            None
+        })?;
+        native.set_method("postToParent", &post_to_parent)?;
+
+        let own_jobs = page.jobs.clone();
+        let close = native_function::<RT>(ctx, move |_| {
+            let _ = own_jobs.send(ScriptJob::Terminate);
+            None
+        })?;
+        native.set_method("close", &close)?;
+
+        let channel = page.clone();
+        let import_script = native_string_function::<RT>(ctx, move |args| {
+            let spec = args.first().and_then(|v| v.as_string().ok()).unwrap_or_default();
+            let url = match &channel.url {
+                Some(base) => base.join(&spec),
+                None => Url::parse(&spec),
+            };
+            let reply = match url {
+                // Cross-origin imports need CORS approval, as `no-cors` responses come back empty.
+                Ok(url) => match fetch_script(&channel, &url, RequestMode::Cors) {
+                    Ok((_, source)) => json!({ "source": source }),
+                    Err(message) => json!({ "error": message }),
+                },
+                Err(e) => json!({ "error": format!("invalid URL {spec:?}: {e}") }),
+            };
+            reply.to_string()
+        })?;
+        native.set_method("importScript", &import_script)?;
+    }
+
+    ctx.set_on_global_object(WORKERS_GLOBAL, native.into())?;
+    ctx.run(WORKERS_SHIM)?;
+    Ok(())
+}
+
+/// Dispatches an `error` event with `message` at the `Worker` object of worker `id`.
+pub(super) fn deliver_error<RT: WebRuntime>(ctx: &mut RT::Context, id: u32, message: &str) -> anyhow::Result<()> {
+    // A JSON string is a valid JS string literal.
+    ctx.run(&format!("{WORKER_MESSAGE}({id}, {})", serde_json::to_string(message)?))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::script::{ResponseType, ScriptFetchResponse, ScriptHost, ScriptRequest, ScriptResult};
+    use gosub_web_platform::permissions::MemoryPermissionStore;
+    use std::sync::atomic::AtomicU32;
+    use tokio::sync::mpsc as tokio_mpsc;
+
+    /// Posts every script it runs, and every message it gets, back to its parent.
+    struct EchoContext(Option<WorkerScope>);
+
+    impl ScriptContext for EchoContext {
+        fn evaluate(&mut self, source: &str) -> ScriptResult {
+            if let Some(scope) = &self.0 {
+                scope.post(source.trim_end_matches("\n;undefined").to_string());
+            }
+            Ok(serde_json::Value::Null)
+        }
+
+        fn message(&mut self, _source: MessageSource, data: String) {
+            if let Some(scope) = &self.0 {
+                scope.post(data);
+            }
+        }
+    }
+
+    struct EchoRuntime;
+
+    impl ScriptRuntime for EchoRuntime {
+        fn new_context(&mut self, page: PageChannel) -> anyhow::Result<Box<dyn ScriptContext>> {
+            Ok(Box::new(EchoContext(page.worker)))
+        }
+    }
+
+    fn next_message(rx: &mpsc::Receiver<ScriptJob>) -> (u64, MessageSource, String) {
+        match rx.recv().unwrap() {
+            ScriptJob::Message { document, source, data } => (document, source, data),
+            _ => panic!("expected a message"),
+        }
+    }
+
+    #[test]
+    fn worker_loads_its_script_and_exchanges_messages() {
+        let (requests, mut tab) = tokio_mpsc::unbounded_channel();
+        let (parent_jobs, parent) = mpsc::channel();
+        let page = PageChannel {
+            requests,
+            jobs: parent_jobs,
+            document: 3,
+            url: Some(Url::parse("https://app.example/app/index.html").unwrap()),
+            indexed_db: None,
+            dom: None,
+            fetch_ids: Arc::new(AtomicU32::new(1)),
+            ports: Default::default(),
+            media: Default::default(),
+            scroll_restoration: Default::default(),
+            permissions: Arc::new(MemoryPermissionStore::new()),
+            host: ScriptHost::new(|| Ok(EchoRuntime)),
+            worker: None,
+            watchdog: None,
+        };
+        let url = Url::parse("https://app.example/app/worker.js").unwrap();
+        let worker = spawn_worker(&page, url, 7, String::new()).unwrap();
+
+        let Some(ScriptRequest::Fetch { request, reply }) = tab.blocking_recv() else {
+            panic!("expected the worker script fetch");
+        };
+        assert_eq!(request.url, "https://app.example/app/worker.js");
+        assert_eq!(request.mode, RequestMode::SameOrigin);
+        assert_eq!(reply.key().0, 3);
+        reply.send(Ok(ScriptFetchResponse {
+            url: request.url,
+            status: 200,
+            status_text: "OK".into(),
+            headers: Vec::new(),
+            redirected: false,
+            kind: ResponseType::Basic,
+            body: b"postMessage('ready')".to_vec(),
+        }));
+        assert_eq!(
+            next_message(&parent),
+            (3, MessageSource::Worker(7), "postMessage('ready')".into())
+        );
+
+        worker.post("ping".into());
+        assert_eq!(next_message(&parent), (3, MessageSource::Worker(7), "ping".into()));
+    }
+}
@@ -0,0 +1,299 @@
+//! Text selection over laid-out text.
+//!
+//! A selection follows the DOM `Range` model: two boundary points, each a text node plus a
+//! character offset into it. Offsets index the *laid-out* text of a node (after whitespace
+//! collapsing and `text-transform`), which is what the user sees and what gets copied.
+//!
+//! The browsing context collects the page's [`TextFragment`]s in document order; dragging maps
+//! the pointer to a [`BoundaryPoint`], and the resulting [`TextRange`] is turned into per-node
+//! [`SelectionRanges`] for the painter and into a string for the clipboard.
+
+use gosub_render_pipeline::common::selection::{SelectionRanges, TextLines};
+use gosub_shared::node::NodeId;
+
+/// One laid-out text element, as seen by selection.
+#[derive(Clone, Debug)]
+pub struct TextFragment {
+    /// The DOM text node the element was laid out from.
+    pub node_id: NodeId,
+    /// The laid-out text.
+    pub text: String,
+    /// Line model of the element in page coordinates.
+    pub lines: TextLines,
+}
+
+/// A position between two characters of a text node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoundaryPoint {
+    pub node_id: NodeId,
+    /// Offset in `char`s into the node's laid-out text.
+    pub offset: usize,
+}
+
+/// A range between two boundary points, with `start` never after `end` in document order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextRange {
+    pub start: BoundaryPoint,
+    pub end: BoundaryPoint,
+}
+
+impl TextRange {
+    /// Builds a range from two points in either order. Returns `None` when a point refers to a
+    /// node that is not among `fragments`.
+    pub fn from_points(a: BoundaryPoint, b: BoundaryPoint, fragments: &[TextFragment]) -> Option<TextRange> {
+        let a_idx = fragments.iter().position(|f| f.node_id == a.node_id)?;
+        let b_idx = fragments.iter().position(|f| f.node_id == b.node_id)?;
+
+        if (a_idx, a.offset) <= (b_idx, b.offset) {
+            Some(TextRange { start: a, end: b })
+        } else {
+            Some(TextRange { start: b, end: a })
+        }
+    }
+
+    /// True when the range selects nothing.
+    pub fn collapsed(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Visits every fragment the range touches with its selected `start..end` char range.
+    fn for_each_selected<'a>(&self, fragments: &'a [TextFragment], mut f: impl FnMut(&'a TextFragment, usize, usize)) {
+        let mut inside = false;
+        for fragment in fragments {
+            let len = fragment.lines.char_count;
+            let is_start = fragment.node_id == self.start.node_id;
+            let is_end = fragment.node_id == self.end.node_id;
+
+            if is_start {
+                inside = true;
+            }
+            if !inside {
+                continue;
+            }
+
+            let from = if is_start { self.start.offset.min(len) } else { 0 };
+            let to = if is_end { self.end.offset.min(len) } else { len };
+            if from < to {
+                f(fragment, from, to);
+            }
+
+            if is_end {
+                break;
+            }
+        }
+    }
+
+    /// True when `point` lies inside the range; the end point itself is outside.
+    pub fn contains(&self, point: BoundaryPoint, fragments: &[TextFragment]) -> bool {
+        let index = |p: BoundaryPoint| fragments.iter().position(|f| f.node_id == p.node_id);
+        match (index(self.start), index(self.end), index(point)) {
+            (Some(start), Some(end), Some(at)) => {
+                (start, self.start.offset) <= (at, point.offset) && (at, point.offset) < (end, self.end.offset)
+            }
+            _ => false,
+        }
+    }
+
+    /// Per-node char ranges to highlight.
+    pub fn ranges(&self, fragments: &[TextFragment]) -> SelectionRanges {
+        let mut ranges = SelectionRanges::new();
+        self.for_each_selected(fragments, |fragment, from, to| {
+            ranges.insert(fragment.node_id, (from, to));
+        });
+        ranges
+    }
+
+    /// The selected text. Fragments that start on a new row are separated by a newline, fragments
+    /// on the same row are joined as-is.
+    pub fn to_text(self, fragments: &[TextFragment]) -> String {
+        let mut out = String::new();
+        let mut prev_bottom: Option<f64> = None;
+        self.for_each_selected(fragments, |fragment, from, to| {
+            let rect = fragment.lines.rect;
+            if prev_bottom.is_some_and(|bottom| rect.y >= bottom - 1.0) {
+                out.push('\n');
+            }
+            out.extend(fragment.text.chars().skip(from).take(to - from));
+            prev_bottom = Some(rect.y + rect.height);
+        });
+        out
+    }
+}
+
+/// Maps a page-space point to the nearest boundary point. A fragment containing the point wins;
+/// otherwise the closest fragment (by vertical, then horizontal distance) is used, so dragging
+/// through margins and gaps between blocks still extends the selection sensibly.
+pub fn boundary_at(fragments: &[TextFragment], x: f64, y: f64) -> Option<BoundaryPoint> {
+    let distance = |fragment: &TextFragment| {
+        let r = fragment.lines.rect;
+        let dy = if y < r.y {
+            r.y - y
+        } else if y >= r.y + r.height {
+            y - (r.y + r.height)
+        } else {
+            0.0
+        };
+        let dx = if x < r.x {
+            r.x - x
+        } else if x >= r.x + r.width {
+            x - (r.x + r.width)
+        } else {
+            0.0
+        };
+        (dy, dx)
+    };
+
+    let nearest = fragments.iter().min_by(|a, b| {
+        distance(a)
+            .partial_cmp(&distance(b))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    })?;
+
+    Some(BoundaryPoint {
+        node_id: nearest.node_id,
+        offset: nearest.lines.offset_at(x, y),
+    })
+}
+
+/// Drag-selection state of a browsing context.
+#[derive(Clone, Debug, Default)]
+pub struct TextSelection {
+    /// Where the drag started.
+    anchor: Option<BoundaryPoint>,
+    /// Current (non-collapsed) selection, if any.
+    range: Option<TextRange>,
+    /// True between mouse down and mouse up.
+    dragging: bool,
+}
+
+impl TextSelection {
+    /// Starts a new drag at `anchor`, dropping any previous selection.
+    pub fn begin(&mut self, anchor: Option<BoundaryPoint>) {
+        self.anchor = anchor;
+        self.range = None;
+        self.dragging = anchor.is_some();
+    }
+
+    /// Moves the focus of an active drag. Returns true when the selected range changed.
+    pub fn extend(&mut self, focus: BoundaryPoint, fragments: &[TextFragment]) -> bool {
+        let Some(anchor) = self.anchor.filter(|_| self.dragging) else {
+            return false;
+        };
+        let range = TextRange::from_points(anchor, focus, fragments).filter(|r| !r.collapsed());
+        if range == self.range {
+            return false;
+        }
+        self.range = range;
+        true
+    }
+
+    /// Ends the drag; the selection stays until the next [`Self::begin`] or [`Self::clear`].
+    pub fn end(&mut self) {
+        self.dragging = false;
+    }
+
+    /// Drops the selection. Returns true when there was one.
+    pub fn clear(&mut self) -> bool {
+        self.anchor = None;
+        self.dragging = false;
+        self.range.take().is_some()
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.dragging
+    }
+
+    pub fn range(&self) -> Option<&TextRange> {
+        self.range.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gosub_render_pipeline::common::geo::Rect;
+
+    fn fragment(id: usize, text: &str, x: f64, y: f64) -> TextFragment {
+        let width = text.chars().count() as f64 * 10.0;
+        TextFragment {
+            node_id: NodeId::from(id),
+            text: text.to_string(),
+            lines: TextLines::new(Rect::new(x, y, width, 20.0), 20.0, text.chars().count()),
+        }
+    }
+
+    fn page() -> Vec<TextFragment> {
+        vec![
+            fragment(1, "Hello ", 0.0, 0.0),
+            fragment(2, "world", 60.0, 0.0),
+            fragment(3, "Second line", 0.0, 40.0),
+        ]
+    }
+
+    fn point(id: usize, offset: usize) -> BoundaryPoint {
+        BoundaryPoint {
+            node_id: NodeId::from(id),
+            offset,
+        }
+    }
+
+    #[test]
+    fn range_orders_points() {
+        let fragments = page();
+        let range = TextRange::from_points(point(3, 2), point(1, 4), &fragments).unwrap();
+        assert_eq!(range.start, point(1, 4));
+        assert_eq!(range.end, point(3, 2));
+        assert!(TextRange::from_points(point(9, 0), point(1, 0), &fragments).is_none());
+    }
+
+    #[test]
+    fn range_text_and_highlight() {
+        let fragments = page();
+        let range = TextRange::from_points(point(1, 2), point(3, 6), &fragments).unwrap();
+
+        assert_eq!(range.to_text(&fragments), "llo world\nSecond");
+
+        let ranges = range.ranges(&fragments);
+        assert_eq!(ranges.get(&NodeId::from(1usize)), Some(&(2, 6)));
+        assert_eq!(ranges.get(&NodeId::from(2usize)), Some(&(0, 5)));
+        assert_eq!(ranges.get(&NodeId::from(3usize)), Some(&(0, 6)));
+
+        assert!(range.contains(point(1, 2), &fragments));
+        assert!(range.contains(point(2, 4), &fragments));
+        assert!(!range.contains(point(1, 1), &fragments));
+        assert!(!range.contains(point(3, 6), &fragments));
+    }
+
+    #[test]
+    fn boundary_snaps_to_nearest_fragment() {
+        let fragments = page();
+        // Inside "world", two characters in
+        assert_eq!(boundary_at(&fragments, 80.0, 10.0), Some(point(2, 2)));
+        // In the gap between the rows, nearest is the first row
+        assert_eq!(
+            boundary_at(&fragments, 0.0, 22.0).map(|p| p.node_id),
+            Some(NodeId::from(1usize))
+        );
+        assert_eq!(boundary_at(&[], 0.0, 0.0), None);
+    }
+
+    #[test]
+    fn drag_selection_lifecycle() {
+        let fragments = page();
+        let mut selection = TextSelection::default();
+
+        selection.begin(Some(point(1, 0)));
+        assert!(selection.is_dragging());
+        assert!(!selection.extend(point(1, 0), &fragments));
+        assert!(selection.range().is_none());
+
+        assert!(selection.extend(point(2, 3), &fragments));
+        selection.end();
+        assert!(!selection.is_dragging());
+        assert!(!selection.extend(point(3, 3), &fragments));
+        assert_eq!(selection.range().unwrap().to_text(&fragments), "Hello wor");
+
+        assert!(selection.clear());
+        assert!(!selection.clear());
+    }
+}
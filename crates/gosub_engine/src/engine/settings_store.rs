@@ -32,7 +32,8 @@ struct JsonEntry {
     _entry_type: String,
     default: String,
     description: String,
-    /// Optional comma-separated list of allowed values or ranges (e.g. `left,right` or `-1,0-9999`).
+    /// Optional comma-separated list of allowed values or ranges (e.g. `left,right` or `-1,0-9999`),
+    /// or a pattern the value has to match (e.g. `/[a-z]+/`).
     #[serde(default)]
     values: Option<String>,
 }
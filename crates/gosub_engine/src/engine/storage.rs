@@ -15,10 +15,13 @@
 //!   valid for the lifetime of a browsing session or until the tab is closed.
 //!   Backed by a [`SessionStore`].
 //!
-//! All stores implement the [`StorageArea`] trait, which provides the
+//! - **IndexedDB** - Object stores of keyed records per `(origin, partition)`, with
+//!   transactions. Backed by an [`IdbStore`]; see [`indexed_db`].
+//!
+//! All local and session stores implement the [`StorageArea`] trait, which provides the
 //! basic API for `get_item`, `set_item`, `remove_item`, and `clear`.
 //!
-//! A [`StorageService`] wraps one local store and one session store into a
+//! A [`StorageService`] wraps one local store, one session store and an IndexedDB store into a
 //! single handle that a [`Zone`](crate::zone::Zone) can use to provide both types
 //! of storage to its tabs.
 //!
@@ -32,11 +35,14 @@
 //! - [`StorageEvent`] - Describes a change in storage (key added, removed, etc.).
 //! - [`SqliteLocalStore`] - SQLite-backed persistent local storage.
 //! - [`InMemorySessionStore`] - In-memory session storage backend.
+//! - [`InMemoryIdbStore`], [`SqliteIdbStore`] - IndexedDB backends.
 //!
 //! # Choosing a backend
 //!
 //! - For persistent **LocalStorage**, use [`SqliteLocalStore`].
 //! - For ephemeral **SessionStorage**, use [`InMemorySessionStore`].
+//! - For persistent **IndexedDB**, pass a [`SqliteIdbStore`] to
+//!   [`StorageService::with_indexed_db`]; it is in-memory otherwise.
 //! - For testing or incognito modes, you can use in-memory for both.
 //!
 //! # Example: Attaching storage to a zone
@@ -88,6 +94,7 @@ use std::sync::Arc;
 pub mod area;
 /// Event module, providing storage change events.
 pub mod event;
+pub mod indexed_db;
 /// Service module, providing a unified storage service for zones.
 pub mod service;
 /// Storage types
@@ -118,6 +125,7 @@ pub struct StorageHandles {
 
 pub use area::{LocalStore, SessionStore, StorageArea};
 pub use event::StorageEvent;
+pub use indexed_db::{IdbStore, InMemoryIdbStore, IndexedDb, SqliteIdbStore};
 pub use local::in_memory::InMemoryLocalStore;
 pub use local::sqlite_store::SqliteLocalStore;
 pub use service::{StorageService, Subscription};
@@ -0,0 +1,526 @@
+//! IndexedDB: object stores of key/value records per `(zone, partition, origin)`.
+//!
+//! An [`IdbStore`] is the backend holding the databases of every origin; [`InMemoryIdbStore`] is
+//! the default, [`SqliteIdbStore`] persists to disk. A page gets an [`IndexedDb`], the databases
+//! of its own origin, and works on them through [`IdbTransaction`]s.
+//!
+//! Transactions write straight through to the backend and keep an undo log, which an abort (or
+//! dropping the transaction without committing) plays back. They are not isolated from each
+//! other: the script thread runs a document's transactions one after another, but two tabs on the
+//! same origin can interleave.
+//!
+//! Values are opaque strings to this module; the script side decides how to serialize them. Only
+//! keys are understood, see [`IdbKey`]. Indexes are not supported.
+
+mod key;
+
+/// In-memory IndexedDB backend.
+pub mod in_memory;
+/// SQLite-backed IndexedDB backend.
+pub mod sqlite_store;
+
+pub use in_memory::InMemoryIdbStore;
+pub use key::{IdbKey, KeyRange};
+pub use sqlite_store::SqliteIdbStore;
+
+use crate::engine::storage::types::PartitionKey;
+use crate::zone::ZoneId;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Largest key the key generator hands out (2^53, the largest integer a JS number holds exactly).
+const MAX_GENERATED_KEY: u64 = 1 << 53;
+
+/// Whose databases: IndexedDB is partitioned like local storage.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IdbScope {
+    pub zone: ZoneId,
+    pub partition: PartitionKey,
+    pub origin: url::Origin,
+}
+
+/// The key path of an object store: where in a value its key lives.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum KeyPath {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+/// An object store's definition.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectStoreInfo {
+    pub name: String,
+    pub key_path: Option<KeyPath>,
+    pub auto_increment: bool,
+    /// Next key the key generator hands out
+    pub next_key: u64,
+}
+
+/// A database's name, version and object stores.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseInfo {
+    pub name: String,
+    pub version: u64,
+    pub stores: Vec<ObjectStoreInfo>,
+}
+
+/// One record of an object store.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Record {
+    pub key: IdbKey,
+    pub value: String,
+}
+
+/// Backend for IndexedDB data. Records are kept ordered by [`IdbKey::encode`].
+pub trait IdbStore: Send + Sync {
+    /// All databases of `scope`.
+    fn databases(&self, scope: &IdbScope) -> Result<Vec<DatabaseInfo>>;
+
+    /// Creates or replaces a database's definition.
+    fn put_database(&self, scope: &IdbScope, info: &DatabaseInfo) -> Result<()>;
+
+    /// Deletes a database and all its records.
+    fn delete_database(&self, scope: &IdbScope, name: &str) -> Result<()>;
+
+    /// Records of `store` in `range`, in key order, at most `limit` of them.
+    fn records(
+        &self,
+        scope: &IdbScope,
+        db: &str,
+        store: &str,
+        range: &KeyRange,
+        limit: Option<usize>,
+    ) -> Result<Vec<Record>>;
+
+    /// Creates or overwrites a record.
+    fn put_record(&self, scope: &IdbScope, db: &str, store: &str, record: &Record) -> Result<()>;
+
+    /// Deletes the records of `store` in `range`.
+    fn delete_records(&self, scope: &IdbScope, db: &str, store: &str, range: &KeyRange) -> Result<()>;
+
+    /// Number of records of `store` in `range`.
+    fn count(&self, scope: &IdbScope, db: &str, store: &str, range: &KeyRange) -> Result<usize> {
+        Ok(self.records(scope, db, store, range, None)?.len())
+    }
+}
+
+/// Why an IndexedDB operation failed. [`IdbError::name`] is the `DOMException` name scripts see.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum IdbError {
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Constraint(String),
+    #[error("{0}")]
+    Data(String),
+    #[error("{0}")]
+    InvalidState(String),
+    /// The document's origin has no IndexedDB
+    #[error("{0}")]
+    Security(String),
+    #[error("storage backend failed: {0}")]
+    Backend(String),
+}
+
+impl IdbError {
+    pub fn name(&self) -> &'static str {
+        match self {
+            IdbError::NotFound(_) => "NotFoundError",
+            IdbError::Constraint(_) => "ConstraintError",
+            IdbError::Data(_) => "DataError",
+            IdbError::InvalidState(_) => "InvalidStateError",
+            IdbError::Security(_) => "SecurityError",
+            IdbError::Backend(_) => "UnknownError",
+        }
+    }
+}
+
+impl From<anyhow::Error> for IdbError {
+    fn from(e: anyhow::Error) -> Self {
+        IdbError::Backend(e.to_string())
+    }
+}
+
+/// The databases of one origin. Cheap to clone.
+#[derive(Clone)]
+pub struct IndexedDb {
+    store: Arc<dyn IdbStore>,
+    scope: IdbScope,
+}
+
+impl std::fmt::Debug for IndexedDb {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IndexedDb")
+            .field("scope", &self.scope)
+            .finish_non_exhaustive()
+    }
+}
+
+impl IndexedDb {
+    pub fn new(store: Arc<dyn IdbStore>, scope: IdbScope) -> Self {
+        Self { store, scope }
+    }
+
+    pub fn databases(&self) -> Result<Vec<DatabaseInfo>, IdbError> {
+        Ok(self.store.databases(&self.scope)?)
+    }
+
+    /// The database called `name`, if it exists.
+    pub fn database(&self, name: &str) -> Result<Option<DatabaseInfo>, IdbError> {
+        Ok(self.databases()?.into_iter().find(|db| db.name == name))
+    }
+
+    /// Deletes the database called `name`, returning its version (0 if it didn't exist).
+    pub fn delete_database(&self, name: &str) -> Result<u64, IdbError> {
+        let version = self.database(name)?.map_or(0, |db| db.version);
+        self.store.delete_database(&self.scope, name)?;
+        Ok(version)
+    }
+
+    /// Starts a transaction on the database called `db`, which need not exist yet if the
+    /// transaction is an upgrade.
+    pub fn transaction(&self, db: &str) -> Result<IdbTransaction, IdbError> {
+        Ok(IdbTransaction {
+            info: self.database(db)?,
+            db: db.to_string(),
+            store: Arc::clone(&self.store),
+            scope: self.scope.clone(),
+            undo: Vec::new(),
+        })
+    }
+}
+
+/// How to take back one change.
+enum Undo {
+    /// Put back the database definition as it was (`None`: the database didn't exist)
+    Database(Option<DatabaseInfo>),
+    /// Put back a record's previous value (`None`: it didn't exist)
+    Record {
+        store: String,
+        key: IdbKey,
+        previous: Option<String>,
+    },
+    /// Put back deleted records
+    Records { store: String, records: Vec<Record> },
+}
+
+/// A transaction on one database. Dropping it without [`commit`](Self::commit) aborts it.
+pub struct IdbTransaction {
+    store: Arc<dyn IdbStore>,
+    scope: IdbScope,
+    db: String,
+    info: Option<DatabaseInfo>,
+    undo: Vec<Undo>,
+}
+
+impl IdbTransaction {
+    /// The database's definition as this transaction sees it.
+    pub fn database(&self) -> Option<&DatabaseInfo> {
+        self.info.as_ref()
+    }
+
+    /// Moves the database to `version`, creating it if needed. For upgrade transactions.
+    pub fn set_version(&mut self, version: u64) -> Result<(), IdbError> {
+        let name = self.db.clone();
+        self.update_database(|info| match info {
+            Some(info) => info.version = version,
+            None => {
+                *info = Some(DatabaseInfo {
+                    name,
+                    version,
+                    stores: Vec::new(),
+                })
+            }
+        })
+    }
+
+    pub fn create_object_store(
+        &mut self,
+        name: &str,
+        key_path: Option<KeyPath>,
+        auto_increment: bool,
+    ) -> Result<(), IdbError> {
+        let info = self.info()?;
+        if info.stores.iter().any(|s| s.name == name) {
+            return Err(IdbError::Constraint(format!("object store {name} already exists")));
+        }
+        let empty_path = matches!(&key_path, Some(KeyPath::Single(p)) if p.is_empty());
+        if auto_increment && (empty_path || matches!(key_path, Some(KeyPath::Multiple(_)))) {
+            return Err(IdbError::InvalidState(
+                "auto-increment needs no key path or a non-empty single one".into(),
+            ));
+        }
+        self.update_database(|info| {
+            if let Some(info) = info {
+                info.stores.push(ObjectStoreInfo {
+                    name: name.to_string(),
+                    key_path,
+                    auto_increment,
+                    next_key: 1,
+                });
+            }
+        })
+    }
+
+    pub fn delete_object_store(&mut self, name: &str) -> Result<(), IdbError> {
+        self.store_info(name)?;
+        self.clear(name)?;
+        self.update_database(|info| {
+            if let Some(info) = info {
+                info.stores.retain(|s| s.name != name);
+            }
+        })
+    }
+
+    /// Stores `value` under `key`, or under a generated key for auto-increment stores. Fails when
+    /// the key exists and `overwrite` is false (`add()` rather than `put()`). Returns the key.
+    pub fn put(
+        &mut self,
+        store: &str,
+        key: Option<IdbKey>,
+        value: String,
+        overwrite: bool,
+    ) -> Result<IdbKey, IdbError> {
+        let info = self.store_info(store)?;
+        let (key, next_key) = match key {
+            Some(key) if !key.is_valid() => return Err(IdbError::Data("invalid key".into())),
+            // Explicit numeric keys push the generator past them.
+            Some(IdbKey::Number(n)) if info.auto_increment && n >= info.next_key as f64 => {
+                let next = (n.floor() + 1.0).min(MAX_GENERATED_KEY as f64 + 1.0) as u64;
+                (IdbKey::Number(n), Some(next))
+            }
+            Some(key) => (key, None),
+            None if info.auto_increment => {
+                if info.next_key > MAX_GENERATED_KEY {
+                    return Err(IdbError::Constraint("key generator exhausted".into()));
+                }
+                (IdbKey::Number(info.next_key as f64), Some(info.next_key + 1))
+            }
+            None => return Err(IdbError::Data(format!("object store {store} needs a key"))),
+        };
+
+        let previous = self.get(store, &KeyRange::only(key.clone()), Some(1))?.pop();
+        if previous.is_some() && !overwrite {
+            return Err(IdbError::Constraint("a record with this key already exists".into()));
+        }
+        if let Some(next_key) = next_key {
+            self.update_database(|info| {
+                if let Some(s) = info
+                    .as_mut()
+                    .and_then(|info| info.stores.iter_mut().find(|s| s.name == store))
+                {
+                    s.next_key = next_key;
+                }
+            })?;
+        }
+
+        let record = Record { key, value };
+        self.store.put_record(&self.scope, &self.db, store, &record)?;
+        self.undo.push(Undo::Record {
+            store: store.to_string(),
+            key: record.key.clone(),
+            previous: previous.map(|r| r.value),
+        });
+        Ok(record.key)
+    }
+
+    /// Records in `range`, at most `limit` of them.
+    pub fn get(&self, store: &str, range: &KeyRange, limit: Option<usize>) -> Result<Vec<Record>, IdbError> {
+        self.store_info(store)?;
+        if range.is_empty() || limit == Some(0) {
+            return Ok(Vec::new());
+        }
+        Ok(self.store.records(&self.scope, &self.db, store, range, limit)?)
+    }
+
+    pub fn count(&self, store: &str, range: &KeyRange) -> Result<usize, IdbError> {
+        self.store_info(store)?;
+        if range.is_empty() {
+            return Ok(0);
+        }
+        Ok(self.store.count(&self.scope, &self.db, store, range)?)
+    }
+
+    pub fn delete(&mut self, store: &str, range: &KeyRange) -> Result<(), IdbError> {
+        let records = self.get(store, range, None)?;
+        if records.is_empty() {
+            return Ok(());
+        }
+        self.store.delete_records(&self.scope, &self.db, store, range)?;
+        self.undo.push(Undo::Records {
+            store: store.to_string(),
+            records,
+        });
+        Ok(())
+    }
+
+    pub fn clear(&mut self, store: &str) -> Result<(), IdbError> {
+        self.delete(store, &KeyRange::all())
+    }
+
+    /// Keeps the changes.
+    pub fn commit(mut self) {
+        self.undo.clear();
+    }
+
+    /// Takes back the changes.
+    pub fn abort(mut self) -> Result<(), IdbError> {
+        self.rollback()
+    }
+
+    fn rollback(&mut self) -> Result<(), IdbError> {
+        while let Some(undo) = self.undo.pop() {
+            match undo {
+                Undo::Database(Some(info)) => {
+                    self.store.put_database(&self.scope, &info)?;
+                    self.info = Some(info);
+                }
+                Undo::Database(None) => {
+                    self.store.delete_database(&self.scope, &self.db)?;
+                    self.info = None;
+                }
+                Undo::Record {
+                    store,
+                    key,
+                    previous: Some(value),
+                } => self
+                    .store
+                    .put_record(&self.scope, &self.db, &store, &Record { key, value })?,
+                Undo::Record {
+                    store,
+                    key,
+                    previous: None,
+                } => self
+                    .store
+                    .delete_records(&self.scope, &self.db, &store, &KeyRange::only(key))?,
+                Undo::Records { store, records } => {
+                    for record in &records {
+                        self.store.put_record(&self.scope, &self.db, &store, record)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn info(&self) -> Result<&DatabaseInfo, IdbError> {
+        self.info
+            .as_ref()
+            .ok_or_else(|| IdbError::NotFound(format!("database {} does not exist", self.db)))
+    }
+
+    fn store_info(&self, store: &str) -> Result<ObjectStoreInfo, IdbError> {
+        self.info()?
+            .stores
+            .iter()
+            .find(|s| s.name == store)
+            .cloned()
+            .ok_or_else(|| IdbError::NotFound(format!("object store {store} does not exist")))
+    }
+
+    fn update_database(&mut self, update: impl FnOnce(&mut Option<DatabaseInfo>)) -> Result<(), IdbError> {
+        let previous = self.info.clone();
+        update(&mut self.info);
+        if let Some(info) = &self.info {
+            self.store.put_database(&self.scope, info)?;
+        }
+        self.undo.push(Undo::Database(previous));
+        Ok(())
+    }
+}
+
+impl Drop for IdbTransaction {
+    fn drop(&mut self) {
+        if let Err(e) = self.rollback() {
+            log::warn!("Rolling back IndexedDB transaction on {} failed: {e}", self.db);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indexed_db() -> IndexedDb {
+        let scope = IdbScope {
+            zone: ZoneId::new(),
+            partition: PartitionKey::None,
+            origin: url::Url::parse("https://app.example").unwrap().origin(),
+        };
+        IndexedDb::new(Arc::new(InMemoryIdbStore::new()), scope)
+    }
+
+    fn values(records: Vec<Record>) -> Vec<String> {
+        records.into_iter().map(|r| r.value).collect()
+    }
+
+    #[test]
+    fn upgrade_then_read_and_write() {
+        let idb = indexed_db();
+        let mut tx = idb.transaction("app").unwrap();
+        tx.set_version(1).unwrap();
+        tx.create_object_store("notes", None, true).unwrap();
+        assert_eq!(
+            tx.create_object_store("notes", None, false),
+            Err(IdbError::Constraint("object store notes already exists".into()))
+        );
+        tx.commit();
+
+        let mut tx = idb.transaction("app").unwrap();
+        assert_eq!(tx.put("notes", None, "a".into(), true), Ok(IdbKey::Number(1.0)));
+        assert_eq!(
+            tx.put("notes", Some(IdbKey::Number(7.5)), "b".into(), true),
+            Ok(IdbKey::Number(7.5))
+        );
+        assert_eq!(tx.put("notes", None, "c".into(), true), Ok(IdbKey::Number(8.0)));
+        assert!(tx.put("notes", Some(IdbKey::Number(1.0)), "x".into(), false).is_err());
+        tx.commit();
+
+        let tx = idb.transaction("app").unwrap();
+        let range = KeyRange {
+            lower: Some(IdbKey::Number(1.0)),
+            lower_open: true,
+            ..KeyRange::default()
+        };
+        assert_eq!(values(tx.get("notes", &range, None).unwrap()), vec!["b", "c"]);
+        assert_eq!(tx.count("notes", &KeyRange::all()), Ok(3));
+        assert_eq!(idb.database("app").unwrap().map(|db| db.version), Some(1));
+    }
+
+    #[test]
+    fn aborted_and_dropped_transactions_roll_back() {
+        let idb = indexed_db();
+        let mut tx = idb.transaction("app").unwrap();
+        tx.set_version(1).unwrap();
+        tx.create_object_store("kv", None, false).unwrap();
+        tx.put("kv", Some(IdbKey::String("a".into())), "1".into(), true)
+            .unwrap();
+        tx.commit();
+
+        let mut tx = idb.transaction("app").unwrap();
+        tx.put("kv", Some(IdbKey::String("a".into())), "2".into(), true)
+            .unwrap();
+        tx.put("kv", Some(IdbKey::String("b".into())), "3".into(), true)
+            .unwrap();
+        tx.abort().unwrap();
+
+        let mut tx = idb.transaction("app").unwrap();
+        tx.clear("kv").unwrap();
+        tx.delete_object_store("kv").unwrap();
+        drop(tx);
+
+        let tx = idb.transaction("app").unwrap();
+        assert_eq!(values(tx.get("kv", &KeyRange::all(), None).unwrap()), vec!["1"]);
+        drop(tx);
+
+        // Aborting the upgrade that created a database deletes it again.
+        let mut tx = idb.transaction("other").unwrap();
+        tx.set_version(1).unwrap();
+        tx.abort().unwrap();
+        assert_eq!(idb.database("other"), Ok(None));
+    }
+}
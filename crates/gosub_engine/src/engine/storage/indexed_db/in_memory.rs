@@ -0,0 +1,111 @@
+use super::{DatabaseInfo, IdbKey, IdbScope, IdbStore, KeyRange, Record};
+use anyhow::Result;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashMap};
+
+/// Records of one object store, by encoded key.
+type StoreRecords = BTreeMap<Vec<u8>, (IdbKey, String)>;
+
+#[derive(Default)]
+struct Database {
+    info: Option<DatabaseInfo>,
+    stores: HashMap<String, StoreRecords>,
+}
+
+/// In-memory IndexedDB (no persistence). Used as a default when no store is defined by the UA.
+#[derive(Default)]
+pub struct InMemoryIdbStore {
+    databases: Mutex<HashMap<(IdbScope, String), Database>>,
+}
+
+impl InMemoryIdbStore {
+    /// Creates a new instance of the in-memory IndexedDB store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl IdbStore for InMemoryIdbStore {
+    fn databases(&self, scope: &IdbScope) -> Result<Vec<DatabaseInfo>> {
+        let guard = self.databases.lock();
+        let mut infos: Vec<DatabaseInfo> = guard
+            .iter()
+            .filter(|((s, _), _)| s == scope)
+            .filter_map(|(_, db)| db.info.clone())
+            .collect();
+        infos.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(infos)
+    }
+
+    fn put_database(&self, scope: &IdbScope, info: &DatabaseInfo) -> Result<()> {
+        let mut guard = self.databases.lock();
+        let db = guard.entry((scope.clone(), info.name.clone())).or_default();
+        db.stores
+            .retain(|name, _| info.stores.iter().any(|store| store.name == *name));
+        db.info = Some(info.clone());
+        Ok(())
+    }
+
+    fn delete_database(&self, scope: &IdbScope, name: &str) -> Result<()> {
+        self.databases.lock().remove(&(scope.clone(), name.to_string()));
+        Ok(())
+    }
+
+    fn records(
+        &self,
+        scope: &IdbScope,
+        db: &str,
+        store: &str,
+        range: &KeyRange,
+        limit: Option<usize>,
+    ) -> Result<Vec<Record>> {
+        // `BTreeMap::range` panics on inverted bounds.
+        if range.is_empty() {
+            return Ok(Vec::new());
+        }
+        let guard = self.databases.lock();
+        let Some(records) = guard
+            .get(&(scope.clone(), db.to_string()))
+            .and_then(|db| db.stores.get(store))
+        else {
+            return Ok(Vec::new());
+        };
+        Ok(records
+            .range(range.bounds())
+            .take(limit.unwrap_or(usize::MAX))
+            .map(|(_, (key, value))| Record {
+                key: key.clone(),
+                value: value.clone(),
+            })
+            .collect())
+    }
+
+    fn put_record(&self, scope: &IdbScope, db: &str, store: &str, record: &Record) -> Result<()> {
+        let mut guard = self.databases.lock();
+        guard
+            .entry((scope.clone(), db.to_string()))
+            .or_default()
+            .stores
+            .entry(store.to_string())
+            .or_default()
+            .insert(record.key.encode(), (record.key.clone(), record.value.clone()));
+        Ok(())
+    }
+
+    fn delete_records(&self, scope: &IdbScope, db: &str, store: &str, range: &KeyRange) -> Result<()> {
+        if range.is_empty() {
+            return Ok(());
+        }
+        let mut guard = self.databases.lock();
+        if let Some(records) = guard
+            .get_mut(&(scope.clone(), db.to_string()))
+            .and_then(|db| db.stores.get_mut(store))
+        {
+            let doomed: Vec<Vec<u8>> = records.range(range.bounds()).map(|(k, _)| k.clone()).collect();
+            for key in doomed {
+                records.remove(&key);
+            }
+        }
+        Ok(())
+    }
+}
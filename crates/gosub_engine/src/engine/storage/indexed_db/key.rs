@@ -0,0 +1,222 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::ops::Bound;
+
+/// An IndexedDB key. Scripts pass keys as JSON: numbers and strings as themselves, dates as
+/// `{"date": ms}`, binary keys as `{"binary": [bytes]}` and arrays as arrays of keys.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum IdbKey {
+    Number(f64),
+    String(String),
+    Date { date: f64 },
+    Binary { binary: Vec<u8> },
+    Array(Vec<IdbKey>),
+}
+
+const TAG_END: u8 = 0x00;
+const TAG_NUMBER: u8 = 0x10;
+const TAG_DATE: u8 = 0x20;
+const TAG_STRING: u8 = 0x30;
+const TAG_BINARY: u8 = 0x40;
+const TAG_ARRAY: u8 = 0x50;
+/// Follows a 0x00 byte inside a string or binary key, so it isn't read as the terminator
+const ESCAPE: u8 = 0xFF;
+
+impl IdbKey {
+    /// The key as bytes that sort (with a plain byte comparison) the way IndexedDB orders keys:
+    /// numbers < dates < strings < binary < arrays, strings by UTF-16 code unit, and arrays
+    /// element by element with shorter prefixes first. Backends store and range-scan this form.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode_into(&mut out);
+        out
+    }
+
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        match self {
+            IdbKey::Number(n) => {
+                out.push(TAG_NUMBER);
+                encode_f64(*n, out);
+            }
+            IdbKey::Date { date } => {
+                out.push(TAG_DATE);
+                encode_f64(*date, out);
+            }
+            IdbKey::String(s) => {
+                out.push(TAG_STRING);
+                let bytes: Vec<u8> = s.encode_utf16().flat_map(u16::to_be_bytes).collect();
+                encode_escaped(&bytes, out);
+            }
+            IdbKey::Binary { binary } => {
+                out.push(TAG_BINARY);
+                encode_escaped(binary, out);
+            }
+            IdbKey::Array(items) => {
+                out.push(TAG_ARRAY);
+                for item in items {
+                    item.encode_into(out);
+                }
+                out.push(TAG_END);
+            }
+        }
+    }
+
+    /// Whether the key is valid: no NaN numbers or invalid dates, in arrays either.
+    pub fn is_valid(&self) -> bool {
+        match self {
+            IdbKey::Number(n) | IdbKey::Date { date: n } => !n.is_nan(),
+            IdbKey::String(_) | IdbKey::Binary { .. } => true,
+            IdbKey::Array(items) => items.iter().all(IdbKey::is_valid),
+        }
+    }
+}
+
+/// Flips the bits of an f64 so that its big-endian bytes sort numerically.
+fn encode_f64(n: f64, out: &mut Vec<u8>) {
+    // -0 and +0 are the same key.
+    let n = if n == 0.0 { 0.0 } else { n };
+    let bits = n.to_bits();
+    let sortable = if bits >> 63 == 1 { !bits } else { bits | (1 << 63) };
+    out.extend_from_slice(&sortable.to_be_bytes());
+}
+
+fn encode_escaped(bytes: &[u8], out: &mut Vec<u8>) {
+    for &b in bytes {
+        out.push(b);
+        if b == TAG_END {
+            out.push(ESCAPE);
+        }
+    }
+    out.push(TAG_END);
+}
+
+impl PartialEq for IdbKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for IdbKey {}
+
+impl PartialOrd for IdbKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for IdbKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.encode().cmp(&other.encode())
+    }
+}
+
+/// An `IDBKeyRange`, or no range at all (every key) when both bounds are missing.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyRange {
+    pub lower: Option<IdbKey>,
+    pub upper: Option<IdbKey>,
+    #[serde(default)]
+    pub lower_open: bool,
+    #[serde(default)]
+    pub upper_open: bool,
+}
+
+impl KeyRange {
+    /// Every key.
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Just `key`.
+    pub fn only(key: IdbKey) -> Self {
+        Self {
+            lower: Some(key.clone()),
+            upper: Some(key),
+            lower_open: false,
+            upper_open: false,
+        }
+    }
+
+    /// The range over encoded keys.
+    pub fn bounds(&self) -> (Bound<Vec<u8>>, Bound<Vec<u8>>) {
+        let bound = |key: &Option<IdbKey>, open: bool| match key {
+            None => Bound::Unbounded,
+            Some(key) if open => Bound::Excluded(key.encode()),
+            Some(key) => Bound::Included(key.encode()),
+        };
+        (bound(&self.lower, self.lower_open), bound(&self.upper, self.upper_open))
+    }
+
+    /// Whether the range can't contain anything (bounds the wrong way round, or an open range
+    /// around a single key). A `BTreeMap` range would panic on these.
+    pub fn is_empty(&self) -> bool {
+        match (&self.lower, &self.upper) {
+            (Some(lower), Some(upper)) => match lower.cmp(upper) {
+                Ordering::Greater => true,
+                Ordering::Equal => self.lower_open || self.upper_open,
+                Ordering::Less => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s(v: &str) -> IdbKey {
+        IdbKey::String(v.into())
+    }
+
+    #[test]
+    fn keys_sort_in_indexeddb_order() {
+        let ordered = [
+            IdbKey::Number(f64::NEG_INFINITY),
+            IdbKey::Number(-1.5),
+            IdbKey::Number(0.0),
+            IdbKey::Number(2.0),
+            IdbKey::Number(10.0),
+            IdbKey::Date { date: -5.0 },
+            IdbKey::Date { date: 1.0 },
+            s(""),
+            s("a\0"),
+            s("a\0b"),
+            s("ab"),
+            s("b"),
+            IdbKey::Binary { binary: vec![0] },
+            IdbKey::Binary { binary: vec![0, 0] },
+            IdbKey::Binary { binary: vec![1] },
+            IdbKey::Array(vec![]),
+            IdbKey::Array(vec![IdbKey::Number(1.0)]),
+            IdbKey::Array(vec![IdbKey::Number(1.0), s("x")]),
+            IdbKey::Array(vec![s("a")]),
+        ];
+        for pair in ordered.windows(2) {
+            assert!(pair[0] < pair[1], "{:?} < {:?}", pair[0], pair[1]);
+        }
+        assert_eq!(IdbKey::Number(-0.0), IdbKey::Number(0.0));
+        assert!(!IdbKey::Array(vec![IdbKey::Number(f64::NAN)]).is_valid());
+    }
+
+    #[test]
+    fn keys_and_ranges_from_json() {
+        let key: IdbKey = serde_json::from_str(r#"[1, "a", {"date": 5}, {"binary": [1, 2]}]"#).unwrap();
+        assert_eq!(
+            key,
+            IdbKey::Array(vec![
+                IdbKey::Number(1.0),
+                s("a"),
+                IdbKey::Date { date: 5.0 },
+                IdbKey::Binary { binary: vec![1, 2] },
+            ])
+        );
+
+        let range: KeyRange = serde_json::from_str(r#"{"lower": 1, "upper": 1, "lowerOpen": true}"#).unwrap();
+        assert!(range.is_empty());
+        assert!(!KeyRange::only(s("a")).is_empty());
+        assert_eq!(KeyRange::all().bounds(), (Bound::Unbounded, Bound::Unbounded));
+    }
+}
@@ -0,0 +1,207 @@
+use super::{DatabaseInfo, IdbKey, IdbScope, IdbStore, KeyRange, Record};
+use anyhow::Result;
+use r2d2::{Pool, PooledConnection};
+use r2d2_sqlite::rusqlite::types::Value;
+use r2d2_sqlite::rusqlite::{params, params_from_iter, OpenFlags};
+use r2d2_sqlite::SqliteConnectionManager;
+use std::ops::Bound;
+
+/// SQLite-based IndexedDB implementation. Keys are stored in their sortable encoding
+/// ([`IdbKey::encode`]), so SQLite's BLOB ordering is the IndexedDB key order.
+pub struct SqliteIdbStore {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+impl SqliteIdbStore {
+    /// Creates a new SQLite IndexedDB store with the specified database file path.
+    pub fn new(path: &str) -> Result<Self> {
+        let manager = SqliteConnectionManager::file(path)
+            .with_flags(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE | OpenFlags::SQLITE_OPEN_URI)
+            .with_init(|c| {
+                c.busy_timeout(std::time::Duration::from_millis(500))?;
+                c.pragma_update(None, "journal_mode", "WAL")?;
+                c.execute_batch(
+                    "CREATE TABLE IF NOT EXISTS idb_databases (
+                        zone TEXT NOT NULL,
+                        partition TEXT NOT NULL,
+                        origin TEXT NOT NULL,
+                        name TEXT NOT NULL,
+                        info TEXT NOT NULL,
+                        PRIMARY KEY(zone, partition, origin, name)
+                    );
+                    CREATE TABLE IF NOT EXISTS idb_records (
+                        zone TEXT NOT NULL,
+                        partition TEXT NOT NULL,
+                        origin TEXT NOT NULL,
+                        db TEXT NOT NULL,
+                        store TEXT NOT NULL,
+                        key BLOB NOT NULL,
+                        key_json TEXT NOT NULL,
+                        value TEXT NOT NULL,
+                        PRIMARY KEY(zone, partition, origin, db, store, key)
+                    );",
+                )?;
+                Ok(())
+            });
+
+        let pool = Pool::builder()
+            .max_size(16)
+            .connection_timeout(std::time::Duration::from_secs(5))
+            .build(manager)?;
+
+        Ok(Self { pool })
+    }
+
+    fn conn(&self) -> Result<PooledConnection<SqliteConnectionManager>> {
+        Ok(self.pool.get()?)
+    }
+}
+
+/// The scope's columns: zone, partition and origin.
+fn scope_values(scope: &IdbScope) -> [String; 3] {
+    [
+        scope.zone.to_string(),
+        scope.partition.as_storage_key(),
+        scope.origin.ascii_serialization(),
+    ]
+}
+
+/// The `WHERE` clause selecting the records of one store in `range`, with its parameters.
+fn range_filter(scope: &IdbScope, db: &str, store: &str, range: &KeyRange) -> (String, Vec<Value>) {
+    let mut sql = "zone=?1 AND partition=?2 AND origin=?3 AND db=?4 AND store=?5".to_string();
+    let mut values: Vec<Value> = scope_values(scope).into_iter().map(Value::from).collect();
+    values.push(Value::from(db.to_string()));
+    values.push(Value::from(store.to_string()));
+
+    let (lower, upper) = range.bounds();
+    for (bound, inclusive, exclusive) in [(lower, ">=", ">"), (upper, "<=", "<")] {
+        let (op, key) = match bound {
+            Bound::Included(key) => (inclusive, key),
+            Bound::Excluded(key) => (exclusive, key),
+            Bound::Unbounded => continue,
+        };
+        values.push(Value::Blob(key));
+        sql.push_str(&format!(" AND key {op} ?{}", values.len()));
+    }
+    (sql, values)
+}
+
+impl IdbStore for SqliteIdbStore {
+    fn databases(&self, scope: &IdbScope) -> Result<Vec<DatabaseInfo>> {
+        let conn = self.conn()?;
+        let mut stmt =
+            conn.prepare("SELECT info FROM idb_databases WHERE zone=?1 AND partition=?2 AND origin=?3 ORDER BY name")?;
+        let rows = stmt.query_map(params_from_iter(scope_values(scope)), |row| row.get::<_, String>(0))?;
+        let mut infos = Vec::new();
+        for info in rows {
+            infos.push(serde_json::from_str(&info?)?);
+        }
+        Ok(infos)
+    }
+
+    fn put_database(&self, scope: &IdbScope, info: &DatabaseInfo) -> Result<()> {
+        let [zone, partition, origin] = scope_values(scope);
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO idb_databases(zone,partition,origin,name,info) VALUES (?1,?2,?3,?4,?5)
+             ON CONFLICT(zone,partition,origin,name) DO UPDATE SET info=excluded.info",
+            params![zone, partition, origin, info.name, serde_json::to_string(info)?],
+        )?;
+        // Records of object stores the database no longer has go with them.
+        let stores = serde_json::to_string(&info.stores.iter().map(|s| &s.name).collect::<Vec<_>>())?;
+        tx.execute(
+            "DELETE FROM idb_records WHERE zone=?1 AND partition=?2 AND origin=?3 AND db=?4
+             AND store NOT IN (SELECT value FROM json_each(?5))",
+            params![zone, partition, origin, info.name, stores],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn delete_database(&self, scope: &IdbScope, name: &str) -> Result<()> {
+        let [zone, partition, origin] = scope_values(scope);
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            "DELETE FROM idb_records WHERE zone=?1 AND partition=?2 AND origin=?3 AND db=?4",
+            params![zone, partition, origin, name],
+        )?;
+        tx.execute(
+            "DELETE FROM idb_databases WHERE zone=?1 AND partition=?2 AND origin=?3 AND name=?4",
+            params![zone, partition, origin, name],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn records(
+        &self,
+        scope: &IdbScope,
+        db: &str,
+        store: &str,
+        range: &KeyRange,
+        limit: Option<usize>,
+    ) -> Result<Vec<Record>> {
+        let (filter, values) = range_filter(scope, db, store, range);
+        // SQLite reads a negative LIMIT as "no limit".
+        let limit = limit.map_or(-1, |l| i64::try_from(l).unwrap_or(i64::MAX));
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT key_json, value FROM idb_records WHERE {filter} ORDER BY key LIMIT {limit}"
+        ))?;
+        let rows = stmt.query_map(params_from_iter(values), |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut records = Vec::new();
+        for row in rows {
+            let (key, value) = row?;
+            let key: IdbKey = serde_json::from_str(&key)?;
+            records.push(Record { key, value });
+        }
+        Ok(records)
+    }
+
+    fn put_record(&self, scope: &IdbScope, db: &str, store: &str, record: &Record) -> Result<()> {
+        let [zone, partition, origin] = scope_values(scope);
+        let conn = self.conn()?;
+        conn.execute(
+            "INSERT INTO idb_records(zone,partition,origin,db,store,key,key_json,value)
+             VALUES (?1,?2,?3,?4,?5,?6,?7,?8)
+             ON CONFLICT(zone,partition,origin,db,store,key) DO UPDATE
+             SET key_json=excluded.key_json, value=excluded.value",
+            params![
+                zone,
+                partition,
+                origin,
+                db,
+                store,
+                record.key.encode(),
+                serde_json::to_string(&record.key)?,
+                record.value
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn delete_records(&self, scope: &IdbScope, db: &str, store: &str, range: &KeyRange) -> Result<()> {
+        let (filter, values) = range_filter(scope, db, store, range);
+        let conn = self.conn()?;
+        conn.execute(
+            &format!("DELETE FROM idb_records WHERE {filter}"),
+            params_from_iter(values),
+        )?;
+        Ok(())
+    }
+
+    fn count(&self, scope: &IdbScope, db: &str, store: &str, range: &KeyRange) -> Result<usize> {
+        let (filter, values) = range_filter(scope, db, store, range);
+        let conn = self.conn()?;
+        let count = conn.query_row(
+            &format!("SELECT COUNT(*) FROM idb_records WHERE {filter}"),
+            params_from_iter(values),
+            |row| row.get::<_, i64>(0),
+        )?;
+        Ok(usize::try_from(count).unwrap_or(0))
+    }
+}
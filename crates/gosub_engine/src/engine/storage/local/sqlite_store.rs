@@ -50,11 +50,7 @@ impl LocalStore for SqliteLocalStore {
         Ok(Arc::new(SqliteLocalArea {
             pool: self.pool.clone(),
             zone,
-            partition: match part {
-                PartitionKey::None => "".to_string(),
-                PartitionKey::TopLevel(o) => format!("top:{}", o.ascii_serialization()),
-                PartitionKey::Custom(s) => s.to_string(),
-            },
+            partition: part.as_storage_key(),
             origin: origin.ascii_serialization(),
         }))
     }
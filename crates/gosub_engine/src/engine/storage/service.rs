@@ -1,5 +1,6 @@
 use super::area::{LocalStore, SessionStore, StorageArea};
 use super::event::{StorageEvent, StorageScope};
+use super::indexed_db::{IdbScope, IdbStore, InMemoryIdbStore, IndexedDb};
 use super::types::PartitionKey;
 use crate::engine::DEFAULT_CHANNEL_CAPACITY;
 use crate::tab::TabId;
@@ -39,6 +40,7 @@ impl StorageBus {
 pub struct StorageService {
     local: Arc<dyn LocalStore>,
     session: Arc<dyn SessionStore>,
+    indexed_db: Arc<dyn IdbStore>,
     bus: Arc<StorageBus>,
 }
 
@@ -53,10 +55,17 @@ impl StorageService {
         Self {
             local,
             session,
+            indexed_db: Arc::new(InMemoryIdbStore::new()),
             bus: Arc::new(StorageBus::default()),
         }
     }
 
+    /// Uses `store` for IndexedDB instead of the in-memory default.
+    pub fn with_indexed_db(mut self, store: Arc<dyn IdbStore>) -> Self {
+        self.indexed_db = store;
+        self
+    }
+
     pub fn subscribe(&self) -> Subscription {
         self.bus.subscribe()
     }
@@ -84,6 +93,19 @@ impl StorageService {
         ))
     }
 
+    /// The IndexedDB databases of `origin`. Opaque origins get none.
+    pub fn indexed_db_for(&self, zone: ZoneId, part: &PartitionKey, origin: &url::Origin) -> Option<IndexedDb> {
+        if !origin.is_tuple() {
+            return None;
+        }
+        let scope = IdbScope {
+            zone,
+            partition: part.clone(),
+            origin: origin.clone(),
+        };
+        Some(IndexedDb::new(Arc::clone(&self.indexed_db), scope))
+    }
+
     pub fn drop_tab(&self, zone: ZoneId, tab: TabId) {
         self.session.drop_tab(zone, tab);
     }
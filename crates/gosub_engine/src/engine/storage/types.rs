@@ -32,6 +32,15 @@ impl PartitionKey {
         let url_str = format!("https://zone-{zone_id}.local");
         Self::from_str(&url_str)
     }
+
+    /// The key as a string, for backends that store it in a column.
+    pub fn as_storage_key(&self) -> String {
+        match self {
+            PartitionKey::None => "".to_string(),
+            PartitionKey::TopLevel(o) => format!("top:{}", o.ascii_serialization()),
+            PartitionKey::Custom(s) => s.to_string(),
+        }
+    }
 }
 
 /// Partitioning policy for determining how to compute the partition key.
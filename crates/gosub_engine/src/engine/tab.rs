@@ -1,14 +1,22 @@
+mod frames;
 mod handle;
+mod history;
+mod media_fetch;
 mod options;
+mod script_fetch;
 mod scroll;
 pub mod services;
+mod session;
 mod sink;
 mod state;
 #[allow(clippy::module_inception)]
 mod tab;
+pub(crate) mod watchdog;
 mod worker;
 
 pub use handle::TabHandle;
+pub use history::{HistoryEntry, ScrollRestoration, SessionHistory};
+pub use session::{SessionEntry, SessionState};
 pub use tab::*;
 
 pub use options::TabCookieJar;
@@ -0,0 +1,324 @@
+//! Child frames: a browsing context of its own for every `<iframe>` in the tab's document.
+//!
+//! When a document commits, the worker loads each iframe's `src` into a child
+//! [`BrowsingContext`] that shares the tab's media store. A loaded frame is laid out at the size
+//! of the iframe's content box and painted as a scene; the parent paints those commands inside the
+//! iframe element. Pointer input over an iframe is mapped into the frame's viewport and handled by
+//! the child context, and a link clicked inside a frame loads in that frame.
+//!
+//! A `loading=lazy` iframe is left unloaded until the parent's browsing context reports that it
+//! came near the viewport (see [`FrameSet::load_deferred`]).
+//!
+//! Frames are one level deep: iframes inside a child document are not loaded.
+
+use crate::cookies::{CookieJarHandle, SameSiteContext};
+use crate::engine::errors::NavigationError;
+use crate::engine::resource_pipeline::ResourcePipelines;
+use crate::engine::types::{IoChannel, NavigationId, RequestId};
+use crate::engine::{BrowsingContext, UaPolicy};
+use crate::html::{EngineDocument, RenderConfiguration};
+use crate::net::blocking::{ContentBlocker, RequestType};
+use crate::net::mixed_content::MixedContentGuard;
+use crate::net::req_ref_tracker::{RequestReference, RequestReferenceMap, REF_REGISTRY};
+use crate::net::types::{FetchRequest, Initiator, Priority, ResourceKind};
+use crate::net::{route_response_for, submit_to_io, FetchError, RequestDestination, RoutedOutcome};
+use crate::storage::PartitionKey;
+use crate::tab::TabId;
+use crate::util::spawn_named;
+use crate::zone::ZoneId;
+use gosub_css3::media::MediaEnvironment;
+use gosub_shared::node::NodeId;
+use http::{HeaderMap, Method};
+use parking_lot::RwLock;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+/// A frame document that finished loading (or failed to).
+pub(crate) struct FrameLoad<C: RenderConfiguration> {
+    generation: u64,
+    node: NodeId,
+    result: Result<Arc<EngineDocument<C>>, NavigationError>,
+}
+
+/// One `<iframe>` of the tab's document.
+pub(crate) struct ChildFrame<C: RenderConfiguration> {
+    /// The iframe element in the parent document
+    pub node: NodeId,
+    /// URL the frame is showing or loading
+    pub url: Url,
+    /// Context of the frame document; `None` until it has loaded
+    pub context: Option<BrowsingContext<C>>,
+    /// A load for `url` is in flight
+    loading: bool,
+    /// A `loading=lazy` frame whose load hasn't started yet
+    deferred: bool,
+    cancel: CancellationToken,
+}
+
+/// What a frame load needs from the tab, cloned into each load task.
+#[derive(Clone)]
+pub(crate) struct FrameFetcher {
+    pub tab_id: TabId,
+    pub zone_id: ZoneId,
+    /// Storage partition of the tab, which its requests are cached under
+    pub partition: PartitionKey,
+    /// Frame requests are attributed to the tab, so their network events reach the UA
+    pub request_reference_map: Arc<RwLock<RequestReferenceMap>>,
+    pub io_tx: IoChannel,
+    pub cookie_jar: CookieJarHandle,
+    pub accept_language: Option<String>,
+    pub max_document_bytes: usize,
+    pub content_blocker: Option<Arc<ContentBlocker>>,
+    /// What the `media` of the frames' stylesheets is matched against
+    pub media: MediaEnvironment,
+    /// Checks insecure loads of secure documents
+    pub mixed_content: MixedContentGuard,
+}
+
+/// The child frames of the current document.
+pub(crate) struct FrameSet<C: RenderConfiguration> {
+    frames: Vec<ChildFrame<C>>,
+    /// Bumped whenever the parent document changes, so loads for the old one are dropped.
+    generation: u64,
+    tx: mpsc::UnboundedSender<FrameLoad<C>>,
+}
+
+impl<C: RenderConfiguration> FrameSet<C> {
+    /// An empty set, plus the receiver the worker polls for finished loads.
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<FrameLoad<C>>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let set = Self {
+            frames: Vec::new(),
+            generation: 0,
+            tx,
+        };
+        (set, rx)
+    }
+
+    /// Drops every frame and cancels their loads.
+    pub fn clear(&mut self) {
+        for frame in self.frames.drain(..) {
+            frame.cancel.cancel();
+        }
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// Cancels loads still in flight. Frames that already loaded keep their document.
+    pub fn stop(&mut self) {
+        for frame in &mut self.frames {
+            frame.cancel.cancel();
+            frame.loading = false;
+        }
+    }
+
+    /// Whether any frame is still loading.
+    pub fn is_loading(&self) -> bool {
+        self.frames.iter().any(|f| f.loading)
+    }
+
+    /// Replaces the frames with `frames` (iframe element, URL and whether it loads lazily),
+    /// starting a load for each one that isn't lazy. `top_level` is the URL of the tab's
+    /// document, for third-party cookie rules.
+    pub fn load_all(&mut self, frames: Vec<(NodeId, Url, bool)>, top_level: &Url, fetcher: &FrameFetcher) {
+        self.clear();
+        for (node, url, lazy) in frames {
+            let cancel = if lazy {
+                CancellationToken::new()
+            } else {
+                self.spawn_load(node, url.clone(), top_level, fetcher)
+            };
+            self.frames.push(ChildFrame {
+                node,
+                url,
+                context: None,
+                loading: !lazy,
+                deferred: lazy,
+                cancel,
+            });
+        }
+    }
+
+    /// Starts loading lazy frame `node`, unless its load has started already.
+    pub fn load_deferred(&mut self, node: NodeId, top_level: &Url, fetcher: &FrameFetcher) {
+        let Some(index) = self.frames.iter().position(|f| f.node == node && f.deferred) else {
+            return;
+        };
+        let cancel = self.spawn_load(node, self.frames[index].url.clone(), top_level, fetcher);
+        let frame = &mut self.frames[index];
+        frame.loading = true;
+        frame.deferred = false;
+        frame.cancel = cancel;
+    }
+
+    /// Loads `url` into the frame at `node`, replacing its document once the load finishes.
+    pub fn navigate(&mut self, node: NodeId, url: Url, top_level: &Url, fetcher: &FrameFetcher) {
+        let Some(index) = self.frames.iter().position(|f| f.node == node) else {
+            return;
+        };
+        self.frames[index].cancel.cancel();
+        let cancel = self.spawn_load(node, url.clone(), top_level, fetcher);
+        let frame = &mut self.frames[index];
+        frame.url = url;
+        frame.loading = true;
+        frame.deferred = false;
+        frame.cancel = cancel;
+    }
+
+    /// Takes a finished load. On success the frame gets its document in a context made by
+    /// `new_context` (or keeps its existing one) and is returned; stale and failed loads return
+    /// `None`.
+    pub fn finish_load(
+        &mut self,
+        load: FrameLoad<C>,
+        new_context: impl FnOnce() -> BrowsingContext<C>,
+    ) -> Option<&mut ChildFrame<C>> {
+        if load.generation != self.generation {
+            return None;
+        }
+        let frame = self.frames.iter_mut().find(|f| f.node == load.node)?;
+        frame.loading = false;
+        match load.result {
+            Ok(doc) => {
+                let context = frame.context.get_or_insert_with(new_context);
+                context.set_document(doc);
+                Some(frame)
+            }
+            Err(e) => {
+                log::warn!("Frame {} failed to load: {e}", frame.url);
+                None
+            }
+        }
+    }
+
+    pub fn get_mut(&mut self, node: NodeId) -> Option<&mut ChildFrame<C>> {
+        self.frames.iter_mut().find(|f| f.node == node)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut ChildFrame<C>> {
+        self.frames.iter_mut()
+    }
+
+    fn spawn_load(&self, node: NodeId, url: Url, top_level: &Url, fetcher: &FrameFetcher) -> CancellationToken {
+        let cancel = CancellationToken::new();
+        let tx = self.tx.clone();
+        let generation = self.generation;
+        let task_cancel = cancel.clone();
+        let top_level = top_level.clone();
+        let fetcher = fetcher.clone();
+        spawn_named("frame-fetcher", async move {
+            let result = fetch_frame_document::<C>(fetcher, url, top_level, task_cancel.clone()).await;
+            if !task_cancel.is_cancelled() {
+                let _ = tx.send(FrameLoad {
+                    generation,
+                    node,
+                    result,
+                });
+            }
+        });
+        cancel
+    }
+}
+
+/// Fetches and parses the document for a frame. Cookies follow the third-party rules for
+/// `top_level`; unlike a tab navigation there are no download or viewer decisions, anything
+/// that isn't an HTML document fails the load.
+async fn fetch_frame_document<C: RenderConfiguration>(
+    fetcher: FrameFetcher,
+    url: Url,
+    top_level: Url,
+    cancel: CancellationToken,
+) -> Result<Arc<EngineDocument<C>>, NavigationError> {
+    if let Some(blocker) = &fetcher.content_blocker {
+        if let Some(rule) = blocker.check(&url, RequestType::Subdocument, Some(&top_level)) {
+            return Err(NavigationError::Blocked(rule.to_string()));
+        }
+    }
+    let Some(url) = fetcher.mixed_content.check(&top_level, &url, ResourceKind::Document) else {
+        return Err(NavigationError::MixedContent(url));
+    };
+
+    let mut headers = HeaderMap::new();
+    fetcher.cookie_jar.add_request_cookies(
+        &mut headers,
+        &url,
+        Some(&top_level),
+        SameSiteContext::for_subrequest(&url, &top_level),
+    );
+    if let Some(val) = fetcher.accept_language.as_deref().and_then(|l| l.parse().ok()) {
+        headers.insert(http::header::ACCEPT_LANGUAGE, val);
+    }
+
+    let nav_id = NavigationId::new();
+    fetcher
+        .request_reference_map
+        .write()
+        .insert(RequestReference::Navigation(nav_id), fetcher.tab_id);
+    let req_id = RequestId::new();
+    REF_REGISTRY.register_request(req_id, ResourceKind::Document, Initiator::Parser);
+    let req = FetchRequest::builder(Method::GET, url.clone())
+        .with_reference(REF_REGISTRY.to_net(RequestReference::Navigation(nav_id)))
+        .with_req_id(req_id)
+        .with_headers(headers)
+        .with_priority(Priority::Normal)
+        .with_kind(ResourceKind::Document.to_net())
+        .with_initiator(Initiator::Parser.to_net())
+        .with_streaming(false)
+        .with_auto_decode(true)
+        .build();
+
+    let pending = submit_to_io(
+        fetcher.zone_id,
+        fetcher.partition.clone(),
+        req.clone(),
+        fetcher.io_tx.clone(),
+        Some(cancel.clone()),
+    )
+    .await
+    .map_err(|_| NavigationError::NetworkError("I/O channel closed".into()))?;
+    let handle = pending.handle().clone();
+    let fetch_result = match pending.result().await {
+        Ok(result) => result,
+        Err(FetchError::Aborted) => return Err(NavigationError::Cancelled("Frame load cancelled".into())),
+        Err(_) => return Err(NavigationError::Cancelled("Response channel closed".into())),
+    };
+    if let Some(meta) = fetch_result.meta() {
+        fetcher
+            .cookie_jar
+            .write()
+            .store_response_cookies(&meta.final_url, &meta.headers, Some(&top_level));
+    }
+
+    let policy = UaPolicy {
+        enable_sniffing: true,
+        enable_sniffing_navigation_upgrade: false,
+        enable_pdf_viewer: false,
+        allow_download_without_user_activation: false,
+    };
+    let mut hooks = ResourcePipelines::<C>::new(
+        fetcher.zone_id,
+        fetcher.partition,
+        fetcher.io_tx,
+        fetcher.accept_language,
+        fetcher.max_document_bytes,
+        fetcher.content_blocker,
+        Some(fetcher.cookie_jar),
+        fetcher.media,
+        fetcher.mixed_content,
+    );
+    match route_response_for(
+        RequestDestination::Document,
+        handle,
+        req,
+        fetch_result,
+        &policy,
+        &mut hooks,
+    )
+    .await
+    {
+        Ok(RoutedOutcome::MainDocument(doc)) => Ok(doc),
+        Ok(_) => Err(NavigationError::NetworkError(format!("{url} is not an HTML document"))),
+        Err(e) => Err(NavigationError::NetworkError(format!("Routing error: {e}"))),
+    }
+}
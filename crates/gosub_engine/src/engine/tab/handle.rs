@@ -1,9 +1,17 @@
+use crate::engine::script::{ClipboardAnswer, ClipboardRequestId, EvaluationId, PermissionRequestId};
 use crate::engine::types::TabChannel;
-use crate::events::TabCommand;
+use crate::engine::user_content::{UserContentId, UserScript, UserStyle};
+use crate::events::{ScrollToBehavior, TabCommand};
 use crate::tab::sink::TabSink;
-use crate::tab::TabId;
+use crate::tab::{SessionState, TabId};
 use crate::EngineError;
+use gosub_css3::media::ColorScheme;
+use gosub_render_pipeline::common::browser_state::PaintDebug;
+use gosub_render_pipeline::paginator::PageSetup;
 use gosub_render_pipeline::render::Viewport;
+use gosub_shared::node::NodeId;
+use gosub_web_platform::permissions::PermissionState;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 /// A handle to a running [`Tab`](crate::tab).
@@ -80,6 +88,14 @@ impl TabHandle {
         .await
     }
 
+    /// Scroll the page to the absolute offset `(x, y)` in CSS pixels, like `window.scrollTo`.
+    ///
+    /// With [`ScrollToBehavior::Auto`] the move animates only when the document sets
+    /// `scroll-behavior: smooth` on its root element.
+    pub async fn scroll_to(&self, x: f32, y: f32, behavior: ScrollToBehavior) -> Result<(), EngineError> {
+        self.send(TabCommand::ScrollTo { x, y, behavior }).await
+    }
+
     /// Navigate the tab to a new URL.
     ///
     /// This triggers a load in the tab’s context. The URL can be any supported scheme
@@ -92,4 +108,146 @@ impl TabHandle {
     pub async fn navigate(&self, url: impl Into<String>) -> Result<(), EngineError> {
         self.send(TabCommand::Navigate { url: url.into() }).await
     }
+
+    /// Reload the current page, restoring its scroll position.
+    pub async fn reload(&self, ignore_cache: bool) -> Result<(), EngineError> {
+        self.send(TabCommand::Reload { ignore_cache }).await
+    }
+
+    /// Stop loading the page, like a browser's Stop button. What is already shown stays.
+    pub async fn stop(&self) -> Result<(), EngineError> {
+        self.send(TabCommand::Stop).await
+    }
+
+    /// Go back one entry in the tab's session history. Does nothing on the first entry.
+    pub async fn go_back(&self) -> Result<(), EngineError> {
+        self.send(TabCommand::GoBack).await
+    }
+
+    /// Go forward one entry in the tab's session history. Does nothing on the last entry.
+    pub async fn go_forward(&self) -> Result<(), EngineError> {
+        self.send(TabCommand::GoForward).await
+    }
+
+    /// Snapshot the tab's history, scroll position and zoom. The state arrives as
+    /// [`EngineEvent::SessionSaved`](crate::events::EngineEvent::SessionSaved); store it with
+    /// [`SessionState::to_bytes`].
+    pub async fn save_session(&self) -> Result<(), EngineError> {
+        self.send(TabCommand::SaveSession).await
+    }
+
+    /// Restore a session saved with [`Self::save_session`]: the history is replaced and the
+    /// current entry loaded, scrolled to where it was.
+    pub async fn restore_session(&self, state: SessionState) -> Result<(), EngineError> {
+        self.send(TabCommand::RestoreSession { state }).await
+    }
+
+    /// Copy the tab's current text selection.
+    ///
+    /// The selected text comes back as an [`EngineEvent::ClipboardWrite`](crate::events::EngineEvent)
+    /// event; the UA decides how to put it on the system clipboard. Nothing is emitted when
+    /// there is no selection.
+    pub async fn copy_selection(&self) -> Result<(), EngineError> {
+        self.send(TabCommand::CopySelection).await
+    }
+
+    /// Ask what is at viewport point `(x, y)`: link, image, selected text and whether it is
+    /// editable, for building a context menu. The answer is delivered as
+    /// [`EngineEvent::HitTested`](crate::events::EngineEvent::HitTested).
+    pub async fn hit_test(&self, x: f32, y: f32) -> Result<(), EngineError> {
+        self.send(TabCommand::HitTest { x, y }).await
+    }
+
+    /// Record the tab whose page opened this one (see
+    /// [`EngineEvent::NewWindowRequested`](crate::events::EngineEvent::NewWindowRequested)).
+    pub async fn set_opener(&self, opener: Option<TabId>) -> Result<(), EngineError> {
+        self.send(TabCommand::SetOpener { opener }).await
+    }
+
+    /// Set the UA's color scheme. Pages see it as `prefers-color-scheme`, and their
+    /// `matchMedia()` lists get a `change` event when it flips their result.
+    pub async fn set_color_scheme(&self, scheme: ColorScheme) -> Result<(), EngineError> {
+        self.send(TabCommand::SetColorScheme { scheme }).await
+    }
+
+    /// Hand the files picked for the file input `node_id` to the tab, in answer to
+    /// [`EngineEvent::FileChooserRequested`](crate::events::EngineEvent::FileChooserRequested).
+    pub async fn set_input_files(&self, node_id: NodeId, files: Vec<PathBuf>) -> Result<(), EngineError> {
+        self.send(TabCommand::SetInputFiles { node_id, files }).await
+    }
+
+    /// Hand the options the user chose for `<select>` `node_id` to the tab, in answer to
+    /// [`EngineEvent::SelectPopupRequested`](crate::events::EngineEvent::SelectPopupRequested).
+    pub async fn choose_select_options(&self, node_id: NodeId, indices: Vec<usize>) -> Result<(), EngineError> {
+        self.send(TabCommand::ChooseSelectOptions { node_id, indices }).await
+    }
+
+    /// Answer the page's clipboard request `id`, from
+    /// [`EngineEvent::ClipboardRequested`](crate::events::EngineEvent::ClipboardRequested).
+    pub async fn answer_clipboard(&self, id: ClipboardRequestId, answer: ClipboardAnswer) -> Result<(), EngineError> {
+        self.send(TabCommand::AnswerClipboard { id, answer }).await
+    }
+
+    /// Answer the page's permission request `id`, from
+    /// [`EngineEvent::PermissionRequested`](crate::events::EngineEvent::PermissionRequested).
+    pub async fn answer_permission(&self, id: PermissionRequestId, state: PermissionState) -> Result<(), EngineError> {
+        self.send(TabCommand::AnswerPermission { id, state }).await
+    }
+
+    /// Run `source` in the current page's JS context. The returned id comes back with the result
+    /// in [`EngineEvent::ScriptEvaluated`](crate::events::EngineEvent::ScriptEvaluated).
+    pub async fn evaluate_script(&self, source: impl Into<String>) -> Result<EvaluationId, EngineError> {
+        let id = EvaluationId::new();
+        self.send(TabCommand::EvaluateScript {
+            id,
+            source: source.into(),
+        })
+        .await?;
+        Ok(id)
+    }
+
+    /// Inject `script` into the pages it matches from the next navigation on.
+    pub async fn add_user_script(&self, script: UserScript) -> Result<(), EngineError> {
+        self.send(TabCommand::AddUserScript { script }).await
+    }
+
+    /// Inject `style` into the pages it matches from the next navigation on.
+    pub async fn add_user_style(&self, style: UserStyle) -> Result<(), EngineError> {
+        self.send(TabCommand::AddUserStyle { style }).await
+    }
+
+    /// Stop injecting the user script or style `id`.
+    pub async fn remove_user_content(&self, id: UserContentId) -> Result<(), EngineError> {
+        self.send(TabCommand::RemoveUserContent { id }).await
+    }
+
+    /// Show the devtools box-model overlay on `node_id` (as returned by
+    /// [`BrowsingContext::hit_test`](crate::BrowsingContext::hit_test)), or hide it with `None`.
+    pub async fn inspect_node(&self, node_id: Option<NodeId>) -> Result<(), EngineError> {
+        self.send(TabCommand::InspectNode { node_id }).await
+    }
+
+    /// Describe `node_id` and its subtree (computed values, box metrics, matched rules), or the
+    /// whole document with `None`. The answer is delivered as
+    /// [`EngineEvent::NodeDescribed`](crate::events::EngineEvent::NodeDescribed).
+    pub async fn describe_node(&self, node_id: Option<NodeId>) -> Result<(), EngineError> {
+        self.send(TabCommand::DescribeNode { node_id }).await
+    }
+
+    /// Switch the paint debugging visualizations (paint order, layer borders, repaint flashing).
+    pub async fn set_paint_debug(&self, debug: PaintDebug) -> Result<(), EngineError> {
+        self.send(TabCommand::SetPaintDebug { debug }).await
+    }
+
+    /// Paginate the current document for printing. The pages are delivered as
+    /// [`EngineEvent::PrintReady`](crate::events::EngineEvent::PrintReady).
+    pub async fn print(&self, setup: PageSetup) -> Result<(), EngineError> {
+        self.send(TabCommand::Print { setup }).await
+    }
+
+    /// Render the whole document at full height, e.g. for a full-page screenshot. The result is
+    /// delivered as [`EngineEvent::FullPageCaptured`](crate::events::EngineEvent::FullPageCaptured).
+    pub async fn capture_full_page(&self) -> Result<(), EngineError> {
+        self.send(TabCommand::CaptureFullPage).await
+    }
 }
@@ -0,0 +1,242 @@
+//! Per-tab session history (the Back/Forward list).
+//!
+//! Entries are appended when a navigation commits. Navigating from the middle of the list drops
+//! everything after the current entry, as browsers do. Traversal doesn't move the current index
+//! by itself: the worker asks for the target entry, loads it, and only [`commit_traversal`]s once
+//! the load succeeds, so a failed Back leaves the list where it was.
+//!
+//! Leaving an entry records how far its page and its scroll containers were scrolled; going
+//! Back/Forward to it or reloading it scrolls them there again once it committed. A page that sets
+//! `history.scrollRestoration = "manual"` restores positions itself, and its entry is left alone.
+//!
+//! [`commit_traversal`]: SessionHistory::commit_traversal
+
+use gosub_shared::node::NodeId;
+use url::Url;
+
+/// Upper bound on entries kept per tab; the oldest are dropped first.
+const MAX_ENTRIES: usize = 50;
+
+/// Who puts an entry's scroll positions back when it is returned to
+/// (`history.scrollRestoration`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScrollRestoration {
+    /// The engine does
+    #[default]
+    Auto,
+    /// The page does
+    Manual,
+}
+
+impl ScrollRestoration {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ScrollRestoration::Auto => "auto",
+            ScrollRestoration::Manual => "manual",
+        }
+    }
+}
+
+/// A scroll container's offset: the element and its horizontal and vertical scroll position.
+pub type ElementScroll = (NodeId, f64, f64);
+
+/// One visited page.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    pub url: Url,
+    pub title: String,
+    /// Page scroll offset when the user left the entry, restored on return.
+    pub scroll: (i32, i32),
+    /// Offsets of the scroll containers that were scrolled, by element, restored on return.
+    /// Elements are matched by node id, which a reload of the same markup reproduces.
+    pub element_scroll: Vec<ElementScroll>,
+    pub scroll_restoration: ScrollRestoration,
+}
+
+impl HistoryEntry {
+    pub fn new(url: Url) -> Self {
+        Self {
+            url,
+            title: String::new(),
+            scroll: (0, 0),
+            element_scroll: Vec::new(),
+            scroll_restoration: ScrollRestoration::default(),
+        }
+    }
+
+    /// Where to scroll the page and its scroll containers when returning to the entry, or `None`
+    /// when that is up to the page.
+    pub fn restored_scroll(&self) -> Option<((i32, i32), &[ElementScroll])> {
+        match self.scroll_restoration {
+            ScrollRestoration::Auto => Some((self.scroll, &self.element_scroll)),
+            ScrollRestoration::Manual => None,
+        }
+    }
+}
+
+/// Ordered list of visited entries plus the index of the current one.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SessionHistory {
+    entries: Vec<HistoryEntry>,
+    index: Option<usize>,
+}
+
+impl SessionHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    /// Index of the current entry, `None` before the first navigation commits.
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    pub fn current(&self) -> Option<&HistoryEntry> {
+        self.index.and_then(|i| self.entries.get(i))
+    }
+
+    pub fn current_mut(&mut self) -> Option<&mut HistoryEntry> {
+        self.index.and_then(|i| self.entries.get_mut(i))
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        self.index.is_some_and(|i| i > 0)
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        self.index.is_some_and(|i| i + 1 < self.entries.len())
+    }
+
+    /// Index `delta` steps from the current entry (negative is back), if there is one.
+    pub fn offset(&self, delta: isize) -> Option<usize> {
+        let target = self.index?.checked_add_signed(delta)?;
+        (target < self.entries.len()).then_some(target)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&HistoryEntry> {
+        self.entries.get(index)
+    }
+
+    /// Appends a new entry after the current one, discarding any forward entries.
+    pub fn push(&mut self, entry: HistoryEntry) {
+        if let Some(i) = self.index {
+            self.entries.truncate(i + 1);
+        }
+        self.entries.push(entry);
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+        self.index = Some(self.entries.len() - 1);
+    }
+
+    /// Puts `entry` in place of the current one (`location.replace()`), or appends it when
+    /// there is none yet. The forward entries stay.
+    pub fn replace(&mut self, entry: HistoryEntry) {
+        match self.current_mut() {
+            Some(current) => *current = entry,
+            None => self.push(entry),
+        }
+    }
+
+    /// Makes `index` current after a Back/Forward load committed. `url` is where the load ended
+    /// up, which differs from the entry's URL when the server redirected.
+    pub fn commit_traversal(&mut self, index: usize, url: Url) {
+        if let Some(entry) = self.entries.get_mut(index) {
+            entry.url = url;
+            self.index = Some(index);
+        }
+    }
+
+    /// Replaces the whole list, e.g. from a saved session. An out-of-range `index` is clamped to
+    /// the last entry.
+    pub fn restore(&mut self, entries: Vec<HistoryEntry>, index: usize) {
+        self.index = (!entries.is_empty()).then(|| index.min(entries.len() - 1));
+        self.entries = entries;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(url: &str) -> HistoryEntry {
+        HistoryEntry::new(Url::parse(url).unwrap())
+    }
+
+    fn urls(history: &SessionHistory) -> Vec<&str> {
+        history.entries().iter().map(|e| e.url.as_str()).collect()
+    }
+
+    #[test]
+    fn push_and_traverse() {
+        let mut history = SessionHistory::new();
+        assert!(!history.can_go_back() && !history.can_go_forward());
+
+        history.push(entry("https://a.example/"));
+        history.push(entry("https://b.example/"));
+        history.push(entry("https://c.example/"));
+        assert!(history.can_go_back());
+        assert!(!history.can_go_forward());
+
+        let back = history.offset(-1).unwrap();
+        assert_eq!(history.get(back).unwrap().url.as_str(), "https://b.example/");
+        history.commit_traversal(back, Url::parse("https://b.example/").unwrap());
+        assert!(history.can_go_forward());
+        assert_eq!(history.offset(-2), None);
+        assert_eq!(history.offset(1), Some(2));
+    }
+
+    #[test]
+    fn navigating_from_the_middle_drops_forward_entries() {
+        let mut history = SessionHistory::new();
+        history.push(entry("https://a.example/"));
+        history.push(entry("https://b.example/"));
+        history.commit_traversal(0, Url::parse("https://a.example/").unwrap());
+
+        history.push(entry("https://d.example/"));
+        assert_eq!(urls(&history), vec!["https://a.example/", "https://d.example/"]);
+        assert!(!history.can_go_forward());
+    }
+
+    #[test]
+    fn replacing_keeps_the_forward_entries() {
+        let mut history = SessionHistory::new();
+        history.replace(entry("https://a.example/"));
+        history.push(entry("https://b.example/"));
+        history.commit_traversal(0, Url::parse("https://a.example/").unwrap());
+
+        history.replace(entry("https://c.example/"));
+        assert_eq!(urls(&history), vec!["https://c.example/", "https://b.example/"]);
+        assert_eq!(history.index(), Some(0));
+        assert!(history.can_go_forward());
+    }
+
+    #[test]
+    fn manual_entries_are_not_restored() {
+        let mut entry = entry("https://a.example/");
+        entry.scroll = (0, 400);
+        entry.element_scroll = vec![(NodeId::from(7usize), 0.0, 120.0)];
+        let (scroll, elements) = entry.restored_scroll().unwrap();
+        assert_eq!(scroll, (0, 400));
+        assert_eq!(elements, [(NodeId::from(7usize), 0.0, 120.0)]);
+
+        entry.scroll_restoration = ScrollRestoration::Manual;
+        assert_eq!(entry.restored_scroll(), None);
+        assert_eq!(ScrollRestoration::Manual.as_str(), "manual");
+    }
+
+    #[test]
+    fn oldest_entries_are_dropped() {
+        let mut history = SessionHistory::new();
+        for i in 0..MAX_ENTRIES + 5 {
+            history.push(entry(&format!("https://example.com/{i}")));
+        }
+        assert_eq!(history.entries().len(), MAX_ENTRIES);
+        assert_eq!(history.entries()[0].url.as_str(), "https://example.com/5");
+        assert_eq!(history.index(), Some(MAX_ENTRIES - 1));
+    }
+}
@@ -0,0 +1,88 @@
+//! The media store's fetcher for a tab.
+//!
+//! Images, SVGs and the images, style sheets and elements SVGs refer to are fetched like the
+//! page's other subresources: through the tab's fetcher, with its cookie jar and content blocker,
+//! showing up as the tab's network traffic.
+
+use super::frames::FrameFetcher;
+use super::script_fetch::script_fetch;
+use crate::engine::script::{RedirectMode, ScriptFetchRequest};
+use crate::net::cors::{CredentialsMode, RequestMode};
+use crate::net::types::ResourceKind;
+use crate::net::{sniff_mime, SniffContext};
+use gosub_render_pipeline::common::media::{FetchedResource, ResourceFetcher};
+use tokio::runtime::Handle;
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+/// Fetches media for the document at `document` through the tab's fetcher.
+pub(crate) struct TabMediaFetcher {
+    fetcher: FrameFetcher,
+    document: Url,
+    /// Runtime the fetches run on; the media store calls in from its own threads
+    runtime: Handle,
+    cancel: CancellationToken,
+}
+
+impl TabMediaFetcher {
+    pub fn new(fetcher: FrameFetcher, document: Url, runtime: Handle, cancel: CancellationToken) -> Self {
+        Self {
+            fetcher,
+            document,
+            runtime,
+            cancel,
+        }
+    }
+}
+
+impl ResourceFetcher for TabMediaFetcher {
+    fn fetch(&self, url: &Url) -> anyhow::Result<FetchedResource> {
+        // Media loads are no-cors requests carrying cookies; they are never read by the page, so
+        // CORS doesn't apply to them.
+        let request = ScriptFetchRequest {
+            url: url.to_string(),
+            method: "GET".into(),
+            headers: Vec::new(),
+            body: None,
+            mode: RequestMode::NoCors,
+            credentials: CredentialsMode::Include,
+            redirect: RedirectMode::Follow,
+        };
+        // Loaded as images, so insecure ones of a secure document are upgraded rather than blocked.
+        let load = script_fetch(
+            &self.fetcher,
+            &self.document,
+            ResourceKind::Image,
+            false,
+            request,
+            self.cancel.clone(),
+        );
+
+        // Blocking on the runtime isn't allowed from one of its own threads, so wait on a
+        // thread of our own whoever the caller is.
+        let response = std::thread::scope(|scope| scope.spawn(|| self.runtime.block_on(load)).join())
+            .map_err(|_| anyhow::anyhow!("media fetch of {url} panicked"))?
+            .map_err(anyhow::Error::msg)?;
+        if !(200..300).contains(&response.status) {
+            anyhow::bail!("HTTP {} fetching {url}", response.status);
+        }
+
+        let header = |wanted: &str| {
+            response
+                .headers
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(wanted))
+                .map(|(_, value)| value.as_str())
+        };
+        // Images are often served with the wrong type; the decoder goes by the sniffed one.
+        let no_sniff = header("x-content-type-options")
+            .and_then(|value| value.split(',').next())
+            .is_some_and(|value| value.trim().eq_ignore_ascii_case("nosniff"));
+        let content_type = sniff_mime(header("content-type"), no_sniff, &response.body, SniffContext::Image)
+            .map(|mime| mime.to_string());
+        Ok(FetchedResource {
+            content_type,
+            body: response.body.into(),
+        })
+    }
+}
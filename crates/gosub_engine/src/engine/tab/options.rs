@@ -12,7 +12,10 @@
 //! within the Gosub engine.
 
 use crate::cookies::CookieJarHandle;
+use crate::engine::script::ScriptHost;
+use crate::net::blocking::ContentBlocker;
 use crate::storage::{PartitionKey, StorageService};
+use crate::tab::TabId;
 use gosub_render_pipeline::render::Viewport;
 use std::sync::Arc;
 
@@ -24,6 +27,7 @@ use std::sync::Arc;
 /// - [`url`](Self::url): initial URL to load
 /// - [`title`](Self::title): optional title (used if no document title is available)
 /// - [`viewport`](Self::viewport): initial viewport size
+/// - [`opener`](Self::opener): the tab whose page asked for this one
 #[derive(Clone, Debug, Default)]
 pub struct TabDefaults {
     /// Initial URL to navigate to.
@@ -34,6 +38,10 @@ pub struct TabDefaults {
 
     /// Initial viewport configuration (width, height, scroll offset).
     pub viewport: Option<Viewport>,
+
+    /// The tab that opened this one, as reported by
+    /// [`EngineEvent::NewWindowRequested`](crate::events::EngineEvent::NewWindowRequested).
+    pub opener: Option<TabId>,
 }
 
 /// Per-tab overrides for configuration.
@@ -56,7 +64,9 @@ pub struct TabDefaults {
 #[derive(Clone, Debug, Default)]
 pub struct TabOverrides {
     // --- Services & partitioning ---
-    /// Storage partition key. `None` = inherit zone policy.
+    /// Storage partition key. `None` = inherit zone policy. Tabs of a zone with the same key share
+    /// an in-memory cookie jar and DOM storage that no other tab sees (container tabs, private
+    /// browsing); see [`Zone::clear_partition`](crate::zone::Zone::clear_partition).
     pub partition_key: Option<PartitionKey>,
 
     /// Cookie jar selection (inherit, ephemeral, or custom).
@@ -69,6 +79,24 @@ pub struct TabOverrides {
     /// Per-tab `Accept-Language` header override. `None` = inherit the zone's
     /// [`ZoneConfig::accept_languages`](crate::zone::ZoneConfig::accept_languages).
     pub accept_language: Option<String>,
+
+    // --- Content ---
+    /// Filter rules checked before the tab's requests go out. `None` = no blocking.
+    pub content_blocker: Option<Arc<ContentBlocker>>,
+
+    /// Runs the tab's JavaScript. `None` = scripting disabled.
+    pub script_host: Option<ScriptHost>,
+}
+
+impl TabOverrides {
+    /// Overrides that put the tab in partition `key`, isolated from the zone's own cookies and
+    /// storage.
+    pub fn partitioned(key: impl Into<String>) -> Self {
+        Self {
+            partition_key: Some(PartitionKey::Custom(key.into())),
+            ..Default::default()
+        }
+    }
 }
 
 /// Policy for selecting a tab's cookie jar.
@@ -0,0 +1,423 @@
+//! The worker side of a page's `fetch()` calls.
+//!
+//! Requests are resolved against the document URL and sent through the tab's fetcher, like a
+//! frame load: they see the tab's cookie jar and content blocker and show up as the tab's
+//! network traffic. Cross-origin requests follow the request's mode (see [`crate::net::cors`]);
+//! with [`CORS_ENFORCEMENT`] off they are treated as same-origin. A redirect to another origin
+//! is judged by the same rules, against an opaque origin as the Fetch spec asks. Insecure requests of
+//! secure documents are blocked as mixed content, or upgraded where the kind of resource allows it.
+
+use super::frames::FrameFetcher;
+use crate::cookies::SameSiteContext;
+use crate::engine::script::{FetchOutcome, RedirectMode, ResponseType, ScriptFetchRequest, ScriptFetchResponse};
+use crate::engine::types::{NavigationId, RequestId};
+use crate::net::blocking::RequestType;
+use crate::net::cors::{self, RequestMode};
+use crate::net::req_ref_tracker::{RequestReference, REF_REGISTRY};
+use crate::net::types::{FetchRequest, Initiator, Priority, RequestBody, ResourceKind};
+use crate::net::{submit_to_io, FetchError};
+use cow_utils::CowUtils;
+use http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use http::Method;
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+/// Setting that turns CORS checks on cross-origin requests on or off.
+pub(crate) const CORS_ENFORCEMENT: &str = "net.security.cors_enforcement";
+
+/// Headers only the user agent may set.
+const FORBIDDEN_HEADERS: &[&str] = &[
+    "accept-charset",
+    "accept-encoding",
+    "access-control-request-headers",
+    "access-control-request-method",
+    "connection",
+    "content-length",
+    "cookie",
+    "cookie2",
+    "date",
+    "dnt",
+    "expect",
+    "host",
+    "keep-alive",
+    "origin",
+    "referer",
+    "set-cookie",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "via",
+];
+
+fn is_forbidden_header(name: &HeaderName) -> bool {
+    let name = name.as_str();
+    FORBIDDEN_HEADERS.contains(&name) || name.starts_with("proxy-") || name.starts_with("sec-")
+}
+
+/// A response as it came off the network.
+struct RawResponse {
+    url: Url,
+    status: u16,
+    status_text: String,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+/// Runs `request` for a document at `base`, loading a `kind` resource: the content blocker and the
+/// mixed-content check judge it by that. `cancel` aborts it.
+pub(crate) async fn script_fetch(
+    fetcher: &FrameFetcher,
+    base: &Url,
+    kind: ResourceKind,
+    enforce_cors: bool,
+    request: ScriptFetchRequest,
+    cancel: CancellationToken,
+) -> FetchOutcome {
+    let url = base
+        .join(&request.url)
+        .map_err(|e| format!("invalid URL {:?}: {e}", request.url))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("unsupported scheme in {url}"));
+    }
+    let url = fetcher
+        .mixed_content
+        .check(base, &url, kind)
+        .ok_or_else(|| format!("blocked mixed content {url}"))?;
+
+    let method = Method::from_bytes(request.method.as_bytes()).map_err(|_| "invalid method".to_string())?;
+    let upper = method.as_str().cow_to_ascii_uppercase();
+    if matches!(&*upper, "CONNECT" | "TRACE" | "TRACK") {
+        return Err(format!("forbidden method {method}"));
+    }
+
+    let mut headers = HeaderMap::new();
+    for (name, value) in &request.headers {
+        let (Ok(name), Ok(value)) = (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(value)) else {
+            return Err(format!("invalid header {name}"));
+        };
+        if !is_forbidden_header(&name) {
+            headers.append(name, value);
+        }
+    }
+
+    let origin = base.origin();
+    let cross_origin = enforce_cors && url.origin() != origin;
+    let credentials = request.credentials.sends_credentials(&origin, &url);
+    let mut response_type = ResponseType::Basic;
+
+    if cross_origin {
+        match request.mode {
+            RequestMode::SameOrigin => return Err(cors::CorsError::SameOriginOnly(url).to_string()),
+            RequestMode::NoCors => {
+                if !cors::is_safelisted_method(&method) {
+                    return Err(cors::CorsError::NoCorsMethod(method).to_string());
+                }
+                retain_safelisted(&mut headers);
+                response_type = ResponseType::Opaque;
+            }
+            RequestMode::Cors => {
+                if cors::needs_preflight(&method, &headers) {
+                    let preflight = cors::preflight_headers(&origin, &method, &headers);
+                    let response = send(
+                        fetcher,
+                        base,
+                        &url,
+                        kind,
+                        Method::OPTIONS,
+                        preflight,
+                        None,
+                        false,
+                        &cancel,
+                    )
+                    .await?;
+                    cors::check_preflight(
+                        &origin,
+                        credentials,
+                        &method,
+                        &headers,
+                        &url,
+                        response.status,
+                        &response.headers,
+                    )
+                    .map_err(|e| e.to_string())?;
+                }
+                headers.insert(header::ORIGIN, cors::origin_header(&origin));
+                response_type = ResponseType::Cors;
+            }
+        }
+    }
+
+    let body = request.body_bytes();
+    let response = send(fetcher, base, &url, kind, method, headers, body, credentials, &cancel).await?;
+    if response.url != url && request.redirect == RedirectMode::Error {
+        return Err(format!("{url} redirected to {}", response.url));
+    }
+    filter_response(
+        &origin,
+        &url,
+        request.mode,
+        credentials,
+        enforce_cors,
+        response_type,
+        response,
+    )
+}
+
+/// What the page gets to see of `response` to a request for `url` from `origin`, which was sent as
+/// a `kind` request. A request that started out same-origin but was redirected to another origin
+/// is treated as cross-origin from there on, following the request's `mode`. Once a redirect has
+/// crossed origins, the request's origin is opaque and CORS only admits it through `*` or `null`.
+fn filter_response(
+    origin: &url::Origin,
+    url: &Url,
+    mode: RequestMode,
+    credentials: bool,
+    enforce_cors: bool,
+    mut kind: ResponseType,
+    response: RawResponse,
+) -> FetchOutcome {
+    let redirected = response.url != *url;
+    let final_origin = response.url.origin();
+    if enforce_cors && kind == ResponseType::Basic && final_origin != *origin {
+        kind = match mode {
+            RequestMode::SameOrigin => return Err(cors::CorsError::SameOriginOnly(response.url).to_string()),
+            RequestMode::NoCors => ResponseType::Opaque,
+            RequestMode::Cors => ResponseType::Cors,
+        };
+    }
+
+    match kind {
+        ResponseType::Opaque => Ok(ScriptFetchResponse::opaque()),
+        ResponseType::Cors => {
+            let tainted = final_origin != url.origin() && final_origin != *origin;
+            let request_origin = if tainted {
+                url::Origin::new_opaque()
+            } else {
+                origin.clone()
+            };
+            cors::check_response(&request_origin, credentials, &response.headers).map_err(|e| e.to_string())?;
+            let exposed = cors::expose_headers(credentials, &response.headers);
+            Ok(to_script_response(response, exposed, redirected, kind))
+        }
+        ResponseType::Basic => {
+            let mut visible = response.headers.clone();
+            visible.remove(header::SET_COOKIE);
+            visible.remove("set-cookie2");
+            Ok(to_script_response(response, visible, redirected, kind))
+        }
+    }
+}
+
+/// `no-cors` requests may only carry safelisted headers.
+fn retain_safelisted(headers: &mut HeaderMap) {
+    let kept: Vec<_> = headers
+        .iter()
+        .filter(|(n, v)| cors::is_safelisted_request_header(n, v))
+        .map(|(n, v)| (n.clone(), v.clone()))
+        .collect();
+    headers.clear();
+    for (name, value) in kept {
+        headers.append(name, value);
+    }
+}
+
+fn to_script_response(
+    response: RawResponse,
+    headers: HeaderMap,
+    redirected: bool,
+    kind: ResponseType,
+) -> ScriptFetchResponse {
+    ScriptFetchResponse {
+        url: response.url.to_string(),
+        status: response.status,
+        status_text: response.status_text,
+        headers: headers
+            .iter()
+            .map(|(n, v)| (n.to_string(), String::from_utf8_lossy(v.as_bytes()).into_owned()))
+            .collect(),
+        redirected,
+        kind,
+        body: response.body,
+    }
+}
+
+/// Sends one request for a `kind` resource to the network on behalf of the document at `base`.
+#[allow(clippy::too_many_arguments)]
+async fn send(
+    fetcher: &FrameFetcher,
+    base: &Url,
+    url: &Url,
+    kind: ResourceKind,
+    method: Method,
+    mut headers: HeaderMap,
+    body: Option<Vec<u8>>,
+    credentials: bool,
+    cancel: &CancellationToken,
+) -> Result<RawResponse, String> {
+    if let Some(blocker) = &fetcher.content_blocker {
+        if let Some(rule) = blocker.check(url, RequestType::from(kind), Some(base)) {
+            return Err(format!("blocked by content blocker ({rule})"));
+        }
+    }
+
+    if credentials {
+        let site = SameSiteContext::for_subrequest(url, base);
+        fetcher
+            .cookie_jar
+            .add_request_cookies(&mut headers, url, Some(base), site);
+    }
+    if !headers.contains_key(header::ACCEPT_LANGUAGE) {
+        if let Some(val) = fetcher.accept_language.as_deref().and_then(|l| l.parse().ok()) {
+            headers.insert(header::ACCEPT_LANGUAGE, val);
+        }
+    }
+
+    let nav_id = NavigationId::new();
+    fetcher
+        .request_reference_map
+        .write()
+        .insert(RequestReference::Navigation(nav_id), fetcher.tab_id);
+    let req_id = RequestId::new();
+    REF_REGISTRY.register_request(req_id, kind, Initiator::Script);
+    let mut builder = FetchRequest::builder(method, url.clone())
+        .with_reference(REF_REGISTRY.to_net(RequestReference::Navigation(nav_id)))
+        .with_req_id(req_id)
+        .with_headers(headers)
+        .with_priority(Priority::Normal)
+        .with_kind(kind.to_net())
+        .with_initiator(Initiator::Script.to_net())
+        .with_streaming(false)
+        .with_auto_decode(true);
+    if let Some(body) = body {
+        builder = builder.with_body(RequestBody::bytes(body));
+    }
+    let req = builder.build();
+
+    let pending = submit_to_io(
+        fetcher.zone_id,
+        fetcher.partition.clone(),
+        req,
+        fetcher.io_tx.clone(),
+        Some(cancel.clone()),
+    )
+    .await
+    .map_err(|_| "I/O channel closed".to_string())?;
+    let (meta, body) = match pending.bytes().await {
+        Ok((meta, body)) => (meta, body.to_vec()),
+        Err(FetchError::Aborted) => return Err("aborted".into()),
+        Err(e) => return Err(e.to_string()),
+    };
+    if credentials {
+        fetcher
+            .cookie_jar
+            .write()
+            .store_response_cookies(&meta.final_url, &meta.headers, Some(base));
+    }
+
+    Ok(RawResponse {
+        url: meta.final_url,
+        status: meta.status,
+        status_text: meta.status_text,
+        headers: meta.headers,
+        body,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_cannot_set_forbidden_headers() {
+        assert!(is_forbidden_header(&HeaderName::from_static("cookie")));
+        assert!(is_forbidden_header(&HeaderName::from_static("sec-fetch-mode")));
+        assert!(is_forbidden_header(&HeaderName::from_static("proxy-authorization")));
+        assert!(!is_forbidden_header(&HeaderName::from_static("x-requested-with")));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_static("*/*"));
+        headers.insert("x-token", HeaderValue::from_static("1"));
+        retain_safelisted(&mut headers);
+        assert_eq!(headers.len(), 1);
+        assert!(headers.contains_key(header::ACCEPT));
+    }
+
+    fn response(url: &str, allow_origin: Option<&'static str>) -> RawResponse {
+        let mut headers = HeaderMap::new();
+        if let Some(allow) = allow_origin {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static(allow));
+        }
+        headers.insert("x-secret", HeaderValue::from_static("1"));
+        RawResponse {
+            url: Url::parse(url).unwrap(),
+            status: 200,
+            status_text: "OK".into(),
+            headers,
+            body: b"secret".to_vec(),
+        }
+    }
+
+    #[test]
+    fn cross_origin_responses_need_cors_by_default() {
+        let enforce_cors = crate::engine::default_settings().get_bool(CORS_ENFORCEMENT);
+        assert!(enforce_cors);
+
+        let origin = Url::parse("https://page.test/").unwrap().origin();
+        let url = Url::parse("https://api.test/data").unwrap();
+        let blocked = filter_response(
+            &origin,
+            &url,
+            RequestMode::Cors,
+            false,
+            enforce_cors,
+            ResponseType::Cors,
+            response("https://api.test/data", None),
+        );
+        assert!(blocked.is_err());
+
+        let allowed = filter_response(
+            &origin,
+            &url,
+            RequestMode::Cors,
+            false,
+            enforce_cors,
+            ResponseType::Cors,
+            response("https://api.test/data", Some("https://page.test")),
+        )
+        .unwrap();
+        assert_eq!(allowed.kind, ResponseType::Cors);
+        assert!(allowed.headers.iter().all(|(name, _)| name != "x-secret"));
+    }
+
+    #[test]
+    fn redirects_to_another_origin_are_cross_origin() {
+        let origin = Url::parse("https://page.test/").unwrap().origin();
+        let url = Url::parse("https://page.test/go").unwrap();
+        let filter = |mode, allow_origin| {
+            filter_response(
+                &origin,
+                &url,
+                mode,
+                false,
+                true,
+                ResponseType::Basic,
+                response("https://other.test/data", allow_origin),
+            )
+        };
+
+        assert!(filter(RequestMode::Cors, None).is_err());
+        // The redirect made the request's origin opaque, so naming the page's origin no longer
+        // admits it.
+        assert!(filter(RequestMode::Cors, Some("https://page.test")).is_err());
+        let admitted = filter(RequestMode::Cors, Some("*")).unwrap();
+        assert_eq!(admitted.kind, ResponseType::Cors);
+        assert!(admitted.redirected);
+
+        assert_eq!(
+            filter(RequestMode::NoCors, None).unwrap(),
+            ScriptFetchResponse::opaque()
+        );
+        assert!(filter(RequestMode::SameOrigin, Some("*")).is_err());
+    }
+}
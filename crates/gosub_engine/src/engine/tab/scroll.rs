@@ -19,6 +19,15 @@ pub(crate) fn default_text_scroll() -> ScrollBehavior {
     }
 }
 
+/// How `scrollTo({behavior: "smooth"})` and `scroll-behavior: smooth` animate. Longer than the
+/// wheel tween since programmatic scrolls usually travel much further than one notch.
+pub(crate) fn default_programmatic_scroll() -> ScrollBehavior {
+    ScrollBehavior::Tween {
+        duration: std::time::Duration::from_millis(400),
+        easing: Easing::EaseInOut,
+    }
+}
+
 /// Owns the engine's scroll offset and animates it toward a target.
 pub(crate) struct ScrollState {
     /// How the offset moves toward its target. `Instant` applies moves immediately; the others ease.
@@ -81,6 +90,34 @@ impl ScrollState {
         None
     }
 
+    /// Move to the absolute offset `(x, y)`, clamped to `[0, max]` per axis.
+    ///
+    /// `behavior` overrides the wheel behavior for this one move: with `Instant` the offset is
+    /// returned for the caller to apply now; otherwise the move is animated from the current
+    /// position (restarting any in-flight animation) and `None` is returned.
+    pub(crate) fn scroll_to(
+        &mut self,
+        x: f64,
+        y: f64,
+        max_x: f64,
+        max_y: f64,
+        behavior: &ScrollBehavior,
+    ) -> Option<(i32, i32)> {
+        self.target = (x.clamp(0.0, max_x), y.clamp(0.0, max_y));
+
+        match (behavior.make_animator(self.pos.0), behavior.make_animator(self.pos.1)) {
+            (Some(ax), Some(ay)) => {
+                self.anim = Some((ax, ay));
+                None
+            }
+            _ => {
+                self.pos = self.target;
+                self.anim = None;
+                Some(round(self.pos))
+            }
+        }
+    }
+
     /// Advance an in-flight animation by `dt` seconds, returning the new integer offset while
     /// animating, or `None` when idle. Settles exactly on the target and stops animating.
     pub(crate) fn tick(&mut self, dt: f64) -> Option<(i32, i32)> {
@@ -175,6 +212,35 @@ mod tests {
         assert_eq!(last, (0, 200), "converges on the extended target");
     }
 
+    #[test]
+    fn scroll_to_instant_jumps_and_clamps() {
+        let mut s = ScrollState::new(tween(200));
+        assert_eq!(
+            s.scroll_to(0.0, 400.0, f64::MAX, 1000.0, &ScrollBehavior::Instant),
+            Some((0, 400))
+        );
+        assert!(!s.animating());
+        assert_eq!(
+            s.scroll_to(-5.0, 5000.0, f64::MAX, 1000.0, &ScrollBehavior::Instant),
+            Some((0, 1000))
+        );
+    }
+
+    #[test]
+    fn scroll_to_smooth_animates_to_absolute_target() {
+        let mut s = ScrollState::new(ScrollBehavior::Instant);
+        s.scroll_by(0.0, 100.0, f64::MAX, 1000.0);
+        assert_eq!(s.scroll_to(0.0, 500.0, f64::MAX, 1000.0, &tween(200)), None);
+        assert!(s.animating());
+        // Linear from 100 to 500: halfway after 100ms.
+        assert_eq!(s.tick(0.1), Some((0, 300)));
+        assert_eq!(s.tick(0.1), Some((0, 500)));
+        assert!(!s.animating());
+
+        // A wheel scroll afterwards still applies instantly, relative to the new position.
+        assert_eq!(s.scroll_by(0.0, 10.0, f64::MAX, 1000.0), Some((0, 510)));
+    }
+
     #[test]
     fn tick_when_idle_is_none() {
         let mut s = ScrollState::new(tween(200));
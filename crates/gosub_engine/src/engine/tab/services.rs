@@ -1,9 +1,42 @@
 use crate::cookies::{CookieJarHandle, DefaultCookieJar};
+use crate::engine::permissions::PermissionHandler;
+use crate::engine::script::ScriptHost;
+use crate::net::blocking::ContentBlocker;
 use crate::storage::{InMemoryLocalStore, InMemorySessionStore, PartitionKey, PartitionPolicy, StorageService};
 use crate::tab::options::{TabCookieJar, TabOverrides, TabStorageScope};
 use crate::zone::{ZoneConfig, ZoneId, ZoneServices};
 use std::sync::Arc;
 
+/// Cookies and DOM storage shared by the tabs of one [`PartitionKey`] within a zone.
+///
+/// Both live in memory for as long as the zone keeps the partition, so a partition doubles as a
+/// private-browsing session: nothing reaches the zone's persistent stores, and dropping the
+/// partition forgets it all. The HTTP cache keeps the partition's responses apart as well, in
+/// memory only (see [`CachePartition`](crate::net::http_cache::CachePartition)).
+#[derive(Clone, Debug)]
+pub struct PartitionServices {
+    pub cookie_jar: CookieJarHandle,
+    pub storage: Arc<StorageService>,
+}
+
+impl PartitionServices {
+    pub fn new() -> Self {
+        Self {
+            cookie_jar: DefaultCookieJar::new().into(),
+            storage: Arc::new(StorageService::new(
+                Arc::new(InMemoryLocalStore::new()),
+                Arc::new(InMemorySessionStore::new()),
+            )),
+        }
+    }
+}
+
+impl Default for PartitionServices {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// The effective services for a tab after applying zone defaults and tab overrides.
 #[derive(Clone, Debug)]
 pub struct EffectiveTabServices {
@@ -13,14 +46,23 @@ pub struct EffectiveTabServices {
     pub cookie_jar: CookieJarHandle,
     /// `Accept-Language` header value for this tab's requests, if configured.
     pub accept_language: Option<String>,
+    /// Filter rules for this tab's requests, if any.
+    pub content_blocker: Option<Arc<ContentBlocker>>,
+    /// JavaScript runtime for this tab, if scripting is enabled.
+    pub script_host: Option<ScriptHost>,
+    /// The zone's embedder callback for permissions, if any.
+    pub permission_handler: Option<PermissionHandler>,
 }
 
 /// Resolve the effective services for a tab based on the zone services/config and tab overrides.
+/// `partition` holds the services of the tab's partition when it has a partition key; inherited
+/// cookies and storage then come from there instead of the zone.
 pub fn resolve_tab_services(
     zone_id: ZoneId,
     services: &ZoneServices,
     zone_config: &ZoneConfig,
     ov: &TabOverrides,
+    partition: Option<&PartitionServices>,
 ) -> EffectiveTabServices {
     let partition_key = ov
         .partition_key
@@ -36,7 +78,10 @@ pub fn resolve_tab_services(
     };
 
     let storage = match &ov.storage_scope {
-        TabStorageScope::Inherit => services.storage.clone(),
+        TabStorageScope::Inherit => match partition {
+            Some(partition) => partition.storage.clone(),
+            None => services.storage.clone(),
+        },
         TabStorageScope::Custom(s) => s.clone(),
         TabStorageScope::Ephemeral => Arc::new(StorageService::new(
             Arc::new(InMemoryLocalStore::new()),
@@ -47,11 +92,14 @@ pub fn resolve_tab_services(
     let cookie_jar = match &ov.cookie_jar {
         // Explicit zone jar wins; otherwise a zone cookie store provides a persistent
         // per-zone jar; otherwise fall back to a fresh in-memory jar.
-        TabCookieJar::Inherit => services
-            .cookie_jar
-            .clone()
-            .or_else(|| services.cookie_store.as_ref().and_then(|store| store.jar_for(zone_id)))
-            .unwrap_or_else(|| DefaultCookieJar::new().into()),
+        TabCookieJar::Inherit => match partition {
+            Some(partition) => partition.cookie_jar.clone(),
+            None => services
+                .cookie_jar
+                .clone()
+                .or_else(|| services.cookie_store.as_ref().and_then(|store| store.jar_for(zone_id)))
+                .unwrap_or_else(|| DefaultCookieJar::new().into()),
+        },
         TabCookieJar::Custom(handle) => handle.clone(),
         TabCookieJar::Ephemeral => DefaultCookieJar::new().into(),
     };
@@ -67,5 +115,48 @@ pub fn resolve_tab_services(
         storage,
         cookie_jar,
         accept_language,
+        content_blocker: ov.content_blocker.clone(),
+        script_host: ov.script_host.clone(),
+        permission_handler: zone_config.permission_handler.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zone_services() -> ZoneServices {
+        ZoneServices {
+            storage: Arc::new(StorageService::new(
+                Arc::new(InMemoryLocalStore::new()),
+                Arc::new(InMemorySessionStore::new()),
+            )),
+            cookie_store: None,
+            cookie_jar: Some(DefaultCookieJar::new().into()),
+            partition_policy: PartitionPolicy::TopLevelOrigin,
+        }
+    }
+
+    #[test]
+    fn partitioned_tabs_share_their_partition_only() {
+        let zone_id = ZoneId::new();
+        let services = zone_services();
+        let config = ZoneConfig::default();
+        let work = PartitionServices::new();
+        let private = PartitionServices::new();
+        let ov = TabOverrides::partitioned("work");
+
+        let a = resolve_tab_services(zone_id, &services, &config, &ov, Some(&work));
+        let b = resolve_tab_services(zone_id, &services, &config, &ov, Some(&work));
+        let c = resolve_tab_services(zone_id, &services, &config, &ov, Some(&private));
+        let plain = resolve_tab_services(zone_id, &services, &config, &TabOverrides::default(), None);
+
+        assert_eq!(a.cookie_jar, b.cookie_jar);
+        assert!(Arc::ptr_eq(&a.storage, &b.storage));
+        assert_ne!(a.cookie_jar, c.cookie_jar);
+        assert!(!Arc::ptr_eq(&a.storage, &c.storage));
+        assert_eq!(Some(&plain.cookie_jar), services.cookie_jar.as_ref());
+        assert_ne!(a.cookie_jar, plain.cookie_jar);
+        assert_eq!(a.partition_key, PartitionKey::Custom("work".into()));
     }
 }
@@ -0,0 +1,145 @@
+//! Saving and restoring a tab's session, for "restore tabs on startup".
+//!
+//! A [`SessionState`] holds what a tab needs to come back where it was: its history entries,
+//! which of them is current, the scroll position in each and the zoom factor. It serializes to
+//! compact JSON so embedders can store it however they like. Restoring reloads the current
+//! entry from the network; page state (forms, scripts) is not kept.
+
+use crate::engine::tab::history::{HistoryEntry, SessionHistory};
+use crate::EngineError;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Bumped whenever the serialized layout changes incompatibly.
+const SESSION_VERSION: u32 = 1;
+
+/// One history entry as stored in a [`SessionState`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SessionEntry {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub title: String,
+    #[serde(default)]
+    pub scroll: (i32, i32),
+}
+
+/// A snapshot of a tab's session.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SessionState {
+    pub version: u32,
+    pub entries: Vec<SessionEntry>,
+    /// Index into `entries` of the current page.
+    pub index: usize,
+    /// Page zoom factor. Kept for the UA's benefit; the engine renders at 1.0 for now.
+    #[serde(default = "default_zoom")]
+    pub zoom: f32,
+}
+
+fn default_zoom() -> f32 {
+    1.0
+}
+
+impl SessionState {
+    /// Snapshots `history`. `scroll` is the live scroll offset, which the current entry only
+    /// records when it's navigated away from.
+    pub(crate) fn capture(history: &SessionHistory, scroll: (i32, i32), zoom: f32) -> Self {
+        let index = history.index().unwrap_or(0);
+        let entries = history
+            .entries()
+            .iter()
+            .enumerate()
+            .map(|(i, e)| SessionEntry {
+                url: e.url.to_string(),
+                title: e.title.clone(),
+                scroll: if Some(i) == history.index() { scroll } else { e.scroll },
+            })
+            .collect();
+        Self {
+            version: SESSION_VERSION,
+            entries,
+            index,
+            zoom,
+        }
+    }
+
+    /// The session's history. Entries whose URL no longer parses are dropped, and the current
+    /// index follows the entry it pointed at.
+    pub(crate) fn to_history(&self) -> SessionHistory {
+        let mut index = self.index;
+        let mut entries = Vec::with_capacity(self.entries.len());
+        for (i, e) in self.entries.iter().enumerate() {
+            match Url::parse(&e.url) {
+                Ok(url) => entries.push(HistoryEntry {
+                    title: e.title.clone(),
+                    scroll: e.scroll,
+                    ..HistoryEntry::new(url)
+                }),
+                Err(_) if i < self.index => index -= 1,
+                Err(_) => {}
+            }
+        }
+        let mut history = SessionHistory::new();
+        history.restore(entries, index);
+        history
+    }
+
+    /// Compact JSON encoding.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap_or_default()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, EngineError> {
+        let state: SessionState =
+            serde_json::from_slice(bytes).map_err(|e| EngineError::InvalidSession(e.to_string()))?;
+        if state.version > SESSION_VERSION {
+            return Err(EngineError::InvalidSession(format!(
+                "unsupported session version {}",
+                state.version
+            )));
+        }
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(urls: &[&str]) -> SessionHistory {
+        let mut history = SessionHistory::new();
+        for url in urls {
+            let mut entry = HistoryEntry::new(Url::parse(url).unwrap());
+            entry.scroll = (0, 100);
+            history.push(entry);
+        }
+        history
+    }
+
+    #[test]
+    fn round_trip() {
+        let state = SessionState::capture(&history(&["https://a.example/", "https://b.example/"]), (0, 640), 1.25);
+        assert_eq!(state.entries[0].scroll, (0, 100));
+        assert_eq!(state.entries[1].scroll, (0, 640), "current entry takes the live scroll");
+
+        let decoded = SessionState::from_bytes(&state.to_bytes()).unwrap();
+        assert_eq!(decoded, state);
+
+        let restored = decoded.to_history();
+        assert_eq!(restored.index(), Some(1));
+        assert_eq!(restored.current().unwrap().url.as_str(), "https://b.example/");
+        assert_eq!(restored.current().unwrap().scroll, (0, 640));
+    }
+
+    #[test]
+    fn bad_entries_and_versions() {
+        let json = br#"{"version":1,"entries":[{"url":"not a url"},{"url":"https://a.example/"}],"index":1}"#;
+        let state = SessionState::from_bytes(json).unwrap();
+        assert_eq!(state.zoom, 1.0);
+        let history = state.to_history();
+        assert_eq!(history.entries().len(), 1);
+        assert_eq!(history.index(), Some(0));
+
+        assert!(SessionState::from_bytes(br#"{"version":99,"entries":[],"index":0}"#).is_err());
+        assert!(SessionState::from_bytes(b"garbage").is_err());
+    }
+}
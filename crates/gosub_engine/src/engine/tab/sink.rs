@@ -3,6 +3,7 @@ use parking_lot::RwLock;
 use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 use std::sync::OnceLock;
 use std::time::Instant;
+use url::Url;
 
 /// Things shared upwards to the zone
 pub struct TabSink {
@@ -16,6 +17,11 @@ pub struct TabSink {
     pub nav_id: RwLock<Option<NavigationId>>,
     /// Last time we painted a frame
     pub last_paint: RwLock<Option<Instant>>,
+    /// Last time the worker finished a unit of work and went back to waiting; read by the
+    /// watchdog
+    pub last_serviced: RwLock<Instant>,
+    /// URL of the committed document, so a hung tab can be respawned where it was
+    pub current_url: RwLock<Option<Url>>,
 }
 
 impl Default for TabSink {
@@ -32,6 +38,8 @@ impl TabSink {
             last_fps_times100: AtomicU32::new(0),
             nav_id: RwLock::new(None),
             last_paint: RwLock::new(None),
+            last_serviced: RwLock::new(Instant::now()),
+            current_url: RwLock::new(None),
         }
     }
 
@@ -50,4 +58,10 @@ impl TabSink {
     pub fn set_nav(&self, id: NavigationId) {
         *self.nav_id.write() = Some(id);
     }
+    pub fn mark_serviced(&self) {
+        *self.last_serviced.write() = Instant::now();
+    }
+    pub fn set_current_url(&self, url: Url) {
+        *self.current_url.write() = Some(url);
+    }
 }
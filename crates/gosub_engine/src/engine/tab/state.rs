@@ -18,6 +18,8 @@ pub(crate) struct TabRuntime {
     pub last_tick_draw: std::time::Instant,
     /// Scene epoch of the last submitted frame; skip re-render when it matches the context's epoch.
     pub committed_scene_epoch: u64,
+    /// Layout epoch of the last published accessibility tree.
+    pub a11y_layout_epoch: u64,
 }
 
 impl Default for TabRuntime {
@@ -40,6 +42,7 @@ impl TabRuntime {
             render_now: false,
             last_tick_draw: std::time::Instant::now(),
             committed_scene_epoch: u64::MAX,
+            a11y_layout_epoch: u64::MAX,
         }
     }
 }
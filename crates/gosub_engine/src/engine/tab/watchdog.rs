@@ -0,0 +1,144 @@
+//! Hang detection for tab workers.
+//!
+//! Every tab gets a small watchdog task. The worker stamps [`TabSink::last_serviced`] each time
+//! it goes back to waiting for work; a worker is considered stuck when commands sit in its
+//! channel while that stamp stays the same for longer than the configured timeout. The watchdog
+//! then reports [`EngineEvent::TabUnresponsive`], and [`EngineEvent::TabResponsive`] once the
+//! worker catches up. Recovering is up to the UA, see
+//! [`Zone::respawn_tab`](crate::zone::Zone::respawn_tab).
+
+use crate::engine::events::EngineEvent;
+use crate::engine::types::{EventChannel, TabChannel};
+use crate::tab::{TabId, TabSink};
+use crate::util::spawn_named;
+use crate::zone::ZoneId;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// A change in a worker's responsiveness.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Transition {
+    /// Stalled for this long
+    Unresponsive(Duration),
+    Responsive,
+}
+
+/// Tracks one worker across watchdog checks.
+#[derive(Debug, Default)]
+struct StallDetector {
+    /// `last_serviced` as seen at the previous check
+    last_seen: Option<Instant>,
+    /// When a stall (work pending, no progress) was first observed
+    stalled_since: Option<Instant>,
+    reported: bool,
+}
+
+impl StallDetector {
+    /// Feeds one check: the current time, the worker's `last_serviced` stamp and whether
+    /// commands are waiting. Returns a transition when the worker crosses `timeout` or recovers.
+    fn observe(
+        &mut self,
+        now: Instant,
+        last_serviced: Instant,
+        pending: bool,
+        timeout: Duration,
+    ) -> Option<Transition> {
+        let progressed = self.last_seen != Some(last_serviced);
+        self.last_seen = Some(last_serviced);
+
+        if progressed || !pending {
+            self.stalled_since = None;
+            if std::mem::take(&mut self.reported) {
+                return Some(Transition::Responsive);
+            }
+            return None;
+        }
+
+        // An idle worker's stamp is old by the time a command arrives, so the stall is timed from
+        // when it was first seen rather than from the stamp.
+        let since = *self.stalled_since.get_or_insert(now);
+        let stalled = now.duration_since(since);
+        if !self.reported && stalled >= timeout {
+            self.reported = true;
+            return Some(Transition::Unresponsive(stalled));
+        }
+        None
+    }
+}
+
+/// Starts the watchdog for a tab. It stops by itself when the worker's channel closes; the zone
+/// aborts it when it replaces the tab.
+pub(crate) fn spawn_watchdog(
+    tab_id: TabId,
+    zone_id: ZoneId,
+    cmd_tx: TabChannel,
+    sink: Arc<TabSink>,
+    event_tx: EventChannel,
+    timeout: Duration,
+) -> JoinHandle<()> {
+    spawn_named("tab-watchdog", async move {
+        let mut detector = StallDetector::default();
+        let mut interval = tokio::time::interval(timeout / 4);
+        loop {
+            interval.tick().await;
+            if cmd_tx.is_closed() {
+                break;
+            }
+            let pending = cmd_tx.capacity() < cmd_tx.max_capacity();
+            let event = match detector.observe(Instant::now(), *sink.last_serviced.read(), pending, timeout) {
+                Some(Transition::Unresponsive(stalled)) => {
+                    log::warn!("Tab {tab_id} has not serviced its commands for {stalled:?}");
+                    EngineEvent::TabUnresponsive {
+                        tab_id,
+                        zone_id,
+                        stalled,
+                    }
+                }
+                Some(Transition::Responsive) => EngineEvent::TabResponsive { tab_id, zone_id },
+                None => continue,
+            };
+            let _ = event_tx.send(event);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn stall_is_reported_once_and_recovery_follows() {
+        let start = Instant::now();
+        let stamp = start;
+        let mut d = StallDetector::default();
+
+        // Idle for a long time with nothing queued: fine.
+        assert_eq!(d.observe(start + Duration::from_secs(60), stamp, false, TIMEOUT), None);
+        // A command arrives; the stale stamp alone doesn't count as a hang.
+        assert_eq!(d.observe(start + Duration::from_secs(61), stamp, true, TIMEOUT), None);
+        assert_eq!(d.observe(start + Duration::from_secs(64), stamp, true, TIMEOUT), None);
+        assert_eq!(
+            d.observe(start + Duration::from_secs(66), stamp, true, TIMEOUT),
+            Some(Transition::Unresponsive(Duration::from_secs(5)))
+        );
+        assert_eq!(d.observe(start + Duration::from_secs(70), stamp, true, TIMEOUT), None);
+
+        // The worker moves again.
+        let later = start + Duration::from_secs(71);
+        assert_eq!(d.observe(later, later, true, TIMEOUT), Some(Transition::Responsive));
+        assert_eq!(d.observe(later + Duration::from_secs(1), later, false, TIMEOUT), None);
+    }
+
+    #[test]
+    fn busy_but_progressing_worker_is_not_stalled() {
+        let start = Instant::now();
+        let mut d = StallDetector::default();
+        for i in 0..20 {
+            let now = start + Duration::from_secs(i);
+            assert_eq!(d.observe(now, now, true, TIMEOUT), None);
+        }
+    }
+}
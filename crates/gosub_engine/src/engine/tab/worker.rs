@@ -1,27 +1,54 @@
 use crate::cookies::SameSiteContext;
 use crate::engine::errors::NavigationError;
-use crate::engine::events::{EngineEvent, NavigationEvent};
+use crate::engine::events::{CancelReason, EngineEvent, NavigationEvent};
+use crate::engine::favicon::{self, Favicon, FAVICON_SIZE};
+use crate::engine::forms::{FormMethod, FormSubmission, SelectedFiles};
+use crate::engine::keyboard;
+use crate::engine::permissions::{ConfigPermissionStore, DocumentPermissions};
 use crate::engine::resource_pipeline::ResourcePipelines;
+use crate::engine::script::{
+    apply_mutations, load_module_graph, module_response, module_scripts, ClipboardAccess, ClipboardReply,
+    ClipboardRequestId, DomEvent, DomEventInit, DomTree, ModuleCache, PermissionReply, PermissionRequestId,
+    RedirectMode, ScriptError, ScriptFetchRequest, ScriptRequest, ScriptThread,
+};
 use crate::engine::types::{NavigationId, RequestId};
+use crate::engine::user_content::{RunAt, UserContent};
 use crate::engine::{BrowsingContext, UaPolicy};
-use crate::events::{IoCommand, TabCommand};
-use crate::html::RenderConfiguration;
+use crate::events::{IoCommand, Modifiers, ScrollToBehavior, TabCommand};
+use crate::html::{EngineDocument, RenderConfiguration};
+use crate::net::blocking::RequestType;
+use crate::net::cors::{CredentialsMode, RequestMode};
+use crate::net::mixed_content::{MixedContentGuard, MixedContentPolicy};
+use crate::net::redirect::take_redirect_chain;
 use crate::net::req_ref_tracker::{RequestReference, REF_REGISTRY};
 use crate::net::types::{FetchRequest, FetchResult, Initiator, NetError, Priority, ResourceKind};
-use crate::net::{route_response_for, submit_to_io, RequestDestination, RoutedOutcome};
+use crate::net::{route_response_for, submit_to_io, FetchError, RequestDestination, RoutedOutcome};
 use crate::storage::types::compute_partition_key;
-use crate::storage::StorageHandles;
-use crate::tab::scroll::{default_text_scroll, ScrollState};
+use crate::storage::{IndexedDb, PartitionKey, StorageHandles};
+use crate::tab::frames::{FrameFetcher, FrameLoad, FrameSet};
+use crate::tab::history::{HistoryEntry, SessionHistory};
+use crate::tab::media_fetch::TabMediaFetcher;
+use crate::tab::script_fetch::{script_fetch, CORS_ENFORCEMENT};
+use crate::tab::scroll::{default_programmatic_scroll, default_text_scroll, ScrollState};
 use crate::tab::services::EffectiveTabServices;
+use crate::tab::session::SessionState;
 use crate::tab::state::{TabRuntime, TabState};
 use crate::tab::{TabId, TabSink};
 use crate::util::spawn_named;
 use crate::zone::{ZoneContext, ZoneId};
 use anyhow::{anyhow, Context};
+use gosub_css3::media::{ColorScheme, MediaEnvironment};
+use gosub_fontmanager::web_fonts::{FontDisplay, FontFetch, FontLoadOutcome, FontLoader, WebFontFace};
 use gosub_render_pipeline::rasterizer::RasterStrategy;
 use gosub_render_pipeline::render::backend::{CompositorSink, ErasedSurface, PresentMode, RenderBackend, SurfaceSize};
 use gosub_render_pipeline::render::Viewport;
+use gosub_shared::animation::ScrollBehavior;
+use gosub_shared::error_report::{self, in_tab, ErrorReport, ErrorSource};
+use gosub_shared::node::NodeId;
+use gosub_web_platform::permissions::{PermissionName, PermissionState, PermissionStore};
+use gosub_web_platform::permissions_policy::PermissionsPolicy;
 use http::{HeaderMap, Method};
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use tokio::select;
 use tokio::sync::{mpsc, oneshot};
@@ -37,6 +64,16 @@ fn about_blank() -> Url {
     Url::parse("about:blank").unwrap()
 }
 
+/// The policy of a document from the `Permissions-Policy` headers of its response.
+fn permissions_policy(headers: &HeaderMap) -> PermissionsPolicy {
+    let values: Vec<&str> = headers
+        .get_all("permissions-policy")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .collect();
+    PermissionsPolicy::parse(&values.join(","))
+}
+
 #[derive(Debug)]
 pub enum NavigationResult<C: RenderConfiguration> {
     Ok {
@@ -44,6 +81,8 @@ pub enum NavigationResult<C: RenderConfiguration> {
         final_url: Url,
         title: Option<String>,
         doc: Arc<crate::html::EngineDocument<C>>,
+        /// From the response's `Permissions-Policy` header
+        policy: PermissionsPolicy,
     },
     Err {
         nav_id: NavigationId,
@@ -51,6 +90,19 @@ pub enum NavigationResult<C: RenderConfiguration> {
     },
 }
 
+/// How a navigation moves through the session history once it commits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum HistoryNav {
+    /// A new page: appended after the current entry.
+    Push,
+    /// Back/Forward to the entry at this index.
+    Traverse(usize),
+    /// The current entry loaded again.
+    Reload,
+    /// A new page in place of the current entry (`location.replace()`).
+    Replace,
+}
+
 // Current active navigation
 struct ActiveNav {
     pub nav_id: NavigationId,
@@ -87,10 +139,14 @@ pub struct TabWorker<C: RenderConfiguration> {
     /// State of the tab (idle, loading, loaded, etc.)
     pub state: TabState,
 
-    /// Favicon binary data for the current tab
-    pub favicon: Vec<u8>,
+    /// Favicon of the current page, once loaded
+    pub favicon: Option<Favicon>,
+    /// The tab whose page opened this one, if it allowed the reference
+    pub opener: Option<TabId>,
     /// Title of the current tab
     pub title: String,
+    /// The document's `<title>` as last seen, to notice when it changes
+    doc_title: Option<String>,
     /// URL that ready to load or is loading
     pub pending_url: Option<Url>,
     /// Current URL that is now loaded
@@ -123,6 +179,65 @@ pub struct TabWorker<C: RenderConfiguration> {
     load: Option<NavJoin<C>>,
     /// Current active navigation (if any)
     active_nav: Option<ActiveNav>,
+    /// Back/Forward list of this tab
+    history: SessionHistory,
+    /// What the in-flight navigation does to `history` when it commits
+    history_nav: HistoryNav,
+    /// Page zoom factor, saved and restored with the session
+    zoom: f32,
+    /// Child browsing contexts of the document's iframes
+    frames: FrameSet<C>,
+    /// Finished frame loads
+    frame_rx: mpsc::UnboundedReceiver<FrameLoad<C>>,
+    /// Committed navigation whose `Finished` waits for its images and frames
+    finishing: Option<(NavigationId, Url)>,
+    /// Favicon fetches report back here with the page they were for
+    favicon_tx: mpsc::UnboundedSender<(Url, Option<Favicon>)>,
+    favicon_rx: mpsc::UnboundedReceiver<(Url, Option<Favicon>)>,
+    /// Key commands waiting for the page's listeners to see the ones before them
+    pending_keys: VecDeque<TabCommand>,
+    /// A key event is with the page's listeners
+    key_in_flight: bool,
+    /// The page canceled the last `keydown`, so the characters it typed don't fire `keypress`
+    keypress_suppressed: bool,
+    key_tx: mpsc::UnboundedSender<(TabCommand, bool)>,
+    key_rx: mpsc::UnboundedReceiver<(TabCommand, bool)>,
+    /// The `@font-face` fonts of the current document
+    fonts: FontLoader,
+    font_tx: mpsc::UnboundedSender<(FontFetch, Result<Vec<u8>, String>)>,
+    font_rx: mpsc::UnboundedReceiver<(FontFetch, Result<Vec<u8>, String>)>,
+    /// Cancels the downloads of the current document's web fonts
+    font_loads: CancellationToken,
+    /// Cancels the media downloads of the current document
+    media_loads: CancellationToken,
+    /// Cancels the download of the current document's favicon
+    favicon_load: CancellationToken,
+    /// Files the user picked for the document's file inputs
+    input_files: SelectedFiles,
+    /// Scripts and styles injected into matching pages
+    user_content: UserContent,
+    /// Runs the page's JavaScript, once something needs it
+    script: Option<ScriptThread>,
+    /// What the page's scripts ask of the worker, such as `fetch()` calls
+    script_request_tx: mpsc::UnboundedSender<ScriptRequest>,
+    script_request_rx: mpsc::UnboundedReceiver<ScriptRequest>,
+    /// The page's `fetch()` calls in flight, by document and call id
+    script_fetches: HashMap<(u64, u32), CancellationToken>,
+    /// Cancels the loading of the current document's module scripts
+    module_loads: CancellationToken,
+    /// The page's clipboard requests waiting for the UA's answer
+    clipboard_requests: HashMap<ClipboardRequestId, ClipboardReply>,
+    /// The permission decisions of the tab's zone, kept in its settings, as far as the current
+    /// document's `Permissions-Policy` allows them
+    permissions: Arc<DocumentPermissions>,
+    /// The page's permission requests waiting for the UA's answer, with the origin and permission
+    /// they are for
+    permission_requests: HashMap<PermissionRequestId, (String, PermissionName, PermissionReply)>,
+    /// The nodes the page's scripts created in the current document, by the id the script thread
+    /// gave them
+    script_nodes: HashMap<usize, NodeId>,
+    /// The UA's color scheme, for `prefers-color-scheme`
+    color_scheme: ColorScheme,
 }
 
 /// Whether a CSS `unicode-range` descriptor (e.g. `"U+0000-00FF, U+0131"`) includes the
@@ -150,111 +265,6 @@ fn unicode_range_covers_basic_latin(range: &str) -> bool {
     false
 }
 
-/// Unwrap a downloaded web-font payload into raw SFNT bytes the font backends can decode.
-///
-/// WOFF2 (magic `wOF2`) is a Brotli-compressed wrapper around an OpenType/TrueType font,
-/// with the `glyf`/`loca` tables stored in a transformed form. Skia and fontconfig don't
-/// decode it (e.g. Google Fonts serves WOFF2 to modern UAs like ours), so we decompress it
-/// to a flat SFNT here. Bare SFNT (`OTTO`/`true`/`ttcf`/`0x00010000`) and anything we don't
-/// recognise are returned unchanged - including WOFF1, which the backends already handle.
-/// On a decode error we log and return the original bytes so the subsequent `register_font`
-/// surfaces a single, consistent failure path.
-fn decode_web_font(bytes: Vec<u8>, font_url: &Url) -> Vec<u8> {
-    const WOFF2_MAGIC: &[u8; 4] = b"wOF2";
-    if bytes.len() < 4 || &bytes[0..4] != WOFF2_MAGIC {
-        return bytes;
-    }
-    match woff2_to_sfnt(&bytes) {
-        Ok(sfnt) => {
-            log::debug!(
-                "Decoded WOFF2 web font from {font_url} ({} → {} bytes)",
-                bytes.len(),
-                sfnt.len()
-            );
-            sfnt
-        }
-        Err(e) => {
-            log::warn!("Failed to decode WOFF2 web font from {font_url}: {e}");
-            bytes
-        }
-    }
-}
-
-/// Decompress a WOFF2 font to a flat SFNT (TTF/OTF) byte buffer. allsorts handles the Brotli
-/// decompression and the `glyf`/`loca` transform reconstruction; we then re-assemble the
-/// reconstructed tables into the on-disk SFNT layout (offset table + table directory + 4-byte
-/// aligned table data) that font backends expect.
-fn woff2_to_sfnt(bytes: &[u8]) -> Result<Vec<u8>, String> {
-    use allsorts::binary::read::ReadScope;
-    use allsorts::woff2::Woff2Font;
-
-    let font = ReadScope::new(bytes)
-        .read::<Woff2Font<'_>>()
-        .map_err(|e| format!("parse: {e:?}"))?;
-    let sfnt_version = font.flavor();
-    let tables = font
-        .table_provider(0)
-        .map_err(|e| format!("reconstruct: {e:?}"))?
-        .into_tables();
-
-    Ok(assemble_sfnt(sfnt_version, tables))
-}
-
-/// Pack a set of font tables into an SFNT byte buffer per the OpenType spec: a 12-byte offset
-/// table, a 16-byte directory entry per table (sorted by tag), then each table's data padded to
-/// a 4-byte boundary. Per-table checksums are computed; the `head` table's `checkSumAdjustment`
-/// is left as-is (font backends parse without validating it).
-fn assemble_sfnt(sfnt_version: u32, tables: std::collections::HashMap<u32, Box<[u8]>>) -> Vec<u8> {
-    let mut entries: Vec<(u32, Box<[u8]>)> = tables.into_iter().collect();
-    entries.sort_by_key(|(tag, _)| *tag);
-    let num_tables = entries.len() as u16;
-
-    // Binary-search hint fields: largest power of two <= num_tables.
-    let mut entry_selector = 0u16;
-    while (1u16 << (entry_selector + 1)) <= num_tables {
-        entry_selector += 1;
-    }
-    let search_range = (1u16 << entry_selector) * 16;
-    let range_shift = num_tables.wrapping_mul(16).wrapping_sub(search_range);
-
-    let mut directory = Vec::with_capacity(16 * entries.len());
-    let mut data = Vec::new();
-    let mut offset = 12 + 16 * entries.len();
-    for (tag, table) in &entries {
-        directory.extend_from_slice(&tag.to_be_bytes());
-        directory.extend_from_slice(&sfnt_table_checksum(table).to_be_bytes());
-        directory.extend_from_slice(&(offset as u32).to_be_bytes());
-        directory.extend_from_slice(&(table.len() as u32).to_be_bytes());
-        data.extend_from_slice(table);
-        while data.len() % 4 != 0 {
-            data.push(0);
-        }
-        offset += (table.len() + 3) & !3;
-    }
-
-    let mut out = Vec::with_capacity(12 + directory.len() + data.len());
-    out.extend_from_slice(&sfnt_version.to_be_bytes());
-    out.extend_from_slice(&num_tables.to_be_bytes());
-    out.extend_from_slice(&search_range.to_be_bytes());
-    out.extend_from_slice(&entry_selector.to_be_bytes());
-    out.extend_from_slice(&range_shift.to_be_bytes());
-    out.extend_from_slice(&directory);
-    out.extend_from_slice(&data);
-    out
-}
-
-/// SFNT table checksum: the sum of the table's contents read as big-endian `u32`s, with the
-/// final partial word zero-padded, in wrapping (mod 2^32) arithmetic.
-fn sfnt_table_checksum(data: &[u8]) -> u32 {
-    let mut sum = 0u32;
-    for chunk in data.chunks(4) {
-        let mut word = [0u8; 4];
-        word[..chunk.len()].copy_from_slice(chunk);
-        sum = sum.wrapping_add(u32::from_be_bytes(word));
-    }
-    sum
-}
-
 /// Parse a `unicode-range` hex bound, expanding `?` wildcards to `0` (low bound) or `F`
 /// (high bound), e.g. `U+00??` → `0x0000..=0x00FF`.
 fn parse_hex_bound(s: &str, high: bool) -> Option<u32> {
@@ -279,6 +289,44 @@ fn parse_hex_bound(s: &str, high: bool) -> Option<u32> {
     u32::from_str_radix(&filled, 16).ok()
 }
 
+/// Fetches the favicon candidates in order through the tab's fetcher and returns the first that
+/// decodes. Icons are no-cors loads carrying cookies, like images.
+async fn fetch_favicon(
+    fetcher: &FrameFetcher,
+    page_url: &Url,
+    candidates: &[favicon::IconCandidate],
+    cancel: CancellationToken,
+) -> Option<Favicon> {
+    for candidate in candidates {
+        let request = ScriptFetchRequest {
+            url: candidate.url.to_string(),
+            method: "GET".into(),
+            headers: Vec::new(),
+            body: None,
+            mode: RequestMode::NoCors,
+            credentials: CredentialsMode::Include,
+            redirect: RedirectMode::Follow,
+        };
+        match script_fetch(fetcher, page_url, ResourceKind::Image, false, request, cancel.clone()).await {
+            Ok(resp) if (200..300).contains(&resp.status) && !resp.body.is_empty() => {
+                let url = candidate.url.clone();
+                let decoded =
+                    tokio::task::spawn_blocking(move || favicon::decode_favicon(&url, &resp.body, FAVICON_SIZE)).await;
+                if let Ok(Some(icon)) = decoded {
+                    return Some(icon);
+                }
+                log::debug!("Favicon {} could not be decoded", candidate.url);
+            }
+            Ok(resp) => log::debug!("Favicon {} returned status {}", candidate.url, resp.status),
+            Err(e) => log::debug!("Favicon {} failed: {e}", candidate.url),
+        }
+        if cancel.is_cancelled() {
+            break;
+        }
+    }
+    None
+}
+
 impl<C: RenderConfiguration> TabWorker<C> {
     /// Creates a new tab. Does NOT spawn the tab worker
     pub fn new(
@@ -292,6 +340,13 @@ impl<C: RenderConfiguration> TabWorker<C> {
         let config_store = zone_context.config_store.clone();
         let context = BrowsingContext::new(config_store.clone());
         let runtime = TabRuntime::with_fps(config_store.get_uint("renderer.tab.default_fps") as u32);
+        let (frames, frame_rx) = FrameSet::new();
+        let (favicon_tx, favicon_rx) = mpsc::unbounded_channel();
+        let (key_tx, key_rx) = mpsc::unbounded_channel();
+        let (font_tx, font_rx) = mpsc::unbounded_channel();
+        let (script_request_tx, script_request_rx) = mpsc::unbounded_channel();
+        let permission_store =
+            ConfigPermissionStore::new(config_store.clone()).with_handler(services.permission_handler.clone());
 
         Self {
             tab_id,
@@ -302,8 +357,10 @@ impl<C: RenderConfiguration> TabWorker<C> {
             cmd_rx,
             context,
             state: TabState::Idle,
-            favicon: vec![],
+            favicon: None,
+            opener: None,
             title: config_store.get_string("useragent.tab.default_title"),
+            doc_title: None,
             pending_url: None,
             current_url: None,
             is_loading: false,
@@ -319,13 +376,45 @@ impl<C: RenderConfiguration> TabWorker<C> {
             runtime,
             load: None,
             active_nav: None,
+            history: SessionHistory::new(),
+            history_nav: HistoryNav::Push,
+            zoom: config_store.get_float("useragent.zoom.default") as f32,
+            frames,
+            frame_rx,
+            finishing: None,
+            favicon_tx,
+            favicon_rx,
+            pending_keys: VecDeque::new(),
+            key_in_flight: false,
+            keypress_suppressed: false,
+            key_tx,
+            key_rx,
+            fonts: FontLoader::new(),
+            font_tx,
+            font_rx,
+            font_loads: CancellationToken::new(),
+            media_loads: CancellationToken::new(),
+            favicon_load: CancellationToken::new(),
+            input_files: SelectedFiles::new(),
+            user_content: UserContent::default(),
+            script: None,
+            script_request_tx,
+            script_request_rx,
+            script_fetches: HashMap::new(),
+            module_loads: CancellationToken::new(),
+            clipboard_requests: HashMap::new(),
+            permissions: Arc::new(DocumentPermissions::new(Arc::new(permission_store))),
+            permission_requests: HashMap::new(),
+            script_nodes: HashMap::new(),
+            color_scheme: ColorScheme::default(),
         }
     }
 
     /// Spawns the tab worker into a new task and returns the join handle
     pub fn spawn_worker(self) -> anyhow::Result<JoinHandle<()>> {
         let name = format!("Tab Worker {}", self.tab_id);
-        let join_handle = spawn_named(&name, self.run_worker());
+        let tab_id = self.tab_id;
+        let join_handle = spawn_named(&name, in_tab(tab_id, self.run_worker()));
 
         Ok(join_handle)
     }
@@ -346,6 +435,8 @@ impl<C: RenderConfiguration> TabWorker<C> {
         let mut pending_nav_rx: Option<oneshot::Receiver<NavigationResult<C>>> = None;
 
         loop {
+            self.sink.mark_serviced();
+
             // Sync pending_nav_rx with self.load so a freshly-set load is picked up.
             // Only take the receiver; leave self.load so the cancel token remains
             // reachable for CancelNavigation commands.
@@ -364,6 +455,7 @@ impl<C: RenderConfiguration> TabWorker<C> {
                         self.state = TabState::Failed(format!("Tab {:?} tick error: {}", self.tab_id, e));
                         self.runtime.dirty = true;
                     }
+                    self.publish_accessibility_tree();
                 }
 
                 // In-flight load completion - uses a persistent receiver so it is not
@@ -383,20 +475,39 @@ impl<C: RenderConfiguration> TabWorker<C> {
                     }
                 }
 
+                // The page's listeners saw a key event
+                Some((cmd, not_canceled)) = self.key_rx.recv() => {
+                    self.on_key_dispatched(cmd, not_canceled);
+                    self.render_if_requested().await;
+                }
+
+                // A favicon fetch finished
+                Some((page_url, icon)) = self.favicon_rx.recv() => {
+                    self.on_favicon_loaded(page_url, icon);
+                }
+
+                // A web font download finished
+                Some((fetch, response)) = self.font_rx.recv() => {
+                    self.on_web_font_loaded(fetch, response);
+                }
+
+                // A child frame finished loading
+                Some(load) = self.frame_rx.recv() => {
+                    self.on_frame_loaded(load);
+                }
+
+                // The page's scripts want something from the network
+                Some(request) = self.script_request_rx.recv() => {
+                    self.on_script_request(request);
+                }
+
                 // Handle incoming tab commands from the UA
                 msg = self.cmd_rx.recv() => {
                     let Some(cmd) = msg else { break; };
                     if self.handle_tab_command(cmd).is_break() {
                         break;
                     }
-                    // If the command (e.g. hover change) requested an immediate render,
-                    // call tick_draw now instead of waiting up to 1/fps seconds for the tick.
-                    if std::mem::replace(&mut self.runtime.render_now, false) {
-                        if let Err(e) = self.tick_draw().await {
-                            self.state = TabState::Failed(format!("Tab {:?} immediate render error: {}", self.tab_id, e));
-                            self.runtime.dirty = true;
-                        }
-                    }
+                    self.render_if_requested().await;
                 }
             }
         }
@@ -409,102 +520,401 @@ impl<C: RenderConfiguration> TabWorker<C> {
         self.services.storage.drop_tab(self.zone_id, self.tab_id);
     }
 
-    /// Fetch and register any `@font-face` web fonts declared in the document's stylesheets
-    /// so the first layout/paint can use them. Runs once per navigation, before the first
-    /// render, and deduplicates by resolved font URL. Fetches are synchronous (blocking this
-    /// worker briefly during initial load); each face is registered under its CSS family so
-    /// the font system selects the right weight/style from the font's own metadata.
-    fn load_web_fonts(&self, doc: &C::Document, base_url: &Url) {
+    /// Starts downloading the `@font-face` web fonts declared in the document's stylesheets,
+    /// through the tab's fetcher, in place of those of the previous document. Faces are
+    /// registered under their CSS family as they arrive (see [`Self::on_web_font_loaded`]);
+    /// until then the first paint waits for the faces in their `font-display` block period.
+    fn load_web_fonts(&mut self, doc: &C::Document, base_url: &Url) {
         use gosub_interface::css3::CssStylesheet as _;
         use gosub_interface::document::Document as _;
-        use gosub_interface::font_system::FontSystem as _;
 
-        let mut fetched: std::collections::HashSet<String> = std::collections::HashSet::new();
+        std::mem::take(&mut self.font_loads).cancel();
+
+        let mut faces = Vec::new();
+        let mut seen: std::collections::HashSet<Url> = std::collections::HashSet::new();
         for sheet in doc.stylesheets() {
             let sheet_url = Url::parse(sheet.url()).ok();
-            for (family, sources, unicode_range) in sheet.font_faces() {
+            for face in sheet.font_faces() {
                 // Google-style web fonts split a family into many `unicode-range` subsets
                 // (latin, cyrillic, greek, …). We don't do per-glyph subset fallback, so
-                // register only subsets covering Basic Latin (and ranges with no descriptor),
+                // load only subsets covering Basic Latin (and ranges with no descriptor),
                 // which covers Latin-script content without piling unusable subsets onto the
                 // same family.
-                if let Some(range) = &unicode_range {
+                if let Some(range) = &face.unicode_range {
                     if !unicode_range_covers_basic_latin(range) {
                         continue;
                     }
                 }
-                for src in &sources {
-                    let resolved = sheet_url
-                        .as_ref()
-                        .unwrap_or(base_url)
-                        .join(src)
-                        .or_else(|_| base_url.join(src));
-                    let Ok(font_url) = resolved else { continue };
-                    if !fetched.insert(font_url.to_string()) {
-                        break; // this exact font file is already registered
-                    }
-                    match gosub_sonar::net::simple::sync_fetch(&font_url) {
-                        Ok(resp) if resp.status == 200 && !resp.body.is_empty() => {
-                            // Web fonts are commonly served as WOFF2 (e.g. Google Fonts content-
-                            // negotiates WOFF2 for modern UAs like ours). The font backends
-                            // (Skia/fontconfig) only decode raw SFNT (TTF/OTF), so unwrap WOFF2
-                            // to TTF first. Other formats pass through unchanged.
-                            let font_bytes = decode_web_font(resp.body, &font_url);
-                            match self
-                                .zone_context
-                                .font_system
-                                .lock()
-                                .register_font(font_bytes, Some(&family))
-                            {
-                                Ok(()) => {
-                                    log::debug!("Registered web font '{family}' from {font_url}");
-                                    break; // family face loaded; skip remaining sources
-                                }
-                                Err(e) => log::warn!("Failed to register web font '{family}': {e:?}"),
-                            }
-                        }
-                        Ok(resp) => log::warn!("Web font fetch {font_url} returned status {}", resp.status),
-                        Err(e) => log::warn!("Web font fetch {font_url} failed: {e}"),
-                    }
+                let sources: Vec<Url> = face
+                    .sources
+                    .iter()
+                    .filter_map(|src| {
+                        sheet_url
+                            .as_ref()
+                            .unwrap_or(base_url)
+                            .join(src)
+                            .or_else(|_| base_url.join(src))
+                            .ok()
+                    })
+                    .collect();
+                // The same font file declared twice is loaded once.
+                if sources.first().is_some_and(|first| !seen.insert(first.clone())) {
+                    continue;
+                }
+                faces.push(WebFontFace {
+                    family: face.family,
+                    sources: sources.iter().map(Url::to_string).collect(),
+                    display: face.display.as_deref().and_then(FontDisplay::parse).unwrap_or_default(),
+                });
+            }
+        }
+
+        let fetches = self.fonts.start(faces, std::time::Instant::now());
+        for fetch in fetches {
+            self.fetch_web_font(fetch, base_url);
+        }
+    }
+
+    /// Has the context's media (images, SVGs and what SVGs refer to) fetched through the tab's
+    /// fetcher on behalf of the document at `url`, cancelling the downloads of the previous one.
+    fn fetch_media_for(&mut self, url: &Url) {
+        std::mem::take(&mut self.media_loads).cancel();
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let fetcher = TabMediaFetcher::new(self.frame_fetcher(), url.clone(), runtime, self.media_loads.clone());
+        self.context.set_media_fetcher(Arc::new(fetcher));
+    }
+
+    /// Downloads one web font source, reporting back to [`Self::on_web_font_loaded`]. Font
+    /// requests are CORS requests, like the browser makes them.
+    fn fetch_web_font(&self, fetch: FontFetch, base_url: &Url) {
+        let fetcher = self.frame_fetcher();
+        let base = base_url.clone();
+        let enforce_cors = self.zone_context.config_store.get_bool(CORS_ENFORCEMENT);
+        let cancel = self.font_loads.clone();
+        let tx = self.font_tx.clone();
+        spawn_named("web-font-fetch", async move {
+            let request = ScriptFetchRequest {
+                url: fetch.url.clone(),
+                method: "GET".into(),
+                headers: Vec::new(),
+                body: None,
+                mode: RequestMode::Cors,
+                credentials: CredentialsMode::SameOrigin,
+                redirect: RedirectMode::Follow,
+            };
+            let response = match script_fetch(
+                &fetcher,
+                &base,
+                ResourceKind::Font,
+                enforce_cors,
+                request,
+                cancel.clone(),
+            )
+            .await
+            {
+                Ok(response) if (200..300).contains(&response.status) => Ok(response.body),
+                Ok(response) => Err(format!("status {}", response.status)),
+                Err(e) => Err(e),
+            };
+            if !cancel.is_cancelled() {
+                let _ = tx.send((fetch, response));
+            }
+        });
+    }
+
+    /// A web font download finished: the loader registers the font, or picks the next source.
+    /// A registered font changes how text measures, so the page is laid out again.
+    fn on_web_font_loaded(&mut self, fetch: FontFetch, response: Result<Vec<u8>, String>) {
+        let url = fetch.url.clone();
+        let outcome = {
+            let mut fonts = self.zone_context.font_system.lock();
+            self.fonts
+                .finish(fetch, response, &mut *fonts, std::time::Instant::now())
+        };
+        match outcome {
+            FontLoadOutcome::Loaded { family } => {
+                log::debug!("Registered web font '{family}' from {url}");
+                self.context.fonts_changed();
+                for child in self.frames.iter_mut().filter_map(|f| f.context.as_mut()) {
+                    child.fonts_changed();
+                }
+                self.runtime.dirty = true;
+            }
+            FontLoadOutcome::Retry { fetch, error } => {
+                log::debug!("Web font {url} failed ({error}), trying {}", fetch.url);
+                if let Some(base) = self.current_url.clone() {
+                    self.fetch_web_font(fetch, &base);
+                }
+            }
+            FontLoadOutcome::Failed { family, error } => {
+                error_report::report(
+                    ErrorReport::error(ErrorSource::Net, format!("Failed to load web font '{family}': {error}"))
+                        .with_url(url.as_str()),
+                );
+            }
+            FontLoadOutcome::Expired { family } => {
+                log::debug!("Web font '{family}' arrived after its swap period, not used");
+            }
+            FontLoadOutcome::Stale => {}
+        }
+    }
+
+    /// Adds the user styles matching `url` to a freshly loaded document as user-origin sheets.
+    /// Documents arrive here fully parsed, so document-start and document-end styles alike are
+    /// in place before the first paint.
+    fn inject_user_styles(&self, doc: &mut Arc<EngineDocument<C>>, url: &Url) {
+        use gosub_interface::css3::{CssOrigin, CssSystem};
+        use gosub_interface::document::Document as _;
+        use gosub_shared::config::{Context as ParseContext, ParserConfig};
+
+        let mut styles = self
+            .user_content
+            .styles_for(url, RunAt::DocumentStart)
+            .chain(self.user_content.styles_for(url, RunAt::DocumentEnd))
+            .peekable();
+        if styles.peek().is_none() {
+            return;
+        }
+        let Some(doc) = Arc::get_mut(doc) else {
+            log::warn!(
+                "Tab {:?}: document already shared, user styles not injected",
+                self.tab_id
+            );
+            return;
+        };
+        for style in styles {
+            let source_url = format!("user-style:{}", style.id);
+            let config = ParserConfig {
+                context: ParseContext::Stylesheet,
+                location: Default::default(),
+                source: Some(source_url.clone()),
+                ignore_errors: true,
+                match_values: false,
+            };
+            match C::CssSystem::parse_str(&style.css, config, CssOrigin::User, &source_url) {
+                Ok(sheet) => doc.add_stylesheet(sheet),
+                Err(e) => error_report::report(
+                    ErrorReport::warning(ErrorSource::Css, format!("User style failed to parse: {e}"))
+                        .with_url(source_url),
+                ),
+            }
+        }
+    }
+
+    /// Runs the user scripts registered for `url` at `run_at` in the page's JS context.
+    fn run_user_scripts(&mut self, url: &Url, run_at: RunAt) {
+        let sources: Vec<String> = self
+            .user_content
+            .scripts_for(url, run_at)
+            .map(|script| script.source.clone())
+            .collect();
+        if sources.is_empty() {
+            return;
+        }
+        let tab_id = self.tab_id;
+        match self.script_thread() {
+            Some(thread) => sources.into_iter().for_each(|source| thread.run(source)),
+            None => log::debug!("Tab {tab_id}: user scripts match {url}, but scripting is disabled"),
+        }
+    }
+
+    /// Loads the module graphs of the document's `<script type="module">`s through the tab's
+    /// fetcher and hands them to the script thread, in document order.
+    fn load_module_scripts(&mut self, doc: &EngineDocument<C>, url: &Url) {
+        let scripts = module_scripts(doc, url);
+        if scripts.is_empty() {
+            return;
+        }
+        let tab_id = self.tab_id;
+        let Some(queue) = self.script_thread().map(ScriptThread::module_queue) else {
+            log::debug!("Tab {tab_id}: {url} has module scripts, but scripting is disabled");
+            return;
+        };
+        let fetcher = self.frame_fetcher();
+        let base = url.clone();
+        let enforce_cors = self.zone_context.config_store.get_bool(CORS_ENFORCEMENT);
+        let cancel = self.module_loads.clone();
+        spawn_named("module-scripts", async move {
+            let fetch = |url: Url| {
+                let request = ScriptFetchRequest {
+                    url: url.to_string(),
+                    method: "GET".into(),
+                    headers: Vec::new(),
+                    body: None,
+                    mode: RequestMode::Cors,
+                    credentials: CredentialsMode::SameOrigin,
+                    redirect: RedirectMode::Follow,
+                };
+                let (fetcher, base, cancel) = (&fetcher, &base, cancel.clone());
+                async move {
+                    let response = script_fetch(
+                        fetcher,
+                        base,
+                        ResourceKind::Script { blocking: false },
+                        enforce_cors,
+                        request,
+                        cancel,
+                    )
+                    .await?;
+                    module_response(&url, response)
+                }
+            };
+            let mut cache = ModuleCache::new();
+            for script in scripts {
+                if cancel.is_cancelled() {
+                    return;
+                }
+                match load_module_graph(&script, &mut cache, fetch).await {
+                    Ok((entry, graph)) => queue.run(entry, graph),
+                    Err(e) => error_report::report(
+                        ErrorReport::error(ErrorSource::Net, format!("Module script failed to load: {e}"))
+                            .with_url(script.name()),
+                    ),
+                }
+            }
+        });
+    }
+
+    /// The tab's script thread, started on first use. `None` when the tab has no script host or
+    /// the thread can't be started.
+    fn script_thread(&mut self) -> Option<&ScriptThread> {
+        if self.script.is_none() {
+            let host = self.services.script_host.as_ref()?;
+            let requests = self.script_request_tx.clone();
+            let url = self.current_url.clone();
+            let indexed_db = url.as_ref().and_then(|url| self.indexed_db_for(url));
+            let dom = self.context.document().map(DomTree::snapshot);
+            let permissions: Arc<dyn PermissionStore> = self.permissions.clone();
+            let name = format!("tab-script-{}", self.tab_id);
+            match ScriptThread::spawn(host, name, requests, url, indexed_db, dom, permissions) {
+                Ok(thread) => {
+                    thread.set_media(self.media_environment());
+                    self.script = Some(thread);
+                }
+                Err(e) => {
+                    log::error!("Tab {:?}: failed to start the script thread: {e}", self.tab_id);
+                    return None;
                 }
             }
         }
+        self.script.as_ref()
+    }
+
+    /// What the page's media queries are evaluated against.
+    fn media_environment(&self) -> MediaEnvironment {
+        MediaEnvironment {
+            width: self.desired_viewport.width as f32,
+            height: self.desired_viewport.height as f32,
+            device_pixel_ratio: self.zone_context.render_backend.device_pixel_ratio() as f32,
+            color_scheme: self.color_scheme,
+        }
+    }
+
+    /// Hands the media environment to the script thread, which fires `change` at the page's
+    /// `MediaQueryList`s if that changed their result.
+    fn update_media(&self) {
+        if let Some(script) = &self.script {
+            script.set_media(self.media_environment());
+        }
+    }
+
+    /// The IndexedDB databases a document at `url` works on.
+    fn indexed_db_for(&self, url: &Url) -> Option<IndexedDb> {
+        let partition = compute_partition_key(url, self.services.partition_policy);
+        self.services
+            .storage
+            .indexed_db_for(self.zone_id, &partition, &url.origin())
     }
 
     fn on_nav_result(&mut self, res: NavigationResult<C>) {
+        let nav_id = match &res {
+            NavigationResult::Ok { nav_id, .. } | NavigationResult::Err { nav_id, .. } => *nav_id,
+        };
+        // A cancelled navigation may still deliver a result; the tab has moved on.
+        if self.active_nav.as_ref().map(|a| a.nav_id) != Some(nav_id) {
+            log::debug!("Tab {:?} dropping result of stale navigation {:?}", self.tab_id, nav_id);
+            return;
+        }
+        let active = self.active_nav.take();
+
         match res {
             NavigationResult::Ok {
                 nav_id,
                 final_url,
                 title,
-                doc,
+                mut doc,
+                policy,
             } => {
+                // The old page's links are gone; clear any status-bar preview.
+                if self.context.hover_link_url.is_some() {
+                    self.send_event(EngineEvent::HoverUrl {
+                        tab_id: self.tab_id,
+                        url: None,
+                    });
+                }
+                self.send_event(EngineEvent::Navigation {
+                    tab_id: self.tab_id,
+                    event: NavigationEvent::Committed {
+                        nav_id,
+                        url: final_url.clone(),
+                    },
+                });
+                self.inject_user_styles(&mut doc, &final_url);
                 self.context.set_document(Arc::clone(&doc));
-                self.load_web_fonts(&doc, &final_url);
+                self.permissions
+                    .set_policy(final_url.origin().ascii_serialization(), policy);
+                self.fetch_media_for(&final_url);
+                // Set before the document's scripts run, which may start the script thread.
                 self.current_url = Some(final_url.clone());
-                if let Some(t) = title {
-                    self.title = t;
+                let indexed_db = self.indexed_db_for(&final_url);
+                self.script_nodes.clear();
+                if let Some(script) = &mut self.script {
+                    for (_, cancel) in self.script_fetches.drain() {
+                        cancel.cancel();
+                    }
+                    std::mem::take(&mut self.module_loads).cancel();
+                    self.clipboard_requests.clear();
+                    self.permission_requests.clear();
+                    script.new_document(Some(final_url.clone()), indexed_db, Some(DomTree::snapshot(&doc)));
                 }
+                self.run_user_scripts(&final_url, RunAt::DocumentStart);
+                self.input_files.clear();
+                self.load_web_fonts(&doc, &final_url);
+                let fetcher = self.frame_fetcher();
+                self.frames
+                    .load_all(self.context.frame_elements(), &final_url, &fetcher);
+                self.sink.set_current_url(final_url.clone());
+                self.load_favicon(&doc, &final_url);
+                self.commit_history(&final_url);
+                self.apply_document_title(title);
                 self.is_loading = false;
                 self.is_error = false;
                 self.state = TabState::Idle;
                 self.runtime.dirty = true;
 
+                self.load_module_scripts(&doc, &final_url);
+                self.run_user_scripts(&final_url, RunAt::DocumentEnd);
                 self.send_event(EngineEvent::Navigation {
                     tab_id: self.tab_id,
-                    event: NavigationEvent::Finished { nav_id, url: final_url },
+                    event: NavigationEvent::DomContentLoaded {
+                        nav_id,
+                        url: final_url.clone(),
+                    },
                 });
+                // `Finished` follows once layout has run and nothing is downloading any more.
+                self.finishing = Some((nav_id, final_url));
             }
             NavigationResult::Err { nav_id, error } => {
+                self.history_nav = HistoryNav::Push;
                 self.is_loading = false;
                 self.is_error = true;
                 self.state = TabState::Failed(error.to_string());
                 self.runtime.dirty = true;
 
-                let url = self
-                    .active_nav
-                    .as_ref()
-                    .map(|a| a.url.clone())
+                let url = active
+                    .map(|a| a.url)
                     .or_else(|| self.pending_url.clone())
                     .unwrap_or_else(about_blank);
 
@@ -520,25 +930,118 @@ impl<C: RenderConfiguration> TabWorker<C> {
         }
     }
 
+    /// Records a committed navigation in the session history and, when returning to an entry,
+    /// restores its scroll positions unless the page asked to do that itself.
+    fn commit_history(&mut self, final_url: &Url) {
+        let returning = match std::mem::replace(&mut self.history_nav, HistoryNav::Push) {
+            HistoryNav::Push => {
+                let mut entry = HistoryEntry::new(final_url.clone());
+                entry.title = self.title.clone();
+                self.history.push(entry);
+                false
+            }
+            HistoryNav::Traverse(index) => {
+                self.history.commit_traversal(index, final_url.clone());
+                true
+            }
+            HistoryNav::Reload => true,
+            HistoryNav::Replace => {
+                self.history.replace(HistoryEntry::new(final_url.clone()));
+                false
+            }
+        };
+        let mut restore = None;
+        if let Some(entry) = self.history.current_mut() {
+            entry.title = self.title.clone();
+            if returning {
+                restore = entry
+                    .restored_scroll()
+                    .map(|(scroll, elements)| (scroll, elements.to_vec()));
+            }
+        }
+        let scroll_restoration = self.history.current().map(|e| e.scroll_restoration).unwrap_or_default();
+        if let Some(script) = &self.script {
+            script.set_scroll_restoration(scroll_restoration);
+        }
+
+        if let Some(((x, y), elements)) = restore {
+            if (x, y) != (0, 0) {
+                self.scroll.reset(x as f64, y as f64);
+                self.scroll_x = x;
+                self.scroll_y = y;
+                self.context.set_scroll(x as f64, y as f64);
+            }
+            self.context.restore_element_scroll(&elements);
+        }
+
+        self.send_event(EngineEvent::HistoryChanged {
+            tab_id: self.tab_id,
+            can_go_back: self.history.can_go_back(),
+            can_go_forward: self.history.can_go_forward(),
+        });
+    }
+
+    /// If a command (e.g. hover change) requested an immediate render, calls tick_draw now
+    /// instead of waiting up to 1/fps seconds for the tick.
+    async fn render_if_requested(&mut self) {
+        if std::mem::replace(&mut self.runtime.render_now, false) {
+            if let Err(e) = self.tick_draw().await {
+                self.state = TabState::Failed(format!("Tab {:?} immediate render error: {}", self.tab_id, e));
+                self.runtime.dirty = true;
+            }
+            self.publish_accessibility_tree();
+        }
+    }
+
     fn handle_tab_command(&mut self, cmd: TabCommand) -> ControlFlow {
         match cmd {
             TabCommand::CloseTab => ControlFlow::Break,
             TabCommand::SetTitle { title } => {
+                if let Some(entry) = self.history.current_mut() {
+                    entry.title = title.clone();
+                }
                 self.title = title;
                 ControlFlow::Continue
             }
+            TabCommand::SetOpener { opener } => {
+                self.opener = opener;
+                ControlFlow::Continue
+            }
             TabCommand::Navigate { url } => {
                 self.navigate_to(&url, false);
                 ControlFlow::Continue
             }
             TabCommand::Reload { ignore_cache } => {
-                let url = self
-                    .current_url
-                    .as_ref()
-                    .map(|u| u.as_str())
-                    .unwrap_or("about:blank")
-                    .to_string();
-                self.navigate_to(url.as_str(), ignore_cache);
+                self.reload(ignore_cache);
+                ControlFlow::Continue
+            }
+            TabCommand::GoBack => {
+                self.traverse_history(-1);
+                ControlFlow::Continue
+            }
+            TabCommand::GoForward => {
+                self.traverse_history(1);
+                ControlFlow::Continue
+            }
+            TabCommand::SaveSession => {
+                let state = SessionState::capture(&self.history, (self.scroll_x, self.scroll_y), self.zoom);
+                self.send_event(EngineEvent::SessionSaved {
+                    tab_id: self.tab_id,
+                    state,
+                });
+                ControlFlow::Continue
+            }
+            TabCommand::RestoreSession { state } => {
+                let history = state.to_history();
+                self.zoom = state.zoom;
+                if let Some(index) = history.index() {
+                    let url = history.get(index).map(|e| e.url.to_string()).unwrap_or_default();
+                    // Start from an empty list so navigating doesn't stamp the current scroll
+                    // offset onto the restored entry; the restored list goes in afterwards.
+                    self.history = SessionHistory::new();
+                    self.navigate_with(url, false, HistoryNav::Traverse(index), None);
+                }
+                self.history = history;
                 ControlFlow::Continue
             }
             TabCommand::SetViewport {
@@ -548,59 +1051,65 @@ impl<C: RenderConfiguration> TabWorker<C> {
                 height,
             } => {
                 self.set_viewport(Viewport::new(0, 0, width, height));
+                self.update_media();
                 self.runtime.dirty = true;
                 ControlFlow::Continue
             }
-            TabCommand::MouseScroll { delta_x, delta_y } => {
-                // When page height is known, clamp to the real maximum so worker and context
-                // stay in sync. When the page hasn't rendered yet, allow free scrolling (the
-                // context will clamp to the actual page height on its own).
-                let max_y = {
-                    let ph = self.context.page_height();
-                    if ph > 0.0 {
-                        (ph - self.desired_viewport.height as f64).max(0.0)
-                    } else {
-                        f64::MAX
-                    }
+            TabCommand::SetColorScheme { scheme } => {
+                self.color_scheme = scheme;
+                self.update_media();
+                self.runtime.dirty = true;
+                ControlFlow::Continue
+            }
+            TabCommand::ScrollTo { x, y, behavior } => {
+                let smooth = match behavior {
+                    ScrollToBehavior::Auto => self.context.prefers_smooth_scroll(),
+                    ScrollToBehavior::Instant => false,
+                    ScrollToBehavior::Smooth => true,
+                };
+                let behavior = if smooth {
+                    default_programmatic_scroll()
+                } else {
+                    ScrollBehavior::Instant
                 };
 
-                match self.scroll.scroll_by(delta_x as f64, delta_y as f64, f64::MAX, max_y) {
-                    // Instant behavior: apply the new offset now and keep the immediate-submit fast
-                    // path (avoids up to 1/fps of latency per scroll event).
+                // Either way any in-flight animation is replaced; restart its clock.
+                self.scroll_anim_last = None;
+                match self
+                    .scroll
+                    .scroll_to(x as f64, y as f64, f64::MAX, self.max_scroll_y(), &behavior)
+                {
                     Some((x, y)) => {
-                        let moved = x != self.scroll_x || y != self.scroll_y;
-                        self.scroll_x = x;
-                        self.scroll_y = y;
-                        self.context.set_scroll(x as f64, y as f64);
-
-                        // GPU-tile-compositing backends skip this CPU TileCache fast path (their
-                        // tiles have no CPU pixels); they re-composite on the next tick.
-                        if self.zone_context.render_backend.raster_strategy() != RasterStrategy::None
-                            && !self.zone_context.render_backend.gpu_tile_compositing()
-                        {
-                            let dpr = self.zone_context.render_backend.device_pixel_ratio();
-                            if let Some(handle) = self.context.take_scroll_handle(dpr) {
-                                self.runtime.committed_scene_epoch = self.context.scene_epoch();
-                                self.zone_context.compositor.submit_frame(self.tab_id, handle);
-                                return ControlFlow::Continue;
-                            }
-                        }
-
-                        // TileCache not ready yet; fall back to the timer path. Only mark dirty if
-                        // the integer offset actually moved (sub-pixel deltas are no-ops).
-                        if moved {
+                        if x != self.scroll_x || y != self.scroll_y {
+                            self.scroll_x = x;
+                            self.scroll_y = y;
+                            self.context.set_scroll(x as f64, y as f64);
                             self.runtime.dirty = true;
                         }
                     }
-                    // Animated behavior: tick_draw advances the ease toward the new target. Request
-                    // an immediate tick so the first frame lands without waiting up to 1/fps.
-                    None => {
-                        self.runtime.render_now = true;
-                    }
+                    // tick_draw eases toward the target from here on.
+                    None => self.runtime.render_now = true,
+                }
+                ControlFlow::Continue
+            }
+            TabCommand::MouseScroll { delta_x, delta_y } => {
+                // A frame or scroll containers under the pointer go first; the page only gets what
+                // they couldn't take.
+                let (fx, fy) = self.scroll_frame(delta_x as f64, delta_y as f64);
+                let (dx, dy) = self.context.scroll_containers(fx, fy);
+                if dx != delta_x as f64 || dy != delta_y as f64 {
+                    self.runtime.dirty = true;
+                    self.runtime.render_now = true;
                 }
+                self.scroll_page_by(dx, dy);
                 ControlFlow::Continue
             }
             TabCommand::MouseMove { x, y } => {
+                if self.context.extend_selection(x as f64, y as f64) {
+                    self.runtime.dirty = true;
+                    self.runtime.render_now = true;
+                }
+
                 // Process the hit-test immediately so hover doesn't wait for the next tick.
                 let (visual_dirty, url_changed, link_url) = self.context.update_hover(x as f64, y as f64);
                 if url_changed {
@@ -609,68 +1118,262 @@ impl<C: RenderConfiguration> TabWorker<C> {
                         url: link_url,
                     });
                 }
+                if let Some(cursor) = self.context.update_cursor() {
+                    self.send_event(EngineEvent::CursorChanged {
+                        tab_id: self.tab_id,
+                        cursor,
+                    });
+                }
                 if visual_dirty {
                     self.runtime.dirty = true;
                     self.runtime.render_now = true;
                 }
+                self.hover_frame(x as f64, y as f64);
                 ControlFlow::Continue
             }
-            TabCommand::MouseDown { button, .. } => {
+            TabCommand::MouseDown { x, y, button } => {
                 if matches!(button, crate::events::MouseButton::Left) {
-                    if let Some(href) = self.context.hover_link_url.clone() {
-                        let resolved = self
-                            .current_url
-                            .as_ref()
-                            .and_then(|base| base.join(&href).ok())
-                            .map(|u| u.to_string())
-                            .unwrap_or(href);
-                        self.navigate_to(resolved, false);
+                    if self.press_in_frame(x as f64, y as f64) {
+                        self.runtime.dirty = true;
                         return ControlFlow::Continue;
                     }
+                    if self.context.press_at(x as f64, y as f64) {
+                        self.runtime.render_now = true;
+                    }
+                    let change = self.context.focus_at(x as f64, y as f64);
+                    self.report_focus_change(change);
+                    if let Some(url) = self.context.hover_link_url.clone() {
+                        match self.context.link_target_at(x as f64, y as f64) {
+                            Some(target) if target.is_new_context() => {
+                                self.request_new_window(&url, target.target, target.opener)
+                            }
+                            _ => self.navigate_to(url, false),
+                        }
+                        return ControlFlow::Continue;
+                    }
+                    if let Some(form) = self.context.submission_at(x as f64, y as f64, &self.input_files) {
+                        self.submit_form(form);
+                        return ControlFlow::Continue;
+                    }
+                    if let Some((node_id, multiple, accept)) = self.context.file_input_at(x as f64, y as f64) {
+                        self.send_event(EngineEvent::FileChooserRequested {
+                            tab_id: self.tab_id,
+                            node_id,
+                            multiple,
+                            accept,
+                        });
+                    }
+                    if let Some(popup) = self.context.select_at(x as f64, y as f64) {
+                        self.send_event(EngineEvent::SelectPopupRequested {
+                            tab_id: self.tab_id,
+                            popup,
+                        });
+                        self.runtime.dirty = true;
+                        return ControlFlow::Continue;
+                    }
+                    // A left press outside a link starts a new text selection.
+                    self.context.begin_selection(x as f64, y as f64);
                 }
                 self.runtime.dirty = true;
                 ControlFlow::Continue
             }
-            TabCommand::MouseUp { .. }
-            | TabCommand::KeyDown { .. }
-            | TabCommand::KeyUp { .. }
-            | TabCommand::CharInput { .. } => {
+            TabCommand::MouseUp { button, .. } => {
+                if matches!(button, crate::events::MouseButton::Left) {
+                    if self.context.release_active() {
+                        self.runtime.render_now = true;
+                    }
+                    self.context.end_selection();
+                    for child in self.frames.iter_mut().filter_map(|f| f.context.as_mut()) {
+                        child.end_selection();
+                    }
+                }
                 self.runtime.dirty = true;
                 ControlFlow::Continue
             }
-            TabCommand::ResumeDrawing { fps: wanted_fps } => {
-                self.runtime.drawing_enabled = true;
-                self.runtime.fps = wanted_fps.max(1) as u32;
-                let period = Duration::from_secs_f64(1.0 / (self.runtime.fps as f64));
-                self.runtime.interval = tokio::time::interval(period);
-                self.runtime
-                    .interval
-                    .set_missed_tick_behavior(MissedTickBehavior::Delay);
+            TabCommand::CopySelection => {
+                if let Some(text) = self.context.selected_text() {
+                    self.send_event(EngineEvent::ClipboardWrite {
+                        tab_id: self.tab_id,
+                        text,
+                    });
+                }
+                ControlFlow::Continue
+            }
+            TabCommand::SetPaintDebug { debug } => {
+                self.context.set_paint_debug(debug);
                 self.runtime.dirty = true;
                 ControlFlow::Continue
             }
-            TabCommand::SuspendDrawing => {
-                self.runtime.drawing_enabled = false;
+            TabCommand::Print { setup } => {
+                let pages = self.context.print_pages(setup);
+                self.send_event(EngineEvent::PrintReady {
+                    tab_id: self.tab_id,
+                    pages: Arc::new(pages),
+                });
                 ControlFlow::Continue
             }
-            TabCommand::CancelNavigation => {
-                if let Some(load) = self.load.take() {
-                    log::warn!("Cancelling in-flight load for tab {:?}", self.tab_id);
-                    load.cancel.cancel();
+            TabCommand::CaptureFullPage => {
+                let dpr = self.zone_context.render_backend.device_pixel_ratio();
+                match self.context.capture_full_page(dpr) {
+                    Ok(Some(capture)) => self.send_event(EngineEvent::FullPageCaptured {
+                        tab_id: self.tab_id,
+                        capture: Arc::new(capture),
+                    }),
+                    Ok(None) => {}
+                    Err(e) => error_report::report(ErrorReport::error(
+                        ErrorSource::Render,
+                        format!("Full-page capture failed: {e}"),
+                    )),
                 }
                 ControlFlow::Continue
             }
-            TabCommand::SubmitDecision {
-                decision_token, action, ..
-            } => {
-                // Proxy the submit decision to the I/O thread
-                let _ = self.zone_context.io_tx.send(IoCommand::Decision {
-                    token: decision_token,
-                    action,
+            TabCommand::HitTest { x, y } => {
+                let (vp_x, vp_y) = (x as f64, y as f64);
+                let in_frame = self.context.frame_at(vp_x, vp_y).and_then(|(node, fx, fy)| {
+                    let child = self.frames.get_mut(node)?.context.as_ref()?;
+                    child.hit_test(fx, fy)
                 });
-
-                // Decisions are handled in the fetcher/io thread, so we can ignore this here
-                ControlFlow::Continue
+                let hit = in_frame.or_else(|| self.context.hit_test(vp_x, vp_y));
+                self.send_event(EngineEvent::HitTested {
+                    tab_id: self.tab_id,
+                    x,
+                    y,
+                    hit,
+                });
+                ControlFlow::Continue
+            }
+            TabCommand::InspectNode { node_id } => {
+                self.context.inspect_node(node_id);
+                self.runtime.dirty = true;
+                ControlFlow::Continue
+            }
+            TabCommand::DescribeNode { node_id } => {
+                self.send_event(EngineEvent::NodeDescribed {
+                    tab_id: self.tab_id,
+                    node_id,
+                    desc: self.context.describe_node(node_id).map(Arc::new),
+                });
+                ControlFlow::Continue
+            }
+            TabCommand::ClearSelection => {
+                if self.context.clear_selection() {
+                    self.runtime.dirty = true;
+                }
+                ControlFlow::Continue
+            }
+            TabCommand::KeyDown { .. } | TabCommand::KeyUp { .. } | TabCommand::CharInput { .. } => {
+                self.pending_keys.push_back(cmd);
+                self.next_key();
+                ControlFlow::Continue
+            }
+            TabCommand::ExecuteScript { source } => {
+                let tab_id = self.tab_id;
+                match self.script_thread() {
+                    Some(thread) => thread.run(source),
+                    None => log::debug!("Tab {tab_id}: scripting is disabled, not running script"),
+                }
+                ControlFlow::Continue
+            }
+            TabCommand::EvaluateScript { id, source } => {
+                let tab_id = self.tab_id;
+                match self.script_thread().map(|thread| thread.evaluate(source)) {
+                    Some(rx) => {
+                        let event_tx = self.zone_context.event_tx.clone();
+                        spawn_named("script-result", async move {
+                            let result = rx
+                                .await
+                                .unwrap_or_else(|_| Err(ScriptError::Runtime("script thread stopped".into())));
+                            let _ = event_tx.send(EngineEvent::ScriptEvaluated { tab_id, id, result });
+                        });
+                    }
+                    None => self.send_event(EngineEvent::ScriptEvaluated {
+                        tab_id,
+                        id,
+                        result: Err(ScriptError::Disabled),
+                    }),
+                }
+                ControlFlow::Continue
+            }
+            TabCommand::AddUserScript { script } => {
+                self.user_content.add_script(script);
+                ControlFlow::Continue
+            }
+            TabCommand::AddUserStyle { style } => {
+                self.user_content.add_style(style);
+                ControlFlow::Continue
+            }
+            TabCommand::RemoveUserContent { id } => {
+                if !self.user_content.remove(id) {
+                    log::debug!("Tab {:?}: no user script or style {id} to remove", self.tab_id);
+                }
+                ControlFlow::Continue
+            }
+            TabCommand::AnswerClipboard { id, answer } => {
+                // Requests of a document that has since been replaced are gone.
+                if let Some(reply) = self.clipboard_requests.remove(&id) {
+                    reply.send(answer.into());
+                }
+                ControlFlow::Continue
+            }
+            TabCommand::AnswerPermission { id, state } => {
+                if let Some((origin, name, reply)) = self.permission_requests.remove(&id) {
+                    // Dismissing the prompt decides nothing, so the page may ask again.
+                    if state != PermissionState::Prompt {
+                        self.permissions.set_state(&origin, name, state);
+                    }
+                    reply.send(state);
+                }
+                ControlFlow::Continue
+            }
+            TabCommand::SetInputFiles { node_id, files } => {
+                if files.is_empty() {
+                    self.input_files.remove(&node_id);
+                } else {
+                    self.input_files.insert(node_id, files);
+                }
+                ControlFlow::Continue
+            }
+            TabCommand::ChooseSelectOptions { node_id, indices } => {
+                if self.context.choose_select_options(node_id, &indices) {
+                    self.runtime.dirty = true;
+                }
+                ControlFlow::Continue
+            }
+            TabCommand::ResumeDrawing { fps: wanted_fps } => {
+                self.runtime.drawing_enabled = true;
+                self.runtime.fps = wanted_fps.max(1) as u32;
+                let period = Duration::from_secs_f64(1.0 / (self.runtime.fps as f64));
+                self.runtime.interval = tokio::time::interval(period);
+                self.runtime
+                    .interval
+                    .set_missed_tick_behavior(MissedTickBehavior::Delay);
+                self.runtime.dirty = true;
+                ControlFlow::Continue
+            }
+            TabCommand::SuspendDrawing => {
+                self.runtime.drawing_enabled = false;
+                ControlFlow::Continue
+            }
+            TabCommand::CancelNavigation => {
+                self.cancel_current_nav(CancelReason::ExplicitCancel);
+                ControlFlow::Continue
+            }
+            TabCommand::Stop => {
+                self.cancel_current_nav(CancelReason::ExplicitCancel);
+                self.frames.stop();
+                ControlFlow::Continue
+            }
+            TabCommand::SubmitDecision {
+                decision_token, action, ..
+            } => {
+                // Proxy the submit decision to the I/O thread
+                let _ = self.zone_context.io_tx.send(IoCommand::Decision {
+                    token: decision_token,
+                    action,
+                });
+
+                // Decisions are handled in the fetcher/io thread, so we can ignore this here
+                ControlFlow::Continue
             }
             _ => {
                 log::warn!("Tab {:?} received unhandled command: {:?}", self.tab_id, cmd);
@@ -679,6 +1382,26 @@ impl<C: RenderConfiguration> TabWorker<C> {
         }
     }
 
+    /// Publishes a fresh accessibility tree to the UA when layout has changed since the last one.
+    /// Does nothing unless `engine.accessibility.enabled` is set.
+    fn publish_accessibility_tree(&mut self) {
+        if !self.zone_context.config_store.get_bool("engine.accessibility.enabled") {
+            return;
+        }
+        let epoch = self.context.layout_epoch();
+        if epoch == self.runtime.a11y_layout_epoch {
+            return;
+        }
+        self.runtime.a11y_layout_epoch = epoch;
+
+        if let Some(tree) = self.context.accessibility_tree() {
+            self.send_event(EngineEvent::AccessibilityTreeChanged {
+                tab_id: self.tab_id,
+                tree: Arc::new(tree),
+            });
+        }
+    }
+
     /// Send an engine event upwards to the UA
     fn send_event(&self, evt: EngineEvent) {
         match self.zone_context.event_tx.send(evt.clone()) {
@@ -690,244 +1413,1061 @@ impl<C: RenderConfiguration> TabWorker<C> {
     }
 
     /// Navigate to a new URL, cancelling any in-flight navigation.
-    fn navigate_to(&mut self, url: impl Into<String>, _ignore_cache: bool) {
+    fn navigate_to(&mut self, url: impl Into<String>, ignore_cache: bool) {
+        self.navigate_with(url, ignore_cache, HistoryNav::Push, None);
+    }
+
+    /// Loads the current URL again, keeping its history entry.
+    fn reload(&mut self, ignore_cache: bool) {
+        let url = self
+            .current_url
+            .as_ref()
+            .map(|u| u.as_str())
+            .unwrap_or("about:blank")
+            .to_string();
+        self.navigate_with(url.as_str(), ignore_cache, HistoryNav::Reload, None);
+    }
+
+    /// Loads the history entry `delta` steps away (negative is back). The entry only becomes
+    /// current once the load commits; does nothing at either end of the list.
+    fn traverse_history(&mut self, delta: isize) {
+        let Some(index) = self.history.offset(delta) else {
+            return;
+        };
+        let Some(url) = self.history.get(index).map(|e| e.url.to_string()) else {
+            return;
+        };
+        self.navigate_with(url, false, HistoryNav::Traverse(index), None);
+    }
+
+    /// Submits a form: a GET navigates to the URL with the entries as query, a POST sends them
+    /// as the request body.
+    fn submit_form(&mut self, form: FormSubmission) {
+        let url = form.url();
+        if crate::html::is_new_context_target(&form.target) {
+            // A new tab can only be handed a URL, so a POST still loads here.
+            if form.method == FormMethod::Get {
+                self.request_new_window(url.as_str(), form.target, false);
+                return;
+            }
+            log::debug!("Submitting POST form with target {:?} in the current tab", form.target);
+        }
+        self.navigate_with(url.as_str(), false, HistoryNav::Push, Some(form));
+    }
+
+    /// Asks the UA to open `url` in a new browsing context; `opener` says whether the new page
+    /// may know this tab.
+    fn request_new_window(&self, url: &str, target: String, opener: bool) {
+        let Ok(url) = Url::parse(url) else {
+            log::debug!("Not opening unparsable URL {url}");
+            return;
+        };
+        self.send_event(EngineEvent::NewWindowRequested {
+            tab_id: self.tab_id,
+            url,
+            target,
+            features: String::new(),
+            opener: opener.then_some(self.tab_id),
+        });
+    }
+
+    fn navigate_with(
+        &mut self,
+        url: impl Into<String>,
+        _ignore_cache: bool,
+        history_nav: HistoryNav,
+        form: Option<FormSubmission>,
+    ) {
+        // Remember where the user was, so coming back to this entry restores it.
+        let scroll = (self.scroll_x, self.scroll_y);
+        let element_scroll = self.context.element_scroll_offsets();
+        if let Some(entry) = self.history.current_mut() {
+            entry.scroll = scroll;
+            entry.element_scroll = element_scroll;
+        }
+        self.history_nav = history_nav;
+
         self.scroll_x = 0;
         self.scroll_y = 0;
         self.scroll.reset(0.0, 0.0);
         self.scroll_anim_last = None;
         self.context.reset_scroll();
         // Cancel any previous running navigation in this tab
-        self.cancel_current_nav();
+        self.cancel_current_nav(CancelReason::NewNavigation);
+        self.finishing = None;
 
         let url = match self.parse_url(url.into()) {
             Ok(u) => u,
             Err(_) => return,
         };
 
-        if let Err(e) = self.bind_storage_for(url.clone()) {
-            self.send_event(EngineEvent::Navigation {
+        if let Some(rule) = self
+            .services
+            .content_blocker
+            .as_ref()
+            .and_then(|b| b.check(&url, RequestType::Document, self.current_url.as_ref()))
+        {
+            self.send_event(EngineEvent::Navigation {
+                tab_id: self.tab_id,
+                event: NavigationEvent::Failed {
+                    nav_id: None,
+                    url,
+                    error: Arc::new(NavigationError::Blocked(rule.to_string()).into()),
+                },
+            });
+            return;
+        }
+
+        if let Err(e) = self.bind_storage_for(url.clone()) {
+            self.send_event(EngineEvent::Navigation {
+                tab_id: self.tab_id,
+                event: NavigationEvent::Failed {
+                    nav_id: None,
+                    url: url.clone(),
+                    error: Arc::new(e),
+                },
+            });
+            return;
+        }
+
+        let nav_id = NavigationId::new();
+        let parent_cancel = CancellationToken::new();
+        self.active_nav = Some(ActiveNav {
+            nav_id,
+            cancel: parent_cancel.clone(),
+            url: url.clone(),
+        });
+
+        {
+            let mut guard = self.zone_context.request_reference_map.write();
+            guard.insert(RequestReference::Navigation(nav_id), self.tab_id);
+        }
+
+        self.sink.set_nav(nav_id);
+        self.pending_url = Some(url.clone());
+        self.is_loading = true;
+        self.is_error = false;
+        self.state = TabState::Loading;
+        self.runtime.dirty = true;
+
+        self.send_event(EngineEvent::Navigation {
+            tab_id: self.tab_id,
+            event: NavigationEvent::Started {
+                nav_id,
+                url: url.clone(),
+            },
+        });
+
+        // Attach cookies for the navigation request.
+        let mut fetch_headers = HeaderMap::new();
+        self.services
+            .cookie_jar
+            .add_request_cookies(&mut fetch_headers, &url, Some(&url), SameSiteContext::SameSite);
+        if let Some(langs) = &self.services.accept_language {
+            if let Ok(val) = langs.parse() {
+                fetch_headers.insert(http::header::ACCEPT_LANGUAGE, val);
+            }
+        }
+
+        let req_id = RequestId::new();
+        REF_REGISTRY.register_request(req_id, ResourceKind::Document, Initiator::Navigation);
+        let method = match &form {
+            Some(form) if form.method == FormMethod::Post => Method::POST,
+            _ => Method::GET,
+        };
+        let builder = FetchRequest::builder(method, url.clone())
+            .with_reference(REF_REGISTRY.to_net(RequestReference::Navigation(nav_id)))
+            .with_req_id(req_id)
+            .with_priority(Priority::High)
+            .with_kind(ResourceKind::Document.to_net())
+            .with_initiator(Initiator::Navigation.to_net())
+            // Use buffered mode so the full document body is available before parsing.
+            // The streaming path has a race where SharedBody can close before parse_stream
+            // subscribes, causing truncated HTML (only the 5 KB peek buffer is parsed).
+            .with_streaming(false)
+            .with_auto_decode(true);
+
+        let (tx_done, rx_done) = oneshot::channel::<NavigationResult<C>>();
+
+        let tab_id = self.tab_id;
+        let zone_id = self.zone_id;
+        let partition = self.cache_partition(Some(&url));
+        let io_tx = self.zone_context.io_tx.clone();
+        let event_tx = self.zone_context.event_tx.clone();
+        let cookie_jar = self.services.cookie_jar.clone();
+        let accept_language = self.services.accept_language.clone();
+        let max_document_bytes = self.zone_context.config_store.get_uint("net.document.max_bytes");
+        let content_blocker = self.services.content_blocker.clone();
+        let media = self.media_environment();
+        let mixed_content = self.mixed_content();
+
+        let span = tracing::info_span!(
+            "tab_nav",
+            tab_id=%tab_id,
+            nav_id=%nav_id.0,
+            scheme=%url.scheme(),
+            host=%url.host_str().unwrap_or(""),
+            path=%url.path(),
+        );
+
+        let parent_cancel_clone = parent_cancel.clone();
+
+        // Spawn the actual fetcher into a separate task
+        spawn_named("tab-fetcher", async move {
+            let _enter = span.enter();
+
+            // A POST form body may include picked files, so it is encoded off the worker.
+            let body = match form {
+                Some(form) => tokio::task::spawn_blocking(move || form.encode_body())
+                    .await
+                    .ok()
+                    .flatten(),
+                None => None,
+            };
+            let mut builder = builder;
+            if let Some(body) = body {
+                if let Ok(val) = body.content_type.parse() {
+                    fetch_headers.insert(http::header::CONTENT_TYPE, val);
+                }
+                builder = builder.with_body(body.into());
+            }
+            let req = builder.with_headers(fetch_headers).build();
+
+            let submit = submit_to_io(
+                zone_id,
+                partition.clone(),
+                req.clone(),
+                io_tx.clone(),
+                Some(parent_cancel_clone.clone()),
+            )
+            .await;
+
+            let pending = match submit {
+                Ok(pending) => pending,
+                Err(_) => {
+                    let _ = tx_done.send(NavigationResult::Err {
+                        nav_id,
+                        error: NavigationError::NetworkError("I/O channel closed".into()),
+                    });
+                    return;
+                }
+            };
+            let handle = pending.handle().clone();
+
+            let fetch_result: FetchResult = match pending.result().await {
+                Ok(result) => result,
+                Err(FetchError::Redirect(e)) => {
+                    let _ = tx_done.send(NavigationResult::Err {
+                        nav_id,
+                        error: NavigationError::NetworkError(e.to_string()),
+                    });
+                    return;
+                }
+                Err(e) => {
+                    let reason = match e {
+                        FetchError::Aborted => "Navigation cancelled",
+                        _ => "Response channel closed",
+                    };
+                    let _ = tx_done.send(NavigationResult::Err {
+                        nav_id,
+                        error: NavigationError::Cancelled(reason.into()),
+                    });
+                    return;
+                }
+            };
+
+            if let Some(meta) = fetch_result.meta() {
+                // Report every hop; a fetcher that didn't report them still shows where it ended up.
+                let mut hops: Vec<_> = take_redirect_chain(handle.req_id)
+                    .hops
+                    .into_iter()
+                    .map(|hop| (hop.from, hop.to))
+                    .collect();
+                if hops.is_empty() && meta.final_url != url {
+                    hops.push((url.clone(), meta.final_url.clone()));
+                }
+                for (from, to) in hops {
+                    let _ = event_tx.send(EngineEvent::Navigation {
+                        tab_id,
+                        event: NavigationEvent::Redirected { nav_id, from, to },
+                    });
+                }
+                let _ = event_tx.send(EngineEvent::Navigation {
+                    tab_id,
+                    event: NavigationEvent::ResponseReceived {
+                        nav_id,
+                        url: meta.final_url.clone(),
+                        status: meta.status,
+                        content_type: meta
+                            .headers
+                            .get(http::header::CONTENT_TYPE)
+                            .and_then(|v| v.to_str().ok())
+                            .map(|s| s.to_string()),
+                    },
+                });
+            }
+
+            let policy = fetch_result
+                .meta()
+                .map(|meta| permissions_policy(&meta.headers))
+                .unwrap_or_default();
+
+            // Store Set-Cookie headers from the navigation response.
+            if let Some(meta) = fetch_result.meta() {
+                cookie_jar
+                    .write()
+                    .store_response_cookies(&meta.final_url, &meta.headers, Some(&url));
+            }
+
+            let ua_policy = UaPolicy {
+                enable_sniffing: true,
+                enable_sniffing_navigation_upgrade: false,
+                enable_pdf_viewer: false,
+                allow_download_without_user_activation: false,
+            };
+
+            let mut hooks = ResourcePipelines::<C>::new(
+                zone_id,
+                partition,
+                io_tx.clone(),
+                accept_language.clone(),
+                max_document_bytes,
+                content_blocker,
+                Some(cookie_jar.clone()),
+                media,
+                mixed_content,
+            );
+
+            // Stopping mid-parse drops the parse; its subresource fetches share the cancel token.
+            let outcome = tokio::select! {
+                _ = parent_cancel_clone.cancelled() => {
+                    let _ = tx_done.send(NavigationResult::Err {
+                        nav_id,
+                        error: NavigationError::Cancelled("Navigation stopped".into()),
+                    });
+                    return;
+                }
+                outcome = route_response_for(
+                    RequestDestination::Document,
+                    handle,
+                    req.clone(),
+                    fetch_result.clone(),
+                    &ua_policy,
+                    &mut hooks,
+                ) => outcome,
+            };
+
+            match outcome {
+                Ok(RoutedOutcome::MainDocument(doc)) => {
+                    use gosub_interface::document::Document as _;
+                    let final_url = doc.url().unwrap_or_else(about_blank);
+                    let title = crate::html::document_title(&doc);
+                    let _ = tx_done.send(NavigationResult::Ok {
+                        nav_id,
+                        final_url,
+                        title,
+                        doc,
+                        policy,
+                    });
+                }
+                Ok(RoutedOutcome::ViewerRendered(_doc)) => {
+                    log::warn!("Tab[{:?}] viewer rendering not supported yet", tab_id);
+                    let _ = tx_done.send(NavigationResult::Err {
+                        nav_id,
+                        error: NavigationError::Other(anyhow!("Viewer rendering not supported yet")),
+                    });
+                }
+                // Subresource outcomes need no main-frame navigation handling.
+                Ok(
+                    RoutedOutcome::CssLoaded(_)
+                    | RoutedOutcome::ScriptExecuted(_)
+                    | RoutedOutcome::ImageDecoded(_)
+                    | RoutedOutcome::FontLoaded(_),
+                ) => {
+                    log::trace!("Tab[{:?}] subresource outcome; nothing to do for navigation", tab_id);
+                }
+                Ok(RoutedOutcome::Blocked(reason)) => {
+                    log::debug!("Tab[{:?}] RoutedOutcome::Blocked", tab_id);
+
+                    let final_url = match fetch_result.meta() {
+                        Some(meta) => meta.final_url.clone(),
+                        None => url.clone(),
+                    };
+
+                    _ = event_tx.send(EngineEvent::Navigation {
+                        tab_id,
+                        event: NavigationEvent::Failed {
+                            nav_id: Some(nav_id),
+                            url: final_url.clone(),
+                            error: Arc::new(anyhow!("Reason: {}", reason)),
+                        },
+                    });
+                }
+                Err(e) => {
+                    let err = format!("Routing error: {e}");
+                    let _ = tx_done.send(NavigationResult::Err {
+                        nav_id,
+                        error: NavigationError::NetworkError(err),
+                    });
+                }
+            }
+        });
+
+        self.load = Some(NavJoin {
+            cancel: parent_cancel.clone(),
+            rx: Some(rx_done),
+        });
+    }
+
+    fn on_script_request(&mut self, request: ScriptRequest) {
+        match request {
+            ScriptRequest::Fetch { request, reply } => {
+                // The document may have been replaced since the call; its reply would be dropped.
+                if self.script.as_ref().map(ScriptThread::document) != Some(reply.key().0) {
+                    return;
+                }
+                let Some(base) = self.current_url.clone() else {
+                    reply.send(Err("the document has no URL".into()));
+                    return;
+                };
+                // Finished fetches cancel their own token, so they can be dropped here.
+                self.script_fetches.retain(|_, cancel| !cancel.is_cancelled());
+                let cancel = CancellationToken::new();
+                self.script_fetches.insert(reply.key(), cancel.clone());
+
+                let fetcher = self.frame_fetcher();
+                let enforce_cors = self.zone_context.config_store.get_bool(CORS_ENFORCEMENT);
+                spawn_named("script-fetch", async move {
+                    let outcome = script_fetch(
+                        &fetcher,
+                        &base,
+                        ResourceKind::Fetch,
+                        enforce_cors,
+                        request,
+                        cancel.clone(),
+                    )
+                    .await;
+                    cancel.cancel();
+                    reply.send(outcome);
+                });
+            }
+            ScriptRequest::AbortFetch { document, id } => {
+                if let Some(cancel) = self.script_fetches.remove(&(document, id)) {
+                    cancel.cancel();
+                }
+            }
+            ScriptRequest::Navigate { document, url, replace } => {
+                // A replaced document can't navigate any more.
+                if self.script.as_ref().map(ScriptThread::document) != Some(document) {
+                    return;
+                }
+                let history_nav = if replace { HistoryNav::Replace } else { HistoryNav::Push };
+                self.navigate_with(url.as_str(), false, history_nav, None);
+            }
+            ScriptRequest::Reload { document } => {
+                if self.script.as_ref().map(ScriptThread::document) == Some(document) {
+                    self.reload(false);
+                }
+            }
+            ScriptRequest::TraverseHistory { document, delta } => {
+                if self.script.as_ref().map(ScriptThread::document) != Some(document) {
+                    return;
+                }
+                // `history.go(0)` reloads.
+                if delta == 0 {
+                    self.reload(false);
+                } else {
+                    self.traverse_history(delta);
+                }
+            }
+            ScriptRequest::ScrollRestoration { document, mode } => {
+                if self.script.as_ref().map(ScriptThread::document) != Some(document) {
+                    return;
+                }
+                if let Some(entry) = self.history.current_mut() {
+                    entry.scroll_restoration = mode;
+                }
+            }
+            ScriptRequest::Clipboard { access, reply } => {
+                if self.script.as_ref().map(ScriptThread::document) != Some(reply.document()) {
+                    return;
+                }
+                let Some(url) = &self.current_url else {
+                    reply.send(Err("the document has no URL".into()));
+                    return;
+                };
+                let origin = url.origin().ascii_serialization();
+                let name = match access {
+                    ClipboardAccess::ReadText => PermissionName::ClipboardRead,
+                    ClipboardAccess::WriteText { .. } => PermissionName::ClipboardWrite,
+                };
+                // A refusal needs no UA round trip; anything else is still the UA's to answer.
+                if self.permissions.state(&origin, name) == PermissionState::Denied {
+                    reply.send(Err(format!("the {name} permission is denied")));
+                    return;
+                }
+                let id = ClipboardRequestId::new();
+                self.send_event(EngineEvent::ClipboardRequested {
+                    tab_id: self.tab_id,
+                    id,
+                    origin,
+                    access,
+                });
+                self.clipboard_requests.insert(id, reply);
+            }
+            ScriptRequest::Permission { name, reply } => {
+                if self.script.as_ref().map(ScriptThread::document) != Some(reply.document()) {
+                    return;
+                }
+                let origin = match &self.current_url {
+                    Some(url) => url.origin().ascii_serialization(),
+                    None => "null".to_string(),
+                };
+                let state = self.permissions.state(&origin, name);
+                if state != PermissionState::Prompt {
+                    reply.send(state);
+                    return;
+                }
+                let id = PermissionRequestId::new();
+                self.send_event(EngineEvent::PermissionRequested {
+                    tab_id: self.tab_id,
+                    id,
+                    origin: origin.clone(),
+                    name,
+                });
+                self.permission_requests.insert(id, (origin, name, reply));
+            }
+            ScriptRequest::ShowNotification {
+                document,
+                id,
+                notification,
+            } => {
+                if self.script.as_ref().map(ScriptThread::document) != Some(document) {
+                    return;
+                }
+                let Some(url) = &self.current_url else {
+                    return;
+                };
+                self.send_event(EngineEvent::NotificationRequested {
+                    tab_id: self.tab_id,
+                    id,
+                    origin: url.origin().ascii_serialization(),
+                    notification,
+                });
+            }
+            ScriptRequest::CloseNotification { document, id } => {
+                if self.script.as_ref().map(ScriptThread::document) == Some(document) {
+                    self.send_event(EngineEvent::NotificationClosed {
+                        tab_id: self.tab_id,
+                        id,
+                    });
+                }
+            }
+            ScriptRequest::Console {
+                document,
+                level,
+                message,
+            } => {
+                if self.script.as_ref().map(ScriptThread::document) != Some(document) {
+                    return;
+                }
+                log::log!(log::Level::from(level), "Tab {:?} console: {message}", self.tab_id);
+                self.send_event(EngineEvent::ConsoleMessage {
+                    tab_id: self.tab_id,
+                    level,
+                    message,
+                });
+            }
+            ScriptRequest::LimitExceeded { document, violation } => {
+                if self.script.as_ref().map(ScriptThread::document) != Some(document) {
+                    return;
+                }
+                log::warn!("Tab {:?}: a script was stopped: {violation}", self.tab_id);
+                self.send_event(EngineEvent::PageUnresponsive {
+                    tab_id: self.tab_id,
+                    violation,
+                });
+            }
+            ScriptRequest::MutateDom { document, mutations } => {
+                if self.script.as_ref().map(ScriptThread::document) != Some(document) {
+                    return;
+                }
+                let nodes = &mut self.script_nodes;
+                if !self
+                    .context
+                    .mutate_document(|doc| apply_mutations(doc, mutations, nodes))
+                {
+                    log::warn!(
+                        "Tab {:?}: document is shared, changes made by scripts are lost",
+                        self.tab_id
+                    );
+                }
+                self.runtime.dirty = true;
+            }
+            ScriptRequest::SubmitForm {
+                document,
+                form,
+                submitter,
+            } => {
+                if self.script.as_ref().map(ScriptThread::document) != Some(document) {
+                    return;
+                }
+                let resolve = |id: usize| self.script_nodes.get(&id).copied().unwrap_or_else(|| NodeId::from(id));
+                let submission = self
+                    .context
+                    .form_submission(resolve(form), submitter.map(resolve), &self.input_files);
+                if let Some(submission) = submission {
+                    self.submit_form(submission);
+                }
+            }
+        }
+    }
+
+    /// Everything a child frame load needs from this tab.
+    fn frame_fetcher(&self) -> FrameFetcher {
+        FrameFetcher {
+            tab_id: self.tab_id,
+            zone_id: self.zone_id,
+            partition: self.cache_partition(self.current_url.as_ref()),
+            request_reference_map: self.zone_context.request_reference_map.clone(),
+            io_tx: self.zone_context.io_tx.clone(),
+            cookie_jar: self.services.cookie_jar.clone(),
+            accept_language: self.services.accept_language.clone(),
+            max_document_bytes: self.zone_context.config_store.get_uint("net.document.max_bytes"),
+            content_blocker: self.services.content_blocker.clone(),
+            media: self.media_environment(),
+            mixed_content: self.mixed_content(),
+        }
+    }
+
+    /// The storage partition the tab's loads under a top-level document at `url` are cached in:
+    /// the tab's own partition when it has one, the zone's partitioning of `url` otherwise.
+    fn cache_partition(&self, url: Option<&Url>) -> PartitionKey {
+        match (&self.services.partition_key, url) {
+            (key @ PartitionKey::Custom(_), _) => key.clone(),
+            (_, Some(url)) => compute_partition_key(url, self.services.partition_policy),
+            (_, None) => PartitionKey::None,
+        }
+    }
+
+    /// Mixed content checks for this tab's loads, which report what they block to the UA.
+    fn mixed_content(&self) -> MixedContentGuard {
+        MixedContentGuard::new(
+            MixedContentPolicy::from_config(&self.zone_context.config_store),
+            self.tab_id,
+            self.zone_context.event_tx.clone(),
+        )
+    }
+
+    fn on_frame_loaded(&mut self, load: FrameLoad<C>) {
+        let context = &self.context;
+        if self.frames.finish_load(load, || context.new_child()).is_some() {
+            self.runtime.dirty = true;
+        }
+    }
+
+    /// Sizes every loaded child frame to its iframe's content box and hands content that changed
+    /// to the parent context, which paints it inside the iframe.
+    fn update_frames(&mut self) {
+        let render_backend = self.zone_context.render_backend.clone();
+        for frame in self.frames.iter_mut() {
+            let Some(child) = frame.context.as_mut() else {
+                continue;
+            };
+            let Some(rect) = self.context.frame_rect(frame.node) else {
+                // Not laid out yet; try again after the parent's next layout. An iframe that has
+                // no box at all (`display: none`) is never shown.
+                if self.context.page_height() <= 0.0 {
+                    self.runtime.dirty = true;
+                }
+                continue;
+            };
+            if !child.has_rasterizer() {
+                if let Some(rasterizer) = gosub_render_pipeline::rasterizer::downcast_rasterizer(
+                    render_backend.create_rasterizer(self.zone_context.font_system.clone()),
+                ) {
+                    child.set_rasterizer(rasterizer, render_backend.raster_strategy());
+                }
+            }
+            child.set_viewport(Viewport::new(
+                0,
+                0,
+                rect.width.round().max(0.0) as u32,
+                rect.height.round().max(0.0) as u32,
+            ));
+            child.set_media_preferences(render_backend.device_pixel_ratio() as f32, self.color_scheme);
+            if let Some(commands) = child.frame_paint() {
+                self.context.set_frame_content(frame.node, commands);
+                self.runtime.dirty = true;
+            }
+        }
+    }
+
+    /// Hover, link preview and cursor inside the child frame under the pointer, if any.
+    fn hover_frame(&mut self, x: f64, y: f64) {
+        let Some((node, fx, fy)) = self.context.frame_at(x, y) else {
+            return;
+        };
+        let Some(child) = self.frames.get_mut(node).and_then(|f| f.context.as_mut()) else {
+            return;
+        };
+        let (visual_dirty, url_changed, link_url) = child.update_hover(fx, fy);
+        let cursor = child.update_cursor();
+
+        if url_changed {
+            self.send_event(EngineEvent::HoverUrl {
+                tab_id: self.tab_id,
+                url: link_url,
+            });
+        }
+        if let Some(cursor) = cursor {
+            self.send_event(EngineEvent::CursorChanged {
+                tab_id: self.tab_id,
+                cursor,
+            });
+        }
+        if visual_dirty {
+            self.runtime.dirty = true;
+            self.runtime.render_now = true;
+        }
+    }
+
+    /// A left press inside a child frame follows the link under it in that frame, or starts a text
+    /// selection there. Returns `false` when the press wasn't over a loaded frame.
+    fn press_in_frame(&mut self, x: f64, y: f64) -> bool {
+        let Some((node, fx, fy)) = self.context.frame_at(x, y) else {
+            return false;
+        };
+        let Some(child) = self.frames.get_mut(node).and_then(|f| f.context.as_mut()) else {
+            return false;
+        };
+        child.update_hover(fx, fy);
+        let link = child.hover_link_url.clone();
+        if link.is_none() {
+            child.begin_selection(fx, fy);
+        }
+
+        if let Some(url) = link.and_then(|u| Url::parse(&u).ok()) {
+            let top_level = self.current_url.clone().unwrap_or_else(about_blank);
+            let fetcher = self.frame_fetcher();
+            self.frames.navigate(node, url, &top_level, &fetcher);
+        }
+        true
+    }
+
+    /// Scrolls the child frame under the pointer: its scroll containers first, then its page.
+    /// Returns the part of the delta the frame couldn't take, which chains to the parent.
+    fn scroll_frame(&mut self, dx: f64, dy: f64) -> (f64, f64) {
+        let Some((node, _, _)) = self.context.frame_under_pointer() else {
+            return (dx, dy);
+        };
+        let Some(child) = self.frames.get_mut(node).and_then(|f| f.context.as_mut()) else {
+            return (dx, dy);
+        };
+        let (rest_x, rest_y) = child.scroll_containers(dx, dy);
+        let (x, y) = child.scroll_xy();
+        child.set_scroll(x + rest_x, y + rest_y);
+        let (new_x, new_y) = child.scroll_xy();
+        (rest_x - (new_x - x), rest_y - (new_y - y))
+    }
+
+    /// Takes the document's `<title>` as the tab title, falling back to the page URL when there
+    /// is none, and tells the UA if the tab title changed.
+    fn apply_document_title(&mut self, doc_title: Option<String>) {
+        self.doc_title = doc_title;
+        let title = self
+            .doc_title
+            .clone()
+            .or_else(|| self.current_url.as_ref().map(Url::to_string))
+            .unwrap_or_default();
+        if title == self.title {
+            return;
+        }
+        if let Some(entry) = self.history.current_mut() {
+            entry.title = title.clone();
+        }
+        self.title = title.clone();
+        self.send_event(EngineEvent::TitleChanged {
+            tab_id: self.tab_id,
+            title,
+        });
+    }
+
+    /// Starts fetching the favicon of the page that just committed, clearing the previous one.
+    fn load_favicon(&mut self, doc: &EngineDocument<C>, page_url: &Url) {
+        if self.favicon.take().is_some() {
+            self.send_event(EngineEvent::FavIconChanged {
                 tab_id: self.tab_id,
-                event: NavigationEvent::Failed {
-                    nav_id: None,
-                    url: url.clone(),
-                    error: Arc::new(e),
-                },
+                favicon: None,
             });
+        }
+        let candidates = favicon::icon_candidates(doc, page_url, FAVICON_SIZE);
+        if candidates.is_empty() {
             return;
         }
-
-        let nav_id = NavigationId::new();
-        let parent_cancel = CancellationToken::new();
-        self.active_nav = Some(ActiveNav {
-            nav_id,
-            cancel: parent_cancel.clone(),
-            url: url.clone(),
+        std::mem::take(&mut self.favicon_load).cancel();
+        let fetcher = self.frame_fetcher();
+        let cancel = self.favicon_load.clone();
+        let tx = self.favicon_tx.clone();
+        let page_url = page_url.clone();
+        spawn_named("favicon-fetch", async move {
+            let icon = fetch_favicon(&fetcher, &page_url, &candidates, cancel.clone()).await;
+            if !cancel.is_cancelled() {
+                let _ = tx.send((page_url, icon));
+            }
         });
+    }
 
-        {
-            let mut guard = self.zone_context.request_reference_map.write();
-            guard.insert(RequestReference::Navigation(nav_id), self.tab_id);
+    fn on_favicon_loaded(&mut self, page_url: Url, icon: Option<Favicon>) {
+        // The tab may have moved on while the icon was loading.
+        if self.current_url.as_ref() != Some(&page_url) || icon.is_none() {
+            return;
         }
-
-        self.sink.set_nav(nav_id);
-        self.pending_url = Some(url.clone());
-        self.is_loading = true;
-        self.is_error = false;
-        self.state = TabState::Loading;
-        self.runtime.dirty = true;
-
-        self.send_event(EngineEvent::Navigation {
+        self.favicon = icon.clone();
+        self.send_event(EngineEvent::FavIconChanged {
             tab_id: self.tab_id,
-            event: NavigationEvent::Started {
-                nav_id,
-                url: url.clone(),
-            },
+            favicon: icon,
         });
+    }
 
-        // Attach cookies for the navigation request.
-        let mut fetch_headers = HeaderMap::new();
-        if let Some(cookie_str) =
-            self.services
-                .cookie_jar
-                .read()
-                .get_request_cookies(&url, Some(&url), SameSiteContext::SameSite)
+    /// Sends `Finished` for the committed navigation once the page has settled: laid out with no
+    /// image or frame still loading. Called from idle ticks, so layout has requested its media.
+    fn check_load_finished(&mut self) {
+        if self.finishing.is_none()
+            || self.frames.is_loading()
+            || self.context.has_pending_media()
+            || self.fonts.is_loading()
         {
-            if let Ok(val) = cookie_str.parse() {
-                fetch_headers.insert(http::header::COOKIE, val);
-            }
+            return;
         }
-        if let Some(langs) = &self.services.accept_language {
-            if let Ok(val) = langs.parse() {
-                fetch_headers.insert(http::header::ACCEPT_LANGUAGE, val);
-            }
+        if let Some((nav_id, url)) = self.finishing.take() {
+            self.send_event(EngineEvent::Navigation {
+                tab_id: self.tab_id,
+                event: NavigationEvent::Finished { nav_id, url },
+            });
         }
+    }
 
-        let req_id = RequestId::new();
-        REF_REGISTRY.register_request(req_id, ResourceKind::Document, Initiator::Navigation);
-        let req = FetchRequest::builder(Method::GET, url.clone())
-            .with_reference(REF_REGISTRY.to_net(RequestReference::Navigation(nav_id)))
-            .with_req_id(req_id)
-            .with_headers(fetch_headers)
-            .with_priority(Priority::High)
-            .with_kind(ResourceKind::Document.to_net())
-            .with_initiator(Initiator::Navigation.to_net())
-            // Use buffered mode so the full document body is available before parsing.
-            // The streaming path has a race where SharedBody can close before parse_stream
-            // subscribes, causing truncated HTML (only the 5 KB peek buffer is parsed).
-            .with_streaming(false)
-            .with_auto_decode(true)
-            .build();
-
-        let (tx_done, rx_done) = oneshot::channel::<NavigationResult<C>>();
-
-        let tab_id = self.tab_id;
-        let zone_id = self.zone_id;
-        let io_tx = self.zone_context.io_tx.clone();
-        let event_tx = self.zone_context.event_tx.clone();
-        let cookie_jar = self.services.cookie_jar.clone();
-        let accept_language = self.services.accept_language.clone();
-        let max_document_bytes = self.zone_context.config_store.get_uint("net.document.max_bytes");
-
-        let span = tracing::info_span!(
-            "tab_nav",
-            tab_id=%tab_id,
-            nav_id=%nav_id.0,
-            scheme=%url.scheme(),
-            host=%url.host_str().unwrap_or(""),
-            path=%url.path(),
-        );
-
-        let parent_cancel_clone = parent_cancel.clone();
+    /// Hands the queued key commands to the page's listeners one at a time, each once the
+    /// listeners have seen the one before it. Without a script thread the default actions run
+    /// right away.
+    fn next_key(&mut self) {
+        while !self.key_in_flight {
+            let Some(cmd) = self.pending_keys.pop_front() else {
+                return;
+            };
+            if matches!(cmd, TabCommand::KeyDown { .. }) {
+                self.keypress_suppressed = false;
+            } else if matches!(cmd, TabCommand::CharInput { .. }) && self.keypress_suppressed {
+                continue;
+            }
+            let dispatched = match (&self.script, self.key_dom_event(&cmd)) {
+                (Some(script), Some(event)) => Some(script.dispatch_event(event)),
+                _ => None,
+            };
+            let Some(canceled) = dispatched else {
+                self.key_default_action(cmd);
+                continue;
+            };
+            let tx = self.key_tx.clone();
+            self.key_in_flight = true;
+            spawn_named("key-event", async move {
+                let not_canceled = canceled.await.unwrap_or(true);
+                let _ = tx.send((cmd, not_canceled));
+            });
+        }
+    }
 
-        // Spawn the actual fetcher into a separate task
-        spawn_named("tab-fetcher", async move {
-            let _enter = span.enter();
+    /// The page's listeners have seen `cmd`'s event. A canceled `keydown` has no default action
+    /// and no `keypress`.
+    fn on_key_dispatched(&mut self, cmd: TabCommand, not_canceled: bool) {
+        self.key_in_flight = false;
+        if not_canceled {
+            self.key_default_action(cmd);
+        } else if matches!(cmd, TabCommand::KeyDown { .. }) {
+            self.keypress_suppressed = true;
+        }
+        self.next_key();
+    }
 
-            let submit = submit_to_io(zone_id, req.clone(), io_tx.clone(), Some(parent_cancel_clone.clone())).await;
+    /// The `keydown`, `keyup` or `keypress` event for a key command, aimed at the focused element
+    /// or the body.
+    fn key_dom_event(&self, cmd: &TabCommand) -> Option<DomEvent> {
+        let (event_type, key, code, modifiers) = match cmd {
+            TabCommand::KeyDown { key, code, modifiers } => ("keydown", key.clone(), code.clone(), *modifiers),
+            TabCommand::KeyUp { key, code, modifiers } => ("keyup", key.clone(), code.clone(), *modifiers),
+            TabCommand::CharInput { ch } => ("keypress", ch.to_string(), String::new(), Modifiers::empty()),
+            _ => return None,
+        };
+        let target = keyboard::key_event_target(self.context.document()?, self.context.focused_node())?;
+        Some(DomEvent {
+            target: self.script_node_id(target),
+            event_type,
+            init: DomEventInit::Key { key, code, modifiers },
+        })
+    }
 
-            let (handle, rx) = match submit {
-                Ok(ok) => ok,
-                Err(_) => {
-                    let _ = tx_done.send(NavigationResult::Err {
-                        nav_id,
-                        error: NavigationError::NetworkError("I/O channel closed".into()),
-                    });
-                    return;
+    /// What a key does when the page doesn't cancel it, see [`keyboard`].
+    fn key_default_action(&mut self, cmd: TabCommand) {
+        match cmd {
+            TabCommand::KeyDown { key, modifiers, .. } if key == "Tab" => {
+                let change = self.context.focus_next(modifiers.contains(Modifiers::SHIFT));
+                self.report_focus_change(change);
+                self.runtime.dirty = true;
+                self.runtime.render_now = true;
+            }
+            TabCommand::KeyDown { key, .. } if key == "Enter" && self.context.focused_link().is_some() => {
+                if let Some(url) = self.context.focused_link() {
+                    match self.context.focused_link_target() {
+                        Some(target) if target.is_new_context() => {
+                            self.request_new_window(&url, target.target, target.opener)
+                        }
+                        _ => self.navigate_to(url, false),
+                    }
                 }
-            };
-
-            let fetch_result: FetchResult = tokio::select! {
-                _ = parent_cancel_clone.cancelled() => {
-                    handle.cancel.cancel();
-                    let _ = tx_done.send(NavigationResult::Err {
-                        nav_id,
-                        error: NavigationError::Cancelled("Response channel closed".into())
-                    });
-                    return;
+            }
+            TabCommand::KeyDown { key, .. } if key == "Enter" => {
+                // A focused submit button, or a text field of a form ("implicit submission").
+                if let Some(form) = self.context.enter_submission(&self.input_files) {
+                    self.submit_form(form);
+                } else {
+                    self.runtime.dirty = true;
                 }
-                r = rx => match r {
-                    Ok(r) => r,
-                    Err(_) => {
-                        let _ = tx_done.send(NavigationResult::Err {
-                            nav_id,
-                            error: NavigationError::Cancelled("Response channel closed".into())
-                        });
-                        return;
+            }
+            TabCommand::KeyDown { key, modifiers, .. } if !self.context.focused_is_editable() => {
+                let viewport_height = self.desired_viewport.height as f64;
+                if let Some((dx, dy)) =
+                    keyboard::scroll_delta(&key, modifiers, viewport_height, self.context.page_height())
+                {
+                    // The focused element's scroll containers first, then the page.
+                    let (rx, ry) = self.context.scroll_focused_containers(dx, dy);
+                    if rx != dx || ry != dy {
+                        self.runtime.dirty = true;
+                        self.runtime.render_now = true;
                     }
+                    self.scroll_page_by(rx, ry);
                 }
-            };
-
-            // Store Set-Cookie headers from the navigation response.
-            if let Some(meta) = fetch_result.meta() {
-                cookie_jar
-                    .write()
-                    .store_response_cookies(&meta.final_url, &meta.headers, Some(&url));
             }
+            _ => self.runtime.dirty = true,
+        }
+    }
 
-            let ua_policy = UaPolicy {
-                enable_sniffing: false,
-                enable_sniffing_navigation_upgrade: false,
-                enable_pdf_viewer: false,
-                allow_download_without_user_activation: false,
-            };
+    /// Fires `blur`, `focusout`, `focus` and `focusin` at the page's elements after a focus move,
+    /// and tells the UA which element lost and which gained focus.
+    fn report_focus_change(&mut self, change: Option<(Option<NodeId>, Option<NodeId>)>) {
+        if let Some((blurred, focused)) = change {
+            if let Some(script) = &self.script {
+                let (blurred, focused) = (
+                    blurred.map(|node| self.script_node_id(node)),
+                    focused.map(|node| self.script_node_id(node)),
+                );
+                for event in DomEvent::focus_change(blurred, focused) {
+                    // Focus events can't be canceled.
+                    drop(script.dispatch_event(event));
+                }
+            }
+            self.send_event(EngineEvent::FocusChanged {
+                tab_id: self.tab_id,
+                blurred,
+                focused,
+            });
+        }
+    }
 
-            let mut hooks =
-                ResourcePipelines::<C>::new(zone_id, io_tx.clone(), accept_language.clone(), max_document_bytes);
+    /// The id the page's scripts know `node` by: the one they gave it if they created it.
+    fn script_node_id(&self, node: NodeId) -> usize {
+        self.script_nodes
+            .iter()
+            .find_map(|(&id, &created)| (created == node).then_some(id))
+            .unwrap_or_else(|| usize::from(node))
+    }
 
-            let outcome = route_response_for(
-                RequestDestination::Document,
-                handle,
-                req.clone(),
-                fetch_result.clone(),
-                &ua_policy,
-                &mut hooks,
-            )
-            .await;
+    /// Scrolls the page viewport by `(dx, dy)`, clamped to the page.
+    fn scroll_page_by(&mut self, dx: f64, dy: f64) {
+        if dx == 0.0 && dy == 0.0 {
+            return;
+        }
 
-            match outcome {
-                Ok(RoutedOutcome::MainDocument(doc)) => {
-                    use gosub_interface::document::Document as _;
-                    let final_url = doc.url().unwrap_or_else(about_blank);
-                    let title = crate::html::document_title(&doc);
-                    let _ = tx_done.send(NavigationResult::Ok {
-                        nav_id,
-                        final_url,
-                        title,
-                        doc,
-                    });
-                }
-                Ok(RoutedOutcome::ViewerRendered(_doc)) => {
-                    log::warn!("Tab[{:?}] viewer rendering not supported yet", tab_id);
-                    let _ = tx_done.send(NavigationResult::Err {
-                        nav_id,
-                        error: NavigationError::Other(anyhow!("Viewer rendering not supported yet")),
-                    });
-                }
-                // Subresource outcomes need no main-frame navigation handling.
-                Ok(
-                    RoutedOutcome::CssLoaded(_)
-                    | RoutedOutcome::ScriptExecuted(_)
-                    | RoutedOutcome::ImageDecoded(_)
-                    | RoutedOutcome::FontLoaded(_),
-                ) => {
-                    log::trace!("Tab[{:?}] subresource outcome; nothing to do for navigation", tab_id);
+        let max_y = self.max_scroll_y();
+        match self.scroll.scroll_by(dx, dy, f64::MAX, max_y) {
+            // Instant behavior: apply the new offset now and keep the immediate-submit fast
+            // path (avoids up to 1/fps of latency per scroll event).
+            Some((x, y)) => {
+                let moved = x != self.scroll_x || y != self.scroll_y;
+                self.scroll_x = x;
+                self.scroll_y = y;
+                self.context.set_scroll(x as f64, y as f64);
+
+                // GPU-tile-compositing backends skip this CPU TileCache fast path (their
+                // tiles have no CPU pixels); they re-composite on the next tick.
+                if self.zone_context.render_backend.raster_strategy() != RasterStrategy::None
+                    && !self.zone_context.render_backend.gpu_tile_compositing()
+                {
+                    let dpr = self.zone_context.render_backend.device_pixel_ratio();
+                    if let Some(handle) = self.context.take_scroll_handle(dpr) {
+                        self.runtime.committed_scene_epoch = self.context.scene_epoch();
+                        self.zone_context.compositor.submit_frame(self.tab_id, handle);
+                        return;
+                    }
                 }
-                Ok(RoutedOutcome::Blocked(reason)) => {
-                    log::debug!("Tab[{:?}] RoutedOutcome::Blocked", tab_id);
-
-                    let final_url = match fetch_result.meta() {
-                        Some(meta) => meta.final_url.clone(),
-                        None => url.clone(),
-                    };
 
-                    _ = event_tx.send(EngineEvent::Navigation {
-                        tab_id,
-                        event: NavigationEvent::Failed {
-                            nav_id: Some(nav_id),
-                            url: final_url.clone(),
-                            error: Arc::new(anyhow!("Reason: {}", reason)),
-                        },
-                    });
-                }
-                Err(e) => {
-                    let err = format!("Routing error: {e}");
-                    let _ = tx_done.send(NavigationResult::Err {
-                        nav_id,
-                        error: NavigationError::NetworkError(err),
-                    });
+                // TileCache not ready yet; fall back to the timer path. Only mark dirty if
+                // the integer offset actually moved (sub-pixel deltas are no-ops).
+                if moved {
+                    self.runtime.dirty = true;
                 }
             }
-        });
+            // Animated behavior: tick_draw advances the ease toward the new target. Request
+            // an immediate tick so the first frame lands without waiting up to 1/fps.
+            None => {
+                self.runtime.render_now = true;
+            }
+        }
+    }
 
-        self.load = Some(NavJoin {
-            cancel: parent_cancel.clone(),
-            rx: Some(rx_done),
-        });
+    /// Largest vertical scroll offset. When page height is known, clamp to the real maximum so
+    /// worker and context stay in sync. When the page hasn't rendered yet, allow free scrolling
+    /// (the context will clamp to the actual page height on its own).
+    fn max_scroll_y(&self) -> f64 {
+        let ph = self.context.page_height();
+        if ph > 0.0 {
+            (ph - self.desired_viewport.height as f64).max(0.0)
+        } else {
+            f64::MAX
+        }
+    }
+
+    /// Runs the page's `requestAnimationFrame()` callbacks ahead of a frame. Slow callbacks get
+    /// one frame interval; after that the frame is drawn without waiting for them.
+    ///
+    /// The returned future doesn't borrow the worker: `&TabWorker` isn't `Send`, so it can't be
+    /// held across the wait.
+    fn run_animation_frames(&self) -> impl std::future::Future<Output = ()> + Send + 'static {
+        let done = self
+            .script
+            .as_ref()
+            .filter(|script| script.wants_animation_frame())
+            .map(|script| script.animation_frame(std::time::Instant::now()));
+        let budget = Duration::from_secs_f64(1.0 / self.runtime.fps.max(1) as f64);
+        let tab_id = self.tab_id;
+        async move {
+            let Some(done) = done else {
+                return;
+            };
+            if tokio::time::timeout(budget, done).await.is_err() {
+                log::debug!("Tab {tab_id}: animation frame callbacks overran the frame");
+            }
+        }
     }
 
     /// Do a draw tick. This will be called based on the FPS that is requested
     #[allow(unreachable_code)] // cfg-conditional tile-cache returns make the display-list path unreachable for some feature combos
     async fn tick_draw(&mut self) -> anyhow::Result<()> {
+        // The backend's device pixel ratio can change under us (the window moved to another
+        // screen), so it is checked every frame.
+        self.update_media();
+        self.run_animation_frames().await;
+
         // Advance an in-flight smooth scroll: ease the engine scroll one step toward its target and
         // keep the frame loop alive (mark dirty) until it settles exactly on the target. Dormant
         // unless the scroll behavior is animated - `Instant` applies moves synchronously in the
@@ -956,16 +2496,57 @@ impl<C: RenderConfiguration> TabWorker<C> {
         // must wake the render loop even when nothing else changed, so the now-available image is
         // laid out and painted. This marks the render dirty under the hood.
         if self.context.poll_media_completed() {
+            // Child frames share the media store, so the fetch may have been theirs.
+            for child in self.frames.iter_mut().filter_map(|f| f.context.as_mut()) {
+                child.invalidate_render();
+            }
             self.runtime.dirty = true;
         }
+        // Animated SVGs decode their next frame once the last one was painted; a new frame has
+        // to be painted too. The store is shared with child frames, as above.
+        if self.context.tick_media_animations(std::time::Instant::now()) {
+            for child in self.frames.iter_mut().filter_map(|f| f.context.as_mut()) {
+                child.invalidate_render();
+            }
+            self.runtime.dirty = true;
+        }
+        // Repaint flashing (a debug mode) needs a follow-up frame to paint the flash away.
+        if self.context.poll_paint_flash() {
+            self.runtime.dirty = true;
+        }
+        // Lazy images and iframes that the last layout or scroll brought near the viewport.
+        let lazy_frames = self.context.load_lazy_media();
+        if !lazy_frames.is_empty() {
+            let top_level = self.current_url.clone().unwrap_or_else(about_blank);
+            let fetcher = self.frame_fetcher();
+            for node in lazy_frames {
+                self.frames.load_deferred(node, &top_level, &fetcher);
+            }
+        }
+        self.update_frames();
 
         // Skip rendering when nothing has changed to avoid burning CPU at the tick rate.
         if !self.runtime.dirty {
+            self.check_load_finished();
+            return Ok(());
+        }
+        // Text waits for web fonts in their `font-display` block period; the frame is drawn
+        // once they arrive or the period ends, whichever comes first.
+        if self.fonts.is_blocking(std::time::Instant::now()) {
             return Ok(());
         }
         self.runtime.dirty = false;
 
+        // Whatever made the tab dirty may have changed the `<title>` text.
+        let doc_title = self.context.document_title();
+        if doc_title != self.doc_title {
+            self.apply_document_title(doc_title);
+        }
+
         let render_backend = self.zone_context.render_backend.clone();
+        // `@media` rules see what `matchMedia()` sees; the viewport is set per build below.
+        self.context
+            .set_media_preferences(render_backend.device_pixel_ratio() as f32, self.color_scheme);
 
         // Install the active backend's rasterizer once (replaces the former per-backend cfg
         // selection and the Vello-specific wgpu_resources extraction).
@@ -1206,16 +2787,37 @@ impl<C: RenderConfiguration> TabWorker<C> {
         Ok(true)
     }
 
-    /// Cancel the current navigation (if any)
-    fn cancel_current_nav(&mut self) {
-        if let Some(active) = self.active_nav.take() {
-            log::warn!(
-                "Cancelling active navigation for tab {:?} nav {:?}",
-                self.tab_id,
-                active.nav_id
-            );
-            active.cancel.cancel();
+    /// Cancel the current navigation (if any). Cancelling the token aborts the fetch, the parse
+    /// and every subresource fetch started under it; a result that still arrives is dropped as
+    /// stale by `on_nav_result`.
+    fn cancel_current_nav(&mut self, reason: CancelReason) {
+        if let Some(load) = self.load.take() {
+            load.cancel.cancel();
         }
+        let Some(active) = self.active_nav.take() else {
+            return;
+        };
+        log::warn!(
+            "Cancelling active navigation for tab {:?} nav {:?}",
+            self.tab_id,
+            active.nav_id
+        );
+        active.cancel.cancel();
+
+        self.history_nav = HistoryNav::Push;
+        self.pending_url = None;
+        self.is_loading = false;
+        self.state = TabState::Idle;
+        self.runtime.dirty = true;
+
+        self.send_event(EngineEvent::Navigation {
+            tab_id: self.tab_id,
+            event: NavigationEvent::Cancelled {
+                nav_id: active.nav_id,
+                url: active.url,
+                reason,
+            },
+        });
     }
 
     /// Convert the URL string into an actual URL
@@ -1305,36 +2907,6 @@ mod tests {
     use bytes::Bytes;
     use futures_util::TryStreamExt;
 
-    /// Verify `decode_web_font` turns a real WOFF2 payload into an SFNT the font stack can
-    /// parse. Reads the fixture path from `GOSUB_WOFF2_FIXTURE` so we neither hit the network
-    /// nor commit a binary font; skips when unset.
-    #[test]
-    fn decode_web_font_woff2_roundtrips_to_sfnt() {
-        let Ok(path) = std::env::var("GOSUB_WOFF2_FIXTURE") else {
-            eprintln!("skipping: set GOSUB_WOFF2_FIXTURE to a .woff2 file to run");
-            return;
-        };
-        let woff2 = std::fs::read(&path).expect("read fixture");
-        assert_eq!(&woff2[0..4], b"wOF2", "fixture must be WOFF2");
-
-        let url = url::Url::parse("https://example.test/font.woff2").unwrap();
-        let sfnt = super::decode_web_font(woff2, &url);
-
-        // Output must be a different, valid SFNT (TrueType `0x00010000` or OpenType `OTTO`).
-        let magic = u32::from_be_bytes([sfnt[0], sfnt[1], sfnt[2], sfnt[3]]);
-        assert!(magic == 0x0001_0000 || magic == 0x4F54_544F, "not SFNT: {magic:#010x}");
-
-        // It must re-parse and expose the core tables a backend reads.
-        use allsorts::binary::read::ReadScope;
-        use allsorts::font_data::FontData;
-        use allsorts::tables::FontTableProvider;
-        let font = ReadScope::new(&sfnt).read::<FontData<'_>>().expect("parse SFNT");
-        let provider = font.table_provider(0).expect("table provider");
-        for tag in [allsorts::tag::HEAD, allsorts::tag::CMAP, allsorts::tag::GLYF] {
-            assert!(provider.has_table(tag), "missing table {tag:#010x}");
-        }
-    }
-
     #[tokio::test]
     async fn shared_body_streamreader_eof() {
         use std::io;
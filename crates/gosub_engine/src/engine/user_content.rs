@@ -0,0 +1,287 @@
+//! Scripts and stylesheets the UA injects into pages, like userscripts and user styles.
+//!
+//! Each entry is registered on a tab with the URL patterns it applies to and the moment it
+//! runs: at document-start (once the document is created, before it is first shown) or at
+//! document-end (once it is parsed). Styles are added to the document as user-origin
+//! stylesheets; scripts run in the page's JS context when the tab has scripting enabled (see
+//! [`script`](crate::engine::script)).
+//!
+//! Patterns follow the WebExtension match-pattern syntax: `<all_urls>`, or
+//! `scheme://host/path` where the scheme may be `*` (http and https), the host may be `*` or
+//! start with `*.` (the domain and its subdomains), and `*` in the path matches anything.
+
+use crate::engine::EngineError;
+use cow_utils::CowUtils;
+use std::fmt::Display;
+use url::Url;
+use uuid::Uuid;
+
+/// Identifies a registered user script or style, for removing it again.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct UserContentId(Uuid);
+
+impl UserContentId {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for UserContentId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for UserContentId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// When an entry is injected into a document.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum RunAt {
+    /// As soon as the document exists, before it is first shown
+    DocumentStart,
+    /// Once the document is parsed (`DOMContentLoaded`)
+    #[default]
+    DocumentEnd,
+}
+
+/// A URL match pattern, see the module docs for the syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlPattern {
+    /// `None` for `<all_urls>`
+    scheme: Option<String>,
+    host: String,
+    path: String,
+}
+
+impl UrlPattern {
+    pub fn parse(pattern: &str) -> Result<Self, EngineError> {
+        let invalid = |why: &str| EngineError::InvalidUrlPattern(format!("{pattern}: {why}"));
+
+        if pattern == "<all_urls>" {
+            return Ok(Self {
+                scheme: None,
+                host: "*".into(),
+                path: "/*".into(),
+            });
+        }
+        let (scheme, rest) = pattern.split_once("://").ok_or_else(|| invalid("missing scheme"))?;
+        let scheme = scheme.cow_to_ascii_lowercase().into_owned();
+        if !matches!(scheme.as_str(), "*" | "http" | "https" | "file") {
+            return Err(invalid("unsupported scheme"));
+        }
+        let (host, path) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => return Err(invalid("missing path")),
+        };
+        if scheme != "file" && host.is_empty() {
+            return Err(invalid("missing host"));
+        }
+        let wildcard = host.strip_prefix("*.").unwrap_or(host);
+        if wildcard.contains('*') && host != "*" {
+            return Err(invalid("'*' in the host must be alone or lead as '*.'"));
+        }
+        Ok(Self {
+            scheme: Some(scheme),
+            host: host.cow_to_ascii_lowercase().into_owned(),
+            path: path.to_string(),
+        })
+    }
+
+    pub fn matches(&self, url: &Url) -> bool {
+        let scheme_ok = match self.scheme.as_deref() {
+            None => matches!(url.scheme(), "http" | "https" | "file"),
+            Some("*") => matches!(url.scheme(), "http" | "https"),
+            Some(scheme) => url.scheme() == scheme,
+        };
+        if !scheme_ok {
+            return false;
+        }
+
+        let host = url.host_str().unwrap_or("");
+        let host_ok = if self.host == "*" {
+            true
+        } else if let Some(domain) = self.host.strip_prefix("*.") {
+            host == domain || host.strip_suffix(domain).is_some_and(|sub| sub.ends_with('.'))
+        } else {
+            host == self.host
+        };
+        if !host_ok {
+            return false;
+        }
+
+        let path = match url.query() {
+            Some(query) => format!("{}?{query}", url.path()),
+            None => url.path().to_string(),
+        };
+        glob_match(&self.path, &path)
+    }
+}
+
+/// `*`-only glob matching, where `*` matches any run of characters.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*` at all: the pattern is literal.
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Which pages an entry applies to.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UrlFilter {
+    /// The entry applies when any of these match...
+    pub matches: Vec<UrlPattern>,
+    /// ...and none of these do.
+    pub excludes: Vec<UrlPattern>,
+}
+
+impl UrlFilter {
+    /// Parses the include and exclude patterns.
+    pub fn new<S: AsRef<str>>(matches: &[S], excludes: &[S]) -> Result<Self, EngineError> {
+        let parse = |list: &[S]| -> Result<Vec<_>, EngineError> {
+            list.iter().map(|p| UrlPattern::parse(p.as_ref())).collect()
+        };
+        Ok(Self {
+            matches: parse(matches)?,
+            excludes: parse(excludes)?,
+        })
+    }
+
+    pub fn applies_to(&self, url: &Url) -> bool {
+        self.matches.iter().any(|p| p.matches(url)) && !self.excludes.iter().any(|p| p.matches(url))
+    }
+}
+
+/// A script to inject.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserScript {
+    pub id: UserContentId,
+    pub source: String,
+    pub filter: UrlFilter,
+    pub run_at: RunAt,
+}
+
+/// A stylesheet to inject, as CSS source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserStyle {
+    pub id: UserContentId,
+    pub css: String,
+    pub filter: UrlFilter,
+    pub run_at: RunAt,
+}
+
+/// The user scripts and styles registered on a tab.
+#[derive(Debug, Clone, Default)]
+pub struct UserContent {
+    scripts: Vec<UserScript>,
+    styles: Vec<UserStyle>,
+}
+
+impl UserContent {
+    pub fn add_script(&mut self, script: UserScript) {
+        self.scripts.retain(|s| s.id != script.id);
+        self.scripts.push(script);
+    }
+
+    pub fn add_style(&mut self, style: UserStyle) {
+        self.styles.retain(|s| s.id != style.id);
+        self.styles.push(style);
+    }
+
+    /// Unregisters a script or style. Returns whether anything was removed.
+    pub fn remove(&mut self, id: UserContentId) -> bool {
+        let before = self.scripts.len() + self.styles.len();
+        self.scripts.retain(|s| s.id != id);
+        self.styles.retain(|s| s.id != id);
+        before != self.scripts.len() + self.styles.len()
+    }
+
+    /// Scripts for `url` at `run_at`, in registration order.
+    pub fn scripts_for<'a>(&'a self, url: &'a Url, run_at: RunAt) -> impl Iterator<Item = &'a UserScript> + 'a {
+        self.scripts
+            .iter()
+            .filter(move |s| s.run_at == run_at && s.filter.applies_to(url))
+    }
+
+    /// Styles for `url` at `run_at`, in registration order.
+    pub fn styles_for<'a>(&'a self, url: &'a Url, run_at: RunAt) -> impl Iterator<Item = &'a UserStyle> + 'a {
+        self.styles
+            .iter()
+            .filter(move |s| s.run_at == run_at && s.filter.applies_to(url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn match_patterns() {
+        let p = UrlPattern::parse("*://*.example.com/*").unwrap();
+        assert!(p.matches(&url("https://example.com/")));
+        assert!(p.matches(&url("http://www.example.com/a/b?c=d")));
+        assert!(!p.matches(&url("https://badexample.com/")));
+        assert!(!p.matches(&url("ftp://example.com/")));
+
+        let p = UrlPattern::parse("https://news.example.org/*/comments").unwrap();
+        assert!(p.matches(&url("https://news.example.org/item/42/comments")));
+        assert!(!p.matches(&url("https://news.example.org/item/42")));
+        assert!(!p.matches(&url("http://news.example.org/item/comments")));
+
+        assert!(UrlPattern::parse("<all_urls>")
+            .unwrap()
+            .matches(&url("file:///tmp/a.html")));
+        assert!(UrlPattern::parse("example.com/*").is_err());
+        assert!(UrlPattern::parse("https://ex*ample.com/*").is_err());
+        assert!(UrlPattern::parse("https://example.com").is_err());
+    }
+
+    #[test]
+    fn entries_filter_by_url_and_time() {
+        let mut content = UserContent::default();
+        let dark = UserContentId::new();
+        content.add_style(UserStyle {
+            id: dark,
+            css: "body { background: black }".into(),
+            filter: UrlFilter::new(&["<all_urls>"], &["https://example.com/print/*"]).unwrap(),
+            run_at: RunAt::DocumentStart,
+        });
+        content.add_script(UserScript {
+            id: UserContentId::new(),
+            source: "console.log(1)".into(),
+            filter: UrlFilter::new(&["https://example.com/*"], &[]).unwrap(),
+            run_at: RunAt::DocumentEnd,
+        });
+
+        let page = url("https://example.com/index.html");
+        assert_eq!(content.styles_for(&page, RunAt::DocumentStart).count(), 1);
+        assert_eq!(content.styles_for(&page, RunAt::DocumentEnd).count(), 0);
+        assert_eq!(content.scripts_for(&page, RunAt::DocumentEnd).count(), 1);
+        let print = url("https://example.com/print/index.html");
+        assert_eq!(content.styles_for(&print, RunAt::DocumentStart).count(), 0);
+
+        assert!(content.remove(dark));
+        assert!(!content.remove(dark));
+        assert_eq!(content.styles_for(&page, RunAt::DocumentStart).count(), 0);
+    }
+}
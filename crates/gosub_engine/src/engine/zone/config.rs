@@ -46,6 +46,7 @@
 //! - `default_font_size`: Default font size in CSS px (default: 16).
 //! - `minimum_font_size`: Minimum allowed font size in CSS px (must be ≤ `default_font_size`).
 //! - `enable_local_file_access`: Allow `file://` (sandboxing concerns).
+//! - `permission_handler`: Optional callback deciding permissions before the stored decisions.
 //!
 //! # Notes
 //!
@@ -58,6 +59,7 @@
 //! (e.g. `font_scale` outside `0.25..=10.0`, `minimum_font_size > default_font_size`,
 //! or `max_tabs == 0`).
 
+use crate::engine::permissions::PermissionHandler;
 use crate::storage::PartitionPolicy;
 use std::fmt;
 
@@ -89,6 +91,8 @@ pub struct ZoneConfig {
     pub enable_local_file_access: bool,
     /// Policy for storage partitioning (cookies, localStorage, etc.).
     pub partition_policy: PartitionPolicy,
+    /// Decides permissions for the zone's pages before the decisions kept in the settings.
+    pub permission_handler: Option<PermissionHandler>,
 }
 
 impl Default for ZoneConfig {
@@ -107,6 +111,7 @@ impl Default for ZoneConfig {
             minimum_font_size: 0,
             enable_local_file_access: false,
             partition_policy: PartitionPolicy::TopLevelOrigin,
+            permission_handler: None,
         }
     }
 }
@@ -182,6 +187,10 @@ impl ZoneConfigBuilder {
     pub fn partition_policy(self, policy: PartitionPolicy) -> Self {
         self.map(|c| c.partition_policy = policy)
     }
+    #[must_use]
+    pub fn permission_handler(self, handler: PermissionHandler) -> Self {
+        self.map(|c| c.permission_handler = Some(handler))
+    }
 
     /// Apply multiple changes in one go.
     pub fn with(self, f: impl FnOnce(&mut ZoneConfig)) -> Self {
@@ -258,6 +267,7 @@ mod tests {
         assert_eq!(c.minimum_font_size, 0);
         assert!(!c.enable_local_file_access);
         assert_eq!(c.partition_policy, PartitionPolicy::TopLevelOrigin);
+        assert!(c.permission_handler.is_none());
     }
 
     #[test]
@@ -8,8 +8,9 @@ use crate::engine::types::{EventChannel, IoChannel, TabChannel};
 use crate::events::TabCommand;
 use crate::html::RenderConfiguration;
 use crate::net::req_ref_tracker::RequestReferenceMap;
-use crate::storage::types::PartitionPolicy;
-use crate::tab::services::resolve_tab_services;
+use crate::storage::types::{PartitionKey, PartitionPolicy};
+use crate::tab::services::{resolve_tab_services, PartitionServices};
+use crate::tab::watchdog::spawn_watchdog;
 use crate::tab::{create_tab_and_spawn, TabDefaults, TabHandle, TabOverrides, TabSink};
 use crate::util::spawn_named;
 use crate::zone::ZoneConfig;
@@ -23,6 +24,7 @@ use std::collections::HashMap;
 use std::fmt::{Debug, Display};
 use std::sync::atomic::AtomicUsize;
 use std::sync::Arc;
+use std::time::Duration;
 use uuid::Uuid;
 
 /// A unique identifier for a [`Zone`] within a [`GosubEngine`](crate::GosubEngine).
@@ -144,6 +146,8 @@ pub struct Zone<C: RenderConfiguration = crate::html::DefaultRenderConfig> {
     pub sink: Arc<ZoneSink>,
     // List of tabs
     tabs: HashMap<TabId, TabInfo>,
+    /// Cookies and storage of the partitions tabs were created in
+    partitions: HashMap<PartitionKey, PartitionServices>,
 
     /// ID of the zone
     pub id: ZoneId,
@@ -178,8 +182,11 @@ struct TabInfo {
     cmd_tx: TabChannel,
     /// Worker join handle, awaited when the tab is closed.
     join_handle: tokio::task::JoinHandle<()>,
-    #[allow(unused)]
     sink: Arc<TabSink>,
+    /// The overrides the tab was created with, reused when it is respawned
+    overrides: TabOverrides,
+    /// Hang detection for the worker, if enabled
+    watchdog: Option<tokio::task::JoinHandle<()>>,
 }
 
 #[allow(unused)]
@@ -251,6 +258,7 @@ impl<C: RenderConfiguration> Zone<C> {
             }),
             id: zone_id,
             tabs: HashMap::new(),
+            partitions: HashMap::new(),
             title: "Untitled Zone".to_string(),
             icon: vec![],
             description: "".to_string(),
@@ -316,21 +324,34 @@ impl<C: RenderConfiguration> Zone<C> {
             return Err(EngineError::TabLimitExceeded);
         }
 
-        let tab_services = resolve_tab_services(
-            self.id,
-            &self.context.services,
-            &self.config,
-            &overrides.unwrap_or_default(),
-        );
+        let overrides = overrides.unwrap_or_default();
+        let partition = overrides
+            .partition_key
+            .as_ref()
+            .map(|key| &*self.partitions.entry(key.clone()).or_default());
+        let tab_services = resolve_tab_services(self.id, &self.context.services, &self.config, &overrides, partition);
 
         let (tab_handle, join_handle) =
             create_tab_and_spawn::<C>(self.id, tab_services, self.context.clone()).map_err(EngineError::CreateTab)?;
+        let unresponsive_ms = self.context.config_store.get_uint("engine.tab.unresponsive_ms") as u64;
+        let watchdog = (unresponsive_ms > 0).then(|| {
+            spawn_watchdog(
+                tab_handle.tab_id,
+                self.id,
+                tab_handle.cmd_tx.clone(),
+                tab_handle.sink.clone(),
+                self.context.event_tx.clone(),
+                Duration::from_millis(unresponsive_ms),
+            )
+        });
         self.tabs.insert(
             tab_handle.tab_id,
             TabInfo {
                 cmd_tx: tab_handle.cmd_tx.clone(),
                 join_handle,
                 sink: tab_handle.sink.clone(),
+                overrides,
+                watchdog,
             },
         );
 
@@ -344,6 +365,9 @@ impl<C: RenderConfiguration> Zone<C> {
             .set_title(initial.title.as_deref().unwrap_or("New Tab"))
             .await?;
         tab_handle.set_viewport(initial.viewport.unwrap_or_default()).await?;
+        if initial.opener.is_some() {
+            tab_handle.set_opener(initial.opener).await?;
+        }
 
         // Load URL in tab if provided
         if let Some(url) = initial.url.as_ref() {
@@ -353,6 +377,13 @@ impl<C: RenderConfiguration> Zone<C> {
         Ok(tab_handle)
     }
 
+    /// Forgets the cookies and storage of partition `key`, e.g. when a private window closes.
+    /// Tabs still open in the partition keep using the old state; tabs created afterwards start
+    /// empty. Returns whether the partition existed.
+    pub fn clear_partition(&mut self, key: &PartitionKey) -> bool {
+        self.partitions.remove(key).is_some()
+    }
+
     /// Forwards storage events from the storage service to the engine event channel.
     fn spawn_storage_events_to_engine(&self) -> Result<tokio::task::JoinHandle<()>, EngineError> {
         let mut rx = self.context.storage_rx.resubscribe();
@@ -383,6 +414,9 @@ impl<C: RenderConfiguration> Zone<C> {
         let Some(info) = self.tabs.remove(&tab_id) else {
             return false;
         };
+        if let Some(watchdog) = &info.watchdog {
+            watchdog.abort();
+        }
 
         // A send error means the worker already exited; awaiting the join handle is
         // still correct in that case.
@@ -399,6 +433,27 @@ impl<C: RenderConfiguration> Zone<C> {
         true
     }
 
+    /// Replaces a tab whose worker stopped responding (see
+    /// [`EngineEvent::TabUnresponsive`]) with a fresh one created with the same overrides. Unless
+    /// `initial.url` says otherwise, the new tab loads the URL the old one last committed.
+    ///
+    /// The old worker is aborted, which takes effect the next time it yields; one stuck in
+    /// blocking code is abandoned and its output is no longer routed anywhere. The new tab has a
+    /// new id, so the UA must swap its handle.
+    pub async fn respawn_tab(&mut self, tab_id: TabId, mut initial: TabDefaults) -> Result<TabHandle, EngineError> {
+        let info = self.tabs.remove(&tab_id).ok_or(EngineError::InvalidTabId)?;
+        if let Some(watchdog) = &info.watchdog {
+            watchdog.abort();
+        }
+        info.join_handle.abort();
+        log::warn!("Respawning tab {tab_id}");
+
+        if initial.url.is_none() {
+            initial.url = info.sink.current_url.read().as_ref().map(|u| u.to_string());
+        }
+        self.create_tab(initial, Some(info.overrides)).await
+    }
+
     /// Closes every tab in this zone. Used by `GosubEngine::close_zone`.
     ///
     /// Signals every tab's worker to stop first, then awaits their joins concurrently, so total
@@ -408,6 +463,9 @@ impl<C: RenderConfiguration> Zone<C> {
         let tabs: Vec<_> = self.tabs.drain().collect();
 
         let waits = tabs.into_iter().map(|(tab_id, info)| async move {
+            if let Some(watchdog) = &info.watchdog {
+                watchdog.abort();
+            }
             // A send error means the worker already exited; awaiting the join handle is
             // still correct in that case.
             let _ = info.cmd_tx.send(TabCommand::CloseTab).await;
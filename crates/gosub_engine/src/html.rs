@@ -3,6 +3,9 @@
 //! This module provides functionality to parse HTML documents, extract resource hints,
 //! and handle various HTML configurations.
 mod parser;
+pub mod stylesheets;
+#[cfg(test)]
+pub(crate) mod testing;
 
 pub use parser::parse_main_document_stream;
 pub use parser::{DocumentError, HtmlParseConfig, ResourceHint};
@@ -95,6 +98,31 @@ where
 /// The parsed document type used by the engine for a given config (defaults to [`DefaultRenderConfig`]).
 pub type EngineDocument<C = DefaultRenderConfig> = DocumentImpl<C>;
 
+/// The `target` of the first `<base>` element that has one: where links and forms without their
+/// own `target` open.
+pub fn base_target<C: RenderConfiguration>(doc: &EngineDocument<C>) -> Option<String> {
+    let mut stack = vec![doc.root()];
+    while let Some(id) = stack.pop() {
+        if doc.tag_name(id) == Some("base") {
+            if let Some(target) = doc.attribute(id, "target") {
+                return Some(target.to_string());
+            }
+        }
+        stack.extend(doc.children(id).iter().rev().copied());
+    }
+    None
+}
+
+/// Whether a link or form `target` asks for a new browsing context rather than the current one.
+/// Named targets count as new, since a tab has no named windows or frames to match them against.
+pub fn is_new_context_target(target: &str) -> bool {
+    let target = target.trim();
+    !(target.is_empty()
+        || target.eq_ignore_ascii_case("_self")
+        || target.eq_ignore_ascii_case("_parent")
+        || target.eq_ignore_ascii_case("_top"))
+}
+
 /// Extract the text content of the first `<title>` element in the document.
 pub fn document_title<C: RenderConfiguration>(doc: &EngineDocument<C>) -> Option<String> {
     find_title(doc, doc.root())
@@ -5,7 +5,7 @@ use crate::net::types::{Priority, ResourceKind};
 use crate::net::RequestDestination;
 use cow_utils::CowUtils;
 use gosub_html5::document::builder::DocumentBuilderImpl;
-use gosub_html5::parser::Html5Parser;
+use gosub_html5::parser::{Html5Parser, Html5ParserOptions};
 use gosub_interface::css3::CssSystem;
 use gosub_interface::document::Document as _;
 use gosub_shared::byte_stream::{ByteStream, Encoding};
@@ -38,6 +38,23 @@ pub struct ResourceHint {
     pub priority: Priority,
 }
 
+impl ResourceHint {
+    /// A `<link rel=stylesheet>`.
+    pub fn stylesheet(url: Url) -> Self {
+        Self {
+            url,
+            dest: RequestDestination::Document,
+            referrer: None,
+            cross_origin: false,
+            integrity: None,
+            kind: ResourceKind::Stylesheet,
+            rel: Some("stylesheet".to_string()),
+            from_attr: "href",
+            priority: Priority::High,
+        }
+    }
+}
+
 /// Errors from buffering and parsing a main document stream.
 #[derive(thiserror::Error, Debug)]
 pub enum DocumentError {
@@ -72,7 +89,8 @@ impl Default for HtmlParseConfig {
 }
 
 /// Main entry point: buffer the HTML stream, parse it into a real DOM document,
-/// and report discovered sub-resources.
+/// and report discovered sub-resources. Only the user agent stylesheet is added to the
+/// document; its own stylesheets are left to the caller (see [`crate::html::stylesheets`]).
 ///
 /// - `base_url`: used to resolve relative URLs and as the document URL.
 /// - `reader`: the response body stream (after the UA has chosen Render).
@@ -149,7 +167,12 @@ where
     let mut stream = ByteStream::new(encoding, None);
     stream.read_from_bytes(&buf)?;
     let mut doc = DocumentBuilderImpl::new_document::<C>(Some(base_url));
-    let _ = Html5Parser::<C>::parse_document(&mut stream, &mut doc, None);
+    // Stylesheets are fetched through the I/O thread and added by the caller.
+    let options = Html5ParserOptions {
+        load_stylesheets: false,
+        ..Default::default()
+    };
+    let _ = Html5Parser::<C>::parse_document(&mut stream, &mut doc, Some(options));
     let ua = <C::CssSystem as CssSystem>::load_default_useragent_stylesheet();
     doc.add_stylesheet(ua);
 
@@ -189,6 +212,8 @@ static RE_DEFER_ATTR: Lazy<Regex> = Lazy::new(|| re(r#"\bdefer\b"#));
 static RE_IMG_SRC: Lazy<Regex> =
     Lazy::new(|| re(r#"(?is)<\s*img\b[^>]*\bsrc\s*=\s*(?P<src>"[^"]*"|'[^']*'|[^\s>]+)[^>]*>"#));
 
+static RE_LAZY_LOADING_ATTR: Lazy<Regex> = Lazy::new(|| re(r#"(?i)\bloading\s*=\s*["']?\s*lazy\b"#));
+
 fn discover_resources(html: &str, base: &Url) -> Vec<ResourceHint> {
     let mut out = Vec::new();
 
@@ -200,17 +225,7 @@ fn discover_resources(html: &str, base: &Url) -> Vec<ResourceHint> {
         let Ok(u) = resolve(base, unquote(m.as_str())) else {
             continue;
         };
-        out.push(ResourceHint {
-            url: u,
-            dest: RequestDestination::Document,
-            referrer: None,
-            cross_origin: false,
-            integrity: None,
-            kind: ResourceKind::Stylesheet,
-            rel: Some("stylesheet".to_string()),
-            from_attr: "href",
-            priority: Priority::High,
-        });
+        out.push(ResourceHint::stylesheet(u));
     }
 
     // Scripts
@@ -238,8 +253,14 @@ fn discover_resources(html: &str, base: &Url) -> Vec<ResourceHint> {
         });
     }
 
-    // Images
+    // Images. Lazy ones are fetched once layout puts them near the viewport.
     for cap in RE_IMG_SRC.captures_iter(html) {
+        if cap
+            .get(0)
+            .is_some_and(|tag| RE_LAZY_LOADING_ATTR.is_match(tag.as_str()))
+        {
+            continue;
+        }
         let Some(m) = cap.name("src") else {
             continue;
         };
@@ -296,6 +317,7 @@ mod tests {
               <body>
                 <script src="app.js"></script>
                 <img src="images/logo.png">
+                <img loading="lazy" src="images/footer.png">
               </body>
             </html>
         "#;
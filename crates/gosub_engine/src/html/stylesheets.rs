@@ -0,0 +1,188 @@
+//! The stylesheets of a parsed document.
+//!
+//! The HTML pipeline parses documents without loading their stylesheets (see
+//! `Html5ParserOptions::load_stylesheets`). [`style_sources`] lists the `<style>` and
+//! `<link rel=stylesheet>` elements that apply in tree order, which is the order their sheets
+//! cascade in. The pipeline fetches the linked ones and adds every sheet to the document before
+//! handing it on, so a document is never rendered without its styles.
+//!
+//! Elements whose `media` doesn't match the tab's [`MediaEnvironment`] (`print`, say) are left
+//! out. That is decided once, when the document loads.
+
+use crate::html::{EngineDocument, RenderConfiguration};
+use cow_utils::CowUtils;
+use gosub_css3::media::MediaEnvironment;
+use gosub_interface::css3::{CssOrigin, CssSystem};
+use gosub_interface::document::Document as _;
+use gosub_interface::node::NodeType;
+use gosub_shared::config::{Context as ParseContext, ParserConfig};
+use url::Url;
+
+/// Where the text of a stylesheet comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StyleSource {
+    /// The contents of a `<style>` element
+    Inline(String),
+    /// The resolved `href` of a `<link rel=stylesheet>`
+    Linked(Url),
+}
+
+/// The stylesheets of `doc` that apply in `media`, in cascade order. `document_url` is the URL
+/// the document was loaded from; a `<base href>` takes precedence over it.
+pub fn style_sources<C: RenderConfiguration>(
+    doc: &EngineDocument<C>,
+    document_url: &Url,
+    media: &MediaEnvironment,
+) -> Vec<StyleSource> {
+    let base = base_url(doc, document_url);
+    let applies = |id| {
+        let media_matches = doc.attribute(id, "media").is_none_or(|m| media.matches_str(m));
+        let type_matches = doc.attribute(id, "type").is_none_or(|t| {
+            let t = t.trim();
+            t.is_empty() || t.eq_ignore_ascii_case("text/css")
+        });
+        media_matches && type_matches
+    };
+
+    let mut sources = Vec::new();
+    let mut stack = vec![doc.root()];
+    while let Some(id) = stack.pop() {
+        match doc.tag_name(id) {
+            Some("style") if applies(id) => {
+                let css: String = doc
+                    .children(id)
+                    .iter()
+                    .filter(|&&child| doc.node_type(child) == NodeType::TextNode)
+                    .filter_map(|&child| doc.text_value(child))
+                    .collect();
+                sources.push(StyleSource::Inline(css));
+            }
+            Some("link") if applies(id) && doc.attribute(id, "disabled").is_none() => {
+                let rel = doc.attribute(id, "rel").map(|rel| rel.cow_to_ascii_lowercase());
+                // Alternate stylesheets are only applied when the user picks them.
+                let is_stylesheet = rel.as_ref().is_some_and(|rel| {
+                    let mut tokens = rel.split_ascii_whitespace();
+                    tokens.clone().any(|t| t == "stylesheet") && !tokens.any(|t| t == "alternate")
+                });
+                let href = doc.attribute(id, "href").map(str::trim).filter(|href| !href.is_empty());
+                if let (true, Some(href)) = (is_stylesheet, href) {
+                    match base.join(href) {
+                        Ok(url) => sources.push(StyleSource::Linked(url)),
+                        Err(e) => log::debug!("Ignoring stylesheet link {href:?}: {e}"),
+                    }
+                }
+            }
+            // The contents of a template aren't part of the document yet.
+            Some("template") => continue,
+            _ => {}
+        }
+        stack.extend(doc.children(id).iter().rev().copied());
+    }
+    sources
+}
+
+/// Parses the text of an author stylesheet. `source_url` is where it came from: the sheet's own
+/// URL, or the document's with `#inline` for a `<style>`.
+pub fn parse_author_stylesheet<C: RenderConfiguration>(
+    css: &str,
+    source_url: &str,
+) -> Option<<C::CssSystem as CssSystem>::Stylesheet> {
+    let config = ParserConfig {
+        context: ParseContext::Stylesheet,
+        location: Default::default(),
+        source: Some(source_url.to_string()),
+        ignore_errors: true,
+        match_values: false,
+    };
+    C::CssSystem::parse_str(css, config, CssOrigin::Author, source_url)
+        .inspect_err(|e| log::warn!("Stylesheet {source_url} failed to parse: {e}"))
+        .ok()
+}
+
+/// The URL relative URLs in `doc` resolve against: the first `<base href>`, or the document's.
+fn base_url<C: RenderConfiguration>(doc: &EngineDocument<C>, document_url: &Url) -> Url {
+    let mut stack = vec![doc.root()];
+    while let Some(id) = stack.pop() {
+        if doc.tag_name(id) == Some("base") {
+            if let Some(href) = doc.attribute(id, "href") {
+                return document_url.join(href.trim()).unwrap_or_else(|_| document_url.clone());
+            }
+        }
+        stack.extend(doc.children(id).iter().rev().copied());
+    }
+    document_url.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::DefaultRenderConfig;
+    use gosub_html5::document::builder::DocumentBuilderImpl;
+    use gosub_html5::parser::{Html5Parser, Html5ParserOptions};
+    use gosub_shared::byte_stream::{ByteStream, Encoding};
+
+    fn parse(html: &str, url: &Url) -> EngineDocument<DefaultRenderConfig> {
+        let mut stream = ByteStream::from_str(html, Encoding::UTF8);
+        let mut doc = DocumentBuilderImpl::new_document::<DefaultRenderConfig>(Some(url.clone()));
+        let options = Html5ParserOptions {
+            load_stylesheets: false,
+            ..Default::default()
+        };
+        let _ = Html5Parser::<DefaultRenderConfig>::parse_document(&mut stream, &mut doc, Some(options));
+        doc
+    }
+
+    #[test]
+    fn lists_applicable_sheets_in_tree_order() {
+        let url = Url::parse("https://example.org/docs/page.html").unwrap();
+        let doc = parse(
+            r#"<html><head>
+                <link rel="stylesheet" href="base.css">
+                <style>p { color: red }</style>
+                <link rel="stylesheet" href="print.css" media="print">
+                <link rel="alternate stylesheet" href="alt.css">
+                <link rel="icon" href="favicon.png">
+                <link rel="Stylesheet" href="/wide.css" media="screen and (min-width: 600px)">
+            </head><body>
+                <style media="screen">h1 { color: blue }</style>
+                <template><style>p { color: green }</style></template>
+            </body></html>"#,
+            &url,
+        );
+        // Nothing is loaded while parsing.
+        assert!(doc.stylesheets().is_empty());
+
+        let media = MediaEnvironment {
+            width: 800.0,
+            height: 600.0,
+            ..Default::default()
+        };
+        assert_eq!(
+            style_sources(&doc, &url, &media),
+            vec![
+                StyleSource::Linked(Url::parse("https://example.org/docs/base.css").unwrap()),
+                StyleSource::Inline("p { color: red }".into()),
+                StyleSource::Linked(Url::parse("https://example.org/wide.css").unwrap()),
+                StyleSource::Inline("h1 { color: blue }".into()),
+            ]
+        );
+
+        let narrow = MediaEnvironment { width: 400.0, ..media };
+        assert_eq!(style_sources(&doc, &url, &narrow).len(), 3);
+    }
+
+    #[test]
+    fn resolves_links_against_base() {
+        let url = Url::parse("https://example.org/page.html").unwrap();
+        let doc = parse(
+            r#"<head><base href="https://cdn.example/assets/"><link rel=stylesheet href=site.css></head>"#,
+            &url,
+        );
+        assert_eq!(
+            style_sources(&doc, &url, &MediaEnvironment::default()),
+            vec![StyleSource::Linked(
+                Url::parse("https://cdn.example/assets/site.css").unwrap()
+            )]
+        );
+    }
+}
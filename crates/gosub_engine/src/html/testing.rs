@@ -0,0 +1,26 @@
+//! Documents for unit tests.
+
+use crate::html::{DefaultRenderConfig, EngineDocument};
+use gosub_html5::document::builder::DocumentBuilderImpl;
+use gosub_html5::parser::Html5Parser;
+use gosub_shared::byte_stream::{ByteStream, Encoding};
+use url::Url;
+
+/// Parses `html` into a document without a URL.
+pub(crate) fn parse(html: &str) -> EngineDocument<DefaultRenderConfig> {
+    parse_document(None, html)
+}
+
+/// Parses `html` into a document loaded from `url`, which relative URLs in it resolve against.
+pub(crate) fn parse_at(url: &str, html: &str) -> EngineDocument<DefaultRenderConfig> {
+    parse_document(Some(Url::parse(url).expect("test URL")), html)
+}
+
+fn parse_document(url: Option<Url>, html: &str) -> EngineDocument<DefaultRenderConfig> {
+    let mut stream = ByteStream::new(Encoding::UTF8, None);
+    stream.read_from_str(html, Some(Encoding::UTF8));
+    stream.close();
+    let mut doc = DocumentBuilderImpl::new_document::<DefaultRenderConfig>(url);
+    let _ = Html5Parser::<DefaultRenderConfig>::parse_document(&mut stream, &mut doc, None);
+    doc
+}
@@ -144,12 +144,18 @@ pub mod net;
 
 pub mod util;
 
+pub mod reftest;
+
+pub mod wpt;
+
 pub mod html;
 
 #[cfg(feature = "metrics")]
 pub mod metrics;
 
-pub use engine::{BrowsingContext, EngineError, GosubEngine};
+pub use engine::{
+    BrowsingContext, EngineError, FullPageCapture, FullPagePixmap, GosubEngine, HitTestResult, LinkTarget, SelectPopup,
+};
 
 /// The engine's ready-made config: a marker that implements both
 /// [`ModuleConfiguration`](gosub_interface::config::ModuleConfiguration) (parse/style stack) and
@@ -172,6 +178,14 @@ pub use engine::tab;
 #[doc(inline)]
 pub use engine::zone;
 
+#[doc(inline)]
+/// Accessibility tree built from the DOM and layout.
+pub use engine::accessibility;
+
+#[doc(inline)]
+/// Serializable descriptions of DOM nodes for inspectors.
+pub use engine::node_desc;
+
 #[doc(inline)]
 pub use engine::cookies;
 
@@ -179,6 +193,10 @@ pub use engine::cookies;
 /// Storage APIs for local/session data.
 pub use engine::storage;
 
+#[doc(inline)]
+/// Per-origin permission decisions.
+pub use engine::permissions;
+
 // EngineConfig at crate root:
 #[doc(inline)]
 pub use crate::engine::config::EngineConfig;
@@ -190,7 +208,7 @@ pub use crate::engine::cookies::ThirdPartyCookiePolicy;
 /// Public `events` namespace with the enums/structs:
 pub mod events {
     pub use crate::engine::events::{EngineCommand, EngineEvent, IoCommand, MouseButton, TabCommand};
-    pub use crate::engine::events::{NavigationEvent, ResourceEvent};
+    pub use crate::engine::events::{Modifiers, NavigationEvent, ResourceEvent, ScrollToBehavior};
 }
 
 /// Configuration options for the Gosub engine.
@@ -8,6 +8,7 @@
 //! |--------|-------------------|----------------------------------------|
 //! | GET    | `/metrics`        | JSON snapshot of all timing namespaces |
 //! | GET    | `/metrics/reset`  | Clear all timing counters              |
+//! | GET    | `/metrics/trace`  | Every timing as a Chrome trace         |
 //! | GET    | `/health`         | Liveness probe (`{"status":"ok"}`)     |
 
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
@@ -43,6 +44,9 @@ async fn handle(mut stream: TcpStream) {
     let (code, phrase, body) = if first_line.starts_with("GET /metrics/reset") {
         gosub_shared::timing::reset_stats();
         (200u16, "OK", r#"{"status":"reset"}"#.to_string())
+    } else if first_line.starts_with("GET /metrics/trace") {
+        // Trace Event JSON, for loading into Perfetto (ui.perfetto.dev) or chrome://tracing
+        (200, "OK", gosub_shared::timing::chrome_trace())
     } else if first_line.starts_with("GET /metrics") || first_line.starts_with("HEAD /metrics") {
         (200, "OK", build_metrics_json())
     } else if first_line.starts_with("GET /health") {
@@ -14,6 +14,16 @@
 //! - A **router** that classifies responses and decides how the engine should handle them
 //!   ([`route_response_for`], [`RoutedOutcome`], [`decide_handling`]).
 //! - **Typed events** emitted during fetch & routing phases ([`events`]).
+//! - **Content blocking** from filter lists, checked per tab before requests are submitted
+//!   ([`blocking`]).
+//! - **Text decoding** of stylesheets and scripts in the encoding they declare ([`charset`]).
+//! - **CORS checks** for requests made on behalf of page scripts ([`cors`]).
+//! - **Form data bodies**, URL-encoded or `multipart/form-data` ([`form_data`]).
+//! - An **HTTP cache** in front of the fetchers, in memory or on disk ([`http_cache`]).
+//! - **HSTS**, upgrading requests to hosts that asked for HTTPS only ([`hsts`]), and **mixed
+//!   content** checks for insecure subresources of secure documents ([`mixed_content`]).
+//! - A **redirect policy** checked against every hop, and the redirect chain of each response
+//!   ([`redirect`]).
 //!
 //! ## Threading model (high level)
 //! ```text
@@ -50,12 +60,21 @@
 //! The submodules below are internal implementation details unless re-exported. Public
 //! items are documented via the re-exports that follow.
 //!
+pub mod blocking;
+pub mod charset;
+pub mod cors;
 mod decision;
 mod decision_hub;
 mod emitter;
 pub mod events;
 mod fetcher;
+pub mod form_data;
+pub mod hsts;
+pub mod http_cache;
 mod io_runtime;
+pub mod mixed_content;
+mod pending;
+pub mod redirect;
 pub mod req_ref_tracker;
 mod router;
 mod shared_body;
@@ -64,6 +83,8 @@ mod utils;
 
 /// Make a **handling decision** for a routed response (e.g., render as document, hand to download manager).
 pub use decision::decide_handling;
+/// Work out the MIME type a response is handled as, per the MIME Sniffing spec.
+pub use decision::sniff::{sniff_mime, SniffContext};
 /// Common decision enums used across the network -> engine boundary.
 pub use decision::types::{DecisionOutcome, HandlingDecision, RenderTarget, RequestDestination};
 /// A **token** used to coordinate decisions across subsystems (e.g., to cancel or defer).
@@ -85,6 +106,9 @@ pub use io_runtime::submit_to_io;
 /// Handle to the I/O runtime; cloneable and sendable across threads.
 pub use io_runtime::IoHandle;
 
+/// A submitted fetch, awaited for its response and abortable mid-flight.
+pub use pending::{FetchAbort, FetchError, PendingFetch};
+
 /// Configuration for the fetcher (timeouts, size limits, user agent, etc.).
 pub use fetcher::FetcherConfig;
 
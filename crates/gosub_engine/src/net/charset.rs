@@ -0,0 +1,108 @@
+//! Decoding fetched text.
+//!
+//! Stylesheets and classic scripts aren't necessarily UTF-8: their encoding comes from a byte
+//! order mark, the `charset` of their `Content-Type`, an `@charset` rule (stylesheets only) or
+//! the encoding of the document that loads them, in that order of precedence. Module and worker
+//! scripts are always UTF-8.
+
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+
+/// How far into a stylesheet an `@charset` rule is looked for
+const CHARSET_RULE_WINDOW: usize = 1024;
+
+/// The encoding named by the `charset` parameter of a `Content-Type` value, if it names one.
+pub fn charset_from_content_type(content_type: &str) -> Option<&'static Encoding> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("charset") {
+            return None;
+        }
+        let value = value.trim().trim_matches('"');
+        Encoding::for_label(value.as_bytes())
+    })
+}
+
+/// Decodes a stylesheet as CSS Syntax's "decode" does. `environment` is the encoding of the
+/// document or stylesheet that refers to it.
+pub fn decode_stylesheet(bytes: &[u8], content_type: Option<&str>, environment: Option<&'static Encoding>) -> String {
+    let fallback = content_type
+        .and_then(charset_from_content_type)
+        .or_else(|| charset_rule(bytes))
+        .or(environment)
+        .unwrap_or(UTF_8);
+    fallback.decode(bytes).0.into_owned()
+}
+
+/// Decodes a classic script. `fallback` is the encoding given by the script element's `charset`
+/// attribute, or else the document's.
+pub fn decode_script(bytes: &[u8], content_type: Option<&str>, fallback: Option<&'static Encoding>) -> String {
+    let encoding = content_type
+        .and_then(charset_from_content_type)
+        .or(fallback)
+        .unwrap_or(UTF_8);
+    encoding.decode(bytes).0.into_owned()
+}
+
+/// Decodes UTF-8 text, dropping a byte order mark, like module and worker scripts are.
+pub fn decode_utf8(bytes: &[u8]) -> String {
+    UTF_8.decode_with_bom_removal(bytes).0.into_owned()
+}
+
+/// The encoding of a stylesheet's leading `@charset "...";` rule. The rule has to be written
+/// exactly like that, and a UTF-16 label means UTF-8: a sheet that can be read this far isn't
+/// UTF-16.
+fn charset_rule(bytes: &[u8]) -> Option<&'static Encoding> {
+    let window = &bytes[..bytes.len().min(CHARSET_RULE_WINDOW)];
+    let rest = window.strip_prefix(b"@charset \"")?;
+    let end = rest.iter().position(|&b| b == b'"' || b == b';')?;
+    if rest.get(end..end + 2) != Some(&b"\";"[..]) {
+        return None;
+    }
+    let encoding = Encoding::for_label(&rest[..end])?;
+    if encoding == UTF_16BE || encoding == UTF_16LE {
+        Some(UTF_8)
+    } else {
+        Some(encoding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{SHIFT_JIS, WINDOWS_1252};
+
+    #[test]
+    fn stylesheet_encoding_precedence() {
+        // "é" in windows-1252
+        let latin = b"@charset \"windows-1252\"; a::after { content: '\xe9' }";
+        assert!(decode_stylesheet(latin, None, None).contains("'é'"));
+        // The Content-Type's charset wins over the rule...
+        assert!(decode_stylesheet(latin, Some("text/css; charset=utf-8"), None).contains("'\u{fffd}'"));
+        // ...and a byte order mark over both.
+        let bom = "\u{feff}a::after { content: 'é' }".as_bytes();
+        assert!(decode_stylesheet(bom, Some("text/css; charset=\"Shift_JIS\""), Some(WINDOWS_1252)).contains("'é'"));
+
+        // Without either, the referring document's encoding is used.
+        let plain = b"a::after { content: '\xe9' }";
+        assert!(decode_stylesheet(plain, Some("text/css"), Some(WINDOWS_1252)).contains("'é'"));
+        assert!(decode_stylesheet(plain, None, None).contains("'\u{fffd}'"));
+        assert_eq!(charset_rule(b"@charset \"utf-16le\";"), Some(UTF_8));
+        assert_eq!(charset_rule(b"@charset 'utf-8';"), None);
+    }
+
+    #[test]
+    fn script_encodings() {
+        let sjis = b"var s = '\x82\xa0';";
+        assert_eq!(
+            decode_script(sjis, Some("text/javascript;charset=shift_jis"), None),
+            "var s = 'あ';"
+        );
+        assert_eq!(
+            decode_script(sjis, Some("text/javascript"), Some(SHIFT_JIS)),
+            "var s = 'あ';"
+        );
+        assert_eq!(decode_utf8("\u{feff}export {};".as_bytes()), "export {};");
+        assert_eq!(charset_from_content_type("text/css"), None);
+        assert_eq!(charset_from_content_type("text/css; charset=bogus"), None);
+    }
+}
@@ -0,0 +1,324 @@
+//! Cross-origin checks for script-initiated requests, after the Fetch standard.
+//!
+//! The fetcher itself is mode-agnostic; callers that fetch on behalf of a page (like `fetch()`)
+//! use these helpers around it:
+//! 1. [`needs_preflight`] tells whether a cross-origin request has to be cleared with an
+//!    `OPTIONS` preflight first, built with [`preflight_headers`] and judged by
+//!    [`check_preflight`].
+//! 2. [`check_response`] decides whether the page may see a cross-origin response at all.
+//! 3. [`expose_headers`] strips the response down to the headers the page may read.
+//!
+//! There is no preflight cache: every non-simple request is preflighted.
+
+use cow_utils::CowUtils;
+use http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use http::Method;
+use serde::Deserialize;
+use url::Url;
+
+/// The request's mode, as in `fetch(url, { mode })`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RequestMode {
+    /// Cross-origin requests must pass the CORS checks
+    #[default]
+    Cors,
+    /// Cross-origin requests are limited to simple ones, and their responses are opaque
+    NoCors,
+    /// Cross-origin requests fail
+    SameOrigin,
+}
+
+/// Whether cookies go along, as in `fetch(url, { credentials })`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CredentialsMode {
+    Omit,
+    #[default]
+    SameOrigin,
+    Include,
+}
+
+impl CredentialsMode {
+    /// Whether a request to `url` from `origin` carries credentials.
+    pub fn sends_credentials(self, origin: &url::Origin, url: &Url) -> bool {
+        match self {
+            CredentialsMode::Omit => false,
+            CredentialsMode::SameOrigin => url.origin() == *origin,
+            CredentialsMode::Include => true,
+        }
+    }
+}
+
+/// Why a cross-origin request or response was refused.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum CorsError {
+    #[error("cross-origin request to {0} is not allowed in same-origin mode")]
+    SameOriginOnly(Url),
+    #[error("method {0} is not allowed in no-cors mode")]
+    NoCorsMethod(Method),
+    #[error("preflight for {0} failed with status {1}")]
+    PreflightStatus(Url, u16),
+    #[error("missing or mismatched Access-Control-Allow-Origin")]
+    AllowOrigin,
+    #[error("credentials are not allowed (Access-Control-Allow-Credentials)")]
+    AllowCredentials,
+    #[error("method {0} is not allowed by the preflight response")]
+    Method(Method),
+    #[error("header {0} is not allowed by the preflight response")]
+    Header(String),
+}
+
+/// `GET`, `HEAD` and `POST`, which need no preflight.
+pub fn is_safelisted_method(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::POST)
+}
+
+/// Request headers a page may set without a preflight.
+pub fn is_safelisted_request_header(name: &HeaderName, value: &HeaderValue) -> bool {
+    if value.len() > 128 {
+        return false;
+    }
+    if *name == header::ACCEPT || *name == header::ACCEPT_LANGUAGE || *name == header::CONTENT_LANGUAGE {
+        return true;
+    }
+    if *name == header::CONTENT_TYPE {
+        let Ok(value) = value.to_str() else {
+            return false;
+        };
+        let essence = value.split(';').next().unwrap_or("").trim().cow_to_ascii_lowercase();
+        return matches!(
+            &*essence,
+            "application/x-www-form-urlencoded" | "multipart/form-data" | "text/plain"
+        );
+    }
+    false
+}
+
+/// Whether a cross-origin request needs a preflight.
+pub fn needs_preflight(method: &Method, headers: &HeaderMap) -> bool {
+    !is_safelisted_method(method) || headers.iter().any(|(n, v)| !is_safelisted_request_header(n, v))
+}
+
+/// The `Origin` header value for `origin`.
+pub fn origin_header(origin: &url::Origin) -> HeaderValue {
+    HeaderValue::from_str(&origin.ascii_serialization()).unwrap_or(HeaderValue::from_static("null"))
+}
+
+/// Headers for the `OPTIONS` preflight of a `method` request carrying `headers`.
+pub fn preflight_headers(origin: &url::Origin, method: &Method, headers: &HeaderMap) -> HeaderMap {
+    let mut preflight = HeaderMap::new();
+    preflight.insert(header::ORIGIN, origin_header(origin));
+    if let Ok(value) = HeaderValue::from_str(method.as_str()) {
+        preflight.insert(header::ACCESS_CONTROL_REQUEST_METHOD, value);
+    }
+    let mut names: Vec<&str> = headers
+        .iter()
+        .filter(|(n, v)| !is_safelisted_request_header(n, v))
+        .map(|(n, _)| n.as_str())
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    if !names.is_empty() {
+        if let Ok(value) = HeaderValue::from_str(&names.join(",")) {
+            preflight.insert(header::ACCESS_CONTROL_REQUEST_HEADERS, value);
+        }
+    }
+    preflight
+}
+
+/// Checks a (preflight or actual) response's `Access-Control-Allow-Origin` and, for
+/// credentialed requests, `Access-Control-Allow-Credentials`.
+pub fn check_response(origin: &url::Origin, credentials: bool, headers: &HeaderMap) -> Result<(), CorsError> {
+    let allow_origin = headers
+        .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim);
+    match allow_origin {
+        Some("*") if !credentials => {}
+        Some(allowed) if allowed == origin.ascii_serialization() => {}
+        _ => return Err(CorsError::AllowOrigin),
+    }
+    if credentials {
+        let allow_credentials = headers
+            .get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS)
+            .and_then(|v| v.to_str().ok());
+        if allow_credentials != Some("true") {
+            return Err(CorsError::AllowCredentials);
+        }
+    }
+    Ok(())
+}
+
+/// Judges the preflight response for a `method` request carrying `headers`.
+pub fn check_preflight(
+    origin: &url::Origin,
+    credentials: bool,
+    method: &Method,
+    headers: &HeaderMap,
+    url: &Url,
+    status: u16,
+    response: &HeaderMap,
+) -> Result<(), CorsError> {
+    if !(200..300).contains(&status) {
+        return Err(CorsError::PreflightStatus(url.clone(), status));
+    }
+    check_response(origin, credentials, response)?;
+
+    let allowed = |name: HeaderName| -> Vec<String> {
+        response
+            .get_all(name)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(|s| s.trim().cow_to_ascii_lowercase().into_owned())
+            .filter(|s| !s.is_empty())
+            .collect()
+    };
+    // `*` is only a wildcard for requests without credentials.
+    let wildcard = |list: &[String]| !credentials && list.iter().any(|s| s == "*");
+
+    let methods = allowed(header::ACCESS_CONTROL_ALLOW_METHODS);
+    let method_name = method.as_str().cow_to_ascii_lowercase();
+    if !is_safelisted_method(method) && !wildcard(&methods) && !methods.iter().any(|m| *m == method_name) {
+        return Err(CorsError::Method(method.clone()));
+    }
+
+    let allowed_headers = allowed(header::ACCESS_CONTROL_ALLOW_HEADERS);
+    for (name, value) in headers {
+        if is_safelisted_request_header(name, value) {
+            continue;
+        }
+        // Authorization is never covered by the wildcard.
+        let by_wildcard = wildcard(&allowed_headers) && *name != header::AUTHORIZATION;
+        if !by_wildcard && !allowed_headers.iter().any(|h| h == name.as_str()) {
+            return Err(CorsError::Header(name.to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Response headers a page may always read.
+const SAFELISTED_RESPONSE_HEADERS: &[HeaderName] = &[
+    header::CACHE_CONTROL,
+    header::CONTENT_LANGUAGE,
+    header::CONTENT_LENGTH,
+    header::CONTENT_TYPE,
+    header::EXPIRES,
+    header::LAST_MODIFIED,
+    header::PRAGMA,
+];
+
+/// The headers of a cross-origin response the page may read: the safelisted ones plus whatever
+/// `Access-Control-Expose-Headers` lists.
+pub fn expose_headers(credentials: bool, headers: &HeaderMap) -> HeaderMap {
+    let exposed: Vec<String> = headers
+        .get_all(header::ACCESS_CONTROL_EXPOSE_HEADERS)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|s| s.trim().cow_to_ascii_lowercase().into_owned())
+        .collect();
+    let all = !credentials && exposed.iter().any(|s| s == "*");
+
+    let mut visible = HeaderMap::new();
+    for (name, value) in headers {
+        let is_cookie = *name == header::SET_COOKIE || name.as_str() == "set-cookie2";
+        let listed = SAFELISTED_RESPONSE_HEADERS.contains(name) || all || exposed.iter().any(|s| s == name.as_str());
+        if listed && !is_cookie {
+            visible.append(name.clone(), value.clone());
+        }
+    }
+    visible
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn origin() -> url::Origin {
+        Url::parse("https://app.example").unwrap().origin()
+    }
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for &(name, value) in pairs {
+            map.append(name, HeaderValue::from_static(value));
+        }
+        map
+    }
+
+    #[test]
+    fn simple_requests_skip_the_preflight() {
+        let form = headers(&[("content-type", "application/x-www-form-urlencoded; charset=utf-8")]);
+        assert!(!needs_preflight(&Method::POST, &form));
+        let json = headers(&[("content-type", "application/json")]);
+        assert!(needs_preflight(&Method::POST, &json));
+        assert!(needs_preflight(&Method::PUT, &HeaderMap::new()));
+        assert!(needs_preflight(&Method::GET, &headers(&[("x-token", "1")])));
+
+        let preflight = preflight_headers(&origin(), &Method::PUT, &headers(&[("x-b", "1"), ("x-a", "2")]));
+        assert_eq!(preflight[header::ORIGIN], "https://app.example");
+        assert_eq!(preflight[header::ACCESS_CONTROL_REQUEST_METHOD], "PUT");
+        assert_eq!(preflight[header::ACCESS_CONTROL_REQUEST_HEADERS], "x-a,x-b");
+    }
+
+    #[test]
+    fn allow_origin_and_credentials() {
+        let star = headers(&[("access-control-allow-origin", "*")]);
+        assert_eq!(check_response(&origin(), false, &star), Ok(()));
+        assert_eq!(check_response(&origin(), true, &star), Err(CorsError::AllowOrigin));
+
+        let exact = headers(&[
+            ("access-control-allow-origin", "https://app.example"),
+            ("access-control-allow-credentials", "true"),
+        ]);
+        assert_eq!(check_response(&origin(), true, &exact), Ok(()));
+        let other = headers(&[("access-control-allow-origin", "https://evil.example")]);
+        assert_eq!(check_response(&origin(), false, &other), Err(CorsError::AllowOrigin));
+        assert_eq!(
+            check_response(&origin(), false, &HeaderMap::new()),
+            Err(CorsError::AllowOrigin)
+        );
+    }
+
+    #[test]
+    fn preflight_checks_methods_and_headers() {
+        let url = Url::parse("https://api.example/items").unwrap();
+        let request = headers(&[("x-token", "1"), ("authorization", "Bearer t")]);
+        let response = headers(&[
+            ("access-control-allow-origin", "*"),
+            ("access-control-allow-methods", "GET, PUT"),
+            ("access-control-allow-headers", "*"),
+        ]);
+        assert_eq!(
+            check_preflight(&origin(), false, &Method::PUT, &request, &url, 204, &response),
+            Err(CorsError::Header("authorization".into()))
+        );
+        let request = headers(&[("x-token", "1")]);
+        assert_eq!(
+            check_preflight(&origin(), false, &Method::PUT, &request, &url, 204, &response),
+            Ok(())
+        );
+        assert_eq!(
+            check_preflight(&origin(), false, &Method::DELETE, &request, &url, 204, &response),
+            Err(CorsError::Method(Method::DELETE))
+        );
+        assert!(check_preflight(&origin(), false, &Method::PUT, &request, &url, 403, &response).is_err());
+
+        let visible = expose_headers(
+            false,
+            &headers(&[
+                ("content-type", "text/plain"),
+                ("x-secret", "1"),
+                ("x-total", "3"),
+                ("set-cookie", "a=b"),
+                ("access-control-expose-headers", "X-Total"),
+            ]),
+        );
+        assert!(visible.contains_key("content-type"));
+        assert!(visible.contains_key("x-total"));
+        assert!(!visible.contains_key("x-secret"));
+        assert!(!visible.contains_key("set-cookie"));
+    }
+}
@@ -4,12 +4,12 @@
 
 use crate::engine::types::PeekBuf;
 use crate::engine::UaPolicy;
-use crate::net::decision::sniff::{sniff_class, ResponseClass};
-use crate::net::decision::types::{DecisionOutcome, HandlingDecision, RenderTarget, RequestDestination};
+use crate::net::decision::sniff::{sniff_class, sniff_mime, ResponseClass, SniffContext};
+use crate::net::decision::types::{BlockReason, DecisionOutcome, HandlingDecision, RenderTarget, RequestDestination};
 use crate::net::types::FetchResultMeta;
 use cow_utils::CowUtils;
 
-mod sniff;
+pub mod sniff;
 pub mod types;
 
 /// Decide how the user agent should handle a fetched response.
@@ -21,35 +21,64 @@ pub mod types;
 /// 3) **Sniffed class** from a peek buffer (when allowed)
 ///
 /// High-level rules:
+/// - A script or stylesheet sent with `nosniff` and a type that doesn't fit is **Block**ed.
 /// - If `Content-Disposition` indicates *attachment* and UA policy allows downloads without
 ///   user activation, prefer **Download**.
-/// - If a trustworthy declared type is present, prefer it. If not trustworthy or absent,
-///   and sniffing is enabled (and not blocked by `nosniff`), use the **sniffed** class.
+/// - The declared type is corrected by [sniffing](sniff::sniff_mime) as far as the destination
+///   allows, if sniffing is enabled (`nosniff` limits it further).
 /// - If the result is (or looks like) **PDF** and the UA has an embedded PDF viewer,
 ///   prefer **Render(PdfViewer)**.
 /// - If navigation sniffing upgrade is enabled, allow HTML upgrade for mislabelled
 ///   navigations (e.g., `text/plain` / `application/octet-stream` that sniff as HTML).
 ///
 /// Returns a [`DecisionOutcome`] with both the *final* class and the auxiliary evidence
-/// (declared MIME, sniffed class, disposition flag).
+/// (declared and computed MIME, sniffed class, disposition flag).
 pub fn decide_handling(
     meta: &FetchResultMeta,
     dest: RequestDestination,
     peek_buf: PeekBuf,
     policy: &UaPolicy,
 ) -> DecisionOutcome {
-    let declared_mime = content_type_from_headers(meta);
-    let nosniff = header_eq(meta, http::header::X_CONTENT_TYPE_OPTIONS, "nosniff");
+    let content_type = meta
+        .headers
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok());
+    let declared_mime = content_type.and_then(|s| s.parse::<mime::Mime>().ok());
+    let nosniff = is_nosniff(meta);
     let is_attachment = content_disposition_is_attachment(meta);
 
+    // Responses to nosniff requests must be what the destination expects.
+    if nosniff && nosniff_blocks(dest, declared_mime.as_ref()) {
+        return DecisionOutcome {
+            class: declared_mime
+                .as_ref()
+                .map(ResponseClass::from_mime)
+                .unwrap_or(ResponseClass::Unknown),
+            sniffed_class: None,
+            declared_mime: declared_mime.clone(),
+            computed_mime: declared_mime,
+            disposition_attachment: is_attachment,
+            decision: HandlingDecision::Block(BlockReason::NoSniff),
+        };
+    }
+
     let sniffed_class = if policy.enable_sniffing && !nosniff {
-        Some(sniff_class(peek_buf))
+        Some(sniff_class(peek_buf.clone()))
     } else {
         None
     };
 
-    // Prefer trustworthy declared types, otherwise fall back to sniffed type.
-    let mut effective_class = declared_mime.as_ref().and_then(class_from_mime).or(sniffed_class);
+    // The type the response is handled as: the declared one, corrected by sniffing where the
+    // destination allows it.
+    let computed_mime = match sniff_context(dest) {
+        Some(context) if policy.enable_sniffing => sniff_mime(content_type, nosniff, peek_buf.as_slice(), context),
+        _ => declared_mime.clone(),
+    };
+    let mut effective_class = computed_mime
+        .as_ref()
+        .map(ResponseClass::from_mime)
+        .filter(|class| *class != ResponseClass::Unknown)
+        .or(sniffed_class);
 
     // Optional "navigation sniffing upgrade": allow HTML if mislabelled during document nav.
     if policy.enable_sniffing_navigation_upgrade
@@ -70,6 +99,7 @@ pub fn decide_handling(
             class: effective_class.unwrap_or(ResponseClass::Binary),
             sniffed_class,
             declared_mime,
+            computed_mime,
             disposition_attachment: true,
             decision: HandlingDecision::Download {
                 path: std::path::PathBuf::new(),
@@ -79,7 +109,7 @@ pub fn decide_handling(
 
     // Special case: if we look like a PDF, and the UA has a PDF viewer, render in it.
     if policy.enable_pdf_viewer {
-        let looks_like_pdf = declared_mime.as_ref().map(mime_is_pdf).unwrap_or(false)
+        let looks_like_pdf = computed_mime.as_ref().map(mime_is_pdf).unwrap_or(false)
             || matches!(sniffed_class, Some(ResponseClass::Pdf));
 
         if looks_like_pdf {
@@ -87,6 +117,7 @@ pub fn decide_handling(
                 class: ResponseClass::Pdf,
                 sniffed_class,
                 declared_mime,
+                computed_mime,
                 disposition_attachment: false,
                 decision: HandlingDecision::Render(RenderTarget::PdfViewer),
             };
@@ -133,25 +164,49 @@ pub fn decide_handling(
         class,
         sniffed_class,
         declared_mime,
+        computed_mime,
         disposition_attachment: is_attachment,
         decision,
     }
 }
 
-/// Extract and parse the `Content-Type` header from the response metadata.
-fn content_type_from_headers(meta: &FetchResultMeta) -> Option<mime::Mime> {
+/// Whether the response has `X-Content-Type-Options: nosniff`. Only the first value counts.
+fn is_nosniff(meta: &FetchResultMeta) -> bool {
     meta.headers
-        .get(http::header::CONTENT_TYPE)
+        .get(http::header::X_CONTENT_TYPE_OPTIONS)
         .and_then(|v| v.to_str().ok())
-        .and_then(|s| s.parse::<mime::Mime>().ok())
+        .and_then(|s| s.split(',').next())
+        .is_some_and(|s| s.trim().eq_ignore_ascii_case("nosniff"))
 }
 
-/// Check if a header equals a given value, case-insensitively.
-fn header_eq(meta: &FetchResultMeta, name: http::header::HeaderName, val_ci: &str) -> bool {
-    meta.headers
-        .get(name)
-        .and_then(|v| v.to_str().ok())
-        .is_some_and(|s| s.eq_ignore_ascii_case(val_ci))
+/// The sniffing rules for a destination, `None` for ones whose responses aren't sniffed.
+fn sniff_context(dest: RequestDestination) -> Option<SniffContext> {
+    match dest {
+        RequestDestination::Document => Some(SniffContext::Browsing),
+        RequestDestination::Image => Some(SniffContext::Image),
+        RequestDestination::Audio | RequestDestination::Video => Some(SniffContext::AudioVideo),
+        RequestDestination::Font => Some(SniffContext::Font),
+        RequestDestination::Style => Some(SniffContext::Style),
+        RequestDestination::Script
+        | RequestDestination::Worker
+        | RequestDestination::SharedWorker
+        | RequestDestination::ServiceWorker => Some(SniffContext::Script),
+        _ => None,
+    }
+}
+
+/// Fetch's "should response to request be blocked due to nosniff?": scripts must have a
+/// JavaScript type and stylesheets `text/css`.
+fn nosniff_blocks(dest: RequestDestination, declared: Option<&mime::Mime>) -> bool {
+    let class = declared.map(ResponseClass::from_mime);
+    match dest {
+        RequestDestination::Script
+        | RequestDestination::Worker
+        | RequestDestination::SharedWorker
+        | RequestDestination::ServiceWorker => class != Some(ResponseClass::Js),
+        RequestDestination::Style => class != Some(ResponseClass::Css),
+        _ => false,
+    }
 }
 
 /// Check if the `Content-Disposition` header indicates an attachment.
@@ -168,26 +223,43 @@ fn mime_is_pdf(m: &mime::Mime) -> bool {
     (m.type_() == mime::APPLICATION && m.subtype() == "pdf") || m.essence_str().eq_ignore_ascii_case("application/pdf")
 }
 
-/// Map a MIME type to a coarse `ResponseClass`.
-fn class_from_mime(m: &mime::Mime) -> Option<ResponseClass> {
-    use ResponseClass::*;
-    if m.type_() == mime::TEXT && m.subtype() == mime::HTML {
-        Some(Html)
-    } else if m.type_() == mime::TEXT && m.subtype() == mime::CSS {
-        Some(Css)
-    } else if m.type_() == mime::APPLICATION && m.subtype() == "javascript" {
-        Some(Js)
-    } else if m.type_() == mime::IMAGE {
-        Some(Image)
-    } else if m.type_() == mime::FONT {
-        Some(Font)
-    } else if mime_is_pdf(m) {
-        Some(Pdf)
-    } else if m.type_() == mime::APPLICATION && m.subtype() == "json" {
-        Some(Json)
-    } else if m.type_() == mime::TEXT {
-        Some(Text)
-    } else {
-        None // treat as untrusted; let sniffing or policy decide
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderMap;
+    use url::Url;
+
+    fn meta(headers: &[(&'static str, &'static str)]) -> FetchResultMeta {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.insert(*name, http::HeaderValue::from_static(value));
+        }
+        FetchResultMeta {
+            final_url: Url::parse("https://example.org/resource").unwrap(),
+            status: 200,
+            status_text: "OK".into(),
+            headers: map,
+            content_length: None,
+            content_type: None,
+            has_body: true,
+        }
+    }
+
+    #[test]
+    fn sniffs_and_enforces_nosniff() {
+        let policy = UaPolicy::default();
+        let html = PeekBuf::from_slice(b"<!DOCTYPE html><title>x</title>");
+
+        let unlabelled = decide_handling(&meta(&[]), RequestDestination::Document, html.clone(), &policy);
+        assert_eq!(unlabelled.decision, HandlingDecision::Render(RenderTarget::HtmlParser));
+        assert_eq!(unlabelled.computed_mime.unwrap().essence_str(), "text/html");
+
+        let script = meta(&[("content-type", "text/plain"), ("x-content-type-options", "nosniff")]);
+        let blocked = decide_handling(&script, RequestDestination::Script, html.clone(), &policy);
+        assert_eq!(blocked.decision, HandlingDecision::Block(BlockReason::NoSniff));
+
+        let style = meta(&[("content-type", "text/css"), ("x-content-type-options", "NoSniff, foo")]);
+        let allowed = decide_handling(&style, RequestDestination::Style, html, &policy);
+        assert_eq!(allowed.decision, HandlingDecision::Render(RenderTarget::CssParser));
     }
 }
@@ -1,8 +1,19 @@
+//! MIME type sniffing, following the [MIME Sniffing](https://mimesniff.spec.whatwg.org/) spec.
+//!
+//! Servers regularly send HTML as `text/plain`, images as `application/octet-stream` or no
+//! `Content-Type` at all. [`sniff_mime`] works out the type a response is handled as from its
+//! declared type and its first bytes, the way the spec does for the context the response is used
+//! in. With `X-Content-Type-Options: nosniff` the declared type is trusted, and scriptable types
+//! (HTML, XML, PDF) are never sniffed.
+//!
+//! Not implemented: telling RSS and Atom feeds served as `text/html` apart, and recognising
+//! MP3 files without an ID3 tag.
+
 use crate::engine::types::PeekBuf;
-use cow_utils::CowUtils;
 use mime::Mime;
-use mimetype_detector::detect;
-use std::str::FromStr;
+
+/// How many bytes of a response are looked at, the spec's "resource header"
+const RESOURCE_HEADER_LEN: usize = 1445;
 
 // Coarse response class used for routing.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -62,38 +73,318 @@ impl ResponseClass {
     }
 }
 
+/// What a response is going to be used for, which decides how it is sniffed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SniffContext {
+    /// Loaded into a browsing context (a navigation)
+    Browsing,
+    Image,
+    /// An `<audio>` or `<video>` source
+    AudioVideo,
+    Font,
+    /// Stylesheets are never sniffed
+    Style,
+    /// Scripts are never sniffed
+    Script,
+}
+
+/// The MIME type a response is handled as: its `Content-Type` (if any) corrected by looking at
+/// its first bytes, as far as `context` allows. `no_sniff` is set when the response has
+/// `X-Content-Type-Options: nosniff`. `None` means there's no declared type and sniffing doesn't
+/// apply.
+pub fn sniff_mime(content_type: Option<&str>, no_sniff: bool, body: &[u8], context: SniffContext) -> Option<Mime> {
+    let header = &body[..body.len().min(RESOURCE_HEADER_LEN)];
+    let supplied = content_type.and_then(|ct| ct.trim().parse::<Mime>().ok());
+
+    match context {
+        SniffContext::Browsing => Some(sniff_browsing(content_type, supplied, no_sniff, header)),
+        SniffContext::Image | SniffContext::AudioVideo | SniffContext::Font if no_sniff => supplied,
+        SniffContext::Image => {
+            if supplied.as_ref().is_some_and(is_xml) {
+                return supplied;
+            }
+            match_image(header).map(mime).or(supplied)
+        }
+        SniffContext::AudioVideo => {
+            if supplied.as_ref().is_some_and(is_xml) {
+                return supplied;
+            }
+            match_audio_video(header).map(mime).or(supplied)
+        }
+        SniffContext::Font => {
+            if supplied.as_ref().is_some_and(is_xml) {
+                return supplied;
+            }
+            match_font(header).map(mime).or(supplied)
+        }
+        SniffContext::Style | SniffContext::Script => supplied,
+    }
+}
+
 /// Sniff the content type from the given peek buffer and return the corresponding ResponseClass.
+/// This ignores any declared type, as if the response had none.
 pub fn sniff_class(peek_buf: PeekBuf) -> ResponseClass {
     let bytes = peek_buf.as_slice();
+    let header = &bytes[..bytes.len().min(RESOURCE_HEADER_LEN)];
+    ResponseClass::from_mime(&mime(identify_unknown(header, true)))
+}
 
-    // Text-based formats: mimetype_detector uses magic bytes and won't detect these,
-    // so we check common text signatures first.
-    if let Ok(text) = std::str::from_utf8(&bytes[..bytes.len().min(512)]) {
-        let trimmed = text.trim_start();
-        let lower = trimmed.cow_to_ascii_lowercase();
-        if lower.starts_with("<!doctype html") || lower.starts_with("<html") {
-            return ResponseClass::Html;
+/// The spec's "determining the computed MIME type of a resource"
+fn sniff_browsing(content_type: Option<&str>, supplied: Option<Mime>, no_sniff: bool, header: &[u8]) -> Mime {
+    let Some(supplied) = supplied.filter(|m| !is_unknown(m)) else {
+        return mime(identify_unknown(header, !no_sniff));
+    };
+    if no_sniff {
+        return supplied;
+    }
+    // Old Apache versions labelled every file without a known extension like this.
+    if content_type.is_some_and(|ct| {
+        matches!(
+            ct,
+            "text/plain"
+                | "text/plain; charset=ISO-8859-1"
+                | "text/plain; charset=iso-8859-1"
+                | "text/plain; charset=UTF-8"
+        )
+    }) {
+        return mime(text_or_binary(header));
+    }
+    if is_xml(&supplied) || supplied.essence_str() == "text/html" {
+        return supplied;
+    }
+    if supplied.type_() == mime::IMAGE {
+        if let Some(matched) = match_image(header) {
+            return mime(matched);
         }
-        if lower.starts_with("<?xml") || lower.starts_with("<rss") || lower.starts_with("<feed") {
-            return ResponseClass::Xml;
+    }
+    if matches!(supplied.type_().as_str(), "audio" | "video") {
+        if let Some(matched) = match_audio_video(header) {
+            return mime(matched);
         }
-        // Heuristic CSS/JS detection by common patterns
-        if lower.contains('{') && (lower.contains(':') || lower.contains(';')) && !lower.starts_with('<') {
-            return ResponseClass::Css;
+    }
+    supplied
+}
+
+/// The spec's "rules for identifying an unknown MIME type"
+fn identify_unknown(header: &[u8], sniff_scriptable: bool) -> &'static str {
+    if sniff_scriptable {
+        if let Some(matched) = match_scriptable(header) {
+            return matched;
+        }
+    }
+    if header.starts_with(b"%!PS-Adobe-") {
+        return "application/postscript";
+    }
+    if has_text_bom(header) {
+        return "text/plain";
+    }
+    if let Some(matched) = match_image(header)
+        .or_else(|| match_audio_video(header))
+        .or_else(|| match_archive(header))
+    {
+        return matched;
+    }
+    if header.iter().copied().any(is_binary_data_byte) {
+        "application/octet-stream"
+    } else {
+        "text/plain"
+    }
+}
+
+/// The spec's "rules for distinguishing if a resource is text or binary"
+fn text_or_binary(header: &[u8]) -> &'static str {
+    if has_text_bom(header) || !header.iter().copied().any(is_binary_data_byte) {
+        return "text/plain";
+    }
+    identify_unknown(header, false)
+}
+
+/// Tags that mark a document as HTML when it starts with them
+const HTML_TAGS: &[&[u8]] = &[
+    b"<!DOCTYPE HTML",
+    b"<HTML",
+    b"<HEAD",
+    b"<SCRIPT",
+    b"<IFRAME",
+    b"<H1",
+    b"<DIV",
+    b"<FONT",
+    b"<TABLE",
+    b"<A",
+    b"<STYLE",
+    b"<TITLE",
+    b"<B",
+    b"<BODY",
+    b"<BR",
+    b"<P",
+    b"<!--",
+];
+
+/// HTML, XML and PDF: types that can run script, sniffed only when that's allowed
+fn match_scriptable(header: &[u8]) -> Option<&'static str> {
+    let start = header
+        .iter()
+        .position(|b| !matches!(b, b'\t' | b'\n' | b'\x0c' | b'\r' | b' '))
+        .unwrap_or(header.len());
+    let content = &header[start..];
+
+    for tag in HTML_TAGS {
+        let Some(prefix) = content.get(..tag.len()) else {
+            continue;
+        };
+        // The tag has to be followed by a space or `>`.
+        if prefix.eq_ignore_ascii_case(tag) && matches!(content.get(tag.len()), Some(b' ' | b'>')) {
+            return Some("text/html");
         }
-        if lower.contains("function ")
-            || lower.contains("console.")
-            || lower.contains("var ")
-            || lower.contains("const ")
-            || lower.contains("let ")
-        {
-            return ResponseClass::Js;
+    }
+    if content.starts_with(b"<?xml") {
+        return Some("text/xml");
+    }
+    if header.starts_with(b"%PDF-") {
+        return Some("application/pdf");
+    }
+    None
+}
+
+/// The spec's "image type pattern matching algorithm"
+fn match_image(header: &[u8]) -> Option<&'static str> {
+    if header.starts_with(b"\x00\x00\x01\x00") || header.starts_with(b"\x00\x00\x02\x00") {
+        Some("image/x-icon")
+    } else if header.starts_with(b"BM") {
+        Some("image/bmp")
+    } else if header.starts_with(b"GIF87a") || header.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if header.starts_with(b"RIFF") && header.get(8..14) == Some(&b"WEBPVP"[..]) {
+        Some("image/webp")
+    } else if header.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if header.starts_with(b"\xff\xd8\xff") {
+        Some("image/jpeg")
+    } else {
+        None
+    }
+}
+
+/// The spec's "audio or video type pattern matching algorithm"
+fn match_audio_video(header: &[u8]) -> Option<&'static str> {
+    let riff = |format: &[u8]| header.starts_with(b"RIFF") && header.get(8..12) == Some(format);
+    if header.starts_with(b".snd") {
+        Some("audio/basic")
+    } else if header.starts_with(b"FORM") && header.get(8..12) == Some(&b"AIFF"[..]) {
+        Some("audio/aiff")
+    } else if header.starts_with(b"ID3") {
+        Some("audio/mpeg")
+    } else if header.starts_with(b"OggS\x00") {
+        Some("application/ogg")
+    } else if header.starts_with(b"MThd\x00\x00\x00\x06") {
+        Some("audio/midi")
+    } else if riff(b"AVI ") {
+        Some("video/avi")
+    } else if riff(b"WAVE") {
+        Some("audio/wave")
+    } else if is_mp4(header) {
+        Some("video/mp4")
+    } else if is_webm(header) {
+        Some("video/webm")
+    } else {
+        None
+    }
+}
+
+/// The spec's "matches the signature for MP4": an `ftyp` box naming an `mp4` brand
+fn is_mp4(header: &[u8]) -> bool {
+    let Some(size) = header.get(..4) else {
+        return false;
+    };
+    let box_size = u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as usize;
+    if header.len() < box_size.max(12) || !box_size.is_multiple_of(4) || header.get(4..8) != Some(&b"ftyp"[..]) {
+        return false;
+    }
+    if header.get(8..11) == Some(&b"mp4"[..]) {
+        return true;
+    }
+    // The compatible brands, after the major brand and its version
+    (16..box_size)
+        .step_by(4)
+        .any(|offset| header.get(offset..offset + 3) == Some(&b"mp4"[..]))
+}
+
+/// The spec's "matches the signature for WebM": an EBML header with a `webm` DocType
+fn is_webm(header: &[u8]) -> bool {
+    if !header.starts_with(b"\x1a\x45\xdf\xa3") {
+        return false;
+    }
+    let mut i = 4;
+    while i < header.len().min(38) {
+        if header.get(i..i + 2) == Some(&b"\x42\x82"[..]) {
+            i += 2;
+            let Some(&first) = header.get(i) else {
+                break;
+            };
+            // Skip the element's size, a variable length integer.
+            i += (first.leading_zeros() as usize + 1).min(8);
+            if header.get(i..i + 4) == Some(&b"webm"[..]) {
+                return true;
+            }
         }
+        i += 1;
     }
+    false
+}
 
-    let mime_type = detect(bytes);
-    let mime = Mime::from_str(mime_type.mime()).unwrap_or(mime::APPLICATION_OCTET_STREAM);
-    ResponseClass::from_mime(&mime)
+/// The spec's "font type pattern matching algorithm"
+fn match_font(header: &[u8]) -> Option<&'static str> {
+    if header.get(34..36) == Some(&b"LP"[..]) {
+        Some("application/vnd.ms-fontobject")
+    } else if header.starts_with(b"\x00\x01\x00\x00") {
+        Some("font/ttf")
+    } else if header.starts_with(b"OTTO") {
+        Some("font/otf")
+    } else if header.starts_with(b"ttcf") {
+        Some("font/collection")
+    } else if header.starts_with(b"wOFF") {
+        Some("font/woff")
+    } else if header.starts_with(b"wOF2") {
+        Some("font/woff2")
+    } else {
+        None
+    }
+}
+
+/// The spec's "archive type pattern matching algorithm"
+fn match_archive(header: &[u8]) -> Option<&'static str> {
+    if header.starts_with(b"\x1f\x8b\x08") {
+        Some("application/x-gzip")
+    } else if header.starts_with(b"PK\x03\x04") {
+        Some("application/zip")
+    } else if header.starts_with(b"Rar \x1a\x07\x00") {
+        Some("application/x-rar-compressed")
+    } else {
+        None
+    }
+}
+
+/// Starts with a UTF-16 or UTF-8 byte order mark
+fn has_text_bom(header: &[u8]) -> bool {
+    header.starts_with(b"\xfe\xff") || header.starts_with(b"\xff\xfe") || header.starts_with(b"\xef\xbb\xbf")
+}
+
+/// Control characters that don't occur in text
+fn is_binary_data_byte(b: u8) -> bool {
+    matches!(b, 0x00..=0x08 | 0x0b | 0x0e..=0x1a | 0x1c..=0x1f)
+}
+
+/// `unknown/unknown`, `application/unknown` and `*/*` say as much as no type at all.
+fn is_unknown(m: &Mime) -> bool {
+    matches!(m.essence_str(), "unknown/unknown" | "application/unknown" | "*/*")
+}
+
+fn is_xml(m: &Mime) -> bool {
+    m.suffix() == Some(mime::XML) || matches!(m.essence_str(), "text/xml" | "application/xml")
+}
+
+fn mime(essence: &'static str) -> Mime {
+    essence.parse().unwrap_or(mime::APPLICATION_OCTET_STREAM)
 }
 
 #[cfg(test)]
@@ -142,12 +433,90 @@ mod tests {
         let unknown_peek = PeekBuf::from_slice(b"\x00\x01\x02\x03\x04");
 
         assert_eq!(sniff_class(html_peek), ResponseClass::Html);
-        assert_eq!(sniff_class(css_peek), ResponseClass::Css);
-        assert_eq!(sniff_class(js_peek), ResponseClass::Js);
+        // Stylesheets and scripts are never sniffed, they look like any other text.
+        assert_eq!(sniff_class(css_peek), ResponseClass::Text);
+        assert_eq!(sniff_class(js_peek), ResponseClass::Text);
         assert_eq!(sniff_class(png_peek), ResponseClass::Image);
         assert_eq!(sniff_class(mp3_peek), ResponseClass::Audio);
-        assert_eq!(sniff_class(woff_peek), ResponseClass::Font);
+        // Fonts are only recognised where a font is expected.
+        assert_eq!(sniff_class(woff_peek), ResponseClass::Text);
         assert_eq!(sniff_class(pdf_peek), ResponseClass::Pdf);
         assert_eq!(sniff_class(unknown_peek), ResponseClass::Binary); // likely falls back to binary
     }
+
+    #[test]
+    fn sniffs_per_context() {
+        let sniff = |ct: Option<&str>, no_sniff: bool, body: &[u8], context: SniffContext| {
+            sniff_mime(ct, no_sniff, body, context).map(|m| m.essence_str().to_string())
+        };
+        let html = b"\n  <HTML><body>hi</body></html>";
+        let png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR";
+
+        // Navigations: no or a meaningless type gets sniffed, scriptable types only without
+        // nosniff.
+        assert_eq!(sniff(None, false, html, SniffContext::Browsing).unwrap(), "text/html");
+        assert_eq!(
+            sniff(Some("*/*"), false, html, SniffContext::Browsing).unwrap(),
+            "text/html"
+        );
+        assert_eq!(sniff(None, true, html, SniffContext::Browsing).unwrap(), "text/plain");
+        assert_eq!(sniff(None, false, png, SniffContext::Browsing).unwrap(), "image/png");
+        assert_eq!(
+            sniff(None, false, b"<htmlish>", SniffContext::Browsing).unwrap(),
+            "text/plain"
+        );
+        assert_eq!(
+            sniff(Some("application/json"), false, html, SniffContext::Browsing).unwrap(),
+            "application/json"
+        );
+
+        // The Apache default type is only ever turned into binary, never into HTML.
+        let apache = Some("text/plain; charset=UTF-8");
+        assert_eq!(
+            sniff(apache, false, html, SniffContext::Browsing).unwrap(),
+            "text/plain"
+        );
+        assert_eq!(sniff(apache, false, png, SniffContext::Browsing).unwrap(), "image/png");
+        assert_eq!(
+            sniff(apache, false, b"\x00\x00\x00\x01junk", SniffContext::Browsing).unwrap(),
+            "application/octet-stream"
+        );
+
+        // Declared images are corrected to the format they turn out to be.
+        assert_eq!(
+            sniff(Some("image/gif"), false, png, SniffContext::Browsing).unwrap(),
+            "image/png"
+        );
+        assert_eq!(
+            sniff(Some("image/gif"), true, png, SniffContext::Browsing).unwrap(),
+            "image/gif"
+        );
+        assert_eq!(
+            sniff(Some("text/plain"), false, png, SniffContext::Image).unwrap(),
+            "image/png"
+        );
+        assert_eq!(
+            sniff(Some("image/svg+xml"), false, png, SniffContext::Image).unwrap(),
+            "image/svg+xml"
+        );
+        assert_eq!(
+            sniff(None, false, b"wOF2\x00\x01", SniffContext::Font).unwrap(),
+            "font/woff2"
+        );
+
+        let mp4 = b"\x00\x00\x00\x18ftypisom\x00\x00\x02\x00isommp41";
+        assert_eq!(sniff(None, false, mp4, SniffContext::AudioVideo).unwrap(), "video/mp4");
+        let webm = b"\x1a\x45\xdf\xa3\x9f\x42\x86\x81\x01\x42\xf7\x81\x01\x42\x82\x84webm";
+        assert_eq!(
+            sniff(None, false, webm, SniffContext::AudioVideo).unwrap(),
+            "video/webm"
+        );
+
+        // Stylesheets and scripts keep the type they're sent with.
+        assert_eq!(sniff(None, false, b"body { color: red }", SniffContext::Style), None);
+        assert_eq!(
+            sniff(Some("text/plain"), false, b"alert(1)", SniffContext::Script).unwrap(),
+            "text/plain"
+        );
+    }
 }
@@ -32,6 +32,9 @@ pub struct DecisionOutcome {
     pub sniffed_class: Option<ResponseClass>,
     /// The declared MIME type from the `Content-Type` header, if any and parseable.
     pub declared_mime: Option<Mime>,
+    /// The MIME type the response is handled as: the declared one, corrected by sniffing where
+    /// that's allowed.
+    pub computed_mime: Option<Mime>,
     /// Whether the response had a `Content-Disposition: attachment` header.
     pub disposition_attachment: bool,
     /// The final decision on how to handle the response.
@@ -40,15 +43,16 @@ pub struct DecisionOutcome {
 
 // Final decision for the response.
 //
-// Deliberately minimal: variants for open-externally, block-on-type-mismatch,
-// nosniff enforcement and silent cancellation were removed until the features
-// that produce them exist.
+// Deliberately minimal: variants for open-externally, block-on-type-mismatch
+// and silent cancellation were removed until the features that produce them exist.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum HandlingDecision {
     /// Resource needs to be rendered based on its target (html parser, css parser, js engine, image decoder, etc).
     Render(RenderTarget),
     /// Resource should be downloaded to the given path.
     Download { path: PathBuf },
+    /// Resource must not be used at all.
+    Block(BlockReason),
 }
 
 /// Reason on why the response was blocked.
@@ -57,12 +61,16 @@ pub enum BlockReason {
     /// A user agent or site policy explicitly forbids this load.
     /// Example: mixed-content block, CSP violation, or UA rule against auto-downloads.
     Policy,
+    /// A script or stylesheet sent with `X-Content-Type-Options: nosniff` and a type that
+    /// doesn't match.
+    NoSniff,
 }
 
 impl std::fmt::Display for BlockReason {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             BlockReason::Policy => write!(f, "policy block"),
+            BlockReason::NoSniff => write!(f, "nosniff type mismatch"),
         }
     }
 }
@@ -28,6 +28,8 @@ pub fn fetcher_config_from(cfg: &gosub_config::Config) -> FetcherConfig {
 use crate::engine::types::EventChannel;
 use crate::net::emitter::engine_event_emitter::EngineEventEmitter;
 use crate::net::emitter::null_emitter::NullEmitter;
+use crate::net::hsts::{HstsObserver, HstsStore};
+use crate::net::redirect::{RedirectObserver, RedirectPolicy};
 use crate::net::req_ref_tracker::{RequestRefTracker, RequestReferenceMap, REF_REGISTRY};
 use crate::net::types::{Initiator as EngineInitiator, ResourceKind as EngineResourceKind};
 use gosub_sonar::net::observer::NetObserver;
@@ -46,6 +48,27 @@ pub struct EngineNetContext {
     pub event_tx: EventChannel,
     pub request_reference_map: Arc<RwLock<RequestReferenceMap>>,
     pub request_ref_tracker: Arc<RequestRefTracker>,
+    /// Checked against every redirect the fetcher follows
+    pub redirect_policy: RedirectPolicy,
+    /// The zone's HSTS hosts, learning from the `Strict-Transport-Security` of every response;
+    /// `None` when HSTS is off
+    pub hsts: Option<Arc<HstsStore>>,
+}
+
+impl EngineNetContext {
+    /// `emitter` behind the observers every fetch gets.
+    fn observe(
+        &self,
+        req_id: RequestId,
+        emitter: Arc<dyn NetObserver + Send + Sync>,
+    ) -> Arc<dyn NetObserver + Send + Sync> {
+        let observer: Arc<dyn NetObserver + Send + Sync> =
+            Arc::new(RedirectObserver::new(req_id, self.redirect_policy, emitter));
+        match &self.hsts {
+            Some(hsts) => Arc::new(HstsObserver::new(req_id, Arc::clone(hsts), observer)),
+            None => observer,
+        }
+    }
 }
 
 impl FetcherContext for EngineNetContext {
@@ -58,7 +81,7 @@ impl FetcherContext for EngineNetContext {
     ) -> Arc<dyn NetObserver + Send + Sync> {
         let Some(reference) = REF_REGISTRY.from_net(reference) else {
             log::trace!("Cannot resolve net reference {:?} to an engine reference", reference);
-            return Arc::new(NullEmitter) as Arc<dyn NetObserver + Send + Sync>;
+            return self.observe(req_id, Arc::new(NullEmitter));
         };
 
         // Recover the rich (kind, initiator) pair registered when the request was built;
@@ -69,17 +92,20 @@ impl FetcherContext for EngineNetContext {
 
         let guard = self.request_reference_map.read();
         match guard.get(&reference) {
-            Some(&tab_id) => Arc::new(EngineEventEmitter::new(
-                tab_id,
+            Some(&tab_id) => self.observe(
                 req_id,
-                reference,
-                self.event_tx.clone(),
-                kind,
-                initiator,
-            )),
+                Arc::new(EngineEventEmitter::new(
+                    tab_id,
+                    req_id,
+                    reference,
+                    self.event_tx.clone(),
+                    kind,
+                    initiator,
+                )),
+            ),
             None => {
                 log::trace!("Cannot find the request reference for reference {:?}", reference);
-                Arc::new(NullEmitter) as Arc<dyn NetObserver + Send + Sync>
+                self.observe(req_id, Arc::new(NullEmitter))
             }
         }
     }
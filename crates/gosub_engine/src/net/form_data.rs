@@ -0,0 +1,162 @@
+//! Request bodies for form data.
+//!
+//! [`UrlEncodedBody`] builds `application/x-www-form-urlencoded` bodies (and the query strings of
+//! GET submissions), [`MultipartBody`] builds `multipart/form-data` ones with text and file
+//! parts. Both finish into an [`EncodedBody`]: the bytes to send along with the `Content-Type`
+//! they have to be sent with, which for multipart bodies carries the boundary.
+
+use crate::net::types::RequestBody;
+use cow_utils::CowUtils;
+
+/// A request body with its `Content-Type`
+#[derive(Clone, Debug, PartialEq)]
+pub struct EncodedBody {
+    pub content_type: String,
+    pub bytes: Vec<u8>,
+}
+
+impl From<EncodedBody> for RequestBody {
+    fn from(body: EncodedBody) -> Self {
+        RequestBody {
+            bytes: body.bytes.into(),
+            content_type: Some(body.content_type),
+        }
+    }
+}
+
+/// Builds an `application/x-www-form-urlencoded` body from name/value pairs.
+#[derive(Clone, Debug, Default)]
+pub struct UrlEncodedBody {
+    encoded: String,
+}
+
+impl UrlEncodedBody {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a pair; names and values may contain anything.
+    pub fn append(&mut self, name: &str, value: &str) -> &mut Self {
+        url::form_urlencoded::Serializer::for_suffix(&mut self.encoded, 0).append_pair(name, value);
+        self
+    }
+
+    /// The pairs as a query string, without the `?`.
+    pub fn as_query(&self) -> &str {
+        &self.encoded
+    }
+
+    pub fn finish(self) -> EncodedBody {
+        EncodedBody {
+            content_type: "application/x-www-form-urlencoded".to_string(),
+            bytes: self.encoded.into_bytes(),
+        }
+    }
+}
+
+/// Builds a `multipart/form-data` body, one part per field.
+#[derive(Clone, Debug)]
+pub struct MultipartBody {
+    boundary: String,
+    bytes: Vec<u8>,
+}
+
+impl Default for MultipartBody {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MultipartBody {
+    /// A body with a random boundary.
+    pub fn new() -> Self {
+        Self::with_boundary(format!("----GosubFormBoundary{}", uuid::Uuid::new_v4().simple()))
+    }
+
+    /// A body with `boundary` between its parts; it must not occur in any of them.
+    pub fn with_boundary(boundary: impl Into<String>) -> Self {
+        Self {
+            boundary: boundary.into(),
+            bytes: Vec::new(),
+        }
+    }
+
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Adds a text field.
+    pub fn text(&mut self, name: &str, value: &str) -> &mut Self {
+        self.start_part();
+        self.bytes
+            .extend_from_slice(format!("Content-Disposition: form-data; name=\"{}\"\r\n\r\n", escape(name)).as_bytes());
+        self.bytes.extend_from_slice(value.as_bytes());
+        self.bytes.extend_from_slice(b"\r\n");
+        self
+    }
+
+    /// Adds a file field: `data` uploaded as `filename`, of type `content_type`.
+    pub fn file(&mut self, name: &str, filename: &str, content_type: &str, data: &[u8]) -> &mut Self {
+        self.start_part();
+        self.bytes.extend_from_slice(
+            format!(
+                "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: {content_type}\r\n\r\n",
+                escape(name),
+                escape(filename),
+            )
+            .as_bytes(),
+        );
+        self.bytes.extend_from_slice(data);
+        self.bytes.extend_from_slice(b"\r\n");
+        self
+    }
+
+    fn start_part(&mut self) {
+        self.bytes
+            .extend_from_slice(format!("--{}\r\n", self.boundary).as_bytes());
+    }
+
+    pub fn finish(mut self) -> EncodedBody {
+        self.bytes
+            .extend_from_slice(format!("--{}--\r\n", self.boundary).as_bytes());
+        EncodedBody {
+            content_type: format!("multipart/form-data; boundary={}", self.boundary),
+            bytes: self.bytes,
+        }
+    }
+}
+
+/// Quotes and line breaks can't appear raw inside the quoted header parameters.
+fn escape(s: &str) -> String {
+    s.cow_replace("\"", "%22")
+        .cow_replace("\r", "%0D")
+        .cow_replace("\n", "%0A")
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_bodies() {
+        let mut form = UrlEncodedBody::new();
+        form.append("q", "a b&c").append("lang", "nl");
+        assert_eq!(form.as_query(), "q=a+b%26c&lang=nl");
+        let body = form.finish();
+        assert_eq!(body.content_type, "application/x-www-form-urlencoded");
+        assert_eq!(body.bytes, b"q=a+b%26c&lang=nl");
+
+        let mut form = MultipartBody::with_boundary("XyZ");
+        form.text("say\"", "hi\r\nthere")
+            .file("upload", "a.txt", "text/plain", b"data");
+        let body = form.finish();
+        assert_eq!(body.content_type, "multipart/form-data; boundary=XyZ");
+        assert_eq!(
+            String::from_utf8(body.bytes).unwrap(),
+            "--XyZ\r\nContent-Disposition: form-data; name=\"say%22\"\r\n\r\nhi\r\nthere\r\n\
+             --XyZ\r\nContent-Disposition: form-data; name=\"upload\"; filename=\"a.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\ndata\r\n--XyZ--\r\n"
+        );
+    }
+}
@@ -0,0 +1,375 @@
+//! HTTP Strict Transport Security (RFC 6797).
+//!
+//! A host that sends `Strict-Transport-Security` over HTTPS is only fetched over HTTPS for as
+//! long as its `max-age` says; with `includeSubDomains` that goes for its subdomains too.
+//! [`HstsStore`] remembers those hosts, plus the ones listed in `security.hsts.preload`, and
+//! the I/O thread upgrades every `http:` request to such a host before it goes out. Each zone
+//! has a store of its own in memory, so one zone's browsing can't be read back from another's.
+//!
+//! The zone fetchers follow redirects themselves, so a redirect to such a host can't be
+//! rewritten on its way out. Instead [`HstsObserver`] stops the fetch at that hop and records the
+//! upgraded URL, which the I/O thread collects with [`take_upgrade`] and fetches next.
+
+use crate::engine::types::RequestId;
+use crate::net::emitter::NetObserver;
+use crate::net::events::NetEvent;
+use cow_utils::CowUtils;
+use dashmap::DashMap;
+use http::HeaderMap;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, SystemTime};
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+/// What is known about one host
+#[derive(Debug, Clone, Copy)]
+struct HstsEntry {
+    /// `None` for preloaded hosts, which never expire
+    expires: Option<SystemTime>,
+    include_subdomains: bool,
+}
+
+/// The hosts that are only fetched over HTTPS
+#[derive(Debug, Default)]
+pub struct HstsStore {
+    hosts: RwLock<HashMap<String, HstsEntry>>,
+}
+
+impl HstsStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The store the settings ask for, with the preloaded hosts, or `None` when HSTS is off.
+    pub fn from_config(cfg: &gosub_config::Config) -> Option<Self> {
+        if !cfg.get_bool("security.hsts.enabled") {
+            return None;
+        }
+        let store = Self::new();
+        for host in cfg.get_map("security.hsts.preload") {
+            store.preload(&host);
+        }
+        Some(store)
+    }
+
+    /// Fetches `host` and its subdomains over HTTPS only, for good.
+    pub fn preload(&self, host: &str) {
+        let host = host.trim().trim_end_matches('.').cow_to_ascii_lowercase();
+        if host.is_empty() {
+            return;
+        }
+        self.hosts.write().insert(
+            host.into_owned(),
+            HstsEntry {
+                expires: None,
+                include_subdomains: true,
+            },
+        );
+    }
+
+    /// Takes note of the `Strict-Transport-Security` header of a response from `url`. It only
+    /// counts over HTTPS, and not for IP addresses; `max-age=0` forgets the host.
+    pub fn observe(&self, url: &Url, headers: &HeaderMap) {
+        if url.scheme() != "https" {
+            return;
+        }
+        let Some(url::Host::Domain(host)) = url.host() else {
+            return;
+        };
+        // Only the first header counts.
+        let Some(value) = headers
+            .get(http::header::STRICT_TRANSPORT_SECURITY)
+            .and_then(|v| v.to_str().ok())
+        else {
+            return;
+        };
+        let Some((max_age, include_subdomains)) = parse_sts(value) else {
+            log::debug!("Ignoring invalid Strict-Transport-Security from {host}: {value:?}");
+            return;
+        };
+        let host = host.cow_to_ascii_lowercase().into_owned();
+        let mut hosts = self.hosts.write();
+        if hosts.get(&host).is_some_and(|entry| entry.expires.is_none()) {
+            return;
+        }
+        if max_age == 0 {
+            hosts.remove(&host);
+            return;
+        }
+        let expires = SystemTime::now().checked_add(Duration::from_secs(max_age));
+        hosts.insert(
+            host,
+            HstsEntry {
+                expires: Some(expires.unwrap_or_else(far_future)),
+                include_subdomains,
+            },
+        );
+    }
+
+    /// Whether `host` may only be fetched over HTTPS.
+    pub fn is_known(&self, host: &str) -> bool {
+        if host.parse::<IpAddr>().is_ok() {
+            return false;
+        }
+        let host = host.trim_end_matches('.').cow_to_ascii_lowercase();
+        let now = SystemTime::now();
+        let hosts = self.hosts.read();
+        let live = |entry: &HstsEntry| entry.expires.is_none_or(|expires| expires > now);
+        if hosts.get(&*host).is_some_and(live) {
+            return true;
+        }
+        // A superdomain's entry covers `host` when it includes subdomains.
+        let mut rest = &*host;
+        while let Some((_, parent)) = rest.split_once('.') {
+            if hosts
+                .get(parent)
+                .is_some_and(|entry| entry.include_subdomains && live(entry))
+            {
+                return true;
+            }
+            rest = parent;
+        }
+        false
+    }
+
+    /// `url` over HTTPS, when it is an insecure URL of a known host.
+    pub fn upgrade(&self, url: &Url) -> Option<Url> {
+        let host = url.host_str()?;
+        if !matches!(url.scheme(), "http" | "ws") || !self.is_known(host) {
+            return None;
+        }
+        secure_url(url)
+    }
+}
+
+/// `url` with `https:` for `http:` and `wss:` for `ws:`. The default port of the insecure scheme
+/// becomes the default port of the secure one; other ports are kept.
+pub fn secure_url(url: &Url) -> Option<Url> {
+    let scheme = match url.scheme() {
+        "http" => "https",
+        "ws" => "wss",
+        _ => return None,
+    };
+    let mut secure = url.clone();
+    let default_port = secure.port() == Some(80);
+    secure.set_scheme(scheme).ok()?;
+    if default_port {
+        secure.set_port(None).ok()?;
+    }
+    Some(secure)
+}
+
+/// The `max-age` and `includeSubDomains` of a `Strict-Transport-Security` value, or `None` when
+/// it has no valid `max-age` or a directive appears twice.
+fn parse_sts(value: &str) -> Option<(u64, bool)> {
+    let mut max_age = None;
+    let mut include_subdomains = false;
+    for directive in value.split(';').map(str::trim).filter(|d| !d.is_empty()) {
+        let (name, arg) = match directive.split_once('=') {
+            Some((name, arg)) => (name.trim(), Some(arg.trim().trim_matches('"'))),
+            None => (directive, None),
+        };
+        if name.eq_ignore_ascii_case("max-age") {
+            if max_age.is_some() {
+                return None;
+            }
+            max_age = Some(arg?.parse::<u64>().ok()?);
+        } else if name.eq_ignore_ascii_case("includesubdomains") {
+            if include_subdomains {
+                return None;
+            }
+            include_subdomains = true;
+        }
+    }
+    Some((max_age?, include_subdomains))
+}
+
+fn far_future() -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_secs(u32::MAX as u64)
+}
+
+/// A redirect hop to a known host, see [`take_upgrade`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpgradedHop {
+    /// Where the redirect pointed, over HTTPS
+    pub url: Url,
+    /// The 3xx status of the response that redirected
+    pub status: u16,
+}
+
+/// What is known about a fetch that may be redirected to a known host
+#[derive(Default)]
+struct Watched {
+    /// Stops the current attempt at the fetch
+    cancel: Option<CancellationToken>,
+    hop: Option<UpgradedHop>,
+}
+
+/// Fetches in flight whose redirects are checked against the store
+static WATCHED: LazyLock<DashMap<RequestId, Watched>> = LazyLock::new(DashMap::new);
+
+/// Starts checking the redirects of fetch `req_id`. A redirect to a known host cancels `cancel`.
+pub(crate) fn watch(req_id: RequestId, cancel: CancellationToken) {
+    WATCHED.entry(req_id).or_default().cancel = Some(cancel);
+}
+
+/// The hop fetch `req_id` was stopped at because it led to a known host, if it was. Collecting it
+/// stops checking the fetch until it is watched again.
+pub(crate) fn take_upgrade(req_id: RequestId) -> Option<UpgradedHop> {
+    WATCHED.remove(&req_id).and_then(|(_, watched)| watched.hop)
+}
+
+/// Feeds the response headers of one fetch to the store and stops the fetch at redirects to
+/// known hosts, passing all events on to `inner`. Those redirects are passed on with the
+/// upgraded URL, so the redirect chain shows where the fetch really went.
+pub(crate) struct HstsObserver {
+    req_id: RequestId,
+    store: Arc<HstsStore>,
+    inner: Arc<dyn NetObserver + Send + Sync>,
+}
+
+impl HstsObserver {
+    pub fn new(req_id: RequestId, store: Arc<HstsStore>, inner: Arc<dyn NetObserver + Send + Sync>) -> Self {
+        Self { req_id, store, inner }
+    }
+}
+
+impl NetObserver for HstsObserver {
+    fn on_event(&self, ev: NetEvent) {
+        if let NetEvent::ResponseHeaders { url, headers, .. } = &ev {
+            self.store.observe(url, headers);
+        }
+        let ev = match ev {
+            NetEvent::Redirected { from, to, status } => match self.store.upgrade(&to) {
+                Some(url) => {
+                    log::debug!("HSTS: redirect to {to} continues at {url}");
+                    if let Some(mut watched) = WATCHED.get_mut(&self.req_id) {
+                        watched.hop = Some(UpgradedHop {
+                            url: url.clone(),
+                            status,
+                        });
+                        if let Some(cancel) = &watched.cancel {
+                            cancel.cancel();
+                        }
+                    }
+                    NetEvent::Redirected { from, to: url, status }
+                }
+                None => NetEvent::Redirected { from, to, status },
+            },
+            ev => ev,
+        };
+        self.inner.on_event(ev);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    fn sts(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(http::header::STRICT_TRANSPORT_SECURITY, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn parses_directives() {
+        assert_eq!(parse_sts("max-age=31536000"), Some((31536000, false)));
+        assert_eq!(
+            parse_sts("max-age=\"600\"; includeSubDomains; preload"),
+            Some((600, true))
+        );
+        assert_eq!(parse_sts("IncludeSubdomains;MAX-AGE=0"), Some((0, true)));
+        assert_eq!(parse_sts("includeSubDomains"), None);
+        assert_eq!(parse_sts("max-age=1; max-age=2"), None);
+        assert_eq!(parse_sts("max-age=soon"), None);
+    }
+
+    #[test]
+    fn upgrades_known_hosts() {
+        let store = HstsStore::new();
+        store.observe(&url("https://example.org/"), &sts("max-age=3600; includeSubDomains"));
+        store.observe(&url("https://shop.example/"), &sts("max-age=3600"));
+        // Not over HTTPS, so it doesn't count.
+        store.observe(&url("http://plain.example/"), &sts("max-age=3600"));
+
+        assert_eq!(
+            store.upgrade(&url("http://www.example.org/a?b")).unwrap().as_str(),
+            "https://www.example.org/a?b"
+        );
+        assert_eq!(
+            store.upgrade(&url("http://shop.example:8080/")).unwrap().as_str(),
+            "https://shop.example:8080/"
+        );
+        assert_eq!(
+            store.upgrade(&url("ws://example.org/socket")).unwrap().as_str(),
+            "wss://example.org/socket"
+        );
+        assert!(store.upgrade(&url("http://cdn.shop.example/")).is_none());
+        assert!(store.upgrade(&url("http://plain.example/")).is_none());
+        assert!(store.upgrade(&url("https://example.org/")).is_none());
+
+        store.observe(&url("https://shop.example/"), &sts("max-age=0"));
+        assert!(!store.is_known("shop.example"));
+
+        store.preload("Bank.Example");
+        assert!(store.is_known("login.bank.example"));
+        // Preloaded hosts can't be taken off the list by the site.
+        store.observe(&url("https://bank.example/"), &sts("max-age=0"));
+        assert!(store.is_known("bank.example"));
+    }
+
+    #[derive(Default)]
+    struct Redirects(parking_lot::Mutex<Vec<Url>>);
+
+    impl NetObserver for Redirects {
+        fn on_event(&self, ev: NetEvent) {
+            if let NetEvent::Redirected { to, .. } = ev {
+                self.0.lock().push(to);
+            }
+        }
+    }
+
+    #[test]
+    fn redirects_to_known_hosts_are_stopped_and_upgraded() {
+        let store = Arc::new(HstsStore::new());
+        store.preload("secure.example");
+        let redirects = Arc::new(Redirects::default());
+        let req_id = RequestId::new();
+        let observer = HstsObserver::new(req_id, Arc::clone(&store), redirects.clone());
+
+        let cancel = CancellationToken::new();
+        watch(req_id, cancel.clone());
+        observer.on_event(NetEvent::Redirected {
+            from: url("https://a.example/"),
+            to: url("http://b.example/"),
+            status: 302,
+        });
+        assert!(!cancel.is_cancelled());
+        observer.on_event(NetEvent::Redirected {
+            from: url("http://b.example/"),
+            to: url("http://www.secure.example/login"),
+            status: 303,
+        });
+        assert!(cancel.is_cancelled());
+        assert_eq!(
+            take_upgrade(req_id),
+            Some(UpgradedHop {
+                url: url("https://www.secure.example/login"),
+                status: 303,
+            })
+        );
+        assert_eq!(take_upgrade(req_id), None);
+        assert_eq!(
+            *redirects.0.lock(),
+            [url("http://b.example/"), url("https://www.secure.example/login")]
+        );
+    }
+}
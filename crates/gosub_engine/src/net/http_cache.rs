@@ -0,0 +1,749 @@
+//! HTTP response cache in front of the zone fetchers (RFC 9111, as a private browser cache).
+//!
+//! When a cache is configured, the I/O thread hands every fetch to [`HttpCache::fetch`]. `GET`
+//! responses that may be stored are kept in a [`CacheBackend`]; a later request for the same URL
+//! is answered from it while the response is fresh, and revalidated with `If-None-Match` /
+//! `If-Modified-Since` once it is stale. A successful unsafe request (`POST`, `PUT`, ...)
+//! invalidates what is stored for its URL.
+//!
+//! The backend is picked with the `net.cache.backend` setting: `memory` ([`MemoryCache`], bounded
+//! by `net.cache.memory_bytes`), `disk` ([`DiskCache`] in `net.cache.directory`, bounded by
+//! `net.cache.disk_bytes`) or `none`.
+//!
+//! Entries are kept per [`CachePartition`]: a zone, or a storage partition within it, never sees
+//! what another one cached, and `Set-Cookie` is never stored. Responses of private partitions are
+//! cached in memory only, whatever the backend.
+//!
+//! Answers from the cache never reach the fetcher, so no per-request network events are emitted
+//! for them.
+
+mod disk;
+mod memory;
+
+pub use disk::DiskCache;
+pub use memory::MemoryCache;
+
+use crate::net::fetcher::Fetcher;
+use crate::net::req_ref_tracker::REF_REGISTRY;
+use crate::net::types::{FetchHandle, FetchRequest, FetchResult, FetchResultMeta, NetError};
+use crate::net::utils::stream_to_bytes;
+use crate::storage::PartitionKey;
+use crate::zone::ZoneId;
+use bytes::Bytes;
+use http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use http::Method;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::oneshot;
+use url::Url;
+
+/// Statuses that may be cached without explicit freshness information (RFC 9110 15.1)
+const HEURISTICALLY_CACHEABLE: [u16; 11] = [200, 203, 204, 300, 301, 308, 404, 405, 410, 414, 501];
+
+/// Fraction of the time since `Last-Modified` a response is considered fresh for when it
+/// doesn't say how long
+const HEURISTIC_FRACTION: u32 = 10;
+
+/// Upper bound on a heuristic freshness lifetime
+const MAX_HEURISTIC_LIFETIME: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Largest share of the backend's capacity a single response may take
+const MAX_ENTRY_SHARE: usize = 8;
+
+/// A stored response, with what is needed to tell whether it can answer a request
+#[derive(Clone, Debug)]
+pub struct CachedResponse {
+    /// The URL the response came from, after redirects
+    pub url: Url,
+    pub status: u16,
+    pub status_text: String,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+    /// The request headers the response's `Vary` names, as they were sent
+    pub vary: Vec<(HeaderName, Option<HeaderValue>)>,
+    /// When the request that got this response was sent
+    pub request_time: SystemTime,
+    /// When the response arrived
+    pub response_time: SystemTime,
+}
+
+impl CachedResponse {
+    /// Bytes this entry accounts for in a backend
+    pub fn size(&self) -> usize {
+        let headers: usize = self
+            .headers
+            .iter()
+            .map(|(name, value)| name.as_str().len() + value.len())
+            .sum();
+        self.body.len() + headers + self.url.as_str().len()
+    }
+
+    /// Whether the response can answer a request with `headers`, as far as `Vary` goes
+    fn matches(&self, headers: &HeaderMap) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| headers.get(name) == value.as_ref())
+    }
+
+    /// How long after it was generated the response stays fresh
+    fn freshness_lifetime(&self) -> Duration {
+        let directives = CacheControl::from_headers(&self.headers);
+        if let Some(max_age) = directives.max_age {
+            return max_age;
+        }
+        let date = header_date(&self.headers, header::DATE).unwrap_or(self.response_time);
+        if let Some(expires) = self.headers.get(header::EXPIRES) {
+            // An invalid `Expires` (like "0") means already expired.
+            return header_value_date(expires)
+                .and_then(|expires| expires.duration_since(date).ok())
+                .unwrap_or_default();
+        }
+        if !HEURISTICALLY_CACHEABLE.contains(&self.status) {
+            return Duration::ZERO;
+        }
+        header_date(&self.headers, header::LAST_MODIFIED)
+            .and_then(|modified| date.duration_since(modified).ok())
+            .map_or(Duration::ZERO, |since| {
+                (since / HEURISTIC_FRACTION).min(MAX_HEURISTIC_LIFETIME)
+            })
+    }
+
+    /// The response's age at `now` (RFC 9111 4.2.3)
+    fn current_age(&self, now: SystemTime) -> Duration {
+        let age = self
+            .headers
+            .get(header::AGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map_or(Duration::ZERO, Duration::from_secs);
+        let apparent_age = header_date(&self.headers, header::DATE)
+            .and_then(|date| self.response_time.duration_since(date).ok())
+            .unwrap_or_default();
+        let response_delay = self.response_time.duration_since(self.request_time).unwrap_or_default();
+        let resident = now.duration_since(self.response_time).unwrap_or_default();
+
+        apparent_age.max(age + response_delay) + resident
+    }
+
+    /// Whether the response may be used without revalidation at `now` for a request with
+    /// `request` directives
+    fn is_fresh(&self, now: SystemTime, request: &CacheControl) -> bool {
+        if request.no_cache || CacheControl::from_headers(&self.headers).no_cache {
+            return false;
+        }
+        let lifetime = match request.max_age {
+            Some(max_age) => self.freshness_lifetime().min(max_age),
+            None => self.freshness_lifetime(),
+        };
+        self.current_age(now) < lifetime
+    }
+
+    /// Adds the conditional headers revalidating this response to `headers`. Returns false when
+    /// the response has no validators, or the request already is conditional.
+    fn add_validators(&self, headers: &mut HeaderMap) -> bool {
+        if headers.contains_key(header::IF_NONE_MATCH) || headers.contains_key(header::IF_MODIFIED_SINCE) {
+            return false;
+        }
+        if let Some(etag) = self.headers.get(header::ETAG) {
+            headers.insert(header::IF_NONE_MATCH, etag.clone());
+            return true;
+        }
+        if let Some(modified) = self.headers.get(header::LAST_MODIFIED) {
+            headers.insert(header::IF_MODIFIED_SINCE, modified.clone());
+            return true;
+        }
+        false
+    }
+
+    /// The response after a `304 Not Modified` with `headers` confirmed it
+    fn revalidated(mut self, headers: &HeaderMap, request_time: SystemTime, response_time: SystemTime) -> Self {
+        for name in headers.keys() {
+            // The 304 describes the stored body, it doesn't have one of its own.
+            if name == header::CONTENT_LENGTH || name == header::TRANSFER_ENCODING {
+                continue;
+            }
+            // Cookies are set by the response that carries them, never replayed from the cache.
+            if name == header::SET_COOKIE || name == "set-cookie2" {
+                continue;
+            }
+            self.headers.remove(name);
+            for value in headers.get_all(name) {
+                self.headers.append(name.clone(), value.clone());
+            }
+        }
+        self.request_time = request_time;
+        self.response_time = response_time;
+        self
+    }
+
+    fn meta(&self) -> FetchResultMeta {
+        let header_str = |name: HeaderName| self.headers.get(name).and_then(|v| v.to_str().ok());
+        FetchResultMeta {
+            final_url: self.url.clone(),
+            status: self.status,
+            status_text: self.status_text.clone(),
+            headers: self.headers.clone(),
+            content_length: header_str(header::CONTENT_LENGTH).and_then(|v| v.parse().ok()),
+            content_type: header_str(header::CONTENT_TYPE).and_then(|v| v.parse().ok()),
+            has_body: !self.body.is_empty(),
+        }
+    }
+
+    fn into_result(self) -> FetchResult {
+        FetchResult::Buffered {
+            meta: self.meta(),
+            body: self.body,
+        }
+    }
+}
+
+/// Where a [`HttpCache`] keeps its responses
+pub trait CacheBackend: Send + Sync {
+    fn get(&self, key: &str) -> Option<CachedResponse>;
+    /// Stores `response` under `key`, replacing what was there. Backends drop entries (least
+    /// recently used first) to stay within their capacity.
+    fn put(&self, key: &str, response: CachedResponse);
+    fn remove(&self, key: &str);
+    fn clear(&self);
+    /// Bytes the backend may hold
+    fn capacity(&self) -> usize;
+}
+
+/// Whose cache entries a request reads and writes
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CachePartition {
+    pub zone_id: ZoneId,
+    /// The storage partition of the tab making the request
+    pub key: PartitionKey,
+}
+
+impl CachePartition {
+    /// Tabs with a partition of their own browse privately (see
+    /// [`PartitionServices`](crate::tab::services::PartitionServices)), so nothing they load may
+    /// reach the disk.
+    fn is_private(&self) -> bool {
+        matches!(self.key, PartitionKey::Custom(_))
+    }
+}
+
+/// Where a request's entry is stored
+struct CacheKey {
+    id: String,
+    private: bool,
+}
+
+/// What the cache has for a request
+enum Lookup {
+    /// A response that can be used as is
+    Fresh(CachedResponse),
+    /// A response that has to be revalidated first
+    Stale(CachedResponse),
+    Miss,
+}
+
+/// The HTTP cache of an engine. Its entries are kept apart per [`CachePartition`].
+pub struct HttpCache {
+    backend: Arc<dyn CacheBackend>,
+    /// Entries of private partitions, which never go to `backend` in case it is on disk
+    private: MemoryCache,
+}
+
+impl HttpCache {
+    pub fn new(backend: Arc<dyn CacheBackend>) -> Self {
+        let private = MemoryCache::new(backend.capacity());
+        Self { backend, private }
+    }
+
+    /// The cache the settings ask for, or `None` when caching is turned off.
+    pub fn from_config(cfg: &gosub_config::Config) -> Option<Self> {
+        let memory = || Arc::new(MemoryCache::new(cfg.get_uint("net.cache.memory_bytes")));
+        let backend: Arc<dyn CacheBackend> = match cfg.get_string("net.cache.backend").as_str() {
+            "none" => return None,
+            "disk" => {
+                let dir = cfg.get_string("net.cache.directory");
+                if dir.is_empty() {
+                    log::warn!("net.cache.directory is not set, caching HTTP responses in memory");
+                    memory()
+                } else {
+                    match DiskCache::open(&dir, cfg.get_uint("net.cache.disk_bytes")) {
+                        Ok(disk) => Arc::new(disk),
+                        Err(e) => {
+                            log::warn!("Cannot open the HTTP cache in {dir}, caching in memory: {e}");
+                            memory()
+                        }
+                    }
+                }
+            }
+            _ => memory(),
+        };
+        Some(Self {
+            backend,
+            private: MemoryCache::new(cfg.get_uint("net.cache.memory_bytes")),
+        })
+    }
+
+    /// Drops every stored response.
+    pub fn clear(&self) {
+        self.backend.clear();
+        self.private.clear();
+    }
+
+    fn backend(&self, key: &CacheKey) -> &dyn CacheBackend {
+        if key.private {
+            &self.private
+        } else {
+            self.backend.as_ref()
+        }
+    }
+
+    /// Answers `req` for `partition` from the cache or through `fetcher`, storing what may be
+    /// stored. The result goes to `reply_tx` like a plain [`Fetcher::submit`] would send it.
+    pub async fn fetch(
+        &self,
+        fetcher: &Fetcher,
+        partition: &CachePartition,
+        mut req: FetchRequest,
+        handle: FetchHandle,
+        reply_tx: oneshot::Sender<FetchResult>,
+    ) {
+        let key = cache_key(partition, &req.key_data.url);
+        if req.key_data.method != Method::GET {
+            let unsafe_method = !matches!(req.key_data.method, Method::HEAD | Method::OPTIONS | Method::TRACE);
+            let Some(result) = forward(fetcher, req, handle).await else {
+                return;
+            };
+            if unsafe_method && result.meta().is_some_and(|meta| meta.status < 400) {
+                self.backend(&key).remove(&key.id);
+            }
+            let _ = reply_tx.send(result);
+            return;
+        }
+
+        let directives = CacheControl::from_headers(&req.key_data.headers);
+        if directives.no_store {
+            if let Some(result) = forward(fetcher, req, handle).await {
+                let _ = reply_tx.send(result);
+            }
+            return;
+        }
+
+        let request_time = SystemTime::now();
+        let request_headers = req.key_data.headers.clone();
+        let revalidating = match self.lookup(&key, &request_headers, request_time) {
+            Lookup::Fresh(response) => {
+                log::trace!("HTTP cache hit for {}", req.key_data.url);
+                REF_REGISTRY.forget_request(req.req_id);
+                let _ = reply_tx.send(response.into_result());
+                return;
+            }
+            Lookup::Stale(response) => response.add_validators(&mut req.key_data.headers).then_some(response),
+            Lookup::Miss => None,
+        };
+
+        let Some(result) = forward(fetcher, req, handle).await else {
+            return;
+        };
+        let response_time = SystemTime::now();
+        let confirmed = match (revalidating, result.meta()) {
+            (Some(stored), Some(meta)) if meta.status == 304 => {
+                Some(stored.revalidated(&meta.headers, request_time, response_time))
+            }
+            _ => None,
+        };
+        let result = match confirmed {
+            Some(response) => {
+                self.backend(&key).put(&key.id, response.clone());
+                response.into_result()
+            }
+            None => {
+                self.store(&key, &request_headers, result, request_time, response_time)
+                    .await
+            }
+        };
+        let _ = reply_tx.send(result);
+    }
+
+    fn lookup(&self, key: &CacheKey, headers: &HeaderMap, now: SystemTime) -> Lookup {
+        let directives = CacheControl::from_headers(headers);
+        match self.backend(key).get(&key.id) {
+            Some(response) if !response.matches(headers) => Lookup::Miss,
+            Some(response) if response.is_fresh(now, &directives) => Lookup::Fresh(response),
+            Some(response) => Lookup::Stale(response),
+            None => Lookup::Miss,
+        }
+    }
+
+    /// Stores `result` under `key` when it may be stored, and returns it. A streamed response
+    /// that gets stored is read to the end first and returned buffered.
+    async fn store(
+        &self,
+        key: &CacheKey,
+        request_headers: &HeaderMap,
+        result: FetchResult,
+        request_time: SystemTime,
+        response_time: SystemTime,
+    ) -> FetchResult {
+        let Some(vary) = result.meta().and_then(|meta| storable(meta, request_headers)) else {
+            return result;
+        };
+        let backend = self.backend(key);
+        let max_entry = backend.capacity() / MAX_ENTRY_SHARE;
+        let (meta, body) = match result {
+            FetchResult::Buffered { meta, body } if body.len() <= max_entry => (meta, body),
+            FetchResult::Stream { meta, peek_buf, shared } => {
+                let length = meta
+                    .headers
+                    .get(header::CONTENT_LENGTH)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.parse::<usize>().ok());
+                if length.is_none_or(|length| length > max_entry) {
+                    return FetchResult::Stream { meta, peek_buf, shared };
+                }
+                match stream_to_bytes(peek_buf, shared).await {
+                    Ok(body) => (meta, body),
+                    Err(e) => return FetchResult::Error(NetError::Other(Arc::new(e))),
+                }
+            }
+            result => return result,
+        };
+
+        backend.put(
+            &key.id,
+            CachedResponse {
+                url: meta.final_url.clone(),
+                status: meta.status,
+                status_text: meta.status_text.clone(),
+                headers: without_cookies(meta.headers.clone()),
+                body: body.clone(),
+                vary,
+                request_time,
+                response_time,
+            },
+        );
+        FetchResult::Buffered { meta, body }
+    }
+}
+
+/// Submits `req` to `fetcher` and waits for the result. `None` when the fetcher dropped the
+/// request (it was cancelled, or the zone shut down).
+async fn forward(fetcher: &Fetcher, req: FetchRequest, handle: FetchHandle) -> Option<FetchResult> {
+    let (tx, rx) = oneshot::channel();
+    fetcher.submit(req, handle, tx).await;
+    rx.await.ok()
+}
+
+/// Responses are stored per partition and URL, without its fragment.
+fn cache_key(partition: &CachePartition, url: &Url) -> CacheKey {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    CacheKey {
+        id: format!("{} {} {url}", partition.zone_id, partition.key.as_storage_key()),
+        private: partition.is_private(),
+    }
+}
+
+/// `headers` without the cookies they set, which must not be replayed to later requests.
+fn without_cookies(mut headers: HeaderMap) -> HeaderMap {
+    headers.remove(header::SET_COOKIE);
+    headers.remove("set-cookie2");
+    headers
+}
+
+/// Whether a response to a `GET` with `request_headers` may be stored. Returns the request
+/// headers its `Vary` names if so.
+fn storable(meta: &FetchResultMeta, request_headers: &HeaderMap) -> Option<Vec<(HeaderName, Option<HeaderValue>)>> {
+    let directives = CacheControl::from_headers(&meta.headers);
+    if directives.no_store || !HEURISTICALLY_CACHEABLE.contains(&meta.status) {
+        return None;
+    }
+    let explicit = directives.max_age.is_some() || meta.headers.contains_key(header::EXPIRES);
+    let validated = meta.headers.contains_key(header::ETAG) || meta.headers.contains_key(header::LAST_MODIFIED);
+    if !explicit && !validated {
+        return None;
+    }
+
+    let mut vary = Vec::new();
+    for value in meta.headers.get_all(header::VARY) {
+        for name in value.to_str().ok()?.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            // `Vary: *` never matches a later request.
+            if name == "*" {
+                return None;
+            }
+            let name = HeaderName::from_bytes(name.as_bytes()).ok()?;
+            vary.push((name.clone(), request_headers.get(&name).cloned()));
+        }
+    }
+    Some(vary)
+}
+
+/// The `Cache-Control` directives the cache acts on
+#[derive(Debug, Default, PartialEq)]
+struct CacheControl {
+    no_store: bool,
+    no_cache: bool,
+    max_age: Option<Duration>,
+}
+
+impl CacheControl {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let mut directives = CacheControl::default();
+        let mut has_cache_control = false;
+        for value in headers.get_all(header::CACHE_CONTROL) {
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+            has_cache_control = true;
+            for directive in value.split(',') {
+                let (name, argument) = match directive.split_once('=') {
+                    Some((name, argument)) => (name.trim(), Some(argument.trim().trim_matches('"'))),
+                    None => (directive.trim(), None),
+                };
+                if name.eq_ignore_ascii_case("no-store") {
+                    directives.no_store = true;
+                } else if name.eq_ignore_ascii_case("no-cache") {
+                    directives.no_cache = true;
+                } else if name.eq_ignore_ascii_case("max-age") {
+                    // An invalid max-age makes the response stale right away.
+                    let seconds = argument.and_then(|a| a.parse::<u64>().ok()).unwrap_or(0);
+                    directives.max_age = Some(Duration::from_secs(seconds));
+                }
+            }
+        }
+        // `Pragma: no-cache` only counts without a Cache-Control header (RFC 9111 5.4).
+        if !has_cache_control {
+            directives.no_cache = headers
+                .get_all(header::PRAGMA)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .any(|v| v.split(',').any(|d| d.trim().eq_ignore_ascii_case("no-cache")));
+        }
+        directives
+    }
+}
+
+fn header_date(headers: &HeaderMap, name: HeaderName) -> Option<SystemTime> {
+    headers.get(name).and_then(header_value_date)
+}
+
+fn header_value_date(value: &HeaderValue) -> Option<SystemTime> {
+    let value = value.to_str().ok()?;
+    chrono::DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(SystemTime::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn http_date(time: SystemTime) -> HeaderValue {
+        let time: chrono::DateTime<chrono::Utc> = time.into();
+        HeaderValue::from_str(&time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()).unwrap()
+    }
+
+    fn meta(headers: &[(HeaderName, HeaderValue)]) -> FetchResultMeta {
+        FetchResultMeta {
+            final_url: Url::parse("https://example.org/app.js").unwrap(),
+            status: 200,
+            status_text: "OK".into(),
+            headers: headers.iter().cloned().collect(),
+            content_length: None,
+            content_type: None,
+            has_body: true,
+        }
+    }
+
+    async fn store(
+        cache: &HttpCache,
+        key: &CacheKey,
+        meta: FetchResultMeta,
+        request_headers: &HeaderMap,
+        at: SystemTime,
+    ) {
+        let result = FetchResult::Buffered {
+            meta,
+            body: Bytes::from_static(b"console.log(1)"),
+        };
+        cache.store(key, request_headers, result, at, at).await;
+    }
+
+    fn app_js(zone_id: ZoneId, key: PartitionKey) -> CacheKey {
+        let url = Url::parse("https://example.org/app.js#main").unwrap();
+        cache_key(&CachePartition { zone_id, key }, &url)
+    }
+
+    fn zone() -> ZoneId {
+        ZoneId::from(uuid::Uuid::nil())
+    }
+
+    fn memory_cache() -> HttpCache {
+        HttpCache::new(Arc::new(MemoryCache::new(1 << 20)))
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn fresh_until_max_age_then_revalidated() {
+        let cache = memory_cache();
+        let key = &app_js(zone(), PartitionKey::None);
+        let now = SystemTime::now();
+        let headers = [
+            (header::CACHE_CONTROL, HeaderValue::from_static("public, max-age=60")),
+            (header::ETAG, HeaderValue::from_static("\"v1\"")),
+            (header::DATE, http_date(now)),
+        ];
+        store(&cache, key, meta(&headers), &HeaderMap::new(), now).await;
+
+        let Lookup::Fresh(response) = cache.lookup(key, &HeaderMap::new(), now + Duration::from_secs(30)) else {
+            panic!("expected a fresh response");
+        };
+        assert_eq!(&response.body[..], b"console.log(1)");
+
+        // A reload asks for revalidation even while fresh.
+        let mut reload = HeaderMap::new();
+        reload.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+        assert!(matches!(cache.lookup(key, &reload, now), Lookup::Stale(_)));
+
+        let Lookup::Stale(response) = cache.lookup(key, &HeaderMap::new(), now + Duration::from_secs(61)) else {
+            panic!("expected a stale response");
+        };
+        let mut request = HeaderMap::new();
+        assert!(response.add_validators(&mut request));
+        assert_eq!(request.get(header::IF_NONE_MATCH).unwrap(), "\"v1\"");
+
+        let later = now + Duration::from_secs(61);
+        let not_modified: HeaderMap = [
+            (header::CACHE_CONTROL, HeaderValue::from_static("max-age=120")),
+            (header::DATE, http_date(later)),
+        ]
+        .into_iter()
+        .collect();
+        let response = response.revalidated(&not_modified, later, later);
+        assert_eq!(response.headers.get(header::ETAG).unwrap(), "\"v1\"");
+        assert!(response.is_fresh(later + Duration::from_secs(100), &CacheControl::default()));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn heuristic_freshness_from_last_modified() {
+        let cache = memory_cache();
+        let key = &app_js(zone(), PartitionKey::None);
+        let now = SystemTime::now();
+        let headers = [
+            (header::DATE, http_date(now)),
+            (
+                header::LAST_MODIFIED,
+                http_date(now - Duration::from_secs(10 * 60 * 60)),
+            ),
+        ];
+        store(&cache, key, meta(&headers), &HeaderMap::new(), now).await;
+
+        // A tenth of the ten hours since the last change.
+        assert!(matches!(
+            cache.lookup(key, &HeaderMap::new(), now + Duration::from_secs(50 * 60)),
+            Lookup::Fresh(_)
+        ));
+        assert!(matches!(
+            cache.lookup(key, &HeaderMap::new(), now + Duration::from_secs(70 * 60)),
+            Lookup::Stale(_)
+        ));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn only_storable_responses_are_kept() {
+        let cache = memory_cache();
+        let key = &app_js(zone(), PartitionKey::None);
+        let now = SystemTime::now();
+
+        store(&cache, key, meta(&[]), &HeaderMap::new(), now).await;
+        assert!(matches!(cache.lookup(key, &HeaderMap::new(), now), Lookup::Miss));
+
+        let no_store = [(header::CACHE_CONTROL, HeaderValue::from_static("no-store, max-age=60"))];
+        store(&cache, key, meta(&no_store), &HeaderMap::new(), now).await;
+        assert!(matches!(cache.lookup(key, &HeaderMap::new(), now), Lookup::Miss));
+
+        let vary_all = [
+            (header::CACHE_CONTROL, HeaderValue::from_static("max-age=60")),
+            (header::VARY, HeaderValue::from_static("*")),
+        ];
+        store(&cache, key, meta(&vary_all), &HeaderMap::new(), now).await;
+        assert!(matches!(cache.lookup(key, &HeaderMap::new(), now), Lookup::Miss));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn vary_headers_must_match() {
+        let cache = memory_cache();
+        let key = &app_js(zone(), PartitionKey::None);
+        let now = SystemTime::now();
+        let headers = [
+            (header::CACHE_CONTROL, HeaderValue::from_static("max-age=60")),
+            (header::VARY, HeaderValue::from_static("Accept-Language")),
+        ];
+        let mut english = HeaderMap::new();
+        english.insert(header::ACCEPT_LANGUAGE, HeaderValue::from_static("en"));
+        store(&cache, key, meta(&headers), &english, now).await;
+
+        assert!(matches!(cache.lookup(key, &english, now), Lookup::Fresh(_)));
+        let mut dutch = HeaderMap::new();
+        dutch.insert(header::ACCEPT_LANGUAGE, HeaderValue::from_static("nl"));
+        assert!(matches!(cache.lookup(key, &dutch, now), Lookup::Miss));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn entries_are_kept_per_partition_without_cookies() {
+        let cache = memory_cache();
+        let now = SystemTime::now();
+        let headers = [
+            (header::CACHE_CONTROL, HeaderValue::from_static("max-age=60")),
+            (header::SET_COOKIE, HeaderValue::from_static("session=1")),
+        ];
+        let key = &app_js(zone(), PartitionKey::None);
+        store(&cache, key, meta(&headers), &HeaderMap::new(), now).await;
+
+        let Lookup::Fresh(response) = cache.lookup(key, &HeaderMap::new(), now) else {
+            panic!("stored response should be fresh");
+        };
+        assert!(!response.headers.contains_key(header::SET_COOKIE));
+
+        let other_zone = app_js(ZoneId::new(), PartitionKey::None);
+        assert!(matches!(
+            cache.lookup(&other_zone, &HeaderMap::new(), now),
+            Lookup::Miss
+        ));
+        let top_level = PartitionKey::TopLevel(Url::parse("https://other.test/").unwrap().origin());
+        let other_site = app_js(zone(), top_level);
+        assert!(matches!(
+            cache.lookup(&other_site, &HeaderMap::new(), now),
+            Lookup::Miss
+        ));
+
+        // Private partitions never reach the configured backend, which may be on disk.
+        let private = &app_js(zone(), PartitionKey::Custom("work".into()));
+        store(&cache, private, meta(&headers), &HeaderMap::new(), now).await;
+        assert!(matches!(
+            cache.lookup(private, &HeaderMap::new(), now),
+            Lookup::Fresh(_)
+        ));
+        assert!(cache.backend.get(&private.id).is_none());
+        assert!(cache.private.get(&private.id).is_some());
+    }
+
+    #[test]
+    fn cache_control_parsing() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static("No-Cache, max-age=\"30\""),
+        );
+        headers.insert(header::PRAGMA, HeaderValue::from_static("no-store"));
+        assert_eq!(
+            CacheControl::from_headers(&headers),
+            CacheControl {
+                no_store: false,
+                no_cache: true,
+                max_age: Some(Duration::from_secs(30)),
+            }
+        );
+
+        let mut pragma = HeaderMap::new();
+        pragma.insert(header::PRAGMA, HeaderValue::from_static("no-cache"));
+        assert!(CacheControl::from_headers(&pragma).no_cache);
+    }
+}
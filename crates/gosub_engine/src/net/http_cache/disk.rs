@@ -0,0 +1,319 @@
+use super::{CacheBackend, CachedResponse};
+use bytes::Bytes;
+use http::{HeaderMap, HeaderName, HeaderValue};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use url::Url;
+
+/// Extension of the files entries are stored in
+const ENTRY_EXTENSION: &str = "entry";
+
+/// Keeps responses in a directory, one file per URL, dropping the least recently used past its
+/// capacity.
+///
+/// An entry file holds a line of JSON with the response's status, headers and times, followed
+/// by the body.
+pub struct DiskCache {
+    dir: PathBuf,
+    capacity: usize,
+    index: Mutex<DiskIndex>,
+}
+
+/// The entry files in the directory, in memory
+#[derive(Default)]
+struct DiskIndex {
+    /// File name to size and when it was last used
+    files: HashMap<String, (usize, u64)>,
+    /// File names by when they were last used, oldest first
+    lru: BTreeMap<u64, String>,
+    tick: u64,
+    size: usize,
+}
+
+impl DiskIndex {
+    fn touch(&mut self, file: &str, size: usize) {
+        self.forget(file);
+        self.tick += 1;
+        self.size += size;
+        self.lru.insert(self.tick, file.to_string());
+        self.files.insert(file.to_string(), (size, self.tick));
+    }
+
+    fn forget(&mut self, file: &str) {
+        if let Some((size, tick)) = self.files.remove(file) {
+            self.lru.remove(&tick);
+            self.size -= size;
+        }
+    }
+}
+
+/// Everything of a stored response but its body
+#[derive(Serialize, Deserialize)]
+struct EntryHeader {
+    key: String,
+    url: String,
+    status: u16,
+    status_text: String,
+    headers: Vec<(String, String)>,
+    vary: Vec<(String, Option<String>)>,
+    /// Milliseconds since the Unix epoch
+    request_time: u64,
+    response_time: u64,
+}
+
+impl DiskCache {
+    /// Opens (creating it if needed) the cache in `dir`, holding up to `capacity` bytes.
+    /// Entries left by an earlier run are picked up, the most recently written counting as the
+    /// most recently used.
+    pub fn open(dir: impl AsRef<Path>, capacity: usize) -> std::io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let mut found = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != ENTRY_EXTENSION) {
+                continue;
+            }
+            let (Some(name), Ok(metadata)) = (path.file_name().and_then(|n| n.to_str()), path.metadata()) else {
+                continue;
+            };
+            let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+            found.push((modified, name.to_string(), metadata.len() as usize));
+        }
+        found.sort();
+
+        let cache = Self {
+            dir,
+            capacity,
+            index: Mutex::new(DiskIndex::default()),
+        };
+        {
+            let mut index = cache.index.lock();
+            for (_, name, size) in found {
+                index.touch(&name, size);
+            }
+        }
+        cache.evict(0);
+        Ok(cache)
+    }
+
+    /// Bytes of entry files in the directory
+    pub fn size(&self) -> usize {
+        self.index.lock().size
+    }
+
+    fn path(&self, file: &str) -> PathBuf {
+        self.dir.join(file)
+    }
+
+    /// Removes the least recently used entries until `incoming` more bytes fit.
+    fn evict(&self, incoming: usize) {
+        let mut index = self.index.lock();
+        while index.size + incoming > self.capacity {
+            let Some((_, oldest)) = index.lru.pop_first() else {
+                break;
+            };
+            index.forget(&oldest);
+            let _ = fs::remove_file(self.path(&oldest));
+        }
+    }
+
+    fn read(&self, file: &str, key: &str) -> Option<CachedResponse> {
+        let data = fs::read(self.path(file)).ok()?;
+        let split = data.iter().position(|&b| b == b'\n')?;
+        let header: EntryHeader = serde_json::from_slice(&data[..split]).ok()?;
+        // Another URL with the same file name
+        if header.key != key {
+            return None;
+        }
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in header.headers {
+            headers.append(
+                HeaderName::from_bytes(name.as_bytes()).ok()?,
+                HeaderValue::from_str(&value).ok()?,
+            );
+        }
+        let mut vary = Vec::new();
+        for (name, value) in header.vary {
+            let value = match value {
+                Some(value) => Some(HeaderValue::from_str(&value).ok()?),
+                None => None,
+            };
+            vary.push((HeaderName::from_bytes(name.as_bytes()).ok()?, value));
+        }
+        Some(CachedResponse {
+            url: Url::parse(&header.url).ok()?,
+            status: header.status,
+            status_text: header.status_text,
+            headers,
+            body: Bytes::copy_from_slice(&data[split + 1..]),
+            vary,
+            request_time: UNIX_EPOCH + Duration::from_millis(header.request_time),
+            response_time: UNIX_EPOCH + Duration::from_millis(header.response_time),
+        })
+    }
+
+    /// The entry file's contents, or `None` for responses with headers that aren't text
+    fn encode(key: &str, response: &CachedResponse) -> Option<Vec<u8>> {
+        let text = |value: &HeaderValue| value.to_str().ok().map(str::to_string);
+        let mut headers = Vec::new();
+        for (name, value) in &response.headers {
+            headers.push((name.to_string(), text(value)?));
+        }
+        let mut vary = Vec::new();
+        for (name, value) in &response.vary {
+            let value = match value {
+                Some(value) => Some(text(value)?),
+                None => None,
+            };
+            vary.push((name.to_string(), value));
+        }
+        let millis = |time: SystemTime| time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64);
+        let header = EntryHeader {
+            key: key.to_string(),
+            url: response.url.to_string(),
+            status: response.status,
+            status_text: response.status_text.clone(),
+            headers,
+            vary,
+            request_time: millis(response.request_time),
+            response_time: millis(response.response_time),
+        };
+
+        let mut data = serde_json::to_vec(&header).ok()?;
+        data.push(b'\n');
+        data.extend_from_slice(&response.body);
+        Some(data)
+    }
+}
+
+impl CacheBackend for DiskCache {
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        let file = file_name(key);
+        let size = self.index.lock().files.get(&file)?.0;
+        let response = self.read(&file, key)?;
+        self.index.lock().touch(&file, size);
+        Some(response)
+    }
+
+    fn put(&self, key: &str, response: CachedResponse) {
+        let file = file_name(key);
+        self.remove(key);
+        let Some(data) = Self::encode(key, &response) else {
+            return;
+        };
+        if data.len() > self.capacity {
+            return;
+        }
+        self.evict(data.len());
+
+        // Written next to the entry and renamed over it, so readers never see half an entry.
+        let partial = self.path(&format!("{file}.partial"));
+        let written = fs::File::create(&partial)
+            .and_then(|mut f| f.write_all(&data))
+            .and_then(|()| fs::rename(&partial, self.path(&file)));
+        match written {
+            Ok(()) => self.index.lock().touch(&file, data.len()),
+            Err(e) => {
+                log::warn!("Cannot write HTTP cache entry for {key}: {e}");
+                let _ = fs::remove_file(&partial);
+            }
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        let file = file_name(key);
+        let mut index = self.index.lock();
+        if index.files.contains_key(&file) {
+            index.forget(&file);
+            let _ = fs::remove_file(self.path(&file));
+        }
+    }
+
+    fn clear(&self) {
+        let mut index = self.index.lock();
+        for file in index.files.keys() {
+            let _ = fs::remove_file(self.path(file));
+        }
+        *index = DiskIndex::default();
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+/// The entry file for `key`: a 64-bit FNV-1a hash of it, which stays the same across runs.
+fn file_name(key: &str) -> String {
+    let hash = key.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}.{ENTRY_EXTENSION}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::header;
+
+    fn response(body: &'static [u8]) -> CachedResponse {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("text/css"));
+        headers.insert(header::ETAG, HeaderValue::from_static("\"abc\""));
+        CachedResponse {
+            url: Url::parse("https://example.org/site.css").unwrap(),
+            status: 200,
+            status_text: "OK".into(),
+            headers,
+            body: Bytes::from_static(body),
+            vary: vec![(header::ACCEPT_ENCODING, None)],
+            request_time: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            response_time: UNIX_EPOCH + Duration::from_secs(1_700_000_001),
+        }
+    }
+
+    #[test]
+    fn entries_survive_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let cache = DiskCache::open(dir.path(), 1 << 20).unwrap();
+            cache.put("https://example.org/site.css", response(b"body { color: red }"));
+        }
+
+        let cache = DiskCache::open(dir.path(), 1 << 20).unwrap();
+        let stored = cache.get("https://example.org/site.css").expect("entry on disk");
+        assert_eq!(&stored.body[..], b"body { color: red }");
+        assert_eq!(stored.headers.get(header::ETAG).unwrap(), "\"abc\"");
+        assert_eq!(stored.vary, vec![(header::ACCEPT_ENCODING, None)]);
+        assert_eq!(stored.response_time, UNIX_EPOCH + Duration::from_secs(1_700_000_001));
+
+        cache.remove("https://example.org/site.css");
+        assert!(cache.get("https://example.org/site.css").is_none());
+        assert_eq!(cache.size(), 0);
+    }
+
+    #[test]
+    fn stays_within_capacity() {
+        let dir = tempfile::tempdir().unwrap();
+        let entry = DiskCache::encode("https://example.org/0", &response(&[b'x'; 200]))
+            .unwrap()
+            .len();
+        let cache = DiskCache::open(dir.path(), entry * 3).unwrap();
+        for i in 0..5 {
+            cache.put(&format!("https://example.org/{i}"), response(&[b'x'; 200]));
+        }
+
+        assert!(cache.size() <= entry * 3);
+        assert!(cache.get("https://example.org/0").is_none());
+        assert!(cache.get("https://example.org/4").is_some());
+        let files = fs::read_dir(dir.path()).unwrap().count();
+        assert_eq!(files, 3);
+    }
+}
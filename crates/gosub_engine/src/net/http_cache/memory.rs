@@ -0,0 +1,133 @@
+use super::{CacheBackend, CachedResponse};
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashMap};
+
+/// Keeps responses in memory, dropping the least recently used past its capacity.
+pub struct MemoryCache {
+    capacity: usize,
+    state: Mutex<MemoryState>,
+}
+
+#[derive(Default)]
+struct MemoryState {
+    /// Entries and when they were last used
+    entries: HashMap<String, (CachedResponse, u64)>,
+    /// Keys by when they were last used, oldest first
+    lru: BTreeMap<u64, String>,
+    tick: u64,
+    size: usize,
+}
+
+impl MemoryState {
+    fn remove(&mut self, key: &str) {
+        if let Some((response, tick)) = self.entries.remove(key) {
+            self.lru.remove(&tick);
+            self.size -= response.size();
+        }
+    }
+}
+
+impl MemoryCache {
+    /// A cache holding up to `capacity` bytes of responses.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(MemoryState::default()),
+        }
+    }
+
+    /// Bytes of responses held
+    pub fn size(&self) -> usize {
+        self.state.lock().size
+    }
+}
+
+impl CacheBackend for MemoryCache {
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        let mut state = self.state.lock();
+        state.tick += 1;
+        let tick = state.tick;
+        let (response, used) = state.entries.get_mut(key)?;
+        let previous = std::mem::replace(used, tick);
+        let response = response.clone();
+        state.lru.remove(&previous);
+        state.lru.insert(tick, key.to_string());
+        Some(response)
+    }
+
+    fn put(&self, key: &str, response: CachedResponse) {
+        let mut state = self.state.lock();
+        state.remove(key);
+        let size = response.size();
+        if size > self.capacity {
+            return;
+        }
+        while state.size + size > self.capacity {
+            let Some((_, oldest)) = state.lru.pop_first() else {
+                break;
+            };
+            state.remove(&oldest);
+        }
+
+        state.tick += 1;
+        let tick = state.tick;
+        state.size += size;
+        state.lru.insert(tick, key.to_string());
+        state.entries.insert(key.to_string(), (response, tick));
+    }
+
+    fn remove(&self, key: &str) {
+        self.state.lock().remove(key);
+    }
+
+    fn clear(&self) {
+        *self.state.lock() = MemoryState::default();
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use http::HeaderMap;
+    use std::time::SystemTime;
+    use url::Url;
+
+    fn response(body: &'static [u8]) -> CachedResponse {
+        CachedResponse {
+            url: Url::parse("https://example.org/").unwrap(),
+            status: 200,
+            status_text: "OK".into(),
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(body),
+            vary: Vec::new(),
+            request_time: SystemTime::now(),
+            response_time: SystemTime::now(),
+        }
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let entry = response(&[0; 100]).size();
+        let cache = MemoryCache::new(entry * 2);
+        cache.put("a", response(&[0; 100]));
+        cache.put("b", response(&[0; 100]));
+        // Using "a" makes "b" the one to go.
+        assert!(cache.get("a").is_some());
+        cache.put("c", response(&[0; 100]));
+
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("c").is_some());
+        assert_eq!(cache.size(), entry * 2);
+
+        cache.put("huge", response(&[0; 1000]));
+        assert!(cache.get("huge").is_none());
+        cache.remove("a");
+        assert_eq!(cache.size(), entry);
+    }
+}
@@ -3,8 +3,13 @@ use crate::engine::EngineContext;
 use crate::events::IoCommand;
 use crate::net::decision_hub::DecisionHub;
 use crate::net::fetcher::{EngineNetContext, Fetcher, FetcherConfig};
+use crate::net::hsts::{self, HstsStore};
+use crate::net::http_cache::{CachePartition, HttpCache};
+use crate::net::pending::PendingFetch;
+use crate::net::redirect::{self, RedirectPolicy};
 use crate::net::req_ref_tracker::RequestRefTracker;
 use crate::net::types::{FetchHandle, FetchRequest, FetchResult};
+use crate::storage::PartitionKey;
 use crate::util::spawn_named;
 use crate::zone::ZoneId;
 use crate::EngineError;
@@ -77,6 +82,8 @@ impl IoHandle {
 
 pub struct ZoneEntry {
     fetcher: Arc<Fetcher>,
+    /// The zone's HSTS hosts; `None` when HSTS is turned off
+    hsts: Option<Arc<HstsStore>>,
     shutdown: CancellationToken,
     join: JoinHandle<()>,
 }
@@ -92,6 +99,8 @@ pub struct IoRouter {
     /// Pending UA decisions (render/download/...) keyed by decision token.
     /// Tokens are process-wide unique, so one hub serves all zones.
     decision_hub: Arc<DecisionHub>,
+    /// Response cache shared by all zones; `None` when caching is turned off
+    cache: Option<Arc<HttpCache>>,
 }
 
 impl IoRouter {
@@ -99,6 +108,7 @@ impl IoRouter {
         Self {
             zones: DashMap::new(),
             cfg,
+            cache: HttpCache::from_config(&engine_ctx.config_store).map(Arc::new),
             engine_ctx,
             decision_hub: Arc::new(DecisionHub::new()),
         }
@@ -110,11 +120,14 @@ impl IoRouter {
         }
 
         let zone_shutdown = CancellationToken::new();
+        let hsts = HstsStore::from_config(&self.engine_ctx.config_store).map(Arc::new);
 
         let engine_ctx = Arc::new(EngineNetContext {
             event_tx: self.engine_ctx.event_tx.clone(),
             request_reference_map: self.engine_ctx.request_reference_map.clone(),
             request_ref_tracker: Arc::new(RequestRefTracker::new()),
+            redirect_policy: RedirectPolicy::from_config(&self.engine_ctx.config_store),
+            hsts: hsts.clone(),
         });
         let f =
             Arc::new(Fetcher::new(self.cfg.clone(), engine_ctx).map_err(|e| EngineError::NetworkError(e.to_string()))?);
@@ -130,6 +143,7 @@ impl IoRouter {
             zone_id,
             ZoneEntry {
                 fetcher: f.clone(),
+                hsts,
                 shutdown: zone_shutdown,
                 join: join_handle,
             },
@@ -138,6 +152,11 @@ impl IoRouter {
         Ok(f)
     }
 
+    /// The HSTS hosts of zone `zone_id`, once its fetcher runs.
+    fn zone_hsts(&self, zone_id: ZoneId) -> Option<Arc<HstsStore>> {
+        self.zones.get(&zone_id).and_then(|entry| entry.hsts.clone())
+    }
+
     #[instrument(
         name = "zone.shutdown",
         level = "debug",
@@ -180,12 +199,15 @@ impl IoRouter {
     }
 }
 
+/// Submits `req` to the I/O thread on behalf of a tab in storage partition `partition`.
+/// Cancelling `parent_cancel` aborts the fetch, along with every other fetch submitted under it.
 pub async fn submit_to_io(
     zone_id: ZoneId,
+    partition: PartitionKey,
     req: FetchRequest,
     io_tx: IoChannel,
     parent_cancel: Option<CancellationToken>,
-) -> anyhow::Result<(FetchHandle, oneshot::Receiver<FetchResult>)> {
+) -> anyhow::Result<PendingFetch> {
     let (reply_tx, reply_rx) = oneshot::channel::<FetchResult>();
 
     let cancel = match parent_cancel {
@@ -198,17 +220,80 @@ pub async fn submit_to_io(
         key: req.key_data.clone(),
         cancel: cancel.clone(),
     };
+    redirect::watch(handle.req_id, cancel.clone());
 
     io_tx
         .send(IoCommand::Fetch {
             zone_id,
+            partition,
             req,
             handle: handle.clone(),
             reply_tx,
         })
         .map_err(|_| anyhow::anyhow!("I/O thread has shut down"))?;
 
-    Ok((handle, reply_rx))
+    Ok(PendingFetch::new(handle, reply_rx))
+}
+
+/// Hands `req` to the zone's fetcher, through the cache when there is one.
+async fn dispatch(
+    fetcher: &Fetcher,
+    cache: Option<(Arc<HttpCache>, CachePartition)>,
+    req: FetchRequest,
+    handle: FetchHandle,
+    reply_tx: oneshot::Sender<FetchResult>,
+) {
+    match cache {
+        Some((cache, partition)) => cache.fetch(fetcher, &partition, req, handle, reply_tx).await,
+        None => fetcher.submit(req, handle, reply_tx).await,
+    }
+}
+
+/// Fetches `req`, sending it again over HTTPS whenever a redirect leads to a host that asked for
+/// HTTPS only (see [`hsts`]). The upgraded hop counts as the redirect it replaces.
+async fn fetch_upgrading(
+    fetcher: &Fetcher,
+    cache: Option<(Arc<HttpCache>, CachePartition)>,
+    mut req: FetchRequest,
+    handle: FetchHandle,
+    reply_tx: oneshot::Sender<FetchResult>,
+) {
+    let req_id = handle.req_id;
+    loop {
+        // Each attempt can be stopped on its own; aborting the fetch stops them all.
+        let attempt = FetchHandle {
+            cancel: handle.cancel.child_token(),
+            ..handle.clone()
+        };
+        hsts::watch(req_id, attempt.cancel.clone());
+        let (tx, rx) = oneshot::channel();
+        dispatch(fetcher, cache.clone(), req.clone(), attempt.clone(), tx).await;
+        let result = tokio::select! {
+            biased;
+            _ = attempt.cancel.cancelled() => None,
+            result = rx => result.ok(),
+        };
+
+        let hop = hsts::take_upgrade(req_id);
+        match (hop, result) {
+            (Some(hop), _) if !handle.cancel.is_cancelled() => {
+                let method = redirect::method_after_redirect(&req.key_data.method, hop.status);
+                if method != req.key_data.method {
+                    req.key_data.method = method;
+                    req.body = None;
+                }
+                req.key_data.url = hop.url;
+                // The stopped attempt finished its redirect tracking; the next one goes on with it.
+                redirect::watch(req_id, handle.cancel.clone());
+            }
+            (_, Some(result)) => {
+                let _ = reply_tx.send(result);
+                return;
+            }
+            // Aborted: whoever waits for the result sees that on the fetch's own token.
+            (_, None) => return,
+        }
+    }
 }
 
 /// Spawns the IO thread and runs a single fetcher on top. If needed, we can expand this system to
@@ -231,11 +316,39 @@ pub fn spawn_io_thread(cfg: FetcherConfig, engine_ctx: Arc<EngineContext>) -> Io
                 }
                 maybe_req = rx_submit.recv() => {
                     match maybe_req {
-                        Some(IoCommand::Fetch { zone_id, req, handle, reply_tx }) => {
+                        Some(IoCommand::Fetch { zone_id, partition, mut req, handle, reply_tx }) => {
                             // The I/O thread must keep running; drop the request on fetcher failure.
-                            match router.get_or_spawn_zone_fetcher(zone_id) {
-                                Ok(fetcher) => fetcher.submit(req, handle, reply_tx).await,
-                                Err(e) => log::error!("Failed to create fetcher for zone {zone_id}: {e}"),
+                            let fetcher = match router.get_or_spawn_zone_fetcher(zone_id) {
+                                Ok(fetcher) => fetcher,
+                                Err(e) => {
+                                    log::error!("Failed to create fetcher for zone {zone_id}: {e}");
+                                    continue;
+                                }
+                            };
+                            let cache = router.cache.as_ref().map(|cache| {
+                                (Arc::clone(cache), CachePartition { zone_id, key: partition })
+                            });
+                            match router.zone_hsts(zone_id) {
+                                // Hosts that asked for HTTPS only are never fetched without it,
+                                // not even after a redirect, so the fetch gets a task of its own
+                                // that can send it again.
+                                Some(hsts) => {
+                                    if let Some(url) = hsts.upgrade(&req.key_data.url) {
+                                        log::debug!("HSTS: upgrading {} to {url}", req.key_data.url);
+                                        req.key_data.url = url;
+                                    }
+                                    spawn_named("hsts-fetch", async move {
+                                        fetch_upgrading(&fetcher, cache, req, handle, reply_tx).await;
+                                    });
+                                }
+                                // The cache waits for the response to store it, so it gets a task
+                                // of its own rather than holding up the loop.
+                                None if cache.is_some() => {
+                                    spawn_named("http-cache", async move {
+                                        dispatch(&fetcher, cache, req, handle, reply_tx).await;
+                                    });
+                                }
+                                None => fetcher.submit(req, handle, reply_tx).await,
                             }
                         }
                         Some(IoCommand::Decision { token, action }) => {
@@ -374,6 +487,24 @@ mod tests {
         router.shutdown_all().await;
     }
 
+    /// What one zone learns about HSTS hosts stays out of the others.
+    #[tokio::test(flavor = "current_thread")]
+    async fn router_keeps_hsts_hosts_per_zone() {
+        let router = IoRouter::new(test_cfg(), test_engine_ctx());
+        let z1 = ZoneId::new();
+        let z2 = ZoneId::new();
+        router.get_or_spawn_zone_fetcher(z1).unwrap();
+        router.get_or_spawn_zone_fetcher(z2).unwrap();
+
+        let hsts1 = router.zone_hsts(z1).expect("HSTS is on by default");
+        let hsts2 = router.zone_hsts(z2).expect("HSTS is on by default");
+        hsts1.preload("tracker.example");
+        assert!(hsts1.is_known("tracker.example"));
+        assert!(!hsts2.is_known("tracker.example"));
+
+        router.shutdown_all().await;
+    }
+
     /// Shutting down an unknown zone is a no-op (returns false).
     #[tokio::test(flavor = "current_thread")]
     async fn router_shutdown_unknown_zone_is_noop() {
@@ -0,0 +1,182 @@
+//! Mixed content: insecure subresources of secure documents.
+//!
+//! A resource an `https:` document loads over plain `http:` (or `ws:`) can be read and replaced
+//! by anyone on the path, which undoes what HTTPS promised for the document. As the Mixed
+//! Content spec has it, images, audio and video are upgraded to HTTPS and everything else
+//! (scripts, stylesheets, frames, fetches) is blocked. `security.mixed_content` can make that
+//! stricter or turn the checks off. Loads from `localhost` and loopback addresses aren't mixed
+//! content. Blocked loads are reported to the UA as [`EngineEvent::MixedContentBlocked`].
+
+use crate::engine::events::EngineEvent;
+use crate::engine::types::EventChannel;
+use crate::net::hsts::secure_url;
+use crate::net::types::ResourceKind;
+use crate::tab::TabId;
+use cow_utils::CowUtils;
+use std::net::IpAddr;
+use url::Url;
+
+/// What happens to insecure subresources of secure documents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MixedContentPolicy {
+    /// Nothing is upgraded; every insecure load is blocked
+    Block,
+    /// Images, audio and video are upgraded to HTTPS, the rest is blocked
+    #[default]
+    Upgrade,
+    /// Insecure loads go ahead
+    Allow,
+}
+
+impl MixedContentPolicy {
+    /// The policy the settings ask for.
+    pub fn from_config(cfg: &gosub_config::Config) -> Self {
+        match cfg.get_string("security.mixed_content").as_str() {
+            "block" => Self::Block,
+            "allow" => Self::Allow,
+            _ => Self::Upgrade,
+        }
+    }
+}
+
+/// What becomes of one load
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MixedContent {
+    /// Not mixed content, or allowed anyway
+    Allowed,
+    /// Requested over HTTPS instead
+    Upgraded(Url),
+    Blocked,
+}
+
+/// Decides what becomes of a `kind` load of `url` by the document at `document`.
+pub fn classify(policy: MixedContentPolicy, document: &Url, url: &Url, kind: ResourceKind) -> MixedContent {
+    if policy == MixedContentPolicy::Allow || !is_secure_document(document) || !is_insecure(url) {
+        return MixedContent::Allowed;
+    }
+    let upgradeable = matches!(kind, ResourceKind::Image | ResourceKind::Media);
+    match (policy, upgradeable) {
+        (MixedContentPolicy::Upgrade, true) => secure_url(url).map_or(MixedContent::Blocked, MixedContent::Upgraded),
+        _ => MixedContent::Blocked,
+    }
+}
+
+/// Whether the document at `url` was delivered securely.
+fn is_secure_document(url: &Url) -> bool {
+    matches!(url.scheme(), "https" | "wss")
+}
+
+/// Whether loading `url` goes over the network unprotected.
+fn is_insecure(url: &Url) -> bool {
+    if !matches!(url.scheme(), "http" | "ws") {
+        return false;
+    }
+    match url.host() {
+        Some(url::Host::Domain(host)) => {
+            let host = host.trim_end_matches('.').cow_to_ascii_lowercase();
+            !(host == "localhost" || host.ends_with(".localhost"))
+        }
+        Some(url::Host::Ipv4(ip)) => !IpAddr::V4(ip).is_loopback(),
+        Some(url::Host::Ipv6(ip)) => !IpAddr::V6(ip).is_loopback(),
+        None => true,
+    }
+}
+
+/// Mixed content checks for the loads of one tab, which reports what they block.
+#[derive(Clone, Debug, Default)]
+pub struct MixedContentGuard {
+    policy: MixedContentPolicy,
+    /// Where blocked loads are reported; unreported when `None`
+    reporter: Option<(TabId, EventChannel)>,
+}
+
+impl MixedContentGuard {
+    pub fn new(policy: MixedContentPolicy, tab_id: TabId, event_tx: EventChannel) -> Self {
+        Self {
+            policy,
+            reporter: Some((tab_id, event_tx)),
+        }
+    }
+
+    /// The URL to request for a `kind` load of `url` by the document at `document`, or `None`
+    /// when it is blocked.
+    pub fn check(&self, document: &Url, url: &Url, kind: ResourceKind) -> Option<Url> {
+        match classify(self.policy, document, url, kind) {
+            MixedContent::Allowed => Some(url.clone()),
+            MixedContent::Upgraded(secure) => {
+                log::debug!("Upgrading mixed content {url} on {document}");
+                Some(secure)
+            }
+            MixedContent::Blocked => {
+                log::warn!("Blocked mixed content {url} on {document}");
+                if let Some((tab_id, event_tx)) = &self.reporter {
+                    let _ = event_tx.send(EngineEvent::MixedContentBlocked {
+                        tab_id: *tab_id,
+                        document: document.clone(),
+                        url: url.clone(),
+                    });
+                }
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn upgrades_media_and_blocks_the_rest() {
+        let page = url("https://example.org/");
+        let image = url("http://cdn.example/a.png");
+        let check = |policy, target: &Url, kind| classify(policy, &page, target, kind);
+
+        assert_eq!(
+            check(MixedContentPolicy::Upgrade, &image, ResourceKind::Image),
+            MixedContent::Upgraded(url("https://cdn.example/a.png"))
+        );
+        assert_eq!(
+            check(
+                MixedContentPolicy::Upgrade,
+                &url("http://cdn.example/a.js"),
+                ResourceKind::Script { blocking: true }
+            ),
+            MixedContent::Blocked
+        );
+        assert_eq!(
+            check(MixedContentPolicy::Block, &image, ResourceKind::Image),
+            MixedContent::Blocked
+        );
+        assert_eq!(
+            check(MixedContentPolicy::Allow, &image, ResourceKind::Image),
+            MixedContent::Allowed
+        );
+        // Secure and local loads aren't mixed content...
+        for target in [
+            "https://cdn.example/a.js",
+            "http://localhost:8000/a.js",
+            "http://127.0.0.1/a.js",
+            "data:,x",
+        ] {
+            assert_eq!(
+                check(MixedContentPolicy::Block, &url(target), ResourceKind::Fetch),
+                MixedContent::Allowed
+            );
+        }
+        // ...and neither is anything an insecure document loads.
+        assert_eq!(
+            classify(
+                MixedContentPolicy::Block,
+                &url("http://example.org/"),
+                &image,
+                ResourceKind::Image
+            ),
+            MixedContent::Allowed
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! Fetches in flight, and aborting them.
+//!
+//! [`submit_to_io`](crate::net::submit_to_io) hands back a [`PendingFetch`]. Awaiting its
+//! [`result`](PendingFetch::result) gives the response; calling [`abort`](PendingFetch::abort),
+//! or [`FetchAbort::abort`] from anywhere else, cancels the request's token. The fetcher drops
+//! the request and its connection when it sees that, and whoever awaits the result gets
+//! [`FetchError::Aborted`] straight away instead of waiting for the fetcher to wind down.
+
+use crate::net::redirect::{redirect_error, RedirectError};
+use crate::net::types::{FetchHandle, FetchResult, FetchResultMeta, NetError};
+use crate::net::utils::stream_to_bytes;
+use bytes::Bytes;
+use std::sync::Arc;
+use tokio::sync::oneshot;
+use tokio_util::sync::CancellationToken;
+
+/// Why a fetch didn't produce a response
+#[derive(Debug, thiserror::Error)]
+pub enum FetchError {
+    #[error("fetch aborted")]
+    Aborted,
+    /// The I/O thread dropped the request without answering (it shut down, say)
+    #[error("I/O thread went away before the fetch finished")]
+    Closed,
+    #[error(transparent)]
+    Net(NetError),
+    /// A redirect broke the redirect policy, so the fetch was stopped there
+    #[error(transparent)]
+    Redirect(RedirectError),
+}
+
+/// Aborts a fetch. Cheap to clone, to hand to whatever may need to stop it.
+#[derive(Debug, Clone)]
+pub struct FetchAbort(CancellationToken);
+
+impl FetchAbort {
+    pub fn abort(&self) {
+        self.0.cancel();
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        self.0.is_cancelled()
+    }
+
+    /// Resolves once the fetch is aborted.
+    pub async fn aborted(&self) {
+        self.0.cancelled().await;
+    }
+}
+
+/// A fetch submitted to the I/O thread
+pub struct PendingFetch {
+    handle: FetchHandle,
+    rx: oneshot::Receiver<FetchResult>,
+}
+
+impl PendingFetch {
+    pub(crate) fn new(handle: FetchHandle, rx: oneshot::Receiver<FetchResult>) -> Self {
+        Self { handle, rx }
+    }
+
+    pub fn handle(&self) -> &FetchHandle {
+        &self.handle
+    }
+
+    pub fn abort_handle(&self) -> FetchAbort {
+        FetchAbort(self.handle.cancel.clone())
+    }
+
+    pub fn abort(&self) {
+        self.handle.cancel.cancel();
+    }
+
+    /// Waits for the response. A streamed body is left for the caller to read; see
+    /// [`bytes`](Self::bytes) to read it in a way that can be aborted too.
+    pub async fn result(self) -> Result<FetchResult, FetchError> {
+        let req_id = self.handle.req_id;
+        let cancel = self.handle.cancel;
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => match redirect_error(req_id) {
+                Some(e) => Err(FetchError::Redirect(e)),
+                None => Err(FetchError::Aborted),
+            },
+            result = self.rx => result.map_err(|_| FetchError::Closed),
+        }
+    }
+
+    /// Waits for the response and reads its body to the end. Aborting stops the read and drops
+    /// the body stream.
+    pub async fn bytes(self) -> Result<(FetchResultMeta, Bytes), FetchError> {
+        let abort = self.abort_handle();
+        match self.result().await? {
+            FetchResult::Buffered { meta, body } => Ok((meta, body)),
+            FetchResult::Stream { meta, peek_buf, shared } => {
+                let body = tokio::select! {
+                    biased;
+                    _ = abort.aborted() => return Err(FetchError::Aborted),
+                    body = stream_to_bytes(peek_buf, shared) => body,
+                };
+                let body = body.map_err(|e| match e.downcast::<NetError>() {
+                    Ok(e) => FetchError::Net(e),
+                    Err(e) => FetchError::Net(NetError::Other(Arc::new(e))),
+                })?;
+                Ok((meta, body))
+            }
+            FetchResult::Error(e) => Err(FetchError::Net(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::types::{PeekBuf, RequestId};
+    use crate::net::types::FetchRequest;
+    use crate::net::SharedBody;
+    use http::{HeaderMap, Method};
+    use url::Url;
+
+    fn pending() -> (PendingFetch, oneshot::Sender<FetchResult>) {
+        let req = FetchRequest::builder(Method::GET, Url::parse("https://example.org/big.bin").unwrap())
+            .with_req_id(RequestId::new())
+            .build();
+        let handle = FetchHandle {
+            req_id: req.req_id,
+            key: req.key_data.clone(),
+            cancel: CancellationToken::new(),
+        };
+        let (tx, rx) = oneshot::channel();
+        (PendingFetch::new(handle, rx), tx)
+    }
+
+    fn meta() -> FetchResultMeta {
+        FetchResultMeta {
+            final_url: Url::parse("https://example.org/big.bin").unwrap(),
+            status: 200,
+            status_text: "OK".into(),
+            headers: HeaderMap::new(),
+            content_length: None,
+            content_type: None,
+            has_body: true,
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn abort_resolves_waiters() {
+        // Before any response arrives
+        let (fetch, _tx) = pending();
+        let abort = fetch.abort_handle();
+        let waiter = tokio::spawn(fetch.result());
+        abort.abort();
+        assert!(matches!(waiter.await.unwrap(), Err(FetchError::Aborted)));
+
+        // While the body is still streaming in
+        let (fetch, tx) = pending();
+        let abort = fetch.abort_handle();
+        let shared = Arc::new(SharedBody::new(8));
+        shared.push(Bytes::from_static(b"first chunk"));
+        let _ = tx.send(FetchResult::Stream {
+            meta: meta(),
+            peek_buf: PeekBuf::from_slice(b""),
+            shared: shared.clone(),
+        });
+        let reader = tokio::spawn(fetch.bytes());
+        tokio::task::yield_now().await;
+        abort.abort();
+        assert!(matches!(reader.await.unwrap(), Err(FetchError::Aborted)));
+
+        // The I/O thread dropping the request isn't an abort.
+        let (fetch, tx) = pending();
+        drop(tx);
+        assert!(matches!(fetch.result().await, Err(FetchError::Closed)));
+    }
+}
@@ -0,0 +1,320 @@
+//! Redirects: how many are followed, where to, and which ones a response went through.
+//!
+//! The zone fetchers follow redirects themselves and report every hop. [`RedirectPolicy`] is
+//! checked against each hop as it's reported; a fetch that breaks the policy is aborted, and its
+//! [`PendingFetch`](crate::net::PendingFetch) fails with
+//! [`FetchError::Redirect`](crate::net::FetchError::Redirect). The hops of a fetch are kept as
+//! a [`RedirectChain`] until [`take_redirect_chain`] collects them, so whoever shows the final
+//! response can show where it really came from.
+
+use crate::engine::types::RequestId;
+use crate::net::emitter::NetObserver;
+use crate::net::events::NetEvent;
+use dashmap::DashMap;
+use http::Method;
+use std::sync::{Arc, LazyLock};
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+/// Whether redirects to another origin are followed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CrossOriginRedirects {
+    #[default]
+    Follow,
+    /// Only to the same site (registrable domain), like `example.org` to `www.example.org`
+    SameSite,
+    Block,
+}
+
+/// Which redirects are followed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RedirectPolicy {
+    pub max_redirects: usize,
+    pub cross_origin: CrossOriginRedirects,
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        // The limit the Fetch spec sets
+        Self {
+            max_redirects: 20,
+            cross_origin: CrossOriginRedirects::Follow,
+        }
+    }
+}
+
+/// Why a redirect wasn't followed
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RedirectError {
+    #[error("more than {0} redirects")]
+    TooMany(usize),
+    #[error("cross-origin redirect from {from} to {to} not allowed")]
+    CrossOrigin { from: Box<Url>, to: Box<Url> },
+}
+
+/// One redirect
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    pub from: Url,
+    pub to: Url,
+    /// The 3xx status of the response that redirected
+    pub status: u16,
+}
+
+/// The redirects a fetch went through, in order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RedirectChain {
+    pub hops: Vec<Redirect>,
+}
+
+impl RedirectChain {
+    pub fn is_empty(&self) -> bool {
+        self.hops.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.hops.len()
+    }
+
+    /// The URL the final response came from, if there were redirects at all.
+    pub fn final_url(&self) -> Option<&Url> {
+        self.hops.last().map(|hop| &hop.to)
+    }
+
+    /// Every URL the fetch visited, the requested one first.
+    pub fn urls(&self) -> impl Iterator<Item = &Url> {
+        self.hops
+            .first()
+            .map(|hop| &hop.from)
+            .into_iter()
+            .chain(self.hops.iter().map(|hop| &hop.to))
+    }
+}
+
+impl RedirectPolicy {
+    /// The policy the settings ask for.
+    pub fn from_config(cfg: &gosub_config::Config) -> Self {
+        let cross_origin = match cfg.get_string("net.http.cross_origin_redirects").as_str() {
+            "same-site" => CrossOriginRedirects::SameSite,
+            "block" => CrossOriginRedirects::Block,
+            _ => CrossOriginRedirects::Follow,
+        };
+        Self {
+            max_redirects: cfg.get_uint("net.http.max_redirects"),
+            cross_origin,
+        }
+    }
+
+    /// Whether `next` may be followed after the redirects in `chain`.
+    pub fn check(&self, chain: &RedirectChain, next: &Redirect) -> Result<(), RedirectError> {
+        if chain.len() >= self.max_redirects {
+            return Err(RedirectError::TooMany(self.max_redirects));
+        }
+        let allowed = match self.cross_origin {
+            CrossOriginRedirects::Follow => true,
+            CrossOriginRedirects::SameSite => next.from.origin() == next.to.origin() || same_site(&next.from, &next.to),
+            CrossOriginRedirects::Block => next.from.origin() == next.to.origin(),
+        };
+        if !allowed {
+            return Err(RedirectError::CrossOrigin {
+                from: Box::new(next.from.clone()),
+                to: Box::new(next.to.clone()),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The method a redirected request is sent again with. 307 and 308 keep it; the others turn a
+/// POST (and for 303 anything but HEAD) into a GET, which then has no body.
+pub fn method_after_redirect(method: &Method, status: u16) -> Method {
+    match status {
+        301 | 302 if *method == Method::POST => Method::GET,
+        303 if *method != Method::HEAD => Method::GET,
+        _ => method.clone(),
+    }
+}
+
+fn same_site(a: &Url, b: &Url) -> bool {
+    let site = |url: &Url| {
+        let host = url.host_str()?;
+        let domain = psl::domain_str(host).unwrap_or(host).to_string();
+        Some((url.scheme().to_string(), domain))
+    };
+    site(a).is_some() && site(a) == site(b)
+}
+
+/// What is known about a fetch's redirects
+#[derive(Default)]
+struct Tracked {
+    chain: RedirectChain,
+    /// Aborts the fetch; gone once it finished
+    cancel: Option<CancellationToken>,
+    error: Option<RedirectError>,
+}
+
+/// Redirects of the fetches in flight, and of finished ones until they're collected
+static REDIRECTS: LazyLock<DashMap<RequestId, Tracked>> = LazyLock::new(DashMap::new);
+
+/// Starts following the redirects of fetch `req_id`, which `cancel` aborts.
+pub(crate) fn watch(req_id: RequestId, cancel: CancellationToken) {
+    REDIRECTS.entry(req_id).or_default().cancel = Some(cancel);
+}
+
+/// The redirects fetch `req_id` went through. Collecting them forgets them.
+pub fn take_redirect_chain(req_id: RequestId) -> RedirectChain {
+    REDIRECTS
+        .remove(&req_id)
+        .map(|(_, tracked)| tracked.chain)
+        .unwrap_or_default()
+}
+
+/// The policy error fetch `req_id` was aborted with, if it was.
+pub(crate) fn redirect_error(req_id: RequestId) -> Option<RedirectError> {
+    REDIRECTS.get(&req_id).and_then(|tracked| tracked.error.clone())
+}
+
+/// Records a redirect of fetch `req_id`, aborting the fetch if `policy` doesn't allow it.
+fn record(req_id: RequestId, policy: &RedirectPolicy, redirect: Redirect) {
+    let mut tracked = REDIRECTS.entry(req_id).or_default();
+    if tracked.error.is_some() {
+        return;
+    }
+    match policy.check(&tracked.chain, &redirect) {
+        Ok(()) => tracked.chain.hops.push(redirect),
+        Err(e) => {
+            log::debug!("Aborting request {req_id:?}: {e}");
+            tracked.error = Some(e);
+            if let Some(cancel) = &tracked.cancel {
+                cancel.cancel();
+            }
+        }
+    }
+}
+
+/// The fetch is over: a fetch without redirects has nothing left to collect.
+fn finish(req_id: RequestId) {
+    REDIRECTS.remove_if(&req_id, |_, tracked| {
+        tracked.chain.is_empty() && tracked.error.is_none()
+    });
+    if let Some(mut tracked) = REDIRECTS.get_mut(&req_id) {
+        tracked.cancel = None;
+    }
+}
+
+/// Watches the events of one fetch for redirects, passing them all on to `inner`.
+pub(crate) struct RedirectObserver {
+    req_id: RequestId,
+    policy: RedirectPolicy,
+    inner: Arc<dyn NetObserver + Send + Sync>,
+}
+
+impl RedirectObserver {
+    pub fn new(req_id: RequestId, policy: RedirectPolicy, inner: Arc<dyn NetObserver + Send + Sync>) -> Self {
+        Self { req_id, policy, inner }
+    }
+}
+
+impl NetObserver for RedirectObserver {
+    fn on_event(&self, ev: NetEvent) {
+        match &ev {
+            NetEvent::Redirected { from, to, status } => {
+                let redirect = Redirect {
+                    from: from.clone(),
+                    to: to.clone(),
+                    status: *status,
+                };
+                record(self.req_id, &self.policy, redirect);
+            }
+            NetEvent::Finished { .. } | NetEvent::Failed { .. } | NetEvent::Cancelled { .. } => finish(self.req_id),
+            _ => {}
+        }
+        self.inner.on_event(ev);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hop(from: &str, to: &str) -> Redirect {
+        Redirect {
+            from: Url::parse(from).unwrap(),
+            to: Url::parse(to).unwrap(),
+            status: 302,
+        }
+    }
+
+    #[test]
+    fn policy_limits_and_origins() {
+        let policy = RedirectPolicy {
+            max_redirects: 2,
+            cross_origin: CrossOriginRedirects::SameSite,
+        };
+        let mut chain = RedirectChain::default();
+        for next in [
+            hop("http://example.org/", "https://example.org/"),
+            hop("https://example.org/", "https://www.example.org/login"),
+        ] {
+            // http to https is another origin, and another site.
+            if next.from.scheme() == "http" {
+                assert!(policy.check(&chain, &next).is_err());
+                continue;
+            }
+            policy.check(&chain, &next).unwrap();
+            chain.hops.push(next);
+        }
+        assert_eq!(
+            policy.check(&chain, &hop("https://www.example.org/login", "https://evil.example/")),
+            Err(RedirectError::CrossOrigin {
+                from: Box::new(Url::parse("https://www.example.org/login").unwrap()),
+                to: Box::new(Url::parse("https://evil.example/").unwrap()),
+            })
+        );
+
+        let chain = RedirectChain {
+            hops: vec![
+                hop("https://a.example/", "https://a.example/1"),
+                hop("https://a.example/1", "https://a.example/2"),
+            ],
+        };
+        assert_eq!(
+            policy.check(&chain, &hop("https://a.example/2", "https://a.example/3")),
+            Err(RedirectError::TooMany(2))
+        );
+        assert_eq!(chain.final_url().unwrap().as_str(), "https://a.example/2");
+        let urls: Vec<_> = chain.urls().map(Url::as_str).collect();
+        assert_eq!(
+            urls,
+            ["https://a.example/", "https://a.example/1", "https://a.example/2"]
+        );
+    }
+
+    #[test]
+    fn redirected_methods() {
+        assert_eq!(method_after_redirect(&Method::POST, 302), Method::GET);
+        assert_eq!(method_after_redirect(&Method::POST, 307), Method::POST);
+        assert_eq!(method_after_redirect(&Method::PUT, 308), Method::PUT);
+        assert_eq!(method_after_redirect(&Method::PUT, 301), Method::PUT);
+        assert_eq!(method_after_redirect(&Method::DELETE, 303), Method::GET);
+        assert_eq!(method_after_redirect(&Method::HEAD, 303), Method::HEAD);
+    }
+
+    #[test]
+    fn aborts_fetches_breaking_the_policy() {
+        let policy = RedirectPolicy {
+            max_redirects: 1,
+            cross_origin: CrossOriginRedirects::Follow,
+        };
+        let req_id = RequestId::new();
+        let cancel = CancellationToken::new();
+        watch(req_id, cancel.clone());
+        record(req_id, &policy, hop("https://a.example/", "https://b.example/"));
+        assert!(!cancel.is_cancelled());
+        record(req_id, &policy, hop("https://b.example/", "https://c.example/"));
+        assert!(cancel.is_cancelled());
+        assert_eq!(redirect_error(req_id), Some(RedirectError::TooMany(1)));
+        assert_eq!(take_redirect_chain(req_id).len(), 1);
+        assert!(take_redirect_chain(req_id).is_empty());
+    }
+}
@@ -131,6 +131,8 @@ pub async fn route_response_for<C: RenderConfiguration>(
             Ok(RoutedOutcome::FontLoaded(font))
         }
 
+        (_, HandlingDecision::Block(reason), _) => Ok(RoutedOutcome::Blocked(reason)),
+
         // Safety net: any other subresource decision (Download, or a Render target that
         // doesn't match the destination) is treated as a policy block.
         (_, HandlingDecision::Download { .. } | HandlingDecision::Render(_), _) => {
@@ -0,0 +1,247 @@
+//! Reference (pixel) tests.
+//!
+//! A reftest renders a test page and a reference page that builds the same picture some simpler
+//! way, and compares the two pixel by pixel. Tests are listed in a `reftest.list` manifest (see
+//! [`read_manifest`] for the format), rendered by a [`ReftestRunner`] without a window and
+//! compared with a perceptual [`Diff`], tolerating the differences their `fuzzy()` annotation
+//! allows.
+//!
+//! The runner renders with the render configuration it's given, so its backend has to
+//! rasterize on the CPU (Skia or Cairo); the null backend produces no pixels. Pages are parsed
+//! and rendered without networking: inline `<style>` works, external stylesheets, images and
+//! scripts aren't loaded.
+//!
+//! Set `GOSUB_REFTEST_OUTPUT` to a directory to get the test, reference and diff images of every
+//! failing test written there as PNGs.
+//!
+//! The engine's own reftests live in `tests/reftests` at the top of the repository and run as
+//! part of `cargo test`.
+
+mod diff;
+mod manifest;
+
+pub use diff::{Diff, Fuzz};
+pub use manifest::{parse_manifest, read_manifest, ManifestError, Reftest, Relation};
+
+use crate::engine::{default_settings, BrowsingContext, EngineError, FullPagePixmap};
+use crate::html::{EngineDocument, RenderConfiguration};
+use gosub_config::Config;
+use gosub_html5::document::builder::DocumentBuilderImpl;
+use gosub_html5::parser::Html5Parser;
+use gosub_interface::css3::CssSystem;
+use gosub_interface::document::Document as _;
+use gosub_interface::render::backend::RenderBackend;
+use gosub_render_pipeline::rasterizer::downcast_rasterizer;
+use gosub_render_pipeline::render::Viewport;
+use gosub_shared::byte_stream::{ByteStream, Encoding};
+use image::ColorType;
+use parking_lot::Mutex;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use url::Url;
+
+/// Environment variable naming the directory to write the images of failing tests to
+const OUTPUT_DIR_ENV: &str = "GOSUB_REFTEST_OUTPUT";
+
+/// Viewport tests are rendered at, the size WPT and Gecko use
+const DEFAULT_VIEWPORT: (u32, u32) = (800, 600);
+
+/// Errors from rendering a page for a reftest
+#[derive(Debug, thiserror::Error)]
+pub enum ReftestError {
+    #[error("cannot read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("the render backend doesn't rasterize on the CPU")]
+    NoPixels,
+
+    #[error("cannot capture the page: {0}")]
+    Capture(#[from] EngineError),
+}
+
+/// How a reftest went
+#[derive(Debug)]
+pub enum Outcome {
+    Pass,
+    /// The renderings matched when they shouldn't have, or the other way around
+    Fail(Diff),
+    /// One of the pages couldn't be rendered
+    Error(ReftestError),
+}
+
+/// A reftest and how it went
+#[derive(Debug)]
+pub struct ReftestResult {
+    pub reftest: Reftest,
+    pub outcome: Outcome,
+}
+
+impl ReftestResult {
+    pub fn passed(&self) -> bool {
+        matches!(self.outcome, Outcome::Pass)
+    }
+}
+
+impl Display for ReftestResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let relation = match self.reftest.relation {
+            Relation::Match => "==",
+            Relation::Mismatch => "!=",
+        };
+        let status = match &self.outcome {
+            Outcome::Pass => "PASS".to_string(),
+            Outcome::Fail(diff) if diff.size_mismatch => "FAIL (sizes differ)".to_string(),
+            Outcome::Fail(diff) => format!(
+                "FAIL ({} pixels differ, by up to {})",
+                diff.differing_pixels, diff.max_difference
+            ),
+            Outcome::Error(e) => format!("ERROR ({e})"),
+        };
+        write!(
+            f,
+            "{status}: {} {relation} {}",
+            self.reftest.test.display(),
+            self.reftest.reference.display()
+        )
+    }
+}
+
+/// Renders pages without a window and runs reftests on them.
+pub struct ReftestRunner<C: RenderConfiguration> {
+    backend: Arc<C::RenderBackend>,
+    font_system: Arc<Mutex<C::FontSystem>>,
+    config: Config,
+    viewport: (u32, u32),
+    output_dir: Option<PathBuf>,
+}
+
+impl<C: RenderConfiguration> ReftestRunner<C> {
+    /// A runner rendering with `backend` in an 800×600 viewport.
+    pub fn new(backend: C::RenderBackend) -> Self {
+        Self {
+            backend: Arc::new(backend),
+            font_system: Arc::new(Mutex::new(C::FontSystem::default())),
+            config: default_settings(),
+            viewport: DEFAULT_VIEWPORT,
+            output_dir: std::env::var_os(OUTPUT_DIR_ENV).map(PathBuf::from),
+        }
+    }
+
+    /// Renders in a `width` × `height` viewport instead.
+    pub fn with_viewport(mut self, width: u32, height: u32) -> Self {
+        self.viewport = (width, height);
+        self
+    }
+
+    /// Renders `html` as a page at `url`, if it has one. The pixmap covers the full page, the
+    /// viewport width by the document height, at the backend's device pixel ratio.
+    pub fn render_html(&self, html: &str, url: Option<Url>) -> Result<FullPagePixmap, ReftestError> {
+        let mut stream = ByteStream::new(Encoding::UTF8, None);
+        stream.read_from_str(html, Some(Encoding::UTF8));
+        stream.close();
+        let mut doc: EngineDocument<C> = DocumentBuilderImpl::new_document::<C>(url);
+        let _ = Html5Parser::<C>::parse_document(&mut stream, &mut doc, None);
+        doc.add_stylesheet(<C::CssSystem as CssSystem>::load_default_useragent_stylesheet());
+
+        let mut context = BrowsingContext::<C>::new(self.config.clone());
+        let rasterizer = downcast_rasterizer(self.backend.create_rasterizer(self.font_system.clone()))
+            .ok_or(ReftestError::NoPixels)?;
+        context.set_rasterizer(rasterizer, self.backend.raster_strategy());
+        context.set_viewport(Viewport::new(0, 0, self.viewport.0, self.viewport.1));
+        context.set_document(Arc::new(doc));
+
+        context
+            .capture_full_page(self.backend.device_pixel_ratio())?
+            .and_then(|capture| capture.pixels)
+            .ok_or(ReftestError::NoPixels)
+    }
+
+    /// Renders the page at `path`.
+    pub fn render_file(&self, path: &Path) -> Result<FullPagePixmap, ReftestError> {
+        let io_error = |source| ReftestError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        let html = std::fs::read_to_string(path).map_err(io_error)?;
+        let absolute = std::path::absolute(path).map_err(io_error)?;
+        self.render_html(&html, Url::from_file_path(absolute).ok())
+    }
+
+    /// Renders a test and its reference and compares them.
+    pub fn run(&self, reftest: &Reftest) -> ReftestResult {
+        let outcome = match self.render_file(&reftest.test).and_then(|test| {
+            let reference = self.render_file(&reftest.reference)?;
+            Ok((test, reference))
+        }) {
+            Err(e) => Outcome::Error(e),
+            Ok((test, reference)) => {
+                let diff = Diff::compare(&test, &reference);
+                let matches = diff.within(reftest.fuzz);
+                if matches == (reftest.relation == Relation::Match) {
+                    Outcome::Pass
+                } else {
+                    self.write_failure(reftest, &test, &reference, &diff);
+                    Outcome::Fail(diff)
+                }
+            }
+        };
+        ReftestResult {
+            reftest: reftest.clone(),
+            outcome,
+        }
+    }
+
+    /// Runs every test of the manifest at `path`.
+    pub fn run_manifest(&self, path: &Path) -> Result<Vec<ReftestResult>, ManifestError> {
+        Ok(read_manifest(path)?.iter().map(|reftest| self.run(reftest)).collect())
+    }
+
+    /// Writes the renderings of a failing test, and their diff, to the output directory if
+    /// there is one.
+    fn write_failure(&self, reftest: &Reftest, test: &FullPagePixmap, reference: &FullPagePixmap, diff: &Diff) {
+        let Some(dir) = &self.output_dir else {
+            return;
+        };
+        let stem = reftest.test.file_stem().unwrap_or_default().to_string_lossy();
+        let images = [("test", test), ("ref", reference), ("diff", &diff.image(test))];
+        for (suffix, pixmap) in images {
+            let path = dir.join(format!("{stem}-{suffix}.png"));
+            let saved = std::fs::create_dir_all(dir)
+                .map_err(image::ImageError::IoError)
+                .and_then(|()| image::save_buffer(&path, &pixmap.rgba, pixmap.width, pixmap.height, ColorType::Rgba8));
+            if let Err(e) = saved {
+                log::warn!("Cannot write {}: {e}", path.display());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::DefaultRenderConfig;
+    use gosub_renderer_skia::{SkiaBackend, SkiaFontSystem};
+
+    #[test]
+    fn engine_reftests() {
+        let manifest = Path::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../tests/reftests/reftest.list"
+        ));
+        let runner = ReftestRunner::<DefaultRenderConfig<SkiaBackend, SkiaFontSystem>>::new(SkiaBackend::new());
+        let results = runner.run_manifest(manifest).unwrap();
+        assert!(!results.is_empty());
+
+        let failures: Vec<_> = results
+            .iter()
+            .filter(|r| !r.passed())
+            .map(ToString::to_string)
+            .collect();
+        assert!(failures.is_empty(), "failing reftests:\n{}", failures.join("\n"));
+    }
+}
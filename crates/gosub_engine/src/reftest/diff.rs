@@ -0,0 +1,197 @@
+//! Perceptual comparison of two renderings.
+
+use crate::engine::FullPagePixmap;
+
+/// Largest possible YIQ distance between two colors, used to scale distances to `0..=255`
+const MAX_YIQ_DELTA: f64 = 35215.0;
+
+/// How much a rendering may differ from its reference and still match, like WPT's and Gecko's
+/// `fuzzy()` annotations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Fuzz {
+    /// Largest perceptual difference allowed in any one pixel (`0..=255`)
+    pub max_difference: u8,
+    /// Number of pixels allowed to differ at all
+    pub max_pixels: usize,
+}
+
+/// How two renderings differ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diff {
+    /// Size of the rendering that was compared
+    pub width: u32,
+    pub height: u32,
+    /// Whether the two renderings have different sizes. The overlapping area is still compared;
+    /// the rest counts as differing.
+    pub size_mismatch: bool,
+    /// Pixels whose perceptual difference is above zero
+    pub differing_pixels: usize,
+    /// The largest perceptual difference of any pixel (`0..=255`)
+    pub max_difference: u8,
+    /// Perceptual difference per pixel, row by row, over `width` × `height`
+    differences: Vec<u8>,
+}
+
+impl Diff {
+    /// Compares `actual` against `expected` pixel by pixel.
+    ///
+    /// Pixels are compared as they'd show on a white page, by their distance in YIQ space: it
+    /// weighs brightness above hue the way the eye does, so the slight color shifts of
+    /// antialiasing count for less than a glyph that moved.
+    pub fn compare(actual: &FullPagePixmap, expected: &FullPagePixmap) -> Self {
+        let width = actual.width.max(expected.width);
+        let height = actual.height.max(expected.height);
+        let mut differences = vec![0u8; width as usize * height as usize];
+        let mut differing_pixels = 0;
+        let mut max_difference = 0;
+
+        for y in 0..height {
+            for x in 0..width {
+                let difference = match (pixel(actual, x, y), pixel(expected, x, y)) {
+                    (Some(a), Some(b)) => perceptual_difference(a, b),
+                    (None, None) => 0,
+                    _ => u8::MAX,
+                };
+                if difference > 0 {
+                    differing_pixels += 1;
+                    max_difference = max_difference.max(difference);
+                    differences[(y * width + x) as usize] = difference;
+                }
+            }
+        }
+
+        Diff {
+            width,
+            height,
+            size_mismatch: actual.width != expected.width || actual.height != expected.height,
+            differing_pixels,
+            max_difference,
+            differences,
+        }
+    }
+
+    /// Whether the renderings are the same, give or take `fuzz`. Renderings of different sizes
+    /// never are.
+    pub fn within(&self, fuzz: Fuzz) -> bool {
+        !self.size_mismatch
+            && (self.differing_pixels == 0
+                || (self.max_difference <= fuzz.max_difference && self.differing_pixels <= fuzz.max_pixels))
+    }
+
+    /// An RGBA image of the differences: differing pixels in red, brighter the more they differ,
+    /// over a faded copy of `actual`.
+    pub fn image(&self, actual: &FullPagePixmap) -> FullPagePixmap {
+        let mut rgba = Vec::with_capacity(self.differences.len() * 4);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let difference = self.differences[(y * self.width + x) as usize];
+                if difference > 0 {
+                    rgba.extend_from_slice(&[128 + difference / 2, 0, 0, 255]);
+                } else {
+                    let gray = pixel(actual, x, y).map_or(255.0, |p| luma(on_white(p)));
+                    let faded = (255.0 - (255.0 - gray) * 0.25).round() as u8;
+                    rgba.extend_from_slice(&[faded, faded, faded, 255]);
+                }
+            }
+        }
+        FullPagePixmap {
+            width: self.width,
+            height: self.height,
+            rgba,
+        }
+    }
+}
+
+fn pixel(pixmap: &FullPagePixmap, x: u32, y: u32) -> Option<[u8; 4]> {
+    if x >= pixmap.width || y >= pixmap.height {
+        return None;
+    }
+    let offset = (y as usize * pixmap.width as usize + x as usize) * 4;
+    let p = pixmap.rgba.get(offset..offset + 4)?;
+    Some([p[0], p[1], p[2], p[3]])
+}
+
+/// The color a (non-premultiplied) pixel shows as on a white background
+fn on_white([r, g, b, a]: [u8; 4]) -> [f64; 3] {
+    let alpha = f64::from(a) / 255.0;
+    let blend = |c: u8| 255.0 + (f64::from(c) - 255.0) * alpha;
+    [blend(r), blend(g), blend(b)]
+}
+
+fn luma([r, g, b]: [f64; 3]) -> f64 {
+    r * 0.298_895_31 + g * 0.586_622_47 + b * 0.114_482_23
+}
+
+/// Distance between two pixels in YIQ space, scaled to `0..=255`
+fn perceptual_difference(a: [u8; 4], b: [u8; 4]) -> u8 {
+    if a == b {
+        return 0;
+    }
+    let (a, b) = (on_white(a), on_white(b));
+    let in_phase = |[r, g, b]: [f64; 3]| r * 0.595_977_99 - g * 0.274_176_10 - b * 0.321_801_89;
+    let quadrature = |[r, g, b]: [f64; 3]| r * 0.211_470_17 - g * 0.522_617_11 + b * 0.311_146_94;
+
+    let dy = luma(a) - luma(b);
+    let di = in_phase(a) - in_phase(b);
+    let dq = quadrature(a) - quadrature(b);
+    let delta = 0.5053 * dy * dy + 0.299 * di * di + 0.1957 * dq * dq;
+    // Rounding down would call visibly different pixels identical.
+    ((delta / MAX_YIQ_DELTA).sqrt() * 255.0).ceil().min(255.0) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filled(width: u32, height: u32, color: [u8; 4]) -> FullPagePixmap {
+        FullPagePixmap {
+            width,
+            height,
+            rgba: color.repeat((width * height) as usize),
+        }
+    }
+
+    #[test]
+    fn fuzz_tolerates_small_differences() {
+        let expected = filled(4, 4, [255, 255, 255, 255]);
+        let mut actual = filled(4, 4, [255, 255, 255, 255]);
+        assert!(Diff::compare(&actual, &expected).within(Fuzz::default()));
+
+        // An antialiasing-like nudge on two pixels
+        actual.rgba[0..4].copy_from_slice(&[250, 250, 250, 255]);
+        actual.rgba[4..8].copy_from_slice(&[252, 252, 252, 255]);
+        let diff = Diff::compare(&actual, &expected);
+        assert_eq!(diff.differing_pixels, 2);
+        assert!(diff.max_difference < 10);
+        assert!(!diff.within(Fuzz::default()));
+        assert!(diff.within(Fuzz {
+            max_difference: 10,
+            max_pixels: 2
+        }));
+        assert!(!diff.within(Fuzz {
+            max_difference: 10,
+            max_pixels: 1
+        }));
+
+        // Black on white is about as different as it gets; a transparent pixel shows as white.
+        actual.rgba[8..12].copy_from_slice(&[0, 0, 0, 255]);
+        actual.rgba[12..16].copy_from_slice(&[0, 0, 0, 0]);
+        let diff = Diff::compare(&actual, &expected);
+        assert_eq!(diff.differing_pixels, 3);
+        assert!(diff.max_difference > 240);
+        let image = diff.image(&actual);
+        assert_eq!(&image.rgba[9..12], &[0, 0, 255]);
+        assert_eq!(&image.rgba[12..16], &[255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn different_sizes_never_match() {
+        let diff = Diff::compare(&filled(4, 4, [0, 0, 0, 255]), &filled(4, 5, [0, 0, 0, 255]));
+        assert!(diff.size_mismatch);
+        assert_eq!(diff.differing_pixels, 4);
+        assert!(!diff.within(Fuzz {
+            max_difference: 255,
+            max_pixels: usize::MAX
+        }));
+    }
+}
@@ -0,0 +1,138 @@
+//! `reftest.list` manifests.
+//!
+//! The format is Gecko's, trimmed to what the harness supports. Each line names a test, how it
+//! relates to its reference, and optionally how much the two may differ:
+//!
+//! ```text
+//! # Comments run to the end of the line
+//! == box-margin.html box-margin-ref.html
+//! != red.html green.html
+//! fuzzy(0-2,0-40) == text-shadow.html text-shadow-ref.html
+//! ```
+//!
+//! `fuzzy(a-b,c-d)` allows up to `b` perceptual difference per pixel over up to `d` pixels; the
+//! lower bounds are accepted but not enforced. Paths are relative to the manifest.
+
+use super::diff::Fuzz;
+use std::path::{Path, PathBuf};
+
+/// What a test's rendering should be to its reference's
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    /// `==`: the renderings are the same, give or take the fuzz
+    Match,
+    /// `!=`: the renderings differ by more than the fuzz
+    Mismatch,
+}
+
+/// One test of a manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reftest {
+    pub test: PathBuf,
+    pub reference: PathBuf,
+    pub relation: Relation,
+    pub fuzz: Fuzz,
+}
+
+/// Errors from reading a manifest
+#[derive(Debug, thiserror::Error)]
+pub enum ManifestError {
+    #[error("cannot read manifest: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("line {line}: {message}")]
+    Syntax { line: usize, message: String },
+}
+
+/// Reads the manifest at `path`.
+pub fn read_manifest(path: &Path) -> Result<Vec<Reftest>, ManifestError> {
+    let source = std::fs::read_to_string(path)?;
+    parse_manifest(&source, path.parent().unwrap_or(Path::new("")))
+}
+
+/// Parses a manifest's `source`, resolving the paths in it against `base`.
+pub fn parse_manifest(source: &str, base: &Path) -> Result<Vec<Reftest>, ManifestError> {
+    let mut tests = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        let error = |message: String| ManifestError::Syntax {
+            line: index + 1,
+            message,
+        };
+        let line = line.split('#').next().unwrap_or_default();
+        let mut words = line.split_whitespace().peekable();
+        if words.peek().is_none() {
+            continue;
+        }
+
+        let mut fuzz = Fuzz::default();
+        if let Some(annotation) = words.next_if(|word| word.starts_with("fuzzy(")) {
+            fuzz = parse_fuzzy(annotation).ok_or_else(|| error(format!("invalid annotation `{annotation}`")))?;
+        }
+        let relation = match words.next() {
+            Some("==") => Relation::Match,
+            Some("!=") => Relation::Mismatch,
+            Some(other) => return Err(error(format!("expected `==` or `!=`, found `{other}`"))),
+            None => return Err(error("missing `==` or `!=`".into())),
+        };
+        let (Some(test), Some(reference), None) = (words.next(), words.next(), words.next()) else {
+            return Err(error("expected a test and a reference".into()));
+        };
+
+        tests.push(Reftest {
+            test: base.join(test),
+            reference: base.join(reference),
+            relation,
+            fuzz,
+        });
+    }
+    Ok(tests)
+}
+
+/// Parses `fuzzy(0-2,0-40)`, or `fuzzy(2,40)` for ranges starting at zero.
+fn parse_fuzzy(annotation: &str) -> Option<Fuzz> {
+    let args = annotation.strip_prefix("fuzzy(")?.strip_suffix(')')?;
+    let (difference, pixels) = args.split_once(',')?;
+    fn upper<T: std::str::FromStr>(range: &str) -> Option<T> {
+        let upper = range.rsplit('-').next().unwrap_or(range);
+        upper.trim().parse().ok()
+    }
+    Some(Fuzz {
+        max_difference: upper(difference)?,
+        max_pixels: upper(pixels)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tests_and_annotations() {
+        let source = "
+            # Boxes
+            == margin.html margin-ref.html   # same box either way
+            fuzzy(0-2,0-40) != text.html ../text-ref.html
+            fuzzy(3,10) == aa.html aa-ref.html
+        ";
+        let tests = parse_manifest(source, Path::new("reftests")).unwrap();
+        assert_eq!(tests.len(), 3);
+        assert_eq!(tests[0].test, Path::new("reftests/margin.html"));
+        assert_eq!(tests[0].relation, Relation::Match);
+        assert_eq!(tests[0].fuzz, Fuzz::default());
+        assert_eq!(tests[1].reference, Path::new("reftests/../text-ref.html"));
+        assert_eq!(tests[1].relation, Relation::Mismatch);
+        assert_eq!(
+            tests[1].fuzz,
+            Fuzz {
+                max_difference: 2,
+                max_pixels: 40
+            }
+        );
+        assert_eq!(tests[2].fuzz.max_difference, 3);
+
+        let err = parse_manifest("== a.html b.html\n\n<= a.html b.html", Path::new("")).unwrap_err();
+        assert_eq!(err.to_string(), "line 3: expected `==` or `!=`, found `<=`");
+        assert!(parse_manifest("fuzzy(0-300,1) == a.html b.html", Path::new("")).is_err());
+        assert!(parse_manifest("== a.html", Path::new("")).is_err());
+    }
+}
@@ -0,0 +1,369 @@
+//! Web Platform Tests.
+//!
+//! Runs [testharness.js] tests from a [WPT] checkout in a tab and collects their results, so
+//! conformance can be tracked over time. The tests are loaded the way any page is, so they have
+//! to be served by the checkout's `wpt serve` (at `http://web-platform.test:8000/` by default)
+//! and the tab needs a [`ScriptHost`](crate::engine::script::ScriptHost).
+//!
+//! The [`WptRunner`] injects a script into every page that registers a testharness.js completion
+//! callback, navigates to each test and polls the page until the callback has fired or the test
+//! timed out. Pages that don't load testharness.js never report and time out.
+//!
+//! Results are compared against [`Expectations`], which are kept in a file in the format of
+//! wptrunner's metadata files: only tests and subtests that don't pass are listed, so the file
+//! shrinks as the engine gets better.
+//!
+//! Reftests are run by the [`reftest`](crate::reftest) harness instead.
+//!
+//! [testharness.js]: https://web-platform-tests.org/writing-tests/testharness.html
+//! [WPT]: https://github.com/web-platform-tests/wpt
+
+mod expectations;
+
+pub use expectations::{Expectations, ExpectationsError, Unexpected};
+
+use crate::engine::script::EvaluationId;
+use crate::engine::user_content::{RunAt, UrlFilter, UserContentId, UserScript};
+use crate::events::EngineEvent;
+use crate::tab::TabHandle;
+use crate::EngineError;
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use url::Url;
+
+/// How long a test may take before the runner gives up on it, testharness.js's "normal" timeout
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often the page is asked whether the test has finished
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Global the injected script stores the results in
+const RESULTS_GLOBAL: &str = "__gosubWptResults";
+
+/// Registers the completion callback. Runs at document-end, when the test's own scripts (and
+/// with them testharness.js) have run but the tests, which wait for the load event, haven't
+/// finished yet.
+const REPORT_SCRIPT: &str = r#"(() => {
+    if (typeof add_completion_callback !== "function") return;
+    add_completion_callback((tests, status) => {
+        globalThis.__gosubWptResults = {
+            url: location.href,
+            status: status.status,
+            message: status.message,
+            tests: tests.map(t => ({ name: t.name, status: t.status, message: t.message })),
+        };
+    });
+})();"#;
+
+/// How a test file as a whole went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HarnessStatus {
+    Ok,
+    /// The harness hit an error outside of any subtest
+    Error,
+    /// The test didn't finish in time
+    Timeout,
+    PreconditionFailed,
+}
+
+impl HarnessStatus {
+    /// testharness.js's name for the status
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HarnessStatus::Ok => "OK",
+            HarnessStatus::Error => "ERROR",
+            HarnessStatus::Timeout => "TIMEOUT",
+            HarnessStatus::PreconditionFailed => "PRECONDITION_FAILED",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        [Self::Ok, Self::Error, Self::Timeout, Self::PreconditionFailed]
+            .into_iter()
+            .find(|status| status.as_str() == name)
+    }
+
+    /// The status for testharness.js's numeric status code
+    fn from_code(code: u8) -> Self {
+        match code {
+            0 => HarnessStatus::Ok,
+            2 => HarnessStatus::Timeout,
+            3 => HarnessStatus::PreconditionFailed,
+            _ => HarnessStatus::Error,
+        }
+    }
+}
+
+/// How a single subtest of a test file went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtestStatus {
+    Pass,
+    Fail,
+    Timeout,
+    /// The subtest never got to run
+    NotRun,
+    PreconditionFailed,
+}
+
+impl SubtestStatus {
+    /// testharness.js's name for the status
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SubtestStatus::Pass => "PASS",
+            SubtestStatus::Fail => "FAIL",
+            SubtestStatus::Timeout => "TIMEOUT",
+            SubtestStatus::NotRun => "NOTRUN",
+            SubtestStatus::PreconditionFailed => "PRECONDITION_FAILED",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        [
+            Self::Pass,
+            Self::Fail,
+            Self::Timeout,
+            Self::NotRun,
+            Self::PreconditionFailed,
+        ]
+        .into_iter()
+        .find(|status| status.as_str() == name)
+    }
+
+    /// The status for testharness.js's numeric status code
+    fn from_code(code: u8) -> Self {
+        match code {
+            0 => SubtestStatus::Pass,
+            2 => SubtestStatus::Timeout,
+            3 => SubtestStatus::NotRun,
+            4 => SubtestStatus::PreconditionFailed,
+            _ => SubtestStatus::Fail,
+        }
+    }
+}
+
+/// The results of one test file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestResult {
+    /// Path of the test in the WPT checkout, like `/dom/nodes/Node-appendChild.html`
+    pub test: String,
+    pub status: HarnessStatus,
+    pub message: Option<String>,
+    pub subtests: Vec<SubtestResult>,
+}
+
+/// The result of one subtest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubtestResult {
+    pub name: String,
+    pub status: SubtestStatus,
+    pub message: Option<String>,
+}
+
+/// What the injected script stores
+#[derive(Deserialize)]
+struct Report {
+    url: String,
+    status: u8,
+    message: Option<String>,
+    tests: Vec<ReportedSubtest>,
+}
+
+#[derive(Deserialize)]
+struct ReportedSubtest {
+    name: String,
+    status: u8,
+    message: Option<String>,
+}
+
+impl TestResult {
+    fn from_report(test: &str, report: Report) -> Self {
+        TestResult {
+            test: test.to_string(),
+            status: HarnessStatus::from_code(report.status),
+            message: report.message,
+            subtests: report
+                .tests
+                .into_iter()
+                .map(|t| SubtestResult {
+                    name: t.name,
+                    status: SubtestStatus::from_code(t.status),
+                    message: t.message,
+                })
+                .collect(),
+        }
+    }
+
+    fn timed_out(test: &str, after: Duration) -> Self {
+        TestResult {
+            test: test.to_string(),
+            status: HarnessStatus::Timeout,
+            message: Some(format!("no results after {}s", after.as_secs_f32())),
+            subtests: Vec::new(),
+        }
+    }
+}
+
+/// Pass counts over a set of results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Summary {
+    pub tests: usize,
+    /// Test files whose harness finished with OK
+    pub tests_ok: usize,
+    pub subtests: usize,
+    pub subtests_passed: usize,
+}
+
+impl Summary {
+    pub fn of(results: &[TestResult]) -> Self {
+        let mut summary = Summary::default();
+        for result in results {
+            summary.tests += 1;
+            summary.tests_ok += usize::from(result.status == HarnessStatus::Ok);
+            summary.subtests += result.subtests.len();
+            summary.subtests_passed += result
+                .subtests
+                .iter()
+                .filter(|s| s.status == SubtestStatus::Pass)
+                .count();
+        }
+        summary
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}/{} tests OK, {}/{} subtests pass",
+            self.tests_ok, self.tests, self.subtests_passed, self.subtests
+        )
+    }
+}
+
+/// Runs testharness.js tests in a tab, one after the other.
+pub struct WptRunner {
+    tab: TabHandle,
+    events: broadcast::Receiver<EngineEvent>,
+    base: Url,
+    timeout: Duration,
+    report_script: UserContentId,
+}
+
+impl WptRunner {
+    /// A runner loading tests from the `wpt serve` at `base` in `tab`, which must have scripting
+    /// enabled. `events` are the engine's events, from `GosubEngine::subscribe_events`.
+    pub async fn new(tab: TabHandle, events: broadcast::Receiver<EngineEvent>, base: Url) -> Result<Self, EngineError> {
+        let report_script = UserContentId::new();
+        tab.add_user_script(UserScript {
+            id: report_script,
+            source: REPORT_SCRIPT.to_string(),
+            filter: UrlFilter::new(&["<all_urls>"], &[])?,
+            run_at: RunAt::DocumentEnd,
+        })
+        .await?;
+        Ok(Self {
+            tab,
+            events,
+            base,
+            timeout: DEFAULT_TIMEOUT,
+            report_script,
+        })
+    }
+
+    /// Gives up on tests after `timeout` instead of 10 seconds.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Runs the test at `path` in the checkout, like `/dom/nodes/Node-appendChild.html`.
+    pub async fn run_test(&mut self, path: &str) -> Result<TestResult, EngineError> {
+        let url = self
+            .base
+            .join(path)
+            .map_err(|e| EngineError::InvalidUrl(e.to_string()))?;
+        self.tab.navigate(url.as_str()).await?;
+
+        let started = Instant::now();
+        let query = format!("globalThis.{RESULTS_GLOBAL} ?? null");
+        while started.elapsed() < self.timeout {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let id = self.tab.evaluate_script(query.as_str()).await?;
+            let Some(value) = self.evaluation(id).await? else {
+                continue;
+            };
+            // Until the test has committed, this is still the previous page.
+            match serde_json::from_value::<Report>(value) {
+                Ok(report) if report.url == url.as_str() => return Ok(TestResult::from_report(path, report)),
+                _ => continue,
+            }
+        }
+        Ok(TestResult::timed_out(path, self.timeout))
+    }
+
+    /// Runs the tests at `paths`, logging a line per test.
+    pub async fn run_all<S: AsRef<str>>(&mut self, paths: &[S]) -> Result<Vec<TestResult>, EngineError> {
+        let mut results = Vec::with_capacity(paths.len());
+        for path in paths {
+            let result = self.run_test(path.as_ref()).await?;
+            log::info!("{} {}", result.status.as_str(), result.test);
+            results.push(result);
+        }
+        Ok(results)
+    }
+
+    /// Stops injecting the report script into the tab's pages.
+    pub async fn finish(self) -> Result<(), EngineError> {
+        self.tab.remove_user_content(self.report_script).await
+    }
+
+    /// Waits for the result of evaluation `id`. Scripts that fail, like ones evaluated before
+    /// the page has a JS context, produce `None`.
+    async fn evaluation(&mut self, id: EvaluationId) -> Result<Option<serde_json::Value>, EngineError> {
+        loop {
+            match self.events.recv().await {
+                Ok(EngineEvent::ScriptEvaluated {
+                    tab_id,
+                    id: evaluated,
+                    result,
+                }) if tab_id == self.tab.tab_id && evaluated == id => {
+                    return Ok(result.ok().filter(|value| !value.is_null()));
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return Err(EngineError::ChannelClosed),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_testharness_reports() {
+        let report = serde_json::json!({
+            "url": "http://web-platform.test:8000/dom/nodes/Node-appendChild.html",
+            "status": 0,
+            "message": null,
+            "tests": [
+                { "name": "Appending to a leaf node", "status": 0, "message": null },
+                { "name": "Appending a document", "status": 1, "message": "assert_throws_dom: function did not throw" },
+                { "name": "Appending a doctype", "status": 3, "message": null },
+            ],
+        });
+        let report: Report = serde_json::from_value(report).unwrap();
+        let result = TestResult::from_report("/dom/nodes/Node-appendChild.html", report);
+        assert_eq!(result.status, HarnessStatus::Ok);
+        let statuses: Vec<_> = result.subtests.iter().map(|s| s.status).collect();
+        assert_eq!(
+            statuses,
+            [SubtestStatus::Pass, SubtestStatus::Fail, SubtestStatus::NotRun]
+        );
+
+        let summary = Summary::of(&[result, TestResult::timed_out("/dom/slow.html", DEFAULT_TIMEOUT)]);
+        assert_eq!(summary.to_string(), "1/2 tests OK, 1/3 subtests pass");
+    }
+}
@@ -0,0 +1,308 @@
+//! Expected results of WPT tests.
+//!
+//! Expectations are written the way wptrunner's metadata files are, all tests in one file. A
+//! test or subtest that isn't listed is expected to be OK or to pass:
+//!
+//! ```text
+//! [/dom/nodes/Node-appendChild.html]
+//!   [Appending a document]
+//!     expected: FAIL
+//!
+//! [/dom/events/Event-dispatch-timeout.html]
+//!   expected: TIMEOUT
+//! ```
+//!
+//! `]` and `\` in names are escaped with a `\`.
+
+use super::{HarnessStatus, SubtestStatus, TestResult};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+/// Errors from reading an expectations file
+#[derive(Debug, thiserror::Error)]
+pub enum ExpectationsError {
+    #[error("cannot read expectations: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("line {line}: {message}")]
+    Syntax { line: usize, message: String },
+}
+
+/// The results tests are expected to have
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Expectations {
+    tests: BTreeMap<String, TestExpectation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestExpectation {
+    status: HarnessStatus,
+    subtests: BTreeMap<String, SubtestStatus>,
+}
+
+impl Default for TestExpectation {
+    fn default() -> Self {
+        Self {
+            status: HarnessStatus::Ok,
+            subtests: BTreeMap::new(),
+        }
+    }
+}
+
+/// A result other than the expected one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unexpected {
+    pub test: String,
+    /// `None` when it's the test file as a whole
+    pub subtest: Option<String>,
+    pub expected: &'static str,
+    pub actual: &'static str,
+}
+
+impl Display for Unexpected {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.test)?;
+        if let Some(subtest) = &self.subtest {
+            write!(f, " [{subtest}]")?;
+        }
+        write!(f, ": expected {}, got {}", self.expected, self.actual)
+    }
+}
+
+impl Expectations {
+    /// Expectations that match `results`, to write out as the new baseline.
+    pub fn from_results(results: &[TestResult]) -> Self {
+        let mut expectations = Expectations::default();
+        for result in results {
+            let subtests: BTreeMap<_, _> = result
+                .subtests
+                .iter()
+                .filter(|s| s.status != SubtestStatus::Pass)
+                .map(|s| (s.name.clone(), s.status))
+                .collect();
+            if result.status != HarnessStatus::Ok || !subtests.is_empty() {
+                let expectation = TestExpectation {
+                    status: result.status,
+                    subtests,
+                };
+                expectations.tests.insert(result.test.clone(), expectation);
+            }
+        }
+        expectations
+    }
+
+    /// Reads the expectations file at `path`.
+    pub fn read(path: &Path) -> Result<Self, ExpectationsError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parses the contents of an expectations file.
+    pub fn parse(source: &str) -> Result<Self, ExpectationsError> {
+        let mut expectations = Expectations::default();
+        let mut test: Option<String> = None;
+        let mut subtest: Option<String> = None;
+
+        for (index, line) in source.lines().enumerate() {
+            let error = |message: &str| ExpectationsError::Syntax {
+                line: index + 1,
+                message: message.to_string(),
+            };
+            let content = line.trim_start();
+            if content.is_empty() || content.starts_with('#') {
+                continue;
+            }
+            let indent = line.len() - content.len();
+
+            if let Some(name) = content.strip_prefix('[') {
+                let name = unescape(name.strip_suffix(']').ok_or_else(|| error("unterminated `[`"))?);
+                match indent {
+                    0 => {
+                        expectations.tests.entry(name.clone()).or_default();
+                        test = Some(name);
+                        subtest = None;
+                    }
+                    2 if test.is_some() => subtest = Some(name),
+                    _ => return Err(error("unexpected indentation")),
+                }
+                continue;
+            }
+
+            let status = content
+                .strip_prefix("expected:")
+                .map(str::trim)
+                .ok_or_else(|| error("expected a `[name]` or an `expected:` line"))?;
+            let expectation = test
+                .as_ref()
+                .and_then(|test| expectations.tests.get_mut(test))
+                .ok_or_else(|| error("`expected:` outside of a test"))?;
+            match (&subtest, indent) {
+                (None, 2) => {
+                    expectation.status = HarnessStatus::parse(status).ok_or_else(|| error("unknown test status"))?;
+                }
+                (Some(subtest), 4) => {
+                    let status = SubtestStatus::parse(status).ok_or_else(|| error("unknown subtest status"))?;
+                    expectation.subtests.insert(subtest.clone(), status);
+                }
+                _ => return Err(error("unexpected indentation")),
+            }
+        }
+        Ok(expectations)
+    }
+
+    /// Number of tests with a result other than OK, or with subtests that don't pass
+    pub fn len(&self) -> usize {
+        self.tests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tests.is_empty()
+    }
+
+    /// How `result` differs from what it's expected to be.
+    pub fn unexpected(&self, result: &TestResult) -> Vec<Unexpected> {
+        let expectation = self.tests.get(&result.test).cloned().unwrap_or_default();
+        let mut unexpected = Vec::new();
+        if result.status != expectation.status {
+            unexpected.push(Unexpected {
+                test: result.test.clone(),
+                subtest: None,
+                expected: expectation.status.as_str(),
+                actual: result.status.as_str(),
+            });
+        }
+        for subtest in &result.subtests {
+            let expected = expectation
+                .subtests
+                .get(&subtest.name)
+                .copied()
+                .unwrap_or(SubtestStatus::Pass);
+            if subtest.status != expected {
+                unexpected.push(Unexpected {
+                    test: result.test.clone(),
+                    subtest: Some(subtest.name.clone()),
+                    expected: expected.as_str(),
+                    actual: subtest.status.as_str(),
+                });
+            }
+        }
+        unexpected
+    }
+}
+
+impl Display for Expectations {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, (test, expectation)) in self.tests.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "[{}]", escape(test))?;
+            if expectation.status != HarnessStatus::Ok {
+                writeln!(f, "  expected: {}", expectation.status.as_str())?;
+            }
+            for (subtest, status) in &expectation.subtests {
+                writeln!(f, "  [{}]", escape(subtest))?;
+                writeln!(f, "    expected: {}", status.as_str())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn escape(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        if matches!(c, '\\' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn unescape(name: &str) -> String {
+    let mut unescaped = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wpt::SubtestResult;
+
+    fn result(test: &str, status: HarnessStatus, subtests: &[(&str, SubtestStatus)]) -> TestResult {
+        TestResult {
+            test: test.to_string(),
+            status,
+            message: None,
+            subtests: subtests
+                .iter()
+                .map(|&(name, status)| SubtestResult {
+                    name: name.to_string(),
+                    status,
+                    message: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn round_trips_and_reports_unexpected_results() {
+        let baseline = [
+            result(
+                "/dom/nodes/Node-appendChild.html",
+                HarnessStatus::Ok,
+                &[
+                    ("Appending to a leaf node", SubtestStatus::Pass),
+                    ("Appending a [document]", SubtestStatus::Fail),
+                ],
+            ),
+            result("/dom/slow.html", HarnessStatus::Timeout, &[]),
+            result(
+                "/css/passing.html",
+                HarnessStatus::Ok,
+                &[("all good", SubtestStatus::Pass)],
+            ),
+        ];
+        let expectations = Expectations::from_results(&baseline);
+        let written = expectations.to_string();
+        assert_eq!(
+            written,
+            "[/dom/nodes/Node-appendChild.html]\n  [Appending a [document\\]]\n    expected: FAIL\n\n\
+             [/dom/slow.html]\n  expected: TIMEOUT\n"
+        );
+        assert_eq!(Expectations::parse(&written).unwrap(), expectations);
+        assert!(baseline.iter().all(|r| expectations.unexpected(r).is_empty()));
+
+        let progressed = result(
+            "/dom/nodes/Node-appendChild.html",
+            HarnessStatus::Ok,
+            &[
+                ("Appending to a leaf node", SubtestStatus::Fail),
+                ("Appending a [document]", SubtestStatus::Pass),
+            ],
+        );
+        let unexpected: Vec<_> = expectations
+            .unexpected(&progressed)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            unexpected,
+            [
+                "/dom/nodes/Node-appendChild.html [Appending to a leaf node]: expected PASS, got FAIL",
+                "/dom/nodes/Node-appendChild.html [Appending a [document]]: expected FAIL, got PASS",
+            ]
+        );
+
+        let err = Expectations::parse("[/a.html]\n    expected: FAIL").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: unexpected indentation");
+    }
+}
@@ -0,0 +1,332 @@
+//! Least-recently-used caches for font binaries and shaped text, bounded by a memory budget.
+//!
+//! Font systems keep the bytes of every face they hand out and may shape the same text many
+//! times per frame. Without a bound, a page cycling through many web fonts keeps all of them
+//! alive; [`FontCache`] evicts the least recently used entries once the budget is spent.
+
+use gosub_interface::font::{FontBlob, FontStyle};
+use gosub_interface::font_system::{ShapedGlyph, ShapedRun, ShapedText, TextAlign, TextStyle};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// Default budget of a [`FontCache`]: 64 MiB, the default of the engine's font cache setting
+pub const DEFAULT_BUDGET_BYTES: usize = 64 * 1024 * 1024;
+
+/// Share of the budget that goes to shaped text; the rest holds font binaries
+const SHAPED_SHARE: usize = 4;
+
+/// An LRU map whose entries each account for a number of bytes, evicting the least recently used
+/// entries to stay within its budget.
+#[derive(Debug)]
+pub struct ByteLru<K, V> {
+    entries: HashMap<K, LruEntry<V>>,
+    /// Keys by the tick they were last used at, oldest first
+    order: BTreeMap<u64, K>,
+    tick: u64,
+    used: usize,
+    budget: usize,
+}
+
+#[derive(Debug)]
+struct LruEntry<V> {
+    value: V,
+    size: usize,
+    tick: u64,
+}
+
+impl<K: Eq + Hash + Clone, V> ByteLru<K, V> {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+            used: 0,
+            budget,
+        }
+    }
+
+    /// The value for `key`, marking it as the most recently used.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let tick = self.next_tick();
+        let entry = self.entries.get_mut(key)?;
+        if let Some(key) = self.order.remove(&entry.tick) {
+            self.order.insert(tick, key);
+        }
+        entry.tick = tick;
+        Some(&entry.value)
+    }
+
+    /// Stores `value` as taking `size` bytes, evicting older entries to make room. A value larger
+    /// than the whole budget is not stored.
+    pub fn insert(&mut self, key: K, value: V, size: usize) {
+        self.remove(&key);
+        if size > self.budget {
+            return;
+        }
+        let tick = self.next_tick();
+        self.order.insert(tick, key.clone());
+        self.entries.insert(key, LruEntry { value, size, tick });
+        self.used += size;
+        self.evict();
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let entry = self.entries.remove(key)?;
+        self.order.remove(&entry.tick);
+        self.used -= entry.size;
+        Some(entry.value)
+    }
+
+    /// Changes the budget, evicting entries when it shrank.
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.evict();
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+        self.used = 0;
+    }
+
+    /// Bytes taken by the stored entries
+    pub fn used_bytes(&self) -> usize {
+        self.used
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn evict(&mut self) {
+        while self.used > self.budget {
+            let Some((_, key)) = self.order.pop_first() else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&key) {
+                self.used -= entry.size;
+            }
+        }
+    }
+}
+
+/// Everything about a [`TextStyle`] that changes how text shapes, in hashable form
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ShapeKey {
+    text: String,
+    family: String,
+    lang: Option<String>,
+    features: Vec<(u32, u32)>,
+    /// Size, weight, stretch, line height, letter spacing, wrap width and display scale as bits
+    numbers: [u32; 7],
+    style: u8,
+    align: u8,
+}
+
+impl ShapeKey {
+    pub fn new(text: &str, style: &TextStyle) -> Self {
+        let optional = |value: Option<f32>| value.map_or(u32::MAX, f32::to_bits);
+        Self {
+            text: text.to_string(),
+            family: style.family.clone(),
+            lang: style.lang.clone(),
+            features: style
+                .features
+                .iter()
+                .map(|f| (u32::from_be_bytes(f.tag), f.value))
+                .collect(),
+            numbers: [
+                style.size.to_bits(),
+                u32::from(style.weight.0),
+                style.stretch.0.to_bits(),
+                optional(style.line_height),
+                style.letter_spacing.to_bits(),
+                optional(style.max_width),
+                style.display_scale.to_bits(),
+            ],
+            style: match style.style {
+                FontStyle::Normal => 0,
+                FontStyle::Italic => 1,
+                FontStyle::Oblique => 2,
+            },
+            align: match style.align {
+                TextAlign::Start => 0,
+                TextAlign::Center => 1,
+                TextAlign::End => 2,
+                TextAlign::Justify => 3,
+            },
+        }
+    }
+
+    /// Rough heap footprint of the key
+    fn size(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.text.len()
+            + self.family.len()
+            + self.lang.as_ref().map_or(0, String::len)
+            + self.features.len() * std::mem::size_of::<(u32, u32)>()
+    }
+}
+
+/// Rough heap footprint of shaped text. Font bytes are shared with the font cache, so they don't
+/// count here.
+fn shaped_size(shaped: &ShapedText) -> usize {
+    std::mem::size_of::<ShapedText>()
+        + shaped
+            .runs
+            .iter()
+            .map(|run| {
+                std::mem::size_of::<ShapedRun>()
+                    + run.font.family.len()
+                    + run.glyphs.len() * std::mem::size_of::<ShapedGlyph>()
+            })
+            .sum::<usize>()
+}
+
+/// The font binaries and shaped text a font system keeps around, within one memory budget.
+///
+/// A quarter of the budget goes to shaped text and the rest to font binaries, keyed by whatever
+/// identifies a face in the font system (`K`).
+#[derive(Debug)]
+pub struct FontCache<K> {
+    fonts: ByteLru<K, FontBlob>,
+    shaped: ByteLru<ShapeKey, ShapedText>,
+}
+
+impl<K: Eq + Hash + Clone> Default for FontCache<K> {
+    fn default() -> Self {
+        Self::new(DEFAULT_BUDGET_BYTES)
+    }
+}
+
+impl<K: Eq + Hash + Clone> FontCache<K> {
+    pub fn new(budget: usize) -> Self {
+        let (fonts, shaped) = Self::split(budget);
+        Self {
+            fonts: ByteLru::new(fonts),
+            shaped: ByteLru::new(shaped),
+        }
+    }
+
+    /// Changes the memory budget, evicting what no longer fits.
+    pub fn set_budget(&mut self, budget: usize) {
+        let (fonts, shaped) = Self::split(budget);
+        self.fonts.set_budget(fonts);
+        self.shaped.set_budget(shaped);
+    }
+
+    /// The cached bytes of the face `key`, or those `load` returns, which are cached in turn.
+    pub fn font<E>(&mut self, key: K, load: impl FnOnce() -> Result<FontBlob, E>) -> Result<FontBlob, E> {
+        if let Some(blob) = self.fonts.get(&key) {
+            return Ok(blob.clone());
+        }
+        let blob = load()?;
+        let size = blob.as_u8().len();
+        self.fonts.insert(key, blob.clone(), size);
+        Ok(blob)
+    }
+
+    /// The cached result of shaping the text and style behind `key`.
+    pub fn shaped(&mut self, key: &ShapeKey) -> Option<ShapedText> {
+        self.shaped.get(key).cloned()
+    }
+
+    pub fn insert_shaped(&mut self, key: ShapeKey, shaped: &ShapedText) {
+        let size = key.size() + shaped_size(shaped);
+        self.shaped.insert(key, shaped.clone(), size);
+    }
+
+    /// Forgets the shaped text, which a newly registered font or changed family mapping can
+    /// make stale.
+    pub fn invalidate_shaped(&mut self) {
+        self.shaped.clear();
+    }
+
+    /// Bytes taken by cached font binaries and shaped text
+    pub fn used_bytes(&self) -> usize {
+        self.fonts.used_bytes() + self.shaped.used_bytes()
+    }
+
+    fn split(budget: usize) -> (usize, usize) {
+        let shaped = budget / SHAPED_SHARE;
+        (budget - shaped, shaped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn evicts_least_recently_used_within_budget() {
+        let mut lru = ByteLru::new(100);
+        lru.insert("a", 1, 40);
+        lru.insert("b", 2, 40);
+        assert_eq!(lru.get(&"a"), Some(&1));
+
+        // "b" is now the least recently used, so it makes room for "c".
+        lru.insert("c", 3, 40);
+        assert_eq!(lru.get(&"b"), None);
+        assert_eq!(lru.get(&"a"), Some(&1));
+        assert_eq!(lru.used_bytes(), 80);
+
+        lru.insert("huge", 4, 101);
+        assert_eq!(lru.get(&"huge"), None, "larger than the budget");
+        assert_eq!(lru.len(), 2);
+
+        lru.set_budget(50);
+        assert_eq!(lru.len(), 1);
+        assert_eq!(lru.get(&"a"), Some(&1), "the most recent entry survives");
+    }
+
+    #[test]
+    fn caches_fonts_and_shaped_text() {
+        let mut cache: FontCache<u32> = FontCache::new(4000);
+        let blob = || Ok::<_, ()>(FontBlob::new(Arc::new(vec![0u8; 1000]), 0));
+        let mut loads = 0;
+        for _ in 0..2 {
+            let _ = cache.font(1, || {
+                loads += 1;
+                blob()
+            });
+        }
+        assert_eq!(loads, 1, "the second lookup is a hit");
+        assert_eq!(cache.used_bytes(), 1000);
+
+        // 3000 bytes go to fonts, so a third 1000-byte face pushes out the first.
+        for face in 2..=4 {
+            let _ = cache.font(face, blob);
+        }
+        let _ = cache.font(1, || {
+            loads += 1;
+            blob()
+        });
+        assert_eq!(loads, 2, "face 1 was evicted");
+
+        let style = TextStyle::new("Roboto", 16.0);
+        let key = ShapeKey::new("Hello", &style);
+        assert!(cache.shaped(&key).is_none());
+        cache.insert_shaped(key.clone(), &ShapedText::empty());
+        assert!(cache.shaped(&key).is_some());
+
+        let mut bigger = style.clone();
+        bigger.size = 17.0;
+        assert!(
+            cache.shaped(&ShapeKey::new("Hello", &bigger)).is_none(),
+            "a different size is a different key"
+        );
+
+        cache.set_budget(0);
+        assert_eq!(cache.used_bytes(), 0);
+    }
+}
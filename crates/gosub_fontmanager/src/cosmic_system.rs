@@ -6,20 +6,27 @@
 //! Note: cosmic-text doesn't expose the underlying shared font bytes, so `blob_for` copies them
 //! when filling a [`FontBlob`] (cached upstream by whoever holds the `ResolvedFont`).
 
+use crate::cache::{FontCache, ShapeKey};
+use crate::metrics::{resolved_metrics, sfnt_metrics};
+use crate::parley_system::split_css_families;
 use cosmic_text::{
-    fontdb, Align, Attrs, Buffer, Family, FontSystem as CosmicTextFontSystem, Metrics, Shaping, Stretch, Style, Weight,
+    fontdb, Align, Attrs, Buffer, Family, FeatureTag, FontFeatures, FontSystem as CosmicTextFontSystem, Metrics,
+    Shaping, Stretch, Style, Weight,
 };
 use cow_utils::CowUtils;
 use gosub_interface::font::{FontBlob, FontError, FontStyle};
 use gosub_interface::font_system::{
-    FontQuery, FontStretch, FontSystem, ResolvedFont, RunMetrics, ShapedGlyph, ShapedRun, ShapedText, TextAlign,
-    TextStyle,
+    FontMetrics, FontQuery, FontStretch, FontSystem, GenericFamilies, ResolvedFont, RunMetrics, ShapedGlyph, ShapedRun,
+    ShapedText, TextAlign, TextStyle,
 };
 use std::sync::Arc;
 
 /// A [`FontSystem`] backed by cosmic-text.
 pub struct CosmicFontSystem {
     inner: CosmicTextFontSystem,
+    generics: GenericFamilies,
+    /// Copied face bytes by fontdb id and weight, and shaped text by style
+    cache: FontCache<(fontdb::ID, u16)>,
 }
 
 impl std::fmt::Debug for CosmicFontSystem {
@@ -46,11 +53,10 @@ struct RawRun {
     glyphs: Vec<ShapedGlyph>,
 }
 
-/// Decoration metrics estimated from the font size.
+/// Decoration metrics estimated from the font size, for fonts whose tables can't be read.
 ///
-/// cosmic-text doesn't surface the font's own underline/strikeout tables, so use the common
-/// conventions (underline ~1/10 em below the baseline, strikeout ~1/4 em above, both ~1/14 em
-/// thick) until a swash-based lookup replaces this.
+/// Uses the common conventions: underline ~1/10 em below the baseline, strikeout ~1/4 em above,
+/// both ~1/14 em thick.
 fn heuristic_metrics(size: f32) -> RunMetrics {
     RunMetrics {
         underline_offset: size * 0.1,
@@ -69,7 +75,32 @@ impl CosmicFontSystem {
         inner
             .db_mut()
             .load_font_source(fontdb::Source::Binary(Arc::new(gosub_shared::ROBOTO_FONT)));
-        Self { inner }
+        Self {
+            inner,
+            generics: GenericFamilies::new(),
+            cache: FontCache::default(),
+        }
+    }
+
+    /// The family cosmic-text shapes `style` with. It takes a single family, so this is the first
+    /// installed name in the CSS list (with the configured families standing in front of each
+    /// generic), or the first generic when none is installed.
+    fn shaping_family(&self, style: &TextStyle) -> String {
+        let names = split_css_families(&style.family);
+        for name in self.generics.expand(&names, style.lang.as_deref()) {
+            let generic = !matches!(css_family(name), Family::Name(_));
+            let installed = || {
+                self.inner.db().faces().any(|face| {
+                    face.families
+                        .iter()
+                        .any(|(family, _)| family.eq_ignore_ascii_case(name))
+                })
+            };
+            if generic || installed() {
+                return name.to_string();
+            }
+        }
+        "sans-serif".to_string()
     }
 
     /// Build and shape a cosmic-text buffer for `text` in the given style.
@@ -77,11 +108,17 @@ impl CosmicFontSystem {
         let metrics = Metrics::new(style.size, style.line_height.unwrap_or(style.size * 1.2));
         let mut buffer = Buffer::new(&mut self.inner, metrics);
         buffer.set_size(style.max_width, None);
+        let mut features = FontFeatures::new();
+        for feature in &style.features {
+            features.set(FeatureTag::new(&feature.tag), feature.value);
+        }
+        let family = self.shaping_family(style);
         let attrs = Attrs::new()
-            .family(css_family(&style.family))
+            .family(css_family(&family))
             .weight(Weight(style.weight.0))
             .style(to_style(style.style))
-            .stretch(to_stretch(style.stretch));
+            .stretch(to_stretch(style.stretch))
+            .font_features(features);
         buffer.set_text(text, &attrs, Shaping::Advanced, None);
         let align = match style.align {
             TextAlign::Start => None, // natural per-direction default
@@ -101,88 +138,21 @@ impl CosmicFontSystem {
     /// Raw font bytes for a resolved face, as a [`FontBlob`].
     ///
     /// cosmic-text doesn't expose the underlying shared `Arc<[u8]>`, so this copies the file
-    /// bytes and assumes face index 0 (correct for single-face files; `.ttc` collections would
-    /// need the real index). Only used to fill `FontBlob`, which a cosmic draw path doesn't yet
-    /// consume - so the copy is harmless for now.
+    /// bytes, along with the face's index in it for `.ttc` collections. The copies are kept in
+    /// the font cache, so each face is copied once until it is evicted.
     fn blob_for(&mut self, id: fontdb::ID, weight: Weight) -> Option<FontBlob> {
-        let font = self.inner.get_font(id, weight)?;
-        Some(FontBlob::new(Arc::new(font.data().to_vec()), 0))
-    }
-}
-
-impl FontSystem for CosmicFontSystem {
-    fn register_font(&mut self, data: Vec<u8>, _family_override: Option<&str>) -> Result<(), FontError> {
-        // fontdb derives the family name from the font's own `name` table; overrides unsupported.
-        self.inner.db_mut().load_font_data(data);
-        Ok(())
-    }
-
-    fn measure(&mut self, text: &str, style: &TextStyle) -> (f32, f32) {
-        if text.is_empty() {
-            return (0.0, 0.0);
-        }
-        let buffer = self.shaped_buffer(text, style);
-        let mut width = 0.0f32;
-        let mut height = 0.0f32;
-        for run in buffer.layout_runs() {
-            width = width.max(run.line_w);
-            height += run.line_height;
-        }
-        (width, height)
-    }
-
-    /// Resolve a CSS font query to a concrete font via fontdb.
-    fn resolve(&mut self, query: &FontQuery<'_>) -> Result<ResolvedFont, FontError> {
-        let mut families: Vec<Family> = query.families.iter().map(|f| css_family(f)).collect();
-        // Bundled last-resort fallback so resolution always succeeds even with no system fonts
-        // (e.g. headless/CI) - Roboto is registered in `new()`.
-        families.push(Family::Name("Roboto"));
-        let weight = Weight(query.weight.0);
-        let fq = fontdb::Query {
-            families: &families,
-            weight,
-            stretch: to_stretch(query.stretch),
-            style: to_style(query.style),
-        };
-
-        let id = self
-            .inner
-            .db_mut()
-            .query(&fq)
-            .ok_or_else(|| FontError::FontNotFound(query.families.join(", ")))?;
-        let blob = self
-            .blob_for(id, weight)
-            .ok_or_else(|| FontError::FontNotFound(query.families.join(", ")))?;
-
-        Ok(ResolvedFont {
-            family: query.families.first().copied().unwrap_or("sans-serif").to_string(),
-            style: query.style,
-            weight: query.weight,
-            stretch: query.stretch,
-            blob,
-        })
-    }
-
-    fn families(&mut self) -> Vec<String> {
-        // A face's `families` holds one name per localisation; the first entry is the
-        // primary (typically English) name, which is what CSS matches against.
-        let mut out: Vec<String> = self
-            .inner
-            .db()
-            .faces()
-            .filter_map(|face| face.families.first().map(|(name, _)| name.clone()))
-            .collect();
-        out.sort_unstable();
-        out.dedup();
-        out
+        let inner = &mut self.inner;
+        self.cache
+            .font::<()>((id, weight.0), || {
+                let index = inner.db().face(id).map_or(0, |face| face.index);
+                let font = inner.get_font(id, weight).ok_or(())?;
+                Ok(FontBlob::new(Arc::new(font.data().to_vec()), index))
+            })
+            .ok()
     }
 
     /// Shape `text` into positioned glyph runs.
-    fn shape(&mut self, text: &str, style: &TextStyle) -> ShapedText {
-        if text.is_empty() {
-            return ShapedText::empty();
-        }
-
+    fn shape_uncached(&mut self, text: &str, style: &TextStyle) -> ShapedText {
         let buffer = self.shaped_buffer(text, style);
 
         // Collect owned run data first (borrows `buffer`), then look up font blobs afterwards
@@ -212,10 +182,12 @@ impl FontSystem for CosmicFontSystem {
                 let mut glyphs = Vec::new();
                 while i < run.glyphs.len() && run.glyphs[i].font_id == fid {
                     let g = &run.glyphs[i];
+                    // The shaper's offsets (mark attachment, kerning adjustments) are kept apart
+                    // from the hitbox, in units of the font size, y-up.
                     glyphs.push(ShapedGlyph {
                         id: g.glyph_id as u32,
-                        x: g.x,
-                        y: run.line_y + g.y,
+                        x: g.x + g.x_offset * g.font_size,
+                        y: run.line_y + g.y - g.y_offset * g.font_size,
                     });
                     run_width = (g.x + g.w) - run_x;
                     i += 1;
@@ -238,6 +210,10 @@ impl FontSystem for CosmicFontSystem {
             .into_iter()
             .filter_map(|r| {
                 let blob = self.blob_for(r.id, r.weight)?;
+                // cosmic-text doesn't surface the font's underline/strikeout values, so read them
+                // from the font's own tables.
+                let metrics = sfnt_metrics(blob.as_u8(), blob.index, style.size)
+                    .map_or_else(|| heuristic_metrics(style.size), |m| m.run_metrics());
                 Some(ShapedRun {
                     font: ResolvedFont {
                         family: style.family.clone(),
@@ -250,7 +226,7 @@ impl FontSystem for CosmicFontSystem {
                     x: r.x,
                     baseline: r.baseline,
                     width: r.width,
-                    metrics: heuristic_metrics(style.size),
+                    metrics,
                     glyphs: r.glyphs,
                 })
             })
@@ -266,6 +242,107 @@ impl FontSystem for CosmicFontSystem {
     }
 }
 
+impl FontSystem for CosmicFontSystem {
+    fn register_font(&mut self, data: Vec<u8>, _family_override: Option<&str>) -> Result<(), FontError> {
+        // fontdb derives the family name from the font's own `name` table; overrides unsupported.
+        self.inner.db_mut().load_font_data(data);
+        self.cache.invalidate_shaped();
+        Ok(())
+    }
+
+    fn measure(&mut self, text: &str, style: &TextStyle) -> (f32, f32) {
+        if text.is_empty() {
+            return (0.0, 0.0);
+        }
+        let buffer = self.shaped_buffer(text, style);
+        let mut width = 0.0f32;
+        let mut height = 0.0f32;
+        for run in buffer.layout_runs() {
+            width = width.max(run.line_w);
+            height += run.line_height;
+        }
+        (width, height)
+    }
+
+    /// Resolve a CSS font query to a concrete font via fontdb.
+    fn resolve(&mut self, query: &FontQuery<'_>) -> Result<ResolvedFont, FontError> {
+        let mut families: Vec<Family> = self
+            .generics
+            .expand(query.families, query.lang)
+            .into_iter()
+            .map(css_family)
+            .collect();
+        // Bundled last-resort fallback so resolution always succeeds even with no system fonts
+        // (e.g. headless/CI) - Roboto is registered in `new()`.
+        families.push(Family::Name("Roboto"));
+        let weight = Weight(query.weight.0);
+        let fq = fontdb::Query {
+            families: &families,
+            weight,
+            stretch: to_stretch(query.stretch),
+            style: to_style(query.style),
+        };
+
+        let id = self
+            .inner
+            .db_mut()
+            .query(&fq)
+            .ok_or_else(|| FontError::FontNotFound(query.families.join(", ")))?;
+        let blob = self
+            .blob_for(id, weight)
+            .ok_or_else(|| FontError::FontNotFound(query.families.join(", ")))?;
+
+        Ok(ResolvedFont {
+            family: query.families.first().copied().unwrap_or("sans-serif").to_string(),
+            style: query.style,
+            weight: query.weight,
+            stretch: query.stretch,
+            blob,
+        })
+    }
+
+    fn set_generic_families(&mut self, families: GenericFamilies) {
+        self.generics = families;
+        self.cache.invalidate_shaped();
+    }
+
+    fn set_cache_budget(&mut self, bytes: usize) {
+        self.cache.set_budget(bytes);
+    }
+
+    fn families(&mut self) -> Vec<String> {
+        // A face's `families` holds one name per localisation; the first entry is the
+        // primary (typically English) name, which is what CSS matches against.
+        let mut out: Vec<String> = self
+            .inner
+            .db()
+            .faces()
+            .filter_map(|face| face.families.first().map(|(name, _)| name.clone()))
+            .collect();
+        out.sort_unstable();
+        out.dedup();
+        out
+    }
+
+    fn metrics(&mut self, style: &TextStyle) -> Option<FontMetrics> {
+        resolved_metrics(self, style)
+    }
+
+    /// Shape `text` into positioned glyph runs, cached by text and style.
+    fn shape(&mut self, text: &str, style: &TextStyle) -> ShapedText {
+        if text.is_empty() {
+            return ShapedText::empty();
+        }
+        let key = ShapeKey::new(text, style);
+        if let Some(shaped) = self.cache.shaped(&key) {
+            return shaped;
+        }
+        let shaped = self.shape_uncached(text, style);
+        self.cache.insert_shaped(key, &shaped);
+        shaped
+    }
+}
+
 // ── conversions: our neutral font-query types → cosmic-text/fontdb types ──
 
 fn css_family(name: &str) -> Family<'_> {
@@ -343,4 +420,24 @@ mod tests {
         let glyphs: usize = shaped.runs.iter().map(|r| r.glyphs.len()).sum();
         assert!(glyphs >= 5, "expected >= 5 glyphs for \"Hello\", got {glyphs}");
     }
+
+    #[test]
+    fn combining_marks_sit_on_their_base() {
+        // There's no precomposed q with acute, so the shaper has to attach the mark. Whether it
+        // gets there through an offset or the mark's own bearing depends on the font, but either
+        // way it takes no room of its own.
+        let mut fs = CosmicFontSystem::new();
+        let style = TextStyle::new("Roboto", 32.0);
+        let base = fs.shape("q", &style);
+        let shaped = fs.shape("q\u{301}", &style);
+        let glyphs: Vec<ShapedGlyph> = shaped.runs.iter().flat_map(|r| r.glyphs.clone()).collect();
+        assert_eq!(glyphs.len(), 2, "base and mark");
+        assert_eq!(shaped.width, base.width, "the mark must not advance the pen");
+        assert!(
+            glyphs[1].x <= shaped.width,
+            "the mark must stay over the base: mark at {}, advance {}",
+            glyphs[1].x,
+            shaped.width
+        );
+    }
 }
@@ -0,0 +1,413 @@
+//! Script- and language-aware font fallback.
+//!
+//! A page's `font-family` list rarely covers every character it is used for: a Latin font has no
+//! CJK ideographs, Arabic or emoji. Text is split into runs per Unicode script, and each run gets
+//! the first installed font from a per-script candidate list, picked by the content language
+//! where that matters (the same ideograph is drawn differently in Japanese, Korean and
+//! Simplified/Traditional Chinese fonts). Which candidate is installed is looked up once per
+//! script and language and cached in [`FallbackCache`].
+
+use cow_utils::CowUtils;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+/// The scripts fallback tells apart. Characters that belong to no script in particular (spaces,
+/// punctuation, digits) are [`Script::Common`] and join the run around them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Script {
+    Common,
+    Latin,
+    Greek,
+    Cyrillic,
+    Arabic,
+    Hebrew,
+    Devanagari,
+    Bengali,
+    Thai,
+    /// CJK ideographs, and the CJK symbols and fullwidth forms used with them
+    Han,
+    /// Hiragana and katakana
+    Kana,
+    Hangul,
+    Emoji,
+    /// A script without fallback candidates of its own; the font system's fallback handles it
+    Other,
+}
+
+/// Combining marks, joiners and selectors: they belong to whatever character they follow
+fn is_inherited(c: char) -> bool {
+    matches!(c,
+        '\u{0300}'..='\u{036F}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{200C}'..='\u{200D}'
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{FE00}'..='\u{FE0F}'
+        | '\u{FE20}'..='\u{FE2F}'
+        | '\u{1F3FB}'..='\u{1F3FF}'
+        | '\u{E0020}'..='\u{E007F}'
+        | '\u{E0100}'..='\u{E01EF}')
+}
+
+/// The script of `c`, by Unicode block.
+pub fn script_of(c: char) -> Script {
+    match c {
+        'A'..='Z' | 'a'..='z' => Script::Latin,
+        '\u{0000}'..='\u{00BF}' | '\u{00D7}' | '\u{00F7}' => Script::Common,
+        '\u{00C0}'..='\u{024F}' | '\u{1E00}'..='\u{1EFF}' | '\u{2C60}'..='\u{2C7F}' | '\u{A720}'..='\u{A7FF}' => {
+            Script::Latin
+        }
+        '\u{0370}'..='\u{03FF}' | '\u{1F00}'..='\u{1FFF}' => Script::Greek,
+        '\u{0400}'..='\u{052F}' | '\u{2DE0}'..='\u{2DFF}' | '\u{A640}'..='\u{A69F}' => Script::Cyrillic,
+        '\u{0590}'..='\u{05FF}' | '\u{FB1D}'..='\u{FB4F}' => Script::Hebrew,
+        '\u{0600}'..='\u{06FF}'
+        | '\u{0750}'..='\u{077F}'
+        | '\u{08A0}'..='\u{08FF}'
+        | '\u{FB50}'..='\u{FDFF}'
+        | '\u{FE70}'..='\u{FEFF}' => Script::Arabic,
+        '\u{0900}'..='\u{097F}' | '\u{A8E0}'..='\u{A8FF}' => Script::Devanagari,
+        '\u{0980}'..='\u{09FF}' => Script::Bengali,
+        '\u{0E00}'..='\u{0E7F}' => Script::Thai,
+        '\u{1100}'..='\u{11FF}' | '\u{3130}'..='\u{318F}' | '\u{A960}'..='\u{A97F}' | '\u{AC00}'..='\u{D7FF}' => {
+            Script::Hangul
+        }
+        '\u{3040}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}' | '\u{FF66}'..='\u{FF9F}' => Script::Kana,
+        '\u{2E80}'..='\u{2FDF}'
+        | '\u{3000}'..='\u{303F}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FF00}'..='\u{FF65}'
+        | '\u{20000}'..='\u{3134F}' => Script::Han,
+        '\u{2600}'..='\u{27BF}'
+        | '\u{1F000}'..='\u{1F02F}'
+        | '\u{1F0A0}'..='\u{1F0FF}'
+        | '\u{1F1E6}'..='\u{1F1FF}'
+        | '\u{1F300}'..='\u{1FAFF}' => Script::Emoji,
+        // General punctuation, currency, letter-like symbols, arrows, math, box drawing, …
+        '\u{2000}'..='\u{25FF}' => Script::Common,
+        c if is_inherited(c) => Script::Common,
+        _ => Script::Other,
+    }
+}
+
+/// A stretch of text in one script, as a byte range into the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptRun {
+    pub range: Range<usize>,
+    pub script: Script,
+}
+
+/// Splits `text` into runs of one script each. Common characters join the run before them (or
+/// the one after, at the start of the text), and combining marks, joiners and emoji modifiers
+/// always stay with the character they follow, so `👩‍💻` or `é` written as `e` plus an accent is
+/// never torn apart. Text with no script at all is a single [`Script::Common`] run.
+pub fn script_runs(text: &str) -> Vec<ScriptRun> {
+    let mut runs: Vec<ScriptRun> = Vec::new();
+    // Common text seen before the first real script
+    let mut leading = 0;
+
+    for (i, c) in text.char_indices() {
+        let end = i + c.len_utf8();
+        let script = if is_inherited(c) { Script::Common } else { script_of(c) };
+        match runs.last_mut() {
+            Some(run) if script == Script::Common || script == run.script => run.range.end = end,
+            Some(_) => runs.push(ScriptRun { range: i..end, script }),
+            None if script == Script::Common => leading = end,
+            None => runs.push(ScriptRun { range: 0..end, script }),
+        }
+    }
+
+    if runs.is_empty() && leading > 0 {
+        runs.push(ScriptRun {
+            range: 0..leading,
+            script: Script::Common,
+        });
+    }
+    runs
+}
+
+/// Picks the fallback candidates for `script`: which of the per-language lists of
+/// [`fallback_families`] applies. `text` is the whole string being laid out, used to tell
+/// unlabelled Japanese and Korean from Chinese.
+pub fn fallback_language(script: Script, lang: Option<&str>, text: &str) -> &'static str {
+    let lang = lang
+        .map(|l| l.cow_to_ascii_lowercase().into_owned())
+        .unwrap_or_default();
+    let primary = lang.split(['-', '_']).next().unwrap_or_default();
+    match script {
+        Script::Han | Script::Kana | Script::Hangul => match primary {
+            "ja" => "ja",
+            "ko" => "ko",
+            "zh" if lang.contains("hant")
+                || lang.ends_with("-tw")
+                || lang.ends_with("-hk")
+                || lang.ends_with("-mo") =>
+            {
+                "zh-hant"
+            }
+            "zh" => "zh-hans",
+            // Kana only occurs in Japanese and hangul only in Korean, so either one in the
+            // text says how its ideographs should look.
+            _ if text.chars().any(|c| script_of(c) == Script::Kana) => "ja",
+            _ if text.chars().any(|c| script_of(c) == Script::Hangul) => "ko",
+            _ => "zh-hans",
+        },
+        Script::Arabic if primary == "ur" => "ur",
+        _ => "",
+    }
+}
+
+/// The fonts tried, in order, for text in `script` written in `language` (as returned by
+/// [`fallback_language`]). Covers the families the common desktop platforms ship, with the
+/// Noto families first as they are what Linux distributions install.
+pub fn fallback_families(script: Script, language: &str) -> &'static [&'static str] {
+    const JAPANESE: &[&str] = &[
+        "Noto Sans CJK JP",
+        "Noto Sans JP",
+        "Source Han Sans JP",
+        "Hiragino Sans",
+        "Hiragino Kaku Gothic ProN",
+        "Yu Gothic",
+        "Meiryo",
+        "MS Gothic",
+    ];
+    const KOREAN: &[&str] = &[
+        "Noto Sans CJK KR",
+        "Noto Sans KR",
+        "Source Han Sans KR",
+        "Apple SD Gothic Neo",
+        "Malgun Gothic",
+    ];
+    const TRADITIONAL_CHINESE: &[&str] = &[
+        "Noto Sans CJK TC",
+        "Noto Sans TC",
+        "Source Han Sans TC",
+        "PingFang TC",
+        "Microsoft JhengHei",
+    ];
+    const SIMPLIFIED_CHINESE: &[&str] = &[
+        "Noto Sans CJK SC",
+        "Noto Sans SC",
+        "Source Han Sans SC",
+        "PingFang SC",
+        "Microsoft YaHei",
+        "WenQuanYi Micro Hei",
+    ];
+
+    match (script, language) {
+        (Script::Han | Script::Kana | Script::Hangul, "ja") => JAPANESE,
+        (Script::Han | Script::Kana | Script::Hangul, "ko") => KOREAN,
+        (Script::Han | Script::Kana | Script::Hangul, "zh-hant") => TRADITIONAL_CHINESE,
+        (Script::Han | Script::Kana | Script::Hangul, _) => SIMPLIFIED_CHINESE,
+        (Script::Arabic, "ur") => &[
+            "Noto Nastaliq Urdu",
+            "Noto Naskh Arabic",
+            "Noto Sans Arabic",
+            "Segoe UI",
+        ],
+        (Script::Arabic, _) => &[
+            "Noto Naskh Arabic",
+            "Noto Sans Arabic",
+            "Geeza Pro",
+            "Segoe UI",
+            "Arial",
+        ],
+        (Script::Hebrew, _) => &["Noto Sans Hebrew", "Arial Hebrew", "Segoe UI", "Arial"],
+        (Script::Devanagari, _) => &["Noto Sans Devanagari", "Kohinoor Devanagari", "Nirmala UI", "Mangal"],
+        (Script::Bengali, _) => &["Noto Sans Bengali", "Kohinoor Bangla", "Nirmala UI", "Vrinda"],
+        (Script::Thai, _) => &["Noto Sans Thai", "Thonburi", "Leelawadee UI", "Tahoma"],
+        (Script::Greek | Script::Cyrillic, _) => &["Noto Sans", "DejaVu Sans", "Arial"],
+        (Script::Emoji, _) => &[
+            "Noto Color Emoji",
+            "Apple Color Emoji",
+            "Segoe UI Emoji",
+            "Twemoji Mozilla",
+            "Noto Emoji",
+        ],
+        (Script::Common | Script::Latin | Script::Other, _) => &[],
+    }
+}
+
+/// A run of text and the fallback family for it, if it needs one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FallbackRun {
+    pub range: Range<usize>,
+    /// The installed family to fall back to for this run; `None` when the primary family (or
+    /// the font system's own fallback) is all there is.
+    pub family: Option<String>,
+}
+
+/// Which fallback candidate is installed, per script and language. Font systems keep one and
+/// call [`FallbackCache::invalidate`] whenever fonts are added.
+#[derive(Debug, Default)]
+pub struct FallbackCache {
+    /// Lowercased names of the installed families; `None` until first needed
+    installed: Option<HashSet<String>>,
+    chosen: HashMap<(Script, &'static str), Option<String>>,
+}
+
+impl FallbackCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forgets what was looked up, e.g. after a font was registered.
+    pub fn invalidate(&mut self) {
+        self.installed = None;
+        self.chosen.clear();
+    }
+
+    /// The first installed family of the candidates for `script` in `language` (see
+    /// [`fallback_language`]). `installed` lists the font system's families; it is only called
+    /// when the cache was empty.
+    pub fn family_for(
+        &mut self,
+        script: Script,
+        language: &'static str,
+        installed: impl FnOnce() -> Vec<String>,
+    ) -> Option<&str> {
+        let available = self.installed.get_or_insert_with(|| {
+            installed()
+                .into_iter()
+                .map(|family| family.cow_to_ascii_lowercase().into_owned())
+                .collect()
+        });
+        self.chosen
+            .entry((script, language))
+            .or_insert_with(|| {
+                fallback_families(script, language)
+                    .iter()
+                    .find(|family| available.contains(family.cow_to_ascii_lowercase().as_ref()))
+                    .map(|family| (*family).to_string())
+            })
+            .as_deref()
+    }
+
+    /// Splits `text` into runs that each fall back to one family, merging neighbouring script
+    /// runs that end up with the same one (kanji and kana of Japanese text share a font).
+    pub fn fallback_runs(
+        &mut self,
+        text: &str,
+        lang: Option<&str>,
+        installed: impl FnOnce() -> Vec<String>,
+    ) -> Vec<FallbackRun> {
+        let mut installed = Some(installed);
+        let mut out: Vec<FallbackRun> = Vec::new();
+        for run in script_runs(text) {
+            let family = if fallback_families(run.script, "").is_empty() {
+                None
+            } else {
+                let language = fallback_language(run.script, lang, text);
+                let list = || installed.take().map(|f| f()).unwrap_or_default();
+                self.family_for(run.script, language, list).map(str::to_string)
+            };
+            match out.last_mut() {
+                Some(last) if last.family == family => last.range.end = run.range.end,
+                _ => out.push(FallbackRun {
+                    range: run.range,
+                    family,
+                }),
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scripts(text: &str) -> Vec<(&str, Script)> {
+        script_runs(text)
+            .into_iter()
+            .map(|run| (&text[run.range], run.script))
+            .collect()
+    }
+
+    #[test]
+    fn runs_split_at_script_boundaries() {
+        assert_eq!(
+            scripts("Hello, 世界! مرحبا"),
+            [
+                ("Hello, ", Script::Latin),
+                ("世界! ", Script::Han),
+                ("مرحبا", Script::Arabic)
+            ]
+        );
+        assert_eq!(scripts("  (Привет)"), [("  (Привет)", Script::Cyrillic)]);
+        assert_eq!(scripts("42 %"), [("42 %", Script::Common)]);
+        assert!(scripts("").is_empty());
+    }
+
+    #[test]
+    fn joiners_and_marks_stay_with_their_character() {
+        assert_eq!(
+            scripts("hi 👩\u{200D}💻👍\u{1F3FD} ok"),
+            [
+                ("hi ", Script::Latin),
+                ("👩\u{200D}💻👍\u{1F3FD} ", Script::Emoji),
+                ("ok", Script::Latin)
+            ]
+        );
+        assert_eq!(scripts("cafe\u{0301}"), [("cafe\u{0301}", Script::Latin)]);
+    }
+
+    #[test]
+    fn language_picks_the_cjk_variant() {
+        assert_eq!(fallback_language(Script::Han, Some("ja-JP"), "漢字"), "ja");
+        assert_eq!(fallback_language(Script::Han, Some("zh-Hant"), "漢字"), "zh-hant");
+        assert_eq!(fallback_language(Script::Han, Some("zh-TW"), "漢字"), "zh-hant");
+        assert_eq!(fallback_language(Script::Han, Some("zh"), "汉字"), "zh-hans");
+        assert_eq!(fallback_language(Script::Han, None, "漢字とかな"), "ja");
+        assert_eq!(fallback_language(Script::Han, None, "韓國 한국"), "ko");
+        assert_eq!(fallback_language(Script::Han, None, "汉字"), "zh-hans");
+        assert_eq!(fallback_language(Script::Arabic, Some("ur"), "اردو"), "ur");
+        assert_eq!(fallback_language(Script::Thai, Some("ja"), "ไทย"), "");
+    }
+
+    #[test]
+    fn cache_picks_the_first_installed_candidate() {
+        let mut cache = FallbackCache::new();
+        let lookups = std::cell::Cell::new(0);
+        let installed = || {
+            lookups.set(lookups.get() + 1);
+            vec![
+                "Roboto".to_string(),
+                "Noto Sans JP".to_string(),
+                "noto color emoji".to_string(),
+            ]
+        };
+
+        assert_eq!(cache.family_for(Script::Kana, "ja", installed), Some("Noto Sans JP"));
+        assert_eq!(cache.family_for(Script::Emoji, "", installed), Some("Noto Color Emoji"));
+        assert_eq!(cache.family_for(Script::Hangul, "ko", installed), None);
+        assert_eq!(lookups.get(), 1, "the installed families are listed once");
+
+        cache.invalidate();
+        cache.family_for(Script::Kana, "ja", installed);
+        assert_eq!(lookups.get(), 2);
+    }
+
+    #[test]
+    fn japanese_text_falls_back_to_one_font() {
+        let mut cache = FallbackCache::new();
+        let installed = || vec!["Noto Sans CJK JP".to_string(), "Noto Sans CJK SC".to_string()];
+
+        let text = "Tokyo 東京とうきょう";
+        let runs = cache.fallback_runs(text, None, installed);
+        assert_eq!(
+            runs,
+            [
+                FallbackRun {
+                    range: 0..6,
+                    family: None
+                },
+                FallbackRun {
+                    range: 6..text.len(),
+                    family: Some("Noto Sans CJK JP".to_string())
+                },
+            ]
+        );
+    }
+}
@@ -1,5 +1,9 @@
+pub mod cache;
 pub mod cosmic_system;
+pub mod fallback;
+pub mod metrics;
 pub mod parley_system;
+pub mod web_fonts;
 
 #[cfg(feature = "pango")]
 pub mod pango_system;
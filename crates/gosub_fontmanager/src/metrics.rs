@@ -0,0 +1,202 @@
+//! Font metrics read straight from the SFNT tables, so every font system reports the same
+//! numbers for the same font file whatever its engine exposes.
+
+use crate::parley_system::split_css_families;
+use gosub_interface::font_system::{FontMetrics, FontQuery, FontSystem, TextStyle};
+
+/// `fsSelection` bit saying the typographic ascender/descender/line gap are the ones to use
+const USE_TYPO_METRICS: u16 = 1 << 7;
+
+/// Metrics of the font `style` resolves to in `fs`, at `style.size`.
+pub fn resolved_metrics(fs: &mut dyn FontSystem, style: &TextStyle) -> Option<FontMetrics> {
+    let families = split_css_families(&style.family);
+    let query = FontQuery {
+        families: &families,
+        style: style.style,
+        weight: style.weight,
+        stretch: style.stretch,
+        lang: style.lang.as_deref(),
+    };
+    let font = fs.resolve(&query).ok()?;
+    sfnt_metrics(font.blob.as_u8(), font.blob.index, style.size)
+}
+
+/// Reads the metrics of face `index` of the SFNT (or collection) `data` at `size` px. Only `head`
+/// is required; missing `OS/2` and `post` values fall back to the conventional fractions of the
+/// em (CSS uses half an em for a missing x-height).
+pub fn sfnt_metrics(data: &[u8], index: u32, size: f32) -> Option<FontMetrics> {
+    let tables = Tables::parse(data, index)?;
+
+    let head = tables.get(b"head")?;
+    let units_per_em = read_u16(head, 18).filter(|&u| u > 0)?;
+    let scale = size / f32::from(units_per_em);
+    let em = |fraction: f32| size * fraction;
+
+    let hhea = tables.get(b"hhea");
+    let os2 = tables.get(b"OS/2");
+    let post = tables.get(b"post");
+
+    let hhea_metrics = hhea.and_then(|t| Some((read_i16(t, 4)?, read_i16(t, 6)?, read_i16(t, 8)?)));
+    let typo_metrics = os2.and_then(|t| Some((read_i16(t, 68)?, read_i16(t, 70)?, read_i16(t, 72)?)));
+    let use_typo = os2
+        .and_then(|t| read_u16(t, 62))
+        .is_some_and(|f| f & USE_TYPO_METRICS != 0);
+    let (ascender, descender, line_gap) = match (hhea_metrics, typo_metrics) {
+        (_, Some(typo)) if use_typo => typo,
+        (Some(hhea), _) if hhea.0 != 0 || hhea.1 != 0 => hhea,
+        (_, Some(typo)) => typo,
+        _ => {
+            let os2 = os2?;
+            (
+                read_u16(os2, 74)? as i16,
+                (read_u16(os2, 76)? as i16).saturating_neg(),
+                0,
+            )
+        }
+    };
+
+    // sxHeight and sCapHeight only exist from OS/2 version 2 on.
+    let os2_v2 = os2.filter(|t| read_u16(t, 0).is_some_and(|version| version >= 2));
+    let positive = |value: Option<i16>| value.filter(|&v| v > 0).map(|v| f32::from(v) * scale);
+
+    let x_height = positive(os2_v2.and_then(|t| read_i16(t, 86))).unwrap_or_else(|| em(0.5));
+    let cap_height = positive(os2_v2.and_then(|t| read_i16(t, 88))).unwrap_or_else(|| em(0.7));
+    let avg_char_width = positive(os2.and_then(|t| read_i16(t, 2))).unwrap_or_else(|| em(0.5));
+
+    let underline_size = positive(post.and_then(|t| read_i16(t, 10))).unwrap_or_else(|| size / 14.0);
+    let underline_offset = post
+        .and_then(|t| read_i16(t, 8))
+        .filter(|&v| v != 0)
+        .map_or_else(|| em(0.1), |v| -f32::from(v) * scale);
+    let strikethrough_size = positive(os2.and_then(|t| read_i16(t, 26))).unwrap_or(underline_size);
+    let strikethrough_offset = os2
+        .and_then(|t| read_i16(t, 28))
+        .filter(|&v| v > 0)
+        .map_or_else(|| -x_height / 2.0 - strikethrough_size / 2.0, |v| -f32::from(v) * scale);
+
+    Some(FontMetrics {
+        ascent: f32::from(ascender) * scale,
+        descent: -f32::from(descender) * scale,
+        line_gap: f32::from(line_gap.max(0)) * scale,
+        x_height,
+        cap_height,
+        underline_offset,
+        underline_size,
+        strikethrough_offset,
+        strikethrough_size,
+        avg_char_width,
+    })
+}
+
+/// The table directory of one face
+struct Tables<'a> {
+    data: &'a [u8],
+    /// Offset of the face's offset table
+    base: usize,
+    count: usize,
+}
+
+impl<'a> Tables<'a> {
+    fn parse(data: &'a [u8], index: u32) -> Option<Self> {
+        let base = if data.get(0..4)? == b"ttcf" {
+            if index >= read_u32(data, 8)? {
+                return None;
+            }
+            read_u32(data, 12 + 4 * index as usize)? as usize
+        } else if index == 0 {
+            0
+        } else {
+            return None;
+        };
+        let count = usize::from(read_u16(data, base + 4)?);
+        Some(Self { data, base, count })
+    }
+
+    fn get(&self, tag: &[u8; 4]) -> Option<&'a [u8]> {
+        (0..self.count).find_map(|i| {
+            let record = self.base + 12 + i * 16;
+            if self.data.get(record..record + 4)? != tag {
+                return None;
+            }
+            let offset = read_u32(self.data, record + 8)? as usize;
+            let length = read_u32(self.data, record + 12)? as usize;
+            self.data.get(offset..offset.checked_add(length)?)
+        })
+    }
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_i16(bytes: &[u8], at: usize) -> Option<i16> {
+    read_u16(bytes, at).map(|v| v as i16)
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_bundled_roboto() {
+        let m = sfnt_metrics(gosub_shared::ROBOTO_FONT, 0, 100.0).unwrap();
+        // Roboto's x-height is about 0.53em and its cap height about 0.71em.
+        assert!((m.x_height - 53.0).abs() < 2.0, "x-height {}", m.x_height);
+        assert!((m.cap_height - 71.0).abs() < 2.0, "cap height {}", m.cap_height);
+        assert!(m.ascent > m.cap_height && m.descent > 0.0);
+        assert!(
+            m.underline_offset > 0.0 && m.underline_size > 0.0,
+            "underline sits below the baseline"
+        );
+        assert!(m.strikethrough_offset < 0.0, "strikethrough sits above the baseline");
+        assert!(m.avg_char_width > 30.0 && m.avg_char_width < 70.0);
+        assert!(m.normal_line_height() > 100.0);
+    }
+
+    /// A font with only `head` and `hhea`, like some stripped-down web font subsets
+    fn minimal_font() -> Vec<u8> {
+        let mut head = vec![0u8; 54];
+        head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+        let mut hhea = vec![0u8; 36];
+        hhea[4..6].copy_from_slice(&800i16.to_be_bytes());
+        hhea[6..8].copy_from_slice(&(-200i16).to_be_bytes());
+        hhea[8..10].copy_from_slice(&100i16.to_be_bytes());
+
+        let tables: [(&[u8; 4], &[u8]); 2] = [(b"head", &head), (b"hhea", &hhea)];
+        let mut font = vec![0, 1, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0];
+        let mut offset = 12 + 16 * tables.len();
+        for (tag, table) in &tables {
+            font.extend_from_slice(*tag);
+            font.extend_from_slice(&[0; 4]);
+            font.extend_from_slice(&(offset as u32).to_be_bytes());
+            font.extend_from_slice(&(table.len() as u32).to_be_bytes());
+            offset += table.len();
+        }
+        for (_, table) in &tables {
+            font.extend_from_slice(table);
+        }
+        font
+    }
+
+    #[test]
+    fn missing_tables_fall_back_to_em_fractions() {
+        // At 1000px one font unit is one pixel.
+        let m = sfnt_metrics(&minimal_font(), 0, 1000.0).unwrap();
+        assert_eq!((m.ascent, m.descent, m.line_gap), (800.0, 200.0, 100.0));
+        assert_eq!(m.normal_line_height(), 1100.0);
+        assert_eq!(m.x_height, 500.0);
+        assert_eq!(m.cap_height, 700.0);
+        assert_eq!(m.avg_char_width, 500.0);
+        assert_eq!(m.underline_offset, 100.0);
+
+        assert!(
+            sfnt_metrics(&minimal_font(), 1, 20.0).is_none(),
+            "a plain SFNT has one face"
+        );
+        assert!(sfnt_metrics(b"not a font", 0, 20.0).is_none());
+    }
+}
@@ -4,16 +4,17 @@
 //! renderer-independent: it resolves, shapes, and measures; any glyph-painting backend can
 //! consume its output.
 
+use crate::cache::{FontCache, ShapeKey};
 use cow_utils::CowUtils;
 use gosub_interface::font::{FontBlob, FontError, FontStyle};
 use gosub_interface::font_system::{
-    FontQuery, FontSystem, ResolvedFont, RunMetrics, ShapedGlyph, ShapedRun, ShapedText, TextAlign, TextStyle,
+    FontMetrics, FontQuery, FontSystem, GenericFamilies, ResolvedFont, RunMetrics, ShapedGlyph, ShapedRun, ShapedText,
+    TextAlign, TextStyle,
 };
 use gtk4::pango;
 use gtk4::pango::Weight;
 use gtk4::prelude::{FontExt, FontFamilyExt};
 use parking_lot::Mutex;
-use std::collections::HashMap;
 use std::ffi::c_int;
 use std::sync::{Arc, OnceLock};
 
@@ -243,21 +244,21 @@ fn pango_generic_family(name: &str) -> Option<&'static str> {
 /// Obtain a shared instance via [`get`] (which returns the process-wide singleton
 /// initialised by [`init`]) or construct an independent instance with [`new`] and
 /// call [`PangoFontSystem::init_from_gtk_thread`] yourself.
-/// Font file bytes keyed by `(path, ttc index)`.
-type BlobCache = HashMap<(String, u32), Arc<Vec<u8>>>;
-
 pub struct PangoFontSystem {
     system_ui_font: Option<String>,
-    /// Cached font file bytes. Shaping resolves a font per glyph run, and re-reading e.g.
-    /// DejaVu Sans from disk for every text run would hurt; interior mutability keeps the
-    /// read-only-after-init sharing contract of the struct intact.
-    blob_cache: Mutex<BlobCache>,
+    /// Cached font file bytes keyed by `(path, ttc index)`, and shaped text. Shaping resolves a
+    /// font per glyph run, and re-reading e.g. DejaVu Sans from disk for every text run would
+    /// hurt; interior mutability keeps the read-only-after-init sharing contract of the struct
+    /// intact.
+    cache: Mutex<FontCache<(String, u32)>>,
+    generics: GenericFamilies,
 }
 
 impl std::fmt::Debug for PangoFontSystem {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("PangoFontSystem")
             .field("system_ui_font", &self.system_ui_font)
+            .field("generics", &self.generics)
             .finish()
     }
 }
@@ -266,7 +267,8 @@ impl PangoFontSystem {
     pub fn new() -> Self {
         Self {
             system_ui_font: None,
-            blob_cache: Mutex::new(HashMap::new()),
+            cache: Mutex::new(FontCache::default()),
+            generics: GenericFamilies::new(),
         }
     }
 
@@ -281,8 +283,9 @@ impl PangoFontSystem {
     }
 
     /// Walk `families` (comma-separated CSS `font-family` value) and return the
-    /// first family name that Pango knows about, falling back to `"sans"`.
-    pub fn find_available_font(&self, families: &str, ctx: &pango::Context) -> String {
+    /// first family name that Pango knows about, falling back to `"sans"`. A generic is
+    /// tried as its configured families for `lang` first.
+    pub fn find_available_font(&self, families: &str, lang: Option<&str>, ctx: &pango::Context) -> String {
         let available_fonts: Vec<String> = ctx
             .list_families()
             .iter()
@@ -305,6 +308,14 @@ impl PangoFontSystem {
             // in `"Source Serif 4", Georgia, serif`) is skipped and the heading wrongly
             // falls back to the default sans family.
             if let Some(generic) = pango_generic_family(&font_name) {
+                let configured = self
+                    .generics
+                    .lookup(&font_name, lang)
+                    .iter()
+                    .find(|name| available_fonts.contains(&name.cow_to_ascii_lowercase().into_owned()));
+                if let Some(name) = configured {
+                    return name.clone();
+                }
                 return generic.to_string();
             }
 
@@ -327,10 +338,11 @@ impl Default for PangoFontSystem {
 impl PangoFontSystem {
     /// Map a CSS family list onto the names fontconfig understands: `system-ui` becomes the
     /// GSettings-resolved desktop font (or is skipped when unknown), CSS generics become their
-    /// fontconfig aliases, concrete names pass through. Never returns an empty list.
-    fn fc_family_names<'a>(&'a self, families: &[&'a str]) -> Vec<&'a str> {
+    /// configured families for `lang` followed by their fontconfig alias, concrete names pass
+    /// through. Never returns an empty list.
+    fn fc_family_names<'a>(&'a self, families: &[&'a str], lang: Option<&str>) -> Vec<&'a str> {
         let mut out = Vec::new();
-        for name in families {
+        for name in self.generics.expand(families, lang) {
             if name.eq_ignore_ascii_case("system-ui") {
                 if let Some(ref system_font) = self.system_ui_font {
                     out.push(system_font.as_str());
@@ -347,17 +359,10 @@ impl PangoFontSystem {
 
     /// Font file bytes for a fontconfig match, served from the cache when possible.
     fn blob_for_path(&self, path: &str, index: u32) -> Result<FontBlob, FontError> {
-        use std::collections::hash_map::Entry;
-
-        let mut cache = self.blob_cache.lock();
-        let data = match cache.entry((path.to_string(), index)) {
-            Entry::Occupied(e) => Arc::clone(e.get()),
-            Entry::Vacant(e) => {
-                let bytes = std::fs::read(path).map_err(|err| FontError::InvalidFont(format!("read {path}: {err}")))?;
-                Arc::clone(e.insert(Arc::new(bytes)))
-            }
-        };
-        Ok(FontBlob::new(data, index))
+        self.cache.lock().font((path.to_string(), index), || {
+            let bytes = std::fs::read(path).map_err(|err| FontError::InvalidFont(format!("read {path}: {err}")))?;
+            Ok(FontBlob::new(Arc::new(bytes), index))
+        })
     }
 
     /// Build a laid-out Pango layout for `text` in `style` on a throwaway 1×1 surface (no pixels
@@ -373,7 +378,7 @@ impl PangoFontSystem {
         // 96 DPI matches the browser/CSS convention, same as the rasterizer.
         context_set_resolution(&layout.context(), 96.0);
 
-        let family = self.find_available_font(&style.family, &layout.context());
+        let family = self.find_available_font(&style.family, style.lang.as_deref(), &layout.context());
         let mut font_desc = pango::FontDescription::new();
         font_desc.set_family(&family);
         // CSS px → pt (× 72/96), then to Pango units (× SCALE).
@@ -385,6 +390,20 @@ impl PangoFontSystem {
         }
         layout.set_font_description(Some(&font_desc));
         layout.set_text(text);
+        let features = style.feature_settings();
+        if features.is_some() || style.lang.is_some() {
+            let attrs = pango::AttrList::new();
+            if let Some(features) = features {
+                // Pango hands the list to HarfBuzz, which reads the CSS `"liga" 0` syntax.
+                attrs.insert(pango::AttrFontFeatures::new(&features));
+            }
+            if let Some(lang) = style.lang.as_deref() {
+                // The language picks HarfBuzz's language system, e.g. Serbian italic forms or
+                // Urdu digits, and narrows fontconfig's fallback for the script.
+                attrs.insert(pango::AttrLanguage::new(&pango::Language::from_string(lang)));
+            }
+            layout.set_attributes(Some(&attrs));
+        }
         layout.set_wrap(pango::WrapMode::Word);
         match style.align {
             TextAlign::Start => {} // pango's default (left for LTR)
@@ -471,6 +490,7 @@ impl PangoFontSystem {
                         style: style.style,
                         weight: style.weight,
                         stretch: style.stretch,
+                        lang: None,
                     };
                     if let Ok(font) = self.resolve(&query) {
                         runs.push(ShapedRun {
@@ -512,11 +532,13 @@ impl PangoFontSystem {
 /// `TextStyle::line_height` is intentionally not applied during measurement or shaping.
 impl FontSystem for PangoFontSystem {
     fn register_font(&mut self, data: Vec<u8>, family_override: Option<&str>) -> Result<(), FontError> {
-        register_font_via_fontconfig(&data, family_override)
+        register_font_via_fontconfig(&data, family_override)?;
+        self.cache.lock().invalidate_shaped();
+        Ok(())
     }
 
     fn resolve(&mut self, query: &FontQuery<'_>) -> Result<ResolvedFont, FontError> {
-        let names = self.fc_family_names(query.families);
+        let names = self.fc_family_names(query.families, query.lang);
         let matched = fontconfig_match(
             &names,
             to_fc_weight(query.weight.0),
@@ -533,6 +555,19 @@ impl FontSystem for PangoFontSystem {
         })
     }
 
+    fn metrics(&mut self, style: &TextStyle) -> Option<FontMetrics> {
+        crate::metrics::resolved_metrics(self, style)
+    }
+
+    fn set_generic_families(&mut self, families: GenericFamilies) {
+        self.generics = families;
+        self.cache.lock().invalidate_shaped();
+    }
+
+    fn set_cache_budget(&mut self, bytes: usize) {
+        self.cache.lock().set_budget(bytes);
+    }
+
     fn families(&mut self) -> Vec<String> {
         // A throwaway pangocairo context (same construction as `build_layout`) reads the
         // default font map - the fontconfig database, including web fonts registered before
@@ -560,10 +595,16 @@ impl FontSystem for PangoFontSystem {
         if text.is_empty() {
             return ShapedText::empty();
         }
+        let key = ShapeKey::new(text, style);
+        if let Some(shaped) = self.cache.lock().shaped(&key) {
+            return shaped;
+        }
         let Some(layout) = self.build_layout(text, style) else {
             return ShapedText::empty();
         };
-        self.runs_from_layout(&layout, style)
+        let shaped = self.runs_from_layout(&layout, style);
+        self.cache.lock().insert_shaped(key, &shaped);
+        shaped
     }
 
     fn measure(&mut self, text: &str, style: &TextStyle) -> (f32, f32) {
@@ -1,12 +1,14 @@
+use crate::cache::{FontCache, ShapeKey};
+use crate::fallback::{FallbackCache, FallbackRun};
 use cow_utils::CowUtils;
 use gosub_interface::font::{FontBlob, FontError, FontStyle};
 use gosub_interface::font_system::{
-    FontQuery, FontStretch, FontSystem, FontWeight, ResolvedFont, RunMetrics, ShapedGlyph, ShapedRun, ShapedText,
-    TextAlign, TextStyle,
+    FontMetrics, FontQuery, FontStretch, FontSystem, FontWeight, GenericFamilies, ResolvedFont, RunMetrics,
+    ShapedGlyph, ShapedRun, ShapedText, TextAlign, TextStyle,
 };
 use parley::fontique::{Attributes, FontWidth, GenericFamily, QueryFamily, QueryStatus, SourceCache};
 use parley::style::{FontStyle as ParleyStyle, FontWeight as ParleyWeight};
-use parley::{Alignment, AlignmentOptions, FontContext, LayoutContext, PositionedLayoutItem};
+use parley::{Alignment, AlignmentOptions, FontContext, LayoutContext, PositionedLayoutItem, RangedBuilder};
 
 /// A [`FontSystem`] implementation backed by Parley + Fontique.
 ///
@@ -20,6 +22,11 @@ pub struct ParleyFontSystem {
     font_cx: FontContext,
     layout_cx: LayoutContext<()>,
     source_cache: SourceCache,
+    fallback: FallbackCache,
+    generics: GenericFamilies,
+    /// Shaped text by style. Faces come out of fontique without copying, so the font half of
+    /// the cache stays unused.
+    cache: FontCache<()>,
 }
 
 impl std::fmt::Debug for ParleyFontSystem {
@@ -50,6 +57,9 @@ impl ParleyFontSystem {
             font_cx,
             layout_cx: LayoutContext::new(),
             source_cache: SourceCache::new_shared(),
+            fallback: FallbackCache::new(),
+            generics: GenericFamilies::new(),
+            cache: FontCache::default(),
         }
     }
 }
@@ -69,12 +79,19 @@ impl FontSystem for ParleyFontSystem {
         // fontique derives the family name from the font's own `name` table;
         // custom name overrides are not yet supported here.
         self.font_cx.collection.register_fonts(data.into(), None);
+        self.fallback.invalidate();
+        self.cache.invalidate_shaped();
         Ok(())
     }
 
     /// Resolve a CSS font query to a concrete font + its bytes via fontique.
     fn resolve(&mut self, query: &FontQuery<'_>) -> Result<ResolvedFont, FontError> {
-        let families: Vec<QueryFamily> = query.families.iter().map(|&name| css_family_to_query(name)).collect();
+        let families: Vec<QueryFamily> = self
+            .generics
+            .expand(query.families, query.lang)
+            .into_iter()
+            .map(css_family_to_query)
+            .collect();
 
         let attrs = Attributes::new(
             stretch_to_width(query.stretch),
@@ -113,6 +130,19 @@ impl FontSystem for ParleyFontSystem {
         found.ok_or_else(|| FontError::FontNotFound(query.families.join(", ")))
     }
 
+    fn metrics(&mut self, style: &TextStyle) -> Option<FontMetrics> {
+        crate::metrics::resolved_metrics(self, style)
+    }
+
+    fn set_generic_families(&mut self, families: GenericFamilies) {
+        self.generics = families;
+        self.cache.invalidate_shaped();
+    }
+
+    fn set_cache_budget(&mut self, bytes: usize) {
+        self.cache.set_budget(bytes);
+    }
+
     fn families(&mut self) -> Vec<String> {
         let mut out: Vec<String> = self.font_cx.collection.family_names().map(str::to_string).collect();
         out.sort_unstable();
@@ -121,22 +151,30 @@ impl FontSystem for ParleyFontSystem {
     }
 
     /// Shape `text` into positioned glyph runs, resolving `style.family` first so shaping starts
-    /// from the same concrete font that [`FontSystem::measure`] used.
+    /// from the same concrete font that [`FontSystem::measure`] used. Results are cached by text
+    /// and style.
     fn shape(&mut self, text: &str, style: &TextStyle) -> ShapedText {
         if text.is_empty() {
             return ShapedText::empty();
         }
+        let key = ShapeKey::new(text, style);
+        if let Some(shaped) = self.cache.shaped(&key) {
+            return shaped;
+        }
         let families = split_css_families(&style.family);
         let query = FontQuery {
             families: &families,
             style: style.style,
             weight: style.weight,
             stretch: style.stretch,
+            lang: style.lang.as_deref(),
         };
         let Ok(font) = self.resolve(&query) else {
             return ShapedText::empty();
         };
-        self.shape_resolved(text, &font, style)
+        let shaped = self.shape_resolved(text, &font, style);
+        self.cache.insert_shaped(key, &shaped);
+        shaped
     }
 
     /// Measure the bounding box of `text` laid out in `style`, in CSS pixels.
@@ -153,10 +191,12 @@ impl FontSystem for ParleyFontSystem {
             style: style.style,
             weight: style.weight,
             stretch: style.stretch,
+            lang: style.lang.as_deref(),
         };
         let Ok(resolved) = self.resolve(&query) else {
             return (text.chars().count() as f32 * style.size * 0.5, style.size * 1.2);
         };
+        let fallbacks = self.fallback_runs(text, style);
 
         let mut builder = self
             .layout_cx
@@ -165,6 +205,7 @@ impl FontSystem for ParleyFontSystem {
         builder.push_default(parley::StyleProperty::FontFamily(parley::FontFamily::Source(
             resolved.family.as_str().into(),
         )));
+        push_fallbacks(&mut builder, &resolved.family, &fallbacks);
         builder.push_default(parley::StyleProperty::FontWeight(ParleyWeight::new(
             style.weight.0 as f32,
         )));
@@ -175,6 +216,11 @@ impl FontSystem for ParleyFontSystem {
         if style.letter_spacing != 0.0 {
             builder.push_default(parley::StyleProperty::LetterSpacing(style.letter_spacing));
         }
+        if let Some(features) = style.feature_settings() {
+            builder.push_default(parley::StyleProperty::FontFeatures(parley::FontFeatures::Source(
+                features.into(),
+            )));
+        }
         builder.push_default(parley::StyleProperty::Brush(()));
 
         let mut layout = builder.build(text);
@@ -196,6 +242,14 @@ impl FontSystem for ParleyFontSystem {
 }
 
 impl ParleyFontSystem {
+    /// The script runs of `text` and the installed font each falls back to (see
+    /// [`crate::fallback`]).
+    fn fallback_runs(&mut self, text: &str, style: &TextStyle) -> Vec<FallbackRun> {
+        self.fallback.fallback_runs(text, style.lang.as_deref(), || {
+            self.font_cx.collection.family_names().map(str::to_string).collect()
+        })
+    }
+
     /// Shape `text` with an already-resolved font. Layout parameters (size, line height, wrap
     /// width, letter spacing, display scale) come from `style`; the font identity comes from
     /// `font` - which is why measurement and drawing agree when both go through this path.
@@ -203,6 +257,7 @@ impl ParleyFontSystem {
         if text.is_empty() {
             return ShapedText::empty();
         }
+        let fallbacks = self.fallback_runs(text, style);
 
         let mut builder = self
             .layout_cx
@@ -211,6 +266,7 @@ impl ParleyFontSystem {
         builder.push_default(parley::StyleProperty::FontFamily(parley::FontFamily::Source(
             font.family.as_str().into(),
         )));
+        push_fallbacks(&mut builder, &font.family, &fallbacks);
         builder.push_default(parley::StyleProperty::FontWeight(ParleyWeight::new(
             font.weight.0 as f32,
         )));
@@ -223,6 +279,11 @@ impl ParleyFontSystem {
         if style.letter_spacing != 0.0 {
             builder.push_default(parley::StyleProperty::LetterSpacing(style.letter_spacing));
         }
+        if let Some(features) = style.feature_settings() {
+            builder.push_default(parley::StyleProperty::FontFeatures(parley::FontFeatures::Source(
+                features.into(),
+            )));
+        }
         builder.push_default(parley::StyleProperty::Brush(()));
 
         let mut layout = builder.build(text);
@@ -311,6 +372,22 @@ impl ParleyFontSystem {
     }
 }
 
+/// Gives each run that needs a fallback font the family list `primary, fallback`, so parley
+/// splits its glyph runs there and takes whatever `primary` lacks from the fallback picked for
+/// the run's script and language. Shaping and measurement both go through here, so they fall
+/// back alike.
+fn push_fallbacks(builder: &mut RangedBuilder<'_, ()>, primary: &str, runs: &[FallbackRun]) {
+    for run in runs {
+        if let Some(family) = &run.family {
+            let stack = format!("\"{primary}\", \"{family}\"");
+            builder.push(
+                parley::StyleProperty::FontFamily(parley::FontFamily::Source(stack.into())),
+                run.range.clone(),
+            );
+        }
+    }
+}
+
 /// Split a CSS `font-family` value (e.g. `Verdana, Geneva, sans-serif`) into individual family
 /// names, trimming whitespace and matching quotes. A trailing `sans-serif` generic is appended as
 /// an ultimate fallback if the list doesn't already end in a generic, so resolution always has a
@@ -435,4 +512,41 @@ mod tests {
             "letter-spacing should widen the measurement: {base_width} -> {spaced_width}"
         );
     }
+
+    #[test]
+    fn font_features_apply_to_shaping() {
+        use gosub_interface::font_system::FontFeature;
+
+        // The bundled Roboto kerns `AV` pairs, so turning kerning off widens the text.
+        let mut fs = ParleyFontSystem::new();
+        let mut style = TextStyle::new("Roboto", 32.0);
+        let kerned = fs.shape("AVAVAV", &style).width;
+
+        style.features = vec![FontFeature::new(b"kern", 0)];
+        let unkerned = fs.shape("AVAVAV", &style).width;
+        assert!(
+            unkerned > kerned,
+            "kern off must widen AV pairs: {kerned} -> {unkerned}"
+        );
+        let (measured, _) = fs.measure("AVAVAV", &style);
+        assert!((measured - unkerned).abs() < 0.01, "measure must apply features too");
+    }
+
+    #[test]
+    fn configured_generic_families_resolve_per_language() {
+        let mut generics = GenericFamilies::new();
+        generics.set(
+            "fantasy",
+            Some("ja"),
+            vec!["Gosub Missing Family".into(), "Roboto".into()],
+        );
+        let mut fs = ParleyFontSystem::new();
+        fs.set_generic_families(generics);
+
+        let families = ["fantasy"];
+        let mut query = FontQuery::new(&families);
+        query.lang = Some("ja-JP");
+        let resolved = fs.resolve(&query).expect("fantasy must resolve");
+        assert_eq!(resolved.family, "Roboto", "uninstalled configured families are skipped");
+    }
 }
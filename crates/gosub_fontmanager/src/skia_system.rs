@@ -4,10 +4,11 @@
 //! renderer-independent: it resolves, shapes, and measures; any glyph-painting backend can
 //! consume its output.
 
+use crate::cache::{ByteLru, DEFAULT_BUDGET_BYTES};
 use gosub_interface::font::{FontBlob, FontError, FontStyle as CssFontStyle};
 use gosub_interface::font_system::{
-    FontQuery, FontSystem, ResolvedFont, RunMetrics, ShapedGlyph, ShapedRun, ShapedText, TextAlign as GosubTextAlign,
-    TextStyle as GosubTextStyle,
+    FontMetrics, FontQuery, FontSystem, GenericFamilies, ResolvedFont, RunMetrics, ShapedGlyph, ShapedRun, ShapedText,
+    TextAlign as GosubTextAlign, TextStyle as GosubTextStyle,
 };
 use parking_lot::Mutex;
 use skia_safe::textlayout::{
@@ -16,6 +17,7 @@ use skia_safe::textlayout::{
 use skia_safe::{FontMgr, FontStyle};
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, OnceLock};
 
 // ── Registered web fonts ──────────────────────────────────────────────────────
@@ -29,6 +31,8 @@ use std::sync::{Arc, OnceLock};
 struct FontRegistry {
     generation: u64,
     fonts: Vec<(String, Vec<u8>)>,
+    /// The configured families for the CSS generics, shared the same way as the fonts
+    generics: GenericFamilies,
 }
 
 fn registry() -> &'static Mutex<FontRegistry> {
@@ -37,6 +41,7 @@ fn registry() -> &'static Mutex<FontRegistry> {
         Mutex::new(FontRegistry {
             generation: 0,
             fonts: Vec::new(),
+            generics: GenericFamilies::new(),
         })
     })
 }
@@ -155,6 +160,22 @@ pub(crate) fn with_font_collection<R>(f: impl FnOnce(&FontCollection) -> R) -> R
     })
 }
 
+/// `families` with the configured families for `lang` placed in front of each generic in it.
+fn expand_generics(families: &str, lang: Option<&str>) -> String {
+    let reg = registry().lock();
+    if reg.generics.is_empty() {
+        return families.to_string();
+    }
+    let names = split_font_families(families);
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    reg.generics
+        .expand(&names, lang)
+        .iter()
+        .map(|name| format!("\"{name}\""))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 /// Split a CSS `font-family` value (`"Source Serif 4", Georgia, serif`) into its individual
 /// family names, trimmed and unquoted, in priority order. The font system tries each in turn
 /// so the CSS fallback chain (including the generic `serif`/`sans-serif`/`monospace`) is
@@ -213,7 +234,12 @@ thread_local! {
 /// real generic decides instead of the platform default impersonating an unavailable family. If
 /// nothing survives, fall back to the original list so text still draws. Applied to both measure and
 /// draw so they stay on the same faces.
-pub(crate) fn resolve_family_list(families: &str) -> Vec<String> {
+///
+/// Generics are preceded by their configured families for `lang`, which are pruned like any other
+/// name when they aren't installed.
+pub(crate) fn resolve_family_list(families: &str, lang: Option<&str>) -> Vec<String> {
+    let families = expand_generics(families, lang);
+    let families = families.as_str();
     RESOLVED_FAMILIES.with(|cell| {
         let mut cell = cell.borrow_mut();
         let gen = web_font_generation();
@@ -283,27 +309,37 @@ pub(crate) fn resolve_family_list(families: &str) -> Vec<String> {
 /// Cached font-file bytes + collection index for a typeface; `None` when Skia can't hand them back.
 type CachedFontData = Option<(Arc<Vec<u8>>, u32)>;
 
+/// Memory budget of each thread's [`TYPEFACE_BLOBS`], set through
+/// [`FontSystem::set_cache_budget`].
+static TYPEFACE_BLOB_BUDGET: AtomicUsize = AtomicUsize::new(DEFAULT_BUDGET_BYTES);
+
 thread_local! {
     /// Per-thread cache of typeface file bytes: `to_font_data` copies the whole font file, and
     /// shaping asks once per glyph run. `None` results are cached too, so faces whose bytes Skia
-    /// can't hand back aren't retried on every run.
-    static TYPEFACE_BLOBS: RefCell<HashMap<skia_safe::typeface::TypefaceId, CachedFontData>> =
-        RefCell::new(HashMap::new());
+    /// can't hand back aren't retried on every run. Least recently used faces are dropped once
+    /// the cache outgrows its budget.
+    static TYPEFACE_BLOBS: RefCell<ByteLru<skia_safe::typeface::TypefaceId, CachedFontData>> =
+        RefCell::new(ByteLru::new(DEFAULT_BUDGET_BYTES));
 }
 
 /// Raw file bytes + collection index for a typeface, as a [`FontBlob`].
 fn typeface_blob(typeface: &skia_safe::Typeface) -> Option<FontBlob> {
     TYPEFACE_BLOBS.with(|cell| {
-        let mut map = cell.borrow_mut();
-        let entry = map.entry(typeface.unique_id()).or_insert_with(|| {
-            typeface
-                .to_font_data()
-                .map(|(data, index)| (Arc::new(data), index as u32))
-        });
-        entry.as_ref().map(|(data, index)| {
-            let bytes: Arc<Vec<u8>> = Arc::clone(data);
-            FontBlob::new(bytes, *index)
-        })
+        let mut cache = cell.borrow_mut();
+        cache.set_budget(TYPEFACE_BLOB_BUDGET.load(Ordering::Relaxed));
+        let id = typeface.unique_id();
+        let data = match cache.get(&id) {
+            Some(data) => data.clone(),
+            None => {
+                let data = typeface
+                    .to_font_data()
+                    .map(|(data, index)| (Arc::new(data), index as u32));
+                let size = data.as_ref().map_or(0, |(bytes, _)| bytes.len());
+                cache.insert(id, data.clone(), size);
+                data
+            }
+        };
+        data.map(|(bytes, index)| FontBlob::new(bytes, index))
     })
 }
 
@@ -346,9 +382,19 @@ fn build_style_paragraph(fc: &FontCollection, text: &str, style: &GosubTextStyle
     if style.letter_spacing != 0.0 {
         ts.set_letter_spacing(style.letter_spacing);
     }
+    // OpenType features (ligatures, kerning, `font-feature-settings`) change glyph selection and
+    // advances, so they are part of the measured paragraph as well.
+    for feature in &style.features {
+        let tag: String = feature.tag.iter().map(|&b| b as char).collect();
+        ts.add_font_feature(tag, feature.value as i32);
+    }
+    // The locale selects the OpenType language system and steers Skia's per-script fallback.
+    if let Some(lang) = style.lang.as_deref() {
+        ts.set_locale(lang);
+    }
     // Pass the pruned family list so Skia's FontCollection reaches the real generic instead
     // of letting an unavailable leading family capture the platform default.
-    ts.set_font_families(&resolve_family_list(&style.family));
+    ts.set_font_families(&resolve_family_list(&style.family, style.lang.as_deref()));
     ts.set_font_style(FontStyle::new(
         skia_safe::font_style::Weight::from(style.weight.0 as i32),
         skia_safe::font_style::Width::NORMAL,
@@ -402,7 +448,7 @@ impl FontSystem for SkiaFontSystem {
         let joined = query.families.join(", ");
         // Prune the list the same way measure/draw do, and end on the sans-serif generic so
         // resolution always has a last resort.
-        let mut names = resolve_family_list(&joined);
+        let mut names = resolve_family_list(&joined, query.lang);
         if !names.iter().any(|n| n.eq_ignore_ascii_case("sans-serif")) {
             names.push("sans-serif".to_string());
         }
@@ -429,6 +475,23 @@ impl FontSystem for SkiaFontSystem {
         Err(FontError::FontNotFound(joined))
     }
 
+    fn metrics(&mut self, style: &GosubTextStyle) -> Option<FontMetrics> {
+        crate::metrics::resolved_metrics(self, style)
+    }
+
+    /// Skia keeps the mapping process-wide, next to the registered web fonts, so every thread's
+    /// measure and draw paths see it. The resolved-family cache is keyed by the expanded list, so
+    /// a new mapping needs no invalidation.
+    fn set_generic_families(&mut self, families: GenericFamilies) {
+        registry().lock().generics = families;
+    }
+
+    /// Skia shapes through textlayout's own caches; the budget bounds the face bytes each
+    /// thread copies out of Skia.
+    fn set_cache_budget(&mut self, bytes: usize) {
+        TYPEFACE_BLOB_BUDGET.store(bytes, Ordering::Relaxed);
+    }
+
     fn families(&mut self) -> Vec<String> {
         // System fonts plus the registered web fonts, which live in a separate provider
         // (`web_font_mgr`) rather than the platform font manager.
@@ -622,7 +685,7 @@ mod tests {
         // `matchFamilyStyle()` (e.g. FreeMono instead of DejaVu Sans Mono for `monospace`),
         // so `resolve_family_list` must hand textlayout the concrete family name.
         for stack in ["monospace", "ui-monospace, SFMono-Regular, Menlo, Consolas, monospace"] {
-            let resolved = resolve_family_list(stack);
+            let resolved = resolve_family_list(stack, None);
             assert_eq!(resolved.len(), 1, "stack '{stack}' resolved to {resolved:?}");
             let name = &resolved[0];
             // If the system has any monospace font, the generic must have been replaced by
@@ -0,0 +1,10 @@
+//! Web fonts: the `@font-face` fonts a page downloads.
+//!
+//! [`loader`] follows the faces of a document from download to registration with the font
+//! system, and [`woff`] unwraps the WOFF/WOFF2 files they usually come in.
+
+pub mod loader;
+pub mod woff;
+
+pub use loader::{FaceState, FontDisplay, FontFetch, FontLoadOutcome, FontLoader, WebFontFace};
+pub use woff::{decode_web_font, FontFormat};
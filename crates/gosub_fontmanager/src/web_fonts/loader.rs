@@ -0,0 +1,385 @@
+//! Tracks the `@font-face` fonts of a document while they download.
+//!
+//! The loader doesn't fetch anything itself: [`FontLoader::start`] hands out the requests to
+//! make, and the embedder (the tab worker, through its fetcher) reports each response back with
+//! [`FontLoader::finish`]. That decodes and registers the font, moves on to the next `src` when
+//! a source fails, and applies the face's `font-display` periods: while a face is in its block
+//! period the page waits for it ([`FontLoader::is_blocking`]), and a font that arrives after its
+//! swap period is dropped instead of shifting text that is already on screen.
+
+use super::decode_web_font;
+use gosub_interface::font_system::FontSystem;
+use std::time::{Duration, Instant};
+
+/// CSS `font-display`: how long text waits for a web font, and how long after that the font
+/// may still replace the fallback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FontDisplay {
+    #[default]
+    Auto,
+    Block,
+    Swap,
+    Fallback,
+    Optional,
+}
+
+impl FontDisplay {
+    /// Parses a `font-display` keyword.
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        [
+            ("auto", Self::Auto),
+            ("block", Self::Block),
+            ("swap", Self::Swap),
+            ("fallback", Self::Fallback),
+            ("optional", Self::Optional),
+        ]
+        .into_iter()
+        .find(|(name, _)| value.eq_ignore_ascii_case(name))
+        .map(|(_, display)| display)
+    }
+
+    /// How long text in this face waits for the font before the fallback is used.
+    #[must_use]
+    pub fn block_period(self) -> Duration {
+        match self {
+            Self::Auto | Self::Block => Duration::from_secs(3),
+            Self::Swap => Duration::ZERO,
+            Self::Fallback | Self::Optional => Duration::from_millis(100),
+        }
+    }
+
+    /// How long after the block period the font still replaces the fallback; `None` is forever.
+    #[must_use]
+    pub fn swap_period(self) -> Option<Duration> {
+        match self {
+            Self::Auto | Self::Block | Self::Swap => None,
+            Self::Fallback => Some(Duration::from_secs(3)),
+            Self::Optional => Some(Duration::ZERO),
+        }
+    }
+}
+
+/// One `@font-face` rule to load.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebFontFace {
+    /// The family CSS refers to the face by
+    pub family: String,
+    /// The resolved URLs of its `src`, tried in order
+    pub sources: Vec<String>,
+    pub display: FontDisplay,
+}
+
+/// Where a face is at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaceState {
+    Loading,
+    /// Registered with the font system
+    Loaded,
+    /// None of its sources gave a usable font
+    Failed,
+    /// It arrived after its swap period and isn't used
+    Expired,
+}
+
+/// A download the embedder should make for the loader, reported back with
+/// [`FontLoader::finish`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FontFetch {
+    generation: u64,
+    face: usize,
+    source: usize,
+    /// What to download
+    pub url: String,
+}
+
+/// What a finished download came to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FontLoadOutcome {
+    /// The font was registered; text in `family` should be laid out again.
+    Loaded { family: String },
+    /// The source didn't work out; download the next one.
+    Retry { fetch: FontFetch, error: String },
+    /// The last source didn't work out either.
+    Failed { family: String, error: String },
+    /// The font came in after its swap period and was dropped.
+    Expired { family: String },
+    /// The download was for the faces of an earlier [`FontLoader::start`].
+    Stale,
+}
+
+/// The web fonts of the current document, see the [module docs](self).
+#[derive(Debug, Default)]
+pub struct FontLoader {
+    /// Bumped by every `start`, so downloads for an earlier document are told apart
+    generation: u64,
+    /// When the current faces started loading; their periods count from here
+    started: Option<Instant>,
+    faces: Vec<(WebFontFace, FaceState)>,
+}
+
+impl FontLoader {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts loading `faces` in place of the ones loading so far, returning the first download
+    /// of each. Faces without sources fail right away.
+    pub fn start(&mut self, faces: Vec<WebFontFace>, now: Instant) -> Vec<FontFetch> {
+        self.generation += 1;
+        self.started = Some(now);
+        self.faces = faces
+            .into_iter()
+            .map(|face| {
+                let state = if face.sources.is_empty() {
+                    FaceState::Failed
+                } else {
+                    FaceState::Loading
+                };
+                (face, state)
+            })
+            .collect();
+
+        (0..self.faces.len()).filter_map(|face| self.fetch(face, 0)).collect()
+    }
+
+    /// Takes in the response to `fetch`: the font bytes, or why there are none. A font that
+    /// decodes is registered with `fonts` under the face's family, unless it is too late.
+    pub fn finish(
+        &mut self,
+        fetch: FontFetch,
+        response: Result<Vec<u8>, String>,
+        fonts: &mut dyn FontSystem,
+        now: Instant,
+    ) -> FontLoadOutcome {
+        if fetch.generation != self.generation {
+            return FontLoadOutcome::Stale;
+        }
+        let expired = self.swap_deadline(fetch.face).is_some_and(|deadline| now > deadline);
+        let Some((face, state)) = self.faces.get_mut(fetch.face) else {
+            return FontLoadOutcome::Stale;
+        };
+        if *state != FaceState::Loading {
+            return FontLoadOutcome::Stale;
+        }
+        let family = face.family.clone();
+        if expired {
+            *state = FaceState::Expired;
+            return FontLoadOutcome::Expired { family };
+        }
+
+        let registered = response
+            .and_then(|bytes| decode_web_font(bytes).map_err(|e| format!("{e:?}")))
+            .and_then(|font| fonts.register_font(font, Some(&family)).map_err(|e| format!("{e:?}")));
+        match registered {
+            Ok(()) => {
+                *state = FaceState::Loaded;
+                FontLoadOutcome::Loaded { family }
+            }
+            Err(error) => match self.fetch(fetch.face, fetch.source + 1) {
+                Some(fetch) => FontLoadOutcome::Retry { fetch, error },
+                None => {
+                    if let Some((_, state)) = self.faces.get_mut(fetch.face) {
+                        *state = FaceState::Failed;
+                    }
+                    FontLoadOutcome::Failed { family, error }
+                }
+            },
+        }
+    }
+
+    /// Whether a face is still in its block period, so text should wait for it.
+    #[must_use]
+    pub fn is_blocking(&self, now: Instant) -> bool {
+        let Some(started) = self.started else {
+            return false;
+        };
+        self.faces
+            .iter()
+            .any(|(face, state)| *state == FaceState::Loading && now < started + face.display.block_period())
+    }
+
+    /// When the last block period ends, for waking up to paint with the fallback.
+    #[must_use]
+    pub fn block_deadline(&self) -> Option<Instant> {
+        let started = self.started?;
+        self.faces
+            .iter()
+            .filter(|(_, state)| *state == FaceState::Loading)
+            .map(|(face, _)| started + face.display.block_period())
+            .max()
+    }
+
+    /// Whether any face is still downloading.
+    #[must_use]
+    pub fn is_loading(&self) -> bool {
+        self.faces.iter().any(|(_, state)| *state == FaceState::Loading)
+    }
+
+    /// The faces and where each is at.
+    pub fn faces(&self) -> impl Iterator<Item = (&WebFontFace, FaceState)> {
+        self.faces.iter().map(|(face, state)| (face, *state))
+    }
+
+    /// The download of `source` of `face`, if it has that many
+    fn fetch(&self, face: usize, source: usize) -> Option<FontFetch> {
+        let url = self.faces.get(face)?.0.sources.get(source)?.clone();
+        Some(FontFetch {
+            generation: self.generation,
+            face,
+            source,
+            url,
+        })
+    }
+
+    /// When `face` stops being swapped in, if ever
+    fn swap_deadline(&self, face: usize) -> Option<Instant> {
+        let display = self.faces.get(face)?.0.display;
+        Some(self.started? + display.block_period() + display.swap_period()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gosub_interface::font::FontError;
+    use gosub_interface::font_system::{FontQuery, ResolvedFont, ShapedText, TextStyle};
+
+    /// Remembers the families registered with it
+    #[derive(Default)]
+    struct Registry(Vec<String>);
+
+    impl FontSystem for Registry {
+        fn register_font(&mut self, _data: Vec<u8>, family: Option<&str>) -> Result<(), FontError> {
+            self.0.push(family.unwrap_or_default().to_string());
+            Ok(())
+        }
+
+        fn resolve(&mut self, query: &FontQuery<'_>) -> Result<ResolvedFont, FontError> {
+            Err(FontError::FontNotFound(query.families.join(", ")))
+        }
+
+        fn families(&mut self) -> Vec<String> {
+            self.0.clone()
+        }
+
+        fn shape(&mut self, _text: &str, _style: &TextStyle) -> ShapedText {
+            ShapedText::empty()
+        }
+    }
+
+    /// A stand-in for a font: what matters is that it sniffs as one
+    fn font() -> Vec<u8> {
+        b"OTTO and some tables".to_vec()
+    }
+
+    fn face(family: &str, sources: &[&str], display: FontDisplay) -> WebFontFace {
+        WebFontFace {
+            family: family.to_string(),
+            sources: sources.iter().map(|s| s.to_string()).collect(),
+            display,
+        }
+    }
+
+    #[test]
+    fn parses_font_display() {
+        assert_eq!(FontDisplay::parse("swap"), Some(FontDisplay::Swap));
+        assert_eq!(FontDisplay::parse(" Optional "), Some(FontDisplay::Optional));
+        assert_eq!(FontDisplay::parse("sometimes"), None);
+    }
+
+    #[test]
+    fn falls_back_to_the_next_source() {
+        let mut loader = FontLoader::new();
+        let mut fonts = Registry::default();
+        let now = Instant::now();
+
+        let fetches = loader.start(vec![face("Serif 4", &["a.woff2", "a.ttf"], FontDisplay::Auto)], now);
+        assert_eq!(fetches.len(), 1);
+        assert_eq!(fetches[0].url, "a.woff2");
+
+        let next = match loader.finish(fetches[0].clone(), Ok(b"<html>".to_vec()), &mut fonts, now) {
+            FontLoadOutcome::Retry { fetch, .. } => fetch,
+            other => panic!("expected a retry, got {other:?}"),
+        };
+        assert_eq!(next.url, "a.ttf");
+        assert!(loader.is_loading());
+
+        assert_eq!(
+            loader.finish(next, Ok(font()), &mut fonts, now),
+            FontLoadOutcome::Loaded {
+                family: "Serif 4".into()
+            }
+        );
+        assert_eq!(fonts.0, ["Serif 4"]);
+        assert!(!loader.is_loading());
+    }
+
+    #[test]
+    fn fails_after_the_last_source() {
+        let mut loader = FontLoader::new();
+        let fetches = loader.start(vec![face("Gone", &["gone.woff"], FontDisplay::Auto)], Instant::now());
+        let outcome = loader.finish(
+            fetches[0].clone(),
+            Err("404".into()),
+            &mut Registry::default(),
+            Instant::now(),
+        );
+        assert!(matches!(outcome, FontLoadOutcome::Failed { ref error, .. } if error == "404"));
+        assert_eq!(loader.faces().next().map(|(_, state)| state), Some(FaceState::Failed));
+    }
+
+    #[test]
+    fn font_display_periods() {
+        let mut loader = FontLoader::new();
+        let mut fonts = Registry::default();
+        let start = Instant::now();
+        let fetches = loader.start(
+            vec![
+                face("Optional", &["o.ttf"], FontDisplay::Optional),
+                face("Fallback", &["f.ttf"], FontDisplay::Fallback),
+            ],
+            start,
+        );
+
+        assert!(loader.is_blocking(start));
+        assert_eq!(loader.block_deadline(), Some(start + Duration::from_millis(100)));
+        let later = start + Duration::from_secs(1);
+        assert!(
+            !loader.is_blocking(later),
+            "the fallback is shown after the block period"
+        );
+
+        // Too late for `optional`, but within the swap period of `fallback`.
+        assert_eq!(
+            loader.finish(fetches[0].clone(), Ok(font()), &mut fonts, later),
+            FontLoadOutcome::Expired {
+                family: "Optional".into()
+            }
+        );
+        assert_eq!(
+            loader.finish(fetches[1].clone(), Ok(font()), &mut fonts, later),
+            FontLoadOutcome::Loaded {
+                family: "Fallback".into()
+            }
+        );
+        assert_eq!(fonts.0, ["Fallback"]);
+    }
+
+    #[test]
+    fn downloads_for_an_earlier_document_are_stale() {
+        let mut loader = FontLoader::new();
+        let now = Instant::now();
+        let old = loader.start(vec![face("Old", &["old.ttf"], FontDisplay::Swap)], now);
+        loader.start(vec![face("New", &["new.ttf"], FontDisplay::Swap)], now);
+
+        let mut fonts = Registry::default();
+        assert_eq!(
+            loader.finish(old[0].clone(), Ok(font()), &mut fonts, now),
+            FontLoadOutcome::Stale
+        );
+        assert!(fonts.0.is_empty());
+    }
+}
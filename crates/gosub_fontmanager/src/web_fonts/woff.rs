@@ -0,0 +1,291 @@
+//! Unwrapping downloaded web fonts into the raw SFNT (TrueType/OpenType) bytes the font systems
+//! decode.
+//!
+//! WOFF wraps the tables of an SFNT, each optionally zlib-compressed; WOFF2 Brotli-compresses
+//! them as a whole and stores `glyf`/`loca` in a transformed form. Neither is understood by every
+//! font system (Skia and fontconfig don't read WOFF2), so fonts are unwrapped before they are
+//! registered.
+
+use flate2::read::ZlibDecoder;
+use gosub_interface::font::FontError;
+use std::collections::HashMap;
+use std::io::Read;
+
+/// Size of the WOFF header, after which the table directory starts
+const WOFF_HEADER_LEN: usize = 44;
+/// Size of a WOFF table directory entry
+const WOFF_ENTRY_LEN: usize = 20;
+
+/// The container a font file comes in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontFormat {
+    /// A bare TrueType/OpenType font or collection
+    Sfnt,
+    /// WOFF 1.0
+    Woff,
+    /// WOFF 2.0
+    Woff2,
+}
+
+impl FontFormat {
+    /// Tells the format from the first bytes of a font file, or `None` when it isn't a font.
+    #[must_use]
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        match bytes.get(..4)? {
+            b"wOFF" => Some(Self::Woff),
+            b"wOF2" => Some(Self::Woff2),
+            [0, 1, 0, 0] | b"OTTO" | b"true" | b"ttcf" => Some(Self::Sfnt),
+            _ => None,
+        }
+    }
+}
+
+/// Unwraps a downloaded font into SFNT bytes. SFNT passes through unchanged; anything that isn't
+/// a font, or a WOFF/WOFF2 file that doesn't decode, is an [`FontError::InvalidFont`].
+pub fn decode_web_font(bytes: Vec<u8>) -> Result<Vec<u8>, FontError> {
+    match FontFormat::sniff(&bytes) {
+        Some(FontFormat::Sfnt) => Ok(bytes),
+        Some(FontFormat::Woff) => woff_to_sfnt(&bytes),
+        Some(FontFormat::Woff2) => woff2_to_sfnt(&bytes),
+        None => Err(FontError::InvalidFont(
+            "not a TrueType, OpenType, WOFF or WOFF2 font".into(),
+        )),
+    }
+}
+
+/// Unpacks a WOFF 1.0 font: reads the table directory and inflates each table that is stored
+/// compressed (a table whose compressed length equals its original length is stored as is).
+fn woff_to_sfnt(bytes: &[u8]) -> Result<Vec<u8>, FontError> {
+    let invalid = |what: &str| FontError::InvalidFont(format!("WOFF: {what}"));
+
+    let flavor = read_u32(bytes, 4).ok_or_else(|| invalid("truncated header"))?;
+    let num_tables = read_u16(bytes, 12).ok_or_else(|| invalid("truncated header"))?;
+
+    let mut tables = HashMap::new();
+    for index in 0..usize::from(num_tables) {
+        let entry = WOFF_HEADER_LEN + index * WOFF_ENTRY_LEN;
+        let (Some(tag), Some(offset), Some(comp_length), Some(orig_length)) = (
+            read_u32(bytes, entry),
+            read_u32(bytes, entry + 4),
+            read_u32(bytes, entry + 8),
+            read_u32(bytes, entry + 12),
+        ) else {
+            return Err(invalid("truncated table directory"));
+        };
+
+        let start = offset as usize;
+        let data = start
+            .checked_add(comp_length as usize)
+            .and_then(|end| bytes.get(start..end))
+            .ok_or_else(|| invalid("table outside the file"))?;
+
+        let table = if comp_length < orig_length {
+            let mut table = Vec::new();
+            ZlibDecoder::new(data)
+                .take(u64::from(orig_length))
+                .read_to_end(&mut table)
+                .map_err(|e| invalid(&e.to_string()))?;
+            if table.len() != orig_length as usize {
+                return Err(invalid("table inflates to the wrong size"));
+            }
+            table
+        } else if comp_length == orig_length {
+            data.to_vec()
+        } else {
+            return Err(invalid("table is larger compressed than it is"));
+        };
+        tables.insert(tag, table.into_boxed_slice());
+    }
+
+    Ok(assemble_sfnt(flavor, tables))
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Decompress a WOFF2 font to a flat SFNT (TTF/OTF) byte buffer. allsorts handles the Brotli
+/// decompression and the `glyf`/`loca` transform reconstruction; we then re-assemble the
+/// reconstructed tables into the on-disk SFNT layout (offset table + table directory + 4-byte
+/// aligned table data) that font backends expect.
+fn woff2_to_sfnt(bytes: &[u8]) -> Result<Vec<u8>, FontError> {
+    use allsorts::binary::read::ReadScope;
+    use allsorts::woff2::Woff2Font;
+
+    let font = ReadScope::new(bytes)
+        .read::<Woff2Font<'_>>()
+        .map_err(|e| FontError::InvalidFont(format!("WOFF2: {e:?}")))?;
+    let sfnt_version = font.flavor();
+    let tables = font
+        .table_provider(0)
+        .map_err(|e| FontError::InvalidFont(format!("WOFF2: {e:?}")))?
+        .into_tables();
+
+    Ok(assemble_sfnt(sfnt_version, tables))
+}
+
+/// Pack a set of font tables into an SFNT byte buffer per the OpenType spec: a 12-byte offset
+/// table, a 16-byte directory entry per table (sorted by tag), then each table's data padded to
+/// a 4-byte boundary. Per-table checksums are computed; the `head` table's `checkSumAdjustment`
+/// is left as-is (font backends parse without validating it).
+fn assemble_sfnt(sfnt_version: u32, tables: HashMap<u32, Box<[u8]>>) -> Vec<u8> {
+    let mut entries: Vec<(u32, Box<[u8]>)> = tables.into_iter().collect();
+    entries.sort_by_key(|(tag, _)| *tag);
+    let num_tables = entries.len() as u16;
+
+    // Binary-search hint fields: largest power of two <= num_tables.
+    let mut entry_selector = 0u16;
+    while (1u16 << (entry_selector + 1)) <= num_tables {
+        entry_selector += 1;
+    }
+    let search_range = (1u16 << entry_selector) * 16;
+    let range_shift = num_tables.wrapping_mul(16).wrapping_sub(search_range);
+
+    let mut directory = Vec::with_capacity(16 * entries.len());
+    let mut data = Vec::new();
+    let mut offset = 12 + 16 * entries.len();
+    for (tag, table) in &entries {
+        directory.extend_from_slice(&tag.to_be_bytes());
+        directory.extend_from_slice(&sfnt_table_checksum(table).to_be_bytes());
+        directory.extend_from_slice(&(offset as u32).to_be_bytes());
+        directory.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        data.extend_from_slice(table);
+        while data.len() % 4 != 0 {
+            data.push(0);
+        }
+        offset += (table.len() + 3) & !3;
+    }
+
+    let mut out = Vec::with_capacity(12 + directory.len() + data.len());
+    out.extend_from_slice(&sfnt_version.to_be_bytes());
+    out.extend_from_slice(&num_tables.to_be_bytes());
+    out.extend_from_slice(&search_range.to_be_bytes());
+    out.extend_from_slice(&entry_selector.to_be_bytes());
+    out.extend_from_slice(&range_shift.to_be_bytes());
+    out.extend_from_slice(&directory);
+    out.extend_from_slice(&data);
+    out
+}
+
+/// SFNT table checksum: the sum of the table's contents read as big-endian `u32`s, with the
+/// final partial word zero-padded, in wrapping (mod 2^32) arithmetic.
+fn sfnt_table_checksum(data: &[u8]) -> u32 {
+    let mut sum = 0u32;
+    for chunk in data.chunks(4) {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum = sum.wrapping_add(u32::from_be_bytes(word));
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    /// The tables of an SFNT, by tag, as read back from its table directory
+    fn sfnt_tables(sfnt: &[u8]) -> HashMap<u32, Vec<u8>> {
+        let num_tables = read_u16(sfnt, 4).unwrap();
+        (0..usize::from(num_tables))
+            .map(|index| {
+                let entry = 12 + index * 16;
+                let offset = read_u32(sfnt, entry + 8).unwrap() as usize;
+                let length = read_u32(sfnt, entry + 12).unwrap() as usize;
+                (read_u32(sfnt, entry).unwrap(), sfnt[offset..offset + length].to_vec())
+            })
+            .collect()
+    }
+
+    /// Wraps `tables` in a WOFF file, compressing those that shrink
+    fn woff(flavor: u32, tables: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut directory = Vec::new();
+        let mut data = Vec::new();
+        let mut offset = WOFF_HEADER_LEN + WOFF_ENTRY_LEN * tables.len();
+        for (tag, table) in tables {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+            encoder.write_all(table).unwrap();
+            let compressed = encoder.finish().unwrap();
+            let stored = if compressed.len() < table.len() {
+                compressed
+            } else {
+                table.clone()
+            };
+
+            directory.extend_from_slice(*tag);
+            directory.extend_from_slice(&(offset as u32).to_be_bytes());
+            directory.extend_from_slice(&(stored.len() as u32).to_be_bytes());
+            directory.extend_from_slice(&(table.len() as u32).to_be_bytes());
+            directory.extend_from_slice(&0u32.to_be_bytes());
+            offset += stored.len();
+            data.extend_from_slice(&stored);
+        }
+
+        let mut file = b"wOFF".to_vec();
+        file.extend_from_slice(&flavor.to_be_bytes());
+        file.extend_from_slice(&((offset) as u32).to_be_bytes());
+        file.extend_from_slice(&(tables.len() as u16).to_be_bytes());
+        file.resize(WOFF_HEADER_LEN, 0);
+        file.extend_from_slice(&directory);
+        file.extend_from_slice(&data);
+        file
+    }
+
+    #[test]
+    fn sniffs_formats() {
+        assert_eq!(FontFormat::sniff(b"wOF2...."), Some(FontFormat::Woff2));
+        assert_eq!(FontFormat::sniff(b"wOFF...."), Some(FontFormat::Woff));
+        assert_eq!(FontFormat::sniff(&[0, 1, 0, 0, 0]), Some(FontFormat::Sfnt));
+        assert_eq!(FontFormat::sniff(b"OTTO"), Some(FontFormat::Sfnt));
+        assert_eq!(FontFormat::sniff(b"<!DOCTYPE html>"), None);
+        assert_eq!(FontFormat::sniff(b"wO"), None);
+        assert!(decode_web_font(b"<!DOCTYPE html>".to_vec()).is_err());
+    }
+
+    #[test]
+    fn woff_unwraps_to_sfnt() {
+        let repetitive = b"glyph data ".repeat(40);
+        let small = vec![1, 2, 3];
+        let file = woff(0x0001_0000, &[(b"glyf", repetitive.clone()), (b"head", small.clone())]);
+
+        let sfnt = decode_web_font(file).unwrap();
+        assert_eq!(FontFormat::sniff(&sfnt), Some(FontFormat::Sfnt));
+        let tables = sfnt_tables(&sfnt);
+        assert_eq!(tables[&u32::from_be_bytes(*b"glyf")], repetitive);
+        assert_eq!(tables[&u32::from_be_bytes(*b"head")], small);
+    }
+
+    #[test]
+    fn truncated_woff_is_an_error() {
+        let file = woff(0x0001_0000, &[(b"glyf", b"glyph data ".repeat(40))]);
+        assert!(decode_web_font(file[..file.len() - 10].to_vec()).is_err());
+        assert!(decode_web_font(file[..20].to_vec()).is_err());
+    }
+
+    /// Needs a real WOFF2 font, as the transformed `glyf`/`loca` tables can't be made up here.
+    /// The fixture is Open Sans Light Italic (Apache License 2.0, see the license next to it).
+    #[test]
+    fn woff2_unwraps_to_sfnt() {
+        let woff2 = include_bytes!("../../resources/test/OpenSans-LightItalic.woff2").to_vec();
+        assert_eq!(FontFormat::sniff(&woff2), Some(FontFormat::Woff2));
+
+        let sfnt = decode_web_font(woff2).unwrap();
+        assert_eq!(FontFormat::sniff(&sfnt), Some(FontFormat::Sfnt));
+
+        // It must re-parse and expose the core tables a font system reads.
+        use allsorts::binary::read::ReadScope;
+        use allsorts::font_data::FontData;
+        use allsorts::tables::FontTableProvider;
+        let font = ReadScope::new(&sfnt).read::<FontData<'_>>().expect("parse SFNT");
+        let provider = font.table_provider(0).expect("table provider");
+        for tag in [allsorts::tag::HEAD, allsorts::tag::CMAP, allsorts::tag::GLYF] {
+            assert!(provider.has_table(tag), "missing table {tag:#010x}");
+        }
+    }
+}
@@ -32,6 +32,8 @@ pub struct DocumentImpl<C: HasDocument> {
     pub quirks_mode: QuirksMode,
     pub stylesheets: Vec<<C::CssSystem as CssSystem>::Stylesheet>,
     hovered_nodes: parking_lot::RwLock<std::collections::HashSet<NodeId>>,
+    active_nodes: parking_lot::RwLock<std::collections::HashSet<NodeId>>,
+    focused_node: parking_lot::RwLock<Option<NodeId>>,
 }
 
 impl<C: HasDocument> PartialEq for DocumentImpl<C> {
@@ -58,6 +60,8 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
             quirks_mode: QuirksMode::NoQuirks,
             stylesheets: Vec::new(),
             hovered_nodes: parking_lot::RwLock::new(std::collections::HashSet::new()),
+            active_nodes: parking_lot::RwLock::new(std::collections::HashSet::new()),
+            focused_node: parking_lot::RwLock::new(None),
         };
         let root = NodeImpl::new_document(Location::default(), QuirksMode::NoQuirks);
         doc.arena.register_node(root);
@@ -371,6 +375,14 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
     fn is_hovered(&self, id: NodeId) -> bool {
         self.hovered_nodes.read().contains(&id)
     }
+
+    fn is_active(&self, id: NodeId) -> bool {
+        self.active_nodes.read().contains(&id)
+    }
+
+    fn focused(&self) -> Option<NodeId> {
+        *self.focused_node.read()
+    }
 }
 
 // ── Internal helpers (not part of Document trait) ───────────────────────────
@@ -379,7 +391,16 @@ impl<C: HasDocument<Document = Self>> DocumentImpl<C> {
     /// Update the set of hovered nodes to the ancestor chain of `leaf` (inclusive).
     /// Pass `None` to clear hover state. Uses interior mutability so it works through Arc.
     pub fn set_hovered_nodes(&self, leaf: Option<NodeId>) {
-        let mut set = self.hovered_nodes.write();
+        self.fill_ancestor_chain(&mut self.hovered_nodes.write(), leaf);
+    }
+
+    /// Update the set of `:active` nodes to the ancestor chain of `leaf` (inclusive), the node
+    /// the pointer was pressed on. Pass `None` on release.
+    pub fn set_active_nodes(&self, leaf: Option<NodeId>) {
+        self.fill_ancestor_chain(&mut self.active_nodes.write(), leaf);
+    }
+
+    fn fill_ancestor_chain(&self, set: &mut std::collections::HashSet<NodeId>, leaf: Option<NodeId>) {
         set.clear();
         if let Some(mut id) = leaf {
             loop {
@@ -392,6 +413,12 @@ impl<C: HasDocument<Document = Self>> DocumentImpl<C> {
         }
     }
 
+    /// Set the element that has keyboard focus (`None` to blur). Uses interior mutability so it
+    /// works through Arc.
+    pub fn set_focused_node(&self, node: Option<NodeId>) {
+        *self.focused_node.write() = node;
+    }
+
     fn on_document_node_mutation(&mut self, node: &NodeImpl) {
         self.on_document_node_mutation_update_named_id(node);
     }
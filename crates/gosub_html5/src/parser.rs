@@ -111,12 +111,16 @@ impl ActiveElement {
 
 pub struct Html5ParserOptions {
     pub scripting_enabled: bool,
+    /// If false, `<style>` and `<link rel=stylesheet>` elements are only inserted into the tree.
+    /// Embedders that fetch stylesheets themselves load them from there, in tree order.
+    pub load_stylesheets: bool,
 }
 
 impl ParserOptions for Html5ParserOptions {
     fn new(scripting: bool) -> Self {
         Self {
             scripting_enabled: scripting,
+            ..Self::default()
         }
     }
 }
@@ -125,6 +129,7 @@ impl Default for Html5ParserOptions {
     fn default() -> Self {
         Self {
             scripting_enabled: true,
+            load_stylesheets: true,
         }
     }
 }
@@ -157,6 +162,8 @@ pub struct Html5Parser<'tokens, C: HasDocument> {
     form_element: Option<NodeId>,
     /// If true, scripting is enabled
     scripting_enabled: bool,
+    /// If true, stylesheets are loaded and added to the document while parsing
+    load_stylesheets: bool,
     /// if true, we can insert a frameset
     frameset_ok: bool,
     /// Foster parenting flag
@@ -255,18 +262,17 @@ fn meta_charset_encoding(token: &Token) -> Option<Encoding> {
     None
 }
 
-/// Maps a charset label string to our `Encoding` enum.
+/// Maps a charset label string to our `Encoding` enum, by the labels of the Encoding Standard.
 /// Per WHATWG spec §13.2.3.5, a UTF-16 label from a meta element must be
-/// treated as UTF-8 (a truly UTF-16 document would have a BOM detected earlier).
+/// treated as UTF-8 (a truly UTF-16 document would have a BOM detected earlier),
+/// and x-user-defined as windows-1252.
 fn charset_label_to_encoding(label: &str) -> Option<Encoding> {
-    match label.cow_to_ascii_lowercase().as_ref() {
-        "utf-8" | "utf8" | "unicode-1-1-utf-8" => Some(Encoding::UTF8),
-        "us-ascii" | "ascii" | "ansi_x3.4-1968" | "iso-ir-6" | "iso646-us" | "latin1" | "iso-8859-1" => {
-            Some(Encoding::Latin1)
+    match Encoding::from_label(label)? {
+        Encoding::UTF16LE | Encoding::UTF16BE => Some(Encoding::UTF8),
+        Encoding::Legacy(encoding) if encoding == encoding_rs::X_USER_DEFINED => {
+            Some(Encoding::Legacy(encoding_rs::WINDOWS_1252))
         }
-        // UTF-16 from meta → treat as UTF-8 per spec
-        "utf-16" | "utf-16le" | "utf-16be" => Some(Encoding::UTF8),
-        _ => None,
+        encoding => Some(encoding),
     }
 }
 
@@ -278,6 +284,7 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
         error_logger: Rc<RefCell<ErrorLogger>>,
         options: Option<Html5ParserOptions>,
     ) -> Self {
+        let options = options.unwrap_or_default();
         Self {
             tokenizer,
             insertion_mode: InsertionMode::Initial,
@@ -292,7 +299,8 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
             open_elements: Vec::new(),
             head_element: None,
             form_element: None,
-            scripting_enabled: options.unwrap_or_default().scripting_enabled,
+            scripting_enabled: options.scripting_enabled,
+            load_stylesheets: options.load_stylesheets,
             frameset_ok: true,
             foster_parenting: false,
             script_already_started: false,
@@ -332,6 +340,7 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
             head_element: None,
             form_element: None,
             scripting_enabled: true,
+            load_stylesheets: true,
             frameset_ok: true,
             foster_parenting: false,
             script_already_started: false,
@@ -1059,8 +1068,11 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
                         let style_text_node_id = *self.document.children(style_node_id).first().unwrap();
 
                         // Load stylesheet from text node
-                        if let Some(stylesheet) = self.load_inline_stylesheet(CssOrigin::Author, style_text_node_id) {
-                            self.document.add_stylesheet(stylesheet);
+                        if self.load_stylesheets {
+                            if let Some(stylesheet) = self.load_inline_stylesheet(CssOrigin::Author, style_text_node_id)
+                            {
+                                self.document.add_stylesheet(stylesheet);
+                            }
                         }
 
                         self.open_elements.pop();
@@ -4205,6 +4217,9 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
                         }
                     }
                 };
+                if !self.load_stylesheets {
+                    return;
+                }
                 if let Some(stylesheet) = self.load_external_stylesheet(CssOrigin::Author, css_url) {
                     self.document.add_stylesheet(stylesheet);
                 } else {
@@ -4250,6 +4265,25 @@ mod test {
         }};
     }
 
+    #[test]
+    fn meta_charset_labels() {
+        assert_eq!(charset_label_to_encoding("UTF-8"), Some(Encoding::UTF8));
+        assert_eq!(charset_label_to_encoding("utf-16le"), Some(Encoding::UTF8));
+        assert_eq!(
+            charset_label_to_encoding("iso-8859-1"),
+            Some(Encoding::Legacy(encoding_rs::WINDOWS_1252))
+        );
+        assert_eq!(
+            charset_label_to_encoding("x-user-defined"),
+            Some(Encoding::Legacy(encoding_rs::WINDOWS_1252))
+        );
+        assert_eq!(
+            charset_label_to_encoding("euc-kr"),
+            Some(Encoding::Legacy(encoding_rs::EUC_KR))
+        );
+        assert_eq!(charset_label_to_encoding("klingon"), None);
+    }
+
     #[test]
     fn is_in_scope() {
         let mut stream = ByteStream::new(Encoding::UTF8, None);
@@ -1,4 +1,5 @@
 use gosub_shared::byte_stream::Location;
+use gosub_shared::error_report::{self, ErrorReport, ErrorSource};
 use gosub_shared::types::ParseError;
 
 /// Possible parser error enumerated
@@ -176,6 +177,7 @@ impl ErrorLogger {
         }
         self.seen.entry(location).or_default().insert(message.to_string());
 
+        error_report::report(ErrorReport::warning(ErrorSource::Html, message).with_location(location));
         self.errors.push(ParseError {
             message: message.to_string(),
             location,
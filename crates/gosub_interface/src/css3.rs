@@ -18,15 +18,18 @@ pub enum CssOrigin {
 
 /// Hover-sensitivity fingerprints for a set of stylesheets.
 ///
-/// Records which element types/classes/ids appear in a `:hover` compound selector, so the
-/// engine can skip style recalculation when the pointer moves between elements that no `:hover`
-/// rule targets. Computed by the [`CssSystem`] (via [`CssSystem::hover_fingerprints`]) because
+/// Records which element types/classes/ids appear in a `:hover` or `:active` compound selector,
+/// so the engine can skip style recalculation when the pointer moves between (or presses on)
+/// elements that no such rule targets. Computed by the [`CssSystem`] (via [`CssSystem::hover_fingerprints`]) because
 /// only the CSS implementation understands its own selector structure - the engine stays
 /// agnostic of how selectors are represented.
 #[derive(Default, Debug, Clone)]
 pub struct HoverFingerprints {
     /// A bare `:hover` / `*:hover` rule exists - every node is hover-sensitive.
     pub has_universal: bool,
+    /// A `+` or `~` combinator follows a `:hover` compound (`a:hover + p`), so a state change can
+    /// restyle the subject's later siblings, not just its own subtree.
+    pub has_sibling_combinator: bool,
     /// Element type names that appear in a `:hover` compound.
     pub types: std::collections::HashSet<String>,
     /// Class names that appear in a `:hover` compound.
@@ -69,10 +72,21 @@ pub trait CssSystem: Clone + Debug + 'static {
         None
     }
 
+    /// Returns the style rules in `sheets` whose selectors match element `id`, in the order the
+    /// cascade applies them (the rule that wins a conflict last). Only used by inspectors; the
+    /// default implementation reports none.
+    fn matched_rules<C: HasDocument<CssSystem = Self>>(
+        _doc: &C::Document,
+        _id: NodeId,
+        _sheets: &[Self::Stylesheet],
+    ) -> Vec<MatchedRule> {
+        Vec::new()
+    }
+
     fn load_default_useragent_stylesheet() -> Self::Stylesheet;
 
     /// Scan `sheets` and collect the [`HoverFingerprints`] - the element types/classes/ids that
-    /// are the subject of a `:hover` rule. Lets the engine cheaply decide whether a hover change
+    /// are the subject of a `:hover` or `:active` rule. Lets the engine cheaply decide whether a hover change
     /// can affect styling without re-running selector matching.
     fn hover_fingerprints(sheets: &[Self::Stylesheet]) -> HoverFingerprints;
 }
@@ -84,15 +98,49 @@ pub trait CssStylesheet: PartialEq + Debug {
     /// Returns the source URL of the stylesheet
     fn url(&self) -> &str;
 
-    /// `@font-face` web fonts declared in this stylesheet, as
-    /// `(family, source_urls, unicode_range)` tuples. The source URLs are unresolved
-    /// (relative to the stylesheet's own URL); `unicode_range` is the raw descriptor or
-    /// `None` when the face covers all code points.
-    fn font_faces(&self) -> Vec<(String, Vec<String>, Option<String>)> {
+    /// `@font-face` web fonts declared in this stylesheet.
+    fn font_faces(&self) -> Vec<FontFaceRule> {
         Vec::new()
     }
 }
 
+/// An `@font-face` rule, see [`CssStylesheet::font_faces`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontFaceRule {
+    /// The family the face provides (unquoted)
+    pub family: String,
+    /// The `src` URLs in declared order, unresolved (relative to the stylesheet's own URL)
+    pub sources: Vec<String>,
+    /// The raw `unicode-range` descriptor, or `None` when the face covers all code points
+    pub unicode_range: Option<String>,
+    /// The `font-display` keyword, or `None` when it isn't given
+    pub display: Option<String>,
+}
+
+/// A style rule matching an element, see [`CssSystem::matched_rules`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchedRule {
+    /// The selector of the rule that matched, as CSS text (one selector out of a selector list)
+    pub selector: String,
+    /// Specificity of `selector`: id, class and type counts
+    pub specificity: (u32, u32, u32),
+    /// Origin of the stylesheet holding the rule
+    pub origin: CssOrigin,
+    /// URL of the stylesheet holding the rule
+    pub url: String,
+    /// The declarations of the rule, in source order
+    pub declarations: Vec<MatchedDeclaration>,
+}
+
+/// A declaration of a [`MatchedRule`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchedDeclaration {
+    pub property: String,
+    /// The declared value as CSS text
+    pub value: String,
+    pub important: bool,
+}
+
 pub trait CssPropertyMap<S: CssSystem>: Default + Debug + WasmNotSend {
     fn insert_inherited(&mut self, name: &str, value: S::Property);
 
@@ -112,6 +160,116 @@ pub trait CssPropertyMap<S: CssSystem>: Default + Debug + WasmNotSend {
     fn is_dirty(&self) -> bool;
 }
 
+/// A length that mixes units the style system can't combine on its own, such as
+/// `calc(100% - 20px)` or `min(10px, 2em)`. Font-relative parts need the element's font size
+/// and percentages need the containing block, so both are left for layout to resolve.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalcLength {
+    /// `px + em * font-size + percent% * basis`
+    Linear { px: f32, em: f32, percent: f32 },
+    /// The sum of the terms
+    Sum(Vec<CalcLength>),
+    /// A term multiplied by a number
+    Scaled(Box<CalcLength>, f32),
+    /// The smallest of the arguments
+    Min(Vec<CalcLength>),
+    /// The largest of the arguments
+    Max(Vec<CalcLength>),
+}
+
+impl CalcLength {
+    /// Resolves the length to px, with 1em being `font_size` px and percentages taken of
+    /// `basis`.
+    pub fn resolve(&self, font_size: f32, basis: f32) -> f32 {
+        match self {
+            CalcLength::Linear { px, em, percent } => px + em * font_size + percent / 100.0 * basis,
+            CalcLength::Sum(terms) => terms.iter().map(|t| t.resolve(font_size, basis)).sum(),
+            CalcLength::Scaled(term, factor) => term.resolve(font_size, basis) * factor,
+            CalcLength::Min(args) => args
+                .iter()
+                .map(|a| a.resolve(font_size, basis))
+                .fold(f32::INFINITY, f32::min),
+            CalcLength::Max(args) => args
+                .iter()
+                .map(|a| a.resolve(font_size, basis))
+                .fold(f32::NEG_INFINITY, f32::max),
+        }
+    }
+
+    /// The same length with its em parts turned into px, leaving only the percentages open.
+    pub fn at_font_size(&self, font_size: f32) -> CalcLength {
+        let all = |args: &[CalcLength]| args.iter().map(|a| a.at_font_size(font_size)).collect();
+        match self {
+            CalcLength::Linear { px, em, percent } => CalcLength::Linear {
+                px: px + em * font_size,
+                em: 0.0,
+                percent: *percent,
+            },
+            CalcLength::Sum(terms) => CalcLength::Sum(all(terms)),
+            CalcLength::Scaled(term, factor) => CalcLength::Scaled(Box::new(term.at_font_size(font_size)), *factor),
+            CalcLength::Min(args) => CalcLength::Min(all(args)),
+            CalcLength::Max(args) => CalcLength::Max(all(args)),
+        }
+    }
+
+    /// Whether the length depends on the percentage basis.
+    pub fn has_percentage(&self) -> bool {
+        match self {
+            CalcLength::Linear { percent, .. } => *percent != 0.0,
+            CalcLength::Scaled(term, _) => term.has_percentage(),
+            CalcLength::Sum(args) | CalcLength::Min(args) | CalcLength::Max(args) => {
+                args.iter().any(CalcLength::has_percentage)
+            }
+        }
+    }
+}
+
+/// Serializes to a `calc()`, `min()` or `max()` expression.
+impl Display for CalcLength {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn join(f: &mut std::fmt::Formatter<'_>, args: &[CalcLength], sep: &str) -> std::fmt::Result {
+            for (i, arg) in args.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(sep)?;
+                }
+                write!(f, "{arg}")?;
+            }
+            Ok(())
+        }
+
+        match self {
+            CalcLength::Linear { px, em, percent } => {
+                let parts = [(*percent, "%"), (*px, "px"), (*em, "em")]
+                    .into_iter()
+                    .filter(|(v, _)| *v != 0.0)
+                    .map(|(v, unit)| format!("{v}{unit}"))
+                    .collect::<Vec<_>>();
+                if parts.is_empty() {
+                    write!(f, "calc(0px)")
+                } else {
+                    write!(f, "calc({})", parts.join(" + "))
+                }
+            }
+            CalcLength::Sum(terms) => {
+                f.write_str("calc(")?;
+                join(f, terms, " + ")?;
+                f.write_str(")")
+            }
+            CalcLength::Scaled(term, factor) => write!(f, "calc({term} * {factor})"),
+            CalcLength::Min(args) => {
+                f.write_str("min(")?;
+                join(f, args, ", ")?;
+                f.write_str(")")
+            }
+            CalcLength::Max(args) => {
+                f.write_str("max(")?;
+                join(f, args, ", ")?;
+                f.write_str(")")
+            }
+        }
+    }
+}
+
 pub trait CssProperty<S: CssSystem>: Debug + Display + Sized + From<S::Value> {
     fn compute_value(&mut self); // this should probably be removed
 
@@ -129,6 +287,11 @@ pub trait CssProperty<S: CssSystem>: Debug + Display + Sized + From<S::Value> {
 
     fn as_function(&self) -> Option<(&str, &[S::Value])>;
 
+    /// A math function left for layout to resolve, see [`CalcLength`].
+    fn as_calc(&self) -> Option<CalcLength> {
+        None
+    }
+
     fn is_none(&self) -> bool;
 }
 
@@ -141,4 +141,15 @@ pub trait Document<C: HasCssSystem>: Sized + Display + Debug + PartialEq + 'stat
     fn is_hovered(&self, _id: NodeId) -> bool {
         false
     }
+
+    /// Whether `id` is being activated: the pointer was pressed on it or a descendant and not yet
+    /// released (`:active`).
+    fn is_active(&self, _id: NodeId) -> bool {
+        false
+    }
+
+    /// The element that has keyboard focus, if any.
+    fn focused(&self) -> Option<NodeId> {
+        None
+    }
 }
@@ -55,6 +55,8 @@ pub struct FontQuery<'a> {
     pub style: FontStyle,
     pub weight: FontWeight,
     pub stretch: FontStretch,
+    /// Language of the text (BCP 47), which picks the configured families for generics.
+    pub lang: Option<&'a str>,
 }
 
 impl<'a> FontQuery<'a> {
@@ -64,6 +66,7 @@ impl<'a> FontQuery<'a> {
             style: FontStyle::Normal,
             weight: FontWeight::NORMAL,
             stretch: FontStretch::NORMAL,
+            lang: None,
         }
     }
 }
@@ -111,6 +114,45 @@ pub struct RunMetrics {
     pub strikethrough_size: f32,
 }
 
+/// Metrics of a font at a given size, in pixels, read from its `OS/2`, `hhea` and `post` tables
+/// (with the usual fallbacks where a table or field is missing).
+///
+/// `ascent` and `descent` are distances from the baseline, both positive. Decoration offsets
+/// follow [`RunMetrics`]: from the baseline to the top of the stroke, positive downward.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FontMetrics {
+    pub ascent: f32,
+    pub descent: f32,
+    pub line_gap: f32,
+    /// Height of lowercase letters (CSS `ex`)
+    pub x_height: f32,
+    /// Height of capital letters (CSS `cap`)
+    pub cap_height: f32,
+    pub underline_offset: f32,
+    pub underline_size: f32,
+    pub strikethrough_offset: f32,
+    pub strikethrough_size: f32,
+    /// Average advance of the font's characters
+    pub avg_char_width: f32,
+}
+
+impl FontMetrics {
+    /// The line height `line-height: normal` stands for.
+    pub fn normal_line_height(&self) -> f32 {
+        self.ascent + self.descent + self.line_gap
+    }
+
+    /// The decoration part, as carried by a [`ShapedRun`].
+    pub fn run_metrics(&self) -> RunMetrics {
+        RunMetrics {
+            underline_offset: self.underline_offset,
+            underline_size: self.underline_size,
+            strikethrough_offset: self.strikethrough_offset,
+            strikethrough_size: self.strikethrough_size,
+        }
+    }
+}
+
 /// A contiguous run of glyphs rendered with the same font and size.
 ///
 /// A single call to `FontSystem::shape` may return multiple runs when font
@@ -162,6 +204,39 @@ impl ShapedText {
 
 // Text style for measurement
 
+/// One OpenType feature setting, e.g. `liga` off or `smcp` on: what CSS `font-kerning`,
+/// `font-variant-ligatures` and `font-feature-settings` resolve to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FontFeature {
+    /// Four-character feature tag, e.g. `*b"liga"`
+    pub tag: [u8; 4],
+    /// `0` turns the feature off, `1` on; higher values pick an alternate
+    pub value: u32,
+}
+
+impl FontFeature {
+    pub const fn new(tag: &[u8; 4], value: u32) -> Self {
+        Self { tag: *tag, value }
+    }
+
+    /// Parses a CSS feature tag (`liga`, quotes already stripped). Tags are four printable
+    /// ASCII characters.
+    pub fn from_tag(tag: &str, value: u32) -> Option<Self> {
+        let tag: [u8; 4] = tag.as_bytes().try_into().ok()?;
+        tag.iter()
+            .all(|b| (0x20..=0x7e).contains(b))
+            .then_some(Self { tag, value })
+    }
+}
+
+/// The CSS `font-feature-settings` form, `"liga" 0`, which HarfBuzz-based shapers parse too.
+impl std::fmt::Display for FontFeature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let tag: String = self.tag.iter().map(|&b| b as char).collect();
+        write!(f, "\"{tag}\" {}", self.value)
+    }
+}
+
 /// CSS `text-align`, applied during shaping within [`TextStyle::max_width`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum TextAlign {
@@ -197,6 +272,13 @@ pub struct TextStyle {
     pub align: TextAlign,
     /// Device-pixel scale (DPI). `1.0` = CSS pixels.
     pub display_scale: f32,
+    /// Language of the text (BCP 47, from the `lang` attribute), used to pick fallback fonts:
+    /// the same ideographs look different in Japanese, Korean and Chinese fonts.
+    pub lang: Option<String>,
+    /// OpenType features to turn on or off while shaping, later entries winning; empty = the
+    /// font's defaults. Features change glyph selection and advances, so measurement applies
+    /// them too.
+    pub features: Vec<FontFeature>,
 }
 
 impl TextStyle {
@@ -213,8 +295,109 @@ impl TextStyle {
             max_width: None,
             align: TextAlign::Start,
             display_scale: 1.0,
+            lang: None,
+            features: Vec::new(),
+        }
+    }
+
+    /// [`TextStyle::features`] as a CSS `font-feature-settings` list, `None` when empty.
+    pub fn feature_settings(&self) -> Option<String> {
+        if self.features.is_empty() {
+            return None;
+        }
+        Some(
+            self.features
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+        )
+    }
+}
+
+/// The CSS generic families whose resolution can be configured through [`GenericFamilies`].
+pub const CONFIGURABLE_GENERICS: [&str; 5] = ["serif", "sans-serif", "monospace", "cursive", "fantasy"];
+
+/// The families the CSS generics (`serif`, `sans-serif`, …) resolve to, optionally per language.
+///
+/// A generic in a `font-family` list is tried as the configured families first, in order, and
+/// then as the platform's own choice for it. Families that are not installed are skipped, so a
+/// mapping can list alternatives for several platforms.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GenericFamilies {
+    entries: Vec<GenericFamilyEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct GenericFamilyEntry {
+    generic: String,
+    /// `None` for the mapping used when no language-specific one matches
+    lang: Option<String>,
+    families: Vec<String>,
+}
+
+impl GenericFamilies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maps `generic` to `families` for `lang` (a BCP 47 tag or prefix like `ja` or `zh-Hant`),
+    /// or for every language when `lang` is `None`. Replaces an earlier mapping for the same
+    /// generic and language; an empty `families` removes it.
+    pub fn set(&mut self, generic: &str, lang: Option<&str>, families: Vec<String>) {
+        self.entries.retain(|e| {
+            !(e.generic.eq_ignore_ascii_case(generic)
+                && match (&e.lang, lang) {
+                    (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+                    (None, None) => true,
+                    _ => false,
+                })
+        });
+        if !families.is_empty() {
+            self.entries.push(GenericFamilyEntry {
+                generic: generic.to_string(),
+                lang: lang.map(str::to_string),
+                families,
+            });
         }
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The families configured for `generic` in `lang`: the mapping with the longest language
+    /// tag that is a prefix of `lang` (`zh-Hant` beats `zh` for `zh-Hant-TW`), otherwise the
+    /// mapping for every language. Empty when nothing is configured.
+    pub fn lookup(&self, generic: &str, lang: Option<&str>) -> &[String] {
+        self.entries
+            .iter()
+            .filter(|e| e.generic.eq_ignore_ascii_case(generic))
+            .filter_map(|e| match (&e.lang, lang) {
+                (None, _) => Some((0, e)),
+                (Some(prefix), Some(lang)) if lang_matches(prefix, lang) => Some((prefix.len() + 1, e)),
+                _ => None,
+            })
+            .max_by_key(|(rank, _)| *rank)
+            .map_or(&[][..], |(_, e)| e.families.as_slice())
+    }
+
+    /// `families` with the configured families for `lang` inserted before every generic in it.
+    pub fn expand<'a>(&'a self, families: &[&'a str], lang: Option<&str>) -> Vec<&'a str> {
+        let mut out = Vec::with_capacity(families.len());
+        for &family in families {
+            out.extend(self.lookup(family, lang).iter().map(String::as_str));
+            out.push(family);
+        }
+        out
+    }
+}
+
+/// Whether the language range `prefix` covers `lang`, matching whole subtags.
+fn lang_matches(prefix: &str, lang: &str) -> bool {
+    lang.get(..prefix.len())
+        .is_some_and(|head| head.eq_ignore_ascii_case(prefix))
+        && matches!(lang.as_bytes().get(prefix.len()), None | Some(b'-' | b'_'))
 }
 
 // Core trait
@@ -276,6 +459,20 @@ pub trait FontSystem: Send + Sync + 'static {
         let shaped = self.shape(text, style);
         (shaped.width, shaped.height)
     }
+    /// Metrics of the font `style` resolves to, at `style.size`: what `line-height: normal`, the
+    /// `ex`/`ch`/`cap` units and text decorations are placed by. `None` when no font resolves
+    /// or the engine can't tell.
+    fn metrics(&mut self, _style: &TextStyle) -> Option<FontMetrics> {
+        None
+    }
+
+    /// Sets the families the CSS generics resolve to, replacing the previous mapping. Engines
+    /// that can't override their generic resolution ignore it.
+    fn set_generic_families(&mut self, _families: GenericFamilies) {}
+
+    /// Caps the memory the font system spends on cached font binaries and shaped text, evicting
+    /// the least recently used entries beyond it. Engines without such caches ignore it.
+    fn set_cache_budget(&mut self, _bytes: usize) {}
 }
 
 // Config integration
@@ -166,6 +166,10 @@ fn run_pipeline_once(html: &str, layouter: &mut TaffyLayouter) -> StageTimes {
         wireframed: WireframeState::None,
         debug_hover: false,
         current_hovered_element: None,
+        inspected_element: None,
+        paint_debug: Default::default(),
+        damage: Vec::new(),
+        selection: Default::default(),
         show_tilegrid: false,
         debug_table_cells: false,
         viewport: full_rect,
@@ -3,6 +3,7 @@ pub mod document;
 pub mod font;
 pub mod geo;
 pub mod media;
+pub mod selection;
 pub mod texture;
 pub mod texture_store;
 
@@ -1,4 +1,5 @@
 use crate::common::geo::Rect;
+use crate::common::selection::SelectionRanges;
 use crate::layouter::LayoutElementId;
 use crate::tiler::TileList;
 use parking_lot::RwLock;
@@ -11,6 +12,24 @@ pub enum WireframeState {
     Both,
 }
 
+/// Paint and compositing debug visualizations. All off by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PaintDebug {
+    /// Tint every element by its position in paint order, from blue (painted first) to red
+    /// (painted last).
+    pub paint_order: bool,
+    /// Outline the bounds of every compositing layer.
+    pub layer_borders: bool,
+    /// Flash the regions repainted by a pass.
+    pub repaint_flashing: bool,
+}
+
+impl PaintDebug {
+    pub fn any(&self) -> bool {
+        self.paint_order || self.layer_borders || self.repaint_flashing
+    }
+}
+
 /// Per-tab render settings passed through the pipeline instead of living in a global.
 pub struct BrowserState {
     /// Indexed by layer id; `true` means the layer is drawn.
@@ -23,6 +42,13 @@ pub struct BrowserState {
     /// Draw a 1px red border around every table-cell element (set via GOSUB_DEBUG_TABLE_CELLS=1)
     pub debug_table_cells: bool,
     pub current_hovered_element: Option<LayoutElementId>,
+    /// Element to draw the inspector (box-model) overlay on.
+    pub inspected_element: Option<LayoutElementId>,
+    pub paint_debug: PaintDebug,
+    /// Regions repainted by this pass, in page coordinates. Only used for repaint flashing.
+    pub damage: Vec<Rect>,
+    /// Selected text ranges to paint a highlight behind.
+    pub selection: SelectionRanges,
     /// Current viewport offset + size
     pub viewport: Rect,
     pub tile_list: Option<RwLock<TileList>>,
@@ -38,6 +64,10 @@ impl Debug for BrowserState {
             .field("show_tilegrid", &self.show_tilegrid)
             .field("debug_table_cells", &self.debug_table_cells)
             .field("current_hovered_element", &self.current_hovered_element)
+            .field("inspected_element", &self.inspected_element)
+            .field("paint_debug", &self.paint_debug)
+            .field("damage", &self.damage)
+            .field("selection", &self.selection)
             .field("viewport", &self.viewport)
             .field("dpi_scale_factor", &self.dpi_scale_factor)
             .finish()
@@ -1,4 +1,5 @@
 pub mod inline_style;
+pub mod inline_svg;
 pub mod node;
 pub mod pipeline_doc;
 pub mod style;
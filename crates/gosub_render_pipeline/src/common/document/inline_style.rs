@@ -39,7 +39,7 @@ fn parse_background_color_token(value: &str) -> Option<Value> {
     None
 }
 
-fn parse_named_color(value: &str) -> Value {
+pub(crate) fn parse_named_color(value: &str) -> Value {
     match value {
         // Not in the named-color table: not a color, but a fully transparent value.
         s if s.eq_ignore_ascii_case("transparent") => Value::Color(0, 0, 0, 0),
@@ -324,6 +324,24 @@ fn apply_style_kv(style: &mut NodeStyle, key: &str, value: &str) {
         "white-space" => style.set(StyleProperty::WhiteSpace, parse_style_str(value)),
         "text-transform" => style.set(StyleProperty::TextTransform, parse_style_str(value)),
         "mix-blend-mode" => style.set(StyleProperty::MixBlendMode, parse_style_str(value)),
+        "cursor" => style.set(StyleProperty::Cursor, parse_style_str(value)),
+        "scroll-behavior" => style.set(StyleProperty::ScrollBehavior, parse_style_str(value)),
+        "font-kerning" => style.set(StyleProperty::FontKerning, parse_style_str(value)),
+        "font-variant-ligatures" => style.set(StyleProperty::FontVariantLigatures, parse_style_str(value)),
+        "font-feature-settings" => style.set(StyleProperty::FontFeatureSettings, parse_style_str(value)),
+        "font-size-adjust" => style.set(StyleProperty::FontSizeAdjust, parse_style_num(value)),
+        "fill" => style.set(StyleProperty::Fill, parse_named_color(value)),
+        "fill-opacity" => style.set(StyleProperty::FillOpacity, parse_style_num(value)),
+        "stroke" => style.set(StyleProperty::Stroke, parse_named_color(value)),
+        "stroke-width" => style.set(StyleProperty::StrokeWidth, parse_style_value(value)),
+        "stroke-opacity" => style.set(StyleProperty::StrokeOpacity, parse_style_num(value)),
+        "filter" => style.set(StyleProperty::Filter, parse_style_str(value)),
+        "clip-path" => style.set(StyleProperty::ClipPath, parse_style_str(value)),
+        "mask" => style.set(StyleProperty::Mask, parse_style_str(value)),
+        "pointer-events" => style.set(StyleProperty::PointerEvents, parse_style_str(value)),
+        "break-before" | "page-break-before" => style.set(StyleProperty::BreakBefore, parse_style_str(value)),
+        "break-after" | "page-break-after" => style.set(StyleProperty::BreakAfter, parse_style_str(value)),
+        "break-inside" | "page-break-inside" => style.set(StyleProperty::BreakInside, parse_style_str(value)),
         "text-decoration" | "text-decoration-line" => {
             let has_underline = value.contains("underline");
             let has_line_through = value.contains("line-through");
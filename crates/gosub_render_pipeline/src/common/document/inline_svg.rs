@@ -0,0 +1,183 @@
+//! Inline `<svg>` elements as usvg input, written from the current DOM with the document's
+//! cascade applied.
+//!
+//! usvg only understands SVG markup, so an inline SVG is serialized from the live node tree on
+//! every layout (DOM mutations show up on the next one) and usvg is left with rasterizing it.
+//! What the page's CSS says about an element's paint and effects (filters, clip paths, masks)
+//! goes into its `style` attribute, which usvg ranks above presentation attributes just like
+//! the cascade does, and elements the cascade hides with `display: none` are left out.
+//!
+//! Elements without an id get one naming their node, so that what usvg reports about its tree
+//! (hit tests, for one) leads back to the DOM through [`inline_svg_element`].
+
+use crate::common::document::node::{ElementData, NodeType};
+use crate::common::document::pipeline_doc::PipelineDocument;
+use crate::common::document::style::StyleProperty;
+use gosub_shared::node::NodeId;
+
+const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+const XLINK_NAMESPACE: &str = "http://www.w3.org/1999/xlink";
+
+/// Prefix of the ids given to elements without one, followed by the node id
+const NODE_ID_PREFIX: &str = "gosub-node-";
+
+/// Properties handed over to usvg, by their CSS (and presentation attribute) name
+const SVG_PROPERTIES: [(StyleProperty, &str); 10] = [
+    (StyleProperty::Color, "color"),
+    (StyleProperty::Fill, "fill"),
+    (StyleProperty::FillOpacity, "fill-opacity"),
+    (StyleProperty::Stroke, "stroke"),
+    (StyleProperty::StrokeWidth, "stroke-width"),
+    (StyleProperty::StrokeOpacity, "stroke-opacity"),
+    (StyleProperty::Opacity, "opacity"),
+    (StyleProperty::Filter, "filter"),
+    (StyleProperty::ClipPath, "clip-path"),
+    (StyleProperty::Mask, "mask"),
+];
+
+/// SVG markup of the `<svg>` element `root` and its subtree, styled by the document.
+pub fn inline_svg_markup(doc: &dyn PipelineDocument, root: NodeId) -> String {
+    let mut out = String::new();
+    write_node(doc, root, true, &mut out);
+    out
+}
+
+/// The element of the inline `<svg>` `root` that has the id `svg_id` in its markup.
+pub fn inline_svg_element(doc: &dyn PipelineDocument, root: NodeId, svg_id: &str) -> Option<NodeId> {
+    if let Some(node) = svg_id.strip_prefix(NODE_ID_PREFIX) {
+        return node.parse::<usize>().ok().map(NodeId::from);
+    }
+    let mut stack = vec![root];
+    while let Some(id) = stack.pop() {
+        let node = doc.get_node_by_id(id)?;
+        if let NodeType::Element(data) = &node.node_type {
+            if data.get_attribute("id").is_some_and(|value| value == svg_id) {
+                return Some(id);
+            }
+        }
+        stack.extend(node.children.iter().rev());
+    }
+    None
+}
+
+/// The `pointer-events` of `id` inside the inline `<svg>` `root`: the cascaded value, else the
+/// presentation attribute, else what the element inherits. `root` inherits from the HTML around it.
+pub fn inline_svg_pointer_events(doc: &dyn PipelineDocument, root: NodeId, id: NodeId) -> String {
+    let mut current = id;
+    loop {
+        if let Some(value) = doc.get_own_style(current, &StyleProperty::PointerEvents) {
+            return value.to_css_string();
+        }
+        let attribute = doc.get_node_by_id(current).and_then(|node| match node.node_type {
+            NodeType::Element(data) => data.get_attribute("pointer-events").cloned(),
+            _ => None,
+        });
+        if let Some(value) = attribute {
+            return value;
+        }
+        match doc.parent(current) {
+            Some(parent) if current != root => current = parent,
+            _ => return doc.get_style(current, &StyleProperty::PointerEvents).to_css_string(),
+        }
+    }
+}
+
+fn write_node(doc: &dyn PipelineDocument, id: NodeId, is_root: bool, out: &mut String) {
+    let Some(node) = doc.get_node_by_id(id) else {
+        return;
+    };
+    let data = match &node.node_type {
+        NodeType::Text(text) => return escape_into(text, out),
+        NodeType::Comment(_) => return,
+        NodeType::Element(data) => data,
+    };
+    if doc.is_display_none(id) || data.tag_name.eq_ignore_ascii_case("script") {
+        return;
+    }
+
+    out.push('<');
+    out.push_str(&data.tag_name);
+    let mut attributes: Vec<_> = data.attributes.iter().filter(|(name, _)| *name != "style").collect();
+    attributes.sort();
+    for (name, value) in attributes {
+        write_attribute(name, value, out);
+    }
+    if data.get_attribute("id").is_none() {
+        write_attribute("id", &format!("{NODE_ID_PREFIX}{}", usize::from(id)), out);
+    }
+    if is_root {
+        // The HTML parser puts inline SVG in the SVG namespace without needing the declarations
+        // in the source, but usvg reads the markup as XML.
+        if data.get_attribute("xmlns").is_none() {
+            write_attribute("xmlns", SVG_NAMESPACE, out);
+        }
+        if data.get_attribute("xmlns:xlink").is_none() {
+            write_attribute("xmlns:xlink", XLINK_NAMESPACE, out);
+        }
+    }
+    let style = style_attribute(doc, id, data, is_root);
+    if !style.is_empty() {
+        write_attribute("style", &style, out);
+    }
+    out.push('>');
+
+    for child in node.children {
+        write_node(doc, child, false, out);
+    }
+
+    out.push_str("</");
+    out.push_str(&data.tag_name);
+    out.push('>');
+}
+
+/// The element's own `style` attribute followed by the cascaded values of [`SVG_PROPERTIES`].
+/// The root also takes the values it inherits from the HTML around it, unless a presentation
+/// attribute on it says otherwise, since usvg's inheritance stops at the `<svg>` element.
+fn style_attribute(doc: &dyn PipelineDocument, id: NodeId, data: &ElementData, is_root: bool) -> String {
+    let mut declarations: Vec<String> = data
+        .get_attribute("style")
+        .map(|style| style.trim().trim_end_matches(';').to_string())
+        .filter(|style| !style.is_empty())
+        .into_iter()
+        .collect();
+
+    for (prop, name) in &SVG_PROPERTIES {
+        let value = match doc.get_own_style(id, prop) {
+            Some(value) => Some(value),
+            None if is_root && prop.meta().inherited && data.get_attribute(name).is_none() => {
+                doc.parent(id).map(|parent| doc.get_style(parent, prop))
+            }
+            None => None,
+        };
+        if let Some(value) = value {
+            declarations.push(format!("{name}:{}", value.to_css_string()));
+        }
+    }
+    declarations.join(";")
+}
+
+fn write_attribute(name: &str, value: &str, out: &mut String) {
+    out.push(' ');
+    out.push_str(name);
+    out.push_str("=\"");
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn escape_into(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            c => out.push(c),
+        }
+    }
+}
@@ -29,6 +29,10 @@ impl AttrMap {
     pub fn set(&mut self, key: &str, value: &str) {
         self.attributes.insert(key.to_string(), value.to_string());
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.attributes.iter()
+    }
 }
 
 impl std::fmt::Display for AttrMap {
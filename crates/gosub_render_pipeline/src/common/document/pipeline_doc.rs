@@ -1,6 +1,7 @@
 use crate::common::document::node::{AttrMap, ElementData, Node, NodeType};
 use crate::common::document::style::{
-    intern, BorderStyle, Display, FontWeight, NodeStyle, StyleProperty, TextAlign, TextWrap, Unit, Value,
+    intern, intern_calc, lookup_calc, BorderStyle, Display, FontWeight, NodeStyle, StyleProperty, TextAlign, TextWrap,
+    Unit, Value,
 };
 use crate::painter::commands::color::Color;
 use crate::painter::commands::gradient::{ColorStop, Gradient, LinearGradient, Tiling};
@@ -126,6 +127,39 @@ fn css_property_to_value<S: CssSystem>(p: &S::Property, prop: &StyleProperty) ->
         | StyleProperty::AspectRatio
         | StyleProperty::ScrollbarWidth => Some(Value::Number(p.as_number()?)),
 
+        // ── SVG paint: a color, or `none` / `currentcolor` / `url(#id)` ────
+        StyleProperty::Fill | StyleProperty::Stroke => {
+            if let Some((name, args)) = p.as_function() {
+                let target = args.first()?.as_string()?;
+                return Some(Value::Keyword(intern(&format!("{name}({target})"))));
+            }
+            if let Some((r, g, b, a)) = p.parse_color() {
+                return Some(Value::Color(r as u8, g as u8, b as u8, a as u8));
+            }
+            Some(Value::Keyword(intern(p.as_string()?)))
+        }
+        StyleProperty::FillOpacity | StyleProperty::StrokeOpacity => Some(Value::Number(p.as_number()?)),
+        StyleProperty::StrokeWidth => {
+            if let Some(n) = p.as_number() {
+                Some(Value::Unit(n, Unit::Px))
+            } else if let Some(pct) = p.as_percentage() {
+                Some(Value::Unit(pct, Unit::Percent))
+            } else if p.as_unit().is_some() {
+                Some(Value::Unit(p.unit_to_px(), Unit::Px))
+            } else {
+                None
+            }
+        }
+
+        // ── font-size-adjust: a ratio, or `none` ───────────────────────────
+        StyleProperty::FontSizeAdjust => {
+            if let Some(n) = p.as_number() {
+                Some(Value::Number(n))
+            } else {
+                Some(Value::Keyword(intern(p.as_string()?)))
+            }
+        }
+
         // ── line-height: unitless number is a multiplier, not pixels ───────
         StyleProperty::LineHeight => {
             if p.as_unit().is_some() {
@@ -177,6 +211,44 @@ fn css_property_to_value<S: CssSystem>(p: &S::Property, prop: &StyleProperty) ->
             }
         }
 
+        // ── cursor: `[url(...) ,]* <keyword>`; images aren't supported, use the fallback ──
+        StyleProperty::Cursor => {
+            if let Some(s) = p.as_string() {
+                return Some(Value::Keyword(intern(s)));
+            }
+            let fallback = p.as_list()?.iter().rev().find_map(|v| v.as_string())?;
+            Some(Value::Keyword(intern(fallback)))
+        }
+
+        // ── Font features: keyword lists and `"tag" value, …` lists ───────
+        // Re-serialized to CSS text for `resolve_font_features`, keeping the commas.
+        StyleProperty::FontVariantLigatures | StyleProperty::FontFeatureSettings => {
+            if let Some(s) = p.as_string() {
+                return Some(Value::Keyword(intern(s)));
+            }
+            let mut text = String::new();
+            for v in p.as_list()? {
+                if v.is_comma() {
+                    text.push(',');
+                    continue;
+                }
+                let token = if let Some(s) = v.as_string() {
+                    s.to_string()
+                } else if let Some(n) = v.as_number() {
+                    n.to_string()
+                } else if let Some((n, _)) = v.as_unit() {
+                    n.to_string()
+                } else {
+                    continue;
+                };
+                if !text.is_empty() {
+                    text.push(' ');
+                }
+                text.push_str(&token);
+            }
+            Some(Value::Keyword(intern(&text)))
+        }
+
         // ── Grid track lists: `repeat(3, 1fr)`, `210px 1fr`, `auto`, … ─────
         // Stored as a `Function` (repeat/minmax) or a `List` - neither of which `as_string()`
         // returns - and a bare `1fr` is a `Unit`, so the default branch would drop or mis-type
@@ -184,25 +256,18 @@ fn css_property_to_value<S: CssSystem>(p: &S::Property, prop: &StyleProperty) ->
         StyleProperty::GridTemplateColumns
         | StyleProperty::GridTemplateRows
         | StyleProperty::GridAutoColumns
-        | StyleProperty::GridAutoRows => {
-            let s = if let Some(str) = p.as_string() {
-                str.to_string()
-            } else if let Some((name, args)) = p.as_function() {
-                format!("{name}({})", join_grid_args::<S>(args))
-            } else if let Some(list) = p.as_list() {
-                list.iter().map(grid_value_to_string::<S>).collect::<Vec<_>>().join(" ")
-            } else if let Some((val, unit)) = p.as_unit() {
-                format!("{val}{unit}")
-            } else {
-                let pct = p.as_percentage()?;
-                format!("{pct}%")
-            };
-            Some(Value::Keyword(intern(&s)))
+        | StyleProperty::GridAutoRows => Some(Value::Keyword(intern(&property_to_css_text::<S>(p)?))),
+
+        // ── SVG effects: only read by usvg, which takes them as CSS text ──
+        StyleProperty::Filter | StyleProperty::ClipPath | StyleProperty::Mask => {
+            Some(Value::Keyword(intern(&property_to_css_text::<S>(p)?)))
         }
 
         // ── Default: unit-based or keyword ────────────────────────────────
         _ => {
-            if let Some((v, unit)) = p.as_unit() {
+            if let Some(calc) = p.as_calc() {
+                Some(Value::Calc(intern_calc(calc)))
+            } else if let Some((v, unit)) = p.as_unit() {
                 // Font-relative units must scale with the *element's* font-size, which we
                 // don't know here. Express them as `em` (with an approximate factor for the
                 // ones that aren't already font-multiples) and let `get_style` resolve them
@@ -233,6 +298,24 @@ fn css_property_to_value<S: CssSystem>(p: &S::Property, prop: &StyleProperty) ->
     }
 }
 
+/// Serializes a property value back to CSS text, for values that are parsed again downstream
+/// (grid track lists by the layouter, SVG effects by usvg).
+fn property_to_css_text<S: CssSystem>(p: &S::Property) -> Option<String> {
+    let s = if let Some(str) = p.as_string() {
+        str.to_string()
+    } else if let Some((name, args)) = p.as_function() {
+        format!("{name}({})", join_grid_args::<S>(args))
+    } else if let Some(list) = p.as_list() {
+        list.iter().map(grid_value_to_string::<S>).collect::<Vec<_>>().join(" ")
+    } else if let Some((val, unit)) = p.as_unit() {
+        format!("{val}{unit}")
+    } else {
+        let pct = p.as_percentage()?;
+        format!("{pct}%")
+    };
+    Some(s)
+}
+
 /// Serializes one grid track-list value back to canonical CSS text (`1fr`, `minmax(100px, 1fr)`,
 /// …), reconstructing a `grid-template-*` string the layouter can parse.
 fn grid_value_to_string<S: CssSystem>(v: &S::Value) -> String {
@@ -678,6 +761,28 @@ pub trait PipelineDocument: Send + Sync {
         None
     }
 
+    /// The element with keyboard focus, if any (the painter draws its focus ring).
+    fn focused(&self) -> Option<NodeId> {
+        None
+    }
+
+    /// Language of node `id`: the `lang` (or `xml:lang`) attribute of the node or its nearest
+    /// ancestor that has one. An empty attribute means the language is unknown.
+    fn lang(&self, id: NodeId) -> Option<String> {
+        let mut current = Some(id);
+        while let Some(id) = current {
+            let node = self.get_node_by_id(id)?;
+            if let NodeType::Element(data) = &node.node_type {
+                if let Some(lang) = data.get_attribute("lang").or_else(|| data.get_attribute("xml:lang")) {
+                    let lang = lang.trim();
+                    return (!lang.is_empty()).then(|| lang.to_string());
+                }
+            }
+            current = node.parent_id;
+        }
+        None
+    }
+
     /// Returns the own (explicitly-set) value for `prop` on node `id`, without recursing.
     fn get_own_style(&self, id: NodeId, prop: &StyleProperty) -> Option<Value>;
 
@@ -732,18 +837,27 @@ pub trait PipelineDocument: Send + Sync {
         // element's font-size (16px default). `em` is relative to the *parent's* computed
         // font-size for `font-size` itself, and to the element's *own* computed font-size
         // for every other property (e.g. `max-width: 17ch` lands here as `em`).
+        let em_basis = || {
+            if matches!(prop, StyleProperty::FontSize) {
+                match self.parent(id) {
+                    Some(parent) => self.font_size_px(parent),
+                    None => 16.0,
+                }
+            } else {
+                self.font_size_px(id)
+            }
+        };
         match &raw {
             Value::Unit(v, Unit::Rem) => Value::Unit(v * 16.0, Unit::Px),
-            Value::Unit(v, Unit::Em) => {
-                let basis = if matches!(prop, StyleProperty::FontSize) {
-                    match self.parent(id) {
-                        Some(parent) => self.font_size_px(parent),
-                        None => 16.0,
-                    }
+            Value::Unit(v, Unit::Em) => Value::Unit(v * em_basis(), Unit::Px),
+            // Fold the em parts in; what's left needs the percentage basis only layout knows.
+            Value::Calc(calc) => {
+                let calc = lookup_calc(*calc).at_font_size(em_basis());
+                if calc.has_percentage() {
+                    Value::Calc(intern_calc(calc))
                 } else {
-                    self.font_size_px(id)
-                };
-                Value::Unit(v * basis, Unit::Px)
+                    Value::Unit(calc.resolve(0.0, 0.0), Unit::Px)
+                }
             }
             _ => raw,
         }
@@ -1081,7 +1195,15 @@ where
             StyleProperty::InsetInlineEnd => Some("right"),
             _ => None,
         };
-        if let Some(physical) = inset_physical {
+        // Likewise the CSS2 `page-break-*` properties are legacy aliases of `break-*`. Their
+        // `always` value is mapped to `page` by the paginator.
+        let legacy_break = match prop {
+            StyleProperty::BreakBefore => Some("page-break-before"),
+            StyleProperty::BreakAfter => Some("page-break-after"),
+            StyleProperty::BreakInside => Some("page-break-inside"),
+            _ => None,
+        };
+        if let Some(physical) = inset_physical.or(legacy_break) {
             for key in [css_name, physical] {
                 if let Some(p) = <_ as CssPropertyMap<C::CssSystem>>::get(map, key) {
                     if let Some(v) = css_property_to_value::<C::CssSystem>(p, prop) {
@@ -1394,6 +1516,10 @@ where
         }
     }
 
+    fn focused(&self) -> Option<NodeId> {
+        self.doc.focused()
+    }
+
     fn html_node_id(&self) -> Option<NodeId> {
         let root = self.doc.root();
         self.find_child_by_tag(root, "html")
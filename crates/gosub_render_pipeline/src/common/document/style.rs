@@ -1,5 +1,6 @@
+use gosub_interface::css3::CalcLength;
 use parking_lot::Mutex;
-use std::sync::OnceLock;
+use std::sync::{Arc, OnceLock};
 
 // ── String interner ──────────────────────────────────────────────────────────
 
@@ -24,6 +25,44 @@ pub fn lookup(id: u32) -> String {
     interner().lock()[id as usize].clone()
 }
 
+static CALC_INTERNER: OnceLock<Mutex<Vec<Arc<CalcLength>>>> = OnceLock::new();
+
+fn calc_interner() -> &'static Mutex<Vec<Arc<CalcLength>>> {
+    CALC_INTERNER.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Intern a math-function length and return its stable u32 id. Entries are never dropped, so
+/// the `CalcLength` behind an id keeps its address for the life of the process.
+pub fn intern_calc(calc: CalcLength) -> u32 {
+    let mut table = calc_interner().lock();
+    if let Some(i) = table.iter().position(|x| **x == calc) {
+        return i as u32;
+    }
+    let id = table.len() as u32;
+    table.push(Arc::new(calc));
+    id
+}
+
+pub fn lookup_calc(id: u32) -> Arc<CalcLength> {
+    Arc::clone(&calc_interner().lock()[id as usize])
+}
+
+/// The address of an interned length, for taffy's `CompactLength::calc`.
+pub fn calc_ptr(id: u32) -> *const () {
+    Arc::as_ptr(&calc_interner().lock()[id as usize]).cast()
+}
+
+/// Resolves a length taffy hands back from `CompactLength::calc`, with percentages taken of
+/// `basis`. The em parts must already be folded in; a pointer that isn't one of ours resolves
+/// to 0.
+pub fn resolve_calc(ptr: *const (), basis: f32) -> f32 {
+    let table = calc_interner().lock();
+    table
+        .iter()
+        .find(|calc| std::ptr::eq(Arc::as_ptr(calc).cast(), ptr))
+        .map_or(0.0, |calc| calc.resolve(0.0, basis))
+}
+
 // ── Sub-enums ────────────────────────────────────────────────────────────────
 
 #[derive(Clone, Debug, PartialEq)]
@@ -122,6 +161,8 @@ pub enum Value {
     BorderStyle(BorderStyle),
     /// An interned keyword string (font-family, position, flex-direction, …).
     Keyword(u32),
+    /// An interned `calc()`/`min()`/`max()` length that layout has to resolve.
+    Calc(u32),
 }
 
 impl Value {
@@ -213,6 +254,7 @@ impl Value {
             }
             .to_string(),
             Value::Keyword(id) => lookup(*id),
+            Value::Calc(id) => lookup_calc(*id).to_string(),
         }
     }
 }
@@ -299,6 +341,24 @@ pub enum StyleProperty {
     ZIndex,
     LetterSpacing,
     MixBlendMode,
+    BreakBefore,
+    BreakAfter,
+    BreakInside,
+    Cursor,
+    ScrollBehavior,
+    FontKerning,
+    FontVariantLigatures,
+    FontFeatureSettings,
+    FontSizeAdjust,
+    Fill,
+    FillOpacity,
+    Stroke,
+    StrokeWidth,
+    StrokeOpacity,
+    Filter,
+    ClipPath,
+    Mask,
+    PointerEvents,
 }
 
 impl StyleProperty {
@@ -383,6 +443,24 @@ impl StyleProperty {
             StyleProperty::ZIndex => 75,
             StyleProperty::LetterSpacing => 76,
             StyleProperty::MixBlendMode => 77,
+            StyleProperty::BreakBefore => 78,
+            StyleProperty::BreakAfter => 79,
+            StyleProperty::BreakInside => 80,
+            StyleProperty::Cursor => 81,
+            StyleProperty::ScrollBehavior => 82,
+            StyleProperty::FontKerning => 83,
+            StyleProperty::FontVariantLigatures => 84,
+            StyleProperty::FontFeatureSettings => 85,
+            StyleProperty::FontSizeAdjust => 86,
+            StyleProperty::Fill => 87,
+            StyleProperty::FillOpacity => 88,
+            StyleProperty::Stroke => 89,
+            StyleProperty::StrokeWidth => 90,
+            StyleProperty::StrokeOpacity => 91,
+            StyleProperty::Filter => 92,
+            StyleProperty::ClipPath => 93,
+            StyleProperty::Mask => 94,
+            StyleProperty::PointerEvents => 95,
         }
     }
 
@@ -917,6 +995,114 @@ static PROPERTIES: &[PropertyMeta] = &[
         inherited: false,
         initial_kind: InitialKind::Keyword("normal"),
     },
+    // 78 break-before - not inherited; initial = auto (only used when paginating)
+    PropertyMeta {
+        name: "break-before",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("auto"),
+    },
+    // 79 break-after
+    PropertyMeta {
+        name: "break-after",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("auto"),
+    },
+    // 80 break-inside
+    PropertyMeta {
+        name: "break-inside",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("auto"),
+    },
+    // 81 cursor - inherited; initial = auto (resolved per hit target by the engine)
+    PropertyMeta {
+        name: "cursor",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("auto"),
+    },
+    // 82 scroll-behavior - not inherited; only read on the root element
+    PropertyMeta {
+        name: "scroll-behavior",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("auto"),
+    },
+    // 83 font-kerning - inherited; initial = auto (the font's default)
+    PropertyMeta {
+        name: "font-kerning",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("auto"),
+    },
+    // 84 font-variant-ligatures
+    PropertyMeta {
+        name: "font-variant-ligatures",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("normal"),
+    },
+    // 85 font-feature-settings - inherited; a comma-separated list of `"tag" value`
+    PropertyMeta {
+        name: "font-feature-settings",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("normal"),
+    },
+    // 86 font-size-adjust - inherited; `none` or the x-height to size ratio to scale fonts to
+    PropertyMeta {
+        name: "font-size-adjust",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("none"),
+    },
+    // 87 fill - inherited; SVG paint: a color, `none`, `currentcolor` or `url(#id)`
+    PropertyMeta {
+        name: "fill",
+        inherited: true,
+        initial_kind: InitialKind::Color(0, 0, 0, 255),
+    },
+    // 88 fill-opacity
+    PropertyMeta {
+        name: "fill-opacity",
+        inherited: true,
+        initial_kind: InitialKind::Number(1.0),
+    },
+    // 89 stroke - inherited; SVG paint
+    PropertyMeta {
+        name: "stroke",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("none"),
+    },
+    // 90 stroke-width
+    PropertyMeta {
+        name: "stroke-width",
+        inherited: true,
+        initial_kind: InitialKind::Unit(1.0, Unit::Px),
+    },
+    // 91 stroke-opacity
+    PropertyMeta {
+        name: "stroke-opacity",
+        inherited: true,
+        initial_kind: InitialKind::Number(1.0),
+    },
+    // 92 filter
+    PropertyMeta {
+        name: "filter",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("none"),
+    },
+    // 93 clip-path
+    PropertyMeta {
+        name: "clip-path",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("none"),
+    },
+    // 94 mask
+    PropertyMeta {
+        name: "mask",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("none"),
+    },
+    // 95 pointer-events
+    PropertyMeta {
+        name: "pointer-events",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("auto"),
+    },
 ];
 
 // ── NodeStyle - replaces StylePropertyList ────────────────────────────────────
@@ -1056,6 +1242,24 @@ fn from_id(id: u8) -> Option<StyleProperty> {
         75 => Some(StyleProperty::ZIndex),
         76 => Some(StyleProperty::LetterSpacing),
         77 => Some(StyleProperty::MixBlendMode),
+        78 => Some(StyleProperty::BreakBefore),
+        79 => Some(StyleProperty::BreakAfter),
+        80 => Some(StyleProperty::BreakInside),
+        81 => Some(StyleProperty::Cursor),
+        82 => Some(StyleProperty::ScrollBehavior),
+        83 => Some(StyleProperty::FontKerning),
+        84 => Some(StyleProperty::FontVariantLigatures),
+        85 => Some(StyleProperty::FontFeatureSettings),
+        86 => Some(StyleProperty::FontSizeAdjust),
+        87 => Some(StyleProperty::Fill),
+        88 => Some(StyleProperty::FillOpacity),
+        89 => Some(StyleProperty::Stroke),
+        90 => Some(StyleProperty::StrokeWidth),
+        91 => Some(StyleProperty::StrokeOpacity),
+        92 => Some(StyleProperty::Filter),
+        93 => Some(StyleProperty::ClipPath),
+        94 => Some(StyleProperty::Mask),
+        95 => Some(StyleProperty::PointerEvents),
         _ => None,
     }
 }
@@ -1097,6 +1301,7 @@ mod tests {
             StyleProperty::MarginTop,
             StyleProperty::Display,
             StyleProperty::FlexGrow,
+            StyleProperty::FontFeatureSettings,
         ];
         for prop in &props {
             let id = prop.id();
@@ -1,3 +1,8 @@
+use crate::common::document::pipeline_doc::PipelineDocument;
+use crate::common::document::style::{lookup, StyleProperty, Value};
+use gosub_interface::font_system::{FontFeature, FontMetrics, FontSystem, TextStyle};
+use gosub_shared::node::NodeId;
+
 #[derive(Debug, Clone)]
 pub enum FontAlignment {
     /// Start of the line (left for LTR, right for RTL)
@@ -26,4 +31,258 @@ pub struct FontInfo {
     pub alignment: FontAlignment,
     pub underline: bool,
     pub line_through: bool,
+    /// Language of the text, from the nearest `lang` attribute
+    pub lang: Option<String>,
+    /// OpenType features from `font-kerning`, `font-variant-ligatures` and `font-feature-settings`
+    pub features: Vec<FontFeature>,
+}
+
+/// `line-height: normal` as a multiple of the font size, for when the font system can't report
+/// the font's own metrics
+const FALLBACK_LINE_HEIGHT: f64 = 1.4;
+
+/// The `font-size-adjust` ratio of node `id`, `None` for `none`.
+pub fn font_size_adjust_of(doc: &dyn PipelineDocument, id: NodeId) -> Option<f64> {
+    match doc.get_style(id, &StyleProperty::FontSizeAdjust) {
+        Value::Number(ratio) if ratio.is_finite() && ratio >= 0.0 => Some(ratio as f64),
+        _ => None,
+    }
+}
+
+/// How big text is set and how tall its lines are, taken from the first available font of its
+/// family list. Characters that fall back to another font keep these, so a fallback font with
+/// different proportions doesn't move the line boxes around.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UsedFontSize {
+    /// Font size in px after `font-size-adjust`
+    pub size: f64,
+    /// What `line-height: normal` comes to at that size
+    pub normal_line_height: f64,
+}
+
+/// Sizes text in `style` (at its computed `font-size`) by the metrics of the first font of its
+/// family list that `font_system` has, applying `font-size-adjust`.
+pub fn used_font_size(font_system: &mut dyn FontSystem, style: &TextStyle, adjust: Option<f64>) -> UsedFontSize {
+    size_from_metrics(style.size as f64, adjust, font_system.metrics(style).as_ref())
+}
+
+/// [`used_font_size`] from the metrics of the font at the computed `size`. `font-size-adjust`
+/// scales the size so the font's x-height is `adjust` times the computed size.
+fn size_from_metrics(size: f64, adjust: Option<f64>, metrics: Option<&FontMetrics>) -> UsedFontSize {
+    let Some(metrics) = metrics.filter(|_| size > 0.0) else {
+        return UsedFontSize {
+            size,
+            normal_line_height: size * FALLBACK_LINE_HEIGHT,
+        };
+    };
+    let aspect = metrics.x_height as f64 / size;
+    let used = match adjust {
+        Some(adjust) if aspect > 0.0 => size * adjust / aspect,
+        _ => size,
+    };
+    UsedFontSize {
+        size: used,
+        // Metrics scale linearly with the size.
+        normal_line_height: metrics.normal_line_height() as f64 * used / size,
+    }
+}
+
+/// Minimal [`FontInfo`] for text the engine lays out itself rather than taking from a text node
+/// (`alt` text, the values of form controls): node `id`'s computed family and size, start-aligned
+/// and undecorated.
+pub fn element_font_info(doc: &dyn PipelineDocument, id: NodeId) -> FontInfo {
+    let size = match doc.get_style(id, &StyleProperty::FontSize) {
+        Value::Unit(px, _) => px as f64,
+        _ => 16.0,
+    };
+    let family = match doc.get_style(id, &StyleProperty::FontFamily) {
+        Value::Keyword(kw) => lookup(kw),
+        _ => "sans-serif".to_string(),
+    };
+    FontInfo {
+        family,
+        size,
+        weight: 400,
+        width: 100,
+        slant: 0,
+        line_height: size * FALLBACK_LINE_HEIGHT,
+        letter_spacing: 0.0,
+        alignment: FontAlignment::Start,
+        underline: false,
+        line_through: false,
+        lang: doc.lang(id),
+        features: font_features_of(doc, id),
+    }
+}
+
+/// The OpenType features node `id` is shaped with, see [`resolve_font_features`].
+pub fn font_features_of(doc: &dyn PipelineDocument, id: NodeId) -> Vec<FontFeature> {
+    let keyword = |prop: &StyleProperty| match doc.get_style(id, prop) {
+        Value::Keyword(kw) => lookup(kw),
+        _ => String::new(),
+    };
+    resolve_font_features(
+        &keyword(&StyleProperty::FontKerning),
+        &keyword(&StyleProperty::FontVariantLigatures),
+        &keyword(&StyleProperty::FontFeatureSettings),
+    )
+}
+
+/// Resolves the CSS font feature properties to OpenType features, in the order CSS Fonts
+/// applies them: `font-kerning` and `font-variant-ligatures` first, then `font-feature-settings`,
+/// which overrides them for the tags it names. `auto`/`normal` leave the font's defaults alone.
+pub fn resolve_font_features(kerning: &str, ligatures: &str, settings: &str) -> Vec<FontFeature> {
+    let mut features: Vec<FontFeature> = Vec::new();
+    let mut set = |tag: &[u8; 4], value: u32| {
+        features.retain(|f| &f.tag != tag);
+        features.push(FontFeature::new(tag, value));
+    };
+
+    match kerning.trim() {
+        "normal" => set(b"kern", 1),
+        "none" => set(b"kern", 0),
+        _ => {}
+    }
+
+    for keyword in ligatures.split_whitespace() {
+        match keyword {
+            "none" => {
+                for tag in [b"liga", b"clig", b"dlig", b"hlig", b"calt"] {
+                    set(tag, 0);
+                }
+            }
+            "common-ligatures" => {
+                set(b"liga", 1);
+                set(b"clig", 1);
+            }
+            "no-common-ligatures" => {
+                set(b"liga", 0);
+                set(b"clig", 0);
+            }
+            "discretionary-ligatures" => set(b"dlig", 1),
+            "no-discretionary-ligatures" => set(b"dlig", 0),
+            "historical-ligatures" => set(b"hlig", 1),
+            "no-historical-ligatures" => set(b"hlig", 0),
+            "contextual" => set(b"calt", 1),
+            "no-contextual" => set(b"calt", 0),
+            _ => {}
+        }
+    }
+
+    if settings.trim() != "normal" {
+        for setting in settings.split(',') {
+            let mut parts = setting.split_whitespace();
+            let Some(tag) = parts.next() else { continue };
+            let tag = tag.trim_matches(|c| c == '"' || c == '\'');
+            let value = match parts.next() {
+                None | Some("on") => 1,
+                Some("off") => 0,
+                Some(n) => match n.parse::<u32>() {
+                    Ok(n) => n,
+                    Err(_) => continue,
+                },
+            };
+            if let Some(feature) = FontFeature::from_tag(tag, value) {
+                set(&feature.tag, feature.value);
+            }
+        }
+    }
+
+    features
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gosub_fontmanager::ParleyFontSystem;
+
+    fn tags(features: &[FontFeature]) -> Vec<String> {
+        features.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn normal_keeps_the_font_defaults() {
+        assert!(resolve_font_features("auto", "normal", "normal").is_empty());
+        assert!(resolve_font_features("", "", "").is_empty());
+    }
+
+    #[test]
+    fn kerning_and_ligatures_map_to_features() {
+        assert_eq!(
+            tags(&resolve_font_features(
+                "none",
+                "no-common-ligatures discretionary-ligatures",
+                "normal"
+            )),
+            [r#""kern" 0"#, r#""liga" 0"#, r#""clig" 0"#, r#""dlig" 1"#]
+        );
+        assert_eq!(resolve_font_features("auto", "none", "normal").len(), 5);
+    }
+
+    #[test]
+    fn feature_settings_override_the_keywords() {
+        assert_eq!(
+            tags(&resolve_font_features(
+                "normal",
+                "none",
+                r#""kern" off, "liga", 'ss02' 2, "toolong" 1, smcp on, "tnum" x"#
+            )),
+            [
+                r#""clig" 0"#,
+                r#""dlig" 0"#,
+                r#""hlig" 0"#,
+                r#""calt" 0"#,
+                r#""kern" 0"#,
+                r#""liga" 1"#,
+                r#""ss02" 2"#,
+                r#""smcp" 1"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn font_size_adjust_scales_to_the_x_height() {
+        let metrics = FontMetrics {
+            ascent: 16.0,
+            descent: 4.0,
+            line_gap: 2.0,
+            x_height: 8.0,
+            ..FontMetrics::default()
+        };
+        let plain = size_from_metrics(20.0, None, Some(&metrics));
+        assert_eq!(
+            plain,
+            UsedFontSize {
+                size: 20.0,
+                normal_line_height: 22.0
+            }
+        );
+
+        // An x-height of 0.4em adjusted to 0.5em needs the font a quarter bigger.
+        let adjusted = size_from_metrics(20.0, Some(0.5), Some(&metrics));
+        assert!((adjusted.size - 25.0).abs() < 1e-9, "size {}", adjusted.size);
+        assert!((adjusted.normal_line_height - 27.5).abs() < 1e-9);
+
+        let unknown = size_from_metrics(20.0, Some(0.5), None);
+        assert_eq!(
+            unknown,
+            UsedFontSize {
+                size: 20.0,
+                normal_line_height: 28.0
+            }
+        );
+    }
+
+    #[test]
+    fn sizes_by_the_first_available_font() {
+        let mut fs = ParleyFontSystem::new();
+        let roboto = used_font_size(&mut fs, &TextStyle::new("Roboto", 100.0), None);
+        let listed = used_font_size(&mut fs, &TextStyle::new("No Such Font, Roboto", 100.0), None);
+        assert_eq!(roboto, listed, "a missing family is skipped, not averaged in");
+        assert!(roboto.normal_line_height > 100.0 && roboto.normal_line_height < 140.0);
+
+        // Roboto's x-height is about 0.53em, so adjusting to 0.5 shrinks it a little.
+        let adjusted = used_font_size(&mut fs, &TextStyle::new("Roboto", 100.0), Some(0.5));
+        assert!(adjusted.size > 90.0 && adjusted.size < 99.0, "size {}", adjusted.size);
+    }
 }
@@ -39,6 +39,19 @@ impl Rect {
             height: self.height,
         }
     }
+
+    /// The overlap of two rects; zero-sized when they don't overlap.
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        let x0 = self.x.max(other.x);
+        let y0 = self.y.max(other.y);
+        let x1 = (self.x + self.width).min(other.x + other.width);
+        let y1 = (self.y + self.height).min(other.y + other.height);
+        Rect::new(x0, y0, (x1 - x0).max(0.0), (y1 - y0).max(0.0))
+    }
+
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
 }
 
 impl From<Rect> for Coordinate {
@@ -116,6 +129,17 @@ mod tests {
         assert_eq!(dimension.height, 10.0);
     }
 
+    #[test]
+    fn test_rect_intersection() {
+        let a = Rect::new(0.0, 0.0, 100.0, 100.0);
+        let r = a.intersection(&Rect::new(50.0, 80.0, 100.0, 100.0));
+        assert_eq!((r.x, r.y, r.width, r.height), (50.0, 80.0, 50.0, 20.0));
+        let none = a.intersection(&Rect::new(120.0, 0.0, 5.0, 5.0));
+        assert_eq!(none.width, 0.0);
+        assert!(a.contains(0.0, 99.0));
+        assert!(!a.contains(100.0, 50.0));
+    }
+
     #[test]
     fn test_coordinate_new() {
         let coord = Coordinate::new(10.0, 20.0);
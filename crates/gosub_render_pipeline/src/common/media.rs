@@ -1,15 +1,18 @@
 mod decoder;
+mod fetch;
 mod image;
 mod svg;
+mod svg_animation;
 
 #[allow(clippy::module_inception)]
 mod media;
 mod media_store;
 
 pub use decoder::{
-    DecodedImage, DecodedMedia, ImageDecodeError, MediaDecoder, MediaDecoderRegistry, PixelBuffer, RasterDecoder,
-    SvgDecoder,
+    DecodeContext, DecodedImage, DecodedMedia, ImageDecodeError, MediaDecoder, MediaDecoderRegistry, PixelBuffer,
+    RasterDecoder, Subresource, SubresourceLoader, SvgDecoder,
 };
+pub use fetch::{DirectFetcher, FetchedResource, ResourceFetcher};
 
 pub use media::Media;
 pub use media::MediaId;
@@ -19,6 +22,7 @@ pub use media::MediaType;
 
 pub use image::Image;
 pub use svg::Svg;
+pub use svg_animation::SvgAnimation;
 
 pub use media_store::MediaRequest;
 pub use media_store::MediaStore;
@@ -6,11 +6,15 @@
 
 mod raster;
 mod svg;
+mod svg_resources;
 
 pub use raster::RasterDecoder;
 pub use svg::SvgDecoder;
 
+use crate::common::media::FetchedResource;
 use std::fmt;
+use std::sync::Arc;
+use url::Url;
 
 /// Pixel storage for a decoded raster image. Only 8-bit RGBA is supported today; the enum
 /// leaves room for wider/greyscale buffers without churning the public surface.
@@ -127,6 +131,30 @@ impl fmt::Display for ImageDecodeError {
 
 impl std::error::Error for ImageDecodeError {}
 
+/// What a [`SubresourceLoader`] has for a URL
+#[derive(Debug, Clone)]
+pub enum Subresource {
+    Loaded(Arc<FetchedResource>),
+    /// The fetch failed; the media goes without it.
+    Failed,
+    /// Still being fetched; the media goes without it for now.
+    Pending,
+}
+
+/// Hands decoders the resources media refers to, such as the images and style sheets of an SVG.
+pub trait SubresourceLoader {
+    fn load(&self, url: &Url) -> Subresource;
+}
+
+/// Where the bytes being decoded came from. Without a base URL or loader, references to other
+/// resources are left unresolved.
+#[derive(Default, Clone, Copy)]
+pub struct DecodeContext<'a> {
+    /// URL relative references resolve against
+    pub base_url: Option<&'a Url>,
+    pub loader: Option<&'a dyn SubresourceLoader>,
+}
+
 /// A single format handler. Decoders are matched first by MIME hint, then by magic bytes.
 pub trait MediaDecoder: Send + Sync {
     /// Short, stable identifier (used in logs).
@@ -140,6 +168,13 @@ pub trait MediaDecoder: Send + Sync {
     fn supports_magic(&self, bytes: &[u8]) -> bool;
 
     fn decode(&self, bytes: &[u8]) -> Result<DecodedMedia, ImageDecodeError>;
+
+    /// Decodes with `context` at hand for the resources the media refers to. Formats without
+    /// external references can leave this to [`decode`](Self::decode).
+    fn decode_with(&self, bytes: &[u8], context: &DecodeContext) -> Result<DecodedMedia, ImageDecodeError> {
+        let _ = context;
+        self.decode(bytes)
+    }
 }
 
 /// Ordered set of decoders. The MIME hint (e.g. an HTTP `Content-Type`) is treated as a hint
@@ -171,12 +206,22 @@ impl MediaDecoderRegistry {
     /// Decode `bytes`, using `mime` as a hint. Tries MIME-matched decoders first, then falls
     /// back to magic-byte sniffing.
     pub fn decode(&self, mime: Option<&str>, bytes: &[u8]) -> Result<DecodedMedia, ImageDecodeError> {
+        self.decode_with(mime, bytes, &DecodeContext::default())
+    }
+
+    /// [`decode`](Self::decode), resolving the resources the media refers to through `context`.
+    pub fn decode_with(
+        &self,
+        mime: Option<&str>,
+        bytes: &[u8],
+        context: &DecodeContext,
+    ) -> Result<DecodedMedia, ImageDecodeError> {
         let mut last_err: Option<ImageDecodeError> = None;
 
         if let Some(mime) = mime {
             for decoder in &self.decoders {
                 if decoder.supports_mime(mime) {
-                    match decoder.decode(bytes) {
+                    match decoder.decode_with(bytes, context) {
                         Ok(media) => return Ok(media),
                         Err(e) => {
                             log::debug!("decoder '{}' (mime '{}') failed: {}", decoder.name(), mime, e);
@@ -189,7 +234,7 @@ impl MediaDecoderRegistry {
 
         for decoder in &self.decoders {
             if decoder.supports_magic(bytes) {
-                match decoder.decode(bytes) {
+                match decoder.decode_with(bytes, context) {
                     Ok(media) => return Ok(media),
                     Err(e) => {
                         log::debug!("decoder '{}' (magic) failed: {}", decoder.name(), e);
@@ -1,4 +1,4 @@
-use super::{DecodedMedia, ImageDecodeError, MediaDecoder};
+use super::{svg_resources, DecodeContext, DecodedMedia, ImageDecodeError, MediaDecoder};
 use resvg::usvg;
 use std::sync::{Arc, OnceLock};
 
@@ -6,7 +6,8 @@ use std::sync::{Arc, OnceLock};
 const SVG_SNIFF_LEN: usize = 1024;
 
 /// `usvg::Options` backed by a shared fontdb, built once and reused so system font discovery
-/// happens only once per process.
+/// happens only once per process. Images are only resolved from `data:` URLs or through the
+/// [`DecodeContext`]'s loader, never from the file system.
 fn svg_options() -> usvg::Options<'static> {
     static FONTDB: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
     let fontdb = Arc::clone(FONTDB.get_or_init(|| {
@@ -14,10 +15,12 @@ fn svg_options() -> usvg::Options<'static> {
         db.load_system_fonts();
         Arc::new(db)
     }));
-    usvg::Options {
+    let mut options = usvg::Options {
         fontdb,
         ..Default::default()
-    }
+    };
+    options.image_href_resolver.resolve_string = Box::new(|_: &str, _: &usvg::Options| None);
+    options
 }
 
 /// Parses SVG into a retained `usvg::Tree`. Unlike raster decoders it does not rasterize -
@@ -59,7 +62,21 @@ impl MediaDecoder for SvgDecoder {
     }
 
     fn decode(&self, bytes: &[u8]) -> Result<DecodedMedia, ImageDecodeError> {
-        let tree = usvg::Tree::from_data(bytes, &svg_options()).map_err(|e| ImageDecodeError::Decode(e.to_string()))?;
+        self.decode_with(bytes, &DecodeContext::default())
+    }
+
+    /// Loads the images, style sheets and elements from other documents the SVG refers to
+    /// through the context's loader first (see [`svg_resources`]).
+    fn decode_with(&self, bytes: &[u8], context: &DecodeContext) -> Result<DecodedMedia, ImageDecodeError> {
+        let mut options = svg_options();
+        let markup = match (context.base_url, context.loader, std::str::from_utf8(bytes)) {
+            (Some(base), Some(loader), Ok(source)) => {
+                svg_resources::load(source, base, loader).and_then(|resources| resources.apply(source, &mut options))
+            }
+            _ => None,
+        };
+        let data = markup.as_deref().map_or(bytes, str::as_bytes);
+        let tree = usvg::Tree::from_data(data, &options).map_err(|e| ImageDecodeError::Decode(e.to_string()))?;
         Ok(DecodedMedia::Vector(Box::new(tree)))
     }
 }
@@ -0,0 +1,325 @@
+//! The external references of an SVG document, loaded through a [`SubresourceLoader`] instead of
+//! usvg's resolver, which can only read files from disk.
+//!
+//! Everything is looked up before usvg sees the document. Images reach usvg through an href
+//! resolver serving the loaded bytes, and style sheets (`<?xml-stylesheet?>` and `@import` in
+//! `<style>`) through its `style_sheet` option. usvg only follows `<use>` within a document, so
+//! elements used from other documents are copied into a `<defs>` of the document itself, with
+//! the `<use>` pointing at the copy. References inside the loaded resources are not followed.
+
+use super::{Subresource, SubresourceLoader};
+use crate::common::media::FetchedResource;
+use resvg::usvg;
+use std::collections::HashMap;
+use std::sync::Arc;
+use url::Url;
+
+const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+const XLINK_NAMESPACE: &str = "http://www.w3.org/1999/xlink";
+const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// Prefix of the ids given to elements copied in from other documents
+const COPY_ID_PREFIX: &str = "gosub-external-";
+
+/// Image types usvg decodes; anything else is left to its sniffing
+const IMAGE_TYPES: [&str; 6] = [
+    "image/png",
+    "image/jpeg",
+    "image/jpg",
+    "image/gif",
+    "image/webp",
+    "image/svg+xml",
+];
+
+/// The loaded resources of one document
+#[derive(Default)]
+pub(super) struct Resources {
+    /// Images by their `href` as written in the document
+    images: HashMap<String, Arc<FetchedResource>>,
+    style_sheet: String,
+    /// Elements used from other documents: the `href` as written, the id of the copy and the
+    /// copy's markup
+    uses: Vec<(String, String, String)>,
+}
+
+impl Resources {
+    /// Points `options` at the loaded images and style sheets. Returns the document to parse
+    /// instead of the original when elements were copied into it.
+    pub(super) fn apply(self, source: &str, options: &mut usvg::Options<'static>) -> Option<String> {
+        let images = self.images;
+        let decode = usvg::ImageHrefResolver::default_data_resolver();
+        options.image_href_resolver.resolve_string = Box::new(move |href: &str, options: &usvg::Options| {
+            let image = images.get(href)?;
+            decode(image_type(image), Arc::new(image.body.to_vec()), options)
+        });
+        if !self.style_sheet.is_empty() {
+            options.style_sheet = Some(self.style_sheet);
+        }
+
+        if self.uses.is_empty() {
+            return None;
+        }
+        let doc = parse(source).ok()?;
+        let mut out = String::with_capacity(source.len());
+        write_element(doc.root_element(), None, &self.uses, &mut out);
+        Some(out)
+    }
+}
+
+/// Loads what the document `source` refers to outside of itself, resolving against `base`.
+/// `None` when `source` isn't a well-formed document.
+pub(super) fn load(source: &str, base: &Url, loader: &dyn SubresourceLoader) -> Option<Resources> {
+    let doc = parse(source).ok()?;
+    let mut resources = Resources::default();
+    let fetch = |href: &str| match loader.load(&base.join(href).ok()?) {
+        Subresource::Loaded(resource) => Some(resource),
+        Subresource::Failed | Subresource::Pending => None,
+    };
+
+    for node in doc.descendants() {
+        if let Some(pi) = node.pi() {
+            if pi.target == "xml-stylesheet" {
+                if let Some(sheet) = pi.value.and_then(pseudo_attribute_href).and_then(fetch) {
+                    add_style_sheet(&mut resources, &sheet);
+                }
+            }
+            continue;
+        }
+        if !node.is_element() || node.tag_name().namespace() != Some(SVG_NAMESPACE) {
+            continue;
+        }
+        match node.tag_name().name() {
+            "image" => {
+                let Some(href) = href(node).filter(|href| !href.starts_with("data:")) else {
+                    continue;
+                };
+                if !resources.images.contains_key(href) {
+                    if let Some(image) = fetch(href) {
+                        resources.images.insert(href.to_string(), image);
+                    }
+                }
+            }
+            "use" => {
+                let Some(href) = href(node).filter(|href| !href.starts_with('#')) else {
+                    continue;
+                };
+                if resources.uses.iter().any(|(used, _, _)| used == href) {
+                    continue;
+                }
+                let Some(mut url) = base.join(href).ok() else {
+                    continue;
+                };
+                let fragment = url.fragment().map(str::to_string);
+                url.set_fragment(None);
+                let Subresource::Loaded(document) = loader.load(&url) else {
+                    continue;
+                };
+                let id = format!("{COPY_ID_PREFIX}{}", resources.uses.len());
+                if let Some(copy) = copy_element(&document, fragment.as_deref(), &id) {
+                    resources.uses.push((href.to_string(), id, copy));
+                }
+            }
+            "style" => {
+                let css: String = node.children().filter_map(|child| child.text()).collect();
+                for import in css_imports(&css) {
+                    if let Some(sheet) = fetch(import) {
+                        add_style_sheet(&mut resources, &sheet);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(resources)
+}
+
+fn parse(source: &str) -> Result<roxmltree::Document<'_>, roxmltree::Error> {
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    roxmltree::Document::parse_with_options(source, options)
+}
+
+fn href<'a>(node: roxmltree::Node<'a, '_>) -> Option<&'a str> {
+    node.attribute((XLINK_NAMESPACE, "href"))
+        .or_else(|| node.attribute("href"))
+}
+
+fn add_style_sheet(resources: &mut Resources, sheet: &FetchedResource) {
+    resources.style_sheet.push_str(&String::from_utf8_lossy(&sheet.body));
+    resources.style_sheet.push('\n');
+}
+
+/// The type usvg should decode `image` as
+fn image_type(image: &FetchedResource) -> &'static str {
+    let essence = image
+        .content_type
+        .as_deref()
+        .and_then(|value| value.split(';').next())
+        .unwrap_or_default()
+        .trim();
+    IMAGE_TYPES
+        .into_iter()
+        .find(|known| known.eq_ignore_ascii_case(essence))
+        .unwrap_or("text/plain")
+}
+
+/// The `href` pseudo-attribute of an `<?xml-stylesheet?>` instruction
+fn pseudo_attribute_href(value: &str) -> Option<&str> {
+    let mut rest = value;
+    while let Some(at) = rest.find("href") {
+        let after = rest[at + "href".len()..].trim_start();
+        rest = &rest[at + "href".len()..];
+        let Some(after) = after.strip_prefix('=') else {
+            continue;
+        };
+        return quoted(after.trim_start()).map(|(href, _)| href);
+    }
+    None
+}
+
+/// The URLs of the `@import` rules in `css`
+fn css_imports(css: &str) -> Vec<&str> {
+    let mut imports = Vec::new();
+    let mut rest = css;
+    while let Some(at) = rest.find("@import") {
+        rest = rest[at + "@import".len()..].trim_start();
+        let import = match rest.strip_prefix("url(") {
+            Some(inner) => inner.split_once(')').map(|(url, after)| {
+                let url = url.trim();
+                (quoted(url).map_or(url, |(url, _)| url), after)
+            }),
+            None => quoted(rest),
+        };
+        if let Some((url, after)) = import {
+            imports.push(url);
+            rest = after;
+        }
+    }
+    imports
+}
+
+/// The contents of the quoted string `value` starts with, and what follows it
+fn quoted(value: &str) -> Option<(&str, &str)> {
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    value[1..].split_once(quote)
+}
+
+/// Markup of the element `id` of the document `resource` (its root without an id), under the
+/// id `copy_id`.
+fn copy_element(resource: &FetchedResource, id: Option<&str>, copy_id: &str) -> Option<String> {
+    let source = std::str::from_utf8(&resource.body).ok()?;
+    let doc = parse(source).ok()?;
+    let element = match id {
+        Some(id) => doc.descendants().find(|node| node.attribute("id") == Some(id))?,
+        None => doc.root_element(),
+    };
+    if element.tag_name().namespace() != Some(SVG_NAMESPACE) {
+        return None;
+    }
+    let mut out = String::new();
+    write_element(element, Some(copy_id), &[], &mut out);
+    Some(out)
+}
+
+/// Writes `node` and its subtree, leaving out what isn't SVG. `id` replaces the element's own
+/// id. The outermost element declares the namespaces, and as the document root it also gets
+/// the `<defs>` with the copies of `uses`, to which the `<use>` elements are pointed.
+fn write_element(node: roxmltree::Node, id: Option<&str>, uses: &[(String, String, String)], out: &mut String) {
+    let outermost = node.parent_element().is_none() || id.is_some();
+    let name = node.tag_name().name();
+    out.push('<');
+    out.push_str(name);
+    if outermost {
+        write_attribute("xmlns", SVG_NAMESPACE, out);
+        write_attribute("xmlns:xlink", XLINK_NAMESPACE, out);
+    }
+    if let Some(id) = id {
+        write_attribute("id", id, out);
+    }
+    for attribute in node.attributes() {
+        let local = attribute.name();
+        let name = match attribute.namespace() {
+            None if local == "id" && id.is_some() => continue,
+            None => local.to_string(),
+            Some(XLINK_NAMESPACE) => format!("xlink:{local}"),
+            Some(XML_NAMESPACE) => format!("xml:{local}"),
+            Some(_) => continue,
+        };
+        let copy = match (name.as_str(), node.tag_name().name()) {
+            ("href" | "xlink:href", "use") => uses.iter().find(|(href, _, _)| href == attribute.value()),
+            _ => None,
+        };
+        match copy {
+            Some((_, copy_id, _)) => write_attribute(&name, &format!("#{copy_id}"), out),
+            None => write_attribute(&name, attribute.value(), out),
+        }
+    }
+    out.push('>');
+
+    for child in node.children() {
+        if child.is_element() {
+            if child.tag_name().namespace() == Some(SVG_NAMESPACE) {
+                write_element(child, None, uses, out);
+            }
+        } else if child.is_text() {
+            escape_into(child.text().unwrap_or_default(), out);
+        }
+    }
+    if node.parent_element().is_none() && !uses.is_empty() {
+        out.push_str("<defs>");
+        for (_, _, copy) in uses {
+            out.push_str(copy);
+        }
+        out.push_str("</defs>");
+    }
+
+    out.push_str("</");
+    out.push_str(name);
+    out.push('>');
+}
+
+fn write_attribute(name: &str, value: &str, out: &mut String) {
+    out.push(' ');
+    out.push_str(name);
+    out.push_str("=\"");
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn escape_into(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_style_sheet_references() {
+        assert_eq!(
+            pseudo_attribute_href(r#"type="text/css" href='style.css'"#),
+            Some("style.css")
+        );
+        assert_eq!(pseudo_attribute_href(r#"type="text/css""#), None);
+        assert_eq!(
+            css_imports(r#"@import url("a.css"); @import 'b.css' screen; @import url(c.css); rect { fill: red }"#),
+            ["a.css", "b.css", "c.css"]
+        );
+    }
+}
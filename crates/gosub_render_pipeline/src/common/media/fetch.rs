@@ -0,0 +1,41 @@
+//! How the [`MediaStore`](crate::common::media::MediaStore) gets at the network.
+//!
+//! The store fetches images, SVGs and the resources SVGs refer to through a [`ResourceFetcher`].
+//! On its own it uses [`DirectFetcher`]; an engine hands it a fetcher going through the tab's
+//! network stack, so media gets the same cookies, content blocking and caching as the rest of
+//! the page.
+
+use bytes::Bytes;
+use url::Url;
+
+/// A fetched resource: the raw `Content-Type` header and the body
+#[derive(Debug, Clone)]
+pub struct FetchedResource {
+    pub content_type: Option<String>,
+    pub body: Bytes,
+}
+
+/// Fetches the resources media needs. Only called from the media store's background threads
+/// (or from callers of its blocking loads), so implementations may block.
+pub trait ResourceFetcher: Send + Sync {
+    /// Fetches `url`, failing on anything but a successful response.
+    fn fetch(&self, url: &Url) -> anyhow::Result<FetchedResource>;
+}
+
+/// Fetches straight over the network, outside of any tab: no cookies and no content blocking.
+pub struct DirectFetcher;
+
+impl ResourceFetcher for DirectFetcher {
+    fn fetch(&self, url: &Url) -> anyhow::Result<FetchedResource> {
+        let response = gosub_sonar::net::simple::sync_fetch(url)?;
+
+        if !response.is_ok() {
+            anyhow::bail!("HTTP {} fetching resource", response.status);
+        }
+
+        Ok(FetchedResource {
+            content_type: response.headers.get("content-type").cloned(),
+            body: Bytes::from(response.body),
+        })
+    }
+}
@@ -1,12 +1,15 @@
 use crate::common::hash::{hash_from_data, hash_from_string, Sha256Hash};
 use crate::common::media::{
-    DecodedMedia, Image, Media, MediaDecoderRegistry, MediaId, MediaImage, MediaSvg, MediaType, Svg,
+    DecodeContext, DecodedMedia, DirectFetcher, FetchedResource, Image, Media, MediaDecoderRegistry, MediaId,
+    MediaImage, MediaSvg, MediaType, ResourceFetcher, Subresource, SubresourceLoader, Svg, SvgAnimation,
 };
 use bytes::Bytes;
 use parking_lot::RwLock;
+use std::cell::Cell;
 use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
 use url::Url;
 
 const DEFAULT_SVG_ID: MediaId = MediaId::new(0);
@@ -41,6 +44,32 @@ pub struct MediaStore {
     /// Compiled-in placeholder returned when an image is missing or failed to load
     default_image: Arc<Media>,
     decoders: MediaDecoderRegistry,
+    /// Fetches media and the resources SVGs refer to
+    fetcher: RwLock<Arc<dyn ResourceFetcher>>,
+    /// Resources SVGs refer to, by URL; `None` when the fetch failed
+    subresources: RwLock<HashMap<Url, Option<Arc<FetchedResource>>>>,
+    /// Inline SVGs decoded while some of their resources were still being fetched, keyed like
+    /// `cache`, so the complete decode replaces the same media
+    provisional: RwLock<HashMap<Sha256Hash, MediaId>>,
+    /// SVGs with animations, played by [`tick_animations`](Self::tick_animations)
+    animations: RwLock<HashMap<MediaId, AnimatedSvg>>,
+}
+
+/// An animated SVG and where its animation is at
+struct AnimatedSvg {
+    src: String,
+    base_url: Option<Url>,
+    animation: SvgAnimation,
+    /// The first tick after the SVG was painted
+    started: Option<Instant>,
+    /// Frames decoded so far. Paint commands carry it, so tiles showing an older frame are redrawn
+    frame: u64,
+    /// Hash of the markup the current frame was decoded from
+    frame_hash: Option<Sha256Hash>,
+    /// Whether the current frame was painted; animations nobody looks at are not advanced
+    painted: AtomicBool,
+    /// Whether the current frame is the last one
+    finished: bool,
 }
 
 impl Default for MediaStore {
@@ -89,9 +118,23 @@ impl MediaStore {
             default_svg,
             default_image,
             decoders,
+            fetcher: RwLock::new(Arc::new(DirectFetcher)),
+            subresources: RwLock::new(HashMap::new()),
+            provisional: RwLock::new(HashMap::new()),
+            animations: RwLock::new(HashMap::new()),
         }
     }
 
+    /// Fetches media through `fetcher` from now on, instead of straight from the network.
+    pub fn set_fetcher(&self, fetcher: Arc<dyn ResourceFetcher>) {
+        *self.fetcher.write() = fetcher;
+    }
+
+    /// The media already loaded for `src`, without fetching it when it isn't.
+    pub fn cached_media(&self, src: &str) -> Option<MediaId> {
+        self.cache.read().get(&hash_from_string(src)).copied()
+    }
+
     /// Non-blocking media load: cached hits return `Ready`, otherwise a background fetch (deduped
     /// per src) starts and `Pending` is returned without blocking layout. On completion the
     /// `completed` flag rises and the engine's [`take_completed`](Self::take_completed) poll
@@ -126,6 +169,11 @@ impl MediaStore {
         MediaRequest::Pending
     }
 
+    /// Whether any background fetch is still in flight.
+    pub fn has_pending(&self) -> bool {
+        !self.pending.read().is_empty()
+    }
+
     /// Returns and clears the "background fetch completed" flag; `true` means the engine should
     /// re-lay-out the page to pick up the new media.
     pub fn take_completed(&self) -> bool {
@@ -133,8 +181,14 @@ impl MediaStore {
     }
 
     /// Shared by the data, source and inline decode paths.
-    fn decode_media(&self, src: &str, mime: Option<&str>, data: &[u8]) -> anyhow::Result<Media> {
-        match self.decoders.decode(mime, data) {
+    fn decode_media(
+        &self,
+        src: &str,
+        mime: Option<&str>,
+        data: &[u8],
+        context: &DecodeContext,
+    ) -> anyhow::Result<Media> {
+        match self.decoders.decode_with(mime, data, context) {
             Ok(DecodedMedia::Raster(img)) => Ok(Media::image(src, img)),
             Ok(DecodedMedia::Vector(tree)) => Ok(Media::svg(src, Svg::new(*tree))),
             Err(e) => Err(anyhow::anyhow!("Failed to decode media from '{}': {}", src, e)),
@@ -190,15 +244,90 @@ impl MediaStore {
             MediaType::Svg => Some("image/svg+xml"),
             MediaType::Image => None,
         };
-        let media = self.decode_media("gosub://data", mime, data)?;
+        let media = self.decode_media("gosub://data", mime, data, &DecodeContext::default())?;
 
         let media_id = self.allocate_media_id();
+        self.register_animation(media_id, "gosub://data", &media, data, None);
         self.entries.write().insert(media_id, Arc::new(media));
         self.cache.write().insert(h, media_id);
 
         Ok(media_id)
     }
 
+    /// Loads the markup of an inline `<svg>`, resolving its references against `base_url`. The
+    /// resources it refers to are fetched in the background, as with
+    /// [`request_media`](Self::request_media): until they land, the SVG goes without them and
+    /// stays out of the cache, so the reflow after the fetch decodes it again into the same id.
+    pub fn load_inline_svg(self: &Arc<Self>, markup: &str, base_url: &str) -> anyhow::Result<MediaId> {
+        let h = hash_from_string(&format!("inline-svg:{base_url}:{markup}"));
+        if let Some(media_id) = self.cache.read().get(&h) {
+            return Ok(*media_id);
+        }
+
+        let base = Url::parse(base_url).ok();
+        let loader = StoreLoader::background(self);
+        let context = DecodeContext {
+            base_url: base.as_ref(),
+            loader: Some(&loader),
+        };
+        let media = self.decode_media("gosub://inline-svg", Some("image/svg+xml"), markup.as_bytes(), &context)?;
+
+        let provisional = self.provisional.read().get(&h).copied();
+        let media_id = provisional.unwrap_or_else(|| self.allocate_media_id());
+        self.register_animation(media_id, "gosub://inline-svg", &media, markup.as_bytes(), base.as_ref());
+        self.entries.write().insert(media_id, Arc::new(media));
+        if loader.incomplete.get() {
+            self.provisional.write().insert(h, media_id);
+        } else {
+            self.provisional.write().remove(&h);
+            self.cache.write().insert(h, media_id);
+        }
+
+        Ok(media_id)
+    }
+
+    /// A resource an SVG refers to that was fetched before
+    fn cached_subresource(&self, url: &Url) -> Option<Subresource> {
+        let subresources = self.subresources.read();
+        let entry = subresources.get(url)?;
+        Some(entry.clone().map_or(Subresource::Failed, Subresource::Loaded))
+    }
+
+    /// Fetches a resource an SVG refers to and remembers it, failed or not, so it is fetched once.
+    fn fetch_subresource(&self, url: &Url) -> Subresource {
+        let fetcher = Arc::clone(&*self.fetcher.read());
+        let resource = match fetcher.fetch(url) {
+            Ok(resource) => Some(Arc::new(resource)),
+            Err(e) => {
+                log::warn!("Failed to load SVG resource '{}': {}", url, e);
+                None
+            }
+        };
+        self.subresources.write().insert(url.clone(), resource.clone());
+        resource.map_or(Subresource::Failed, Subresource::Loaded)
+    }
+
+    /// Starts fetching a resource an SVG refers to on a background thread (deduped per URL),
+    /// raising the `completed` flag when it lands.
+    fn fetch_subresource_in_background(self: &Arc<Self>, url: &Url) {
+        let h = hash_from_string(url.as_str());
+        if !self.pending.write().insert(h) {
+            return;
+        }
+
+        let store = Arc::clone(self);
+        let url = url.clone();
+        let spawned = std::thread::Builder::new().name("media-fetch".into()).spawn(move || {
+            store.fetch_subresource(&url);
+            store.pending.write().remove(&h);
+            store.completed.store(true, Ordering::Relaxed);
+        });
+
+        if spawned.is_err() {
+            self.pending.write().remove(&h);
+        }
+    }
+
     /// Rasterize an SVG background to a `w`×`h` raster tile and return its media id, so a tiled
     /// `background-image: url(x.svg)` reuses the raster tiling path. Cached per (svg id, w, h) so
     /// it renders once. Returns `None` if the source is not an SVG or the pixmap can't allocate.
@@ -226,18 +355,114 @@ impl MediaStore {
     fn load_media_from_source(&self, src: &str) -> anyhow::Result<MediaId> {
         log::debug!("Loading non-cached media from path: {}", src);
         // `data:` URIs carry the bytes inline - decode them directly instead of going to the network.
-        let media = if let Some(rest) = src.strip_prefix("data:") {
+        let media_id = self.allocate_media_id();
+        if let Some(rest) = src.strip_prefix("data:") {
             let (mime, bytes) = decode_data_uri(rest)?;
-            self.decode_media(src, mime.as_deref(), &bytes)?
+            let media = self.decode_media(src, mime.as_deref(), &bytes, &DecodeContext::default())?;
+            self.register_animation(media_id, src, &media, &bytes, None);
+            self.entries.write().insert(media_id, Arc::new(media));
         } else {
-            let (content_type, raw_data) = self.fetch_resource(src)?;
-            self.decode_media(src, content_type.as_deref(), &raw_data)?
+            let url = Url::parse(src)?;
+            let (content_type, raw_data) = self.fetch_resource(&url)?;
+            // Already off the layout path here, so an SVG's own resources are waited for.
+            let loader = StoreLoader::blocking(self);
+            let context = DecodeContext {
+                base_url: Some(&url),
+                loader: Some(&loader),
+            };
+            let media = self.decode_media(src, content_type.as_deref(), &raw_data, &context)?;
+            self.register_animation(media_id, src, &media, &raw_data, Some(&url));
+            self.entries.write().insert(media_id, Arc::new(media));
+        }
+
+        Ok(media_id)
+    }
+
+    /// Plays the animations of `media` if it is an SVG that has any, decoded from `data` at `src`.
+    /// Decoding an SVG again (once its resources have landed) shows its current frame anew
+    /// without restarting the animation.
+    fn register_animation(&self, media_id: MediaId, src: &str, media: &Media, data: &[u8], base_url: Option<&Url>) {
+        if !matches!(media, Media::Svg(_)) {
+            return;
+        }
+        let Some(animation) = std::str::from_utf8(data).ok().and_then(SvgAnimation::parse) else {
+            return;
         };
+        self.animations
+            .write()
+            .entry(media_id)
+            .and_modify(|animated| {
+                animated.frame_hash = None;
+                animated.finished = false;
+            })
+            .or_insert_with(|| AnimatedSvg {
+                src: src.to_string(),
+                base_url: base_url.cloned(),
+                animation,
+                started: None,
+                frame: 0,
+                frame_hash: None,
+                painted: AtomicBool::new(false),
+                finished: false,
+            });
+    }
 
-        let media_id = self.allocate_media_id();
-        self.entries.write().insert(media_id, Arc::new(media));
+    /// The frame the SVG `media_id` is showing, 0 for SVGs that aren't animated. Painters tell
+    /// the frames of an animated SVG apart by it, and asking marks the frame as painted, which
+    /// lets [`tick_animations`](Self::tick_animations) move on to the next one.
+    pub fn svg_frame(&self, media_id: MediaId) -> u64 {
+        match self.animations.read().get(&media_id) {
+            Some(animated) => {
+                animated.painted.store(true, Ordering::Relaxed);
+                animated.frame
+            }
+            None => 0,
+        }
+    }
 
-        Ok(media_id)
+    /// Advances the animated SVGs painted since their last frame to `now`, decoding the frames
+    /// that look different into the SVG's media id. Returns whether any did, meaning the page
+    /// needs repainting. Frame resources come from the store like those of any other SVG.
+    pub fn tick_animations(self: &Arc<Self>, now: Instant) -> bool {
+        let frames: Vec<(MediaId, String, String, Option<Url>)> = self
+            .animations
+            .write()
+            .iter_mut()
+            .filter(|(_, animated)| !animated.finished && animated.painted.load(Ordering::Relaxed))
+            .filter_map(|(media_id, animated)| {
+                let started = *animated.started.get_or_insert(now);
+                let time = now.saturating_duration_since(started).as_secs_f64();
+                let markup = animated.animation.frame(time);
+                animated.finished = !animated.animation.is_running(time);
+                let hash = hash_from_string(&markup);
+                if animated.frame_hash == Some(hash) {
+                    return None;
+                }
+                animated.frame_hash = Some(hash);
+                Some((*media_id, animated.src.clone(), markup, animated.base_url.clone()))
+            })
+            .collect();
+
+        let mut advanced = false;
+        for (media_id, src, markup, base_url) in frames {
+            let loader = StoreLoader::background(self);
+            let context = DecodeContext {
+                base_url: base_url.as_ref(),
+                loader: Some(&loader),
+            };
+            match self.decode_media(&src, Some("image/svg+xml"), markup.as_bytes(), &context) {
+                Ok(media) => {
+                    self.entries.write().insert(media_id, Arc::new(media));
+                    if let Some(animated) = self.animations.write().get_mut(&media_id) {
+                        animated.frame += 1;
+                        animated.painted.store(false, Ordering::Relaxed);
+                    }
+                    advanced = true;
+                }
+                Err(e) => log::warn!("Failed to decode a frame of the animated SVG '{}': {}", src, e),
+            }
+        }
+        advanced
     }
 
     /// Falls back to the default image if `media_id` is missing or is not an image.
@@ -302,18 +527,54 @@ impl MediaStore {
 
     /// Blocking fetch returning the raw `Content-Type` header and body. Classification is left to
     /// the decoder registry, which treats the content type as a hint only.
-    fn fetch_resource(&self, src: &str) -> anyhow::Result<(Option<String>, Bytes)> {
-        let url = Url::parse(src)?;
-        let response = gosub_sonar::net::simple::sync_fetch(&url)?;
+    fn fetch_resource(&self, url: &Url) -> anyhow::Result<(Option<String>, Bytes)> {
+        let fetcher = Arc::clone(&*self.fetcher.read());
+        let resource = fetcher.fetch(url)?;
+        Ok((resource.content_type, resource.body))
+    }
+}
+
+/// Hands decoders the resources media refers to from the store, fetching what it doesn't have
+/// yet either right away or in the background.
+struct StoreLoader<'a> {
+    store: &'a MediaStore,
+    /// Set to fetch in the background instead of waiting
+    background: Option<&'a Arc<MediaStore>>,
+    /// Whether a resource was still being fetched
+    incomplete: Cell<bool>,
+}
 
-        if !response.is_ok() {
-            anyhow::bail!("HTTP {} fetching resource", response.status);
+impl<'a> StoreLoader<'a> {
+    fn blocking(store: &'a MediaStore) -> Self {
+        Self {
+            store,
+            background: None,
+            incomplete: Cell::new(false),
         }
+    }
 
-        let content_type = response.headers.get("content-type").cloned();
-        let raw_bytes = Bytes::from(response.body);
+    fn background(store: &'a Arc<MediaStore>) -> Self {
+        Self {
+            store,
+            background: Some(store),
+            incomplete: Cell::new(false),
+        }
+    }
+}
 
-        Ok((content_type, raw_bytes))
+impl SubresourceLoader for StoreLoader<'_> {
+    fn load(&self, url: &Url) -> Subresource {
+        if let Some(resource) = self.store.cached_subresource(url) {
+            return resource;
+        }
+        match self.background {
+            Some(store) => {
+                store.fetch_subresource_in_background(url);
+                self.incomplete.set(true);
+                Subresource::Pending
+            }
+            None => self.store.fetch_subresource(url),
+        }
     }
 }
 
@@ -387,6 +648,7 @@ mod tests {
     use super::*;
     use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
     use std::io::Cursor;
+    use std::time::Duration;
 
     fn encode(format: ImageFormat) -> Vec<u8> {
         let rgba = DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 4, Rgba([200, 100, 50, 255])));
@@ -440,4 +702,135 @@ mod tests {
         let size = svg.svg.tree.size();
         assert_eq!((size.width() as u32, size.height() as u32), (20, 10));
     }
+
+    /// Serves fixed responses by URL and counts the requests.
+    struct FakeFetcher {
+        responses: HashMap<&'static str, (&'static str, Vec<u8>)>,
+        requests: std::sync::atomic::AtomicUsize,
+    }
+
+    impl ResourceFetcher for FakeFetcher {
+        fn fetch(&self, url: &Url) -> anyhow::Result<FetchedResource> {
+            self.requests.fetch_add(1, Ordering::Relaxed);
+            let (content_type, body) = self
+                .responses
+                .get(url.as_str())
+                .ok_or_else(|| anyhow::anyhow!("404 for {url}"))?;
+            Ok(FetchedResource {
+                content_type: Some(content_type.to_string()),
+                body: Bytes::from(body.clone()),
+            })
+        }
+    }
+
+    /// An image, an element used from another document and a style sheet, all referred to
+    /// relative to the SVG
+    const SVG_WITH_RESOURCES: &str = r##"<?xml-stylesheet type="text/css" href="style.css"?>
+<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="40" height="10">
+  <image href="dot.png" width="10" height="10"/>
+  <use xlink:href="shapes.svg#box" x="10"/>
+  <rect class="styled" x="30" width="10" height="10"/>
+</svg>"##;
+
+    fn fake_fetcher() -> Arc<FakeFetcher> {
+        let shapes =
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><rect id="box" width="10" height="10" fill="lime"/></svg>"#;
+        Arc::new(FakeFetcher {
+            responses: HashMap::from([
+                (
+                    "http://example.test/pic.svg",
+                    ("image/svg+xml", SVG_WITH_RESOURCES.into()),
+                ),
+                ("http://example.test/dot.png", ("image/png", encode(ImageFormat::Png))),
+                ("http://example.test/shapes.svg", ("image/svg+xml", shapes.into())),
+                (
+                    "http://example.test/style.css",
+                    ("text/css", b".styled { fill: blue }".to_vec()),
+                ),
+            ]),
+            requests: std::sync::atomic::AtomicUsize::new(0),
+        })
+    }
+
+    /// RGBA of the pixel at (`x`, 5) of the SVG `media_id` drawn at 40×10
+    fn pixel(store: &MediaStore, media_id: MediaId, x: usize) -> [u8; 4] {
+        let svg = store.get_svg(media_id);
+        let image = render_svg_tree_to_image(&svg.svg.tree, 40, 10).expect("render svg");
+        let at = (5 * 40 + x) * 4;
+        let mut rgba = [0; 4];
+        rgba.copy_from_slice(&image.as_raw()[at..at + 4]);
+        rgba
+    }
+
+    #[test]
+    fn svg_resources_load_through_the_fetcher() {
+        let store = MediaStore::new();
+        let fetcher = fake_fetcher();
+        store.set_fetcher(fetcher.clone());
+
+        let media_id = store.load_media("http://example.test/pic.svg").expect("load svg");
+        assert!(!store.is_placeholder(media_id));
+        assert_eq!(pixel(&store, media_id, 5), [200, 100, 50, 255], "the image");
+        assert_eq!(pixel(&store, media_id, 15), [0, 255, 0, 255], "the used element");
+        assert_eq!(pixel(&store, media_id, 35), [0, 0, 255, 255], "the style sheet");
+        assert_eq!(fetcher.requests.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn inline_svg_resources_load_in_the_background() {
+        let store = Arc::new(MediaStore::new());
+        store.set_fetcher(fake_fetcher());
+
+        let first = store
+            .load_inline_svg(SVG_WITH_RESOURCES, "http://example.test/page.html")
+            .expect("load inline svg");
+        assert_eq!(
+            pixel(&store, first, 35)[3],
+            255,
+            "drawn without its resources meanwhile"
+        );
+
+        for _ in 0..500 {
+            if !store.has_pending() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(store.take_completed(), "the fetches ask for a reflow");
+
+        let complete = store
+            .load_inline_svg(SVG_WITH_RESOURCES, "http://example.test/page.html")
+            .expect("reload inline svg");
+        assert_eq!(complete, first, "the complete decode replaces the provisional one");
+        assert_eq!(pixel(&store, complete, 15), [0, 255, 0, 255]);
+        assert_eq!(pixel(&store, complete, 35), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn animated_svgs_advance_once_painted() {
+        let store = Arc::new(MediaStore::new());
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="10">
+  <rect width="40" height="10" fill="red"><animate attributeName="fill" to="blue" dur="1s" fill="freeze"/></rect>
+</svg>"#;
+        let media_id = store.load_media_from_data(MediaType::Svg, svg).expect("load svg");
+        let start = Instant::now();
+        assert!(!store.tick_animations(start), "not painted yet");
+        assert_eq!(store.svg_frame(media_id), 0);
+
+        assert!(store.tick_animations(start));
+        assert_eq!(pixel(&store, media_id, 5), [255, 0, 0, 255]);
+        assert!(
+            !store.tick_animations(start + Duration::from_secs(2)),
+            "frame 1 not painted yet"
+        );
+
+        assert_eq!(store.svg_frame(media_id), 1);
+        assert!(store.tick_animations(start + Duration::from_secs(2)));
+        assert_eq!(pixel(&store, media_id, 5), [0, 0, 255, 255]);
+        assert_eq!(store.svg_frame(media_id), 2);
+        assert!(
+            !store.tick_animations(start + Duration::from_secs(3)),
+            "finished animations stay on their last frame"
+        );
+    }
 }
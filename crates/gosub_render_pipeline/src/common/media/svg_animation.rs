@@ -0,0 +1,1400 @@
+//! Animated SVG: the SMIL `<animate>`, `<set>` and `<animateTransform>` elements and CSS
+//! animations of `transform` and `opacity`, played by writing the document out again as it looks
+//! at a given time.
+//!
+//! usvg has no notion of time, so an animated SVG is kept as its source plus the animations found
+//! in it, and every frame is new markup with the animated values written into the elements. The
+//! [`MediaStore`](crate::common::media::MediaStore) decodes the frames as the engine ticks it.
+//!
+//! SMIL animations start at an offset `begin` (event and sync-base timing never start), take
+//! `values` or `from`/`to`/`by`, and honour `keyTimes`, `calcMode` (`paced` is played as
+//! `linear`), `keySplines`, `repeatCount`, `repeatDur="indefinite"` and `fill="freeze"`. CSS
+//! animations come from `@keyframes` and `animation` declarations in the document's `<style>`
+//! elements (compound selectors and descendant combinators) and `style` attributes; transform
+//! origins are only honoured in absolute lengths. Values interpolate as colors or number for
+//! number, and switch halfway otherwise.
+
+use crate::common::document::inline_style::parse_named_color;
+use crate::common::document::style::Value;
+use cow_utils::CowUtils;
+use gosub_shared::animation::Easing;
+use std::collections::HashMap;
+
+const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+const XLINK_NAMESPACE: &str = "http://www.w3.org/1999/xlink";
+const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// SMIL elements, left out of the frames
+const ANIMATION_ELEMENTS: [&str; 5] = ["animate", "set", "animateTransform", "animateMotion", "animateColor"];
+
+/// An SVG document and the animations in it
+#[derive(Debug)]
+pub struct SvgAnimation {
+    source: String,
+    tracks: Vec<Track>,
+}
+
+/// One animated value of one element
+#[derive(Debug)]
+struct Track {
+    /// Index of the element in document order
+    element: usize,
+    target: Target,
+    /// The values at `key_times`, which run from 0 to 1
+    values: Vec<String>,
+    key_times: Vec<f64>,
+    /// Easing between consecutive values: one for all of them, one per pair, or none at all for
+    /// holding each value until the next
+    easing: Vec<Easing>,
+    timing: Timing,
+}
+
+/// What a track animates
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    /// An attribute, replaced by the animated value
+    Attribute(String),
+    /// The `transform` attribute, replaced by the animated value or followed by it
+    Transform { additive: bool },
+    /// A CSS property, added to the element's `style`
+    Style(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Direction {
+    Normal,
+    Reverse,
+    Alternate,
+    AlternateReverse,
+}
+
+/// When an animation plays, in seconds from the start of the document
+#[derive(Debug, Clone)]
+struct Timing {
+    begin: f64,
+    /// Duration of one iteration; infinite for values that never progress
+    duration: f64,
+    iterations: f64,
+    direction: Direction,
+    /// Whether the first value applies before `begin`
+    fill_backwards: bool,
+    /// Whether the last value applies after the last iteration
+    fill_forwards: bool,
+}
+
+impl Timing {
+    /// Progress through the current iteration at `time`, or `None` when the animation doesn't
+    /// apply then.
+    fn progress(&self, time: f64) -> Option<f64> {
+        let local = time - self.begin;
+        if local < 0.0 {
+            return self.fill_backwards.then(|| self.directed(0.0, 0.0));
+        }
+        if !self.duration.is_finite() {
+            return Some(self.directed(0.0, 0.0));
+        }
+        if self.duration <= 0.0 || self.iterations <= 0.0 {
+            return None;
+        }
+        if local >= self.duration * self.iterations {
+            if !self.fill_forwards {
+                return None;
+            }
+            let fract = self.iterations.fract();
+            return Some(if fract > 0.0 {
+                self.directed(fract, self.iterations.floor())
+            } else {
+                self.directed(1.0, self.iterations - 1.0)
+            });
+        }
+        let iteration = (local / self.duration).floor();
+        Some(self.directed(local / self.duration - iteration, iteration))
+    }
+
+    fn directed(&self, progress: f64, iteration: f64) -> f64 {
+        let odd = iteration % 2.0 == 1.0;
+        let reversed = match self.direction {
+            Direction::Normal => false,
+            Direction::Reverse => true,
+            Direction::Alternate => odd,
+            Direction::AlternateReverse => !odd,
+        };
+        if reversed {
+            1.0 - progress
+        } else {
+            progress
+        }
+    }
+
+    /// When the animation stops changing
+    fn end(&self) -> f64 {
+        if self.duration.is_finite() {
+            self.begin + self.duration * self.iterations
+        } else {
+            self.begin
+        }
+    }
+}
+
+impl Track {
+    fn value(&self, time: f64) -> Option<String> {
+        let progress = self.timing.progress(time)?;
+        let last = self.values.len() - 1;
+        if last == 0 {
+            return Some(self.values[0].clone());
+        }
+        if self.easing.is_empty() {
+            let index = self.key_times.iter().rposition(|key| *key <= progress).unwrap_or(0);
+            return Some(self.values[index].clone());
+        }
+
+        let index = self.key_times[1..last]
+            .iter()
+            .position(|key| progress < *key)
+            .unwrap_or(last - 1);
+        let (from, to) = (self.key_times[index], self.key_times[index + 1]);
+        let local = if to > from {
+            ((progress - from) / (to - from)).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let easing = self.easing.get(index).unwrap_or(&self.easing[0]);
+        Some(interpolate(
+            &self.values[index],
+            &self.values[index + 1],
+            easing.eval(local as f32) as f64,
+        ))
+    }
+}
+
+impl SvgAnimation {
+    /// The animations in `source`; `None` when there are none (or it isn't well-formed).
+    pub fn parse(source: &str) -> Option<Self> {
+        if !source.contains("<animate") && !source.contains("<set") && !source.contains("animation") {
+            return None;
+        }
+        let doc = parse_xml(source).ok()?;
+        let elements: Vec<roxmltree::Node> = doc.descendants().filter(roxmltree::Node::is_element).collect();
+        let index_of: HashMap<roxmltree::NodeId, usize> = elements
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.id(), index))
+            .collect();
+
+        let mut tracks = Vec::new();
+        for node in &elements {
+            if node.tag_name().namespace() == Some(SVG_NAMESPACE) {
+                if let Some(track) = smil_track(*node, &elements, &index_of) {
+                    tracks.push(track);
+                }
+            }
+        }
+        tracks.extend(css_tracks(&doc, &elements));
+
+        if tracks.is_empty() {
+            return None;
+        }
+        Some(Self {
+            source: source.to_string(),
+            tracks,
+        })
+    }
+
+    /// Whether the document still changes after `time` seconds
+    pub fn is_running(&self, time: f64) -> bool {
+        self.tracks.iter().any(|track| time < track.timing.end())
+    }
+
+    /// Markup of the document `time` seconds after its animations started
+    pub fn frame(&self, time: f64) -> String {
+        let Ok(doc) = parse_xml(&self.source) else {
+            return self.source.clone();
+        };
+
+        let mut overrides: HashMap<usize, Override> = HashMap::new();
+        for track in &self.tracks {
+            let Some(value) = track.value(time) else {
+                continue;
+            };
+            let element = overrides.entry(track.element).or_default();
+            match &track.target {
+                Target::Attribute(name) => element.attributes.push((name.clone(), value)),
+                Target::Transform { additive } => element.transforms.push((*additive, value)),
+                Target::Style(name) => element.style.push((name.clone(), value)),
+            }
+        }
+
+        let index_of: HashMap<roxmltree::NodeId, usize> = doc
+            .descendants()
+            .filter(roxmltree::Node::is_element)
+            .enumerate()
+            .map(|(index, node)| (node.id(), index))
+            .collect();
+        let mut out = String::with_capacity(self.source.len());
+        for node in doc.root().children() {
+            if let Some(pi) = node.pi() {
+                out.push_str("<?");
+                out.push_str(pi.target);
+                if let Some(value) = pi.value {
+                    out.push(' ');
+                    out.push_str(value);
+                }
+                out.push_str("?>");
+            }
+        }
+        write_element(doc.root_element(), &index_of, &overrides, &mut out);
+        out
+    }
+}
+
+/// The animated values of one element at one time
+#[derive(Default)]
+struct Override {
+    attributes: Vec<(String, String)>,
+    /// Transforms in the order they apply, and whether each adds to the ones before it
+    transforms: Vec<(bool, String)>,
+    style: Vec<(String, String)>,
+}
+
+impl Override {
+    fn transform(&self, base: Option<&str>) -> Option<String> {
+        let mut transform = base.map(str::to_string);
+        for (additive, value) in &self.transforms {
+            transform = match (additive, transform) {
+                (true, Some(base)) if !base.trim().is_empty() => Some(format!("{base} {value}")),
+                _ => Some(value.clone()),
+            };
+        }
+        transform
+    }
+}
+
+fn parse_xml(source: &str) -> Result<roxmltree::Document<'_>, roxmltree::Error> {
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    roxmltree::Document::parse_with_options(source, options)
+}
+
+// ── SMIL ──────────────────────────────────────────────────────────────────────
+
+fn smil_track(
+    node: roxmltree::Node,
+    elements: &[roxmltree::Node],
+    index_of: &HashMap<roxmltree::NodeId, usize>,
+) -> Option<Track> {
+    let kind = node.tag_name().name();
+    if !matches!(kind, "animate" | "set" | "animateTransform" | "animateColor") {
+        return None;
+    }
+
+    let target = match node
+        .attribute((XLINK_NAMESPACE, "href"))
+        .or_else(|| node.attribute("href"))
+    {
+        Some(href) => {
+            let id = href.strip_prefix('#')?;
+            *elements.iter().find(|element| element.attribute("id") == Some(id))?
+        }
+        None => node.parent_element()?,
+    };
+    let element = *index_of.get(&target.id())?;
+
+    let attribute = node.attribute("attributeName")?.trim();
+    let (target_kind, base) = match kind {
+        "animateTransform" if attribute == "transform" || attribute == "gradientTransform" => {
+            let additive = node.attribute("additive") == Some("sum");
+            if attribute == "transform" {
+                (Target::Transform { additive }, None)
+            } else {
+                (Target::Attribute(attribute.to_string()), target.attribute(attribute))
+            }
+        }
+        "animateTransform" | "animate" if attribute == "transform" => return None,
+        _ => (Target::Attribute(attribute.to_string()), target.attribute(attribute)),
+    };
+
+    let mut values = if kind == "set" {
+        vec![node.attribute("to")?.to_string()]
+    } else if let Some(values) = node.attribute("values") {
+        values
+            .split(';')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+            .collect()
+    } else {
+        let from = node.attribute("from").or(base);
+        match (from, node.attribute("to"), node.attribute("by")) {
+            (Some(from), Some(to), _) => vec![from.to_string(), to.to_string()],
+            (Some(from), None, Some(by)) => vec![from.to_string(), add_numbers(from, by)?],
+            _ => return None,
+        }
+    };
+    if values.is_empty() {
+        return None;
+    }
+    if kind == "animateTransform" {
+        let transform = node.attribute("type").unwrap_or("translate").trim();
+        values = values
+            .iter()
+            .map(|value| {
+                let numbers: Vec<String> = number_list(value)?.into_iter().map(format_number).collect();
+                Some(format!("{transform}({})", numbers.join(" ")))
+            })
+            .collect::<Option<_>>()?;
+    }
+
+    let discrete = kind == "set" || node.attribute("calcMode") == Some("discrete");
+    let key_times = node
+        .attribute("keyTimes")
+        .and_then(number_list)
+        .filter(|times| times.len() == values.len())
+        .unwrap_or_else(|| even_key_times(values.len(), discrete));
+    let easing = if discrete {
+        Vec::new()
+    } else if node.attribute("calcMode") == Some("spline") {
+        let splines: Option<Vec<Easing>> = node
+            .attribute("keySplines")?
+            .split(';')
+            .filter(|spline| !spline.trim().is_empty())
+            .map(|spline| match number_list(spline)?.as_slice() {
+                [x1, y1, x2, y2] => Some(Easing::CubicBezier(*x1 as f32, *y1 as f32, *x2 as f32, *y2 as f32)),
+                _ => None,
+            })
+            .collect();
+        splines.filter(|splines| splines.len() + 1 == values.len())?
+    } else {
+        vec![Easing::Linear]
+    };
+
+    let begin = match node.attribute("begin") {
+        Some(begin) => begin.split(';').find_map(clock_value)?,
+        None => 0.0,
+    };
+    let duration = node
+        .attribute("dur")
+        .and_then(clock_value)
+        .filter(|duration| *duration > 0.0)
+        .unwrap_or(f64::INFINITY);
+    let iterations = match (node.attribute("repeatCount"), node.attribute("repeatDur")) {
+        (Some("indefinite"), _) | (_, Some("indefinite")) => f64::INFINITY,
+        (Some(count), _) => count.trim().parse().ok().filter(|count: &f64| *count > 0.0)?,
+        _ => 1.0,
+    };
+
+    Some(Track {
+        element,
+        target: target_kind,
+        values,
+        key_times,
+        easing,
+        timing: Timing {
+            begin,
+            duration,
+            iterations,
+            direction: Direction::Normal,
+            fill_backwards: false,
+            fill_forwards: kind == "set" || node.attribute("fill") == Some("freeze"),
+        },
+    })
+}
+
+/// Evenly spread key times: one per value from 0 to 1, or, for discrete animations, the start
+/// of each of as many equal intervals.
+fn even_key_times(count: usize, discrete: bool) -> Vec<f64> {
+    let intervals = if discrete {
+        count
+    } else {
+        count.saturating_sub(1).max(1)
+    };
+    (0..count).map(|index| index as f64 / intervals as f64).collect()
+}
+
+/// A SMIL clock value in seconds: `02:30.5`, `1:20`, `3.5s`, `200ms`, `1min`, `1h` or plain
+/// seconds. `None` for anything else, including `indefinite` and event timing.
+fn clock_value(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if value.contains(':') {
+        let parts: Vec<f64> = value.split(':').map(|part| part.parse().ok()).collect::<Option<_>>()?;
+        return match parts.as_slice() {
+            [minutes, seconds] => Some(minutes * 60.0 + seconds),
+            [hours, minutes, seconds] => Some(hours * 3600.0 + minutes * 60.0 + seconds),
+            _ => None,
+        };
+    }
+    let units = [("ms", 0.001), ("min", 60.0), ("h", 3600.0), ("s", 1.0)];
+    let (number, scale) = units
+        .iter()
+        .find_map(|(unit, scale)| value.strip_suffix(unit).map(|number| (number, *scale)))
+        .unwrap_or((value, 1.0));
+    number.parse::<f64>().ok().map(|number| number * scale)
+}
+
+// ── CSS ───────────────────────────────────────────────────────────────────────
+
+/// A `selector { declarations }` rule of a style sheet
+struct Rule {
+    selectors: Vec<Vec<Compound>>,
+    declarations: Vec<(String, String)>,
+}
+
+/// A compound selector: a type selector and any ids and classes
+#[derive(Default)]
+struct Compound {
+    tag: Option<String>,
+    ids: Vec<String>,
+    classes: Vec<String>,
+}
+
+/// The declarations that make up an element's CSS animations
+#[derive(Default)]
+struct AnimationStyle {
+    names: Vec<String>,
+    durations: Vec<f64>,
+    easings: Vec<Easing>,
+    delays: Vec<f64>,
+    iterations: Vec<f64>,
+    directions: Vec<Direction>,
+    fill_modes: Vec<(bool, bool)>,
+    paused: Vec<bool>,
+    origin: Option<(f64, f64)>,
+}
+
+fn css_tracks(doc: &roxmltree::Document, elements: &[roxmltree::Node]) -> Vec<Track> {
+    let css: String = doc
+        .descendants()
+        .filter(|node| node.has_tag_name((SVG_NAMESPACE, "style")))
+        .flat_map(|node| node.children().filter_map(|child| child.text()))
+        .collect();
+    let (rules, keyframes) = parse_style_sheet(&strip_comments(&css));
+
+    let mut tracks = Vec::new();
+    for (index, element) in elements.iter().enumerate() {
+        let mut matched: Vec<((usize, usize, usize), usize, &Rule)> = Vec::new();
+        for (order, rule) in rules.iter().enumerate() {
+            let specificity = rule
+                .selectors
+                .iter()
+                .filter(|selector| matches_selector(selector, *element))
+                .map(|selector| specificity(selector))
+                .max();
+            if let Some(specificity) = specificity {
+                matched.push((specificity, order, rule));
+            }
+        }
+        matched.sort_by_key(|(specificity, order, _)| (*specificity, *order));
+
+        let mut style = AnimationStyle::default();
+        for (_, _, rule) in matched {
+            for (name, value) in &rule.declarations {
+                style.apply(name, value);
+            }
+        }
+        if let Some(inline) = element.attribute("style") {
+            for (name, value) in parse_declarations(inline) {
+                style.apply(&name, &value);
+            }
+        }
+        tracks.extend(style.tracks(index, *element, &keyframes));
+    }
+    tracks
+}
+
+impl AnimationStyle {
+    fn apply(&mut self, name: &str, value: &str) {
+        let list = || split_top_level(value, ',');
+        match name {
+            "animation" => {
+                *self = AnimationStyle {
+                    origin: self.origin,
+                    ..AnimationStyle::default()
+                };
+                for animation in list() {
+                    self.push_shorthand(animation);
+                }
+            }
+            "animation-name" => self.names = list().into_iter().map(str::to_string).collect(),
+            "animation-duration" => self.durations = list().into_iter().filter_map(css_time).collect(),
+            "animation-delay" => self.delays = list().into_iter().filter_map(css_time).collect(),
+            "animation-timing-function" => self.easings = list().into_iter().filter_map(Easing::from_css).collect(),
+            "animation-iteration-count" => self.iterations = list().into_iter().filter_map(iteration_count).collect(),
+            "animation-direction" => self.directions = list().into_iter().filter_map(direction).collect(),
+            "animation-fill-mode" => self.fill_modes = list().into_iter().filter_map(fill_mode).collect(),
+            "animation-play-state" => self.paused = list().into_iter().map(|state| state == "paused").collect(),
+            "transform-origin" => self.origin = transform_origin(value),
+            _ => {}
+        }
+    }
+
+    /// Adds one animation of an `animation` shorthand
+    fn push_shorthand(&mut self, animation: &str) {
+        let (mut name, mut duration, mut delay) = (None, None, None);
+        let (mut easing, mut count, mut dir, mut fill, mut paused) = (None, None, None, None, false);
+        for token in split_top_level(animation, ' ') {
+            if let Some(time) = css_time(token) {
+                match duration {
+                    None => duration = Some(time),
+                    Some(_) => delay = Some(time),
+                }
+            } else if easing.is_none() && Easing::from_css(token).is_some() {
+                easing = Easing::from_css(token);
+            } else if count.is_none() && iteration_count(token).is_some() {
+                count = iteration_count(token);
+            } else if dir.is_none() && direction(token).is_some() {
+                dir = direction(token);
+            } else if fill.is_none() && fill_mode(token).is_some() {
+                fill = fill_mode(token);
+            } else if token == "paused" || token == "running" {
+                paused = token == "paused";
+            } else {
+                name = Some(token.to_string());
+            }
+        }
+        self.names.push(name.unwrap_or_else(|| "none".to_string()));
+        self.durations.push(duration.unwrap_or(0.0));
+        self.delays.push(delay.unwrap_or(0.0));
+        self.easings.push(easing.unwrap_or(Easing::Ease));
+        self.iterations.push(count.unwrap_or(1.0));
+        self.directions.push(dir.unwrap_or(Direction::Normal));
+        self.fill_modes.push(fill.unwrap_or((false, false)));
+        self.paused.push(paused);
+    }
+
+    fn tracks(&self, element: usize, node: roxmltree::Node, keyframes: &HashMap<String, Keyframes>) -> Vec<Track> {
+        // Longhand lists repeat to the length of the name list.
+        fn nth<T: Clone>(list: &[T], index: usize, default: T) -> T {
+            match list.len() {
+                0 => default,
+                len => list[index % len].clone(),
+            }
+        }
+
+        let mut tracks = Vec::new();
+        for (index, name) in self.names.iter().enumerate() {
+            let Some(frames) = keyframes.get(name) else {
+                continue;
+            };
+            if nth(&self.paused, index, false) {
+                continue;
+            }
+            let (fill_backwards, fill_forwards) = nth(&self.fill_modes, index, (false, false));
+            let timing = Timing {
+                begin: nth(&self.delays, index, 0.0),
+                duration: nth(&self.durations, index, 0.0),
+                iterations: nth(&self.iterations, index, 1.0),
+                direction: nth(&self.directions, index, Direction::Normal),
+                fill_backwards,
+                fill_forwards,
+            };
+            let easing = nth(&self.easings, index, Easing::Ease);
+
+            for property in ["transform", "opacity"] {
+                let mut stops: Vec<(f64, String)> = frames
+                    .iter()
+                    .filter_map(|(offset, declarations)| {
+                        let value = declarations.iter().rev().find(|(name, _)| name == property)?;
+                        let value = match property {
+                            "transform" => format_transform(&css_transform(&value.1)?),
+                            _ => value.1.clone(),
+                        };
+                        Some((*offset, value))
+                    })
+                    .collect();
+                if stops.is_empty() {
+                    continue;
+                }
+                stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+                stops.dedup_by(|later, earlier| {
+                    let same = later.0 == earlier.0;
+                    if same {
+                        earlier.1 = later.1.clone();
+                    }
+                    same
+                });
+
+                // Keyframes left out at either end take the element's own value.
+                let underlying = |neighbour: &str| match property {
+                    "transform" => identity_transform(neighbour),
+                    _ => node.attribute("opacity").unwrap_or("1").to_string(),
+                };
+                if stops[0].0 > 0.0 {
+                    let value = underlying(&stops[0].1);
+                    stops.insert(0, (0.0, value));
+                }
+                if stops[stops.len() - 1].0 < 1.0 {
+                    let value = underlying(&stops[stops.len() - 1].1);
+                    stops.push((1.0, value));
+                }
+
+                if property == "transform" {
+                    for (_, value) in &mut stops {
+                        *value = self.about_origin(value);
+                    }
+                }
+                let (key_times, values) = stops.into_iter().unzip();
+                tracks.push(Track {
+                    element,
+                    target: match property {
+                        "transform" => Target::Transform { additive: false },
+                        _ => Target::Style(property.to_string()),
+                    },
+                    values,
+                    key_times,
+                    easing: vec![easing.clone()],
+                    timing: timing.clone(),
+                });
+            }
+        }
+        tracks
+    }
+
+    /// `transform` applied about the transform origin
+    fn about_origin(&self, transform: &str) -> String {
+        match self.origin {
+            Some((x, y)) if x != 0.0 || y != 0.0 => format!(
+                "translate({} {}) {transform} translate({} {})",
+                format_number(x),
+                format_number(y),
+                format_number(-x),
+                format_number(-y)
+            ),
+            _ => transform.to_string(),
+        }
+    }
+}
+
+/// Keyframes by offset, each with its declarations
+type Keyframes = Vec<(f64, Vec<(String, String)>)>;
+
+/// The rules and `@keyframes` of a style sheet. Other at-rules are skipped.
+fn parse_style_sheet(css: &str) -> (Vec<Rule>, HashMap<String, Keyframes>) {
+    let mut rules = Vec::new();
+    let mut keyframes = HashMap::new();
+    let mut rest = css;
+    loop {
+        rest = rest.trim_start();
+        // Statement at-rules (`@import ...;`) end at their semicolon.
+        if rest.starts_with('@') {
+            let open = rest.find('{');
+            if let Some(end) = rest.find(';').filter(|end| open.is_none_or(|open| *end < open)) {
+                rest = &rest[end + 1..];
+                continue;
+            }
+        }
+        let Some(open) = rest.find('{') else {
+            break;
+        };
+        let prelude = rest[..open].trim();
+        let Some(close) = matching_brace(rest, open) else {
+            break;
+        };
+        let block = &rest[open + 1..close];
+        rest = &rest[close + 1..];
+
+        if let Some(name) = prelude
+            .strip_prefix("@keyframes")
+            .or_else(|| prelude.strip_prefix("@-webkit-keyframes"))
+        {
+            keyframes.insert(
+                name.trim().trim_matches(['"', '\'']).to_string(),
+                parse_keyframes(block),
+            );
+        } else if !prelude.starts_with('@') {
+            let selectors = prelude.split(',').map(parse_selector).collect::<Option<Vec<_>>>();
+            if let Some(selectors) = selectors {
+                rules.push(Rule {
+                    selectors,
+                    declarations: parse_declarations(block),
+                });
+            }
+        }
+    }
+    (rules, keyframes)
+}
+
+fn parse_keyframes(block: &str) -> Keyframes {
+    let mut frames = Vec::new();
+    let mut rest = block;
+    while let Some(open) = rest.find('{') {
+        let Some(close) = rest[open..].find('}').map(|close| open + close) else {
+            break;
+        };
+        let declarations = parse_declarations(&rest[open + 1..close]);
+        for selector in rest[..open].split(',') {
+            let offset = match selector.trim() {
+                "from" => Some(0.0),
+                "to" => Some(1.0),
+                percentage => percentage
+                    .strip_suffix('%')
+                    .and_then(|number| number.trim().parse::<f64>().ok())
+                    .map(|percent| percent / 100.0),
+            };
+            if let Some(offset) = offset.filter(|offset| (0.0..=1.0).contains(offset)) {
+                frames.push((offset, declarations.clone()));
+            }
+        }
+        rest = &rest[close + 1..];
+    }
+    frames
+}
+
+/// The index of the `}` closing the block opened at `open`
+fn matching_brace(css: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in css[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn strip_comments(css: &str) -> String {
+    let mut out = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("/*") {
+        out.push_str(&rest[..start]);
+        rest = rest[start + 2..].split_once("*/").map_or("", |(_, after)| after);
+    }
+    out.push_str(rest);
+    out
+}
+
+fn parse_declarations(block: &str) -> Vec<(String, String)> {
+    block
+        .split(';')
+        .filter_map(|declaration| {
+            let (name, value) = declaration.split_once(':')?;
+            let value = value.trim();
+            let value = value.strip_suffix("!important").unwrap_or(value).trim();
+            Some((name.trim().cow_to_ascii_lowercase().into_owned(), value.to_string()))
+        })
+        .collect()
+}
+
+/// A selector of compounds joined by descendant combinators, innermost last. `None` for
+/// selectors using anything else.
+fn parse_selector(selector: &str) -> Option<Vec<Compound>> {
+    let compounds: Vec<Compound> = selector
+        .split_whitespace()
+        .map(|compound| {
+            if compound.contains(['>', '+', '~', '[', ':']) {
+                return None;
+            }
+            let mut parsed = Compound::default();
+            let mut rest = compound;
+            let tag_end = rest.find(['#', '.']).unwrap_or(rest.len());
+            match &rest[..tag_end] {
+                "" | "*" => {}
+                tag => parsed.tag = Some(tag.to_string()),
+            }
+            rest = &rest[tag_end..];
+            while let Some(kind) = rest.chars().next() {
+                let end = rest[1..].find(['#', '.']).map_or(rest.len(), |end| end + 1);
+                let name = rest[1..end].to_string();
+                if name.is_empty() {
+                    return None;
+                }
+                match kind {
+                    '#' => parsed.ids.push(name),
+                    _ => parsed.classes.push(name),
+                }
+                rest = &rest[end..];
+            }
+            Some(parsed)
+        })
+        .collect::<Option<_>>()?;
+    (!compounds.is_empty()).then_some(compounds)
+}
+
+fn matches_compound(compound: &Compound, node: roxmltree::Node) -> bool {
+    let classes: Vec<&str> = node.attribute("class").unwrap_or_default().split_whitespace().collect();
+    compound.tag.as_deref().is_none_or(|tag| node.tag_name().name() == tag)
+        && compound.ids.iter().all(|id| node.attribute("id") == Some(id.as_str()))
+        && compound.classes.iter().all(|class| classes.contains(&class.as_str()))
+}
+
+fn matches_selector(selector: &[Compound], node: roxmltree::Node) -> bool {
+    let Some((last, ancestors)) = selector.split_last() else {
+        return false;
+    };
+    if !matches_compound(last, node) {
+        return false;
+    }
+    let mut remaining = ancestors.iter().rev().peekable();
+    for ancestor in node.ancestors().skip(1).filter(roxmltree::Node::is_element) {
+        match remaining.peek() {
+            Some(compound) if matches_compound(compound, ancestor) => {
+                remaining.next();
+            }
+            Some(_) => {}
+            None => break,
+        }
+    }
+    remaining.peek().is_none()
+}
+
+/// Specificity as (ids, classes, types)
+fn specificity(selector: &[Compound]) -> (usize, usize, usize) {
+    selector.iter().fold((0, 0, 0), |(ids, classes, tags), compound| {
+        (
+            ids + compound.ids.len(),
+            classes + compound.classes.len(),
+            tags + usize::from(compound.tag.is_some()),
+        )
+    })
+}
+
+/// Splits `value` at `separator` outside of parentheses, dropping empty parts
+fn split_top_level(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (index, c) in value.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if c == separator && depth == 0 => {
+                parts.push(value[start..index].trim());
+                start = index + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(value[start..].trim());
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+/// A CSS `<time>` in seconds
+fn css_time(value: &str) -> Option<f64> {
+    if let Some(ms) = value.strip_suffix("ms") {
+        return ms.parse::<f64>().ok().map(|ms| ms / 1000.0);
+    }
+    value.strip_suffix('s')?.parse().ok()
+}
+
+fn iteration_count(value: &str) -> Option<f64> {
+    match value {
+        "infinite" => Some(f64::INFINITY),
+        count => count.parse().ok().filter(|count: &f64| *count >= 0.0),
+    }
+}
+
+fn direction(value: &str) -> Option<Direction> {
+    match value {
+        "normal" => Some(Direction::Normal),
+        "reverse" => Some(Direction::Reverse),
+        "alternate" => Some(Direction::Alternate),
+        "alternate-reverse" => Some(Direction::AlternateReverse),
+        _ => None,
+    }
+}
+
+/// Whether an `animation-fill-mode` fills backwards and forwards
+fn fill_mode(value: &str) -> Option<(bool, bool)> {
+    match value {
+        "none" => Some((false, false)),
+        "forwards" => Some((false, true)),
+        "backwards" => Some((true, false)),
+        "both" => Some((true, true)),
+        _ => None,
+    }
+}
+
+/// A `transform-origin` in absolute lengths
+fn transform_origin(value: &str) -> Option<(f64, f64)> {
+    let lengths: Vec<f64> = value.split_whitespace().map(css_length).collect::<Option<_>>()?;
+    match lengths.as_slice() {
+        [x] => Some((*x, 0.0)),
+        [x, y] | [x, y, _] => Some((*x, *y)),
+        _ => None,
+    }
+}
+
+fn css_length(value: &str) -> Option<f64> {
+    value.strip_suffix("px").unwrap_or(value).parse().ok()
+}
+
+/// A CSS transform as SVG transform functions, `none` as no functions at all. `None` for
+/// functions SVG has no equivalent of and for percentages.
+fn css_transform(value: &str) -> Option<Vec<(&'static str, Vec<f64>)>> {
+    if value.trim() == "none" {
+        return Some(Vec::new());
+    }
+    let mut functions = Vec::new();
+    let mut rest = value.trim();
+    while !rest.is_empty() {
+        let (name, after) = rest.split_once('(')?;
+        let (args, after) = after.split_once(')')?;
+        rest = after.trim_start();
+        let name = name.trim();
+        let angles = matches!(name, "rotate" | "skew" | "skewX" | "skewY");
+        let args: Vec<f64> = args
+            .split([',', ' '])
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| if angles { css_angle(arg) } else { css_length(arg) })
+            .collect::<Option<_>>()?;
+        let function = match (name, args.as_slice()) {
+            ("translate", [x]) => ("translate", vec![*x, 0.0]),
+            ("translate", [x, y]) => ("translate", vec![*x, *y]),
+            ("translateX", [x]) => ("translate", vec![*x, 0.0]),
+            ("translateY", [y]) => ("translate", vec![0.0, *y]),
+            ("scale", [s]) => ("scale", vec![*s, *s]),
+            ("scale", [x, y]) => ("scale", vec![*x, *y]),
+            ("scaleX", [x]) => ("scale", vec![*x, 1.0]),
+            ("scaleY", [y]) => ("scale", vec![1.0, *y]),
+            ("rotate", [angle]) => ("rotate", vec![*angle]),
+            ("skewX", [angle]) | ("skew", [angle]) => ("skewX", vec![*angle]),
+            ("skewY", [angle]) => ("skewY", vec![*angle]),
+            ("skew", [x, y]) => {
+                functions.push(("skewX", vec![*x]));
+                ("skewY", vec![*y])
+            }
+            ("matrix", [_, _, _, _, _, _]) => ("matrix", args.clone()),
+            _ => return None,
+        };
+        functions.push(function);
+    }
+    Some(functions)
+}
+
+/// A CSS `<angle>` in degrees
+fn css_angle(value: &str) -> Option<f64> {
+    let units = [
+        ("deg", 1.0),
+        ("grad", 0.9),
+        ("rad", 180.0 / std::f64::consts::PI),
+        ("turn", 360.0),
+    ];
+    for (unit, scale) in units {
+        if let Some(number) = value.strip_suffix(unit) {
+            return number.parse::<f64>().ok().map(|number| number * scale);
+        }
+    }
+    // Unitless zero is the only angle without a unit.
+    value.parse::<f64>().ok().filter(|number| *number == 0.0)
+}
+
+fn format_transform(functions: &[(&'static str, Vec<f64>)]) -> String {
+    functions
+        .iter()
+        .map(|(name, args)| {
+            let args: Vec<String> = args.iter().copied().map(format_number).collect();
+            format!("{name}({})", args.join(" "))
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The transform doing nothing, written with the same functions as `transform` so the two
+/// interpolate function by function
+fn identity_transform(transform: &str) -> String {
+    let mut identity = Vec::new();
+    let mut rest = transform;
+    while let Some((name, after)) = rest.split_once('(') {
+        let Some((_, after)) = after.split_once(')') else {
+            break;
+        };
+        identity.push(match name.trim() {
+            "translate" => "translate(0 0)",
+            "scale" => "scale(1 1)",
+            "rotate" => "rotate(0)",
+            "skewX" => "skewX(0)",
+            "skewY" => "skewY(0)",
+            _ => "matrix(1 0 0 1 0 0)",
+        });
+        rest = after;
+    }
+    identity.join(" ")
+}
+
+// ── Values ────────────────────────────────────────────────────────────────────
+
+/// The value `progress` of the way from `from` to `to`: colors as colors, values made of the
+/// same text around their numbers number for number, anything else switching halfway.
+fn interpolate(from: &str, to: &str, progress: f64) -> String {
+    if let (Some(from), Some(to)) = (color(from), color(to)) {
+        let channel = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * progress).round().clamp(0.0, 255.0) as u8;
+        return Value::Color(
+            channel(from.0, to.0),
+            channel(from.1, to.1),
+            channel(from.2, to.2),
+            channel(from.3, to.3),
+        )
+        .to_css_string();
+    }
+
+    let (from_numbers, from_text) = split_numbers(from);
+    let (to_numbers, to_text) = split_numbers(to);
+    let same_shape = from_numbers.len() == to_numbers.len()
+        && from_text.len() == to_text.len()
+        && from_text.iter().zip(&to_text).all(|(a, b)| same_text(a, b));
+    if !same_shape || from_numbers.is_empty() {
+        let nearest = if progress < 0.5 { from } else { to };
+        return nearest.to_string();
+    }
+
+    let mut out = String::new();
+    for (index, text) in from_text.iter().enumerate() {
+        out.push_str(text);
+        if let (Some(a), Some(b)) = (from_numbers.get(index), to_numbers.get(index)) {
+            out.push_str(&format_number(a + (b - a) * progress));
+        }
+    }
+    out
+}
+
+/// Whether two pieces of text between numbers are the same, apart from spacing and commas
+fn same_text(a: &str, b: &str) -> bool {
+    let significant = |text: &str| {
+        text.chars()
+            .filter(|c| !c.is_whitespace() && *c != ',')
+            .collect::<String>()
+    };
+    significant(a) == significant(b)
+}
+
+fn color(value: &str) -> Option<(u8, u8, u8, u8)> {
+    match parse_named_color(value.trim()) {
+        Value::Color(r, g, b, a) => Some((r, g, b, a)),
+        _ => None,
+    }
+}
+
+/// The numbers in `value` and the text around them: text, number, text, ... text
+fn split_numbers(value: &str) -> (Vec<f64>, Vec<&str>) {
+    let bytes = value.as_bytes();
+    let (mut numbers, mut text) = (Vec::new(), Vec::new());
+    let (mut index, mut text_start) = (0, 0);
+    while index < bytes.len() {
+        let starts_number = bytes[index].is_ascii_digit()
+            || (matches!(bytes[index], b'-' | b'+' | b'.')
+                && bytes
+                    .get(index + 1)
+                    .is_some_and(|next| next.is_ascii_digit() || *next == b'.'));
+        // Digits ending a name (`#grad1`) belong to it; after a lone letter, like the commands of
+        // path data (`M10`), they are numbers.
+        let in_name = index >= 2
+            && bytes[index - 1].is_ascii_alphabetic()
+            && (bytes[index - 2].is_ascii_alphanumeric() || matches!(bytes[index - 2], b'#' | b'_' | b'-'));
+        if !starts_number || in_name {
+            index += 1;
+            continue;
+        }
+        let end = number_end(bytes, index);
+        let Ok(number) = value[index..end].parse::<f64>() else {
+            index += 1;
+            continue;
+        };
+        text.push(&value[text_start..index]);
+        numbers.push(number);
+        index = end;
+        text_start = end;
+    }
+    text.push(&value[text_start..]);
+    (numbers, text)
+}
+
+/// End of the number starting at `start`
+fn number_end(bytes: &[u8], start: usize) -> usize {
+    let mut index = start;
+    if matches!(bytes[index], b'-' | b'+') {
+        index += 1;
+    }
+    let mut seen_dot = false;
+    while index < bytes.len() && (bytes[index].is_ascii_digit() || (bytes[index] == b'.' && !seen_dot)) {
+        seen_dot |= bytes[index] == b'.';
+        index += 1;
+    }
+    // An exponent only when digits follow, so `1em` stays a number and a unit.
+    if index < bytes.len() && matches!(bytes[index], b'e' | b'E') {
+        let mut exponent = index + 1;
+        if exponent < bytes.len() && matches!(bytes[exponent], b'-' | b'+') {
+            exponent += 1;
+        }
+        if exponent < bytes.len() && bytes[exponent].is_ascii_digit() {
+            index = exponent;
+            while index < bytes.len() && bytes[index].is_ascii_digit() {
+                index += 1;
+            }
+        }
+    }
+    index
+}
+
+/// A list of numbers separated by spaces and/or commas
+fn number_list(value: &str) -> Option<Vec<f64>> {
+    value
+        .split([',', ' ', ';', '\t', '\n'])
+        .filter(|number| !number.is_empty())
+        .map(|number| number.parse().ok())
+        .collect()
+}
+
+/// `by` added to `from`, number for number
+fn add_numbers(from: &str, by: &str) -> Option<String> {
+    let (from_numbers, text) = split_numbers(from);
+    let (by_numbers, _) = split_numbers(by);
+    if from_numbers.len() != by_numbers.len() || from_numbers.is_empty() {
+        return None;
+    }
+    let mut out = String::new();
+    for (index, piece) in text.iter().enumerate() {
+        out.push_str(piece);
+        if let (Some(a), Some(b)) = (from_numbers.get(index), by_numbers.get(index)) {
+            out.push_str(&format_number(a + b));
+        }
+    }
+    Some(out)
+}
+
+fn format_number(number: f64) -> String {
+    let rounded = (number * 10_000.0).round() / 10_000.0;
+    // Avoid writing `-0`.
+    format!("{}", rounded + 0.0)
+}
+
+// ── Frames ────────────────────────────────────────────────────────────────────
+
+/// Writes `node` and its subtree with the overridden values, leaving out what isn't SVG and the
+/// animation elements.
+fn write_element(
+    node: roxmltree::Node,
+    index_of: &HashMap<roxmltree::NodeId, usize>,
+    overrides: &HashMap<usize, Override>,
+    out: &mut String,
+) {
+    let name = node.tag_name().name();
+    let values = index_of.get(&node.id()).and_then(|index| overrides.get(index));
+    out.push('<');
+    out.push_str(name);
+    if node.parent_element().is_none() {
+        write_attribute("xmlns", SVG_NAMESPACE, out);
+        write_attribute("xmlns:xlink", XLINK_NAMESPACE, out);
+    }
+
+    let mut style = node.attribute("style").map(str::to_string);
+    for attribute in node.attributes() {
+        let local = attribute.name();
+        let name = match attribute.namespace() {
+            None => local.to_string(),
+            Some(XLINK_NAMESPACE) => format!("xlink:{local}"),
+            Some(XML_NAMESPACE) => format!("xml:{local}"),
+            Some(_) => continue,
+        };
+        let overridden = values.is_some_and(|values| {
+            values.attributes.iter().any(|(animated, _)| *animated == name)
+                || (name == "transform" && !values.transforms.is_empty())
+        });
+        if !overridden && name != "style" {
+            write_attribute(&name, attribute.value(), out);
+        }
+    }
+    if let Some(values) = values {
+        // Later animations of the same attribute win.
+        let mut written: Vec<&str> = Vec::new();
+        for (name, value) in values.attributes.iter().rev() {
+            if !written.contains(&name.as_str()) {
+                write_attribute(name, value, out);
+                written.push(name);
+            }
+        }
+        if !values.transforms.is_empty() {
+            if let Some(transform) = values.transform(node.attribute("transform")) {
+                write_attribute("transform", &transform, out);
+            }
+        }
+        for (property, value) in &values.style {
+            let declarations = style.get_or_insert_with(String::new);
+            if !declarations.trim().is_empty() {
+                declarations.push(';');
+            }
+            declarations.push_str(property);
+            declarations.push(':');
+            declarations.push_str(value);
+        }
+    }
+    if let Some(style) = style {
+        write_attribute("style", &style, out);
+    }
+    out.push('>');
+
+    for child in node.children() {
+        if child.is_element() {
+            let svg = child.tag_name().namespace() == Some(SVG_NAMESPACE);
+            if svg && !ANIMATION_ELEMENTS.contains(&child.tag_name().name()) {
+                write_element(child, index_of, overrides, out);
+            }
+        } else if child.is_text() {
+            escape_into(child.text().unwrap_or_default(), out);
+        }
+    }
+
+    out.push_str("</");
+    out.push_str(name);
+    out.push('>');
+}
+
+fn write_attribute(name: &str, value: &str, out: &mut String) {
+    out.push(' ');
+    out.push_str(name);
+    out.push_str("=\"");
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn escape_into(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn svg(body: &str) -> String {
+        format!(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">{body}</svg>"#)
+    }
+
+    /// The value of `attribute` on the element with id `id` in a frame
+    fn attribute(frame: &str, id: &str, attribute: &str) -> Option<String> {
+        let doc = roxmltree::Document::parse(frame).expect("frames are well-formed");
+        let element = doc.descendants().find(|node| node.attribute("id") == Some(id))?;
+        element.attribute(attribute).map(str::to_string)
+    }
+
+    #[test]
+    fn documents_without_animations_are_not_animated() {
+        assert!(SvgAnimation::parse(&svg(r#"<rect width="10" height="10"/>"#)).is_none());
+        assert!(SvgAnimation::parse("<svg").is_none());
+    }
+
+    #[test]
+    fn plays_smil_animations() {
+        let source = svg(r##"
+            <rect id="box" width="10" height="10" fill="red" opacity="1">
+                <animate attributeName="opacity" from="0" to="1" dur="2s" fill="freeze"/>
+                <animate attributeName="fill" values="red;blue" begin="1s" dur="1s"/>
+                <set attributeName="width" to="20" begin="500ms"/>
+            </rect>
+            <g id="spinner">
+                <animateTransform attributeName="transform" type="rotate" from="0 50 50" to="360 50 50"
+                                  dur="4s" repeatCount="indefinite"/>
+            </g>
+            <circle id="dot" r="5">
+                <animate attributeName="cx" values="0;10;30" calcMode="discrete" dur="3s"/>
+            </circle>
+            <rect id="later" width="1" height="1">
+                <animate attributeName="width" to="9" begin="click" dur="1s"/>
+            </rect>
+        "##);
+        let animation = SvgAnimation::parse(&source).expect("animated");
+
+        let frame = animation.frame(0.0);
+        assert_eq!(attribute(&frame, "box", "opacity").as_deref(), Some("0"));
+        assert_eq!(attribute(&frame, "box", "fill").as_deref(), Some("red"));
+        assert_eq!(attribute(&frame, "box", "width").as_deref(), Some("10"));
+        assert!(!frame.contains("<animate"), "animation elements are left out: {frame}");
+
+        let frame = animation.frame(1.5);
+        assert_eq!(attribute(&frame, "box", "opacity").as_deref(), Some("0.75"));
+        assert_eq!(attribute(&frame, "box", "fill").as_deref(), Some("rgb(128, 0, 128)"));
+        assert_eq!(attribute(&frame, "box", "width").as_deref(), Some("20"));
+        assert_eq!(attribute(&frame, "dot", "cx").as_deref(), Some("10"));
+
+        let frame = animation.frame(5.0);
+        assert_eq!(attribute(&frame, "box", "opacity").as_deref(), Some("1"), "frozen");
+        assert_eq!(attribute(&frame, "box", "fill").as_deref(), Some("red"), "removed");
+        assert_eq!(
+            attribute(&frame, "spinner", "transform").as_deref(),
+            Some("rotate(90 50 50)")
+        );
+        assert_eq!(
+            attribute(&frame, "later", "width").as_deref(),
+            Some("1"),
+            "never begins"
+        );
+
+        assert!(animation.is_running(100.0), "the spinner repeats indefinitely");
+    }
+
+    #[test]
+    fn plays_css_animations() {
+        let source = svg(r#"
+            <style>
+                @keyframes spin { to { transform: rotate(1turn); } }
+                @keyframes pulse { 0% { opacity: 1 } 50% { opacity: 0.2 } }
+                .icon rect { animation: spin 2s linear infinite; transform-origin: 5px 5px; }
+                #faded { animation: pulse 1s linear 1s both; }
+            </style>
+            <g class="icon"><rect id="spinning" width="10" height="10" transform="scale(2)"/></g>
+            <circle id="faded" r="5" style="fill: red"/>
+        "#);
+        let animation = SvgAnimation::parse(&source).expect("animated");
+
+        let frame = animation.frame(0.5);
+        assert_eq!(
+            attribute(&frame, "spinning", "transform").as_deref(),
+            Some("translate(5 5) rotate(90) translate(-5 -5)")
+        );
+        assert_eq!(
+            attribute(&frame, "faded", "style").as_deref(),
+            Some("fill: red;opacity:1"),
+            "the first keyframe fills backwards"
+        );
+
+        let frame = animation.frame(1.25);
+        assert_eq!(
+            attribute(&frame, "faded", "style").as_deref(),
+            Some("fill: red;opacity:0.6")
+        );
+        let frame = animation.frame(10.0);
+        assert_eq!(
+            attribute(&frame, "faded", "style").as_deref(),
+            Some("fill: red;opacity:1"),
+            "the missing last keyframe is the element's own opacity"
+        );
+    }
+
+    #[test]
+    fn reads_clock_values() {
+        assert_eq!(clock_value("2.5s"), Some(2.5));
+        assert_eq!(clock_value("250ms"), Some(0.25));
+        assert_eq!(clock_value("1:30"), Some(90.0));
+        assert_eq!(clock_value("01:00:02"), Some(3602.0));
+        assert_eq!(clock_value("3"), Some(3.0));
+        assert_eq!(clock_value("indefinite"), None);
+        assert_eq!(clock_value("click"), None);
+    }
+
+    #[test]
+    fn interpolates_numbers_in_place() {
+        assert_eq!(interpolate("M 0,0 L 10,10", "M 10,10 L 30,-10", 0.5), "M 5,5 L 20,0");
+        assert_eq!(interpolate("10px", "20px", 0.25), "12.5px");
+        assert_eq!(interpolate("#000", "#fff", 0.5), "rgb(128, 128, 128)");
+        assert_eq!(interpolate("start", "end", 0.4), "start");
+        assert_eq!(interpolate("start", "end", 0.6), "end");
+    }
+}
@@ -0,0 +1,138 @@
+//! Geometry for text selection highlights.
+//!
+//! Shaped glyphs don't carry a cluster → character mapping yet, so both directions (point →
+//! character offset, character range → highlight rects) approximate a text element as
+//! `lines` equally wide rows of evenly spaced characters, flowing left-to-right, top-to-bottom.
+//! The engine uses [`TextLines::offset_at`] while dragging and the painter uses
+//! [`TextLines::rects_for`] to draw the highlight, so the two always agree with each other.
+
+use crate::common::document::node::NodeId;
+use crate::common::geo::Rect;
+use std::collections::HashMap;
+
+/// Selected character range (`start..end`, in `char`s of the laid-out text) per text node.
+pub type SelectionRanges = HashMap<NodeId, (usize, usize)>;
+
+/// The line model of one laid-out text element.
+#[derive(Debug, Clone, Copy)]
+pub struct TextLines {
+    /// Content box of the text element, in page coordinates.
+    pub rect: Rect,
+    /// Height of a single line box.
+    pub line_height: f64,
+    /// Number of characters in the laid-out text.
+    pub char_count: usize,
+}
+
+impl TextLines {
+    pub fn new(rect: Rect, line_height: f64, char_count: usize) -> Self {
+        Self {
+            rect,
+            line_height,
+            char_count,
+        }
+    }
+
+    /// Number of line boxes the text wraps into (at least one).
+    fn line_count(&self) -> usize {
+        if self.line_height <= 0.0 {
+            return 1;
+        }
+        ((self.rect.height / self.line_height).round() as usize).max(1)
+    }
+
+    /// Height of one row, derived from the box so the rows tile it exactly.
+    fn row_height(&self) -> f64 {
+        self.rect.height / self.line_count() as f64
+    }
+
+    /// Character offset closest to the page-space point `(x, y)`. Points outside the box clamp
+    /// to its first or last character.
+    pub fn offset_at(&self, x: f64, y: f64) -> usize {
+        if self.char_count == 0 || self.rect.width <= 0.0 {
+            return 0;
+        }
+        if y < self.rect.y {
+            return 0;
+        }
+        if y >= self.rect.y + self.rect.height {
+            return self.char_count;
+        }
+
+        let lines = self.line_count();
+        let line = (((y - self.rect.y) / self.row_height()) as usize).min(lines - 1);
+        let col = ((x - self.rect.x) / self.rect.width).clamp(0.0, 1.0);
+        let frac = (line as f64 + col) / lines as f64;
+
+        ((frac * self.char_count as f64).round() as usize).min(self.char_count)
+    }
+
+    /// Highlight rectangles covering characters `start..end`, one per line box touched.
+    pub fn rects_for(&self, start: usize, end: usize) -> Vec<Rect> {
+        let end = end.min(self.char_count);
+        if start >= end || self.char_count == 0 {
+            return Vec::new();
+        }
+
+        let lines = self.line_count();
+        let row_height = self.row_height();
+        // Position of a character boundary in "line units" (0.0 ..= lines).
+        let pos = |offset: usize| offset as f64 / self.char_count as f64 * lines as f64;
+        let (from, to) = (pos(start), pos(end));
+
+        let mut rects = Vec::new();
+        for line in 0..lines {
+            let lo = from.max(line as f64);
+            let hi = to.min((line + 1) as f64);
+            if hi <= lo {
+                continue;
+            }
+            let x0 = self.rect.x + (lo - line as f64) * self.rect.width;
+            let x1 = self.rect.x + (hi - line as f64) * self.rect.width;
+            rects.push(Rect::new(
+                x0,
+                self.rect.y + line as f64 * row_height,
+                x1 - x0,
+                row_height,
+            ));
+        }
+        rects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offset_at_single_line() {
+        let lines = TextLines::new(Rect::new(10.0, 0.0, 100.0, 20.0), 20.0, 10);
+        assert_eq!(lines.offset_at(10.0, 5.0), 0);
+        assert_eq!(lines.offset_at(60.0, 5.0), 5);
+        assert_eq!(lines.offset_at(500.0, 5.0), 10);
+        // Above / below the box clamp to the ends
+        assert_eq!(lines.offset_at(60.0, -5.0), 0);
+        assert_eq!(lines.offset_at(60.0, 50.0), 10);
+    }
+
+    #[test]
+    fn offset_at_second_line() {
+        // 20 chars over two lines: the start of line two is char 10
+        let lines = TextLines::new(Rect::new(0.0, 0.0, 100.0, 40.0), 20.0, 20);
+        assert_eq!(lines.offset_at(0.0, 25.0), 10);
+        assert_eq!(lines.offset_at(50.0, 25.0), 15);
+    }
+
+    #[test]
+    fn rects_for_spans_lines() {
+        let lines = TextLines::new(Rect::new(0.0, 0.0, 100.0, 40.0), 20.0, 20);
+
+        let rects = lines.rects_for(5, 15);
+        assert_eq!(rects.len(), 2);
+        assert_eq!((rects[0].x, rects[0].y, rects[0].width), (50.0, 0.0, 50.0));
+        assert_eq!((rects[1].x, rects[1].y, rects[1].width), (0.0, 20.0, 50.0));
+
+        assert!(lines.rects_for(7, 7).is_empty());
+        assert!(lines.rects_for(9, 3).is_empty());
+    }
+}
@@ -1,5 +1,6 @@
 use crate::common::document::node::NodeId;
 use crate::common::document::style::{lookup, StyleProperty, Unit, Value};
+use crate::layouter::scroll::clip_rect;
 use crate::layouter::{LayoutElementId, LayoutElementNode, LayoutTree};
 use crate::render::backend::{StickyConstraint, TileAnchor};
 use parking_lot::RwLock;
@@ -148,6 +149,10 @@ impl LayerList {
                     && y >= box_model.margin_box.y
                     && y < box_model.margin_box.y + box_model.margin_box.height
                 {
+                    // Content scrolled out of (or overflowing) a clipping box isn't hittable.
+                    if clip_rect(&self.layout_tree, *element_id).is_some_and(|clip| !clip.contains(x, y)) {
+                        continue;
+                    }
                     return Some(*element_id);
                 }
             }
@@ -3,18 +3,21 @@ use crate::common::font::FontInfo;
 use crate::common::geo::{Coordinate, Dimension};
 use crate::common::media::MediaId;
 use crate::layouter::box_model::BoxModel;
+use crate::layouter::widget::ElementContextWidget;
 use crate::rendertree_builder::{RenderNodeId, RenderTree};
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::ops::AddAssign;
 use std::sync::Arc;
 
-mod box_model;
+pub mod box_model;
 mod css_taffy_converter;
 mod inline_run;
+pub mod scroll;
 pub mod table;
 pub mod taffy;
 pub mod text;
+pub mod widget;
 
 /// ID's for layout elements
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -82,13 +85,14 @@ pub struct ElementContextImage {
     pub alt: Option<String>,
 }
 
-/// Per-element data (text, image, svg) needed by later phases of the rendering pipeline.
+/// Per-element data (text, image, svg, form control) needed by later phases of the rendering pipeline.
 #[derive(Debug, Clone)]
 pub enum ElementContext {
     None,
     Text(ElementContextText),
     Image(ElementContextImage),
     Svg(ElementContextSvg),
+    Widget(ElementContextWidget),
 }
 
 impl ElementContext {
@@ -175,6 +179,8 @@ pub struct LayoutTree {
     pub root_id: LayoutElementId,
     next_node_id: Arc<RwLock<LayoutElementId>>,
     pub root_dimension: Dimension,
+    /// Element scroll containers, filled in by [`scroll::apply_scroll_offsets`].
+    pub scroll_ports: HashMap<LayoutElementId, scroll::ScrollPort>,
 }
 
 impl LayoutTree {
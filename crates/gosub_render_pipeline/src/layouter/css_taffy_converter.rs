@@ -1,7 +1,8 @@
 use crate::common::document::node::NodeId;
 use crate::common::document::pipeline_doc::PipelineDocument;
 use crate::common::document::style::{
-    lookup, Display as CssDisplay, StyleProperty, TextAlign as CssTextAlign, Unit as CssUnit, Value,
+    calc_ptr, intern_calc, lookup, lookup_calc, Display as CssDisplay, StyleProperty, TextAlign as CssTextAlign,
+    Unit as CssUnit, Value,
 };
 use taffy::prelude::{
     minmax, span, FromFr, FromLength, MaxTrackSizingFunction, MinTrackSizingFunction, TaffyAuto, TaffyGridLine,
@@ -13,6 +14,13 @@ use taffy::{
     Rect, Size, Style, TextAlign, TrackSizingFunction,
 };
 
+/// A math-function length after its em parts are resolved, see [`CssTaffyConverter::calc`].
+enum Calc {
+    Length(f32),
+    /// Still needs the percentage basis: the address of the interned `CalcLength`
+    Deferred(*const ()),
+}
+
 /// Converts CSS properties from a `PipelineDocument` node into a Taffy `Style`.
 pub struct CssTaffyConverter<'a> {
     node_id: NodeId,
@@ -38,6 +46,18 @@ impl<'a> CssTaffyConverter<'a> {
         }
     }
 
+    /// Resolves the em parts of an interned math-function length. A length that still depends
+    /// on a percentage goes to taffy as a calc value, resolved through
+    /// [`resolve_calc`](crate::common::document::style::resolve_calc) once the basis is known.
+    fn calc(&self, id: u32) -> Calc {
+        let calc = lookup_calc(id).at_font_size(self.font_size_px());
+        if calc.has_percentage() {
+            Calc::Deferred(calc_ptr(intern_calc(calc)))
+        } else {
+            Calc::Length(calc.resolve(0.0, 0.0))
+        }
+    }
+
     fn get_f32(&self, prop: StyleProperty, default: f32) -> f32 {
         match self.get_own(&prop) {
             Some(Value::Number(num)) => num,
@@ -178,6 +198,10 @@ impl<'a> CssTaffyConverter<'a> {
                 CssUnit::Percent => Dimension::percent(val / 100.0),
                 _ => Dimension::from_length(val),
             },
+            Some(Value::Calc(id)) => match self.calc(id) {
+                Calc::Length(px) => Dimension::from_length(px),
+                Calc::Deferred(ptr) => Dimension::calc(ptr),
+            },
             Some(Value::Number(val)) => Dimension::from_length(val),
             Some(Value::Keyword(id)) if lookup(id) == "auto" => Dimension::auto(),
             _ => default,
@@ -235,6 +259,10 @@ impl<'a> CssTaffyConverter<'a> {
                 CssUnit::Percent => LengthPercentageAuto::percent(value / 100.0),
                 CssUnit::Em | CssUnit::Rem => LengthPercentageAuto::length(value * self.font_size_px()),
             },
+            Some(Value::Calc(id)) => match self.calc(id) {
+                Calc::Length(px) => LengthPercentageAuto::length(px),
+                Calc::Deferred(ptr) => LengthPercentageAuto::calc(ptr),
+            },
             Some(Value::Number(value)) => LengthPercentageAuto::length(value),
             Some(Value::Keyword(id)) if lookup(id) == "auto" => LengthPercentageAuto::auto(),
             _ => default,
@@ -248,6 +276,10 @@ impl<'a> CssTaffyConverter<'a> {
                 CssUnit::Percent => LengthPercentage::percent(value / 100.0),
                 CssUnit::Em | CssUnit::Rem => LengthPercentage::length(value * self.font_size_px()),
             },
+            Some(Value::Calc(id)) => match self.calc(id) {
+                Calc::Length(px) => LengthPercentage::length(px),
+                Calc::Deferred(ptr) => LengthPercentage::calc(ptr),
+            },
             Some(Value::Number(value)) => LengthPercentage::length(value),
             _ => default,
         }
@@ -262,6 +294,10 @@ impl<'a> CssTaffyConverter<'a> {
                 CssUnit::Percent => LengthPercentage::percent(value / 100.0),
                 CssUnit::Em | CssUnit::Rem => LengthPercentage::length(value * self.font_size_px()),
             },
+            Value::Calc(id) => match self.calc(id) {
+                Calc::Length(px) => LengthPercentage::length(px),
+                Calc::Deferred(ptr) => LengthPercentage::calc(ptr),
+            },
             Value::Number(value) => LengthPercentage::length(value),
             _ => default,
         }
@@ -274,6 +310,10 @@ impl<'a> CssTaffyConverter<'a> {
                 CssUnit::Percent => Dimension::percent(value / 100.0),
                 CssUnit::Em | CssUnit::Rem => Dimension::from_length(value * self.font_size_px()),
             },
+            Some(Value::Calc(id)) => match self.calc(id) {
+                Calc::Length(px) => Dimension::from_length(px),
+                Calc::Deferred(ptr) => Dimension::calc(ptr),
+            },
             Some(Value::Number(value)) => Dimension::from_length(value),
             _ => default,
         }
@@ -286,6 +326,16 @@ impl<'a> CssTaffyConverter<'a> {
                 CssUnit::Percent => Size::percent(value / 100.0),
                 CssUnit::Em | CssUnit::Rem => Size::length(value * self.font_size_px()),
             },
+            Some(Value::Calc(id)) => {
+                let value = match self.calc(id) {
+                    Calc::Length(px) => LengthPercentage::length(px),
+                    Calc::Deferred(ptr) => LengthPercentage::calc(ptr),
+                };
+                Size {
+                    width: value,
+                    height: value,
+                }
+            }
             Some(Value::Number(value)) => Size::length(value),
             _ => default,
         }
@@ -0,0 +1,255 @@
+//! Element scroll containers.
+//!
+//! A box with `overflow: auto | scroll` whose content is larger than its padding box scrolls on
+//! its own. Offsets are applied after layout by translating every descendant box, so hit testing,
+//! layering, tiling and painting all see the scrolled positions without knowing about scrolling.
+//! Descendants of any box that clips (`overflow` other than `visible`) are clipped to its padding
+//! box when painted and hit-tested, see [`clip_rect`].
+//!
+//! Overflow on the root element and `<body>` propagates to the viewport, which the host scrolls,
+//! so neither becomes a scroll container or clips here.
+
+use crate::common::document::node::NodeId as DomNodeId;
+use crate::common::document::style::{lookup, StyleProperty, Value};
+use crate::common::geo::{Coordinate, Rect};
+use crate::layouter::{LayoutElementId, LayoutTree};
+use std::collections::HashMap;
+
+/// Scroll offsets of element scroll containers, keyed by DOM node so they survive re-layout.
+pub type ScrollOffsets = HashMap<DomNodeId, Coordinate>;
+
+/// An element that scrolls its own content.
+#[derive(Debug, Clone, Copy)]
+pub struct ScrollPort {
+    pub element: LayoutElementId,
+    pub node: DomNodeId,
+    /// Offset applied to the descendants in this layout.
+    pub offset: Coordinate,
+    /// Furthest the content can scroll; zero on an axis that doesn't scroll.
+    pub max: Coordinate,
+}
+
+fn overflow(tree: &LayoutTree, node: DomNodeId, prop: StyleProperty) -> String {
+    match tree.render_tree.doc.get_style(node, &prop) {
+        Value::Keyword(kw) => lookup(kw),
+        _ => "visible".to_string(),
+    }
+}
+
+fn propagates_to_viewport(tree: &LayoutTree, node: DomNodeId) -> bool {
+    let doc = &tree.render_tree.doc;
+    Some(node) == doc.html_node_id() || Some(node) == doc.body_node_id()
+}
+
+/// True when `node` clips its descendants to its padding box.
+pub fn clips_overflow(tree: &LayoutTree, node: DomNodeId) -> bool {
+    if propagates_to_viewport(tree, node) {
+        return false;
+    }
+    overflow(tree, node, StyleProperty::OverflowX) != "visible"
+        || overflow(tree, node, StyleProperty::OverflowY) != "visible"
+}
+
+/// Which axes of `node` the user can scroll (wheel, keyboard). `overflow: hidden` only scrolls
+/// programmatically, and `visible` next to a non-`visible` axis computes to `auto`.
+fn user_scrollable_axes(tree: &LayoutTree, node: DomNodeId) -> (bool, bool) {
+    if propagates_to_viewport(tree, node) {
+        return (false, false);
+    }
+    let x = overflow(tree, node, StyleProperty::OverflowX);
+    let y = overflow(tree, node, StyleProperty::OverflowY);
+    let axis = |own: &str, other: &str| match own {
+        "auto" | "scroll" => true,
+        "visible" => !matches!(other, "visible" | "clip"),
+        _ => false,
+    };
+    (axis(&x, &y), axis(&y, &x))
+}
+
+/// Right and bottom edge of everything laid out below `id`.
+fn content_extent(tree: &LayoutTree, id: LayoutElementId) -> (f64, f64) {
+    let mut extent = (f64::MIN, f64::MIN);
+    let mut stack: Vec<LayoutElementId> = tree
+        .get_node_by_id(id)
+        .map(|el| el.children.clone())
+        .unwrap_or_default();
+    while let Some(child) = stack.pop() {
+        let Some(el) = tree.get_node_by_id(child) else {
+            continue;
+        };
+        let b = el.box_model.border_box;
+        extent.0 = extent.0.max(b.x + b.width);
+        extent.1 = extent.1.max(b.y + b.height);
+        stack.extend(el.children.iter().copied());
+    }
+    extent
+}
+
+/// The scroll port of `id` in an unscrolled layout, or `None` when it has nothing to scroll.
+fn measure_port(tree: &LayoutTree, id: LayoutElementId) -> Option<ScrollPort> {
+    let el = tree.get_node_by_id(id)?;
+    let (sx, sy) = user_scrollable_axes(tree, el.dom_node_id);
+    if !sx && !sy {
+        return None;
+    }
+    let bm = &el.box_model;
+    let pb = bm.padding_box;
+    let (right, bottom) = content_extent(tree, id);
+    let max_x = if sx {
+        (right + bm.padding.right - (pb.x + pb.width)).max(0.0)
+    } else {
+        0.0
+    };
+    let max_y = if sy {
+        (bottom + bm.padding.bottom - (pb.y + pb.height)).max(0.0)
+    } else {
+        0.0
+    };
+    if max_x <= 0.0 && max_y <= 0.0 {
+        return None;
+    }
+    Some(ScrollPort {
+        element: id,
+        node: el.dom_node_id,
+        offset: Coordinate::ZERO,
+        max: Coordinate::new(max_x, max_y),
+    })
+}
+
+fn translate_subtree(tree: &mut LayoutTree, id: LayoutElementId, dx: f64, dy: f64) {
+    let mut stack: Vec<LayoutElementId> = tree
+        .get_node_by_id(id)
+        .map(|el| el.children.clone())
+        .unwrap_or_default();
+    while let Some(child) = stack.pop() {
+        let Some(el) = tree.get_node_by_id_mut(child) else {
+            continue;
+        };
+        let bm = &mut el.box_model;
+        for rect in [
+            &mut bm.margin_box,
+            &mut bm.border_box,
+            &mut bm.padding_box,
+            &mut bm.content_box,
+        ] {
+            rect.x += dx;
+            rect.y += dy;
+        }
+        stack.extend(el.children.iter().copied());
+    }
+}
+
+/// Finds the scroll containers of a freshly laid-out tree, scrolls each by its entry in
+/// `offsets` (clamped to what it can scroll) and records them in `tree.scroll_ports`.
+///
+/// All ports are measured before anything moves, so nested containers don't skew each other.
+pub fn apply_scroll_offsets(tree: &mut LayoutTree, offsets: &ScrollOffsets) {
+    let ids: Vec<LayoutElementId> = tree.arena.keys().copied().collect();
+    let ports: Vec<ScrollPort> = ids.into_iter().filter_map(|id| measure_port(tree, id)).collect();
+
+    tree.scroll_ports.clear();
+    for mut port in ports {
+        if let Some(requested) = offsets.get(&port.node) {
+            port.offset = Coordinate::new(requested.x.clamp(0.0, port.max.x), requested.y.clamp(0.0, port.max.y));
+            translate_subtree(tree, port.element, -port.offset.x, -port.offset.y);
+        }
+        tree.scroll_ports.insert(port.element, port);
+    }
+}
+
+/// The scroll containers `id` is inside (including itself), innermost first.
+pub fn scroll_chain(tree: &LayoutTree, id: LayoutElementId) -> Vec<ScrollPort> {
+    let mut chain = Vec::new();
+    let mut current = Some(id);
+    while let Some(id) = current {
+        if let Some(port) = tree.scroll_ports.get(&id) {
+            chain.push(*port);
+        }
+        current = tree.get_node_by_id(id).and_then(|el| el.parent);
+    }
+    chain
+}
+
+/// Distributes a wheel `delta` along `chain` (innermost first), per axis: each container takes
+/// as much as it can before hitting its limit and the remainder chains outward. Returns the new
+/// offset of every container that moved, and what is left over for the viewport.
+pub fn route_delta(chain: &[ScrollPort], delta: Coordinate) -> (Vec<(DomNodeId, Coordinate)>, Coordinate) {
+    let mut rest = delta;
+    let mut moved = Vec::new();
+    for port in chain {
+        let x = (port.offset.x + rest.x).clamp(0.0, port.max.x);
+        let y = (port.offset.y + rest.y).clamp(0.0, port.max.y);
+        rest.x -= x - port.offset.x;
+        rest.y -= y - port.offset.y;
+        if x != port.offset.x || y != port.offset.y {
+            moved.push((port.node, Coordinate::new(x, y)));
+        }
+    }
+    (moved, rest)
+}
+
+/// The area `id` is visible in after clipping by every ancestor that clips its overflow, or
+/// `None` when no ancestor clips.
+pub fn clip_rect(tree: &LayoutTree, id: LayoutElementId) -> Option<Rect> {
+    let mut clip: Option<Rect> = None;
+    let mut current = tree.get_node_by_id(id).and_then(|el| el.parent);
+    while let Some(ancestor) = current.and_then(|id| tree.get_node_by_id(id)) {
+        if clips_overflow(tree, ancestor.dom_node_id) {
+            let pb = ancestor.box_model.padding_box;
+            clip = Some(match clip {
+                Some(c) => c.intersection(&pb),
+                None => pb,
+            });
+        }
+        current = ancestor.parent;
+    }
+    clip
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn port(node: usize, offset: (f64, f64), max: (f64, f64)) -> ScrollPort {
+        ScrollPort {
+            element: LayoutElementId::new(node as u64),
+            node: DomNodeId::from(node),
+            offset: Coordinate::new(offset.0, offset.1),
+            max: Coordinate::new(max.0, max.1),
+        }
+    }
+
+    #[test]
+    fn innermost_container_takes_the_delta() {
+        let chain = [port(2, (0.0, 10.0), (0.0, 100.0)), port(1, (0.0, 0.0), (0.0, 500.0))];
+        let (moved, rest) = route_delta(&chain, Coordinate::new(0.0, 30.0));
+        assert_eq!(moved.len(), 1);
+        assert_eq!(moved[0].0, DomNodeId::from(2usize));
+        assert_eq!(moved[0].1.y, 40.0);
+        assert_eq!(rest.y, 0.0);
+    }
+
+    #[test]
+    fn overscroll_chains_to_parents_then_viewport() {
+        let chain = [port(2, (0.0, 90.0), (0.0, 100.0)), port(1, (0.0, 495.0), (0.0, 500.0))];
+        let (moved, rest) = route_delta(&chain, Coordinate::new(0.0, 40.0));
+        let ys: Vec<f64> = moved.iter().map(|(_, o)| o.y).collect();
+        assert_eq!(ys, vec![100.0, 500.0]);
+        assert_eq!(rest.y, 25.0);
+
+        // Scrolling back up at the top goes straight through.
+        let chain = [port(2, (0.0, 0.0), (0.0, 100.0))];
+        let (moved, rest) = route_delta(&chain, Coordinate::new(0.0, -20.0));
+        assert!(moved.is_empty());
+        assert_eq!(rest.y, -20.0);
+    }
+
+    #[test]
+    fn axes_chain_independently() {
+        // Only scrolls horizontally: the vertical part passes to the page.
+        let chain = [port(3, (0.0, 0.0), (200.0, 0.0))];
+        let (moved, rest) = route_delta(&chain, Coordinate::new(15.0, 30.0));
+        assert_eq!(moved[0].1.x, 15.0);
+        assert_eq!((rest.x, rest.y), (0.0, 30.0));
+    }
+}
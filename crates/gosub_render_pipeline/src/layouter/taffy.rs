@@ -1,9 +1,12 @@
 use cow_utils::CowUtils;
 
+use crate::common::document::inline_svg::inline_svg_markup;
 use crate::common::document::node::{Node, NodeId as DomNodeId, NodeType};
 use crate::common::document::pipeline_doc::BgSize;
 use crate::common::document::style::{lookup, FontWeight, StyleProperty, TextAlign, Unit, Value};
-use crate::common::font::{FontAlignment, FontInfo};
+use crate::common::font::{
+    element_font_info, font_features_of, font_size_adjust_of, used_font_size, FontAlignment, FontInfo,
+};
 use crate::common::geo;
 use crate::common::geo::Coordinate;
 use crate::common::media::MediaStore;
@@ -12,13 +15,15 @@ use crate::layouter::box_model::Edges;
 use crate::layouter::css_taffy_converter::CssTaffyConverter;
 use crate::layouter::table::post_process_tables;
 use crate::layouter::text::get_text_layout;
+use crate::layouter::widget::ElementContextWidget;
 use crate::layouter::{
     box_model, BackgroundMedia, CanLayout, ElementContext, ElementContextImage, ElementContextSvg, ElementContextText,
     LayoutElementId, LayoutElementNode, LayoutTree,
 };
 use crate::rendertree_builder::{RenderNodeId, RenderTree};
 use gosub_fontmanager::ParleyFontSystem;
-use gosub_interface::font_system::FontSystem;
+use gosub_interface::font::FontStyle;
+use gosub_interface::font_system::{FontSystem, TextStyle};
 use parking_lot::{Mutex, RwLock};
 use std::borrow::Borrow;
 use std::collections::HashMap;
@@ -28,6 +33,9 @@ use taffy::NodeId as TaffyNodeId;
 
 const DEFAULT_FONT_SIZE: f64 = 16.0;
 const DEFAULT_FONT_FAMILY: &str = "sans-serif";
+/// Size of an `<iframe>` with no CSS size or width/height attributes.
+const IFRAME_DEFAULT_WIDTH: f32 = 300.0;
+const IFRAME_DEFAULT_HEIGHT: f32 = 150.0;
 
 /// Parse an HTML presentational length attribute (e.g. `<img width="80">`) into pixels.
 /// Accepts a bare integer/float or a trailing `px`; ignores `%` and other units.
@@ -127,6 +135,7 @@ pub enum TaffyContext {
     Text(ElementContextText),
     Image(ElementContextImage),
     Svg(ElementContextSvg),
+    Widget(ElementContextWidget),
 }
 
 impl TaffyContext {
@@ -242,6 +251,7 @@ impl CanLayout for TaffyLayouter {
                 root_id: LayoutElementId::new(0),
                 next_node_id: Arc::new(RwLock::new(LayoutElementId::new(0))),
                 root_dimension: geo::Dimension::ZERO,
+                scroll_ports: HashMap::new(),
             };
         };
         // let root_id = RenderNodeId::new(2);
@@ -342,6 +352,11 @@ impl CanLayout for TaffyLayouter {
                     // SVG-backed <img> elements carry their intrinsic size the same way.
                     // Without this arm they measured as 0×0 and collapsed (e.g. the HN logo).
                     Some(TaffyContext::Svg(svg_ctx)) => measure_replaced(v_kd, svg_ctx.dimension),
+                    // Form controls have a fixed intrinsic size; CSS may override either side.
+                    Some(TaffyContext::Widget(widget_ctx)) => Size {
+                        width: v_kd.width.unwrap_or(widget_ctx.dimension.width as f32),
+                        height: v_kd.height.unwrap_or(widget_ctx.dimension.height as f32),
+                    },
                     _ => Size::ZERO,
                 }
             })
@@ -460,6 +475,7 @@ impl TaffyLayouter {
             root_id: LayoutElementId::new(0), // Will be filled in later
             next_node_id: Arc::new(RwLock::new(LayoutElementId::new(0))),
             root_dimension: geo::Dimension::ZERO,
+            scroll_ports: HashMap::new(),
         };
 
         let Some((layout_element_root_id, taffy_root_id)) = self.generate_taffy_element(&mut layout_tree, root_id)
@@ -800,7 +816,16 @@ impl TaffyLayouter {
                     let line_height = match doc.get_style(nid, &StyleProperty::LineHeight) {
                         Value::Unit(v, Unit::Px) => v as f64,
                         Value::Number(ratio) => font_size * ratio as f64,
-                        _ => font_size * 1.4,
+                        _ => {
+                            let family = match doc.get_style(nid, &StyleProperty::FontFamily) {
+                                Value::Keyword(id) => lookup(id),
+                                _ => DEFAULT_FONT_FAMILY.to_string(),
+                            };
+                            let mut style = TextStyle::new(family, font_size as f32);
+                            style.lang = doc.lang(nid);
+                            let adjust = font_size_adjust_of(doc.as_ref(), nid);
+                            used_font_size(&mut *self.font_system.lock(), &style, adjust).normal_line_height
+                        }
                     };
                     current_inline_group.push(InlineEntry::Break(line_height));
                     trailing_ws_count = 0;
@@ -952,8 +977,19 @@ impl TaffyLayouter {
                     // Non-blocking: an uncached image kicks off a background fetch and returns
                     // Pending without stalling layout. The element is kept with a placeholder size
                     // (HTML width/height attrs if present, else 0×0); a reflow lands once the fetch
-                    // completes and installs the real intrinsic size.
-                    match self.media_store.request_media(src.as_str()) {
+                    // completes and installs the real intrinsic size. A `loading=lazy` image isn't
+                    // fetched here: the engine starts its fetch once it comes near the viewport.
+                    let lazy = data
+                        .get_attribute("loading")
+                        .is_some_and(|l| l.trim().eq_ignore_ascii_case("lazy"));
+                    let request = if lazy {
+                        self.media_store
+                            .cached_media(&src)
+                            .map_or(MediaRequest::Pending, MediaRequest::Ready)
+                    } else {
+                        self.media_store.request_media(src.as_str())
+                    };
+                    match request {
                         MediaRequest::Ready(media_id) => {
                             let media = self.media_store.get(media_id, MediaType::Image);
                             // When the media is a placeholder (load failed), use a small fixed
@@ -1060,12 +1096,25 @@ impl TaffyLayouter {
                     }
                 }
 
+                // An iframe is a replaced element sized independently of the document it shows:
+                // CSS size, else the width/height attributes, else the HTML default of 300×150.
+                if data.tag_name.eq_ignore_ascii_case("iframe") {
+                    if taffy_style.size.width == Dimension::auto() {
+                        let w = data.get_attribute("width").and_then(|s| parse_px_attr(s));
+                        taffy_style.size.width = Dimension::from_length(w.unwrap_or(IFRAME_DEFAULT_WIDTH));
+                    }
+                    if taffy_style.size.height == Dimension::auto() {
+                        let h = data.get_attribute("height").and_then(|s| parse_px_attr(s));
+                        taffy_style.size.height = Dimension::from_length(h.unwrap_or(IFRAME_DEFAULT_HEIGHT));
+                    }
+                }
+
                 if data.tag_name.eq_ignore_ascii_case("svg") {
-                    let inner_html = layout_tree.render_tree.doc.inner_html(dom_node.node_id);
-                    match self
-                        .media_store
-                        .load_media_from_data(MediaType::Svg, inner_html.into_bytes().as_slice())
-                    {
+                    // Written from the live DOM with the document's CSS applied; usvg only
+                    // rasterizes it.
+                    let doc = layout_tree.render_tree.doc.as_ref();
+                    let markup = inline_svg_markup(doc, dom_node.node_id);
+                    match self.media_store.load_inline_svg(&markup, &doc.base_url()) {
                         Ok(media_id) => {
                             let media = self.media_store.get(media_id, MediaType::Svg);
                             let dimension = match media.borrow() {
@@ -1087,6 +1136,15 @@ impl TaffyLayouter {
                         }
                     }
                 }
+
+                if ["input", "textarea", "select"]
+                    .iter()
+                    .any(|tag| data.tag_name.eq_ignore_ascii_case(tag))
+                {
+                    taffy_context = self
+                        .widget_context(layout_tree, dom_node.node_id)
+                        .map(TaffyContext::Widget);
+                }
             }
             NodeType::Text(text) => {
                 let parent_node = match dom_node.parent_id {
@@ -1137,16 +1195,31 @@ impl TaffyLayouter {
                     _ => FontAlignment::Start,
                 };
 
+                let lang = doc.lang(dom_node.node_id);
+
+                // `font-size-adjust` and `line-height: normal` follow the metrics of the first
+                // available font in the list, the same for every font system, rather than those of
+                // whichever font a character falls back to.
+                let used = {
+                    let mut style = TextStyle::new(font_family.as_str(), font_size as f32);
+                    style.weight = gosub_interface::font_system::FontWeight(font_weight.clamp(1.0, 1000.0) as u16);
+                    style.style = if font_italic {
+                        FontStyle::Italic
+                    } else {
+                        FontStyle::Normal
+                    };
+                    style.lang = lang.clone();
+                    let adjust = font_size_adjust_of(doc.as_ref(), dom_node.node_id);
+                    used_font_size(&mut *self.font_system.lock(), &style, adjust)
+                };
+
                 let line_height = match doc.get_style(dom_node.node_id, &StyleProperty::LineHeight) {
                     Value::Unit(value, Unit::Px) => value as f64,
+                    // A number multiplies the computed size, whatever `font-size-adjust` makes of it.
                     Value::Number(ratio) => font_size * ratio as f64,
-                    // CSS "normal" line-height. We use 1.4 instead of the CSS-spec minimum of
-                    // ~1.2 because pango and parley use different font metrics tables. Parley
-                    // (layout) may return a smaller height than pango (raster), so without this
-                    // buffer the rendered text surface can exceed the span's background
-                    // rectangle, making descenders (e.g. "p") appear to overflow the colored box.
-                    _ => font_size * 1.4,
+                    _ => used.normal_line_height,
                 };
+                let font_size = used.size;
 
                 // Calculate vertical offset for centering based on the line height.
                 let text_offset = Coordinate::new(0.0, (line_height - font_size) / 2.0);
@@ -1223,6 +1296,8 @@ impl TaffyLayouter {
                     alignment,
                     underline: text_decoration.contains("underline"),
                     line_through: text_decoration.contains("line-through"),
+                    lang,
+                    features: font_features_of(doc.as_ref(), dom_node.node_id),
                 };
 
                 taffy_context = Some(TaffyContext::text(
@@ -1242,6 +1317,27 @@ impl TaffyLayouter {
 
         Some((taffy_context, taffy_style))
     }
+
+    /// The form control `node_id` is, sized in its own font, with its mark loaded into the media
+    /// store. See [`crate::layouter::widget`].
+    fn widget_context(&self, layout_tree: &LayoutTree, node_id: DomNodeId) -> Option<ElementContextWidget> {
+        let doc = layout_tree.render_tree.doc.as_ref();
+        let font_info = element_font_info(doc, node_id);
+        let measure = |text: &str| {
+            let mut fs = self.font_system.lock();
+            get_text_layout(text, &font_info, 1_000_000_000.0, &mut *fs)
+                .map(|size| size.width)
+                .unwrap_or(0.0)
+        };
+        let mut widget = ElementContextWidget::from_element(doc, node_id, font_info.line_height, measure)?;
+        if let Some(markup) = widget.mark_markup() {
+            match self.media_store.load_inline_svg(markup, &doc.base_url()) {
+                Ok(media_id) => widget.mark = Some(media_id),
+                Err(e) => log::warn!("Could not load form control mark: {:?}", e),
+            }
+        }
+        Some(widget)
+    }
 }
 
 // Convert a URI to an absolute URL based on the base URL if this is needed
@@ -1310,6 +1406,7 @@ fn to_element_context(taffy_context: Option<&TaffyContext>) -> ElementContext {
             svg_ctx.dimension,
             svg_ctx.node_id,
         ),
+        Some(TaffyContext::Widget(widget_ctx)) => ElementContext::Widget(widget_ctx.clone()),
         None => ElementContext::None,
     }
 }
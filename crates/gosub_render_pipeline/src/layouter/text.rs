@@ -25,6 +25,8 @@ pub fn get_text_layout(
         align: TextAlign::Start,
         // The layouter works in CSS pixels; DPI scaling is applied later in the pipeline.
         display_scale: 1.0,
+        lang: font_info.lang.clone(),
+        features: font_info.features.clone(),
     };
 
     let (width, height) = font_system.measure(text, &style);
@@ -0,0 +1,222 @@
+//! Form controls drawn by the engine.
+//!
+//! `<input>`, `<textarea>` and `<select>` don't render children of their own: what they show
+//! follows from their state, which lives in the DOM - the `value` and `checked` attributes, the
+//! text of a `<textarea>`, the `selected` `<option>`. The layouter turns each into an
+//! [`ElementContextWidget`] holding the text to show and the size the control takes when CSS
+//! doesn't size it, and the painter draws it as a native-looking control. CSS padding, borders
+//! and backgrounds apply as for any other box.
+
+use crate::common::document::node::{NodeId as DomNodeId, NodeType};
+use crate::common::document::pipeline_doc::PipelineDocument;
+use crate::common::geo::Dimension;
+use crate::common::media::MediaId;
+use cow_utils::CowUtils;
+
+/// Default `size` of a text field, in characters
+const TEXT_FIELD_SIZE: f64 = 20.0;
+/// Default `cols` and `rows` of a `<textarea>`
+const TEXT_AREA_COLS: f64 = 20.0;
+const TEXT_AREA_ROWS: f64 = 2.0;
+/// Checkboxes and radio buttons are this many px square
+const TOGGLE_SIZE: f64 = 13.0;
+/// Room for the drop-down arrow of a `<select>`
+pub const SELECT_ARROW_WIDTH: f64 = 20.0;
+
+/// What kind of control an element is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WidgetKind {
+    /// A single-line `<input>`
+    TextField,
+    TextArea,
+    Checkbox,
+    Radio,
+    /// A drop-down `<select>`
+    Select,
+    /// `<input type=submit|reset|button|file>`
+    Button,
+}
+
+#[derive(Debug, Clone)]
+pub struct ElementContextWidget {
+    pub node_id: DomNodeId,
+    pub kind: WidgetKind,
+    /// What the control shows: its value, the selected option's label or the button's label
+    pub text: String,
+    /// `text` is the `placeholder`, drawn dimmed
+    pub placeholder: bool,
+    /// A checkbox or radio button is checked
+    pub checked: bool,
+    /// Content box size the control takes when CSS leaves it `auto`
+    pub dimension: Dimension,
+    /// Drawn over the control: the box of a checkbox or radio button, the arrow of a `<select>`
+    pub mark: Option<MediaId>,
+}
+
+impl ElementContextWidget {
+    /// The control element `id` is, or `None` when it isn't one the engine draws (e.g.
+    /// `<input type=hidden>`). `line_height` is that of the element's font, and `measure` gives
+    /// the width of a string in it.
+    pub fn from_element(
+        doc: &dyn PipelineDocument,
+        id: DomNodeId,
+        line_height: f64,
+        measure: impl Fn(&str) -> f64,
+    ) -> Option<Self> {
+        let node = doc.get_node_by_id(id)?;
+        let NodeType::Element(data) = &node.node_type else {
+            return None;
+        };
+        let attr = |name: &str| data.get_attribute(name).map(String::as_str);
+        let number_attr = |name: &str, default: f64| {
+            attr(name)
+                .and_then(|v| v.trim().parse::<f64>().ok())
+                .filter(|n| *n > 0.0)
+                .unwrap_or(default)
+        };
+        let char_width = measure("0");
+
+        let widget = |kind, text: String, placeholder, dimension| ElementContextWidget {
+            node_id: id,
+            kind,
+            text,
+            placeholder,
+            checked: attr("checked").is_some(),
+            dimension,
+            mark: None,
+        };
+        let with_placeholder = |value: String| match attr("placeholder") {
+            Some(placeholder) if value.is_empty() => (placeholder.to_string(), true),
+            _ => (value, false),
+        };
+
+        match data.tag_name.cow_to_ascii_lowercase().as_ref() {
+            "input" => {
+                let input_type = attr("type").unwrap_or("text").trim().cow_to_ascii_lowercase();
+                let value = attr("value").unwrap_or_default().to_string();
+                match input_type.as_ref() {
+                    "hidden" | "image" | "range" | "color" => None,
+                    "checkbox" | "radio" => {
+                        let kind = if input_type == "checkbox" {
+                            WidgetKind::Checkbox
+                        } else {
+                            WidgetKind::Radio
+                        };
+                        let size = Dimension::new(TOGGLE_SIZE, TOGGLE_SIZE);
+                        Some(widget(kind, String::new(), false, size))
+                    }
+                    "submit" | "reset" | "button" | "file" => {
+                        let label = match (input_type.as_ref(), attr("value")) {
+                            ("file", _) => "Choose File".to_string(),
+                            (_, Some(value)) => value.to_string(),
+                            ("submit", None) => "Submit".to_string(),
+                            ("reset", None) => "Reset".to_string(),
+                            _ => String::new(),
+                        };
+                        let size = Dimension::new(measure(&label).ceil(), line_height);
+                        Some(widget(WidgetKind::Button, label, false, size))
+                    }
+                    _ => {
+                        let value = if input_type == "password" {
+                            "\u{2022}".repeat(value.chars().count())
+                        } else {
+                            value
+                        };
+                        let (text, placeholder) = with_placeholder(value);
+                        let width = number_attr("size", TEXT_FIELD_SIZE) * char_width;
+                        let size = Dimension::new(width.ceil(), line_height);
+                        Some(widget(WidgetKind::TextField, text, placeholder, size))
+                    }
+                }
+            }
+            "textarea" => {
+                let (text, placeholder) = with_placeholder(text_content(doc, id));
+                let size = Dimension::new(
+                    (number_attr("cols", TEXT_AREA_COLS) * char_width).ceil(),
+                    (number_attr("rows", TEXT_AREA_ROWS) * line_height).ceil(),
+                );
+                Some(widget(WidgetKind::TextArea, text, placeholder, size))
+            }
+            "select" => {
+                let options = options(doc, id);
+                let widest = options.iter().map(|(label, _)| measure(label)).fold(0.0, f64::max);
+                // The last `selected` option wins, as when the parser sets them one by one.
+                let label = options
+                    .iter()
+                    .rev()
+                    .find(|(_, selected)| *selected)
+                    .or(options.first())
+                    .map(|(label, _)| label.clone())
+                    .unwrap_or_default();
+                let size = Dimension::new((widest + SELECT_ARROW_WIDTH).ceil(), line_height);
+                Some(widget(WidgetKind::Select, label, false, size))
+            }
+            _ => None,
+        }
+    }
+
+    /// SVG markup of what is drawn over the control, if anything.
+    pub fn mark_markup(&self) -> Option<&'static str> {
+        match (self.kind, self.checked) {
+            (WidgetKind::Checkbox, false) => Some(CHECKBOX),
+            (WidgetKind::Checkbox, true) => Some(CHECKBOX_CHECKED),
+            (WidgetKind::Radio, false) => Some(RADIO),
+            (WidgetKind::Radio, true) => Some(RADIO_CHECKED),
+            (WidgetKind::Select, _) => Some(SELECT_ARROW),
+            _ => None,
+        }
+    }
+
+    /// Whether the control draws its own box instead of the element's CSS box.
+    pub fn is_toggle(&self) -> bool {
+        matches!(self.kind, WidgetKind::Checkbox | WidgetKind::Radio)
+    }
+}
+
+const CHECKBOX: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="13" height="13" viewBox="0 0 13 13"><rect x="0.5" y="0.5" width="12" height="12" rx="2" fill="#ffffff" stroke="#767676"/></svg>"##;
+const CHECKBOX_CHECKED: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="13" height="13" viewBox="0 0 13 13"><rect width="13" height="13" rx="2" fill="#0075ff"/><path d="M3 6.5l2.5 2.5 4.5-5" fill="none" stroke="#ffffff" stroke-width="1.8"/></svg>"##;
+const RADIO: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="13" height="13" viewBox="0 0 13 13"><circle cx="6.5" cy="6.5" r="6" fill="#ffffff" stroke="#767676"/></svg>"##;
+const RADIO_CHECKED: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="13" height="13" viewBox="0 0 13 13"><circle cx="6.5" cy="6.5" r="6" fill="#ffffff" stroke="#0075ff"/><circle cx="6.5" cy="6.5" r="3.5" fill="#0075ff"/></svg>"##;
+const SELECT_ARROW: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10" viewBox="0 0 10 10"><path d="M1 3l4 4 4-4" fill="none" stroke="#000000" stroke-width="1.5"/></svg>"##;
+
+/// The text of the text nodes under `id`, in tree order.
+fn text_content(doc: &dyn PipelineDocument, id: DomNodeId) -> String {
+    let mut text = String::new();
+    let mut stack = vec![id];
+    while let Some(id) = stack.pop() {
+        if let Some(node) = doc.get_node_by_id(id) {
+            if let NodeType::Text(t) = &node.node_type {
+                text.push_str(t);
+            }
+        }
+        stack.extend(doc.children(id).into_iter().rev());
+    }
+    text
+}
+
+/// The labels of the `<option>`s of a `<select>` (also inside `<optgroup>`s), with whether they
+/// are `selected`.
+fn options(doc: &dyn PipelineDocument, select: DomNodeId) -> Vec<(String, bool)> {
+    let mut options = Vec::new();
+    let mut stack: Vec<DomNodeId> = doc.children(select).into_iter().rev().collect();
+    while let Some(id) = stack.pop() {
+        let Some(node) = doc.get_node_by_id(id) else {
+            continue;
+        };
+        let NodeType::Element(data) = &node.node_type else {
+            continue;
+        };
+        match data.tag_name.cow_to_ascii_lowercase().as_ref() {
+            "option" => {
+                let label = match data.get_attribute("label") {
+                    Some(label) => label.clone(),
+                    None => text_content(doc, id).split_whitespace().collect::<Vec<_>>().join(" "),
+                };
+                options.push((label, data.get_attribute("selected").is_some()));
+            }
+            "optgroup" => stack.extend(doc.children(id).into_iter().rev()),
+            _ => {}
+        }
+    }
+    options
+}
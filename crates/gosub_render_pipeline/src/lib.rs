@@ -1,6 +1,7 @@
 pub mod common;
 pub mod layering;
 pub mod layouter;
+pub mod paginator;
 pub mod painter;
 pub mod rasterizer;
 pub mod render;
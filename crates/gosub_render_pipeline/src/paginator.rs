@@ -0,0 +1,353 @@
+//! Paginated layout for printing.
+//!
+//! The page is laid out once as a continuous strip at the width of the printable area, then cut
+//! into page-sized bands. Break positions honour `break-before` / `break-after` (forced and
+//! `avoid`), `break-inside: avoid`, and never cut through a line of text or a replaced element
+//! that fits on a page. [`Painter::paint_region`] then paints each band into its own scene.
+//!
+//! All lengths are CSS pixels (96 per inch).
+//!
+//! [`Painter::paint_region`]: crate::painter::Painter::paint_region
+
+use crate::common::document::style::{lookup, StyleProperty, Value};
+use crate::common::geo::Rect;
+use crate::layouter::{ElementContext, LayoutTree};
+use crate::painter::PaintScene;
+
+/// CSS pixels per millimetre.
+const PX_PER_MM: f64 = 96.0 / 25.4;
+
+/// Paper size and margins.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageSetup {
+    pub width: f64,
+    pub height: f64,
+    pub margin_top: f64,
+    pub margin_right: f64,
+    pub margin_bottom: f64,
+    pub margin_left: f64,
+}
+
+impl PageSetup {
+    /// A page of `width` × `height` with the same margin on every side.
+    pub fn new(width: f64, height: f64, margin: f64) -> Self {
+        Self {
+            width,
+            height,
+            margin_top: margin,
+            margin_right: margin,
+            margin_bottom: margin,
+            margin_left: margin,
+        }
+    }
+
+    /// ISO A4 (210 × 297 mm) with 10 mm margins.
+    pub fn a4() -> Self {
+        Self::new(210.0 * PX_PER_MM, 297.0 * PX_PER_MM, 10.0 * PX_PER_MM)
+    }
+
+    /// US Letter (8.5 × 11 in) with 0.4 in margins.
+    pub fn letter() -> Self {
+        Self::new(8.5 * 96.0, 11.0 * 96.0, 0.4 * 96.0)
+    }
+
+    /// Width of the printable area; the document is laid out at this width.
+    pub fn content_width(&self) -> f64 {
+        (self.width - self.margin_left - self.margin_right).max(1.0)
+    }
+
+    /// Height of the printable area, i.e. how much of the document fits on one page.
+    pub fn content_height(&self) -> f64 {
+        (self.height - self.margin_top - self.margin_bottom).max(1.0)
+    }
+}
+
+impl Default for PageSetup {
+    fn default() -> Self {
+        Self::a4()
+    }
+}
+
+/// A `break-before` / `break-after` value, reduced to what a single-column paginator needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BreakKind {
+    #[default]
+    Auto,
+    /// `page`, `left`, `right`, `recto`, `verso` and the legacy `always`.
+    Page,
+    /// `avoid` and `avoid-page`.
+    Avoid,
+}
+
+impl BreakKind {
+    pub fn from_keyword(kw: &str) -> Self {
+        match kw {
+            "page" | "always" | "left" | "right" | "recto" | "verso" => BreakKind::Page,
+            "avoid" | "avoid-page" => BreakKind::Avoid,
+            _ => BreakKind::Auto,
+        }
+    }
+}
+
+/// One laid-out box as the paginator sees it, in document coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BreakBox {
+    pub top: f64,
+    pub bottom: f64,
+    pub break_before: BreakKind,
+    pub break_after: BreakKind,
+    /// Keep the whole box on one page when it fits (`break-inside: avoid`, replaced elements).
+    pub avoid_inside: bool,
+    /// For text: breaks inside the box snap to line boundaries.
+    pub line_height: Option<f64>,
+}
+
+impl BreakBox {
+    fn straddles(&self, y: f64) -> bool {
+        self.top < y && y < self.bottom
+    }
+}
+
+/// Collects the break-relevant boxes of a layout tree.
+pub fn break_boxes(layout: &LayoutTree) -> Vec<BreakBox> {
+    let doc = &layout.render_tree.doc;
+    let keyword = |node, prop| match doc.get_style(node, prop) {
+        Value::Keyword(kw) => BreakKind::from_keyword(&lookup(kw)),
+        _ => BreakKind::Auto,
+    };
+
+    layout
+        .arena
+        .values()
+        .filter(|el| el.box_model.border_box.height > 0.0)
+        .map(|el| {
+            let b = el.box_model.border_box;
+            let (avoid_replaced, line_height) = match &el.context {
+                ElementContext::Text(text) => (false, Some(text.font_info.line_height)),
+                ElementContext::Image(_) | ElementContext::Svg(_) | ElementContext::Widget(_) => (true, None),
+                ElementContext::None => (false, None),
+            };
+            let avoid_inside = avoid_replaced
+                || matches!(
+                    doc.get_style(el.dom_node_id, &StyleProperty::BreakInside),
+                    Value::Keyword(kw) if BreakKind::from_keyword(&lookup(kw)) == BreakKind::Avoid
+                );
+            BreakBox {
+                top: b.y,
+                bottom: b.y + b.height,
+                break_before: keyword(el.dom_node_id, &StyleProperty::BreakBefore),
+                break_after: keyword(el.dom_node_id, &StyleProperty::BreakAfter),
+                avoid_inside,
+                line_height,
+            }
+        })
+        .collect()
+}
+
+/// Cuts a document of `doc_height` into bands of at most `page_height`, returning each band as
+/// `(top, bottom)` in document coordinates. Always returns at least one band.
+///
+/// For each page the natural break (one page height down) is tried first; a forced break before
+/// it wins. The break is then pulled up, repeatedly, to the top of any box it would split that
+/// asked not to be split, to the last whole line of a text box, and above boxes joined by
+/// `break-*: avoid`. A pull that would leave the page empty is ignored, so oversized content is
+/// sliced rather than looping forever.
+pub fn paginate(boxes: &[BreakBox], doc_height: f64, page_height: f64) -> Vec<(f64, f64)> {
+    const EPS: f64 = 0.5;
+
+    let mut forced: Vec<f64> = boxes
+        .iter()
+        .flat_map(|b| {
+            let before = (b.break_before == BreakKind::Page).then_some(b.top);
+            let after = (b.break_after == BreakKind::Page).then_some(b.bottom);
+            before.into_iter().chain(after)
+        })
+        .collect();
+    forced.sort_by(f64::total_cmp);
+
+    let mut pages = Vec::new();
+    let mut start = 0.0;
+    while start < doc_height - EPS {
+        let natural = start + page_height;
+
+        let mut end = if let Some(&f) = forced.iter().find(|&&f| f > start + EPS && f <= natural + EPS) {
+            f
+        } else if natural >= doc_height {
+            doc_height
+        } else {
+            // The nearest box edge above `y` to break at instead.
+            let previous = |y: f64| {
+                boxes
+                    .iter()
+                    .map(|b| b.top)
+                    .filter(|&top| top < y - EPS)
+                    .fold(f64::NEG_INFINITY, f64::max)
+            };
+            let mut end = natural;
+            loop {
+                let mut pulled = end;
+                for b in boxes {
+                    let candidate = if b.avoid_inside && b.straddles(end) && b.bottom - b.top <= page_height {
+                        b.top
+                    } else if let (Some(lh), true) = (b.line_height, b.straddles(end)) {
+                        if lh > 0.0 {
+                            b.top + ((end - b.top) / lh).floor() * lh
+                        } else {
+                            end
+                        }
+                    } else if b.break_before == BreakKind::Avoid && (b.top - end).abs() < EPS {
+                        previous(end)
+                    } else if b.break_after == BreakKind::Avoid && (b.bottom - end).abs() < EPS {
+                        b.top
+                    } else {
+                        end
+                    };
+                    if candidate > start + EPS && candidate < pulled {
+                        pulled = candidate;
+                    }
+                }
+                if pulled >= end {
+                    break;
+                }
+                end = pulled;
+            }
+            end
+        };
+
+        end = end.min(doc_height);
+        if end <= start + EPS {
+            end = natural.min(doc_height);
+        }
+        pages.push((start, end));
+        start = end;
+    }
+
+    if pages.is_empty() {
+        pages.push((0.0, doc_height.max(0.0)));
+    }
+    pages
+}
+
+/// One printed page.
+pub struct PrintPage {
+    /// Zero-based page number.
+    pub index: usize,
+    /// The slice of the document on this page, in document coordinates.
+    pub band: Rect,
+    /// Paper size and margins the page was laid out for.
+    pub setup: PageSetup,
+    /// Paint commands for every element that intersects `band`, in document coordinates. To
+    /// place them on paper, translate by `(margin_left, margin_top - band.y)` and clip to the
+    /// printable area; elements split across pages are painted on both and clipped.
+    pub scene: PaintScene,
+}
+
+impl std::fmt::Debug for PrintPage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrintPage")
+            .field("index", &self.index)
+            .field("band", &self.band)
+            .field("setup", &self.setup)
+            .field("commands", &self.scene.commands.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(top: f64, bottom: f64) -> BreakBox {
+        BreakBox {
+            top,
+            bottom,
+            break_before: BreakKind::Auto,
+            break_after: BreakKind::Auto,
+            avoid_inside: false,
+            line_height: None,
+        }
+    }
+
+    #[test]
+    fn page_setup_content_area() {
+        let letter = PageSetup::letter();
+        assert_eq!(letter.width, 816.0);
+        assert!((letter.content_width() - (816.0 - 76.8)).abs() < 1e-9);
+        assert!((PageSetup::a4().width - 793.7).abs() < 0.1);
+    }
+
+    #[test]
+    fn plain_document_splits_at_page_height() {
+        let pages = paginate(&[block(0.0, 250.0)], 250.0, 100.0);
+        assert_eq!(pages, vec![(0.0, 100.0), (100.0, 200.0), (200.0, 250.0)]);
+
+        // An empty document still yields one page.
+        assert_eq!(paginate(&[], 0.0, 100.0), vec![(0.0, 0.0)]);
+    }
+
+    #[test]
+    fn forced_breaks() {
+        let mut heading = block(40.0, 60.0);
+        heading.break_before = BreakKind::Page;
+        let mut intro = block(60.0, 70.0);
+        intro.break_after = BreakKind::Page;
+
+        let pages = paginate(&[block(0.0, 40.0), heading, intro, block(70.0, 120.0)], 120.0, 100.0);
+        assert_eq!(pages, vec![(0.0, 40.0), (40.0, 70.0), (70.0, 120.0)]);
+    }
+
+    #[test]
+    fn avoid_inside_moves_box_to_next_page() {
+        let mut figure = block(80.0, 130.0);
+        figure.avoid_inside = true;
+
+        let pages = paginate(&[block(0.0, 80.0), figure], 130.0, 100.0);
+        assert_eq!(pages, vec![(0.0, 80.0), (80.0, 130.0)]);
+
+        // Too tall to ever fit: sliced at the page height instead.
+        let mut tall = block(0.0, 250.0);
+        tall.avoid_inside = true;
+        assert_eq!(paginate(&[tall], 250.0, 100.0)[0], (0.0, 100.0));
+    }
+
+    #[test]
+    fn text_breaks_between_lines() {
+        let mut para = block(10.0, 130.0);
+        para.line_height = Some(20.0);
+
+        // Page height 100 would cut the 5th line (90..110); break after the 4th instead.
+        let pages = paginate(&[para], 130.0, 100.0);
+        assert_eq!(pages[0], (0.0, 90.0));
+        assert_eq!(pages[1], (90.0, 130.0));
+    }
+
+    #[test]
+    fn break_after_avoid_keeps_heading_with_content() {
+        let mut heading = block(70.0, 100.0);
+        heading.break_after = BreakKind::Avoid;
+
+        let pages = paginate(&[block(0.0, 70.0), heading, block(100.0, 150.0)], 150.0, 100.0);
+        assert_eq!(pages[0], (0.0, 70.0));
+    }
+
+    #[test]
+    fn break_before_avoid_moves_to_previous_break() {
+        let mut caption = block(100.0, 130.0);
+        caption.break_before = BreakKind::Avoid;
+
+        // The figure before the caption goes to the next page with it.
+        let pages = paginate(&[block(0.0, 60.0), block(60.0, 100.0), caption], 130.0, 100.0);
+        assert_eq!(pages, vec![(0.0, 60.0), (60.0, 130.0)]);
+
+        // With nothing earlier to break at, the page is cut where it would have been.
+        let pages = paginate(&[block(0.0, 100.0), caption], 130.0, 100.0);
+        assert_eq!(pages[0], (0.0, 100.0));
+    }
+
+    #[test]
+    fn legacy_keywords() {
+        assert_eq!(BreakKind::from_keyword("always"), BreakKind::Page);
+        assert_eq!(BreakKind::from_keyword("avoid-page"), BreakKind::Avoid);
+        assert_eq!(BreakKind::from_keyword("avoid-column"), BreakKind::Auto);
+    }
+}
@@ -1,13 +1,17 @@
 pub mod commands;
+pub mod inspector;
 
 use crate::common::browser_state::{BrowserState, WireframeState};
 use crate::common::document::node::NodeId;
 use crate::common::document::pipeline_doc::{BgImageLayout, BgSize};
 use crate::common::document::style::{lookup, BorderStyle as CssBorderStyle, Display, StyleProperty, Value};
-use crate::common::font::{FontAlignment, FontInfo};
+use crate::common::font::{element_font_info, FontAlignment, FontInfo};
 use crate::common::geo::Rect;
 use crate::common::media::MediaStore;
-use crate::layering::layer::LayerList;
+use crate::common::selection::TextLines;
+use crate::layering::layer::{Layer, LayerId, LayerList};
+use crate::layouter::scroll::clips_overflow;
+use crate::layouter::widget::{ElementContextWidget, WidgetKind, SELECT_ARROW_WIDTH};
 use crate::layouter::{BackgroundMedia, ElementContext, LayoutElementId, LayoutElementNode};
 use crate::painter::commands::border::{Border, BorderStyle};
 use crate::painter::commands::brush::Brush;
@@ -16,12 +20,14 @@ use crate::painter::commands::gradient::{Gradient, Tiling};
 use crate::painter::commands::rectangle::{BlendMode, Radius, Rectangle};
 use crate::painter::commands::text::Text;
 use crate::painter::commands::PaintCommand;
+use crate::painter::inspector::{BoxArea, InspectorOverlay, LABEL_HEIGHT, LABEL_PADDING};
 use crate::render::backend::TileAnchor;
 use crate::tiler::TiledLayoutElement;
 use gosub_interface::font::FontStyle;
 use gosub_interface::font_system::{FontStretch, FontSystem, FontWeight, ShapedText, TextAlign, TextStyle};
 use parking_lot::Mutex;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, OnceLock};
 
 /// A whole-viewport paint command list for the GPU-scene path, translated by a backend's `render`
 /// into its native scene. Replaces the tile/rasterize/composite stages for GPU backends.
@@ -33,6 +39,11 @@ pub struct PaintScene {
     pub page_height: f64,
 }
 
+/// Paint commands of child documents (iframes), keyed by the DOM node of the frame element in
+/// the parent. Each list is in the child's own page coordinates, already scrolled; the painter
+/// moves it to the frame's content box and clips it there.
+pub type FrameContents = HashMap<NodeId, Arc<Vec<PaintCommand>>>;
+
 /// The same [`TextStyle`] mapping the layouter measured with, so shaping reproduces its box.
 ///
 /// Start-aligned text wraps at the layouter's container width to reproduce its line breaks (a
@@ -65,6 +76,8 @@ fn paint_text_style(font_info: &FontInfo, rect_width: f64, available_width: f64)
         align,
         // Paint commands are in CSS pixels; DPI scaling is applied later in the pipeline.
         display_scale: 1.0,
+        lang: font_info.lang.clone(),
+        features: font_info.features.clone(),
     }
 }
 
@@ -75,6 +88,16 @@ pub struct Painter {
     /// time. `None` (e.g. the null backend) yields empty glyph runs, drawable only by
     /// engine-native text rasterizers.
     font_system: Option<Arc<Mutex<dyn FontSystem>>>,
+    /// Paint-order index of every element, built on first use by the paint-order debug mode.
+    paint_order: OnceLock<HashMap<LayoutElementId, usize>>,
+    /// Elements the focus ring is drawn around, built on first use.
+    focus_ring: OnceLock<HashSet<LayoutElementId>>,
+    /// Clip rect of every element inside an `overflow` clipping box, built on first use.
+    clips: OnceLock<HashMap<LayoutElementId, Rect>>,
+    /// Child frame content painted inside frame elements.
+    frames: FrameContents,
+    /// Where animated SVGs keep the frame they are at; see [`with_media_store`](Self::with_media_store).
+    media_store: Arc<MediaStore>,
 }
 
 impl Painter {
@@ -82,9 +105,26 @@ impl Painter {
         Painter {
             layer_list,
             font_system,
+            paint_order: OnceLock::new(),
+            focus_ring: OnceLock::new(),
+            clips: OnceLock::new(),
+            frames: FrameContents::new(),
+            media_store: Arc::new(MediaStore::new()),
         }
     }
 
+    /// Paints `frames` into their frame elements.
+    pub fn with_frames(mut self, frames: FrameContents) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    /// Paint SVGs at the animation frame `media_store` has them at, instead of their first frame.
+    pub fn with_media_store(mut self, media_store: Arc<MediaStore>) -> Self {
+        self.media_store = media_store;
+        self
+    }
+
     /// Shape `text` into the positioned glyph runs a glyph-based rasterizer will paint.
     fn shape_text(&self, text: &str, font_info: &FontInfo, rect_width: f64, available_width: f64) -> ShapedText {
         let Some(ref fs) = self.font_system else {
@@ -105,6 +145,11 @@ impl Painter {
     /// (`layer.elements`) - matching the tiler's z-ordering. For GPU-scene backends that render
     /// the whole viewport in one pass.
     pub fn paint_all(&self, state: &BrowserState) -> Vec<PaintCommand> {
+        // Build the order map before taking the layer locks below; it reads them itself.
+        if state.paint_debug.paint_order {
+            self.paint_order();
+        }
+
         let mut out = Vec::new();
         let layer_ids = self.layer_list.layer_ids.read();
         let layers = self.layer_list.layers.read();
@@ -125,13 +170,232 @@ impl Painter {
             for &element_id in &layer.elements {
                 out.extend(self.paint_element(element_id, state));
             }
+            if state.paint_debug.layer_borders {
+                out.extend(self.layer_border_commands(layer));
+            }
             if promoted {
                 out.push(PaintCommand::PopLayer);
             }
         }
+        out.extend(self.repaint_flash_commands(state));
         out
     }
 
+    /// Like [`Painter::paint_all`], but only paints elements whose border box intersects `band`
+    /// (in page coordinates). Used to cut a paginated layout into per-page scenes; debug overlays
+    /// are never painted.
+    pub fn paint_region(&self, band: Rect, state: &BrowserState) -> Vec<PaintCommand> {
+        let arena = &self.layer_list.layout_tree.arena;
+        let intersects = |id: &LayoutElementId| {
+            arena.get(id).is_some_and(|el| {
+                let b = el.box_model.border_box;
+                b.y < band.y + band.height && b.y + b.height > band.y
+            })
+        };
+
+        let mut out = Vec::new();
+        let layer_ids = self.layer_list.layer_ids.read();
+        let layers = self.layer_list.layers.read();
+        for layer_id in layer_ids.iter() {
+            let Some(layer) = layers.get(layer_id) else {
+                continue;
+            };
+            let promoted = layer.opacity < 1.0 || !matches!(layer.anchor, TileAnchor::Scroll);
+            if promoted {
+                out.push(PaintCommand::PushLayer {
+                    opacity: layer.opacity,
+                    anchor: layer.anchor,
+                });
+            }
+            for element_id in layer.elements.iter().filter(|id| intersects(id)) {
+                out.extend(self.paint_element(*element_id, state));
+            }
+            if promoted {
+                out.push(PaintCommand::PopLayer);
+            }
+        }
+        out
+    }
+
+    /// Paint-order index per element: layers in stacking order, then elements in layer order.
+    fn paint_order(&self) -> &HashMap<LayoutElementId, usize> {
+        self.paint_order.get_or_init(|| {
+            let layer_ids = self.layer_list.layer_ids.read();
+            let layers = self.layer_list.layers.read();
+            layer_ids
+                .iter()
+                .filter_map(|id| layers.get(id))
+                .flat_map(|layer| layer.elements.iter().copied())
+                .enumerate()
+                .map(|(index, id)| (id, index))
+                .collect()
+        })
+    }
+
+    /// Elements that get a focus ring: the focused element's own boxes, or when it has none (an
+    /// inline `<a>` is flattened into its text runs) the text and replaced boxes inside it.
+    fn focus_ring(&self) -> &HashSet<LayoutElementId> {
+        self.focus_ring.get_or_init(|| {
+            let tree = &self.layer_list.layout_tree;
+            let doc = &tree.render_tree.doc;
+            let Some(focused) = doc.focused() else {
+                return HashSet::new();
+            };
+
+            let own: HashSet<_> = tree
+                .arena
+                .values()
+                .filter(|el| el.dom_node_id == focused)
+                .map(|el| el.id)
+                .collect();
+            if !own.is_empty() {
+                return own;
+            }
+
+            let inside = |mut id: NodeId| loop {
+                match doc.parent(id) {
+                    Some(parent) if parent == focused => return true,
+                    Some(parent) => id = parent,
+                    None => return false,
+                }
+            };
+            tree.arena
+                .values()
+                .filter(|el| !matches!(el.context, ElementContext::None) && inside(el.dom_node_id))
+                .map(|el| el.id)
+                .collect()
+        })
+    }
+
+    /// What each element is clipped to by its overflow-clipping ancestors (see
+    /// [`clip_rect`](crate::layouter::scroll::clip_rect)), computed for the whole tree top-down.
+    fn clips(&self) -> &HashMap<LayoutElementId, Rect> {
+        self.clips.get_or_init(|| {
+            let tree = &self.layer_list.layout_tree;
+            let mut clips = HashMap::new();
+            let mut stack = vec![(tree.root_id, None::<Rect>)];
+            while let Some((id, clip)) = stack.pop() {
+                let Some(el) = tree.get_node_by_id(id) else {
+                    continue;
+                };
+                if let Some(clip) = clip {
+                    clips.insert(id, clip);
+                }
+                let inner = if clips_overflow(tree, el.dom_node_id) {
+                    let pb = el.box_model.padding_box;
+                    Some(clip.map_or(pb, |c| c.intersection(&pb)))
+                } else {
+                    clip
+                };
+                stack.extend(el.children.iter().map(|&child| (child, inner)));
+            }
+            clips
+        })
+    }
+
+    /// A 2px ring just outside the border box, like the UA `outline: auto` focus indicator.
+    fn focus_ring_commands(&self, layout_element: &LayoutElementNode) -> Vec<PaintCommand> {
+        const WIDTH: f64 = 2.0;
+        let b = layout_element.box_model.border_box;
+        let rect = Rect::new(b.x - WIDTH, b.y - WIDTH, b.width + WIDTH * 2.0, b.height + WIDTH * 2.0);
+        let color = Color::from_rgba8(16, 108, 255, 255);
+        let border = Border::new(
+            WIDTH as f32,
+            BorderStyle::Solid,
+            [
+                Brush::Solid(color.clone()),
+                Brush::Solid(color.clone()),
+                Brush::Solid(color.clone()),
+                Brush::Solid(color),
+            ],
+        );
+        vec![PaintCommand::rectangle(Rectangle::new(rect).with_border(border))]
+    }
+
+    /// Debug overlays for one tile of `layer_id`: the layer outline and repaint flash, as enabled
+    /// in `state.paint_debug`. Tiled pipelines append these after the tile's own elements; the
+    /// tile clips them to its area.
+    pub fn tile_debug_commands(&self, layer_id: LayerId, state: &BrowserState) -> Vec<PaintCommand> {
+        let mut commands = Vec::new();
+        if state.paint_debug.layer_borders {
+            if let Some(layer) = self.layer_list.layers.read().get(&layer_id) {
+                commands.extend(self.layer_border_commands(layer));
+            }
+        }
+        commands.extend(self.repaint_flash_commands(state));
+        commands
+    }
+
+    /// Outline around the union of a layer's element border boxes. Layers that scroll with the
+    /// page are orange; promoted layers (fixed, sticky or group opacity) are cyan.
+    fn layer_border_commands(&self, layer: &Layer) -> Vec<PaintCommand> {
+        let bounds = layer
+            .elements
+            .iter()
+            .filter_map(|id| self.layer_list.layout_tree.get_node_by_id(*id))
+            .map(|el| el.box_model.border_box)
+            .filter(|r| r.width > 0.0 && r.height > 0.0)
+            .reduce(|a, b| {
+                let x0 = a.x.min(b.x);
+                let y0 = a.y.min(b.y);
+                let x1 = (a.x + a.width).max(b.x + b.width);
+                let y1 = (a.y + a.height).max(b.y + b.height);
+                Rect::new(x0, y0, x1 - x0, y1 - y0)
+            });
+        let Some(bounds) = bounds else {
+            return Vec::new();
+        };
+
+        let promoted = layer.opacity < 1.0 || !matches!(layer.anchor, TileAnchor::Scroll);
+        let color = if promoted {
+            Color::from_rgba8(0, 200, 255, 220)
+        } else {
+            Color::from_rgba8(255, 160, 0, 220)
+        };
+        let border = Border::new(
+            2.0,
+            BorderStyle::Solid,
+            [
+                Brush::Solid(color.clone()),
+                Brush::Solid(color.clone()),
+                Brush::Solid(color.clone()),
+                Brush::Solid(color),
+            ],
+        );
+        vec![PaintCommand::rectangle(Rectangle::new(bounds).with_border(border))]
+    }
+
+    /// Translucent green over every damaged region of this pass.
+    fn repaint_flash_commands(&self, state: &BrowserState) -> Vec<PaintCommand> {
+        if !state.paint_debug.repaint_flashing {
+            return Vec::new();
+        }
+        state
+            .damage
+            .iter()
+            .map(|rect| {
+                let brush = Brush::Solid(Color::from_rgba8(0, 200, 0, 77));
+                PaintCommand::rectangle(Rectangle::new(*rect).with_background(brush))
+            })
+            .collect()
+    }
+
+    /// Tint over an element's border box, colored by where it falls in paint order.
+    fn paint_order_commands(&self, layout_element: &LayoutElementNode) -> Vec<PaintCommand> {
+        let order = self.paint_order();
+        let Some(&index) = order.get(&layout_element.id) else {
+            return Vec::new();
+        };
+        let t = if order.len() > 1 {
+            index as f64 / (order.len() - 1) as f64
+        } else {
+            0.0
+        };
+        let color = Color::from_rgba8((255.0 * t) as u8, 64, (255.0 * (1.0 - t)) as u8, 64);
+        let r = Rectangle::new(layout_element.box_model.border_box).with_background(Brush::Solid(color));
+        vec![PaintCommand::rectangle(r)]
+    }
+
     pub fn paint_element(&self, element_id: LayoutElementId, state: &BrowserState) -> Vec<PaintCommand> {
         let mut commands = Vec::new();
 
@@ -140,10 +404,6 @@ impl Painter {
         };
         let dom_node_id = layout_element.dom_node_id;
 
-        if state.debug_hover && state.current_hovered_element == Some(layout_element.id) {
-            commands.extend(self.generate_boxmodel_commands(layout_element));
-        }
-
         match state.wireframed {
             WireframeState::Only => {
                 commands.extend(self.generate_wireframe_commands(layout_element));
@@ -157,6 +417,45 @@ impl Painter {
             }
         }
 
+        if let Some(frame) = self.frames.get(&dom_node_id) {
+            commands.extend(self.frame_commands(layout_element, frame));
+        }
+
+        if let ElementContext::Text(text) = &layout_element.context {
+            if let Some(&(start, end)) = state.selection.get(&text.node_id) {
+                commands.extend(self.generate_selection_commands(
+                    layout_element,
+                    &text.text,
+                    &text.font_info,
+                    start,
+                    end,
+                ));
+            }
+        }
+
+        if state.paint_debug.paint_order {
+            commands.extend(self.paint_order_commands(layout_element));
+        }
+
+        if self.focus_ring().contains(&layout_element.id) {
+            commands.extend(self.focus_ring_commands(layout_element));
+        }
+
+        // Content inside a scroll container or other overflow clip; debug overlays stay unclipped.
+        if let Some(&clip) = self.clips().get(&layout_element.id) {
+            if !commands.is_empty() {
+                commands.insert(0, PaintCommand::PushClip(clip));
+                commands.push(PaintCommand::PopClip);
+            }
+        }
+
+        // The inspector overlay goes on top of the element's own content.
+        let inspected = state.inspected_element == Some(layout_element.id)
+            || (state.debug_hover && state.current_hovered_element == Some(layout_element.id));
+        if inspected {
+            commands.extend(self.generate_boxmodel_commands(layout_element, state));
+        }
+
         if state.debug_table_cells {
             commands.extend(self.generate_table_debug_commands(layout_element, dom_node_id));
         }
@@ -164,6 +463,44 @@ impl Painter {
         commands
     }
 
+    /// A child frame's commands placed at the frame element's content box and clipped to it.
+    fn frame_commands(&self, layout_element: &LayoutElementNode, frame: &[PaintCommand]) -> Vec<PaintCommand> {
+        let content = layout_element.box_model.content_box;
+        let mut commands = Vec::with_capacity(frame.len() + 2);
+        commands.push(PaintCommand::PushClip(content));
+        commands.extend(frame.iter().cloned().map(|mut cmd| {
+            cmd.translate(content.x, content.y);
+            cmd
+        }));
+        commands.push(PaintCommand::PopClip);
+        commands
+    }
+
+    /// Translucent `Highlight`-colored rects over the selected characters of a text element.
+    /// Painted after the glyphs, so the alpha keeps the text readable underneath.
+    fn generate_selection_commands(
+        &self,
+        layout_element: &LayoutElementNode,
+        text: &str,
+        font_info: &FontInfo,
+        start: usize,
+        end: usize,
+    ) -> Vec<PaintCommand> {
+        let lines = TextLines::new(
+            layout_element.box_model.content_box,
+            font_info.line_height,
+            text.chars().count(),
+        );
+        lines
+            .rects_for(start, end)
+            .into_iter()
+            .map(|rect| {
+                let brush = Brush::Solid(Color::from_rgba8(0, 120, 215, 96));
+                PaintCommand::rectangle(Rectangle::new(rect).with_background(brush))
+            })
+            .collect()
+    }
+
     fn get_brush(&self, node_id: NodeId, css_prop: &StyleProperty, default: Brush) -> Brush {
         let doc = &self.layer_list.layout_tree.render_tree.doc;
         let brush = match doc.get_style(node_id, css_prop) {
@@ -255,30 +592,9 @@ impl Painter {
         )))
     }
 
-    /// Minimal [`FontInfo`] for `alt` text: the element's computed family/size, start-aligned and
-    /// undecorated, matching how browsers render the placeholder label.
+    /// [`FontInfo`] for `alt` text, matching how browsers render the placeholder label.
     fn alt_font_info(&self, node_id: NodeId) -> FontInfo {
-        let doc = &self.layer_list.layout_tree.render_tree.doc;
-        let size = match doc.get_style(node_id, &StyleProperty::FontSize) {
-            Value::Unit(px, _) => px as f64,
-            _ => 16.0,
-        };
-        let family = match doc.get_style(node_id, &StyleProperty::FontFamily) {
-            Value::Keyword(id) => lookup(id),
-            _ => "sans-serif".to_string(),
-        };
-        FontInfo {
-            family,
-            size,
-            weight: 400,
-            width: 100,
-            slant: 0,
-            line_height: size * 1.4,
-            letter_spacing: 0.0,
-            alignment: FontAlignment::Start,
-            underline: false,
-            line_through: false,
-        }
+        element_font_info(self.layer_list.layout_tree.render_tree.doc.as_ref(), node_id)
     }
 
     fn get_parent_brush(&self, node_id: NodeId, css_prop: &StyleProperty, default: Brush) -> Brush {
@@ -308,20 +624,70 @@ impl Painter {
         commands
     }
 
-    fn generate_boxmodel_commands(&self, layout_element: &LayoutElementNode) -> Vec<PaintCommand> {
+    /// Devtools-style inspector overlay: tinted margin/border/padding/content areas, rulers along
+    /// the border box and a `tag width × height` label. See [`InspectorOverlay`].
+    ///
+    /// On tiled backends an element is only painted into the tiles it covers, so the rulers are
+    /// cut off at those tiles; the GPU-scene path draws them across the whole page.
+    fn generate_boxmodel_commands(
+        &self,
+        layout_element: &LayoutElementNode,
+        state: &BrowserState,
+    ) -> Vec<PaintCommand> {
+        let doc = &self.layer_list.layout_tree.render_tree.doc;
+        let name = doc
+            .tag_name(layout_element.dom_node_id)
+            .unwrap_or_else(|| "#text".to_string());
+        let overlay = InspectorOverlay::new(&name, &layout_element.box_model, state.viewport);
+
         let mut commands = Vec::new();
+        for (area, rect) in &overlay.areas {
+            let color = match area {
+                BoxArea::Margin => Color::from_rgba8(246, 178, 107, 168),
+                BoxArea::Border => Color::from_rgba8(255, 229, 153, 168),
+                BoxArea::Padding => Color::from_rgba8(147, 196, 125, 140),
+                BoxArea::Content => Color::from_rgba8(111, 168, 220, 140),
+            };
+            commands.push(PaintCommand::rectangle(
+                Rectangle::new(*rect).with_background(Brush::Solid(color)),
+            ));
+        }
 
-        let brush = Brush::Solid(Color::YELLOW);
-        let r = Rectangle::new(layout_element.box_model.margin_box).with_background(brush);
-        commands.push(PaintCommand::rectangle(r));
+        for ruler in &overlay.rulers {
+            let brush = Brush::Solid(Color::from_rgba8(236, 64, 122, 200));
+            commands.push(PaintCommand::rectangle(Rectangle::new(*ruler).with_background(brush)));
+        }
 
-        let brush = Brush::Solid(Color::GREEN);
-        let r = Rectangle::new(layout_element.box_model.padding_box).with_background(brush);
-        commands.push(PaintCommand::rectangle(r));
+        let brush = Brush::Solid(Color::from_rgba8(36, 36, 36, 230));
+        commands.push(PaintCommand::rectangle(
+            Rectangle::new(overlay.label_rect).with_background(brush),
+        ));
 
-        let brush = Brush::Solid(Color::CYAN);
-        let r = Rectangle::new(layout_element.box_model.content_box).with_background(brush);
-        commands.push(PaintCommand::rectangle(r));
+        let font_info = FontInfo {
+            family: "sans-serif".to_string(),
+            size: 11.0,
+            weight: 400,
+            width: 100,
+            slant: 0,
+            line_height: LABEL_HEIGHT,
+            letter_spacing: 0.0,
+            alignment: FontAlignment::Start,
+            underline: false,
+            line_through: false,
+            lang: None,
+            features: Vec::new(),
+        };
+        let r = overlay.label_rect;
+        let text_rect = Rect::new(r.x + LABEL_PADDING, r.y, r.width - LABEL_PADDING * 2.0, r.height);
+        let shaped = self.shape_text(&overlay.label, &font_info, text_rect.width, text_rect.width);
+        commands.push(PaintCommand::text(Text::new(
+            text_rect,
+            &overlay.label,
+            &font_info,
+            Brush::Solid(Color::WHITE),
+            text_rect.width,
+            shaped,
+        )));
 
         commands
     }
@@ -382,7 +748,10 @@ impl Painter {
                 let r = self.decorate_with_border_and_radius(dom_node_id, r);
                 vec![PaintCommand::rectangle(r)]
             }
-            BackgroundMedia::Svg(media_id) => vec![PaintCommand::svg(media_id, Rectangle::new(border_box))],
+            BackgroundMedia::Svg(media_id) => {
+                let frame = self.media_store.svg_frame(media_id);
+                vec![PaintCommand::svg(media_id, frame, Rectangle::new(border_box))]
+            }
         }
     }
 
@@ -416,7 +785,8 @@ impl Painter {
             }
             ElementContext::Svg(svg_ctx) => {
                 let border_box = layout_element.box_model.border_box;
-                commands.push(PaintCommand::svg(svg_ctx.media_id, Rectangle::new(border_box)));
+                let frame = self.media_store.svg_frame(svg_ctx.media_id);
+                commands.push(PaintCommand::svg(svg_ctx.media_id, frame, Rectangle::new(border_box)));
                 // The SVG painter doesn't draw the element's CSS border/radius, so emit it as a
                 // separate border-only rectangle painted on top of the icon (e.g. the HN logo's
                 // `border:1px white solid`).
@@ -475,6 +845,9 @@ impl Painter {
                     }
                 }
             }
+            ElementContext::Widget(widget) => {
+                commands.extend(self.widget_commands(layout_element, widget, dom_node_id));
+            }
             ElementContext::None => {
                 let (brush, overlay_layers) = self.background_fill(dom_node_id);
                 let border_box = layout_element.box_model.border_box;
@@ -505,6 +878,116 @@ impl Painter {
         commands
     }
 
+    /// Draws a form control (see [`crate::layouter::widget`]). A control CSS gives neither a
+    /// border nor a background gets a default frame, as the platform would draw it.
+    fn widget_commands(
+        &self,
+        layout_element: &LayoutElementNode,
+        widget: &ElementContextWidget,
+        dom_node_id: NodeId,
+    ) -> Vec<PaintCommand> {
+        let mut commands = Vec::new();
+        let border_box = layout_element.box_model.border_box;
+        let content = layout_element.box_model.content_box;
+
+        // Checkboxes and radio buttons are only their mark, square in the content box.
+        if widget.is_toggle() {
+            if let Some(mark) = widget.mark {
+                let side = content.width.min(content.height);
+                let rect = Rect::new(
+                    content.x + (content.width - side) / 2.0,
+                    content.y + (content.height - side) / 2.0,
+                    side,
+                    side,
+                );
+                let frame = self.media_store.svg_frame(mark);
+                commands.push(PaintCommand::svg(mark, frame, Rectangle::new(rect)));
+            }
+            return commands;
+        }
+
+        let (background, _) = self.background_fill(dom_node_id);
+        let transparent = matches!(&background, Brush::Solid(c) if c.a() == 0.0);
+        if !transparent || self.has_border(dom_node_id) {
+            let r = Rectangle::new(border_box)
+                .with_background(background)
+                .with_blend_mode(self.mix_blend_mode(dom_node_id));
+            commands.push(PaintCommand::rectangle(
+                self.decorate_with_border_and_radius(dom_node_id, r),
+            ));
+        } else {
+            let face = if widget.kind == WidgetKind::Button {
+                Color::from_rgb8(0xef, 0xef, 0xef)
+            } else {
+                Color::WHITE
+            };
+            let edge = Brush::solid(Color::from_rgb8(0x76, 0x76, 0x76));
+            let r = Rectangle::new(border_box)
+                .with_background(Brush::solid(face))
+                .with_border(Border::new(
+                    1.0,
+                    BorderStyle::Solid,
+                    [edge.clone(), edge.clone(), edge.clone(), edge],
+                ))
+                .with_radius(Radius::new(2.0));
+            commands.push(PaintCommand::rectangle(r));
+        }
+
+        let mut text_box = content;
+        if widget.kind == WidgetKind::Select {
+            text_box.width = (text_box.width - SELECT_ARROW_WIDTH).max(0.0);
+            if let Some(mark) = widget.mark {
+                let side = 10.0_f64.min(content.height);
+                let rect = Rect::new(
+                    content.x + content.width - (SELECT_ARROW_WIDTH + side) / 2.0,
+                    content.y + (content.height - side) / 2.0,
+                    side,
+                    side,
+                );
+                let frame = self.media_store.svg_frame(mark);
+                commands.push(PaintCommand::svg(mark, frame, Rectangle::new(rect)));
+            }
+        }
+
+        if widget.text.is_empty() || text_box.width <= 0.0 || text_box.height <= 0.0 {
+            return commands;
+        }
+
+        let mut font_info = element_font_info(self.layer_list.layout_tree.render_tree.doc.as_ref(), dom_node_id);
+        if widget.kind == WidgetKind::Button {
+            font_info.alignment = FontAlignment::Center;
+        }
+        let brush = if widget.placeholder {
+            Brush::solid(Color::from_rgb8(0x75, 0x75, 0x75))
+        } else {
+            self.get_brush(dom_node_id, &StyleProperty::Color, Brush::solid(Color::BLACK))
+        };
+        let brush = self.apply_opacity(dom_node_id, brush);
+
+        // Only a textarea wraps; single-line controls centre their line vertically.
+        let (rect, avail_w) = if widget.kind == WidgetKind::TextArea {
+            (text_box, text_box.width)
+        } else {
+            let y = text_box.y + (text_box.height - font_info.line_height) / 2.0;
+            (
+                Rect::new(text_box.x, y, text_box.width, font_info.line_height),
+                1_000_000_000.0,
+            )
+        };
+        let shaped = self.shape_text(&widget.text, &font_info, rect.width, avail_w);
+        commands.push(PaintCommand::PushClip(text_box));
+        commands.push(PaintCommand::text(Text::new(
+            rect,
+            &widget.text,
+            &font_info,
+            brush,
+            avail_w,
+            shaped,
+        )));
+        commands.push(PaintCommand::PopClip);
+        commands
+    }
+
     fn has_border(&self, dom_node_id: NodeId) -> bool {
         let doc = &self.layer_list.layout_tree.render_tree.doc;
         doc.get_style_f32(dom_node_id, &StyleProperty::BorderTopWidth) != 0.0
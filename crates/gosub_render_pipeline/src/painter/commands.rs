@@ -1,3 +1,4 @@
+use crate::common::geo::Rect;
 use crate::common::media::MediaId;
 use crate::painter::commands::rectangle::Rectangle;
 use crate::painter::commands::text::Text;
@@ -23,6 +24,8 @@ pub struct Trbl<T> {
 pub struct PaintSvg {
     pub rect: Rectangle,
     pub media_id: MediaId,
+    /// Frame of an animated SVG, see [`MediaStore::svg_frame`](crate::common::media::MediaStore::svg_frame)
+    pub frame: u64,
 }
 
 #[derive(Clone, Debug)]
@@ -40,6 +43,12 @@ pub enum PaintCommand {
     },
     /// End the most recent [`PaintCommand::PushLayer`] group.
     PopLayer,
+    /// Clip everything up to the matching [`PaintCommand::PopClip`] to `rect` (page coordinates).
+    /// Wraps the commands of elements inside an `overflow` clipping box. Unlike the layer markers
+    /// these appear in per-tile command lists too, so every rasterizer must honour them.
+    PushClip(Rect),
+    /// End the most recent [`PaintCommand::PushClip`].
+    PopClip,
 }
 
 impl PaintCommand {
@@ -47,11 +56,29 @@ impl PaintCommand {
         PaintCommand::Text(text)
     }
 
-    pub fn svg(media_id: MediaId, rect: Rectangle) -> Self {
-        PaintCommand::Svg(PaintSvg { rect, media_id })
+    pub fn svg(media_id: MediaId, frame: u64, rect: Rectangle) -> Self {
+        PaintCommand::Svg(PaintSvg { rect, media_id, frame })
     }
 
     pub fn rectangle(rectangle: Rectangle) -> Self {
         PaintCommand::Rectangle(rectangle)
     }
+
+    /// Moves the command by `(dx, dy)`, e.g. to place a child frame's commands at the frame's
+    /// position in the parent page. Glyph runs are relative to the text rect and move with it.
+    pub fn translate(&mut self, dx: f64, dy: f64) {
+        match self {
+            PaintCommand::Text(text) => {
+                text.rect.x += dx;
+                text.rect.y += dy;
+            }
+            PaintCommand::Rectangle(rectangle) => rectangle.translate(dx, dy),
+            PaintCommand::Svg(svg) => svg.rect.translate(dx, dy),
+            PaintCommand::PushClip(rect) => {
+                rect.x += dx;
+                rect.y += dy;
+            }
+            PaintCommand::PushLayer { .. } | PaintCommand::PopLayer | PaintCommand::PopClip => {}
+        }
+    }
 }
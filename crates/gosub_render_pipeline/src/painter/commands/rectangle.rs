@@ -167,6 +167,12 @@ impl Rectangle {
         self.rect
     }
 
+    /// Moves the rectangle by `(dx, dy)`. Brush tiling is relative to the rect and moves with it.
+    pub fn translate(&mut self, dx: f64, dy: f64) {
+        self.rect.x += dx;
+        self.rect.y += dy;
+    }
+
     pub fn background(&self) -> Option<&Brush> {
         self.background.as_ref()
     }
@@ -0,0 +1,198 @@
+//! Geometry of the element inspector overlay.
+//!
+//! Mirrors what browser devtools draw over a selected element: the margin, border, padding and
+//! content areas each tinted in their own color, a label with the element's name and border-box
+//! size, and guide lines ("rulers") along the border-box edges that run across the whole page.
+//! Everything here is plain geometry; [`crate::painter::Painter`] turns it into paint commands.
+
+use crate::common::geo::Rect;
+use crate::layouter::box_model::BoxModel;
+
+/// Thickness of a ruler line in CSS pixels.
+pub const RULER_WIDTH: f64 = 1.0;
+/// Height of the dimension label.
+pub const LABEL_HEIGHT: f64 = 18.0;
+/// Horizontal padding inside the dimension label.
+pub const LABEL_PADDING: f64 = 6.0;
+/// Rough advance of one label character at the label font size, used to size the label box.
+const LABEL_CHAR_WIDTH: f64 = 6.5;
+
+/// Which box-model area a tinted rect belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoxArea {
+    Margin,
+    Border,
+    Padding,
+    Content,
+}
+
+/// The overlay for one element, in page coordinates.
+#[derive(Debug, Clone)]
+pub struct InspectorOverlay {
+    /// Tinted areas. Margin, border and padding are rings around the next inner box, so the
+    /// tints never overlap.
+    pub areas: Vec<(BoxArea, Rect)>,
+    /// Label background box.
+    pub label_rect: Rect,
+    /// Label text, e.g. `div 320 × 48`.
+    pub label: String,
+    /// Horizontal and vertical guide lines at the border-box edges.
+    pub rulers: Vec<Rect>,
+}
+
+impl InspectorOverlay {
+    /// Builds the overlay for an element named `name` with the given box model. `page` bounds the
+    /// rulers and keeps the label on the page.
+    pub fn new(name: &str, box_model: &BoxModel, page: Rect) -> Self {
+        let mut areas = Vec::new();
+        for r in ring(box_model.margin_box, box_model.border_box) {
+            areas.push((BoxArea::Margin, r));
+        }
+        for r in ring(box_model.border_box, box_model.padding_box) {
+            areas.push((BoxArea::Border, r));
+        }
+        for r in ring(box_model.padding_box, box_model.content_box) {
+            areas.push((BoxArea::Padding, r));
+        }
+        if !is_empty(box_model.content_box) {
+            areas.push((BoxArea::Content, box_model.content_box));
+        }
+
+        let b = box_model.border_box;
+        let label = format!("{} {} × {}", name, format_px(b.width), format_px(b.height));
+        let label_rect = label_rect(&label, b, page);
+
+        let rulers = vec![
+            Rect::new(page.x, b.y, page.width, RULER_WIDTH),
+            Rect::new(page.x, b.y + b.height - RULER_WIDTH, page.width, RULER_WIDTH),
+            Rect::new(b.x, page.y, RULER_WIDTH, page.height),
+            Rect::new(b.x + b.width - RULER_WIDTH, page.y, RULER_WIDTH, page.height),
+        ];
+
+        InspectorOverlay {
+            areas,
+            label_rect,
+            label,
+            rulers,
+        }
+    }
+}
+
+fn is_empty(r: Rect) -> bool {
+    r.width <= 0.0 || r.height <= 0.0
+}
+
+/// The area of `outer` not covered by `inner`, as up to four non-overlapping strips (top and
+/// bottom span the full width, left and right fill the gap in between).
+pub fn ring(outer: Rect, inner: Rect) -> Vec<Rect> {
+    if is_empty(outer) {
+        return Vec::new();
+    }
+
+    let top = (inner.y - outer.y).clamp(0.0, outer.height);
+    let bottom = ((outer.y + outer.height) - (inner.y + inner.height)).clamp(0.0, outer.height - top);
+    let left = (inner.x - outer.x).clamp(0.0, outer.width);
+    let right = ((outer.x + outer.width) - (inner.x + inner.width)).clamp(0.0, outer.width - left);
+    let middle = outer.height - top - bottom;
+
+    let strips = [
+        Rect::new(outer.x, outer.y, outer.width, top),
+        Rect::new(outer.x, outer.y + outer.height - bottom, outer.width, bottom),
+        Rect::new(outer.x, outer.y + top, left, middle),
+        Rect::new(outer.x + outer.width - right, outer.y + top, right, middle),
+    ];
+    strips.into_iter().filter(|r| !is_empty(*r)).collect()
+}
+
+/// Places the label just above the border box, or just below it when there is no room above,
+/// and shifts it left when it would run off the page.
+fn label_rect(label: &str, border_box: Rect, page: Rect) -> Rect {
+    let width = label.chars().count() as f64 * LABEL_CHAR_WIDTH + LABEL_PADDING * 2.0;
+    let y = if border_box.y - LABEL_HEIGHT >= page.y {
+        border_box.y - LABEL_HEIGHT
+    } else {
+        border_box.y + border_box.height
+    };
+    let max_x = (page.x + page.width - width).max(page.x);
+    let x = border_box.x.clamp(page.x, max_x);
+    Rect::new(x, y, width, LABEL_HEIGHT)
+}
+
+/// Formats a pixel size the way devtools do: whole numbers without decimals, others with two.
+fn format_px(v: f64) -> String {
+    if (v - v.round()).abs() < 0.005 {
+        format!("{}", v.round() as i64)
+    } else {
+        format!("{v:.2}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect_tuple(r: Rect) -> (f64, f64, f64, f64) {
+        (r.x, r.y, r.width, r.height)
+    }
+
+    fn box_model() -> BoxModel {
+        BoxModel {
+            margin_box: Rect::new(0.0, 50.0, 120.0, 70.0),
+            border_box: Rect::new(10.0, 60.0, 100.0, 50.0),
+            padding_box: Rect::new(12.0, 62.0, 96.0, 46.0),
+            content_box: Rect::new(20.0, 70.0, 80.0, 30.0),
+            ..BoxModel::ZERO
+        }
+    }
+
+    #[test]
+    fn ring_strips_cover_the_gap() {
+        let strips = ring(Rect::new(0.0, 0.0, 100.0, 50.0), Rect::new(10.0, 5.0, 80.0, 40.0));
+        let tuples: Vec<_> = strips.into_iter().map(rect_tuple).collect();
+        assert_eq!(
+            tuples,
+            vec![
+                (0.0, 0.0, 100.0, 5.0),
+                (0.0, 45.0, 100.0, 5.0),
+                (0.0, 5.0, 10.0, 40.0),
+                (90.0, 5.0, 10.0, 40.0),
+            ]
+        );
+
+        // Identical boxes leave nothing to tint
+        assert!(ring(Rect::new(0.0, 0.0, 10.0, 10.0), Rect::new(0.0, 0.0, 10.0, 10.0)).is_empty());
+    }
+
+    #[test]
+    fn overlay_areas_and_label() {
+        let page = Rect::new(0.0, 0.0, 800.0, 2000.0);
+        let overlay = InspectorOverlay::new("div", &box_model(), page);
+
+        let count = |area| overlay.areas.iter().filter(|(a, _)| *a == area).count();
+        assert_eq!(count(BoxArea::Margin), 4);
+        assert_eq!(count(BoxArea::Border), 4);
+        assert_eq!(count(BoxArea::Padding), 4);
+        assert_eq!(count(BoxArea::Content), 1);
+
+        assert_eq!(overlay.label, "div 100 × 50");
+        // Room above the border box: label sits on top of it
+        assert_eq!(overlay.label_rect.y, 60.0 - LABEL_HEIGHT);
+        assert_eq!(overlay.label_rect.x, 10.0);
+
+        // Rulers span the page along the border-box edges
+        assert_eq!(rect_tuple(overlay.rulers[0]), (0.0, 60.0, 800.0, RULER_WIDTH));
+        assert_eq!(rect_tuple(overlay.rulers[2]), (10.0, 0.0, RULER_WIDTH, 2000.0));
+    }
+
+    #[test]
+    fn label_flips_below_at_page_top() {
+        let mut bm = box_model();
+        bm.border_box = Rect::new(790.0, 0.0, 10.5, 20.0);
+        let overlay = InspectorOverlay::new("span", &bm, Rect::new(0.0, 0.0, 800.0, 600.0));
+
+        assert_eq!(overlay.label, "span 10.50 × 20");
+        assert_eq!(overlay.label_rect.y, 20.0);
+        // Pushed back onto the page horizontally
+        assert!(overlay.label_rect.x + overlay.label_rect.width <= 800.0);
+    }
+}
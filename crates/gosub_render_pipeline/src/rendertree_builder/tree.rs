@@ -98,6 +98,11 @@ impl RenderTree {
 
 const INVISIBLE_ELEMENTS: [&str; 6] = ["head", "style", "script", "meta", "link", "title"];
 
+/// Elements whose DOM children don't become boxes: an iframe's content comes from a child
+/// document (the children are fallback markup), an inline `<svg>` draws its own subtree, and a
+/// `<select>` or `<textarea>` is drawn as a form control showing its options or text.
+const EMBEDDING_ELEMENTS: [&str; 4] = ["iframe", "svg", "select", "textarea"];
+
 impl RenderTree {
     /// Dump each element's computed CSS to JSON: an array sorted by node_id, of
     /// `{"node_id": 5, "tag": "p", "id": "", "class": "foo", "styles": {"color": "red", ...}}`.
@@ -206,7 +211,11 @@ impl RenderTree {
                         results.push(None);
                         continue;
                     }
-                    let children = self.doc.children(node_id);
+                    let embeds = self
+                        .doc
+                        .tag_name(node_id)
+                        .is_some_and(|tag| EMBEDDING_ELEMENTS.contains(&tag.as_str()));
+                    let children = if embeds { Vec::new() } else { self.doc.children(node_id) };
                     let num_children = children.len();
                     stack.push(Frame::Collect { node_id, num_children });
                     for child_id in children.into_iter().rev() {
@@ -110,6 +110,96 @@ mod rendertree_from_engine {
         }
     }
 
+    #[test]
+    fn iframe_fallback_content_is_not_rendered() {
+        let html = r#"<html><body><iframe src="child.html">No frames</iframe></body></html>"#;
+
+        let rt = parse_to_rendertree(html);
+        let doc_ref = rt.doc.clone();
+        let mut saw_iframe = false;
+        for render_id in rt.arena.keys() {
+            let node_id = gosub_shared::node::NodeId::from(*render_id);
+            if doc_ref.tag_name(node_id).as_deref() == Some("iframe") {
+                saw_iframe = true;
+                assert!(
+                    rt.arena[render_id].children.is_empty(),
+                    "fallback content must not be rendered"
+                );
+            }
+        }
+        assert!(saw_iframe, "the iframe itself is rendered");
+    }
+
+    #[test]
+    fn form_controls_show_their_dom_state() {
+        use crate::layouter::widget::{ElementContextWidget, WidgetKind};
+
+        let html = r#"<html><body>
+            <input value="hello" size="10">
+            <input placeholder="Search">
+            <input type="password" value="abc">
+            <input type="checkbox" checked>
+            <input type="submit">
+            <textarea rows="4">Some text</textarea>
+            <select><option>One</option><optgroup><option selected>Two</option></optgroup></select>
+        </body></html>"#;
+
+        let rt = parse_to_rendertree(html);
+        let doc = rt.doc.as_ref();
+        let mut ids: Vec<_> = rt
+            .arena
+            .keys()
+            .map(|render_id| (gosub_shared::node::NodeId::from(*render_id), *render_id))
+            .collect();
+        ids.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        let mut widgets = Vec::new();
+        for (id, render_id) in ids {
+            let tag = doc.tag_name(id).unwrap_or_default();
+            if !matches!(tag.as_str(), "input" | "textarea" | "select") {
+                continue;
+            }
+            assert!(
+                rt.arena[&render_id].children.is_empty(),
+                "<{tag}> children are not boxes"
+            );
+            widgets.push(ElementContextWidget::from_element(doc, id, 20.0, |s| {
+                s.chars().count() as f64 * 8.0
+            }));
+        }
+
+        let shown: Vec<_> = widgets
+            .iter()
+            .map(|w| w.as_ref().map(|w| (w.kind, w.text.as_str(), w.placeholder, w.checked)))
+            .collect();
+        assert_eq!(
+            shown,
+            vec![
+                Some((WidgetKind::TextField, "hello", false, false)),
+                Some((WidgetKind::TextField, "Search", true, false)),
+                Some((WidgetKind::TextField, "\u{2022}\u{2022}\u{2022}", false, false)),
+                Some((WidgetKind::Checkbox, "", false, true)),
+                Some((WidgetKind::Button, "Submit", false, false)),
+                Some((WidgetKind::TextArea, "Some text", false, false)),
+                Some((WidgetKind::Select, "Two", false, false)),
+            ]
+        );
+
+        let field = widgets[0].as_ref().unwrap();
+        assert_eq!(
+            (field.dimension.width, field.dimension.height),
+            (80.0, 20.0),
+            "size=10 zeros"
+        );
+        let textarea = widgets[5].as_ref().unwrap();
+        assert_eq!(textarea.dimension.height, 80.0, "four rows");
+        let select = widgets[6].as_ref().unwrap();
+        assert!(
+            select.mark.is_none() && select.mark_markup().is_some(),
+            "the arrow is loaded by the layouter"
+        );
+    }
+
     #[test]
     fn css_dimensions_are_extracted() {
         let html = r#"
@@ -150,6 +240,43 @@ mod rendertree_from_engine {
         );
     }
 
+    #[test]
+    fn math_lengths_resolve_with_font_size_and_basis() {
+        let html = r#"
+            <html>
+            <head>
+                <style>
+                    #box { font-size: 10px; width: calc(100% - 20px); height: min(30px, 2em); }
+                </style>
+            </head>
+            <body><div id="box">content</div></body>
+            </html>
+        "#;
+
+        use crate::common::document::pipeline_doc::PipelineDocument;
+        use crate::common::document::style::{calc_ptr, resolve_calc, StyleProperty, Unit, Value};
+
+        let mut doc = html_compile::<Config>(html);
+        let ua = Css3System::load_default_useragent_stylesheet();
+        doc.add_stylesheet(ua);
+
+        let adapter = GosubDocumentAdapter::<Config>::new(Arc::new(doc));
+        let root = adapter.doc.root();
+        let id = find_node_by_id_attr(&adapter.doc, root, "box").unwrap();
+
+        let height = adapter.get_style(id, &StyleProperty::Height);
+        assert!(
+            matches!(height, Value::Unit(h, Unit::Px) if (h - 20.0).abs() < 0.01),
+            "2em at 10px is smaller than 30px, got {height:?}"
+        );
+
+        let Value::Calc(width) = adapter.get_style(id, &StyleProperty::Width) else {
+            panic!("calc(100% - 20px) needs the containing block");
+        };
+        assert_eq!(resolve_calc(calc_ptr(width), 300.0), 280.0);
+        assert_eq!(resolve_calc(std::ptr::null(), 300.0), 0.0);
+    }
+
     #[test]
     fn letter_spacing_em_resolves_to_px_and_inherits() {
         let html = r#"
@@ -364,6 +491,193 @@ mod rendertree_from_engine {
         assert_eq!(url_of(plain), "none", "plain element should be `none`");
     }
 
+    #[test]
+    fn inline_svg_is_styled_by_the_document() {
+        use crate::common::document::inline_svg::inline_svg_markup;
+
+        let html = r#"
+            <html>
+            <head>
+                <style>
+                    .icon { fill: red; }
+                    .icon .hidden { display: none; }
+                    #outline { stroke: rgb(0, 0, 255); stroke-width: 3px; }
+                </style>
+            </head>
+            <body style="color: green">
+                <svg id="icon" class="icon" width="10" height="10" viewBox="0 0 10 10">
+                    <rect id="outline" width="10" height="10" fill="black"/>
+                    <circle class="hidden" cx="5" cy="5" r="2"/>
+                    <text>a &lt; b</text>
+                </svg>
+            </body>
+            </html>
+        "#;
+
+        let mut doc = html_compile::<Config>(html);
+        let ua = Css3System::load_default_useragent_stylesheet();
+        doc.add_stylesheet(ua);
+        let adapter = GosubDocumentAdapter::<Config>::new(Arc::new(doc));
+        let root = adapter.doc.root();
+        let svg = find_node_by_id_attr(&adapter.doc, root, "icon").expect("find the svg");
+
+        let markup = inline_svg_markup(&adapter, svg);
+        assert!(markup.contains("fill:rgb(255, 0, 0)"), "stylesheet fill: {markup}");
+        assert!(markup.contains("stroke:rgb(0, 0, 255)"), "stylesheet stroke: {markup}");
+        assert!(
+            markup.contains("color:rgb(0, 128, 0)"),
+            "inherited from the body: {markup}"
+        );
+        assert!(!markup.contains("<circle"), "display: none is left out: {markup}");
+        assert!(markup.contains("a &lt; b"), "text is escaped: {markup}");
+
+        resvg::usvg::Tree::from_str(&markup, &resvg::usvg::Options::default()).expect("usvg reads the markup");
+
+        // The subtree is painted by usvg, not laid out as boxes.
+        let rt = parse_to_rendertree(html);
+        let svg_render_id = rt
+            .arena
+            .keys()
+            .find(|id| rt.doc.tag_name(gosub_shared::node::NodeId::from(**id)).as_deref() == Some("svg"))
+            .expect("the svg is rendered");
+        assert!(rt.arena[svg_render_id].children.is_empty());
+    }
+
+    #[test]
+    fn inline_svg_ids_lead_back_to_the_dom() {
+        use crate::common::document::inline_svg::{inline_svg_element, inline_svg_markup, inline_svg_pointer_events};
+        use crate::common::document::pipeline_doc::PipelineDocument;
+
+        let html = r#"
+            <html>
+            <head>
+                <style>
+                    .decoration { pointer-events: none; }
+                </style>
+            </head>
+            <body>
+                <svg id="map" width="40" height="20">
+                    <a href="/north"><rect id="north" width="20" height="20"/></a>
+                    <g pointer-events="stroke">
+                        <rect class="decoration" x="20" width="20" height="20"/>
+                        <circle cx="30" cy="10" r="5"/>
+                    </g>
+                </svg>
+            </body>
+            </html>
+        "#;
+
+        let mut doc = html_compile::<Config>(html);
+        let ua = Css3System::load_default_useragent_stylesheet();
+        doc.add_stylesheet(ua);
+        let adapter = GosubDocumentAdapter::<Config>::new(Arc::new(doc));
+        let root = adapter.doc.root();
+        let svg = find_node_by_id_attr(&adapter.doc, root, "map").expect("find the svg");
+        let north = find_node_by_id_attr(&adapter.doc, root, "north").expect("find #north");
+
+        let markup = inline_svg_markup(&adapter, svg);
+        let tree =
+            resvg::usvg::Tree::from_str(&markup, &resvg::usvg::Options::default()).expect("usvg reads the markup");
+
+        // The elements usvg keeps, the `<a>` as a group of its own, come out with an id that
+        // maps back to their node.
+        let mut tags = Vec::new();
+        let mut pointer_events = Vec::new();
+        let mut stack = vec![tree.root()];
+        while let Some(group) = stack.pop() {
+            for child in group.children() {
+                if let resvg::usvg::Node::Group(inner) = child {
+                    stack.push(inner);
+                }
+                if child.id().is_empty() {
+                    continue;
+                }
+                let node = inline_svg_element(&adapter, svg, child.id()).expect("the id maps to a node");
+                tags.push(adapter.tag_name(node).unwrap_or_default());
+                pointer_events.push(inline_svg_pointer_events(&adapter, svg, node));
+            }
+        }
+        tags.sort();
+        assert_eq!(tags, ["a", "circle", "g", "rect", "rect"]);
+        assert_eq!(inline_svg_element(&adapter, svg, "north"), Some(north));
+        assert_eq!(inline_svg_element(&adapter, svg, "south"), None);
+
+        // The cascade wins over presentation attributes, which are inherited like it.
+        pointer_events.sort();
+        assert_eq!(pointer_events, ["auto", "auto", "none", "stroke", "stroke"]);
+    }
+
+    #[test]
+    fn inline_svg_keeps_filters_clip_paths_and_masks() {
+        use crate::common::document::inline_svg::inline_svg_markup;
+
+        let html = r#"
+            <html>
+            <head>
+                <style>
+                    #blurred { filter: url(#gray); }
+                    #clipped { clip-path: url(#left); }
+                </style>
+            </head>
+            <body>
+                <svg id="art" width="20" height="10" viewBox="0 0 20 10">
+                    <defs>
+                        <filter id="gray">
+                            <feGaussianBlur stdDeviation="0.5"/>
+                            <feColorMatrix type="saturate" values="0"/>
+                        </filter>
+                        <clipPath id="left" clipPathUnits="userSpaceOnUse"><rect width="5" height="10"/></clipPath>
+                        <mask id="opaque"><rect width="20" height="10" fill="white"/></mask>
+                    </defs>
+                    <rect id="blurred" width="10" height="10" fill="red"/>
+                    <rect id="clipped" width="10" height="10" fill="blue"/>
+                    <rect mask="url(#opaque)" x="10" width="10" height="10" fill="green"/>
+                </svg>
+            </body>
+            </html>
+        "#;
+
+        let mut doc = html_compile::<Config>(html);
+        let ua = Css3System::load_default_useragent_stylesheet();
+        doc.add_stylesheet(ua);
+        let adapter = GosubDocumentAdapter::<Config>::new(Arc::new(doc));
+        let root = adapter.doc.root();
+        let svg = find_node_by_id_attr(&adapter.doc, root, "art").expect("find the svg");
+
+        let markup = inline_svg_markup(&adapter, svg);
+        for expected in [
+            "<feGaussianBlur",
+            "stdDeviation=",
+            "<clipPath",
+            "clipPathUnits=",
+            "filter:url(#gray)",
+            "clip-path:url(#left)",
+        ] {
+            assert!(markup.contains(expected), "{expected} in {markup}");
+        }
+
+        let tree =
+            resvg::usvg::Tree::from_str(&markup, &resvg::usvg::Options::default()).expect("usvg reads the markup");
+        assert_eq!(tree.filters().len(), 1);
+        assert_eq!(tree.clip_paths().len(), 1);
+        assert_eq!(tree.masks().len(), 1);
+
+        let mut pixmap = resvg::tiny_skia::Pixmap::new(20, 10).expect("pixmap");
+        resvg::render(&tree, resvg::usvg::Transform::identity(), &mut pixmap.as_mut());
+        let rgba = |x: u32| {
+            let px = pixmap.pixel(x, 5).expect("pixel");
+            [px.red(), px.green(), px.blue(), px.alpha()]
+        };
+        assert_eq!(rgba(2), [0, 0, 255, 255], "the blue rect left of the clip edge");
+        let [r, g, b, a] = rgba(7);
+        assert!(
+            r == g && g == b && a > 200,
+            "the red rect, grayed, right of it: {:?}",
+            rgba(7)
+        );
+        assert_eq!(rgba(15), [0, 128, 0, 255], "the masked rect");
+    }
+
     fn find_node_by_id_attr(
         doc: &DocumentImpl<Config>,
         node: gosub_shared::node::NodeId,
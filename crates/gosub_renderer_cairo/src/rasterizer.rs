@@ -76,6 +76,15 @@ impl Rasterable for CairoRasterizer {
                         // The tile path applies layer opacity/anchor at composite, so these
                         // scene-only group markers never appear here - ignore them.
                         PaintCommand::PushLayer { .. } | PaintCommand::PopLayer => {}
+                        PaintCommand::PushClip(clip) => {
+                            _ = cr.save();
+                            cr.new_path();
+                            cr.rectangle(clip.x - tile.rect.x, clip.y - tile.rect.y, clip.width, clip.height);
+                            cr.clip();
+                        }
+                        PaintCommand::PopClip => {
+                            _ = cr.restore();
+                        }
                         PaintCommand::Svg(command) => {
                             svg::do_paint_svg(&cr.clone(), tile, &command.rect, command.media_id, media_store, dpr);
                         }
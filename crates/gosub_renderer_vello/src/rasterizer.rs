@@ -73,6 +73,13 @@ pub(crate) fn paint_commands_to_scene(
                     cur = prev;
                 }
             }
+            PaintCommand::PushClip(clip) => {
+                let rect = Rect::new(clip.x, clip.y, clip.x + clip.width, clip.y + clip.height);
+                scene.push_clip_layer(Fill::NonZero, cur, &rect);
+            }
+            PaintCommand::PopClip => {
+                scene.pop_layer();
+            }
             PaintCommand::Svg(command) => {
                 svg::do_paint_svg(scene, command.media_id, &command.rect, cur, media_store);
             }
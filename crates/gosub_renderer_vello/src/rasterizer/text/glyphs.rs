@@ -96,6 +96,8 @@ mod tests {
             alignment: FontAlignment::Start,
             underline: true,
             line_through: false,
+            lang: None,
+            features: Vec::new(),
         };
         let mut style = TextStyle::new("sans-serif", 24.0);
         style.line_height = Some(28.0);
@@ -79,6 +79,51 @@ impl Easing {
             Easing::Custom(f) => f(t),
         }
     }
+
+    /// Parses a CSS `<easing-function>`: the named curves, `linear`, `cubic-bezier()`, `steps()`
+    /// and `step-start`/`step-end`. `None` for anything else, including `linear()` with stops.
+    pub fn from_css(value: &str) -> Option<Easing> {
+        let value = value.trim();
+        let named = [
+            ("linear", Easing::Linear),
+            ("ease", Easing::Ease),
+            ("ease-in", Easing::EaseIn),
+            ("ease-out", Easing::EaseOut),
+            ("ease-in-out", Easing::EaseInOut),
+            ("step-start", Easing::Steps(1, StepPosition::JumpStart)),
+            ("step-end", Easing::Steps(1, StepPosition::JumpEnd)),
+        ];
+        if let Some((_, easing)) = named.iter().find(|(name, _)| name.eq_ignore_ascii_case(value)) {
+            return Some(easing.clone());
+        }
+
+        let (name, args) = value.strip_suffix(')')?.split_once('(')?;
+        let args: Vec<&str> = args.split(',').map(str::trim).collect();
+        match name.trim() {
+            "cubic-bezier" => {
+                let [x1, y1, x2, y2] = args.as_slice() else {
+                    return None;
+                };
+                let (x1, x2) = (x1.parse::<f32>().ok()?, x2.parse::<f32>().ok()?);
+                if !(0.0..=1.0).contains(&x1) || !(0.0..=1.0).contains(&x2) {
+                    return None;
+                }
+                Some(Easing::CubicBezier(x1, y1.parse().ok()?, x2, y2.parse().ok()?))
+            }
+            "steps" => {
+                let n = args.first()?.parse::<u32>().ok().filter(|n| *n > 0)?;
+                let position = match args.get(1).copied() {
+                    None | Some("end" | "jump-end") => StepPosition::JumpEnd,
+                    Some("start" | "jump-start") => StepPosition::JumpStart,
+                    Some("jump-none") if n > 1 => StepPosition::JumpNone,
+                    Some("jump-both") => StepPosition::JumpBoth,
+                    Some(_) => return None,
+                };
+                Some(Easing::Steps(n, position))
+            }
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Debug for Easing {
@@ -530,6 +575,29 @@ mod tests {
     }
 
     /// Elastic overshoots above 1.0 somewhere in the middle before settling.
+    #[test]
+    fn parses_css_easing_functions() {
+        assert!(matches!(Easing::from_css("ease-in-out"), Some(Easing::EaseInOut)));
+        assert!(matches!(
+            Easing::from_css("cubic-bezier(0.1, 0.7, 1.0, 0.1)"),
+            Some(Easing::CubicBezier(..))
+        ));
+        assert!(matches!(
+            Easing::from_css("steps(4, jump-start)"),
+            Some(Easing::Steps(4, StepPosition::JumpStart))
+        ));
+        assert!(matches!(
+            Easing::from_css("step-end"),
+            Some(Easing::Steps(1, StepPosition::JumpEnd))
+        ));
+        assert!(
+            Easing::from_css("cubic-bezier(2, 0, 1, 1)").is_none(),
+            "x outside [0, 1]"
+        );
+        assert!(Easing::from_css("steps(1, jump-none)").is_none());
+        assert!(Easing::from_css("wobbly").is_none());
+    }
+
     #[test]
     fn elastic_overshoots() {
         let e = Easing::Elastic;
@@ -9,9 +9,70 @@
 //!
 //! [`spawn`] and [`spawn_from`] are fire-and-forget executors on top of the same split:
 //! `wasm_bindgen_futures::spawn_local` on wasm, a dedicated thread running `block_on` on
-//! native.
+//! native. [`spawn_with_handle`] also hands back the future's output.
+//!
+//! Native code can opt into a work-stealing [`ThreadPool`] instead of a thread per future by
+//! calling [`init_thread_pool`] once at startup; from then on [`spawn`] and [`spawn_with_handle`]
+//! run their futures on the pool, so decoding and raster work overlap on multicore machines
+//! without a thread for each.
+
+#[cfg(not(target_arch = "wasm32"))]
+mod thread_pool;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub use thread_pool::ThreadPool;
+
+use futures::channel::oneshot;
 use std::future::Future;
+use std::pin::Pin;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::OnceLock;
+use std::task::{Context, Poll};
+
+/// The output of a spawned task, as a future. Resolves to `None` when the task panicked or was
+/// dropped before completing.
+pub struct TaskHandle<T> {
+    receiver: oneshot::Receiver<T>,
+}
+
+impl<T> TaskHandle<T> {
+    fn new(receiver: oneshot::Receiver<T>) -> Self {
+        Self { receiver }
+    }
+}
+
+impl<T> Future for TaskHandle<T> {
+    type Output = Option<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.receiver).poll(cx).map(Result::ok)
+    }
+}
+
+/// The pool [`spawn`] runs futures on, once [`init_thread_pool`] set it up
+#[cfg(not(target_arch = "wasm32"))]
+static THREAD_POOL: OnceLock<ThreadPool> = OnceLock::new();
+
+/// Runs spawned futures on a work-stealing pool of `threads` workers (a thread per core when
+/// `None`) from now on. Only the first call sets up a pool; it lives until the process exits.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn init_thread_pool(threads: Option<usize>) -> std::io::Result<&'static ThreadPool> {
+    if let Some(pool) = THREAD_POOL.get() {
+        return Ok(pool);
+    }
+    let pool = match threads {
+        Some(threads) => ThreadPool::with_threads(threads)?,
+        None => ThreadPool::new()?,
+    };
+    // Lost a race with another caller: theirs is kept, and ours stops its threads on drop.
+    Ok(THREAD_POOL.get_or_init(|| pool))
+}
+
+/// The pool set up by [`init_thread_pool`], if any
+#[cfg(not(target_arch = "wasm32"))]
+pub fn thread_pool() -> Option<&'static ThreadPool> {
+    THREAD_POOL.get()
+}
 
 /// `Send` on native targets; no bound at all on wasm32.
 #[cfg(not(target_arch = "wasm32"))]
@@ -46,8 +107,8 @@ impl<T: WasmNotSync + WasmNotSend> WasmNotSendSync for T {}
 
 /// Spawn a future and let it run to completion in the background (fire-and-forget).
 ///
-/// On wasm32 the future is queued on the JS event loop; on native it gets a dedicated
-/// thread that blocks on it.
+/// On wasm32 the future is queued on the JS event loop; on native it runs on the pool set up by
+/// [`init_thread_pool`], or without one gets a dedicated thread that blocks on it.
 pub fn spawn<F: Future<Output = ()> + WasmNotSend + 'static>(f: F) {
     #[cfg(target_arch = "wasm32")]
     {
@@ -56,14 +117,32 @@ pub fn spawn<F: Future<Output = ()> + WasmNotSend + 'static>(f: F) {
 
     #[cfg(not(target_arch = "wasm32"))]
     {
-        //TODO: this should be done with a thread pool
-        std::thread::spawn(|| {
-            futures::executor::block_on(f);
-        });
+        match THREAD_POOL.get() {
+            Some(pool) => pool.spawn(f),
+            None => {
+                std::thread::spawn(|| {
+                    futures::executor::block_on(f);
+                });
+            }
+        }
     }
 }
 
-/// Like [`spawn`], but takes a closure that *creates* the future.
+/// Like [`spawn`], but returns a handle that resolves to the future's output.
+pub fn spawn_with_handle<F>(f: F) -> TaskHandle<F::Output>
+where
+    F: Future + WasmNotSend + 'static,
+    F::Output: WasmNotSend + 'static,
+{
+    let (sender, receiver) = oneshot::channel();
+    spawn(async move {
+        let _ = sender.send(f.await);
+    });
+    TaskHandle::new(receiver)
+}
+
+/// Like [`spawn`], but takes a closure that *creates* the future. Always runs on a thread of its
+/// own on native, as the future may not move between the pool's threads.
 ///
 /// Only the closure has to be `Send` (on native): it is moved to the spawned thread and the
 /// future is constructed there, so the future itself may be `!Send`. Use this for async work
@@ -0,0 +1,295 @@
+//! A multi-threaded, work-stealing executor for `Send` futures.
+//!
+//! Every worker thread owns a deque of runnable tasks. Tasks spawned or woken on a worker go to
+//! its own deque, everything else to a shared injector queue. A worker out of work takes a batch
+//! from the injector, and failing that steals from the other workers, so long-running decode or
+//! raster work on one thread doesn't hold up the tasks queued behind it.
+
+use super::TaskHandle;
+use crossbeam_deque::{Injector, Stealer, Worker};
+use futures::channel::oneshot;
+use futures::future::BoxFuture;
+use futures::task::{waker_ref, ArcWake};
+use parking_lot::{Condvar, Mutex};
+use std::cell::RefCell;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::thread::JoinHandle;
+
+/// Threads used when none are asked for and the available parallelism is unknown
+const FALLBACK_THREADS: usize = 4;
+
+static NEXT_POOL_ID: AtomicUsize = AtomicUsize::new(1);
+
+thread_local! {
+    /// The pool id and local deque of the worker running on this thread
+    static LOCAL: RefCell<Option<(usize, Worker<Arc<Task>>)>> = const { RefCell::new(None) };
+}
+
+/// A spawned future and the pool it runs on
+struct Task {
+    future: Mutex<Option<BoxFuture<'static, ()>>>,
+    /// Set while the task sits in a queue, so repeated wakes queue it once
+    scheduled: AtomicBool,
+    pool: Arc<Shared>,
+}
+
+impl ArcWake for Task {
+    fn wake_by_ref(task: &Arc<Self>) {
+        if !task.scheduled.swap(true, Ordering::AcqRel) {
+            task.pool.schedule(Arc::clone(task));
+        }
+    }
+}
+
+/// State shared by the pool's threads and handles
+struct Shared {
+    id: usize,
+    injector: Injector<Arc<Task>>,
+    stealers: Vec<Stealer<Arc<Task>>>,
+    /// Tasks in any of the queues; sleeping workers wait for it to rise
+    queued: AtomicUsize,
+    sleep: Mutex<()>,
+    wakeup: Condvar,
+    shutdown: AtomicBool,
+}
+
+impl Shared {
+    fn schedule(&self, task: Arc<Task>) {
+        if self.shutdown.load(Ordering::Acquire) {
+            return;
+        }
+        let task = LOCAL.with(|local| match &*local.borrow() {
+            Some((id, worker)) if *id == self.id => {
+                worker.push(task);
+                None
+            }
+            _ => Some(task),
+        });
+        if let Some(task) = task {
+            self.injector.push(task);
+        }
+
+        self.queued.fetch_add(1, Ordering::AcqRel);
+        // Taking the lock orders this with a worker checking `queued` before it waits.
+        drop(self.sleep.lock());
+        self.wakeup.notify_one();
+    }
+
+    /// The next task for the worker owning `local`: its own first, then the injector's, then
+    /// the other workers'
+    fn find_task(&self, local: &Worker<Arc<Task>>, index: usize) -> Option<Arc<Task>> {
+        if let Some(task) = local.pop() {
+            return Some(task);
+        }
+        loop {
+            let mut retry = false;
+            match self.injector.steal_batch_and_pop(local) {
+                crossbeam_deque::Steal::Success(task) => return Some(task),
+                crossbeam_deque::Steal::Retry => retry = true,
+                crossbeam_deque::Steal::Empty => {}
+            }
+            let others = self.stealers.len();
+            for offset in 1..others {
+                match self.stealers[(index + offset) % others].steal() {
+                    crossbeam_deque::Steal::Success(task) => return Some(task),
+                    crossbeam_deque::Steal::Retry => retry = true,
+                    crossbeam_deque::Steal::Empty => {}
+                }
+            }
+            if !retry {
+                return None;
+            }
+        }
+    }
+
+    fn run_worker(self: Arc<Self>, local: Worker<Arc<Task>>, index: usize) {
+        LOCAL.with(|slot| *slot.borrow_mut() = Some((self.id, local)));
+        while !self.shutdown.load(Ordering::Acquire) {
+            let task = LOCAL.with(|slot| {
+                let slot = slot.borrow();
+                slot.as_ref().and_then(|(_, local)| self.find_task(local, index))
+            });
+            match task {
+                Some(task) => {
+                    self.queued.fetch_sub(1, Ordering::AcqRel);
+                    run(&task);
+                }
+                None => {
+                    let mut guard = self.sleep.lock();
+                    if self.queued.load(Ordering::Acquire) == 0 && !self.shutdown.load(Ordering::Acquire) {
+                        self.wakeup.wait(&mut guard);
+                    }
+                }
+            }
+        }
+        LOCAL.with(|slot| slot.borrow_mut().take());
+    }
+}
+
+/// Polls `task` once. A panicking task is dropped without taking its worker down.
+fn run(task: &Arc<Task>) {
+    let mut slot = task.future.lock();
+    // Cleared before polling: a wake during the poll must queue the task again.
+    task.scheduled.store(false, Ordering::Release);
+    let Some(mut future) = slot.take() else {
+        return;
+    };
+    let waker = waker_ref(task);
+    let mut cx = Context::from_waker(&waker);
+    match std::panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(&mut cx))) {
+        Ok(Poll::Pending) => *slot = Some(future),
+        Ok(Poll::Ready(())) | Err(_) => {}
+    }
+}
+
+/// A pool of worker threads running `Send` futures, stealing work from each other.
+///
+/// Dropping the pool stops its threads once they finish the poll they're in; tasks that haven't
+/// completed by then are dropped.
+pub struct ThreadPool {
+    shared: Arc<Shared>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl ThreadPool {
+    /// A pool with a thread for every core.
+    pub fn new() -> std::io::Result<Self> {
+        let threads = std::thread::available_parallelism().map_or(FALLBACK_THREADS, |n| n.get());
+        Self::with_threads(threads)
+    }
+
+    /// A pool of `threads` workers (at least one).
+    pub fn with_threads(threads: usize) -> std::io::Result<Self> {
+        let workers: Vec<Worker<Arc<Task>>> = (0..threads.max(1)).map(|_| Worker::new_lifo()).collect();
+        let shared = Arc::new(Shared {
+            id: NEXT_POOL_ID.fetch_add(1, Ordering::Relaxed),
+            injector: Injector::new(),
+            stealers: workers.iter().map(Worker::stealer).collect(),
+            queued: AtomicUsize::new(0),
+            sleep: Mutex::new(()),
+            wakeup: Condvar::new(),
+            shutdown: AtomicBool::new(false),
+        });
+
+        let mut pool = ThreadPool {
+            shared: Arc::clone(&shared),
+            threads: Vec::with_capacity(workers.len()),
+        };
+        for (index, local) in workers.into_iter().enumerate() {
+            let shared = Arc::clone(&shared);
+            let thread = std::thread::Builder::new()
+                .name(format!("gosub-worker-{index}"))
+                .spawn(move || shared.run_worker(local, index))?;
+            pool.threads.push(thread);
+        }
+        Ok(pool)
+    }
+
+    /// Number of worker threads
+    pub fn threads(&self) -> usize {
+        self.threads.len()
+    }
+
+    /// Runs `future` on the pool, fire-and-forget.
+    pub fn spawn<F: Future<Output = ()> + Send + 'static>(&self, future: F) {
+        let task = Arc::new(Task {
+            future: Mutex::new(Some(Box::pin(future))),
+            scheduled: AtomicBool::new(true),
+            pool: Arc::clone(&self.shared),
+        });
+        self.shared.schedule(task);
+    }
+
+    /// Runs `future` on the pool. The returned handle resolves to its output.
+    pub fn spawn_with_handle<F>(&self, future: F) -> TaskHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        self.spawn(async move {
+            let _ = sender.send(future.await);
+        });
+        TaskHandle::new(receiver)
+    }
+
+    /// Runs the blocking function `work` (a decode, a raster job) on the pool. The returned
+    /// handle resolves to its result.
+    pub fn spawn_work<T, W>(&self, work: W) -> TaskHandle<T>
+    where
+        T: Send + 'static,
+        W: FnOnce() -> T + Send + 'static,
+    {
+        self.spawn_with_handle(async move { work() })
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        self.shared.shutdown.store(true, Ordering::Release);
+        drop(self.shared.sleep.lock());
+        self.shared.wakeup.notify_all();
+        let current = std::thread::current().id();
+        for thread in self.threads.drain(..) {
+            // A pool dropped by one of its own tasks can't wait for that task's thread.
+            if thread.thread().id() != current {
+                let _ = thread.join();
+            }
+        }
+        // Queued tasks refer back to the pool; drop them to break the cycle.
+        while !self.shared.injector.steal().is_empty() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use std::collections::HashSet;
+    use std::time::Duration;
+
+    #[test]
+    fn runs_tasks_on_several_threads() {
+        let pool = ThreadPool::with_threads(4).expect("start pool");
+        let handles: Vec<_> = (0..16)
+            .map(|i| {
+                pool.spawn_work(move || {
+                    std::thread::sleep(Duration::from_millis(20));
+                    (i * 2, std::thread::current().name().map(str::to_string))
+                })
+            })
+            .collect();
+
+        let mut threads = HashSet::new();
+        for (i, handle) in handles.into_iter().enumerate() {
+            let (doubled, thread) = block_on(handle).expect("task output");
+            assert_eq!(doubled, i * 2);
+            threads.insert(thread);
+        }
+        assert!(threads.len() > 1, "work was spread over the workers");
+    }
+
+    #[test]
+    fn woken_tasks_resume() {
+        let pool = ThreadPool::with_threads(2).expect("start pool");
+        let (sender, receiver) = oneshot::channel::<u32>();
+        let waiting = pool.spawn_with_handle(async move { receiver.await.map(|n| n + 1) });
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            let _ = sender.send(41);
+        });
+        assert_eq!(block_on(waiting), Some(Ok(42)));
+    }
+
+    #[test]
+    fn panicking_tasks_leave_the_pool_running() {
+        let pool = ThreadPool::with_threads(1).expect("start pool");
+        let failed = pool.spawn_work(|| -> u32 { panic!("task failure") });
+        assert_eq!(block_on(failed), None);
+        assert_eq!(block_on(pool.spawn_work(|| 7)), Some(7));
+    }
+}
@@ -19,6 +19,51 @@ pub enum Encoding {
     UTF16LE,
     /// Stream consists of 16-bit UTF characters (Big Endian)
     UTF16BE,
+    /// Stream is in one of the legacy encodings of the Encoding Standard (windows-1252,
+    /// Shift_JIS, EUC-KR, ISO-8859-x, ...), decoded by encoding_rs
+    Legacy(&'static encoding_rs::Encoding),
+}
+
+impl Encoding {
+    /// The encoding an Encoding Standard label (`"utf-8"`, `"latin1"`, `"sjis"`, ...) stands for.
+    /// Note that like in browsers, the ASCII and Latin-1 labels mean windows-1252.
+    pub fn from_label(label: &str) -> Option<Self> {
+        encoding_rs::Encoding::for_label(label.trim().as_bytes()).map(Self::from)
+    }
+}
+
+impl From<&'static encoding_rs::Encoding> for Encoding {
+    fn from(encoding: &'static encoding_rs::Encoding) -> Self {
+        if encoding == encoding_rs::UTF_8 {
+            Encoding::UTF8
+        } else if encoding == encoding_rs::UTF_16LE {
+            Encoding::UTF16LE
+        } else if encoding == encoding_rs::UTF_16BE {
+            Encoding::UTF16BE
+        } else {
+            Encoding::Legacy(encoding)
+        }
+    }
+}
+
+/// encoding_rs decoder of a stream in a legacy encoding, fed one byte at a time so every
+/// character knows the byte it starts at
+struct LegacyDecoder {
+    decoder: encoding_rs::Decoder,
+    /// First byte fed since the decoder last produced output
+    pending_from: usize,
+}
+
+impl LegacyDecoder {
+    fn for_encoding(encoding: &Encoding) -> Option<Self> {
+        match encoding {
+            Encoding::Legacy(encoding) => Some(Self {
+                decoder: encoding.new_decoder_without_bom_handling(),
+                pending_from: 0,
+            }),
+            _ => None,
+        }
+    }
 }
 
 /// Defines a single character/element in the stream. This is either a UTF8 character, or
@@ -130,6 +175,9 @@ pub struct ByteStream {
     closed: bool,
     /// Current encoding
     encoding: Encoding,
+    /// Decoder state carried between appends when the encoding is [`Encoding::Legacy`]. Taken
+    /// once the stream is closed and the decoder flushed.
+    legacy: Option<LegacyDecoder>,
     /// Configuration for the stream
     config: Config,
 }
@@ -323,6 +371,7 @@ impl ByteStream {
             last_line_idx: std::cell::Cell::new(0),
             lines_scanned_chars: 0,
             closed: false,
+            legacy: LegacyDecoder::for_encoding(&encoding),
             encoding,
         }
     }
@@ -359,6 +408,7 @@ impl ByteStream {
         self.line_starts.push(0);
         self.lines_scanned_chars = 0;
         self.last_line_idx.set(0);
+        self.legacy = LegacyDecoder::for_encoding(&self.encoding);
         self.decode_from(0);
     }
 
@@ -466,11 +516,55 @@ impl ByteStream {
                     byte_pos += len;
                 }
             }
+            Encoding::Legacy(_) => {
+                byte_pos = self.decode_legacy(byte_pos);
+            }
         }
         self.decoded_bytes = byte_pos;
         self.extend_line_starts();
     }
 
+    /// Decode `self.buffer[byte_pos..]` through the legacy decoder, flushing it when the stream
+    /// is closed. A character split across appends stays in the decoder until the rest of it
+    /// arrives. Returns the new decode position, which is always the end of the buffer.
+    fn decode_legacy(&mut self, mut byte_pos: usize) -> usize {
+        let Some(mut legacy) = self.legacy.take() else {
+            return self.buffer.len();
+        };
+        let mut out = vec![0u8; legacy.decoder.max_utf8_buffer_length(1).unwrap_or(32)];
+
+        // One byte at a time: a character starts at the first byte fed since the previous
+        // character came out. When a byte produces more than one character (an invalid sequence
+        // decodes to U+FFFD and the byte is reprocessed), the others start at that byte.
+        while byte_pos < self.buffer.len() {
+            let (_, _, written, _) = legacy
+                .decoder
+                .decode_to_utf8(&self.buffer[byte_pos..=byte_pos], &mut out, false);
+            if written > 0 {
+                self.push_decoded(&out[..written], legacy.pending_from, byte_pos);
+                legacy.pending_from = byte_pos + 1;
+            }
+            byte_pos += 1;
+        }
+
+        if self.closed {
+            // An incomplete sequence at the end decodes to a replacement character.
+            let (_, _, written, _) = legacy.decoder.decode_to_utf8(&[], &mut out, true);
+            self.push_decoded(&out[..written], legacy.pending_from, byte_pos);
+        } else {
+            self.legacy = Some(legacy);
+        }
+        byte_pos
+    }
+
+    /// Push the characters of `utf8`, the first starting at byte `first` and the rest at `rest`
+    fn push_decoded(&mut self, utf8: &[u8], first: usize, rest: usize) {
+        for (i, ch) in String::from_utf8_lossy(utf8).chars().enumerate() {
+            self.char_byte_offsets.push(if i == 0 { first } else { rest });
+            self.chars.push(Ch(ch));
+        }
+    }
+
     /// Extend the `line_starts` table to cover any characters decoded since the last
     /// call, respecting CR/LF config. `line_starts[n]` is the char_pos of the first
     /// character on line n+1. Only the newly-decoded tail is scanned (incremental).
@@ -536,6 +630,13 @@ impl ByteStream {
         self.decode_from(self.decoded_bytes);
     }
 
+    /// Append raw bytes in the stream's encoding, for feeding the stream as data arrives. A
+    /// character split between two appends is decoded once its last byte is in.
+    pub fn append_bytes(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+        self.decode_from(self.decoded_bytes);
+    }
+
     pub fn close(&mut self) {
         self.closed = true;
         // Resume from the trailing incomplete sequence (if any) so it resolves to a
@@ -582,14 +683,12 @@ impl ByteStream {
         encoding_detector.feed(buf, complete);
 
         let encoding = encoding_detector.guess(None, chardetng::Utf8Detection::Allow);
-        if encoding == encoding_rs::UTF_16BE {
-            Encoding::UTF16BE
-        } else if encoding == encoding_rs::UTF_16LE {
-            Encoding::UTF16LE
-        } else {
-            // Default to UTF-8 for all other detected encodings (including ASCII-compatible ones)
-            Encoding::UTF8
+        // Plain ASCII is guessed as windows-1252, but may well turn out to be UTF-8 further on
+        // (or say so in a <meta charset>), so read it as UTF-8.
+        if encoding == encoding_rs::WINDOWS_1252 && buf.is_ascii() {
+            return Encoding::UTF8;
         }
+        Encoding::from(encoding)
     }
 
     pub fn set_encoding(&mut self, e: Encoding) {
@@ -850,6 +949,72 @@ mod test {
         assert_eq!(stream.read_and_next(), Ch('A'));
     }
 
+    // ── Legacy encodings ─────────────────────────────────────────────────────
+
+    #[test]
+    fn test_encoding_from_label() {
+        assert_eq!(Encoding::from_label("utf8"), Some(Encoding::UTF8));
+        assert_eq!(
+            Encoding::from_label(" latin1 "),
+            Some(Encoding::Legacy(encoding_rs::WINDOWS_1252))
+        );
+        assert_eq!(
+            Encoding::from_label("Shift_JIS"),
+            Some(Encoding::Legacy(encoding_rs::SHIFT_JIS))
+        );
+        assert_eq!(Encoding::from_label("no-such-charset"), None);
+    }
+
+    #[test]
+    fn test_windows_1252() {
+        let mut stream = ByteStream::new(Encoding::Legacy(encoding_rs::WINDOWS_1252), None);
+        stream.read_from_bytes(&[0x80, 0x20, 0xE9]).unwrap(); // €, space, é
+        assert_eq!(stream.read_and_next(), Ch('€'));
+        assert_eq!(stream.read_and_next(), Ch(' '));
+        assert_eq!(stream.tell_bytes(), 2);
+        assert_eq!(stream.read_and_next(), Ch('é'));
+        assert!(matches!(stream.read_and_next(), StreamEnd));
+    }
+
+    #[test]
+    fn test_shift_jis_split_across_appends() {
+        // "A日本" in Shift_JIS, with 日 split between the two appends
+        let mut stream = ByteStream::new(Encoding::Legacy(encoding_rs::SHIFT_JIS), None);
+        stream.append_bytes(&[0x41, 0x93]);
+        assert_eq!(stream.chars_left(), 1);
+        stream.append_bytes(&[0xFA, 0x96, 0x7B]);
+        stream.close();
+        assert_eq!(stream.read_and_next(), Ch('A'));
+        assert_eq!(stream.tell_bytes(), 1);
+        assert_eq!(stream.read_and_next(), Ch('日'));
+        assert_eq!(stream.tell_bytes(), 3);
+        assert_eq!(stream.read_and_next(), Ch('本'));
+        assert!(stream.eof());
+    }
+
+    #[test]
+    fn test_euc_kr_incomplete_at_close() {
+        // 한 is 0xC7 0xD1 in EUC-KR; a lone lead byte at the end becomes a replacement character
+        let mut stream = ByteStream::new(Encoding::Legacy(encoding_rs::EUC_KR), None);
+        stream.append_bytes(&[0xC7, 0xD1, 0xC7]);
+        assert_eq!(stream.chars_left(), 1);
+        stream.close();
+        assert_eq!(stream.read_and_next(), Ch('한'));
+        assert_eq!(stream.read_and_next(), Ch(REPLACEMENT_CHARACTER));
+        assert!(stream.eof());
+    }
+
+    #[test]
+    fn test_set_legacy_encoding() {
+        // ISO-8859-7 (Greek) read as UTF-8 first, as happens before a <meta charset> is seen
+        let mut stream = ByteStream::new(Encoding::UTF8, None);
+        stream.read_from_bytes(&[0x61, 0xE1, 0xE2]).unwrap();
+        stream.next();
+        stream.set_encoding(Encoding::from_label("iso-8859-7").unwrap());
+        assert_eq!(stream.read_and_next(), Ch('α'));
+        assert_eq!(stream.read_and_next(), Ch('β'));
+    }
+
     // ── detect_encoding ──────────────────────────────────────────────────────
 
     #[test]
@@ -873,6 +1038,13 @@ mod test {
         assert_eq!(stream.detect_encoding(), Encoding::UTF16BE);
     }
 
+    #[test]
+    fn test_detect_encoding_ascii_is_utf8() {
+        let mut stream = ByteStream::new(Encoding::Unknown, None);
+        stream.read_from_bytes(b"<p>plain ascii</p>").unwrap();
+        assert_eq!(stream.detect_encoding(), Encoding::UTF8);
+    }
+
     // ── Unknown encoding ─────────────────────────────────────────────────────
 
     #[test]
@@ -0,0 +1,272 @@
+//! Engine-wide reporting of recoverable errors.
+//!
+//! Modules report what went wrong but didn't stop them (a parse error recovered from, a failed
+//! fetch, an uncaught script exception) with [`report`] instead of logging it themselves.
+//! Every report is logged, and handed to whoever [`subscribe`]d: embedders showing a console,
+//! test harnesses collecting parse errors, and so on.
+//!
+//! Reports carry the tab they happened in without threading a [`TabId`] through every parser.
+//! Code running for a tab sets it as the current tab, for a thread with [`TabScope`] or for an
+//! async task with [`in_tab`], and reports made meanwhile pick it up.
+
+use crate::byte_stream::Location;
+use crate::tab_id::TabId;
+use parking_lot::RwLock;
+use std::cell::Cell;
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// How bad a reported error is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Worth knowing, nothing is lost (a deprecated construct, a quirk applied)
+    Info,
+    /// Something was wrong with the input and got ignored or repaired (most parse errors)
+    Warning,
+    /// Something didn't work: a resource didn't load, a script threw
+    Error,
+}
+
+impl Severity {
+    fn log_level(self) -> log::Level {
+        match self {
+            Severity::Info => log::Level::Info,
+            Severity::Warning => log::Level::Warn,
+            Severity::Error => log::Level::Error,
+        }
+    }
+}
+
+/// The part of the engine an error comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorSource {
+    Html,
+    Css,
+    Net,
+    Js,
+    Render,
+    Engine,
+}
+
+impl ErrorSource {
+    /// Name of the source, also used as the log target
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorSource::Html => "html",
+            ErrorSource::Css => "css",
+            ErrorSource::Net => "net",
+            ErrorSource::Js => "js",
+            ErrorSource::Render => "render",
+            ErrorSource::Engine => "engine",
+        }
+    }
+}
+
+impl Display for ErrorSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A recoverable error, as reported to subscribers
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorReport {
+    pub severity: Severity,
+    pub source: ErrorSource,
+    pub message: String,
+    /// The document, style sheet or script the error is in
+    pub url: Option<String>,
+    /// Where in `url` the error is
+    pub location: Option<Location>,
+    /// The tab it happened in; filled in from the current tab when not set
+    pub tab: Option<TabId>,
+}
+
+impl ErrorReport {
+    pub fn new(severity: Severity, source: ErrorSource, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            source,
+            message: message.into(),
+            url: None,
+            location: None,
+            tab: None,
+        }
+    }
+
+    pub fn warning(source: ErrorSource, message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, source, message)
+    }
+
+    pub fn error(source: ErrorSource, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, source, message)
+    }
+
+    #[must_use]
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_location(mut self, location: Location) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    #[must_use]
+    pub fn with_tab(mut self, tab: TabId) -> Self {
+        self.tab = Some(tab);
+        self
+    }
+}
+
+impl Display for ErrorReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)?;
+        match (&self.url, &self.location) {
+            (Some(url), Some(location)) => write!(f, " ({url}:{}:{})", location.line, location.column),
+            (Some(url), None) => write!(f, " ({url})"),
+            (None, Some(location)) => write!(f, " (at {}:{})", location.line, location.column),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// Identifies a subscription, for [`unsubscribe`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+type Subscriber = Arc<dyn Fn(&ErrorReport) + Send + Sync>;
+
+static NEXT_SUBSCRIPTION: AtomicU64 = AtomicU64::new(1);
+
+static SUBSCRIBERS: RwLock<Vec<(SubscriptionId, Subscriber)>> = parking_lot::const_rwlock(Vec::new());
+
+thread_local! {
+    static CURRENT_TAB: Cell<Option<TabId>> = const { Cell::new(None) };
+}
+
+/// Calls `subscriber` with every report from now on, on the thread making the report. Keep it
+/// quick: send the report on to where it is handled rather than handling it in place.
+pub fn subscribe(subscriber: impl Fn(&ErrorReport) + Send + Sync + 'static) -> SubscriptionId {
+    let id = SubscriptionId(NEXT_SUBSCRIPTION.fetch_add(1, Ordering::Relaxed));
+    SUBSCRIBERS.write().push((id, Arc::new(subscriber)));
+    id
+}
+
+pub fn unsubscribe(id: SubscriptionId) {
+    SUBSCRIBERS.write().retain(|(subscription, _)| *subscription != id);
+}
+
+/// Reports a recoverable error: logs it under its source and hands it to the subscribers.
+pub fn report(mut report: ErrorReport) {
+    if report.tab.is_none() {
+        report.tab = current_tab();
+    }
+    log::log!(target: report.source.name(), report.severity.log_level(), "{report}");
+
+    // Called outside the lock, so subscribers may (un)subscribe themselves.
+    let subscribers: Vec<Subscriber> = SUBSCRIBERS.read().iter().map(|(_, s)| Arc::clone(s)).collect();
+    for subscriber in subscribers {
+        subscriber(&report);
+    }
+}
+
+/// The tab the current thread or task is working for, if any
+pub fn current_tab() -> Option<TabId> {
+    CURRENT_TAB.with(Cell::get)
+}
+
+/// Makes `tab` the current tab of this thread until dropped, restoring the one before.
+pub struct TabScope {
+    previous: Option<TabId>,
+}
+
+impl TabScope {
+    pub fn enter(tab: TabId) -> Self {
+        Self {
+            previous: CURRENT_TAB.with(|current| current.replace(Some(tab))),
+        }
+    }
+}
+
+impl Drop for TabScope {
+    fn drop(&mut self) {
+        CURRENT_TAB.with(|current| current.set(self.previous));
+    }
+}
+
+/// Runs `future` with `tab` as the current tab, whichever thread polls it.
+pub fn in_tab<F: Future>(tab: TabId, future: F) -> InTab<F> {
+    InTab {
+        tab,
+        future: Box::pin(future),
+    }
+}
+
+/// Future returned by [`in_tab`]
+pub struct InTab<F> {
+    tab: TabId,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for InTab<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let _scope = TabScope::enter(self.tab);
+        self.future.as_mut().poll(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+
+    #[test]
+    fn subscribers_get_reports_with_the_current_tab() {
+        let tab = TabId::new();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&received);
+        let id = subscribe(move |report| {
+            // Reports from tests running alongside this one show up too.
+            if report.tab == Some(tab) {
+                sink.lock().push(report.clone());
+            }
+        });
+
+        report(ErrorReport::warning(ErrorSource::Css, "outside the tab"));
+        {
+            let _scope = TabScope::enter(tab);
+            report(
+                ErrorReport::warning(ErrorSource::Css, "unexpected token")
+                    .with_url("https://example.test/site.css")
+                    .with_location(Location::new(3, 7, 40)),
+            );
+        }
+        futures::executor::block_on(in_tab(tab, async {
+            report(ErrorReport::error(ErrorSource::Net, "connection refused"));
+        }));
+        unsubscribe(id);
+        {
+            let _scope = TabScope::enter(tab);
+            report(ErrorReport::warning(ErrorSource::Css, "after unsubscribing"));
+        }
+        assert_eq!(current_tab(), None);
+
+        let received = received.lock();
+        assert_eq!(received.len(), 2);
+        assert_eq!(
+            received[0].to_string(),
+            "unexpected token (https://example.test/site.css:3:7)"
+        );
+        assert_eq!(received[1].severity, Severity::Error);
+        assert_eq!(received[1].source, ErrorSource::Net);
+    }
+}
@@ -6,3 +6,388 @@ pub type Size = SizeT<f32>;
 pub type SizeU32 = SizeT<u32>;
 pub type Rect = crate::types::Rect<FP>;
 pub type NormalizedCoord = i16;
+
+/// A point in 3D space. `z` points towards the viewer, as in CSS.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Point3 {
+    pub x: FP,
+    pub y: FP,
+    pub z: FP,
+}
+
+impl Point3 {
+    pub const ZERO: Self = Self { x: 0.0, y: 0.0, z: 0.0 };
+
+    #[must_use]
+    pub fn new(x: FP, y: FP, z: FP) -> Self {
+        Self { x, y, z }
+    }
+}
+
+impl From<Point> for Point3 {
+    fn from(point: Point) -> Self {
+        Self::new(point.x, point.y, 0.0)
+    }
+}
+
+/// A 4x4 matrix for CSS 3D transforms, `m[row][column]`, applied to column vectors: `a * b`
+/// transforms by `b` first, then by `a`, so a transform list like `translate(…) rotateY(…)`
+/// is the product of its functions from left to right.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix4 {
+    pub m: [[FP; 4]; 4],
+}
+
+impl Default for Matrix4 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Matrix4 {
+    pub const IDENTITY: Self = Self {
+        m: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+    };
+
+    /// The matrix of CSS `matrix(a, b, c, d, e, f)`
+    #[must_use]
+    pub fn from_2d(a: FP, b: FP, c: FP, d: FP, e: FP, f: FP) -> Self {
+        Self {
+            m: [
+                [a, c, 0.0, e],
+                [b, d, 0.0, f],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    /// The matrix of CSS `matrix3d()`, whose 16 arguments list the matrix column by column
+    #[must_use]
+    pub fn from_column_major(values: [FP; 16]) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, value) in values.into_iter().enumerate() {
+            m[i % 4][i / 4] = value;
+        }
+        Self { m }
+    }
+
+    #[must_use]
+    pub fn translate(x: FP, y: FP, z: FP) -> Self {
+        let mut matrix = Self::IDENTITY;
+        matrix.m[0][3] = x;
+        matrix.m[1][3] = y;
+        matrix.m[2][3] = z;
+        matrix
+    }
+
+    #[must_use]
+    pub fn scale(x: FP, y: FP, z: FP) -> Self {
+        let mut matrix = Self::IDENTITY;
+        matrix.m[0][0] = x;
+        matrix.m[1][1] = y;
+        matrix.m[2][2] = z;
+        matrix
+    }
+
+    /// CSS `skew(ax, ay)`, angles in radians
+    #[must_use]
+    pub fn skew(ax: FP, ay: FP) -> Self {
+        let mut matrix = Self::IDENTITY;
+        matrix.m[0][1] = ax.tan();
+        matrix.m[1][0] = ay.tan();
+        matrix
+    }
+
+    /// CSS `rotateX()`, in radians
+    #[must_use]
+    pub fn rotate_x(angle: FP) -> Self {
+        Self::rotate_3d(1.0, 0.0, 0.0, angle)
+    }
+
+    /// CSS `rotateY()`, in radians
+    #[must_use]
+    pub fn rotate_y(angle: FP) -> Self {
+        Self::rotate_3d(0.0, 1.0, 0.0, angle)
+    }
+
+    /// CSS `rotate()` and `rotateZ()`, in radians
+    #[must_use]
+    pub fn rotate_z(angle: FP) -> Self {
+        Self::rotate_3d(0.0, 0.0, 1.0, angle)
+    }
+
+    /// CSS `rotate3d(x, y, z, angle)`: a rotation by `angle` radians around the vector
+    /// `(x, y, z)`. A zero vector doesn't rotate.
+    #[must_use]
+    pub fn rotate_3d(x: FP, y: FP, z: FP, angle: FP) -> Self {
+        let length = (x * x + y * y + z * z).sqrt();
+        if length == 0.0 {
+            return Self::IDENTITY;
+        }
+        let (x, y, z) = (x / length, y / length, z / length);
+        let (sin, cos) = angle.sin_cos();
+        let t = 1.0 - cos;
+        Self {
+            m: [
+                [t * x * x + cos, t * x * y - sin * z, t * x * z + sin * y, 0.0],
+                [t * x * y + sin * z, t * y * y + cos, t * y * z - sin * x, 0.0],
+                [t * x * z - sin * y, t * y * z + sin * x, t * z * z + cos, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    /// CSS `perspective(d)`: the viewer at `d` pixels in front of the `z = 0` plane. Distances
+    /// below one pixel count as one pixel, as CSS specifies.
+    #[must_use]
+    pub fn perspective(distance: FP) -> Self {
+        let mut matrix = Self::IDENTITY;
+        matrix.m[3][2] = -1.0 / distance.max(1.0);
+        matrix
+    }
+
+    /// The `perspective` property of a containing block: `perspective(d)` seen from `origin`
+    /// (its `perspective-origin`), in the coordinates of the element being transformed.
+    #[must_use]
+    pub fn perspective_from(distance: FP, origin: Point) -> Self {
+        Self::perspective(distance).about(Point3::from(origin))
+    }
+
+    /// This transform applied around `origin` instead of the coordinate origin, as
+    /// `transform-origin` does
+    #[must_use]
+    pub fn about(&self, origin: Point3) -> Self {
+        Self::translate(origin.x, origin.y, origin.z) * *self * Self::translate(-origin.x, -origin.y, -origin.z)
+    }
+
+    #[must_use]
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+
+    /// Whether the matrix only moves things around in the plane, so a 2D backend can draw it
+    #[must_use]
+    pub fn is_2d(&self) -> bool {
+        let m = &self.m;
+        m[0][2] == 0.0
+            && m[1][2] == 0.0
+            && m[2][0] == 0.0
+            && m[2][1] == 0.0
+            && m[2][2] == 1.0
+            && m[2][3] == 0.0
+            && m[3][0] == 0.0
+            && m[3][1] == 0.0
+            && m[3][2] == 0.0
+            && m[3][3] == 1.0
+    }
+
+    /// The `matrix(a, b, c, d, e, f)` of a 2D transform
+    #[must_use]
+    pub fn to_2d(&self) -> Option<[FP; 6]> {
+        let m = &self.m;
+        self.is_2d()
+            .then(|| [m[0][0], m[1][0], m[0][1], m[1][1], m[0][3], m[1][3]])
+    }
+
+    /// The matrix with the `z` coordinate dropped after transforming, the way an element without
+    /// `transform-style: preserve-3d` flattens its 3D content into its own plane
+    #[must_use]
+    pub fn flatten(&self) -> Self {
+        let mut matrix = *self;
+        for values in &mut matrix.m {
+            values[2] = 0.0;
+        }
+        matrix.m[2] = [0.0, 0.0, 1.0, 0.0];
+        matrix
+    }
+
+    #[must_use]
+    pub fn determinant(&self) -> FP {
+        let m = &self.m;
+        let mut det = 0.0;
+        for (column, value) in m[0].iter().enumerate() {
+            let sign = if column % 2 == 0 { 1.0 } else { -1.0 };
+            det += sign * value * minor(m, 0, column);
+        }
+        det
+    }
+
+    /// The inverse matrix, `None` when the matrix can't be inverted (it flattens everything
+    /// onto a line or a point)
+    #[must_use]
+    pub fn inverse(&self) -> Option<Self> {
+        let det = self.determinant();
+        if det.abs() <= FP::EPSILON {
+            return None;
+        }
+        let mut inverse = [[0.0; 4]; 4];
+        for (row, values) in inverse.iter_mut().enumerate() {
+            for (column, value) in values.iter_mut().enumerate() {
+                let sign = if (row + column) % 2 == 0 { 1.0 } else { -1.0 };
+                // The adjugate is the transposed cofactor matrix.
+                *value = sign * minor(&self.m, column, row) / det;
+            }
+        }
+        Some(Self { m: inverse })
+    }
+
+    /// Whether a plane transformed by this (accumulated) matrix faces away from the viewer,
+    /// for `backface-visibility: hidden`. A matrix that can't be inverted shows the plane
+    /// edge-on, which doesn't count as its back.
+    #[must_use]
+    pub fn shows_backface(&self) -> bool {
+        self.inverse().is_some_and(|inverse| inverse.m[2][2] < -FP::EPSILON)
+    }
+
+    /// `point` transformed and projected, `None` when it ends up behind the viewer
+    #[must_use]
+    pub fn transform_point3(&self, point: Point3) -> Option<Point3> {
+        let m = &self.m;
+        let v = [point.x, point.y, point.z, 1.0];
+        let row = |r: usize| m[r][0] * v[0] + m[r][1] * v[1] + m[r][2] * v[2] + m[r][3] * v[3];
+        let w = row(3);
+        if w <= 0.0 {
+            return None;
+        }
+        Some(Point3::new(row(0) / w, row(1) / w, row(2) / w))
+    }
+
+    /// The point of the `z = 0` plane at `point`, transformed and projected back onto it
+    #[must_use]
+    pub fn transform_point(&self, point: Point) -> Option<Point> {
+        self.transform_point3(point.into()).map(|p| Point::new(p.x, p.y))
+    }
+
+    /// Bounds of the rectangle at `origin` with `size` once transformed, `None` when part of it
+    /// ends up behind the viewer
+    #[must_use]
+    pub fn transform_rect(&self, origin: Point, size: Size) -> Option<Rect> {
+        let corners = [
+            Point::new(origin.x, origin.y),
+            Point::new(origin.x + size.width, origin.y),
+            Point::new(origin.x, origin.y + size.height),
+            Point::new(origin.x + size.width, origin.y + size.height),
+        ];
+        let mut min = Point::new(FP::INFINITY, FP::INFINITY);
+        let mut max = Point::new(FP::NEG_INFINITY, FP::NEG_INFINITY);
+        for corner in corners {
+            let p = self.transform_point(corner)?;
+            min = Point::new(min.x.min(p.x), min.y.min(p.y));
+            max = Point::new(max.x.max(p.x), max.y.max(p.y));
+        }
+        Some(Rect::from_components(min, Size::new(max.x - min.x, max.y - min.y)))
+    }
+}
+
+impl std::ops::Mul for Matrix4 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (row, values) in m.iter_mut().enumerate() {
+            for (column, value) in values.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[row][k] * rhs.m[k][column]).sum();
+            }
+        }
+        Self { m }
+    }
+}
+
+/// Determinant of `m` without `row` and `column`
+fn minor(m: &[[FP; 4]; 4], row: usize, column: usize) -> FP {
+    let mut sub = [[0.0; 3]; 3];
+    for (r, source) in (0..4).filter(|r| *r != row).enumerate() {
+        for (c, source_column) in (0..4).filter(|c| *c != column).enumerate() {
+            sub[r][c] = m[source][source_column];
+        }
+    }
+    sub[0][0] * (sub[1][1] * sub[2][2] - sub[1][2] * sub[2][1])
+        - sub[0][1] * (sub[1][0] * sub[2][2] - sub[1][2] * sub[2][0])
+        + sub[0][2] * (sub[1][0] * sub[2][1] - sub[1][1] * sub[2][0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    fn close(a: Point, b: Point) -> bool {
+        (a.x - b.x).abs() < 1e-3 && (a.y - b.y).abs() < 1e-3
+    }
+
+    #[test]
+    fn composes_left_to_right() {
+        // translate(10px, 0) rotate(90deg): rotate first, then move
+        let matrix = Matrix4::translate(10.0, 0.0, 0.0) * Matrix4::rotate_z(FRAC_PI_2);
+        let p = matrix.transform_point(Point::new(1.0, 0.0)).unwrap();
+        assert!(close(p, Point::new(10.0, 1.0)), "{p:?}");
+        assert_eq!(
+            Matrix4::from_2d(1.0, 0.0, 0.0, 1.0, 5.0, 6.0).to_2d(),
+            Some([1.0, 0.0, 0.0, 1.0, 5.0, 6.0])
+        );
+        assert_eq!(Matrix4::rotate_y(0.5).to_2d(), None);
+    }
+
+    #[test]
+    fn matrix3d_is_column_major() {
+        let mut values = [0.0; 16];
+        for i in [0, 5, 10, 15] {
+            values[i] = 1.0;
+        }
+        values[12] = 7.0; // the translation column
+        assert_eq!(Matrix4::from_column_major(values), Matrix4::translate(7.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn perspective_grows_what_comes_closer() {
+        let matrix = Matrix4::perspective(100.0) * Matrix4::translate(0.0, 0.0, 50.0);
+        let p = matrix.transform_point(Point::new(10.0, 10.0)).unwrap();
+        assert!(close(p, Point::new(20.0, 20.0)), "{p:?}");
+        let behind = Matrix4::perspective(100.0) * Matrix4::translate(0.0, 0.0, 150.0);
+        assert_eq!(behind.transform_point(Point::new(10.0, 10.0)), None);
+
+        // Seen from (50, 50), a point there doesn't move.
+        let centred = Matrix4::perspective_from(100.0, Point::new(50.0, 50.0)) * Matrix4::translate(0.0, 0.0, 50.0);
+        let p = centred.transform_point(Point::new(50.0, 50.0)).unwrap();
+        assert!(close(p, Point::new(50.0, 50.0)), "{p:?}");
+    }
+
+    #[test]
+    fn inverts() {
+        let matrix =
+            Matrix4::translate(3.0, 4.0, 5.0) * Matrix4::rotate_3d(1.0, 1.0, 0.0, 0.7) * Matrix4::scale(2.0, 2.0, 2.0);
+        let product = matrix * matrix.inverse().unwrap();
+        for (row, values) in product.m.iter().enumerate() {
+            for (column, value) in values.iter().enumerate() {
+                let expected = if row == column { 1.0 } else { 0.0 };
+                assert!((value - expected).abs() < 1e-4);
+            }
+        }
+        assert_eq!(Matrix4::scale(1.0, 0.0, 1.0).inverse(), None);
+    }
+
+    #[test]
+    fn detects_backfaces() {
+        assert!(!Matrix4::IDENTITY.shows_backface());
+        assert!(Matrix4::rotate_y(PI).shows_backface());
+        assert!(Matrix4::rotate_x(PI * 0.75).shows_backface());
+        assert!(!Matrix4::rotate_y(PI / 4.0).shows_backface());
+        assert!(!Matrix4::rotate_y(FRAC_PI_2).shows_backface(), "edge-on");
+    }
+
+    #[test]
+    fn transforms_rect_bounds() {
+        let matrix = Matrix4::rotate_z(FRAC_PI_2).about(Point3::new(5.0, 5.0, 0.0));
+        let bounds = matrix
+            .transform_rect(Point::new(0.0, 0.0), Size::new(10.0, 4.0))
+            .unwrap();
+        assert!(close(bounds.origin(), Point::new(6.0, 0.0)));
+        assert!((bounds.x2 - 4.0).abs() < 1e-3 && (bounds.y2 - 10.0).abs() < 1e-3);
+    }
+}
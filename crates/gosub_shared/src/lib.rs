@@ -10,6 +10,7 @@ pub mod async_executor;
 pub mod byte_stream;
 pub mod config;
 pub mod css_colors;
+pub mod error_report;
 pub mod errors;
 pub mod font;
 pub mod geo;
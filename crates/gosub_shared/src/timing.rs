@@ -1,6 +1,7 @@
 use parking_lot::Mutex;
 use std::collections::HashMap;
-use std::fmt::{Display, Formatter};
+use std::fmt::{Display, Formatter, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
 #[cfg(not(target_arch = "wasm32"))]
 use std::time::Instant;
 
@@ -14,6 +15,35 @@ fn new_timer_id() -> TimerId {
     uuid::Uuid::new_v4()
 }
 
+static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    /// Small sequential id of the thread, used as the `tid` of trace events
+    static THREAD_ID: u64 = NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed);
+}
+
+fn current_thread_id() -> u64 {
+    THREAD_ID.with(|id| *id)
+}
+
+/// Microseconds since the first timer of the process started, the clock of trace events
+#[cfg(not(target_arch = "wasm32"))]
+fn trace_clock_us() -> u64 {
+    lazy_static! {
+        static ref TRACE_EPOCH: Instant = Instant::now();
+    }
+    TRACE_EPOCH.elapsed().as_micros() as u64
+}
+
+/// Microseconds since the page's time origin, the clock of trace events
+#[cfg(target_arch = "wasm32")]
+fn trace_clock_us() -> u64 {
+    window()
+        .and_then(|w| w.performance())
+        .map(|p| p.now() * 1000.0)
+        .unwrap_or(0.0) as u64
+}
+
 #[derive(Debug, Clone)]
 pub enum Scale {
     MicroSecond,
@@ -39,6 +69,8 @@ impl Display for Duration {
 pub struct TimingTable {
     timers: HashMap<TimerId, Timer>,
     namespaces: HashMap<String, Vec<TimerId>>,
+    /// Names of the threads timers were started on, by thread id
+    threads: HashMap<u64, String>,
 }
 
 pub struct Stats {
@@ -78,11 +110,15 @@ impl TimingTable {
         TimingTable {
             timers: HashMap::new(),
             namespaces: HashMap::new(),
+            threads: HashMap::new(),
         }
     }
 
     pub fn start_timer(&mut self, namespace: &str, context: Option<String>) -> TimerId {
         let timer = Timer::new(context);
+        if let Some(name) = std::thread::current().name() {
+            self.threads.entry(timer.thread_id).or_insert_with(|| name.to_string());
+        }
         self.timers.insert(timer.id, timer.clone());
         self.namespaces.entry(namespace.to_string()).or_default().push(timer.id);
 
@@ -159,6 +195,61 @@ impl TimingTable {
     pub fn clear(&mut self) {
         self.timers.clear();
         self.namespaces.clear();
+        self.threads.clear();
+    }
+
+    /// The finished timers in the Trace Event JSON format, for opening in Perfetto or
+    /// `chrome://tracing`. Every timer is a complete event named after its namespace on the
+    /// thread that started it, categorized by the namespace's first segment (`pipeline`,
+    /// `html5`, ...), with its context as an argument.
+    #[must_use]
+    pub fn chrome_trace(&self) -> String {
+        #[cfg(not(target_arch = "wasm32"))]
+        let pid = std::process::id();
+        #[cfg(target_arch = "wasm32")]
+        let pid = 1;
+
+        let mut spans: Vec<(&str, &Timer)> = self
+            .namespaces
+            .iter()
+            .flat_map(|(namespace, ids)| ids.iter().map(move |id| (namespace.as_str(), id)))
+            .filter_map(|(namespace, id)| Some((namespace, self.timers.get(id)?)))
+            .filter(|(_, timer)| timer.has_finished())
+            .collect();
+        spans.sort_by_key(|(_, timer)| (timer.start_us, timer.thread_id));
+
+        let mut events = vec![format!(
+            r#"{{"name":"process_name","ph":"M","pid":{pid},"tid":0,"args":{{"name":"gosub"}}}}"#
+        )];
+        let mut threads: Vec<_> = self.threads.iter().collect();
+        threads.sort();
+        for (tid, name) in threads {
+            events.push(format!(
+                r#"{{"name":"thread_name","ph":"M","pid":{pid},"tid":{tid},"args":{{"name":{}}}}}"#,
+                json_string(name)
+            ));
+        }
+        for (namespace, timer) in spans {
+            let category = namespace.split('.').next().unwrap_or(namespace);
+            let mut event = format!(
+                r#"{{"name":{},"cat":{},"ph":"X","ts":{},"dur":{},"pid":{pid},"tid":{}"#,
+                json_string(namespace),
+                json_string(category),
+                timer.start_us,
+                timer.duration_us,
+                timer.thread_id
+            );
+            if let Some(context) = &timer.context {
+                let _ = write!(event, r#","args":{{"context":{}}}"#, json_string(context));
+            }
+            event.push('}');
+            events.push(event);
+        }
+
+        format!(
+            "{{\"traceEvents\":[\n{}\n],\"displayTimeUnit\":\"ms\"}}\n",
+            events.join(",\n")
+        )
     }
 
     fn scale(&self, value: u64, scale: Scale) -> String {
@@ -242,6 +333,37 @@ pub fn reset_stats() {
     TIMING_TABLE.lock().clear();
 }
 
+/// The global timing table as a Chrome trace, see [`TimingTable::chrome_trace`].
+pub fn chrome_trace() -> String {
+    TIMING_TABLE.lock().chrome_trace()
+}
+
+/// Writes the global timing table as a Chrome trace to `path`, to load into Perfetto.
+pub fn write_chrome_trace(path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+    std::fs::write(path, chrome_trace())
+}
+
+/// `value` as a JSON string literal
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
 /// Print the full timing table (all namespaces, aggregated stats) to stdout, auto-scaling units.
 /// When `details` is true, also prints each individual timer's duration and context.
 pub fn dump(details: bool) {
@@ -347,6 +469,10 @@ pub struct Timer {
     #[cfg(target_arch = "wasm32")]
     end: Option<f64>,
     duration_us: u64,
+    /// Start on the trace clock
+    start_us: u64,
+    /// Thread the timer was started on
+    thread_id: u64,
 }
 
 impl Timer {
@@ -369,12 +495,15 @@ impl Timer {
             start,
             end: None,
             duration_us: 0,
+            start_us: trace_clock_us(),
+            thread_id: current_thread_id(),
         }
     }
 
     #[cfg(not(target_arch = "wasm32"))]
     pub fn start(&mut self) {
         self.start = Instant::now();
+        self.start_us = trace_clock_us();
     }
 
     #[cfg(target_arch = "wasm32")]
@@ -383,6 +512,7 @@ impl Timer {
             .and_then(|w| w.performance())
             .map(|p| p.now())
             .unwrap_or(f64::NAN);
+        self.start_us = trace_clock_us();
     }
 
     #[cfg(not(target_arch = "wasm32"))]
@@ -462,6 +592,27 @@ mod tests {
         TIMING_TABLE.lock().print_timings(true, Scale::Auto);
     }
 
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_chrome_trace() {
+        let mut table = TimingTable::new();
+        let t = table.start_timer("html5.parse", Some("a \"quoted\" page".to_string()));
+        table.stop_timer(t);
+        let unfinished = table.start_timer("pipeline.layout", None);
+        let t = table.start_timer("pipeline.paint", None);
+        table.stop_timer(t);
+
+        let trace = table.chrome_trace();
+        assert!(trace.starts_with(r#"{"traceEvents":["#));
+        assert!(trace.contains(r#""name":"html5.parse","cat":"html5","ph":"X""#));
+        assert!(trace.contains(r#""args":{"context":"a \"quoted\" page"}"#));
+        assert!(trace.contains(r#""name":"pipeline.paint","cat":"pipeline""#));
+        assert!(!trace.contains("pipeline.layout"), "unfinished timers are left out");
+        assert!(trace.contains(&format!(r#""tid":{}"#, current_thread_id())));
+        assert!(trace.contains(r#""name":"thread_name""#), "test threads are named");
+        table.stop_timer(unfinished);
+    }
+
     #[wasm_bindgen_test]
     #[cfg(target_arch = "wasm32")]
     fn test_timing_defaults_wasm() {
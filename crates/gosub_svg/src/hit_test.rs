@@ -0,0 +1,373 @@
+//! Hit testing of a rendered SVG: which elements are under a point, honouring `pointer-events`.
+//!
+//! Shapes are tested against their geometry rather than their bounding boxes: the fill by the
+//! shape's fill rule and the stroke by its width, with curves flattened into line segments.
+//! Images and text are tested against their bounding boxes. Clip paths and masks don't narrow
+//! the area an element is hit in.
+
+use ::resvg::usvg;
+use usvg::tiny_skia_path::{PathSegment, Point};
+
+/// Line segments a curve is flattened into
+const CURVE_STEPS: usize = 16;
+
+/// The `pointer-events` values an SVG element can have
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PointerEvents {
+    /// `auto`, which behaves like `visiblePainted` in SVG
+    #[default]
+    VisiblePainted,
+    VisibleFill,
+    VisibleStroke,
+    Visible,
+    Painted,
+    Fill,
+    Stroke,
+    All,
+    None,
+}
+
+impl PointerEvents {
+    /// Parses a `pointer-events` value. The HTML-only `auto` counts as `visiblePainted`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let known = [
+            ("auto", Self::VisiblePainted),
+            ("visiblePainted", Self::VisiblePainted),
+            ("visibleFill", Self::VisibleFill),
+            ("visibleStroke", Self::VisibleStroke),
+            ("visible", Self::Visible),
+            ("painted", Self::Painted),
+            ("fill", Self::Fill),
+            ("stroke", Self::Stroke),
+            ("all", Self::All),
+            ("none", Self::None),
+        ];
+        known
+            .into_iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(value))
+            .map(|(_, pointer_events)| pointer_events)
+    }
+
+    /// Whether an element is hit, given where the point is and how the element is painted
+    fn hits(self, visible: bool, fill: Area, stroke: Area) -> bool {
+        match self {
+            Self::VisiblePainted => visible && (fill.painted_hit() || stroke.painted_hit()),
+            Self::VisibleFill => visible && fill.inside,
+            Self::VisibleStroke => visible && stroke.inside,
+            Self::Visible => visible && (fill.inside || stroke.inside),
+            Self::Painted => fill.painted_hit() || stroke.painted_hit(),
+            Self::Fill => fill.inside,
+            Self::Stroke => stroke.inside,
+            Self::All => fill.inside || stroke.inside,
+            Self::None => false,
+        }
+    }
+}
+
+/// Whether the point is inside the fill or stroke area of an element, and whether it is painted
+#[derive(Clone, Copy)]
+struct Area {
+    inside: bool,
+    painted: bool,
+}
+
+impl Area {
+    fn painted_hit(self) -> bool {
+        self.inside && self.painted
+    }
+}
+
+/// The elements under a point
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SvgHit {
+    /// Ids of the hit element and the groups it is in, innermost first. Elements without an id
+    /// are left out.
+    pub ids: Vec<String>,
+}
+
+/// Finds the topmost element of `tree` under `(x, y)`, in the tree's user space.
+///
+/// `pointer_events` gives the `pointer-events` of the element with an id; elements it has no
+/// answer for inherit the value of the group they are in.
+pub fn hit_test_tree(
+    tree: &usvg::Tree,
+    x: f32,
+    y: f32,
+    pointer_events: &dyn Fn(&str) -> Option<PointerEvents>,
+) -> Option<SvgHit> {
+    let point = Point::from_xy(x, y);
+    let ids = hit_group(tree.root(), point, PointerEvents::default(), pointer_events)?;
+    Some(SvgHit { ids })
+}
+
+/// Tests the children of `group` front to back; the ids of what was hit, innermost first.
+fn hit_group(
+    group: &usvg::Group,
+    point: Point,
+    inherited: PointerEvents,
+    lookup: &dyn Fn(&str) -> Option<PointerEvents>,
+) -> Option<Vec<String>> {
+    let pointer_events = own_or(group.id(), inherited, lookup);
+
+    group.children().iter().rev().find_map(|child| {
+        let mut ids = match child {
+            usvg::Node::Group(inner) => hit_group(inner, point, pointer_events, lookup)?,
+            usvg::Node::Path(path) => hit_path(path, point, own_or(path.id(), pointer_events, lookup))?,
+            usvg::Node::Image(_) | usvg::Node::Text(_) => {
+                let fill = Area {
+                    inside: contains(child.abs_bounding_box(), point),
+                    painted: true,
+                };
+                hit_box(child.id(), own_or(child.id(), pointer_events, lookup), fill)?
+            }
+        };
+        if !group.id().is_empty() {
+            ids.push(group.id().to_string());
+        }
+        Some(ids)
+    })
+}
+
+/// The `pointer-events` of the element `id`, or the inherited value
+fn own_or(id: &str, inherited: PointerEvents, lookup: &dyn Fn(&str) -> Option<PointerEvents>) -> PointerEvents {
+    match id {
+        "" => inherited,
+        id => lookup(id).unwrap_or(inherited),
+    }
+}
+
+fn leaf(id: &str) -> Vec<String> {
+    match id {
+        "" => Vec::new(),
+        id => vec![id.to_string()],
+    }
+}
+
+/// Images and text count as filled, without a stroke.
+fn hit_box(id: &str, pointer_events: PointerEvents, fill: Area) -> Option<Vec<String>> {
+    let stroke = Area {
+        inside: false,
+        painted: false,
+    };
+    pointer_events.hits(true, fill, stroke).then(|| leaf(id))
+}
+
+fn hit_path(path: &usvg::Path, point: Point, pointer_events: PointerEvents) -> Option<Vec<String>> {
+    if pointer_events == PointerEvents::None {
+        return None;
+    }
+    let mut local = point;
+    path.abs_transform().invert()?.map_point(&mut local);
+    let polylines = flatten(path.data());
+
+    let fill_rule = path.fill().map_or(usvg::FillRule::NonZero, |fill| fill.rule());
+    let fill = Area {
+        inside: fill_contains(&polylines, local, fill_rule),
+        painted: path.fill().is_some(),
+    };
+    let stroke = Area {
+        inside: path
+            .stroke()
+            .is_some_and(|stroke| stroke_contains(&polylines, local, stroke.width().get() / 2.0)),
+        painted: path.stroke().is_some(),
+    };
+    pointer_events
+        .hits(path.is_visible(), fill, stroke)
+        .then(|| leaf(path.id()))
+}
+
+fn contains(rect: usvg::Rect, point: Point) -> bool {
+    (rect.left()..=rect.right()).contains(&point.x) && (rect.top()..=rect.bottom()).contains(&point.y)
+}
+
+/// A subpath flattened into points, and whether it is closed
+struct Polyline {
+    points: Vec<Point>,
+    closed: bool,
+}
+
+fn flatten(path: &usvg::tiny_skia_path::Path) -> Vec<Polyline> {
+    let mut polylines: Vec<Polyline> = Vec::new();
+    let mut current = Polyline {
+        points: Vec::new(),
+        closed: false,
+    };
+    let mut last = Point::zero();
+
+    for segment in path.segments() {
+        match segment {
+            PathSegment::MoveTo(p) => {
+                if current.points.len() > 1 {
+                    polylines.push(std::mem::replace(
+                        &mut current,
+                        Polyline {
+                            points: Vec::new(),
+                            closed: false,
+                        },
+                    ));
+                }
+                current.points.clear();
+                current.points.push(p);
+                last = p;
+            }
+            PathSegment::LineTo(p) => {
+                current.points.push(p);
+                last = p;
+            }
+            PathSegment::QuadTo(c, p) => {
+                for step in 1..=CURVE_STEPS {
+                    let t = step as f32 / CURVE_STEPS as f32;
+                    let u = 1.0 - t;
+                    current.points.push(Point::from_xy(
+                        u * u * last.x + 2.0 * u * t * c.x + t * t * p.x,
+                        u * u * last.y + 2.0 * u * t * c.y + t * t * p.y,
+                    ));
+                }
+                last = p;
+            }
+            PathSegment::CubicTo(c1, c2, p) => {
+                for step in 1..=CURVE_STEPS {
+                    let t = step as f32 / CURVE_STEPS as f32;
+                    let u = 1.0 - t;
+                    let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+                    current.points.push(Point::from_xy(
+                        a * last.x + b * c1.x + c * c2.x + d * p.x,
+                        a * last.y + b * c1.y + c * c2.y + d * p.y,
+                    ));
+                }
+                last = p;
+            }
+            PathSegment::Close => {
+                current.closed = true;
+                let start = current.points.first().copied().unwrap_or(last);
+                polylines.push(std::mem::replace(
+                    &mut current,
+                    Polyline {
+                        points: vec![start],
+                        closed: false,
+                    },
+                ));
+                last = start;
+            }
+        }
+    }
+    if current.points.len() > 1 {
+        polylines.push(current);
+    }
+    polylines
+}
+
+/// Whether `point` is inside the fill of `polylines`, every one of which is filled as if closed
+fn fill_contains(polylines: &[Polyline], point: Point, rule: usvg::FillRule) -> bool {
+    let mut winding = 0;
+    for polyline in polylines {
+        let points = &polyline.points;
+        let closing = points.first().copied();
+        for (a, b) in points
+            .iter()
+            .copied()
+            .zip(points.iter().copied().skip(1).chain(closing))
+        {
+            if a.y <= point.y && b.y > point.y && cross(a, b, point) > 0.0 {
+                winding += 1;
+            } else if a.y > point.y && b.y <= point.y && cross(a, b, point) < 0.0 {
+                winding -= 1;
+            }
+        }
+    }
+    match rule {
+        usvg::FillRule::NonZero => winding != 0,
+        usvg::FillRule::EvenOdd => winding % 2 != 0,
+    }
+}
+
+/// Which side of the line through `a` and `b` the point `p` is on
+fn cross(a: Point, b: Point, p: Point) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (p.x - a.x) * (b.y - a.y)
+}
+
+/// Whether `point` is within `half_width` of the outline of `polylines`
+fn stroke_contains(polylines: &[Polyline], point: Point, half_width: f32) -> bool {
+    polylines.iter().any(|polyline| {
+        let points = &polyline.points;
+        let closing = polyline
+            .closed
+            .then(|| points.last().copied().zip(points.first().copied()))
+            .flatten();
+        points
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .chain(closing)
+            .any(|(a, b)| distance_to_segment(point, a, b) <= half_width)
+    })
+}
+
+fn distance_to_segment(p: Point, a: Point, b: Point) -> f32 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared > 0.0 {
+        (((p.x - a.x) * dx + (p.y - a.y) * dy) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let (nx, ny) = (a.x + t * dx - p.x, a.y + t * dy - p.y);
+    (nx * nx + ny * ny).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SVGDocument;
+
+    const SVG: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+        <g id="link">
+            <rect id="button" x="10" y="10" width="30" height="30" fill="red"/>
+        </g>
+        <rect id="overlay" x="20" y="20" width="30" height="30" fill="blue" pointer-events="none"/>
+        <circle id="ring" cx="70" cy="70" r="20" fill="none" stroke="black" stroke-width="4"/>
+        <g style="pointer-events: all">
+            <circle id="hidden" cx="70" cy="20" r="10" fill="green" visibility="hidden"/>
+        </g>
+        <path id="donut" fill-rule="evenodd" fill="black"
+              d="M 10 60 H 40 V 90 H 10 Z M 20 70 H 30 V 80 H 20 Z"/>
+    </svg>"#;
+
+    fn hit(doc: &SVGDocument, x: f32, y: f32) -> Option<Vec<String>> {
+        doc.hit_test(x, y).map(|hit| hit.ids)
+    }
+
+    #[test]
+    fn finds_the_topmost_element_under_a_point() {
+        let doc = SVGDocument::from_str(SVG).unwrap();
+
+        // The overlay is on top but lets events through to the button.
+        assert_eq!(
+            hit(&doc, 25.0, 25.0),
+            Some(vec!["button".to_string(), "link".to_string()])
+        );
+        assert_eq!(hit(&doc, 45.0, 45.0), None);
+
+        // The ring is only painted along its stroke.
+        assert_eq!(hit(&doc, 70.0, 51.0), Some(vec!["ring".to_string()]));
+        assert_eq!(hit(&doc, 70.0, 70.0), None);
+
+        // Hidden elements are hit when the pointer-events they inherit say so.
+        assert_eq!(hit(&doc, 70.0, 20.0), Some(vec!["hidden".to_string()]));
+
+        // Fill rules decide what is inside.
+        assert_eq!(hit(&doc, 15.0, 65.0), Some(vec!["donut".to_string()]));
+        assert_eq!(hit(&doc, 25.0, 75.0), None);
+    }
+
+    #[test]
+    fn parses_pointer_events() {
+        assert_eq!(PointerEvents::parse("auto"), Some(PointerEvents::VisiblePainted));
+        assert_eq!(
+            PointerEvents::parse(" visibleStroke "),
+            Some(PointerEvents::VisibleStroke)
+        );
+        assert_eq!(PointerEvents::parse("NONE"), Some(PointerEvents::None));
+        assert_eq!(PointerEvents::parse("sometimes"), None);
+    }
+}
@@ -3,8 +3,13 @@ use gosub_interface::config::HasDocument;
 use gosub_interface::document::Document;
 use gosub_shared::node::NodeId;
 use gosub_shared::types::Result;
+use std::collections::HashMap;
 use std::sync::{Arc, OnceLock};
 
+mod hit_test;
+
+pub use hit_test::{hit_test_tree, PointerEvents, SvgHit};
+
 /// Return `usvg::Options` backed by a shared fontdb that has system fonts loaded.
 fn svg_options() -> usvg::Options<'static> {
     static FONTDB: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
@@ -21,6 +26,8 @@ fn svg_options() -> usvg::Options<'static> {
 
 pub struct SVGDocument {
     pub tree: usvg::Tree,
+    /// `pointer-events` of the elements with an id, inherited values included
+    pointer_events: HashMap<String, PointerEvents>,
 }
 
 impl SVGDocument {
@@ -29,7 +36,10 @@ impl SVGDocument {
         let opts = svg_options();
 
         let tree = usvg::Tree::from_str(svg, &opts)?;
-        Ok(Self { tree })
+        Ok(Self {
+            tree,
+            pointer_events: pointer_events(svg),
+        })
     }
 
     pub fn from_html_doc<C: HasDocument>(id: NodeId, doc: C::Document) -> Result<Self> {
@@ -37,4 +47,44 @@ impl SVGDocument {
 
         Self::from_str(&str)
     }
+
+    /// The topmost element under `(x, y)` in user space (see [`hit_test_tree`]), with the
+    /// `pointer-events` set in the source.
+    pub fn hit_test(&self, x: f32, y: f32) -> Option<SvgHit> {
+        hit_test_tree(&self.tree, x, y, &|id| self.pointer_events.get(id).copied())
+    }
+}
+
+/// The `pointer-events` of every element with an id in `svg`, from its presentation attribute or
+/// `style` declaration or else from its parent. usvg doesn't keep the property.
+fn pointer_events(svg: &str) -> HashMap<String, PointerEvents> {
+    fn walk(node: roxmltree::Node, inherited: PointerEvents, out: &mut HashMap<String, PointerEvents>) {
+        let declared = node
+            .attribute("style")
+            .and_then(|style| {
+                style.split(';').find_map(|declaration| {
+                    let (name, value) = declaration.split_once(':')?;
+                    name.trim().eq_ignore_ascii_case("pointer-events").then_some(value)
+                })
+            })
+            .or_else(|| node.attribute("pointer-events"))
+            .and_then(PointerEvents::parse);
+        let value = declared.unwrap_or(inherited);
+        if let Some(id) = node.attribute("id") {
+            out.insert(id.to_string(), value);
+        }
+        for child in node.children().filter(roxmltree::Node::is_element) {
+            walk(child, value, out);
+        }
+    }
+
+    let mut out = HashMap::new();
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    if let Ok(doc) = roxmltree::Document::parse_with_options(svg, options) {
+        walk(doc.root_element(), PointerEvents::default(), &mut out);
+    }
+    out
 }
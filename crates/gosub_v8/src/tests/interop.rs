@@ -4,9 +4,9 @@ use std::rc::Rc;
 use crate::V8Engine;
 use gosub_shared::types::Result;
 use gosub_webexecutor::js::{
-    Args, IntoRustValue, IntoWebValue, JSInterop, VariadicArgs, VariadicArgsInternal, WebContext, WebFunction,
-    WebFunctionCallBack, WebFunctionCallBackVariadic, WebFunctionVariadic, WebGetterCallback, WebObject, WebRuntime,
-    WebSetterCallback, WebValue,
+    define_class, run_local_tasks, spawn_local, Args, IntoRustValue, IntoWebValue, JSInterop, VariadicArgs,
+    VariadicArgsInternal, WebContext, WebFunction, WebFunctionCallBack, WebFunctionCallBackVariadic,
+    WebFunctionVariadic, WebGetterCallback, WebObject, WebPromise, WebRuntime, WebSetterCallback, WebValue,
 };
 use gosub_webinterop::{web_fns, web_interop};
 
@@ -160,6 +160,158 @@ fn macro_interop() {
     }
 }
 
+#[web_interop(js_name = node, class = Node)]
+struct NodeStruct {}
+
+#[web_interop(js_name = element, class = Element, extends = Node)]
+struct ElementStruct {}
+
+#[web_interop(js_name = htmlElement, class = HTMLElement, extends = Element)]
+struct HtmlElementStruct {}
+
+#[test]
+fn prototype_chains() {
+    let mut engine = V8Engine::new();
+    let mut context = engine.new_context().unwrap();
+
+    // A type may come before the one it extends.
+    HtmlElementStruct::implement::<V8Engine>(Rc::new(RefCell::new(HtmlElementStruct {})), context.clone()).unwrap();
+    ElementStruct::implement::<V8Engine>(Rc::new(RefCell::new(ElementStruct {})), context.clone()).unwrap();
+    NodeStruct::implement::<V8Engine>(Rc::new(RefCell::new(NodeStruct {})), context.clone()).unwrap();
+
+    let out = context
+        .run(
+            r#"
+        [
+            htmlElement instanceof HTMLElement,
+            htmlElement instanceof Element,
+            htmlElement instanceof Node,
+            element instanceof Node,
+            element instanceof HTMLElement,
+            node instanceof Element,
+            Object.getPrototypeOf(HTMLElement.prototype) === Element.prototype,
+            HTMLElement.name,
+        ]
+        "#,
+        )
+        .unwrap();
+    assert_eq!(
+        out.as_string().unwrap(),
+        "true,true,true,true,false,false,true,HTMLElement"
+    );
+
+    assert!(context.run("new Node()").is_err());
+}
+
+#[web_interop]
+struct Accessors {
+    #[property(readonly)]
+    id: u32,
+    #[property(
+        getter = "Accessors::get_celsius",
+        setter = "Accessors::set_celsius",
+        rename = "fahrenheit"
+    )]
+    celsius: f64,
+    name: String,
+}
+
+impl Accessors {
+    fn get_celsius(&self) -> f64 {
+        self.celsius * 9.0 / 5.0 + 32.0
+    }
+
+    fn set_celsius(&mut self, fahrenheit: f64) {
+        self.celsius = (fahrenheit - 32.0) * 5.0 / 9.0;
+    }
+}
+
+#[web_fns(1)]
+impl Accessors {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+}
+
+#[test]
+fn accessor_properties() {
+    let mut engine = V8Engine::new();
+    let mut context = engine.new_context().unwrap();
+
+    let accessors = Rc::new(RefCell::new(Accessors {
+        id: 7,
+        celsius: 100.0,
+        name: "kettle".into(),
+    }));
+    Accessors::implement::<V8Engine>(accessors.clone(), context.clone()).unwrap();
+
+    let out = context
+        .run("[Accessors.id, Accessors.fahrenheit, Accessors.name()]")
+        .unwrap();
+    assert_eq!(out.as_string().unwrap(), "7,212,kettle");
+
+    assert!(context.run("Accessors.id = 8").is_err());
+    context.run("Accessors.fahrenheit = 32").unwrap();
+
+    assert_eq!(accessors.borrow().id, 7);
+    assert_eq!(accessors.borrow().celsius, 0.0);
+}
+
+#[web_interop]
+#[derive(Clone)]
+struct AsyncStruct {
+    #[property]
+    factor: i32,
+}
+
+#[web_fns(1)]
+impl AsyncStruct {
+    async fn scale(&self, num: i32) -> i32 {
+        self.factor * num
+    }
+
+    async fn concat(a: String, b: String) -> String {
+        a + &b
+    }
+}
+
+#[test]
+fn async_interop() {
+    let mut engine = V8Engine::new();
+    let mut context = engine.new_context().unwrap();
+
+    AsyncStruct::implement::<V8Engine>(Rc::new(RefCell::new(AsyncStruct { factor: 3 })), context.clone()).unwrap();
+
+    let out = context
+        .run(
+            r#"
+        globalThis.results = []
+        AsyncStruct.scale(4).then((v) => results.push(v))
+        AsyncStruct.concat("a", "b").then((v) => results.push(v))
+        AsyncStruct.scale(4) instanceof Promise
+        "#,
+        )
+        .expect("failed to run");
+    assert!(out.as_bool().unwrap());
+    assert_eq!(context.run("results.length").unwrap().as_number().unwrap(), 0.0);
+
+    run_local_tasks();
+    context.perform_microtask_checkpoint().unwrap();
+
+    let out = context.run("results.join()").unwrap();
+    assert_eq!(out.as_string().unwrap(), "12,ab");
+
+    // The method sees the value as it was when called, and holds no borrow while pending.
+    context
+        .run("AsyncStruct.scale(2).then((v) => results.push(v)); AsyncStruct.factor = 5")
+        .unwrap();
+    run_local_tasks();
+    context.perform_microtask_checkpoint().unwrap();
+
+    let out = context.run("results.join()").unwrap();
+    assert_eq!(out.as_string().unwrap(), "12,ab,6");
+}
+
 #[derive(Debug)]
 struct Test2 {
     field: i32,
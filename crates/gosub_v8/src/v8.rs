@@ -14,16 +14,22 @@ pub use compile::*;
 pub use context::*;
 pub use function::*;
 use gosub_shared::types::Result;
-use gosub_webexecutor::js::WebRuntime;
+use gosub_webexecutor::js::{ExecutionLimits, WebRuntime};
+pub use inspector::*;
 pub use object::*;
 pub use value::*;
+pub use wrapper_cache::*;
 
 mod array;
 mod compile;
 mod context;
 mod function;
+mod inspector;
+mod limits;
+mod module;
 mod object;
 mod value;
+mod wrapper_cache;
 
 // status of the V8 engine
 static V8_INITIALIZING: AtomicBool = AtomicBool::new(false);
@@ -197,6 +203,10 @@ impl WebRuntime for V8Engine {
     fn new_context(&mut self) -> Result<Self::Context> {
         V8Context::with_default()
     }
+
+    fn new_context_with_limits(&mut self, limits: &ExecutionLimits) -> Result<Self::Context> {
+        V8Context::with_limits(limits)
+    }
 }
 
 #[cfg(test)]
@@ -1,14 +1,27 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
 use v8::{CreateParams, Global, HandleScope, Isolate, Local, OwnedIsolate, StackFrame, StackTrace, TryCatch};
 
 use gosub_shared::types::Result;
-use gosub_webexecutor::js::{JSError, WebCompiled, WebContext, WebRuntime};
+use gosub_webexecutor::js::{
+    InspectorSend, InspectorWait, JSError, LimitViolation, ModuleGraph, WebCompiled, WebContext, WebInspectorSession,
+    WebInterrupt, WebRuntime,
+};
 use gosub_webexecutor::Error;
 
+use crate::v8::limits::LimitState;
 use crate::{FromContext, V8Compiled, V8Context, V8Engine};
 
 pub struct V8Ctx {
     isolate: OwnedIsolate, // Safety: this should NEVER be replaced with a new isolate
     pub ctx: Global<v8::Context>,
+    /// The ES modules compiled in this context, by URL
+    pub(crate) modules: HashMap<String, Global<v8::Module>>,
+    /// What the scripts ran into; V8's heap callback points at it, so it outlives the isolate
+    pub(crate) limits: Arc<LimitState>,
+    /// The heap limit the isolate was created with, if it has one of its own
+    pub(crate) max_heap: Option<usize>,
 
     parent_scope: Option<HandleScope<'static>>, // Safety: this does NOT have an actual 'static lifetime
 }
@@ -30,6 +43,9 @@ impl Drop for ScopeGuard<'_> {
 impl V8Ctx {
     pub(crate) fn new(params: CreateParams) -> Self {
         let mut isolate = Isolate::new(params);
+        let limits = Arc::new(LimitState::new(isolate.thread_safe_handle()));
+        // The event loop decides when promise jobs run, see `perform_microtask_checkpoint`.
+        isolate.set_microtasks_policy(v8::MicrotasksPolicy::Explicit);
 
         let ctx = {
             let mut handle_scope = HandleScope::new(&mut isolate);
@@ -42,6 +58,9 @@ impl V8Ctx {
         Self {
             isolate,
             ctx,
+            modules: HashMap::new(),
+            limits,
+            max_heap: None,
             parent_scope: None,
         }
     }
@@ -188,4 +207,53 @@ impl WebContext for V8Context {
 
         Ok(())
     }
+
+    fn run_module(&mut self, url: &str, graph: &ModuleGraph) -> Result<<Self::RT as WebRuntime>::Value> {
+        self.run_module_graph(url, graph)
+    }
+
+    fn connect_inspector(
+        &mut self,
+        name: &str,
+        send: InspectorSend,
+        wait: InspectorWait,
+    ) -> Option<Box<dyn WebInspectorSession>> {
+        Some(Box::new(self.connect_debugger(name, send, wait)))
+    }
+
+    fn interrupt_handle(&self) -> Option<Arc<dyn WebInterrupt>> {
+        Some(self.interrupt())
+    }
+
+    fn take_limit_violation(&mut self) -> Option<LimitViolation> {
+        self.limit_violation()
+    }
+
+    fn perform_microtask_checkpoint(&mut self) -> Result<()> {
+        let mut c = self.borrow_mut();
+        let scope = &mut c.new_scope();
+        scope.perform_microtask_checkpoint();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gosub_webexecutor::js::{WebContext, WebRuntime, WebValue};
+
+    use crate::v8::V8Engine;
+
+    #[test]
+    fn promise_jobs_wait_for_a_checkpoint() {
+        let mut engine = V8Engine::new();
+        let mut context = engine.new_context().unwrap();
+
+        context
+            .run("globalThis.log = []; Promise.resolve().then(() => log.push('job')); log.push('task');")
+            .unwrap();
+        assert_eq!(context.run("log.join()").unwrap().as_string().unwrap(), "task");
+
+        context.perform_microtask_checkpoint().unwrap();
+        assert_eq!(context.run("log.join()").unwrap().as_string().unwrap(), "task,job");
+    }
 }
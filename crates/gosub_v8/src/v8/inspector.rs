@@ -0,0 +1,218 @@
+use std::cell::Cell;
+use std::ptr::NonNull;
+use std::rc::Rc;
+
+use v8::inspector::{
+    ChannelBase, ChannelImpl, StringBuffer, StringView, V8Inspector, V8InspectorClientBase, V8InspectorClientImpl,
+    V8InspectorClientTrustLevel, V8InspectorSession,
+};
+use v8::{Local, UniquePtr, UniqueRef};
+
+use gosub_webexecutor::js::{InspectorSend, InspectorWait, WebInspectorSession};
+
+use crate::V8Context;
+
+/// The inspector only ever sees the one context of its isolate.
+const CONTEXT_GROUP_ID: i32 = 1;
+
+/// Makes the context the one DevTools evaluates console input in.
+const CONTEXT_AUX_DATA: &str = r#"{"isDefault":true}"#;
+
+/// The session of [`InspectorClient`], once there is one.
+type SessionPtr = Rc<Cell<Option<NonNull<V8InspectorSession>>>>;
+
+/// A debugger attached to a [`V8Context`]. Each context gets an inspector of its own, created
+/// when the debugger attaches.
+pub struct V8Debugger {
+    // Dropped in this order: the session before the inspector it belongs to, the inspector
+    // before the client and channel it points at, and all of them before the isolate.
+    session: UniqueRef<V8InspectorSession>,
+    _inspector: UniqueRef<V8Inspector>,
+    _channel: Box<InspectorChannel>,
+    _client: Box<InspectorClient>,
+    session_ptr: SessionPtr,
+    _ctx: V8Context,
+}
+
+impl V8Context {
+    pub(crate) fn connect_debugger(&mut self, name: &str, send: InspectorSend, wait: InspectorWait) -> V8Debugger {
+        let session_ptr = SessionPtr::default();
+        let mut client = Box::new(InspectorClient {
+            base: V8InspectorClientBase::new::<InspectorClient>(),
+            paused: Rc::new(Cell::new(false)),
+            wait,
+            session: Rc::clone(&session_ptr),
+        });
+        let mut inspector = V8Inspector::create(&mut self.isolate(), &mut *client);
+
+        {
+            let scope = &mut self.scope();
+            let context = Local::new(scope, &self.borrow().ctx);
+            inspector.context_created(
+                context,
+                CONTEXT_GROUP_ID,
+                StringView::from(name.as_bytes()),
+                StringView::from(CONTEXT_AUX_DATA.as_bytes()),
+            );
+        }
+
+        let mut channel = Box::new(InspectorChannel {
+            base: ChannelBase::new::<InspectorChannel>(),
+            send,
+        });
+        let mut session = inspector.connect(
+            CONTEXT_GROUP_ID,
+            &mut *channel,
+            StringView::empty(),
+            V8InspectorClientTrustLevel::FullyTrusted,
+        );
+        session_ptr.set(Some(NonNull::from(&mut *session)));
+
+        V8Debugger {
+            session,
+            _inspector: inspector,
+            _channel: channel,
+            _client: client,
+            session_ptr,
+            _ctx: V8Context::clone(self),
+        }
+    }
+}
+
+impl WebInspectorSession for V8Debugger {
+    fn dispatch(&mut self, message: &str) {
+        self.session
+            .dispatch_protocol_message(StringView::from(message.as_bytes()));
+    }
+}
+
+impl Drop for V8Debugger {
+    fn drop(&mut self) {
+        self.session_ptr.set(None);
+    }
+}
+
+/// Keeps the page's thread while a breakpoint holds it, handling the debugger's messages (steps,
+/// evaluations, resuming) until V8 lets it go.
+struct InspectorClient {
+    base: V8InspectorClientBase,
+    paused: Rc<Cell<bool>>,
+    wait: InspectorWait,
+    session: SessionPtr,
+}
+
+impl V8InspectorClientImpl for InspectorClient {
+    fn base(&self) -> &V8InspectorClientBase {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut V8InspectorClientBase {
+        &mut self.base
+    }
+
+    unsafe fn base_ptr(this: *const Self) -> *const V8InspectorClientBase
+    where
+        Self: Sized,
+    {
+        // SAFETY: the caller hands us a valid pointer to the client
+        unsafe { std::ptr::addr_of!((*this).base) }
+    }
+
+    fn run_message_loop_on_pause(&mut self, _context_group_id: i32) {
+        let paused = Rc::clone(&self.paused);
+        paused.set(true);
+        while paused.get() {
+            // A debugger that went away can't resume the page, so we do.
+            let Some(message) = (self.wait)() else {
+                break;
+            };
+            let Some(mut session) = self.session.get() else {
+                break;
+            };
+            // SAFETY: the pointer is cleared before the session is dropped, and V8 pauses from
+            // inside a call on this thread, while the session is still there.
+            unsafe { session.as_mut() }.dispatch_protocol_message(StringView::from(message.as_bytes()));
+        }
+        paused.set(false);
+    }
+
+    fn quit_message_loop_on_pause(&mut self) {
+        self.paused.set(false);
+    }
+}
+
+/// Hands what V8 has to say to the debugger.
+struct InspectorChannel {
+    base: ChannelBase,
+    send: InspectorSend,
+}
+
+impl InspectorChannel {
+    fn send(&mut self, message: UniquePtr<StringBuffer>) {
+        if let Some(message) = message.into_option() {
+            (self.send)(message.string().to_string());
+        }
+    }
+}
+
+impl ChannelImpl for InspectorChannel {
+    fn base(&self) -> &ChannelBase {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut ChannelBase {
+        &mut self.base
+    }
+
+    unsafe fn base_ptr(this: *const Self) -> *const ChannelBase
+    where
+        Self: Sized,
+    {
+        // SAFETY: the caller hands us a valid pointer to the channel
+        unsafe { std::ptr::addr_of!((*this).base) }
+    }
+
+    fn send_response(&mut self, _call_id: i32, message: UniquePtr<StringBuffer>) {
+        self.send(message);
+    }
+
+    fn send_notification(&mut self, message: UniquePtr<StringBuffer>) {
+        self.send(message);
+    }
+
+    fn flush_protocol_notifications(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use gosub_webexecutor::js::{WebContext, WebRuntime};
+
+    use crate::V8Engine;
+
+    use super::*;
+
+    #[test]
+    fn debugger_evaluates_in_the_page() {
+        let mut engine = V8Engine::new();
+        let mut ctx = engine.new_context().unwrap();
+        ctx.run("globalThis.answer = 42").unwrap();
+
+        let sent = Rc::new(RefCell::new(Vec::new()));
+        let out = Rc::clone(&sent);
+        let mut debugger = ctx
+            .connect_inspector(
+                "page",
+                Box::new(move |message| out.borrow_mut().push(message)),
+                Box::new(|| None),
+            )
+            .unwrap();
+
+        debugger.dispatch(r#"{"id":1,"method":"Runtime.evaluate","params":{"expression":"answer + 1"}}"#);
+
+        let sent = sent.borrow();
+        let response = sent.iter().find(|m| m.contains(r#""id":1"#)).unwrap();
+        assert!(response.contains(r#""value":43"#), "{response}");
+    }
+}
@@ -0,0 +1,141 @@
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use v8::{CreateParams, IsolateHandle};
+
+use gosub_shared::types::Result;
+use gosub_webexecutor::js::{ExecutionLimits, LimitViolation, WebInterrupt};
+
+use crate::V8Context;
+
+/// What a context's scripts ran into, shared with the watchdog and V8's heap callback.
+pub(crate) struct LimitState {
+    handle: IsolateHandle,
+    interrupted: AtomicBool,
+    heap_exceeded: AtomicBool,
+}
+
+impl LimitState {
+    pub(crate) fn new(handle: IsolateHandle) -> Self {
+        Self {
+            handle,
+            interrupted: AtomicBool::new(false),
+            heap_exceeded: AtomicBool::new(false),
+        }
+    }
+}
+
+/// Interrupts the scripts of a [`V8Context`] from another thread.
+struct V8Interrupt(Arc<LimitState>);
+
+impl WebInterrupt for V8Interrupt {
+    fn interrupt(&self) {
+        self.0.interrupted.store(true, Ordering::SeqCst);
+        self.0.handle.terminate_execution();
+    }
+}
+
+/// Called by V8 when the heap is about to run out. Terminates the script instead of the process.
+extern "C" fn near_heap_limit(data: *mut c_void, current_heap_limit: usize, _initial_heap_limit: usize) -> usize {
+    // SAFETY: `data` is the context's `LimitState`, which lives as long as the isolate.
+    let state = unsafe { &*(data as *const LimitState) };
+    state.heap_exceeded.store(true, Ordering::SeqCst);
+    state.handle.terminate_execution();
+    // Room for the script to unwind; the limit is put back once it has.
+    current_heap_limit * 2
+}
+
+impl V8Context {
+    pub(crate) fn with_limits(limits: &ExecutionLimits) -> Result<Self> {
+        let mut params = CreateParams::default();
+        if let Some(max_heap) = limits.max_heap {
+            params = params.heap_limits(0, max_heap);
+        }
+        let ctx = Self::new(params)?;
+        if limits.max_heap.is_some() {
+            ctx.borrow_mut().max_heap = limits.max_heap;
+            ctx.watch_heap();
+        }
+        Ok(ctx)
+    }
+
+    fn watch_heap(&self) {
+        let state = Arc::as_ptr(&self.borrow().limits) as *mut c_void;
+        self.isolate().add_near_heap_limit_callback(near_heap_limit, state);
+    }
+
+    pub(crate) fn interrupt(&self) -> Arc<dyn WebInterrupt> {
+        Arc::new(V8Interrupt(Arc::clone(&self.borrow().limits)))
+    }
+
+    pub(crate) fn limit_violation(&mut self) -> Option<LimitViolation> {
+        let (state, max_heap) = {
+            let this = self.borrow();
+            (Arc::clone(&this.limits), this.max_heap)
+        };
+        let violation = if state.heap_exceeded.swap(false, Ordering::SeqCst) {
+            if let Some(max_heap) = max_heap {
+                let mut isolate = self.isolate();
+                isolate.remove_near_heap_limit_callback(near_heap_limit, max_heap);
+                isolate.add_near_heap_limit_callback(near_heap_limit, Arc::as_ptr(&state) as *mut c_void);
+            }
+            LimitViolation::Heap
+        } else if state.interrupted.swap(false, Ordering::SeqCst) {
+            LimitViolation::TimeSlice
+        } else {
+            return None;
+        };
+        self.isolate().cancel_terminate_execution();
+        // An interrupt can also land after the script was done; either way the context is
+        // usable again.
+        state.interrupted.store(false, Ordering::SeqCst);
+        Some(violation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use gosub_webexecutor::js::{Watchdog, WebContext, WebRuntime, WebValue};
+
+    use crate::V8Engine;
+
+    use super::*;
+
+    #[test]
+    fn runaway_scripts_are_interrupted() {
+        let mut engine = V8Engine::new();
+        let limits = ExecutionLimits {
+            time_slice: Some(Duration::from_millis(50)),
+            max_heap: None,
+        };
+        let mut ctx = engine.new_context_with_limits(&limits).unwrap();
+        let watchdog = Watchdog::new(Duration::from_millis(50)).unwrap();
+        watchdog.watch(ctx.interrupt_handle());
+
+        watchdog.start();
+        assert!(ctx.run("while (true) {}").is_err());
+        watchdog.stop();
+        assert_eq!(ctx.take_limit_violation(), Some(LimitViolation::TimeSlice));
+
+        assert_eq!(ctx.run("1 + 1").unwrap().as_number().unwrap(), 2.0);
+        assert_eq!(ctx.take_limit_violation(), None);
+    }
+
+    #[test]
+    fn heap_hogs_are_stopped() {
+        let mut engine = V8Engine::new();
+        let limits = ExecutionLimits {
+            time_slice: None,
+            max_heap: Some(32 * 1024 * 1024),
+        };
+        let mut ctx = engine.new_context_with_limits(&limits).unwrap();
+
+        assert!(ctx
+            .run("const hog = []; while (true) hog.push(new Array(1000).fill(1));")
+            .is_err());
+        assert_eq!(ctx.take_limit_violation(), Some(LimitViolation::Heap));
+    }
+}
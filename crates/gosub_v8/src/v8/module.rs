@@ -0,0 +1,155 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use v8::{CallbackScope, Context, FixedArray, Global, HandleScope, Local, Module, ModuleStatus, TryCatch};
+
+use gosub_shared::types::Result;
+use gosub_webexecutor::js::{JSError, ModuleGraph};
+use gosub_webexecutor::Error;
+
+use crate::{V8Context, V8Ctx, V8Value};
+
+thread_local! {
+    /// What the imports of the modules being linked resolve to: module identity hash to import
+    /// specifier to module. The resolve callback gets no state of its own, and linking happens
+    /// on the isolate's thread.
+    static RESOLVING: RefCell<HashMap<i32, HashMap<String, Global<Module>>>> = RefCell::new(HashMap::new());
+}
+
+impl V8Context {
+    pub(crate) fn run_module_graph(&mut self, url: &str, graph: &ModuleGraph) -> Result<V8Value> {
+        let ctx = V8Context::clone(self);
+        let scope = &mut self.scope();
+        let try_catch = &mut TryCatch::new(scope);
+
+        // Compile the modules the entry needs that this context hasn't seen yet.
+        let mut queue = vec![url.to_owned()];
+        while let Some(current) = queue.pop() {
+            if self.borrow().modules.contains_key(&current) {
+                continue;
+            }
+            let Some(record) = graph.get(&current) else {
+                return Err(Error::JS(JSError::Compile(format!("module {current} is not in the graph"))).into());
+            };
+            let Some(module) = compile_module(try_catch, &current, &record.source) else {
+                return Err(V8Ctx::report_exception(try_catch).into());
+            };
+            let module = Global::new(try_catch, module);
+            self.borrow_mut().modules.insert(current, module);
+            queue.extend(record.imports.values().cloned());
+        }
+
+        let mut resolving = HashMap::new();
+        let entry = {
+            let this = self.borrow();
+            for (module_url, record) in graph.iter() {
+                let Some(module) = this.modules.get(module_url) else {
+                    continue;
+                };
+                let hash = Local::new(try_catch, module).get_identity_hash().get();
+                let imports = record
+                    .imports
+                    .iter()
+                    .filter_map(|(specifier, dep)| Some((specifier.clone(), this.modules.get(dep)?.clone())))
+                    .collect();
+                resolving.insert(hash, imports);
+            }
+            this.modules.get(url).cloned()
+        };
+        let Some(entry) = entry else {
+            return Err(Error::JS(JSError::Compile(format!("module {url} is not in the graph"))).into());
+        };
+        let entry = Local::new(try_catch, entry);
+
+        RESOLVING.with(|r| *r.borrow_mut() = resolving);
+        let linked = entry.instantiate_module(try_catch, resolve);
+        RESOLVING.with(|r| r.borrow_mut().clear());
+        if linked != Some(true) {
+            return Err(V8Ctx::report_exception(try_catch).into());
+        }
+
+        let Some(value) = entry.evaluate(try_catch) else {
+            return Err(V8Ctx::report_exception(try_catch).into());
+        };
+        if entry.get_status() == ModuleStatus::Errored {
+            let exception = entry.get_exception().to_rust_string_lossy(try_catch);
+            return Err(Error::JS(JSError::Exception(exception)).into());
+        }
+
+        Ok(V8Value::from_local(ctx, value))
+    }
+}
+
+fn compile_module<'s>(scope: &mut HandleScope<'s>, url: &str, source: &str) -> Option<Local<'s, Module>> {
+    let name = v8::String::new(scope, url)?;
+    let code = v8::String::new(scope, source)?;
+    let origin = v8::ScriptOrigin::new(scope, name.into(), 0, 0, false, 0, None, false, false, true, None);
+    let mut source = v8::script_compiler::Source::new(code, Some(&origin));
+    v8::script_compiler::compile_module(scope, &mut source)
+}
+
+fn resolve<'a>(
+    context: Local<'a, Context>,
+    specifier: Local<'a, v8::String>,
+    _import_attributes: Local<'a, FixedArray>,
+    referrer: Local<'a, Module>,
+) -> Option<Local<'a, Module>> {
+    // Safety: V8 calls this while linking, from within the context it passes.
+    let scope = &mut unsafe { CallbackScope::new(context) };
+    let specifier = specifier.to_rust_string_lossy(scope);
+    let hash = referrer.get_identity_hash().get();
+    let module = RESOLVING.with(|r| r.borrow().get(&hash)?.get(&specifier).cloned());
+    match module {
+        Some(module) => Some(Local::new(scope, module)),
+        None => {
+            let message = v8::String::new(scope, &format!("Failed to resolve module specifier \"{specifier}\""))?;
+            let exception = v8::Exception::type_error(scope, message);
+            scope.throw_exception(exception);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use gosub_webexecutor::js::{ModuleGraph, ModuleRecord, WebContext, WebRuntime, WebValue};
+
+    use crate::V8Engine;
+
+    #[test]
+    fn modules_import_each_other_once() {
+        let mut engine = V8Engine::new();
+        let mut context = engine.new_context().unwrap();
+        context.run("globalThis.log = [];").unwrap();
+
+        let mut graph = ModuleGraph::new();
+        graph.insert(
+            "https://a.example/main.js",
+            ModuleRecord {
+                source: "import { name } from './a.js'; log.push('main:' + name);".into(),
+                imports: HashMap::from([("./a.js".into(), "https://a.example/a.js".into())]),
+            },
+        );
+        // `a` and `b` import each other.
+        graph.insert(
+            "https://a.example/a.js",
+            ModuleRecord {
+                source: "import './b.js'; export const name = 'a'; log.push('a');".into(),
+                imports: HashMap::from([("./b.js".into(), "https://a.example/b.js".into())]),
+            },
+        );
+        graph.insert(
+            "https://a.example/b.js",
+            ModuleRecord {
+                source: "import './a.js'; log.push('b');".into(),
+                imports: HashMap::from([("./a.js".into(), "https://a.example/a.js".into())]),
+            },
+        );
+
+        context.run_module("https://a.example/main.js", &graph).unwrap();
+        context.run_module("https://a.example/a.js", &graph).unwrap();
+        assert_eq!(context.run("log.join()").unwrap().as_string().unwrap(), "b,a,main:a");
+    }
+}
@@ -0,0 +1,222 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::rc::Rc;
+
+use v8::{Global, Local, Object, Weak};
+
+use gosub_shared::types::Result;
+
+use crate::{V8Context, V8Object};
+
+/// The JS wrappers of native objects, such as DOM nodes, by the key of the object they wrap.
+///
+/// A wrapper is held weakly, so JS decides how long it lives: as long as a script can reach it,
+/// asking for the key's wrapper hands out that same object (expando properties and all), and
+/// once it is garbage it is collected and the key shows up in [`Self::take_collected`], telling
+/// the native side that JS no longer refers to its object. A wrapper that has to survive without
+/// references from JS, e.g. because its node is in the document and carries event listeners,
+/// can be [pinned](Self::pin).
+pub struct WrapperCache<K> {
+    wrappers: RefCell<HashMap<K, Wrapper>>,
+    /// Keys whose wrapper was collected, with its generation. Finalizers only ever push here,
+    /// and never while the cache is using it, so collection can happen during any V8 call.
+    dead: Rc<RefCell<Vec<(K, u64)>>>,
+    collected: RefCell<Vec<K>>,
+    next_generation: Cell<u64>,
+}
+
+struct Wrapper {
+    weak: Weak<Object>,
+    /// Keeps the wrapper alive while it is pinned
+    pinned: Option<Global<Object>>,
+    /// Tells a collected wrapper's finalizer from that of the one that replaced it
+    generation: u64,
+}
+
+impl<K: Clone + Eq + Hash + 'static> WrapperCache<K> {
+    pub fn new() -> Self {
+        Self {
+            wrappers: RefCell::new(HashMap::new()),
+            dead: Rc::new(RefCell::new(Vec::new())),
+            collected: RefCell::new(Vec::new()),
+            next_generation: Cell::new(0),
+        }
+    }
+
+    /// The wrapper of `key`, if it has one that is still alive.
+    pub fn get(&self, ctx: &V8Context, key: &K) -> Option<V8Object> {
+        self.reap();
+        let scope = &mut ctx.scope();
+        let local = self.wrappers.borrow().get(key)?.weak.to_local(scope);
+        match local {
+            Some(local) => Some(V8Object {
+                ctx: V8Context::clone(ctx),
+                value: Global::new(scope, local),
+            }),
+            None => {
+                // Collected, with the finalizer yet to run.
+                self.forget(key);
+                None
+            }
+        }
+    }
+
+    /// The wrapper of `key`, made by `create` when it has none.
+    pub fn get_or_insert_with(
+        &self,
+        ctx: &V8Context,
+        key: K,
+        create: impl FnOnce() -> Result<V8Object>,
+    ) -> Result<V8Object> {
+        if let Some(wrapper) = self.get(ctx, &key) {
+            return Ok(wrapper);
+        }
+        let wrapper = create()?;
+        self.insert(ctx, key, &wrapper);
+        Ok(wrapper)
+    }
+
+    /// Makes `wrapper` the wrapper of `key`, in place of the one it had.
+    pub fn insert(&self, ctx: &V8Context, key: K, wrapper: &V8Object) {
+        self.reap();
+        let generation = self.next_generation.get();
+        self.next_generation.set(generation + 1);
+
+        // A replacement stays pinned if the old wrapper was.
+        let was_pinned = self.wrappers.borrow().get(&key).is_some_and(|w| w.pinned.is_some());
+        let dead = Rc::downgrade(&self.dead);
+        let finalized = key.clone();
+        let scope = &mut ctx.scope();
+        let local = Local::new(scope, &wrapper.value);
+        let weak = Weak::with_finalizer(
+            scope,
+            local,
+            Box::new(move |_| {
+                if let Some(dead) = dead.upgrade() {
+                    dead.borrow_mut().push((finalized, generation));
+                }
+            }),
+        );
+        self.wrappers.borrow_mut().insert(
+            key,
+            Wrapper {
+                weak,
+                pinned: was_pinned.then(|| Global::new(scope, local)),
+                generation,
+            },
+        );
+    }
+
+    /// Keeps the wrapper of `key` alive without references from JS. Returns whether `key` has
+    /// a wrapper.
+    pub fn pin(&self, ctx: &V8Context, key: &K) -> bool {
+        self.reap();
+        let scope = &mut ctx.scope();
+        let mut wrappers = self.wrappers.borrow_mut();
+        let Some(wrapper) = wrappers.get_mut(key) else {
+            return false;
+        };
+        if wrapper.pinned.is_none() {
+            let Some(local) = wrapper.weak.to_local(scope) else {
+                return false;
+            };
+            wrapper.pinned = Some(Global::new(scope, local));
+        }
+        true
+    }
+
+    /// Lets the wrapper of `key` be collected again once JS is done with it.
+    pub fn unpin(&self, key: &K) {
+        if let Some(wrapper) = self.wrappers.borrow_mut().get_mut(key) {
+            wrapper.pinned = None;
+        }
+    }
+
+    /// Drops the wrapper of `key`, e.g. because its native object is gone. JS may still hold on
+    /// to it, but it won't be handed out again.
+    pub fn remove(&self, key: &K) {
+        self.wrappers.borrow_mut().remove(key);
+    }
+
+    /// The keys whose wrapper was collected since the last call.
+    pub fn take_collected(&self) -> Vec<K> {
+        self.reap();
+        std::mem::take(&mut *self.collected.borrow_mut())
+    }
+
+    /// How many keys have a wrapper, counting collected ones whose finalizer hasn't run yet.
+    pub fn len(&self) -> usize {
+        self.reap();
+        self.wrappers.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forgets the wrappers the finalizers reported as collected.
+    fn reap(&self) {
+        let dead = std::mem::take(&mut *self.dead.borrow_mut());
+        let mut wrappers = self.wrappers.borrow_mut();
+        for (key, generation) in dead {
+            if wrappers.get(&key).is_some_and(|w| w.generation == generation) {
+                wrappers.remove(&key);
+                self.collected.borrow_mut().push(key);
+            }
+        }
+    }
+
+    fn forget(&self, key: &K) {
+        if self.wrappers.borrow_mut().remove(key).is_some() {
+            self.collected.borrow_mut().push(key.clone());
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash + 'static> Default for WrapperCache<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gosub_webexecutor::js::{WebContext, WebObject, WebRuntime, WebValue};
+
+    use crate::{V8Engine, V8Value};
+
+    use super::*;
+
+    #[test]
+    fn wrappers_live_as_long_as_js_needs_them() {
+        let mut engine = V8Engine::new();
+        let mut ctx = engine.new_context().unwrap();
+        let cache = WrapperCache::<usize>::new();
+
+        // The same wrapper comes back while JS holds on to it.
+        let node = cache.get_or_insert_with(&ctx, 1, || V8Object::new(&ctx)).unwrap();
+        let value = V8Value::new_string(ctx.clone(), "expando").unwrap();
+        node.set_property("mark", &value).unwrap();
+        ctx.set_on_global_object("kept", node.into()).unwrap();
+        let again = cache.get_or_insert_with(&ctx, 1, || V8Object::new(&ctx)).unwrap();
+        assert_eq!(again.get_property("mark").unwrap().as_string().unwrap(), "expando");
+        drop(again);
+
+        // A pinned wrapper survives without references; an unpinned one goes.
+        let pinned = cache.get_or_insert_with(&ctx, 2, || V8Object::new(&ctx)).unwrap();
+        assert!(cache.pin(&ctx, &2));
+        drop(pinned);
+        let garbage = cache.get_or_insert_with(&ctx, 3, || V8Object::new(&ctx)).unwrap();
+        drop(garbage);
+
+        ctx.isolate().low_memory_notification();
+
+        assert!(cache.get(&ctx, &1).is_some());
+        assert!(cache.get(&ctx, &2).is_some());
+        assert!(cache.get(&ctx, &3).is_none());
+        assert_eq!(cache.take_collected(), vec![3]);
+        assert!(cache.take_collected().is_empty());
+        assert_eq!(cache.len(), 2);
+    }
+}
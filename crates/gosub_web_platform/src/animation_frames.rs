@@ -0,0 +1,172 @@
+//! The callbacks behind `requestAnimationFrame()`.
+//!
+//! Callbacks wait for the next frame. Right before drawing, the driver calls
+//! [`AnimationFrames::run`]; it runs the callbacks that were queued when the frame started, so a
+//! callback that requests another frame (the usual animation loop) lands in the next one. All
+//! callbacks of a frame see the same timestamp, measured from the document's time origin with
+//! [`high_res_timestamp`].
+
+use std::cell::RefCell;
+use std::fmt::{Debug, Display, Formatter};
+use std::rc::Rc;
+use std::time::Instant;
+
+/// The id `requestAnimationFrame()` returns. Ids are never 0.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AnimationFrameId(u32);
+
+impl AnimationFrameId {
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
+}
+
+impl From<u32> for AnimationFrameId {
+    fn from(id: u32) -> Self {
+        Self(id)
+    }
+}
+
+impl Display for AnimationFrameId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A `DOMHighResTimeStamp`: milliseconds from `origin` to `at`. `Instant` is monotonic, so
+/// timestamps never go backwards.
+pub fn high_res_timestamp(origin: Instant, at: Instant) -> f64 {
+    at.saturating_duration_since(origin).as_secs_f64() * 1000.0
+}
+
+struct AnimationFramesInner<T> {
+    queue: Vec<(AnimationFrameId, T)>,
+    /// Callbacks of the frame being run that haven't run (or been cancelled) yet
+    in_flight: Vec<AnimationFrameId>,
+    next_id: u32,
+}
+
+/// The animation frame callbacks of one document, each carrying a `T` for the driver to run.
+///
+/// Cloning gives another handle to the same queue, so callbacks can request and cancel frames
+/// while a frame runs.
+pub struct AnimationFrames<T = ()> {
+    inner: Rc<RefCell<AnimationFramesInner<T>>>,
+}
+
+impl<T> Clone for AnimationFrames<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Rc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T> Debug for AnimationFrames<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AnimationFrames")
+            .field("queued", &self.inner.borrow().queue.len())
+            .finish()
+    }
+}
+
+impl<T> Default for AnimationFrames<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> AnimationFrames<T> {
+    pub fn new() -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(AnimationFramesInner {
+                queue: Vec::new(),
+                in_flight: Vec::new(),
+                next_id: 1,
+            })),
+        }
+    }
+
+    /// `requestAnimationFrame()`: runs `payload` before the next frame.
+    pub fn request(&self, payload: T) -> AnimationFrameId {
+        let mut inner = self.inner.borrow_mut();
+        let id = AnimationFrameId(inner.next_id);
+        inner.next_id = inner.next_id.checked_add(1).unwrap_or(1);
+        inner.queue.push((id, payload));
+        id
+    }
+
+    /// `cancelAnimationFrame()`. Also stops a callback of the frame being run that hasn't run yet.
+    pub fn cancel(&self, id: AnimationFrameId) {
+        let mut inner = self.inner.borrow_mut();
+        inner.queue.retain(|(queued, _)| *queued != id);
+        inner.in_flight.retain(|queued| *queued != id);
+    }
+
+    /// Whether a frame has been requested.
+    pub fn is_empty(&self) -> bool {
+        self.inner.borrow().queue.is_empty()
+    }
+
+    /// Runs the callbacks queued so far, in the order they were requested. Returns how many ran.
+    pub fn run(&self, mut run: impl FnMut(AnimationFrameId, &mut T)) -> usize {
+        let batch = {
+            let mut inner = self.inner.borrow_mut();
+            let batch = std::mem::take(&mut inner.queue);
+            inner.in_flight = batch.iter().map(|(id, _)| *id).collect();
+            batch
+        };
+
+        let mut ran = 0;
+        for (id, mut payload) in batch {
+            let live = {
+                let mut inner = self.inner.borrow_mut();
+                let pos = inner.in_flight.iter().position(|queued| *queued == id);
+                pos.map(|pos| inner.in_flight.remove(pos)).is_some()
+            };
+            if live {
+                run(id, &mut payload);
+                ran += 1;
+            }
+        }
+        ran
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn callbacks_requested_during_a_frame_wait_for_the_next() {
+        let frames = AnimationFrames::new();
+        frames.request("a");
+        let b = frames.request("b");
+        frames.request("c");
+
+        let handle = frames.clone();
+        let mut ran = vec![];
+        frames.run(|_, name| {
+            ran.push(*name);
+            if *name == "a" {
+                handle.cancel(b);
+                handle.request("next");
+            }
+        });
+        assert_eq!(ran, vec!["a", "c"]);
+        assert!(!frames.is_empty());
+
+        ran.clear();
+        assert_eq!(frames.run(|_, name| ran.push(*name)), 1);
+        assert_eq!(ran, vec!["next"]);
+        assert!(frames.is_empty());
+    }
+
+    #[test]
+    fn timestamps_are_relative_to_the_origin() {
+        let origin = Instant::now();
+        assert_eq!(high_res_timestamp(origin, origin + Duration::from_millis(1500)), 1500.0);
+        assert_eq!(high_res_timestamp(origin + Duration::from_secs(1), origin), 0.0);
+    }
+}
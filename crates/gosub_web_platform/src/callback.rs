@@ -12,6 +12,10 @@ impl PollCallback for TestPollGuard {
 
 pub trait FutureExecutor {
     fn execute<T: Future<Output = ()> + 'static>(&mut self, future: T);
+
+    /// Runs the microtasks (promise jobs) the callbacks so far queued. The event loop calls this
+    /// after every task and callback, and before animation frames are handed to rendering.
+    fn microtask_checkpoint(&mut self) {}
 }
 
 pub struct Callback<T: FutureExecutor, D = ()> {
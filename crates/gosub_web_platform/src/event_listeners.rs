@@ -1,4 +1,5 @@
 use crate::callback::{Callback, FutureExecutor};
+use crate::event_target::{EventPhase, EventTarget, ListenerOptions};
 use gosub_interface::input::{InputEvent, MouseButton};
 use gosub_shared::geo::Point;
 use std::fmt::Debug;
@@ -32,72 +33,77 @@ pub struct MouseScrollEvent {
     pub delta: Point,
 }
 
+/// The listeners for one kind of input, by event type.
 pub struct EventListener<D, E: FutureExecutor> {
-    listeners: Vec<Callback<E, D>>,
+    target: EventTarget<Callback<E, D>>,
 }
 
 impl<D: Clone + Debug, E: FutureExecutor> EventListener<D, E> {
-    pub fn handle_event(&mut self, event: D, e: &mut E) {
-        for listener in &mut self.listeners {
-            listener.execute(e, event.clone());
-        }
+    fn add(&self, event_type: &str, callback: Callback<E, D>) {
+        self.target.add(event_type, ListenerOptions::default(), callback);
+    }
+
+    pub fn handle_event(&mut self, event_type: &str, event: D, e: &mut E) {
+        self.target
+            .dispatch(event_type, EventPhase::AtTarget, |_, _, listener| {
+                listener.execute(e, event.clone());
+                e.microtask_checkpoint();
+            });
     }
 }
 
 impl<D, E: FutureExecutor> Debug for EventListener<D, E> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("EventListener")
-            .field("listeners", &self.listeners.len())
-            .finish()
+        f.debug_struct("EventListener").field("target", &self.target).finish()
     }
 }
 
 impl<D, E: FutureExecutor> Default for EventListener<D, E> {
     fn default() -> Self {
-        Self { listeners: Vec::new() }
+        Self {
+            target: EventTarget::new(),
+        }
     }
 }
 
 pub struct EventListeners<E: FutureExecutor> {
-    mouse_up: EventListener<MouseButtonEvent, E>,
-    mouse_down: EventListener<MouseButtonEvent, E>,
+    buttons: EventListener<MouseButtonEvent, E>,
     mouse_move: EventListener<MouseMoveEvent, E>,
     mouse_scroll: EventListener<MouseScrollEvent, E>,
-    keyboard_up: EventListener<KeyboardEvent, E>,
-    keyboard_down: EventListener<KeyboardEvent, E>,
+    keyboard: EventListener<KeyboardEvent, E>,
 }
 
 impl<E: FutureExecutor> EventListeners<E> {
     pub(crate) fn add_listener(&mut self, listener: Listeners<E>) {
         match listener {
-            Listeners::MouseDown(callback) => self.mouse_down.listeners.push(callback),
-            Listeners::MouseUp(callback) => self.mouse_up.listeners.push(callback),
-            Listeners::MouseMove(callback) => self.mouse_move.listeners.push(callback),
-            Listeners::MouseScroll(callback) => self.mouse_scroll.listeners.push(callback),
-            Listeners::KeyboardUp(callback) => self.keyboard_up.listeners.push(callback),
-            Listeners::KeyboardDown(callback) => self.keyboard_down.listeners.push(callback),
+            Listeners::MouseDown(callback) => self.buttons.add("mousedown", callback),
+            Listeners::MouseUp(callback) => self.buttons.add("mouseup", callback),
+            Listeners::MouseMove(callback) => self.mouse_move.add("mousemove", callback),
+            Listeners::MouseScroll(callback) => self.mouse_scroll.add("wheel", callback),
+            Listeners::KeyboardUp(callback) => self.keyboard.add("keyup", callback),
+            Listeners::KeyboardDown(callback) => self.keyboard.add("keydown", callback),
         }
     }
 
     pub(crate) fn handle_input_event(&mut self, event: InputEvent, e: &mut E) {
         match event {
             InputEvent::MouseDown(button) => {
-                self.mouse_down.handle_event(MouseButtonEvent { button }, e);
+                self.buttons.handle_event("mousedown", MouseButtonEvent { button }, e);
             }
             InputEvent::MouseUp(button) => {
-                self.mouse_up.handle_event(MouseButtonEvent { button }, e);
+                self.buttons.handle_event("mouseup", MouseButtonEvent { button }, e);
             }
             InputEvent::MouseMove(pos) => {
-                self.mouse_move.handle_event(MouseMoveEvent { pos }, e);
+                self.mouse_move.handle_event("mousemove", MouseMoveEvent { pos }, e);
             }
             InputEvent::MouseScroll(delta) => {
-                self.mouse_scroll.handle_event(MouseScrollEvent { delta }, e);
+                self.mouse_scroll.handle_event("wheel", MouseScrollEvent { delta }, e);
             }
             InputEvent::KeyboardDown(key) => {
-                self.keyboard_down.handle_event(KeyboardEvent { key }, e);
+                self.keyboard.handle_event("keydown", KeyboardEvent { key }, e);
             }
             InputEvent::KeyboardUp(key) => {
-                self.keyboard_up.handle_event(KeyboardEvent { key }, e);
+                self.keyboard.handle_event("keyup", KeyboardEvent { key }, e);
             }
         }
     }
@@ -106,12 +112,10 @@ impl<E: FutureExecutor> EventListeners<E> {
 impl<E: FutureExecutor> Default for EventListeners<E> {
     fn default() -> Self {
         Self {
-            mouse_up: EventListener::default(),
-            mouse_down: EventListener::default(),
+            buttons: EventListener::default(),
             mouse_move: EventListener::default(),
             mouse_scroll: EventListener::default(),
-            keyboard_up: EventListener::default(),
-            keyboard_down: EventListener::default(),
+            keyboard: EventListener::default(),
         }
     }
 }
@@ -119,12 +123,10 @@ impl<E: FutureExecutor> Default for EventListeners<E> {
 impl<E: FutureExecutor> Debug for EventListeners<E> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("EventListeners")
-            .field("mouse_down", &self.mouse_down)
-            .field("mouse_up", &self.mouse_up)
+            .field("buttons", &self.buttons)
             .field("mouse_move", &self.mouse_move)
             .field("mouse_scroll", &self.mouse_scroll)
-            .field("keyboard_down", &self.keyboard_down)
-            .field("keyboard_up", &self.keyboard_up)
+            .field("keyboard", &self.keyboard)
             .finish()
     }
 }
@@ -0,0 +1,250 @@
+//! The listener lists behind `addEventListener()`.
+//!
+//! An [`EventTarget`] keeps the listeners of one object (a DOM node, an `XMLHttpRequest`, a
+//! `WebSocket`, a worker) by event type, each carrying a `T` for the driver to run. Dispatch
+//! follows the DOM rules: listeners run in the order they were added, `once` listeners are
+//! removed before they run, and a listener removed during dispatch doesn't run, while one added
+//! during dispatch waits for the next event. Walking the capture and bubble path is up to the
+//! caller, which dispatches on each target along it with the matching [`EventPhase`].
+
+use std::cell::RefCell;
+use std::fmt::{Debug, Display, Formatter};
+use std::rc::Rc;
+
+/// The id `add()` returns, for removing the listener again. Ids are never 0.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ListenerId(u32);
+
+impl ListenerId {
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
+}
+
+impl Display for ListenerId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The options of `addEventListener()`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ListenerOptions {
+    /// Runs in the capture phase instead of the bubble phase
+    pub capture: bool,
+    /// Removed the first time it runs
+    pub once: bool,
+    /// Won't call `preventDefault()`; the driver ignores it if it does
+    pub passive: bool,
+}
+
+/// Where the event is on its way, seen from the target it is dispatched on.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EventPhase {
+    /// On an ancestor, on the way down: runs the capture listeners
+    Capturing,
+    /// On the target itself: runs the capture listeners, then the others
+    AtTarget,
+    /// On an ancestor, on the way up: runs the non-capture listeners
+    Bubbling,
+}
+
+struct Listener<T> {
+    id: ListenerId,
+    event_type: String,
+    options: ListenerOptions,
+    /// Taken out while the listener runs
+    payload: Option<T>,
+}
+
+struct EventTargetInner<T> {
+    listeners: Vec<Listener<T>>,
+    next_id: u32,
+}
+
+/// The event listeners of one object, each carrying a `T` for the driver to run.
+///
+/// Cloning gives another handle to the same listeners, so listeners can add and remove others
+/// while an event is dispatched.
+pub struct EventTarget<T = ()> {
+    inner: Rc<RefCell<EventTargetInner<T>>>,
+}
+
+impl<T> Clone for EventTarget<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Rc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T> Debug for EventTarget<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventTarget")
+            .field("listeners", &self.inner.borrow().listeners.len())
+            .finish()
+    }
+}
+
+impl<T> Default for EventTarget<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> EventTarget<T> {
+    pub fn new() -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(EventTargetInner {
+                listeners: Vec::new(),
+                next_id: 1,
+            })),
+        }
+    }
+
+    /// `addEventListener()`: runs `payload` for every `event_type` event dispatched from now on.
+    pub fn add(&self, event_type: &str, options: ListenerOptions, payload: T) -> ListenerId {
+        let mut inner = self.inner.borrow_mut();
+        let id = ListenerId(inner.next_id);
+        inner.next_id = inner.next_id.checked_add(1).unwrap_or(1);
+        inner.listeners.push(Listener {
+            id,
+            event_type: event_type.to_string(),
+            options,
+            payload: Some(payload),
+        });
+        id
+    }
+
+    /// `removeEventListener()`. Also stops a listener that hasn't run yet in the current dispatch.
+    pub fn remove(&self, id: ListenerId) {
+        self.inner.borrow_mut().listeners.retain(|listener| listener.id != id);
+    }
+
+    /// Whether anything listens for `event_type`, so dispatching can be skipped when not.
+    pub fn has_listeners(&self, event_type: &str) -> bool {
+        self.inner
+            .borrow()
+            .listeners
+            .iter()
+            .any(|listener| listener.event_type == event_type)
+    }
+
+    /// Runs the `event_type` listeners that take part in `phase`. A listener that is already
+    /// running further up the stack (a nested dispatch) is skipped. Returns how many ran.
+    pub fn dispatch(
+        &self,
+        event_type: &str,
+        phase: EventPhase,
+        mut run: impl FnMut(ListenerId, &ListenerOptions, &mut T),
+    ) -> usize {
+        let batch: Vec<ListenerId> = {
+            let inner = self.inner.borrow();
+            let of_type = || inner.listeners.iter().filter(|l| l.event_type == event_type);
+            let capture = of_type().filter(|l| l.options.capture).map(|l| l.id);
+            let bubble = of_type().filter(|l| !l.options.capture).map(|l| l.id);
+            match phase {
+                EventPhase::Capturing => capture.collect(),
+                EventPhase::AtTarget => capture.chain(bubble).collect(),
+                EventPhase::Bubbling => bubble.collect(),
+            }
+        };
+
+        let mut ran = 0;
+        for id in batch {
+            let taken = {
+                let mut inner = self.inner.borrow_mut();
+                let pos = inner.listeners.iter().position(|l| l.id == id);
+                match pos {
+                    Some(pos) if inner.listeners[pos].options.once => {
+                        let listener = inner.listeners.remove(pos);
+                        listener.payload.map(|payload| (listener.options, payload, true))
+                    }
+                    Some(pos) => {
+                        let listener = &mut inner.listeners[pos];
+                        listener
+                            .payload
+                            .take()
+                            .map(|payload| (listener.options, payload, false))
+                    }
+                    None => None,
+                }
+            };
+            let Some((options, mut payload, once)) = taken else {
+                continue;
+            };
+
+            run(id, &options, &mut payload);
+            ran += 1;
+
+            // Put it back, unless it was removed while it ran.
+            if !once {
+                let mut inner = self.inner.borrow_mut();
+                if let Some(listener) = inner.listeners.iter_mut().find(|l| l.id == id) {
+                    listener.payload = Some(payload);
+                }
+            }
+        }
+        ran
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listeners_removed_during_dispatch_do_not_run() {
+        let target = EventTarget::new();
+        target.add("click", ListenerOptions::default(), "a");
+        let b = target.add("click", ListenerOptions::default(), "b");
+        target.add("click", ListenerOptions::default(), "c");
+
+        let handle = target.clone();
+        let mut ran = vec![];
+        target.dispatch("click", EventPhase::AtTarget, |_, _, name| {
+            ran.push(*name);
+            if *name == "a" {
+                handle.remove(b);
+                handle.add("click", ListenerOptions::default(), "late");
+            }
+        });
+        assert_eq!(ran, vec!["a", "c"]);
+
+        ran.clear();
+        target.dispatch("click", EventPhase::AtTarget, |_, _, name| ran.push(*name));
+        assert_eq!(ran, vec!["a", "c", "late"]);
+    }
+
+    #[test]
+    fn once_listeners_run_a_single_time() {
+        let target = EventTarget::new();
+        let once = ListenerOptions {
+            once: true,
+            ..Default::default()
+        };
+        target.add("load", once, 1);
+        target.add("load", ListenerOptions::default(), 2);
+
+        assert_eq!(target.dispatch("load", EventPhase::AtTarget, |_, _, _| {}), 2);
+        assert_eq!(target.dispatch("load", EventPhase::AtTarget, |_, _, _| {}), 1);
+        assert!(!target.has_listeners("error"));
+    }
+
+    #[test]
+    fn phases_pick_capture_or_bubble_listeners() {
+        let target = EventTarget::new();
+        let capture = ListenerOptions {
+            capture: true,
+            ..Default::default()
+        };
+        target.add("click", ListenerOptions::default(), "bubble");
+        target.add("click", capture, "capture");
+
+        let mut ran = vec![];
+        target.dispatch("click", EventPhase::Capturing, |_, _, name| ran.push(*name));
+        target.dispatch("click", EventPhase::Bubbling, |_, _, name| ran.push(*name));
+        target.dispatch("click", EventPhase::AtTarget, |_, _, name| ran.push(*name));
+        assert_eq!(ran, vec!["capture", "bubble", "capture", "bubble"]);
+    }
+}
@@ -1,20 +1,25 @@
 extern crate core;
 
-use crate::callback::{FutureExecutor, TokioExecutor};
+use crate::animation_frames::{high_res_timestamp, AnimationFrames};
+use crate::callback::{Callback, FutureExecutor, TokioExecutor};
 use crate::event_listeners::{EventListeners, Listeners};
 use crate::timers::WebTimers;
 use gosub_interface::input::InputEvent;
 use gosub_shared::types::Result;
 use std::thread;
+use std::time::Instant;
 use tokio::runtime::{Handle, Runtime};
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::task::LocalSet;
 
+pub mod animation_frames;
 mod callback;
 mod event_listeners;
+pub mod event_target;
+pub mod permissions;
+pub mod permissions_policy;
 pub mod poll_guard;
-#[allow(dead_code)]
-mod timers;
+pub mod timers;
 
 /// The web event loop for a JS or Lua runtime. Previously generic over `HasWebComponents`;
 /// the rendering/chrome handles now live outside this crate.
@@ -25,7 +30,11 @@ pub struct WebEventLoop<E: FutureExecutor = TokioExecutor> {
     rx: Receiver<WebEventLoopMessage>,
     irx: Receiver<LocalEventLoopMessage<E>>,
     itx: Sender<LocalEventLoopMessage<E>>,
-    timers: WebTimers,
+    timers: WebTimers<Callback<E>>,
+    /// `requestAnimationFrame()` callbacks, given the frame's timestamp
+    animation_frames: AnimationFrames<Callback<E, f64>>,
+    /// The time origin animation frame timestamps are relative to
+    time_origin: Instant,
 }
 
 /// Handle to the event loop - use to spawn tasks or send messages.
@@ -36,6 +45,8 @@ pub struct WebEventLoopHandle {
 
 pub enum WebEventLoopMessage {
     InputEvent(InputEvent),
+    /// A frame is about to be drawn; runs the animation frame callbacks first
+    AnimationFrame(Instant),
     Close,
 }
 
@@ -58,6 +69,8 @@ impl WebEventLoop {
                 itx,
                 rx,
                 timers: WebTimers::new(),
+                animation_frames: AnimationFrames::new(),
+                time_origin: Instant::now(),
             };
             el.run(rt, TokioExecutor);
         });
@@ -81,6 +94,12 @@ impl<E: FutureExecutor> WebEventLoop<E> {
                         let Some(msg) = val else { break; };
                         self.handle_local_message(msg);
                     }
+                    _ = sleep_until(self.timers.next_deadline()) => {
+                        self.timers.run_due(Instant::now(), |_, callback| {
+                            callback.exec(&mut e);
+                            e.microtask_checkpoint();
+                        });
+                    }
                 }
             }
         });
@@ -91,6 +110,15 @@ impl<E: FutureExecutor> WebEventLoop<E> {
             WebEventLoopMessage::InputEvent(e) => {
                 self.listeners.handle_input_event(e, exec);
             }
+            WebEventLoopMessage::AnimationFrame(at) => {
+                let timestamp = high_res_timestamp(self.time_origin, at);
+                // Promise jobs of one callback run before the next one, and all of them before
+                // the frame is drawn.
+                self.animation_frames.run(|_, callback| {
+                    callback.execute(exec, timestamp);
+                    exec.microtask_checkpoint();
+                });
+            }
             WebEventLoopMessage::Close => {
                 self.rx.close();
             }
@@ -105,3 +133,54 @@ impl<E: FutureExecutor> WebEventLoop<E> {
         }
     }
 }
+
+/// Waits until `deadline`, or forever when there is none.
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::Future;
+
+    /// Runs nothing; records the callbacks and checkpoints in order.
+    #[derive(Default)]
+    struct Recorder(Vec<&'static str>);
+
+    impl FutureExecutor for Recorder {
+        fn execute<T: Future<Output = ()> + 'static>(&mut self, _future: T) {}
+
+        fn microtask_checkpoint(&mut self) {
+            self.0.push("checkpoint");
+        }
+    }
+
+    #[test]
+    fn microtasks_run_after_each_animation_frame_callback() {
+        let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let (_tx, rx) = tokio::sync::mpsc::channel(1);
+        let (itx, irx) = tokio::sync::mpsc::channel(1);
+        let mut el = WebEventLoop {
+            listeners: EventListeners::default(),
+            rt: rt.handle().clone(),
+            rx,
+            irx,
+            itx,
+            timers: WebTimers::new(),
+            animation_frames: AnimationFrames::new(),
+            time_origin: Instant::now(),
+        };
+        for _ in 0..2 {
+            el.animation_frames
+                .request(Callback::new(|exec: &mut Recorder, _| exec.0.push("callback")));
+        }
+
+        let mut exec = Recorder::default();
+        el.handle_message(WebEventLoopMessage::AnimationFrame(Instant::now()), &mut exec);
+        assert_eq!(exec.0, ["callback", "checkpoint", "callback", "checkpoint"]);
+    }
+}
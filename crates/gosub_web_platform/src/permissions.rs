@@ -0,0 +1,159 @@
+//! Permission states, as the Permissions API and the APIs gated by it see them.
+//!
+//! A permission is held per origin. It starts out as [`PermissionState::Prompt`]: the page may
+//! ask, and the UA decides (usually by asking the user). The answer is remembered in a
+//! [`PermissionStore`], so later visits get it without asking again. Where it is remembered is up
+//! to the embedder; [`MemoryPermissionStore`] forgets everything when dropped.
+
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::str::FromStr;
+
+/// A powerful feature a page needs permission for, by its Permissions API name.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PermissionName {
+    /// Showing system notifications (`Notification`)
+    Notifications,
+    /// The device's location (`navigator.geolocation`)
+    Geolocation,
+    /// Reading the clipboard (`navigator.clipboard.readText()`)
+    ClipboardRead,
+    /// Writing the clipboard (`navigator.clipboard.writeText()`)
+    ClipboardWrite,
+    /// Playing media with sound before the user interacted with the page
+    Autoplay,
+}
+
+impl PermissionName {
+    pub const ALL: [PermissionName; 5] = [
+        PermissionName::Notifications,
+        PermissionName::Geolocation,
+        PermissionName::ClipboardRead,
+        PermissionName::ClipboardWrite,
+        PermissionName::Autoplay,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PermissionName::Notifications => "notifications",
+            PermissionName::Geolocation => "geolocation",
+            PermissionName::ClipboardRead => "clipboard-read",
+            PermissionName::ClipboardWrite => "clipboard-write",
+            PermissionName::Autoplay => "autoplay",
+        }
+    }
+
+    /// Whether a document's [`PermissionsPolicy`](crate::permissions_policy::PermissionsPolicy)
+    /// can take the feature away. Notifications aren't policy-controlled.
+    pub fn is_policy_controlled(self) -> bool {
+        !matches!(self, PermissionName::Notifications)
+    }
+}
+
+impl Display for PermissionName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PermissionName {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|p| p.as_str() == name).ok_or(())
+    }
+}
+
+/// Whether an origin may use a feature.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum PermissionState {
+    Granted,
+    Denied,
+    /// Not decided yet; asking is allowed
+    #[default]
+    Prompt,
+}
+
+impl PermissionState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PermissionState::Granted => "granted",
+            PermissionState::Denied => "denied",
+            PermissionState::Prompt => "prompt",
+        }
+    }
+}
+
+impl Display for PermissionState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Where permission decisions are kept. Origins are in their serialized form
+/// (`https://example.com`); opaque origins never get a permission.
+pub trait PermissionStore: Debug + Send + Sync {
+    /// The state of `name` for `origin`.
+    fn state(&self, origin: &str, name: PermissionName) -> PermissionState;
+
+    /// Remembers a decision. Setting [`PermissionState::Prompt`] forgets it.
+    fn set_state(&self, origin: &str, name: PermissionName, state: PermissionState);
+}
+
+/// A [`PermissionStore`] that keeps decisions in memory only.
+#[derive(Debug, Default)]
+pub struct MemoryPermissionStore {
+    states: Mutex<HashMap<(String, PermissionName), PermissionState>>,
+}
+
+impl MemoryPermissionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl PermissionStore for MemoryPermissionStore {
+    fn state(&self, origin: &str, name: PermissionName) -> PermissionState {
+        if origin == "null" {
+            return PermissionState::Denied;
+        }
+        self.states
+            .lock()
+            .get(&(origin.to_string(), name))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn set_state(&self, origin: &str, name: PermissionName, state: PermissionState) {
+        let key = (origin.to_string(), name);
+        let mut states = self.states.lock();
+        match state {
+            PermissionState::Prompt => states.remove(&key),
+            state => states.insert(key, state),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decisions_are_per_origin() {
+        let store = MemoryPermissionStore::new();
+        let name = PermissionName::Notifications;
+        assert_eq!(store.state("https://a.example", name), PermissionState::Prompt);
+
+        store.set_state("https://a.example", name, PermissionState::Granted);
+        assert_eq!(store.state("https://a.example", name), PermissionState::Granted);
+        assert_eq!(store.state("https://b.example", name), PermissionState::Prompt);
+        assert_eq!(store.state("null", name), PermissionState::Denied);
+
+        store.set_state("https://a.example", name, PermissionState::Prompt);
+        assert_eq!(store.state("https://a.example", name), PermissionState::Prompt);
+        assert_eq!("notifications".parse(), Ok(PermissionName::Notifications));
+        assert_eq!("clipboard-write".parse(), Ok(PermissionName::ClipboardWrite));
+        assert_eq!("camera".parse::<PermissionName>(), Err(()));
+    }
+}
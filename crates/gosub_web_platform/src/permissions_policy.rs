@@ -0,0 +1,147 @@
+//! `Permissions-Policy`: which origins a document lets use a feature at all.
+//!
+//! A document's policy comes from its `Permissions-Policy` response header, a structured field
+//! dictionary of feature names and allowlists:
+//!
+//! ```text
+//! Permissions-Policy: geolocation=(self "https://maps.example"), autoplay=*, clipboard-read=()
+//! ```
+//!
+//! A feature the policy takes away is denied whatever the origin's [`PermissionState`] says; one
+//! it allows still needs the permission. Features the header doesn't mention get their default
+//! allowlist, which is `self` for every feature handled here.
+//!
+//! [`PermissionState`]: crate::permissions::PermissionState
+
+use crate::permissions::PermissionName;
+use std::collections::HashMap;
+
+/// The origins a feature is allowed for
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Allowlist {
+    /// `*`: every origin
+    pub all: bool,
+    /// `self`: the document's own origin
+    pub this: bool,
+    /// Origins listed by their serialization
+    pub origins: Vec<String>,
+}
+
+impl Allowlist {
+    /// The allowlist of a feature the policy doesn't mention.
+    pub fn default_for(_name: PermissionName) -> Self {
+        Self {
+            this: true,
+            ..Self::default()
+        }
+    }
+
+    /// Parses one member value: `*`, `self`, `"https://a.example"`, or an inner list of those
+    /// such as `(self "https://a.example")`. `()` allows no origin at all.
+    fn parse(value: &str) -> Self {
+        let value = value.trim();
+        let items = match value.strip_prefix('(').and_then(|v| v.strip_suffix(')')) {
+            Some(inner) => inner,
+            None => value,
+        };
+        let mut list = Self::default();
+        for item in items.split_ascii_whitespace() {
+            // Parameters (`;report-to=x`) don't change who is allowed.
+            let item = item.split(';').next().unwrap_or_default();
+            match item {
+                "*" => list.all = true,
+                "self" => list.this = true,
+                _ => {
+                    if let Some(origin) = item.strip_prefix('"').and_then(|o| o.strip_suffix('"')) {
+                        list.origins.push(origin.trim_end_matches('/').to_string());
+                    }
+                }
+            }
+        }
+        list
+    }
+
+    /// Whether `origin` is allowed in a document of `document_origin`.
+    pub fn allows(&self, origin: &str, document_origin: &str) -> bool {
+        if origin == "null" {
+            return false;
+        }
+        self.all || (self.this && origin == document_origin) || self.origins.iter().any(|o| o == origin)
+    }
+}
+
+/// The `Permissions-Policy` of one document
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PermissionsPolicy {
+    features: HashMap<PermissionName, Allowlist>,
+}
+
+impl PermissionsPolicy {
+    /// The policy of a document without a `Permissions-Policy` header.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a `Permissions-Policy` header value. Several headers are joined with `,` first.
+    /// Unknown features and malformed members are skipped; a feature listed twice keeps its last
+    /// allowlist.
+    pub fn parse(header: &str) -> Self {
+        let mut features = HashMap::new();
+        for member in header.split(',') {
+            let Some((name, value)) = member.split_once('=') else {
+                continue;
+            };
+            let Ok(name) = name.trim().parse::<PermissionName>() else {
+                continue;
+            };
+            if name.is_policy_controlled() {
+                features.insert(name, Allowlist::parse(value));
+            }
+        }
+        Self { features }
+    }
+
+    /// The allowlist of `name`.
+    pub fn allowlist(&self, name: PermissionName) -> Allowlist {
+        self.features
+            .get(&name)
+            .cloned()
+            .unwrap_or_else(|| Allowlist::default_for(name))
+    }
+
+    /// Whether `origin` may use `name` at all in a document of `document_origin`.
+    pub fn allows(&self, name: PermissionName, origin: &str, document_origin: &str) -> bool {
+        if !name.is_policy_controlled() {
+            return true;
+        }
+        match self.features.get(&name) {
+            Some(list) => list.allows(origin, document_origin),
+            None => Allowlist::default_for(name).allows(origin, document_origin),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_allowlists() {
+        let policy = PermissionsPolicy::parse(
+            r#"geolocation=(self "https://maps.example/"), autoplay=*, clipboard-read=(), camera=(), bogus"#,
+        );
+        let page = "https://a.example";
+
+        assert!(policy.allows(PermissionName::Geolocation, page, page));
+        assert!(policy.allows(PermissionName::Geolocation, "https://maps.example", page));
+        assert!(!policy.allows(PermissionName::Geolocation, "https://b.example", page));
+        assert!(policy.allows(PermissionName::Autoplay, "https://b.example", page));
+        assert!(!policy.allows(PermissionName::ClipboardRead, page, page));
+        // Not mentioned: only the document's own origin
+        assert!(policy.allows(PermissionName::ClipboardWrite, page, page));
+        assert!(!policy.allows(PermissionName::ClipboardWrite, "https://b.example", page));
+        // Not policy-controlled
+        assert!(PermissionsPolicy::parse("notifications=()").allows(PermissionName::Notifications, page, page));
+        assert_eq!(PermissionsPolicy::parse(""), PermissionsPolicy::new());
+    }
+}
@@ -1,83 +1,315 @@
-use crate::callback::{Callback, TokioExecutor};
-use slotmap::{DefaultKey, SlotMap};
+//! The timers behind `setTimeout()` and `setInterval()`.
+//!
+//! [`WebTimers`] only keeps the schedule: whoever drives the script (the event loop, a script
+//! thread) waits until [`WebTimers::next_deadline`] and then calls [`WebTimers::run_due`]. It
+//! follows the HTML timer initialization steps: negative timeouts count as zero, timers nested more
+//! than five levels deep are clamped to at least 4ms, and timers that become due at the same time
+//! run in the order they were scheduled.
+
 use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Debug, Display, Formatter};
 use std::rc::Rc;
-use std::time::Duration;
-use tokio::task;
-use tokio::task::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Nesting level above which timeouts are clamped.
+const MAX_NESTING_LEVEL: u32 = 5;
+/// Smallest timeout for deeply nested timers.
+const MIN_NESTED_TIMEOUT: Duration = Duration::from_millis(4);
 
-#[derive(Debug)]
-pub struct WebTimers {
-    inner: Rc<RefCell<WebTimersInner>>,
+/// The id `setTimeout()`/`setInterval()` return. Ids are never 0.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TimerId(u32);
+
+impl TimerId {
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
 }
 
-#[derive(Debug)]
-pub struct WebTimersInner {
-    timers: SlotMap<DefaultKey, Timer>,
+impl From<u32> for TimerId {
+    fn from(id: u32) -> Self {
+        Self(id)
+    }
 }
 
-impl WebTimers {
-    pub fn new() -> Self {
+impl Display for TimerId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A timer that came due, as handed to the [`WebTimers::run_due`] callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DueTimer {
+    pub id: TimerId,
+    /// Whether the timer is an interval, and so fires again
+    pub repeat: bool,
+}
+
+struct Timer<T> {
+    /// Position in the queue: deadline, then scheduling order
+    key: (Instant, u64),
+    /// Nesting level of the task this timer runs as
+    nesting: u32,
+    /// The requested timeout, for intervals
+    interval: Option<Duration>,
+    payload: T,
+}
+
+/// The timer whose callback is running.
+struct Running {
+    id: TimerId,
+    nesting: u32,
+    cleared: bool,
+}
+
+struct WebTimersInner<T> {
+    queue: BTreeMap<(Instant, u64), TimerId>,
+    timers: HashMap<TimerId, Timer<T>>,
+    running: Option<Running>,
+    next_id: u32,
+    next_seq: u64,
+}
+
+/// The active timers of one global (a window or a worker), each carrying a `T` for the driver
+/// to run: a callback, or just nothing when the script side keeps track of the handlers itself.
+///
+/// Cloning gives another handle to the same timers, so native `setTimeout()` functions can
+/// schedule while a timer callback runs.
+pub struct WebTimers<T = ()> {
+    inner: Rc<RefCell<WebTimersInner<T>>>,
+}
+
+impl<T> Clone for WebTimers<T> {
+    fn clone(&self) -> Self {
         Self {
-            inner: Rc::new(RefCell::new(WebTimersInner { timers: SlotMap::new() })),
+            inner: Rc::clone(&self.inner),
         }
     }
+}
 
-    pub fn add_timer(&mut self, timer: Timer) -> TimerId {
-        TimerId(self.inner.borrow_mut().timers.insert(timer))
+impl<T> Debug for WebTimers<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let inner = self.inner.borrow();
+        f.debug_struct("WebTimers")
+            .field("active", &inner.timers.len())
+            .field("running", &inner.running.as_ref().map(|r| r.id))
+            .finish()
     }
+}
 
-    /// Removes and cancels the timer with the given id.
-    pub fn remove(&mut self, id: TimerId) {
-        if let Some(timer) = self.inner.borrow_mut().timers.remove(id.0) {
-            timer.handle.abort();
+impl<T> Default for WebTimers<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> WebTimers<T> {
+    pub fn new() -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(WebTimersInner {
+                queue: BTreeMap::new(),
+                timers: HashMap::new(),
+                running: None,
+                next_id: 1,
+                next_seq: 0,
+            })),
         }
     }
 
-    pub fn set_timeout(&mut self, duration: Duration, mut callback: Callback<TokioExecutor>) {
-        let inner = self.inner.clone();
+    /// `setTimeout()`: runs `payload` once after `timeout`.
+    pub fn set_timeout(&self, timeout: Duration, payload: T) -> TimerId {
+        self.schedule_at(Instant::now(), timeout, false, payload)
+    }
 
-        self.inner.borrow_mut().timers.insert_with_key(move |key| {
-            let handle = task::spawn_local(async move {
-                tokio::time::sleep(duration).await;
+    /// `setInterval()`: runs `payload` every `timeout` until cleared.
+    pub fn set_interval(&self, timeout: Duration, payload: T) -> TimerId {
+        self.schedule_at(Instant::now(), timeout, true, payload)
+    }
 
-                callback.exec(&mut TokioExecutor);
+    /// `clearTimeout()`/`clearInterval()`. Unknown ids are ignored, like on the web.
+    pub fn clear(&self, id: TimerId) {
+        let mut inner = self.inner.borrow_mut();
+        if let Some(timer) = inner.timers.remove(&id) {
+            inner.queue.remove(&timer.key);
+        }
+        if let Some(running) = inner.running.as_mut().filter(|r| r.id == id) {
+            running.cleared = true;
+        }
+    }
 
-                inner.borrow_mut().timers.remove(key);
-            });
+    /// Drops every timer, e.g. when the document goes away.
+    pub fn clear_all(&self) {
+        let mut inner = self.inner.borrow_mut();
+        inner.queue.clear();
+        inner.timers.clear();
+        if let Some(running) = inner.running.as_mut() {
+            running.cleared = true;
+        }
+    }
 
-            Timer { handle }
-        });
+    pub fn is_empty(&self) -> bool {
+        self.inner.borrow().timers.is_empty()
     }
 
-    pub fn set_interval(&mut self, duration: Duration, mut callback: Callback<TokioExecutor>) {
-        let handle = task::spawn_local(async move {
-            let mut interval = tokio::time::interval(duration);
+    /// When the earliest timer is due.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.inner.borrow().queue.keys().next().map(|(deadline, _)| *deadline)
+    }
 
-            interval.tick().await; // First tick is immediate
+    /// Runs every timer due at `now`, earliest first. Timers scheduled by the callbacks, including
+    /// the next round of an interval, wait for the next call even when they are already due.
+    ///
+    /// Returns how many timers ran.
+    pub fn run_due(&self, now: Instant, mut run: impl FnMut(DueTimer, &mut T)) -> usize {
+        let scheduled_before = self.inner.borrow().next_seq;
+        let mut ran = 0;
+        while let Some((id, mut timer)) = self.pop_due(now, scheduled_before) {
+            run(
+                DueTimer {
+                    id,
+                    repeat: timer.interval.is_some(),
+                },
+                &mut timer.payload,
+            );
+            ran += 1;
 
-            loop {
-                interval.tick().await;
-                callback.exec(&mut TokioExecutor);
+            // Intervals go back in while still "running", so each round nests one level deeper.
+            let cleared = self.inner.borrow().running.as_ref().is_some_and(|r| r.cleared);
+            if let (Some(interval), false) = (timer.interval, cleared) {
+                self.insert(id, Instant::now().max(now), interval, Some(interval), timer.payload);
             }
+            self.inner.borrow_mut().running = None;
+        }
+        ran
+    }
+
+    /// Takes the first timer due at `now` that was scheduled before sequence number `before`.
+    fn pop_due(&self, now: Instant, before: u64) -> Option<(TimerId, Timer<T>)> {
+        let mut inner = self.inner.borrow_mut();
+        let (&key, &id) = inner
+            .queue
+            .range(..=(now, u64::MAX))
+            .find(|((_, seq), _)| *seq < before)?;
+        inner.queue.remove(&key);
+        let timer = inner.timers.remove(&id)?;
+        inner.running = Some(Running {
+            id,
+            nesting: timer.nesting,
+            cleared: false,
         });
+        Some((id, timer))
+    }
+
+    fn schedule_at(&self, now: Instant, timeout: Duration, repeat: bool, payload: T) -> TimerId {
+        let id = {
+            let mut inner = self.inner.borrow_mut();
+            let mut id = inner.next_id;
+            let in_use =
+                |id| inner.timers.contains_key(&TimerId(id)) || inner.running.as_ref().is_some_and(|r| r.id.0 == id);
+            while id == 0 || in_use(id) {
+                id = id.wrapping_add(1);
+            }
+            inner.next_id = id.wrapping_add(1);
+            TimerId(id)
+        };
+        self.insert(id, now, timeout, repeat.then_some(timeout), payload);
+        id
+    }
+
+    fn insert(&self, id: TimerId, now: Instant, timeout: Duration, interval: Option<Duration>, payload: T) {
+        let mut inner = self.inner.borrow_mut();
+
+        // Timers set from within a timer callback nest one level deeper than it.
+        let nesting = inner.running.as_ref().map_or(0, |r| r.nesting);
+        let timeout = if nesting > MAX_NESTING_LEVEL {
+            timeout.max(MIN_NESTED_TIMEOUT)
+        } else {
+            timeout
+        };
 
-        let timer = Timer { handle };
+        let key = (now + timeout, inner.next_seq);
+        inner.next_seq += 1;
+        inner.queue.insert(key, id);
+        inner.timers.insert(
+            id,
+            Timer {
+                key,
+                nesting: nesting + 1,
+                interval,
+                payload,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
 
-        self.inner.borrow_mut().timers.insert(timer);
+    #[test]
+    fn timers_run_by_deadline_then_scheduling_order() {
+        let timers = WebTimers::new();
+        let start = Instant::now();
+        let late = timers.schedule_at(start, 10 * MS, false, "late");
+        let first = timers.schedule_at(start, 5 * MS, false, "first");
+        let second = timers.schedule_at(start, 5 * MS, false, "second");
+        assert_eq!(timers.next_deadline(), Some(start + 5 * MS));
+
+        let mut order = vec![];
+        assert_eq!(
+            timers.run_due(start + 5 * MS, |due, name| order.push((due.id, *name))),
+            2
+        );
+        assert_eq!(order, vec![(first, "first"), (second, "second")]);
+
+        timers.run_due(start + 20 * MS, |due, name| order.push((due.id, *name)));
+        assert_eq!(order.last(), Some(&(late, "late")));
+        assert!(timers.is_empty());
     }
 
-    pub fn remove_all(&mut self) {
-        for (_, timer) in self.inner.borrow_mut().timers.drain() {
-            timer.handle.abort();
+    #[test]
+    fn intervals_repeat_until_cleared_from_their_callback() {
+        let timers = WebTimers::new();
+        let start = Instant::now();
+        let id = timers.schedule_at(start, 10 * MS, true, 0);
+
+        for _ in 0..3 {
+            let now = timers.next_deadline().unwrap();
+            timers.run_due(now, |_, count| *count += 1);
+            assert!(timers.next_deadline().unwrap() >= now + 10 * MS);
         }
+
+        let handle = timers.clone();
+        timers.run_due(timers.next_deadline().unwrap(), |due, count| {
+            assert_eq!(*count, 3);
+            handle.clear(due.id);
+        });
+        assert!(timers.is_empty());
+        timers.clear(id);
     }
-}
 
-#[derive(Debug)]
-pub struct Timer {
-    handle: JoinHandle<()>,
-}
+    #[test]
+    fn deeply_nested_timers_are_clamped() {
+        let timers = WebTimers::new();
+        let start = Instant::now();
+        timers.schedule_at(start, Duration::ZERO, false, ());
 
-#[derive(Debug)]
-pub struct TimerId(DefaultKey);
+        // Each callback schedules the next zero-delay timer from "inside" itself.
+        let mut delays = vec![];
+        for _ in 0..8 {
+            let now = timers.next_deadline().unwrap();
+            let handle = timers.clone();
+            timers.run_due(now, |_, _| {
+                handle.schedule_at(now, Duration::ZERO, false, ());
+            });
+            delays.push(timers.next_deadline().unwrap() - now);
+        }
+        // The first five levels run without delay; from then on the 4ms minimum applies.
+        assert_eq!(&delays[..5], &[Duration::ZERO; 5]);
+        assert!(delays[5..].iter().all(|d| *d == MIN_NESTED_TIMEOUT));
+    }
+}
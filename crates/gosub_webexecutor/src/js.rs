@@ -4,9 +4,14 @@ pub use array::*;
 pub use compile::*;
 pub use context::*;
 pub use function::*;
+pub use inspector::*;
 pub use interop::*;
+pub use limits::*;
+pub use module::*;
 pub use object::*;
+pub use promise::*;
 pub use runtime::*;
+pub use tasks::*;
 pub use value::*;
 pub use value_conversion::*;
 
@@ -14,9 +19,14 @@ mod array;
 mod compile;
 mod context;
 mod function;
+mod inspector;
 mod interop;
+mod limits;
+mod module;
 mod object;
+mod promise;
 mod runtime;
+mod tasks;
 mod value;
 mod value_conversion;
 
@@ -1,6 +1,10 @@
+use std::sync::Arc;
+
 use gosub_shared::types::Result;
 
-use crate::js::WebRuntime;
+use crate::js::{
+    InspectorSend, InspectorWait, LimitViolation, ModuleGraph, WebInspectorSession, WebInterrupt, WebRuntime,
+};
 
 //main trait for JS context (can be implemented for different JS engines like V8, SpiderMonkey, JSC, etc.)
 pub trait WebContext: Clone {
@@ -14,6 +18,11 @@ pub trait WebContext: Clone {
         compiled: &mut <Self::RT as WebRuntime>::Compiled,
     ) -> Result<<Self::RT as WebRuntime>::Value>;
 
+    /// Links and evaluates the module at `url`, taking it and the modules it imports from
+    /// `graph`. A module that already ran in this context isn't run again. Returns the promise
+    /// of the module's evaluation, which settles once top-level `await`s are done.
+    fn run_module(&mut self, url: &str, graph: &ModuleGraph) -> Result<<Self::RT as WebRuntime>::Value>;
+
     // fn compile_stream(&self, code: &str) -> Result<()>;
 
     // fn new_global_object(&mut self, name: &str) -> Result<<Self::RT as WebRuntime>::Object>;
@@ -23,4 +32,34 @@ pub trait WebContext: Clone {
         name: &str, //TODO: this should be impl IntoWebValue
         value: <Self::RT as WebRuntime>::Value,
     ) -> Result<()>;
+
+    /// Attaches a debugger to this context, which shows up in it as `name`. `send` takes what
+    /// the runtime has to say to the debugger; `wait` is how the runtime gets messages while the
+    /// page is paused. Runtimes without a debugger return `None`.
+    fn connect_inspector(
+        &mut self,
+        _name: &str,
+        _send: InspectorSend,
+        _wait: InspectorWait,
+    ) -> Option<Box<dyn WebInspectorSession>> {
+        None
+    }
+
+    /// A handle to interrupt this context's scripts with, e.g. for a [`Watchdog`]. Runtimes that
+    /// can't interrupt scripts return `None`.
+    ///
+    /// [`Watchdog`]: crate::js::Watchdog
+    fn interrupt_handle(&self) -> Option<Arc<dyn WebInterrupt>> {
+        None
+    }
+
+    /// The limit the last interrupted script ran into, if any. This also readies the context to
+    /// run scripts again.
+    fn take_limit_violation(&mut self) -> Option<LimitViolation> {
+        None
+    }
+
+    /// Runs the queued microtasks (promise jobs), including the ones they queue in turn. Running
+    /// code doesn't drain them by itself; the embedder's event loop calls this after each task.
+    fn perform_microtask_checkpoint(&mut self) -> Result<()>;
 }
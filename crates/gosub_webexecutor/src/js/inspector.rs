@@ -0,0 +1,15 @@
+/// Blocks for the debugger's next message while the page is paused, or returns `None` once the
+/// debugger is gone, which resumes the page.
+pub type InspectorWait = Box<dyn FnMut() -> Option<String>>;
+
+/// Takes the responses and notifications for the debugger.
+pub type InspectorSend = Box<dyn FnMut(String)>;
+
+/// A debugger attached to a context with
+/// [`WebContext::connect_inspector`](crate::js::WebContext::connect_inspector), speaking the
+/// Chrome DevTools protocol. Dropping it detaches the debugger.
+pub trait WebInspectorSession {
+    /// Handles a protocol message from the debugger. While a breakpoint holds the page, messages
+    /// come through the session's [`InspectorWait`] instead.
+    fn dispatch(&mut self, message: &str);
+}
@@ -3,8 +3,45 @@ use std::rc::Rc;
 
 use gosub_shared::types::Result;
 
-use crate::js::WebRuntime;
+use crate::js::{WebContext, WebObject, WebRuntime, WebValue};
 
 pub trait JSInterop {
     fn implement<RT: WebRuntime>(s: Rc<RefCell<Self>>, ctx: RT::Context) -> Result<()>;
 }
+
+/// Defines the interface object `class` on the global, one that can't be constructed from
+/// scripts, unless it's there already. Returns something to hand instances to.
+const DEFINE_CLASS: &str = r#"((name, parent) => {
+    const define = (name) => {
+        if (typeof globalThis[name] === "function") return globalThis[name];
+        const ctor = function () {
+            throw new TypeError("Illegal constructor");
+        };
+        Object.defineProperty(ctor, "name", { value: name });
+        Object.defineProperty(globalThis, name, { value: ctor, writable: true, configurable: true });
+        return ctor;
+    };
+    const ctor = define(name);
+    if (parent !== null) {
+        // The parent may not be implemented yet; it then picks up the stub made here.
+        const base = define(parent);
+        Object.setPrototypeOf(ctor.prototype, base.prototype);
+        Object.setPrototypeOf(ctor, base);
+    }
+    return { adopt: (object) => Object.setPrototypeOf(object, ctor.prototype) };
+})"#;
+
+/// Makes `object` an instance of the interface `class`, whose prototype inherits from that of
+/// `extends`, so `instanceof` works along the chain. Interfaces are made as they're first named,
+/// so types can be implemented in any order.
+pub fn define_class<RT: WebRuntime>(
+    ctx: &mut RT::Context,
+    object: &RT::Object,
+    class: &str,
+    extends: Option<&str>,
+) -> Result<()> {
+    let parent = extends.map_or_else(|| "null".to_string(), |parent| format!("{parent:?}"));
+    let class = ctx.run(&format!("{DEFINE_CLASS}({class:?}, {parent})"))?.as_object()?;
+    class.call_method("adopt", &[&object.clone().into()])?;
+    Ok(())
+}
@@ -0,0 +1,161 @@
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::{Condvar, Mutex};
+
+/// Sandbox limits for the scripts of a context, see [`WebRuntime::new_context_with_limits`].
+///
+/// [`WebRuntime::new_context_with_limits`]: crate::js::WebRuntime::new_context_with_limits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExecutionLimits {
+    /// How long one task may run before it is interrupted, enforced by a [`Watchdog`]
+    pub time_slice: Option<Duration>,
+    /// The most heap the context may use, in bytes
+    pub max_heap: Option<usize>,
+}
+
+/// The limit a script ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LimitViolation {
+    /// It ran longer than its time slice
+    TimeSlice,
+    /// It used more heap than allowed
+    Heap,
+}
+
+impl Display for LimitViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::TimeSlice => "time slice exceeded",
+            Self::Heap => "heap limit exceeded",
+        })
+    }
+}
+
+/// Stops the script running in a context, from any thread. The script ends with an uncatchable
+/// exception, and [`WebContext::take_limit_violation`] reports it.
+///
+/// [`WebContext::take_limit_violation`]: crate::js::WebContext::take_limit_violation
+pub trait WebInterrupt: Send + Sync {
+    fn interrupt(&self);
+}
+
+/// Interrupts tasks that run longer than a time slice. It runs a thread of its own, which stops
+/// when the watchdog is dropped.
+///
+/// The thread running the tasks [starts](Self::start) and [stops](Self::stop) the clock around
+/// each of them; what gets interrupted is whatever was last [watched](Self::watch).
+pub struct Watchdog {
+    shared: Arc<(Mutex<WatchState>, Condvar)>,
+}
+
+#[derive(Default)]
+struct WatchState {
+    target: Option<Arc<dyn WebInterrupt>>,
+    deadline: Option<Instant>,
+    time_slice: Duration,
+    stopped: bool,
+}
+
+impl Watchdog {
+    pub fn new(time_slice: Duration) -> std::io::Result<Self> {
+        let shared = Arc::new((
+            Mutex::new(WatchState {
+                time_slice,
+                ..WatchState::default()
+            }),
+            Condvar::new(),
+        ));
+        let watched = Arc::clone(&shared);
+        std::thread::Builder::new()
+            .name("script-watchdog".into())
+            .spawn(move || watch(&watched))?;
+        Ok(Self { shared })
+    }
+
+    /// Makes `target` what gets interrupted from now on.
+    pub fn watch(&self, target: Option<Arc<dyn WebInterrupt>>) {
+        self.update(|state| state.target = target);
+    }
+
+    /// Starts the clock on a task.
+    pub fn start(&self) {
+        self.update(|state| state.deadline = Some(Instant::now() + state.time_slice));
+    }
+
+    /// The task is done.
+    pub fn stop(&self) {
+        self.update(|state| state.deadline = None);
+    }
+
+    fn update(&self, change: impl FnOnce(&mut WatchState)) {
+        let (state, wake) = &*self.shared;
+        change(&mut state.lock());
+        wake.notify_one();
+    }
+}
+
+impl Debug for Watchdog {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Watchdog").finish_non_exhaustive()
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.update(|state| state.stopped = true);
+    }
+}
+
+fn watch(shared: &(Mutex<WatchState>, Condvar)) {
+    let (state, wake) = shared;
+    let mut state = state.lock();
+    while !state.stopped {
+        match state.deadline {
+            None => wake.wait(&mut state),
+            Some(deadline) if Instant::now() >= deadline => {
+                state.deadline = None;
+                if let Some(target) = &state.target {
+                    target.interrupt();
+                }
+            }
+            Some(deadline) => {
+                wake.wait_until(&mut state, deadline);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Counter(AtomicUsize);
+
+    impl WebInterrupt for Counter {
+        fn interrupt(&self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn only_overlong_tasks_are_interrupted() {
+        let watchdog = Watchdog::new(Duration::from_millis(50)).unwrap();
+        let counter = Arc::new(Counter::default());
+        watchdog.watch(Some(Arc::clone(&counter) as Arc<dyn WebInterrupt>));
+
+        watchdog.start();
+        watchdog.stop();
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(counter.0.load(Ordering::SeqCst), 0);
+
+        watchdog.start();
+        std::thread::sleep(Duration::from_millis(200));
+        watchdog.stop();
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+    }
+}
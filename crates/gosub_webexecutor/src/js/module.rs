@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+/// The ES modules a module script needs, already fetched: each module's source by URL, with the
+/// specifiers of its `import`s resolved to URLs of other modules in the graph. Loading is up to
+/// the embedder; [`WebContext::run_module`](crate::js::WebContext::run_module) only links and
+/// evaluates.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModuleGraph {
+    modules: HashMap<String, ModuleRecord>,
+}
+
+/// One module of a [`ModuleGraph`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModuleRecord {
+    pub source: String,
+    /// Import specifier to the URL of the module it resolves to
+    pub imports: HashMap<String, String>,
+}
+
+impl ModuleGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, url: impl Into<String>, module: ModuleRecord) {
+        self.modules.insert(url.into(), module);
+    }
+
+    pub fn get(&self, url: &str) -> Option<&ModuleRecord> {
+        self.modules.get(url)
+    }
+
+    pub fn contains(&self, url: &str) -> bool {
+        self.modules.contains_key(url)
+    }
+
+    /// The module `specifier` resolves to when imported by the module at `referrer`.
+    pub fn resolve(&self, referrer: &str, specifier: &str) -> Option<&str> {
+        self.modules.get(referrer)?.imports.get(specifier).map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ModuleRecord)> {
+        self.modules.iter().map(|(url, module)| (url.as_str(), module))
+    }
+
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn specifiers_resolve_per_referrer() {
+        let mut graph = ModuleGraph::new();
+        graph.insert(
+            "https://a.example/main.js",
+            ModuleRecord {
+                source: "import './util.js';".into(),
+                imports: HashMap::from([("./util.js".into(), "https://a.example/util.js".into())]),
+            },
+        );
+        graph.insert("https://a.example/util.js", ModuleRecord::default());
+
+        assert_eq!(
+            graph.resolve("https://a.example/main.js", "./util.js"),
+            Some("https://a.example/util.js")
+        );
+        assert_eq!(graph.resolve("https://a.example/util.js", "./util.js"), None);
+        assert_eq!(graph.len(), 2);
+    }
+}
@@ -0,0 +1,45 @@
+use core::fmt::Display;
+
+use gosub_shared::types::Result;
+
+use crate::js::{WebContext, WebObject, WebRuntime, WebValue};
+
+/// Makes a promise along with the functions that settle it.
+const NEW_PROMISE: &str = r#"(() => {
+    let resolve, reject;
+    const promise = new Promise((res, rej) => { resolve = res; reject = rej; });
+    return { promise, resolve, reject: (message) => reject(new Error(message)) };
+})()"#;
+
+/// A pending promise, for results that come in after the call that returns it, e.g. those of the
+/// `async` methods of `#[web_fns]`.
+pub struct WebPromise<RT: WebRuntime> {
+    ctx: RT::Context,
+    capability: RT::Object,
+}
+
+impl<RT: WebRuntime> WebPromise<RT> {
+    pub fn new(ctx: &RT::Context) -> Result<Self> {
+        let mut ctx = ctx.clone();
+        let capability = ctx.run(NEW_PROMISE)?.as_object()?;
+        Ok(Self { ctx, capability })
+    }
+
+    /// The promise, to hand to the script.
+    pub fn promise(&self) -> Result<RT::Value> {
+        self.capability.get_property("promise")
+    }
+
+    /// Fulfills the promise with `value`.
+    pub fn resolve(self, value: &RT::Value) -> Result<()> {
+        self.capability.call_method("resolve", &[value])?;
+        Ok(())
+    }
+
+    /// Rejects the promise with an `Error` saying `error`.
+    pub fn reject(self, error: impl Display) -> Result<()> {
+        let message = RT::Value::new_string(self.ctx.clone(), &error.to_string())?;
+        self.capability.call_method("reject", &[&message])?;
+        Ok(())
+    }
+}
@@ -1,8 +1,9 @@
 use gosub_shared::types::Result;
 
 use crate::js::{
-    Args, VariadicArgs, VariadicArgsInternal, WebArray, WebCompiled, WebContext, WebFunction, WebFunctionCallBack,
-    WebFunctionCallBackVariadic, WebFunctionVariadic, WebGetterCallback, WebObject, WebSetterCallback, WebValue,
+    Args, ExecutionLimits, VariadicArgs, VariadicArgsInternal, WebArray, WebCompiled, WebContext, WebFunction,
+    WebFunctionCallBack, WebFunctionCallBackVariadic, WebFunctionVariadic, WebGetterCallback, WebObject,
+    WebSetterCallback, WebValue,
 };
 
 // trait around the main JS engine (e.g V8, SpiderMonkey, JSC, etc.)
@@ -23,4 +24,10 @@ pub trait WebRuntime {
     type VariadicArgsInternal: VariadicArgsInternal<RT = Self>;
 
     fn new_context(&mut self) -> Result<Self::Context>;
+
+    /// Creates a context whose scripts are held to `limits`. Runtimes that can't enforce the
+    /// heap limit ignore it.
+    fn new_context_with_limits(&mut self, _limits: &ExecutionLimits) -> Result<Self::Context> {
+        self.new_context()
+    }
 }
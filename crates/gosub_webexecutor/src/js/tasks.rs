@@ -0,0 +1,168 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Wake, Waker};
+
+use parking_lot::Mutex;
+
+/// Tells the embedder that a local task is ready, so it calls [`run_local_tasks`] on the thread
+/// the task belongs to. Called from whatever thread woke the task.
+pub type TaskNotify = Arc<dyn Fn() + Send + Sync>;
+
+type LocalFuture = Pin<Box<dyn Future<Output = ()>>>;
+
+thread_local! {
+    static TASKS: RefCell<LocalTasks> = RefCell::default();
+}
+
+/// The futures started on this thread, e.g. by the `async` methods of `#[web_fns]`. They hold on
+/// to values of the thread's contexts, so they never leave it.
+#[derive(Default)]
+struct LocalTasks {
+    next_id: u64,
+    tasks: HashMap<u64, LocalFuture>,
+    ready: Arc<Ready>,
+}
+
+/// Shared with the wakers of a thread's tasks
+#[derive(Default)]
+struct Ready {
+    ids: Mutex<Vec<u64>>,
+    notify: Mutex<Option<TaskNotify>>,
+}
+
+impl Ready {
+    fn push(&self, id: u64) {
+        self.ids.lock().push(id);
+        self.notify();
+    }
+
+    fn notify(&self) {
+        let notify = self.notify.lock().clone();
+        if let Some(notify) = notify {
+            notify();
+        }
+    }
+}
+
+struct TaskWaker {
+    id: u64,
+    ready: Arc<Ready>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.ready.push(self.id);
+    }
+}
+
+/// Starts `future` on this thread. It is first polled by the next [`run_local_tasks`], and then
+/// each time it is woken.
+pub fn spawn_local(future: impl Future<Output = ()> + 'static) {
+    let ready = TASKS.with_borrow_mut(|tasks| {
+        let id = tasks.next_id;
+        tasks.next_id += 1;
+        tasks.tasks.insert(id, Box::pin(future));
+        tasks.ready.ids.lock().push(id);
+        tasks.ready.clone()
+    });
+    ready.notify();
+}
+
+/// Polls this thread's tasks that are ready. Tasks woken while they run are left for the next
+/// call, so a task that keeps waking itself doesn't starve the thread.
+pub fn run_local_tasks() {
+    let ready = TASKS.with_borrow(|tasks| tasks.ready.clone());
+    let ids = std::mem::take(&mut *ready.ids.lock());
+    for id in ids {
+        // Tasks are taken out while they run, so they can start others. One that already
+        // finished, or was woken twice, isn't there.
+        let Some(mut future) = TASKS.with_borrow_mut(|tasks| tasks.tasks.remove(&id)) else {
+            continue;
+        };
+        let waker = Waker::from(Arc::new(TaskWaker {
+            id,
+            ready: ready.clone(),
+        }));
+        if future.as_mut().poll(&mut Context::from_waker(&waker)).is_pending() {
+            TASKS.with_borrow_mut(|tasks| tasks.tasks.insert(id, future));
+        }
+    }
+}
+
+/// Sets who to tell when one of this thread's tasks is ready.
+pub fn set_local_task_notify(notify: TaskNotify) {
+    TASKS.with_borrow(|tasks| *tasks.ready.notify.lock() = Some(notify));
+}
+
+/// Drops this thread's tasks, e.g. along with the context they work on.
+pub fn clear_local_tasks() {
+    let dropped = TASKS.with_borrow_mut(|tasks| {
+        tasks.ready.ids.lock().clear();
+        std::mem::take(&mut tasks.tasks)
+    });
+    // Dropping a task may start another.
+    drop(dropped);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Poll;
+
+    use super::*;
+
+    /// Pending until its waker has been taken and woken once
+    struct Yield(Rc<RefCell<Option<Waker>>>, bool);
+
+    impl Future for Yield {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.1 {
+                return Poll::Ready(());
+            }
+            self.1 = true;
+            *self.0.borrow_mut() = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn tasks_run_when_woken() {
+        let notified = Arc::new(AtomicUsize::new(0));
+        let counter = notified.clone();
+        set_local_task_notify(Arc::new(move || {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }));
+
+        let waker = Rc::new(RefCell::new(None));
+        let done = Rc::new(Cell::new(false));
+        let (slot, flag) = (waker.clone(), done.clone());
+        spawn_local(async move {
+            Yield(slot, false).await;
+            flag.set(true);
+        });
+        assert_eq!(notified.load(Ordering::Relaxed), 1);
+
+        run_local_tasks();
+        assert!(!done.get());
+        run_local_tasks();
+        assert!(!done.get(), "a task that wasn't woken doesn't run");
+
+        waker.borrow_mut().take().unwrap().wake();
+        assert_eq!(notified.load(Ordering::Relaxed), 2);
+        run_local_tasks();
+        assert!(done.get());
+
+        clear_local_tasks();
+    }
+}
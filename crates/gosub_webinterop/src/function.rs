@@ -19,6 +19,7 @@ pub(crate) struct Function {
     pub(crate) func_generics: syn::Generics,
     pub(crate) variadic: bool,
     pub(crate) needs_ctx: bool,
+    pub(crate) is_async: bool,
 }
 
 impl Function {
@@ -224,6 +225,12 @@ impl Function {
         let ident = &self.ident;
         let call_args = self.call_args();
 
+        let func_generics = self.get_generics();
+
+        if self.is_async {
+            return self.call_async(name, func_generics, call_args);
+        }
+
         let func = {
             match self.self_type {
                 SelfType::NoSelf => quote! { #name::#ident },
@@ -231,7 +238,6 @@ impl Function {
                 SelfType::SelfMutRef => quote! { s.borrow_mut().#ident },
             }
         };
-        let func_generics = self.get_generics();
 
         quote! {
             let ret = match #func #func_generics(#call_args).to_web_value(ctx.clone()) {
@@ -245,6 +251,49 @@ impl Function {
         }
     }
 
+    /// Returns a promise right away, and settles it on the thread's local tasks once the
+    /// method's future is done.
+    ///
+    /// A `&self` method runs on a clone of the value taken when it's called: its future can be
+    /// pending across many polls, and a `RefCell` borrow held that long would make every
+    /// `&mut self` call in between panic.
+    fn call_async(&self, name: &Ident, func_generics: TokenStream, call_args: TokenStream) -> TokenStream {
+        let ident = &self.ident;
+        let (clone, func) = match self.self_type {
+            SelfType::NoSelf => (TokenStream::new(), quote! { #name::#ident }),
+            _ => (quote! { let this = s.borrow().clone(); }, quote! { this.#ident }),
+        };
+
+        quote! {
+            let promise = match WebPromise::<RT>::new(&ctx) {
+                Ok(promise) => promise,
+                Err(e) => {
+                    cb.error(e);
+                    return;
+                }
+            };
+            let ret = match promise.promise() {
+                Ok(ret) => ret,
+                Err(e) => {
+                    cb.error(e);
+                    return;
+                }
+            };
+
+            #clone
+            let task = async move {
+                let ret = #func #func_generics(#call_args).await;
+                // Settling only fails when the context is gone, and then there's no one to tell.
+                let _ = match ret.to_web_value(ctx.clone()) {
+                    Ok(ret) => promise.resolve(&ret),
+                    Err(e) => promise.reject(e),
+                };
+            };
+            spawn_local(task);
+            cb.ret(ret);
+        }
+    }
+
     fn get_generics(&self) -> TokenStream {
         if !self
             .func_generics
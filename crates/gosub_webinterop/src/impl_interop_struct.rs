@@ -3,12 +3,29 @@ use quote::{format_ident, quote};
 
 use crate::types::Field;
 
-pub fn impl_interop_struct(name: Ident, fields: &[Field], js_name: TokenStream) -> TokenStream {
+/// The interface a struct's object is an instance of, e.g. `class = HTMLElement, extends = Element`
+pub struct Class {
+    pub name: TokenStream,
+    pub extends: Option<TokenStream>,
+}
+
+pub fn impl_interop_struct(name: Ident, fields: &[Field], js_name: TokenStream, class: Option<&Class>) -> TokenStream {
     let marker_struct = format_ident!("{}JSMethodsMarker", name);
     let marker_trait = format_ident!("{}JSMethods", name);
 
     let getters_setters = Field::getters_setters(fields);
 
+    let define_class = class.map(|class| {
+        let class_name = &class.name;
+        let extends = match &class.extends {
+            Some(extends) => quote! { Some(stringify!(#extends)) },
+            None => quote! { None },
+        };
+        quote! {
+            define_class::<RT>(&mut ctx, &obj, stringify!(#class_name), #extends)?;
+        }
+    });
+
     quote! {
         impl JSInterop for #name {
             fn implement<RT: WebRuntime>(s: Rc<RefCell<Self>>, mut ctx: RT::Context) -> Result<()> {
@@ -16,6 +33,8 @@ pub fn impl_interop_struct(name: Ident, fields: &[Field], js_name: TokenStream)
 
                 ctx.set_on_global_object(stringify!(#js_name), obj.clone().into())?; //#name
 
+                #define_class
+
                 #getters_setters
 
                 (&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&#marker_struct)
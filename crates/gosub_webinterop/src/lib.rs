@@ -6,7 +6,7 @@ use std::collections::HashMap;
 
 use crate::function::Function;
 use crate::impl_function::impl_js_functions;
-use crate::impl_interop_struct::impl_interop_struct;
+use crate::impl_interop_struct::{impl_interop_struct, Class};
 use crate::property::{FieldProperty, FunctionProperty};
 use crate::types::{Arg, ArgVariant, Field, GenericsMatcher, ReturnType, SelfType};
 use crate::utils::crate_name;
@@ -50,6 +50,9 @@ pub fn web_interop(args: TokenStream, item: TokenStream) -> TokenStream {
                 executor: property.executor,
                 ty: field.ty.clone(),
                 ident: ident.clone(),
+                getter: property.getter,
+                setter: property.setter,
+                readonly: property.readonly,
             };
 
             fields.push(f);
@@ -60,14 +63,30 @@ pub fn web_interop(args: TokenStream, item: TokenStream) -> TokenStream {
         parse_macro_input!(args with Punctuated::<MetaNameValue, Token![,]>::parse_terminated);
 
     let mut js_name = input.ident.to_token_stream();
+    let mut class = None;
+    let mut extends = None;
 
     for item in items {
         if item.path.is_ident("js_name") {
             js_name = item.value.to_token_stream();
+        } else if item.path.is_ident("class") {
+            class = Some(item.value.to_token_stream());
+        } else if item.path.is_ident("extends") {
+            extends = Some(item.value.to_token_stream());
         }
     }
 
-    let extend = impl_interop_struct(input.ident.clone(), &fields, js_name);
+    let class = match (class, extends) {
+        (Some(class), extends) => Some(Class { name: class, extends }),
+        (None, Some(extends)) => {
+            return syn::Error::new_spanned(extends, "extends needs a class to extend it with")
+                .to_compile_error()
+                .into()
+        }
+        (None, None) => None,
+    };
+
+    let extend = impl_interop_struct(input.ident.clone(), &fields, js_name, class.as_ref());
 
     let name = input.ident.clone().into_token_stream().to_string();
     STATE.write().insert((crate_name(), name), 0);
@@ -117,6 +136,7 @@ pub fn web_fns(attr: TokenStream, item: TokenStream) -> TokenStream {
                 func_generics: method.sig.generics.clone(),
                 variadic: false,
                 needs_ctx: false,
+                is_async: method.sig.asyncness.is_some(),
             };
 
             if let Some(FnArg::Receiver(self_arg)) = args.first() {
@@ -130,6 +150,13 @@ pub fn web_fns(attr: TokenStream, item: TokenStream) -> TokenStream {
                     Some(_) => func.self_type = SelfType::SelfMutRef,
                     None => func.self_type = SelfType::SelfRef,
                 }
+
+                // The future runs on a clone of `self`, so changes it made would be lost.
+                if func.is_async && func.self_type == SelfType::SelfMutRef {
+                    return syn::Error::new_spanned(self_arg, "async methods can't take &mut self")
+                        .to_compile_error()
+                        .into();
+                }
             }
 
             let mut index = 0;
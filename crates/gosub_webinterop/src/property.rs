@@ -1,5 +1,5 @@
 use quote::{format_ident, ToTokens};
-use syn::{Attribute, LitStr, Meta};
+use syn::{Attribute, LitStr, Meta, Path};
 
 use crate::types::executor::Executor;
 use crate::types::{GenericProperty, Primitive};
@@ -7,6 +7,12 @@ use crate::types::{GenericProperty, Primitive};
 pub(crate) struct FieldProperty {
     pub(crate) rename: Option<String>,
     pub(crate) executor: Executor,
+    /// Called with the struct to get the value, instead of reading the field
+    pub(crate) getter: Option<Path>,
+    /// Called with the struct and the new value, instead of writing the field
+    pub(crate) setter: Option<Path>,
+    /// Assigning to the property throws
+    pub(crate) readonly: bool,
 }
 
 pub(crate) struct FunctionProperty {
@@ -20,6 +26,9 @@ impl Default for FieldProperty {
         Self {
             rename: None,
             executor: Executor::Both,
+            getter: None,
+            setter: None,
+            readonly: false,
         }
     }
 }
@@ -41,10 +50,7 @@ impl FieldProperty {
 
         for (index, attr) in attrs.iter().enumerate() {
             if attr.path().is_ident("property") {
-                let mut prop = FieldProperty {
-                    rename: None,
-                    executor: Executor::Both,
-                };
+                let mut prop = FieldProperty::default();
 
                 //rename = "____", js => rename to name, and it is a js only property
                 //rename = "____", wasm => rename to name, and it is a wasm only property
@@ -52,6 +58,9 @@ impl FieldProperty {
                 //js => name is the same, and it is a js only property
                 //wasm => name is the same, and it is a wasm only property
                 //<nothing> => name is the same, and it is a property for both, js and wasm
+                //getter = "path" => the property is read with `path(&self)`
+                //setter = "path" => the property is written with `path(&mut self, value)`
+                //readonly => the property can't be written
 
                 match &attr.meta {
                     Meta::Path(_) => {}
@@ -90,6 +99,19 @@ impl FieldProperty {
                                     }
                                     prop.executor = Executor::None;
                                 }
+                                path if path.is_ident("getter") => {
+                                    let lit: LitStr = meta.value()?.parse()?;
+
+                                    prop.getter = Some(lit.parse()?);
+                                }
+                                path if path.is_ident("setter") => {
+                                    let lit: LitStr = meta.value()?.parse()?;
+
+                                    prop.setter = Some(lit.parse()?);
+                                }
+                                path if path.is_ident("readonly") => {
+                                    prop.readonly = true;
+                                }
                                 _ => Err(syn::Error::new_spanned(attr, "Unknown attribute in property attribute"))?,
                             }
 
@@ -104,6 +126,10 @@ impl FieldProperty {
                     }
                 }
 
+                if prop.readonly && prop.setter.is_some() {
+                    return Err(syn::Error::new_spanned(attr, "a readonly property can't have a setter"));
+                }
+
                 property = Some(prop);
                 remove_attrs = Some(index);
             }
@@ -11,6 +11,9 @@ pub(crate) struct Field {
     pub(crate) executor: Executor,
     //needed later
     pub(crate) ty: syn::Type,
+    pub(crate) getter: Option<syn::Path>,
+    pub(crate) setter: Option<syn::Path>,
+    pub(crate) readonly: bool,
 }
 
 impl Field {
@@ -42,12 +45,16 @@ impl Field {
 
     fn getter(&self) -> TokenStream {
         let ident = &self.ident;
+        let read = match &self.getter {
+            Some(getter) => quote! { #getter(&s.borrow()) },
+            None => quote! { s.borrow().#ident },
+        };
         quote! {
             let getter = {
                 let s = Rc::clone(&s);
                 Box::new(move |cb: &mut RT::GetterCB| {
                     let ctx = cb.context();
-                    let value = s.borrow().#ident;
+                    let value = #read;
                     let value = match value.to_web_value(ctx.clone()) {
                         Ok(value) => value,
                         Err(e) => {
@@ -64,6 +71,18 @@ impl Field {
     fn setter(&self) -> TokenStream {
         let ident = &self.ident;
         let field_type = &self.ty;
+        if self.readonly {
+            let message = format!("{} is read-only", self.name);
+            return quote! {
+                let setter = Box::new(move |cb: &mut RT::SetterCB| {
+                    cb.error(#message);
+                });
+            };
+        }
+        let write = match &self.setter {
+            Some(setter) => quote! { #setter(&mut s.borrow_mut(), value) },
+            None => quote! { s.borrow_mut().#ident = value },
+        };
         quote! {
             let setter = {
                 let s = Rc::clone(&s);
@@ -76,7 +95,7 @@ impl Field {
                             return;
                         }
                     };
-                    s.borrow_mut().#ident = value;
+                    #write;
                 })
             };
         }
@@ -89,7 +89,7 @@ fn main() -> anyhow::Result<()> {
             }
 
             let info = config.get_info(&key).unwrap();
-            let value = config.get(&key)?.unwrap_or(info.default.clone());
+            let value = config.get(&key)?;
 
             println!("Key            : {key}");
             println!("Type           : {}", value.type_name());
@@ -130,7 +130,7 @@ fn print_table(config: &Config, keys: &[String]) -> anyhow::Result<()> {
         let Some(info) = config.get_info(key) else {
             continue;
         };
-        let value = config.get(key)?.unwrap_or_else(|| info.default.clone());
+        let value = config.get(key)?;
         let rendered = value.value_string();
 
         rows.push(Row {
@@ -126,6 +126,7 @@ impl BrowserApp {
                     url: None,
                     title: Some("Gosub".to_string()),
                     viewport: None,
+                    opener: None,
                 },
                 None,
             ))
@@ -216,6 +216,7 @@ impl BrowserApp {
                     // Vello needs a non-zero viewport to create the wgpu texture.
                     // The real size is sent via SetViewport on the first panel resize.
                     viewport: Some(Viewport::new(0, 0, 1024, 768)),
+                    opener: None,
                 },
                 None,
             ))
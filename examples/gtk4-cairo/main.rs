@@ -136,6 +136,7 @@ fn main() {
                         // If we pre-set a viewport here (DPR=1), the engine won't recreate the
                         // surface when connect_resize sends the same CSS dimensions with DPR=2.
                         viewport: None,
+                        opener: None,
                     },
                     None,
                 ))
@@ -108,6 +108,7 @@ async fn main() -> Result<(), EngineError> {
         url: None,
         title: Some("New Tab".into()),
         viewport: Some(Viewport::new(0, 0, 800, 600)),
+        opener: None,
     };
     let tab = zone.create_tab(def_values, None).await.expect("cannot create tab");
 
@@ -239,9 +240,18 @@ async fn handle_event(ev: EngineEvent, tab_handle: TabHandle) {
                 NavigationEvent::Started { url, .. } => {
                     println!("[nav ] →         [{t}] {url}");
                 }
+                NavigationEvent::Redirected { from, to, .. } => {
+                    println!("[nav ] redirect  [{t}] {from} → {to}");
+                }
+                NavigationEvent::ResponseReceived { url, status, .. } => {
+                    println!("[nav ] response  [{t}] {status} {url}");
+                }
                 NavigationEvent::Committed { url, .. } => {
                     println!("[nav ] committed [{t}] {url}");
                 }
+                NavigationEvent::DomContentLoaded { url, .. } => {
+                    println!("[nav ] dom-ready [{t}] {url}");
+                }
                 NavigationEvent::Finished { url, .. } => {
                     println!("[nav ] finished  [{t}] {url}");
                 }
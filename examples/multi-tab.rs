@@ -137,6 +137,7 @@ async fn main() -> Result<(), EngineError> {
             url: None,
             title: Some(format!("Tab {i}")),
             viewport: Some(Viewport::new(0, 0, 800, 600)),
+            opener: None,
         };
 
         let tab = zone
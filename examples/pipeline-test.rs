@@ -176,6 +176,7 @@ async fn main() -> Result<(), EngineError> {
                 url: None,
                 title: None,
                 viewport: Some(Viewport::new(0, 0, 800, 600)),
+                opener: None,
             },
             None,
         )
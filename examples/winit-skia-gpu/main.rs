@@ -586,6 +586,7 @@ fn main() {
                 url: None,
                 title: Some("Gosub".to_string()),
                 viewport: None,
+                opener: None,
             },
             None,
         ))
@@ -283,6 +283,7 @@ impl ApplicationHandler<()> for BrowserApp {
                     url: None,
                     title: Some("Gosub".to_string()),
                     viewport: Some(Viewport::new(0, 0, logical_w, logical_h)),
+                    opener: None,
                 },
                 None,
             ))
@@ -1,16 +1,20 @@
 use gosub_shared::types::Result;
-use gosub_v8::{V8Context, V8Engine};
 use gosub_webexecutor::js::{WebContext, WebRuntime, WebValue};
 use std::env::args;
 
+#[cfg(feature = "boa")]
+use gosub_boa::BoaEngine as Engine;
+#[cfg(not(feature = "boa"))]
+use gosub_v8::V8Engine as Engine;
+
 fn main() -> Result<()> {
     let Some(file) = args().nth(1) else {
         eprintln!("Usage: run-js <file>");
         return Ok(());
     };
 
-    let mut runtime = V8Engine::new();
-    let mut ctx: V8Context = runtime.new_context()?;
+    let mut runtime = Engine::new();
+    let mut ctx = runtime.new_context()?;
 
     let code = std::fs::read_to_string(file)?;
 
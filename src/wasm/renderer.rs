@@ -98,6 +98,7 @@ impl GosubBrowser {
                             url: None,
                             title: Some("New Tab".to_string()),
                             viewport: Some(Viewport::new(0, 0, width, height)),
+                            opener: None,
                         },
                         None,
                     )